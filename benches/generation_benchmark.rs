@@ -6,8 +6,10 @@ use rust_bert::gpt2::{
     Gpt2ConfigResources, Gpt2MergesResources, Gpt2ModelResources, Gpt2VocabResources,
 };
 use rust_bert::pipelines::common::ModelType;
+use rust_bert::pipelines::generation_utils::NoRepeatNgramScope;
 use rust_bert::pipelines::text_generation::{TextGenerationConfig, TextGenerationModel};
 use rust_bert::resources::RemoteResource;
+use std::collections::HashMap;
 use std::time::{Duration, Instant};
 use tch::Device;
 
@@ -29,8 +31,16 @@ fn create_text_generation_model() -> TextGenerationModel {
         repetition_penalty: 1.0,
         length_penalty: 1.0,
         no_repeat_ngram_size: 3,
+        no_repeat_ngram_scope: NoRepeatNgramScope::Sequence,
         num_beam_groups: None,
         diversity_penalty: None,
+        penalty_alpha: None,
+        typical_p: None,
+        epsilon_cutoff: None,
+        eta_cutoff: None,
+        prefill_chunk_size: None,
+        stop_sequences: vec![],
+        logit_bias: HashMap::new(),
         num_return_sequences: 5,
         device: Device::cuda_if_available(),
     };
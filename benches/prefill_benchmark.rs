@@ -0,0 +1,84 @@
+#[macro_use]
+extern crate criterion;
+
+use criterion::{black_box, BenchmarkId, Criterion, Throughput};
+use rust_bert::gpt_neo::{
+    GptNeoConfigResources, GptNeoGenerator, GptNeoMergesResources, GptNeoModelResources,
+    GptNeoVocabResources,
+};
+use rust_bert::pipelines::generation_utils::{GenerateConfig, GenerateOptions, LanguageGenerator};
+use rust_bert::resources::RemoteResource;
+use std::time::{Duration, Instant};
+use tch::{Device, Kind, Tensor};
+
+fn create_generator() -> GptNeoGenerator {
+    let generate_config = GenerateConfig {
+        model_resource: Box::new(RemoteResource::from_pretrained(
+            GptNeoModelResources::GPT_NEO_125M,
+        )),
+        config_resource: Box::new(RemoteResource::from_pretrained(
+            GptNeoConfigResources::GPT_NEO_125M,
+        )),
+        vocab_resource: Box::new(RemoteResource::from_pretrained(
+            GptNeoVocabResources::GPT_NEO_125M,
+        )),
+        merges_resource: Box::new(RemoteResource::from_pretrained(
+            GptNeoMergesResources::GPT_NEO_125M,
+        )),
+        max_length: 2048,
+        do_sample: false,
+        num_beams: 1,
+        no_repeat_ngram_size: 0,
+        device: Device::cuda_if_available(),
+        ..Default::default()
+    };
+    GptNeoGenerator::new(generate_config).unwrap()
+}
+
+// Generating a single token only runs the prefill: one forward pass over the prompt
+fn prefill(iters: u64, model: &GptNeoGenerator, input_ids: &Tensor) -> Duration {
+    let generate_options = GenerateOptions {
+        max_new_tokens: Some(1),
+        ..Default::default()
+    };
+    let mut duration = Duration::new(0, 0);
+    for _i in 0..iters {
+        let start = Instant::now();
+        let _ = model.generate_from_ids_and_past(input_ids.copy(), None, Some(generate_options));
+        duration = duration.checked_add(start.elapsed()).unwrap();
+    }
+    duration
+}
+
+fn bench_prefill(c: &mut Criterion) {
+    //    Set-up generation model
+    unsafe {
+        torch_sys::dummy_cuda_dependency();
+    }
+    let model = create_generator();
+
+    //    Define input (random prompts, the throughput does not depend on the tokens)
+    let mut group = c.benchmark_group("Prefill");
+    for prompt_length in [256i64, 512, 1024, 2000] {
+        let input_ids = Tensor::randint(
+            50257,
+            &[1, prompt_length],
+            (Kind::Int64, Device::cuda_if_available()),
+        );
+        group.throughput(Throughput::Elements(prompt_length as u64));
+        group.bench_with_input(
+            BenchmarkId::from_parameter(prompt_length),
+            &input_ids,
+            |b, input_ids| b.iter_custom(|iters| black_box(prefill(iters, &model, input_ids))),
+        );
+    }
+    group.finish();
+}
+
+criterion_group! {
+name = benches;
+config = Criterion::default().sample_size(10);
+targets = bench_prefill
+}
+
+criterion_main!(benches);
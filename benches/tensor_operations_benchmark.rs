@@ -2,9 +2,10 @@
 extern crate criterion;
 
 use criterion::{black_box, Criterion};
+use rust_bert::bert::{BertConfig, BertLayer};
 use std::time::{Duration, Instant};
 use tch::kind::Kind;
-use tch::{Device, Tensor};
+use tch::{nn, no_grad, Device, Tensor};
 
 fn matrix_multiply(iters: u64, input: &Tensor, weights: &Tensor) -> Duration {
     let mut duration = Duration::new(0, 0);
@@ -16,6 +17,16 @@ fn matrix_multiply(iters: u64, input: &Tensor, weights: &Tensor) -> Duration {
     duration
 }
 
+fn bert_layer(iters: u64, layer: &BertLayer, input: &Tensor) -> Duration {
+    let mut duration = Duration::new(0, 0);
+    for _i in 0..iters {
+        let start = Instant::now();
+        let _ = no_grad(|| layer.forward_t(input, None, None, None, false));
+        duration = duration.checked_add(start.elapsed()).unwrap();
+    }
+    duration
+}
+
 fn bench_tensor_ops(c: &mut Criterion) {
     //    Set-up summarization model
     unsafe {
@@ -28,6 +39,22 @@ fn bench_tensor_ops(c: &mut Criterion) {
     c.bench_function("Matrix multiply ", |b| {
         b.iter_custom(|iters| black_box(matrix_multiply(iters, &input, &weights)))
     });
+
+    //    BERT-base layer, with and without fused feed-forward operations
+    let vs = nn::VarStore::new(Device::cuda_if_available());
+    let layer = BertLayer::new(vs.root(), &BertConfig::default());
+    let fused_config = BertConfig {
+        fused_feed_forward: Some(true),
+        ..Default::default()
+    };
+    let fused_layer = BertLayer::new(vs.root() / "fused", &fused_config);
+    let input = Tensor::rand(&[8, 128, 768], (Kind::Float, vs.device()));
+    c.bench_function("BERT layer", |b| {
+        b.iter_custom(|iters| black_box(bert_layer(iters, &layer, &input)))
+    });
+    c.bench_function("BERT layer (fused feed-forward)", |b| {
+        b.iter_custom(|iters| black_box(bert_layer(iters, &fused_layer, &input)))
+    });
 }
 
 criterion_group! {
@@ -0,0 +1,106 @@
+// Copyright 2022 Guillaume Becquin
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//     http://www.apache.org/licenses/LICENSE-2.0
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+extern crate anyhow;
+
+use rust_bert::bert::{BertConfig, BertConfigResources, BertEmbeddings, BertModel, BertModelResources};
+use rust_bert::export::export_torchscript;
+use rust_bert::gpt2::{GPT2LMHeadModel, Gpt2Config, Gpt2ConfigResources, Gpt2ModelResources};
+use rust_bert::pipelines::generation_utils::{Cache, LMHeadModel};
+use rust_bert::resources::{RemoteResource, ResourceProvider};
+use rust_bert::Config;
+use tch::{nn, Device, Kind, Tensor};
+
+/// Exports BERT and GPT2 to TorchScript. The attention masks and position ids are passed as inputs
+/// to the traced modules so that they are not recorded as constants in the graph.
+/// The exported files can be converted to ONNX in Python, declaring the batch and sequence dynamic axes:
+///   ```python
+///   model = torch.jit.load("bert.pt")
+///   torch.onnx.export(model, (input_ids, attention_mask, position_ids), "bert.onnx",
+///                     input_names=["input_ids", "attention_mask", "position_ids"],
+///                     dynamic_axes={name: {0: "batch", 1: "sequence"} for name in ["input_ids", "attention_mask", "position_ids"]})
+///   ```
+fn main() -> anyhow::Result<()> {
+    let device = Device::Cpu;
+    let (batch_size, sequence_length) = (2, 16);
+    let input_ids = Tensor::ones(&[batch_size, sequence_length], (Kind::Int64, device));
+    let attention_mask = Tensor::ones(&[batch_size, sequence_length], (Kind::Int64, device));
+    let position_ids = Tensor::arange(sequence_length, (Kind::Int64, device))
+        .unsqueeze(0)
+        .expand(&[batch_size, sequence_length], true);
+
+    //    BERT export
+    let config_path =
+        RemoteResource::from_pretrained(BertConfigResources::BERT).get_local_path()?;
+    let weights_path =
+        RemoteResource::from_pretrained(BertModelResources::BERT).get_local_path()?;
+    let mut vs = nn::VarStore::new(device);
+    let config = BertConfig::from_file(config_path);
+    let bert_model: BertModel<BertEmbeddings> = BertModel::new(&vs.root(), &config);
+    vs.load(weights_path)?;
+
+    export_torchscript(
+        &[
+            input_ids.shallow_clone(),
+            attention_mask.shallow_clone(),
+            position_ids.shallow_clone(),
+        ],
+        &mut |inputs| {
+            let output = bert_model
+                .forward_t(
+                    Some(&inputs[0]),
+                    Some(&inputs[1]),
+                    None,
+                    Some(&inputs[2]),
+                    None,
+                    None,
+                    None,
+                    false,
+                )
+                .unwrap();
+            vec![output.hidden_state, output.pooled_output.unwrap()]
+        },
+        "bert.pt",
+    )?;
+
+    //    GPT2 export
+    let config_path =
+        RemoteResource::from_pretrained(Gpt2ConfigResources::GPT2).get_local_path()?;
+    let weights_path =
+        RemoteResource::from_pretrained(Gpt2ModelResources::GPT2).get_local_path()?;
+    let mut vs = nn::VarStore::new(device);
+    let config = Gpt2Config::from_file(config_path);
+    let gpt2_model = GPT2LMHeadModel::new(&vs.root(), &config);
+    vs.load(weights_path)?;
+
+    export_torchscript(
+        &[input_ids, attention_mask, position_ids],
+        &mut |inputs| {
+            let output = gpt2_model
+                .forward_t(
+                    Some(&inputs[0]),
+                    Cache::None,
+                    Some(&inputs[1]),
+                    None,
+                    Some(&inputs[2]),
+                    None,
+                    None,
+                    None,
+                    false,
+                )
+                .unwrap();
+            vec![output.lm_logits]
+        },
+        "gpt2.pt",
+    )?;
+
+    Ok(())
+}
@@ -0,0 +1,111 @@
+//! Fuzz target for the tokenization of untrusted inputs by the pipelines: the arbitrary bytes are converted to a text
+//! with `InputSanitizer::sanitize_bytes`, then tokenized, encoded and decoded by the tokenizers of the main model
+//! families (WordPiece, byte-level BPE and SentencePiece). The tokenizers should never panic and the offsets should
+//! always point within the text.
+//!
+//! Run with `cargo +nightly fuzz run tokenizers` from the root of the repository. The vocabularies are downloaded to
+//! the cache of the library on the first run.
+#![no_main]
+
+use lazy_static::lazy_static;
+use libfuzzer_sys::fuzz_target;
+use rust_bert::albert::AlbertVocabResources;
+use rust_bert::bert::BertVocabResources;
+use rust_bert::gpt2::{Gpt2MergesResources, Gpt2VocabResources};
+use rust_bert::pipelines::common::{ModelType, TokenizerOption};
+use rust_bert::pipelines::sanitization::InputSanitizer;
+use rust_bert::resources::{RemoteResource, ResourceProvider};
+use rust_bert::roberta::{RobertaMergesResources, RobertaVocabResources};
+use rust_bert::t5::T5VocabResources;
+use rust_tokenizers::tokenizer::TruncationStrategy;
+use rust_tokenizers::Offset;
+
+fn load_tokenizer(
+    model_type: ModelType,
+    vocab: (&str, &str),
+    merges: Option<(&str, &str)>,
+    lower_case: bool,
+) -> TokenizerOption {
+    let vocab_path = RemoteResource::from_pretrained(vocab)
+        .get_local_path()
+        .unwrap();
+    let merges_path = merges.map(|merges| {
+        RemoteResource::from_pretrained(merges)
+            .get_local_path()
+            .unwrap()
+    });
+    TokenizerOption::from_file(
+        model_type,
+        vocab_path.to_str().unwrap(),
+        merges_path.as_ref().map(|path| path.to_str().unwrap()),
+        lower_case,
+        None,
+        None,
+    )
+    .unwrap()
+}
+
+lazy_static! {
+    static ref TOKENIZERS: Vec<TokenizerOption> = vec![
+        load_tokenizer(ModelType::Bert, BertVocabResources::BERT, None, true),
+        load_tokenizer(
+            ModelType::GPT2,
+            Gpt2VocabResources::GPT2,
+            Some(Gpt2MergesResources::GPT2),
+            false,
+        ),
+        load_tokenizer(
+            ModelType::Roberta,
+            RobertaVocabResources::ROBERTA,
+            Some(RobertaMergesResources::ROBERTA),
+            false,
+        ),
+        load_tokenizer(ModelType::T5, T5VocabResources::T5_SMALL, None, false),
+        load_tokenizer(
+            ModelType::XLMRoberta,
+            RobertaVocabResources::XLM_ROBERTA_NER_EN,
+            None,
+            false,
+        ),
+        load_tokenizer(
+            ModelType::Albert,
+            AlbertVocabResources::ALBERT_BASE_V2,
+            None,
+            true,
+        ),
+    ];
+}
+
+fn check_offsets(offsets: &[Option<Offset>], num_characters: usize) {
+    for offset in offsets.iter().flatten() {
+        assert!(offset.begin <= offset.end);
+        assert!(offset.end as usize <= num_characters);
+    }
+}
+
+fuzz_target!(|data: &[u8]| {
+    let text = InputSanitizer::default().sanitize_bytes(data);
+    let num_characters = text.chars().count();
+    let split = text
+        .char_indices()
+        .nth(num_characters / 2)
+        .map_or(text.len(), |(position, _)| position);
+    let (text_1, text_2) = text.split_at(split);
+
+    for tokenizer in TOKENIZERS.iter() {
+        let tokens = tokenizer.tokenize_with_offsets(&text);
+        check_offsets(&tokens.offsets, num_characters);
+
+        let encoded =
+            tokenizer.encode_list(&[text.as_str()], 128, &TruncationStrategy::LongestFirst, 0);
+        check_offsets(&encoded[0].token_offsets, num_characters);
+        let _ = tokenizer.decode(&encoded[0].token_ids, true, true);
+
+        let _ = tokenizer.encode_pair_list(
+            &[(text_1, text_2)],
+            128,
+            &TruncationStrategy::LongestFirst,
+            16,
+        );
+    }
+});
@@ -15,6 +15,7 @@ use crate::bart::attention::LayerState;
 use crate::bart::decoder::BartDecoder;
 use crate::bart::encoder::BartEncoder;
 use crate::common::activations::Activation;
+use crate::common::attention_mask::get_query_attention_mask;
 use crate::common::dropout::Dropout;
 use crate::common::kind::get_negative_infinity;
 use crate::pipelines::common::{ModelType, TokenizerOption};
@@ -303,13 +304,18 @@ pub(crate) fn _make_causal_mask(
 }
 
 pub(crate) fn _expand_mask(mask: &Tensor, target_length: Option<i64>, dtype: Kind) -> Tensor {
-    let (batch_size, source_length) = mask.size2().unwrap();
-    let target_length = target_length.unwrap_or(source_length);
-    let expanded_mask = mask
-        .unsqueeze(1)
-        .unsqueeze(1)
-        .expand(&[batch_size, 1, target_length, source_length], true)
-        .totype(dtype);
+    let expanded_mask = match mask.dim() {
+        3 => mask.unsqueeze(1),
+        4 => mask.shallow_clone(),
+        _ => {
+            let (batch_size, source_length) = mask.size2().unwrap();
+            let target_length = target_length.unwrap_or(source_length);
+            mask.unsqueeze(1)
+                .unsqueeze(1)
+                .expand(&[batch_size, 1, target_length, source_length], true)
+        }
+    }
+    .totype(dtype);
     let inverted_mask: Tensor = 1 - expanded_mask;
     inverted_mask.masked_fill(
         &inverted_mask.to_kind(Kind::Bool),
@@ -322,7 +328,21 @@ pub(crate) fn _prepare_decoder_attention_mask(
     input_shape: &[i64],
     input_embeds: &Tensor,
     past_key_values_length: i64,
-) -> Option<Tensor> {
+) -> Result<Option<Tensor>, RustBertError> {
+    if let Some(attention_mask) = attention_mask {
+        if attention_mask.dim() > 2 {
+            let attention_mask = get_query_attention_mask(
+                attention_mask,
+                *input_shape.last().unwrap(),
+                past_key_values_length,
+            )?;
+            return Ok(Some(_expand_mask(
+                &attention_mask,
+                None,
+                input_embeds.kind(),
+            )));
+        }
+    }
     let last_input_shape_dim = *input_shape.last().unwrap();
     let mut combined_attention_mask = if last_input_shape_dim > 1 {
         Some(_make_causal_mask(
@@ -347,7 +367,7 @@ pub(crate) fn _prepare_decoder_attention_mask(
         };
     }
 
-    combined_attention_mask
+    Ok(combined_attention_mask)
 }
 
 fn _shift_tokens_right(input_ids: &Tensor, pad_token_id: i64) -> Tensor {
@@ -436,10 +456,13 @@ impl BartModel {
     ///
     /// * `input_ids` - Optional input tensor of shape (*batch size*, *source_sequence_length*). Must be provided when not running in generation mode
     /// * `attention_mask` - Optional attention mask of shape (*batch size*, *source_sequence_length*) for the encoder positions. Positions with a mask with value 0 will be masked.
+    ///   Pre-built masks of shape (*batch size*, *source_sequence_length*, *source_sequence_length*) or (*batch size*, *num_heads or 1*, *source_sequence_length*, *source_sequence_length*)
+    ///   are used as-is for the encoder self-attention and reduced to a padding mask for the decoder cross-attention.
     /// * `decoder_input_ids` - Optional input tensor of shape (*batch size*, *target_sequence_length*). Must be provided when running in generation mode (e.g. initialized with a BOS token)
     /// * `encoder_outputs` - Optional tuple made of a tensor of shape (*batch size*, *source_sequence_length*, *encoder_hidden_dim*) and optional vectors of tensors of length *num_encoder_layers* with shape (*batch size*, *source_sequence_length*, *hidden_size*).
     /// These correspond to the encoder last hidden state and optional hidden states/attention weights for encoder layers. When provided, the encoder hidden state will not be recalculated. Useful for generation tasks.
     /// * `decoder_attention_mask` - Optional attention mask of shape (*batch size*, *target_sequence_length*) for the decoder positions. Positions with a mask with value 0 will be masked.
+    ///   Masks of shape (*batch size*, *target_sequence_length*, *target_sequence_length*) replace the causal mask (e.g. prefix-LM masks built with `build_prefix_lm_attention_mask`).
     /// * `train` - boolean flag to turn on/off the dropout layers in the model. Should be set to false for inference.
     ///
     /// # Returns
@@ -496,7 +519,7 @@ impl BartModel {
         decoder_attention_mask: Option<&Tensor>,
         layer_states: Option<Vec<(Option<LayerState>, Option<LayerState>)>>,
         train: bool,
-    ) -> BartModelOutput {
+    ) -> Result<BartModelOutput, RustBertError> {
         let calc_decoder_input_ids = if decoder_input_ids.is_none() {
             Some(_shift_tokens_right(input_ids.unwrap(), self.pad_token_id))
         } else {
@@ -538,16 +561,17 @@ impl BartModel {
             &self.embeddings,
             layer_states,
             train,
-        );
-        BartModelOutput {
+        )?;
+        Ok(BartModelOutput {
             decoder_output: decoder_output.hidden_state,
             encoder_hidden_state: calc_hidden_states,
             cache: decoder_output.next_decoder_cache,
             all_decoder_hidden_states: decoder_output.all_hidden_states,
             all_decoder_attentions: decoder_output.all_attentions,
+            all_decoder_cross_attentions: decoder_output.all_cross_attentions,
             all_encoder_hidden_states,
             all_encoder_attentions,
-        }
+        })
     }
 }
 
@@ -654,7 +678,7 @@ impl BartForConditionalGeneration {
         decoder_attention_mask: Option<&Tensor>,
         old_layer_states: Option<Vec<(Option<LayerState>, Option<LayerState>)>>,
         train: bool,
-    ) -> BartModelOutput {
+    ) -> Result<BartModelOutput, RustBertError> {
         let base_model_output = self.base_model.forward_t(
             input_ids,
             attention_mask,
@@ -663,15 +687,15 @@ impl BartForConditionalGeneration {
             decoder_attention_mask,
             old_layer_states,
             train,
-        );
+        )?;
 
         let lm_logits = base_model_output
             .decoder_output
             .linear::<Tensor>(&self.base_model.embeddings.ws, None);
-        BartModelOutput {
+        Ok(BartModelOutput {
             decoder_output: lm_logits,
             ..base_model_output
-        }
+        })
     }
 
     pub fn encode(&self, input_ids: &Tensor, attention_mask: Option<&Tensor>) -> Tensor {
@@ -846,7 +870,7 @@ impl BartForSequenceClassification {
         decoder_input_ids: Option<&Tensor>,
         decoder_attention_mask: Option<&Tensor>,
         train: bool,
-    ) -> BartModelOutput {
+    ) -> Result<BartModelOutput, RustBertError> {
         let base_model_output = self.base_model.forward_t(
             Some(input_ids),
             attention_mask,
@@ -855,7 +879,7 @@ impl BartForSequenceClassification {
             decoder_attention_mask,
             None,
             train,
-        );
+        )?;
         let eos_mask = input_ids.eq(self.eos_token_id);
         let reshape = eos_mask.sum_dim_intlist(&[1], true, input_ids.kind());
         let sentence_representation = base_model_output
@@ -874,15 +898,16 @@ impl BartForSequenceClassification {
         let logits = self
             .classification_head
             .forward_t(&sentence_representation, train);
-        BartModelOutput {
+        Ok(BartModelOutput {
             decoder_output: logits,
             encoder_hidden_state: base_model_output.encoder_hidden_state,
             cache: None,
             all_decoder_hidden_states: base_model_output.all_decoder_hidden_states,
             all_decoder_attentions: base_model_output.all_decoder_attentions,
+            all_decoder_cross_attentions: base_model_output.all_decoder_cross_attentions,
             all_encoder_hidden_states: base_model_output.all_encoder_hidden_states,
             all_encoder_attentions: base_model_output.all_encoder_attentions,
-        }
+        })
     }
 }
 
@@ -908,6 +933,7 @@ impl LMHeadModel for BartForConditionalGeneration {
     ///   - `lm_logits` - `Tensor` of shape (*batch size*, *sequence_length*, *vocab_size*) representing the logits for each vocab item and position
     ///   - `cache` - `BartCache` made of `Option<Vec<(Option<Vec<&LayerState, &LayerState>>)>>` of length *n_layer* containing the encoder past keys and values for
     ///     both the self attention and the encoder cross attention of each layer of the decoder.
+    ///   - `hidden_states` - `Option<Tensor>` of shape (*batch size*, *sequence_length*, *hidden_size*) representing the activations of the last decoder hidden state
     ///
     /// # Example
     ///
@@ -962,7 +988,7 @@ impl LMHeadModel for BartForConditionalGeneration {
                 None,
                 cached_layer_states,
                 train,
-            ),
+            )?,
 
             Cache::None => self.base_model.forward_t(
                 input_ids,
@@ -972,7 +998,7 @@ impl LMHeadModel for BartForConditionalGeneration {
                 None,
                 None,
                 train,
-            ),
+            )?,
             _ => {
                 return Err(RustBertError::ValueError(
                     "Cache not compatible with BART Model".into(),
@@ -986,6 +1012,45 @@ impl LMHeadModel for BartForConditionalGeneration {
         Ok(LMModelOutput {
             lm_logits,
             cache: Cache::BARTCache(base_model_output.cache),
+            hidden_states: Some(base_model_output.decoder_output),
+        })
+    }
+
+    fn forward_with_decoder_attention_mask(
+        &self,
+        decoder_input_ids: &Tensor,
+        cache: Cache,
+        attention_mask: Option<&Tensor>,
+        encoder_outputs: &Tensor,
+        decoder_attention_mask: &Tensor,
+        train: bool,
+    ) -> Result<LMModelOutput, RustBertError> {
+        let cached_layer_states = match cache {
+            Cache::BARTCache(cached_layer_states) => cached_layer_states,
+            Cache::None => None,
+            _ => {
+                return Err(RustBertError::ValueError(
+                    "Cache not compatible with BART Model".into(),
+                ));
+            }
+        };
+        let base_model_output = self.base_model.forward_t(
+            None,
+            attention_mask,
+            Some(decoder_input_ids),
+            Some(encoder_outputs),
+            Some(decoder_attention_mask),
+            cached_layer_states,
+            train,
+        )?;
+
+        let lm_logits = base_model_output
+            .decoder_output
+            .linear::<Tensor>(&self.base_model.embeddings.ws, None);
+        Ok(LMModelOutput {
+            lm_logits,
+            cache: Cache::BARTCache(base_model_output.cache),
+            hidden_states: Some(base_model_output.decoder_output),
         })
     }
 }
@@ -1005,6 +1070,8 @@ pub struct BartModelOutput {
     pub all_decoder_hidden_states: Option<Vec<Tensor>>,
     /// Attention weights for all layers of the decoder
     pub all_decoder_attentions: Option<Vec<Tensor>>,
+    /// Cross-attention weights (over the encoder hidden states) for all layers of the decoder
+    pub all_decoder_cross_attentions: Option<Vec<Tensor>>,
     /// Hidden states for all layers of the encoder
     pub all_encoder_hidden_states: Option<Vec<Tensor>>,
     /// Attention weights for all layers of the encoder
@@ -1086,14 +1153,16 @@ impl BartGenerator {
         tokenizer: TokenizerOption,
     ) -> Result<BartGenerator, RustBertError> {
         let config_path = generate_config.config_resource.get_local_path()?;
-        let weights_path = generate_config.model_resource.get_local_path()?;
         let device = generate_config.device;
 
         generate_config.validate();
         let mut var_store = nn::VarStore::new(device);
-        let config = BartConfig::from_file(config_path);
+        let mut config = BartConfig::from_file(config_path);
+        if generate_config.output_attentions {
+            config.output_attentions = Some(true);
+        }
         let model = BartForConditionalGeneration::new(&var_store.root(), &config);
-        var_store.load(weights_path)?;
+        crate::resources::load_weights(&generate_config.model_resource, &mut var_store)?;
 
         let bos_token_id = Some(config.bos_token_id.unwrap_or(0));
         let eos_token_ids = Some(match config.eos_token_id {
@@ -1224,6 +1293,7 @@ impl PrivateLanguageGenerator<BartForConditionalGeneration, RobertaVocab, Robert
         prompt_text: &[S],
         max_len: i64,
         pad_token_id: Option<i64>,
+        _add_bos_token: bool,
     ) -> Tensor
     where
         S: AsRef<str> + Sync,
@@ -17,11 +17,13 @@ use crate::bart::embeddings::{
 };
 use crate::bart::BartConfig;
 use crate::common::activations::Activation;
+use crate::common::attention_mask::get_padding_mask;
 use crate::common::dropout::Dropout;
 use crate::{
     bart::attention::{BartAttention, LayerState},
     common::activations::TensorFunction,
 };
+use crate::RustBertError;
 use std::borrow::{Borrow, BorrowMut};
 use tch::{nn, Tensor};
 
@@ -127,6 +129,7 @@ impl DecoderLayer {
     ) -> (
         Tensor,
         Option<Tensor>,
+        Option<Tensor>,
         (Option<LayerState>, Option<LayerState>),
     ) {
         let (output, attention_weights, new_self_layer_states) =
@@ -135,13 +138,14 @@ impl DecoderLayer {
         let output: Tensor = output.apply_t(&self.dropout, train) + x;
         let output = output.apply(&self.self_attention_layer_norm);
 
-        let (output1, _, new_encoder_layer_states) = self.encoder_attention.forward_t(
-            &output,
-            Some(encoder_hidden_states),
-            encoder_attention_mask,
-            layer_states.1,
-            train,
-        );
+        let (output1, cross_attention_weights, new_encoder_layer_states) =
+            self.encoder_attention.forward_t(
+                &output,
+                Some(encoder_hidden_states),
+                encoder_attention_mask,
+                layer_states.1,
+                train,
+            );
         let output1: Tensor = output1.apply_t(&self.dropout, train) + output;
         let output1 = output1.apply(&self.encoder_attention_layer_norm);
         let output2 = (self.activation.get_fn())(&output1.apply(&self.fc1));
@@ -153,6 +157,7 @@ impl DecoderLayer {
         (
             output2.apply(&self.final_layer_norm),
             attention_weights,
+            cross_attention_weights,
             (new_self_layer_states, new_encoder_layer_states),
         )
     }
@@ -248,7 +253,7 @@ impl BartDecoder {
         embeddings: &nn::Embedding,
         old_layer_states: Option<Vec<(Option<LayerState>, Option<LayerState>)>>,
         train: bool,
-    ) -> BartDecoderOutput {
+    ) -> Result<BartDecoderOutput, RustBertError> {
         let past_key_values_length = if let Some(old_layer_states_values) = &old_layer_states {
             if let Some(old_value_state) = &old_layer_states_values[0].0 {
                 old_value_state.prev_key.size()[2]
@@ -270,10 +275,12 @@ impl BartDecoder {
             input_ids.size().as_slice(),
             &x,
             past_key_values_length,
-        );
+        )?;
 
         let encoder_attention_mask = encoder_attention_mask
-            .map(|mask| _expand_mask(mask, Some(*input_ids.size().last().unwrap()), x.kind()));
+            .map(get_padding_mask)
+            .transpose()?
+            .map(|mask| _expand_mask(&mask, Some(*input_ids.size().last().unwrap()), x.kind()));
 
         let x = if let Some(layer_norm_embedding) = &self.layer_norm_embedding {
             x.apply(layer_norm_embedding)
@@ -291,6 +298,11 @@ impl BartDecoder {
         } else {
             None
         };
+        let mut all_cross_attentions: Option<Vec<Tensor>> = if self.output_attentions {
+            Some(Vec::with_capacity(self.layers.len()))
+        } else {
+            None
+        };
         let mut next_decoder_cache: Option<Vec<(Option<LayerState>, Option<LayerState>)>> =
             if self.output_past {
                 if old_layer_states.is_some() {
@@ -325,18 +337,22 @@ impl BartDecoder {
             if let Some(attentions) = all_attentions.borrow_mut() {
                 attentions.push(attention_weights.as_ref().unwrap().copy());
             };
+            if let Some(cross_attentions) = all_cross_attentions.borrow_mut() {
+                cross_attentions.push(temp.2.as_ref().unwrap().copy());
+            };
             if let Some(value) = &mut next_decoder_cache {
-                value[layer_idx] = temp.2
+                value[layer_idx] = temp.3
             };
         }
 
-        BartDecoderOutput {
+        Ok(BartDecoderOutput {
             hidden_state,
             encoder_attention_mask,
             next_decoder_cache,
             all_hidden_states,
             all_attentions,
-        }
+            all_cross_attentions,
+        })
     }
 }
 
@@ -352,4 +368,6 @@ pub struct BartDecoderOutput {
     pub all_hidden_states: Option<Vec<Tensor>>,
     /// Attention weights for all intermediate layers
     pub all_attentions: Option<Vec<Tensor>>,
+    /// Attention weights over the encoder hidden states (cross-attention) for all intermediate layers
+    pub all_cross_attentions: Option<Vec<Tensor>>,
 }
@@ -12,8 +12,11 @@
 // limitations under the License.
 
 use crate::bert::bert_model::BertConfig;
-use crate::common::activations::TensorFunction;
+use crate::common::activations::{Activation, TensorFunction};
 use crate::common::dropout::Dropout;
+use crate::common::fused::{linear_activation, linear_residual};
+use crate::common::pruning::{get_head_dimensions, prune_linear};
+use crate::RustBertError;
 use std::borrow::Borrow;
 use tch::{nn, Tensor};
 
@@ -26,6 +29,7 @@ pub struct BertSelfAttention {
     query: nn::Linear,
     key: nn::Linear,
     value: nn::Linear,
+    head_mask: Option<Tensor>,
 }
 
 impl BertSelfAttention {
@@ -71,6 +75,7 @@ impl BertSelfAttention {
             query,
             key,
             value,
+            head_mask: None,
         }
     }
 
@@ -126,6 +131,16 @@ impl BertSelfAttention {
         let weights = scores
             .softmax(-1, scores.kind())
             .apply_t(&self.dropout, train);
+        let weights = match &self.head_mask {
+            Some(head_mask) => {
+                weights
+                    * head_mask
+                        .to_device(scores.device())
+                        .to_kind(scores.kind())
+                        .view([1, -1, 1, 1])
+            }
+            None => weights,
+        };
         let context = self.flatten(weights.matmul(&value_layer), bs, self.attention_head_size);
 
         if !self.output_attentions {
@@ -134,13 +149,49 @@ impl BertSelfAttention {
             (context, Some(weights))
         }
     }
+
+    /// Keeps the given attention heads, returning the indices of their dimensions in the context vector
+    pub(crate) fn retain_heads(&mut self, heads: &[i64]) -> Result<Tensor, RustBertError> {
+        let head_dimensions = get_head_dimensions(
+            heads,
+            self.num_attention_heads,
+            self.attention_head_size,
+            self.query.ws.device(),
+        )?;
+        prune_linear(&mut self.query, &head_dimensions, true);
+        prune_linear(&mut self.key, &head_dimensions, true);
+        prune_linear(&mut self.value, &head_dimensions, true);
+        self.num_attention_heads = heads.len() as i64;
+        self.head_mask = None;
+        Ok(head_dimensions)
+    }
+
+    pub(crate) fn num_attention_heads(&self) -> usize {
+        self.num_attention_heads as usize
+    }
+
+    /// Sets the mask of shape (*num_attention_heads*) multiplying the attention weights of each head
+    pub(crate) fn set_head_mask(&mut self, head_mask: Option<Tensor>) -> Result<(), RustBertError> {
+        if let Some(head_mask) = &head_mask {
+            if head_mask.size() != [self.num_attention_heads] {
+                return Err(RustBertError::ValueError(format!(
+                    "Head mask of shape {:?} does not match the {} attention heads of the layer",
+                    head_mask.size(),
+                    self.num_attention_heads
+                )));
+            }
+        }
+        self.head_mask = head_mask;
+        Ok(())
+    }
 }
 
 #[derive(Debug)]
 pub struct BertSelfOutput {
     linear: nn::Linear,
-    layer_norm: nn::LayerNorm,
+    layer_norm: Option<nn::LayerNorm>,
     dropout: Dropout,
+    fused: bool,
 }
 
 impl BertSelfOutput {
@@ -156,27 +207,38 @@ impl BertSelfOutput {
             config.hidden_size,
             Default::default(),
         );
-        let layer_norm_config = nn::LayerNormConfig {
-            eps: 1e-12,
-            ..Default::default()
-        };
-        let layer_norm =
-            nn::layer_norm(p / "LayerNorm", vec![config.hidden_size], layer_norm_config);
+        // With pre-norm layers, the attention inputs are normalized by the `BertAttention` instead
+        let layer_norm = (!config.is_pre_layer_norm()).then(|| {
+            let layer_norm_config = nn::LayerNormConfig {
+                eps: 1e-12,
+                ..Default::default()
+            };
+            nn::layer_norm(p / "LayerNorm", vec![config.hidden_size], layer_norm_config)
+        });
         let dropout = Dropout::new(config.hidden_dropout_prob);
+        let fused = config.fused_feed_forward.unwrap_or(false);
 
         BertSelfOutput {
             linear,
             layer_norm,
             dropout,
+            fused,
         }
     }
 
     pub fn forward_t(&self, hidden_states: &Tensor, input_tensor: &Tensor, train: bool) -> Tensor {
-        let hidden_states: Tensor = input_tensor
-            + hidden_states
-                .apply(&self.linear)
-                .apply_t(&self.dropout, train);
-        hidden_states.apply(&self.layer_norm)
+        let hidden_states = if self.fused && !train {
+            linear_residual(hidden_states, &self.linear, input_tensor)
+        } else {
+            input_tensor
+                + hidden_states
+                    .apply(&self.linear)
+                    .apply_t(&self.dropout, train)
+        };
+        match &self.layer_norm {
+            Some(layer_norm) => hidden_states.apply(layer_norm),
+            None => hidden_states,
+        }
     }
 }
 
@@ -184,6 +246,7 @@ impl BertSelfOutput {
 pub struct BertAttention {
     _self: BertSelfAttention,
     output: BertSelfOutput,
+    layer_norm: Option<nn::LayerNorm>,
 }
 
 impl BertAttention {
@@ -195,7 +258,22 @@ impl BertAttention {
 
         let _self = BertSelfAttention::new(p / "self", config);
         let output = BertSelfOutput::new(p / "output", config);
-        BertAttention { _self, output }
+        let layer_norm = config.is_pre_layer_norm().then(|| {
+            let layer_norm_config = nn::LayerNormConfig {
+                eps: 1e-12,
+                ..Default::default()
+            };
+            nn::layer_norm(
+                p / "self_attn_layer_norm",
+                vec![config.hidden_size],
+                layer_norm_config,
+            )
+        });
+        BertAttention {
+            _self,
+            output,
+            layer_norm,
+        }
     }
 
     pub fn forward_t(
@@ -206,8 +284,12 @@ impl BertAttention {
         encoder_mask: Option<&Tensor>,
         train: bool,
     ) -> (Tensor, Option<Tensor>) {
+        let normalized_hidden_states = self
+            .layer_norm
+            .as_ref()
+            .map(|layer_norm| hidden_states.apply(layer_norm));
         let (self_output, attention_weights) = self._self.forward_t(
-            hidden_states,
+            normalized_hidden_states.as_ref().unwrap_or(hidden_states),
             mask,
             encoder_hidden_states,
             encoder_mask,
@@ -217,11 +299,26 @@ impl BertAttention {
         let self_output = self.output.forward_t(&self_output, hidden_states, train);
         (self_output, attention_weights)
     }
+
+    pub(crate) fn retain_heads(&mut self, heads: &[i64]) -> Result<(), RustBertError> {
+        let head_dimensions = self._self.retain_heads(heads)?;
+        prune_linear(&mut self.output.linear, &head_dimensions, false);
+        Ok(())
+    }
+
+    pub(crate) fn num_attention_heads(&self) -> usize {
+        self._self.num_attention_heads()
+    }
+
+    pub(crate) fn set_head_mask(&mut self, head_mask: Option<Tensor>) -> Result<(), RustBertError> {
+        self._self.set_head_mask(head_mask)
+    }
 }
 
 pub struct BertIntermediate {
     lin: nn::Linear,
     activation: TensorFunction,
+    fused_activation: Option<Activation>,
 }
 
 impl BertIntermediate {
@@ -238,18 +335,34 @@ impl BertIntermediate {
             Default::default(),
         );
         let activation = config.hidden_act.get_function();
-        BertIntermediate { lin, activation }
+        let fused_activation = if config.fused_feed_forward.unwrap_or(false) {
+            Some(config.hidden_act)
+        } else {
+            None
+        };
+        BertIntermediate {
+            lin,
+            activation,
+            fused_activation,
+        }
     }
 
     pub fn forward(&self, hidden_states: &Tensor) -> Tensor {
-        (self.activation.get_fn())(&hidden_states.apply(&self.lin))
+        // The activation may be applied in place: the fused path is only taken when gradients are not tracked
+        match self.fused_activation {
+            Some(activation) if !hidden_states.requires_grad() => {
+                linear_activation(hidden_states, &self.lin, activation)
+            }
+            _ => (self.activation.get_fn())(&hidden_states.apply(&self.lin)),
+        }
     }
 }
 
 pub struct BertOutput {
     lin: nn::Linear,
-    layer_norm: nn::LayerNorm,
+    layer_norm: Option<nn::LayerNorm>,
     dropout: Dropout,
+    fused: bool,
 }
 
 impl BertOutput {
@@ -265,24 +378,34 @@ impl BertOutput {
             config.hidden_size,
             Default::default(),
         );
-        let layer_norm_config = nn::LayerNormConfig {
-            eps: 1e-12,
-            ..Default::default()
-        };
-        let layer_norm =
-            nn::layer_norm(p / "LayerNorm", vec![config.hidden_size], layer_norm_config);
+        // With pre-norm layers, the feed-forward inputs are normalized by the `BertLayer` instead
+        let layer_norm = (!config.is_pre_layer_norm()).then(|| {
+            let layer_norm_config = nn::LayerNormConfig {
+                eps: 1e-12,
+                ..Default::default()
+            };
+            nn::layer_norm(p / "LayerNorm", vec![config.hidden_size], layer_norm_config)
+        });
         let dropout = Dropout::new(config.hidden_dropout_prob);
+        let fused = config.fused_feed_forward.unwrap_or(false);
 
         BertOutput {
             lin,
             layer_norm,
             dropout,
+            fused,
         }
     }
 
     pub fn forward_t(&self, hidden_states: &Tensor, input_tensor: &Tensor, train: bool) -> Tensor {
-        let hidden_states: Tensor =
-            input_tensor + hidden_states.apply(&self.lin).apply_t(&self.dropout, train);
-        hidden_states.apply(&self.layer_norm)
+        let hidden_states = if self.fused && !train {
+            linear_residual(hidden_states, &self.lin, input_tensor)
+        } else {
+            input_tensor + hidden_states.apply(&self.lin).apply_t(&self.dropout, train)
+        };
+        match &self.layer_norm {
+            Some(layer_norm) => hidden_states.apply(layer_norm),
+            None => hidden_states,
+        }
     }
 }
@@ -13,9 +13,11 @@
 
 use crate::bert::encoder::{BertEncoder, BertPooler};
 use crate::common::activations::Activation;
-use crate::common::dropout::Dropout;
+use crate::common::dropout::{Dropout, StochasticDepth};
+use crate::common::early_exit::{EarlyExitBatch, EarlyExitConfig};
 use crate::common::embeddings::get_shape_and_device_from_ids_embeddings_pair;
 use crate::common::linear::{linear_no_bias, LinearNoBias};
+use crate::common::pruning::{get_remaining_layers, PrunableModel};
 use crate::{
     bert::embeddings::{BertEmbedding, BertEmbeddings},
     common::activations::TensorFunction,
@@ -25,7 +27,7 @@ use serde::{Deserialize, Serialize};
 use std::borrow::Borrow;
 use std::collections::HashMap;
 use tch::nn::Init;
-use tch::{nn, Kind, Tensor};
+use tch::{nn, Device, Kind, Tensor};
 
 /// # BERT Pretrained model weight files
 pub struct BertModelResources;
@@ -140,10 +142,31 @@ pub struct BertConfig {
     pub is_decoder: Option<bool>,
     pub id2label: Option<HashMap<i64, String>>,
     pub label2id: Option<HashMap<String, i64>>,
+    /// Optional stochastic depth (layer drop) schedule applied to the encoder layers during training
+    pub stochastic_depth: Option<StochasticDepth>,
+    /// Use fused bias, activation and residual operations in the feed-forward layers during inference (default: false)
+    pub fused_feed_forward: Option<bool>,
+    /// Optional early exit of the sequence classification model: classifier heads on the intermediate layers let
+    /// the confident inputs leave the encoder early during inference
+    pub early_exit: Option<EarlyExitConfig>,
+    /// Model type of the configuration file (e.g. `xlm-roberta-xl`)
+    pub model_type: Option<String>,
+    /// Apply the layer normalization before the attention and feed-forward blocks (pre-norm, with a final layer
+    /// normalization after the last layer and none on the embeddings) instead of after the residual connections.
+    /// Defaults to true for the `xlm-roberta-xl` model type (XLM-RoBERTa-XL and XXL), false otherwise
+    pub pre_layer_norm: Option<bool>,
 }
 
 impl Config for BertConfig {}
 
+impl BertConfig {
+    /// Returns true if the layer normalization is applied before the attention and feed-forward blocks
+    pub fn is_pre_layer_norm(&self) -> bool {
+        self.pre_layer_norm
+            .unwrap_or_else(|| self.model_type.as_deref() == Some("xlm-roberta-xl"))
+    }
+}
+
 impl Default for BertConfig {
     fn default() -> Self {
         BertConfig {
@@ -163,6 +186,11 @@ impl Default for BertConfig {
             is_decoder: None,
             id2label: None,
             label2id: None,
+            stochastic_depth: None,
+            fused_feed_forward: None,
+            early_exit: None,
+            model_type: None,
+            pre_layer_norm: None,
         }
     }
 }
@@ -283,7 +311,9 @@ impl<T: BertEmbedding> BertModel<T> {
     /// # Arguments
     ///
     /// * `input_ids` - Optional input tensor of shape (*batch size*, *sequence_length*). If None, pre-computed embeddings must be provided (see `input_embeds`)
-    /// * `mask` - Optional mask of shape (*batch size*, *sequence_length*). Masked position have value 0, non-masked value 1. If None set to 1
+    /// * `mask` - Optional mask of shape (*batch size*, *sequence_length*). Masked position have value 0, non-masked value 1. If None set to 1.
+    ///   Pre-built masks of shape (*batch size*, *sequence_length*, *sequence_length*) or (*batch size*, *num_heads or 1*, *sequence_length*, *sequence_length*)
+    ///   (e.g. block-diagonal masks for packed sequences) are used as-is.
     /// * `token_type_ids` - Optional segment id of shape (*batch size*, *sequence_length*). Convention is value of 0 for the first sentence (incl. *SEP*) and 1 for the second sentence. If None set to 0.
     /// * `position_ids` - Optional position ids of shape (*batch size*, *sequence_length*). If None, will be incremented from 0.
     /// * `input_embeds` - Optional pre-computed input embeddings of shape (*batch size*, *sequence_length*, *hidden_size*). If None, input ids must be provided (see `input_ids`)
@@ -346,32 +376,8 @@ impl<T: BertEmbedding> BertModel<T> {
     ) -> Result<BertModelOutput, RustBertError> {
         let (input_shape, device) =
             get_shape_and_device_from_ids_embeddings_pair(input_ids, input_embeds)?;
-
-        let calc_mask = Tensor::ones(&input_shape, (Kind::Int8, device));
-        let mask = mask.unwrap_or(&calc_mask);
-
-        let extended_attention_mask = match mask.dim() {
-            3 => mask.unsqueeze(1),
-            2 => {
-                if self.is_decoder {
-                    let seq_ids = Tensor::arange(input_shape[1], (Kind::Int8, device));
-                    let causal_mask = seq_ids.unsqueeze(0).unsqueeze(0).repeat(&[
-                        input_shape[0],
-                        input_shape[1],
-                        1,
-                    ]);
-                    let causal_mask = causal_mask.le_tensor(&seq_ids.unsqueeze(0).unsqueeze(-1));
-                    causal_mask * mask.unsqueeze(1).unsqueeze(1)
-                } else {
-                    mask.unsqueeze(1).unsqueeze(1)
-                }
-            }
-            _ => {
-                return Err(RustBertError::ValueError(
-                    "Invalid attention mask dimension, must be 2 or 3".into(),
-                ));
-            }
-        };
+        let extended_attention_mask =
+            self.get_extended_attention_mask(mask, &input_shape, device)?;
 
         let embedding_output = self.embeddings.forward_t(
             input_ids,
@@ -432,6 +438,72 @@ impl<T: BertEmbedding> BertModel<T> {
             all_attentions: encoder_output.all_attentions,
         })
     }
+
+    /// Returns the attention mask broadcastable to the attention scores (1 for the positions attended to, 0 for
+    /// the masked positions), including the causal mask for decoders
+    fn get_extended_attention_mask(
+        &self,
+        mask: Option<&Tensor>,
+        input_shape: &[i64],
+        device: Device,
+    ) -> Result<Tensor, RustBertError> {
+        let calc_mask = Tensor::ones(input_shape, (Kind::Int8, device));
+        let mask = mask.unwrap_or(&calc_mask);
+
+        Ok(match mask.dim() {
+            4 => mask.shallow_clone(),
+            3 => mask.unsqueeze(1),
+            2 => {
+                if self.is_decoder {
+                    let seq_ids = Tensor::arange(input_shape[1], (Kind::Int8, device));
+                    let causal_mask = seq_ids.unsqueeze(0).unsqueeze(0).repeat(&[
+                        input_shape[0],
+                        input_shape[1],
+                        1,
+                    ]);
+                    let causal_mask = causal_mask.le_tensor(&seq_ids.unsqueeze(0).unsqueeze(-1));
+                    causal_mask * mask.unsqueeze(1).unsqueeze(1)
+                } else {
+                    mask.unsqueeze(1).unsqueeze(1)
+                }
+            }
+            _ => {
+                return Err(RustBertError::ValueError(
+                    "Invalid attention mask dimension, must be 2, 3 or 4".into(),
+                ));
+            }
+        })
+    }
+}
+
+impl<T: BertEmbedding> PrunableModel for BertModel<T> {
+    fn num_layers(&self) -> usize {
+        self.encoder.num_layers()
+    }
+
+    fn drop_top_layers(&mut self, num_layers: usize) -> Result<(), RustBertError> {
+        self.encoder.drop_top_layers(num_layers)
+    }
+
+    fn retain_attention_heads(
+        &mut self,
+        layer_index: usize,
+        heads: &[i64],
+    ) -> Result<(), RustBertError> {
+        self.encoder.retain_attention_heads(layer_index, heads)
+    }
+
+    fn num_attention_heads(&self, layer_index: usize) -> Result<usize, RustBertError> {
+        self.encoder.num_attention_heads(layer_index)
+    }
+
+    fn set_head_mask(
+        &mut self,
+        layer_index: usize,
+        head_mask: Option<Tensor>,
+    ) -> Result<(), RustBertError> {
+        self.encoder.set_head_mask(layer_index, head_mask)
+    }
 }
 
 pub struct BertPredictionHeadTransform {
@@ -636,15 +708,81 @@ impl BertForMaskedLM {
     }
 }
 
+impl PrunableModel for BertForMaskedLM {
+    fn num_layers(&self) -> usize {
+        self.bert.num_layers()
+    }
+
+    fn drop_top_layers(&mut self, num_layers: usize) -> Result<(), RustBertError> {
+        self.bert.drop_top_layers(num_layers)
+    }
+
+    fn retain_attention_heads(
+        &mut self,
+        layer_index: usize,
+        heads: &[i64],
+    ) -> Result<(), RustBertError> {
+        self.bert.retain_attention_heads(layer_index, heads)
+    }
+
+    fn num_attention_heads(&self, layer_index: usize) -> Result<usize, RustBertError> {
+        self.bert.num_attention_heads(layer_index)
+    }
+
+    fn set_head_mask(
+        &mut self,
+        layer_index: usize,
+        head_mask: Option<Tensor>,
+    ) -> Result<(), RustBertError> {
+        self.bert.set_head_mask(layer_index, head_mask)
+    }
+}
+
+/// # BERT exit head
+/// Pooler and classifier applied to the hidden states of an intermediate layer of a BERT sequence classification
+/// model with early exit
+struct BertExitHead {
+    pooler: BertPooler,
+    classifier: nn::Linear,
+}
+
+impl BertExitHead {
+    fn new<'p, P>(p: P, config: &BertConfig, num_labels: i64) -> BertExitHead
+    where
+        P: Borrow<nn::Path<'p>>,
+    {
+        let p = p.borrow();
+
+        let pooler = BertPooler::new(p / "pooler", config);
+        let classifier = nn::linear(
+            p / "classifier",
+            config.hidden_size,
+            num_labels,
+            Default::default(),
+        );
+        BertExitHead { pooler, classifier }
+    }
+
+    fn forward_t(&self, hidden_states: &Tensor, dropout: &Dropout, train: bool) -> Tensor {
+        self.pooler
+            .forward(hidden_states)
+            .apply_t(dropout, train)
+            .apply(&self.classifier)
+    }
+}
+
 /// # BERT for sequence classification
 /// Base BERT model with a classifier head to perform sentence or document-level classification
 /// It is made of the following blocks:
 /// - `bert`: Base BertModel
 /// - `classifier`: BERT linear layer for classification
+/// - `exit_heads`: classifier heads of the intermediate layers, if early exit is enabled in the configuration
 pub struct BertForSequenceClassification {
     bert: BertModel<BertEmbeddings>,
     dropout: Dropout,
     classifier: nn::Linear,
+    exit_heads: Vec<BertExitHead>,
+    early_exit: Option<EarlyExitConfig>,
 }
 
 impl BertForSequenceClassification {
@@ -688,11 +826,21 @@ impl BertForSequenceClassification {
             num_labels,
             Default::default(),
         );
+        let exit_heads = match config.early_exit {
+            Some(_) => (0..config.num_hidden_layers - 1)
+                .map(|layer_index| {
+                    BertExitHead::new(p / "exit_heads" / layer_index, config, num_labels)
+                })
+                .collect(),
+            None => vec![],
+        };
 
         BertForSequenceClassification {
             bert,
             dropout,
             classifier,
+            exit_heads,
+            early_exit: config.early_exit,
         }
     }
 
@@ -713,6 +861,10 @@ impl BertForSequenceClassification {
     ///   - `logits` - `Tensor` of shape (*batch size*, *num_labels*)
     ///   - `all_hidden_states` - `Option<Vec<Tensor>>` of length *num_hidden_layers* with shape (*batch size*, *sequence_length*, *hidden_size*)
     ///   - `all_attentions` - `Option<Vec<Tensor>>` of length *num_hidden_layers* with shape (*batch size*, *sequence_length*, *hidden_size*)
+    ///   - `exit_logits` - `Option<Vec<Tensor>>` logits of the exit heads of the intermediate layers (early exit, training mode)
+    ///   - `exit_layers` - `Option<Vec<i64>>` number of layers processed for each input (early exit, inference mode)
+    ///
+    /// With early exit, the hidden states and attentions of the intermediate layers are not returned.
     ///
     /// # Example
     ///
@@ -753,6 +905,19 @@ impl BertForSequenceClassification {
         input_embeds: Option<&Tensor>,
         train: bool,
     ) -> BertSequenceClassificationOutput {
+        if let Some(early_exit) = &self.early_exit {
+            return self
+                .forward_early_exit(
+                    early_exit,
+                    input_ids,
+                    mask,
+                    token_type_ids,
+                    position_ids,
+                    input_embeds,
+                    train,
+                )
+                .unwrap();
+        }
         let base_model_output = self
             .bert
             .forward_t(
@@ -776,8 +941,145 @@ impl BertForSequenceClassification {
             logits,
             all_hidden_states: base_model_output.all_hidden_states,
             all_attentions: base_model_output.all_attentions,
+            exit_logits: None,
+            exit_layers: None,
         }
     }
+
+    /// Forward pass processing the inputs layer by layer. In training mode, all the inputs go through all the
+    /// layers and the logits of every exit head are returned. In inference mode, the inputs for which an exit head
+    /// is confident enough leave the encoder, and the following layers only process the remaining inputs.
+    fn forward_early_exit(
+        &self,
+        early_exit: &EarlyExitConfig,
+        input_ids: Option<&Tensor>,
+        mask: Option<&Tensor>,
+        token_type_ids: Option<&Tensor>,
+        position_ids: Option<&Tensor>,
+        input_embeds: Option<&Tensor>,
+        train: bool,
+    ) -> Result<BertSequenceClassificationOutput, RustBertError> {
+        let (input_shape, device) =
+            get_shape_and_device_from_ids_embeddings_pair(input_ids, input_embeds)?;
+        let extended_attention_mask =
+            self.bert
+                .get_extended_attention_mask(mask, &input_shape, device)?;
+        let mut hidden_state = self.bert.embeddings.forward_t(
+            input_ids,
+            token_type_ids,
+            position_ids,
+            input_embeds,
+            train,
+        )?;
+        let mut extended_attention_mask: Tensor =
+            ((extended_attention_mask.ones_like() - extended_attention_mask) * -10000.0)
+                .to_kind(hidden_state.kind());
+
+        let num_layers = self.bert.encoder.num_layers();
+        let mut exit_logits = Vec::with_capacity(self.exit_heads.len());
+        let mut exit_batch = EarlyExitBatch::new(input_shape[0], device);
+        for layer_index in 0..num_layers {
+            hidden_state = self.bert.encoder.forward_layer_t(
+                layer_index,
+                &hidden_state,
+                Some(&extended_attention_mask),
+                train,
+            );
+            let layers_processed = layer_index + 1;
+            if train {
+                if let Some(exit_head) = self.exit_heads.get(layer_index) {
+                    exit_logits.push(exit_head.forward_t(&hidden_state, &self.dropout, train));
+                }
+            } else if early_exit.can_exit(layers_processed, num_layers) {
+                let layer_logits =
+                    self.exit_heads[layer_index].forward_t(&hidden_state, &self.dropout, train);
+                match exit_batch.exit(
+                    &layer_logits,
+                    layers_processed as i64,
+                    Some(early_exit.confidence_threshold),
+                ) {
+                    Some(remaining_positions) => {
+                        hidden_state = hidden_state.index_select(0, &remaining_positions);
+                        extended_attention_mask =
+                            extended_attention_mask.index_select(0, &remaining_positions);
+                    }
+                    None => {
+                        let (logits, exit_layers) = exit_batch.finish();
+                        return Ok(BertSequenceClassificationOutput {
+                            logits,
+                            all_hidden_states: None,
+                            all_attentions: None,
+                            exit_logits: None,
+                            exit_layers: Some(exit_layers),
+                        });
+                    }
+                }
+            }
+        }
+
+        let logits = self
+            .bert
+            .pooler
+            .as_ref()
+            .unwrap()
+            .forward(&hidden_state)
+            .apply_t(&self.dropout, train)
+            .apply(&self.classifier);
+        let (logits, exit_logits, exit_layers) = if train {
+            (logits, Some(exit_logits), None)
+        } else {
+            let _ = exit_batch.exit(&logits, num_layers as i64, None);
+            let (logits, exit_layers) = exit_batch.finish();
+            (logits, None, Some(exit_layers))
+        };
+        Ok(BertSequenceClassificationOutput {
+            logits,
+            all_hidden_states: None,
+            all_attentions: None,
+            exit_logits,
+            exit_layers,
+        })
+    }
+}
+
+impl PrunableModel for BertForSequenceClassification {
+    fn num_layers(&self) -> usize {
+        self.bert.num_layers()
+    }
+
+    /// Drops the top layers of the encoder. With early exit, the exit head of the new last layer replaces the
+    /// pooler and classifier of the model.
+    fn drop_top_layers(&mut self, num_layers: usize) -> Result<(), RustBertError> {
+        let remaining_layers = get_remaining_layers(self.bert.num_layers(), num_layers)?;
+        self.bert.drop_top_layers(num_layers)?;
+        if (num_layers > 0) & !self.exit_heads.is_empty() {
+            self.exit_heads.truncate(remaining_layers);
+            let exit_head = self.exit_heads.pop().unwrap();
+            self.bert.pooler = Some(exit_head.pooler);
+            self.classifier = exit_head.classifier;
+        }
+        Ok(())
+    }
+
+    fn retain_attention_heads(
+        &mut self,
+        layer_index: usize,
+        heads: &[i64],
+    ) -> Result<(), RustBertError> {
+        self.bert.retain_attention_heads(layer_index, heads)
+    }
+
+    fn num_attention_heads(&self, layer_index: usize) -> Result<usize, RustBertError> {
+        self.bert.num_attention_heads(layer_index)
+    }
+
+    fn set_head_mask(
+        &mut self,
+        layer_index: usize,
+        head_mask: Option<Tensor>,
+    ) -> Result<(), RustBertError> {
+        self.bert.set_head_mask(layer_index, head_mask)
+    }
 }
 
 /// # BERT for multiple choices
@@ -920,10 +1222,42 @@ impl BertForMultipleChoice {
             logits,
             all_hidden_states: base_model_output.all_hidden_states,
             all_attentions: base_model_output.all_attentions,
+            exit_logits: None,
+            exit_layers: None,
         }
     }
 }
 
+impl PrunableModel for BertForMultipleChoice {
+    fn num_layers(&self) -> usize {
+        self.bert.num_layers()
+    }
+
+    fn drop_top_layers(&mut self, num_layers: usize) -> Result<(), RustBertError> {
+        self.bert.drop_top_layers(num_layers)
+    }
+
+    fn retain_attention_heads(
+        &mut self,
+        layer_index: usize,
+        heads: &[i64],
+    ) -> Result<(), RustBertError> {
+        self.bert.retain_attention_heads(layer_index, heads)
+    }
+
+    fn num_attention_heads(&self, layer_index: usize) -> Result<usize, RustBertError> {
+        self.bert.num_attention_heads(layer_index)
+    }
+
+    fn set_head_mask(
+        &mut self,
+        layer_index: usize,
+        head_mask: Option<Tensor>,
+    ) -> Result<(), RustBertError> {
+        self.bert.set_head_mask(layer_index, head_mask)
+    }
+}
+
 /// # BERT for token classification (e.g. NER, POS)
 /// Token-level classifier predicting a label for each token provided. Note that because of wordpiece tokenization, the labels predicted are
 /// not necessarily aligned with words in the sentence.
@@ -1069,6 +1403,36 @@ impl BertForTokenClassification {
     }
 }
 
+impl PrunableModel for BertForTokenClassification {
+    fn num_layers(&self) -> usize {
+        self.bert.num_layers()
+    }
+
+    fn drop_top_layers(&mut self, num_layers: usize) -> Result<(), RustBertError> {
+        self.bert.drop_top_layers(num_layers)
+    }
+
+    fn retain_attention_heads(
+        &mut self,
+        layer_index: usize,
+        heads: &[i64],
+    ) -> Result<(), RustBertError> {
+        self.bert.retain_attention_heads(layer_index, heads)
+    }
+
+    fn num_attention_heads(&self, layer_index: usize) -> Result<usize, RustBertError> {
+        self.bert.num_attention_heads(layer_index)
+    }
+
+    fn set_head_mask(
+        &mut self,
+        layer_index: usize,
+        head_mask: Option<Tensor>,
+    ) -> Result<(), RustBertError> {
+        self.bert.set_head_mask(layer_index, head_mask)
+    }
+}
+
 /// # BERT for question answering
 /// Extractive question-answering model based on a BERT language model. Identifies the segment of a context that answers a provided question.
 /// Please note that a significant amount of pre- and post-processing is required to perform end-to-end question answering.
@@ -1209,6 +1573,36 @@ impl BertForQuestionAnswering {
     }
 }
 
+impl PrunableModel for BertForQuestionAnswering {
+    fn num_layers(&self) -> usize {
+        self.bert.num_layers()
+    }
+
+    fn drop_top_layers(&mut self, num_layers: usize) -> Result<(), RustBertError> {
+        self.bert.drop_top_layers(num_layers)
+    }
+
+    fn retain_attention_heads(
+        &mut self,
+        layer_index: usize,
+        heads: &[i64],
+    ) -> Result<(), RustBertError> {
+        self.bert.retain_attention_heads(layer_index, heads)
+    }
+
+    fn num_attention_heads(&self, layer_index: usize) -> Result<usize, RustBertError> {
+        self.bert.num_attention_heads(layer_index)
+    }
+
+    fn set_head_mask(
+        &mut self,
+        layer_index: usize,
+        head_mask: Option<Tensor>,
+    ) -> Result<(), RustBertError> {
+        self.bert.set_head_mask(layer_index, head_mask)
+    }
+}
+
 /// # BERT for sentence embeddings
 /// Transformer usable in [`SentenceEmbeddingsModel`](crate::pipelines::sentence_embeddings::SentenceEmbeddingsModel).
 pub type BertForSentenceEmbeddings = BertModel<BertEmbeddings>;
@@ -1243,6 +1637,10 @@ pub struct BertSequenceClassificationOutput {
     pub all_hidden_states: Option<Vec<Tensor>>,
     /// Attention weights for all intermediate layers
     pub all_attentions: Option<Vec<Tensor>>,
+    /// Logits of the exit heads of the intermediate layers (early exit, training mode)
+    pub exit_logits: Option<Vec<Tensor>>,
+    /// Number of layers processed for each input (early exit, inference mode)
+    pub exit_layers: Option<Vec<i64>>,
 }
 
 /// Container for the BERT token classification model output.
@@ -13,6 +13,9 @@
 
 use crate::bert::attention::{BertAttention, BertIntermediate, BertOutput};
 use crate::bert::bert_model::BertConfig;
+use crate::common::dropout::DropPath;
+use crate::common::pruning::get_remaining_layers;
+use crate::RustBertError;
 use std::borrow::{Borrow, BorrowMut};
 use tch::{nn, Tensor};
 
@@ -24,12 +27,14 @@ use tch::{nn, Tensor};
 /// - `is_decoder`: flag indicating if the model is used as a decoder
 /// - `intermediate`: `BertIntermediate` intermediate layer
 /// - `output`: `BertOutput` output layer
+/// - `layer_norm`: (optional) layer normalization of the feed-forward inputs, for pre-norm layers (XLM-RoBERTa-XL)
 pub struct BertLayer {
     attention: BertAttention,
     is_decoder: bool,
     cross_attention: Option<BertAttention>,
     intermediate: BertIntermediate,
     output: BertOutput,
+    layer_norm: Option<nn::LayerNorm>,
 }
 
 impl BertLayer {
@@ -77,6 +82,9 @@ impl BertLayer {
 
         let intermediate = BertIntermediate::new(p / "intermediate", config);
         let output = BertOutput::new(p / "output", config);
+        let layer_norm = config
+            .is_pre_layer_norm()
+            .then(|| pre_layer_norm(p / "LayerNorm", config));
 
         BertLayer {
             attention,
@@ -84,6 +92,7 @@ impl BertLayer {
             cross_attention,
             intermediate,
             output,
+            layer_norm,
         }
     }
 
@@ -152,7 +161,12 @@ impl BertLayer {
                 (attention_output, attention_weights, None)
             };
 
-        let output = self.intermediate.forward(&attention_output);
+        let output = match &self.layer_norm {
+            Some(layer_norm) => self
+                .intermediate
+                .forward(&attention_output.apply(layer_norm)),
+            None => self.intermediate.forward(&attention_output),
+        };
         let output = self.output.forward_t(&output, &attention_output, train);
 
         BertLayerOutput {
@@ -161,16 +175,45 @@ impl BertLayer {
             cross_attention_weights: cross_attention_scores,
         }
     }
+
+    /// Keeps the given heads of the self-attention (the cross-attention, if any, is not pruned)
+    pub(crate) fn retain_attention_heads(&mut self, heads: &[i64]) -> Result<(), RustBertError> {
+        self.attention.retain_heads(heads)
+    }
+
+    pub(crate) fn num_attention_heads(&self) -> usize {
+        self.attention.num_attention_heads()
+    }
+
+    /// Sets the mask multiplying the attention weights of each self-attention head
+    pub(crate) fn set_head_mask(&mut self, head_mask: Option<Tensor>) -> Result<(), RustBertError> {
+        self.attention.set_head_mask(head_mask)
+    }
+}
+
+/// Layer normalization of the pre-norm layers and encoders (XLM-RoBERTa-XL)
+fn pre_layer_norm<'p, P>(p: P, config: &BertConfig) -> nn::LayerNorm
+where
+    P: Borrow<nn::Path<'p>>,
+{
+    let layer_norm_config = nn::LayerNormConfig {
+        eps: 1e-12,
+        ..Default::default()
+    };
+    nn::layer_norm(p, vec![config.hidden_size], layer_norm_config)
 }
 
 /// # BERT Encoder
 /// Encoder used in BERT models.
 /// It is made of a Vector of `BertLayer` through which hidden states will be passed. The encoder can also be
 /// used as a decoder (with cross-attention) if `encoder_hidden_states` are provided.
+/// With pre-norm layers, a final layer normalization is applied to the output of the last layer.
 pub struct BertEncoder {
     output_attentions: bool,
     output_hidden_states: bool,
     layers: Vec<BertLayer>,
+    layer_drops: Vec<DropPath>,
+    layer_norm: Option<nn::LayerNorm>,
 }
 
 impl BertEncoder {
@@ -199,19 +242,33 @@ impl BertEncoder {
     where
         P: Borrow<nn::Path<'p>>,
     {
+        let layer_norm = config
+            .is_pre_layer_norm()
+            .then(|| pre_layer_norm(p.borrow() / "LayerNorm", config));
         let p = p.borrow() / "layer";
         let output_attentions = config.output_attentions.unwrap_or(false);
         let output_hidden_states = config.output_hidden_states.unwrap_or(false);
 
         let mut layers: Vec<BertLayer> = vec![];
+        let mut layer_drops: Vec<DropPath> = vec![];
         for layer_index in 0..config.num_hidden_layers {
             layers.push(BertLayer::new(&p / layer_index, config));
+            let drop_prob = config
+                .stochastic_depth
+                .as_ref()
+                .map_or(0.0, |stochastic_depth| {
+                    stochastic_depth
+                        .drop_prob(layer_index as usize, config.num_hidden_layers as usize)
+                });
+            layer_drops.push(DropPath::new(drop_prob));
         }
 
         BertEncoder {
             output_attentions,
             output_hidden_states,
             layers,
+            layer_drops,
+            layer_norm,
         }
     }
 
@@ -276,20 +333,18 @@ impl BertEncoder {
         let mut hidden_state = None::<Tensor>;
         let mut attention_weights: Option<Tensor>;
 
-        for layer in &self.layers {
-            let layer_output = if let Some(hidden_state) = &hidden_state {
-                layer.forward_t(
-                    hidden_state,
-                    mask,
-                    encoder_hidden_states,
-                    encoder_mask,
-                    train,
-                )
-            } else {
-                layer.forward_t(input, mask, encoder_hidden_states, encoder_mask, train)
-            };
+        for (layer, layer_drop) in self.layers.iter().zip(self.layer_drops.iter()) {
+            let layer_input = hidden_state.as_ref().unwrap_or(input);
+            let layer_output = layer.forward_t(
+                layer_input,
+                mask,
+                encoder_hidden_states,
+                encoder_mask,
+                train,
+            );
 
-            hidden_state = Some(layer_output.hidden_state);
+            hidden_state =
+                Some(layer_drop.skip_layer(layer_input, &layer_output.hidden_state, train));
             attention_weights = layer_output.attention_weights;
             if let Some(attentions) = all_attentions.borrow_mut() {
                 attentions.push(attention_weights.as_ref().unwrap().copy());
@@ -299,12 +354,103 @@ impl BertEncoder {
             };
         }
 
+        let mut hidden_state = hidden_state.unwrap();
+        if let Some(layer_norm) = &self.layer_norm {
+            hidden_state = hidden_state.apply(layer_norm);
+            if let Some(hidden_states) = all_hidden_states.borrow_mut() {
+                if let Some(last_hidden_state) = hidden_states.last_mut() {
+                    *last_hidden_state = hidden_state.copy();
+                }
+            };
+        }
+
         BertEncoderOutput {
-            hidden_state: hidden_state.unwrap(),
+            hidden_state,
             all_hidden_states,
             all_attentions,
         }
     }
+
+    /// Returns the number of layers of the encoder
+    pub(crate) fn num_layers(&self) -> usize {
+        self.layers.len()
+    }
+
+    /// Forward pass through a single layer of the encoder (without cross-attention), used to process the inputs
+    /// layer by layer (e.g. for early exit)
+    pub(crate) fn forward_layer_t(
+        &self,
+        layer_index: usize,
+        hidden_states: &Tensor,
+        mask: Option<&Tensor>,
+        train: bool,
+    ) -> Tensor {
+        let layer_output =
+            self.layers[layer_index].forward_t(hidden_states, mask, None, None, train);
+        let hidden_state = self.layer_drops[layer_index].skip_layer(
+            hidden_states,
+            &layer_output.hidden_state,
+            train,
+        );
+        match &self.layer_norm {
+            Some(layer_norm) if layer_index + 1 == self.layers.len() => {
+                hidden_state.apply(layer_norm)
+            }
+            _ => hidden_state,
+        }
+    }
+
+    /// Removes the top `num_layers` layers of the encoder
+    pub(crate) fn drop_top_layers(&mut self, num_layers: usize) -> Result<(), RustBertError> {
+        let remaining_layers = get_remaining_layers(self.layers.len(), num_layers)?;
+        self.layers.truncate(remaining_layers);
+        self.layer_drops.truncate(remaining_layers);
+        Ok(())
+    }
+
+    fn get_layer_mut(&mut self, layer_index: usize) -> Result<&mut BertLayer, RustBertError> {
+        let num_layers = self.layers.len();
+        self.layers.get_mut(layer_index).ok_or_else(|| {
+            RustBertError::ValueError(format!(
+                "Layer index {} out of range for an encoder with {} layers",
+                layer_index, num_layers
+            ))
+        })
+    }
+
+    /// Keeps the given self-attention heads of a layer
+    pub(crate) fn retain_attention_heads(
+        &mut self,
+        layer_index: usize,
+        heads: &[i64],
+    ) -> Result<(), RustBertError> {
+        self.get_layer_mut(layer_index)?
+            .retain_attention_heads(heads)
+    }
+
+    /// Returns the number of self-attention heads of a layer
+    pub(crate) fn num_attention_heads(&self, layer_index: usize) -> Result<usize, RustBertError> {
+        Ok(self
+            .layers
+            .get(layer_index)
+            .ok_or_else(|| {
+                RustBertError::ValueError(format!(
+                    "Layer index {} out of range for an encoder with {} layers",
+                    layer_index,
+                    self.layers.len()
+                ))
+            })?
+            .num_attention_heads())
+    }
+
+    /// Sets the mask multiplying the attention weights of each self-attention head of a layer
+    pub(crate) fn set_head_mask(
+        &mut self,
+        layer_index: usize,
+        head_mask: Option<Tensor>,
+    ) -> Result<(), RustBertError> {
+        self.get_layer_mut(layer_index)?.set_head_mask(head_mask)
+    }
 }
 
 /// # BERT Pooler
@@ -52,7 +52,7 @@
 //! # }
 //! ```
 
-mod attention;
+pub(crate) mod attention;
 mod bert_model;
 mod embeddings;
 pub(crate) mod encoder;
@@ -0,0 +1,390 @@
+// Copyright 2021 Google Research and The HuggingFace Inc. team. All rights reserved.
+// Copyright 2022 Guillaume Becquin
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//     http://www.apache.org/licenses/LICENSE-2.0
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use crate::bert::attention::BertSelfOutput;
+use crate::bert::BertConfig;
+use crate::bigbird::BigBirdConfig;
+use crate::common::dropout::Dropout;
+use std::borrow::Borrow;
+use tch::{nn, Device, Kind, Tensor};
+
+/// Number of key blocks attended by every query block of the sparse band, besides the random blocks: the first
+/// and last (global) blocks and a sliding window of 3 blocks
+const NUM_BAND_KEY_BLOCKS: i64 = 5;
+
+/// Returns the indices of the random key blocks attended by the query blocks of the sparse band (all blocks but
+/// the first and last). During training, every query block attends to `num_random_blocks` blocks sampled outside
+/// of its window and of the global blocks. As in the reference implementation, random attention is disabled at
+/// inference and the random blocks are replaced by the first (global) block.
+fn get_random_blocks(num_blocks: i64, num_random_blocks: i64, train: bool) -> Vec<Vec<i64>> {
+    (1..num_blocks - 1)
+        .map(|query_block| {
+            if !train {
+                return vec![0; num_random_blocks as usize];
+            }
+            let candidates = (1..num_blocks - 1)
+                .filter(|key_block| (key_block - query_block).abs() > 1)
+                .collect::<Vec<i64>>();
+            let permutation = Vec::<i64>::from(&Tensor::randperm(
+                candidates.len() as i64,
+                (Kind::Int64, Device::Cpu),
+            ));
+            permutation
+                .iter()
+                .take(num_random_blocks as usize)
+                .map(|index| candidates[*index as usize])
+                .collect()
+        })
+        .collect()
+}
+
+/// Returns the key blocks attended by each query block of the sparse band (flattened), and a mask indicating
+/// the blocks that are already attended earlier in the list (window overlapping with a global block)
+fn get_band_key_blocks(random_blocks: &[Vec<i64>], num_blocks: i64) -> (Vec<i64>, Vec<bool>) {
+    let mut key_blocks = Vec::new();
+    let mut duplicates = Vec::new();
+    for (query_block, random_blocks) in (1..num_blocks - 1).zip(random_blocks.iter()) {
+        key_blocks.extend_from_slice(&[
+            0,
+            query_block - 1,
+            query_block,
+            query_block + 1,
+            num_blocks - 1,
+        ]);
+        duplicates.extend_from_slice(&[
+            false,
+            query_block == 1,
+            false,
+            query_block == num_blocks - 2,
+            false,
+        ]);
+        key_blocks.extend_from_slice(random_blocks);
+        duplicates.extend(random_blocks.iter().map(|_| false));
+    }
+    (key_blocks, duplicates)
+}
+
+/// # BigBird self-attention
+/// Multi-head self-attention computing either the full attention, or the block-sparse attention of BigBird:
+/// the sequence is split in blocks of `block_size` tokens, the first and last blocks attend to (and are attended
+/// by) all tokens, and every other block attends to a sliding window of 3 blocks and to `num_random_blocks`
+/// random blocks (during training).
+pub struct BigBirdSelfAttention {
+    num_attention_heads: i64,
+    attention_head_size: i64,
+    block_size: i64,
+    num_random_blocks: i64,
+    dropout: Dropout,
+    output_attentions: bool,
+    query: nn::Linear,
+    key: nn::Linear,
+    value: nn::Linear,
+}
+
+impl BigBirdSelfAttention {
+    pub fn new<'p, P>(p: P, config: &BigBirdConfig) -> BigBirdSelfAttention
+    where
+        P: Borrow<nn::Path<'p>>,
+    {
+        assert_eq!(
+            config.hidden_size % config.num_attention_heads,
+            0,
+            "Hidden size not a multiple of the number of attention heads"
+        );
+        let p = p.borrow();
+
+        let linear_config = nn::LinearConfig {
+            bias: config.use_bias.unwrap_or(true),
+            ..Default::default()
+        };
+        let query = nn::linear(
+            p / "query",
+            config.hidden_size,
+            config.hidden_size,
+            linear_config,
+        );
+        let key = nn::linear(
+            p / "key",
+            config.hidden_size,
+            config.hidden_size,
+            linear_config,
+        );
+        let value = nn::linear(
+            p / "value",
+            config.hidden_size,
+            config.hidden_size,
+            linear_config,
+        );
+
+        BigBirdSelfAttention {
+            num_attention_heads: config.num_attention_heads,
+            attention_head_size: config.hidden_size / config.num_attention_heads,
+            block_size: config.block_size,
+            num_random_blocks: config.num_random_blocks,
+            dropout: Dropout::new(config.attention_probs_dropout_prob),
+            output_attentions: config.output_attentions.unwrap_or(false),
+            query,
+            key,
+            value,
+        }
+    }
+
+    fn split_heads(&self, x: Tensor, bs: i64) -> Tensor {
+        x.view((bs, -1, self.num_attention_heads, self.attention_head_size))
+            .transpose(1, 2)
+    }
+
+    fn full_attention(
+        &self,
+        query_layer: &Tensor,
+        key_layer: &Tensor,
+        value_layer: &Tensor,
+        additive_mask: &Tensor,
+        train: bool,
+    ) -> (Tensor, Tensor) {
+        let (bs, sequence_length) = additive_mask.size2().unwrap();
+        let scores = query_layer.matmul(&key_layer.transpose(-1, -2))
+            + additive_mask.view([bs, 1, 1, sequence_length]);
+        let weights = scores
+            .softmax(-1, scores.kind())
+            .apply_t(&self.dropout, train);
+        let context = weights.matmul(value_layer);
+        (context, weights)
+    }
+
+    fn block_sparse_attention(
+        &self,
+        query_layer: &Tensor,
+        key_layer: &Tensor,
+        value_layer: &Tensor,
+        additive_mask: &Tensor,
+        train: bool,
+    ) -> (Tensor, Option<Tensor>) {
+        let (bs, num_heads, sequence_length, head_size) = query_layer.size4().unwrap();
+        let block_size = self.block_size;
+        let num_blocks = sequence_length / block_size;
+        let num_key_blocks = NUM_BAND_KEY_BLOCKS + self.num_random_blocks;
+        let device = query_layer.device();
+
+        //    The first and last query blocks attend to all tokens
+        let global_query = Tensor::cat(
+            &[
+                query_layer.narrow(2, 0, block_size),
+                query_layer.narrow(2, sequence_length - block_size, block_size),
+            ],
+            2,
+        );
+        let (global_context, global_weights) =
+            self.full_attention(&global_query, key_layer, value_layer, additive_mask, train);
+
+        //    The other query blocks attend to the global blocks, their window and the random blocks
+        let random_blocks = get_random_blocks(num_blocks, self.num_random_blocks, train);
+        let (key_blocks, duplicates) = get_band_key_blocks(&random_blocks, num_blocks);
+        let key_blocks = Tensor::of_slice(&key_blocks).to(device);
+        let band_size = num_key_blocks * block_size;
+
+        let gather_blocks = |layer: &Tensor| {
+            layer
+                .view([bs, num_heads, num_blocks, block_size, head_size])
+                .index_select(2, &key_blocks)
+                .view([bs, num_heads, num_blocks - 2, band_size, head_size])
+        };
+        let band_keys = gather_blocks(key_layer);
+        let band_values = gather_blocks(value_layer);
+        let band_query = query_layer
+            .view([bs, num_heads, num_blocks, block_size, head_size])
+            .narrow(2, 1, num_blocks - 2);
+
+        let band_mask = additive_mask
+            .view([bs, num_blocks, block_size])
+            .index_select(1, &key_blocks)
+            .view([bs, 1, num_blocks - 2, 1, band_size]);
+        let duplicate_mask = (Tensor::of_slice(&duplicates)
+            .to_kind(additive_mask.kind())
+            .to(device)
+            * -10000.0)
+            .view([num_blocks - 2, num_key_blocks, 1])
+            .expand(&[num_blocks - 2, num_key_blocks, block_size], true)
+            .reshape(&[1, 1, num_blocks - 2, 1, band_size]);
+
+        let band_scores =
+            band_query.matmul(&band_keys.transpose(-1, -2)) + band_mask + duplicate_mask;
+        let band_weights = band_scores
+            .softmax(-1, band_scores.kind())
+            .apply_t(&self.dropout, train);
+        let band_context = band_weights.matmul(&band_values).view([
+            bs,
+            num_heads,
+            (num_blocks - 2) * block_size,
+            head_size,
+        ]);
+
+        let context = Tensor::cat(
+            &[
+                global_context.narrow(2, 0, block_size),
+                band_context,
+                global_context.narrow(2, block_size, block_size),
+            ],
+            2,
+        );
+
+        let weights = if self.output_attentions {
+            //    Scatter the attention weights of the sparse band to the attended token positions
+            let token_positions = (key_blocks.view([num_blocks - 2, num_key_blocks, 1])
+                * block_size
+                + Tensor::arange(block_size, (Kind::Int64, device)).view([1, 1, block_size]))
+            .view([1, 1, num_blocks - 2, 1, band_size])
+            .expand(band_weights.size().as_slice(), true);
+            let band_weights = Tensor::zeros(
+                &[bs, num_heads, num_blocks - 2, block_size, sequence_length],
+                (band_weights.kind(), device),
+            )
+            .scatter_add(-1, &token_positions, &band_weights)
+            .view([
+                bs,
+                num_heads,
+                (num_blocks - 2) * block_size,
+                sequence_length,
+            ]);
+            Some(Tensor::cat(
+                &[
+                    global_weights.narrow(2, 0, block_size),
+                    band_weights,
+                    global_weights.narrow(2, block_size, block_size),
+                ],
+                2,
+            ))
+        } else {
+            None
+        };
+
+        (context, weights)
+    }
+
+    /// Forward pass through the self-attention layer
+    ///
+    /// # Arguments
+    ///
+    /// * `hidden_states` - input tensor of shape (*batch size*, *sequence_length*, *hidden_size*).
+    /// * `mask` - Mask of shape (*batch size*, *sequence_length*). Masked position have value 0, non-masked value 1.
+    /// * `block_sparse` - Use the block-sparse attention. The sequence length must then be a multiple of the block size.
+    /// * `train` - boolean flag to turn on/off the dropout layers in the model. Should be set to false for inference.
+    pub fn forward_t(
+        &self,
+        hidden_states: &Tensor,
+        mask: &Tensor,
+        block_sparse: bool,
+        train: bool,
+    ) -> (Tensor, Option<Tensor>) {
+        let bs = hidden_states.size()[0];
+        let query_layer = self.split_heads(hidden_states.apply(&self.query), bs)
+            / (self.attention_head_size as f64).sqrt();
+        let key_layer = self.split_heads(hidden_states.apply(&self.key), bs);
+        let value_layer = self.split_heads(hidden_states.apply(&self.value), bs);
+        let mask = mask.to_kind(query_layer.kind());
+        let additive_mask = (mask.ones_like() - &mask) * -10000.0;
+
+        let (context, weights) = if block_sparse {
+            let (context, weights) = self.block_sparse_attention(
+                &query_layer,
+                &key_layer,
+                &value_layer,
+                &additive_mask,
+                train,
+            );
+            //    Masked query positions do not attend to all tokens as in the full attention, they are zeroed
+            (context * mask.view([bs, 1, -1, 1]), weights)
+        } else {
+            let (context, weights) = self.full_attention(
+                &query_layer,
+                &key_layer,
+                &value_layer,
+                &additive_mask,
+                train,
+            );
+            (context, Some(weights).filter(|_| self.output_attentions))
+        };
+
+        let context = context.transpose(1, 2).contiguous().view((
+            bs,
+            -1,
+            self.num_attention_heads * self.attention_head_size,
+        ));
+        (context, weights)
+    }
+}
+
+/// # BigBird attention layer
+/// Self-attention followed by the output projection, residual connection and layer normalization.
+pub struct BigBirdAttention {
+    _self: BigBirdSelfAttention,
+    output: BertSelfOutput,
+}
+
+impl BigBirdAttention {
+    pub fn new<'p, P>(p: P, config: &BigBirdConfig, bert_config: &BertConfig) -> BigBirdAttention
+    where
+        P: Borrow<nn::Path<'p>>,
+    {
+        let p = p.borrow();
+
+        let _self = BigBirdSelfAttention::new(p / "self", config);
+        let output = BertSelfOutput::new(p / "output", bert_config);
+        BigBirdAttention { _self, output }
+    }
+
+    pub fn forward_t(
+        &self,
+        hidden_states: &Tensor,
+        mask: &Tensor,
+        block_sparse: bool,
+        train: bool,
+    ) -> (Tensor, Option<Tensor>) {
+        let (self_output, attention_weights) =
+            self._self
+                .forward_t(hidden_states, mask, block_sparse, train);
+        let self_output = self.output.forward_t(&self_output, hidden_states, train);
+        (self_output, attention_weights)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn band_key_blocks() {
+        let random_blocks = get_random_blocks(8, 2, true);
+        assert_eq!(random_blocks.len(), 6);
+        for (query_block, blocks) in (1..7).zip(random_blocks.iter()) {
+            assert_eq!(blocks.len(), 2);
+            assert_ne!(blocks[0], blocks[1]);
+            assert!(blocks
+                .iter()
+                .all(|block| (*block > 0) & (*block < 7) & ((block - query_block).abs() > 1)));
+        }
+
+        let random_blocks = get_random_blocks(8, 2, false);
+        let (key_blocks, duplicates) = get_band_key_blocks(&random_blocks, 8);
+        assert_eq!(key_blocks.len(), 6 * 7);
+        assert_eq!(&key_blocks[..7], &[0, 0, 1, 2, 7, 0, 0]);
+        assert_eq!(
+            &duplicates[..7],
+            &[false, true, false, false, false, false, false]
+        );
+        assert_eq!(&key_blocks[35..], &[0, 5, 6, 7, 7, 0, 0]);
+        assert_eq!(
+            &duplicates[35..],
+            &[false, false, false, true, false, false, false]
+        );
+    }
+}
@@ -0,0 +1,754 @@
+// Copyright 2021 Google Research and The HuggingFace Inc. team. All rights reserved.
+// Copyright 2022 Guillaume Becquin
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//     http://www.apache.org/licenses/LICENSE-2.0
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use crate::bert::attention::{BertIntermediate, BertOutput};
+use crate::bert::{BertConfig, BertEmbedding, BertEmbeddings, BertPooler};
+use crate::bigbird::encoder::BigBirdEncoder;
+use crate::common::activations::{Activation, TensorFunction};
+use crate::common::dropout::Dropout;
+use crate::common::embeddings::get_shape_and_device_from_ids_embeddings_pair;
+use crate::{Config, RustBertError};
+use serde::{Deserialize, Serialize};
+use std::borrow::Borrow;
+use std::collections::HashMap;
+use tch::{nn, Kind, Tensor};
+
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+/// # BigBird attention type
+pub enum AttentionType {
+    /// Full (quadratic) self-attention, as in BERT
+    OriginalFull,
+    /// Block-sparse self-attention combining global, sliding window and random attention. Sequences too short to
+    /// benefit from the sparse attention are processed with the full attention.
+    BlockSparse,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+/// # BigBird model configuration
+/// Defines the BigBird model architecture (e.g. number of layers, hidden layer size, sparse attention block size, label mapping...)
+pub struct BigBirdConfig {
+    pub hidden_act: Activation,
+    pub attention_probs_dropout_prob: f64,
+    pub hidden_dropout_prob: f64,
+    pub hidden_size: i64,
+    pub initializer_range: f32,
+    pub intermediate_size: i64,
+    pub max_position_embeddings: i64,
+    pub num_attention_heads: i64,
+    pub num_hidden_layers: i64,
+    pub type_vocab_size: i64,
+    pub vocab_size: i64,
+    pub attention_type: AttentionType,
+    /// Number of tokens in a block of the block-sparse attention
+    pub block_size: i64,
+    /// Number of random blocks attended by each query block of the block-sparse attention during training
+    pub num_random_blocks: i64,
+    pub use_bias: Option<bool>,
+    pub pad_token_id: Option<i64>,
+    pub sep_token_id: Option<i64>,
+    pub classifier_dropout: Option<f64>,
+    pub output_attentions: Option<bool>,
+    pub output_hidden_states: Option<bool>,
+    pub id2label: Option<HashMap<i64, String>>,
+    pub label2id: Option<HashMap<String, i64>>,
+}
+
+impl Config for BigBirdConfig {}
+
+impl Default for BigBirdConfig {
+    fn default() -> Self {
+        BigBirdConfig {
+            hidden_act: Activation::gelu_new,
+            attention_probs_dropout_prob: 0.1,
+            hidden_dropout_prob: 0.1,
+            hidden_size: 768,
+            initializer_range: 0.02,
+            intermediate_size: 3072,
+            max_position_embeddings: 4096,
+            num_attention_heads: 12,
+            num_hidden_layers: 12,
+            type_vocab_size: 2,
+            vocab_size: 50358,
+            attention_type: AttentionType::BlockSparse,
+            block_size: 64,
+            num_random_blocks: 3,
+            use_bias: Some(true),
+            pad_token_id: Some(0),
+            sep_token_id: Some(66),
+            classifier_dropout: None,
+            output_attentions: None,
+            output_hidden_states: None,
+            id2label: None,
+            label2id: None,
+        }
+    }
+}
+
+impl From<&BigBirdConfig> for BertConfig {
+    fn from(config: &BigBirdConfig) -> Self {
+        BertConfig {
+            hidden_act: config.hidden_act,
+            attention_probs_dropout_prob: config.attention_probs_dropout_prob,
+            hidden_dropout_prob: config.hidden_dropout_prob,
+            hidden_size: config.hidden_size,
+            initializer_range: config.initializer_range,
+            intermediate_size: config.intermediate_size,
+            max_position_embeddings: config.max_position_embeddings,
+            num_attention_heads: config.num_attention_heads,
+            num_hidden_layers: config.num_hidden_layers,
+            type_vocab_size: config.type_vocab_size,
+            vocab_size: config.vocab_size,
+            output_attentions: config.output_attentions,
+            output_hidden_states: config.output_hidden_states,
+            is_decoder: None,
+            id2label: config.id2label.clone(),
+            label2id: config.label2id.clone(),
+            stochastic_depth: None,
+            fused_feed_forward: None,
+            early_exit: None,
+            model_type: None,
+            pre_layer_norm: None,
+        }
+    }
+}
+
+fn pad_with_value(tensor: &Tensor, padding_length: i64, padding_value: i64) -> Tensor {
+    (tensor - padding_value).constant_pad_nd(&[0, padding_length]) + padding_value
+}
+
+/// # BigBird Base model
+/// Base architecture for BigBird models. Task-specific models will be built from this common base model.
+/// The block-sparse attention scales linearly with the sequence length, allowing to process documents of several
+/// thousand tokens. Inputs are padded to a multiple of the block size when the block-sparse attention is used.
+/// It is made of the following blocks:
+/// - `embeddings`: `token`, `position` and `segment_id` embeddings
+/// - `encoder`: Encoder (transformer) made of a vector of layers. Each layer is made of a block-sparse self-attention layer, an intermediate (linear) and output (linear + layer norm) layers
+/// - `pooler`: Optional linear layer applied to the first element of the sequence (*CLS* token)
+pub struct BigBirdModel {
+    embeddings: BertEmbeddings,
+    encoder: BigBirdEncoder,
+    pooler: Option<BertPooler>,
+    attention_type: AttentionType,
+    block_size: i64,
+    num_random_blocks: i64,
+    max_position_embeddings: i64,
+    pad_token_id: i64,
+}
+
+impl BigBirdModel {
+    /// Build a new `BigBirdModel`
+    ///
+    /// # Arguments
+    ///
+    /// * `p` - Variable store path for the root of the BigBird model
+    /// * `config` - `BigBirdConfig` object defining the model architecture
+    /// * `add_pooling_layer` - Enable/Disable an optional pooling layer at the end of the model
+    ///
+    /// # Example
+    ///
+    /// ```no_run
+    /// use rust_bert::bigbird::{BigBirdConfig, BigBirdModel};
+    /// use rust_bert::Config;
+    /// use std::path::Path;
+    /// use tch::{nn, Device};
+    ///
+    /// let config_path = Path::new("path/to/config.json");
+    /// let device = Device::Cpu;
+    /// let p = nn::VarStore::new(device);
+    /// let config = BigBirdConfig::from_file(config_path);
+    /// let bigbird_model: BigBirdModel = BigBirdModel::new(&p.root() / "bert", &config, true);
+    /// ```
+    pub fn new<'p, P>(p: P, config: &BigBirdConfig, add_pooling_layer: bool) -> BigBirdModel
+    where
+        P: Borrow<nn::Path<'p>>,
+    {
+        let p = p.borrow();
+        let bert_config = BertConfig::from(config);
+
+        let embeddings = BertEmbeddings::new(p / "embeddings", &bert_config);
+        let encoder = BigBirdEncoder::new(p / "encoder", config, &bert_config);
+        let pooler = if add_pooling_layer {
+            Some(BertPooler::new(p / "pooler", &bert_config))
+        } else {
+            None
+        };
+
+        BigBirdModel {
+            embeddings,
+            encoder,
+            pooler,
+            attention_type: config.attention_type,
+            block_size: config.block_size,
+            num_random_blocks: config.num_random_blocks,
+            max_position_embeddings: config.max_position_embeddings,
+            pad_token_id: config.pad_token_id.unwrap_or(0),
+        }
+    }
+
+    /// Returns true if sequences of `sequence_length` tokens are processed with the block-sparse attention. Shorter
+    /// sequences, for which every query block would attend to all key blocks, use the full attention.
+    pub fn uses_block_sparse_attention(&self, sequence_length: i64) -> bool {
+        let max_tokens_to_attend = (5 + 2 * self.num_random_blocks) * self.block_size;
+        (self.attention_type == AttentionType::BlockSparse)
+            & (sequence_length > max_tokens_to_attend)
+    }
+
+    /// Forward pass through the model
+    ///
+    /// # Arguments
+    ///
+    /// * `input_ids` - Optional input tensor of shape (*batch size*, *sequence_length*). If None, pre-computed embeddings must be provided (see `input_embeds`)
+    /// * `mask` - Optional mask of shape (*batch size*, *sequence_length*). Masked position have value 0, non-masked value 1. If None set to 1
+    /// * `token_type_ids` - Optional segment id of shape (*batch size*, *sequence_length*). Convention is value of 0 for the first sentence (incl. *SEP*) and 1 for the second sentence. If None set to 0.
+    /// * `position_ids` - Optional position ids of shape (*batch size*, *sequence_length*). If None, will be incremented from 0.
+    /// * `input_embeds` - Optional pre-computed input embeddings of shape (*batch size*, *sequence_length*, *hidden_size*). If None, input ids must be provided (see `input_ids`)
+    /// * `train` - boolean flag to turn on/off the dropout layers in the model. Should be set to false for inference.
+    ///
+    /// # Returns
+    ///
+    /// * `BigBirdModelOutput` containing:
+    ///   - `hidden_state` - `Tensor` of shape (*batch size*, *sequence_length*, *hidden_size*)
+    ///   - `pooled_output` - `Option<Tensor>` of shape (*batch size*, *hidden_size*)
+    ///   - `all_hidden_states` - `Option<Vec<Tensor>>` of length *num_hidden_layers* with shape (*batch size*, *padded_sequence_length*, *hidden_size*)
+    ///   - `all_attentions` - `Option<Vec<Tensor>>` of length *num_hidden_layers* with shape (*batch size*, *num_heads*, *padded_sequence_length*, *padded_sequence_length*)
+    ///
+    /// # Example
+    ///
+    /// ```no_run
+    /// # use rust_bert::bigbird::{BigBirdConfig, BigBirdModel};
+    /// # use tch::{nn, Device, Tensor, no_grad};
+    /// # use rust_bert::Config;
+    /// # use std::path::Path;
+    /// # use tch::kind::Kind::Int64;
+    /// # let config_path = Path::new("path/to/config.json");
+    /// # let device = Device::Cpu;
+    /// # let vs = nn::VarStore::new(device);
+    /// # let config = BigBirdConfig::from_file(config_path);
+    /// # let bigbird_model = BigBirdModel::new(&vs.root(), &config, true);
+    /// let (batch_size, sequence_length) = (2, 4096);
+    /// let input_tensor = Tensor::rand(&[batch_size, sequence_length], (Int64, device));
+    /// let mask = Tensor::ones(&[batch_size, sequence_length], (Int64, device));
+    ///
+    /// let model_output = no_grad(|| {
+    ///     bigbird_model
+    ///         .forward_t(Some(&input_tensor), Some(&mask), None, None, None, false)
+    ///         .unwrap()
+    /// });
+    /// ```
+    pub fn forward_t(
+        &self,
+        input_ids: Option<&Tensor>,
+        mask: Option<&Tensor>,
+        token_type_ids: Option<&Tensor>,
+        position_ids: Option<&Tensor>,
+        input_embeds: Option<&Tensor>,
+        train: bool,
+    ) -> Result<BigBirdModelOutput, RustBertError> {
+        let (input_shape, device) =
+            get_shape_and_device_from_ids_embeddings_pair(input_ids, input_embeds)?;
+        let (batch_size, sequence_length) = (input_shape[0], input_shape[1]);
+
+        let mask = match mask {
+            Some(mask) if mask.dim() != 2 => {
+                return Err(RustBertError::ValueError(
+                    "Invalid attention mask dimension, must be 2".into(),
+                ));
+            }
+            Some(mask) => mask.shallow_clone(),
+            None => Tensor::ones(&input_shape, (Kind::Int64, device)),
+        };
+
+        let block_sparse = self.uses_block_sparse_attention(sequence_length);
+        let padding_length = if block_sparse {
+            (self.block_size - sequence_length % self.block_size) % self.block_size
+        } else {
+            0
+        };
+        if position_ids.is_none()
+            & (sequence_length + padding_length > self.max_position_embeddings)
+        {
+            return Err(RustBertError::InputTooLongError(format!(
+                "The input of {} tokens (padded to {} tokens) exceeds the maximum number of positions ({})",
+                sequence_length,
+                sequence_length + padding_length,
+                self.max_position_embeddings
+            )));
+        }
+
+        let (input_ids, mask, token_type_ids, position_ids, input_embeds) = if padding_length > 0 {
+            let input_embeds = match input_embeds {
+                Some(input_embeds) => {
+                    let input_ids_padding = Tensor::full(
+                        &[batch_size, padding_length],
+                        self.pad_token_id,
+                        (Kind::Int64, device),
+                    );
+                    let input_embeds_padding = self.embeddings.forward_t(
+                        Some(&input_ids_padding),
+                        None,
+                        None,
+                        None,
+                        train,
+                    )?;
+                    Some(Tensor::cat(&[input_embeds, &input_embeds_padding], 1))
+                }
+                None => None,
+            };
+            (
+                input_ids.map(|value| pad_with_value(value, padding_length, self.pad_token_id)),
+                mask.constant_pad_nd(&[0, padding_length]),
+                token_type_ids.map(|value| value.constant_pad_nd(&[0, padding_length])),
+                position_ids.map(|value| pad_with_value(value, padding_length, self.pad_token_id)),
+                input_embeds,
+            )
+        } else {
+            (
+                input_ids.map(|value| value.shallow_clone()),
+                mask,
+                token_type_ids.map(|value| value.shallow_clone()),
+                position_ids.map(|value| value.shallow_clone()),
+                input_embeds.map(|value| value.shallow_clone()),
+            )
+        };
+
+        let embedding_output = self.embeddings.forward_t(
+            input_ids.as_ref(),
+            token_type_ids.as_ref(),
+            position_ids.as_ref(),
+            input_embeds.as_ref(),
+            train,
+        )?;
+
+        let encoder_output = self
+            .encoder
+            .forward_t(&embedding_output, &mask, block_sparse, train);
+
+        let pooled_output = self
+            .pooler
+            .as_ref()
+            .map(|pooler| pooler.forward(&encoder_output.hidden_state));
+
+        let hidden_state = if padding_length > 0 {
+            encoder_output.hidden_state.slice(1, 0, sequence_length, 1)
+        } else {
+            encoder_output.hidden_state
+        };
+
+        Ok(BigBirdModelOutput {
+            hidden_state,
+            pooled_output,
+            all_hidden_states: encoder_output.all_hidden_states,
+            all_attentions: encoder_output.all_attentions,
+        })
+    }
+}
+
+/// # BigBird classification head
+/// Classification head applied to the hidden state of the first token (*CLS*)
+pub struct BigBirdClassificationHead {
+    dense: nn::Linear,
+    dropout: Dropout,
+    out_proj: nn::Linear,
+    activation: TensorFunction,
+}
+
+impl BigBirdClassificationHead {
+    pub fn new<'p, P>(p: P, config: &BigBirdConfig) -> BigBirdClassificationHead
+    where
+        P: Borrow<nn::Path<'p>>,
+    {
+        let p = p.borrow();
+
+        let dense = nn::linear(
+            p / "dense",
+            config.hidden_size,
+            config.hidden_size,
+            Default::default(),
+        );
+        let dropout = Dropout::new(
+            config
+                .classifier_dropout
+                .unwrap_or(config.hidden_dropout_prob),
+        );
+        let num_labels = config
+            .id2label
+            .as_ref()
+            .expect("num_labels not provided in configuration")
+            .len() as i64;
+        let out_proj = nn::linear(
+            p / "out_proj",
+            config.hidden_size,
+            num_labels,
+            Default::default(),
+        );
+        let activation = config.hidden_act.get_function();
+
+        BigBirdClassificationHead {
+            dense,
+            dropout,
+            out_proj,
+            activation,
+        }
+    }
+
+    pub fn forward_t(&self, hidden_states: &Tensor, train: bool) -> Tensor {
+        let hidden_states = hidden_states
+            .select(1, 0)
+            .apply_t(&self.dropout, train)
+            .apply(&self.dense);
+        self.activation.get_fn()(&hidden_states)
+            .apply_t(&self.dropout, train)
+            .apply(&self.out_proj)
+    }
+}
+
+/// # BigBird for sequence classification
+/// Base BigBird model with a classifier head to perform sentence or document-level classification
+/// It is made of the following blocks:
+/// - `bert`: Base BigBirdModel
+/// - `classifier`: BigBird classification head
+pub struct BigBirdForSequenceClassification {
+    bert: BigBirdModel,
+    classifier: BigBirdClassificationHead,
+}
+
+impl BigBirdForSequenceClassification {
+    /// Build a new `BigBirdForSequenceClassification`
+    ///
+    /// # Arguments
+    ///
+    /// * `p` - Variable store path for the root of the BigBird model
+    /// * `config` - `BigBirdConfig` object defining the model architecture and number of classes
+    ///
+    /// # Example
+    ///
+    /// ```no_run
+    /// use rust_bert::bigbird::{BigBirdConfig, BigBirdForSequenceClassification};
+    /// use rust_bert::Config;
+    /// use std::path::Path;
+    /// use tch::{nn, Device};
+    ///
+    /// let config_path = Path::new("path/to/config.json");
+    /// let device = Device::Cpu;
+    /// let p = nn::VarStore::new(device);
+    /// let config = BigBirdConfig::from_file(config_path);
+    /// let bigbird_model = BigBirdForSequenceClassification::new(&p.root(), &config);
+    /// ```
+    pub fn new<'p, P>(p: P, config: &BigBirdConfig) -> BigBirdForSequenceClassification
+    where
+        P: Borrow<nn::Path<'p>>,
+    {
+        let p = p.borrow();
+
+        let bert = BigBirdModel::new(p / "bert", config, true);
+        let classifier = BigBirdClassificationHead::new(p / "classifier", config);
+
+        BigBirdForSequenceClassification { bert, classifier }
+    }
+
+    /// Forward pass through the model
+    ///
+    /// # Arguments
+    ///
+    /// * `input_ids` - Optional input tensor of shape (*batch size*, *sequence_length*). If None, pre-computed embeddings must be provided (see `input_embeds`)
+    /// * `mask` - Optional mask of shape (*batch size*, *sequence_length*). Masked position have value 0, non-masked value 1. If None set to 1
+    /// * `token_type_ids` - Optional segment id of shape (*batch size*, *sequence_length*). Convention is value of 0 for the first sentence (incl. *SEP*) and 1 for the second sentence. If None set to 0.
+    /// * `position_ids` - Optional position ids of shape (*batch size*, *sequence_length*). If None, will be incremented from 0.
+    /// * `input_embeds` - Optional pre-computed input embeddings of shape (*batch size*, *sequence_length*, *hidden_size*). If None, input ids must be provided (see `input_ids`)
+    /// * `train` - boolean flag to turn on/off the dropout layers in the model. Should be set to false for inference.
+    ///
+    /// # Returns
+    ///
+    /// * `BigBirdSequenceClassificationOutput` containing:
+    ///   - `logits` - `Tensor` of shape (*batch size*, *num_labels*)
+    ///   - `all_hidden_states` - `Option<Vec<Tensor>>` of length *num_hidden_layers* with shape (*batch size*, *padded_sequence_length*, *hidden_size*)
+    ///   - `all_attentions` - `Option<Vec<Tensor>>` of length *num_hidden_layers* with shape (*batch size*, *num_heads*, *padded_sequence_length*, *padded_sequence_length*)
+    ///
+    /// # Example
+    ///
+    /// ```no_run
+    /// # use rust_bert::bigbird::{BigBirdConfig, BigBirdForSequenceClassification};
+    /// # use tch::{nn, Device, Tensor, no_grad};
+    /// # use rust_bert::Config;
+    /// # use std::path::Path;
+    /// # use tch::kind::Kind::Int64;
+    /// # let config_path = Path::new("path/to/config.json");
+    /// # let device = Device::Cpu;
+    /// # let vs = nn::VarStore::new(device);
+    /// # let config = BigBirdConfig::from_file(config_path);
+    /// # let bigbird_model = BigBirdForSequenceClassification::new(&vs.root(), &config);
+    /// let (batch_size, sequence_length) = (2, 4096);
+    /// let input_tensor = Tensor::rand(&[batch_size, sequence_length], (Int64, device));
+    /// let mask = Tensor::ones(&[batch_size, sequence_length], (Int64, device));
+    ///
+    /// let model_output = no_grad(|| {
+    ///     bigbird_model
+    ///         .forward_t(Some(&input_tensor), Some(&mask), None, None, None, false)
+    ///         .unwrap()
+    /// });
+    /// ```
+    pub fn forward_t(
+        &self,
+        input_ids: Option<&Tensor>,
+        mask: Option<&Tensor>,
+        token_type_ids: Option<&Tensor>,
+        position_ids: Option<&Tensor>,
+        input_embeds: Option<&Tensor>,
+        train: bool,
+    ) -> Result<BigBirdSequenceClassificationOutput, RustBertError> {
+        let base_model_output = self.bert.forward_t(
+            input_ids,
+            mask,
+            token_type_ids,
+            position_ids,
+            input_embeds,
+            train,
+        )?;
+
+        let logits = self
+            .classifier
+            .forward_t(&base_model_output.hidden_state, train);
+
+        Ok(BigBirdSequenceClassificationOutput {
+            logits,
+            all_hidden_states: base_model_output.all_hidden_states,
+            all_attentions: base_model_output.all_attentions,
+        })
+    }
+}
+
+/// # BigBird question answering head
+/// Feed-forward block (with residual connection) followed by a linear layer predicting the start and end logits
+pub struct BigBirdQuestionAnsweringHead {
+    dropout: Dropout,
+    intermediate: BertIntermediate,
+    output: BertOutput,
+    qa_outputs: nn::Linear,
+}
+
+impl BigBirdQuestionAnsweringHead {
+    pub fn new<'p, P>(p: P, config: &BigBirdConfig) -> BigBirdQuestionAnsweringHead
+    where
+        P: Borrow<nn::Path<'p>>,
+    {
+        let p = p.borrow();
+        let bert_config = BertConfig::from(config);
+
+        let dropout = Dropout::new(config.hidden_dropout_prob);
+        let intermediate = BertIntermediate::new(p / "intermediate", &bert_config);
+        let output = BertOutput::new(p / "output", &bert_config);
+        let qa_outputs = nn::linear(p / "qa_outputs", config.hidden_size, 2, Default::default());
+
+        BigBirdQuestionAnsweringHead {
+            dropout,
+            intermediate,
+            output,
+            qa_outputs,
+        }
+    }
+
+    pub fn forward_t(&self, hidden_states: &Tensor, train: bool) -> Tensor {
+        let intermediate_output = self
+            .intermediate
+            .forward(&hidden_states.apply_t(&self.dropout, train));
+        self.output
+            .forward_t(&intermediate_output, hidden_states, train)
+            .apply(&self.qa_outputs)
+    }
+}
+
+/// # BigBird for question answering
+/// Extractive question-answering model based on a BigBird language model. Identifies the segment of a context that answers a provided question.
+/// The inputs are expected in the format `[CLS] question [SEP] context [SEP]`: when the input ids are provided, the tokens of
+/// the question (up to the first *SEP* token) are excluded from the predicted answer span.
+/// It is made of the following blocks:
+/// - `bert`: Base BigBirdModel
+/// - `qa_classifier`: BigBird question answering head
+pub struct BigBirdForQuestionAnswering {
+    bert: BigBirdModel,
+    qa_classifier: BigBirdQuestionAnsweringHead,
+    sep_token_id: Option<i64>,
+}
+
+impl BigBirdForQuestionAnswering {
+    /// Build a new `BigBirdForQuestionAnswering`
+    ///
+    /// # Arguments
+    ///
+    /// * `p` - Variable store path for the root of the BigBird model
+    /// * `config` - `BigBirdConfig` object defining the model architecture
+    ///
+    /// # Example
+    ///
+    /// ```no_run
+    /// use rust_bert::bigbird::{BigBirdConfig, BigBirdForQuestionAnswering};
+    /// use rust_bert::Config;
+    /// use std::path::Path;
+    /// use tch::{nn, Device};
+    ///
+    /// let config_path = Path::new("path/to/config.json");
+    /// let device = Device::Cpu;
+    /// let p = nn::VarStore::new(device);
+    /// let config = BigBirdConfig::from_file(config_path);
+    /// let bigbird_model = BigBirdForQuestionAnswering::new(&p.root(), &config);
+    /// ```
+    pub fn new<'p, P>(p: P, config: &BigBirdConfig) -> BigBirdForQuestionAnswering
+    where
+        P: Borrow<nn::Path<'p>>,
+    {
+        let p = p.borrow();
+
+        let bert = BigBirdModel::new(p / "bert", config, false);
+        let qa_classifier = BigBirdQuestionAnsweringHead::new(p / "qa_classifier", config);
+
+        BigBirdForQuestionAnswering {
+            bert,
+            qa_classifier,
+            sep_token_id: config.sep_token_id,
+        }
+    }
+
+    /// Forward pass through the model
+    ///
+    /// # Arguments
+    ///
+    /// * `input_ids` - Optional input tensor of shape (*batch size*, *sequence_length*). If None, pre-computed embeddings must be provided (see `input_embeds`)
+    /// * `mask` - Optional mask of shape (*batch size*, *sequence_length*). Masked position have value 0, non-masked value 1. If None set to 1
+    /// * `token_type_ids` - Optional segment id of shape (*batch size*, *sequence_length*). If None, set to 0 for the question and 1 for the context when the input ids are provided.
+    /// * `position_ids` - Optional position ids of shape (*batch size*, *sequence_length*). If None, will be incremented from 0.
+    /// * `input_embeds` - Optional pre-computed input embeddings of shape (*batch size*, *sequence_length*, *hidden_size*). If None, input ids must be provided (see `input_ids`)
+    /// * `train` - boolean flag to turn on/off the dropout layers in the model. Should be set to false for inference.
+    ///
+    /// # Returns
+    ///
+    /// * `BigBirdQuestionAnsweringOutput` containing:
+    ///   - `start_logits` - `Tensor` of shape (*batch size*, *sequence_length*) containing the logits for start of the answer
+    ///   - `end_logits` - `Tensor` of shape (*batch size*, *sequence_length*) containing the logits for end of the answer
+    ///   - `all_hidden_states` - `Option<Vec<Tensor>>` of length *num_hidden_layers* with shape (*batch size*, *padded_sequence_length*, *hidden_size*)
+    ///   - `all_attentions` - `Option<Vec<Tensor>>` of length *num_hidden_layers* with shape (*batch size*, *num_heads*, *padded_sequence_length*, *padded_sequence_length*)
+    ///
+    /// # Example
+    ///
+    /// ```no_run
+    /// # use rust_bert::bigbird::{BigBirdConfig, BigBirdForQuestionAnswering};
+    /// # use tch::{nn, Device, Tensor, no_grad};
+    /// # use rust_bert::Config;
+    /// # use std::path::Path;
+    /// # use tch::kind::Kind::Int64;
+    /// # let config_path = Path::new("path/to/config.json");
+    /// # let device = Device::Cpu;
+    /// # let vs = nn::VarStore::new(device);
+    /// # let config = BigBirdConfig::from_file(config_path);
+    /// # let bigbird_model = BigBirdForQuestionAnswering::new(&vs.root(), &config);
+    /// let (batch_size, sequence_length) = (2, 4096);
+    /// let input_tensor = Tensor::rand(&[batch_size, sequence_length], (Int64, device));
+    /// let mask = Tensor::ones(&[batch_size, sequence_length], (Int64, device));
+    ///
+    /// let model_output = no_grad(|| {
+    ///     bigbird_model
+    ///         .forward_t(Some(&input_tensor), Some(&mask), None, None, None, false)
+    ///         .unwrap()
+    /// });
+    /// ```
+    pub fn forward_t(
+        &self,
+        input_ids: Option<&Tensor>,
+        mask: Option<&Tensor>,
+        token_type_ids: Option<&Tensor>,
+        position_ids: Option<&Tensor>,
+        input_embeds: Option<&Tensor>,
+        train: bool,
+    ) -> Result<BigBirdQuestionAnsweringOutput, RustBertError> {
+        //    Tokens up to the first separator (question) are excluded from the answer, except the CLS token
+        let (question_mask, calc_token_type_ids) = match (input_ids, self.sep_token_id) {
+            (Some(input_ids), Some(sep_token_id)) => {
+                let question_lengths = input_ids
+                    .eq(sep_token_id)
+                    .to_kind(Kind::Int)
+                    .argmax(-1, true)
+                    + 1;
+                let positions =
+                    Tensor::arange(input_ids.size()[1], (Kind::Int64, input_ids.device()))
+                        .unsqueeze(0);
+                let question_mask = positions
+                    .lt_tensor(&question_lengths)
+                    .logical_and(&positions.ge(1));
+                let token_type_ids = positions.ge_tensor(&question_lengths).to_kind(Kind::Int64);
+                (Some(question_mask), Some(token_type_ids))
+            }
+            _ => (None, None),
+        };
+        let token_type_ids = token_type_ids.or(calc_token_type_ids.as_ref());
+
+        let base_model_output = self.bert.forward_t(
+            input_ids,
+            mask,
+            token_type_ids,
+            position_ids,
+            input_embeds,
+            train,
+        )?;
+
+        let mut logits = self
+            .qa_classifier
+            .forward_t(&base_model_output.hidden_state, train);
+        if let Some(question_mask) = question_mask {
+            logits = logits - question_mask.unsqueeze(-1).to_kind(logits.kind()) * 1e6;
+        }
+        let logits = logits.split(1, -1);
+        let (start_logits, end_logits) = (&logits[0], &logits[1]);
+        let start_logits = start_logits.squeeze_dim(-1);
+        let end_logits = end_logits.squeeze_dim(-1);
+
+        Ok(BigBirdQuestionAnsweringOutput {
+            start_logits,
+            end_logits,
+            all_hidden_states: base_model_output.all_hidden_states,
+            all_attentions: base_model_output.all_attentions,
+        })
+    }
+}
+
+/// Container for the BigBird model output.
+pub struct BigBirdModelOutput {
+    /// Last hidden states from the model
+    pub hidden_state: Tensor,
+    /// Pooled output (hidden state for the first token)
+    pub pooled_output: Option<Tensor>,
+    /// Hidden states for all intermediate layers
+    pub all_hidden_states: Option<Vec<Tensor>>,
+    /// Attention weights for all intermediate layers
+    pub all_attentions: Option<Vec<Tensor>>,
+}
+
+/// Container for the BigBird sequence classification model output.
+pub struct BigBirdSequenceClassificationOutput {
+    /// Logits for each input (sequence) for each target class
+    pub logits: Tensor,
+    /// Hidden states for all intermediate layers
+    pub all_hidden_states: Option<Vec<Tensor>>,
+    /// Attention weights for all intermediate layers
+    pub all_attentions: Option<Vec<Tensor>>,
+}
+
+/// Container for the BigBird question answering model output.
+pub struct BigBirdQuestionAnsweringOutput {
+    /// Logits for the start position for token of each input sequence
+    pub start_logits: Tensor,
+    /// Logits for the end position for token of each input sequence
+    pub end_logits: Tensor,
+    /// Hidden states for all intermediate layers
+    pub all_hidden_states: Option<Vec<Tensor>>,
+    /// Attention weights for all intermediate layers
+    pub all_attentions: Option<Vec<Tensor>>,
+}
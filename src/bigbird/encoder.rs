@@ -0,0 +1,138 @@
+// Copyright 2021 Google Research and The HuggingFace Inc. team. All rights reserved.
+// Copyright 2022 Guillaume Becquin
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//     http://www.apache.org/licenses/LICENSE-2.0
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use crate::bert::attention::{BertIntermediate, BertOutput};
+use crate::bert::{BertConfig, BertEncoderOutput};
+use crate::bigbird::attention::BigBirdAttention;
+use crate::bigbird::BigBirdConfig;
+use std::borrow::{Borrow, BorrowMut};
+use tch::{nn, Tensor};
+
+/// # BigBird layer
+/// Transformer layer made of a (block-sparse or full) self-attention layer, an intermediate (linear) and output
+/// (linear + layer norm) layers.
+pub struct BigBirdLayer {
+    attention: BigBirdAttention,
+    intermediate: BertIntermediate,
+    output: BertOutput,
+}
+
+impl BigBirdLayer {
+    pub fn new<'p, P>(p: P, config: &BigBirdConfig, bert_config: &BertConfig) -> BigBirdLayer
+    where
+        P: Borrow<nn::Path<'p>>,
+    {
+        let p = p.borrow();
+
+        let attention = BigBirdAttention::new(p / "attention", config, bert_config);
+        let intermediate = BertIntermediate::new(p / "intermediate", bert_config);
+        let output = BertOutput::new(p / "output", bert_config);
+
+        BigBirdLayer {
+            attention,
+            intermediate,
+            output,
+        }
+    }
+
+    pub fn forward_t(
+        &self,
+        hidden_states: &Tensor,
+        mask: &Tensor,
+        block_sparse: bool,
+        train: bool,
+    ) -> (Tensor, Option<Tensor>) {
+        let (attention_output, attention_weights) =
+            self.attention
+                .forward_t(hidden_states, mask, block_sparse, train);
+        let output = self.output.forward_t(
+            &self.intermediate.forward(&attention_output),
+            &attention_output,
+            train,
+        );
+        (output, attention_weights)
+    }
+}
+
+/// # BigBird encoder
+/// Stack of `BigBirdLayer`
+pub struct BigBirdEncoder {
+    output_attentions: bool,
+    output_hidden_states: bool,
+    layers: Vec<BigBirdLayer>,
+}
+
+impl BigBirdEncoder {
+    pub fn new<'p, P>(p: P, config: &BigBirdConfig, bert_config: &BertConfig) -> BigBirdEncoder
+    where
+        P: Borrow<nn::Path<'p>>,
+    {
+        let p = p.borrow() / "layer";
+        let layers = (0..config.num_hidden_layers)
+            .map(|layer_index| BigBirdLayer::new(&p / layer_index, config, bert_config))
+            .collect();
+
+        BigBirdEncoder {
+            output_attentions: config.output_attentions.unwrap_or(false),
+            output_hidden_states: config.output_hidden_states.unwrap_or(false),
+            layers,
+        }
+    }
+
+    /// Forward pass through the encoder
+    ///
+    /// # Arguments
+    ///
+    /// * `input` - input tensor of shape (*batch size*, *sequence_length*, *hidden_size*).
+    /// * `mask` - Mask of shape (*batch size*, *sequence_length*). Masked position have value 0, non-masked value 1.
+    /// * `block_sparse` - Use the block-sparse attention. The sequence length must then be a multiple of the block size.
+    /// * `train` - boolean flag to turn on/off the dropout layers in the model. Should be set to false for inference.
+    pub fn forward_t(
+        &self,
+        input: &Tensor,
+        mask: &Tensor,
+        block_sparse: bool,
+        train: bool,
+    ) -> BertEncoderOutput {
+        let mut all_hidden_states: Option<Vec<Tensor>> = if self.output_hidden_states {
+            Some(vec![])
+        } else {
+            None
+        };
+        let mut all_attentions: Option<Vec<Tensor>> = if self.output_attentions {
+            Some(vec![])
+        } else {
+            None
+        };
+
+        let mut hidden_state = None::<Tensor>;
+        for layer in &self.layers {
+            let layer_input = hidden_state.as_ref().unwrap_or(input);
+            let (layer_output, attention_weights) =
+                layer.forward_t(layer_input, mask, block_sparse, train);
+
+            if let Some(attentions) = all_attentions.borrow_mut() {
+                attentions.push(attention_weights.unwrap());
+            };
+            if let Some(hidden_states) = all_hidden_states.borrow_mut() {
+                hidden_states.push(layer_output.copy());
+            };
+            hidden_state = Some(layer_output);
+        }
+
+        BertEncoderOutput {
+            hidden_state: hidden_state.unwrap(),
+            all_hidden_states,
+            all_attentions,
+        }
+    }
+}
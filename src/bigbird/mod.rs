@@ -0,0 +1,59 @@
+//! # BigBird: Transformers for Longer Sequences (Zaheer et al.)
+//!
+//! Implementation of the BigBird language model ([Big Bird: Transformers for Longer Sequences](https://arxiv.org/abs/2007.14062) Zaheer, Guruganesh, Dubey, Ainslie, Alberti, Ontanon, Pham, Ravula, Wang, Yang, Ahmed, 2020).
+//! BigBird replaces the full self-attention of BERT by a block-sparse attention: the sequence is split in blocks of tokens, the first and last blocks
+//! attend to the entire sequence, and the other blocks attend to a sliding window of neighbouring blocks and to randomly selected blocks (during training).
+//! The memory requirements grow linearly with the sequence length, allowing classification and question answering over documents of several thousand tokens.
+//! The base model is implemented in the `bigbird_model::BigBirdModel` struct. Several language model heads have also been implemented, including:
+//! - Question answering: `bigbird_model::BigBirdForQuestionAnswering`
+//! - Sequence classification: `bigbird_model::BigBirdForSequenceClassification`
+//!
+//! # Model set-up and pre-trained weights loading
+//!
+//! All models expect the following resources:
+//! - Configuration file expected to have a structure following the [Transformers library](https://github.com/huggingface/transformers)
+//! - Model weights are expected to have a structure and parameter names following the [Transformers library](https://github.com/huggingface/transformers). A conversion using the Python utility scripts is required to convert the `.bin` weights to the `.ot` format.
+//! - Token ids produced by the SentencePiece tokenizer of the checkpoint (`spiece.model`), in the format `[CLS] text [SEP]` (or `[CLS] question [SEP] context [SEP]` for question answering)
+//!
+//! No pre-trained checkpoint is hosted with the crate, the resources are provided as local files:
+//!
+//! ```no_run
+//! # fn main() -> anyhow::Result<()> {
+//! #
+//! use tch::{nn, no_grad, Device, Kind, Tensor};
+//! # use std::path::PathBuf;
+//! use rust_bert::bigbird::{BigBirdConfig, BigBirdForSequenceClassification};
+//! use rust_bert::resources::{LocalResource, ResourceProvider};
+//! use rust_bert::Config;
+//!
+//! let config_resource = LocalResource {
+//!     local_path: PathBuf::from("path/to/config.json"),
+//! };
+//! let weights_resource = LocalResource {
+//!     local_path: PathBuf::from("path/to/model.ot"),
+//! };
+//! let config_path = config_resource.get_local_path()?;
+//! let weights_path = weights_resource.get_local_path()?;
+//!
+//! let device = Device::cuda_if_available();
+//! let mut vs = nn::VarStore::new(device);
+//! let config = BigBirdConfig::from_file(config_path);
+//! let bigbird_model = BigBirdForSequenceClassification::new(&vs.root(), &config);
+//! vs.load(weights_path)?;
+//!
+//! let input_ids = Tensor::ones(&[1, 4096], (Kind::Int64, device));
+//! let output = no_grad(|| bigbird_model.forward_t(Some(&input_ids), None, None, None, None, false))?;
+//! # Ok(())
+//! # }
+//! ```
+
+mod attention;
+mod bigbird_model;
+mod encoder;
+
+pub use bigbird_model::{
+    AttentionType, BigBirdClassificationHead, BigBirdConfig, BigBirdForQuestionAnswering,
+    BigBirdForSequenceClassification, BigBirdModel, BigBirdModelOutput,
+    BigBirdQuestionAnsweringHead, BigBirdQuestionAnsweringOutput,
+    BigBirdSequenceClassificationOutput,
+};
@@ -0,0 +1,255 @@
+// Copyright 2022 Guillaume Becquin
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//     http://www.apache.org/licenses/LICENSE-2.0
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! # Foreign language bindings (UniFFI)
+//!
+//! Stable interface exposing the sequence classification, sentence embeddings and text generation pipelines
+//! to Python, Kotlin and Swift through [UniFFI](https://mozilla.github.io/uniffi-rs/) generated bindings.
+//! The interface is only compiled with the `uniffi` feature. It deliberately exposes a small set of owned
+//! types (strings, numbers, lists and records) rather than the tensors and generic configurations of the crate:
+//! - `SequenceClassifier`: sequence classification (default: DistilBERT fine-tuned on SST-2 for sentiment analysis)
+//! - `SentenceEmbedder`: sentence embeddings for a pretrained `SentenceEmbeddingsModelType` or a local model directory
+//! - `TextGenerator`: text generation (default: GPT2 medium) with `GenerationParameters` overriding the generation settings
+//!
+//! All errors are surfaced as `RustBertError` exceptions in the foreign language. The pipelines are guarded by a mutex
+//! and can be shared between threads of the calling application, the calls to a given pipeline being serialized.
+//!
+//! The bindings are generated from the compiled dynamic library with the `uniffi-bindgen` binary of the crate:
+//!
+//! ```bash
+//! cargo rustc --lib --release --features uniffi --crate-type cdylib
+//! cargo run --features uniffi --bin uniffi-bindgen -- generate --library target/release/librust_bert.so --language python --out-dir bindings
+//! ```
+//!
+//! The generated Python module can then be used as follows (the dynamic library being placed next to it):
+//!
+//! ```python
+//! from rust_bert import SequenceClassifier, TextGenerator, GenerationParameters
+//!
+//! classifier = SequenceClassifier()
+//! labels = classifier.predict(["This is great!", "This is terrible."])
+//!
+//! generator = TextGenerator(GenerationParameters(max_length=32))
+//! outputs = generator.generate(["The dog"], None)
+//! ```
+
+use crate::pipelines::sentence_embeddings::{
+    SentenceEmbeddingsBuilder, SentenceEmbeddingsModel, SentenceEmbeddingsModelType,
+};
+use crate::pipelines::sequence_classification::{Label, SequenceClassificationModel};
+use crate::pipelines::text_generation::{TextGenerationConfig, TextGenerationModel};
+use crate::RustBertError;
+use std::sync::{Arc, Mutex};
+
+/// # Label returned by a `SequenceClassifier`
+#[derive(Debug, Clone, uniffi::Record)]
+pub struct ClassificationLabel {
+    /// Label String representation
+    pub text: String,
+    /// Confidence score
+    pub score: f64,
+    /// Label ID
+    pub id: i64,
+    /// Index of the classified text in the input
+    pub sentence: u64,
+}
+
+impl From<Label> for ClassificationLabel {
+    fn from(label: Label) -> Self {
+        ClassificationLabel {
+            text: label.text,
+            score: label.score,
+            id: label.id,
+            sentence: label.sentence as u64,
+        }
+    }
+}
+
+/// # Sequence classification pipeline exposed to the foreign languages
+#[derive(uniffi::Object)]
+pub struct SequenceClassifier {
+    model: Mutex<SequenceClassificationModel>,
+}
+
+#[uniffi::export]
+impl SequenceClassifier {
+    /// Loads the default sequence classification model (DistilBERT fine-tuned on SST-2)
+    #[uniffi::constructor]
+    pub fn new() -> Result<Arc<Self>, RustBertError> {
+        Ok(Arc::new(SequenceClassifier {
+            model: Mutex::new(SequenceClassificationModel::new(Default::default())?),
+        }))
+    }
+
+    /// Classifies the texts, returning the top label of each text
+    pub fn predict(&self, texts: Vec<String>) -> Vec<ClassificationLabel> {
+        let texts = texts.iter().map(String::as_str).collect::<Vec<&str>>();
+        self.model
+            .lock()
+            .unwrap()
+            .predict(&texts)
+            .into_iter()
+            .map(ClassificationLabel::from)
+            .collect()
+    }
+}
+
+/// # Sentence embeddings pipeline exposed to the foreign languages
+#[derive(uniffi::Object)]
+pub struct SentenceEmbedder {
+    model: Mutex<SentenceEmbeddingsModel>,
+}
+
+#[uniffi::export]
+impl SentenceEmbedder {
+    /// Loads a pretrained sentence embeddings model
+    #[uniffi::constructor]
+    pub fn new(model_type: SentenceEmbeddingsModelType) -> Result<Arc<Self>, RustBertError> {
+        Ok(Arc::new(SentenceEmbedder {
+            model: Mutex::new(SentenceEmbeddingsBuilder::remote(model_type).create_model()?),
+        }))
+    }
+
+    /// Loads a sentence embeddings model from a local directory following the Sentence Transformers layout
+    #[uniffi::constructor]
+    pub fn from_local_dir(model_dir: String) -> Result<Arc<Self>, RustBertError> {
+        Ok(Arc::new(SentenceEmbedder {
+            model: Mutex::new(SentenceEmbeddingsBuilder::local(model_dir).create_model()?),
+        }))
+    }
+
+    /// Computes the embedding of each text
+    pub fn encode(&self, texts: Vec<String>) -> Result<Vec<Vec<f32>>, RustBertError> {
+        self.model.lock().unwrap().encode(&texts)
+    }
+}
+
+/// # Generation settings of a `TextGenerator`
+/// Settings left to `None` keep the value of the default `TextGenerationConfig`.
+#[derive(Debug, Clone, Default, uniffi::Record)]
+pub struct GenerationParameters {
+    /// Minimum sequence length
+    #[uniffi(default = None)]
+    pub min_length: Option<i64>,
+    /// Maximum sequence length
+    #[uniffi(default = None)]
+    pub max_length: Option<i64>,
+    /// Sampling flag. If true, will perform top-k and/or nucleus sampling on generated tokens, otherwise greedy (deterministic) decoding
+    #[uniffi(default = None)]
+    pub do_sample: Option<bool>,
+    /// Number of beams for beam search
+    #[uniffi(default = None)]
+    pub num_beams: Option<i64>,
+    /// Temperature setting. Values higher than 1 will improve originality at the risk of reducing relevance
+    #[uniffi(default = None)]
+    pub temperature: Option<f64>,
+    /// Top_k values for sampling tokens. Value higher than 0 will enable the feature
+    #[uniffi(default = None)]
+    pub top_k: Option<i64>,
+    /// Top_p value for [Nucleus sampling, Holtzman et al.](http://arxiv.org/abs/1904.09751). Keep top tokens until cumulative probability reaches top_p
+    #[uniffi(default = None)]
+    pub top_p: Option<f64>,
+    /// Repetition penalty (mostly useful for CTRL decoders). Values higher than 1 will penalize tokens that have been already generated.
+    #[uniffi(default = None)]
+    pub repetition_penalty: Option<f64>,
+    /// Size of n-grams to prevent repeating. Values higher than 0 turn on this feature
+    #[uniffi(default = None)]
+    pub no_repeat_ngram_size: Option<i64>,
+    /// Number of sequences to return for each prompt text
+    #[uniffi(default = None)]
+    pub num_return_sequences: Option<i64>,
+}
+
+impl GenerationParameters {
+    fn apply(&self, mut config: TextGenerationConfig) -> TextGenerationConfig {
+        if let Some(min_length) = self.min_length {
+            config.min_length = min_length;
+        }
+        if let Some(max_length) = self.max_length {
+            config.max_length = max_length;
+        }
+        if let Some(do_sample) = self.do_sample {
+            config.do_sample = do_sample;
+        }
+        if let Some(num_beams) = self.num_beams {
+            config.num_beams = num_beams;
+        }
+        if let Some(temperature) = self.temperature {
+            config.temperature = temperature;
+        }
+        if let Some(top_k) = self.top_k {
+            config.top_k = top_k;
+        }
+        if let Some(top_p) = self.top_p {
+            config.top_p = top_p;
+        }
+        if let Some(repetition_penalty) = self.repetition_penalty {
+            config.repetition_penalty = repetition_penalty;
+        }
+        if let Some(no_repeat_ngram_size) = self.no_repeat_ngram_size {
+            config.no_repeat_ngram_size = no_repeat_ngram_size;
+        }
+        if let Some(num_return_sequences) = self.num_return_sequences {
+            config.num_return_sequences = num_return_sequences;
+        }
+        config
+    }
+}
+
+/// # Text generation pipeline exposed to the foreign languages
+#[derive(uniffi::Object)]
+pub struct TextGenerator {
+    model: Mutex<TextGenerationModel>,
+}
+
+#[uniffi::export]
+impl TextGenerator {
+    /// Loads the default text generation model (GPT2 medium) with the generation settings provided
+    #[uniffi::constructor]
+    pub fn new(parameters: GenerationParameters) -> Result<Arc<Self>, RustBertError> {
+        let config = parameters.apply(TextGenerationConfig::default());
+        Ok(Arc::new(TextGenerator {
+            model: Mutex::new(TextGenerationModel::new(config)?),
+        }))
+    }
+
+    /// Generates continuations of the prompts, optionally preceded by a prefix (e.g. to provide context)
+    pub fn generate(&self, prompts: Vec<String>, prefix: Option<String>) -> Vec<String> {
+        self.model
+            .lock()
+            .unwrap()
+            .generate(&prompts, prefix.as_deref())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn generation_parameters_override_defaults() {
+        let parameters = GenerationParameters {
+            max_length: Some(32),
+            do_sample: Some(false),
+            num_return_sequences: Some(2),
+            ..Default::default()
+        };
+        let default_config = TextGenerationConfig::default();
+        let (default_min_length, default_top_k) = (default_config.min_length, default_config.top_k);
+        let config = parameters.apply(default_config);
+
+        assert_eq!(config.max_length, 32);
+        assert!(!config.do_sample);
+        assert_eq!(config.num_return_sequences, 2);
+        assert_eq!(config.min_length, default_min_length);
+        assert_eq!(config.top_k, default_top_k);
+    }
+}
@@ -0,0 +1,224 @@
+// Copyright 2022 the Big Science Workshop and HuggingFace Inc. team. All rights reserved.
+// Copyright 2022 Guillaume Becquin
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//     http://www.apache.org/licenses/LICENSE-2.0
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use crate::bloom::BloomConfig;
+use crate::common::dropout::Dropout;
+use std::borrow::Borrow;
+use tch::{nn, Kind, Tensor};
+
+#[derive(Debug)]
+/// # Cache for BLOOM attention layers
+/// Stores the cached value of key and value (of shape (*batch size*, *num_heads*, *sequence_length*,
+/// *head dimension*))
+pub struct LayerState {
+    /// Cached keys
+    pub prev_key: Tensor,
+    /// Cached values
+    pub prev_value: Tensor,
+}
+
+impl Clone for LayerState {
+    fn clone(&self) -> Self {
+        LayerState {
+            prev_key: self.prev_key.copy(),
+            prev_value: self.prev_value.copy(),
+        }
+    }
+}
+
+impl LayerState {
+    pub(crate) fn reorder_cache(&mut self, new_indices: &Tensor) {
+        self.prev_key = self.prev_key.index_select(0, new_indices);
+        self.prev_value = self.prev_value.index_select(0, new_indices);
+    }
+}
+
+/// Slopes of the ALiBi attention biases ([Press et al., 2021](https://arxiv.org/abs/2108.12409)), a geometric
+/// sequence for each attention head. For a number of heads that is not a power of 2, the slopes of the closest
+/// lower power of 2 are completed with every other slope of the next power of 2.
+pub(crate) fn alibi_slopes(num_heads: i64) -> Vec<f64> {
+    // The slopes of `n` heads are the powers of 2^(-8/n)
+    let closest_power_of_2 = 2i64.pow((num_heads as f64).log2().floor() as u32);
+    let base = 2f64.powf(-8.0 / closest_power_of_2 as f64);
+    let mut slopes: Vec<f64> = (1..=closest_power_of_2)
+        .map(|power| base.powi(power as i32))
+        .collect();
+    if closest_power_of_2 != num_heads {
+        let extra_base = 2f64.powf(-8.0 / (2 * closest_power_of_2) as f64);
+        let num_remaining_heads = closest_power_of_2.min(num_heads - closest_power_of_2);
+        slopes
+            .extend((0..num_remaining_heads).map(|index| extra_base.powi((2 * index + 1) as i32)));
+    }
+    slopes
+}
+
+/// Builds the ALiBi attention biases of shape (*batch size*, *num_heads*, 1, *past_length + sequence_length*): the
+/// slope of each head multiplied by the position of the keys. The positions are computed from the attention mask of
+/// shape (*batch size*, *past_length + sequence_length*), the padding does not shift the positions of the tokens.
+pub(crate) fn build_alibi_tensor(attention_mask: &Tensor, num_heads: i64, kind: Kind) -> Tensor {
+    let (batch_size, sequence_length) = attention_mask.size2().unwrap();
+    let slopes = Tensor::of_slice(&alibi_slopes(num_heads))
+        .to_kind(Kind::Float)
+        .to_device(attention_mask.device());
+    let attention_mask = attention_mask.to_kind(Kind::Float);
+    let positions = (attention_mask.cumsum(-1, Kind::Float) - 1) * &attention_mask;
+    (slopes.view([1, num_heads, 1, 1]) * positions.view([batch_size, 1, 1, sequence_length]))
+        .to_kind(kind)
+}
+
+pub struct BloomAttention {
+    query_key_value: nn::Linear,
+    dense: nn::Linear,
+    attention_dropout: Dropout,
+    num_heads: i64,
+    head_dim: i64,
+    output_attentions: bool,
+}
+
+impl BloomAttention {
+    pub fn new<'p, P>(p: P, config: &BloomConfig) -> BloomAttention
+    where
+        P: Borrow<nn::Path<'p>>,
+    {
+        let p = p.borrow();
+
+        let num_heads = config.n_head;
+        let head_dim = config.hidden_size / num_heads;
+
+        let query_key_value = nn::linear(
+            p / "query_key_value",
+            config.hidden_size,
+            3 * config.hidden_size,
+            Default::default(),
+        );
+        let dense = nn::linear(
+            p / "dense",
+            config.hidden_size,
+            config.hidden_size,
+            Default::default(),
+        );
+
+        let attention_dropout = Dropout::new(config.attention_dropout.unwrap_or(0.0));
+        let output_attentions = config.output_attentions.unwrap_or(false);
+
+        BloomAttention {
+            query_key_value,
+            dense,
+            attention_dropout,
+            num_heads,
+            head_dim,
+            output_attentions,
+        }
+    }
+
+    pub fn forward_t(
+        &self,
+        hidden_states: &Tensor,
+        alibi: &Tensor,
+        attention_mask: &Tensor,
+        layer_state: Option<&LayerState>,
+        train: bool,
+    ) -> (Tensor, Option<Tensor>, Option<LayerState>) {
+        let (batch_size, sequence_length, _) = hidden_states.size3().unwrap();
+
+        // The fused projection is interleaved by head: (query, key, value) for each head
+        let fused_qkv = hidden_states.apply(&self.query_key_value).view([
+            batch_size,
+            sequence_length,
+            self.num_heads,
+            3,
+            self.head_dim,
+        ]);
+        let query = fused_qkv.select(3, 0).transpose(1, 2);
+        let key = fused_qkv.select(3, 1).transpose(1, 2);
+        let value = fused_qkv.select(3, 2).transpose(1, 2);
+
+        let (key, value) = match layer_state {
+            Some(layer_state_value) => (
+                Tensor::cat(&[&layer_state_value.prev_key, &key], -2),
+                Tensor::cat(&[&layer_state_value.prev_value, &value], -2),
+            ),
+            None => (key, value),
+        };
+        let layer_state = Some(LayerState {
+            prev_key: key.copy(),
+            prev_value: value.copy(),
+        });
+
+        let attention_weights = query.matmul(&key.transpose(-1, -2))
+            / (self.head_dim as f64).sqrt()
+            + alibi
+            + attention_mask;
+        let attention_weights = attention_weights
+            .softmax(-1, Kind::Float)
+            .to_kind(value.kind())
+            .apply_t(&self.attention_dropout, train);
+
+        let attention_output = attention_weights
+            .matmul(&value)
+            .transpose(1, 2)
+            .contiguous()
+            .view([batch_size, sequence_length, self.num_heads * self.head_dim])
+            .apply(&self.dense);
+
+        let attention_weights = if self.output_attentions {
+            Some(attention_weights)
+        } else {
+            None
+        };
+
+        (attention_output, attention_weights, layer_state)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn assert_close(values: &[f64], expected: &[f64]) {
+        assert_eq!(values.len(), expected.len());
+        for (value, expected) in values.iter().zip(expected.iter()) {
+            assert!((value - expected).abs() < 1e-9, "{} != {}", value, expected);
+        }
+    }
+
+    #[test]
+    fn alibi_slopes_power_of_2() {
+        let expected: Vec<f64> = (1..=8).map(|power| 0.5f64.powi(power)).collect();
+        assert_close(&alibi_slopes(8), &expected);
+
+        let expected: Vec<f64> = (1..=16)
+            .map(|power| 0.5f64.powf(power as f64 / 2.0))
+            .collect();
+        assert_close(&alibi_slopes(16), &expected);
+    }
+
+    #[test]
+    fn alibi_slopes_other_number_of_heads() {
+        let mut expected: Vec<f64> = (1..=8).map(|power| 0.5f64.powi(power)).collect();
+        expected.extend([0.5, 1.5, 2.5, 3.5].iter().map(|power| 0.5f64.powf(*power)));
+        assert_close(&alibi_slopes(12), &expected);
+    }
+
+    #[test]
+    fn alibi_positions_ignore_padding() {
+        let attention_mask = Tensor::of_slice(&[0i64, 0, 1, 1, 1, 1, 1, 1, 1, 1]).view([2, 5]);
+        let alibi = build_alibi_tensor(&attention_mask, 8, Kind::Float);
+
+        assert_eq!(alibi.size(), vec![2, 8, 1, 5]);
+        let first_head = Vec::<f64>::from(alibi.select(1, 0).flatten(0, -1));
+        assert_close(
+            &first_head,
+            &[0.0, 0.0, 0.0, 0.5, 1.0, 0.0, 0.5, 1.0, 1.5, 2.0],
+        );
+    }
+}
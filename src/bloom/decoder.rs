@@ -0,0 +1,145 @@
+// Copyright 2022 the Big Science Workshop and HuggingFace Inc. team. All rights reserved.
+// Copyright 2022 Guillaume Becquin
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//     http://www.apache.org/licenses/LICENSE-2.0
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use crate::bloom::attention::{BloomAttention, LayerState};
+use crate::bloom::BloomConfig;
+use crate::common::activations::_gelu_new;
+use crate::common::dropout::Dropout;
+use std::borrow::Borrow;
+use tch::nn::Module;
+use tch::{nn, Tensor};
+
+/// # BLOOM feed-forward layer
+/// Feed-forward layer with a GeLU activation (tanh approximation)
+pub struct BloomMLP {
+    dense_h_to_4h: nn::Linear,
+    dense_4h_to_h: nn::Linear,
+}
+
+impl BloomMLP {
+    pub fn new<'p, P>(p: P, config: &BloomConfig) -> BloomMLP
+    where
+        P: Borrow<nn::Path<'p>>,
+    {
+        let p = p.borrow();
+
+        let dense_h_to_4h = nn::linear(
+            p / "dense_h_to_4h",
+            config.hidden_size,
+            4 * config.hidden_size,
+            Default::default(),
+        );
+        let dense_4h_to_h = nn::linear(
+            p / "dense_4h_to_h",
+            4 * config.hidden_size,
+            config.hidden_size,
+            Default::default(),
+        );
+
+        BloomMLP {
+            dense_h_to_4h,
+            dense_4h_to_h,
+        }
+    }
+}
+
+impl Module for BloomMLP {
+    fn forward(&self, hidden_states: &Tensor) -> Tensor {
+        _gelu_new(&hidden_states.apply(&self.dense_h_to_4h)).apply(&self.dense_4h_to_h)
+    }
+}
+
+/// # BLOOM decoder layer
+/// Pre-normalization decoder layer. If `apply_residual_connection_post_layernorm` is set, the residual connections
+/// start from the normalized hidden states instead of the layer inputs.
+pub struct BloomBlock {
+    input_layernorm: nn::LayerNorm,
+    self_attention: BloomAttention,
+    post_attention_layernorm: nn::LayerNorm,
+    mlp: BloomMLP,
+    dropout: Dropout,
+    apply_residual_connection_post_layernorm: bool,
+}
+
+impl BloomBlock {
+    pub fn new<'p, P>(p: P, config: &BloomConfig) -> BloomBlock
+    where
+        P: Borrow<nn::Path<'p>>,
+    {
+        let p = p.borrow();
+
+        let layer_norm_config = nn::LayerNormConfig {
+            eps: config.layer_norm_epsilon,
+            ..Default::default()
+        };
+
+        let input_layernorm = nn::layer_norm(
+            p / "input_layernorm",
+            vec![config.hidden_size],
+            layer_norm_config,
+        );
+        let self_attention = BloomAttention::new(p / "self_attention", config);
+        let post_attention_layernorm = nn::layer_norm(
+            p / "post_attention_layernorm",
+            vec![config.hidden_size],
+            layer_norm_config,
+        );
+        let mlp = BloomMLP::new(p / "mlp", config);
+        let dropout = Dropout::new(config.hidden_dropout.unwrap_or(0.0));
+        let apply_residual_connection_post_layernorm = config
+            .apply_residual_connection_post_layernorm
+            .unwrap_or(false);
+
+        BloomBlock {
+            input_layernorm,
+            self_attention,
+            post_attention_layernorm,
+            mlp,
+            dropout,
+            apply_residual_connection_post_layernorm,
+        }
+    }
+
+    pub fn forward_t(
+        &self,
+        hidden_states: &Tensor,
+        alibi: &Tensor,
+        attention_mask: &Tensor,
+        layer_state: Option<&LayerState>,
+        train: bool,
+    ) -> (Tensor, Option<Tensor>, Option<LayerState>) {
+        let attention_input = hidden_states.apply(&self.input_layernorm);
+        let residual = if self.apply_residual_connection_post_layernorm {
+            &attention_input
+        } else {
+            hidden_states
+        };
+        let (attention_output, attention_weights, layer_state) = self.self_attention.forward_t(
+            &attention_input,
+            alibi,
+            attention_mask,
+            layer_state,
+            train,
+        );
+        let hidden_states = residual + attention_output.apply_t(&self.dropout, train);
+
+        let mlp_input = hidden_states.apply(&self.post_attention_layernorm);
+        let residual = if self.apply_residual_connection_post_layernorm {
+            &mlp_input
+        } else {
+            &hidden_states
+        };
+        let output = residual + mlp_input.apply(&self.mlp).apply_t(&self.dropout, train);
+
+        (output, attention_weights, layer_state)
+    }
+}
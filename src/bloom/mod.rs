@@ -0,0 +1,71 @@
+//! # BLOOM
+//!
+//! Implementation of the BLOOM multilingual language model ([BLOOM: A 176B-Parameter Open-Access Multilingual Language Model](https://arxiv.org/abs/2211.05100) BigScience Workshop, 2022).
+//! The base model is implemented in the `bloom_model::BloomModel` struct. A causal language modeling head is implemented in `bloom_model::BloomForCausalLM`.
+//! The model does not use position embeddings: the attention scores receive a linear bias proportional to the distance between the query and key
+//! positions, with a different slope for each head ([ALiBi](https://arxiv.org/abs/2108.12409) Press, Smith, Lewis, 2021). The positions are computed
+//! from the attention mask so that left-padded sequences are handled consistently. The word embeddings are followed by a layer normalization.
+//!
+//! # Model set-up and pre-trained weights loading
+//!
+//! The model is available for text generation with `ModelType::Bloom`. All models expect the following resources:
+//! - Configuration file expected to have a structure following the [Transformers library](https://github.com/huggingface/transformers)
+//! - Model weights are expected to have a structure and parameter names following the [Transformers library](https://github.com/huggingface/transformers). A conversion using the Python utility scripts is required to convert the `.bin` weights to the `.ot` format (the shards of a checkpoint can be passed together: `python utils/convert_model.py path/to/pytorch_model-*.bin`).
+//! - `Gpt2Tokenizer` using a `vocab.json` vocabulary and a `merges.txt` merges file. The checkpoints only provide a `tokenizer.json` file: the vocabulary and merges files are saved with the Transformers tokenizer (`tokenizer.save_vocabulary(path)`)
+//!
+//! No pre-trained checkpoint is hosted with the crate, the resources are provided as local files:
+//!
+//! ```no_run
+//! use rust_bert::pipelines::common::ModelType;
+//! use rust_bert::pipelines::text_generation::{TextGenerationConfig, TextGenerationModel};
+//! use rust_bert::resources::LocalResource;
+//! use std::path::PathBuf;
+//! use tch::Device;
+//!
+//! fn main() -> anyhow::Result<()> {
+//!     let config_resource = Box::new(LocalResource {
+//!         local_path: PathBuf::from("path/to/config.json"),
+//!     });
+//!     let vocab_resource = Box::new(LocalResource {
+//!         local_path: PathBuf::from("path/to/vocab.json"),
+//!     });
+//!     let merges_resource = Box::new(LocalResource {
+//!         local_path: PathBuf::from("path/to/merges.txt"),
+//!     });
+//!     let model_resource = Box::new(LocalResource {
+//!         local_path: PathBuf::from("path/to/rust_model.ot"),
+//!     });
+//!
+//!     let text_generation_config = TextGenerationConfig {
+//!         model_type: ModelType::Bloom,
+//!         model_resource,
+//!         config_resource,
+//!         vocab_resource,
+//!         merges_resource,
+//!         do_sample: false,
+//!         max_length: 64,
+//!         device: Device::cuda_if_available(),
+//!         ..Default::default()
+//!     };
+//!     let model = TextGenerationModel::new(text_generation_config)?;
+//!
+//!     let input_context = "Traduis en anglais : Je t'aime.";
+//!     let output = model.generate(&[input_context], None);
+//!
+//!     for sentence in output {
+//!         println!("{}", sentence);
+//!     }
+//!
+//!     Ok(())
+//! }
+//! ```
+
+mod attention;
+mod bloom_model;
+mod decoder;
+
+pub use bloom_model::{
+    BloomConfig, BloomForCausalLM, BloomGenerator, BloomModel, BloomModelLMOutput, BloomModelOutput,
+};
+
+pub use attention::LayerState;
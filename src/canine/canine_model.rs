@@ -0,0 +1,700 @@
+// Copyright 2021 Google AI and The HuggingFace Inc. team. All rights reserved.
+// Copyright 2022 Guillaume Becquin
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//     http://www.apache.org/licenses/LICENSE-2.0
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use crate::bert::{BertConfig, BertEncoder, BertPooler};
+use crate::canine::embeddings::{CanineEmbeddings, HASH_PRIMES};
+use crate::canine::encoder::{CanineCharactersToMolecules, CanineConvProjection, CanineLocalLayer};
+use crate::common::activations::Activation;
+use crate::common::dropout::Dropout;
+use crate::common::embeddings::get_shape_and_device_from_ids_embeddings_pair;
+use crate::{Config, RustBertError};
+use serde::{Deserialize, Serialize};
+use std::borrow::Borrow;
+use std::collections::HashMap;
+use tch::{nn, Kind, Tensor};
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+/// # CANINE model configuration
+/// Defines the CANINE model architecture (e.g. number of layers, hidden layer size, hashing of the code points, downsampling rate, label mapping...)
+pub struct CanineConfig {
+    pub hidden_act: Activation,
+    pub attention_probs_dropout_prob: f64,
+    pub hidden_dropout_prob: f64,
+    pub hidden_size: i64,
+    pub initializer_range: f32,
+    pub intermediate_size: i64,
+    pub layer_norm_eps: f64,
+    pub max_position_embeddings: i64,
+    pub num_attention_heads: i64,
+    pub num_hidden_layers: i64,
+    pub type_vocab_size: i64,
+    /// Number of characters merged in a molecule by the downsampling convolution
+    pub downsampling_rate: i64,
+    /// Kernel size of the convolution projecting the upsampled molecules back to the characters
+    pub upsampling_kernel_size: i64,
+    /// Number of hash functions used to embed the code points (at most 16)
+    pub num_hash_functions: i64,
+    /// Number of buckets of each hash function
+    pub num_hash_buckets: i64,
+    /// Number of characters of the chunks attended by the local transformer layers processing the characters
+    pub local_transformer_stride: i64,
+    pub pad_token_id: Option<i64>,
+    pub bos_token_id: Option<i64>,
+    pub eos_token_id: Option<i64>,
+    pub classifier_dropout: Option<f64>,
+    pub output_attentions: Option<bool>,
+    pub output_hidden_states: Option<bool>,
+    pub id2label: Option<HashMap<i64, String>>,
+    pub label2id: Option<HashMap<String, i64>>,
+}
+
+impl Config for CanineConfig {}
+
+impl Default for CanineConfig {
+    fn default() -> Self {
+        CanineConfig {
+            hidden_act: Activation::gelu,
+            attention_probs_dropout_prob: 0.1,
+            hidden_dropout_prob: 0.1,
+            hidden_size: 768,
+            initializer_range: 0.02,
+            intermediate_size: 3072,
+            layer_norm_eps: 1e-12,
+            max_position_embeddings: 16384,
+            num_attention_heads: 12,
+            num_hidden_layers: 12,
+            type_vocab_size: 16,
+            downsampling_rate: 4,
+            upsampling_kernel_size: 4,
+            num_hash_functions: 8,
+            num_hash_buckets: 16384,
+            local_transformer_stride: 128,
+            pad_token_id: Some(0),
+            bos_token_id: Some(0xE000),
+            eos_token_id: Some(0xE001),
+            classifier_dropout: None,
+            output_attentions: None,
+            output_hidden_states: None,
+            id2label: None,
+            label2id: None,
+        }
+    }
+}
+
+impl From<&CanineConfig> for BertConfig {
+    fn from(config: &CanineConfig) -> Self {
+        BertConfig {
+            hidden_act: config.hidden_act,
+            attention_probs_dropout_prob: config.attention_probs_dropout_prob,
+            hidden_dropout_prob: config.hidden_dropout_prob,
+            hidden_size: config.hidden_size,
+            initializer_range: config.initializer_range,
+            intermediate_size: config.intermediate_size,
+            max_position_embeddings: config.max_position_embeddings,
+            num_attention_heads: config.num_attention_heads,
+            num_hidden_layers: config.num_hidden_layers,
+            type_vocab_size: config.type_vocab_size,
+            vocab_size: config.num_hash_buckets,
+            output_attentions: config.output_attentions,
+            output_hidden_states: config.output_hidden_states,
+            is_decoder: None,
+            id2label: config.id2label.clone(),
+            label2id: config.label2id.clone(),
+            stochastic_depth: None,
+            fused_feed_forward: None,
+            early_exit: None,
+            model_type: None,
+            pre_layer_norm: None,
+        }
+    }
+}
+
+/// Converts a (*batch size*, *sequence_length*) mask with 0 for the padding positions to an additive attention mask
+/// of shape (*batch size*, 1, 1, *sequence_length*)
+fn get_extended_attention_mask(mask: &Tensor, kind: Kind) -> Tensor {
+    ((mask.ones_like() - mask) * -10000.0)
+        .to_kind(kind)
+        .unsqueeze(1)
+        .unsqueeze(1)
+}
+
+/// # CANINE Base model
+/// Tokenization-free encoder operating directly on the Unicode code points of the text. The characters are embedded
+/// with hash embeddings (no vocabulary), contextualized by a local transformer layer, downsampled to a 4 times
+/// shorter sequence of molecules processed by the deep transformer stack, and upsampled back to the characters.
+/// It is made of the following blocks:
+/// - `char_embeddings`: hash embeddings of the code points, with `position` and `segment_id` embeddings
+/// - `initial_char_encoder`: transformer layer with a local attention over chunks of characters
+/// - `chars_to_molecules`: strided convolution downsampling the characters to molecules
+/// - `encoder`: BERT encoder (transformer) processing the molecules
+/// - `pooler`: Optional linear layer applied to the first molecule (*CLS* token)
+/// - `projection`: convolution projecting the characters concatenated with their (repeated) molecule
+/// - `final_char_encoder`: transformer layer processing the characters
+pub struct CanineModel {
+    char_embeddings: CanineEmbeddings,
+    initial_char_encoder: CanineLocalLayer,
+    chars_to_molecules: CanineCharactersToMolecules,
+    encoder: BertEncoder,
+    pooler: Option<BertPooler>,
+    projection: CanineConvProjection,
+    final_char_encoder: BertEncoder,
+    downsampling_rate: i64,
+    max_position_embeddings: i64,
+}
+
+impl CanineModel {
+    /// Build a new `CanineModel`
+    ///
+    /// # Arguments
+    ///
+    /// * `p` - Variable store path for the root of the CANINE model
+    /// * `config` - `CanineConfig` object defining the model architecture
+    /// * `add_pooling_layer` - Enable/Disable an optional pooling layer at the end of the model
+    ///
+    /// # Example
+    ///
+    /// ```no_run
+    /// use rust_bert::canine::{CanineConfig, CanineModel};
+    /// use rust_bert::Config;
+    /// use std::path::Path;
+    /// use tch::{nn, Device};
+    ///
+    /// let config_path = Path::new("path/to/config.json");
+    /// let device = Device::Cpu;
+    /// let p = nn::VarStore::new(device);
+    /// let config = CanineConfig::from_file(config_path);
+    /// let canine_model: CanineModel = CanineModel::new(&p.root() / "canine", &config, true);
+    /// ```
+    pub fn new<'p, P>(p: P, config: &CanineConfig, add_pooling_layer: bool) -> CanineModel
+    where
+        P: Borrow<nn::Path<'p>>,
+    {
+        assert!(
+            config.num_hash_functions > 0 && config.num_hash_functions <= HASH_PRIMES.len() as i64,
+            "The number of hash functions must be between 1 and {}",
+            HASH_PRIMES.len()
+        );
+        assert_eq!(
+            config.hidden_size % config.num_hash_functions,
+            0,
+            "Hidden size not a multiple of the number of hash functions"
+        );
+        let p = p.borrow();
+        let bert_config = BertConfig::from(config);
+        let char_layer_config = BertConfig {
+            num_hidden_layers: 1,
+            output_attentions: None,
+            output_hidden_states: None,
+            ..bert_config.clone()
+        };
+
+        let char_embeddings = CanineEmbeddings::new(p / "char_embeddings", config);
+        let initial_char_encoder = CanineLocalLayer::new(
+            p / "initial_char_encoder" / "layer" / 0,
+            config,
+            &char_layer_config,
+        );
+        let chars_to_molecules = CanineCharactersToMolecules::new(p / "chars_to_molecules", config);
+        let encoder = BertEncoder::new(p / "encoder", &bert_config);
+        let pooler = if add_pooling_layer {
+            Some(BertPooler::new(p / "pooler", &bert_config))
+        } else {
+            None
+        };
+        let projection = CanineConvProjection::new(p / "projection", config);
+        let final_char_encoder = BertEncoder::new(p / "final_char_encoder", &char_layer_config);
+
+        CanineModel {
+            char_embeddings,
+            initial_char_encoder,
+            chars_to_molecules,
+            encoder,
+            pooler,
+            projection,
+            final_char_encoder,
+            downsampling_rate: config.downsampling_rate,
+            max_position_embeddings: config.max_position_embeddings,
+        }
+    }
+
+    /// Repeats each molecule (except the *CLS* molecule) `downsampling_rate` times, the last molecule covering the
+    /// remaining characters, to align the molecules with the characters they were computed from
+    fn repeat_molecules(&self, molecules: &Tensor, char_sequence_length: i64) -> Tensor {
+        let num_molecules = molecules.size()[1];
+        let repeated = molecules
+            .narrow(1, 1, num_molecules - 1)
+            .repeat_interleave_self_int(self.downsampling_rate, 1, None);
+        let remainder_length = char_sequence_length % self.downsampling_rate;
+        let last_repeated = molecules
+            .narrow(1, num_molecules - 1, 1)
+            .repeat_interleave_self_int(remainder_length + self.downsampling_rate, 1, None);
+        Tensor::cat(&[repeated, last_repeated], 1)
+    }
+
+    /// Forward pass through the model
+    ///
+    /// # Arguments
+    ///
+    /// * `input_ids` - Optional Unicode code points of shape (*batch size*, *sequence_length*) (see `CanineTokenizer`). If None, pre-computed embeddings must be provided (see `input_embeds`)
+    /// * `mask` - Optional mask of shape (*batch size*, *sequence_length*). Masked position have value 0, non-masked value 1. If None set to 1
+    /// * `token_type_ids` - Optional segment id of shape (*batch size*, *sequence_length*). Convention is value of 0 for the first sentence (incl. *SEP*) and 1 for the second sentence. If None set to 0.
+    /// * `position_ids` - Optional position ids of shape (*batch size*, *sequence_length*). If None, will be incremented from 0.
+    /// * `input_embeds` - Optional pre-computed character embeddings of shape (*batch size*, *sequence_length*, *hidden_size*). If None, input ids must be provided (see `input_ids`)
+    /// * `train` - boolean flag to turn on/off the dropout layers in the model. Should be set to false for inference.
+    ///
+    /// # Returns
+    ///
+    /// * `CanineModelOutput` containing:
+    ///   - `hidden_state` - `Tensor` of shape (*batch size*, *sequence_length*, *hidden_size*), one hidden state per character
+    ///   - `pooled_output` - `Option<Tensor>` of shape (*batch size*, *hidden_size*)
+    ///   - `all_hidden_states` - `Option<Vec<Tensor>>` of length *num_hidden_layers* with shape (*batch size*, *num_molecules*, *hidden_size*)
+    ///   - `all_attentions` - `Option<Vec<Tensor>>` of length *num_hidden_layers* with shape (*batch size*, *num_heads*, *num_molecules*, *num_molecules*)
+    ///
+    /// # Example
+    ///
+    /// ```no_run
+    /// # use rust_bert::canine::{CanineConfig, CanineModel, CanineTokenizer};
+    /// # use tch::{nn, Device, Tensor, no_grad};
+    /// # use rust_bert::Config;
+    /// # use std::path::Path;
+    /// # fn main() -> anyhow::Result<()> {
+    /// # let config_path = Path::new("path/to/config.json");
+    /// # let device = Device::Cpu;
+    /// # let vs = nn::VarStore::new(device);
+    /// # let config = CanineConfig::from_file(config_path);
+    /// # let canine_model = CanineModel::new(&vs.root(), &config, true);
+    /// let tokenizer = CanineTokenizer::new(2048);
+    /// let (input_ids, mask) = tokenizer.encode_list(&["Ths txt iz noisy :)"], device)?;
+    ///
+    /// let model_output = no_grad(|| {
+    ///     canine_model.forward_t(Some(&input_ids), Some(&mask), None, None, None, false)
+    /// })?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn forward_t(
+        &self,
+        input_ids: Option<&Tensor>,
+        mask: Option<&Tensor>,
+        token_type_ids: Option<&Tensor>,
+        position_ids: Option<&Tensor>,
+        input_embeds: Option<&Tensor>,
+        train: bool,
+    ) -> Result<CanineModelOutput, RustBertError> {
+        let (input_shape, device) =
+            get_shape_and_device_from_ids_embeddings_pair(input_ids, input_embeds)?;
+        let sequence_length = input_shape[1];
+        if position_ids.is_none() && (sequence_length > self.max_position_embeddings) {
+            return Err(RustBertError::InputTooLongError(format!(
+                "The input of {} characters exceeds the maximum number of positions ({})",
+                sequence_length, self.max_position_embeddings
+            )));
+        }
+
+        let mask = match mask {
+            Some(mask) if mask.dim() != 2 => {
+                return Err(RustBertError::ValueError(
+                    "Invalid attention mask dimension, must be 2".into(),
+                ));
+            }
+            Some(mask) => mask.shallow_clone(),
+            None => Tensor::ones(&input_shape, (Kind::Int64, device)),
+        };
+
+        // Sequences shorter than the downsampling rate are padded to form a molecule
+        let padding_length = (self.downsampling_rate - sequence_length).max(0);
+        let pad = |value: &Tensor| value.constant_pad_nd(&[0, padding_length]);
+        let (input_ids, mask, token_type_ids, position_ids, input_embeds) = if padding_length > 0 {
+            (
+                input_ids.map(pad),
+                pad(&mask),
+                token_type_ids.map(pad),
+                position_ids.map(pad),
+                input_embeds.map(|value| value.constant_pad_nd(&[0, 0, 0, padding_length])),
+            )
+        } else {
+            (
+                input_ids.map(|value| value.shallow_clone()),
+                mask,
+                token_type_ids.map(|value| value.shallow_clone()),
+                position_ids.map(|value| value.shallow_clone()),
+                input_embeds.map(|value| value.shallow_clone()),
+            )
+        };
+        let padded_length = sequence_length + padding_length;
+
+        let embedding_output = self.char_embeddings.forward_t(
+            input_ids.as_ref(),
+            token_type_ids.as_ref(),
+            position_ids.as_ref(),
+            input_embeds.as_ref(),
+            train,
+        )?;
+        let kind = embedding_output.kind();
+        let char_attention_mask = get_extended_attention_mask(&mask, kind);
+        // A molecule is attended if any of its characters is not padding
+        let molecule_mask = mask
+            .to_kind(Kind::Float)
+            .unsqueeze(1)
+            .max_pool1d(
+                &[self.downsampling_rate],
+                &[self.downsampling_rate],
+                &[0],
+                &[1],
+                false,
+            )
+            .squeeze_dim(1);
+        let molecule_attention_mask = get_extended_attention_mask(&molecule_mask, kind);
+
+        let input_char_encoding =
+            self.initial_char_encoder
+                .forward_t(&embedding_output, &char_attention_mask, train);
+        let molecules = self.chars_to_molecules.forward(&input_char_encoding);
+        let encoder_output = self.encoder.forward_t(
+            &molecules,
+            Some(&molecule_attention_mask),
+            None,
+            None,
+            train,
+        );
+        let pooled_output = self
+            .pooler
+            .as_ref()
+            .map(|pooler| pooler.forward(&encoder_output.hidden_state));
+
+        let repeated_molecules = self.repeat_molecules(&encoder_output.hidden_state, padded_length);
+        let projected = self.projection.forward_t(
+            &Tensor::cat(&[input_char_encoding, repeated_molecules], -1),
+            train,
+        );
+        let hidden_state = self
+            .final_char_encoder
+            .forward_t(&projected, Some(&char_attention_mask), None, None, train)
+            .hidden_state;
+        let hidden_state = if padding_length > 0 {
+            hidden_state.narrow(1, 0, sequence_length)
+        } else {
+            hidden_state
+        };
+
+        Ok(CanineModelOutput {
+            hidden_state,
+            pooled_output,
+            all_hidden_states: encoder_output.all_hidden_states,
+            all_attentions: encoder_output.all_attentions,
+        })
+    }
+}
+
+/// # CANINE for sequence classification
+/// Base CANINE model with a classifier head to perform sentence or document-level classification
+/// It is made of the following blocks:
+/// - `canine`: Base CanineModel
+/// - `classifier`: linear layer applied to the pooled output
+pub struct CanineForSequenceClassification {
+    canine: CanineModel,
+    dropout: Dropout,
+    classifier: nn::Linear,
+}
+
+impl CanineForSequenceClassification {
+    /// Build a new `CanineForSequenceClassification`
+    ///
+    /// # Arguments
+    ///
+    /// * `p` - Variable store path for the root of the CANINE model
+    /// * `config` - `CanineConfig` object defining the model architecture and number of classes
+    ///
+    /// # Example
+    ///
+    /// ```no_run
+    /// use rust_bert::canine::{CanineConfig, CanineForSequenceClassification};
+    /// use rust_bert::Config;
+    /// use std::path::Path;
+    /// use tch::{nn, Device};
+    ///
+    /// let config_path = Path::new("path/to/config.json");
+    /// let device = Device::Cpu;
+    /// let p = nn::VarStore::new(device);
+    /// let config = CanineConfig::from_file(config_path);
+    /// let canine_model = CanineForSequenceClassification::new(&p.root(), &config);
+    /// ```
+    pub fn new<'p, P>(p: P, config: &CanineConfig) -> CanineForSequenceClassification
+    where
+        P: Borrow<nn::Path<'p>>,
+    {
+        let p = p.borrow();
+
+        let canine = CanineModel::new(p / "canine", config, true);
+        let dropout = Dropout::new(
+            config
+                .classifier_dropout
+                .unwrap_or(config.hidden_dropout_prob),
+        );
+        let num_labels = config
+            .id2label
+            .as_ref()
+            .expect("num_labels not provided in configuration")
+            .len() as i64;
+        let classifier = nn::linear(
+            p / "classifier",
+            config.hidden_size,
+            num_labels,
+            Default::default(),
+        );
+
+        CanineForSequenceClassification {
+            canine,
+            dropout,
+            classifier,
+        }
+    }
+
+    /// Forward pass through the model
+    ///
+    /// # Arguments
+    ///
+    /// * `input_ids` - Optional Unicode code points of shape (*batch size*, *sequence_length*). If None, pre-computed embeddings must be provided (see `input_embeds`)
+    /// * `mask` - Optional mask of shape (*batch size*, *sequence_length*). Masked position have value 0, non-masked value 1. If None set to 1
+    /// * `token_type_ids` - Optional segment id of shape (*batch size*, *sequence_length*). Convention is value of 0 for the first sentence (incl. *SEP*) and 1 for the second sentence. If None set to 0.
+    /// * `position_ids` - Optional position ids of shape (*batch size*, *sequence_length*). If None, will be incremented from 0.
+    /// * `input_embeds` - Optional pre-computed character embeddings of shape (*batch size*, *sequence_length*, *hidden_size*). If None, input ids must be provided (see `input_ids`)
+    /// * `train` - boolean flag to turn on/off the dropout layers in the model. Should be set to false for inference.
+    ///
+    /// # Returns
+    ///
+    /// * `CanineSequenceClassificationOutput` containing:
+    ///   - `logits` - `Tensor` of shape (*batch size*, *num_labels*)
+    ///   - `all_hidden_states` - `Option<Vec<Tensor>>` of length *num_hidden_layers* with shape (*batch size*, *num_molecules*, *hidden_size*)
+    ///   - `all_attentions` - `Option<Vec<Tensor>>` of length *num_hidden_layers* with shape (*batch size*, *num_heads*, *num_molecules*, *num_molecules*)
+    ///
+    /// # Example
+    ///
+    /// ```no_run
+    /// # use rust_bert::canine::{CanineConfig, CanineForSequenceClassification, CanineTokenizer};
+    /// # use tch::{nn, Device, Tensor, no_grad};
+    /// # use rust_bert::Config;
+    /// # use std::path::Path;
+    /// # fn main() -> anyhow::Result<()> {
+    /// # let config_path = Path::new("path/to/config.json");
+    /// # let device = Device::Cpu;
+    /// # let vs = nn::VarStore::new(device);
+    /// # let config = CanineConfig::from_file(config_path);
+    /// # let canine_model = CanineForSequenceClassification::new(&vs.root(), &config);
+    /// let tokenizer = CanineTokenizer::new(2048);
+    /// let (input_ids, mask) = tokenizer.encode_list(&["gr8 movie!!1", "worst. film. evr."], device)?;
+    ///
+    /// let model_output = no_grad(|| {
+    ///     canine_model.forward_t(Some(&input_ids), Some(&mask), None, None, None, false)
+    /// })?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn forward_t(
+        &self,
+        input_ids: Option<&Tensor>,
+        mask: Option<&Tensor>,
+        token_type_ids: Option<&Tensor>,
+        position_ids: Option<&Tensor>,
+        input_embeds: Option<&Tensor>,
+        train: bool,
+    ) -> Result<CanineSequenceClassificationOutput, RustBertError> {
+        let base_model_output = self.canine.forward_t(
+            input_ids,
+            mask,
+            token_type_ids,
+            position_ids,
+            input_embeds,
+            train,
+        )?;
+
+        let logits = base_model_output
+            .pooled_output
+            .unwrap()
+            .apply_t(&self.dropout, train)
+            .apply(&self.classifier);
+
+        Ok(CanineSequenceClassificationOutput {
+            logits,
+            all_hidden_states: base_model_output.all_hidden_states,
+            all_attentions: base_model_output.all_attentions,
+        })
+    }
+}
+
+/// # CANINE for token classification (e.g. NER, POS)
+/// Character-level classification model: a label is predicted for each character of the input (e.g. the
+/// beginning, inside and outside tags of the entities), without depending on a subword segmentation.
+/// It is made of the following blocks:
+/// - `canine`: Base CanineModel
+/// - `classifier`: linear layer for token classification
+pub struct CanineForTokenClassification {
+    canine: CanineModel,
+    dropout: Dropout,
+    classifier: nn::Linear,
+}
+
+impl CanineForTokenClassification {
+    /// Build a new `CanineForTokenClassification`
+    ///
+    /// # Arguments
+    ///
+    /// * `p` - Variable store path for the root of the CANINE model
+    /// * `config` - `CanineConfig` object defining the model architecture and number of classes
+    ///
+    /// # Example
+    ///
+    /// ```no_run
+    /// use rust_bert::canine::{CanineConfig, CanineForTokenClassification};
+    /// use rust_bert::Config;
+    /// use std::path::Path;
+    /// use tch::{nn, Device};
+    ///
+    /// let config_path = Path::new("path/to/config.json");
+    /// let device = Device::Cpu;
+    /// let p = nn::VarStore::new(device);
+    /// let config = CanineConfig::from_file(config_path);
+    /// let canine_model = CanineForTokenClassification::new(&p.root(), &config);
+    /// ```
+    pub fn new<'p, P>(p: P, config: &CanineConfig) -> CanineForTokenClassification
+    where
+        P: Borrow<nn::Path<'p>>,
+    {
+        let p = p.borrow();
+
+        let canine = CanineModel::new(p / "canine", config, false);
+        let dropout = Dropout::new(
+            config
+                .classifier_dropout
+                .unwrap_or(config.hidden_dropout_prob),
+        );
+        let num_labels = config
+            .id2label
+            .as_ref()
+            .expect("num_labels not provided in configuration")
+            .len() as i64;
+        let classifier = nn::linear(
+            p / "classifier",
+            config.hidden_size,
+            num_labels,
+            Default::default(),
+        );
+
+        CanineForTokenClassification {
+            canine,
+            dropout,
+            classifier,
+        }
+    }
+
+    /// Forward pass through the model
+    ///
+    /// # Arguments
+    ///
+    /// * `input_ids` - Optional Unicode code points of shape (*batch size*, *sequence_length*). If None, pre-computed embeddings must be provided (see `input_embeds`)
+    /// * `mask` - Optional mask of shape (*batch size*, *sequence_length*). Masked position have value 0, non-masked value 1. If None set to 1
+    /// * `token_type_ids` - Optional segment id of shape (*batch size*, *sequence_length*). Convention is value of 0 for the first sentence (incl. *SEP*) and 1 for the second sentence. If None set to 0.
+    /// * `position_ids` - Optional position ids of shape (*batch size*, *sequence_length*). If None, will be incremented from 0.
+    /// * `input_embeds` - Optional pre-computed character embeddings of shape (*batch size*, *sequence_length*, *hidden_size*). If None, input ids must be provided (see `input_ids`)
+    /// * `train` - boolean flag to turn on/off the dropout layers in the model. Should be set to false for inference.
+    ///
+    /// # Returns
+    ///
+    /// * `CanineTokenClassificationOutput` containing:
+    ///   - `logits` - `Tensor` of shape (*batch size*, *sequence_length*, *num_labels*) containing the logits for each character of the input
+    ///   - `all_hidden_states` - `Option<Vec<Tensor>>` of length *num_hidden_layers* with shape (*batch size*, *num_molecules*, *hidden_size*)
+    ///   - `all_attentions` - `Option<Vec<Tensor>>` of length *num_hidden_layers* with shape (*batch size*, *num_heads*, *num_molecules*, *num_molecules*)
+    ///
+    /// # Example
+    ///
+    /// ```no_run
+    /// # use rust_bert::canine::{CanineConfig, CanineForTokenClassification, CanineTokenizer};
+    /// # use tch::{nn, Device, Tensor, no_grad};
+    /// # use rust_bert::Config;
+    /// # use std::path::Path;
+    /// # fn main() -> anyhow::Result<()> {
+    /// # let config_path = Path::new("path/to/config.json");
+    /// # let device = Device::Cpu;
+    /// # let vs = nn::VarStore::new(device);
+    /// # let config = CanineConfig::from_file(config_path);
+    /// # let canine_model = CanineForTokenClassification::new(&vs.root(), &config);
+    /// let tokenizer = CanineTokenizer::new(2048);
+    /// let (input_ids, mask) = tokenizer.encode_list(&["mEEting w/ john smith @ berlin tmrw"], device)?;
+    ///
+    /// let model_output = no_grad(|| {
+    ///     canine_model.forward_t(Some(&input_ids), Some(&mask), None, None, None, false)
+    /// })?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn forward_t(
+        &self,
+        input_ids: Option<&Tensor>,
+        mask: Option<&Tensor>,
+        token_type_ids: Option<&Tensor>,
+        position_ids: Option<&Tensor>,
+        input_embeds: Option<&Tensor>,
+        train: bool,
+    ) -> Result<CanineTokenClassificationOutput, RustBertError> {
+        let base_model_output = self.canine.forward_t(
+            input_ids,
+            mask,
+            token_type_ids,
+            position_ids,
+            input_embeds,
+            train,
+        )?;
+
+        let logits = base_model_output
+            .hidden_state
+            .apply_t(&self.dropout, train)
+            .apply(&self.classifier);
+
+        Ok(CanineTokenClassificationOutput {
+            logits,
+            all_hidden_states: base_model_output.all_hidden_states,
+            all_attentions: base_model_output.all_attentions,
+        })
+    }
+}
+
+/// Container for the CANINE model output.
+pub struct CanineModelOutput {
+    /// Last hidden states from the model, one for each character
+    pub hidden_state: Tensor,
+    /// Pooled output (hidden state for the first molecule)
+    pub pooled_output: Option<Tensor>,
+    /// Hidden states for all intermediate layers of the deep (molecule) encoder
+    pub all_hidden_states: Option<Vec<Tensor>>,
+    /// Attention weights for all intermediate layers of the deep (molecule) encoder
+    pub all_attentions: Option<Vec<Tensor>>,
+}
+
+/// Container for the CANINE sequence classification model output.
+pub struct CanineSequenceClassificationOutput {
+    /// Logits for each input (sequence) for each target class
+    pub logits: Tensor,
+    /// Hidden states for all intermediate layers of the deep (molecule) encoder
+    pub all_hidden_states: Option<Vec<Tensor>>,
+    /// Attention weights for all intermediate layers of the deep (molecule) encoder
+    pub all_attentions: Option<Vec<Tensor>>,
+}
+
+/// Container for the CANINE token classification model output.
+pub struct CanineTokenClassificationOutput {
+    /// Logits for each character for each target class
+    pub logits: Tensor,
+    /// Hidden states for all intermediate layers of the deep (molecule) encoder
+    pub all_hidden_states: Option<Vec<Tensor>>,
+    /// Attention weights for all intermediate layers of the deep (molecule) encoder
+    pub all_attentions: Option<Vec<Tensor>>,
+}
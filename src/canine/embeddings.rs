@@ -0,0 +1,153 @@
+// Copyright 2021 Google AI and The HuggingFace Inc. team. All rights reserved.
+// Copyright 2022 Guillaume Becquin
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//     http://www.apache.org/licenses/LICENSE-2.0
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use crate::canine::CanineConfig;
+use crate::common::dropout::Dropout;
+use crate::common::embeddings::get_shape_and_device_from_ids_embeddings_pair;
+use crate::RustBertError;
+use std::borrow::Borrow;
+use tch::{nn, Kind, Tensor};
+
+/// Primes used by the hash functions mapping the Unicode code points to the embedding buckets
+pub(crate) const HASH_PRIMES: [i64; 16] = [
+    31, 43, 59, 61, 73, 97, 103, 113, 137, 149, 157, 173, 181, 193, 211, 223,
+];
+
+/// # CANINE character embeddings
+/// The code points are not looked up in a vocabulary: each code point is hashed by `num_hash_functions` functions
+/// into `num_hash_buckets` buckets, and the embeddings of the buckets (each of size
+/// `hidden_size / num_hash_functions`) are concatenated. Any Unicode character therefore receives an embedding,
+/// shared with the few characters colliding with it for every hash function. Position and token type embeddings
+/// are added as in BERT.
+pub struct CanineEmbeddings {
+    hash_embeddings: Vec<nn::Embedding>,
+    char_position_embeddings: nn::Embedding,
+    token_type_embeddings: nn::Embedding,
+    layer_norm: nn::LayerNorm,
+    dropout: Dropout,
+    num_hash_buckets: i64,
+}
+
+impl CanineEmbeddings {
+    pub fn new<'p, P>(p: P, config: &CanineConfig) -> CanineEmbeddings
+    where
+        P: Borrow<nn::Path<'p>>,
+    {
+        let p = p.borrow();
+        let shard_embedding_size = config.hidden_size / config.num_hash_functions;
+
+        let hash_embeddings = (0..config.num_hash_functions)
+            .map(|hash_index| {
+                nn::embedding(
+                    p / format!("HashBucketCodepointEmbedder_{}", hash_index),
+                    config.num_hash_buckets,
+                    shard_embedding_size,
+                    Default::default(),
+                )
+            })
+            .collect();
+        // The position embeddings of the reference implementation have one entry per hash bucket
+        let char_position_embeddings = nn::embedding(
+            p / "char_position_embeddings",
+            config.num_hash_buckets,
+            config.hidden_size,
+            Default::default(),
+        );
+        let token_type_embeddings = nn::embedding(
+            p / "token_type_embeddings",
+            config.type_vocab_size,
+            config.hidden_size,
+            Default::default(),
+        );
+        let layer_norm = nn::layer_norm(
+            p / "LayerNorm",
+            vec![config.hidden_size],
+            nn::LayerNormConfig {
+                eps: config.layer_norm_eps,
+                ..Default::default()
+            },
+        );
+        let dropout = Dropout::new(config.hidden_dropout_prob);
+
+        CanineEmbeddings {
+            hash_embeddings,
+            char_position_embeddings,
+            token_type_embeddings,
+            layer_norm,
+            dropout,
+            num_hash_buckets: config.num_hash_buckets,
+        }
+    }
+
+    /// Embeds the code points by concatenating the embeddings of their hash buckets
+    fn embed_hash_buckets(&self, input_ids: &Tensor) -> Tensor {
+        let shard_embeddings = self
+            .hash_embeddings
+            .iter()
+            .zip(HASH_PRIMES.iter())
+            .map(|(embeddings, prime)| {
+                ((input_ids + 1) * *prime)
+                    .remainder(self.num_hash_buckets)
+                    .apply(embeddings)
+            })
+            .collect::<Vec<Tensor>>();
+        Tensor::cat(&shard_embeddings, -1)
+    }
+
+    /// Forward pass through the embedding layer
+    ///
+    /// # Arguments
+    ///
+    /// * `input_ids` - Optional Unicode code points of shape (*batch size*, *sequence_length*). If None, pre-computed embeddings must be provided (see `input_embeds`)
+    /// * `token_type_ids` - Optional segment id of shape (*batch size*, *sequence_length*). If None set to 0.
+    /// * `position_ids` - Optional position ids of shape (*batch size*, *sequence_length*). If None, will be incremented from 0.
+    /// * `input_embeds` - Optional pre-computed character embeddings of shape (*batch size*, *sequence_length*, *hidden_size*). If None, input ids must be provided (see `input_ids`)
+    /// * `train` - boolean flag to turn on/off the dropout layers in the model. Should be set to false for inference.
+    ///
+    /// # Returns
+    ///
+    /// * `Tensor` character embeddings of shape (*batch size*, *sequence_length*, *hidden_size*)
+    pub fn forward_t(
+        &self,
+        input_ids: Option<&Tensor>,
+        token_type_ids: Option<&Tensor>,
+        position_ids: Option<&Tensor>,
+        input_embeds: Option<&Tensor>,
+        train: bool,
+    ) -> Result<Tensor, RustBertError> {
+        let (input_shape, device) =
+            get_shape_and_device_from_ids_embeddings_pair(input_ids, input_embeds)?;
+        let input_embeddings = match input_ids {
+            Some(input_ids) => self.embed_hash_buckets(input_ids),
+            None => input_embeds.unwrap().shallow_clone(),
+        };
+        let sequence_length = input_shape[1];
+
+        let position_ids = match position_ids {
+            Some(value) => value.shallow_clone(),
+            None => Tensor::arange(sequence_length, (Kind::Int64, device))
+                .unsqueeze(0)
+                .expand(&input_shape, true),
+        };
+        let token_type_ids = match token_type_ids {
+            Some(value) => value.shallow_clone(),
+            None => Tensor::zeros(&input_shape, (Kind::Int64, device)),
+        };
+
+        let embeddings = input_embeddings
+            + position_ids.apply(&self.char_position_embeddings)
+            + token_type_ids.apply(&self.token_type_embeddings);
+        Ok(embeddings
+            .apply(&self.layer_norm)
+            .apply_t(&self.dropout, train))
+    }
+}
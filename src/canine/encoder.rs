@@ -0,0 +1,208 @@
+// Copyright 2021 Google AI and The HuggingFace Inc. team. All rights reserved.
+// Copyright 2022 Guillaume Becquin
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//     http://www.apache.org/licenses/LICENSE-2.0
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use crate::bert::attention::{BertIntermediate, BertOutput, BertSelfAttention, BertSelfOutput};
+use crate::bert::BertConfig;
+use crate::canine::CanineConfig;
+use crate::common::activations::TensorFunction;
+use crate::common::dropout::Dropout;
+use std::borrow::Borrow;
+use tch::nn::ConvConfig;
+use tch::{nn, Tensor};
+
+/// # CANINE local transformer layer
+/// Transformer layer processing the character sequence, in which each character only attends to the characters
+/// of its chunk of `local_transformer_stride` characters. The cost of the attention therefore grows linearly with
+/// the number of characters.
+pub struct CanineLocalLayer {
+    self_attention: BertSelfAttention,
+    self_output: BertSelfOutput,
+    intermediate: BertIntermediate,
+    output: BertOutput,
+    chunk_size: i64,
+}
+
+impl CanineLocalLayer {
+    pub fn new<'p, P>(p: P, config: &CanineConfig, bert_config: &BertConfig) -> CanineLocalLayer
+    where
+        P: Borrow<nn::Path<'p>>,
+    {
+        let p = p.borrow();
+        let p_attention = p / "attention";
+
+        let self_attention = BertSelfAttention::new(&p_attention / "self", bert_config);
+        let self_output = BertSelfOutput::new(&p_attention / "output", bert_config);
+        let intermediate = BertIntermediate::new(p / "intermediate", bert_config);
+        let output = BertOutput::new(p / "output", bert_config);
+
+        CanineLocalLayer {
+            self_attention,
+            self_output,
+            intermediate,
+            output,
+            chunk_size: config.local_transformer_stride,
+        }
+    }
+
+    /// Forward pass through the layer
+    ///
+    /// # Arguments
+    ///
+    /// * `hidden_states` - input tensor of shape (*batch size*, *sequence_length*, *hidden_size*).
+    /// * `mask` - Additive attention mask of shape (*batch size*, 1, 1, *sequence_length*), with large negative values for the padding positions.
+    /// * `train` - boolean flag to turn on/off the dropout layers in the model. Should be set to false for inference.
+    pub fn forward_t(&self, hidden_states: &Tensor, mask: &Tensor, train: bool) -> Tensor {
+        let sequence_length = hidden_states.size()[1];
+        let contexts = (0..sequence_length)
+            .step_by(self.chunk_size as usize)
+            .map(|chunk_start| {
+                let chunk_length = self.chunk_size.min(sequence_length - chunk_start);
+                let hidden_states_chunk = hidden_states.narrow(1, chunk_start, chunk_length);
+                let mask_chunk = mask.narrow(-1, chunk_start, chunk_length);
+                self.self_attention
+                    .forward_t(&hidden_states_chunk, Some(&mask_chunk), None, None, train)
+                    .0
+            })
+            .collect::<Vec<Tensor>>();
+        let attention_output =
+            self.self_output
+                .forward_t(&Tensor::cat(&contexts, 1), hidden_states, train);
+
+        self.output.forward_t(
+            &self.intermediate.forward(&attention_output),
+            &attention_output,
+            train,
+        )
+    }
+}
+
+/// # CANINE downsampling layer
+/// Strided convolution turning the character sequence into a shorter sequence of "molecules" (one every
+/// `downsampling_rate` characters), processed by the deep transformer stack. The first character (*CLS*) is kept
+/// as the first molecule.
+pub struct CanineCharactersToMolecules {
+    conv: nn::Conv1D,
+    activation: TensorFunction,
+    layer_norm: nn::LayerNorm,
+}
+
+impl CanineCharactersToMolecules {
+    pub fn new<'p, P>(p: P, config: &CanineConfig) -> CanineCharactersToMolecules
+    where
+        P: Borrow<nn::Path<'p>>,
+    {
+        let p = p.borrow();
+
+        let conv = nn::conv1d(
+            p / "conv",
+            config.hidden_size,
+            config.hidden_size,
+            config.downsampling_rate,
+            ConvConfig {
+                stride: config.downsampling_rate,
+                ..Default::default()
+            },
+        );
+        let layer_norm = nn::layer_norm(
+            p / "LayerNorm",
+            vec![config.hidden_size],
+            nn::LayerNormConfig {
+                eps: config.layer_norm_eps,
+                ..Default::default()
+            },
+        );
+
+        CanineCharactersToMolecules {
+            conv,
+            activation: config.hidden_act.get_function(),
+            layer_norm,
+        }
+    }
+
+    /// Downsamples a character encoding of shape (*batch size*, *sequence_length*, *hidden_size*) to a molecule
+    /// encoding of shape (*batch size*, *sequence_length* / *downsampling_rate*, *hidden_size*)
+    pub fn forward(&self, char_encoding: &Tensor) -> Tensor {
+        let cls_encoding = char_encoding.narrow(1, 0, 1);
+        let downsampled = char_encoding
+            .transpose(1, 2)
+            .apply(&self.conv)
+            .transpose(1, 2);
+        let downsampled = self.activation.get_fn()(&downsampled);
+        // The last molecule is dropped to make room for the CLS molecule
+        let num_molecules = downsampled.size()[1];
+        Tensor::cat(
+            &[cls_encoding, downsampled.narrow(1, 0, num_molecules - 1)],
+            1,
+        )
+        .apply(&self.layer_norm)
+    }
+}
+
+/// # CANINE upsampling projection
+/// Convolution projecting the concatenation of the initial character encoding and of the repeated molecule
+/// encoding back to the hidden size, keeping the length of the character sequence.
+pub struct CanineConvProjection {
+    conv: nn::Conv1D,
+    activation: TensorFunction,
+    layer_norm: nn::LayerNorm,
+    dropout: Dropout,
+    kernel_size: i64,
+}
+
+impl CanineConvProjection {
+    pub fn new<'p, P>(p: P, config: &CanineConfig) -> CanineConvProjection
+    where
+        P: Borrow<nn::Path<'p>>,
+    {
+        let p = p.borrow();
+
+        let conv = nn::conv1d(
+            p / "conv",
+            config.hidden_size * 2,
+            config.hidden_size,
+            config.upsampling_kernel_size,
+            Default::default(),
+        );
+        let layer_norm = nn::layer_norm(
+            p / "LayerNorm",
+            vec![config.hidden_size],
+            nn::LayerNormConfig {
+                eps: config.layer_norm_eps,
+                ..Default::default()
+            },
+        );
+
+        CanineConvProjection {
+            conv,
+            activation: config.hidden_act.get_function(),
+            layer_norm,
+            dropout: Dropout::new(config.hidden_dropout_prob),
+            kernel_size: config.upsampling_kernel_size,
+        }
+    }
+
+    /// Projects an input of shape (*batch size*, *sequence_length*, 2 x *hidden_size*) to
+    /// (*batch size*, *sequence_length*, *hidden_size*)
+    pub fn forward_t(&self, input: &Tensor, train: bool) -> Tensor {
+        // "Same" padding of the convolution
+        let pad_total = self.kernel_size - 1;
+        let pad_begin = pad_total / 2;
+        let projected = input
+            .transpose(1, 2)
+            .constant_pad_nd(&[pad_begin, pad_total - pad_begin])
+            .apply(&self.conv)
+            .transpose(1, 2);
+        self.activation.get_fn()(&projected)
+            .apply(&self.layer_norm)
+            .apply_t(&self.dropout, train)
+    }
+}
@@ -0,0 +1,62 @@
+//! # CANINE: Pre-training an Efficient Tokenization-Free Encoder for Language Representation (Clark et al.)
+//!
+//! Implementation of the CANINE language model ([CANINE: Pre-training an Efficient Tokenization-Free Encoder for Language Representation](https://arxiv.org/abs/2103.06874) Clark, Garrette, Turc, Wieting, 2021).
+//! CANINE operates directly on the Unicode code points of the text instead of subword tokens: the code points are embedded with multiple hash functions,
+//! contextualized by a local transformer layer and downsampled by a strided convolution to a 4 times shorter sequence of "molecules" processed by the deep transformer stack.
+//! The molecules are then upsampled back to one hidden state per character. Without a vocabulary, misspellings, unusual casing, emojis or rare scripts of noisy,
+//! multilingual user-generated text never fall back to unknown or over-fragmented tokens.
+//! The base model is implemented in the `canine_model::CanineModel` struct. Several language model heads have also been implemented, including:
+//! - Sequence classification: `canine_model::CanineForSequenceClassification`
+//! - Token (character) classification: `canine_model::CanineForTokenClassification`
+//!
+//! # Model set-up and pre-trained weights loading
+//!
+//! All models expect the following resources:
+//! - Configuration file expected to have a structure following the [Transformers library](https://github.com/huggingface/transformers)
+//! - Model weights are expected to have a structure and parameter names following the [Transformers library](https://github.com/huggingface/transformers). A conversion using the Python utility scripts is required to convert the `.bin` weights to the `.ot` format.
+//!
+//! No vocabulary is required: the `CanineTokenizer` encodes the texts as `[CLS] code points [SEP]`. No pre-trained checkpoint is hosted with the crate, the resources are provided as local files:
+//!
+//! ```no_run
+//! # fn main() -> anyhow::Result<()> {
+//! #
+//! use tch::{nn, no_grad, Device};
+//! # use std::path::PathBuf;
+//! use rust_bert::canine::{CanineConfig, CanineForSequenceClassification, CanineTokenizer};
+//! use rust_bert::resources::{LocalResource, ResourceProvider};
+//! use rust_bert::Config;
+//!
+//! let config_resource = LocalResource {
+//!     local_path: PathBuf::from("path/to/config.json"),
+//! };
+//! let weights_resource = LocalResource {
+//!     local_path: PathBuf::from("path/to/model.ot"),
+//! };
+//! let config_path = config_resource.get_local_path()?;
+//! let weights_path = weights_resource.get_local_path()?;
+//!
+//! let device = Device::cuda_if_available();
+//! let mut vs = nn::VarStore::new(device);
+//! let config = CanineConfig::from_file(config_path);
+//! let canine_model = CanineForSequenceClassification::new(&vs.root(), &config);
+//! vs.load(weights_path)?;
+//!
+//! let tokenizer = CanineTokenizer::new(2048);
+//! let (input_ids, mask) = tokenizer.encode_list(&["this movie was sooo gooood 😍"], device)?;
+//! let output = no_grad(|| {
+//!     canine_model.forward_t(Some(&input_ids), Some(&mask), None, None, None, false)
+//! })?;
+//! # Ok(())
+//! # }
+//! ```
+
+mod canine_model;
+mod embeddings;
+mod encoder;
+mod tokenization;
+
+pub use canine_model::{
+    CanineConfig, CanineForSequenceClassification, CanineForTokenClassification, CanineModel,
+    CanineModelOutput, CanineSequenceClassificationOutput, CanineTokenClassificationOutput,
+};
+pub use tokenization::{CanineTokenizer, CANINE_CLS_ID, CANINE_MASK_ID, CANINE_PAD_ID, CANINE_SEP_ID};
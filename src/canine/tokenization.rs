@@ -0,0 +1,143 @@
+// Copyright 2021 Google AI and The HuggingFace Inc. team. All rights reserved.
+// Copyright 2022 Guillaume Becquin
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//     http://www.apache.org/licenses/LICENSE-2.0
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use crate::RustBertError;
+use std::convert::TryFrom;
+use tch::{Device, Tensor};
+
+/// Padding id (code point 0)
+pub const CANINE_PAD_ID: i64 = 0;
+/// Code point of the *CLS* token (first code point of the Unicode private use area)
+pub const CANINE_CLS_ID: i64 = 0xE000;
+/// Code point of the *SEP* token
+pub const CANINE_SEP_ID: i64 = 0xE001;
+/// Code point of the *MASK* token
+pub const CANINE_MASK_ID: i64 = 0xE003;
+
+/// # CANINE character tokenizer
+/// CANINE does not rely on a vocabulary: the text is encoded as the sequence of its Unicode code points,
+/// wrapped in the *CLS* and *SEP* special code points (taken from the Unicode private use area).
+/// Misspellings, unusual casing, emojis or scripts unseen during pre-training never map to unknown tokens.
+pub struct CanineTokenizer {
+    max_length: usize,
+}
+
+impl CanineTokenizer {
+    /// Build a new `CanineTokenizer`
+    ///
+    /// # Arguments
+    ///
+    /// * `max_length` - Maximum number of code points of an encoded text, including the special code points. Longer texts are truncated.
+    pub fn new(max_length: usize) -> CanineTokenizer {
+        assert!(
+            max_length >= 2,
+            "The maximum length must leave room for the special tokens"
+        );
+        CanineTokenizer { max_length }
+    }
+
+    /// Encodes a text as `[CLS] code points [SEP]`, truncated to the maximum length
+    pub fn encode(&self, text: &str) -> Vec<i64> {
+        let mut ids = Vec::with_capacity(text.len().min(self.max_length));
+        ids.push(CANINE_CLS_ID);
+        ids.extend(
+            text.chars()
+                .take(self.max_length - 2)
+                .map(|character| character as i64),
+        );
+        ids.push(CANINE_SEP_ID);
+        ids
+    }
+
+    /// Encodes a batch of texts
+    ///
+    /// # Arguments
+    ///
+    /// * `texts` - Texts to encode
+    /// * `device` - Device on which the tensors are created
+    ///
+    /// # Returns
+    ///
+    /// * `(Tensor, Tensor)` code points and attention mask, of shape (*batch size*, *sequence_length*). The sequences are right-padded with `CANINE_PAD_ID`.
+    pub fn encode_list<S>(
+        &self,
+        texts: &[S],
+        device: Device,
+    ) -> Result<(Tensor, Tensor), RustBertError>
+    where
+        S: AsRef<str>,
+    {
+        if texts.is_empty() {
+            return Err(RustBertError::ValueError(
+                "At least one text must be provided".into(),
+            ));
+        }
+        let encoded = texts
+            .iter()
+            .map(|text| self.encode(text.as_ref()))
+            .collect::<Vec<Vec<i64>>>();
+        let max_length = encoded.iter().map(|ids| ids.len()).max().unwrap();
+
+        let (input_ids, masks): (Vec<Tensor>, Vec<Tensor>) = encoded
+            .iter()
+            .map(|ids| {
+                let padding = vec![CANINE_PAD_ID; max_length - ids.len()];
+                let mut mask = vec![1; ids.len()];
+                mask.extend(&padding);
+                (
+                    Tensor::of_slice(&[ids.as_slice(), padding.as_slice()].concat()),
+                    Tensor::of_slice(&mask),
+                )
+            })
+            .unzip();
+
+        Ok((
+            Tensor::stack(&input_ids, 0).to(device),
+            Tensor::stack(&masks, 0).to(device),
+        ))
+    }
+
+    /// Decodes a sequence of code points, skipping the special and padding code points
+    pub fn decode(&self, ids: &[i64]) -> String {
+        ids.iter()
+            .filter(|id| {
+                ![CANINE_PAD_ID, CANINE_CLS_ID, CANINE_SEP_ID, CANINE_MASK_ID].contains(id)
+            })
+            .filter_map(|id| u32::try_from(*id).ok().and_then(char::from_u32))
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn encode_decode_round_trip() -> anyhow::Result<()> {
+        let tokenizer = CanineTokenizer::new(8);
+        let ids = tokenizer.encode("héllo 👋");
+        assert_eq!(
+            ids,
+            vec![CANINE_CLS_ID, 104, 233, 108, 108, 111, 32, CANINE_SEP_ID]
+        );
+        assert_eq!(tokenizer.decode(&ids), "héllo ");
+
+        let (input_ids, mask) = tokenizer.encode_list(&["ok", "héllo 👋"], Device::Cpu)?;
+        assert_eq!(input_ids.size(), vec![2, 8]);
+        assert_eq!(Vec::<i64>::from(&mask.get(0)), vec![1, 1, 1, 1, 0, 0, 0, 0]);
+        assert_eq!(
+            Vec::<i64>::from(&input_ids.get(0)),
+            vec![CANINE_CLS_ID, 111, 107, CANINE_SEP_ID, 0, 0, 0, 0]
+        );
+        Ok(())
+    }
+}
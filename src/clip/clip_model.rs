@@ -0,0 +1,620 @@
+// Copyright 2021 The Open AI Team Authors and The HuggingFace Inc. team. All rights reserved.
+// Copyright 2022 Guillaume Becquin
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//     http://www.apache.org/licenses/LICENSE-2.0
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use crate::clip::encoder::{ClipEncoder, ClipEncoderConfig};
+use crate::common::activations::Activation;
+use crate::{Config, RustBertError};
+use serde::{Deserialize, Serialize};
+use std::borrow::Borrow;
+use tch::nn::{ConvConfig, EmbeddingConfig, Init, LinearConfig};
+use tch::{nn, Kind, Tensor};
+
+/// Per-channel mean used to normalize the images of the CLIP checkpoints
+pub const CLIP_IMAGE_MEAN: [f64; 3] = [0.48145466, 0.4578275, 0.40821073];
+/// Per-channel standard deviation used to normalize the images of the CLIP checkpoints
+pub const CLIP_IMAGE_STD: [f64; 3] = [0.26862954, 0.26130258, 0.27577711];
+
+/// # CLIP Pretrained model config files
+pub struct ClipConfigResources;
+
+/// # CLIP Pretrained model vocab files
+pub struct ClipVocabResources;
+
+/// # CLIP Pretrained model merges files
+pub struct ClipMergesResources;
+
+impl ClipConfigResources {
+    /// Shared under MIT license by the OpenAI team at <https://huggingface.co/openai/clip-vit-base-patch32>. Modified with conversion to C-array format.
+    pub const CLIP_VIT_BASE_PATCH32: (&'static str, &'static str) = (
+        "clip-vit-base-patch32/config",
+        "https://huggingface.co/openai/clip-vit-base-patch32/resolve/main/config.json",
+    );
+}
+
+impl ClipVocabResources {
+    /// Shared under MIT license by the OpenAI team at <https://huggingface.co/openai/clip-vit-base-patch32>. Modified with conversion to C-array format.
+    pub const CLIP_VIT_BASE_PATCH32: (&'static str, &'static str) = (
+        "clip-vit-base-patch32/vocab",
+        "https://huggingface.co/openai/clip-vit-base-patch32/resolve/main/vocab.json",
+    );
+}
+
+impl ClipMergesResources {
+    /// Shared under MIT license by the OpenAI team at <https://huggingface.co/openai/clip-vit-base-patch32>. Modified with conversion to C-array format.
+    pub const CLIP_VIT_BASE_PATCH32: (&'static str, &'static str) = (
+        "clip-vit-base-patch32/merges",
+        "https://huggingface.co/openai/clip-vit-base-patch32/resolve/main/merges.txt",
+    );
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+#[serde(default)]
+/// # CLIP text encoder configuration
+pub struct ClipTextConfig {
+    pub vocab_size: i64,
+    pub hidden_size: i64,
+    pub intermediate_size: i64,
+    pub num_hidden_layers: i64,
+    pub num_attention_heads: i64,
+    pub max_position_embeddings: i64,
+    pub hidden_act: Activation,
+    pub layer_norm_eps: f64,
+    pub attention_dropout: f64,
+    /// Id of the end of text token, whose final hidden state is the text embedding. Older configurations set an
+    /// invalid value of 2, in which case the position of the highest token id (the end of text token) is used.
+    pub eos_token_id: Option<i64>,
+}
+
+impl Default for ClipTextConfig {
+    fn default() -> Self {
+        ClipTextConfig {
+            vocab_size: 49408,
+            hidden_size: 512,
+            intermediate_size: 2048,
+            num_hidden_layers: 12,
+            num_attention_heads: 8,
+            max_position_embeddings: 77,
+            hidden_act: Activation::quick_gelu,
+            layer_norm_eps: 1e-5,
+            attention_dropout: 0.0,
+            eos_token_id: Some(49407),
+        }
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+#[serde(default)]
+/// # CLIP vision encoder configuration
+pub struct ClipVisionConfig {
+    pub hidden_size: i64,
+    pub intermediate_size: i64,
+    pub num_hidden_layers: i64,
+    pub num_attention_heads: i64,
+    pub num_channels: i64,
+    pub image_size: i64,
+    pub patch_size: i64,
+    pub hidden_act: Activation,
+    pub layer_norm_eps: f64,
+    pub attention_dropout: f64,
+}
+
+impl Default for ClipVisionConfig {
+    fn default() -> Self {
+        ClipVisionConfig {
+            hidden_size: 768,
+            intermediate_size: 3072,
+            num_hidden_layers: 12,
+            num_attention_heads: 12,
+            num_channels: 3,
+            image_size: 224,
+            patch_size: 32,
+            hidden_act: Activation::quick_gelu,
+            layer_norm_eps: 1e-5,
+            attention_dropout: 0.0,
+        }
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+/// # CLIP model configuration
+/// Defines the architecture of the text and vision towers and the dimension of the shared embedding space
+pub struct ClipConfig {
+    #[serde(default)]
+    pub text_config: ClipTextConfig,
+    #[serde(default)]
+    pub vision_config: ClipVisionConfig,
+    /// Dimension of the shared embedding space
+    pub projection_dim: i64,
+    /// Initial value of the log of the logits temperature
+    pub logit_scale_init_value: Option<f64>,
+}
+
+impl Config for ClipConfig {}
+
+impl Default for ClipConfig {
+    fn default() -> Self {
+        ClipConfig {
+            text_config: ClipTextConfig::default(),
+            vision_config: ClipVisionConfig::default(),
+            projection_dim: 512,
+            logit_scale_init_value: Some(2.6592),
+        }
+    }
+}
+
+impl From<&ClipTextConfig> for ClipEncoderConfig {
+    fn from(config: &ClipTextConfig) -> Self {
+        ClipEncoderConfig {
+            hidden_size: config.hidden_size,
+            intermediate_size: config.intermediate_size,
+            num_hidden_layers: config.num_hidden_layers,
+            num_attention_heads: config.num_attention_heads,
+            hidden_act: config.hidden_act,
+            layer_norm_eps: config.layer_norm_eps,
+            attention_dropout: config.attention_dropout,
+        }
+    }
+}
+
+impl From<&ClipVisionConfig> for ClipEncoderConfig {
+    fn from(config: &ClipVisionConfig) -> Self {
+        ClipEncoderConfig {
+            hidden_size: config.hidden_size,
+            intermediate_size: config.intermediate_size,
+            num_hidden_layers: config.num_hidden_layers,
+            num_attention_heads: config.num_attention_heads,
+            hidden_act: config.hidden_act,
+            layer_norm_eps: config.layer_norm_eps,
+            attention_dropout: config.attention_dropout,
+        }
+    }
+}
+
+/// # CLIP text encoder
+/// Causal transformer over the token sequence. The text is represented by the final hidden state of the end of text
+/// token.
+pub struct ClipTextTransformer {
+    token_embedding: nn::Embedding,
+    position_embedding: nn::Embedding,
+    encoder: ClipEncoder,
+    final_layer_norm: nn::LayerNorm,
+    max_position_embeddings: i64,
+    eos_token_id: Option<i64>,
+}
+
+impl ClipTextTransformer {
+    pub fn new<'p, P>(p: P, config: &ClipTextConfig) -> ClipTextTransformer
+    where
+        P: Borrow<nn::Path<'p>>,
+    {
+        let p = p.borrow();
+
+        let token_embedding = nn::embedding(
+            p / "embeddings" / "token_embedding",
+            config.vocab_size,
+            config.hidden_size,
+            EmbeddingConfig::default(),
+        );
+        let position_embedding = nn::embedding(
+            p / "embeddings" / "position_embedding",
+            config.max_position_embeddings,
+            config.hidden_size,
+            EmbeddingConfig::default(),
+        );
+        let encoder = ClipEncoder::new(p / "encoder", &ClipEncoderConfig::from(config));
+        let final_layer_norm = nn::layer_norm(
+            p / "final_layer_norm",
+            vec![config.hidden_size],
+            nn::LayerNormConfig {
+                eps: config.layer_norm_eps,
+                ..Default::default()
+            },
+        );
+
+        ClipTextTransformer {
+            token_embedding,
+            position_embedding,
+            encoder,
+            final_layer_norm,
+            max_position_embeddings: config.max_position_embeddings,
+            eos_token_id: config
+                .eos_token_id
+                .filter(|eos_token_id| *eos_token_id != 2),
+        }
+    }
+
+    /// Forward pass through the text encoder
+    ///
+    /// # Arguments
+    ///
+    /// * `input_ids` - Input tensor of shape (*batch size*, *sequence_length*), starting with the start of text token
+    /// and containing an end of text token
+    /// * `attention_mask` - Optional mask of shape (*batch size*, *sequence_length*). Masked positions have value 0, non-masked value 1.
+    /// * `train` - boolean flag to turn on/off the dropout layers in the model. Should be set to false for inference.
+    ///
+    /// # Returns
+    ///
+    /// * `Tensor` of shape (*batch size*, *hidden_size*): final hidden state of the end of text token
+    pub fn forward_t(
+        &self,
+        input_ids: &Tensor,
+        attention_mask: Option<&Tensor>,
+        train: bool,
+    ) -> Result<Tensor, RustBertError> {
+        let (batch_size, sequence_length) = (input_ids.size()[0], input_ids.size()[1]);
+        if sequence_length > self.max_position_embeddings {
+            return Err(RustBertError::InputTooLongError(format!(
+                "CLIP text inputs are limited to {} tokens, got {}",
+                self.max_position_embeddings, sequence_length
+            )));
+        }
+        let device = input_ids.device();
+        let position_ids = Tensor::arange(sequence_length, (Kind::Int64, device));
+        let hidden_states =
+            input_ids.apply(&self.token_embedding) + position_ids.apply(&self.position_embedding);
+
+        let causal_mask = Tensor::ones(&[sequence_length, sequence_length], (Kind::Bool, device))
+            .triu(1)
+            .view([1, 1, sequence_length, sequence_length]);
+        let mask = match attention_mask {
+            Some(attention_mask) => causal_mask.logical_or(&attention_mask.eq(0).view([
+                batch_size,
+                1,
+                1,
+                sequence_length,
+            ])),
+            None => causal_mask,
+        };
+        let mask = Tensor::zeros(&mask.size(), (hidden_states.kind(), device))
+            .masked_fill(&mask, f64::NEG_INFINITY);
+
+        let hidden_states = self
+            .encoder
+            .forward_t(&hidden_states, Some(&mask), train)
+            .apply(&self.final_layer_norm);
+
+        let eos_positions = match self.eos_token_id {
+            Some(eos_token_id) => input_ids
+                .eq(eos_token_id)
+                .to_kind(Kind::Int64)
+                .argmax(-1, false),
+            None => input_ids.argmax(-1, false),
+        };
+        let hidden_size = hidden_states.size()[2];
+        Ok(hidden_states
+            .gather(
+                1,
+                &eos_positions
+                    .view([batch_size, 1, 1])
+                    .expand(&[batch_size, 1, hidden_size], true),
+                false,
+            )
+            .squeeze_dim(1))
+    }
+}
+
+/// # CLIP vision encoder
+/// Transformer over the image patches and a class embedding. The image is represented by the final hidden state of
+/// the class embedding.
+pub struct ClipVisionTransformer {
+    class_embedding: Tensor,
+    patch_embedding: nn::Conv2D,
+    position_embedding: nn::Embedding,
+    pre_layernorm: nn::LayerNorm,
+    encoder: ClipEncoder,
+    post_layernorm: nn::LayerNorm,
+    image_size: i64,
+    num_channels: i64,
+}
+
+impl ClipVisionTransformer {
+    pub fn new<'p, P>(p: P, config: &ClipVisionConfig) -> ClipVisionTransformer
+    where
+        P: Borrow<nn::Path<'p>>,
+    {
+        let p = p.borrow();
+
+        let class_embedding = (p / "embeddings").var(
+            "class_embedding",
+            &[config.hidden_size],
+            Init::Randn {
+                mean: 0.0,
+                stdev: 1.0,
+            },
+        );
+        let patch_embedding = nn::conv2d(
+            p / "embeddings" / "patch_embedding",
+            config.num_channels,
+            config.hidden_size,
+            config.patch_size,
+            ConvConfig {
+                stride: config.patch_size,
+                bias: false,
+                ..Default::default()
+            },
+        );
+        let num_positions = (config.image_size / config.patch_size).pow(2) + 1;
+        let position_embedding = nn::embedding(
+            p / "embeddings" / "position_embedding",
+            num_positions,
+            config.hidden_size,
+            EmbeddingConfig::default(),
+        );
+        let layer_norm_config = nn::LayerNormConfig {
+            eps: config.layer_norm_eps,
+            ..Default::default()
+        };
+        // The parameter name of the pre-encoder layer normalization is misspelled in the original checkpoints
+        let pre_layernorm = nn::layer_norm(
+            p / "pre_layrnorm",
+            vec![config.hidden_size],
+            layer_norm_config,
+        );
+        let encoder = ClipEncoder::new(p / "encoder", &ClipEncoderConfig::from(config));
+        let post_layernorm = nn::layer_norm(
+            p / "post_layernorm",
+            vec![config.hidden_size],
+            layer_norm_config,
+        );
+
+        ClipVisionTransformer {
+            class_embedding,
+            patch_embedding,
+            position_embedding,
+            pre_layernorm,
+            encoder,
+            post_layernorm,
+            image_size: config.image_size,
+            num_channels: config.num_channels,
+        }
+    }
+
+    /// Forward pass through the vision encoder
+    ///
+    /// # Arguments
+    ///
+    /// * `pixel_values` - Normalized images of shape (*batch size*, *num_channels*, *image_size*, *image_size*)
+    /// * `train` - boolean flag to turn on/off the dropout layers in the model. Should be set to false for inference.
+    ///
+    /// # Returns
+    ///
+    /// * `Tensor` of shape (*batch size*, *hidden_size*): final hidden state of the class embedding
+    pub fn forward_t(&self, pixel_values: &Tensor, train: bool) -> Result<Tensor, RustBertError> {
+        let input_shape = pixel_values.size();
+        if input_shape.len() != 4
+            || input_shape[1] != self.num_channels
+            || input_shape[2] != self.image_size
+            || input_shape[3] != self.image_size
+        {
+            return Err(RustBertError::ValueError(format!(
+                "Expected pixel values of shape (batch size, {}, {}, {}), got {:?}",
+                self.num_channels, self.image_size, self.image_size, input_shape
+            )));
+        }
+        let batch_size = input_shape[0];
+        let patch_embeddings = pixel_values
+            .apply(&self.patch_embedding)
+            .flatten(2, -1)
+            .transpose(1, 2);
+        let class_embeddings = self
+            .class_embedding
+            .view([1, 1, -1])
+            .expand(&[batch_size, 1, -1], true);
+        let embeddings = Tensor::cat(&[class_embeddings, patch_embeddings], 1);
+        let position_ids = Tensor::arange(embeddings.size()[1], (Kind::Int64, embeddings.device()));
+        let hidden_states =
+            (embeddings + position_ids.apply(&self.position_embedding)).apply(&self.pre_layernorm);
+
+        let hidden_states = self.encoder.forward_t(&hidden_states, None, train);
+        Ok(hidden_states.select(1, 0).apply(&self.post_layernorm))
+    }
+}
+
+/// # CLIP model
+/// Dual encoder embedding texts and images in a shared space. It is made of the following blocks:
+/// - `text_model`: text encoder
+/// - `vision_model`: vision encoder
+/// - `text_projection` and `visual_projection`: linear projections to the shared embedding space
+/// - `logit_scale`: log of the inverse temperature of the image-text similarity logits
+pub struct ClipModel {
+    text_model: ClipTextTransformer,
+    vision_model: ClipVisionTransformer,
+    text_projection: nn::Linear,
+    visual_projection: nn::Linear,
+    logit_scale: Tensor,
+}
+
+impl ClipModel {
+    /// Build a new `ClipModel`
+    ///
+    /// # Arguments
+    ///
+    /// * `p` - Variable store path for the root of the CLIP model
+    /// * `config` - `ClipConfig` object defining the model architecture
+    ///
+    /// # Example
+    ///
+    /// ```no_run
+    /// use rust_bert::clip::{ClipConfig, ClipModel};
+    /// use rust_bert::Config;
+    /// use std::path::Path;
+    /// use tch::{nn, Device};
+    ///
+    /// let config_path = Path::new("path/to/config.json");
+    /// let device = Device::Cpu;
+    /// let p = nn::VarStore::new(device);
+    /// let config = ClipConfig::from_file(config_path);
+    /// let clip_model = ClipModel::new(&p.root(), &config);
+    /// ```
+    pub fn new<'p, P>(p: P, config: &ClipConfig) -> ClipModel
+    where
+        P: Borrow<nn::Path<'p>>,
+    {
+        let p = p.borrow();
+
+        let text_model = ClipTextTransformer::new(p / "text_model", &config.text_config);
+        let vision_model = ClipVisionTransformer::new(p / "vision_model", &config.vision_config);
+        let projection_config = LinearConfig {
+            bias: false,
+            ..Default::default()
+        };
+        let text_projection = nn::linear(
+            p / "text_projection",
+            config.text_config.hidden_size,
+            config.projection_dim,
+            projection_config,
+        );
+        let visual_projection = nn::linear(
+            p / "visual_projection",
+            config.vision_config.hidden_size,
+            config.projection_dim,
+            projection_config,
+        );
+        let logit_scale = p.var(
+            "logit_scale",
+            &[],
+            Init::Const(config.logit_scale_init_value.unwrap_or(2.6592)),
+        );
+
+        ClipModel {
+            text_model,
+            vision_model,
+            text_projection,
+            visual_projection,
+            logit_scale,
+        }
+    }
+
+    /// Projects texts to the shared embedding space (the embeddings are not normalized)
+    ///
+    /// # Arguments
+    ///
+    /// * `input_ids` - Input tensor of shape (*batch size*, *sequence_length*)
+    /// * `attention_mask` - Optional mask of shape (*batch size*, *sequence_length*). Masked positions have value 0, non-masked value 1.
+    /// * `train` - boolean flag to turn on/off the dropout layers in the model. Should be set to false for inference.
+    ///
+    /// # Returns
+    ///
+    /// * `Tensor` of shape (*batch size*, *projection_dim*)
+    pub fn get_text_features(
+        &self,
+        input_ids: &Tensor,
+        attention_mask: Option<&Tensor>,
+        train: bool,
+    ) -> Result<Tensor, RustBertError> {
+        Ok(self
+            .text_model
+            .forward_t(input_ids, attention_mask, train)?
+            .apply(&self.text_projection))
+    }
+
+    /// Projects images to the shared embedding space (the embeddings are not normalized)
+    ///
+    /// # Arguments
+    ///
+    /// * `pixel_values` - Normalized images of shape (*batch size*, *num_channels*, *image_size*, *image_size*)
+    /// * `train` - boolean flag to turn on/off the dropout layers in the model. Should be set to false for inference.
+    ///
+    /// # Returns
+    ///
+    /// * `Tensor` of shape (*batch size*, *projection_dim*)
+    pub fn get_image_features(
+        &self,
+        pixel_values: &Tensor,
+        train: bool,
+    ) -> Result<Tensor, RustBertError> {
+        Ok(self
+            .vision_model
+            .forward_t(pixel_values, train)?
+            .apply(&self.visual_projection))
+    }
+
+    /// Scale applied to the cosine similarities of the embeddings to obtain the logits
+    pub fn logit_scale(&self) -> f64 {
+        self.logit_scale.exp().double_value(&[])
+    }
+
+    /// Forward pass through the model
+    ///
+    /// # Arguments
+    ///
+    /// * `input_ids` - Input tensor of shape (*text batch size*, *sequence_length*)
+    /// * `attention_mask` - Optional mask of shape (*text batch size*, *sequence_length*). Masked positions have value 0, non-masked value 1.
+    /// * `pixel_values` - Normalized images of shape (*image batch size*, *num_channels*, *image_size*, *image_size*)
+    /// * `train` - boolean flag to turn on/off the dropout layers in the model. Should be set to false for inference.
+    ///
+    /// # Returns
+    ///
+    /// * `ClipModelOutput` containing:
+    ///   - `logits_per_image` - `Tensor` of shape (*image batch size*, *text batch size*), scaled cosine similarities
+    ///   - `logits_per_text` - `Tensor` of shape (*text batch size*, *image batch size*)
+    ///   - `text_embeds` - `Tensor` of shape (*text batch size*, *projection_dim*), normalized text embeddings
+    ///   - `image_embeds` - `Tensor` of shape (*image batch size*, *projection_dim*), normalized image embeddings
+    ///
+    /// # Example
+    ///
+    /// ```no_run
+    /// # use rust_bert::clip::{ClipConfig, ClipModel};
+    /// # use rust_bert::Config;
+    /// # use std::path::Path;
+    /// # use tch::{nn, no_grad, Device, Kind, Tensor};
+    /// # fn main() -> anyhow::Result<()> {
+    /// # let config_path = Path::new("path/to/config.json");
+    /// # let device = Device::Cpu;
+    /// # let vs = nn::VarStore::new(device);
+    /// # let config = ClipConfig::from_file(config_path);
+    /// # let clip_model = ClipModel::new(&vs.root(), &config);
+    /// let input_ids = Tensor::of_slice(&[49406i64, 320, 1125, 539, 320, 2368, 49407]).view([1, 7]);
+    /// let pixel_values = Tensor::rand(&[2, 3, 224, 224], (Kind::Float, device));
+    /// let model_output = no_grad(|| clip_model.forward_t(&input_ids, None, &pixel_values, false))?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn forward_t(
+        &self,
+        input_ids: &Tensor,
+        attention_mask: Option<&Tensor>,
+        pixel_values: &Tensor,
+        train: bool,
+    ) -> Result<ClipModelOutput, RustBertError> {
+        let text_embeds = normalize(&self.get_text_features(input_ids, attention_mask, train)?);
+        let image_embeds = normalize(&self.get_image_features(pixel_values, train)?);
+        let logits_per_text = text_embeds.matmul(&image_embeds.tr()) * self.logit_scale.exp();
+        let logits_per_image = logits_per_text.tr();
+
+        Ok(ClipModelOutput {
+            logits_per_image,
+            logits_per_text,
+            text_embeds,
+            image_embeds,
+        })
+    }
+}
+
+fn normalize(embeddings: &Tensor) -> Tensor {
+    embeddings
+        / embeddings
+            .norm_scalaropt_dim(2, &[-1], true)
+            .clamp_min(1e-12)
+}
+
+/// Container for the CLIP model output.
+pub struct ClipModelOutput {
+    /// Scaled cosine similarities of each image to each text
+    pub logits_per_image: Tensor,
+    /// Scaled cosine similarities of each text to each image
+    pub logits_per_text: Tensor,
+    /// Normalized text embeddings
+    pub text_embeds: Tensor,
+    /// Normalized image embeddings
+    pub image_embeds: Tensor,
+}
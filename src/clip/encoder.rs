@@ -0,0 +1,219 @@
+// Copyright 2021 The Open AI Team Authors and The HuggingFace Inc. team. All rights reserved.
+// Copyright 2022 Guillaume Becquin
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//     http://www.apache.org/licenses/LICENSE-2.0
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use crate::common::activations::{Activation, TensorFunction};
+use crate::common::dropout::Dropout;
+use std::borrow::Borrow;
+use tch::{nn, Tensor};
+
+/// Architecture of a transformer tower, shared by the text and vision encoders
+#[derive(Debug, Clone, Copy)]
+pub(crate) struct ClipEncoderConfig {
+    pub hidden_size: i64,
+    pub intermediate_size: i64,
+    pub num_hidden_layers: i64,
+    pub num_attention_heads: i64,
+    pub hidden_act: Activation,
+    pub layer_norm_eps: f64,
+    pub attention_dropout: f64,
+}
+
+/// # CLIP multi-head attention
+pub struct ClipAttention {
+    num_heads: i64,
+    head_dim: i64,
+    q_proj: nn::Linear,
+    k_proj: nn::Linear,
+    v_proj: nn::Linear,
+    out_proj: nn::Linear,
+    dropout: Dropout,
+}
+
+impl ClipAttention {
+    pub(crate) fn new<'p, P>(p: P, config: &ClipEncoderConfig) -> ClipAttention
+    where
+        P: Borrow<nn::Path<'p>>,
+    {
+        assert_eq!(
+            config.hidden_size % config.num_attention_heads,
+            0,
+            "Hidden size not a multiple of the number of attention heads"
+        );
+        let p = p.borrow();
+
+        let linear = |name: &str| {
+            nn::linear(
+                p / name,
+                config.hidden_size,
+                config.hidden_size,
+                Default::default(),
+            )
+        };
+
+        ClipAttention {
+            num_heads: config.num_attention_heads,
+            head_dim: config.hidden_size / config.num_attention_heads,
+            q_proj: linear("q_proj"),
+            k_proj: linear("k_proj"),
+            v_proj: linear("v_proj"),
+            out_proj: linear("out_proj"),
+            dropout: Dropout::new(config.attention_dropout),
+        }
+    }
+
+    fn split_heads(&self, x: Tensor, bs: i64) -> Tensor {
+        x.view((bs, -1, self.num_heads, self.head_dim))
+            .transpose(1, 2)
+    }
+
+    /// Forward pass through the attention layer
+    ///
+    /// # Arguments
+    ///
+    /// * `hidden_states` - input tensor of shape (*batch size*, *sequence_length*, *hidden_size*)
+    /// * `attention_mask` - optional additive mask of shape (*batch size*, 1, *sequence_length*, *sequence_length*)
+    /// * `train` - boolean flag to turn on/off the dropout layers in the model. Should be set to false for inference.
+    pub fn forward_t(
+        &self,
+        hidden_states: &Tensor,
+        attention_mask: Option<&Tensor>,
+        train: bool,
+    ) -> Tensor {
+        let bs = hidden_states.size()[0];
+
+        let query = self.split_heads(hidden_states.apply(&self.q_proj), bs)
+            * (self.head_dim as f64).powf(-0.5);
+        let key = self.split_heads(hidden_states.apply(&self.k_proj), bs);
+        let value = self.split_heads(hidden_states.apply(&self.v_proj), bs);
+
+        let mut scores = query.matmul(&key.transpose(-1, -2));
+        if let Some(attention_mask) = attention_mask {
+            scores = scores + attention_mask;
+        }
+        let weights = scores
+            .softmax(-1, scores.kind())
+            .apply_t(&self.dropout, train);
+        weights
+            .matmul(&value)
+            .transpose(1, 2)
+            .contiguous()
+            .view((bs, -1, self.num_heads * self.head_dim))
+            .apply(&self.out_proj)
+    }
+}
+
+/// # CLIP encoder layer
+/// Pre-normalization transformer layer: `x + attention(layer_norm1(x))` followed by `x + mlp(layer_norm2(x))`
+pub struct ClipEncoderLayer {
+    self_attn: ClipAttention,
+    layer_norm1: nn::LayerNorm,
+    fc1: nn::Linear,
+    fc2: nn::Linear,
+    layer_norm2: nn::LayerNorm,
+    activation: TensorFunction,
+}
+
+impl ClipEncoderLayer {
+    pub(crate) fn new<'p, P>(p: P, config: &ClipEncoderConfig) -> ClipEncoderLayer
+    where
+        P: Borrow<nn::Path<'p>>,
+    {
+        let p = p.borrow();
+
+        let layer_norm_config = nn::LayerNormConfig {
+            eps: config.layer_norm_eps,
+            ..Default::default()
+        };
+        let self_attn = ClipAttention::new(p / "self_attn", config);
+        let layer_norm1 = nn::layer_norm(
+            p / "layer_norm1",
+            vec![config.hidden_size],
+            layer_norm_config,
+        );
+        let fc1 = nn::linear(
+            p / "mlp" / "fc1",
+            config.hidden_size,
+            config.intermediate_size,
+            Default::default(),
+        );
+        let fc2 = nn::linear(
+            p / "mlp" / "fc2",
+            config.intermediate_size,
+            config.hidden_size,
+            Default::default(),
+        );
+        let layer_norm2 = nn::layer_norm(
+            p / "layer_norm2",
+            vec![config.hidden_size],
+            layer_norm_config,
+        );
+
+        ClipEncoderLayer {
+            self_attn,
+            layer_norm1,
+            fc1,
+            fc2,
+            layer_norm2,
+            activation: config.hidden_act.get_function(),
+        }
+    }
+
+    pub fn forward_t(
+        &self,
+        hidden_states: &Tensor,
+        attention_mask: Option<&Tensor>,
+        train: bool,
+    ) -> Tensor {
+        let hidden_states = hidden_states
+            + self.self_attn.forward_t(
+                &hidden_states.apply(&self.layer_norm1),
+                attention_mask,
+                train,
+            );
+        let mlp_output =
+            self.activation.get_fn()(&hidden_states.apply(&self.layer_norm2).apply(&self.fc1))
+                .apply(&self.fc2);
+        hidden_states + mlp_output
+    }
+}
+
+/// # CLIP encoder
+/// Stack of `ClipEncoderLayer`, used by both towers
+pub struct ClipEncoder {
+    layers: Vec<ClipEncoderLayer>,
+}
+
+impl ClipEncoder {
+    pub(crate) fn new<'p, P>(p: P, config: &ClipEncoderConfig) -> ClipEncoder
+    where
+        P: Borrow<nn::Path<'p>>,
+    {
+        let p_layers = p.borrow() / "layers";
+        let layers = (0..config.num_hidden_layers)
+            .map(|layer_index| ClipEncoderLayer::new(&p_layers / layer_index, config))
+            .collect::<Vec<ClipEncoderLayer>>();
+        ClipEncoder { layers }
+    }
+
+    pub fn forward_t(
+        &self,
+        hidden_states: &Tensor,
+        attention_mask: Option<&Tensor>,
+        train: bool,
+    ) -> Tensor {
+        let mut hidden_states = hidden_states.shallow_clone();
+        for layer in &self.layers {
+            hidden_states = layer.forward_t(&hidden_states, attention_mask, train);
+        }
+        hidden_states
+    }
+}
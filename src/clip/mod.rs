@@ -0,0 +1,81 @@
+//! # CLIP (Contrastive Language-Image Pre-training)
+//!
+//! Implementation of the CLIP dual encoder ([Learning Transferable Visual Models From Natural Language Supervision](https://arxiv.org/abs/2103.00020) Radford, Kim, Hallacy, Ramesh, Goh, Agarwal, Sastry, Askell, Mishkin, Clark, Krueger, Sutskever, 2021).
+//! The model is implemented in the `clip_model::ClipModel` struct, made of a causal text transformer (`ClipTextTransformer`) and a vision transformer
+//! (`ClipVisionTransformer`) whose outputs are projected to a shared embedding space. Texts are represented by the hidden state of their end of text token
+//! and images by the hidden state of the class embedding. The scaled cosine similarities of the embeddings are the image-text logits.
+//! The texts are tokenized with the byte-level BPE `ClipTokenizer` and the images are processed by a `ViTImageProcessor` with center cropping
+//! and the `CLIP_IMAGE_MEAN` and `CLIP_IMAGE_STD` normalization.
+//! A ready-to-use pipeline is available in `pipelines::zero_shot_image_classification`.
+//!
+//! # Model set-up and pre-trained weights loading
+//!
+//! All models expect the following resources:
+//! - Configuration file expected to have a structure following the [Transformers library](https://github.com/huggingface/transformers)
+//! - Model weights are expected to have a structure and parameter names following the [Transformers library](https://github.com/huggingface/transformers). A conversion using the Python utility scripts is required to convert the `.bin` weights to the `.ot` format.
+//! - `ClipTokenizer` using a `vocab.json` vocabulary and a `merges.txt` merges file
+//!
+//! The configuration and tokenizer files of the CLIP ViT-B/32 checkpoint can be downloaded using RemoteResources. No converted weights are hosted
+//! with the crate: they are converted locally from the PyTorch checkpoint (`python utils/convert_model.py path/to/pytorch_model.bin`).
+//!
+//! ```no_run
+//! # fn main() -> anyhow::Result<()> {
+//! #
+//! use tch::{nn, no_grad, Device, Tensor};
+//! # use std::path::PathBuf;
+//! use rust_bert::clip::{
+//!     ClipConfig, ClipConfigResources, ClipMergesResources, ClipModel, ClipTokenizer,
+//!     ClipVocabResources, CLIP_IMAGE_MEAN, CLIP_IMAGE_STD,
+//! };
+//! use rust_bert::resources::{LocalResource, RemoteResource, ResourceProvider};
+//! use rust_bert::vit::{ImageBuffer, ViTImageProcessor};
+//! use rust_bert::Config;
+//!
+//! let config_resource = RemoteResource::from_pretrained(ClipConfigResources::CLIP_VIT_BASE_PATCH32);
+//! let vocab_resource = RemoteResource::from_pretrained(ClipVocabResources::CLIP_VIT_BASE_PATCH32);
+//! let merges_resource = RemoteResource::from_pretrained(ClipMergesResources::CLIP_VIT_BASE_PATCH32);
+//! let weights_resource = LocalResource {
+//!     local_path: PathBuf::from("path/to/rust_model.ot"),
+//! };
+//! let config_path = config_resource.get_local_path()?;
+//! let vocab_path = vocab_resource.get_local_path()?;
+//! let merges_path = merges_resource.get_local_path()?;
+//! let weights_path = weights_resource.get_local_path()?;
+//!
+//! let device = Device::cuda_if_available();
+//! let mut vs = nn::VarStore::new(device);
+//! let tokenizer = ClipTokenizer::from_file(
+//!     vocab_path.to_str().unwrap(),
+//!     merges_path.to_str().unwrap(),
+//! )?;
+//! let config = ClipConfig::from_file(config_path);
+//! let clip_model = ClipModel::new(&vs.root(), &config);
+//! vs.load(weights_path)?;
+//!
+//! let input_ids = Tensor::of_slice(&tokenizer.encode("a photo of a cat", 77))
+//!     .unsqueeze(0)
+//!     .to(device);
+//! let image_processor = ViTImageProcessor::new(
+//!     config.vision_config.image_size,
+//!     CLIP_IMAGE_MEAN,
+//!     CLIP_IMAGE_STD,
+//!     device,
+//! )
+//! .with_center_crop(true);
+//! let pixels = vec![127u8; 640 * 480 * 3];
+//! let pixel_values = image_processor.preprocess(&[ImageBuffer::rgb(&pixels, 640, 480)])?;
+//! let output = no_grad(|| clip_model.forward_t(&input_ids, None, &pixel_values, false))?;
+//! # Ok(())
+//! # }
+//! ```
+
+mod clip_model;
+mod encoder;
+mod tokenizer;
+
+pub use clip_model::{
+    ClipConfig, ClipConfigResources, ClipMergesResources, ClipModel, ClipModelOutput,
+    ClipTextConfig, ClipTextTransformer, ClipVisionConfig, ClipVisionTransformer,
+    ClipVocabResources, CLIP_IMAGE_MEAN, CLIP_IMAGE_STD,
+};
+pub use tokenizer::{ClipTokenizer, CLIP_END_OF_TEXT, CLIP_START_OF_TEXT};
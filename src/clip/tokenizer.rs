@@ -0,0 +1,260 @@
+// Copyright 2021 The Open AI Team Authors and The HuggingFace Inc. team. All rights reserved.
+// Copyright 2022 Guillaume Becquin
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//     http://www.apache.org/licenses/LICENSE-2.0
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use crate::RustBertError;
+use rust_tokenizers::vocab::{BpePairRef, BpePairVocab, Gpt2Vocab, Vocab};
+
+/// Start of text token, prepended to all sequences
+pub const CLIP_START_OF_TEXT: &str = "<|startoftext|>";
+/// End of text token, appended to all sequences and used for padding
+pub const CLIP_END_OF_TEXT: &str = "<|endoftext|>";
+
+/// Contractions split from the preceding word, in the order they are matched
+const CONTRACTIONS: [&str; 7] = ["'s", "'t", "'re", "'ve", "'m", "'ll", "'d"];
+
+/// Reversible mapping of the bytes to printable characters used by the byte-level BPE vocabularies
+fn bytes_to_unicode() -> [char; 256] {
+    let mut mapping = ['\0'; 256];
+    let mut shift = 0;
+    for byte in 0..=255u32 {
+        let is_printable = (33..=126).contains(&byte)
+            || (161..=172).contains(&byte)
+            || (174..=255).contains(&byte);
+        mapping[byte as usize] = if is_printable {
+            char::from_u32(byte).unwrap()
+        } else {
+            shift += 1;
+            char::from_u32(255 + shift).unwrap()
+        };
+    }
+    mapping
+}
+
+/// # CLIP tokenizer
+/// Byte-level BPE tokenizer of the CLIP text encoder. The text is lower-cased and split in words (letters), single
+/// digits, contractions and runs of punctuation before applying the BPE merges, the end of each word being marked by a
+/// `</w>` suffix. Sequences start with `<|startoftext|>` and end with `<|endoftext|>`.
+pub struct ClipTokenizer {
+    vocab: Gpt2Vocab,
+    bpe_ranks: BpePairVocab,
+    byte_encoder: [char; 256],
+    bos_token_id: i64,
+    eos_token_id: i64,
+}
+
+impl ClipTokenizer {
+    /// Create a new `ClipTokenizer` from the vocabulary and merges files of a CLIP checkpoint
+    ///
+    /// # Arguments
+    ///
+    /// * `vocab_path` - Path to the `vocab.json` vocabulary file
+    /// * `merges_path` - Path to the `merges.txt` merges file
+    pub fn from_file(vocab_path: &str, merges_path: &str) -> Result<ClipTokenizer, RustBertError> {
+        let vocab = Gpt2Vocab::from_file(vocab_path)?;
+        let bpe_ranks = BpePairVocab::from_file(merges_path)?;
+        let bos_token_id = *vocab.values().get(CLIP_START_OF_TEXT).ok_or_else(|| {
+            RustBertError::TokenizerError(format!(
+                "{} token not found in the vocabulary",
+                CLIP_START_OF_TEXT
+            ))
+        })?;
+        let eos_token_id = vocab.token_to_id(CLIP_END_OF_TEXT);
+        Ok(ClipTokenizer {
+            vocab,
+            bpe_ranks,
+            byte_encoder: bytes_to_unicode(),
+            bos_token_id,
+            eos_token_id,
+        })
+    }
+
+    /// Id of the start of text token
+    pub fn bos_token_id(&self) -> i64 {
+        self.bos_token_id
+    }
+
+    /// Id of the end of text token, also used for padding
+    pub fn eos_token_id(&self) -> i64 {
+        self.eos_token_id
+    }
+
+    /// Splits a lower-cased text in words, single digits, contractions and runs of punctuation
+    fn split_words(text: &str) -> Vec<String> {
+        let chars = text.to_lowercase().chars().collect::<Vec<char>>();
+        let mut words = vec![];
+        let mut position = 0;
+        while position < chars.len() {
+            let character = chars[position];
+            if character.is_whitespace() {
+                position += 1;
+                continue;
+            }
+            let contraction = CONTRACTIONS.iter().find(|contraction| {
+                let length = contraction.chars().count();
+                position + length <= chars.len()
+                    && contraction
+                        .chars()
+                        .eq(chars[position..position + length].iter().cloned())
+            });
+            let end = if let Some(contraction) = contraction {
+                position + contraction.chars().count()
+            } else if character.is_alphabetic() {
+                position
+                    + chars[position..]
+                        .iter()
+                        .take_while(|character| character.is_alphabetic())
+                        .count()
+            } else if character.is_numeric() {
+                position + 1
+            } else {
+                position
+                    + chars[position..]
+                        .iter()
+                        .take_while(|character| {
+                            !(character.is_whitespace()
+                                || character.is_alphabetic()
+                                || character.is_numeric())
+                        })
+                        .count()
+            };
+            words.push(chars[position..end].iter().collect());
+            position = end;
+        }
+        words
+    }
+
+    /// Applies the BPE merges to a byte-encoded word, by order of priority
+    fn bpe(&self, word: &str) -> Vec<String> {
+        let mut symbols = word.chars().map(String::from).collect::<Vec<String>>();
+        if let Some(last) = symbols.last_mut() {
+            last.push_str("</w>");
+        }
+        while symbols.len() > 1 {
+            let best_pair = symbols
+                .windows(2)
+                .filter_map(|pair| {
+                    self.bpe_ranks
+                        .byte_pair_to_id(&BpePairRef {
+                            byte_1: &pair[0],
+                            byte_2: &pair[1],
+                        })
+                        .map(|rank| (*rank, pair[0].clone(), pair[1].clone()))
+                })
+                .min_by_key(|(rank, _, _)| *rank);
+            let (first, second) = match best_pair {
+                Some((_, first, second)) => (first, second),
+                None => break,
+            };
+            let mut merged = Vec::with_capacity(symbols.len());
+            let mut index = 0;
+            while index < symbols.len() {
+                if index + 1 < symbols.len()
+                    && symbols[index] == first
+                    && symbols[index + 1] == second
+                {
+                    merged.push(format!("{}{}", first, second));
+                    index += 2;
+                } else {
+                    merged.push(symbols[index].clone());
+                    index += 1;
+                }
+            }
+            symbols = merged;
+        }
+        symbols
+    }
+
+    /// Splits a text in BPE tokens (without the special tokens)
+    ///
+    /// # Arguments
+    ///
+    /// * `text` - Text to tokenize
+    ///
+    /// # Returns
+    ///
+    /// * `Vec<String>` BPE tokens, the last token of each word ending with `</w>`
+    pub fn tokenize(&self, text: &str) -> Vec<String> {
+        let text = text.split_whitespace().collect::<Vec<&str>>().join(" ");
+        ClipTokenizer::split_words(&text)
+            .iter()
+            .flat_map(|word| {
+                let word = word
+                    .bytes()
+                    .map(|byte| self.byte_encoder[byte as usize])
+                    .collect::<String>();
+                self.bpe(&word)
+            })
+            .collect()
+    }
+
+    /// Converts a text to token ids, surrounded by the start and end of text tokens
+    ///
+    /// # Arguments
+    ///
+    /// * `text` - Text to encode
+    /// * `max_length` - Maximum number of tokens (including the special tokens). Longer texts are truncated.
+    ///
+    /// # Returns
+    ///
+    /// * `Vec<i64>` token ids
+    pub fn encode(&self, text: &str, max_length: usize) -> Vec<i64> {
+        let mut token_ids = Vec::with_capacity(max_length);
+        token_ids.push(self.bos_token_id);
+        token_ids.extend(
+            self.tokenize(text)
+                .iter()
+                .map(|token| self.vocab.token_to_id(token))
+                .take(max_length.saturating_sub(2)),
+        );
+        token_ids.push(self.eos_token_id);
+        token_ids
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use std::io::Write;
+
+    fn test_tokenizer() -> anyhow::Result<ClipTokenizer> {
+        let directory = tempfile::tempdir()?;
+        let vocab_path = directory.path().join("vocab.json");
+        let merges_path = directory.path().join("merges.txt");
+        std::fs::File::create(&vocab_path)?.write_all(
+            br#"{"<|startoftext|>": 0, "<|endoftext|>": 1, "a": 2, "b": 3, "c</w>": 4, "ab": 5, "abc</w>": 6, "!!</w>": 7, "1</w>": 8, "2</w>": 9, "'s</w>": 10, "!": 11, "!</w>": 12}"#,
+        )?;
+        std::fs::File::create(&merges_path)?
+            .write_all(b"#version: 0.2\na b\nab c</w>\n! !</w>\n' s</w>\n")?;
+        Ok(ClipTokenizer::from_file(
+            vocab_path.to_str().unwrap(),
+            merges_path.to_str().unwrap(),
+        )?)
+    }
+
+    #[test]
+    fn test_split_words() {
+        assert_eq!(
+            ClipTokenizer::split_words("It's 12 cats!!"),
+            vec!["it", "'s", "1", "2", "cats", "!!"]
+        );
+    }
+
+    #[test]
+    fn test_encode() -> anyhow::Result<()> {
+        let tokenizer = test_tokenizer()?;
+        assert_eq!(tokenizer.tokenize("ABC  !!"), vec!["abc</w>", "!!</w>"]);
+        assert_eq!(tokenizer.encode("abc 12 !!", 77), vec![0, 6, 8, 9, 7, 1]);
+        // Truncation keeps the end of text token
+        assert_eq!(tokenizer.encode("abc 12 !!", 4), vec![0, 6, 8, 1]);
+        Ok(())
+    }
+}
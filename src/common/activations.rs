@@ -30,6 +30,31 @@ pub fn _identity(x: &Tensor) -> Tensor {
     x.shallow_clone()
 }
 
+pub fn _quick_gelu(x: &Tensor) -> Tensor {
+    x * (x * 1.702).sigmoid()
+}
+
+pub fn _sigmoid(x: &Tensor) -> Tensor {
+    x.sigmoid()
+}
+
+fn gated(x: &Tensor, activation: fn(&Tensor) -> Tensor) -> Tensor {
+    let chunks = x.chunk(2, -1);
+    activation(&chunks[0]) * &chunks[1]
+}
+
+pub fn _geglu(x: &Tensor) -> Tensor {
+    gated(x, _gelu)
+}
+
+pub fn _swiglu(x: &Tensor) -> Tensor {
+    gated(x, _swish)
+}
+
+pub fn _reglu(x: &Tensor) -> Tensor {
+    gated(x, _relu)
+}
+
 pub struct TensorFunction(Box<fn(&Tensor) -> Tensor>);
 
 impl TensorFunction {
@@ -49,21 +74,41 @@ impl std::fmt::Debug for TensorFunction {
 #[allow(non_camel_case_types)]
 #[derive(Clone, Debug, Serialize, Deserialize, Copy)]
 /// # Activation function used in the attention layer and masked language model head
+/// Registry of the activation functions shared by all models. The names used in the configuration files
+/// (including the aliases used by the Python Transformers library, e.g. `silu` or `gelu_pytorch_tanh`)
+/// are mapped to the corresponding variant.
+///
+/// The gated activations (`geglu`, `swiglu` and `reglu`, [Shazeer, 2020](https://arxiv.org/abs/2002.05202)) expect an
+/// input with a last dimension twice the size of the output: the input is split in two halves, and the activation
+/// of the first half is multiplied by the second half.
 pub enum Activation {
     /// Gaussian Error Linear Unit ([Hendrycks et al., 2016,](https://arxiv.org/abs/1606.08415))
     gelu,
     /// Rectified Linear Unit
     relu,
-    /// Swish ([Ramachandran, 2017](https://arxiv.org/abs/1710.05941))
+    /// Swish ([Ramachandran, 2017](https://arxiv.org/abs/1710.05941)), also known as SiLU
+    #[serde(alias = "silu")]
     swish,
     /// Mish ([Misra, 2019](https://arxiv.org/abs/1908.08681))
     mish,
-    /// Gaussian Error Linear Unit (New) ([Hendrycks et al., 2016,](https://arxiv.org/abs/1606.08415))
+    /// Gaussian Error Linear Unit (New) ([Hendrycks et al., 2016,](https://arxiv.org/abs/1606.08415)), tanh approximation
+    #[serde(alias = "gelu_fast", alias = "gelu_pytorch_tanh")]
     gelu_new,
     /// Tanh
     tanh,
     /// Identity
+    #[serde(alias = "linear")]
     identity,
+    /// Quick Gaussian Error Linear Unit approximation, `x * sigmoid(1.702 * x)`
+    quick_gelu,
+    /// Sigmoid
+    sigmoid,
+    /// Gated Gaussian Error Linear Unit
+    geglu,
+    /// Gated Swish (SiLU)
+    swiglu,
+    /// Gated Rectified Linear Unit
+    reglu,
 }
 
 impl Activation {
@@ -76,8 +121,32 @@ impl Activation {
             Activation::mish => _mish,
             Activation::tanh => _tanh,
             Activation::identity => _identity,
+            Activation::quick_gelu => _quick_gelu,
+            Activation::sigmoid => _sigmoid,
+            Activation::geglu => _geglu,
+            Activation::swiglu => _swiglu,
+            Activation::reglu => _reglu,
         }))
     }
+
+    /// Returns true for the gated activations, halving the last dimension of their input
+    pub fn is_gated(&self) -> bool {
+        matches!(
+            self,
+            Activation::geglu | Activation::swiglu | Activation::reglu
+        )
+    }
+
+    /// Returns the gated variant of an activation (e.g. `geglu` for `gelu`), if it exists
+    pub fn to_gated(&self) -> Option<Activation> {
+        match self {
+            Activation::gelu => Some(Activation::geglu),
+            Activation::swish => Some(Activation::swiglu),
+            Activation::relu => Some(Activation::reglu),
+            Activation::geglu | Activation::swiglu | Activation::reglu => Some(*self),
+            _ => None,
+        }
+    }
 }
 
 #[cfg(test)]
@@ -88,4 +157,35 @@ mod test {
     fn tensorfunction_send() {
         let _: Box<dyn Send> = Box::new(Activation::gelu.get_function());
     }
+
+    #[test]
+    fn activation_aliases() {
+        let activations: Vec<Activation> =
+            serde_json::from_str(r#"["silu", "gelu_pytorch_tanh", "linear", "swiglu"]"#).unwrap();
+        assert!(matches!(
+            activations.as_slice(),
+            [
+                Activation::swish,
+                Activation::gelu_new,
+                Activation::identity,
+                Activation::swiglu
+            ]
+        ));
+    }
+
+    #[test]
+    fn gated_activations() {
+        let input = Tensor::of_slice(&[-1.0f64, 2.0, 3.0, -4.0]).view([1, 4]);
+        let output = Activation::reglu.get_function().get_fn()(&input);
+        assert_eq!(output, Tensor::of_slice(&[0.0f64, -8.0]).view([1, 2]));
+
+        let (gate, value) = (input.narrow(-1, 0, 2), input.narrow(-1, 2, 2));
+        for activation in [Activation::gelu, Activation::swish, Activation::relu] {
+            let gated_activation = activation.to_gated().unwrap();
+            assert!(gated_activation.is_gated());
+            let expected = activation.get_function().get_fn()(&gate) * &value;
+            let output = gated_activation.get_function().get_fn()(&input);
+            assert!((output - expected).abs().max().double_value(&[]) < 1e-6);
+        }
+    }
 }
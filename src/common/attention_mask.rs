@@ -0,0 +1,171 @@
+// Copyright 2022 Guillaume Becquin
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//     http://www.apache.org/licenses/LICENSE-2.0
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! # Attention mask utilities
+//! Helpers to build attention masks that can be passed to the model forward passes in place of
+//! the default padding masks (e.g. prefix-LM masks for decoders).
+
+use crate::RustBertError;
+use tch::{Kind, Tensor};
+
+/// Reduces an attention mask to a per-token padding mask of shape (*batch size*, *sequence_length*).
+///
+/// Encoders accept either a standard padding mask of shape (*batch size*, *sequence_length*), or
+/// pre-built masks of shape (*batch size*, *query_length*, *key_length*) and
+/// (*batch size*, *num_heads or 1*, *query_length*, *key_length*) (e.g. block-diagonal masks for packed
+/// sequences or causal-prefix masks). Some layers (embedding masking, cross-attention) require
+/// a mask over tokens only: a key position is considered valid if at least one query attends to it.
+pub(crate) fn get_padding_mask(attention_mask: &Tensor) -> Result<Tensor, RustBertError> {
+    Ok(match attention_mask.dim() {
+        2 => attention_mask.shallow_clone(),
+        3 => attention_mask
+            .ne(0)
+            .sum_dim_intlist(&[1], false, Kind::Int64)
+            .gt(0)
+            .to_kind(attention_mask.kind()),
+        4 => attention_mask
+            .ne(0)
+            .sum_dim_intlist(&[1, 2], false, Kind::Int64)
+            .gt(0)
+            .to_kind(attention_mask.kind()),
+        _ => {
+            return Err(RustBertError::ValueError(format!(
+                "Invalid attention mask dimension, must be 2, 3 or 4, got {}",
+                attention_mask.dim()
+            )));
+        }
+    })
+}
+
+/// Selects the rows of a 3D or 4D attention mask for the query positions of a forward pass with a cache.
+///
+/// Masks built for the whole sequence (e.g. prefix-LM masks) have one row per position. When the first
+/// `past_key_values_length` positions are already cached, the queries of the forward pass are the `query_length`
+/// following positions, attending to the cached positions and to the queries. Masks already restricted to these
+/// queries and keys are returned unchanged.
+pub(crate) fn get_query_attention_mask(
+    attention_mask: &Tensor,
+    query_length: i64,
+    past_key_values_length: i64,
+) -> Result<Tensor, RustBertError> {
+    let mask_shape = attention_mask.size();
+    let key_length = past_key_values_length + query_length;
+    match mask_shape.as_slice() {
+        [.., mask_query_length, mask_key_length]
+            if (*mask_query_length == query_length) & (*mask_key_length == key_length) =>
+        {
+            Ok(attention_mask.shallow_clone())
+        }
+        [.., mask_query_length, mask_key_length]
+            if (*mask_query_length >= key_length) & (*mask_key_length >= key_length) =>
+        {
+            Ok(attention_mask
+                .narrow(-2, past_key_values_length, query_length)
+                .narrow(-1, 0, key_length))
+        }
+        _ => Err(RustBertError::ValueError(format!(
+            "Attention mask of shape {:?} does not cover the {} cached and {} new positions",
+            mask_shape, past_key_values_length, query_length
+        ))),
+    }
+}
+
+/// # Build a prefix-LM attention mask
+/// Builds a 3D attention mask for decoders where the leading prefix of each sequence attends
+/// bidirectionally to the entire prefix, while the remaining positions attend causally (to the prefix
+/// and to previous positions). This allows running UL2/FLAN-style checkpoints trained with a prefix-LM
+/// objective, or infilling with T5 and BART decoders.
+/// The mask can be passed as `decoder_attention_mask` to the T5 and BART-based models, in which case it
+/// replaces the default causal mask of the decoder. When decoding incrementally with a cache, the mask built for the
+/// whole sequence can be passed at every step: the rows of the positions following the cache are selected.
+/// For generation, see `GenerateOptions::decoder_prefix_ids`.
+///
+/// # Arguments
+///
+/// * `attention_mask` - Padding mask of shape (*batch size*, *sequence_length*). Masked position have value 0, non-masked value 1.
+/// * `prefix_lengths` - Length of the bidirectional prefix for each sequence in the batch
+///
+/// # Returns
+///
+/// * `Tensor` of shape (*batch size*, *sequence_length*, *sequence_length*) with value 1 for the key positions (last dimension) each query position (second dimension) may attend to.
+///
+/// # Example
+///
+/// ```no_run
+/// use rust_bert::attention_mask::build_prefix_lm_attention_mask;
+/// use tch::{Device, Kind, Tensor};
+/// # fn main() -> anyhow::Result<()> {
+/// let padding_mask = Tensor::ones(&[2, 8], (Kind::Int64, Device::Cpu));
+/// let prefix_lm_mask = build_prefix_lm_attention_mask(&padding_mask, &[3, 5])?;
+/// # Ok(())
+/// # }
+/// ```
+pub fn build_prefix_lm_attention_mask(
+    attention_mask: &Tensor,
+    prefix_lengths: &[i64],
+) -> Result<Tensor, RustBertError> {
+    let (batch_size, sequence_length) = attention_mask.size2()?;
+    if prefix_lengths.len() as i64 != batch_size {
+        return Err(RustBertError::ValueError(format!(
+            "Number of prefix lengths ({}) does not match the batch size ({})",
+            prefix_lengths.len(),
+            batch_size
+        )));
+    }
+    let device = attention_mask.device();
+    let positions = Tensor::arange(sequence_length, (Kind::Int64, device));
+    let causal_mask = positions.unsqueeze(0).le_tensor(&positions.unsqueeze(-1));
+    let prefix_mask = positions
+        .unsqueeze(0)
+        .lt_tensor(&Tensor::of_slice(prefix_lengths).to(device).unsqueeze(-1));
+    let visible_positions = causal_mask
+        .unsqueeze(0)
+        .logical_or(&prefix_mask.unsqueeze(1));
+    Ok(visible_positions
+        .logical_and(&attention_mask.ne(0).unsqueeze(1))
+        .to_kind(attention_mask.kind()))
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use tch::Device;
+
+    #[test]
+    fn test_prefix_lm_attention_mask() -> anyhow::Result<()> {
+        let padding_mask = Tensor::of_slice(&[1i64, 1, 1, 1, 1, 1, 1, 0]).view((2, 4));
+        let mask = build_prefix_lm_attention_mask(&padding_mask, &[2, 1])?;
+        let expected = Tensor::of_slice(&[
+            1i64, 1, 0, 0, 1, 1, 0, 0, 1, 1, 1, 0, 1, 1, 1, 1, //
+            1, 0, 0, 0, 1, 1, 0, 0, 1, 1, 1, 0, 1, 1, 1, 0,
+        ])
+        .view((2, 4, 4))
+        .to(Device::Cpu);
+        assert!(mask.equal(&expected));
+        assert!(build_prefix_lm_attention_mask(&padding_mask, &[2]).is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn test_query_attention_mask() -> anyhow::Result<()> {
+        let padding_mask = Tensor::ones(&[1, 4], (Kind::Int64, Device::Cpu));
+        let mask = build_prefix_lm_attention_mask(&padding_mask, &[2])?;
+
+        let step_mask = get_query_attention_mask(&mask, 1, 2)?;
+        assert_eq!(step_mask.size(), vec![1, 1, 3]);
+        assert!(step_mask.equal(&mask.narrow(1, 2, 1).narrow(2, 0, 3)));
+
+        let prefill_mask = get_query_attention_mask(&mask, 4, 0)?;
+        assert!(prefill_mask.equal(&mask));
+        assert!(get_query_attention_mask(&mask, 2, 3).is_err());
+        Ok(())
+    }
+}
@@ -9,6 +9,7 @@
 // See the License for the specific language governing permissions and
 // limitations under the License.
 
+use serde::{Deserialize, Serialize};
 use tch::nn::ModuleT;
 use tch::{Kind, Tensor};
 
@@ -55,3 +56,135 @@ impl ModuleT for XDropout {
         }
     }
 }
+
+/// # Stochastic depth schedule
+/// Drop probability of each layer of a deep encoder, used for stochastic depth (layer drop) regularization
+/// during training.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub enum StochasticDepth {
+    /// Same drop probability for all layers
+    Uniform(f64),
+    /// Drop probability increasing linearly from 0 for the first layer to the value provided for the last layer
+    /// ([Huang et al., 2016](https://arxiv.org/abs/1603.09382))
+    Linear(f64),
+    /// Drop probability of each layer. Layers without a value are never dropped.
+    PerLayer(Vec<f64>),
+}
+
+impl StochasticDepth {
+    /// Returns the drop probability of a layer
+    ///
+    /// # Arguments
+    ///
+    /// * `layer_index` - Index of the layer (starting from 0)
+    /// * `num_layers` - Total number of layers of the encoder
+    pub fn drop_prob(&self, layer_index: usize, num_layers: usize) -> f64 {
+        match self {
+            StochasticDepth::Uniform(drop_prob) => *drop_prob,
+            StochasticDepth::Linear(max_drop_prob) => {
+                if num_layers > 1 {
+                    max_drop_prob * layer_index as f64 / (num_layers - 1) as f64
+                } else {
+                    *max_drop_prob
+                }
+            }
+            StochasticDepth::PerLayer(drop_probs) => {
+                drop_probs.get(layer_index).copied().unwrap_or(0.0)
+            }
+        }
+    }
+}
+
+/// # Drop path (stochastic depth)
+/// Drops entire samples of a residual branch or layer during training, as opposed to individual
+/// activations for `Dropout`. Behaves as the identity in inference mode.
+#[derive(Debug)]
+pub struct DropPath {
+    drop_prob: f64,
+}
+
+impl DropPath {
+    pub fn new(p: f64) -> DropPath {
+        DropPath { drop_prob: p }
+    }
+
+    /// Sample-wise keep mask of shape (*batch size*, 1, ..., 1), broadcastable to the input
+    fn keep_mask(&self, input: &Tensor) -> Tensor {
+        let mut mask_shape = vec![1; input.dim()];
+        mask_shape[0] = input.size()[0];
+        Tensor::empty(&mask_shape, (input.kind(), input.device()))
+            .bernoulli_float_(1_f64 - self.drop_prob)
+    }
+
+    /// Layer drop: during training, the output of the layer is replaced by its input for the dropped samples,
+    /// skipping the layer. Returns the layer output in inference mode.
+    ///
+    /// # Arguments
+    ///
+    /// * `input` - Input of the layer
+    /// * `layer_output` - Output of the layer, of the same shape as the input
+    /// * `train` - boolean flag to turn on/off the layer drop
+    pub fn skip_layer(&self, input: &Tensor, layer_output: &Tensor, train: bool) -> Tensor {
+        if train && self.drop_prob > 0_f64 {
+            let keep_mask = self.keep_mask(input).to_kind(Kind::Bool);
+            layer_output.where_self(&keep_mask, input)
+        } else {
+            layer_output.shallow_clone()
+        }
+    }
+}
+
+impl ModuleT for DropPath {
+    /// Drops samples of a residual branch output, scaling the kept samples by the inverse of the keep probability
+    fn forward_t(&self, input: &Tensor, train: bool) -> Tensor {
+        if train && self.drop_prob > 0_f64 {
+            input * self.keep_mask(input) / (1_f64 - self.drop_prob)
+        } else {
+            input.shallow_clone()
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use tch::Device;
+
+    #[test]
+    fn test_stochastic_depth_schedule() {
+        assert_eq!(StochasticDepth::Uniform(0.1).drop_prob(3, 12), 0.1);
+        assert_eq!(StochasticDepth::Linear(0.2).drop_prob(0, 5), 0.0);
+        assert!((StochasticDepth::Linear(0.2).drop_prob(2, 5) - 0.1).abs() < 1e-12);
+        assert_eq!(StochasticDepth::Linear(0.2).drop_prob(4, 5), 0.2);
+        assert_eq!(
+            StochasticDepth::PerLayer(vec![0.0, 0.5]).drop_prob(1, 3),
+            0.5
+        );
+        assert_eq!(
+            StochasticDepth::PerLayer(vec![0.0, 0.5]).drop_prob(2, 3),
+            0.0
+        );
+    }
+
+    #[test]
+    fn test_drop_path() {
+        let input = Tensor::ones(&[64, 4, 8], (Kind::Float, Device::Cpu));
+        let layer_output = Tensor::zeros(&[64, 4, 8], (Kind::Float, Device::Cpu));
+
+        let drop_path = DropPath::new(0.5);
+        assert_eq!(drop_path.forward_t(&input, false), input);
+        assert_eq!(
+            drop_path.skip_layer(&input, &layer_output, false),
+            layer_output
+        );
+
+        // All positions of a sample are either kept or dropped
+        let output = drop_path.forward_t(&input, true);
+        let sample_values = output.amax(&[1, 2], false);
+        assert_eq!(output.amin(&[1, 2], false), sample_values);
+        let skipped = drop_path.skip_layer(&input, &layer_output, true);
+        assert_eq!(skipped.amin(&[1, 2], false), skipped.amax(&[1, 2], false));
+
+        assert_eq!(DropPath::new(0.0).forward_t(&input, true), input);
+    }
+}
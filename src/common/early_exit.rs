@@ -0,0 +1,188 @@
+// Copyright 2022 Guillaume Becquin
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//     http://www.apache.org/licenses/LICENSE-2.0
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! # Early exit
+//! Sequence-level early exit for classification encoders ([Xin et al., 2020](https://arxiv.org/abs/2004.12993)):
+//! a classifier head is attached to each intermediate layer of the encoder, and an input leaves the encoder as soon
+//! as the probability of the most likely class predicted by one of these heads reaches a confidence threshold. Easy
+//! inputs are classified after a few layers, while the harder ones go through the full encoder. The inputs of a
+//! batch exit independently: the following layers only process the inputs that did not exit yet.
+//!
+//! Early exit is enabled by setting the `early_exit` field of the configuration of the BERT and DistilBERT
+//! sequence classification models. The exit heads are stored under `exit_heads.{layer index}` and need to be trained
+//! (for example by adding the losses of the `exit_logits` returned in training mode to the loss of the final
+//! classifier), the last layer using the original classifier of the model.
+//!
+//! ```no_run
+//! use rust_bert::bert::{BertConfig, BertForSequenceClassification};
+//! use rust_bert::early_exit::EarlyExitConfig;
+//! use rust_bert::Config;
+//! use std::path::Path;
+//! use tch::{nn, no_grad, Device, Tensor};
+//!
+//! let mut config = BertConfig::from_file(Path::new("path/to/config.json"));
+//! config.early_exit = Some(EarlyExitConfig::new(0.9));
+//! let vs = nn::VarStore::new(Device::Cpu);
+//! let model = BertForSequenceClassification::new(vs.root(), &config);
+//!
+//! let input_ids = Tensor::of_slice(&[101i64, 2023, 3185, 2003, 2307, 102]).view([1, -1]);
+//! let output = no_grad(|| model.forward_t(Some(&input_ids), None, None, None, None, false));
+//! // Number of layers processed for each input
+//! let exit_layers = output.exit_layers.unwrap();
+//! ```
+
+use serde::{Deserialize, Serialize};
+use tch::{Device, Kind, Tensor};
+
+/// # Early exit configuration
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct EarlyExitConfig {
+    /// Probability of the most likely class above which an input exits the encoder
+    pub confidence_threshold: f64,
+    /// Number of layers processed by all the inputs before the first exit (default: 1)
+    pub min_layers: Option<i64>,
+}
+
+impl EarlyExitConfig {
+    /// Creates a new `EarlyExitConfig` allowing the inputs to exit from the first layer
+    ///
+    /// # Arguments
+    ///
+    /// * `confidence_threshold` - Probability of the most likely class above which an input exits the encoder
+    pub fn new(confidence_threshold: f64) -> EarlyExitConfig {
+        EarlyExitConfig {
+            confidence_threshold,
+            min_layers: None,
+        }
+    }
+
+    /// Returns true if the inputs may exit after the given number of layers of an encoder of `num_layers` layers
+    pub(crate) fn can_exit(&self, layers_processed: usize, num_layers: usize) -> bool {
+        (layers_processed as i64 >= self.min_layers.unwrap_or(1)) & (layers_processed < num_layers)
+    }
+}
+
+/// Tracks the inputs of a batch leaving the encoder: collects the logits of the inputs that exited and the number of
+/// layers they went through, along with the position in the batch of the inputs still processed.
+pub(crate) struct EarlyExitBatch {
+    logits: Option<Tensor>,
+    exit_layers: Vec<i64>,
+    active_indices: Tensor,
+}
+
+impl EarlyExitBatch {
+    pub(crate) fn new(batch_size: i64, device: Device) -> EarlyExitBatch {
+        EarlyExitBatch {
+            logits: None,
+            exit_layers: vec![0; batch_size as usize],
+            active_indices: Tensor::arange(batch_size, (Kind::Int64, device)),
+        }
+    }
+
+    /// Records the inputs exiting after `layers_processed` layers
+    ///
+    /// # Arguments
+    ///
+    /// * `layer_logits` - Logits of the exit head for the inputs still processed, of shape (*active inputs*, *num_labels*)
+    /// * `layers_processed` - Number of layers processed by the inputs
+    /// * `confidence_threshold` - Confidence threshold of the exit. All the inputs exit if `None` (last layer).
+    ///
+    /// # Returns
+    ///
+    /// * `Option<Tensor>` Positions among the inputs still processed of the inputs continuing to the next layer,
+    ///   `None` if all the inputs exited
+    pub(crate) fn exit(
+        &mut self,
+        layer_logits: &Tensor,
+        layers_processed: i64,
+        confidence_threshold: Option<f64>,
+    ) -> Option<Tensor> {
+        let exits = match confidence_threshold {
+            Some(confidence_threshold) => layer_logits
+                .softmax(-1, Kind::Float)
+                .max_dim(-1, false)
+                .0
+                .ge(confidence_threshold),
+            None => Tensor::ones(
+                &[layer_logits.size()[0]],
+                (Kind::Bool, layer_logits.device()),
+            ),
+        };
+        let exit_positions = exits.nonzero().squeeze_dim(-1);
+        if exit_positions.numel() > 0 {
+            let exit_indices = self.active_indices.index_select(0, &exit_positions);
+            let batch_size = self.exit_layers.len() as i64;
+            let logits = self.logits.get_or_insert_with(|| {
+                Tensor::zeros(
+                    &[batch_size, layer_logits.size()[1]],
+                    (layer_logits.kind(), layer_logits.device()),
+                )
+            });
+            let _ = logits.index_copy_(
+                0,
+                &exit_indices,
+                &layer_logits.index_select(0, &exit_positions),
+            );
+            for exit_index in exit_indices.iter::<i64>().unwrap() {
+                self.exit_layers[exit_index as usize] = layers_processed;
+            }
+        }
+
+        let remaining_positions = exits.logical_not().nonzero().squeeze_dim(-1);
+        if remaining_positions.numel() > 0 {
+            self.active_indices = self.active_indices.index_select(0, &remaining_positions);
+            Some(remaining_positions)
+        } else {
+            None
+        }
+    }
+
+    /// Returns the logits of all the inputs and the number of layers each of them went through
+    pub(crate) fn finish(self) -> (Tensor, Vec<i64>) {
+        (
+            self.logits.expect("No input exited the encoder"),
+            self.exit_layers,
+        )
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn early_exit_batch() {
+        let config = EarlyExitConfig {
+            confidence_threshold: 0.9,
+            min_layers: Some(2),
+        };
+        assert!(!config.can_exit(1, 6));
+        assert!(config.can_exit(2, 6));
+        assert!(!config.can_exit(6, 6));
+
+        let mut batch = EarlyExitBatch::new(3, Device::Cpu);
+        // The first and last inputs are confident enough to exit
+        let layer_logits = Tensor::of_slice(&[10.0f32, 0.0, 0.0, 0.0, 0.0, 10.0]).view([3, 2]);
+        let remaining = batch.exit(&layer_logits, 2, Some(config.confidence_threshold));
+        assert_eq!(Vec::<i64>::from(remaining.unwrap()), vec![1]);
+
+        // The last layer classifies the remaining input
+        let layer_logits = Tensor::of_slice(&[1.0f32, 2.0]).view([1, 2]);
+        assert!(batch.exit(&layer_logits, 6, None).is_none());
+
+        let (logits, exit_layers) = batch.finish();
+        assert_eq!(exit_layers, vec![2, 6, 2]);
+        assert_eq!(
+            Vec::<Vec<f32>>::from(logits),
+            vec![vec![10.0, 0.0], vec![1.0, 2.0], vec![0.0, 10.0]]
+        );
+    }
+}
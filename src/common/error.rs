@@ -3,6 +3,7 @@ use tch::TchError;
 use thiserror::Error;
 
 #[derive(Error, Debug)]
+#[cfg_attr(feature = "uniffi", derive(uniffi::Error), uniffi(flat_error))]
 pub enum RustBertError {
     #[cfg(feature = "remote")]
     #[error("Endpoint not available error: {0}")]
@@ -22,6 +23,15 @@ pub enum RustBertError {
 
     #[error("Value error: {0}")]
     ValueError(String),
+
+    #[error("Memory budget exceeded: {0}")]
+    MemoryBudgetExceededError(String),
+
+    #[error("Resource integrity error: {0}")]
+    ResourceIntegrityError(String),
+
+    #[error("Input too long: {0}")]
+    InputTooLongError(String),
 }
 
 impl From<std::io::Error> for RustBertError {
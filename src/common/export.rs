@@ -0,0 +1,98 @@
+// Copyright 2022 Guillaume Becquin
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//     http://www.apache.org/licenses/LICENSE-2.0
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! # Model export utilities
+//! Serializes models prepared in Rust to TorchScript by tracing their forward pass. The resulting
+//! file can be loaded with `tch::CModule::load` or `torch.jit.load` without the Rust model definitions.
+//!
+//! Direct ONNX serialization is not available: the ONNX exporter is only part of the Python
+//! `torch.onnx` package and is not exposed by the libtorch C++ API used by `tch`. A TorchScript
+//! file exported from Rust can be converted with `torch.onnx.export(torch.jit.load(path), ...)`,
+//! where the batch and sequence `dynamic_axes` are declared.
+//!
+//! Tracing records the operations executed for the example inputs. Dimensions read on the Rust side
+//! (e.g. `Tensor::size()` used to build position ids or attention masks) are stored as constants
+//! in the traced graph: the exported module should therefore be traced with all the inputs it will
+//! receive at inference (e.g. explicit attention masks and position ids) and validated on inputs
+//! of different batch sizes and sequence lengths before deployment.
+
+use crate::RustBertError;
+use std::path::Path;
+use tch::{CModule, Tensor};
+
+/// # Export a model forward pass to TorchScript
+/// Traces the `forward` closure with the example inputs provided and saves the resulting
+/// TorchScript module to `path`.
+///
+/// # Arguments
+///
+/// * `example_inputs` - Slice of tensors passed to the forward closure during tracing
+/// * `forward` - Closure running the model forward pass and returning the output tensors
+/// * `path` - Path of the TorchScript file to create
+///
+/// # Returns
+///
+/// * `CModule` traced TorchScript module
+///
+/// # Example
+///
+/// ```no_run
+/// # use rust_bert::bert::{BertConfig, BertModel, BertEmbeddings};
+/// # use rust_bert::Config;
+/// # use std::path::Path;
+/// # use tch::{nn, Device, Kind, Tensor};
+/// use rust_bert::export::export_torchscript;
+/// # fn main() -> anyhow::Result<()> {
+/// # let config_path = Path::new("path/to/config.json");
+/// # let device = Device::Cpu;
+/// # let vs = nn::VarStore::new(device);
+/// # let config = BertConfig::from_file(config_path);
+/// let bert_model: BertModel<BertEmbeddings> = BertModel::new(&vs.root(), &config);
+/// let input_ids = Tensor::ones(&[2, 16], (Kind::Int64, device));
+/// let attention_mask = Tensor::ones(&[2, 16], (Kind::Int64, device));
+///
+/// let _traced_module = export_torchscript(
+///     &[input_ids, attention_mask],
+///     &mut |inputs| {
+///         let output = bert_model
+///             .forward_t(
+///                 Some(&inputs[0]),
+///                 Some(&inputs[1]),
+///                 None,
+///                 None,
+///                 None,
+///                 None,
+///                 None,
+///                 false,
+///             )
+///             .unwrap();
+///         vec![output.hidden_state, output.pooled_output.unwrap()]
+///     },
+///     "bert.pt",
+/// )?;
+/// # Ok(())
+/// # }
+/// ```
+pub fn export_torchscript<F, P>(
+    example_inputs: &[Tensor],
+    forward: &mut F,
+    path: P,
+) -> Result<CModule, RustBertError>
+where
+    F: FnMut(&[Tensor]) -> Vec<Tensor>,
+    P: AsRef<Path>,
+{
+    let traced_module = tch::no_grad(|| {
+        CModule::create_by_tracing("RustBertModel", "forward", example_inputs, forward)
+    })?;
+    traced_module.save(path)?;
+    Ok(traced_module)
+}
@@ -0,0 +1,103 @@
+// Copyright 2022 Guillaume Becquin
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//     http://www.apache.org/licenses/LICENSE-2.0
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Fused feed-forward operations for inference. The bias, activation and residual connection are applied in place
+//! on the output of the matrix multiplication (using the native kernels of the backend for the activations when
+//! available) instead of allocating an intermediate tensor for each operation. These are only valid when the
+//! intermediate results do not need to be kept for the backward pass, i.e. in inference mode.
+
+use crate::common::activations::Activation;
+use tch::{nn, Tensor};
+
+fn linear_(input: &Tensor, linear: &nn::Linear) -> Tensor {
+    let mut output = input.matmul(&linear.ws.tr());
+    if let Some(bias) = &linear.bs {
+        let _ = output.add_(bias);
+    }
+    output
+}
+
+fn activation_(mut input: Tensor, activation: Activation) -> Tensor {
+    match activation {
+        Activation::relu => {
+            let _ = input.relu_();
+            input
+        }
+        Activation::swish => {
+            let _ = input.silu_();
+            input
+        }
+        Activation::mish => {
+            let _ = input.mish_();
+            input
+        }
+        Activation::tanh => {
+            let _ = input.tanh_();
+            input
+        }
+        Activation::sigmoid => {
+            let _ = input.sigmoid_();
+            input
+        }
+        Activation::gelu => input.gelu("none"),
+        Activation::gelu_new => input.gelu("tanh"),
+        Activation::identity => input,
+        _ => activation.get_function().get_fn()(&input),
+    }
+}
+
+/// Linear layer followed by an activation, with the bias and activation applied in place
+pub(crate) fn linear_activation(
+    input: &Tensor,
+    linear: &nn::Linear,
+    activation: Activation,
+) -> Tensor {
+    activation_(linear_(input, linear), activation)
+}
+
+/// Linear layer followed by a residual connection, with the bias and residual added in place
+pub(crate) fn linear_residual(input: &Tensor, linear: &nn::Linear, residual: &Tensor) -> Tensor {
+    let mut output = linear_(input, linear);
+    let _ = output.add_(residual);
+    output
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use tch::nn::{Module, VarStore};
+    use tch::{Device, Kind};
+
+    #[test]
+    fn fused_linear() {
+        let vs = VarStore::new(Device::Cpu);
+        let linear = nn::linear(vs.root(), 8, 16, Default::default());
+        let input = Tensor::randn(&[2, 3, 8], (Kind::Float, Device::Cpu));
+        let residual = Tensor::randn(&[2, 3, 16], (Kind::Float, Device::Cpu));
+
+        for activation in [
+            Activation::gelu,
+            Activation::gelu_new,
+            Activation::relu,
+            Activation::swish,
+            Activation::mish,
+            Activation::quick_gelu,
+        ] {
+            let expected = activation.get_function().get_fn()(&input.apply(&linear));
+            let output = linear_activation(&input, &linear, activation);
+            assert!((output - expected).abs().max().double_value(&[]) < 1e-5);
+        }
+
+        let expected = linear.forward(&input) + &residual;
+        let output = linear_residual(&input, &linear, &residual);
+        assert!((output - expected).abs().max().double_value(&[]) < 1e-5);
+    }
+}
@@ -1,11 +1,19 @@
 pub(crate) mod activations;
+pub mod attention_mask;
 pub mod config;
 pub(crate) mod dropout;
+pub mod early_exit;
 pub(crate) mod embeddings;
 pub mod error;
+pub mod export;
+pub(crate) mod fused;
 pub(crate) mod kind;
 pub(crate) mod linear;
+pub mod normalization;
+pub mod offsets;
+pub mod pruning;
 pub mod resources;
+pub mod rotary;
 pub(crate) mod summary;
 
 pub use activations::Activation;
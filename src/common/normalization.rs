@@ -0,0 +1,233 @@
+// Copyright 2018 Mesh TensorFlow authors, T5 Authors and HuggingFace Inc. team.
+// Copyright 2022 Guillaume Becquin
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//     http://www.apache.org/licenses/LICENSE-2.0
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! # Normalization layers
+//! Normalization layers shared by the model implementations, in addition to the standard `LayerNorm` provided by `tch`:
+//! - `RMSNorm`: root mean square normalization, without mean subtraction nor bias ([Zhang and Sennrich, 2019](https://arxiv.org/abs/1910.07467)),
+//! used by T5, LLaMA or Mistral
+//! - `ScaleNorm`: L2 normalization with a single learned scale ([Nguyen and Salazar, 2019](https://arxiv.org/abs/1910.05895))
+//!
+//! The name of the learned scale variable differs across checkpoints and can be set in the layer configuration
+//! (e.g. `weight` for T5 and LLaMA, `g` for the fairseq implementation of `ScaleNorm`).
+
+use serde::{Deserialize, Serialize};
+use std::borrow::Borrow;
+use tch::nn::{Init, Module};
+use tch::{nn, Kind, Tensor};
+
+/// # Configuration for the `RMSNorm` and `ScaleNorm` layers
+#[derive(Debug, Clone, Copy)]
+pub struct NormConfig {
+    /// Value added to the denominator for numerical stability
+    pub eps: f64,
+    /// Name of the learned scale variable in the checkpoint
+    pub weight_name: &'static str,
+}
+
+impl Default for NormConfig {
+    fn default() -> Self {
+        NormConfig {
+            eps: 1e-6,
+            weight_name: "weight",
+        }
+    }
+}
+
+/// # Root mean square normalization
+/// Scales the input by the inverse of its root mean square over the last dimension, followed by an element-wise
+/// learned scale. The statistics are computed in single precision for half-precision inputs.
+#[derive(Debug)]
+pub struct RMSNorm {
+    pub weight: Tensor,
+    eps: f64,
+}
+
+impl RMSNorm {
+    pub fn new<'p, P>(p: P, hidden_size: i64, config: NormConfig) -> RMSNorm
+    where
+        P: Borrow<nn::Path<'p>>,
+    {
+        let weight = p
+            .borrow()
+            .var(config.weight_name, &[hidden_size], Init::Const(1.0));
+        RMSNorm {
+            weight,
+            eps: config.eps,
+        }
+    }
+}
+
+impl Module for RMSNorm {
+    fn forward(&self, x: &Tensor) -> Tensor {
+        let input_type = x.kind();
+        let variance =
+            x.to_kind(Kind::Float)
+                .pow_tensor_scalar(2.0_f64)
+                .mean_dim(&[-1], true, Kind::Float);
+        let x = x * (variance + self.eps).rsqrt();
+        if input_type != Kind::Float {
+            (&self.weight * x).to_kind(input_type)
+        } else {
+            &self.weight * x
+        }
+    }
+}
+
+/// # Scale normalization
+/// Projects the input on a hypersphere by dividing it by its L2 norm over the last dimension, followed by a single
+/// learned scale (initialized to the square root of the hidden size).
+#[derive(Debug)]
+pub struct ScaleNorm {
+    pub scale: Tensor,
+    eps: f64,
+}
+
+impl ScaleNorm {
+    pub fn new<'p, P>(p: P, hidden_size: i64, config: NormConfig) -> ScaleNorm
+    where
+        P: Borrow<nn::Path<'p>>,
+    {
+        let scale = p.borrow().var(
+            config.weight_name,
+            &[1],
+            Init::Const((hidden_size as f64).sqrt()),
+        );
+        ScaleNorm {
+            scale,
+            eps: config.eps,
+        }
+    }
+}
+
+impl Module for ScaleNorm {
+    fn forward(&self, x: &Tensor) -> Tensor {
+        let input_type = x.kind();
+        let norm = x
+            .to_kind(Kind::Float)
+            .norm_scalaropt_dim(2, &[-1], true)
+            .clamp_min(self.eps);
+        (x * (&self.scale / norm)).to_kind(input_type)
+    }
+}
+
+/// # Normalization layer type
+#[derive(Clone, Copy, Debug, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum NormType {
+    /// Standard layer normalization (mean and variance, with bias)
+    LayerNorm,
+    /// Root mean square normalization
+    RmsNorm,
+    /// Scale normalization
+    ScaleNorm,
+}
+
+/// # Normalization layer of a configurable type
+/// Allows architectures to select their normalization layer from the configuration.
+#[derive(Debug)]
+pub enum Normalization {
+    LayerNorm(nn::LayerNorm),
+    RMSNorm(RMSNorm),
+    ScaleNorm(ScaleNorm),
+}
+
+impl Normalization {
+    /// Creates a new normalization layer
+    ///
+    /// # Arguments
+    ///
+    /// * `p` - Variable store path for the layer
+    /// * `norm_type` - Type of normalization layer
+    /// * `hidden_size` - Size of the last dimension of the inputs
+    /// * `config` - `NormConfig` with the layer epsilon and scale variable name (the `LayerNorm` variables are
+    /// always named `weight` and `bias`)
+    pub fn new<'p, P>(
+        p: P,
+        norm_type: NormType,
+        hidden_size: i64,
+        config: NormConfig,
+    ) -> Normalization
+    where
+        P: Borrow<nn::Path<'p>>,
+    {
+        match norm_type {
+            NormType::LayerNorm => {
+                let layer_norm_config = nn::LayerNormConfig {
+                    eps: config.eps,
+                    ..Default::default()
+                };
+                Normalization::LayerNorm(nn::layer_norm(
+                    p.borrow(),
+                    vec![hidden_size],
+                    layer_norm_config,
+                ))
+            }
+            NormType::RmsNorm => Normalization::RMSNorm(RMSNorm::new(p, hidden_size, config)),
+            NormType::ScaleNorm => Normalization::ScaleNorm(ScaleNorm::new(p, hidden_size, config)),
+        }
+    }
+}
+
+impl Module for Normalization {
+    fn forward(&self, x: &Tensor) -> Tensor {
+        match self {
+            Normalization::LayerNorm(layer_norm) => layer_norm.forward(x),
+            Normalization::RMSNorm(rms_norm) => rms_norm.forward(x),
+            Normalization::ScaleNorm(scale_norm) => scale_norm.forward(x),
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use tch::nn::VarStore;
+    use tch::Device;
+
+    #[test]
+    fn rms_norm() {
+        let vs = VarStore::new(Device::Cpu);
+        let config = NormConfig {
+            weight_name: "scale",
+            ..Default::default()
+        };
+        let rms_norm = RMSNorm::new(vs.root() / "norm", 4, config);
+        assert!(vs.variables().contains_key("norm.scale"));
+
+        let input = Tensor::of_slice(&[1.0f32, -2.0, 3.0, -4.0, 0.5, 0.5, 0.5, 0.5]).view([2, 4]);
+        let output = rms_norm.forward(&input);
+        let root_mean_square = output
+            .pow_tensor_scalar(2.0)
+            .mean_dim(&[-1], false, Kind::Float)
+            .sqrt();
+        assert!(
+            (root_mean_square - Tensor::ones(&[2], (Kind::Float, Device::Cpu)))
+                .abs()
+                .max()
+                .double_value(&[])
+                < 1e-4
+        );
+        // No mean subtraction
+        assert!(output.double_value(&[1, 0]) > 0.0);
+    }
+
+    #[test]
+    fn scale_norm() {
+        let vs = VarStore::new(Device::Cpu);
+        let scale_norm = ScaleNorm::new(vs.root(), 4, Default::default());
+
+        let input = Tensor::of_slice(&[3.0f32, 4.0, 0.0, 0.0]).view([1, 4]);
+        let output = scale_norm.forward(&input);
+        let expected = Tensor::of_slice(&[1.2f32, 1.6, 0.0, 0.0]).view([1, 4]);
+        assert!((output - expected).abs().max().double_value(&[]) < 1e-5);
+    }
+}
@@ -0,0 +1,151 @@
+// Copyright 2022 Guillaume Becquin
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//     http://www.apache.org/licenses/LICENSE-2.0
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! # Offset conversion utilities
+//! The tokenizers return offsets expressed in Unicode scalar values (Rust `char`) of the input text. These
+//! do not match byte offsets (needed to slice a Rust `&str`) or UTF-16 code units offsets (used by JavaScript
+//! strings) as soon as the input contains non-ASCII characters such as accented letters, CJK characters or emoji
+//! (including multi-codepoint ZWJ sequences).
+//!
+//! ```no_run
+//! use rust_bert::offsets::OffsetConverter;
+//! use rust_tokenizers::Offset;
+//!
+//! let text = "I ❤️ 東京";
+//! let converter = OffsetConverter::new(text);
+//! let char_offset = Offset::new(5, 7);
+//! let byte_offset = converter.to_byte_offset(char_offset);
+//! assert_eq!(&text[byte_offset.begin as usize..byte_offset.end as usize], "東京");
+//! ```
+
+use rust_tokenizers::Offset;
+
+/// # Converter from character offsets to byte and UTF-16 offsets
+/// Pre-computes the byte and UTF-16 code units positions of every character of a text, allowing constant-time
+/// conversion of the character offsets returned by the pipelines. Character offsets beyond the end of the text are
+/// mapped to the end of the text.
+#[derive(Debug, Clone)]
+pub struct OffsetConverter {
+    byte_positions: Vec<u32>,
+    utf16_positions: Vec<u32>,
+}
+
+impl OffsetConverter {
+    /// Create a new `OffsetConverter` for the text provided
+    ///
+    /// # Arguments
+    ///
+    /// * `text` - Original text the character offsets refer to
+    pub fn new(text: &str) -> OffsetConverter {
+        let num_chars = text.chars().count();
+        let mut byte_positions = Vec::with_capacity(num_chars + 1);
+        let mut utf16_positions = Vec::with_capacity(num_chars + 1);
+        let mut utf16_position = 0u32;
+        for (byte_position, character) in text.char_indices() {
+            byte_positions.push(byte_position as u32);
+            utf16_positions.push(utf16_position);
+            utf16_position += character.len_utf16() as u32;
+        }
+        byte_positions.push(text.len() as u32);
+        utf16_positions.push(utf16_position);
+        OffsetConverter {
+            byte_positions,
+            utf16_positions,
+        }
+    }
+
+    fn clamp(&self, char_position: u32) -> usize {
+        (char_position as usize).min(self.byte_positions.len() - 1)
+    }
+
+    /// Returns the byte position of the character position provided
+    pub fn char_to_byte(&self, char_position: u32) -> u32 {
+        self.byte_positions[self.clamp(char_position)]
+    }
+
+    /// Returns the UTF-16 code units position of the character position provided
+    pub fn char_to_utf16(&self, char_position: u32) -> u32 {
+        self.utf16_positions[self.clamp(char_position)]
+    }
+
+    /// Converts a character offset to a byte offset, that can be used to slice the original `&str`
+    pub fn to_byte_offset(&self, char_offset: Offset) -> Offset {
+        Offset::new(
+            self.char_to_byte(char_offset.begin),
+            self.char_to_byte(char_offset.end),
+        )
+    }
+
+    /// Converts a character offset to a UTF-16 code units offset (e.g. for use with JavaScript strings)
+    pub fn to_utf16_offset(&self, char_offset: Offset) -> Offset {
+        Offset::new(
+            self.char_to_utf16(char_offset.begin),
+            self.char_to_utf16(char_offset.end),
+        )
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    const TEST_STRINGS: [&str; 8] = [
+        "",
+        "Hello world",
+        "Café déjà vu",
+        "東京は日本の首都です",
+        "I ❤️ Rust 🦀!",
+        "Family: 👨‍👩‍👧‍👦 and flags 🇫🇷🇯🇵",
+        "mixed 한국어 עברית العربية text",
+        "e\u{301}\u{200d}\u{fe0f}\u{10ffff}",
+    ];
+
+    // Checks the conversion for every possible character span of the test strings
+    #[test]
+    fn test_offsets_all_spans() {
+        for text in TEST_STRINGS {
+            let converter = OffsetConverter::new(text);
+            let chars = text.chars().collect::<Vec<char>>();
+            let utf16 = text.encode_utf16().collect::<Vec<u16>>();
+            for begin in 0..=chars.len() {
+                for end in begin..=chars.len() {
+                    let char_offset = Offset::new(begin as u32, end as u32);
+                    let expected = chars[begin..end].iter().collect::<String>();
+
+                    let byte_offset = converter.to_byte_offset(char_offset);
+                    assert_eq!(
+                        &text[byte_offset.begin as usize..byte_offset.end as usize],
+                        expected
+                    );
+
+                    let utf16_offset = converter.to_utf16_offset(char_offset);
+                    assert_eq!(
+                        String::from_utf16(
+                            &utf16[utf16_offset.begin as usize..utf16_offset.end as usize]
+                        )
+                        .unwrap(),
+                        expected
+                    );
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn test_offsets_out_of_bounds() {
+        let text = "東京 🦀";
+        let converter = OffsetConverter::new(text);
+        let byte_offset = converter.to_byte_offset(Offset::new(3, 10));
+        assert_eq!(byte_offset, Offset::new(7, 11));
+        let utf16_offset = converter.to_utf16_offset(Offset::new(3, 10));
+        assert_eq!(utf16_offset, Offset::new(3, 5));
+    }
+}
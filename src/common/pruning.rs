@@ -0,0 +1,433 @@
+// Copyright 2022 Guillaume Becquin
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//     http://www.apache.org/licenses/LICENSE-2.0
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! # Layer and attention head pruning
+//! Structured pruning of loaded encoders, trading accuracy for speed without retraining:
+//! - the top layers of the encoder can be dropped, the task heads of the model (pooler, classifier...) being
+//! re-attached to the output of the new last layer. For sequence classification models with early exit, the
+//! exit head trained on the new last layer replaces the original classifier.
+//! - only selected attention heads of a layer can be kept, the query, key and value projections and the attention
+//! output projection being sliced accordingly.
+//!
+//! The weights of the pruned attention layers are sliced in place in the variable store. The variables of the dropped
+//! layers (and of the exit heads replaced during re-attachment) are left unchanged in the variable store.
+//!
+//! The heads to prune can be selected from their importance on a dataset, measured by `gradient_head_importance`
+//! (sensitivity of a loss to a mask gating each head, [Michel et al., 2019](https://arxiv.org/abs/1905.10650)) or
+//! `leave_one_out_head_importance` (drop of a score when masking each head in turn). The `HeadImportance` returned
+//! gives the heads to retain in each layer for a number of heads to prune, to be passed to
+//! `PrunableModel::retain_heads_per_layer`.
+//!
+//! ```no_run
+//! use rust_bert::bert::{BertConfig, BertForSequenceClassification};
+//! use rust_bert::pruning::PrunableModel;
+//! use rust_bert::Config;
+//! use std::path::Path;
+//! use tch::{nn, Device};
+//!
+//! # fn main() -> anyhow::Result<()> {
+//! let config = BertConfig::from_file(Path::new("path/to/config.json"));
+//! let mut vs = nn::VarStore::new(Device::Cpu);
+//! let mut model = BertForSequenceClassification::new(vs.root(), &config);
+//! vs.load("path/to/model.ot")?;
+//!
+//! // Keep the bottom 8 layers, and 6 attention heads in the first layer
+//! model.drop_top_layers(4)?;
+//! model.retain_attention_heads(0, &[0, 2, 3, 5, 8, 11])?;
+//! # Ok(())
+//! # }
+//! ```
+//!
+//! Pruning the least important heads measured on a labelled dataset:
+//! ```no_run
+//! # use rust_bert::bert::{BertConfig, BertForSequenceClassification};
+//! # use rust_bert::Config;
+//! # use std::path::Path;
+//! # use tch::{nn, Device, Tensor};
+//! use rust_bert::pruning::{gradient_head_importance, PrunableModel};
+//!
+//! # fn main() -> anyhow::Result<()> {
+//! # let config = BertConfig::from_file(Path::new("path/to/config.json"));
+//! # let vs = nn::VarStore::new(Device::Cpu);
+//! # let mut model = BertForSequenceClassification::new(vs.root(), &config);
+//! # let batches: Vec<(Tensor, Tensor)> = vec![];
+//! // Batches of (input ids, labels)
+//! let importance = gradient_head_importance(&mut model, &batches, |model, (input_ids, labels)| {
+//!     model
+//!         .forward_t(Some(input_ids), None, None, None, None, false)
+//!         .logits
+//!         .cross_entropy_for_logits(labels)
+//! })?;
+//! model.retain_heads_per_layer(&importance.retained_heads(48))?;
+//! # Ok(())
+//! # }
+//! ```
+
+use crate::RustBertError;
+use std::cmp::Ordering;
+use tch::{nn, no_grad, Device, Kind, Tensor};
+
+/// # Prunable model
+/// Structured pruning of the layers and attention heads of an encoder model
+pub trait PrunableModel {
+    /// Returns the number of layers of the encoder
+    fn num_layers(&self) -> usize;
+
+    /// Drops the top layers of the encoder
+    ///
+    /// # Arguments
+    ///
+    /// * `num_layers` - Number of layers to remove from the top of the encoder. At least one layer must remain.
+    fn drop_top_layers(&mut self, num_layers: usize) -> Result<(), RustBertError>;
+
+    /// Keeps only the selected attention heads of a layer
+    ///
+    /// # Arguments
+    ///
+    /// * `layer_index` - Index of the layer to prune (after the layers dropped with `drop_top_layers`)
+    /// * `heads` - Indices of the attention heads to keep, among the heads currently in the layer
+    fn retain_attention_heads(
+        &mut self,
+        layer_index: usize,
+        heads: &[i64],
+    ) -> Result<(), RustBertError>;
+
+    /// Returns the number of attention heads of a layer
+    fn num_attention_heads(&self, layer_index: usize) -> Result<usize, RustBertError>;
+
+    /// Sets a mask multiplying the attention weights of each head of a layer, used to measure the importance of
+    /// the heads (see `gradient_head_importance` and `leave_one_out_head_importance`)
+    ///
+    /// # Arguments
+    ///
+    /// * `layer_index` - Index of the layer
+    /// * `head_mask` - Mask of shape (*num_heads*), with a value of 0 to mask a head and 1 to keep it. Removes the mask if `None`.
+    fn set_head_mask(
+        &mut self,
+        layer_index: usize,
+        head_mask: Option<Tensor>,
+    ) -> Result<(), RustBertError>;
+
+    /// Keeps only the selected attention heads of each layer
+    ///
+    /// # Arguments
+    ///
+    /// * `heads` - Indices of the attention heads to keep for each layer of the encoder (e.g. computed by
+    /// `HeadImportance::retained_heads`). The layers keeping all their heads are left unchanged.
+    fn retain_heads_per_layer(&mut self, heads: &[Vec<i64>]) -> Result<(), RustBertError> {
+        if heads.len() != self.num_layers() {
+            return Err(RustBertError::ValueError(format!(
+                "Heads provided for {} layers, the encoder has {} layers",
+                heads.len(),
+                self.num_layers()
+            )));
+        }
+        for (layer_index, layer_heads) in heads.iter().enumerate() {
+            if layer_heads.len() < self.num_attention_heads(layer_index)? {
+                self.retain_attention_heads(layer_index, layer_heads)?;
+            }
+        }
+        Ok(())
+    }
+}
+
+/// # Attention head importance
+/// Importance of each attention head of an encoder, computed on a dataset by `gradient_head_importance` or
+/// `leave_one_out_head_importance`
+#[derive(Debug, Clone)]
+pub struct HeadImportance {
+    /// Importance of each attention head, for each layer
+    pub scores: Vec<Vec<f64>>,
+}
+
+impl HeadImportance {
+    /// Returns the attention heads to retain in each layer after pruning the least important heads of the encoder
+    /// (at least one head is kept in each layer). The output can be passed to `PrunableModel::retain_heads_per_layer`.
+    ///
+    /// # Arguments
+    ///
+    /// * `num_heads_to_prune` - Number of attention heads to prune across all the layers
+    pub fn retained_heads(&self, num_heads_to_prune: usize) -> Vec<Vec<i64>> {
+        let mut heads = self
+            .scores
+            .iter()
+            .enumerate()
+            .flat_map(|(layer_index, layer_scores)| {
+                layer_scores
+                    .iter()
+                    .enumerate()
+                    .map(move |(head_index, score)| (layer_index, head_index, *score))
+            })
+            .collect::<Vec<(usize, usize, f64)>>();
+        heads.sort_by(|head_1, head_2| head_1.2.partial_cmp(&head_2.2).unwrap_or(Ordering::Equal));
+
+        let mut retained = self
+            .scores
+            .iter()
+            .map(|layer_scores| vec![true; layer_scores.len()])
+            .collect::<Vec<Vec<bool>>>();
+        let mut remaining_heads = self
+            .scores
+            .iter()
+            .map(|layer_scores| layer_scores.len())
+            .collect::<Vec<usize>>();
+        let mut num_pruned = 0;
+        for (layer_index, head_index, _) in heads {
+            if num_pruned == num_heads_to_prune {
+                break;
+            }
+            if remaining_heads[layer_index] > 1 {
+                retained[layer_index][head_index] = false;
+                remaining_heads[layer_index] -= 1;
+                num_pruned += 1;
+            }
+        }
+        retained
+            .into_iter()
+            .map(|layer_retained| {
+                layer_retained
+                    .into_iter()
+                    .enumerate()
+                    .filter(|(_, keep)| *keep)
+                    .map(|(head_index, _)| head_index as i64)
+                    .collect()
+            })
+            .collect()
+    }
+}
+
+/// Computes the importance of the attention heads of a model as the sensitivity of a loss to a mask gating each head
+/// ([Michel et al., 2019](https://arxiv.org/abs/1905.10650)): the importance of a head is the sum over the batches
+/// of the absolute value of the gradient of the loss with respect to its mask.
+///
+/// The gradients of the model weights are also accumulated in the variable store: freezing the variable store
+/// (`VarStore::freeze`) beforehand avoids this, the gradients of the head masks being still computed.
+///
+/// # Arguments
+///
+/// * `model` - Model to analyze
+/// * `batches` - Dataset on which the importance is measured
+/// * `loss_fn` - Function computing the scalar loss of the model on a batch (with gradients enabled)
+///
+/// # Returns
+///
+/// * `HeadImportance` importance of each head of each layer
+pub fn gradient_head_importance<M, B, F>(
+    model: &mut M,
+    batches: &[B],
+    mut loss_fn: F,
+) -> Result<HeadImportance, RustBertError>
+where
+    M: PrunableModel,
+    F: FnMut(&M, &B) -> Tensor,
+{
+    let mut head_masks = Vec::with_capacity(model.num_layers());
+    for layer_index in 0..model.num_layers() {
+        let num_heads = model.num_attention_heads(layer_index)? as i64;
+        let head_mask =
+            Tensor::ones(&[num_heads], (Kind::Float, Device::Cpu)).set_requires_grad(true);
+        model.set_head_mask(layer_index, Some(head_mask.shallow_clone()))?;
+        head_masks.push(head_mask);
+    }
+
+    let mut scores = head_masks
+        .iter()
+        .map(|head_mask| vec![0f64; head_mask.size()[0] as usize])
+        .collect::<Vec<Vec<f64>>>();
+    for batch in batches {
+        loss_fn(&*model, batch).backward();
+        for (layer_scores, head_mask) in scores.iter_mut().zip(head_masks.iter()) {
+            let mut gradient = head_mask.grad();
+            for (score, head_gradient) in layer_scores
+                .iter_mut()
+                .zip(Vec::<f64>::from(gradient.abs().to_kind(Kind::Double)))
+            {
+                *score += head_gradient;
+            }
+            let _ = gradient.zero_();
+        }
+    }
+
+    for layer_index in 0..model.num_layers() {
+        model.set_head_mask(layer_index, None)?;
+    }
+    Ok(HeadImportance { scores })
+}
+
+/// Computes the importance of the attention heads of a model as the drop of a score when masking each head in turn.
+/// Runs the model on the dataset once per attention head (plus a reference run without masking).
+///
+/// # Arguments
+///
+/// * `model` - Model to analyze
+/// * `batches` - Dataset on which the importance is measured
+/// * `score_fn` - Function computing a score of the model on a batch, higher being better (e.g. accuracy or
+/// negative loss). Gradients do not need to be tracked.
+///
+/// # Returns
+///
+/// * `HeadImportance` importance of each head of each layer (reference score minus the score with the head masked)
+pub fn leave_one_out_head_importance<M, B, F>(
+    model: &mut M,
+    batches: &[B],
+    mut score_fn: F,
+) -> Result<HeadImportance, RustBertError>
+where
+    M: PrunableModel,
+    F: FnMut(&M, &B) -> f64,
+{
+    let mut dataset_score = |model: &M| {
+        no_grad(|| {
+            batches
+                .iter()
+                .map(|batch| score_fn(model, batch))
+                .sum::<f64>()
+        })
+    };
+    let reference_score = dataset_score(&*model);
+
+    let mut scores = Vec::with_capacity(model.num_layers());
+    for layer_index in 0..model.num_layers() {
+        let num_heads = model.num_attention_heads(layer_index)? as i64;
+        let mut layer_scores = Vec::with_capacity(num_heads as usize);
+        for head_index in 0..num_heads {
+            let head_mask = Tensor::ones(&[num_heads], (Kind::Float, Device::Cpu));
+            let _ = head_mask.get(head_index).fill_(0.0);
+            model.set_head_mask(layer_index, Some(head_mask))?;
+            layer_scores.push(reference_score - dataset_score(&*model));
+        }
+        model.set_head_mask(layer_index, None)?;
+        scores.push(layer_scores);
+    }
+    Ok(HeadImportance { scores })
+}
+
+/// Returns the number of layers remaining after dropping `num_layers_to_drop` top layers
+pub(crate) fn get_remaining_layers(
+    num_layers: usize,
+    num_layers_to_drop: usize,
+) -> Result<usize, RustBertError> {
+    if num_layers_to_drop >= num_layers {
+        return Err(RustBertError::ValueError(format!(
+            "Cannot drop {} layers of an encoder with {} layers, at least one layer must remain",
+            num_layers_to_drop, num_layers
+        )));
+    }
+    Ok(num_layers - num_layers_to_drop)
+}
+
+/// Returns the indices of the dimensions of the attention projections belonging to the retained heads
+pub(crate) fn get_head_dimensions(
+    heads: &[i64],
+    num_heads: i64,
+    head_size: i64,
+    device: Device,
+) -> Result<Tensor, RustBertError> {
+    if heads.is_empty() {
+        return Err(RustBertError::ValueError(
+            "At least one attention head must be retained".to_string(),
+        ));
+    }
+    let mut sorted_heads = heads.to_vec();
+    sorted_heads.sort_unstable();
+    sorted_heads.dedup();
+    if sorted_heads.len() != heads.len() {
+        return Err(RustBertError::ValueError(format!(
+            "Duplicate attention heads in {:?}",
+            heads
+        )));
+    }
+    if let Some(head) = heads
+        .iter()
+        .find(|head| (**head < 0) | (**head >= num_heads))
+    {
+        return Err(RustBertError::ValueError(format!(
+            "Attention head {} out of range for a layer with {} heads",
+            head, num_heads
+        )));
+    }
+    let heads = Tensor::of_slice(&sorted_heads).to(device);
+    let offsets = Tensor::arange(head_size, (Kind::Int64, device));
+    Ok((heads.unsqueeze(-1) * head_size + offsets.unsqueeze(0)).view([-1]))
+}
+
+/// Keeps the given indices of the weight (and bias, for the output dimension) of a linear layer
+///
+/// # Arguments
+///
+/// * `linear` - Linear layer to prune
+/// * `index` - Indices to keep
+/// * `output_dimension` - Prunes the output dimension of the layer if true, the input dimension otherwise
+pub(crate) fn prune_linear(linear: &mut nn::Linear, index: &Tensor, output_dimension: bool) {
+    no_grad(|| {
+        let weight_dim = if output_dimension { 0 } else { 1 };
+        let pruned_weight = linear.ws.index_select(weight_dim, index);
+        linear.ws.set_data(&pruned_weight);
+        if output_dimension {
+            if let Some(bias) = linear.bs.as_mut() {
+                let pruned_bias = bias.index_select(0, index);
+                bias.set_data(&pruned_bias);
+            }
+        }
+    });
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn head_dimensions() {
+        let dimensions = get_head_dimensions(&[2, 0], 4, 3, Device::Cpu).unwrap();
+        assert_eq!(Vec::<i64>::from(dimensions), vec![0, 1, 2, 6, 7, 8]);
+
+        assert!(get_head_dimensions(&[], 4, 3, Device::Cpu).is_err());
+        assert!(get_head_dimensions(&[1, 1], 4, 3, Device::Cpu).is_err());
+        assert!(get_head_dimensions(&[4], 4, 3, Device::Cpu).is_err());
+        assert!(get_remaining_layers(4, 4).is_err());
+        assert_eq!(get_remaining_layers(12, 4).unwrap(), 8);
+    }
+
+    #[test]
+    fn retained_heads() {
+        let importance = HeadImportance {
+            scores: vec![vec![0.5, 0.1, 0.3], vec![0.05, 0.02, 0.9]],
+        };
+        assert_eq!(
+            importance.retained_heads(0),
+            vec![vec![0, 1, 2], vec![0, 1, 2]]
+        );
+        assert_eq!(importance.retained_heads(2), vec![vec![0, 1, 2], vec![2]]);
+        // At least one head remains in each layer
+        assert_eq!(importance.retained_heads(5), vec![vec![0], vec![2]]);
+    }
+
+    #[test]
+    fn linear_pruning() {
+        let vs = nn::VarStore::new(Device::Cpu);
+        let mut linear = nn::linear(vs.root() / "linear", 4, 6, Default::default());
+        let weight = linear.ws.copy();
+        let index = Tensor::of_slice(&[1i64, 4]);
+
+        prune_linear(&mut linear, &index, true);
+        assert_eq!(linear.ws.size(), [2, 4]);
+        assert_eq!(linear.bs.as_ref().unwrap().size(), [2]);
+        assert!(linear
+            .ws
+            .allclose(&weight.index_select(0, &index), 0.0, 0.0, false));
+        // The variable store holds the pruned weights
+        assert_eq!(vs.variables()["linear.weight"].size(), [2, 4]);
+
+        prune_linear(&mut linear, &Tensor::of_slice(&[0i64, 3]), false);
+        assert_eq!(linear.ws.size(), [2, 2]);
+        assert_eq!(linear.bs.as_ref().unwrap().size(), [2]);
+    }
+}
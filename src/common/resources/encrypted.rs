@@ -0,0 +1,220 @@
+use crate::common::error::RustBertError;
+use crate::resources::{Resource, ResourceProvider};
+use aes_gcm::aead::{Aead, AeadCore, KeyInit, OsRng};
+use aes_gcm::{Aes128Gcm, Aes256Gcm, Nonce};
+use std::fs;
+use std::path::{Path, PathBuf};
+
+/// Header of the files encrypted by `encrypt_resource`
+const ENCRYPTED_HEADER: &[u8] = b"RUSTBERT-AES-GCM";
+
+/// Size of the AES-GCM nonce, stored after the header
+const NONCE_SIZE: usize = 12;
+
+/// AES-GCM cipher, selected from the length of the key
+enum Cipher {
+    Aes128(Box<Aes128Gcm>),
+    Aes256(Box<Aes256Gcm>),
+}
+
+impl Cipher {
+    fn new(key: &[u8]) -> Result<Cipher, RustBertError> {
+        match key.len() {
+            16 => Ok(Cipher::Aes128(Box::new(Aes128Gcm::new_from_slice(key).unwrap()))),
+            32 => Ok(Cipher::Aes256(Box::new(Aes256Gcm::new_from_slice(key).unwrap()))),
+            length => Err(RustBertError::InvalidConfigurationError(format!(
+                "Invalid AES key length: {} bytes (expected 16 bytes for AES-128 or 32 bytes for AES-256)",
+                length
+            ))),
+        }
+    }
+
+    fn encrypt(&self, nonce: &Nonce, plaintext: &[u8]) -> Result<Vec<u8>, aes_gcm::Error> {
+        match self {
+            Cipher::Aes128(cipher) => cipher.encrypt(nonce, plaintext),
+            Cipher::Aes256(cipher) => cipher.encrypt(nonce, plaintext),
+        }
+    }
+
+    fn decrypt(&self, nonce: &Nonce, ciphertext: &[u8]) -> Result<Vec<u8>, aes_gcm::Error> {
+        match self {
+            Cipher::Aes128(cipher) => cipher.decrypt(nonce, ciphertext),
+            Cipher::Aes256(cipher) => cipher.decrypt(nonce, ciphertext),
+        }
+    }
+}
+
+/// # Resource encrypted with AES-GCM
+/// Wraps a resource pointing to a file encrypted with `encrypt_resource` (for example the weights of a proprietary
+/// model shipped with an application). The file is decrypted in memory by `get_resource`, with a key returned by a
+/// user-supplied callback (for example read from a key management service or derived from a license), so that the
+/// plaintext content is never written to the disk. The model weights are loaded from the decrypted buffer by
+/// `load_weights`.
+///
+/// The authentication tag of AES-GCM is verified during the decryption: a wrong key or a modified file results in a
+/// `RustBertError::ResourceIntegrityError`.
+pub struct EncryptedResource {
+    /// Resource pointing to the encrypted file
+    pub resource: Box<dyn ResourceProvider + Send>,
+    key_callback: Box<dyn Fn(&Path) -> Result<Vec<u8>, RustBertError> + Send + Sync>,
+}
+
+impl EncryptedResource {
+    /// Creates a new `EncryptedResource`.
+    ///
+    /// # Arguments
+    ///
+    /// * `resource` - `ResourceProvider` pointing to the encrypted file (local or remote)
+    /// * `key_callback` - Function returning the AES key (16 bytes for AES-128, 32 bytes for AES-256) given the
+    /// local path of the encrypted file. It is called each time the resource is decrypted.
+    ///
+    /// # Example
+    ///
+    /// ```no_run
+    /// use rust_bert::pipelines::text_generation::TextGenerationConfig;
+    /// use rust_bert::resources::{EncryptedResource, LocalResource};
+    /// use std::path::PathBuf;
+    ///
+    /// let model_resource = EncryptedResource::new(
+    ///     LocalResource {
+    ///         local_path: PathBuf::from("path/to/model.ot.enc"),
+    ///     },
+    ///     |_path| Ok(std::env::var("MODEL_KEY").unwrap().into_bytes()),
+    /// );
+    /// let text_generation_config = TextGenerationConfig {
+    ///     model_resource: Box::new(model_resource),
+    ///     ..Default::default()
+    /// };
+    /// ```
+    pub fn new<R, F>(resource: R, key_callback: F) -> EncryptedResource
+    where
+        R: ResourceProvider + Send + 'static,
+        F: Fn(&Path) -> Result<Vec<u8>, RustBertError> + Send + Sync + 'static,
+    {
+        EncryptedResource {
+            resource: Box::new(resource),
+            key_callback: Box::new(key_callback),
+        }
+    }
+}
+
+impl ResourceProvider for EncryptedResource {
+    /// Encrypted resources are decrypted in memory and have no local path: this always returns an error. Use
+    /// `get_resource` (or `load_weights` for model weights) instead.
+    fn get_local_path(&self) -> Result<PathBuf, RustBertError> {
+        Err(RustBertError::ValueError(
+            "Encrypted resources are decrypted in memory and have no local path, use `get_resource` or `load_weights`"
+                .to_string(),
+        ))
+    }
+
+    /// Decrypts the resource in memory.
+    ///
+    /// # Returns
+    ///
+    /// * `Resource::Buffer` containing the decrypted content
+    fn get_resource(&self) -> Result<Resource, RustBertError> {
+        let encrypted_path = self.resource.get_local_path()?;
+        let key = (self.key_callback)(&encrypted_path)?;
+        let content = fs::read(&encrypted_path)?;
+        Ok(Resource::Buffer(decrypt(&encrypted_path, &content, &key)?))
+    }
+}
+
+/// Decrypts the content of a file encrypted by `encrypt_resource`
+fn decrypt(path: &Path, content: &[u8], key: &[u8]) -> Result<Vec<u8>, RustBertError> {
+    let header_size = ENCRYPTED_HEADER.len() + NONCE_SIZE;
+    if (content.len() < header_size) || !content.starts_with(ENCRYPTED_HEADER) {
+        return Err(RustBertError::ValueError(format!(
+            "{:?} was not encrypted with `encrypt_resource`",
+            path
+        )));
+    }
+    let nonce = Nonce::from_slice(&content[ENCRYPTED_HEADER.len()..header_size]);
+    Cipher::new(key)?
+        .decrypt(nonce, &content[header_size..])
+        .map_err(|_| {
+            RustBertError::ResourceIntegrityError(format!(
+                "Decryption of {:?} failed (wrong key or modified file)",
+                path
+            ))
+        })
+}
+
+/// Encrypts a file with AES-GCM (with a random nonce) so that it can be loaded with an `EncryptedResource`.
+///
+/// # Arguments
+///
+/// * `input_path` - Path to the file to encrypt (e.g. `model.ot`)
+/// * `output_path` - Path to the encrypted file to create
+/// * `key` - AES key (16 bytes for AES-128, 32 bytes for AES-256)
+///
+/// # Example
+///
+/// ```no_run
+/// # fn main() -> anyhow::Result<()> {
+/// use rust_bert::resources::encrypt_resource;
+/// use std::path::Path;
+///
+/// let key = std::env::var("MODEL_KEY")?.into_bytes();
+/// encrypt_resource(Path::new("model.ot"), Path::new("model.ot.enc"), &key)?;
+/// # Ok(())
+/// # }
+/// ```
+pub fn encrypt_resource(
+    input_path: &Path,
+    output_path: &Path,
+    key: &[u8],
+) -> Result<(), RustBertError> {
+    let cipher = Cipher::new(key)?;
+    let nonce = Aes256Gcm::generate_nonce(&mut OsRng);
+    let ciphertext = cipher
+        .encrypt(&nonce, &fs::read(input_path)?)
+        .map_err(|_| RustBertError::ValueError(format!("Could not encrypt {:?}", input_path)))?;
+    let mut content = Vec::with_capacity(ENCRYPTED_HEADER.len() + NONCE_SIZE + ciphertext.len());
+    content.extend_from_slice(ENCRYPTED_HEADER);
+    content.extend_from_slice(&nonce);
+    content.extend_from_slice(&ciphertext);
+    fs::write(output_path, content)?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::resources::LocalResource;
+
+    #[test]
+    fn test_encrypted_resource() {
+        let directory = tempfile::tempdir().unwrap();
+        let input_path = directory.path().join("model.ot");
+        let encrypted_path = directory.path().join("model.ot.enc");
+        fs::write(&input_path, b"model weights").unwrap();
+        encrypt_resource(&input_path, &encrypted_path, &[7; 32]).unwrap();
+        assert!(!fs::read(&encrypted_path)
+            .unwrap()
+            .windows(13)
+            .any(|window| window == b"model weights"));
+
+        let local_resource = LocalResource {
+            local_path: encrypted_path.clone(),
+        };
+        let resource = EncryptedResource::new(local_resource.clone(), |_| Ok(vec![7; 32]));
+        assert!(resource.get_local_path().is_err());
+        match resource.get_resource().unwrap() {
+            Resource::Buffer(content) => assert_eq!(content, b"model weights"),
+            Resource::PathBuf(_) => panic!("Encrypted resources should be decrypted in memory"),
+        }
+
+        let resource = EncryptedResource::new(local_resource.clone(), |_| Ok(vec![8; 32]));
+        assert!(matches!(
+            resource.get_resource(),
+            Err(RustBertError::ResourceIntegrityError(_))
+        ));
+        let resource = EncryptedResource::new(local_resource, |_| Ok(vec![7; 24]));
+        assert!(matches!(
+            resource.get_resource(),
+            Err(RustBertError::InvalidConfigurationError(_))
+        ));
+    }
+}
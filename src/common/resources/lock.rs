@@ -0,0 +1,277 @@
+use crate::common::error::RustBertError;
+use lazy_static::lazy_static;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::collections::BTreeMap;
+use std::fs::{self, File};
+use std::io;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+
+/// Version of the lockfile format
+const LOCKFILE_VERSION: u32 = 1;
+
+/// Default name of the lockfile
+pub const DEFAULT_LOCKFILE: &str = "rustbert.lock";
+
+/// # Behaviour of a `ResourceLock` when a resource is not locked or does not match the lockfile
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LockMode {
+    /// Records the resources that are not locked yet, and updates the entries of resources that changed
+    Update,
+    /// Fails with a `RustBertError::ResourceIntegrityError` if a resource is not locked or does not match its
+    /// checksum. The lockfile is never modified.
+    Strict,
+}
+
+/// # Resolution of a remote resource recorded in a lockfile
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct LockedResource {
+    /// Local subdirectory of the cache root where the resource is saved
+    pub cache_subdir: String,
+    /// Revision of the resource (the ETag returned by the server when it was downloaded), if any
+    pub revision: Option<String>,
+    /// SHA256 of the downloaded file (hexadecimal)
+    pub sha256: String,
+}
+
+/// # Content of a lockfile
+/// The resources are indexed by their URL.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Lockfile {
+    /// Version of the lockfile format
+    pub version: u32,
+    /// Locked resources, by URL
+    pub resources: BTreeMap<String, LockedResource>,
+}
+
+impl Default for Lockfile {
+    fn default() -> Lockfile {
+        Lockfile {
+            version: LOCKFILE_VERSION,
+            resources: BTreeMap::new(),
+        }
+    }
+}
+
+/// # Lockfile for reproducible model resolution
+/// Records the URL, revision and SHA256 of the remote resources resolved by `RemoteResource::get_local_path`, and
+/// checks the resources resolved afterwards against the recorded checksums. The lockfile is read at each resolution,
+/// so that it can be shared between processes (only one of them should run in `LockMode::Update`).
+///
+/// The checksums are computed after the resources are downloaded or found in the cache: a file of the cache
+/// modified after its download is detected as well.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ResourceLock {
+    /// Path to the lockfile
+    pub path: PathBuf,
+    /// Behaviour for resources missing from the lockfile or not matching it
+    pub mode: LockMode,
+}
+
+impl ResourceLock {
+    /// Create a new `ResourceLock`. The lockfile is created by the first resolution in `LockMode::Update` if it
+    /// does not exist yet.
+    ///
+    /// # Arguments
+    ///
+    /// * `path` - Path to the lockfile (usually `rustbert.lock`)
+    /// * `mode` - `LockMode` for resources missing from the lockfile or not matching it
+    pub fn new<P: AsRef<Path>>(path: P, mode: LockMode) -> ResourceLock {
+        ResourceLock {
+            path: path.as_ref().to_path_buf(),
+            mode,
+        }
+    }
+
+    /// Reads the lockfile, returning an empty `Lockfile` if it does not exist yet
+    pub fn read(&self) -> Result<Lockfile, RustBertError> {
+        if !self.path.is_file() {
+            return Ok(Lockfile::default());
+        }
+        let lockfile: Lockfile =
+            serde_json::from_str(&fs::read_to_string(&self.path)?).map_err(|error| {
+                RustBertError::InvalidConfigurationError(format!(
+                    "Invalid lockfile {:?}: {}",
+                    self.path, error
+                ))
+            })?;
+        if lockfile.version != LOCKFILE_VERSION {
+            return Err(RustBertError::InvalidConfigurationError(format!(
+                "Unsupported lockfile version {} in {:?}",
+                lockfile.version, self.path
+            )));
+        }
+        Ok(lockfile)
+    }
+
+    fn write(&self, lockfile: &Lockfile) -> Result<(), RustBertError> {
+        let mut content = serde_json::to_string_pretty(lockfile)
+            .map_err(|error| RustBertError::IOError(error.to_string()))?;
+        content.push('\n');
+        fs::write(&self.path, content)?;
+        Ok(())
+    }
+
+    /// Checks a resolved resource against the lockfile, recording it in `LockMode::Update`.
+    ///
+    /// # Arguments
+    ///
+    /// * `url` - URL of the resource
+    /// * `cache_subdir` - Local subdirectory of the cache root where the resource is saved
+    /// * `local_path` - Path to the downloaded resource
+    pub fn verify(
+        &self,
+        url: &str,
+        cache_subdir: &str,
+        local_path: &Path,
+    ) -> Result<(), RustBertError> {
+        let mut lockfile = self.read()?;
+        let resolved = LockedResource {
+            cache_subdir: cache_subdir.to_string(),
+            revision: cached_revision(local_path),
+            sha256: sha256(local_path)?,
+        };
+        match (lockfile.resources.get(url), self.mode) {
+            (Some(locked), _) if locked.sha256 == resolved.sha256 => Ok(()),
+            (Some(locked), LockMode::Strict) => {
+                Err(RustBertError::ResourceIntegrityError(format!(
+                    "{} has SHA256 {} (revision {}), {:?} expects {} (revision {})",
+                    url,
+                    resolved.sha256,
+                    resolved.revision.as_deref().unwrap_or("unknown"),
+                    self.path,
+                    locked.sha256,
+                    locked.revision.as_deref().unwrap_or("unknown")
+                )))
+            }
+            (None, LockMode::Strict) => Err(RustBertError::ResourceIntegrityError(format!(
+                "{} is not locked in {:?}",
+                url, self.path
+            ))),
+            (_, LockMode::Update) => {
+                lockfile.resources.insert(url.to_string(), resolved);
+                self.write(&lockfile)
+            }
+        }
+    }
+}
+
+/// Returns the SHA256 of a file (hexadecimal)
+fn sha256(path: &Path) -> Result<String, RustBertError> {
+    let mut hasher = Sha256::new();
+    io::copy(&mut File::open(path)?, &mut hasher)?;
+    Ok(format!("{:x}", hasher.finalize()))
+}
+
+/// Returns the ETag recorded by the cache when the resource was downloaded
+fn cached_revision(local_path: &Path) -> Option<String> {
+    let mut meta_path = local_path.as_os_str().to_os_string();
+    meta_path.push(".meta");
+    let meta: serde_json::Value =
+        serde_json::from_str(&fs::read_to_string(meta_path).ok()?).ok()?;
+    meta.get("etag")?
+        .as_str()
+        .map(|etag| etag.trim_matches('"').to_string())
+}
+
+/// Creates the `ResourceLock` set by the environment: the lockfile path is read from `RUSTBERT_LOCK` and the
+/// strict mode is enabled if `RUSTBERT_LOCK_STRICT` is set to `1` or `true`
+fn resource_lock_from_env() -> Option<ResourceLock> {
+    let path = std::env::var("RUSTBERT_LOCK").ok()?;
+    let mode = match std::env::var("RUSTBERT_LOCK_STRICT").as_deref() {
+        Ok("1") | Ok("true") => LockMode::Strict,
+        _ => LockMode::Update,
+    };
+    Some(ResourceLock::new(path, mode))
+}
+
+lazy_static! {
+    static ref RESOURCE_LOCK: Mutex<Option<ResourceLock>> = Mutex::new(resource_lock_from_env());
+}
+
+/// Sets the `ResourceLock` checking the remote resources resolved by the process, replacing the lock set by the
+/// `RUSTBERT_LOCK` and `RUSTBERT_LOCK_STRICT` environment variables. `None` disables the lock.
+///
+/// # Arguments
+///
+/// * `lock` - Optional `ResourceLock`
+///
+/// # Example
+///
+/// ```no_run
+/// use rust_bert::resources::{set_resource_lock, LockMode, ResourceLock, DEFAULT_LOCKFILE};
+///
+/// set_resource_lock(Some(ResourceLock::new(DEFAULT_LOCKFILE, LockMode::Strict)));
+/// ```
+pub fn set_resource_lock(lock: Option<ResourceLock>) {
+    *RESOURCE_LOCK.lock().unwrap() = lock;
+}
+
+/// Returns the `ResourceLock` checking the remote resources resolved by the process, if any
+pub fn resource_lock() -> Option<ResourceLock> {
+    RESOURCE_LOCK.lock().unwrap().clone()
+}
+
+/// Checks a resolved remote resource against the lock of the process, if any
+pub(crate) fn verify_resource(
+    url: &str,
+    cache_subdir: &str,
+    local_path: &Path,
+) -> Result<(), RustBertError> {
+    match RESOURCE_LOCK.lock().unwrap().as_ref() {
+        Some(lock) => lock.verify(url, cache_subdir, local_path),
+        None => Ok(()),
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    const URL: &str = "https://huggingface.co/test/resolve/main/config.json";
+
+    #[test]
+    fn test_resource_lock() {
+        let directory = tempfile::tempdir().unwrap();
+        let resource_path = directory.path().join("config.json");
+        fs::write(&resource_path, "hello").unwrap();
+        fs::write(
+            directory.path().join("config.json.meta"),
+            r#"{"resource": "config.json", "etag": "\"abcd\""}"#,
+        )
+        .unwrap();
+        let lock_path = directory.path().join(DEFAULT_LOCKFILE);
+
+        let strict = ResourceLock::new(&lock_path, LockMode::Strict);
+        assert!(matches!(
+            strict.verify(URL, "test/config", &resource_path),
+            Err(RustBertError::ResourceIntegrityError(_))
+        ));
+        assert!(!lock_path.exists());
+
+        let update = ResourceLock::new(&lock_path, LockMode::Update);
+        update.verify(URL, "test/config", &resource_path).unwrap();
+        let locked = update.read().unwrap().resources[URL].clone();
+        assert_eq!(
+            locked,
+            LockedResource {
+                cache_subdir: "test/config".to_string(),
+                revision: Some("abcd".to_string()),
+                sha256: "2cf24dba5fb0a30e26e83b2ac5b9e29e1b161e5c1fa7425e73043362938b9824"
+                    .to_string(),
+            }
+        );
+        strict.verify(URL, "test/config", &resource_path).unwrap();
+
+        // Modified resource
+        fs::write(&resource_path, "hello!").unwrap();
+        assert!(matches!(
+            strict.verify(URL, "test/config", &resource_path),
+            Err(RustBertError::ResourceIntegrityError(_))
+        ));
+        update.verify(URL, "test/config", &resource_path).unwrap();
+        assert_ne!(update.read().unwrap().resources[URL], locked);
+        strict.verify(URL, "test/config", &resource_path).unwrap();
+    }
+}
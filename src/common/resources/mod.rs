@@ -16,12 +16,36 @@
 //! `get_local_path`, allowing to reference the resource file location regardless if it is a remote
 //! or local resource. Default implementations for a number of `RemoteResources` are available as
 //! pre-trained models in each model module.
+//!
+//! For reproducible model resolution, a `ResourceLock` records the URL, revision and SHA256 of the remote
+//! resources in a lockfile (`rustbert.lock`), and checks the resources resolved afterwards against it. In
+//! `LockMode::Strict`, resolving a resource that is not locked or does not match its checksum fails. The lock is
+//! set with `set_resource_lock`, or with the `RUSTBERT_LOCK` (path to the lockfile) and `RUSTBERT_LOCK_STRICT`
+//! (`1` or `true`) environment variables.
+//!
+//! With the `encryption` feature, an `EncryptedResource` wraps a resource pointing to model weights encrypted with
+//! AES-GCM (see `encrypt_resource`). The weights are decrypted in memory with a key provided by a callback when the
+//! model is loaded (`load_weights`), and are never written in plaintext to the disk.
+//!
+//! With the `signature` feature, a `SignatureVerifier` set with `set_signature_verifier` checks that the weights
+//! loaded by `load_weights` are signed (ed25519 signature of their SHA256) by one of the trusted keys of the
+//! deployment, so that only approved model binaries are executed.
 
 mod local;
 
 use crate::common::error::RustBertError;
 pub use local::LocalResource;
+use std::io::Cursor;
 use std::path::PathBuf;
+use tch::nn::VarStore;
+
+/// # Content of a resource
+pub enum Resource {
+    /// Resource stored in a local file
+    PathBuf(PathBuf),
+    /// Resource held in memory (e.g. decrypted model weights)
+    Buffer(Vec<u8>),
+}
 
 /// # Resource Trait that can provide the location of the model, configuration or vocabulary resources
 pub trait ResourceProvider {
@@ -42,9 +66,86 @@ pub trait ResourceProvider {
     /// let config_path = config_resource.get_local_path();
     /// ```
     fn get_local_path(&self) -> Result<PathBuf, RustBertError>;
+
+    /// Provides the resource, either as a local file or as an in-memory buffer. Defaults to the local path of the
+    /// resource.
+    ///
+    /// # Returns
+    ///
+    /// * `Resource` containing the resource path or content
+    fn get_resource(&self) -> Result<Resource, RustBertError> {
+        Ok(Resource::PathBuf(self.get_local_path()?))
+    }
+}
+
+impl<T: ResourceProvider + ?Sized> ResourceProvider for Box<T> {
+    fn get_local_path(&self) -> Result<PathBuf, RustBertError> {
+        T::get_local_path(self)
+    }
+
+    fn get_resource(&self) -> Result<Resource, RustBertError> {
+        T::get_resource(self)
+    }
+}
+
+/// Loads the model weights provided by a resource into a `VarStore`. In-memory resources (e.g. decrypted by an
+/// `EncryptedResource`) are loaded without being written to the disk. With the `signature` feature, the weights are
+/// checked by the `SignatureVerifier` of the process, if any.
+///
+/// # Arguments
+///
+/// * `resource` - `ResourceProvider` pointing to the model weights
+/// * `var_store` - `VarStore` of the model
+///
+/// # Example
+///
+/// ```no_run
+/// # fn main() -> anyhow::Result<()> {
+/// use rust_bert::resources::{load_weights, LocalResource};
+/// use std::path::PathBuf;
+/// use tch::{nn, Device};
+///
+/// let weights_resource = LocalResource {
+///     local_path: PathBuf::from("path/to/model.ot"),
+/// };
+/// let mut var_store = nn::VarStore::new(Device::Cpu);
+/// // Create the model with `var_store.root()` before loading its weights
+/// load_weights(&weights_resource, &mut var_store)?;
+/// # Ok(())
+/// # }
+/// ```
+pub fn load_weights<R: ResourceProvider + ?Sized>(
+    resource: &R,
+    var_store: &mut VarStore,
+) -> Result<(), RustBertError> {
+    let resource = resource.get_resource()?;
+    #[cfg(feature = "signature")]
+    signature::verify_signature(&resource)?;
+    match resource {
+        Resource::PathBuf(path) => var_store.load(path)?,
+        Resource::Buffer(content) => var_store.load_from_stream(Cursor::new(content))?,
+    }
+    Ok(())
 }
 
+#[cfg(feature = "remote")]
+mod lock;
 #[cfg(feature = "remote")]
 mod remote;
 #[cfg(feature = "remote")]
+pub use lock::{
+    resource_lock, set_resource_lock, LockMode, LockedResource, Lockfile, ResourceLock,
+    DEFAULT_LOCKFILE,
+};
+#[cfg(feature = "remote")]
 pub use remote::RemoteResource;
+
+#[cfg(feature = "encryption")]
+mod encrypted;
+#[cfg(feature = "encryption")]
+pub use encrypted::{encrypt_resource, EncryptedResource};
+
+#[cfg(feature = "signature")]
+mod signature;
+#[cfg(feature = "signature")]
+pub use signature::{set_signature_verifier, sign_resource, SignatureManifest, SignatureVerifier};
@@ -72,7 +72,7 @@ impl ResourceProvider for RemoteResource {
     /// Gets the local path for a remote resource.
     ///
     /// The remote resource is downloaded and cached. Then the path
-    /// to the local cache is returned.
+    /// to the local cache is returned. If a `ResourceLock` is set, the resource is checked against the lockfile.
     ///
     /// # Returns
     ///
@@ -91,6 +91,7 @@ impl ResourceProvider for RemoteResource {
     fn get_local_path(&self) -> Result<PathBuf, RustBertError> {
         let cached_path = CACHE
             .cached_path_with_options(&self.url, &Options::default().subdir(&self.cache_subdir))?;
+        super::lock::verify_resource(&self.url, &self.cache_subdir, &cached_path)?;
         Ok(cached_path)
     }
 }
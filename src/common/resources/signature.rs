@@ -0,0 +1,275 @@
+use crate::common::error::RustBertError;
+use crate::resources::Resource;
+use ed25519_dalek::{Signature, Signer, SigningKey, Verifier, VerifyingKey};
+use lazy_static::lazy_static;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::collections::BTreeMap;
+use std::convert::TryInto;
+use std::fs::{self, File};
+use std::io;
+use std::path::Path;
+use std::sync::Mutex;
+
+/// # Signatures of approved model artifacts
+/// Content of a signature manifest (JSON file): the ed25519 signature of each approved artifact (hexadecimal),
+/// indexed by the SHA256 of the artifact (hexadecimal). The signatures are computed over the 32 bytes of the SHA256
+/// digest, see `sign_resource`.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SignatureManifest {
+    /// Signatures of the approved artifacts, by SHA256
+    pub signatures: BTreeMap<String, String>,
+}
+
+/// # Signature verification of model weights
+/// Checks that the model weights loaded by `load_weights` are signed by one of the trusted ed25519 keys, so that
+/// deployments only execute approved model binaries. The SHA256 of the weights (after their decryption for an
+/// `EncryptedResource`) is computed at each load, and its signature is read from the signatures registered with
+/// `add_signature` or loaded from a `SignatureManifest` (`load_manifest`). Unsigned weights, or weights whose
+/// signature does not match any trusted key, fail with a `RustBertError::ResourceIntegrityError`.
+///
+/// The verifier of the process is set with `set_signature_verifier`.
+pub struct SignatureVerifier {
+    trusted_keys: Vec<VerifyingKey>,
+    signatures: BTreeMap<String, Signature>,
+}
+
+impl SignatureVerifier {
+    /// Creates a new `SignatureVerifier` without signatures.
+    ///
+    /// # Arguments
+    ///
+    /// * `trusted_keys` - ed25519 public keys (32 bytes, hexadecimal) of the approved signers
+    pub fn new(trusted_keys: &[&str]) -> Result<SignatureVerifier, RustBertError> {
+        let trusted_keys = trusted_keys
+            .iter()
+            .map(|key| {
+                let bytes: [u8; 32] = decode_hex(key)?.try_into().map_err(|_| {
+                    RustBertError::InvalidConfigurationError(format!(
+                        "Invalid ed25519 public key {} (expected 32 bytes)",
+                        key
+                    ))
+                })?;
+                VerifyingKey::from_bytes(&bytes).map_err(|error| {
+                    RustBertError::InvalidConfigurationError(format!(
+                        "Invalid ed25519 public key {}: {}",
+                        key, error
+                    ))
+                })
+            })
+            .collect::<Result<Vec<VerifyingKey>, RustBertError>>()?;
+        Ok(SignatureVerifier {
+            trusted_keys,
+            signatures: BTreeMap::new(),
+        })
+    }
+
+    /// Registers the signature of an approved artifact.
+    ///
+    /// # Arguments
+    ///
+    /// * `sha256` - SHA256 of the artifact (hexadecimal)
+    /// * `signature` - ed25519 signature of the SHA256 digest (64 bytes, hexadecimal)
+    pub fn add_signature(&mut self, sha256: &str, signature: &str) -> Result<(), RustBertError> {
+        let bytes: [u8; 64] = decode_hex(signature)?.try_into().map_err(|_| {
+            RustBertError::InvalidConfigurationError(format!(
+                "Invalid ed25519 signature {} (expected 64 bytes)",
+                signature
+            ))
+        })?;
+        self.signatures
+            .insert(sha256.to_lowercase(), Signature::from_bytes(&bytes));
+        Ok(())
+    }
+
+    /// Registers the signatures of a `SignatureManifest` file.
+    ///
+    /// # Arguments
+    ///
+    /// * `path` - Path to the JSON signature manifest
+    pub fn load_manifest<P: AsRef<Path>>(&mut self, path: P) -> Result<(), RustBertError> {
+        let path = path.as_ref();
+        let manifest: SignatureManifest = serde_json::from_str(&fs::read_to_string(path)?)
+            .map_err(|error| {
+                RustBertError::InvalidConfigurationError(format!(
+                    "Invalid signature manifest {:?}: {}",
+                    path, error
+                ))
+            })?;
+        for (sha256, signature) in manifest.signatures.iter() {
+            self.add_signature(sha256, signature)?;
+        }
+        Ok(())
+    }
+
+    /// Checks that an artifact is signed by one of the trusted keys.
+    ///
+    /// # Arguments
+    ///
+    /// * `reader` - Content of the artifact
+    pub fn verify<R: io::Read>(&self, mut reader: R) -> Result<(), RustBertError> {
+        let digest = sha256(&mut reader)?;
+        let sha256 = encode_hex(&digest);
+        let signature = self.signatures.get(&sha256).ok_or_else(|| {
+            RustBertError::ResourceIntegrityError(format!(
+                "The artifact with SHA256 {} is not signed",
+                sha256
+            ))
+        })?;
+        if self
+            .trusted_keys
+            .iter()
+            .any(|key| key.verify(&digest, signature).is_ok())
+        {
+            Ok(())
+        } else {
+            Err(RustBertError::ResourceIntegrityError(format!(
+                "The signature of the artifact with SHA256 {} does not match any trusted key",
+                sha256
+            )))
+        }
+    }
+}
+
+/// Signs an approved artifact, returning its SHA256 and the signature of the SHA256 digest (hexadecimal), to be
+/// added to a `SignatureManifest`.
+///
+/// # Arguments
+///
+/// * `path` - Path to the artifact (e.g. `model.ot`)
+/// * `signing_key` - ed25519 secret key (32 bytes) of the signer
+///
+/// # Example
+///
+/// ```no_run
+/// # fn main() -> anyhow::Result<()> {
+/// use rust_bert::resources::{sign_resource, SignatureManifest};
+/// use std::path::Path;
+///
+/// let signing_key = [0u8; 32]; // Read from a secure location
+/// let (sha256, signature) = sign_resource(Path::new("model.ot"), &signing_key)?;
+/// let mut manifest = SignatureManifest::default();
+/// manifest.signatures.insert(sha256, signature);
+/// std::fs::write("signatures.json", serde_json::to_string_pretty(&manifest)?)?;
+/// # Ok(())
+/// # }
+/// ```
+pub fn sign_resource(
+    path: &Path,
+    signing_key: &[u8; 32],
+) -> Result<(String, String), RustBertError> {
+    let digest = sha256(&mut File::open(path)?)?;
+    let signature = SigningKey::from_bytes(signing_key).sign(&digest);
+    Ok((encode_hex(&digest), encode_hex(&signature.to_bytes())))
+}
+
+fn sha256<R: io::Read>(reader: &mut R) -> Result<Vec<u8>, RustBertError> {
+    let mut hasher = Sha256::new();
+    io::copy(reader, &mut hasher)?;
+    Ok(hasher.finalize().to_vec())
+}
+
+fn encode_hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|byte| format!("{:02x}", byte)).collect()
+}
+
+fn decode_hex(value: &str) -> Result<Vec<u8>, RustBertError> {
+    let invalid_value =
+        || RustBertError::InvalidConfigurationError(format!("Invalid hexadecimal value {}", value));
+    if value.len() % 2 != 0 {
+        return Err(invalid_value());
+    }
+    (0..value.len())
+        .step_by(2)
+        .map(|index| {
+            value
+                .get(index..index + 2)
+                .and_then(|byte| u8::from_str_radix(byte, 16).ok())
+                .ok_or_else(invalid_value)
+        })
+        .collect()
+}
+
+lazy_static! {
+    static ref SIGNATURE_VERIFIER: Mutex<Option<SignatureVerifier>> = Mutex::new(None);
+}
+
+/// Sets the `SignatureVerifier` checking the model weights loaded by the process. `None` disables the verification.
+///
+/// # Arguments
+///
+/// * `verifier` - Optional `SignatureVerifier`
+///
+/// # Example
+///
+/// ```no_run
+/// # fn main() -> anyhow::Result<()> {
+/// use rust_bert::resources::{set_signature_verifier, SignatureVerifier};
+///
+/// let mut verifier = SignatureVerifier::new(&[
+///     "d75a980182b10ab7d54bfed3c964073a0ee172f3daa62325af021a68f707511a",
+/// ])?;
+/// verifier.load_manifest("signatures.json")?;
+/// set_signature_verifier(Some(verifier));
+/// # Ok(())
+/// # }
+/// ```
+pub fn set_signature_verifier(verifier: Option<SignatureVerifier>) {
+    *SIGNATURE_VERIFIER.lock().unwrap() = verifier;
+}
+
+/// Checks a resource against the signature verifier of the process, if any
+pub(crate) fn verify_signature(resource: &Resource) -> Result<(), RustBertError> {
+    match SIGNATURE_VERIFIER.lock().unwrap().as_ref() {
+        Some(verifier) => match resource {
+            Resource::PathBuf(path) => verifier.verify(File::open(path)?),
+            Resource::Buffer(content) => verifier.verify(content.as_slice()),
+        },
+        None => Ok(()),
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_signature_verifier() {
+        let directory = tempfile::tempdir().unwrap();
+        let resource_path = directory.path().join("model.ot");
+        fs::write(&resource_path, "model weights").unwrap();
+
+        let signing_key = [1u8; 32];
+        let public_key = encode_hex(
+            SigningKey::from_bytes(&signing_key)
+                .verifying_key()
+                .as_bytes(),
+        );
+        let (sha256, signature) = sign_resource(&resource_path, &signing_key).unwrap();
+
+        let mut verifier = SignatureVerifier::new(&[&public_key]).unwrap();
+        assert!(matches!(
+            verifier.verify(File::open(&resource_path).unwrap()),
+            Err(RustBertError::ResourceIntegrityError(_))
+        ));
+        verifier.add_signature(&sha256, &signature).unwrap();
+        verifier
+            .verify(File::open(&resource_path).unwrap())
+            .unwrap();
+        assert!(matches!(
+            verifier.verify(&b"other weights"[..]),
+            Err(RustBertError::ResourceIntegrityError(_))
+        ));
+
+        // Signature from an untrusted key
+        let (_, untrusted_signature) = sign_resource(&resource_path, &[2u8; 32]).unwrap();
+        let mut verifier = SignatureVerifier::new(&[&public_key]).unwrap();
+        verifier
+            .add_signature(&sha256, &untrusted_signature)
+            .unwrap();
+        assert!(matches!(
+            verifier.verify(File::open(&resource_path).unwrap()),
+            Err(RustBertError::ResourceIntegrityError(_))
+        ));
+    }
+}
@@ -0,0 +1,270 @@
+// Copyright 2021 The Eleuther AI and HuggingFace Inc. team. All rights reserved.
+// Copyright 2022 Guillaume Becquin
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//     http://www.apache.org/licenses/LICENSE-2.0
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! # Rotary position embeddings
+//! Rotary position embeddings ([Su et al., 2021](https://arxiv.org/abs/2104.09864)) encode the position of the
+//! tokens by rotating pairs of dimensions of the queries and keys by an angle proportional to the position. They are
+//! used by the GPT-NeoX, Falcon and LLaMA (and Mistral) models of the crate. Architectures differ by the pairing of
+//! the dimensions:
+//! - `RotaryStyle::RotateEveryTwo` (GPT-J pairing): consecutive dimensions are rotated together
+//! - `RotaryStyle::RotateHalf` (GPT-NeoX, Falcon, LLaMA, Mistral): each dimension of the first half is rotated with
+//! the matching dimension of the second half
+//!
+//! The sine and cosine tables are computed once per model instance and shared by its attention layers (each layer
+//! holds a clone of the `RotaryEmbedding` created by the model). The attention layers only gather the rows of the
+//! tables at the positions of the tokens of the current step, rather than recomputing the tables at each layer and
+//! generation step. The tables are released with the model, and computed again if the model is moved to another
+//! device or precision.
+
+use std::sync::{Arc, Mutex};
+use tch::{Device, Kind, Tensor};
+
+/// # Pairing of the dimensions rotated together
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RotaryStyle {
+    /// Dimensions `2i` and `2i + 1` are rotated together (GPT-J pairing)
+    RotateEveryTwo,
+    /// Dimensions `i` and `i + rotary_dim / 2` are rotated together (GPT-NeoX, Falcon, LLaMA, Mistral)
+    RotateHalf,
+}
+
+/// # Configuration for the `RotaryEmbedding`
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct RotaryConfig {
+    /// Number of dimensions of each attention head that are rotated (the first `rotary_dim` dimensions, the
+    /// following dimensions are left unchanged). Must be even.
+    pub rotary_dim: i64,
+    /// Maximum position supported (size of the tables)
+    pub max_position_embeddings: i64,
+    /// Base of the geometric progression of the rotation frequencies
+    pub base: f64,
+    /// Pairing of the rotated dimensions
+    pub style: RotaryStyle,
+}
+
+impl RotaryConfig {
+    /// Creates a new `RotaryConfig` with the default base (10,000)
+    ///
+    /// # Arguments
+    ///
+    /// * `rotary_dim` - Number of rotated dimensions of each attention head
+    /// * `max_position_embeddings` - Maximum position supported
+    /// * `style` - Pairing of the rotated dimensions
+    pub fn new(rotary_dim: i64, max_position_embeddings: i64, style: RotaryStyle) -> RotaryConfig {
+        RotaryConfig {
+            rotary_dim,
+            max_position_embeddings,
+            base: 10000.0,
+            style,
+        }
+    }
+}
+
+/// Sine and cosine tables for the device and precision of a model
+#[derive(Debug)]
+struct RotaryTables {
+    device: Device,
+    kind: Kind,
+    sin: Tensor,
+    cos: Tensor,
+}
+
+/// Computes the sine and cosine tables of shape (*max_position_embeddings*, *rotary_dim*)
+fn build_tables(config: &RotaryConfig, device: Device, kind: Kind) -> (Tensor, Tensor) {
+    let inverse_frequencies =
+        (Tensor::arange_start_step(0, config.rotary_dim, 2, (Kind::Float, device))
+            / config.rotary_dim as f64
+            * config.base.ln())
+        .exp()
+        .reciprocal();
+    let positions = Tensor::arange(config.max_position_embeddings, (Kind::Float, device));
+    let angles = positions.outer(&inverse_frequencies);
+    let angles = match config.style {
+        RotaryStyle::RotateEveryTwo => Tensor::stack(&[&angles, &angles], -1).flatten(-2, -1),
+        RotaryStyle::RotateHalf => Tensor::cat(&[&angles, &angles], -1),
+    };
+    (angles.sin().to_kind(kind), angles.cos().to_kind(kind))
+}
+
+/// # Rotary position embeddings
+/// Applies the rotary position embeddings to the queries or keys of an attention layer. The sine and cosine tables
+/// are computed at the first call, for the device and precision of the queries and keys, and shared by the clones of
+/// the `RotaryEmbedding` (the attention layers of a model).
+#[derive(Debug, Clone)]
+pub struct RotaryEmbedding {
+    tables: Arc<Mutex<Option<RotaryTables>>>,
+    config: RotaryConfig,
+}
+
+impl RotaryEmbedding {
+    /// Creates a new `RotaryEmbedding`. The sine and cosine tables are computed when the embeddings are first
+    /// applied.
+    ///
+    /// # Arguments
+    ///
+    /// * `config` - `RotaryConfig` of the attention layers
+    pub fn new(config: RotaryConfig) -> RotaryEmbedding {
+        RotaryEmbedding {
+            tables: Arc::new(Mutex::new(None)),
+            config,
+        }
+    }
+
+    /// Returns the sine and cosine tables for a device and precision, replacing the tables computed for another
+    /// device or precision (e.g. after a conversion of the model to half precision)
+    fn get_tables(&self, device: Device, kind: Kind) -> (Tensor, Tensor) {
+        let mut tables = self.tables.lock().unwrap();
+        match tables.as_ref() {
+            Some(tables) if (tables.device == device) & (tables.kind == kind) => {
+                (tables.sin.shallow_clone(), tables.cos.shallow_clone())
+            }
+            _ => {
+                let (sin, cos) = tch::no_grad(|| build_tables(&self.config, device, kind));
+                *tables = Some(RotaryTables {
+                    device,
+                    kind,
+                    sin: sin.shallow_clone(),
+                    cos: cos.shallow_clone(),
+                });
+                (sin, cos)
+            }
+        }
+    }
+
+    /// Rotates the queries or keys of an attention layer.
+    ///
+    /// # Arguments
+    ///
+    /// * `hidden_states` - Queries or keys of shape (*batch size*, *number of heads*, *sequence_length*, *head dimension*)
+    /// * `position_ids` - Positions of the tokens of shape (*batch size*, *sequence_length*)
+    ///
+    /// # Returns
+    ///
+    /// * `Tensor` of the same shape as `hidden_states`
+    pub fn apply(&self, hidden_states: &Tensor, position_ids: &Tensor) -> Tensor {
+        let (batch_size, sequence_length) = position_ids.size2().unwrap();
+        let head_dim = *hidden_states.size().last().unwrap();
+        let rotary_dim = self.config.rotary_dim;
+        let gather = |table: &Tensor| {
+            table.index_select(0, &position_ids.reshape(&[-1])).view([
+                batch_size,
+                1,
+                sequence_length,
+                rotary_dim,
+            ])
+        };
+        let (sin, cos) = self.get_tables(hidden_states.device(), hidden_states.kind());
+        let (sin, cos) = (gather(&sin), gather(&cos));
+
+        let rotated_states = hidden_states.narrow(-1, 0, rotary_dim);
+        let rotated_states = &rotated_states * cos + self.rotate(&rotated_states) * sin;
+        if rotary_dim < head_dim {
+            Tensor::cat(
+                &[
+                    &rotated_states,
+                    &hidden_states.narrow(-1, rotary_dim, head_dim - rotary_dim),
+                ],
+                -1,
+            )
+        } else {
+            rotated_states
+        }
+    }
+
+    /// Maps each pair of rotated dimensions `(x, y)` to `(-y, x)`
+    fn rotate(&self, x: &Tensor) -> Tensor {
+        let rotary_dim = self.config.rotary_dim;
+        match self.config.style {
+            RotaryStyle::RotateEveryTwo => {
+                let x1 = x.slice(-1, 0, rotary_dim, 2);
+                let x2 = x.slice(-1, 1, rotary_dim, 2);
+                Tensor::stack(&[x2.neg(), x1], -1).flatten(-2, -1)
+            }
+            RotaryStyle::RotateHalf => {
+                let x1 = x.narrow(-1, 0, rotary_dim / 2);
+                let x2 = x.narrow(-1, rotary_dim / 2, rotary_dim / 2);
+                Tensor::cat(&[x2.neg(), x1], -1)
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn rotary_embedding() {
+        for style in [RotaryStyle::RotateEveryTwo, RotaryStyle::RotateHalf] {
+            let config = RotaryConfig::new(4, 16, style);
+            let rotary = RotaryEmbedding::new(config);
+
+            // (batch size, number of heads, sequence length, head dimension), the last 2 dimensions are not rotated
+            let hidden_states = Tensor::rand(&[1, 2, 3, 6], (Kind::Float, Device::Cpu));
+            let position_ids = Tensor::of_slice(&[0i64, 1, 5]).view([1, 3]);
+            let output = rotary.apply(&hidden_states, &position_ids);
+            assert_eq!(output.size(), hidden_states.size());
+            // Position 0 is not rotated
+            assert!(
+                (output.narrow(2, 0, 1) - hidden_states.narrow(2, 0, 1))
+                    .abs()
+                    .max()
+                    .double_value(&[])
+                    < 1e-6
+            );
+            // The dimensions beyond the rotary dimension are unchanged
+            assert!(
+                (output.narrow(-1, 4, 2) - hidden_states.narrow(-1, 4, 2))
+                    .abs()
+                    .max()
+                    .double_value(&[])
+                    < 1e-6
+            );
+            // Rotations preserve the norm
+            let norm = |x: &Tensor| x.narrow(-1, 0, 4).norm_scalaropt_dim(2, &[-1], false);
+            assert!(
+                (norm(&output) - norm(&hidden_states))
+                    .abs()
+                    .max()
+                    .double_value(&[])
+                    < 1e-5
+            );
+        }
+
+        // The first pair of dimensions (frequency 1) is rotated by an angle equal to the position
+        let rotary = RotaryEmbedding::new(RotaryConfig::new(2, 8, RotaryStyle::RotateHalf));
+        let hidden_states = Tensor::of_slice(&[1.0f32, 0.0]).view([1, 1, 1, 2]);
+        let output = rotary.apply(&hidden_states, &Tensor::of_slice(&[3i64]).view([1, 1]));
+        assert!((output.double_value(&[0, 0, 0, 0]) - 3f64.cos()).abs() < 1e-6);
+        assert!((output.double_value(&[0, 0, 0, 1]) - 3f64.sin()).abs() < 1e-6);
+    }
+    #[test]
+    fn rotary_tables_sharing() {
+        let config = RotaryConfig::new(4, 16, RotaryStyle::RotateHalf);
+        let rotary = RotaryEmbedding::new(config);
+        let layer_rotary = rotary.clone();
+        let other_rotary = RotaryEmbedding::new(config);
+
+        // The tables are shared by the clones of an embedding only
+        let (sin, _) = rotary.get_tables(Device::Cpu, Kind::Float);
+        let (layer_sin, _) = layer_rotary.get_tables(Device::Cpu, Kind::Float);
+        let (other_sin, _) = other_rotary.get_tables(Device::Cpu, Kind::Float);
+        assert_eq!(sin.data_ptr(), layer_sin.data_ptr());
+        assert_ne!(sin.data_ptr(), other_sin.data_ptr());
+
+        // The tables follow the precision of the inputs
+        let (double_sin, _) = layer_rotary.get_tables(Device::Cpu, Kind::Double);
+        assert_eq!(double_sin.kind(), Kind::Double);
+        let (sin, _) = rotary.get_tables(Device::Cpu, Kind::Double);
+        assert_eq!(sin.data_ptr(), double_sin.data_ptr());
+    }
+}
@@ -10,6 +10,7 @@
 // See the License for the specific language governing permissions and
 // limitations under the License.
 
+use crate::common::attention_mask::get_padding_mask;
 use crate::common::dropout::XDropout;
 use crate::common::embeddings::process_ids_embeddings_pair;
 use crate::deberta::deberta_model::DebertaLayerNorm;
@@ -107,6 +108,10 @@ where
         }
     }
 
+    pub(crate) fn word_embeddings(&self) -> &nn::Embedding {
+        &self.word_embeddings
+    }
+
     pub fn forward_t(
         &self,
         input_ids: Option<&Tensor>,
@@ -162,19 +167,9 @@ where
 
         input_embeddings = input_embeddings.apply(&self.layer_norm);
 
-        let mask = if attention_mask.dim() != input_embeddings.dim() {
-            if attention_mask.dim() != 4 {
-                attention_mask
-                    .squeeze_dim(1)
-                    .squeeze_dim(1)
-                    .unsqueeze(2)
-                    .to_kind(input_embeddings.kind())
-            } else {
-                attention_mask.unsqueeze(2).to_kind(input_embeddings.kind())
-            }
-        } else {
-            attention_mask.to_kind(input_embeddings.kind())
-        };
+        let mask = get_padding_mask(attention_mask)?
+            .unsqueeze(2)
+            .to_kind(input_embeddings.kind());
         input_embeddings = input_embeddings * mask;
 
         Ok(input_embeddings.apply_t(&self.dropout, train))
@@ -10,6 +10,7 @@
 // See the License for the specific language governing permissions and
 // limitations under the License.
 
+use crate::common::activations::TensorFunction;
 use crate::common::dropout::{Dropout, XDropout};
 use crate::common::embeddings::get_shape_and_device_from_ids_embeddings_pair;
 use crate::deberta::{
@@ -51,6 +52,21 @@ impl DebertaV2ConfigResources {
         "deberta-v3-base/config",
         "https://huggingface.co/microsoft/deberta-v3-base/resolve/main/config.json",
     );
+    /// Shared under MIT license by Moritz Laurer at <https://huggingface.co/MoritzLaurer/mDeBERTa-v3-base-mnli-xnli>. Modified with conversion to C-array format.
+    pub const MDEBERTA_V3_BASE_MNLI_XNLI: (&'static str, &'static str) = (
+        "mdeberta-v3-base-mnli-xnli/config",
+        "https://huggingface.co/MoritzLaurer/mDeBERTa-v3-base-mnli-xnli/resolve/main/config.json",
+    );
+    /// Shared under MIT license by the OpenAssistant team at <https://huggingface.co/OpenAssistant/reward-model-deberta-v3-base>. Modified with conversion to C-array format.
+    pub const REWARD_MODEL_DEBERTA_V3_BASE: (&'static str, &'static str) = (
+        "reward-model-deberta-v3-base/config",
+        "https://huggingface.co/OpenAssistant/reward-model-deberta-v3-base/resolve/main/config.json",
+    );
+    /// Shared under MIT license by the OpenAssistant team at <https://huggingface.co/OpenAssistant/reward-model-deberta-v3-large-v2>. Modified with conversion to C-array format.
+    pub const REWARD_MODEL_DEBERTA_V3_LARGE_V2: (&'static str, &'static str) = (
+        "reward-model-deberta-v3-large-v2/config",
+        "https://huggingface.co/OpenAssistant/reward-model-deberta-v3-large-v2/resolve/main/config.json",
+    );
 }
 
 impl DebertaV2VocabResources {
@@ -59,6 +75,21 @@ impl DebertaV2VocabResources {
         "deberta-v3-base/vocab",
         "https://huggingface.co/microsoft/deberta-v3-base/resolve/main/spm.model",
     );
+    /// Shared under MIT license by Moritz Laurer at <https://huggingface.co/MoritzLaurer/mDeBERTa-v3-base-mnli-xnli>. Modified with conversion to C-array format.
+    pub const MDEBERTA_V3_BASE_MNLI_XNLI: (&'static str, &'static str) = (
+        "mdeberta-v3-base-mnli-xnli/vocab",
+        "https://huggingface.co/MoritzLaurer/mDeBERTa-v3-base-mnli-xnli/resolve/main/spm.model",
+    );
+    /// Shared under MIT license by the OpenAssistant team at <https://huggingface.co/OpenAssistant/reward-model-deberta-v3-base>. Modified with conversion to C-array format.
+    pub const REWARD_MODEL_DEBERTA_V3_BASE: (&'static str, &'static str) = (
+        "reward-model-deberta-v3-base/vocab",
+        "https://huggingface.co/OpenAssistant/reward-model-deberta-v3-base/resolve/main/spm.model",
+    );
+    /// Shared under MIT license by the OpenAssistant team at <https://huggingface.co/OpenAssistant/reward-model-deberta-v3-large-v2>. Modified with conversion to C-array format.
+    pub const REWARD_MODEL_DEBERTA_V3_LARGE_V2: (&'static str, &'static str) = (
+        "reward-model-deberta-v3-large-v2/vocab",
+        "https://huggingface.co/OpenAssistant/reward-model-deberta-v3-large-v2/resolve/main/spm.model",
+    );
 }
 
 #[derive(Debug, Serialize, Deserialize)]
@@ -102,6 +133,7 @@ pub struct DebertaV2Config {
     pub is_decoder: Option<bool>,
     pub id2label: Option<HashMap<i64, String>>,
     pub label2id: Option<HashMap<String, i64>>,
+    pub legacy: Option<bool>,
 }
 
 #[allow(non_camel_case_types)]
@@ -229,6 +261,7 @@ impl Default for DebertaV2Config {
             is_decoder: None,
             id2label: None,
             label2id: None,
+            legacy: None,
         }
     }
 }
@@ -354,12 +387,17 @@ impl DebertaV2Model {
         }
     }
 
+    pub(crate) fn word_embeddings(&self) -> &nn::Embedding {
+        self.embeddings.word_embeddings()
+    }
+
     /// Forward pass through the model
     ///
     /// # Arguments
     ///
     /// * `input_ids` - Optional input tensor of shape (*batch size*, *sequence_length*). If None, pre-computed embeddings must be provided (see `input_embeds`)
-    /// * `attention_mask` - Optional mask of shape (*batch size*, *sequence_length*). Masked position have value 0, non-masked value 1. If None set to 1
+    /// * `attention_mask` - Optional mask of shape (*batch size*, *sequence_length*). Masked position have value 0, non-masked value 1. If None set to 1.
+    ///   Pre-built masks of shape (*batch size*, *sequence_length*, *sequence_length*) or (*batch size*, *num_heads or 1*, *sequence_length*, *sequence_length*) are used as-is.
     /// * `token_type_ids` - Optional segment id of shape (*batch size*, *sequence_length*). Convention is value of 0 for the first sentence (incl. *SEP*) and 1 for the second sentence. If None set to 0.
     /// * `position_ids` - Optional position ids of shape (*batch size*, *sequence_length*). If None, will be incremented from 0.
     /// * `input_embeds` - Optional pre-computed input embeddings of shape (*batch size*, *sequence_length*, *hidden_size*). If None, input ids must be provided (see `input_ids`)
@@ -442,14 +480,67 @@ impl DebertaV2Model {
     }
 }
 
+/// # DeBERTa V2 language model head sharing its output projection with the word embeddings
+/// Head of the DeBERTa-v3 checkpoints released with the original implementation (`lm_predictions.lm_head`),
+/// projecting the hidden states to the embedding size before the (shared) word embeddings.
+struct DebertaV2LMPredictionHead {
+    dense: nn::Linear,
+    activation: TensorFunction,
+    layer_norm: nn::LayerNorm,
+    bias: Tensor,
+}
+
+impl DebertaV2LMPredictionHead {
+    pub fn new<'p, P>(p: P, config: &DebertaV2Config) -> DebertaV2LMPredictionHead
+    where
+        P: Borrow<nn::Path<'p>>,
+    {
+        let p = p.borrow();
+        let embedding_size = config.embedding_size.unwrap_or(config.hidden_size);
+
+        let dense = nn::linear(
+            p / "dense",
+            config.hidden_size,
+            embedding_size,
+            Default::default(),
+        );
+        let activation = config.hidden_act.get_function();
+        let layer_norm_config = nn::LayerNormConfig {
+            eps: config.layer_norm_eps.unwrap_or(1e-7),
+            ..Default::default()
+        };
+        let layer_norm = nn::layer_norm(p / "LayerNorm", vec![embedding_size], layer_norm_config);
+        let bias = p.var("bias", &[config.vocab_size], nn::Init::Const(0.0));
+
+        DebertaV2LMPredictionHead {
+            dense,
+            activation,
+            layer_norm,
+            bias,
+        }
+    }
+
+    pub fn forward(&self, hidden_states: &Tensor, word_embeddings: &nn::Embedding) -> Tensor {
+        self.activation.get_fn()(&hidden_states.apply(&self.dense))
+            .apply(&self.layer_norm)
+            .linear(&word_embeddings.ws, Some(&self.bias))
+    }
+}
+
+enum DebertaV2MaskedLMHead {
+    Legacy(DebertaLMPredictionHead),
+    SharedEmbeddings(DebertaV2LMPredictionHead),
+}
+
 /// # DeBERTa V2 for masked language model
 /// Base DeBERTa V2 model with a masked language model head to predict missing tokens, for example `"Looks like one [MASK] is missing" -> "person"`
 /// It is made of the following blocks:
 /// - `deberta`: Base DeBERTa V2 model
-/// - `cls`: LM prediction head
+/// - `cls`: LM prediction head. If the `legacy` configuration field is set to false, the head of the original DeBERTa-v3 checkpoints
+///   is loaded instead (`lm_predictions.lm_head`, sharing its output projection with the word embeddings)
 pub struct DebertaV2ForMaskedLM {
     deberta: DebertaV2Model,
-    cls: DebertaLMPredictionHead,
+    cls: DebertaV2MaskedLMHead,
 }
 
 impl DebertaV2ForMaskedLM {
@@ -481,8 +572,18 @@ impl DebertaV2ForMaskedLM {
         let p = p.borrow();
 
         let deberta = DebertaV2Model::new(p / "deberta", config);
-        let cls =
-            DebertaLMPredictionHead::new(p.sub("cls").sub("predictions"), &config.into(), false);
+        let cls = if config.legacy.unwrap_or(true) {
+            DebertaV2MaskedLMHead::Legacy(DebertaLMPredictionHead::new(
+                p.sub("cls").sub("predictions"),
+                &config.into(),
+                false,
+            ))
+        } else {
+            DebertaV2MaskedLMHead::SharedEmbeddings(DebertaV2LMPredictionHead::new(
+                p.sub("lm_predictions").sub("lm_head"),
+                config,
+            ))
+        };
 
         DebertaV2ForMaskedLM { deberta, cls }
     }
@@ -553,7 +654,12 @@ impl DebertaV2ForMaskedLM {
             train,
         )?;
 
-        let logits = model_outputs.hidden_state.apply(&self.cls);
+        let logits = match &self.cls {
+            DebertaV2MaskedLMHead::Legacy(cls) => model_outputs.hidden_state.apply(cls),
+            DebertaV2MaskedLMHead::SharedEmbeddings(lm_head) => {
+                lm_head.forward(&model_outputs.hidden_state, self.deberta.word_embeddings())
+            }
+        };
         Ok(DebertaV2MaskedLMOutput {
             logits,
             all_hidden_states: model_outputs.all_hidden_states,
@@ -562,6 +668,158 @@ impl DebertaV2ForMaskedLM {
     }
 }
 
+/// # DeBERTa V2 for replaced token detection
+/// Base DeBERTa V2 model with the discriminator head used for the ELECTRA-style pre-training of DeBERTa-v3,
+/// predicting for each token whether it was replaced by the generator. The discriminator head of the original
+/// DeBERTa-v3 checkpoints (`mask_predictions`) conditions each token on the first (`[CLS]`) token representation.
+/// It is made of the following blocks:
+/// - `deberta`: Base DeBERTa V2 model
+/// - `dense`: linear layer applied to the contextualized token representations
+/// - `activation`: activation layer
+/// - `layer_norm`: layer normalization of the token representations added to the `[CLS]` representation
+/// - `classifier`: linear layer projecting to a single replaced token logit
+pub struct DebertaV2ForReplacedTokenDetection {
+    deberta: DebertaV2Model,
+    dense: nn::Linear,
+    activation: TensorFunction,
+    layer_norm: nn::LayerNorm,
+    classifier: nn::Linear,
+}
+
+impl DebertaV2ForReplacedTokenDetection {
+    /// Build a new `DebertaV2ForReplacedTokenDetection`
+    ///
+    /// # Arguments
+    ///
+    /// * `p` - Variable store path for the root of the DebertaV2ForReplacedTokenDetection model
+    /// * `config` - `DebertaV2Config` object defining the model architecture
+    ///
+    /// # Example
+    ///
+    /// ```no_run
+    /// use rust_bert::deberta_v2::{DebertaV2Config, DebertaV2ForReplacedTokenDetection};
+    /// use rust_bert::Config;
+    /// use std::path::Path;
+    /// use tch::{nn, Device};
+    ///
+    /// let config_path = Path::new("path/to/config.json");
+    /// let device = Device::Cpu;
+    /// let p = nn::VarStore::new(device);
+    /// let config = DebertaV2Config::from_file(config_path);
+    /// let model = DebertaV2ForReplacedTokenDetection::new(&p.root(), &config);
+    /// ```
+    pub fn new<'p, P>(p: P, config: &DebertaV2Config) -> DebertaV2ForReplacedTokenDetection
+    where
+        P: Borrow<nn::Path<'p>>,
+    {
+        let p = p.borrow();
+
+        let deberta = DebertaV2Model::new(p / "deberta", config);
+        let mask_predictions = p / "mask_predictions";
+        let dense = nn::linear(
+            &mask_predictions / "dense",
+            config.hidden_size,
+            config.hidden_size,
+            Default::default(),
+        );
+        let activation = config.hidden_act.get_function();
+        let layer_norm_config = nn::LayerNormConfig {
+            eps: config.layer_norm_eps.unwrap_or(1e-7),
+            ..Default::default()
+        };
+        let layer_norm = nn::layer_norm(
+            &mask_predictions / "LayerNorm",
+            vec![config.hidden_size],
+            layer_norm_config,
+        );
+        let classifier = nn::linear(
+            &mask_predictions / "classifier",
+            config.hidden_size,
+            1,
+            Default::default(),
+        );
+
+        DebertaV2ForReplacedTokenDetection {
+            deberta,
+            dense,
+            activation,
+            layer_norm,
+            classifier,
+        }
+    }
+
+    /// Forward pass through the model
+    ///
+    /// # Arguments
+    ///
+    /// * `input_ids` - Optional input tensor of shape (*batch size*, *sequence_length*). If None, pre-computed embeddings must be provided (see *input_embeds*)
+    /// * `attention_mask` - Optional mask of shape (*batch size*, *sequence_length*). Masked position have value 0, non-masked value 1. If None set to 1
+    /// * `token_type_ids` -Optional segment id of shape (*batch size*, *sequence_length*). Convention is value of 0 for the first sentence (incl. *SEP*) and 1 for the second sentence. If None set to 0.
+    /// * `position_ids` - Optional position ids of shape (*batch size*, *sequence_length*). If None, will be incremented from 0.
+    /// * `input_embeds` - Optional pre-computed input embeddings of shape (*batch size*, *sequence_length*, *hidden_size*). If None, input ids must be provided (see *input_ids*)
+    /// * `train` - boolean flag to turn on/off the dropout layers in the model. Should be set to false for inference.
+    ///
+    /// # Returns
+    ///
+    /// * `DebertaV2ReplacedTokenDetectionOutput` containing:
+    ///   - `logits` - `Tensor` of shape (*batch size*, *sequence_length*), positive for the tokens predicted as replaced
+    ///   - `all_hidden_states` - `Option<Vec<Tensor>>` of length *num_hidden_layers* with shape (*batch size*, *sequence_length*, *hidden_size*)
+    ///   - `all_attentions` - `Option<Vec<Tensor>>` of length *num_hidden_layers* with shape (*batch size*, *sequence_length*, *hidden_size*)
+    ///
+    /// # Example
+    ///
+    /// ```no_run
+    /// # use rust_bert::deberta_v2::{DebertaV2ForReplacedTokenDetection, DebertaV2Config};
+    /// # use tch::{nn, Device, Tensor, no_grad, Kind};
+    /// # use rust_bert::Config;
+    /// # use std::path::Path;
+    /// # let config_path = Path::new("path/to/config.json");
+    /// # let device = Device::Cpu;
+    /// # let vs = nn::VarStore::new(device);
+    /// # let config = DebertaV2Config::from_file(config_path);
+    /// # let model = DebertaV2ForReplacedTokenDetection::new(&vs.root(), &config);
+    /// let (batch_size, sequence_length) = (64, 128);
+    /// let input_tensor = Tensor::rand(&[batch_size, sequence_length], (Kind::Int64, device));
+    /// let mask = Tensor::ones(&[batch_size, sequence_length], (Kind::Int64, device));
+    ///
+    /// let model_output = no_grad(|| {
+    ///     model.forward_t(Some(&input_tensor), Some(&mask), None, None, None, false)
+    /// });
+    /// ```
+    pub fn forward_t(
+        &self,
+        input_ids: Option<&Tensor>,
+        attention_mask: Option<&Tensor>,
+        token_type_ids: Option<&Tensor>,
+        position_ids: Option<&Tensor>,
+        input_embeds: Option<&Tensor>,
+        train: bool,
+    ) -> Result<DebertaV2ReplacedTokenDetectionOutput, RustBertError> {
+        let model_outputs = self.deberta.forward_t(
+            input_ids,
+            attention_mask,
+            token_type_ids,
+            position_ids,
+            input_embeds,
+            train,
+        )?;
+
+        let context_states = model_outputs.hidden_state.select(1, 0).unsqueeze(-2);
+        let sequence_states = (context_states + &model_outputs.hidden_state)
+            .apply(&self.layer_norm)
+            .apply(&self.dense);
+        let logits = self.activation.get_fn()(&sequence_states)
+            .apply(&self.classifier)
+            .squeeze_dim(-1);
+
+        Ok(DebertaV2ReplacedTokenDetectionOutput {
+            logits,
+            all_hidden_states: model_outputs.all_hidden_states,
+            all_attentions: model_outputs.all_attentions,
+        })
+    }
+}
+
 /// # DeBERTa V2 for sequence classification
 /// Base DeBERTa V2 model with a classifier head to perform sentence or document-level classification
 /// It is made of the following blocks:
@@ -707,6 +965,8 @@ impl DebertaV2ForSequenceClassification {
             logits,
             all_hidden_states: base_model_output.all_hidden_states,
             all_attentions: base_model_output.all_attentions,
+            exit_logits: None,
+            exit_layers: None,
         })
     }
 }
@@ -996,6 +1256,16 @@ pub type DebertaV2ModelOutput = DebertaModelOutput;
 /// Container for the DeBERTa V2masked LM model output.
 pub type DebertaV2MaskedLMOutput = DebertaMaskedLMOutput;
 
+/// Container for the DeBERTa V2 replaced token detection model output.
+pub struct DebertaV2ReplacedTokenDetectionOutput {
+    /// Replaced token logits at each sequence position
+    pub logits: Tensor,
+    /// Hidden states for all intermediate layers
+    pub all_hidden_states: Option<Vec<Tensor>>,
+    /// Attention weights for all intermediate layers
+    pub all_attentions: Option<Vec<Tensor>>,
+}
+
 /// Container for the DeBERTa sequence classification model output.
 pub type DebertaV2SequenceClassificationOutput = DebertaSequenceClassificationOutput;
 
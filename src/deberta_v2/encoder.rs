@@ -11,6 +11,7 @@
 // limitations under the License.
 
 use crate::common::activations::TensorFunction;
+use crate::common::attention_mask::get_padding_mask;
 use crate::common::dropout::XDropout;
 use crate::deberta::{BaseDebertaLayer, BaseDebertaLayerNorm, DebertaEncoderOutput};
 use crate::deberta_v2::attention::{build_relative_position, DebertaV2DisentangledSelfAttention};
@@ -83,10 +84,9 @@ impl ConvLayer {
             .apply(&self.conv)
             .permute(&[0, 2, 1])
             .contiguous();
-        let reverse_mask: Tensor = 1 - input_mask;
         let out = out.masked_fill(
-            &reverse_mask
-                .to_kind(Kind::Bool)
+            &input_mask
+                .eq(0)
                 .unsqueeze(-1)
                 .expand(out.size().as_slice(), true),
             0,
@@ -95,17 +95,7 @@ impl ConvLayer {
 
         let layer_norm_input = residual_states + out;
         let output = layer_norm_input.apply(&self.layer_norm);
-        let new_input_mask = if input_mask.dim() != layer_norm_input.dim() {
-            if input_mask.dim() == 4 {
-                input_mask.squeeze_dim(1).squeeze_dim(1).unsqueeze(2)
-            } else {
-                input_mask.unsqueeze(2)
-            }
-            .to_kind(output.kind())
-        } else {
-            input_mask.to_kind(output.kind())
-        };
-        output * new_input_mask
+        output * input_mask.unsqueeze(2).to_kind(output.kind())
     }
 }
 
@@ -287,14 +277,7 @@ impl DebertaV2Encoder {
             None
         };
 
-        let input_mask = if attention_mask.dim() <= 2 {
-            attention_mask.shallow_clone()
-        } else {
-            attention_mask
-                .sum_dim_intlist(&[-2], false, attention_mask.kind())
-                .gt(0)
-                .to_kind(Kind::Uint8)
-        };
+        let input_mask = get_padding_mask(attention_mask)?;
         let attention_mask = Self::get_attention_mask(attention_mask);
         let relative_pos = self.get_rel_pos(hidden_states, query_states, relative_pos);
         let relative_embeddings = self.get_rel_embedding();
@@ -2,7 +2,9 @@
 //!
 //! Implementation of the DeBERTa V2/V3 language model ([DeBERTaV3: Improving DeBERTa using ELECTRA-Style Pre-Training with Gradient-Disentangled Embedding Sharing](https://arxiv.org/abs/2111.09543) He, Gao, Chen, 2021).
 //! The base model is implemented in the `deberta_v2_model::DebertaV2Model` struct. Several language model heads have also been implemented, including:
+//! - Masked language model: `deberta_v2_model::DebertaV2ForMaskedLM`
 //! - Question answering: `deberta_v2_model::DebertaV2ForQuestionAnswering`
+//! - Replaced token detection (discriminator of the DeBERTa-v3 pre-training): `deberta_v2_model::DebertaV2ForReplacedTokenDetection`
 //! - Sequence classification: `deberta_v2_model::DebertaV2ForSequenceClassification`
 //! - Token classification (e.g. NER, POS tagging): `deberta_v2_model::DebertaV2ForTokenClassification`.
 //!
@@ -54,8 +56,9 @@ mod encoder;
 
 pub use deberta_v2_model::{
     DebertaV2Config, DebertaV2ConfigResources, DebertaV2ForMaskedLM, DebertaV2ForQuestionAnswering,
-    DebertaV2ForSequenceClassification, DebertaV2ForTokenClassification, DebertaV2Model,
+    DebertaV2ForReplacedTokenDetection, DebertaV2ForSequenceClassification,
+    DebertaV2ForTokenClassification, DebertaV2MaskedLMOutput, DebertaV2Model,
     DebertaV2ModelResources, DebertaV2QuestionAnsweringOutput,
-    DebertaV2SequenceClassificationOutput, DebertaV2TokenClassificationOutput,
-    DebertaV2VocabResources,
+    DebertaV2ReplacedTokenDetectionOutput, DebertaV2SequenceClassificationOutput,
+    DebertaV2TokenClassificationOutput, DebertaV2VocabResources,
 };
@@ -15,6 +15,7 @@ extern crate tch;
 use self::tch::{nn, Tensor};
 use crate::common::activations::Activation;
 use crate::common::dropout::Dropout;
+use crate::common::early_exit::{EarlyExitBatch, EarlyExitConfig};
 use crate::distilbert::embeddings::DistilBertEmbedding;
 use crate::distilbert::transformer::{DistilBertTransformerOutput, Transformer};
 use crate::{Config, RustBertError};
@@ -123,6 +124,9 @@ pub struct DistilBertConfig {
     pub sinusoidal_pos_embds: bool,
     pub tie_weights_: bool,
     pub vocab_size: i64,
+    /// Optional early exit of the sequence classification model: classifier heads on the intermediate layers let
+    /// the confident inputs leave the transformer early during inference
+    pub early_exit: Option<EarlyExitConfig>,
 }
 
 impl Config for DistilBertConfig {}
@@ -150,6 +154,7 @@ impl Default for DistilBertConfig {
             sinusoidal_pos_embds: false,
             tie_weights_: false,
             vocab_size: 30522,
+            early_exit: None,
         }
     }
 }
@@ -253,17 +258,58 @@ impl DistilBertModel {
     }
 }
 
+/// # DistilBERT exit head
+/// Pre-classifier and classifier applied to the hidden states of an intermediate layer of a DistilBERT sequence
+/// classification model with early exit
+struct DistilBertExitHead {
+    pre_classifier: nn::Linear,
+    classifier: nn::Linear,
+}
+
+impl DistilBertExitHead {
+    fn new<'p, P>(p: P, config: &DistilBertConfig, num_labels: i64) -> DistilBertExitHead
+    where
+        P: Borrow<nn::Path<'p>>,
+    {
+        let p = p.borrow();
+
+        let pre_classifier = nn::linear(
+            p / "pre_classifier",
+            config.dim,
+            config.dim,
+            Default::default(),
+        );
+        let classifier = nn::linear(p / "classifier", config.dim, num_labels, Default::default());
+        DistilBertExitHead {
+            pre_classifier,
+            classifier,
+        }
+    }
+
+    fn forward_t(&self, hidden_states: &Tensor, dropout: &Dropout, train: bool) -> Tensor {
+        hidden_states
+            .select(1, 0)
+            .apply(&self.pre_classifier)
+            .relu()
+            .apply_t(dropout, train)
+            .apply(&self.classifier)
+    }
+}
+
 /// # DistilBERT for sequence classification
 /// Base DistilBERT model with a pre-classifier and classifier heads to perform sentence or document-level classification
 /// It is made of the following blocks:
 /// - `distil_bert_model`: Base DistilBertModel
 /// - `pre_classifier`: DistilBERT linear layer for classification
 /// - `classifier`: DistilBERT linear layer for classification
+/// - `exit_heads`: classifier heads of the intermediate layers, if early exit is enabled in the configuration
 pub struct DistilBertModelClassifier {
     distil_bert_model: DistilBertModel,
     pre_classifier: nn::Linear,
     classifier: nn::Linear,
     dropout: Dropout,
+    exit_heads: Vec<DistilBertExitHead>,
+    early_exit: Option<EarlyExitConfig>,
 }
 
 impl DistilBertModelClassifier {
@@ -311,12 +357,22 @@ impl DistilBertModelClassifier {
         );
         let classifier = nn::linear(p / "classifier", config.dim, num_labels, Default::default());
         let dropout = Dropout::new(config.seq_classif_dropout);
+        let exit_heads = match config.early_exit {
+            Some(_) => (0..config.n_layers - 1)
+                .map(|layer_index| {
+                    DistilBertExitHead::new(p / "exit_heads" / layer_index, config, num_labels)
+                })
+                .collect(),
+            None => vec![],
+        };
 
         DistilBertModelClassifier {
             distil_bert_model,
             pre_classifier,
             classifier,
             dropout,
+            exit_heads,
+            early_exit: config.early_exit,
         }
     }
 
@@ -335,6 +391,10 @@ impl DistilBertModelClassifier {
     ///   - `logits` - `Tensor` of shape (*batch size*, *num_labels*)
     ///   - `all_hidden_states` - `Option<Vec<Tensor>>` of length *num_hidden_layers* with shape (*batch size*, *sequence_length*, *hidden_size*)
     ///   - `all_attentions` - `Option<Vec<Tensor>>` of length *num_hidden_layers* with shape (*batch size*, *sequence_length*, *hidden_size*)
+    ///   - `exit_logits` - `Option<Vec<Tensor>>` logits of the exit heads of the intermediate layers (early exit, training mode)
+    ///   - `exit_layers` - `Option<Vec<i64>>` number of layers processed for each input (early exit, inference mode)
+    ///
+    /// With early exit, the hidden states and attentions of the intermediate layers are not returned.
     ///
     /// # Example
     ///
@@ -369,6 +429,9 @@ impl DistilBertModelClassifier {
         input_embeds: Option<&Tensor>,
         train: bool,
     ) -> Result<DistilBertSequenceClassificationOutput, RustBertError> {
+        if let Some(early_exit) = &self.early_exit {
+            return self.forward_early_exit(early_exit, input, mask, input_embeds, train);
+        }
         let base_model_output =
             self.distil_bert_model
                 .forward_t(input, mask, input_embeds, train)?;
@@ -385,6 +448,85 @@ impl DistilBertModelClassifier {
             logits,
             all_hidden_states: base_model_output.all_hidden_states,
             all_attentions: base_model_output.all_attentions,
+            exit_logits: None,
+            exit_layers: None,
+        })
+    }
+
+    /// Forward pass processing the inputs layer by layer. In training mode, all the inputs go through all the
+    /// layers and the logits of every exit head are returned. In inference mode, the inputs for which an exit head
+    /// is confident enough leave the transformer, and the following layers only process the remaining inputs.
+    fn forward_early_exit(
+        &self,
+        early_exit: &EarlyExitConfig,
+        input: Option<&Tensor>,
+        mask: Option<&Tensor>,
+        input_embeds: Option<&Tensor>,
+        train: bool,
+    ) -> Result<DistilBertSequenceClassificationOutput, RustBertError> {
+        let mut hidden_state =
+            self.distil_bert_model
+                .embeddings
+                .forward_t(input, input_embeds, train)?;
+        let mut mask = mask.map(|mask| mask.shallow_clone());
+        let transformer = &self.distil_bert_model.transformer;
+
+        let num_layers = transformer.num_layers();
+        let mut exit_logits = Vec::with_capacity(self.exit_heads.len());
+        let mut exit_batch = EarlyExitBatch::new(hidden_state.size()[0], hidden_state.device());
+        for layer_index in 0..num_layers {
+            hidden_state =
+                transformer.forward_layer_t(layer_index, &hidden_state, mask.as_ref(), train);
+            let layers_processed = layer_index + 1;
+            if train {
+                if let Some(exit_head) = self.exit_heads.get(layer_index) {
+                    exit_logits.push(exit_head.forward_t(&hidden_state, &self.dropout, train));
+                }
+            } else if early_exit.can_exit(layers_processed, num_layers) {
+                let layer_logits =
+                    self.exit_heads[layer_index].forward_t(&hidden_state, &self.dropout, train);
+                match exit_batch.exit(
+                    &layer_logits,
+                    layers_processed as i64,
+                    Some(early_exit.confidence_threshold),
+                ) {
+                    Some(remaining_positions) => {
+                        hidden_state = hidden_state.index_select(0, &remaining_positions);
+                        mask = mask.map(|mask| mask.index_select(0, &remaining_positions));
+                    }
+                    None => {
+                        let (logits, exit_layers) = exit_batch.finish();
+                        return Ok(DistilBertSequenceClassificationOutput {
+                            logits,
+                            all_hidden_states: None,
+                            all_attentions: None,
+                            exit_logits: None,
+                            exit_layers: Some(exit_layers),
+                        });
+                    }
+                }
+            }
+        }
+
+        let logits = hidden_state
+            .select(1, 0)
+            .apply(&self.pre_classifier)
+            .relu()
+            .apply_t(&self.dropout, train)
+            .apply(&self.classifier);
+        let (logits, exit_logits, exit_layers) = if train {
+            (logits, Some(exit_logits), None)
+        } else {
+            let _ = exit_batch.exit(&logits, num_layers as i64, None);
+            let (logits, exit_layers) = exit_batch.finish();
+            (logits, None, Some(exit_layers))
+        };
+        Ok(DistilBertSequenceClassificationOutput {
+            logits,
+            all_hidden_states: None,
+            all_attentions: None,
+            exit_logits,
+            exit_layers,
         })
     }
 }
@@ -792,6 +934,10 @@ pub struct DistilBertSequenceClassificationOutput {
     pub all_hidden_states: Option<Vec<Tensor>>,
     /// Attention weights for all intermediate layers
     pub all_attentions: Option<Vec<Tensor>>,
+    /// Logits of the exit heads of the intermediate layers (early exit, training mode)
+    pub exit_logits: Option<Vec<Tensor>>,
+    /// Number of layers processed for each input (early exit, inference mode)
+    pub exit_layers: Option<Vec<i64>>,
 }
 /// Container for the DistilBERT token classification model output
 pub struct DistilBertTokenClassificationOutput {
@@ -177,6 +177,25 @@ impl Transformer {
             all_attentions,
         }
     }
+
+    /// Returns the number of layers of the transformer
+    pub(crate) fn num_layers(&self) -> usize {
+        self.layers.len()
+    }
+
+    /// Forward pass through a single layer of the transformer, used to process the inputs layer by layer
+    /// (e.g. for early exit)
+    pub(crate) fn forward_layer_t(
+        &self,
+        layer_index: usize,
+        hidden_states: &Tensor,
+        mask: Option<&Tensor>,
+        train: bool,
+    ) -> Tensor {
+        self.layers[layer_index]
+            .forward_t(hidden_states, mask, train)
+            .0
+    }
 }
 
 /// Container for the DistilBert transformer output.
@@ -14,7 +14,7 @@
 
 use crate::bert::BertConfig;
 use crate::common::activations::Activation;
-use crate::common::dropout::Dropout;
+use crate::common::dropout::{Dropout, StochasticDepth};
 use crate::common::embeddings::get_shape_and_device_from_ids_embeddings_pair;
 use crate::electra::embeddings::ElectraEmbeddings;
 use crate::{bert::encoder::BertEncoder, common::activations::TensorFunction};
@@ -94,6 +94,10 @@ pub struct ElectraConfig {
     pub output_hidden_states: Option<bool>,
     pub id2label: Option<HashMap<i64, String>>,
     pub label2id: Option<HashMap<String, i64>>,
+    /// Optional stochastic depth (layer drop) schedule applied to the encoder layers during training
+    pub stochastic_depth: Option<StochasticDepth>,
+    /// Use fused bias, activation and residual operations in the feed-forward layers during inference (default: false)
+    pub fused_feed_forward: Option<bool>,
 }
 
 impl Config for ElectraConfig {}
@@ -120,6 +124,8 @@ impl Default for ElectraConfig {
             output_hidden_states: None,
             id2label: None,
             label2id: None,
+            stochastic_depth: None,
+            fused_feed_forward: None,
         }
     }
 }
@@ -193,6 +199,11 @@ impl ElectraModel {
             is_decoder: None,
             id2label: config.id2label.clone(),
             label2id: config.label2id.clone(),
+            stochastic_depth: config.stochastic_depth.clone(),
+            fused_feed_forward: config.fused_feed_forward,
+            early_exit: None,
+            model_type: None,
+            pre_layer_norm: None,
         };
         let encoder = BertEncoder::new(p / "encoder", &bert_config);
         ElectraModel {
@@ -0,0 +1,768 @@
+// Copyright 2023 the Falcon authors and HuggingFace Inc. team. All rights reserved.
+// Copyright 2022 Guillaume Becquin
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//     http://www.apache.org/licenses/LICENSE-2.0
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use crate::common::embeddings::process_ids_embeddings_pair;
+use crate::gpt_neox::decoder::{causal_attention_mask, GptNeoXLayer, ParallelBlockConfig};
+use crate::gpt_neox::LayerState;
+use crate::pipelines::common::{ModelType, TokenizerOption};
+use crate::pipelines::generation_utils::private_generation_utils::{
+    PreparedInput, PrivateLanguageGenerator,
+};
+use crate::pipelines::generation_utils::{
+    Cache, GenerateConfig, LMHeadModel, LMModelOutput, LanguageGenerator,
+};
+use crate::{Activation, Config, RustBertError};
+use rust_tokenizers::tokenizer::Gpt2Tokenizer;
+use rust_tokenizers::vocab::Gpt2Vocab;
+use serde::{Deserialize, Serialize};
+use std::borrow::{Borrow, BorrowMut};
+use tch::{nn, Kind, Tensor};
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+/// # Falcon model configuration
+/// Defines the Falcon model architecture (e.g. number of layers, hidden layer size, vocab size...).
+/// The ALiBi position biases (`alibi`) are not supported, only the checkpoints using rotary position embeddings
+/// can be loaded.
+pub struct FalconConfig {
+    pub vocab_size: i64,
+    pub hidden_size: i64,
+    pub num_hidden_layers: i64,
+    pub num_attention_heads: i64,
+    /// Number of key and value heads with the new decoder architecture (defaults to `num_attention_heads`)
+    pub num_kv_heads: Option<i64>,
+    /// Single key and value head shared by all query heads (defaults to `true`, ignored with the new decoder
+    /// architecture)
+    pub multi_query: Option<bool>,
+    /// Grouped-query attention and separate layer norms for the attention and feed-forward layers (Falcon-40B and
+    /// larger models)
+    pub new_decoder_architecture: Option<bool>,
+    /// Parallel attention and feed-forward layers (defaults to `true`)
+    pub parallel_attn: Option<bool>,
+    /// Bias of the linear layers (defaults to `false`)
+    pub bias: Option<bool>,
+    pub alibi: Option<bool>,
+    pub activation: Option<Activation>,
+    /// Size of the feed-forward layers (defaults to `4 * hidden_size`)
+    pub ffn_hidden_size: Option<i64>,
+    pub layer_norm_epsilon: f64,
+    /// Base of the rotary position embeddings frequencies (defaults to 10,000)
+    pub rope_theta: Option<f64>,
+    pub max_position_embeddings: Option<i64>,
+    pub attention_dropout: Option<f64>,
+    pub hidden_dropout: Option<f64>,
+    pub tie_word_embeddings: Option<bool>,
+    pub bos_token_id: i64,
+    pub eos_token_id: i64,
+    pub pad_token_id: Option<i64>,
+    pub output_attentions: Option<bool>,
+    pub output_hidden_states: Option<bool>,
+}
+
+impl Config for FalconConfig {}
+
+impl Default for FalconConfig {
+    fn default() -> Self {
+        FalconConfig {
+            vocab_size: 65024,
+            hidden_size: 4544,
+            num_hidden_layers: 32,
+            num_attention_heads: 71,
+            num_kv_heads: None,
+            multi_query: None,
+            new_decoder_architecture: None,
+            parallel_attn: None,
+            bias: None,
+            alibi: None,
+            activation: None,
+            ffn_hidden_size: None,
+            layer_norm_epsilon: 1e-5,
+            rope_theta: None,
+            max_position_embeddings: None,
+            attention_dropout: None,
+            hidden_dropout: None,
+            tie_word_embeddings: None,
+            bos_token_id: 11,
+            eos_token_id: 11,
+            pad_token_id: None,
+            output_attentions: None,
+            output_hidden_states: None,
+        }
+    }
+}
+
+impl From<&FalconConfig> for ParallelBlockConfig {
+    fn from(config: &FalconConfig) -> Self {
+        let new_decoder_architecture = config.new_decoder_architecture.unwrap_or(false);
+        let parallel_residual = new_decoder_architecture || config.parallel_attn.unwrap_or(true);
+        let num_key_value_heads = if new_decoder_architecture {
+            config.num_kv_heads.unwrap_or(config.num_attention_heads)
+        } else if config.multi_query.unwrap_or(true) {
+            1
+        } else {
+            config.num_attention_heads
+        };
+        let (attention_layer_norm_name, mlp_layer_norm_name) = if new_decoder_architecture {
+            ("ln_attn", Some("ln_mlp"))
+        } else if parallel_residual {
+            ("input_layernorm", None)
+        } else {
+            ("input_layernorm", Some("post_attention_layernorm"))
+        };
+        let bias = config.bias.unwrap_or(false);
+
+        ParallelBlockConfig {
+            hidden_size: config.hidden_size,
+            intermediate_size: config.ffn_hidden_size.unwrap_or(4 * config.hidden_size),
+            num_attention_heads: config.num_attention_heads,
+            num_key_value_heads,
+            rotary_dim: config.hidden_size / config.num_attention_heads,
+            rotary_base: config.rope_theta.unwrap_or(10000.0),
+            max_position_embeddings: config.max_position_embeddings.unwrap_or(2048),
+            layer_norm_eps: config.layer_norm_epsilon,
+            attention_bias: bias,
+            mlp_bias: bias,
+            activation: config.activation.unwrap_or(Activation::gelu),
+            attention_dropout: config.attention_dropout.unwrap_or(0.0),
+            hidden_dropout: config.hidden_dropout.unwrap_or(0.0),
+            parallel_residual,
+            attention_name: "self_attention",
+            attention_layer_norm_name,
+            mlp_layer_norm_name,
+            output_attentions: config.output_attentions.unwrap_or(false),
+        }
+    }
+}
+
+/// # Falcon Base model
+/// Base architecture for Falcon models. Task-specific models will be built from this common base model
+/// It is made of the following blocks:
+/// - `word_embeddings`: Word embeddings
+/// - `h`: Vector of `GptNeoXLayer` (pre-normalization transformer layers with rotary position embeddings, multi-query
+/// or grouped-query attention and, for most checkpoints, attention and feed-forward layers computed in parallel)
+/// - `ln_f`: Final layer normalization
+pub struct FalconModel {
+    word_embeddings: nn::Embedding,
+    h: Vec<GptNeoXLayer>,
+    ln_f: nn::LayerNorm,
+    output_attentions: bool,
+    output_hidden_states: bool,
+}
+
+impl FalconModel {
+    /// Build a new `FalconModel`
+    ///
+    /// # Arguments
+    ///
+    /// * `p` - Variable store path for the root of the Falcon model
+    /// * `config` - `FalconConfig` object defining the model architecture
+    ///
+    /// # Example
+    ///
+    /// ```no_run
+    /// use rust_bert::falcon::{FalconConfig, FalconModel};
+    /// use rust_bert::Config;
+    /// use std::path::Path;
+    /// use tch::{nn, Device};
+    ///
+    /// let config_path = Path::new("path/to/config.json");
+    /// let device = Device::Cpu;
+    /// let p = nn::VarStore::new(device);
+    /// let config = FalconConfig::from_file(config_path);
+    /// let falcon_model = FalconModel::new(&p.root() / "transformer", &config).unwrap();
+    /// ```
+    pub fn new<'p, P>(p: P, config: &FalconConfig) -> Result<FalconModel, RustBertError>
+    where
+        P: Borrow<nn::Path<'p>>,
+    {
+        let p = p.borrow();
+
+        if config.alibi.unwrap_or(false) {
+            return Err(RustBertError::InvalidConfigurationError(
+                "Falcon models using ALiBi position biases are not supported".to_string(),
+            ));
+        }
+        if config.hidden_size % config.num_attention_heads != 0 {
+            return Err(RustBertError::InvalidConfigurationError(format!(
+                "The hidden size ({}) must be a multiple of the number of attention heads ({})",
+                config.hidden_size, config.num_attention_heads
+            )));
+        }
+        let block_config = ParallelBlockConfig::from(config);
+        if config.num_attention_heads % block_config.num_key_value_heads != 0 {
+            return Err(RustBertError::InvalidConfigurationError(format!(
+                "The number of attention heads ({}) must be a multiple of the number of key and value heads ({})",
+                config.num_attention_heads, block_config.num_key_value_heads
+            )));
+        }
+
+        let word_embeddings = nn::embedding(
+            p / "word_embeddings",
+            config.vocab_size,
+            config.hidden_size,
+            Default::default(),
+        );
+
+        let mut h: Vec<GptNeoXLayer> = Vec::with_capacity(config.num_hidden_layers as usize);
+        let p_layers = p / "h";
+        let rotary_embedding = block_config.rotary_embedding();
+        for layer_index in 0..config.num_hidden_layers {
+            h.push(GptNeoXLayer::new(
+                &p_layers / layer_index,
+                &block_config,
+                &rotary_embedding,
+            ));
+        }
+
+        let ln_f = nn::layer_norm(
+            p / "ln_f",
+            vec![config.hidden_size],
+            nn::LayerNormConfig {
+                eps: config.layer_norm_epsilon,
+                ..Default::default()
+            },
+        );
+
+        let output_attentions = config.output_attentions.unwrap_or(false);
+        let output_hidden_states = config.output_hidden_states.unwrap_or(false);
+
+        Ok(FalconModel {
+            word_embeddings,
+            h,
+            ln_f,
+            output_attentions,
+            output_hidden_states,
+        })
+    }
+
+    /// Forward pass through the model
+    ///
+    /// # Arguments
+    ///
+    /// * `input_ids` - Optional input tensor of shape (*batch size*, *sequence_length*). This or `input_embeds` must be provided.
+    /// * `input_embeds` - Optional input tensor of shape (*batch size*, *sequence_length*, *embeddings dimension*). This or `input_ids` must be provided.
+    /// * `position_ids` - Optional position ids of shape (*batch size*, *sequence_length*). If None, will be incremented starting from the length of the past input.
+    /// * `layer_states` - Optional Vector `Option<Vec<Option<LayerState>>>` of length *n_layer* containing the past keys and values of each layer.
+    /// * `attention_mask` - Optional attention mask of shape (*batch size*, *past_length + sequence_length*) for the past and current positions. Positions with a mask with value 0 will be masked.
+    /// * `train` - boolean flag to turn on/off the dropout layers in the model. Should be set to false for inference.
+    ///
+    /// # Returns
+    ///
+    /// * `Result<FalconModelOutput, RustBertError>` containing:
+    ///   - `hidden_states` - `Tensor` of shape (*batch size*, *sequence_length*, *hidden_size*) representing the activations of the last hidden state
+    ///   - `next_cache` - `Option<Vec<Option<LayerState>>>` of length *n_layer* containing the past content for the the attention layers
+    ///   - `all_hidden_states` - `Option<Vec<Tensor>>` of length *n_layer + 1* with shape (*batch size*, *sequence_length*, *hidden_size*)
+    ///   - `all_attentions` - `Option<Vec<Tensor>>` of length *n_layer* containing the attention weights for each layer
+    ///
+    /// # Example
+    ///
+    /// ```no_run
+    /// # use tch::{nn, Device, Tensor, no_grad, Kind};
+    /// # use rust_bert::Config;
+    /// # use std::path::Path;
+    /// # use tch::kind::Kind::{Int64, Double};
+    /// use rust_bert::falcon::{FalconConfig, FalconModel};
+    /// # let config_path = Path::new("path/to/config.json");
+    /// # let device = Device::Cpu;
+    /// # let vs = nn::VarStore::new(device);
+    /// # let config = FalconConfig::from_file(config_path);
+    /// # let falcon_model = FalconModel::new(&vs.root() / "transformer", &config).unwrap();
+    /// let (batch_size, sequence_length) = (64, 128);
+    /// let input_tensor = Tensor::rand(&[batch_size, sequence_length], (Int64, device));
+    /// let attention_mask = Tensor::ones(&[batch_size, sequence_length], (Int64, device));
+    ///
+    /// let model_output = no_grad(|| {
+    ///     falcon_model.forward_t(
+    ///         Some(&input_tensor),
+    ///         None,
+    ///         None,
+    ///         None,
+    ///         Some(&attention_mask),
+    ///         false,
+    ///     )
+    /// });
+    /// ```
+    pub fn forward_t(
+        &self,
+        input_ids: Option<&Tensor>,
+        input_embeds: Option<&Tensor>,
+        position_ids: Option<&Tensor>,
+        layer_states: Option<Vec<Option<LayerState>>>,
+        attention_mask: Option<&Tensor>,
+        train: bool,
+    ) -> Result<FalconModelOutput, RustBertError> {
+        let (calc_input_embeddings, input_shape, device) =
+            process_ids_embeddings_pair(input_ids, input_embeds, &self.word_embeddings)?;
+
+        let (batch_size, current_sequence_length) = (input_shape[0], input_shape[1]);
+
+        let past_length = match &layer_states {
+            Some(past_state_value) => match &past_state_value[0] {
+                Some(first_layer_state) => first_layer_state.prev_key.size()[2],
+                None => 0,
+            },
+            None => 0,
+        };
+
+        let calc_position_ids = if position_ids.is_none() {
+            Some(
+                Tensor::arange_start(
+                    past_length,
+                    past_length + current_sequence_length,
+                    (Kind::Int64, device),
+                )
+                .unsqueeze(0),
+            )
+        } else {
+            None
+        };
+        let position_ids = position_ids.unwrap_or_else(|| calc_position_ids.as_ref().unwrap());
+
+        let attention_mask = causal_attention_mask(
+            batch_size,
+            current_sequence_length,
+            past_length,
+            attention_mask,
+            device,
+        );
+
+        let mut hidden_state = input_embeds
+            .unwrap_or_else(|| calc_input_embeddings.as_ref().unwrap())
+            .shallow_clone();
+
+        let mut all_hidden_states: Option<Vec<Tensor>> = if self.output_hidden_states {
+            Some(vec![])
+        } else {
+            None
+        };
+        let mut all_attentions: Option<Vec<Tensor>> = if self.output_attentions {
+            Some(vec![])
+        } else {
+            None
+        };
+        let old_cache = layer_states.unwrap_or_else(|| vec![None; self.h.len()]);
+        let mut next_cache = vec![None; self.h.len()];
+
+        for ((layer_idx, layer), layer_state) in
+            self.h.iter().enumerate().zip(old_cache.into_iter())
+        {
+            if let Some(hidden_states) = all_hidden_states.borrow_mut() {
+                hidden_states.push(hidden_state.copy());
+            };
+            let (output, attention_weights, layer_state) = layer.forward_t(
+                &hidden_state,
+                position_ids,
+                Some(&attention_mask),
+                layer_state.as_ref(),
+                train,
+            );
+            hidden_state = output;
+            next_cache[layer_idx] = layer_state;
+            if let Some(attentions) = all_attentions.borrow_mut() {
+                attentions.push(attention_weights.unwrap());
+            };
+        }
+
+        let hidden_states = hidden_state.apply(&self.ln_f);
+        if let Some(all_hidden_states) = all_hidden_states.borrow_mut() {
+            all_hidden_states.push(hidden_states.copy());
+        };
+
+        Ok(FalconModelOutput {
+            hidden_states,
+            next_cache: Some(next_cache),
+            all_hidden_states,
+            all_attentions,
+        })
+    }
+}
+
+/// # Falcon Model for causal language modeling
+/// Falcon model with a vocabulary decoding head (`lm_head`, tied to the word embeddings unless
+/// `tie_word_embeddings` is set to `false`).
+/// It is made of the following blocks:
+/// - `transformer`: `FalconModel` Base Falcon model
+/// - `lm_head`: Linear layer mapping the hidden states to the vocabulary logits
+pub struct FalconForCausalLM {
+    transformer: FalconModel,
+    lm_head: Option<nn::Linear>,
+}
+
+impl FalconForCausalLM {
+    /// Build a new `FalconForCausalLM`
+    ///
+    /// # Arguments
+    ///
+    /// * `p` - Variable store path for the root of the Falcon model
+    /// * `config` - `FalconConfig` object defining the model architecture
+    ///
+    /// # Example
+    ///
+    /// ```no_run
+    /// use rust_bert::falcon::{FalconConfig, FalconForCausalLM};
+    /// use rust_bert::Config;
+    /// use std::path::Path;
+    /// use tch::{nn, Device};
+    ///
+    /// let config_path = Path::new("path/to/config.json");
+    /// let device = Device::Cpu;
+    /// let p = nn::VarStore::new(device);
+    /// let config = FalconConfig::from_file(config_path);
+    /// let falcon_model = FalconForCausalLM::new(&p.root(), &config).unwrap();
+    /// ```
+    pub fn new<'p, P>(p: P, config: &FalconConfig) -> Result<FalconForCausalLM, RustBertError>
+    where
+        P: Borrow<nn::Path<'p>>,
+    {
+        let p = p.borrow();
+
+        let transformer = FalconModel::new(p / "transformer", config)?;
+        let lm_head = if config.tie_word_embeddings.unwrap_or(true) {
+            None
+        } else {
+            Some(nn::linear(
+                p / "lm_head",
+                config.hidden_size,
+                config.vocab_size,
+                nn::LinearConfig {
+                    bias: false,
+                    ..Default::default()
+                },
+            ))
+        };
+
+        Ok(FalconForCausalLM {
+            transformer,
+            lm_head,
+        })
+    }
+
+    /// Forward pass through the model
+    ///
+    /// # Arguments
+    ///
+    /// * `input_ids` - Optional input tensor of shape (*batch size*, *sequence_length*). This or `input_embeds` must be provided.
+    /// * `input_embeds` - Optional input tensor of shape (*batch size*, *sequence_length*, *embeddings dimension*). This or `input_ids` must be provided.
+    /// * `position_ids` - Optional position ids of shape (*batch size*, *sequence_length*). If None, will be incremented starting from the length of the past input.
+    /// * `layer_states` - Optional Vector `Option<Vec<Option<LayerState>>>` of length *n_layer* containing the past keys and values of each layer.
+    /// * `attention_mask` - Optional attention mask of shape (*batch size*, *past_length + sequence_length*) for the past and current positions. Positions with a mask with value 0 will be masked.
+    /// * `train` - boolean flag to turn on/off the dropout layers in the model. Should be set to false for inference.
+    ///
+    /// # Returns
+    ///
+    /// * `Result<FalconModelLMOutput, RustBertError>` containing:
+    ///   - `lm_logits` - `Tensor` of shape (*batch size*, *sequence_length*, *vocab_size*) representing the logits for each vocab item and position
+    ///   - `hidden_states` - `Tensor` of shape (*batch size*, *sequence_length*, *hidden_size*) representing the activations of the last hidden state
+    ///   - `next_cache` - `Option<Vec<Option<LayerState>>>` of length *n_layer* containing the past content for the the attention layers
+    ///   - `all_hidden_states` - `Option<Vec<Tensor>>` of length *n_layer + 1* with shape (*batch size*, *sequence_length*, *hidden_size*)
+    ///   - `all_attentions` - `Option<Vec<Tensor>>` of length *n_layer* containing the attention weights for each layer
+    ///
+    /// # Example
+    ///
+    /// ```no_run
+    /// # use tch::{nn, Device, Tensor, no_grad, Kind};
+    /// # use rust_bert::Config;
+    /// # use std::path::Path;
+    /// # use tch::kind::Kind::{Int64, Double};
+    /// use rust_bert::falcon::{FalconConfig, FalconForCausalLM};
+    /// # let config_path = Path::new("path/to/config.json");
+    /// # let device = Device::Cpu;
+    /// # let vs = nn::VarStore::new(device);
+    /// # let config = FalconConfig::from_file(config_path);
+    /// # let falcon_model = FalconForCausalLM::new(&vs.root(), &config).unwrap();
+    /// let (batch_size, sequence_length) = (64, 128);
+    /// let input_tensor = Tensor::rand(&[batch_size, sequence_length], (Int64, device));
+    /// let attention_mask = Tensor::ones(&[batch_size, sequence_length], (Int64, device));
+    ///
+    /// let model_output = no_grad(|| {
+    ///     falcon_model.forward_t(
+    ///         Some(&input_tensor),
+    ///         None,
+    ///         None,
+    ///         None,
+    ///         Some(&attention_mask),
+    ///         false,
+    ///     )
+    /// });
+    /// ```
+    pub fn forward_t(
+        &self,
+        input_ids: Option<&Tensor>,
+        input_embeds: Option<&Tensor>,
+        position_ids: Option<&Tensor>,
+        layer_states: Option<Vec<Option<LayerState>>>,
+        attention_mask: Option<&Tensor>,
+        train: bool,
+    ) -> Result<FalconModelLMOutput, RustBertError> {
+        let base_model_output = self.transformer.forward_t(
+            input_ids,
+            input_embeds,
+            position_ids,
+            layer_states,
+            attention_mask,
+            train,
+        )?;
+
+        let lm_logits = match &self.lm_head {
+            Some(lm_head) => base_model_output.hidden_states.apply(lm_head),
+            None => base_model_output
+                .hidden_states
+                .linear::<Tensor>(&self.transformer.word_embeddings.ws, None),
+        };
+
+        Ok(FalconModelLMOutput {
+            lm_logits,
+            hidden_states: base_model_output.hidden_states,
+            next_cache: base_model_output.next_cache,
+            all_hidden_states: base_model_output.all_hidden_states,
+            all_attentions: base_model_output.all_attentions,
+        })
+    }
+}
+
+impl LMHeadModel for FalconForCausalLM {
+    fn forward_t(
+        &self,
+        input_ids: Option<&Tensor>,
+        layer_past: Cache,
+        attention_mask: Option<&Tensor>,
+        _token_type_ids: Option<&Tensor>,
+        position_ids: Option<&Tensor>,
+        input_embeds: Option<&Tensor>,
+        _encoder_outputs: Option<&Tensor>,
+        _decoder_input_ids: Option<&Tensor>,
+        train: bool,
+    ) -> Result<LMModelOutput, RustBertError> {
+        let base_model_output = match layer_past {
+            Cache::GPTNeoXCache(layer_past) => self.forward_t(
+                input_ids,
+                input_embeds,
+                position_ids,
+                layer_past,
+                attention_mask,
+                train,
+            ),
+            Cache::None => self.forward_t(
+                input_ids,
+                input_embeds,
+                position_ids,
+                None,
+                attention_mask,
+                train,
+            ),
+            _ => {
+                return Err(RustBertError::ValueError(
+                    "Cache not compatible with Falcon Model".into(),
+                ));
+            }
+        }?;
+
+        Ok(LMModelOutput {
+            lm_logits: base_model_output.lm_logits,
+            cache: Cache::GPTNeoXCache(base_model_output.next_cache),
+            hidden_states: Some(base_model_output.hidden_states),
+        })
+    }
+}
+
+/// Container for the Falcon model output.
+pub struct FalconModelOutput {
+    /// Last hidden states from the model
+    pub hidden_states: Tensor,
+    /// Cached outputs of the model (attention layers keys and values) if the model is used for generation
+    pub next_cache: Option<Vec<Option<LayerState>>>,
+    /// Hidden states for all intermediate layers
+    pub all_hidden_states: Option<Vec<Tensor>>,
+    /// Attention weights for all intermediate layers
+    pub all_attentions: Option<Vec<Tensor>>,
+}
+
+///Container holding a Falcon model with LM head output
+pub struct FalconModelLMOutput {
+    /// logits
+    pub lm_logits: Tensor,
+    /// Last hidden states from the model
+    pub hidden_states: Tensor,
+    /// Cached outputs of the model (attention layers keys and values) if the model is used for generation
+    pub next_cache: Option<Vec<Option<LayerState>>>,
+    /// Hidden states for all intermediate layers
+    pub all_hidden_states: Option<Vec<Tensor>>,
+    /// Attention weights for all intermediate layers
+    pub all_attentions: Option<Vec<Tensor>>,
+}
+
+/// # Language generation model based on the Falcon architecture
+pub struct FalconGenerator {
+    model: FalconForCausalLM,
+    tokenizer: TokenizerOption,
+    var_store: nn::VarStore,
+    generate_config: GenerateConfig,
+    bos_token_id: Option<i64>,
+    eos_token_ids: Option<Vec<i64>>,
+    pad_token_id: Option<i64>,
+    is_encoder_decoder: bool,
+    vocab_size: i64,
+    decoder_start_id: Option<i64>,
+    max_position_embeddings: i64,
+}
+
+impl FalconGenerator {
+    /// Build a new `FalconGenerator`
+    ///
+    /// # Arguments
+    ///
+    /// * `generate_config` - `GenerateConfig` object containing the resource references (model, vocabulary, configuration), generation options and device placement (CPU/GPU)
+    ///
+    /// # Example
+    ///
+    /// ```no_run
+    /// # fn main() -> anyhow::Result<()> {
+    /// use rust_bert::falcon::FalconGenerator;
+    /// use rust_bert::pipelines::generation_utils::GenerateConfig;
+    /// use rust_bert::resources::LocalResource;
+    /// use std::path::PathBuf;
+    ///
+    /// let generate_config = GenerateConfig {
+    ///     model_resource: Box::new(LocalResource {
+    ///         local_path: PathBuf::from("path/to/rust_model.ot"),
+    ///     }),
+    ///     config_resource: Box::new(LocalResource {
+    ///         local_path: PathBuf::from("path/to/config.json"),
+    ///     }),
+    ///     vocab_resource: Box::new(LocalResource {
+    ///         local_path: PathBuf::from("path/to/vocab.json"),
+    ///     }),
+    ///     merges_resource: Box::new(LocalResource {
+    ///         local_path: PathBuf::from("path/to/merges.txt"),
+    ///     }),
+    ///     max_length: 64,
+    ///     do_sample: false,
+    ///     ..Default::default()
+    /// };
+    /// let falcon_generator = FalconGenerator::new(generate_config)?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn new(generate_config: GenerateConfig) -> Result<FalconGenerator, RustBertError> {
+        let vocab_path = generate_config.vocab_resource.get_local_path()?;
+        let merges_path = generate_config.merges_resource.get_local_path()?;
+
+        let tokenizer = TokenizerOption::from_file(
+            ModelType::Falcon,
+            vocab_path.to_str().unwrap(),
+            Some(merges_path.to_str().unwrap()),
+            false,
+            None,
+            None,
+        )?;
+
+        Self::new_with_tokenizer(generate_config, tokenizer)
+    }
+
+    pub fn new_with_tokenizer(
+        generate_config: GenerateConfig,
+        tokenizer: TokenizerOption,
+    ) -> Result<FalconGenerator, RustBertError> {
+        let config_path = generate_config.config_resource.get_local_path()?;
+        let device = generate_config.device;
+
+        generate_config.validate();
+        let mut var_store = nn::VarStore::new(device);
+        let config = FalconConfig::from_file(config_path);
+        let model = FalconForCausalLM::new(&var_store.root(), &config)?;
+        crate::resources::load_weights(&generate_config.model_resource, &mut var_store)?;
+
+        let bos_token_id = tokenizer.get_bos_id();
+        let eos_token_ids = tokenizer.get_eos_id().map(|id| vec![id]);
+        let pad_token_id = tokenizer.get_pad_id();
+        let is_encoder_decoder = false;
+        let vocab_size = config.vocab_size;
+        let decoder_start_id = None;
+        let max_position_embeddings = config.max_position_embeddings.unwrap_or(2048);
+
+        Ok(FalconGenerator {
+            model,
+            tokenizer,
+            var_store,
+            generate_config,
+            bos_token_id,
+            eos_token_ids,
+            pad_token_id,
+            is_encoder_decoder,
+            vocab_size,
+            decoder_start_id,
+            max_position_embeddings,
+        })
+    }
+}
+
+impl PrivateLanguageGenerator<FalconForCausalLM, Gpt2Vocab, Gpt2Tokenizer> for FalconGenerator {
+    fn get_model(&self) -> &FalconForCausalLM {
+        &self.model
+    }
+    fn _get_tokenizer(&self) -> &TokenizerOption {
+        &self.tokenizer
+    }
+    fn get_var_store(&self) -> &nn::VarStore {
+        &self.var_store
+    }
+    fn get_var_store_mut(&mut self) -> &mut nn::VarStore {
+        &mut self.var_store
+    }
+    fn get_config(&self) -> &GenerateConfig {
+        &self.generate_config
+    }
+    fn get_bos_id(&self) -> Option<i64> {
+        self.bos_token_id
+    }
+    fn get_eos_ids(&self) -> Option<&Vec<i64>> {
+        self.eos_token_ids.as_ref()
+    }
+    fn get_pad_id(&self) -> Option<i64> {
+        self.pad_token_id
+    }
+    fn is_encoder_decoder(&self) -> bool {
+        self.is_encoder_decoder
+    }
+    fn get_vocab_size(&self) -> i64 {
+        self.vocab_size
+    }
+    fn get_decoder_start_id(&self) -> Option<i64> {
+        self.decoder_start_id
+    }
+    fn get_max_positions_embeddings(&self) -> i64 {
+        self.max_position_embeddings
+    }
+    fn supports_chunked_prefill(&self) -> bool {
+        true
+    }
+
+    fn prepare_inputs_for_generation<'a>(
+        &self,
+        input_ids: Tensor,
+        _encoder_outputs: Option<&'a Tensor>,
+        past: Cache,
+        attention_mask: Tensor,
+    ) -> PreparedInput<'a> {
+        crate::gpt_neox::prepare_inputs_for_generation(input_ids, past, attention_mask)
+    }
+
+    fn reorder_cache(
+        &self,
+        past: &mut Cache,
+        _encoder_outputs: Option<Tensor>,
+        beam_indices: &Tensor,
+    ) -> Option<Tensor> {
+        crate::gpt_neox::reorder_cache(past, beam_indices)
+    }
+}
+
+impl LanguageGenerator<FalconForCausalLM, Gpt2Vocab, Gpt2Tokenizer> for FalconGenerator {}
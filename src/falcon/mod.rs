@@ -0,0 +1,70 @@
+//! # Falcon
+//!
+//! Implementation of the Falcon language models ([The Falcon Series of Open Language Models](https://arxiv.org/abs/2311.16867) Almazrouei et al., 2023).
+//! The base model is implemented in the `falcon_model::FalconModel` struct. A causal language modeling head is implemented in `falcon_model::FalconForCausalLM`.
+//! The decoder layers are shared with GPT-NeoX (`crate::gpt_neox`): rotary position embeddings, a fused query, key and value projection with a
+//! multi-query attention (a single key and value head, Falcon-7B) or grouped-query attention (`new_decoder_architecture`, Falcon-40B and larger),
+//! and attention and feed-forward layers computed in parallel from the layer input.
+//! Checkpoints using ALiBi position biases (`alibi`) are not supported.
+//!
+//! # Model set-up and pre-trained weights loading
+//!
+//! The model is available for text generation and conversation with `ModelType::Falcon`. All models expect the following resources:
+//! - Configuration file expected to have a structure following the [Transformers library](https://github.com/huggingface/transformers)
+//! - Model weights are expected to have a structure and parameter names following the [Transformers library](https://github.com/huggingface/transformers). A conversion using the Python utility scripts is required to convert the `.bin` weights to the `.ot` format (the shards of a checkpoint can be passed together: `python utils/convert_model.py path/to/pytorch_model-*.bin`).
+//! - `Gpt2Tokenizer` using a `vocab.json` vocabulary and a `merges.txt` merges file. The checkpoints only provide a `tokenizer.json` file: the vocabulary and merges files are saved with the Transformers tokenizer (`tokenizer.save_vocabulary(path)`)
+//!
+//! No pre-trained checkpoint is hosted with the crate, the resources are provided as local files:
+//!
+//! ```no_run
+//! use rust_bert::pipelines::common::ModelType;
+//! use rust_bert::pipelines::text_generation::{TextGenerationConfig, TextGenerationModel};
+//! use rust_bert::resources::LocalResource;
+//! use std::path::PathBuf;
+//! use tch::Device;
+//!
+//! fn main() -> anyhow::Result<()> {
+//!     let config_resource = Box::new(LocalResource {
+//!         local_path: PathBuf::from("path/to/config.json"),
+//!     });
+//!     let vocab_resource = Box::new(LocalResource {
+//!         local_path: PathBuf::from("path/to/vocab.json"),
+//!     });
+//!     let merges_resource = Box::new(LocalResource {
+//!         local_path: PathBuf::from("path/to/merges.txt"),
+//!     });
+//!     let model_resource = Box::new(LocalResource {
+//!         local_path: PathBuf::from("path/to/rust_model.ot"),
+//!     });
+//!
+//!     let text_generation_config = TextGenerationConfig {
+//!         model_type: ModelType::Falcon,
+//!         model_resource,
+//!         config_resource,
+//!         vocab_resource,
+//!         merges_resource,
+//!         do_sample: false,
+//!         max_length: 64,
+//!         device: Device::cuda_if_available(),
+//!         ..Default::default()
+//!     };
+//!     let mut model = TextGenerationModel::new(text_generation_config)?;
+//!     model.half();
+//!
+//!     let input_context = "The capital of France is";
+//!     let output = model.generate(&[input_context], None);
+//!
+//!     for sentence in output {
+//!         println!("{}", sentence);
+//!     }
+//!
+//!     Ok(())
+//! }
+//! ```
+
+mod falcon_model;
+
+pub use falcon_model::{
+    FalconConfig, FalconForCausalLM, FalconGenerator, FalconModel, FalconModelLMOutput,
+    FalconModelOutput,
+};
@@ -552,6 +552,7 @@ impl LMHeadModel for GPT2LMHeadModel {
     /// * `LMModelOutput` containing:
     ///   - `lm_logits` - `Tensor` of shape (*batch size*, *sequence_length*, *vocab_size*) representing the logits for each vocab item and position
     ///   - `cache` - `Gpt2Cache` made of `Option<Vec<Tensor>>` of length *n_layer* containing the past keys and values of each layer of shape (*2*, *batch size*, *number of heads*, *past_sequence_length*, *hidden size per head*)
+    ///   - `hidden_states` - `Option<Tensor>` of shape (*batch size*, *sequence_length*, *hidden_size*) representing the activations of the last hidden state
     ///
     /// # Example
     ///
@@ -648,6 +649,7 @@ impl LMHeadModel for GPT2LMHeadModel {
         Ok(LMModelOutput {
             lm_logits,
             cache: Cache::GPT2Cache(base_model_output.cache),
+            hidden_states: Some(base_model_output.output),
         })
     }
 }
@@ -727,7 +729,6 @@ impl GPT2Generator {
         tokenizer: TokenizerOption,
     ) -> Result<GPT2Generator, RustBertError> {
         let config_path = generate_config.config_resource.get_local_path()?;
-        let weights_path = generate_config.model_resource.get_local_path()?;
         let device = generate_config.device;
 
         generate_config.validate();
@@ -735,7 +736,7 @@ impl GPT2Generator {
 
         let config = Gpt2Config::from_file(config_path);
         let model = GPT2LMHeadModel::new(&var_store.root(), &config);
-        var_store.load(weights_path)?;
+        crate::resources::load_weights(&generate_config.model_resource, &mut var_store)?;
 
         let bos_token_id = tokenizer.get_bos_id();
         let eos_token_ids = tokenizer.get_eos_id().map(|id| vec![id]);
@@ -798,6 +799,9 @@ impl PrivateLanguageGenerator<GPT2LMHeadModel, Gpt2Vocab, Gpt2Tokenizer> for GPT
     fn get_max_positions_embeddings(&self) -> i64 {
         self.max_position_embeddings
     }
+    fn supports_chunked_prefill(&self) -> bool {
+        true
+    }
 
     fn prepare_inputs_for_generation<'a>(
         &self,
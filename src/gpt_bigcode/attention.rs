@@ -0,0 +1,171 @@
+// Copyright 2023 The Bigcode team and HuggingFace Inc. team.
+// Copyright 2022 Guillaume Becquin
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//     http://www.apache.org/licenses/LICENSE-2.0
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use crate::common::dropout::Dropout;
+use crate::gpt_bigcode::GptBigCodeConfig;
+use crate::gpt_neox::LayerState;
+use std::borrow::Borrow;
+use tch::{nn, Kind, Tensor};
+
+/// # GPT-BigCode attention layer
+/// With the multi-query attention (default), the fused `c_attn` projection returns the queries of all heads followed
+/// by a single key head and a single value head, shared by all query heads. The key and value head is broadcasted
+/// in the attention products instead of being repeated, and only this head is cached (of shape (*batch size*, 1,
+/// *sequence_length*, *head dimension*)).
+/// Otherwise, the projection returns the query, key and value of each head interleaved as in GPT-2.
+pub struct GptBigCodeAttention {
+    c_attn: nn::Linear,
+    c_proj: nn::Linear,
+    attn_dropout: Dropout,
+    resid_dropout: Dropout,
+    num_heads: i64,
+    head_dim: i64,
+    multi_query: bool,
+    scale_attention_weights: bool,
+    output_attentions: bool,
+}
+
+impl GptBigCodeAttention {
+    pub fn new<'p, P>(p: P, config: &GptBigCodeConfig) -> GptBigCodeAttention
+    where
+        P: Borrow<nn::Path<'p>>,
+    {
+        let p = p.borrow();
+
+        let num_heads = config.n_head;
+        let head_dim = config.n_embd / num_heads;
+        let multi_query = config.multi_query.unwrap_or(true);
+
+        let key_value_dim = if multi_query {
+            2 * head_dim
+        } else {
+            2 * config.n_embd
+        };
+        let c_attn = nn::linear(
+            p / "c_attn",
+            config.n_embd,
+            config.n_embd + key_value_dim,
+            Default::default(),
+        );
+        let c_proj = nn::linear(
+            p / "c_proj",
+            config.n_embd,
+            config.n_embd,
+            Default::default(),
+        );
+
+        let attn_dropout = Dropout::new(config.attn_pdrop.unwrap_or(0.1));
+        let resid_dropout = Dropout::new(config.resid_pdrop.unwrap_or(0.1));
+
+        GptBigCodeAttention {
+            c_attn,
+            c_proj,
+            attn_dropout,
+            resid_dropout,
+            num_heads,
+            head_dim,
+            multi_query,
+            scale_attention_weights: config.scale_attn_weights.unwrap_or(true),
+            output_attentions: config.output_attentions.unwrap_or(false),
+        }
+    }
+
+    /// Splits the fused projection into the queries (*batch size*, *num_heads*, *sequence_length*, *head dimension*)
+    /// and the keys and values (*batch size*, 1 or *num_heads*, *sequence_length*, *head dimension*)
+    fn split_query_key_value(&self, fused_qkv: &Tensor) -> (Tensor, Tensor, Tensor) {
+        let (batch_size, sequence_length, _) = fused_qkv.size3().unwrap();
+        if self.multi_query {
+            let hidden_size = self.num_heads * self.head_dim;
+            let query = fused_qkv
+                .slice(2, 0, hidden_size, 1)
+                .view([batch_size, sequence_length, self.num_heads, self.head_dim])
+                .transpose(1, 2);
+            let key = fused_qkv
+                .slice(2, hidden_size, hidden_size + self.head_dim, 1)
+                .unsqueeze(1);
+            let value = fused_qkv
+                .slice(
+                    2,
+                    hidden_size + self.head_dim,
+                    hidden_size + 2 * self.head_dim,
+                    1,
+                )
+                .unsqueeze(1);
+            (query, key, value)
+        } else {
+            let fused_qkv = fused_qkv
+                .view([
+                    batch_size,
+                    sequence_length,
+                    self.num_heads,
+                    3 * self.head_dim,
+                ])
+                .transpose(1, 2);
+            let query = fused_qkv.slice(3, 0, self.head_dim, 1);
+            let key = fused_qkv.slice(3, self.head_dim, 2 * self.head_dim, 1);
+            let value = fused_qkv.slice(3, 2 * self.head_dim, 3 * self.head_dim, 1);
+            (query, key, value)
+        }
+    }
+
+    pub fn forward_t(
+        &self,
+        hidden_states: &Tensor,
+        attention_mask: Option<&Tensor>,
+        layer_state: Option<&LayerState>,
+        train: bool,
+    ) -> (Tensor, Option<Tensor>, Option<LayerState>) {
+        let (batch_size, sequence_length, _) = hidden_states.size3().unwrap();
+
+        let (query, key, value) = self.split_query_key_value(&hidden_states.apply(&self.c_attn));
+
+        let (key, value) = match layer_state {
+            Some(layer_state_value) => (
+                Tensor::cat(&[&layer_state_value.prev_key, &key], -2),
+                Tensor::cat(&[&layer_state_value.prev_value, &value], -2),
+            ),
+            None => (key, value),
+        };
+        let layer_state = Some(LayerState {
+            prev_key: key.copy(),
+            prev_value: value.copy(),
+        });
+
+        let mut attention_weights = query.matmul(&key.transpose(-1, -2));
+        if self.scale_attention_weights {
+            attention_weights = attention_weights / (self.head_dim as f64).sqrt();
+        }
+        if let Some(attention_mask) = attention_mask {
+            attention_weights = attention_weights + attention_mask;
+        }
+        let attention_weights = attention_weights
+            .softmax(-1, Kind::Float)
+            .to_kind(value.kind())
+            .apply_t(&self.attn_dropout, train);
+
+        let attention_output = attention_weights
+            .matmul(&value)
+            .transpose(1, 2)
+            .contiguous()
+            .view([batch_size, sequence_length, self.num_heads * self.head_dim])
+            .apply(&self.c_proj)
+            .apply_t(&self.resid_dropout, train);
+
+        let attention_weights = if self.output_attentions {
+            Some(attention_weights)
+        } else {
+            None
+        };
+
+        (attention_output, attention_weights, layer_state)
+    }
+}
@@ -0,0 +1,807 @@
+// Copyright 2023 The Bigcode team and HuggingFace Inc. team.
+// Copyright 2022 Guillaume Becquin
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//     http://www.apache.org/licenses/LICENSE-2.0
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use crate::common::activations::TensorFunction;
+use crate::common::dropout::Dropout;
+use crate::common::embeddings::process_ids_embeddings_pair;
+use crate::gpt_bigcode::attention::GptBigCodeAttention;
+use crate::gpt_neox::decoder::causal_attention_mask;
+use crate::gpt_neox::LayerState;
+use crate::pipelines::common::{ModelType, TokenizerOption};
+use crate::pipelines::generation_utils::private_generation_utils::{
+    PreparedInput, PrivateLanguageGenerator,
+};
+use crate::pipelines::generation_utils::{
+    Cache, GenerateConfig, LMHeadModel, LMModelOutput, LanguageGenerator,
+};
+use crate::{Activation, Config, RustBertError};
+use rust_tokenizers::tokenizer::Gpt2Tokenizer;
+use rust_tokenizers::vocab::Gpt2Vocab;
+use serde::{Deserialize, Serialize};
+use std::borrow::{Borrow, BorrowMut};
+use tch::{nn, Kind, Tensor};
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+/// # GPT-BigCode model configuration
+/// Defines the GPT-BigCode model architecture (e.g. number of layers, hidden layer size, vocab size...).
+/// Used by the SantaCoder and StarCoder code generation checkpoints.
+pub struct GptBigCodeConfig {
+    pub vocab_size: i64,
+    pub n_positions: i64,
+    pub n_embd: i64,
+    pub n_layer: i64,
+    pub n_head: i64,
+    /// Size of the feed-forward layers (defaults to `4 * n_embd`)
+    pub n_inner: Option<i64>,
+    /// Activation of the feed-forward layers (defaults to `gelu_new`, `gelu_pytorch_tanh` in the Python library)
+    pub activation_function: Option<Activation>,
+    pub resid_pdrop: Option<f64>,
+    pub embd_pdrop: Option<f64>,
+    pub attn_pdrop: Option<f64>,
+    pub layer_norm_epsilon: Option<f64>,
+    pub scale_attn_weights: Option<bool>,
+    /// Single key and value head shared by all query heads (defaults to `true`)
+    pub multi_query: Option<bool>,
+    pub tie_word_embeddings: Option<bool>,
+    pub bos_token_id: i64,
+    pub eos_token_id: i64,
+    pub pad_token_id: Option<i64>,
+    pub output_attentions: Option<bool>,
+    pub output_hidden_states: Option<bool>,
+}
+
+impl Config for GptBigCodeConfig {}
+
+impl Default for GptBigCodeConfig {
+    fn default() -> Self {
+        GptBigCodeConfig {
+            vocab_size: 50257,
+            n_positions: 1024,
+            n_embd: 768,
+            n_layer: 12,
+            n_head: 12,
+            n_inner: None,
+            activation_function: None,
+            resid_pdrop: None,
+            embd_pdrop: None,
+            attn_pdrop: None,
+            layer_norm_epsilon: None,
+            scale_attn_weights: None,
+            multi_query: None,
+            tie_word_embeddings: None,
+            bos_token_id: 50256,
+            eos_token_id: 50256,
+            pad_token_id: None,
+            output_attentions: None,
+            output_hidden_states: None,
+        }
+    }
+}
+
+/// # GPT-BigCode feed-forward layer
+pub struct GptBigCodeMLP {
+    c_fc: nn::Linear,
+    c_proj: nn::Linear,
+    activation_function: TensorFunction,
+    dropout: Dropout,
+}
+
+impl GptBigCodeMLP {
+    pub fn new<'p, P>(p: P, config: &GptBigCodeConfig) -> GptBigCodeMLP
+    where
+        P: Borrow<nn::Path<'p>>,
+    {
+        let p = p.borrow();
+
+        let intermediate_size = config.n_inner.unwrap_or(4 * config.n_embd);
+        let c_fc = nn::linear(
+            p / "c_fc",
+            config.n_embd,
+            intermediate_size,
+            Default::default(),
+        );
+        let c_proj = nn::linear(
+            p / "c_proj",
+            intermediate_size,
+            config.n_embd,
+            Default::default(),
+        );
+        let activation_function = config
+            .activation_function
+            .unwrap_or(Activation::gelu_new)
+            .get_function();
+        let dropout = Dropout::new(config.resid_pdrop.unwrap_or(0.1));
+
+        GptBigCodeMLP {
+            c_fc,
+            c_proj,
+            activation_function,
+            dropout,
+        }
+    }
+
+    pub fn forward_t(&self, hidden_states: &Tensor, train: bool) -> Tensor {
+        self.activation_function.get_fn()(&hidden_states.apply(&self.c_fc))
+            .apply(&self.c_proj)
+            .apply_t(&self.dropout, train)
+    }
+}
+
+/// # GPT-BigCode decoder layer
+/// Pre-normalization decoder layer applying the attention and feed-forward layers sequentially as in GPT-2:
+/// `h = x + attention(ln_1(x))` followed by `h + mlp(ln_2(h))`.
+pub struct GptBigCodeBlock {
+    ln_1: nn::LayerNorm,
+    attn: GptBigCodeAttention,
+    ln_2: nn::LayerNorm,
+    mlp: GptBigCodeMLP,
+}
+
+impl GptBigCodeBlock {
+    pub fn new<'p, P>(p: P, config: &GptBigCodeConfig) -> GptBigCodeBlock
+    where
+        P: Borrow<nn::Path<'p>>,
+    {
+        let p = p.borrow();
+
+        let layer_norm_config = nn::LayerNormConfig {
+            eps: config.layer_norm_epsilon.unwrap_or(1e-5),
+            ..Default::default()
+        };
+        let ln_1 = nn::layer_norm(p / "ln_1", vec![config.n_embd], layer_norm_config);
+        let attn = GptBigCodeAttention::new(p / "attn", config);
+        let ln_2 = nn::layer_norm(p / "ln_2", vec![config.n_embd], layer_norm_config);
+        let mlp = GptBigCodeMLP::new(p / "mlp", config);
+
+        GptBigCodeBlock {
+            ln_1,
+            attn,
+            ln_2,
+            mlp,
+        }
+    }
+
+    pub fn forward_t(
+        &self,
+        hidden_states: &Tensor,
+        attention_mask: Option<&Tensor>,
+        layer_state: Option<&LayerState>,
+        train: bool,
+    ) -> (Tensor, Option<Tensor>, Option<LayerState>) {
+        let (attention_output, attention_weights, layer_state) = self.attn.forward_t(
+            &hidden_states.apply(&self.ln_1),
+            attention_mask,
+            layer_state,
+            train,
+        );
+        let hidden_states = hidden_states + attention_output;
+        let mlp_output = self.mlp.forward_t(&hidden_states.apply(&self.ln_2), train);
+
+        (hidden_states + mlp_output, attention_weights, layer_state)
+    }
+}
+
+/// # GPT-BigCode Base model
+/// Base architecture for GPT-BigCode models. Task-specific models will be built from this common base model
+/// It is made of the following blocks:
+/// - `wte`: Word embeddings
+/// - `wpe`: Learned position embeddings
+/// - `h`: Vector of `GptBigCodeBlock` (pre-normalization transformer layers with multi-query attention)
+/// - `ln_f`: Final layer normalization
+pub struct GptBigCodeModel {
+    wte: nn::Embedding,
+    wpe: nn::Embedding,
+    drop: Dropout,
+    h: Vec<GptBigCodeBlock>,
+    ln_f: nn::LayerNorm,
+    output_attentions: bool,
+    output_hidden_states: bool,
+}
+
+impl GptBigCodeModel {
+    /// Build a new `GptBigCodeModel`
+    ///
+    /// # Arguments
+    ///
+    /// * `p` - Variable store path for the root of the GPT-BigCode model
+    /// * `config` - `GptBigCodeConfig` object defining the model architecture
+    ///
+    /// # Example
+    ///
+    /// ```no_run
+    /// use rust_bert::gpt_bigcode::{GptBigCodeConfig, GptBigCodeModel};
+    /// use rust_bert::Config;
+    /// use std::path::Path;
+    /// use tch::{nn, Device};
+    ///
+    /// let config_path = Path::new("path/to/config.json");
+    /// let device = Device::Cpu;
+    /// let p = nn::VarStore::new(device);
+    /// let config = GptBigCodeConfig::from_file(config_path);
+    /// let gpt_bigcode_model = GptBigCodeModel::new(&p.root() / "transformer", &config).unwrap();
+    /// ```
+    pub fn new<'p, P>(p: P, config: &GptBigCodeConfig) -> Result<GptBigCodeModel, RustBertError>
+    where
+        P: Borrow<nn::Path<'p>>,
+    {
+        let p = p.borrow();
+
+        if config.n_embd % config.n_head != 0 {
+            return Err(RustBertError::InvalidConfigurationError(format!(
+                "The hidden size ({}) must be a multiple of the number of attention heads ({})",
+                config.n_embd, config.n_head
+            )));
+        }
+
+        let wte = nn::embedding(
+            p / "wte",
+            config.vocab_size,
+            config.n_embd,
+            Default::default(),
+        );
+        let wpe = nn::embedding(
+            p / "wpe",
+            config.n_positions,
+            config.n_embd,
+            Default::default(),
+        );
+        let drop = Dropout::new(config.embd_pdrop.unwrap_or(0.1));
+
+        let mut h: Vec<GptBigCodeBlock> = Vec::with_capacity(config.n_layer as usize);
+        let p_layers = p / "h";
+        for layer_index in 0..config.n_layer {
+            h.push(GptBigCodeBlock::new(&p_layers / layer_index, config));
+        }
+
+        let ln_f = nn::layer_norm(
+            p / "ln_f",
+            vec![config.n_embd],
+            nn::LayerNormConfig {
+                eps: config.layer_norm_epsilon.unwrap_or(1e-5),
+                ..Default::default()
+            },
+        );
+
+        let output_attentions = config.output_attentions.unwrap_or(false);
+        let output_hidden_states = config.output_hidden_states.unwrap_or(false);
+
+        Ok(GptBigCodeModel {
+            wte,
+            wpe,
+            drop,
+            h,
+            ln_f,
+            output_attentions,
+            output_hidden_states,
+        })
+    }
+
+    /// Forward pass through the model
+    ///
+    /// # Arguments
+    ///
+    /// * `input_ids` - Optional input tensor of shape (*batch size*, *sequence_length*). This or `input_embeds` must be provided.
+    /// * `input_embeds` - Optional input tensor of shape (*batch size*, *sequence_length*, *embeddings dimension*). This or `input_ids` must be provided.
+    /// * `position_ids` - Optional position ids of shape (*batch size*, *sequence_length*). If None, will be incremented starting from the length of the past input.
+    /// * `layer_states` - Optional Vector `Option<Vec<Option<LayerState>>>` of length *n_layer* containing the past keys and values of each layer.
+    /// * `attention_mask` - Optional attention mask of shape (*batch size*, *past_length + sequence_length*) for the past and current positions. Positions with a mask with value 0 will be masked.
+    /// * `train` - boolean flag to turn on/off the dropout layers in the model. Should be set to false for inference.
+    ///
+    /// # Returns
+    ///
+    /// * `Result<GptBigCodeModelOutput, RustBertError>` containing:
+    ///   - `hidden_states` - `Tensor` of shape (*batch size*, *sequence_length*, *hidden_size*) representing the activations of the last hidden state
+    ///   - `next_cache` - `Option<Vec<Option<LayerState>>>` of length *n_layer* containing the past content for the the attention layers
+    ///   - `all_hidden_states` - `Option<Vec<Tensor>>` of length *n_layer + 1* with shape (*batch size*, *sequence_length*, *hidden_size*)
+    ///   - `all_attentions` - `Option<Vec<Tensor>>` of length *n_layer* containing the attention weights for each layer
+    ///
+    /// # Example
+    ///
+    /// ```no_run
+    /// # use tch::{nn, Device, Tensor, no_grad, Kind};
+    /// # use rust_bert::Config;
+    /// # use std::path::Path;
+    /// # use tch::kind::Kind::{Int64, Double};
+    /// use rust_bert::gpt_bigcode::{GptBigCodeConfig, GptBigCodeModel};
+    /// # let config_path = Path::new("path/to/config.json");
+    /// # let device = Device::Cpu;
+    /// # let vs = nn::VarStore::new(device);
+    /// # let config = GptBigCodeConfig::from_file(config_path);
+    /// # let gpt_bigcode_model = GptBigCodeModel::new(&vs.root() / "transformer", &config).unwrap();
+    /// let (batch_size, sequence_length) = (64, 128);
+    /// let input_tensor = Tensor::rand(&[batch_size, sequence_length], (Int64, device));
+    /// let attention_mask = Tensor::ones(&[batch_size, sequence_length], (Int64, device));
+    ///
+    /// let model_output = no_grad(|| {
+    ///     gpt_bigcode_model.forward_t(
+    ///         Some(&input_tensor),
+    ///         None,
+    ///         None,
+    ///         None,
+    ///         Some(&attention_mask),
+    ///         false,
+    ///     )
+    /// });
+    /// ```
+    pub fn forward_t(
+        &self,
+        input_ids: Option<&Tensor>,
+        input_embeds: Option<&Tensor>,
+        position_ids: Option<&Tensor>,
+        layer_states: Option<Vec<Option<LayerState>>>,
+        attention_mask: Option<&Tensor>,
+        train: bool,
+    ) -> Result<GptBigCodeModelOutput, RustBertError> {
+        let (calc_input_embeddings, input_shape, device) =
+            process_ids_embeddings_pair(input_ids, input_embeds, &self.wte)?;
+
+        let (batch_size, current_sequence_length) = (input_shape[0], input_shape[1]);
+
+        let past_length = match &layer_states {
+            Some(past_state_value) => match &past_state_value[0] {
+                Some(first_layer_state) => first_layer_state.prev_key.size()[2],
+                None => 0,
+            },
+            None => 0,
+        };
+
+        let calc_position_ids = if position_ids.is_none() {
+            Some(
+                Tensor::arange_start(
+                    past_length,
+                    past_length + current_sequence_length,
+                    (Kind::Int64, device),
+                )
+                .unsqueeze(0),
+            )
+        } else {
+            None
+        };
+        let position_ids = position_ids.unwrap_or_else(|| calc_position_ids.as_ref().unwrap());
+
+        let attention_mask = causal_attention_mask(
+            batch_size,
+            current_sequence_length,
+            past_length,
+            attention_mask,
+            device,
+        );
+
+        let input_embeddings =
+            input_embeds.unwrap_or_else(|| calc_input_embeddings.as_ref().unwrap());
+        let mut hidden_state =
+            (input_embeddings + position_ids.apply(&self.wpe)).apply_t(&self.drop, train);
+
+        let mut all_hidden_states: Option<Vec<Tensor>> = if self.output_hidden_states {
+            Some(vec![])
+        } else {
+            None
+        };
+        let mut all_attentions: Option<Vec<Tensor>> = if self.output_attentions {
+            Some(vec![])
+        } else {
+            None
+        };
+        let old_cache = layer_states.unwrap_or_else(|| vec![None; self.h.len()]);
+        let mut next_cache = vec![None; self.h.len()];
+
+        for ((layer_idx, layer), layer_state) in
+            self.h.iter().enumerate().zip(old_cache.into_iter())
+        {
+            if let Some(hidden_states) = all_hidden_states.borrow_mut() {
+                hidden_states.push(hidden_state.copy());
+            };
+            let (output, attention_weights, layer_state) = layer.forward_t(
+                &hidden_state,
+                Some(&attention_mask),
+                layer_state.as_ref(),
+                train,
+            );
+            hidden_state = output;
+            next_cache[layer_idx] = layer_state;
+            if let Some(attentions) = all_attentions.borrow_mut() {
+                attentions.push(attention_weights.unwrap());
+            };
+        }
+
+        let hidden_states = hidden_state.apply(&self.ln_f);
+        if let Some(all_hidden_states) = all_hidden_states.borrow_mut() {
+            all_hidden_states.push(hidden_states.copy());
+        };
+
+        Ok(GptBigCodeModelOutput {
+            hidden_states,
+            next_cache: Some(next_cache),
+            all_hidden_states,
+            all_attentions,
+        })
+    }
+}
+
+/// # GPT-BigCode Model for causal language modeling
+/// GPT-BigCode model with a vocabulary decoding head (`lm_head`, tied to the word embeddings unless
+/// `tie_word_embeddings` is set to `false`).
+/// It is made of the following blocks:
+/// - `transformer`: `GptBigCodeModel` Base GPT-BigCode model
+/// - `lm_head`: Linear layer mapping the hidden states to the vocabulary logits
+pub struct GptBigCodeForCausalLM {
+    transformer: GptBigCodeModel,
+    lm_head: Option<nn::Linear>,
+}
+
+impl GptBigCodeForCausalLM {
+    /// Build a new `GptBigCodeForCausalLM`
+    ///
+    /// # Arguments
+    ///
+    /// * `p` - Variable store path for the root of the GPT-BigCode model
+    /// * `config` - `GptBigCodeConfig` object defining the model architecture
+    ///
+    /// # Example
+    ///
+    /// ```no_run
+    /// use rust_bert::gpt_bigcode::{GptBigCodeConfig, GptBigCodeForCausalLM};
+    /// use rust_bert::Config;
+    /// use std::path::Path;
+    /// use tch::{nn, Device};
+    ///
+    /// let config_path = Path::new("path/to/config.json");
+    /// let device = Device::Cpu;
+    /// let p = nn::VarStore::new(device);
+    /// let config = GptBigCodeConfig::from_file(config_path);
+    /// let gpt_bigcode_model = GptBigCodeForCausalLM::new(&p.root(), &config).unwrap();
+    /// ```
+    pub fn new<'p, P>(
+        p: P,
+        config: &GptBigCodeConfig,
+    ) -> Result<GptBigCodeForCausalLM, RustBertError>
+    where
+        P: Borrow<nn::Path<'p>>,
+    {
+        let p = p.borrow();
+
+        let transformer = GptBigCodeModel::new(p / "transformer", config)?;
+        let lm_head = if config.tie_word_embeddings.unwrap_or(true) {
+            None
+        } else {
+            Some(nn::linear(
+                p / "lm_head",
+                config.n_embd,
+                config.vocab_size,
+                nn::LinearConfig {
+                    bias: false,
+                    ..Default::default()
+                },
+            ))
+        };
+
+        Ok(GptBigCodeForCausalLM {
+            transformer,
+            lm_head,
+        })
+    }
+
+    /// Forward pass through the model
+    ///
+    /// # Arguments
+    ///
+    /// * `input_ids` - Optional input tensor of shape (*batch size*, *sequence_length*). This or `input_embeds` must be provided.
+    /// * `input_embeds` - Optional input tensor of shape (*batch size*, *sequence_length*, *embeddings dimension*). This or `input_ids` must be provided.
+    /// * `position_ids` - Optional position ids of shape (*batch size*, *sequence_length*). If None, will be incremented starting from the length of the past input.
+    /// * `layer_states` - Optional Vector `Option<Vec<Option<LayerState>>>` of length *n_layer* containing the past keys and values of each layer.
+    /// * `attention_mask` - Optional attention mask of shape (*batch size*, *past_length + sequence_length*) for the past and current positions. Positions with a mask with value 0 will be masked.
+    /// * `train` - boolean flag to turn on/off the dropout layers in the model. Should be set to false for inference.
+    ///
+    /// # Returns
+    ///
+    /// * `Result<GptBigCodeModelLMOutput, RustBertError>` containing:
+    ///   - `lm_logits` - `Tensor` of shape (*batch size*, *sequence_length*, *vocab_size*) representing the logits for each vocab item and position
+    ///   - `hidden_states` - `Tensor` of shape (*batch size*, *sequence_length*, *hidden_size*) representing the activations of the last hidden state
+    ///   - `next_cache` - `Option<Vec<Option<LayerState>>>` of length *n_layer* containing the past content for the the attention layers
+    ///   - `all_hidden_states` - `Option<Vec<Tensor>>` of length *n_layer + 1* with shape (*batch size*, *sequence_length*, *hidden_size*)
+    ///   - `all_attentions` - `Option<Vec<Tensor>>` of length *n_layer* containing the attention weights for each layer
+    ///
+    /// # Example
+    ///
+    /// ```no_run
+    /// # use tch::{nn, Device, Tensor, no_grad, Kind};
+    /// # use rust_bert::Config;
+    /// # use std::path::Path;
+    /// # use tch::kind::Kind::{Int64, Double};
+    /// use rust_bert::gpt_bigcode::{GptBigCodeConfig, GptBigCodeForCausalLM};
+    /// # let config_path = Path::new("path/to/config.json");
+    /// # let device = Device::Cpu;
+    /// # let vs = nn::VarStore::new(device);
+    /// # let config = GptBigCodeConfig::from_file(config_path);
+    /// # let gpt_bigcode_model = GptBigCodeForCausalLM::new(&vs.root(), &config).unwrap();
+    /// let (batch_size, sequence_length) = (64, 128);
+    /// let input_tensor = Tensor::rand(&[batch_size, sequence_length], (Int64, device));
+    /// let attention_mask = Tensor::ones(&[batch_size, sequence_length], (Int64, device));
+    ///
+    /// let model_output = no_grad(|| {
+    ///     gpt_bigcode_model.forward_t(
+    ///         Some(&input_tensor),
+    ///         None,
+    ///         None,
+    ///         None,
+    ///         Some(&attention_mask),
+    ///         false,
+    ///     )
+    /// });
+    /// ```
+    pub fn forward_t(
+        &self,
+        input_ids: Option<&Tensor>,
+        input_embeds: Option<&Tensor>,
+        position_ids: Option<&Tensor>,
+        layer_states: Option<Vec<Option<LayerState>>>,
+        attention_mask: Option<&Tensor>,
+        train: bool,
+    ) -> Result<GptBigCodeModelLMOutput, RustBertError> {
+        let base_model_output = self.transformer.forward_t(
+            input_ids,
+            input_embeds,
+            position_ids,
+            layer_states,
+            attention_mask,
+            train,
+        )?;
+
+        let lm_logits = match &self.lm_head {
+            Some(lm_head) => base_model_output.hidden_states.apply(lm_head),
+            None => base_model_output
+                .hidden_states
+                .linear::<Tensor>(&self.transformer.wte.ws, None),
+        };
+
+        Ok(GptBigCodeModelLMOutput {
+            lm_logits,
+            hidden_states: base_model_output.hidden_states,
+            next_cache: base_model_output.next_cache,
+            all_hidden_states: base_model_output.all_hidden_states,
+            all_attentions: base_model_output.all_attentions,
+        })
+    }
+}
+
+impl LMHeadModel for GptBigCodeForCausalLM {
+    fn forward_t(
+        &self,
+        input_ids: Option<&Tensor>,
+        layer_past: Cache,
+        attention_mask: Option<&Tensor>,
+        _token_type_ids: Option<&Tensor>,
+        position_ids: Option<&Tensor>,
+        input_embeds: Option<&Tensor>,
+        _encoder_outputs: Option<&Tensor>,
+        _decoder_input_ids: Option<&Tensor>,
+        train: bool,
+    ) -> Result<LMModelOutput, RustBertError> {
+        let base_model_output = match layer_past {
+            Cache::GPTNeoXCache(layer_past) => self.forward_t(
+                input_ids,
+                input_embeds,
+                position_ids,
+                layer_past,
+                attention_mask,
+                train,
+            ),
+            Cache::None => self.forward_t(
+                input_ids,
+                input_embeds,
+                position_ids,
+                None,
+                attention_mask,
+                train,
+            ),
+            _ => {
+                return Err(RustBertError::ValueError(
+                    "Cache not compatible with GPT-BigCode Model".into(),
+                ));
+            }
+        }?;
+
+        Ok(LMModelOutput {
+            lm_logits: base_model_output.lm_logits,
+            cache: Cache::GPTNeoXCache(base_model_output.next_cache),
+            hidden_states: Some(base_model_output.hidden_states),
+        })
+    }
+}
+
+/// Container for the GPT-BigCode model output.
+pub struct GptBigCodeModelOutput {
+    /// Last hidden states from the model
+    pub hidden_states: Tensor,
+    /// Cached outputs of the model (attention layers keys and values) if the model is used for generation
+    pub next_cache: Option<Vec<Option<LayerState>>>,
+    /// Hidden states for all intermediate layers
+    pub all_hidden_states: Option<Vec<Tensor>>,
+    /// Attention weights for all intermediate layers
+    pub all_attentions: Option<Vec<Tensor>>,
+}
+
+///Container holding a GPT-BigCode model with LM head output
+pub struct GptBigCodeModelLMOutput {
+    /// logits
+    pub lm_logits: Tensor,
+    /// Last hidden states from the model
+    pub hidden_states: Tensor,
+    /// Cached outputs of the model (attention layers keys and values) if the model is used for generation
+    pub next_cache: Option<Vec<Option<LayerState>>>,
+    /// Hidden states for all intermediate layers
+    pub all_hidden_states: Option<Vec<Tensor>>,
+    /// Attention weights for all intermediate layers
+    pub all_attentions: Option<Vec<Tensor>>,
+}
+
+/// # Language generation model based on the GPT-BigCode architecture
+pub struct GptBigCodeGenerator {
+    model: GptBigCodeForCausalLM,
+    tokenizer: TokenizerOption,
+    var_store: nn::VarStore,
+    generate_config: GenerateConfig,
+    bos_token_id: Option<i64>,
+    eos_token_ids: Option<Vec<i64>>,
+    pad_token_id: Option<i64>,
+    is_encoder_decoder: bool,
+    vocab_size: i64,
+    decoder_start_id: Option<i64>,
+    max_position_embeddings: i64,
+}
+
+impl GptBigCodeGenerator {
+    /// Build a new `GptBigCodeGenerator`
+    ///
+    /// # Arguments
+    ///
+    /// * `generate_config` - `GenerateConfig` object containing the resource references (model, vocabulary, configuration), generation options and device placement (CPU/GPU)
+    ///
+    /// # Example
+    ///
+    /// ```no_run
+    /// # fn main() -> anyhow::Result<()> {
+    /// use rust_bert::gpt_bigcode::GptBigCodeGenerator;
+    /// use rust_bert::pipelines::generation_utils::GenerateConfig;
+    /// use rust_bert::resources::LocalResource;
+    /// use std::path::PathBuf;
+    ///
+    /// let generate_config = GenerateConfig {
+    ///     model_resource: Box::new(LocalResource::from(PathBuf::from("path/to/rust_model.ot"))),
+    ///     config_resource: Box::new(LocalResource::from(PathBuf::from("path/to/config.json"))),
+    ///     vocab_resource: Box::new(LocalResource::from(PathBuf::from("path/to/vocab.json"))),
+    ///     merges_resource: Box::new(LocalResource::from(PathBuf::from("path/to/merges.txt"))),
+    ///     max_length: 64,
+    ///     do_sample: false,
+    ///     ..Default::default()
+    /// };
+    /// let gpt_bigcode_generator = GptBigCodeGenerator::new(generate_config)?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn new(generate_config: GenerateConfig) -> Result<GptBigCodeGenerator, RustBertError> {
+        let vocab_path = generate_config.vocab_resource.get_local_path()?;
+        let merges_path = generate_config.merges_resource.get_local_path()?;
+
+        let tokenizer = TokenizerOption::from_file(
+            ModelType::GPTBigCode,
+            vocab_path.to_str().unwrap(),
+            Some(merges_path.to_str().unwrap()),
+            false,
+            None,
+            None,
+        )?;
+
+        Self::new_with_tokenizer(generate_config, tokenizer)
+    }
+
+    pub fn new_with_tokenizer(
+        generate_config: GenerateConfig,
+        tokenizer: TokenizerOption,
+    ) -> Result<GptBigCodeGenerator, RustBertError> {
+        let config_path = generate_config.config_resource.get_local_path()?;
+        let device = generate_config.device;
+
+        generate_config.validate();
+        let mut var_store = nn::VarStore::new(device);
+        let config = GptBigCodeConfig::from_file(config_path);
+        let model = GptBigCodeForCausalLM::new(&var_store.root(), &config)?;
+        crate::resources::load_weights(&generate_config.model_resource, &mut var_store)?;
+
+        let bos_token_id = tokenizer.get_bos_id();
+        let eos_token_ids = tokenizer.get_eos_id().map(|id| vec![id]);
+        let pad_token_id = tokenizer.get_pad_id();
+        let is_encoder_decoder = false;
+        let vocab_size = config.vocab_size;
+        let decoder_start_id = None;
+        let max_position_embeddings = config.n_positions;
+
+        Ok(GptBigCodeGenerator {
+            model,
+            tokenizer,
+            var_store,
+            generate_config,
+            bos_token_id,
+            eos_token_ids,
+            pad_token_id,
+            is_encoder_decoder,
+            vocab_size,
+            decoder_start_id,
+            max_position_embeddings,
+        })
+    }
+}
+
+impl PrivateLanguageGenerator<GptBigCodeForCausalLM, Gpt2Vocab, Gpt2Tokenizer>
+    for GptBigCodeGenerator
+{
+    fn get_model(&self) -> &GptBigCodeForCausalLM {
+        &self.model
+    }
+    fn _get_tokenizer(&self) -> &TokenizerOption {
+        &self.tokenizer
+    }
+    fn get_var_store(&self) -> &nn::VarStore {
+        &self.var_store
+    }
+    fn get_var_store_mut(&mut self) -> &mut nn::VarStore {
+        &mut self.var_store
+    }
+    fn get_config(&self) -> &GenerateConfig {
+        &self.generate_config
+    }
+    fn get_bos_id(&self) -> Option<i64> {
+        self.bos_token_id
+    }
+    fn get_eos_ids(&self) -> Option<&Vec<i64>> {
+        self.eos_token_ids.as_ref()
+    }
+    fn get_pad_id(&self) -> Option<i64> {
+        self.pad_token_id
+    }
+    fn is_encoder_decoder(&self) -> bool {
+        self.is_encoder_decoder
+    }
+    fn get_vocab_size(&self) -> i64 {
+        self.vocab_size
+    }
+    fn get_decoder_start_id(&self) -> Option<i64> {
+        self.decoder_start_id
+    }
+    fn get_max_positions_embeddings(&self) -> i64 {
+        self.max_position_embeddings
+    }
+    fn supports_chunked_prefill(&self) -> bool {
+        true
+    }
+
+    fn prepare_inputs_for_generation<'a>(
+        &self,
+        input_ids: Tensor,
+        _encoder_outputs: Option<&'a Tensor>,
+        past: Cache,
+        attention_mask: Tensor,
+    ) -> PreparedInput<'a> {
+        crate::gpt_neox::prepare_inputs_for_generation(input_ids, past, attention_mask)
+    }
+
+    fn reorder_cache(
+        &self,
+        past: &mut Cache,
+        _encoder_outputs: Option<Tensor>,
+        beam_indices: &Tensor,
+    ) -> Option<Tensor> {
+        crate::gpt_neox::reorder_cache(past, beam_indices)
+    }
+}
+
+impl LanguageGenerator<GptBigCodeForCausalLM, Gpt2Vocab, Gpt2Tokenizer> for GptBigCodeGenerator {}
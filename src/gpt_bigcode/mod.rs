@@ -0,0 +1,59 @@
+//! # GPT-BigCode (SantaCoder, StarCoder)
+//!
+//! Implementation of the GPT-BigCode code generation models ([SantaCoder: don't reach for the stars!](https://arxiv.org/abs/2301.03988) Allal et al., 2023,
+//! [StarCoder: may the source be with you!](https://arxiv.org/abs/2305.06161) Li et al., 2023).
+//! The base model is implemented in the `gpt_bigcode_model::GptBigCodeModel` struct. A causal language modeling head is implemented in `gpt_bigcode_model::GptBigCodeForCausalLM`.
+//! Compared to GPT-2, the decoder layers use linear projections and a multi-query attention (`multi_query`): all query heads share a single key and value head,
+//! reducing the size of the keys and values cache by a factor of `n_head`. The cache uses the `LayerState` of the GPT-NeoX models (`crate::gpt_neox`).
+//!
+//! The models are trained with a fill-in-the-middle objective: the code between a prefix and a suffix can be generated with
+//! `TextGenerationModel::fill_in_the_middle`, using the `<fim_prefix>`, `<fim_suffix>` and `<fim_middle>` special tokens of the vocabulary
+//! (`FillInTheMiddleTokens::STARCODER`, or `FillInTheMiddleTokens::SANTACODER` for the SantaCoder vocabulary).
+//!
+//! # Model set-up and pre-trained weights loading
+//!
+//! The model is available for text generation with `ModelType::GPTBigCode`. All models expect the following resources:
+//! - Configuration file expected to have a structure following the [Transformers library](https://github.com/huggingface/transformers)
+//! - Model weights are expected to have a structure and parameter names following the [Transformers library](https://github.com/huggingface/transformers). A conversion using the Python utility scripts is required to convert the `.bin` weights to the `.ot` format.
+//! - `Gpt2Tokenizer` using a `vocab.json` vocabulary and a `merges.txt` merges file
+//!
+//! ```no_run
+//! use rust_bert::pipelines::common::ModelType;
+//! use rust_bert::pipelines::text_generation::{
+//!     FillInTheMiddleTokens, TextGenerationConfig, TextGenerationModel,
+//! };
+//! use rust_bert::resources::LocalResource;
+//! use std::path::PathBuf;
+//! use tch::Device;
+//!
+//! fn main() -> anyhow::Result<()> {
+//!     let text_generation_config = TextGenerationConfig {
+//!         model_type: ModelType::GPTBigCode,
+//!         model_resource: Box::new(LocalResource::from(PathBuf::from("path/to/rust_model.ot"))),
+//!         config_resource: Box::new(LocalResource::from(PathBuf::from("path/to/config.json"))),
+//!         vocab_resource: Box::new(LocalResource::from(PathBuf::from("path/to/vocab.json"))),
+//!         merges_resource: Box::new(LocalResource::from(PathBuf::from("path/to/merges.txt"))),
+//!         do_sample: false,
+//!         max_length: 64,
+//!         device: Device::cuda_if_available(),
+//!         ..Default::default()
+//!     };
+//!     let model = TextGenerationModel::new(text_generation_config)?;
+//!
+//!     let completion = model.generate(&["def fibonacci(n):"], None);
+//!     let middle = model.fill_in_the_middle(
+//!         &[("def add(a, b):\n    ", "\n\nprint(add(1, 2))")],
+//!         &FillInTheMiddleTokens::STARCODER,
+//!     )?;
+//!
+//!     Ok(())
+//! }
+//! ```
+
+mod attention;
+mod gpt_bigcode_model;
+
+pub use gpt_bigcode_model::{
+    GptBigCodeConfig, GptBigCodeForCausalLM, GptBigCodeGenerator, GptBigCodeModel,
+    GptBigCodeModelLMOutput, GptBigCodeModelOutput,
+};
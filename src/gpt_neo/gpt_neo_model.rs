@@ -503,6 +503,7 @@ impl GptNeoForCausalLM {
     ///
     /// * `Result<GptNeoModelLMOutput, RustBertError>` containing:
     ///   - `lm_logits` - `Tensor` of shape (*batch size*, *sequence_length*, *vocab_size*) representing the logits for each vocab item and position
+    ///   - `hidden_states` - `Tensor` of shape (*batch size*, *sequence_length*, *hidden_size*) representing the activations of the last hidden state
     ///   - `next_cache` - `Option<Vec<Option<LayerState>>>` of length *n_layer* containing the past content for the the attention layers
     ///   - `all_hidden_states` - `Option<Vec<Tensor>>` of length *n_layer + 1* with shape (*batch size*, *sequence_length*, *hidden_size*)
     ///   - `all_attentions` - `Option<Vec<Tensor>>` of length *n_layer* containing the attention weights for each layer
@@ -563,6 +564,7 @@ impl GptNeoForCausalLM {
 
         Ok(GptNeoModelLMOutput {
             lm_logits,
+            hidden_states: base_model_output.hidden_states,
             next_cache: base_model_output.next_cache,
             all_hidden_states: base_model_output.all_hidden_states,
             all_attentions: base_model_output.all_attentions,
@@ -612,6 +614,7 @@ impl LMHeadModel for GptNeoForCausalLM {
         Ok(LMModelOutput {
             lm_logits: base_model_output.lm_logits,
             cache: Cache::GPTNeoCache(base_model_output.next_cache),
+            hidden_states: Some(base_model_output.hidden_states),
         })
     }
 }
@@ -632,6 +635,8 @@ pub struct GptNeoModelOutput {
 pub struct GptNeoModelLMOutput {
     /// logits
     pub lm_logits: Tensor,
+    /// Last hidden states from the model
+    pub hidden_states: Tensor,
     /// Cached outputs of the model (attention layers keys and values) if the model is used for generation
     pub next_cache: Option<Vec<Option<LayerState>>>,
     /// Hidden states for all intermediate layers
@@ -702,14 +707,13 @@ impl GptNeoGenerator {
         tokenizer: TokenizerOption,
     ) -> Result<GptNeoGenerator, RustBertError> {
         let config_path = generate_config.config_resource.get_local_path()?;
-        let weights_path = generate_config.model_resource.get_local_path()?;
         let device = generate_config.device;
 
         generate_config.validate();
         let mut var_store = nn::VarStore::new(device);
         let config = GptNeoConfig::from_file(config_path);
         let model = GptNeoForCausalLM::new(&var_store.root(), &config)?;
-        var_store.load(weights_path)?;
+        crate::resources::load_weights(&generate_config.model_resource, &mut var_store)?;
 
         let bos_token_id = tokenizer.get_bos_id();
         let eos_token_ids = tokenizer.get_eos_id().map(|id| vec![id]);
@@ -772,6 +776,9 @@ impl PrivateLanguageGenerator<GptNeoForCausalLM, Gpt2Vocab, Gpt2Tokenizer> for G
     fn get_max_positions_embeddings(&self) -> i64 {
         self.max_position_embeddings
     }
+    fn supports_chunked_prefill(&self) -> bool {
+        true
+    }
 
     fn prepare_inputs_for_generation<'a>(
         &self,
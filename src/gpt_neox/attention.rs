@@ -0,0 +1,199 @@
+// Copyright 2022 EleutherAI and the HuggingFace Inc. team. All rights reserved.
+// Copyright 2022 Guillaume Becquin
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//     http://www.apache.org/licenses/LICENSE-2.0
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use crate::common::dropout::Dropout;
+use crate::common::rotary::RotaryEmbedding;
+use crate::gpt_neox::decoder::ParallelBlockConfig;
+use std::borrow::Borrow;
+use tch::{nn, Kind, Tensor};
+
+#[derive(Debug)]
+/// # Cache for GPT-NeoX and Falcon attention layers
+/// Stores the cached value of key and value, after the rotary position embeddings and before their repetition for
+/// the multi-query or grouped-query attention (of shape (*batch size*, *num_key_value_heads*, *sequence_length*,
+/// *head dimension*))
+pub struct LayerState {
+    /// Cached keys
+    pub prev_key: Tensor,
+    /// Cached values
+    pub prev_value: Tensor,
+}
+
+impl Clone for LayerState {
+    fn clone(&self) -> Self {
+        LayerState {
+            prev_key: self.prev_key.copy(),
+            prev_value: self.prev_value.copy(),
+        }
+    }
+}
+
+impl LayerState {
+    pub(crate) fn reorder_cache(&mut self, new_indices: &Tensor) {
+        self.prev_key = self.prev_key.index_select(0, new_indices);
+        self.prev_value = self.prev_value.index_select(0, new_indices);
+    }
+}
+
+/// # Attention layer with a fused query, key and value projection
+/// The output of the `query_key_value` projection is grouped by key and value head: each group contains the
+/// `num_heads / num_key_value_heads` query heads sharing the key and value heads, followed by the key head and the
+/// value head. This covers the interleaved layout of GPT-NeoX (one query per group) as well as the multi-query
+/// (a single group) and grouped-query layouts of Falcon.
+pub struct GptNeoXAttention {
+    query_key_value: nn::Linear,
+    dense: nn::Linear,
+    attention_dropout: Dropout,
+    rotary_embedding: RotaryEmbedding,
+    num_heads: i64,
+    num_key_value_heads: i64,
+    head_dim: i64,
+    output_attentions: bool,
+}
+
+impl GptNeoXAttention {
+    pub fn new<'p, P>(
+        p: P,
+        config: &ParallelBlockConfig,
+        rotary_embedding: RotaryEmbedding,
+    ) -> GptNeoXAttention
+    where
+        P: Borrow<nn::Path<'p>>,
+    {
+        let p = p.borrow();
+
+        let num_heads = config.num_attention_heads;
+        let num_key_value_heads = config.num_key_value_heads;
+        let head_dim = config.hidden_size / num_heads;
+
+        let linear_config = nn::LinearConfig {
+            bias: config.attention_bias,
+            ..Default::default()
+        };
+        let query_key_value = nn::linear(
+            p / "query_key_value",
+            config.hidden_size,
+            (num_heads + 2 * num_key_value_heads) * head_dim,
+            linear_config,
+        );
+        let dense = nn::linear(
+            p / "dense",
+            num_heads * head_dim,
+            config.hidden_size,
+            linear_config,
+        );
+
+        let attention_dropout = Dropout::new(config.attention_dropout);
+
+        GptNeoXAttention {
+            query_key_value,
+            dense,
+            attention_dropout,
+            rotary_embedding,
+            num_heads,
+            num_key_value_heads,
+            head_dim,
+            output_attentions: config.output_attentions,
+        }
+    }
+
+    /// Repeats the key and value heads to match the number of query heads
+    fn repeat_key_value_heads(&self, input_tensor: &Tensor) -> Tensor {
+        let num_repeats = self.num_heads / self.num_key_value_heads;
+        if num_repeats == 1 {
+            return input_tensor.shallow_clone();
+        }
+        let (batch_size, _, sequence_length, _) = input_tensor.size4().unwrap();
+        input_tensor
+            .unsqueeze(2)
+            .expand(
+                &[
+                    batch_size,
+                    self.num_key_value_heads,
+                    num_repeats,
+                    sequence_length,
+                    self.head_dim,
+                ],
+                true,
+            )
+            .reshape(&[batch_size, self.num_heads, sequence_length, self.head_dim])
+    }
+
+    pub fn forward_t(
+        &self,
+        hidden_states: &Tensor,
+        position_ids: &Tensor,
+        attention_mask: Option<&Tensor>,
+        layer_state: Option<&LayerState>,
+        train: bool,
+    ) -> (Tensor, Option<Tensor>, Option<LayerState>) {
+        let (batch_size, sequence_length, _) = hidden_states.size3().unwrap();
+
+        let group_size = self.num_heads / self.num_key_value_heads + 2;
+        let fused_qkv = hidden_states.apply(&self.query_key_value).view([
+            batch_size,
+            sequence_length,
+            self.num_key_value_heads,
+            group_size,
+            self.head_dim,
+        ]);
+        let query = fused_qkv
+            .slice(3, 0, group_size - 2, 1)
+            .reshape(&[batch_size, sequence_length, self.num_heads, self.head_dim])
+            .transpose(1, 2);
+        let key = fused_qkv.select(3, group_size - 2).transpose(1, 2);
+        let value = fused_qkv.select(3, group_size - 1).transpose(1, 2);
+
+        let query = self.rotary_embedding.apply(&query, position_ids);
+        let key = self.rotary_embedding.apply(&key, position_ids);
+
+        let (key, value) = match layer_state {
+            Some(layer_state_value) => (
+                Tensor::cat(&[&layer_state_value.prev_key, &key], -2),
+                Tensor::cat(&[&layer_state_value.prev_value, &value], -2),
+            ),
+            None => (key, value),
+        };
+        let layer_state = Some(LayerState {
+            prev_key: key.copy(),
+            prev_value: value.copy(),
+        });
+
+        let key = self.repeat_key_value_heads(&key);
+        let value = self.repeat_key_value_heads(&value);
+
+        let mut attention_weights =
+            query.matmul(&key.transpose(-1, -2)) / (self.head_dim as f64).sqrt();
+        if let Some(attention_mask) = attention_mask {
+            attention_weights = attention_weights + attention_mask;
+        }
+        let attention_weights = attention_weights
+            .softmax(-1, Kind::Float)
+            .to_kind(value.kind())
+            .apply_t(&self.attention_dropout, train);
+
+        let attention_output = attention_weights
+            .matmul(&value)
+            .transpose(1, 2)
+            .contiguous()
+            .view([batch_size, sequence_length, self.num_heads * self.head_dim])
+            .apply(&self.dense);
+
+        let attention_weights = if self.output_attentions {
+            Some(attention_weights)
+        } else {
+            None
+        };
+
+        (attention_output, attention_weights, layer_state)
+    }
+}
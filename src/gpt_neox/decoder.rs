@@ -0,0 +1,227 @@
+// Copyright 2022 EleutherAI and the HuggingFace Inc. team. All rights reserved.
+// Copyright 2022 Guillaume Becquin
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//     http://www.apache.org/licenses/LICENSE-2.0
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use crate::common::activations::TensorFunction;
+use crate::common::dropout::Dropout;
+use crate::common::rotary::{RotaryConfig, RotaryEmbedding, RotaryStyle};
+use crate::gpt_neox::attention::{GptNeoXAttention, LayerState};
+use crate::Activation;
+use std::borrow::Borrow;
+use tch::nn::Module;
+use tch::{nn, Device, Kind, Tensor};
+
+/// Architecture of a decoder layer shared by the GPT-NeoX and Falcon models, built from their respective
+/// configurations. The parameter names differ between the two families and are part of the configuration.
+#[derive(Debug, Clone)]
+pub struct ParallelBlockConfig {
+    pub hidden_size: i64,
+    pub intermediate_size: i64,
+    pub num_attention_heads: i64,
+    pub num_key_value_heads: i64,
+    /// Number of rotated dimensions of each attention head
+    pub rotary_dim: i64,
+    pub rotary_base: f64,
+    pub max_position_embeddings: i64,
+    pub layer_norm_eps: f64,
+    pub attention_bias: bool,
+    pub mlp_bias: bool,
+    pub activation: Activation,
+    pub attention_dropout: f64,
+    pub hidden_dropout: f64,
+    /// Attention and feed-forward layers computed in parallel from the layer input (instead of the feed-forward
+    /// layer taking the output of the attention layer)
+    pub parallel_residual: bool,
+    pub attention_name: &'static str,
+    pub attention_layer_norm_name: &'static str,
+    /// Layer norm of the feed-forward input. If `None` (parallel layers only), the attention layer norm is shared
+    pub mlp_layer_norm_name: Option<&'static str>,
+    pub output_attentions: bool,
+}
+
+impl ParallelBlockConfig {
+    /// Creates the rotary position embeddings shared by the layers of a model
+    pub fn rotary_embedding(&self) -> RotaryEmbedding {
+        RotaryEmbedding::new(RotaryConfig {
+            base: self.rotary_base,
+            ..RotaryConfig::new(
+                self.rotary_dim,
+                self.max_position_embeddings,
+                RotaryStyle::RotateHalf,
+            )
+        })
+    }
+}
+
+/// # GPT-NeoX feed-forward layer
+pub struct GptNeoXMLP {
+    dense_h_to_4h: nn::Linear,
+    dense_4h_to_h: nn::Linear,
+    activation_function: TensorFunction,
+}
+
+impl GptNeoXMLP {
+    pub fn new<'p, P>(p: P, config: &ParallelBlockConfig) -> GptNeoXMLP
+    where
+        P: Borrow<nn::Path<'p>>,
+    {
+        let p = p.borrow();
+
+        let linear_config = nn::LinearConfig {
+            bias: config.mlp_bias,
+            ..Default::default()
+        };
+        let dense_h_to_4h = nn::linear(
+            p / "dense_h_to_4h",
+            config.hidden_size,
+            config.intermediate_size,
+            linear_config,
+        );
+        let dense_4h_to_h = nn::linear(
+            p / "dense_4h_to_h",
+            config.intermediate_size,
+            config.hidden_size,
+            linear_config,
+        );
+
+        let activation_function = config.activation.get_function();
+
+        GptNeoXMLP {
+            dense_h_to_4h,
+            dense_4h_to_h,
+            activation_function,
+        }
+    }
+}
+
+impl Module for GptNeoXMLP {
+    fn forward(&self, hidden_states: &Tensor) -> Tensor {
+        self.activation_function.get_fn()(&hidden_states.apply(&self.dense_h_to_4h))
+            .apply(&self.dense_4h_to_h)
+    }
+}
+
+/// # GPT-NeoX / Falcon decoder layer
+/// Pre-normalization decoder layer. With a parallel residual, the attention and feed-forward layers both take the
+/// layer input and their outputs are added to it: `x + attention(ln_1(x)) + mlp(ln_2(x))`. Otherwise the layers
+/// are applied sequentially as in GPT-2.
+pub struct GptNeoXLayer {
+    attention: GptNeoXAttention,
+    mlp: GptNeoXMLP,
+    attention_layer_norm: nn::LayerNorm,
+    mlp_layer_norm: Option<nn::LayerNorm>,
+    dropout: Dropout,
+    parallel_residual: bool,
+}
+
+impl GptNeoXLayer {
+    pub fn new<'p, P>(
+        p: P,
+        config: &ParallelBlockConfig,
+        rotary_embedding: &RotaryEmbedding,
+    ) -> GptNeoXLayer
+    where
+        P: Borrow<nn::Path<'p>>,
+    {
+        let p = p.borrow();
+
+        let layer_norm_config = nn::LayerNormConfig {
+            eps: config.layer_norm_eps,
+            ..Default::default()
+        };
+
+        let attention =
+            GptNeoXAttention::new(p / config.attention_name, config, rotary_embedding.clone());
+        let mlp = GptNeoXMLP::new(p / "mlp", config);
+        let attention_layer_norm = nn::layer_norm(
+            p / config.attention_layer_norm_name,
+            vec![config.hidden_size],
+            layer_norm_config,
+        );
+        let mlp_layer_norm = config
+            .mlp_layer_norm_name
+            .map(|name| nn::layer_norm(p / name, vec![config.hidden_size], layer_norm_config));
+        let dropout = Dropout::new(config.hidden_dropout);
+
+        GptNeoXLayer {
+            attention,
+            mlp,
+            attention_layer_norm,
+            mlp_layer_norm,
+            dropout,
+            parallel_residual: config.parallel_residual,
+        }
+    }
+
+    pub fn forward_t(
+        &self,
+        hidden_states: &Tensor,
+        position_ids: &Tensor,
+        attention_mask: Option<&Tensor>,
+        layer_state: Option<&LayerState>,
+        train: bool,
+    ) -> (Tensor, Option<Tensor>, Option<LayerState>) {
+        let attention_input = hidden_states.apply(&self.attention_layer_norm);
+        let (attention_output, attention_weights, layer_state) = self.attention.forward_t(
+            &attention_input,
+            position_ids,
+            attention_mask,
+            layer_state,
+            train,
+        );
+        let attention_output = attention_output.apply_t(&self.dropout, train);
+
+        let output = if self.parallel_residual {
+            let mlp_input = match &self.mlp_layer_norm {
+                Some(mlp_layer_norm) => hidden_states.apply(mlp_layer_norm),
+                None => attention_input,
+            };
+            let mlp_output = mlp_input.apply(&self.mlp).apply_t(&self.dropout, train);
+            hidden_states + attention_output + mlp_output
+        } else {
+            let hidden_states = hidden_states + attention_output;
+            let mlp_input = match &self.mlp_layer_norm {
+                Some(mlp_layer_norm) => hidden_states.apply(mlp_layer_norm),
+                None => hidden_states.apply(&self.attention_layer_norm),
+            };
+            let mlp_output = mlp_input.apply(&self.mlp).apply_t(&self.dropout, train);
+            hidden_states + mlp_output
+        };
+
+        (output, attention_weights, layer_state)
+    }
+}
+
+/// Builds the additive attention mask of shape (*batch size* or 1, 1, *sequence_length*,
+/// *past_length + sequence_length*), combining the causal mask and the optional padding mask of shape
+/// (*batch size*, *past_length + sequence_length*)
+pub(crate) fn causal_attention_mask(
+    batch_size: i64,
+    sequence_length: i64,
+    past_length: i64,
+    attention_mask: Option<&Tensor>,
+    device: Device,
+) -> Tensor {
+    let full_sequence_length = past_length + sequence_length;
+    let query_positions =
+        Tensor::arange_start(past_length, full_sequence_length, (Kind::Int64, device)).unsqueeze(1);
+    let key_positions = Tensor::arange(full_sequence_length, (Kind::Int64, device)).unsqueeze(0);
+    let mut mask = key_positions.le_tensor(&query_positions).view([
+        1,
+        1,
+        sequence_length,
+        full_sequence_length,
+    ]);
+    if let Some(attention_mask) = attention_mask {
+        mask = mask.logical_and(&attention_mask.ne(0).view([batch_size, 1, 1, -1]));
+    }
+    Tensor::zeros(&mask.size(), (Kind::Float, device)).masked_fill(&mask.logical_not(), -1e9)
+}
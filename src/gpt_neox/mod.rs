@@ -0,0 +1,74 @@
+//! # GPT-NeoX
+//!
+//! Implementation of the GPT-NeoX language model ([GPT-NeoX-20B: An Open-Source Autoregressive Language Model](https://arxiv.org/abs/2204.06745) Black, Biderman, Hallahan, Anthony, Gao, Golding, He, Leahy, McDonell, Phang, Pieler, Prashanth, Purohit, Reynolds, Tow, Wang, Weinbach, 2022),
+//! also used by the Pythia checkpoints ([Pythia: A Suite for Analyzing Large Language Models Across Training and Scaling](https://arxiv.org/abs/2304.01373) Biderman et al., 2023).
+//! The base model is implemented in the `gpt_neox_model::GptNeoXModel` struct. A causal language modeling head is implemented in `gpt_neox_model::GptNeoXForCausalLM`.
+//! Compared to GPT-2, the decoder layers use rotary position embeddings on a fraction of each attention head (`rotary_pct`) and compute the attention and
+//! feed-forward layers in parallel from the layer input (`use_parallel_residual`).
+//! The decoder layer is shared with the Falcon models (`crate::falcon`).
+//!
+//! # Model set-up and pre-trained weights loading
+//!
+//! The model is available for text generation with `ModelType::GPTNeoX`. All models expect the following resources:
+//! - Configuration file expected to have a structure following the [Transformers library](https://github.com/huggingface/transformers)
+//! - Model weights are expected to have a structure and parameter names following the [Transformers library](https://github.com/huggingface/transformers). A conversion using the Python utility scripts is required to convert the `.bin` weights to the `.ot` format (the shards of a checkpoint can be passed together: `python utils/convert_model.py path/to/pytorch_model-*.bin`).
+//! - `Gpt2Tokenizer` using a `vocab.json` vocabulary and a `merges.txt` merges file. The checkpoints only provide a `tokenizer.json` file: the vocabulary and merges files are saved with the Transformers tokenizer (`tokenizer.save_vocabulary(path)`)
+//!
+//! No pre-trained checkpoint is hosted with the crate, the resources are provided as local files:
+//!
+//! ```no_run
+//! use rust_bert::pipelines::common::ModelType;
+//! use rust_bert::pipelines::text_generation::{TextGenerationConfig, TextGenerationModel};
+//! use rust_bert::resources::LocalResource;
+//! use std::path::PathBuf;
+//! use tch::Device;
+//!
+//! fn main() -> anyhow::Result<()> {
+//!     let config_resource = Box::new(LocalResource {
+//!         local_path: PathBuf::from("path/to/config.json"),
+//!     });
+//!     let vocab_resource = Box::new(LocalResource {
+//!         local_path: PathBuf::from("path/to/vocab.json"),
+//!     });
+//!     let merges_resource = Box::new(LocalResource {
+//!         local_path: PathBuf::from("path/to/merges.txt"),
+//!     });
+//!     let model_resource = Box::new(LocalResource {
+//!         local_path: PathBuf::from("path/to/rust_model.ot"),
+//!     });
+//!
+//!     let text_generation_config = TextGenerationConfig {
+//!         model_type: ModelType::GPTNeoX,
+//!         model_resource,
+//!         config_resource,
+//!         vocab_resource,
+//!         merges_resource,
+//!         do_sample: false,
+//!         max_length: 64,
+//!         device: Device::cuda_if_available(),
+//!         ..Default::default()
+//!     };
+//!     let model = TextGenerationModel::new(text_generation_config)?;
+//!
+//!     let input_context = "The capital of France is";
+//!     let output = model.generate(&[input_context], None);
+//!
+//!     for sentence in output {
+//!         println!("{}", sentence);
+//!     }
+//!
+//!     Ok(())
+//! }
+//! ```
+
+mod attention;
+pub(crate) mod decoder;
+mod gpt_neox_model;
+
+pub use gpt_neox_model::{
+    GptNeoXConfig, GptNeoXForCausalLM, GptNeoXGenerator, GptNeoXModel, GptNeoXModelLMOutput,
+    GptNeoXModelOutput,
+};
+
+pub use attention::LayerState;
+pub(crate) use gpt_neox_model::{prepare_inputs_for_generation, reorder_cache};
@@ -25,6 +25,10 @@
 //!     score: 0.9976,
 //!     start: 13,
 //!     end: 21,
+//!     byte_start: 13,
+//!     byte_end: 21,
+//!     utf16_start: 13,
+//!     utf16_end: 21,
 //!     answer: String::from("Amsterdam"),
 //! }]
 //! # ;
@@ -60,6 +64,10 @@
 //!GPT| | | |✅ | | | |  |
 //!GPT2| | | |✅ | | | |  |
 //!GPT-Neo| | | |✅ | | | | |
+//!GPT-BigCode (StarCoder)| | | |✅ | | | | |
+//!GPT-NeoX / Falcon| | | |✅ | | | | |
+//!BLOOM| | | |✅ | | | | |
+//!LLaMA / Mistral| | | |✅ | | | | |
 //!BART|✅| | |✅ |✅| | | |
 //!Marian| | | |  | |✅| |  |
 //!MBart|✅| | |✅ | | | |  |
@@ -141,6 +149,10 @@
 //!     score: 0.9976,
 //!     start: 13,
 //!     end: 21,
+//!     byte_start: 13,
+//!     byte_end: 21,
+//!     utf16_start: 13,
+//!     utf16_end: 21,
 //!     answer: String::from("Amsterdam"),
 //! }]
 //! # ;
@@ -476,12 +488,16 @@
 //!             score: 0.9986,
 //!             label: String::from("I-PER"),
 //!             offset: Offset { begin: 11, end: 14 },
+//!             byte_offset: Offset { begin: 11, end: 14 },
+//!             utf16_offset: Offset { begin: 11, end: 14 },
 //!         },
 //!         Entity {
 //!             word: String::from("Paris"),
 //!             score: 0.9985,
 //!             label: String::from("I-LOC"),
 //!             offset: Offset { begin: 26, end: 31 },
+//!             byte_offset: Offset { begin: 26, end: 31 },
+//!             utf16_offset: Offset { begin: 26, end: 31 },
 //!         },
 //!     ],
 //!     [
@@ -490,12 +506,16 @@
 //!             score: 0.9988,
 //!             label: String::from("I-LOC"),
 //!             offset: Offset { begin: 0, end: 5 },
+//!             byte_offset: Offset { begin: 0, end: 5 },
+//!             utf16_offset: Offset { begin: 0, end: 5 },
 //!         },
 //!         Entity {
 //!             word: String::from("France"),
 //!             score: 0.9993,
 //!             label: String::from("I-LOC"),
 //!             offset: Offset { begin: 19, end: 25 },
+//!             byte_offset: Offset { begin: 19, end: 25 },
+//!             utf16_offset: Offset { begin: 19, end: 25 },
 //!         },
 //!     ],
 //! ]
@@ -628,14 +648,24 @@
 pub mod albert;
 pub mod bart;
 pub mod bert;
+pub mod bigbird;
+#[cfg(feature = "uniffi")]
+pub mod bindings;
+pub mod bloom;
+pub mod canine;
+pub mod clip;
 mod common;
 pub mod deberta;
 pub mod deberta_v2;
 pub mod distilbert;
 pub mod electra;
+pub mod falcon;
 pub mod fnet;
 pub mod gpt2;
+pub mod gpt_bigcode;
 pub mod gpt_neo;
+pub mod gpt_neox;
+pub mod llama;
 pub mod longformer;
 pub mod m2m_100;
 pub mod marian;
@@ -648,9 +678,24 @@ pub mod prophetnet;
 pub mod reformer;
 pub mod roberta;
 pub mod t5;
+pub mod training;
+pub mod vision_encoder_decoder;
+pub mod vit;
+pub mod wav2vec2;
+pub mod whisper;
 pub mod xlnet;
 pub mod memnet;
 
+pub use common::attention_mask;
+pub use common::early_exit;
 pub use common::error::RustBertError;
+pub use common::export;
+pub use common::normalization;
+pub use common::offsets;
+pub use common::pruning;
 pub use common::resources;
+pub use common::rotary;
 pub use common::{Activation, Config};
+
+#[cfg(feature = "uniffi")]
+uniffi::setup_scaffolding!();
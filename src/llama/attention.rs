@@ -0,0 +1,200 @@
+// Copyright 2022 EleutherAI and the HuggingFace Inc. team. All rights reserved.
+// Copyright 2022 Guillaume Becquin
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//     http://www.apache.org/licenses/LICENSE-2.0
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use crate::common::rotary::RotaryEmbedding;
+use crate::llama::LlamaConfig;
+use std::borrow::Borrow;
+use tch::{nn, Kind, Tensor};
+
+#[derive(Debug)]
+/// # Cache for LLaMA attention layers
+/// Stores the cached value of key and value, after the rotary position embeddings and before their repetition for
+/// the grouped-query attention (of shape (*batch size*, *num_key_value_heads*, *sequence_length*, *head dimension*))
+pub struct LayerState {
+    /// Cached keys
+    pub prev_key: Tensor,
+    /// Cached values
+    pub prev_value: Tensor,
+}
+
+impl Clone for LayerState {
+    fn clone(&self) -> Self {
+        LayerState {
+            prev_key: self.prev_key.copy(),
+            prev_value: self.prev_value.copy(),
+        }
+    }
+}
+
+impl LayerState {
+    pub(crate) fn reorder_cache(&mut self, new_indices: &Tensor) {
+        self.prev_key = self.prev_key.index_select(0, new_indices);
+        self.prev_value = self.prev_value.index_select(0, new_indices);
+    }
+}
+
+pub struct LlamaAttention {
+    q_proj: nn::Linear,
+    k_proj: nn::Linear,
+    v_proj: nn::Linear,
+    o_proj: nn::Linear,
+    rotary_embedding: RotaryEmbedding,
+    num_heads: i64,
+    num_key_value_heads: i64,
+    head_dim: i64,
+    output_attentions: bool,
+}
+
+impl LlamaAttention {
+    pub fn new<'p, P>(
+        p: P,
+        config: &LlamaConfig,
+        rotary_embedding: RotaryEmbedding,
+    ) -> LlamaAttention
+    where
+        P: Borrow<nn::Path<'p>>,
+    {
+        let p = p.borrow();
+
+        let num_heads = config.num_attention_heads;
+        let num_key_value_heads = config.num_key_value_heads.unwrap_or(num_heads);
+        let head_dim = config.hidden_size / num_heads;
+
+        let linear_config = nn::LinearConfig {
+            bias: false,
+            ..Default::default()
+        };
+        let q_proj = nn::linear(
+            p / "q_proj",
+            config.hidden_size,
+            num_heads * head_dim,
+            linear_config,
+        );
+        let k_proj = nn::linear(
+            p / "k_proj",
+            config.hidden_size,
+            num_key_value_heads * head_dim,
+            linear_config,
+        );
+        let v_proj = nn::linear(
+            p / "v_proj",
+            config.hidden_size,
+            num_key_value_heads * head_dim,
+            linear_config,
+        );
+        let o_proj = nn::linear(
+            p / "o_proj",
+            num_heads * head_dim,
+            config.hidden_size,
+            linear_config,
+        );
+
+        let output_attentions = config.output_attentions.unwrap_or(false);
+
+        LlamaAttention {
+            q_proj,
+            k_proj,
+            v_proj,
+            o_proj,
+            rotary_embedding,
+            num_heads,
+            num_key_value_heads,
+            head_dim,
+            output_attentions,
+        }
+    }
+
+    fn split_heads(&self, input_tensor: &Tensor, num_heads: i64) -> Tensor {
+        let (batch_size, sequence_length, _) = input_tensor.size3().unwrap();
+        input_tensor
+            .view([batch_size, sequence_length, num_heads, self.head_dim])
+            .transpose(1, 2)
+    }
+
+    /// Repeats the key and value heads to match the number of query heads (grouped-query attention)
+    fn repeat_key_value_heads(&self, input_tensor: &Tensor) -> Tensor {
+        let num_repeats = self.num_heads / self.num_key_value_heads;
+        if num_repeats == 1 {
+            return input_tensor.shallow_clone();
+        }
+        let (batch_size, _, sequence_length, _) = input_tensor.size4().unwrap();
+        input_tensor
+            .unsqueeze(2)
+            .expand(
+                &[
+                    batch_size,
+                    self.num_key_value_heads,
+                    num_repeats,
+                    sequence_length,
+                    self.head_dim,
+                ],
+                true,
+            )
+            .reshape(&[batch_size, self.num_heads, sequence_length, self.head_dim])
+    }
+
+    pub fn forward_t(
+        &self,
+        hidden_states: &Tensor,
+        position_ids: &Tensor,
+        attention_mask: Option<&Tensor>,
+        layer_state: Option<&LayerState>,
+    ) -> (Tensor, Option<Tensor>, Option<LayerState>) {
+        let (batch_size, sequence_length, _) = hidden_states.size3().unwrap();
+
+        let query = self.split_heads(&hidden_states.apply(&self.q_proj), self.num_heads);
+        let key = self.split_heads(&hidden_states.apply(&self.k_proj), self.num_key_value_heads);
+        let value = self.split_heads(&hidden_states.apply(&self.v_proj), self.num_key_value_heads);
+
+        let query = self.rotary_embedding.apply(&query, position_ids);
+        let key = self.rotary_embedding.apply(&key, position_ids);
+
+        let (key, value) = match layer_state {
+            Some(layer_state_value) => (
+                Tensor::cat(&[&layer_state_value.prev_key, &key], -2),
+                Tensor::cat(&[&layer_state_value.prev_value, &value], -2),
+            ),
+            None => (key, value),
+        };
+        let layer_state = Some(LayerState {
+            prev_key: key.copy(),
+            prev_value: value.copy(),
+        });
+
+        let key = self.repeat_key_value_heads(&key);
+        let value = self.repeat_key_value_heads(&value);
+
+        let mut attention_weights =
+            query.matmul(&key.transpose(-1, -2)) / (self.head_dim as f64).sqrt();
+        if let Some(attention_mask) = attention_mask {
+            attention_weights = attention_weights + attention_mask;
+        }
+        let attention_weights = attention_weights
+            .softmax(-1, Kind::Float)
+            .to_kind(value.kind());
+
+        let attention_output = attention_weights
+            .matmul(&value)
+            .transpose(1, 2)
+            .contiguous()
+            .view([batch_size, sequence_length, self.num_heads * self.head_dim])
+            .apply(&self.o_proj);
+
+        let attention_weights = if self.output_attentions {
+            Some(attention_weights)
+        } else {
+            None
+        };
+
+        (attention_output, attention_weights, layer_state)
+    }
+}
@@ -0,0 +1,143 @@
+// Copyright 2022 EleutherAI and the HuggingFace Inc. team. All rights reserved.
+// Copyright 2022 Guillaume Becquin
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//     http://www.apache.org/licenses/LICENSE-2.0
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use crate::common::activations::TensorFunction;
+use crate::common::normalization::{NormConfig, RMSNorm};
+use crate::common::rotary::RotaryEmbedding;
+use crate::llama::attention::{LayerState, LlamaAttention};
+use crate::llama::LlamaConfig;
+use std::borrow::Borrow;
+use tch::nn::Module;
+use tch::{nn, Tensor};
+
+/// # LLaMA feed-forward layer
+/// Gated feed-forward layer (SwiGLU with the default `silu` activation): the activation of the gate projection is
+/// multiplied by the up projection before the down projection.
+#[derive(Debug)]
+pub struct LlamaMLP {
+    gate_proj: nn::Linear,
+    up_proj: nn::Linear,
+    down_proj: nn::Linear,
+    activation_function: TensorFunction,
+}
+
+impl LlamaMLP {
+    pub fn new<'p, P>(p: P, config: &LlamaConfig) -> LlamaMLP
+    where
+        P: Borrow<nn::Path<'p>>,
+    {
+        let p = p.borrow();
+
+        let linear_config = nn::LinearConfig {
+            bias: false,
+            ..Default::default()
+        };
+        let gate_proj = nn::linear(
+            p / "gate_proj",
+            config.hidden_size,
+            config.intermediate_size,
+            linear_config,
+        );
+        let up_proj = nn::linear(
+            p / "up_proj",
+            config.hidden_size,
+            config.intermediate_size,
+            linear_config,
+        );
+        let down_proj = nn::linear(
+            p / "down_proj",
+            config.intermediate_size,
+            config.hidden_size,
+            linear_config,
+        );
+
+        let activation_function = config.hidden_act.get_function();
+
+        LlamaMLP {
+            gate_proj,
+            up_proj,
+            down_proj,
+            activation_function,
+        }
+    }
+}
+
+impl Module for LlamaMLP {
+    fn forward(&self, hidden_states: &Tensor) -> Tensor {
+        let gate = self.activation_function.get_fn()(&hidden_states.apply(&self.gate_proj));
+        (gate * hidden_states.apply(&self.up_proj)).apply(&self.down_proj)
+    }
+}
+
+pub struct LlamaDecoderLayer {
+    self_attn: LlamaAttention,
+    mlp: LlamaMLP,
+    input_layernorm: RMSNorm,
+    post_attention_layernorm: RMSNorm,
+}
+
+impl LlamaDecoderLayer {
+    pub fn new<'p, P>(
+        p: P,
+        config: &LlamaConfig,
+        rotary_embedding: &RotaryEmbedding,
+    ) -> LlamaDecoderLayer
+    where
+        P: Borrow<nn::Path<'p>>,
+    {
+        let p = p.borrow();
+
+        let norm_config = NormConfig {
+            eps: config.rms_norm_eps,
+            ..Default::default()
+        };
+
+        let self_attn = LlamaAttention::new(p / "self_attn", config, rotary_embedding.clone());
+        let mlp = LlamaMLP::new(p / "mlp", config);
+        let input_layernorm = RMSNorm::new(p / "input_layernorm", config.hidden_size, norm_config);
+        let post_attention_layernorm = RMSNorm::new(
+            p / "post_attention_layernorm",
+            config.hidden_size,
+            norm_config,
+        );
+
+        LlamaDecoderLayer {
+            self_attn,
+            mlp,
+            input_layernorm,
+            post_attention_layernorm,
+        }
+    }
+
+    pub fn forward_t(
+        &self,
+        hidden_states: &Tensor,
+        position_ids: &Tensor,
+        attention_mask: Option<&Tensor>,
+        layer_state: Option<&LayerState>,
+    ) -> (Tensor, Option<Tensor>, Option<LayerState>) {
+        let (intermediate, attention_weights, layer_state) = self.self_attn.forward_t(
+            &hidden_states.apply(&self.input_layernorm),
+            position_ids,
+            attention_mask,
+            layer_state,
+        );
+        let hidden_states = hidden_states + intermediate;
+
+        let intermediate = hidden_states
+            .apply(&self.post_attention_layernorm)
+            .apply(&self.mlp);
+        let output = hidden_states + intermediate;
+
+        (output, attention_weights, layer_state)
+    }
+}
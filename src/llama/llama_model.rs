@@ -0,0 +1,776 @@
+// Copyright 2022 EleutherAI and the HuggingFace Inc. team. All rights reserved.
+// Copyright 2022 Guillaume Becquin
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//     http://www.apache.org/licenses/LICENSE-2.0
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use crate::common::embeddings::process_ids_embeddings_pair;
+use crate::common::normalization::{NormConfig, RMSNorm};
+use crate::common::rotary::{RotaryConfig, RotaryEmbedding, RotaryStyle};
+use crate::llama::decoder::LlamaDecoderLayer;
+use crate::llama::LayerState;
+use crate::pipelines::common::{ModelType, TokenizerOption};
+use crate::pipelines::generation_utils::private_generation_utils::{
+    PreparedInput, PrivateLanguageGenerator,
+};
+use crate::pipelines::generation_utils::{
+    Cache, GenerateConfig, LMHeadModel, LMModelOutput, LanguageGenerator,
+};
+use crate::{Activation, Config, RustBertError};
+use rust_tokenizers::tokenizer::SentencePieceBpeTokenizer;
+use rust_tokenizers::vocab::SentencePieceVocab;
+use serde::{Deserialize, Serialize};
+use std::borrow::{Borrow, BorrowMut};
+use tch::{nn, Kind, Tensor};
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+/// # LLaMA model configuration
+/// Defines the LLaMA model architecture (e.g. number of layers, hidden layer size, vocab size...).
+/// Mistral configurations are supported, setting a `sliding_window` for the attention layers.
+pub struct LlamaConfig {
+    pub vocab_size: i64,
+    pub hidden_size: i64,
+    pub intermediate_size: i64,
+    pub num_hidden_layers: i64,
+    pub num_attention_heads: i64,
+    /// Number of key and value heads for the grouped-query attention (defaults to `num_attention_heads`,
+    /// multi-head attention). Each key and value head is shared by `num_attention_heads / num_key_value_heads`
+    /// query heads.
+    pub num_key_value_heads: Option<i64>,
+    pub hidden_act: Activation,
+    pub max_position_embeddings: i64,
+    pub rms_norm_eps: f64,
+    /// Base of the rotary position embeddings frequencies (defaults to 10,000)
+    pub rope_theta: Option<f64>,
+    /// Maximum number of previous tokens attended to by each token (Mistral), unlimited if `None`
+    pub sliding_window: Option<i64>,
+    pub tie_word_embeddings: Option<bool>,
+    pub bos_token_id: i64,
+    pub eos_token_id: i64,
+    pub pad_token_id: Option<i64>,
+    pub output_attentions: Option<bool>,
+    pub output_hidden_states: Option<bool>,
+}
+
+impl Config for LlamaConfig {}
+
+impl Default for LlamaConfig {
+    fn default() -> Self {
+        LlamaConfig {
+            vocab_size: 32000,
+            hidden_size: 4096,
+            intermediate_size: 11008,
+            num_hidden_layers: 32,
+            num_attention_heads: 32,
+            num_key_value_heads: None,
+            hidden_act: Activation::swish,
+            max_position_embeddings: 2048,
+            rms_norm_eps: 1e-6,
+            rope_theta: None,
+            sliding_window: None,
+            tie_word_embeddings: None,
+            bos_token_id: 1,
+            eos_token_id: 2,
+            pad_token_id: None,
+            output_attentions: None,
+            output_hidden_states: None,
+        }
+    }
+}
+
+/// # LLaMA Base model
+/// Base architecture for LLaMA and Mistral models. Task-specific models will be built from this common base model
+/// It is made of the following blocks:
+/// - `embed_tokens`: Word embeddings
+/// - `layers`: Vector of `LlamaDecoderLayer` (pre-normalization transformer layers with rotary position embeddings,
+/// grouped-query attention and SwiGLU feed-forward layers)
+/// - `norm`: Final RMS normalization
+pub struct LlamaModel {
+    embed_tokens: nn::Embedding,
+    layers: Vec<LlamaDecoderLayer>,
+    norm: RMSNorm,
+    sliding_window: Option<i64>,
+    output_attentions: bool,
+    output_hidden_states: bool,
+}
+
+impl LlamaModel {
+    /// Build a new `LlamaModel`
+    ///
+    /// # Arguments
+    ///
+    /// * `p` - Variable store path for the root of the LLaMA model
+    /// * `config` - `LlamaConfig` object defining the model architecture
+    ///
+    /// # Example
+    ///
+    /// ```no_run
+    /// use rust_bert::llama::{LlamaConfig, LlamaModel};
+    /// use rust_bert::Config;
+    /// use std::path::Path;
+    /// use tch::{nn, Device};
+    ///
+    /// let config_path = Path::new("path/to/config.json");
+    /// let device = Device::Cpu;
+    /// let p = nn::VarStore::new(device);
+    /// let config = LlamaConfig::from_file(config_path);
+    /// let llama_model = LlamaModel::new(&p.root() / "model", &config).unwrap();
+    /// ```
+    pub fn new<'p, P>(p: P, config: &LlamaConfig) -> Result<LlamaModel, RustBertError>
+    where
+        P: Borrow<nn::Path<'p>>,
+    {
+        let p = p.borrow();
+
+        if config.hidden_size % config.num_attention_heads != 0 {
+            return Err(RustBertError::InvalidConfigurationError(format!(
+                "The hidden size ({}) must be a multiple of the number of attention heads ({})",
+                config.hidden_size, config.num_attention_heads
+            )));
+        }
+        let num_key_value_heads = config
+            .num_key_value_heads
+            .unwrap_or(config.num_attention_heads);
+        if config.num_attention_heads % num_key_value_heads != 0 {
+            return Err(RustBertError::InvalidConfigurationError(format!(
+                "The number of attention heads ({}) must be a multiple of the number of key and value heads ({})",
+                config.num_attention_heads, num_key_value_heads
+            )));
+        }
+
+        let embed_tokens = nn::embedding(
+            p / "embed_tokens",
+            config.vocab_size,
+            config.hidden_size,
+            Default::default(),
+        );
+
+        // The sine and cosine tables of the rotary embeddings are shared by the layers
+        let rotary_embedding = RotaryEmbedding::new(RotaryConfig {
+            base: config.rope_theta.unwrap_or(10000.0),
+            ..RotaryConfig::new(
+                config.hidden_size / config.num_attention_heads,
+                config.max_position_embeddings,
+                RotaryStyle::RotateHalf,
+            )
+        });
+        let mut layers: Vec<LlamaDecoderLayer> =
+            Vec::with_capacity(config.num_hidden_layers as usize);
+        let p_layers = p / "layers";
+        for layer_index in 0..config.num_hidden_layers {
+            layers.push(LlamaDecoderLayer::new(
+                &p_layers / layer_index,
+                config,
+                &rotary_embedding,
+            ));
+        }
+
+        let norm = RMSNorm::new(
+            p / "norm",
+            config.hidden_size,
+            NormConfig {
+                eps: config.rms_norm_eps,
+                ..Default::default()
+            },
+        );
+
+        let output_attentions = config.output_attentions.unwrap_or(false);
+        let output_hidden_states = config.output_hidden_states.unwrap_or(false);
+
+        Ok(LlamaModel {
+            embed_tokens,
+            layers,
+            norm,
+            sliding_window: config.sliding_window,
+            output_attentions,
+            output_hidden_states,
+        })
+    }
+
+    /// Forward pass through the model
+    ///
+    /// # Arguments
+    ///
+    /// * `input_ids` - Optional input tensor of shape (*batch size*, *sequence_length*). This or `input_embeds` must be provided.
+    /// * `input_embeds` - Optional input tensor of shape (*batch size*, *sequence_length*, *embeddings dimension*). This or `input_ids` must be provided.
+    /// * `position_ids` - Optional position ids of shape (*batch size*, *sequence_length*). If None, will be incremented starting from the length of the past input.
+    /// * `layer_states` - Optional Vector `Option<Vec<Option<LayerState>>>` of length *n_layer* containing the past keys and values of each layer.
+    /// * `attention_mask` - Optional attention mask of shape (*batch size*, *past_length + sequence_length*) for the past and current positions. Positions with a mask with value 0 will be masked.
+    /// * `train` - boolean flag to turn on/off the dropout layers in the model. LLaMA does not use dropout, kept for consistency with the other models.
+    ///
+    /// # Returns
+    ///
+    /// * `Result<LlamaModelOutput, RustBertError>` containing:
+    ///   - `hidden_states` - `Tensor` of shape (*batch size*, *sequence_length*, *hidden_size*) representing the activations of the last hidden state
+    ///   - `next_cache` - `Option<Vec<Option<LayerState>>>` of length *n_layer* containing the past content for the the attention layers
+    ///   - `all_hidden_states` - `Option<Vec<Tensor>>` of length *n_layer + 1* with shape (*batch size*, *sequence_length*, *hidden_size*)
+    ///   - `all_attentions` - `Option<Vec<Tensor>>` of length *n_layer* containing the attention weights for each layer
+    ///
+    /// # Example
+    ///
+    /// ```no_run
+    /// # use tch::{nn, Device, Tensor, no_grad, Kind};
+    /// # use rust_bert::Config;
+    /// # use std::path::Path;
+    /// # use tch::kind::Kind::{Int64, Double};
+    /// use rust_bert::llama::{LlamaConfig, LlamaModel};
+    /// # let config_path = Path::new("path/to/config.json");
+    /// # let device = Device::Cpu;
+    /// # let vs = nn::VarStore::new(device);
+    /// # let config = LlamaConfig::from_file(config_path);
+    /// # let llama_model = LlamaModel::new(&vs.root() / "model", &config).unwrap();
+    /// let (batch_size, sequence_length) = (64, 128);
+    /// let input_tensor = Tensor::rand(&[batch_size, sequence_length], (Int64, device));
+    /// let attention_mask = Tensor::ones(&[batch_size, sequence_length], (Int64, device));
+    ///
+    /// let model_output = no_grad(|| {
+    ///     llama_model.forward_t(
+    ///         Some(&input_tensor),
+    ///         None,
+    ///         None,
+    ///         None,
+    ///         Some(&attention_mask),
+    ///         false,
+    ///     )
+    /// });
+    /// ```
+    pub fn forward_t(
+        &self,
+        input_ids: Option<&Tensor>,
+        input_embeds: Option<&Tensor>,
+        position_ids: Option<&Tensor>,
+        layer_states: Option<Vec<Option<LayerState>>>,
+        attention_mask: Option<&Tensor>,
+        _train: bool,
+    ) -> Result<LlamaModelOutput, RustBertError> {
+        let (calc_input_embeddings, input_shape, device) =
+            process_ids_embeddings_pair(input_ids, input_embeds, &self.embed_tokens)?;
+
+        let (batch_size, current_sequence_length) = (input_shape[0], input_shape[1]);
+
+        let past_length = match &layer_states {
+            Some(past_state_value) => match &past_state_value[0] {
+                Some(first_layer_state) => first_layer_state.prev_key.size()[2],
+                None => 0,
+            },
+            None => 0,
+        };
+        let full_sequence_length = current_sequence_length + past_length;
+
+        let calc_position_ids = if position_ids.is_none() {
+            Some(
+                Tensor::arange_start(past_length, full_sequence_length, (Kind::Int64, device))
+                    .unsqueeze(0),
+            )
+        } else {
+            None
+        };
+        let position_ids = position_ids.unwrap_or_else(|| calc_position_ids.as_ref().unwrap());
+
+        // Causal mask (restricted to the `sliding_window` previous tokens for Mistral), combined with the padding mask
+        let query_positions =
+            Tensor::arange_start(past_length, full_sequence_length, (Kind::Int64, device))
+                .unsqueeze(1);
+        let key_positions =
+            Tensor::arange(full_sequence_length, (Kind::Int64, device)).unsqueeze(0);
+        let mut mask = key_positions.le_tensor(&query_positions);
+        if let Some(sliding_window) = self.sliding_window {
+            mask = mask.logical_and(&key_positions.gt_tensor(&(&query_positions - sliding_window)));
+        }
+        let mut mask = mask.view([1, 1, current_sequence_length, full_sequence_length]);
+        if let Some(attention_mask) = attention_mask {
+            mask = mask.logical_and(&attention_mask.ne(0).view([batch_size, 1, 1, -1]));
+        }
+        let attention_mask = Tensor::zeros(&mask.size(), (Kind::Float, device))
+            .masked_fill(&mask.logical_not(), -1e9);
+
+        let mut hidden_state = input_embeds
+            .unwrap_or_else(|| calc_input_embeddings.as_ref().unwrap())
+            .shallow_clone();
+
+        let mut all_hidden_states: Option<Vec<Tensor>> = if self.output_hidden_states {
+            Some(vec![])
+        } else {
+            None
+        };
+        let mut all_attentions: Option<Vec<Tensor>> = if self.output_attentions {
+            Some(vec![])
+        } else {
+            None
+        };
+        let old_cache = layer_states.unwrap_or_else(|| vec![None; self.layers.len()]);
+        let mut next_cache = vec![None; self.layers.len()];
+
+        for ((layer_idx, layer), layer_state) in
+            self.layers.iter().enumerate().zip(old_cache.into_iter())
+        {
+            if let Some(hidden_states) = all_hidden_states.borrow_mut() {
+                hidden_states.push(hidden_state.copy());
+            };
+            let (output, attention_weights, layer_state) = layer.forward_t(
+                &hidden_state,
+                position_ids,
+                Some(&attention_mask),
+                layer_state.as_ref(),
+            );
+            hidden_state = output;
+            next_cache[layer_idx] = layer_state;
+            if let Some(attentions) = all_attentions.borrow_mut() {
+                attentions.push(attention_weights.unwrap());
+            };
+        }
+
+        let hidden_states = hidden_state.apply(&self.norm);
+        if let Some(all_hidden_states) = all_hidden_states.borrow_mut() {
+            all_hidden_states.push(hidden_states.copy());
+        };
+
+        Ok(LlamaModelOutput {
+            hidden_states,
+            next_cache: Some(next_cache),
+            all_hidden_states,
+            all_attentions,
+        })
+    }
+}
+
+/// # LLaMA Model for causal language modeling
+/// LLaMA model with a vocabulary decoding head (`lm_head`, tied to the word embeddings if `tie_word_embeddings` is set).
+/// Also used for the Mistral checkpoints.
+/// It is made of the following blocks:
+/// - `model`: `LlamaModel` Base LLaMA model
+/// - `lm_head`: Linear layer mapping the hidden states to the vocabulary logits
+pub struct LlamaForCausalLM {
+    model: LlamaModel,
+    lm_head: Option<nn::Linear>,
+}
+
+impl LlamaForCausalLM {
+    /// Build a new `LlamaForCausalLM`
+    ///
+    /// # Arguments
+    ///
+    /// * `p` - Variable store path for the root of the LLaMA model
+    /// * `config` - `LlamaConfig` object defining the model architecture
+    ///
+    /// # Example
+    ///
+    /// ```no_run
+    /// use rust_bert::llama::{LlamaConfig, LlamaForCausalLM};
+    /// use rust_bert::Config;
+    /// use std::path::Path;
+    /// use tch::{nn, Device};
+    ///
+    /// let config_path = Path::new("path/to/config.json");
+    /// let device = Device::Cpu;
+    /// let p = nn::VarStore::new(device);
+    /// let config = LlamaConfig::from_file(config_path);
+    /// let llama_model = LlamaForCausalLM::new(&p.root(), &config).unwrap();
+    /// ```
+    pub fn new<'p, P>(p: P, config: &LlamaConfig) -> Result<LlamaForCausalLM, RustBertError>
+    where
+        P: Borrow<nn::Path<'p>>,
+    {
+        let p = p.borrow();
+
+        let model = LlamaModel::new(p / "model", config)?;
+        let lm_head = if config.tie_word_embeddings.unwrap_or(false) {
+            None
+        } else {
+            Some(nn::linear(
+                p / "lm_head",
+                config.hidden_size,
+                config.vocab_size,
+                nn::LinearConfig {
+                    bias: false,
+                    ..Default::default()
+                },
+            ))
+        };
+
+        Ok(LlamaForCausalLM { model, lm_head })
+    }
+
+    /// Forward pass through the model
+    ///
+    /// # Arguments
+    ///
+    /// * `input_ids` - Optional input tensor of shape (*batch size*, *sequence_length*). This or `input_embeds` must be provided.
+    /// * `input_embeds` - Optional input tensor of shape (*batch size*, *sequence_length*, *embeddings dimension*). This or `input_ids` must be provided.
+    /// * `position_ids` - Optional position ids of shape (*batch size*, *sequence_length*). If None, will be incremented starting from the length of the past input.
+    /// * `layer_states` - Optional Vector `Option<Vec<Option<LayerState>>>` of length *n_layer* containing the past keys and values of each layer.
+    /// * `attention_mask` - Optional attention mask of shape (*batch size*, *past_length + sequence_length*) for the past and current positions. Positions with a mask with value 0 will be masked.
+    /// * `train` - boolean flag to turn on/off the dropout layers in the model. LLaMA does not use dropout, kept for consistency with the other models.
+    ///
+    /// # Returns
+    ///
+    /// * `Result<LlamaModelLMOutput, RustBertError>` containing:
+    ///   - `lm_logits` - `Tensor` of shape (*batch size*, *sequence_length*, *vocab_size*) representing the logits for each vocab item and position
+    ///   - `hidden_states` - `Tensor` of shape (*batch size*, *sequence_length*, *hidden_size*) representing the activations of the last hidden state
+    ///   - `next_cache` - `Option<Vec<Option<LayerState>>>` of length *n_layer* containing the past content for the the attention layers
+    ///   - `all_hidden_states` - `Option<Vec<Tensor>>` of length *n_layer + 1* with shape (*batch size*, *sequence_length*, *hidden_size*)
+    ///   - `all_attentions` - `Option<Vec<Tensor>>` of length *n_layer* containing the attention weights for each layer
+    ///
+    /// # Example
+    ///
+    /// ```no_run
+    /// # use tch::{nn, Device, Tensor, no_grad, Kind};
+    /// # use rust_bert::Config;
+    /// # use std::path::Path;
+    /// # use tch::kind::Kind::{Int64, Double};
+    /// use rust_bert::llama::{LlamaConfig, LlamaForCausalLM};
+    /// # let config_path = Path::new("path/to/config.json");
+    /// # let device = Device::Cpu;
+    /// # let vs = nn::VarStore::new(device);
+    /// # let config = LlamaConfig::from_file(config_path);
+    /// # let llama_model = LlamaForCausalLM::new(&vs.root(), &config).unwrap();
+    /// let (batch_size, sequence_length) = (64, 128);
+    /// let input_tensor = Tensor::rand(&[batch_size, sequence_length], (Int64, device));
+    /// let attention_mask = Tensor::ones(&[batch_size, sequence_length], (Int64, device));
+    ///
+    /// let model_output = no_grad(|| {
+    ///     llama_model.forward_t(
+    ///         Some(&input_tensor),
+    ///         None,
+    ///         None,
+    ///         None,
+    ///         Some(&attention_mask),
+    ///         false,
+    ///     )
+    /// });
+    /// ```
+    pub fn forward_t(
+        &self,
+        input_ids: Option<&Tensor>,
+        input_embeds: Option<&Tensor>,
+        position_ids: Option<&Tensor>,
+        layer_states: Option<Vec<Option<LayerState>>>,
+        attention_mask: Option<&Tensor>,
+        train: bool,
+    ) -> Result<LlamaModelLMOutput, RustBertError> {
+        let base_model_output = self.model.forward_t(
+            input_ids,
+            input_embeds,
+            position_ids,
+            layer_states,
+            attention_mask,
+            train,
+        )?;
+
+        let lm_logits = match &self.lm_head {
+            Some(lm_head) => base_model_output.hidden_states.apply(lm_head),
+            None => base_model_output
+                .hidden_states
+                .linear::<Tensor>(&self.model.embed_tokens.ws, None),
+        };
+
+        Ok(LlamaModelLMOutput {
+            lm_logits,
+            hidden_states: base_model_output.hidden_states,
+            next_cache: base_model_output.next_cache,
+            all_hidden_states: base_model_output.all_hidden_states,
+            all_attentions: base_model_output.all_attentions,
+        })
+    }
+}
+
+impl LMHeadModel for LlamaForCausalLM {
+    fn forward_t(
+        &self,
+        input_ids: Option<&Tensor>,
+        layer_past: Cache,
+        attention_mask: Option<&Tensor>,
+        _token_type_ids: Option<&Tensor>,
+        position_ids: Option<&Tensor>,
+        input_embeds: Option<&Tensor>,
+        _encoder_outputs: Option<&Tensor>,
+        _decoder_input_ids: Option<&Tensor>,
+        train: bool,
+    ) -> Result<LMModelOutput, RustBertError> {
+        let base_model_output = match layer_past {
+            Cache::LlamaCache(layer_past) => self.forward_t(
+                input_ids,
+                input_embeds,
+                position_ids,
+                layer_past,
+                attention_mask,
+                train,
+            ),
+            Cache::None => self.forward_t(
+                input_ids,
+                input_embeds,
+                position_ids,
+                None,
+                attention_mask,
+                train,
+            ),
+            _ => {
+                return Err(RustBertError::ValueError(
+                    "Cache not compatible with LLaMA Model".into(),
+                ));
+            }
+        }?;
+
+        Ok(LMModelOutput {
+            lm_logits: base_model_output.lm_logits,
+            cache: Cache::LlamaCache(base_model_output.next_cache),
+            hidden_states: Some(base_model_output.hidden_states),
+        })
+    }
+}
+
+/// Container for the LLaMA model output.
+pub struct LlamaModelOutput {
+    /// Last hidden states from the model
+    pub hidden_states: Tensor,
+    /// Cached outputs of the model (attention layers keys and values) if the model is used for generation
+    pub next_cache: Option<Vec<Option<LayerState>>>,
+    /// Hidden states for all intermediate layers
+    pub all_hidden_states: Option<Vec<Tensor>>,
+    /// Attention weights for all intermediate layers
+    pub all_attentions: Option<Vec<Tensor>>,
+}
+
+///Container holding a LLaMA model with LM head output
+pub struct LlamaModelLMOutput {
+    /// logits
+    pub lm_logits: Tensor,
+    /// Last hidden states from the model
+    pub hidden_states: Tensor,
+    /// Cached outputs of the model (attention layers keys and values) if the model is used for generation
+    pub next_cache: Option<Vec<Option<LayerState>>>,
+    /// Hidden states for all intermediate layers
+    pub all_hidden_states: Option<Vec<Tensor>>,
+    /// Attention weights for all intermediate layers
+    pub all_attentions: Option<Vec<Tensor>>,
+}
+
+/// # Language generation model based on the LLaMA architecture
+/// Also used for the Mistral checkpoints. The BOS token is prepended to the prompts.
+pub struct LlamaGenerator {
+    model: LlamaForCausalLM,
+    tokenizer: TokenizerOption,
+    var_store: nn::VarStore,
+    generate_config: GenerateConfig,
+    bos_token_id: Option<i64>,
+    eos_token_ids: Option<Vec<i64>>,
+    pad_token_id: Option<i64>,
+    is_encoder_decoder: bool,
+    vocab_size: i64,
+    decoder_start_id: Option<i64>,
+    max_position_embeddings: i64,
+}
+
+impl LlamaGenerator {
+    /// Build a new `LlamaGenerator`
+    ///
+    /// # Arguments
+    ///
+    /// * `generate_config` - `GenerateConfig` object containing the resource references (model, vocabulary, configuration), generation options and device placement (CPU/GPU).
+    /// The vocabulary resource is the SentencePiece model of the checkpoint (`tokenizer.model`).
+    ///
+    /// # Example
+    ///
+    /// ```no_run
+    /// # fn main() -> anyhow::Result<()> {
+    /// use rust_bert::llama::LlamaGenerator;
+    /// use rust_bert::pipelines::generation_utils::GenerateConfig;
+    /// use rust_bert::resources::LocalResource;
+    /// use std::path::PathBuf;
+    ///
+    /// let generate_config = GenerateConfig {
+    ///     model_resource: Box::new(LocalResource {
+    ///         local_path: PathBuf::from("path/to/rust_model.ot"),
+    ///     }),
+    ///     config_resource: Box::new(LocalResource {
+    ///         local_path: PathBuf::from("path/to/config.json"),
+    ///     }),
+    ///     vocab_resource: Box::new(LocalResource {
+    ///         local_path: PathBuf::from("path/to/tokenizer.model"),
+    ///     }),
+    ///     max_length: 64,
+    ///     do_sample: false,
+    ///     ..Default::default()
+    /// };
+    /// let llama_generator = LlamaGenerator::new(generate_config)?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn new(generate_config: GenerateConfig) -> Result<LlamaGenerator, RustBertError> {
+        let vocab_path = generate_config.vocab_resource.get_local_path()?;
+
+        let tokenizer = TokenizerOption::from_file(
+            ModelType::Llama,
+            vocab_path.to_str().unwrap(),
+            None,
+            false,
+            None,
+            None,
+        )?;
+
+        Self::new_with_tokenizer(generate_config, tokenizer)
+    }
+
+    pub fn new_with_tokenizer(
+        generate_config: GenerateConfig,
+        tokenizer: TokenizerOption,
+    ) -> Result<LlamaGenerator, RustBertError> {
+        let config_path = generate_config.config_resource.get_local_path()?;
+        let device = generate_config.device;
+
+        generate_config.validate();
+        let mut var_store = nn::VarStore::new(device);
+        let config = LlamaConfig::from_file(config_path);
+        let model = LlamaForCausalLM::new(&var_store.root(), &config)?;
+        crate::resources::load_weights(&generate_config.model_resource, &mut var_store)?;
+
+        let bos_token_id = tokenizer.get_bos_id();
+        let eos_token_ids = tokenizer.get_eos_id().map(|id| vec![id]);
+        let pad_token_id = tokenizer.get_pad_id();
+        let is_encoder_decoder = false;
+        let vocab_size = config.vocab_size;
+        let decoder_start_id = None;
+        let max_position_embeddings = config.max_position_embeddings;
+
+        Ok(LlamaGenerator {
+            model,
+            tokenizer,
+            var_store,
+            generate_config,
+            bos_token_id,
+            eos_token_ids,
+            pad_token_id,
+            is_encoder_decoder,
+            vocab_size,
+            decoder_start_id,
+            max_position_embeddings,
+        })
+    }
+}
+
+impl PrivateLanguageGenerator<LlamaForCausalLM, SentencePieceVocab, SentencePieceBpeTokenizer>
+    for LlamaGenerator
+{
+    fn get_model(&self) -> &LlamaForCausalLM {
+        &self.model
+    }
+    fn _get_tokenizer(&self) -> &TokenizerOption {
+        &self.tokenizer
+    }
+    fn get_var_store(&self) -> &nn::VarStore {
+        &self.var_store
+    }
+    fn get_var_store_mut(&mut self) -> &mut nn::VarStore {
+        &mut self.var_store
+    }
+    fn get_config(&self) -> &GenerateConfig {
+        &self.generate_config
+    }
+    fn get_bos_id(&self) -> Option<i64> {
+        self.bos_token_id
+    }
+    fn get_eos_ids(&self) -> Option<&Vec<i64>> {
+        self.eos_token_ids.as_ref()
+    }
+    fn get_pad_id(&self) -> Option<i64> {
+        self.pad_token_id
+    }
+    fn is_encoder_decoder(&self) -> bool {
+        self.is_encoder_decoder
+    }
+    fn get_vocab_size(&self) -> i64 {
+        self.vocab_size
+    }
+    fn get_decoder_start_id(&self) -> Option<i64> {
+        self.decoder_start_id
+    }
+    fn get_max_positions_embeddings(&self) -> i64 {
+        self.max_position_embeddings
+    }
+    fn supports_chunked_prefill(&self) -> bool {
+        true
+    }
+    fn add_bos_token(&self) -> bool {
+        true
+    }
+
+    fn prepare_inputs_for_generation<'a>(
+        &self,
+        input_ids: Tensor,
+        _encoder_outputs: Option<&'a Tensor>,
+        past: Cache,
+        attention_mask: Tensor,
+    ) -> PreparedInput<'a> {
+        let position_ids = (attention_mask.totype(Kind::Int64).cumsum(-1, Kind::Int64) - 1)
+            .masked_fill(&attention_mask.eq(0), 1);
+
+        match past {
+            Cache::LlamaCache(past) => {
+                if past.is_some() {
+                    PreparedInput {
+                        prepared_input: Some(input_ids.select(1, -1).unsqueeze(-1)),
+                        prepared_attention_mask: Some(attention_mask),
+                        prepared_encoder_output: None,
+                        prepared_decoder_input: None,
+                        prepared_position_ids: Some(position_ids.select(1, -1).unsqueeze(-1)),
+                        prepared_past: Cache::LlamaCache(past),
+                    }
+                } else {
+                    PreparedInput {
+                        prepared_input: Some(input_ids),
+                        prepared_attention_mask: Some(attention_mask),
+                        prepared_encoder_output: None,
+                        prepared_decoder_input: None,
+                        prepared_position_ids: Some(position_ids),
+                        prepared_past: Cache::LlamaCache(None),
+                    }
+                }
+            }
+            Cache::None => PreparedInput {
+                prepared_input: Some(input_ids),
+                prepared_attention_mask: Some(attention_mask),
+                prepared_encoder_output: None,
+                prepared_decoder_input: None,
+                prepared_position_ids: Some(position_ids),
+                prepared_past: Cache::LlamaCache(None),
+            },
+            _ => panic!("Cache type incompatible with LLaMA"),
+        }
+    }
+
+    fn reorder_cache(
+        &self,
+        past: &mut Cache,
+        _encoder_outputs: Option<Tensor>,
+        beam_indices: &Tensor,
+    ) -> Option<Tensor> {
+        match past {
+            Cache::LlamaCache(cached_decoder_state) => match cached_decoder_state {
+                Some(old_cache) => {
+                    for layer_state in old_cache.iter_mut() {
+                        if layer_state.is_some() {
+                            layer_state.as_mut().unwrap().reorder_cache(beam_indices)
+                        };
+                    }
+                    None
+                }
+                None => None,
+            },
+            Cache::None => None,
+            _ => {
+                panic!("Invalid cache for LLaMA model");
+            }
+        }
+    }
+}
+
+impl LanguageGenerator<LlamaForCausalLM, SentencePieceVocab, SentencePieceBpeTokenizer>
+    for LlamaGenerator
+{
+}
@@ -0,0 +1,70 @@
+//! # LLaMA
+//!
+//! Implementation of the LLaMA language model ([LLaMA: Open and Efficient Foundation Language Models](https://arxiv.org/abs/2302.13971) Touvron, Lavril, Izacard, Martinet, Lachaux, Lacroix, Rozière, Goyal, Hambro, Azhar, Rodriguez, Joulin, Grave, Lample, 2023).
+//! The base model is implemented in the `llama_model::LlamaModel` struct. A causal language modeling head is implemented in `llama_model::LlamaForCausalLM`.
+//! Compared to GPT-2, the decoder layers use a pre-normalization with RMSNorm, rotary position embeddings, a SwiGLU feed-forward layer
+//! and a grouped-query attention (several query heads sharing each key and value head, reducing the size of the cache).
+//!
+//! The same implementation supports the Mistral models ([Mistral 7B](https://arxiv.org/abs/2310.06825) Jiang et al., 2023),
+//! adding a sliding window to the attention layers (`sliding_window` in the configuration).
+//!
+//! # Model set-up and pre-trained weights loading
+//!
+//! The model is available for text generation with `ModelType::Llama`. All models expect the following resources:
+//! - Configuration file expected to have a structure following the [Transformers library](https://github.com/huggingface/transformers)
+//! - Model weights are expected to have a structure and parameter names following the [Transformers library](https://github.com/huggingface/transformers). A conversion using the Python utility scripts is required to convert the `.bin` weights to the `.ot` format (the shards of a checkpoint can be passed together: `python utils/convert_model.py path/to/pytorch_model-*.bin`).
+//! - SentencePiece BPE tokenizer using the `tokenizer.model` file of the checkpoint
+//!
+//! No pre-trained checkpoint is hosted with the crate, the resources are provided as local files:
+//!
+//! ```no_run
+//! use rust_bert::pipelines::common::ModelType;
+//! use rust_bert::pipelines::text_generation::{TextGenerationConfig, TextGenerationModel};
+//! use rust_bert::resources::LocalResource;
+//! use std::path::PathBuf;
+//! use tch::Device;
+//!
+//! fn main() -> anyhow::Result<()> {
+//!     let config_resource = Box::new(LocalResource {
+//!         local_path: PathBuf::from("path/to/config.json"),
+//!     });
+//!     let vocab_resource = Box::new(LocalResource {
+//!         local_path: PathBuf::from("path/to/tokenizer.model"),
+//!     });
+//!     let model_resource = Box::new(LocalResource {
+//!         local_path: PathBuf::from("path/to/rust_model.ot"),
+//!     });
+//!
+//!     let text_generation_config = TextGenerationConfig {
+//!         model_type: ModelType::Llama,
+//!         model_resource,
+//!         config_resource,
+//!         vocab_resource,
+//!         do_sample: false,
+//!         max_length: 64,
+//!         device: Device::cuda_if_available(),
+//!         ..Default::default()
+//!     };
+//!     let mut model = TextGenerationModel::new(text_generation_config)?;
+//!     model.half();
+//!
+//!     let input_context = "The capital of France is";
+//!     let output = model.generate(&[input_context], None);
+//!
+//!     for sentence in output {
+//!         println!("{}", sentence);
+//!     }
+//!
+//!     Ok(())
+//! }
+//! ```
+
+mod attention;
+mod decoder;
+mod llama_model;
+
+pub use llama_model::{
+    LlamaConfig, LlamaForCausalLM, LlamaGenerator, LlamaModel, LlamaModelLMOutput, LlamaModelOutput,
+};
+
+pub use attention::LayerState;
@@ -11,10 +11,12 @@
 // limitations under the License.
 
 use crate::bart::{BartDecoderOutput, _expand_mask, _make_causal_mask};
+use crate::common::attention_mask::{get_padding_mask, get_query_attention_mask};
 use crate::common::dropout::Dropout;
 use crate::m2m_100::embeddings::SinusoidalPositionalEmbedding;
 use crate::m2m_100::{LayerState, M2M100Config};
 use crate::mbart::MBartDecoderLayer;
+use crate::RustBertError;
 use std::borrow::{Borrow, BorrowMut};
 use tch::{nn, Tensor};
 
@@ -89,7 +91,7 @@ impl M2M100Decoder {
         embeddings: &nn::Embedding,
         old_layer_states: Option<Vec<(Option<LayerState>, Option<LayerState>)>>,
         train: bool,
-    ) -> M2M100DecoderOutput {
+    ) -> Result<M2M100DecoderOutput, RustBertError> {
         let past_key_values_length = if let Some(old_layer_states_values) = &old_layer_states {
             if let Some(old_value_state) = &old_layer_states_values[0].0 {
                 old_value_state.prev_key.size()[2]
@@ -119,16 +121,25 @@ impl M2M100Decoder {
             None
         };
 
-        let decoder_attention_mask = decoder_attention_mask.map(|attention_mask| {
-            if let Some(causal_mask) = causal_mask {
-                causal_mask + _expand_mask(attention_mask, Some(sequence_length), x.kind())
-            } else {
-                _expand_mask(attention_mask, Some(sequence_length), x.kind())
-            }
-        });
+        let decoder_attention_mask = match decoder_attention_mask {
+            Some(attention_mask) if attention_mask.dim() > 2 => Some(_expand_mask(
+                &get_query_attention_mask(attention_mask, sequence_length, past_key_values_length)?,
+                None,
+                x.kind(),
+            )),
+            _ => decoder_attention_mask.map(|attention_mask| {
+                if let Some(causal_mask) = causal_mask {
+                    causal_mask + _expand_mask(attention_mask, Some(sequence_length), x.kind())
+                } else {
+                    _expand_mask(attention_mask, Some(sequence_length), x.kind())
+                }
+            }),
+        };
 
         let encoder_attention_mask = encoder_attention_mask
-            .map(|mask| _expand_mask(mask, Some(*input_ids.size().last().unwrap()), x.kind()));
+            .map(get_padding_mask)
+            .transpose()?
+            .map(|mask| _expand_mask(&mask, Some(*input_ids.size().last().unwrap()), x.kind()));
 
         let mut hidden_state = x.apply_t(&self.dropout, train);
 
@@ -142,6 +153,11 @@ impl M2M100Decoder {
         } else {
             None
         };
+        let mut all_cross_attentions: Option<Vec<Tensor>> = if self.output_attentions {
+            Some(Vec::with_capacity(self.layers.len()))
+        } else {
+            None
+        };
         let mut next_decoder_cache: Option<Vec<(Option<LayerState>, Option<LayerState>)>> =
             if self.output_past {
                 if old_layer_states.is_some() {
@@ -176,18 +192,22 @@ impl M2M100Decoder {
             if let Some(attentions) = all_attentions.borrow_mut() {
                 attentions.push(attention_weights.as_ref().unwrap().copy());
             };
+            if let Some(cross_attentions) = all_cross_attentions.borrow_mut() {
+                cross_attentions.push(temp.2.as_ref().unwrap().copy());
+            };
             if let Some(value) = &mut next_decoder_cache {
-                value[layer_idx] = temp.2
+                value[layer_idx] = temp.3
             };
         }
 
-        M2M100DecoderOutput {
+        Ok(M2M100DecoderOutput {
             hidden_state: hidden_state.apply(&self.layer_norm),
             encoder_attention_mask,
             next_decoder_cache,
             all_hidden_states,
             all_attentions,
-        }
+            all_cross_attentions,
+        })
     }
 }
 
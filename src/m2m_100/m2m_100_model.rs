@@ -259,7 +259,7 @@ impl M2M100Model {
         decoder_attention_mask: Option<&Tensor>,
         layer_states: Option<Vec<(Option<LayerState>, Option<LayerState>)>>,
         train: bool,
-    ) -> M2M100ModelOutput {
+    ) -> Result<M2M100ModelOutput, RustBertError> {
         let calc_decoder_input_ids = if decoder_input_ids.is_none() {
             Some(_shift_tokens_right(
                 input_ids.unwrap(),
@@ -305,17 +305,18 @@ impl M2M100Model {
             &self.embeddings,
             layer_states,
             train,
-        );
+        )?;
 
-        M2M100ModelOutput {
+        Ok(M2M100ModelOutput {
             decoder_output: decoder_output.hidden_state,
             encoder_hidden_state: calc_hidden_states,
             cache: decoder_output.next_decoder_cache,
             all_decoder_hidden_states: decoder_output.all_hidden_states,
             all_decoder_attentions: decoder_output.all_attentions,
+            all_decoder_cross_attentions: decoder_output.all_cross_attentions,
             all_encoder_hidden_states,
             all_encoder_attentions,
-        }
+        })
     }
 }
 
@@ -425,7 +426,7 @@ impl M2M100ForConditionalGeneration {
         decoder_attention_mask: Option<&Tensor>,
         old_layer_states: Option<Vec<(Option<LayerState>, Option<LayerState>)>>,
         train: bool,
-    ) -> M2M100ModelOutput {
+    ) -> Result<M2M100ModelOutput, RustBertError> {
         let base_model_output = self.base_model.forward_t(
             input_ids,
             attention_mask,
@@ -434,15 +435,15 @@ impl M2M100ForConditionalGeneration {
             decoder_attention_mask,
             old_layer_states,
             train,
-        );
+        )?;
 
         let lm_logits = base_model_output
             .decoder_output
             .linear::<Tensor>(&self.base_model.embeddings.ws, None);
-        M2M100ModelOutput {
+        Ok(M2M100ModelOutput {
             decoder_output: lm_logits,
             ..base_model_output
-        }
+        })
     }
 
     pub fn encode(&self, input_ids: &Tensor, attention_mask: Option<&Tensor>) -> Tensor {
@@ -479,6 +480,7 @@ impl LMHeadModel for M2M100ForConditionalGeneration {
     ///   - `lm_logits` - `Tensor` of shape (*batch size*, *sequence_length*, *vocab_size*) representing the logits for each vocab item and position
     ///   - `cache` - `BARTCache` made of `Option<Vec<(Option<Vec<&LayerState, &LayerState>>)>>` of length *n_layer* containing the encoder past keys and values for
     ///     both the self attention and the encoder cross attention of each layer of the decoder.
+    ///   - `hidden_states` - `Option<Tensor>` of shape (*batch size*, *sequence_length*, *hidden_size*) representing the activations of the last decoder hidden state
     ///
     /// # Example
     ///
@@ -533,7 +535,7 @@ impl LMHeadModel for M2M100ForConditionalGeneration {
                 None,
                 cached_layer_states,
                 train,
-            ),
+            )?,
 
             Cache::None => self.base_model.forward_t(
                 input_ids,
@@ -543,13 +545,51 @@ impl LMHeadModel for M2M100ForConditionalGeneration {
                 None,
                 None,
                 train,
-            ),
+            )?,
+            _ => {
+                return Err(RustBertError::ValueError(
+                    "Cache not compatible with M2M100 Model".into(),
+                ));
+            }
+        };
+
+        let lm_logits = base_model_output
+            .decoder_output
+            .linear::<Tensor>(&self.base_model.embeddings.ws, None);
+        Ok(LMModelOutput {
+            lm_logits,
+            cache: Cache::BARTCache(base_model_output.cache),
+            hidden_states: Some(base_model_output.decoder_output),
+        })
+    }
+
+    fn forward_with_decoder_attention_mask(
+        &self,
+        decoder_input_ids: &Tensor,
+        cache: Cache,
+        attention_mask: Option<&Tensor>,
+        encoder_outputs: &Tensor,
+        decoder_attention_mask: &Tensor,
+        train: bool,
+    ) -> Result<LMModelOutput, RustBertError> {
+        let cached_layer_states = match cache {
+            Cache::BARTCache(cached_layer_states) => cached_layer_states,
+            Cache::None => None,
             _ => {
                 return Err(RustBertError::ValueError(
                     "Cache not compatible with M2M100 Model".into(),
                 ));
             }
         };
+        let base_model_output = self.base_model.forward_t(
+            None,
+            attention_mask,
+            Some(decoder_input_ids),
+            Some(encoder_outputs),
+            Some(decoder_attention_mask),
+            cached_layer_states,
+            train,
+        )?;
 
         let lm_logits = base_model_output
             .decoder_output
@@ -557,6 +597,7 @@ impl LMHeadModel for M2M100ForConditionalGeneration {
         Ok(LMModelOutput {
             lm_logits,
             cache: Cache::BARTCache(base_model_output.cache),
+            hidden_states: Some(base_model_output.decoder_output),
         })
     }
 }
@@ -636,15 +677,17 @@ impl M2M100Generator {
         tokenizer: TokenizerOption,
     ) -> Result<M2M100Generator, RustBertError> {
         let config_path = generate_config.config_resource.get_local_path()?;
-        let weights_path = generate_config.model_resource.get_local_path()?;
         let device = generate_config.device;
 
         generate_config.validate();
         let mut var_store = nn::VarStore::new(device);
 
-        let config = M2M100Config::from_file(config_path);
+        let mut config = M2M100Config::from_file(config_path);
+        if generate_config.output_attentions {
+            config.output_attentions = Some(true);
+        }
         let model = M2M100ForConditionalGeneration::new(&var_store.root(), &config);
-        var_store.load(weights_path)?;
+        crate::resources::load_weights(&generate_config.model_resource, &mut var_store)?;
 
         let bos_token_id = Some(config.bos_token_id.unwrap_or(0));
         let eos_token_ids = Some(match config.eos_token_id {
@@ -773,6 +816,7 @@ impl PrivateLanguageGenerator<M2M100ForConditionalGeneration, M2M100Vocab, M2M10
         prompt_text: &[S],
         max_len: i64,
         pad_token_id: Option<i64>,
+        _add_bos_token: bool,
     ) -> Tensor
     where
         S: AsRef<str> + Sync,
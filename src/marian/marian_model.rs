@@ -640,7 +640,7 @@ impl MarianForConditionalGeneration {
         decoder_attention_mask: Option<&Tensor>,
         old_layer_states: Option<Vec<(Option<LayerState>, Option<LayerState>)>>,
         train: bool,
-    ) -> BartModelOutput {
+    ) -> Result<BartModelOutput, RustBertError> {
         let base_model_output = self.base_model.forward_t(
             input_ids,
             attention_mask,
@@ -649,15 +649,15 @@ impl MarianForConditionalGeneration {
             decoder_attention_mask,
             old_layer_states,
             train,
-        );
+        )?;
 
         let lm_logits = base_model_output
             .decoder_output
             .linear::<Tensor>(&self.base_model.embeddings.ws, None);
-        BartModelOutput {
+        Ok(BartModelOutput {
             decoder_output: lm_logits,
             ..base_model_output
-        }
+        })
     }
 
     pub fn encode(&self, input_ids: &Tensor, attention_mask: Option<&Tensor>) -> Tensor {
@@ -696,6 +696,7 @@ impl LMHeadModel for MarianForConditionalGeneration {
     ///   - `lm_logits` - `Tensor` of shape (*batch size*, *sequence_length*, *vocab_size*) representing the logits for each vocab item and position
     ///   - `cache` - `BartCache` made of `Option<Vec<(Option<Vec<&LayerState, &LayerState>>)>>` of length *n_layer* containing the encoder past keys and values for
     ///     both the self attention and the encoder cross attention of each layer of the decoder.
+    ///   - `hidden_states` - `Option<Tensor>` of shape (*batch size*, *sequence_length*, *hidden_size*) representing the activations of the last decoder hidden state
     ///
     /// # Example
     ///
@@ -753,7 +754,7 @@ impl LMHeadModel for MarianForConditionalGeneration {
                 None,
                 cached_layer_states,
                 train,
-            ),
+            )?,
             Cache::None => self.base_model.forward_t(
                 input_ids,
                 attention_mask,
@@ -762,13 +763,52 @@ impl LMHeadModel for MarianForConditionalGeneration {
                 None,
                 None,
                 train,
-            ),
+            )?,
+            _ => {
+                return Err(RustBertError::ValueError(
+                    "Cache not compatible with Marian Model".into(),
+                ));
+            }
+        };
+
+        let lm_logits = base_model_output
+            .decoder_output
+            .linear::<Tensor>(&self.base_model.embeddings.ws, None)
+            + &self.final_logits_bias;
+        Ok(LMModelOutput {
+            lm_logits,
+            cache: Cache::BARTCache(base_model_output.cache),
+            hidden_states: Some(base_model_output.decoder_output),
+        })
+    }
+
+    fn forward_with_decoder_attention_mask(
+        &self,
+        decoder_input_ids: &Tensor,
+        cache: Cache,
+        attention_mask: Option<&Tensor>,
+        encoder_outputs: &Tensor,
+        decoder_attention_mask: &Tensor,
+        train: bool,
+    ) -> Result<LMModelOutput, RustBertError> {
+        let cached_layer_states = match cache {
+            Cache::BARTCache(cached_layer_states) => cached_layer_states,
+            Cache::None => None,
             _ => {
                 return Err(RustBertError::ValueError(
                     "Cache not compatible with Marian Model".into(),
                 ));
             }
         };
+        let base_model_output = self.base_model.forward_t(
+            None,
+            attention_mask,
+            Some(decoder_input_ids),
+            Some(encoder_outputs),
+            Some(decoder_attention_mask),
+            cached_layer_states,
+            train,
+        )?;
 
         let lm_logits = base_model_output
             .decoder_output
@@ -777,6 +817,7 @@ impl LMHeadModel for MarianForConditionalGeneration {
         Ok(LMModelOutput {
             lm_logits,
             cache: Cache::BARTCache(base_model_output.cache),
+            hidden_states: Some(base_model_output.decoder_output),
         })
     }
 }
@@ -856,15 +897,17 @@ impl MarianGenerator {
         tokenizer: TokenizerOption,
     ) -> Result<MarianGenerator, RustBertError> {
         let config_path = generate_config.config_resource.get_local_path()?;
-        let weights_path = generate_config.model_resource.get_local_path()?;
         let device = generate_config.device;
 
         generate_config.validate();
         let mut var_store = nn::VarStore::new(device);
 
-        let config = BartConfig::from_file(config_path);
+        let mut config = BartConfig::from_file(config_path);
+        if generate_config.output_attentions {
+            config.output_attentions = Some(true);
+        }
         let model = MarianForConditionalGeneration::new(&var_store.root(), &config);
-        var_store.load(weights_path)?;
+        crate::resources::load_weights(&generate_config.model_resource, &mut var_store)?;
 
         let bos_token_id = Some(config.bos_token_id.unwrap_or(0));
         let eos_token_ids = Some(match config.eos_token_id {
@@ -999,6 +1042,7 @@ impl PrivateLanguageGenerator<MarianForConditionalGeneration, MarianVocab, Maria
         prompt_text: &[S],
         max_len: i64,
         pad_token_id: Option<i64>,
+        _add_bos_token: bool,
     ) -> Tensor
     where
         S: AsRef<str> + Sync,
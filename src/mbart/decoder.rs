@@ -12,11 +12,13 @@
 
 use crate::bart::{BartDecoderOutput, _expand_mask, _prepare_decoder_attention_mask};
 use crate::common::activations::TensorFunction;
+use crate::common::attention_mask::get_padding_mask;
 use crate::common::dropout::Dropout;
 use crate::mbart::attention::MBartAttention;
 use crate::mbart::embeddings::MBartLearnedPositionalEmbedding;
 use crate::mbart::{LayerState, MBartConfig};
 use crate::Activation;
+use crate::RustBertError;
 use std::borrow::{Borrow, BorrowMut};
 use tch::{nn, Tensor};
 
@@ -122,6 +124,7 @@ impl MBartDecoderLayer {
     ) -> (
         Tensor,
         Option<Tensor>,
+        Option<Tensor>,
         (Option<LayerState>, Option<LayerState>),
     ) {
         let output = x.apply(&self.self_attention_layer_norm);
@@ -136,13 +139,14 @@ impl MBartDecoderLayer {
         let output: Tensor = output.apply_t(&self.dropout, train) + x;
 
         let output1 = output.apply(&self.encoder_attention_layer_norm);
-        let (output1, _, new_encoder_layer_states) = self.encoder_attention.forward_t(
-            &output1,
-            Some(encoder_hidden_states),
-            encoder_attention_mask,
-            layer_states.1,
-            train,
-        );
+        let (output1, cross_attention_weights, new_encoder_layer_states) =
+            self.encoder_attention.forward_t(
+                &output1,
+                Some(encoder_hidden_states),
+                encoder_attention_mask,
+                layer_states.1,
+                train,
+            );
         let output1: Tensor = output1.apply_t(&self.dropout, train) + output;
 
         let output2 = output1.apply(&self.final_layer_norm);
@@ -155,6 +159,7 @@ impl MBartDecoderLayer {
         (
             output2,
             attention_weights,
+            cross_attention_weights,
             (new_self_layer_states, new_encoder_layer_states),
         )
     }
@@ -235,7 +240,7 @@ impl MBartDecoder {
         embeddings: &nn::Embedding,
         old_layer_states: Option<Vec<(Option<LayerState>, Option<LayerState>)>>,
         train: bool,
-    ) -> MBartDecoderOutput {
+    ) -> Result<MBartDecoderOutput, RustBertError> {
         let past_key_values_length = if let Some(old_layer_states_values) = &old_layer_states {
             if let Some(old_value_state) = &old_layer_states_values[0].0 {
                 old_value_state.prev_key.size()[2]
@@ -257,10 +262,12 @@ impl MBartDecoder {
             input_ids.size().as_slice(),
             &x,
             past_key_values_length,
-        );
+        )?;
 
         let encoder_attention_mask = encoder_attention_mask
-            .map(|mask| _expand_mask(mask, Some(*input_ids.size().last().unwrap()), x.kind()));
+            .map(get_padding_mask)
+            .transpose()?
+            .map(|mask| _expand_mask(&mask, Some(*input_ids.size().last().unwrap()), x.kind()));
 
         let mut hidden_state = x
             .apply(&self.layer_norm_embedding)
@@ -276,6 +283,11 @@ impl MBartDecoder {
         } else {
             None
         };
+        let mut all_cross_attentions: Option<Vec<Tensor>> = if self.output_attentions {
+            Some(Vec::with_capacity(self.layers.len()))
+        } else {
+            None
+        };
         let mut next_decoder_cache: Option<Vec<(Option<LayerState>, Option<LayerState>)>> =
             if self.output_past {
                 if old_layer_states.is_some() {
@@ -310,18 +322,22 @@ impl MBartDecoder {
             if let Some(attentions) = all_attentions.borrow_mut() {
                 attentions.push(attention_weights.as_ref().unwrap().copy());
             };
+            if let Some(cross_attentions) = all_cross_attentions.borrow_mut() {
+                cross_attentions.push(temp.2.as_ref().unwrap().copy());
+            };
             if let Some(value) = &mut next_decoder_cache {
-                value[layer_idx] = temp.2
+                value[layer_idx] = temp.3
             };
         }
 
-        MBartDecoderOutput {
+        Ok(MBartDecoderOutput {
             hidden_state: hidden_state.apply(&self.layer_norm),
             encoder_attention_mask,
             next_decoder_cache,
             all_hidden_states,
             all_attentions,
-        }
+            all_cross_attentions,
+        })
     }
 }
 
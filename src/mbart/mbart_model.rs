@@ -354,7 +354,7 @@ impl MBartModel {
         decoder_attention_mask: Option<&Tensor>,
         layer_states: Option<Vec<(Option<LayerState>, Option<LayerState>)>>,
         train: bool,
-    ) -> MBartModelOutput {
+    ) -> Result<MBartModelOutput, RustBertError> {
         let calc_decoder_input_ids = if decoder_input_ids.is_none() {
             Some(_shift_tokens_right(input_ids.unwrap(), self.pad_token_id))
         } else {
@@ -396,17 +396,18 @@ impl MBartModel {
             &self.embeddings,
             layer_states,
             train,
-        );
+        )?;
 
-        MBartModelOutput {
+        Ok(MBartModelOutput {
             decoder_output: decoder_output.hidden_state,
             encoder_hidden_state: calc_hidden_states,
             cache: decoder_output.next_decoder_cache,
             all_decoder_hidden_states: decoder_output.all_hidden_states,
             all_decoder_attentions: decoder_output.all_attentions,
+            all_decoder_cross_attentions: decoder_output.all_cross_attentions,
             all_encoder_hidden_states,
             all_encoder_attentions,
-        }
+        })
     }
 }
 
@@ -525,7 +526,7 @@ impl MBartForConditionalGeneration {
         decoder_attention_mask: Option<&Tensor>,
         old_layer_states: Option<Vec<(Option<LayerState>, Option<LayerState>)>>,
         train: bool,
-    ) -> MBartModelOutput {
+    ) -> Result<MBartModelOutput, RustBertError> {
         let base_model_output = self.base_model.forward_t(
             input_ids,
             attention_mask,
@@ -534,16 +535,16 @@ impl MBartForConditionalGeneration {
             decoder_attention_mask,
             old_layer_states,
             train,
-        );
+        )?;
 
         let lm_logits = base_model_output
             .decoder_output
             .linear::<Tensor>(&self.base_model.embeddings.ws, None)
             + &self.final_logits_bias;
-        BartModelOutput {
+        Ok(BartModelOutput {
             decoder_output: lm_logits,
             ..base_model_output
-        }
+        })
     }
 
     pub fn encode(&self, input_ids: &Tensor, attention_mask: Option<&Tensor>) -> Tensor {
@@ -671,7 +672,7 @@ impl MBartForSequenceClassification {
         decoder_input_ids: Option<&Tensor>,
         decoder_attention_mask: Option<&Tensor>,
         train: bool,
-    ) -> MBartModelOutput {
+    ) -> Result<MBartModelOutput, RustBertError> {
         let base_model_output = self.base_model.forward_t(
             Some(input_ids),
             attention_mask,
@@ -680,7 +681,7 @@ impl MBartForSequenceClassification {
             decoder_attention_mask,
             None,
             train,
-        );
+        )?;
         let eos_mask = input_ids.eq(self.eos_token_id);
         let reshape = eos_mask.sum_dim_intlist(&[1], true, Int64);
         let sentence_representation = base_model_output
@@ -699,15 +700,16 @@ impl MBartForSequenceClassification {
         let logits = self
             .classification_head
             .forward_t(&sentence_representation, train);
-        MBartModelOutput {
+        Ok(MBartModelOutput {
             decoder_output: logits,
             encoder_hidden_state: base_model_output.encoder_hidden_state,
             cache: None,
             all_decoder_hidden_states: base_model_output.all_decoder_hidden_states,
             all_decoder_attentions: base_model_output.all_decoder_attentions,
+            all_decoder_cross_attentions: base_model_output.all_decoder_cross_attentions,
             all_encoder_hidden_states: base_model_output.all_encoder_hidden_states,
             all_encoder_attentions: base_model_output.all_encoder_attentions,
-        }
+        })
     }
 }
 
@@ -733,6 +735,7 @@ impl LMHeadModel for MBartForConditionalGeneration {
     ///   - `lm_logits` - `Tensor` of shape (*batch size*, *sequence_length*, *vocab_size*) representing the logits for each vocab item and position
     ///   - `cache` - `BartCache` made of `Option<Vec<(Option<Vec<&LayerState, &LayerState>>)>>` of length *n_layer* containing the encoder past keys and values for
     ///     both the self attention and the encoder cross attention of each layer of the decoder.
+    ///   - `hidden_states` - `Option<Tensor>` of shape (*batch size*, *sequence_length*, *hidden_size*) representing the activations of the last decoder hidden state
     ///
     /// # Example
     ///
@@ -787,7 +790,7 @@ impl LMHeadModel for MBartForConditionalGeneration {
                 None,
                 cached_layer_states,
                 train,
-            ),
+            )?,
 
             Cache::None => self.base_model.forward_t(
                 input_ids,
@@ -797,7 +800,7 @@ impl LMHeadModel for MBartForConditionalGeneration {
                 None,
                 None,
                 train,
-            ),
+            )?,
             _ => {
                 return Err(RustBertError::ValueError(
                     "Cache not compatible with MBART Model".into(),
@@ -812,6 +815,46 @@ impl LMHeadModel for MBartForConditionalGeneration {
         Ok(LMModelOutput {
             lm_logits,
             cache: Cache::BARTCache(base_model_output.cache),
+            hidden_states: Some(base_model_output.decoder_output),
+        })
+    }
+
+    fn forward_with_decoder_attention_mask(
+        &self,
+        decoder_input_ids: &Tensor,
+        cache: Cache,
+        attention_mask: Option<&Tensor>,
+        encoder_outputs: &Tensor,
+        decoder_attention_mask: &Tensor,
+        train: bool,
+    ) -> Result<LMModelOutput, RustBertError> {
+        let cached_layer_states = match cache {
+            Cache::BARTCache(cached_layer_states) => cached_layer_states,
+            Cache::None => None,
+            _ => {
+                return Err(RustBertError::ValueError(
+                    "Cache not compatible with MBART Model".into(),
+                ));
+            }
+        };
+        let base_model_output = self.base_model.forward_t(
+            None,
+            attention_mask,
+            Some(decoder_input_ids),
+            Some(encoder_outputs),
+            Some(decoder_attention_mask),
+            cached_layer_states,
+            train,
+        )?;
+
+        let lm_logits = base_model_output
+            .decoder_output
+            .linear::<Tensor>(&self.base_model.embeddings.ws, None)
+            + &self.final_logits_bias;
+        Ok(LMModelOutput {
+            lm_logits,
+            cache: Cache::BARTCache(base_model_output.cache),
+            hidden_states: Some(base_model_output.decoder_output),
         })
     }
 }
@@ -893,15 +936,17 @@ impl MBartGenerator {
         tokenizer: TokenizerOption,
     ) -> Result<MBartGenerator, RustBertError> {
         let config_path = generate_config.config_resource.get_local_path()?;
-        let weights_path = generate_config.model_resource.get_local_path()?;
         let device = generate_config.device;
 
         generate_config.validate();
         let mut var_store = nn::VarStore::new(device);
 
-        let config = MBartConfig::from_file(config_path);
+        let mut config = MBartConfig::from_file(config_path);
+        if generate_config.output_attentions {
+            config.output_attentions = Some(true);
+        }
         let model = MBartForConditionalGeneration::new(&var_store.root(), &config);
-        var_store.load(weights_path)?;
+        crate::resources::load_weights(&generate_config.model_resource, &mut var_store)?;
 
         let bos_token_id = Some(config.bos_token_id.unwrap_or(0));
         let eos_token_ids = Some(match config.eos_token_id {
@@ -1030,6 +1075,7 @@ impl PrivateLanguageGenerator<MBartForConditionalGeneration, MBart50Vocab, MBart
         prompt_text: &[S],
         max_len: i64,
         pad_token_id: Option<i64>,
+        _add_bos_token: bool,
     ) -> Tensor
     where
         S: AsRef<str> + Sync,
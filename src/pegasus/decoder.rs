@@ -11,11 +11,13 @@
 // limitations under the License.
 
 use crate::bart::{BartDecoderOutput, _expand_mask, _prepare_decoder_attention_mask};
+use crate::common::attention_mask::get_padding_mask;
 use crate::common::dropout::Dropout;
 use crate::mbart::MBartDecoderLayer;
 use crate::pegasus::attention::LayerState;
 use crate::pegasus::embeddings::SinusoidalPositionalEmbedding;
 use crate::pegasus::PegasusConfig;
+use crate::RustBertError;
 use std::borrow::{Borrow, BorrowMut};
 use tch::{nn, Tensor};
 
@@ -94,7 +96,7 @@ impl PegasusDecoder {
         embeddings: &nn::Embedding,
         old_layer_states: Option<Vec<(Option<LayerState>, Option<LayerState>)>>,
         train: bool,
-    ) -> PegasusDecoderOutput {
+    ) -> Result<PegasusDecoderOutput, RustBertError> {
         let past_key_values_length = if let Some(old_layer_states_values) = &old_layer_states {
             if let Some(old_value_state) = &old_layer_states_values[0].0 {
                 old_value_state.prev_key.size()[2]
@@ -120,10 +122,12 @@ impl PegasusDecoder {
             input_ids.size().as_slice(),
             &x,
             past_key_values_length,
-        );
+        )?;
 
         let encoder_attention_mask = encoder_attention_mask
-            .map(|mask| _expand_mask(mask, Some(*input_ids.size().last().unwrap()), x.kind()));
+            .map(get_padding_mask)
+            .transpose()?
+            .map(|mask| _expand_mask(&mask, Some(*input_ids.size().last().unwrap()), x.kind()));
 
         let mut hidden_state = x.apply_t(&self.dropout, train);
         let mut all_hidden_states: Option<Vec<Tensor>> = if self.output_hidden_states {
@@ -136,6 +140,11 @@ impl PegasusDecoder {
         } else {
             None
         };
+        let mut all_cross_attentions: Option<Vec<Tensor>> = if self.output_attentions {
+            Some(Vec::with_capacity(self.layers.len()))
+        } else {
+            None
+        };
         let mut next_decoder_cache: Option<Vec<(Option<LayerState>, Option<LayerState>)>> =
             if self.output_past {
                 if old_layer_states.is_some() {
@@ -170,20 +179,24 @@ impl PegasusDecoder {
             if let Some(attentions) = all_attentions.borrow_mut() {
                 attentions.push(attention_weights.as_ref().unwrap().copy());
             };
+            if let Some(cross_attentions) = all_cross_attentions.borrow_mut() {
+                cross_attentions.push(temp.2.as_ref().unwrap().copy());
+            };
             if let Some(value) = &mut next_decoder_cache {
-                value[layer_idx] = temp.2
+                value[layer_idx] = temp.3
             };
         }
 
         hidden_state = hidden_state.apply(&self.layer_norm);
 
-        PegasusDecoderOutput {
+        Ok(PegasusDecoderOutput {
             hidden_state,
             encoder_attention_mask,
             next_decoder_cache,
             all_hidden_states,
             all_attentions,
-        }
+            all_cross_attentions,
+        })
     }
 }
 
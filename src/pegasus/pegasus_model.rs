@@ -215,7 +215,7 @@ impl PegasusModel {
         decoder_attention_mask: Option<&Tensor>,
         layer_states: Option<Vec<(Option<LayerState>, Option<LayerState>)>>,
         train: bool,
-    ) -> PegasusModelOutput {
+    ) -> Result<PegasusModelOutput, RustBertError> {
         let calc_encoder_output = if encoder_output.is_none() {
             Some(self.encoder.forward_t(
                 input_ids.unwrap(),
@@ -248,16 +248,17 @@ impl PegasusModel {
             &self.embeddings,
             layer_states,
             train,
-        );
-        PegasusModelOutput {
+        )?;
+        Ok(PegasusModelOutput {
             decoder_output: decoder_output.hidden_state,
             encoder_hidden_state: calc_hidden_states,
             cache: decoder_output.next_decoder_cache,
             all_decoder_hidden_states: decoder_output.all_hidden_states,
             all_decoder_attentions: decoder_output.all_attentions,
+            all_decoder_cross_attentions: decoder_output.all_cross_attentions,
             all_encoder_hidden_states,
             all_encoder_attentions,
-        }
+        })
     }
 }
 
@@ -383,7 +384,7 @@ impl PegasusForConditionalGeneration {
         decoder_attention_mask: Option<&Tensor>,
         old_layer_states: Option<Vec<(Option<LayerState>, Option<LayerState>)>>,
         train: bool,
-    ) -> PegasusModelOutput {
+    ) -> Result<PegasusModelOutput, RustBertError> {
         let calc_decoder_input_ids = if decoder_input_ids.is_none() {
             Some(_shift_tokens_right(
                 input_ids.unwrap(),
@@ -405,16 +406,16 @@ impl PegasusForConditionalGeneration {
             decoder_attention_mask,
             old_layer_states,
             train,
-        );
+        )?;
 
         let lm_logits = base_model_output
             .decoder_output
             .linear::<Tensor>(&self.base_model.embeddings.ws, None)
             + &self.final_logits_bias;
-        PegasusModelOutput {
+        Ok(PegasusModelOutput {
             decoder_output: lm_logits,
             ..base_model_output
-        }
+        })
     }
 
     pub fn encode(&self, input_ids: &Tensor, attention_mask: Option<&Tensor>) -> Tensor {
@@ -452,6 +453,7 @@ impl LMHeadModel for PegasusForConditionalGeneration {
     ///   - `lm_logits` - `Tensor` of shape (*batch size*, *sequence_length*, *vocab_size*) representing the logits for each vocab item and position
     ///   - `cache` - `BartCache` made of `Option<Vec<(Option<Vec<&LayerState, &LayerState>>)>>` of length *n_layer* containing the encoder past keys and values for
     ///     both the self attention and the encoder cross attention of each layer of the decoder.
+    ///   - `hidden_states` - `Option<Tensor>` of shape (*batch size*, *sequence_length*, *hidden_size*) representing the activations of the last decoder hidden state
     ///
     /// # Example
     ///
@@ -511,7 +513,7 @@ impl LMHeadModel for PegasusForConditionalGeneration {
                 None,
                 cached_layer_states,
                 train,
-            ),
+            )?,
             Cache::None => self.base_model.forward_t(
                 input_ids,
                 attention_mask,
@@ -525,13 +527,52 @@ impl LMHeadModel for PegasusForConditionalGeneration {
                 None,
                 None,
                 train,
-            ),
+            )?,
+            _ => {
+                return Err(RustBertError::ValueError(
+                    "Cache not compatible with Pegasus Model".into(),
+                ));
+            }
+        };
+
+        let lm_logits = base_model_output
+            .decoder_output
+            .linear::<Tensor>(&self.base_model.embeddings.ws, None)
+            + &self.final_logits_bias;
+        Ok(LMModelOutput {
+            lm_logits,
+            cache: Cache::BARTCache(base_model_output.cache),
+            hidden_states: Some(base_model_output.decoder_output),
+        })
+    }
+
+    fn forward_with_decoder_attention_mask(
+        &self,
+        decoder_input_ids: &Tensor,
+        cache: Cache,
+        attention_mask: Option<&Tensor>,
+        encoder_outputs: &Tensor,
+        decoder_attention_mask: &Tensor,
+        train: bool,
+    ) -> Result<LMModelOutput, RustBertError> {
+        let cached_layer_states = match cache {
+            Cache::BARTCache(cached_layer_states) => cached_layer_states,
+            Cache::None => None,
             _ => {
                 return Err(RustBertError::ValueError(
                     "Cache not compatible with Pegasus Model".into(),
                 ));
             }
         };
+        let base_model_output = self.base_model.forward_t(
+            None,
+            attention_mask,
+            decoder_input_ids,
+            Some(encoder_outputs),
+            Some(decoder_attention_mask),
+            cached_layer_states,
+            train,
+        )?;
 
         let lm_logits = base_model_output
             .decoder_output
@@ -540,6 +581,7 @@ impl LMHeadModel for PegasusForConditionalGeneration {
         Ok(LMModelOutput {
             lm_logits,
             cache: Cache::BARTCache(base_model_output.cache),
+            hidden_states: Some(base_model_output.decoder_output),
         })
     }
 }
@@ -618,14 +660,16 @@ impl PegasusConditionalGenerator {
         tokenizer: TokenizerOption,
     ) -> Result<PegasusConditionalGenerator, RustBertError> {
         let config_path = generate_config.config_resource.get_local_path()?;
-        let weights_path = generate_config.model_resource.get_local_path()?;
         let device = generate_config.device;
 
         generate_config.validate();
         let mut var_store = nn::VarStore::new(device);
-        let config = PegasusConfig::from_file(config_path);
+        let mut config = PegasusConfig::from_file(config_path);
+        if generate_config.output_attentions {
+            config.output_attentions = Some(true);
+        }
         let model = PegasusForConditionalGeneration::new(&var_store.root(), &config);
-        var_store.load(weights_path)?;
+        crate::resources::load_weights(&generate_config.model_resource, &mut var_store)?;
 
         let bos_token_id = Some(config.bos_token_id.unwrap_or(0));
         let eos_token_ids = Some(match config.eos_token_id {
@@ -755,6 +799,7 @@ impl PrivateLanguageGenerator<PegasusForConditionalGeneration, PegasusVocab, Peg
         prompt_text: &[S],
         max_len: i64,
         pad_token_id: Option<i64>,
+        _add_bos_token: bool,
     ) -> Tensor
     where
         S: AsRef<str> + Sync,
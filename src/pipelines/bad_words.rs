@@ -0,0 +1,162 @@
+// Copyright 2022 Guillaume Becquin
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//     http://www.apache.org/licenses/LICENSE-2.0
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! # Surface-level bad words
+//! Bans strings from the generated text, whatever their tokenization. `GenerateOptions::bad_word_ids` only bans
+//! exact sequences of token ids, while a word can be generated with many tokenizations (e.g. `" hello"` as a single
+//! token, `"hello"` after a newline, or `" hel"` followed by `"lo"`). Here, the generated text is decoded at each
+//! step and the tokens whose text would complete a banned string are masked: a token is banned if its text contains
+//! a banned string, or if the generated text ends with the beginning of a banned string and the text of the token
+//! starts with its remainder.
+//!
+//! The matching is done on the text generated so far (excluding the prompt), and is case-sensitive: the whitespace
+//! around the banned strings is significant (ban `" word"` to only ban `word` at the start of a word).
+//!
+//! The bad words are set with `GenerateOptions::bad_words`:
+//!
+//! ```no_run
+//! # fn main() -> anyhow::Result<()> {
+//! use rust_bert::gpt2::GPT2Generator;
+//! use rust_bert::pipelines::generation_utils::{GenerateOptions, LanguageGenerator};
+//!
+//! let model = GPT2Generator::new(Default::default())?;
+//! let generate_options = GenerateOptions {
+//!     bad_words: Some(&["New York", "Boston"]),
+//!     max_new_tokens: Some(32),
+//!     ..Default::default()
+//! };
+//! let output = model.generate(
+//!     Some(&["The largest city of the United States is"]),
+//!     Some(generate_options),
+//! );
+//! # Ok(())
+//! # }
+//! ```
+
+use crate::pipelines::common::TokenizerOption;
+use crate::pipelines::grammar::token_texts;
+use std::collections::HashMap;
+
+/// # Surface-level bad words constraint for the generation
+/// Computes the tokens of a vocabulary that would complete a banned string in the generated text. Created by the
+/// generation when `GenerateOptions::bad_words` is set.
+pub struct SurfaceBadWords {
+    token_texts: HashMap<i64, String>,
+    /// Tokens containing a banned string, banned at every step
+    static_banned_tokens: Vec<i64>,
+    /// Beginnings of the banned strings, with the tokens starting with the remainder of the banned string
+    completions: Vec<(String, Vec<i64>)>,
+}
+
+impl SurfaceBadWords {
+    /// Creates a new `SurfaceBadWords` for the vocabulary of a tokenizer. Special tokens are never banned.
+    ///
+    /// # Arguments
+    ///
+    /// * `bad_words` - Strings that should not appear in the generated text
+    /// * `tokenizer` - `TokenizerOption` of the generation model
+    pub fn new(bad_words: &[&str], tokenizer: &TokenizerOption) -> SurfaceBadWords {
+        SurfaceBadWords::from_tokens(bad_words, token_texts(tokenizer))
+    }
+
+    fn from_tokens(bad_words: &[&str], token_texts: HashMap<i64, String>) -> SurfaceBadWords {
+        let bad_words = bad_words
+            .iter()
+            .copied()
+            .filter(|bad_word| !bad_word.is_empty())
+            .collect::<Vec<&str>>();
+        let tokens_matching = |predicate: &dyn Fn(&str) -> bool| {
+            let mut token_ids = token_texts
+                .iter()
+                .filter(|(_, text)| predicate(text))
+                .map(|(token_id, _)| *token_id)
+                .collect::<Vec<i64>>();
+            token_ids.sort_unstable();
+            token_ids
+        };
+
+        let static_banned_tokens =
+            tokens_matching(&|text: &str| bad_words.iter().any(|bad_word| text.contains(bad_word)));
+        let mut completions = Vec::new();
+        for bad_word in bad_words.iter() {
+            for (split, _) in bad_word.char_indices().skip(1) {
+                let (beginning, remainder) = bad_word.split_at(split);
+                let token_ids = tokens_matching(&|text: &str| text.starts_with(remainder));
+                if !token_ids.is_empty() {
+                    completions.push((beginning.to_string(), token_ids));
+                }
+            }
+        }
+        SurfaceBadWords {
+            token_texts,
+            static_banned_tokens,
+            completions,
+        }
+    }
+
+    /// Returns the tokens banned after the generated tokens. The generated text is the concatenation of the text of
+    /// the tokens (excluding special tokens).
+    ///
+    /// # Arguments
+    ///
+    /// * `generated_ids` - Tokens generated so far (excluding the prompt)
+    pub fn banned_tokens(&self, generated_ids: &[i64]) -> Vec<i64> {
+        let generated_text = generated_ids
+            .iter()
+            .filter_map(|token_id| self.token_texts.get(token_id))
+            .map(String::as_str)
+            .collect::<String>();
+        let mut banned_tokens = self.static_banned_tokens.clone();
+        for (beginning, token_ids) in self.completions.iter() {
+            if generated_text.ends_with(beginning.as_str()) {
+                banned_tokens.extend(token_ids);
+            }
+        }
+        banned_tokens.sort_unstable();
+        banned_tokens.dedup();
+        banned_tokens
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_surface_bad_words() {
+        let tokens = [
+            " New",
+            "New",
+            " York",
+            " Yorkshire",
+            " Y",
+            "ork",
+            " city",
+            "Boston!",
+            "<eos>",
+        ]
+        .iter()
+        .enumerate()
+        .filter(|(_, text)| !text.starts_with('<'))
+        .map(|(token_id, text)| (token_id as i64, text.to_string()))
+        .collect();
+        let bad_words = SurfaceBadWords::from_tokens(&["New York", "Boston", ""], tokens);
+        // Tokens containing a banned string are always banned
+        assert_eq!(bad_words.banned_tokens(&[]), vec![7]);
+        assert_eq!(bad_words.banned_tokens(&[6]), vec![7]);
+        // All tokenizations of the banned string are blocked
+        assert_eq!(bad_words.banned_tokens(&[0]), vec![2, 3, 7]);
+        assert_eq!(bad_words.banned_tokens(&[6, 1]), vec![2, 3, 7]);
+        assert_eq!(bad_words.banned_tokens(&[0, 8]), vec![2, 3, 7]);
+        assert_eq!(bad_words.banned_tokens(&[0, 4]), vec![5, 7]);
+        assert_eq!(bad_words.banned_tokens(&[0, 6]), vec![7]);
+    }
+}
@@ -0,0 +1,520 @@
+// Copyright 2022 Guillaume Becquin
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//     http://www.apache.org/licenses/LICENSE-2.0
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! # Batch processing of large corpora
+//! `BatchJob` runs a pipeline over an iterator of inputs (e.g. the lines of a corpus read lazily), processing them
+//! in chunks and returning the outputs incrementally, as they are computed. The inputs are never fully loaded in
+//! memory, and the outputs can be written out or aggregated as the job progresses.
+//!
+//! An optional progress callback is called after each chunk with a `BatchProgress` (number of inputs processed,
+//! throughput, estimated remaining time if the number of inputs is known).
+//!
+//! When a checkpoint file is provided, the outputs are appended to it (one JSON line per input) after each chunk.
+//! If the job is interrupted (crash, preemption...), running it again with the same inputs and checkpoint file
+//! resumes after the last completed chunk: the outputs stored in the checkpoint are returned first, flagged with
+//! `BatchOutput::from_checkpoint`, and the corresponding inputs are skipped without running the pipeline.
+//!
+//! ```no_run
+//! # fn main() -> anyhow::Result<()> {
+//! use rust_bert::pipelines::batch::{BatchConfig, BatchJob};
+//! use rust_bert::pipelines::sentiment::SentimentModel;
+//! use std::fs::File;
+//! use std::io::{BufRead, BufReader};
+//!
+//! let model = SentimentModel::new(Default::default())?;
+//! let inputs = BufReader::new(File::open("path/to/corpus.txt")?)
+//!     .lines()
+//!     .map(|line| line.unwrap());
+//!
+//! let config = BatchConfig::new(64, Some("path/to/checkpoint.jsonl".into()));
+//! let job = BatchJob::new(&model, config)?.with_progress_callback(|progress| {
+//!     println!("{} documents processed", progress.processed)
+//! });
+//! for output in job.run(inputs)? {
+//!     let output = output?;
+//!     println!("{}: {:?}", output.index, output.output);
+//! }
+//! # Ok(())
+//! # }
+//! ```
+//!
+//! The checkpoint is only valid for the same sequence of inputs: the inputs are skipped by position, and are not
+//! compared with the inputs of the interrupted job.
+
+use crate::common::error::RustBertError;
+use crate::pipelines::common::Pipeline;
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Serialize};
+use std::collections::VecDeque;
+use std::fs::{self, File, OpenOptions};
+use std::io::{BufWriter, Write};
+use std::path::{Path, PathBuf};
+use std::time::{Duration, Instant};
+
+/// # Configuration for a batch job
+#[derive(Debug, Clone)]
+pub struct BatchConfig {
+    /// Number of inputs passed to the pipeline at once
+    pub chunk_size: usize,
+    /// Optional JSON lines file storing the outputs, used to resume interrupted jobs
+    pub checkpoint_path: Option<PathBuf>,
+}
+
+impl BatchConfig {
+    /// Instantiate a new batch job configuration
+    ///
+    /// # Arguments
+    ///
+    /// * `chunk_size` - Number of inputs passed to the pipeline at once
+    /// * `checkpoint_path` - Optional JSON lines file storing the outputs, used to resume interrupted jobs
+    pub fn new(chunk_size: usize, checkpoint_path: Option<PathBuf>) -> BatchConfig {
+        BatchConfig {
+            chunk_size,
+            checkpoint_path,
+        }
+    }
+}
+
+impl Default for BatchConfig {
+    fn default() -> BatchConfig {
+        BatchConfig::new(32, None)
+    }
+}
+
+/// # Progress of a batch job
+#[derive(Debug, Clone, PartialEq)]
+pub struct BatchProgress {
+    /// Number of inputs processed, including the inputs restored from the checkpoint
+    pub processed: usize,
+    /// Number of inputs restored from the checkpoint
+    pub resumed: usize,
+    /// Total number of inputs, if known from the input iterator
+    pub total: Option<usize>,
+    /// Number of chunks processed since the start (or resumption) of the job
+    pub chunks: usize,
+    /// Time elapsed since the start (or resumption) of the job
+    pub elapsed: Duration,
+}
+
+impl BatchProgress {
+    /// Returns the fraction of the inputs processed, if the total number of inputs is known
+    pub fn fraction(&self) -> Option<f64> {
+        self.total.map(|total| {
+            if total == 0 {
+                1.0
+            } else {
+                self.processed as f64 / total as f64
+            }
+        })
+    }
+
+    /// Returns the number of inputs processed per second since the start (or resumption) of the job
+    pub fn throughput(&self) -> f64 {
+        let seconds = self.elapsed.as_secs_f64();
+        if seconds > 0.0 {
+            (self.processed - self.resumed) as f64 / seconds
+        } else {
+            0.0
+        }
+    }
+
+    /// Returns the estimated time until all inputs are processed, if the total number of inputs is known and
+    /// at least one input was processed
+    pub fn remaining_time(&self) -> Option<Duration> {
+        let throughput = self.throughput();
+        match self.total {
+            Some(total) if throughput > 0.0 => Some(Duration::from_secs_f64(
+                total.saturating_sub(self.processed) as f64 / throughput,
+            )),
+            _ => None,
+        }
+    }
+}
+
+/// # Output of a batch job for an input
+#[derive(Debug, Clone, PartialEq)]
+pub struct BatchOutput<O> {
+    /// Position of the input in the input iterator
+    pub index: usize,
+    /// Output of the pipeline
+    pub output: O,
+    /// True if the output was restored from the checkpoint file rather than computed
+    pub from_checkpoint: bool,
+}
+
+#[derive(Serialize, Deserialize)]
+struct CheckpointEntry<O> {
+    index: usize,
+    output: O,
+}
+
+/// Reads the outputs stored in a checkpoint file and truncates the file after the last complete entry (an entry
+/// may have been partially written if the job was interrupted)
+fn restore_checkpoint<O>(path: &Path) -> Result<Vec<O>, RustBertError>
+where
+    O: DeserializeOwned,
+{
+    if !path.exists() {
+        return Ok(vec![]);
+    }
+    let content = fs::read(path)?;
+    let mut outputs = vec![];
+    let mut valid_length = 0;
+    for line in content.split_inclusive(|byte| *byte == b'\n') {
+        if !line.ends_with(b"\n") {
+            break;
+        }
+        let entry = match serde_json::from_slice::<CheckpointEntry<O>>(line) {
+            Ok(entry) => entry,
+            Err(_) => break,
+        };
+        if entry.index != outputs.len() {
+            return Err(RustBertError::ValueError(format!(
+                "Invalid checkpoint {}: expected the output of input {}, got input {}",
+                path.display(),
+                outputs.len(),
+                entry.index
+            )));
+        }
+        outputs.push(entry.output);
+        valid_length += line.len();
+    }
+    if valid_length < content.len() {
+        OpenOptions::new()
+            .write(true)
+            .open(path)?
+            .set_len(valid_length as u64)?;
+    }
+    Ok(outputs)
+}
+
+/// # Batch job running a pipeline over an iterator of inputs
+pub struct BatchJob<'a, P> {
+    pipeline: &'a P,
+    config: BatchConfig,
+    progress_callback: Option<Box<dyn FnMut(&BatchProgress) + 'a>>,
+}
+
+impl<'a, P> BatchJob<'a, P> {
+    /// Create a new batch job
+    ///
+    /// # Arguments
+    ///
+    /// * `pipeline` - Pipeline to run on the inputs
+    /// * `config` - `BatchConfig` with the chunk size and optional checkpoint file
+    pub fn new(pipeline: &'a P, config: BatchConfig) -> Result<BatchJob<'a, P>, RustBertError> {
+        if config.chunk_size == 0 {
+            return Err(RustBertError::InvalidConfigurationError(
+                "The chunk size of a batch job must be at least 1".to_string(),
+            ));
+        }
+        Ok(BatchJob {
+            pipeline,
+            config,
+            progress_callback: None,
+        })
+    }
+
+    /// Sets a callback called with the progress of the job after each chunk
+    ///
+    /// # Arguments
+    ///
+    /// * `callback` - Function called with the `BatchProgress` of the job
+    pub fn with_progress_callback<F>(mut self, callback: F) -> BatchJob<'a, P>
+    where
+        F: FnMut(&BatchProgress) + 'a,
+    {
+        self.progress_callback = Some(Box::new(callback));
+        self
+    }
+
+    /// Starts the job, restoring the outputs stored in the checkpoint file if any. The inputs are processed lazily,
+    /// as the returned iterator is consumed.
+    ///
+    /// # Arguments
+    ///
+    /// * `inputs` - Inputs to process
+    ///
+    /// # Returns
+    ///
+    /// * `BatchIterator` returning the `BatchOutput` of each input in order, or the error of the chunk that failed.
+    /// The iteration stops after an error.
+    pub fn run<I, O>(self, inputs: I) -> Result<BatchIterator<'a, P, I::IntoIter, O>, RustBertError>
+    where
+        I: IntoIterator,
+        P: Pipeline<I::Item, O>,
+        O: Serialize + DeserializeOwned,
+    {
+        let mut inputs = inputs.into_iter();
+        let total = match inputs.size_hint() {
+            (lower, Some(upper)) if lower == upper => Some(lower),
+            _ => None,
+        };
+
+        let mut buffer = VecDeque::new();
+        let checkpoint = match &self.config.checkpoint_path {
+            Some(path) => {
+                let restored = restore_checkpoint::<O>(path)?;
+                let skipped = inputs.by_ref().take(restored.len()).count();
+                if skipped < restored.len() {
+                    return Err(RustBertError::ValueError(format!(
+                        "The checkpoint {} contains {} outputs for {} inputs",
+                        path.display(),
+                        restored.len(),
+                        skipped
+                    )));
+                }
+                buffer.extend(restored.into_iter().enumerate().map(|(index, output)| {
+                    BatchOutput {
+                        index,
+                        output,
+                        from_checkpoint: true,
+                    }
+                }));
+                let file = OpenOptions::new().create(true).append(true).open(path)?;
+                Some(BufWriter::new(file))
+            }
+            None => None,
+        };
+
+        let resumed = buffer.len();
+        Ok(BatchIterator {
+            pipeline: self.pipeline,
+            inputs,
+            chunk_size: self.config.chunk_size,
+            checkpoint,
+            progress_callback: self.progress_callback,
+            progress: BatchProgress {
+                processed: resumed,
+                resumed,
+                total,
+                chunks: 0,
+                elapsed: Duration::from_secs(0),
+            },
+            start: Instant::now(),
+            buffer,
+            finished: false,
+        })
+    }
+}
+
+/// # Iterator over the outputs of a batch job
+/// Processes the next chunk of inputs when the outputs of the previous chunk have been consumed.
+pub struct BatchIterator<'a, P, I, O> {
+    pipeline: &'a P,
+    inputs: I,
+    chunk_size: usize,
+    checkpoint: Option<BufWriter<File>>,
+    progress_callback: Option<Box<dyn FnMut(&BatchProgress) + 'a>>,
+    progress: BatchProgress,
+    start: Instant,
+    buffer: VecDeque<BatchOutput<O>>,
+    finished: bool,
+}
+
+impl<'a, P, I, O> BatchIterator<'a, P, I, O>
+where
+    I: Iterator,
+    P: Pipeline<I::Item, O>,
+    O: Serialize,
+{
+    /// Returns the progress of the job
+    pub fn progress(&self) -> &BatchProgress {
+        &self.progress
+    }
+
+    /// Processes the next chunk of inputs, returning false once all inputs are processed
+    fn process_chunk(&mut self) -> Result<bool, RustBertError> {
+        let chunk = self
+            .inputs
+            .by_ref()
+            .take(self.chunk_size)
+            .collect::<Vec<I::Item>>();
+        if chunk.is_empty() {
+            return Ok(false);
+        }
+        let outputs = self.pipeline.run(&chunk)?;
+        if outputs.len() != chunk.len() {
+            return Err(RustBertError::ValueError(format!(
+                "Got {} outputs for {} inputs",
+                outputs.len(),
+                chunk.len()
+            )));
+        }
+
+        let first_index = self.progress.processed;
+        if let Some(checkpoint) = &mut self.checkpoint {
+            for (offset, output) in outputs.iter().enumerate() {
+                let entry = serde_json::to_string(&CheckpointEntry {
+                    index: first_index + offset,
+                    output,
+                })
+                .map_err(|error| RustBertError::ValueError(error.to_string()))?;
+                writeln!(checkpoint, "{}", entry)?;
+            }
+            checkpoint.flush()?;
+            checkpoint.get_ref().sync_data()?;
+        }
+
+        self.buffer.extend(
+            outputs
+                .into_iter()
+                .enumerate()
+                .map(|(offset, output)| BatchOutput {
+                    index: first_index + offset,
+                    output,
+                    from_checkpoint: false,
+                }),
+        );
+        self.progress.processed += chunk.len();
+        self.progress.chunks += 1;
+        self.progress.elapsed = self.start.elapsed();
+        if let Some(callback) = &mut self.progress_callback {
+            callback(&self.progress);
+        }
+        Ok(true)
+    }
+}
+
+impl<'a, P, I, O> Iterator for BatchIterator<'a, P, I, O>
+where
+    I: Iterator,
+    P: Pipeline<I::Item, O>,
+    O: Serialize,
+{
+    type Item = Result<BatchOutput<O>, RustBertError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            if let Some(output) = self.buffer.pop_front() {
+                return Some(Ok(output));
+            }
+            if self.finished {
+                return None;
+            }
+            match self.process_chunk() {
+                Ok(true) => {}
+                Ok(false) => self.finished = true,
+                Err(error) => {
+                    self.finished = true;
+                    return Some(Err(error));
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use std::cell::Cell;
+
+    struct UppercasePipeline {
+        inputs: Cell<usize>,
+    }
+
+    impl UppercasePipeline {
+        fn new() -> UppercasePipeline {
+            UppercasePipeline {
+                inputs: Cell::new(0),
+            }
+        }
+    }
+
+    impl<S: AsRef<str>> Pipeline<S, String> for UppercasePipeline {
+        type Options = ();
+
+        fn run_with_options(
+            &self,
+            inputs: &[S],
+            _options: &(),
+        ) -> Result<Vec<String>, RustBertError> {
+            self.inputs.set(self.inputs.get() + inputs.len());
+            Ok(inputs
+                .iter()
+                .map(|input| input.as_ref().to_uppercase())
+                .collect())
+        }
+    }
+
+    fn inputs() -> Vec<String> {
+        vec!["a", "b", "c", "d", "e"]
+            .into_iter()
+            .map(String::from)
+            .collect()
+    }
+
+    #[test]
+    fn processes_chunks_with_progress() -> Result<(), RustBertError> {
+        let pipeline = UppercasePipeline::new();
+        let mut reported = vec![];
+        let outputs = BatchJob::new(&pipeline, BatchConfig::new(2, None))?
+            .with_progress_callback(|progress| reported.push((progress.processed, progress.chunks)))
+            .run(inputs())?
+            .map(|output| output.map(|output| output.output))
+            .collect::<Result<Vec<String>, RustBertError>>()?;
+
+        assert_eq!(outputs, vec!["A", "B", "C", "D", "E"]);
+        assert_eq!(reported, vec![(2, 1), (4, 2), (5, 3)]);
+        Ok(())
+    }
+
+    #[test]
+    fn resumes_from_checkpoint() -> Result<(), RustBertError> {
+        let directory = tempfile::tempdir()?;
+        let checkpoint_path = directory.path().join("checkpoint.jsonl");
+        let config = BatchConfig::new(2, Some(checkpoint_path.clone()));
+
+        // Interrupted after the first 2 chunks
+        let pipeline = UppercasePipeline::new();
+        let partial = BatchJob::new(&pipeline, config.clone())?
+            .run(inputs())?
+            .take(3)
+            .collect::<Result<Vec<BatchOutput<String>>, RustBertError>>()?;
+        assert_eq!(partial.len(), 3);
+        assert_eq!(pipeline.inputs.get(), 4);
+        // Partially written entry
+        let mut file = OpenOptions::new().append(true).open(&checkpoint_path)?;
+        write!(file, "{{\"index\":4,\"out")?;
+
+        let pipeline = UppercasePipeline::new();
+        let outputs = BatchJob::new(&pipeline, config)?
+            .run(inputs())?
+            .collect::<Result<Vec<BatchOutput<String>>, RustBertError>>()?;
+        assert_eq!(pipeline.inputs.get(), 1);
+        assert_eq!(outputs.len(), 5);
+        assert!(outputs[..4].iter().all(|output| output.from_checkpoint));
+        assert_eq!(
+            outputs[4],
+            BatchOutput {
+                index: 4,
+                output: "E".to_string(),
+                from_checkpoint: false
+            }
+        );
+        assert_eq!(restore_checkpoint::<String>(&checkpoint_path)?.len(), 5);
+        Ok(())
+    }
+
+    #[test]
+    fn progress_estimates() {
+        let progress = BatchProgress {
+            processed: 30,
+            resumed: 10,
+            total: Some(50),
+            chunks: 2,
+            elapsed: Duration::from_secs(10),
+        };
+        assert_eq!(progress.fraction(), Some(0.6));
+        assert_eq!(progress.throughput(), 2.0);
+        assert_eq!(progress.remaining_time(), Some(Duration::from_secs(10)));
+    }
+}
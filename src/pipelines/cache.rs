@@ -0,0 +1,458 @@
+// Copyright 2022 Guillaume Becquin
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//     http://www.apache.org/licenses/LICENSE-2.0
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! # Result caching for generation pipelines (requires the `cache` feature)
+//! Caches the outputs of expensive pipelines (summarization, translation, text generation...) so that re-processing
+//! unchanged documents, for instance in periodic batch jobs, does not run the model again. Entries are keyed by
+//! the model identifier, a hash of the generation options and a hash of the input: changing the model or the
+//! options invalidates the cached outputs. Hashes are stable across runs and platforms (128-bit FNV-1a), and the
+//! input is stored with the output and compared on lookup.
+//!
+//! `FileCache` persists the entries as JSON files in a directory (written atomically, so that several processes
+//! can share a cache), `MemoryCache` keeps them in memory. Other stores (e.g. a key-value database) can be used
+//! by implementing `CacheStore`.
+//!
+//! ```no_run
+//! # fn main() -> anyhow::Result<()> {
+//! use rust_bert::pipelines::cache::{CachedPipeline, FileCache, GenerationCache};
+//! use rust_bert::pipelines::common::Pipeline;
+//! use rust_bert::pipelines::summarization::SummarizationModel;
+//!
+//! let model = SummarizationModel::new(Default::default())?;
+//! let cache = GenerationCache::new(
+//!     FileCache::new("path/to/cache")?,
+//!     "facebook/bart-large-cnn",
+//!     "num_beams=3,max_length=142",
+//! );
+//! let summarizer = CachedPipeline::new(model, cache);
+//!
+//! let documents = ["In findings published Tuesday in Cornell University's arXiv..."];
+//! let summaries: Vec<String> = summarizer.run(&documents)?;
+//! // Served from the cache
+//! let summaries: Vec<String> = summarizer.run(&documents)?;
+//! # Ok(())
+//! # }
+//! ```
+//!
+//! Pipelines taking additional arguments (e.g. the languages of a translation) can be cached with
+//! `GenerationCache::get_or_compute`:
+//! ```no_run
+//! # fn main() -> anyhow::Result<()> {
+//! # use rust_bert::pipelines::cache::{GenerationCache, MemoryCache};
+//! use rust_bert::pipelines::translation::{Language, TranslationModelBuilder};
+//!
+//! let model = TranslationModelBuilder::new().create_model()?;
+//! let cache = GenerationCache::new(MemoryCache::new(), "marian-en-fr", "");
+//! let translations: Vec<String> = cache.get_or_compute(&["Hello world!"], |missing| {
+//!     model.translate(missing, Language::English, Language::French)
+//! })?;
+//! # Ok(())
+//! # }
+//! ```
+
+use crate::common::error::RustBertError;
+use crate::pipelines::common::Pipeline;
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::RwLock;
+use uuid::Uuid;
+
+/// 128-bit FNV-1a hash of a sequence of fields, as a hexadecimal string (the standard library hasher is not
+/// stable across releases)
+fn stable_hash(fields: &[&str]) -> String {
+    let mut hash: u128 = 0x6c62_272e_07bb_0142_62b8_2175_6295_c58d;
+    let prime: u128 = 0x0000_0000_0100_0000_0000_0000_0000_013b;
+    for field in fields {
+        for byte in field.as_bytes() {
+            hash ^= *byte as u128;
+            hash = hash.wrapping_mul(prime);
+        }
+        // Separator, so that the concatenation of fields is unambiguous
+        hash ^= 0xff;
+        hash = hash.wrapping_mul(prime);
+    }
+    format!("{:032x}", hash)
+}
+
+/// # Key of a cached output
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct CacheKey {
+    /// Identifier of the model producing the output
+    pub model_id: String,
+    /// Hash of the options of the pipeline
+    pub options_hash: String,
+    /// Hash of the input
+    pub input_hash: String,
+}
+
+/// # Storage of cached outputs
+pub trait CacheStore: Send + Sync {
+    /// Returns the entry stored for a key, if any
+    fn get(&self, key: &CacheKey) -> Result<Option<String>, RustBertError>;
+
+    /// Stores an entry, replacing any previous entry for the key
+    fn insert(&self, key: &CacheKey, entry: &str) -> Result<(), RustBertError>;
+}
+
+/// # In-memory cache store
+#[derive(Debug, Default)]
+pub struct MemoryCache {
+    entries: RwLock<HashMap<CacheKey, String>>,
+}
+
+impl MemoryCache {
+    /// Creates a new, empty, in-memory cache store
+    pub fn new() -> MemoryCache {
+        MemoryCache::default()
+    }
+
+    /// Returns the number of entries stored
+    pub fn len(&self) -> usize {
+        self.entries
+            .read()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+            .len()
+    }
+
+    /// Returns true if no entry is stored
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+}
+
+impl CacheStore for MemoryCache {
+    fn get(&self, key: &CacheKey) -> Result<Option<String>, RustBertError> {
+        Ok(self
+            .entries
+            .read()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+            .get(key)
+            .cloned())
+    }
+
+    fn insert(&self, key: &CacheKey, entry: &str) -> Result<(), RustBertError> {
+        self.entries
+            .write()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+            .insert(key.clone(), entry.to_string());
+        Ok(())
+    }
+}
+
+/// # File-based cache store
+/// Entries are stored as `<directory>/<model id>/<options hash>/<input hash>.json` files. Files are written to a
+/// temporary file first and renamed, so that readers (possibly in other processes) never observe partial entries.
+#[derive(Debug, Clone)]
+pub struct FileCache {
+    directory: PathBuf,
+}
+
+impl FileCache {
+    /// Creates a file cache store in a directory, creating the directory if needed
+    ///
+    /// # Arguments
+    ///
+    /// * `directory` - Root directory of the cache
+    pub fn new<P: AsRef<Path>>(directory: P) -> Result<FileCache, RustBertError> {
+        fs::create_dir_all(directory.as_ref())?;
+        Ok(FileCache {
+            directory: directory.as_ref().to_path_buf(),
+        })
+    }
+
+    fn entry_path(&self, key: &CacheKey) -> PathBuf {
+        let model_directory = key
+            .model_id
+            .chars()
+            .map(|c| {
+                if c.is_ascii_alphanumeric() || c == '-' || c == '_' {
+                    c
+                } else {
+                    '_'
+                }
+            })
+            .collect::<String>();
+        self.directory
+            .join(model_directory)
+            .join(&key.options_hash)
+            .join(format!("{}.json", key.input_hash))
+    }
+}
+
+impl CacheStore for FileCache {
+    fn get(&self, key: &CacheKey) -> Result<Option<String>, RustBertError> {
+        match fs::read_to_string(self.entry_path(key)) {
+            Ok(entry) => Ok(Some(entry)),
+            Err(error) if error.kind() == std::io::ErrorKind::NotFound => Ok(None),
+            Err(error) => Err(error.into()),
+        }
+    }
+
+    fn insert(&self, key: &CacheKey, entry: &str) -> Result<(), RustBertError> {
+        let path = self.entry_path(key);
+        let directory = path.parent().unwrap();
+        fs::create_dir_all(directory)?;
+        let temporary_path = directory.join(format!("{}.tmp", Uuid::new_v4()));
+        fs::write(&temporary_path, entry)?;
+        if let Err(error) = fs::rename(&temporary_path, &path) {
+            let _ = fs::remove_file(&temporary_path);
+            return Err(error.into());
+        }
+        Ok(())
+    }
+}
+
+#[derive(Serialize, Deserialize)]
+struct CacheEntry<O> {
+    input: String,
+    output: O,
+}
+
+/// # Cache of pipeline outputs for a model and set of options
+pub struct GenerationCache {
+    store: Box<dyn CacheStore>,
+    model_id: String,
+    options_hash: String,
+    hits: AtomicUsize,
+    misses: AtomicUsize,
+}
+
+impl GenerationCache {
+    /// Creates a new cache
+    ///
+    /// # Arguments
+    ///
+    /// * `store` - `CacheStore` holding the entries
+    /// * `model_id` - Identifier of the model (e.g. name and revision of the pretrained model)
+    /// * `options` - Description of the options affecting the outputs (e.g. decoding settings). Its hash is part of
+    /// the keys: any change of the options invalidates the cached outputs.
+    pub fn new<C>(store: C, model_id: &str, options: &str) -> GenerationCache
+    where
+        C: CacheStore + 'static,
+    {
+        GenerationCache {
+            store: Box::new(store),
+            model_id: model_id.to_string(),
+            options_hash: stable_hash(&[options]),
+            hits: AtomicUsize::new(0),
+            misses: AtomicUsize::new(0),
+        }
+    }
+
+    /// Returns the key of the output for an input
+    pub fn key(&self, input: &str) -> CacheKey {
+        CacheKey {
+            model_id: self.model_id.clone(),
+            options_hash: self.options_hash.clone(),
+            input_hash: stable_hash(&[input]),
+        }
+    }
+
+    /// Returns the number of outputs served from the cache
+    pub fn hits(&self) -> usize {
+        self.hits.load(Ordering::Relaxed)
+    }
+
+    /// Returns the number of outputs that had to be computed
+    pub fn misses(&self) -> usize {
+        self.misses.load(Ordering::Relaxed)
+    }
+
+    /// Returns the cached outputs for the inputs, computing (and caching) the missing ones
+    ///
+    /// # Arguments
+    ///
+    /// * `inputs` - Inputs to process
+    /// * `compute` - Function computing the outputs of the inputs missing from the cache, in order. It is called
+    /// once, with each distinct missing input, and not called if all outputs are cached.
+    ///
+    /// # Returns
+    ///
+    /// * `Vec<O>` Outputs for the inputs
+    pub fn get_or_compute<S, O, F>(&self, inputs: &[S], compute: F) -> Result<Vec<O>, RustBertError>
+    where
+        S: AsRef<str>,
+        O: Serialize + DeserializeOwned + Clone,
+        F: FnOnce(&[&str]) -> Result<Vec<O>, RustBertError>,
+    {
+        let mut outputs: Vec<Option<O>> = Vec::with_capacity(inputs.len());
+        let mut missing_inputs: Vec<&str> = Vec::new();
+        let mut missing_keys: Vec<CacheKey> = Vec::new();
+        let mut missing_positions: HashMap<CacheKey, usize> = HashMap::new();
+        let mut missing_indices: Vec<(usize, usize)> = Vec::new();
+        for (index, input) in inputs.iter().enumerate() {
+            let input = input.as_ref();
+            let key = self.key(input);
+            let cached = match self.store.get(&key)? {
+                Some(entry) => serde_json::from_str::<CacheEntry<O>>(&entry)
+                    .ok()
+                    .filter(|entry| entry.input == input)
+                    .map(|entry| entry.output),
+                None => None,
+            };
+            if cached.is_none() {
+                let position = *missing_positions.entry(key.clone()).or_insert_with(|| {
+                    missing_inputs.push(input);
+                    missing_keys.push(key);
+                    missing_inputs.len() - 1
+                });
+                missing_indices.push((index, position));
+            }
+            outputs.push(cached);
+        }
+        self.hits
+            .fetch_add(inputs.len() - missing_indices.len(), Ordering::Relaxed);
+        self.misses
+            .fetch_add(missing_inputs.len(), Ordering::Relaxed);
+
+        if !missing_inputs.is_empty() {
+            let computed = compute(&missing_inputs)?;
+            if computed.len() != missing_inputs.len() {
+                return Err(RustBertError::ValueError(format!(
+                    "Got {} outputs for {} inputs",
+                    computed.len(),
+                    missing_inputs.len()
+                )));
+            }
+            for ((input, key), output) in missing_inputs
+                .iter()
+                .zip(missing_keys.iter())
+                .zip(computed.iter())
+            {
+                let entry = serde_json::to_string(&CacheEntry {
+                    input: input.to_string(),
+                    output,
+                })
+                .map_err(|error| RustBertError::ValueError(error.to_string()))?;
+                self.store.insert(key, &entry)?;
+            }
+            for (index, position) in missing_indices {
+                outputs[index] = Some(computed[position].clone());
+            }
+        }
+        Ok(outputs.into_iter().map(|output| output.unwrap()).collect())
+    }
+}
+
+/// # Pipeline wrapper serving cached outputs
+/// The wrapped pipeline is run with its default options. Pipelines called with other options (e.g. the languages of
+/// a translation) can be cached with `GenerationCache::get_or_compute`.
+pub struct CachedPipeline<P> {
+    pipeline: P,
+    cache: GenerationCache,
+}
+
+impl<P> CachedPipeline<P> {
+    /// Wraps a pipeline with a cache
+    ///
+    /// # Arguments
+    ///
+    /// * `pipeline` - Pipeline computing the outputs missing from the cache
+    /// * `cache` - `GenerationCache` for the model and options of the pipeline
+    pub fn new(pipeline: P, cache: GenerationCache) -> CachedPipeline<P> {
+        CachedPipeline { pipeline, cache }
+    }
+
+    /// Returns the wrapped pipeline
+    pub fn pipeline(&self) -> &P {
+        &self.pipeline
+    }
+
+    /// Returns the cache
+    pub fn cache(&self) -> &GenerationCache {
+        &self.cache
+    }
+}
+
+impl<S, O, P> Pipeline<S, O> for CachedPipeline<P>
+where
+    S: AsRef<str>,
+    O: Serialize + DeserializeOwned + Clone,
+    P: for<'a> Pipeline<&'a str, O>,
+{
+    type Options = ();
+
+    fn run_with_options(&self, inputs: &[S], _options: &()) -> Result<Vec<O>, RustBertError> {
+        self.cache
+            .get_or_compute(inputs, |missing| self.pipeline.run(missing))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    struct CountingPipeline {
+        calls: AtomicUsize,
+    }
+
+    impl Pipeline<&str, String> for CountingPipeline {
+        type Options = ();
+
+        fn run_with_options(
+            &self,
+            inputs: &[&str],
+            _options: &(),
+        ) -> Result<Vec<String>, RustBertError> {
+            self.calls.fetch_add(inputs.len(), Ordering::Relaxed);
+            Ok(inputs.iter().map(|input| input.to_uppercase()).collect())
+        }
+    }
+
+    #[test]
+    fn outputs_served_from_cache() {
+        let pipeline = CachedPipeline::new(
+            CountingPipeline {
+                calls: AtomicUsize::new(0),
+            },
+            GenerationCache::new(MemoryCache::new(), "model", "options"),
+        );
+        let outputs: Vec<String> = pipeline.run(&["a", "b", "a"]).unwrap();
+        assert_eq!(outputs, vec!["A", "B", "A"]);
+        assert_eq!(pipeline.pipeline().calls.load(Ordering::Relaxed), 2);
+
+        let outputs: Vec<String> = pipeline.run(&["b", "c"]).unwrap();
+        assert_eq!(outputs, vec!["B", "C"]);
+        assert_eq!(pipeline.pipeline().calls.load(Ordering::Relaxed), 3);
+        assert_eq!(pipeline.cache().hits(), 2);
+        assert_eq!(pipeline.cache().misses(), 3);
+    }
+
+    #[test]
+    fn keys_depend_on_model_and_options() {
+        let cache = GenerationCache::new(MemoryCache::new(), "model", "options");
+        let other_options = GenerationCache::new(MemoryCache::new(), "model", "other options");
+        assert_eq!(cache.key("input"), cache.key("input"));
+        assert_ne!(cache.key("input"), cache.key("other input"));
+        assert_ne!(cache.key("input"), other_options.key("input"));
+        assert_eq!(stable_hash(&["ab", "c"]).len(), 32);
+        assert_ne!(stable_hash(&["ab", "c"]), stable_hash(&["a", "bc"]));
+    }
+
+    #[test]
+    fn file_cache_persists_entries() -> Result<(), RustBertError> {
+        let directory = tempfile::tempdir()?;
+        let key = GenerationCache::new(MemoryCache::new(), "org/model", "").key("input");
+        {
+            let store = FileCache::new(directory.path())?;
+            assert_eq!(store.get(&key)?, None);
+            store.insert(&key, "entry")?;
+        }
+        let store = FileCache::new(directory.path())?;
+        assert_eq!(store.get(&key)?, Some("entry".to_string()));
+        assert!(directory.path().join("org_model").is_dir());
+        Ok(())
+    }
+}
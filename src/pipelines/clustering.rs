@@ -0,0 +1,493 @@
+// Copyright 2022 Guillaume Becquin
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//     http://www.apache.org/licenses/LICENSE-2.0
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! # Clustering of embeddings
+//! Groups embeddings (e.g. the output of a `SentenceEmbeddingsModel`) in clusters, with:
+//! - k-means: k-means++ initialization drawn from a seeded generator (the same seed always gives the same clusters),
+//! followed by Lloyd iterations on the euclidean distance.
+//! - agglomerative clustering: hierarchical clustering on the cosine distance, merging the closest clusters until the
+//! requested number of clusters is reached. The distance between clusters is given by the `Linkage`.
+//!
+//! The quality of a clustering can be measured with the silhouette score, from -1 (samples closer to other clusters
+//! than to their own) to 1 (compact and well separated clusters).
+//!
+//! ```no_run
+//! # fn main() -> anyhow::Result<()> {
+//! use rust_bert::pipelines::clustering::{
+//!     cluster_embeddings, silhouette_score, ClusteringAlgorithm, DistanceMetric, Linkage,
+//! };
+//! use rust_bert::pipelines::sentence_embeddings::{
+//!     SentenceEmbeddingsBuilder, SentenceEmbeddingsModelType,
+//! };
+//!
+//! let model = SentenceEmbeddingsBuilder::remote(SentenceEmbeddingsModelType::AllMiniLmL12V2)
+//!     .create_model()?;
+//! let embeddings = model.encode(&[
+//!     "The striker scored twice in the final.",
+//!     "The goalkeeper saved a penalty.",
+//!     "The central bank raised interest rates.",
+//!     "Inflation slowed down.",
+//! ])?;
+//!
+//! let assignments = cluster_embeddings(&embeddings, &ClusteringAlgorithm::kmeans(2, 42))?;
+//! let score = silhouette_score(&embeddings, &assignments, DistanceMetric::Euclidean)?;
+//!
+//! let assignments = cluster_embeddings(
+//!     &embeddings,
+//!     &ClusteringAlgorithm::agglomerative(2, Linkage::Average),
+//! )?;
+//! let score = silhouette_score(&embeddings, &assignments, DistanceMetric::Cosine)?;
+//! # Ok(())
+//! # }
+//! ```
+
+use crate::common::error::RustBertError;
+use crate::pipelines::sentence_embeddings::Embedding;
+use serde::{Deserialize, Serialize};
+use tch::{Kind, Tensor};
+
+/// # Distance between embeddings
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum DistanceMetric {
+    /// Euclidean distance
+    Euclidean,
+    /// Cosine distance (1 - cosine similarity)
+    Cosine,
+}
+
+/// # Distance between two clusters for agglomerative clustering
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum Linkage {
+    /// Distance of the closest members of the clusters
+    Single,
+    /// Distance of the farthest members of the clusters
+    Complete,
+    /// Average distance between the members of the clusters
+    Average,
+}
+
+/// # Clustering algorithm
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub enum ClusteringAlgorithm {
+    /// K-means with a k-means++ initialization (euclidean distance)
+    KMeans {
+        num_clusters: usize,
+        max_iterations: usize,
+        seed: u64,
+    },
+    /// Agglomerative clustering (cosine distance)
+    Agglomerative {
+        num_clusters: usize,
+        linkage: Linkage,
+    },
+}
+
+impl ClusteringAlgorithm {
+    /// K-means with up to 100 iterations
+    ///
+    /// # Arguments
+    ///
+    /// * `num_clusters` - Number of clusters
+    /// * `seed` - Seed of the k-means++ initialization
+    pub fn kmeans(num_clusters: usize, seed: u64) -> ClusteringAlgorithm {
+        ClusteringAlgorithm::KMeans {
+            num_clusters,
+            max_iterations: 100,
+            seed,
+        }
+    }
+
+    /// Agglomerative clustering
+    ///
+    /// # Arguments
+    ///
+    /// * `num_clusters` - Number of clusters
+    /// * `linkage` - `Linkage` defining the distance between clusters
+    pub fn agglomerative(num_clusters: usize, linkage: Linkage) -> ClusteringAlgorithm {
+        ClusteringAlgorithm::Agglomerative {
+            num_clusters,
+            linkage,
+        }
+    }
+}
+
+fn embeddings_to_tensor(embeddings: &[Embedding]) -> Result<Tensor, RustBertError> {
+    let dimension = embeddings.first().map_or(0, |embedding| embedding.len());
+    if embeddings
+        .iter()
+        .any(|embedding| embedding.len() != dimension)
+    {
+        return Err(RustBertError::ValueError(
+            "All embeddings must have the same dimension".to_string(),
+        ));
+    }
+    Ok(Tensor::of_slice(&embeddings.concat()).view((embeddings.len() as i64, dimension as i64)))
+}
+
+/// Clusters embeddings
+///
+/// # Arguments
+///
+/// * `embeddings` - Embeddings to cluster
+/// * `algorithm` - `ClusteringAlgorithm` and its parameters
+///
+/// # Returns
+///
+/// * `Vec<usize>` cluster of each embedding, between 0 and the number of clusters (excluded)
+pub fn cluster_embeddings(
+    embeddings: &[Embedding],
+    algorithm: &ClusteringAlgorithm,
+) -> Result<Vec<usize>, RustBertError> {
+    let num_clusters = match *algorithm {
+        ClusteringAlgorithm::KMeans { num_clusters, .. } => num_clusters,
+        ClusteringAlgorithm::Agglomerative { num_clusters, .. } => num_clusters,
+    };
+    if num_clusters == 0 || num_clusters > embeddings.len() {
+        return Err(RustBertError::ValueError(format!(
+            "The number of clusters must be between 1 and the number of embeddings ({}), got {}",
+            embeddings.len(),
+            num_clusters
+        )));
+    }
+    let data = embeddings_to_tensor(embeddings)?;
+    let assignments = match *algorithm {
+        ClusteringAlgorithm::KMeans {
+            max_iterations,
+            seed,
+            ..
+        } => kmeans(&data, num_clusters as i64, max_iterations, seed)
+            .iter::<i64>()
+            .unwrap()
+            .map(|cluster| cluster as usize)
+            .collect(),
+        ClusteringAlgorithm::Agglomerative { linkage, .. } => {
+            let distances = pairwise_distances(&data, DistanceMetric::Cosine);
+            agglomerative(distances, embeddings.len(), num_clusters, linkage)
+        }
+    };
+    Ok(assignments)
+}
+
+/// Computes the mean silhouette coefficient of a clustering
+///
+/// # Arguments
+///
+/// * `embeddings` - Clustered embeddings
+/// * `assignments` - Cluster of each embedding (e.g. the output of `cluster_embeddings`)
+/// * `metric` - `DistanceMetric` between embeddings
+///
+/// # Returns
+///
+/// * `f64` mean silhouette coefficient over all embeddings, between -1 and 1. The coefficient of embeddings alone in
+/// their cluster is 0.
+pub fn silhouette_score(
+    embeddings: &[Embedding],
+    assignments: &[usize],
+    metric: DistanceMetric,
+) -> Result<f64, RustBertError> {
+    if assignments.len() != embeddings.len() {
+        return Err(RustBertError::ValueError(format!(
+            "Got {} assignments for {} embeddings",
+            assignments.len(),
+            embeddings.len()
+        )));
+    }
+    let num_clusters = assignments.iter().max().map_or(0, |cluster| cluster + 1);
+    let mut cluster_sizes = vec![0usize; num_clusters];
+    for cluster in assignments {
+        cluster_sizes[*cluster] += 1;
+    }
+    let num_non_empty = cluster_sizes.iter().filter(|size| **size > 0).count();
+    if num_non_empty < 2 || num_non_empty >= embeddings.len() {
+        return Err(RustBertError::ValueError(format!(
+            "The silhouette score is defined for 2 to {} clusters, got {}",
+            embeddings.len().saturating_sub(1),
+            num_non_empty
+        )));
+    }
+
+    let data = embeddings_to_tensor(embeddings)?;
+    let assignments_tensor =
+        Tensor::of_slice(&assignments.iter().map(|v| *v as i64).collect::<Vec<i64>>());
+    // Sum of the distances of each embedding to the members of each cluster
+    let cluster_distances = pairwise_distances(&data, metric).matmul(
+        &assignments_tensor
+            .one_hot(num_clusters as i64)
+            .to_kind(Kind::Float),
+    );
+    let cluster_distances = Vec::<Vec<f64>>::from(cluster_distances.to_kind(Kind::Double));
+
+    let mut total = 0.0;
+    for (distances, cluster) in cluster_distances.iter().zip(assignments) {
+        let own_size = cluster_sizes[*cluster];
+        if own_size == 1 {
+            continue;
+        }
+        let intra = distances[*cluster] / (own_size - 1) as f64;
+        let nearest = distances
+            .iter()
+            .zip(cluster_sizes.iter())
+            .enumerate()
+            .filter(|(other, (_, size))| other != cluster && **size > 0)
+            .map(|(_, (distance, size))| distance / *size as f64)
+            .fold(f64::INFINITY, f64::min);
+        let max = intra.max(nearest);
+        if max > 0.0 {
+            total += (nearest - intra) / max;
+        }
+    }
+    Ok(total / embeddings.len() as f64)
+}
+
+/// Pairwise distances between the rows of a matrix, of shape (n, n)
+fn pairwise_distances(data: &Tensor, metric: DistanceMetric) -> Tensor {
+    match metric {
+        DistanceMetric::Euclidean => squared_distances(data, data).sqrt(),
+        DistanceMetric::Cosine => {
+            let normalized = data
+                / data
+                    .norm_scalaropt_dim(2, &[1], true)
+                    .clamp_min(1e-12)
+                    .expand_as(data);
+            (normalized.matmul(&normalized.tr()) * -1.0 + 1.0).clamp_min(0.0)
+        }
+    }
+}
+
+/// Deterministic SplitMix64 random generator used for the k-means initialization
+struct SplitMix64(u64);
+
+impl SplitMix64 {
+    /// Uniform sample in [0, 1)
+    fn next_f64(&mut self) -> f64 {
+        self.0 = self.0.wrapping_add(0x9E37_79B9_7F4A_7C15);
+        let mut z = self.0;
+        z = (z ^ (z >> 30)).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94D0_49BB_1331_11EB);
+        z ^= z >> 31;
+        (z >> 11) as f64 / (1u64 << 53) as f64
+    }
+}
+
+/// Squared euclidean distances between the rows of `a` (n, d) and `b` (k, d), of shape (n, k)
+fn squared_distances(a: &Tensor, b: &Tensor) -> Tensor {
+    let a_norms = a
+        .pow_tensor_scalar(2.0_f64)
+        .sum_dim_intlist(&[1], true, Kind::Float);
+    let b_norms = b
+        .pow_tensor_scalar(2.0_f64)
+        .sum_dim_intlist(&[1], true, Kind::Float);
+    (a_norms - a.matmul(&b.tr()) * 2.0 + b_norms.tr()).clamp_min(0.0)
+}
+
+/// K-means clustering of the rows of a matrix with a k-means++ initialization, returning the cluster of each row
+pub(crate) fn kmeans(data: &Tensor, num_clusters: i64, max_iterations: usize, seed: u64) -> Tensor {
+    let num_samples = data.size()[0];
+    let mut rng = SplitMix64(seed);
+
+    // k-means++: each new centroid is sampled with a probability proportional to its squared distance to the closest
+    // centroid already selected
+    let first = ((rng.next_f64() * num_samples as f64) as i64).min(num_samples - 1);
+    let mut centroid_indices = vec![first];
+    let mut min_distances = squared_distances(data, &data.get(first).unsqueeze(0)).squeeze_dim(1);
+    while (centroid_indices.len() as i64) < num_clusters {
+        let distances = min_distances.iter::<f64>().unwrap().collect::<Vec<f64>>();
+        let total = distances.iter().sum::<f64>();
+        let next = if total > 0.0 {
+            let target = rng.next_f64() * total;
+            let mut cumulative = 0.0;
+            distances
+                .iter()
+                .position(|distance| {
+                    cumulative += distance;
+                    cumulative > target
+                })
+                .unwrap_or(distances.len() - 1) as i64
+        } else {
+            // All points coincide with a centroid: any point not selected yet
+            (0..num_samples)
+                .find(|index| !centroid_indices.contains(index))
+                .unwrap()
+        };
+        centroid_indices.push(next);
+        let distances = squared_distances(data, &data.get(next).unsqueeze(0)).squeeze_dim(1);
+        min_distances = distances.where_self(&distances.lt_tensor(&min_distances), &min_distances);
+    }
+    let mut centroids =
+        data.index_select(0, &Tensor::of_slice(&centroid_indices).to(data.device()));
+
+    let mut assignments = squared_distances(data, &centroids).argmin(1, false);
+    for _ in 0..max_iterations {
+        let one_hot = assignments.one_hot(num_clusters).to_kind(Kind::Float);
+        let counts = one_hot
+            .sum_dim_intlist(&[0], false, Kind::Float)
+            .unsqueeze(1);
+        let sums = one_hot.tr().matmul(data);
+        // Empty clusters keep their previous centroid
+        centroids = (sums / counts.clamp_min(1.0)).where_self(&counts.gt(0.0), &centroids);
+        let new_assignments = squared_distances(data, &centroids).argmin(1, false);
+        if new_assignments.equal(&assignments) {
+            break;
+        }
+        assignments = new_assignments;
+    }
+    assignments
+}
+
+fn find_root(parents: &mut [usize], mut node: usize) -> usize {
+    while parents[node] != node {
+        parents[node] = parents[parents[node]];
+        node = parents[node];
+    }
+    node
+}
+
+/// Agglomerative clustering from a distance matrix, using the nearest-neighbor chain algorithm to build the
+/// hierarchy in quadratic time. The merges are then applied by increasing distance until `num_clusters` remain.
+fn agglomerative(
+    distances: Tensor,
+    num_samples: usize,
+    num_clusters: usize,
+    linkage: Linkage,
+) -> Vec<usize> {
+    let mut distances = Vec::<f64>::from(distances.to_kind(Kind::Double).view(-1));
+    let mut sizes = vec![1usize; num_samples];
+    let mut active = vec![true; num_samples];
+    let mut merges: Vec<(f64, usize, usize)> = Vec::with_capacity(num_samples.saturating_sub(1));
+    let mut chain: Vec<usize> = vec![];
+
+    for _ in 1..num_samples {
+        if chain.is_empty() {
+            chain.push(active.iter().position(|is_active| *is_active).unwrap());
+        }
+        // Extend the chain with nearest neighbors until two clusters are reciprocal nearest neighbors
+        let (first, second) = loop {
+            let current = *chain.last().unwrap();
+            let previous = chain.len().checked_sub(2).map(|position| chain[position]);
+            let mut nearest = previous;
+            let mut nearest_distance = previous.map_or(f64::INFINITY, |previous| {
+                distances[current * num_samples + previous]
+            });
+            for other in 0..num_samples {
+                if active[other] && other != current {
+                    let distance = distances[current * num_samples + other];
+                    if distance < nearest_distance {
+                        nearest = Some(other);
+                        nearest_distance = distance;
+                    }
+                }
+            }
+            let nearest = nearest.unwrap();
+            if Some(nearest) == previous {
+                chain.pop();
+                chain.pop();
+                break (current, nearest);
+            }
+            chain.push(nearest);
+        };
+
+        // Merge the second cluster into the first one (Lance-Williams update of the distances)
+        merges.push((distances[first * num_samples + second], first, second));
+        for other in 0..num_samples {
+            if active[other] && other != first && other != second {
+                let first_distance = distances[first * num_samples + other];
+                let second_distance = distances[second * num_samples + other];
+                let distance = match linkage {
+                    Linkage::Single => first_distance.min(second_distance),
+                    Linkage::Complete => first_distance.max(second_distance),
+                    Linkage::Average => {
+                        (sizes[first] as f64 * first_distance
+                            + sizes[second] as f64 * second_distance)
+                            / (sizes[first] + sizes[second]) as f64
+                    }
+                };
+                distances[first * num_samples + other] = distance;
+                distances[other * num_samples + first] = distance;
+            }
+        }
+        sizes[first] += sizes[second];
+        active[second] = false;
+    }
+
+    merges.sort_by(|a, b| a.0.partial_cmp(&b.0).unwrap());
+    let mut parents = (0..num_samples).collect::<Vec<usize>>();
+    for (_, first, second) in merges.into_iter().take(num_samples - num_clusters) {
+        let first_root = find_root(&mut parents, first);
+        let second_root = find_root(&mut parents, second);
+        parents[second_root] = first_root;
+    }
+
+    // Clusters numbered by order of first appearance
+    let mut cluster_ids = vec![None; num_samples];
+    let mut num_found = 0;
+    (0..num_samples)
+        .map(|sample| {
+            let root = find_root(&mut parents, sample);
+            *cluster_ids[root].get_or_insert_with(|| {
+                num_found += 1;
+                num_found - 1
+            })
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use std::collections::HashSet;
+
+    #[test]
+    fn test_kmeans() {
+        let data = Tensor::of_slice(&[
+            0.0f32, 0.0, 0.1, 0.0, 0.0, 0.1, 5.0, 5.0, 5.1, 5.0, 5.0, 5.1, -5.0, 5.0, -5.1, 5.0,
+        ])
+        .view((-1, 2));
+        let assignments = kmeans(&data, 3, 100, 42)
+            .iter::<i64>()
+            .unwrap()
+            .collect::<Vec<i64>>();
+        assert_eq!(assignments[0], assignments[1]);
+        assert_eq!(assignments[0], assignments[2]);
+        assert_eq!(assignments[3], assignments[4]);
+        assert_eq!(assignments[3], assignments[5]);
+        assert_eq!(assignments[6], assignments[7]);
+        let clusters = [assignments[0], assignments[3], assignments[6]]
+            .iter()
+            .collect::<HashSet<_>>();
+        assert_eq!(clusters.len(), 3);
+
+        // Deterministic for a given seed
+        assert!(kmeans(&data, 3, 100, 7).equal(&kmeans(&data, 3, 100, 7)));
+    }
+
+    #[test]
+    fn test_agglomerative_and_silhouette() {
+        let embeddings = vec![
+            vec![1.0, 0.0],
+            vec![0.0, 1.0],
+            vec![0.95, 0.05],
+            vec![0.1, 0.9],
+            vec![-1.0, 0.05],
+        ];
+        for linkage in [Linkage::Single, Linkage::Complete, Linkage::Average] {
+            let assignments =
+                cluster_embeddings(&embeddings, &ClusteringAlgorithm::agglomerative(3, linkage))
+                    .unwrap();
+            assert_eq!(assignments, vec![0, 1, 0, 1, 2]);
+        }
+
+        let good = silhouette_score(&embeddings, &[0, 1, 0, 1, 2], DistanceMetric::Cosine).unwrap();
+        let bad = silhouette_score(&embeddings, &[0, 0, 1, 1, 2], DistanceMetric::Cosine).unwrap();
+        assert!(good > 0.7);
+        assert!(bad < 0.0);
+        assert!(silhouette_score(&embeddings, &[0; 5], DistanceMetric::Cosine).is_err());
+    }
+}
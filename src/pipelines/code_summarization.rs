@@ -0,0 +1,431 @@
+// Copyright 2022 Guillaume Becquin
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//     http://www.apache.org/licenses/LICENSE-2.0
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! # Code summarization pipeline
+//! Generates natural language summaries and docstrings for source code snippets (e.g. functions selected in an IDE),
+//! with CodeT5-style models: T5 encoder-decoders pre-trained on code, using a RoBERTa byte-level BPE tokenizer.
+//! CodeT5-base fine-tuned for multilingual code summarization (Ruby, JavaScript, Go, Python, Java and PHP) is available
+//! as a preset (`CodeSummarizationConfig::codet5_base_multi_sum`): its configuration and tokenizer files are downloaded
+//! from the original repository and its weights are converted locally from the PyTorch checkpoint
+//! (`python utils/convert_model.py path/to/pytorch_model.bin`).
+//!
+//! Code is tokenized without lowercasing and with its whitespace (line breaks and indentation) preserved by the
+//! byte-level tokenizer. Snippets are only normalized for the quirks of editor selections: Windows line endings are
+//! converted, the indentation common to all lines is removed and leading and trailing blank lines are dropped.
+//!
+//! ```no_run
+//! # fn main() -> anyhow::Result<()> {
+//! use rust_bert::pipelines::code_summarization::{
+//!     CodeSummarizationConfig, CodeSummarizationModel, DocstringStyle,
+//! };
+//! use rust_bert::resources::LocalResource;
+//! use std::path::PathBuf;
+//!
+//! let model = CodeSummarizationModel::new(CodeSummarizationConfig::codet5_base_multi_sum(
+//!     LocalResource::from(PathBuf::from("path/to/rust_model.ot")),
+//! ))?;
+//!
+//! let code = "    def add(a, b):\n        return a + b\n";
+//! let summaries = model.summarize(&[code]);
+//! let docstrings = model.generate_docstrings(&[code], DocstringStyle::Python);
+//! # Ok(())
+//! # }
+//! ```
+
+use crate::common::error::RustBertError;
+use crate::pipelines::common::{ModelType, Pipeline, TokenizerOption};
+use crate::pipelines::generation_utils::{GenerateConfig, LanguageGenerator, NoRepeatNgramScope};
+use crate::resources::ResourceProvider;
+use crate::t5::T5Generator;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use tch::Device;
+
+#[cfg(feature = "remote")]
+use crate::{
+    resources::RemoteResource,
+    t5::{T5ConfigResources, T5MergesResources, T5VocabResources},
+};
+
+/// # Comment style of the generated docstrings
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum DocstringStyle {
+    /// Summary without comment markers
+    Plain,
+    /// Rust documentation comment (`/// summary`)
+    Rust,
+    /// Python docstring (`"""summary"""`)
+    Python,
+    /// Javadoc/JSDoc/PHPDoc block comment (`/** ... */`)
+    Javadoc,
+    /// Go line comment (`// summary`)
+    Go,
+    /// Ruby (or shell) line comment (`# summary`)
+    Ruby,
+}
+
+impl DocstringStyle {
+    /// Formats a summary as a docstring, indented with `indentation`
+    ///
+    /// # Arguments
+    ///
+    /// * `summary` - Summary of the code
+    /// * `indentation` - Indentation of the docstring (typically the indentation of the summarized code)
+    pub fn format(&self, summary: &str, indentation: &str) -> String {
+        let summary = summary.trim();
+        match self {
+            DocstringStyle::Plain => format!("{}{}", indentation, summary),
+            DocstringStyle::Rust => format!("{}/// {}", indentation, summary),
+            DocstringStyle::Python => format!("{}\"\"\"{}\"\"\"", indentation, summary),
+            DocstringStyle::Javadoc => format!(
+                "{indent}/**\n{indent} * {}\n{indent} */",
+                summary,
+                indent = indentation
+            ),
+            DocstringStyle::Go => format!("{}// {}", indentation, summary),
+            DocstringStyle::Ruby => format!("{}# {}", indentation, summary),
+        }
+    }
+}
+
+/// Normalizes a code snippet selected in an editor: converts Windows line endings, drops leading and trailing blank
+/// lines and removes the indentation common to all non-blank lines. Returns the snippet and its removed indentation.
+fn normalize_snippet(code: &str) -> (String, String) {
+    let code = code.replace("\r\n", "\n");
+    let lines = code
+        .lines()
+        .skip_while(|line| line.trim().is_empty())
+        .collect::<Vec<&str>>();
+    let num_lines = lines.len()
+        - lines
+            .iter()
+            .rev()
+            .take_while(|line| line.trim().is_empty())
+            .count();
+    let lines = &lines[..num_lines];
+
+    let indentation = lines
+        .iter()
+        .filter(|line| !line.trim().is_empty())
+        .map(|line| {
+            let indentation_length = line.len() - line.trim_start().len();
+            &line[..indentation_length]
+        })
+        .fold(None, |common: Option<&str>, indentation| match common {
+            None => Some(indentation),
+            Some(common) => {
+                let length = common
+                    .chars()
+                    .zip(indentation.chars())
+                    .take_while(|(a, b)| a == b)
+                    .map(|(a, _)| a.len_utf8())
+                    .sum::<usize>();
+                Some(&common[..length])
+            }
+        })
+        .unwrap_or("");
+
+    let snippet = lines
+        .iter()
+        .map(|line| {
+            line.strip_prefix(indentation)
+                .unwrap_or_else(|| line.trim_start())
+        })
+        .collect::<Vec<&str>>()
+        .join("\n");
+    (snippet, indentation.to_string())
+}
+
+/// # Configuration for code summarization
+/// Contains information regarding the model to load, mirrors the GenerateConfig, with a
+/// different set of default parameters and sets the device to place the model on.
+pub struct CodeSummarizationConfig {
+    /// Model weights resource
+    pub model_resource: Box<dyn ResourceProvider + Send>,
+    /// Config resource
+    pub config_resource: Box<dyn ResourceProvider + Send>,
+    /// Vocab resource, RoBERTa `vocab.json`
+    pub vocab_resource: Box<dyn ResourceProvider + Send>,
+    /// Merges resource, RoBERTa `merges.txt`
+    pub merges_resource: Box<dyn ResourceProvider + Send>,
+    /// Minimum sequence length (default: 0)
+    pub min_length: i64,
+    /// Maximum sequence length (default: 64)
+    pub max_length: i64,
+    /// Number of beams for beam search (default: 4)
+    pub num_beams: i64,
+    /// Exponential penalty based on the length of the hypotheses generated (default: 1.0)
+    pub length_penalty: f64,
+    /// Number of allowed repetitions of n-grams. Values higher than 0 turn on this feature (default: 0)
+    pub no_repeat_ngram_size: i64,
+    /// Flag indicating if the snippets are normalized (line endings, blank lines and common indentation) before summarization (default: true)
+    pub normalize_snippets: bool,
+    /// Bias added to the logits of the given token ids at every generation step, nudging the vocabulary used by the
+    /// model. Positive values encourage the generation of a token and negative values discourage it (default: empty)
+    pub logit_bias: HashMap<i64, f64>,
+    /// Device to place the model on (default: CUDA/GPU when available)
+    pub device: Device,
+}
+
+impl CodeSummarizationConfig {
+    /// Instantiate a new code summarization configuration with the supplied resources.
+    ///
+    /// # Arguments
+    ///
+    /// * model_resource - The `ResourceProvider` pointing to the model to load (e.g.  model.ot)
+    /// * config_resource - The `ResourceProvider` pointing to the model configuration to load (e.g. config.json)
+    /// * vocab_resource - The `ResourceProvider` pointing to the tokenizer's vocabulary to load (e.g.  vocab.json)
+    /// * merges_resource - The `ResourceProvider` pointing to the tokenizer's merge file to load (e.g.  merges.txt)
+    pub fn new<R>(
+        model_resource: R,
+        config_resource: R,
+        vocab_resource: R,
+        merges_resource: R,
+    ) -> CodeSummarizationConfig
+    where
+        R: ResourceProvider + Send + 'static,
+    {
+        CodeSummarizationConfig {
+            model_resource: Box::new(model_resource),
+            config_resource: Box::new(config_resource),
+            vocab_resource: Box::new(vocab_resource),
+            merges_resource: Box::new(merges_resource),
+            min_length: 0,
+            max_length: 64,
+            num_beams: 4,
+            length_penalty: 1.0,
+            no_repeat_ngram_size: 0,
+            normalize_snippets: true,
+            logit_bias: HashMap::new(),
+            device: Device::cuda_if_available(),
+        }
+    }
+}
+
+#[cfg(feature = "remote")]
+impl CodeSummarizationConfig {
+    /// Instantiate the configuration of CodeT5-base fine-tuned for multilingual code summarization. The configuration
+    /// and tokenizer files are downloaded from the original repository of the model, the weights are provided as a
+    /// resource converted locally.
+    ///
+    /// # Arguments
+    ///
+    /// * `model_resource` - The `ResourceProvider` pointing to the converted weights of the model (e.g. rust_model.ot)
+    pub fn codet5_base_multi_sum<R>(model_resource: R) -> CodeSummarizationConfig
+    where
+        R: ResourceProvider + Send + 'static,
+    {
+        CodeSummarizationConfig {
+            model_resource: Box::new(model_resource),
+            config_resource: Box::new(RemoteResource::from_pretrained(
+                T5ConfigResources::CODET5_BASE_MULTI_SUM,
+            )),
+            vocab_resource: Box::new(RemoteResource::from_pretrained(
+                T5VocabResources::CODET5_BASE_MULTI_SUM,
+            )),
+            merges_resource: Box::new(RemoteResource::from_pretrained(
+                T5MergesResources::CODET5_BASE_MULTI_SUM,
+            )),
+            min_length: 0,
+            max_length: 64,
+            num_beams: 4,
+            length_penalty: 1.0,
+            no_repeat_ngram_size: 0,
+            normalize_snippets: true,
+            logit_bias: HashMap::new(),
+            device: Device::cuda_if_available(),
+        }
+    }
+}
+
+/// # CodeSummarizationModel to generate summaries and docstrings of code
+pub struct CodeSummarizationModel {
+    model: T5Generator,
+    normalize_snippets: bool,
+}
+
+impl CodeSummarizationModel {
+    /// Build a new `CodeSummarizationModel`
+    ///
+    /// # Arguments
+    ///
+    /// * `config` - `CodeSummarizationConfig` object containing the resource references (model, vocabulary, merges, configuration), generation options and device placement (CPU/GPU)
+    ///
+    /// # Example
+    ///
+    /// ```no_run
+    /// # fn main() -> anyhow::Result<()> {
+    /// use rust_bert::pipelines::code_summarization::{
+    ///     CodeSummarizationConfig, CodeSummarizationModel,
+    /// };
+    /// use rust_bert::resources::LocalResource;
+    /// use std::path::PathBuf;
+    ///
+    /// let model = CodeSummarizationModel::new(CodeSummarizationConfig::codet5_base_multi_sum(
+    ///     LocalResource::from(PathBuf::from("path/to/rust_model.ot")),
+    /// ))?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn new(config: CodeSummarizationConfig) -> Result<CodeSummarizationModel, RustBertError> {
+        let vocab_path = config.vocab_resource.get_local_path()?;
+        let merges_path = config.merges_resource.get_local_path()?;
+        // Code is case-sensitive: the byte-level BPE tokenizer is loaded without lowercasing
+        let tokenizer = TokenizerOption::from_file(
+            ModelType::Roberta,
+            vocab_path.to_str().unwrap(),
+            Some(merges_path.to_str().unwrap()),
+            false,
+            None,
+            false,
+        )?;
+        let normalize_snippets = config.normalize_snippets;
+        let generate_config = GenerateConfig {
+            model_resource: config.model_resource,
+            config_resource: config.config_resource,
+            vocab_resource: config.vocab_resource,
+            merges_resource: config.merges_resource,
+            min_length: config.min_length,
+            max_length: config.max_length,
+            do_sample: false,
+            early_stopping: true,
+            num_beams: config.num_beams,
+            temperature: 1.0,
+            top_k: 0,
+            top_p: 1.0,
+            repetition_penalty: 1.0,
+            length_penalty: config.length_penalty,
+            no_repeat_ngram_size: config.no_repeat_ngram_size,
+            no_repeat_ngram_scope: NoRepeatNgramScope::Sequence,
+            num_return_sequences: 1,
+            num_beam_groups: None,
+            diversity_penalty: None,
+            penalty_alpha: None,
+            typical_p: None,
+            epsilon_cutoff: None,
+            eta_cutoff: None,
+            prefill_chunk_size: None,
+            stop_sequences: vec![],
+            logit_bias: config.logit_bias,
+            output_attentions: false,
+            device: config.device,
+        };
+        let model = T5Generator::new_with_tokenizer(generate_config, tokenizer)?;
+        Ok(CodeSummarizationModel {
+            model,
+            normalize_snippets,
+        })
+    }
+
+    fn generate_summaries<S>(&self, code: &[S]) -> (Vec<String>, Vec<String>)
+    where
+        S: AsRef<str>,
+    {
+        let (snippets, indentations): (Vec<String>, Vec<String>) = code
+            .iter()
+            .map(|snippet| {
+                if self.normalize_snippets {
+                    normalize_snippet(snippet.as_ref())
+                } else {
+                    (snippet.as_ref().to_string(), String::new())
+                }
+            })
+            .unzip();
+        let summaries = self
+            .model
+            .generate(Some(&snippets), None)
+            .into_iter()
+            .map(|output| output.text.trim().to_string())
+            .collect();
+        (summaries, indentations)
+    }
+
+    /// Summarize code snippets
+    ///
+    /// # Arguments
+    ///
+    /// * `code` - `&[&str]` Array of code snippets to summarize.
+    ///
+    /// # Returns
+    /// * `Vec<String>` Natural language summaries
+    pub fn summarize<S>(&self, code: &[S]) -> Vec<String>
+    where
+        S: AsRef<str>,
+    {
+        self.generate_summaries(code).0
+    }
+
+    /// Generate docstrings for code snippets, indented like the snippets
+    ///
+    /// # Arguments
+    ///
+    /// * `code` - `&[&str]` Array of code snippets to document.
+    /// * `style` - `DocstringStyle` of the docstrings
+    ///
+    /// # Returns
+    /// * `Vec<String>` Docstrings, to be inserted before (or, for Python, after the signature of) the snippets
+    pub fn generate_docstrings<S>(&self, code: &[S], style: DocstringStyle) -> Vec<String>
+    where
+        S: AsRef<str>,
+    {
+        let (summaries, indentations) = self.generate_summaries(code);
+        summaries
+            .iter()
+            .zip(indentations.iter())
+            .map(|(summary, indentation)| style.format(summary, indentation))
+            .collect()
+    }
+}
+
+impl<S> Pipeline<S, String> for CodeSummarizationModel
+where
+    S: AsRef<str>,
+{
+    type Options = ();
+
+    fn run_with_options(&self, inputs: &[S], _options: &()) -> Result<Vec<String>, RustBertError> {
+        Ok(self.summarize(inputs))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_normalize_snippet() {
+        let code = "\r\n    def add(a, b):\r\n\r\n        return a + b\r\n  \r\n";
+        let (snippet, indentation) = normalize_snippet(code);
+        assert_eq!(snippet, "def add(a, b):\n\n    return a + b");
+        assert_eq!(indentation, "    ");
+
+        let (snippet, indentation) =
+            normalize_snippet("\tfn main() {\n\t\tprintln!(\"Hello\");\n\t}");
+        assert_eq!(snippet, "fn main() {\n\tprintln!(\"Hello\");\n}");
+        assert_eq!(indentation, "\t");
+    }
+
+    #[test]
+    fn test_docstring_format() {
+        assert_eq!(
+            DocstringStyle::Rust.format(" Add two numbers ", "    "),
+            "    /// Add two numbers"
+        );
+        assert_eq!(
+            DocstringStyle::Python.format("Add two numbers", ""),
+            "\"\"\"Add two numbers\"\"\""
+        );
+        assert_eq!(
+            DocstringStyle::Javadoc.format("Add two numbers", "  "),
+            "  /**\n   * Add two numbers\n   */"
+        );
+    }
+}
@@ -19,14 +19,19 @@
 use crate::albert::AlbertConfig;
 use crate::bart::BartConfig;
 use crate::bert::BertConfig;
+use crate::bloom::BloomConfig;
 use crate::common::error::RustBertError;
 use crate::deberta::DebertaConfig;
 use crate::deberta_v2::DebertaV2Config;
 use crate::distilbert::DistilBertConfig;
 use crate::electra::ElectraConfig;
+use crate::falcon::FalconConfig;
 use crate::fnet::FNetConfig;
 use crate::gpt2::Gpt2Config;
+use crate::gpt_bigcode::GptBigCodeConfig;
 use crate::gpt_neo::GptNeoConfig;
+use crate::gpt_neox::GptNeoXConfig;
+use crate::llama::LlamaConfig;
 use crate::longformer::LongformerConfig;
 use crate::m2m_100::M2M100Config;
 use crate::marian::MarianConfig;
@@ -34,10 +39,12 @@ use crate::mbart::MBartConfig;
 use crate::mobilebert::MobileBertConfig;
 use crate::openai_gpt::OpenAiGptConfig;
 use crate::pegasus::PegasusConfig;
+use crate::pipelines::registry::get_model_registration;
+use crate::pipelines::sanitization::sanitize_input;
 use crate::prophetnet::ProphetNetConfig;
 use crate::reformer::ReformerConfig;
 use crate::roberta::RobertaConfig;
-use crate::t5::T5Config;
+use crate::t5::{ByT5Tokenizer, ByT5Vocab, T5Config};
 use crate::xlnet::XLNetConfig;
 use crate::memnet::tokenizer;
 use crate::Config;
@@ -45,18 +52,23 @@ use rust_tokenizers::tokenizer::{
     AlbertTokenizer, BertTokenizer, DeBERTaTokenizer, DeBERTaV2Tokenizer, FNetTokenizer,
     Gpt2Tokenizer, M2M100Tokenizer, MBart50Tokenizer, MarianTokenizer, MultiThreadedTokenizer,
     OpenAiGptTokenizer, PegasusTokenizer, ProphetNetTokenizer, ReformerTokenizer, RobertaTokenizer,
-    T5Tokenizer, Tokenizer, TruncationStrategy, XLMRobertaTokenizer, XLNetTokenizer,
+    SentencePieceBpeTokenizer, T5Tokenizer, Tokenizer, TruncationStrategy, XLMRobertaTokenizer,
+    XLNetTokenizer,
 };
 use rust_tokenizers::vocab::{
     AlbertVocab, BertVocab, DeBERTaV2Vocab, DeBERTaVocab, FNetVocab, Gpt2Vocab, M2M100Vocab,
     MBart50Vocab, MarianVocab, OpenAiGptVocab, PegasusVocab, ProphetNetVocab, ReformerVocab,
-    RobertaVocab, T5Vocab, Vocab, XLMRobertaVocab, XLNetVocab,
+    RobertaVocab, SentencePieceBpeModel, SentencePieceVocab, T5Vocab, Vocab, XLMRobertaVocab,
+    XLNetVocab,
 };
 use rust_tokenizers::{TokenIdsWithOffsets, TokenizedInput, TokensWithOffsets};
 use serde::{Deserialize, Serialize};
+use std::borrow::Cow;
 use std::collections::HashMap;
 use std::convert::TryFrom;
+use std::marker::PhantomData;
 use std::path::Path;
+use tch::{Device, Tensor};
 use crate::memnet::tokenizer::{MemnetTokenizer, MemnetVocab};
 
 #[derive(Clone, Copy, Serialize, Deserialize, Debug, PartialEq)]
@@ -75,8 +87,11 @@ pub enum ModelType {
     Electra,
     Marian,
     MobileBert,
-    #[serde(alias = "t5")]
+    #[serde(alias = "t5", alias = "mt5")]
     T5,
+    /// T5 architecture with the byte-level ByT5 tokenizer
+    #[serde(alias = "byt5")]
+    ByT5,
     #[serde(alias = "albert")]
     Albert,
     XLNet,
@@ -87,10 +102,23 @@ pub enum ModelType {
     Longformer,
     Pegasus,
     GPTNeo,
+    #[serde(alias = "gpt_bigcode")]
+    GPTBigCode,
+    #[serde(alias = "gpt_neox")]
+    GPTNeoX,
+    #[serde(alias = "falcon")]
+    Falcon,
+    #[serde(alias = "bloom")]
+    Bloom,
+    #[serde(alias = "llama", alias = "mistral")]
+    Llama,
     MBart,
     M2M100,
     FNet,
-    Memnet
+    Memnet,
+    /// Custom architecture registered with `registry::register_model`
+    #[serde(skip)]
+    Custom(&'static str),
 }
 
 /// # Abstraction that holds a model configuration, can be of any of the supported models
@@ -133,6 +161,16 @@ pub enum ConfigOption {
     Pegasus(PegasusConfig),
     /// GPT-Neo configuration
     GPTNeo(GptNeoConfig),
+    /// GPT-BigCode configuration
+    GPTBigCode(GptBigCodeConfig),
+    /// GPT-NeoX configuration
+    GPTNeoX(GptNeoXConfig),
+    /// Falcon configuration
+    Falcon(FalconConfig),
+    /// BLOOM configuration
+    Bloom(BloomConfig),
+    /// LLaMA configuration
+    Llama(LlamaConfig),
     /// MBart configuration
     MBart(MBartConfig),
     /// M2M100 configuration
@@ -157,6 +195,8 @@ pub enum TokenizerOption {
     Marian(MarianTokenizer),
     /// T5 Tokenizer
     T5(T5Tokenizer),
+    /// ByT5 byte-level Tokenizer
+    ByT5(ByT5Tokenizer),
     /// Albert Tokenizer
     Albert(AlbertTokenizer),
     /// XLNet Tokenizer
@@ -177,6 +217,8 @@ pub enum TokenizerOption {
     M2M100(M2M100Tokenizer),
     /// FNet Tokenizer
     FNet(FNetTokenizer),
+    /// LLaMA Tokenizer
+    Llama(SentencePieceBpeTokenizer),
     /// Bart Tokenizer
     Bart(RobertaTokenizer),
     /// Memnet Tokenizer
@@ -185,8 +227,11 @@ pub enum TokenizerOption {
 
 impl ConfigOption {
     /// Interface method to load a configuration from file
-    pub fn from_file<P: AsRef<Path>>(model_type: ModelType, path: P) -> Self {
-        match model_type {
+    pub fn from_file<P: AsRef<Path>>(
+        model_type: ModelType,
+        path: P,
+    ) -> Result<Self, RustBertError> {
+        Ok(match model_type {
             ModelType::Bart => ConfigOption::Bart(BartConfig::from_file(path)),
             ModelType::Bert => ConfigOption::Bert(BertConfig::from_file(path)),
             ModelType::Deberta => ConfigOption::Deberta(DebertaConfig::from_file(path)),
@@ -195,11 +240,16 @@ impl ConfigOption {
             ModelType::Electra => ConfigOption::Electra(ElectraConfig::from_file(path)),
             ModelType::Marian => ConfigOption::Marian(MarianConfig::from_file(path)),
             ModelType::MobileBert => ConfigOption::MobileBert(MobileBertConfig::from_file(path)),
-            ModelType::T5 => ConfigOption::T5(T5Config::from_file(path)),
+            ModelType::T5 | ModelType::ByT5 => ConfigOption::T5(T5Config::from_file(path)),
             ModelType::Albert => ConfigOption::Albert(AlbertConfig::from_file(path)),
             ModelType::XLNet => ConfigOption::XLNet(XLNetConfig::from_file(path)),
             ModelType::GPT2 => ConfigOption::GPT2(Gpt2Config::from_file(path)),
             ModelType::GPTNeo => ConfigOption::GPTNeo(GptNeoConfig::from_file(path)),
+            ModelType::GPTBigCode => ConfigOption::GPTBigCode(GptBigCodeConfig::from_file(path)),
+            ModelType::GPTNeoX => ConfigOption::GPTNeoX(GptNeoXConfig::from_file(path)),
+            ModelType::Falcon => ConfigOption::Falcon(FalconConfig::from_file(path)),
+            ModelType::Bloom => ConfigOption::Bloom(BloomConfig::from_file(path)),
+            ModelType::Llama => ConfigOption::Llama(LlamaConfig::from_file(path)),
             ModelType::OpenAiGpt => ConfigOption::OpenAiGpt(OpenAiGptConfig::from_file(path)),
             ModelType::Reformer => ConfigOption::Reformer(ReformerConfig::from_file(path)),
             ModelType::ProphetNet => ConfigOption::ProphetNet(ProphetNetConfig::from_file(path)),
@@ -211,8 +261,12 @@ impl ConfigOption {
             ModelType::MBart => ConfigOption::MBart(MBartConfig::from_file(path)),
             ModelType::M2M100 => ConfigOption::M2M100(M2M100Config::from_file(path)),
             ModelType::FNet => ConfigOption::FNet(FNetConfig::from_file(path)),
-            ModelType::Memnet => ConfigOption::Bert(BertConfig::from_file(path))
-        }
+            ModelType::Memnet => ConfigOption::Bert(BertConfig::from_file(path)),
+            ModelType::Custom(_) => {
+                let registration = get_model_registration(model_type)?;
+                (registration.config_builder)(path.as_ref())
+            }
+        })
     }
 
     pub fn get_label_mapping(&self) -> &HashMap<i64, String> {
@@ -289,6 +343,11 @@ impl ConfigOption {
             Self::OpenAiGpt(_) => panic!("OpenAI GPT does not use a label mapping"),
             Self::GPT2(_) => panic!("GPT2 does not use a label mapping"),
             Self::GPTNeo(_) => panic!("GPT-Neo does not use a label mapping"),
+            Self::GPTBigCode(_) => panic!("GPT-BigCode does not use a label mapping"),
+            Self::GPTNeoX(_) => panic!("GPT-NeoX does not use a label mapping"),
+            Self::Falcon(_) => panic!("Falcon does not use a label mapping"),
+            Self::Bloom(_) => panic!("BLOOM does not use a label mapping"),
+            Self::Llama(_) => panic!("LLaMA does not use a label mapping"),
             Self::Pegasus(_) => panic!("Pegasus does not use a label mapping"),
 
         }
@@ -314,12 +373,137 @@ impl ConfigOption {
             Self::Pegasus(config) => Some(config.max_position_embeddings),
             Self::OpenAiGpt(config) => Some(config.n_positions),
             Self::GPTNeo(config) => Some(config.max_position_embeddings),
+            Self::GPTBigCode(config) => Some(config.n_positions),
+            Self::GPTNeoX(config) => Some(config.max_position_embeddings),
+            Self::Falcon(config) => Some(config.max_position_embeddings.unwrap_or(2048)),
+            Self::Bloom(config) => Some(config.seq_length.unwrap_or(2048)),
+            Self::Llama(config) => Some(config.max_position_embeddings),
             Self::MBart(config) => Some(config.max_position_embeddings),
             Self::M2M100(config) => Some(config.max_position_embeddings),
             Self::FNet(config) => Some(config.max_position_embeddings),
             Self::Roberta(config) => Some(config.max_position_embeddings),
         }
     }
+
+    /// Sets the flags controlling if the model returns the hidden states and attention weights of all layers,
+    /// overriding the values of the configuration file
+    ///
+    /// # Arguments
+    ///
+    /// * `output_hidden_states` - Flag indicating if the model should return the hidden states of all layers
+    /// * `output_attentions` - Flag indicating if the model should return the attention weights of all layers
+    pub fn set_output_options(&mut self, output_hidden_states: bool, output_attentions: bool) {
+        match self {
+            Self::Bart(config) => {
+                config.output_hidden_states = Some(output_hidden_states);
+                config.output_attentions = Some(output_attentions);
+            }
+            Self::Bert(config) => {
+                config.output_hidden_states = Some(output_hidden_states);
+                config.output_attentions = Some(output_attentions);
+            }
+            Self::Deberta(config) => {
+                config.output_hidden_states = Some(output_hidden_states);
+                config.output_attentions = Some(output_attentions);
+            }
+            Self::DebertaV2(config) => {
+                config.output_hidden_states = Some(output_hidden_states);
+                config.output_attentions = Some(output_attentions);
+            }
+            Self::DistilBert(config) => {
+                config.output_hidden_states = Some(output_hidden_states);
+                config.output_attentions = Some(output_attentions);
+            }
+            Self::Electra(config) => {
+                config.output_hidden_states = Some(output_hidden_states);
+                config.output_attentions = Some(output_attentions);
+            }
+            Self::Marian(config) => {
+                config.output_hidden_states = Some(output_hidden_states);
+                config.output_attentions = Some(output_attentions);
+            }
+            Self::MobileBert(config) => {
+                config.output_hidden_states = Some(output_hidden_states);
+                config.output_attentions = Some(output_attentions);
+            }
+            Self::OpenAiGpt(config) => {
+                config.output_hidden_states = Some(output_hidden_states);
+                config.output_attentions = Some(output_attentions);
+            }
+            Self::T5(config) => {
+                config.output_hidden_states = Some(output_hidden_states);
+                config.output_attentions = Some(output_attentions);
+            }
+            Self::Albert(config) => {
+                config.output_hidden_states = Some(output_hidden_states);
+                config.output_attentions = Some(output_attentions);
+            }
+            Self::XLNet(config) => {
+                config.output_hidden_states = Some(output_hidden_states);
+                config.output_attentions = Some(output_attentions);
+            }
+            Self::GPT2(config) => {
+                config.output_hidden_states = Some(output_hidden_states);
+                config.output_attentions = Some(output_attentions);
+            }
+            Self::Reformer(config) => {
+                config.output_hidden_states = Some(output_hidden_states);
+                config.output_attentions = Some(output_attentions);
+            }
+            Self::Roberta(config) => {
+                config.output_hidden_states = Some(output_hidden_states);
+                config.output_attentions = Some(output_attentions);
+            }
+            Self::ProphetNet(config) => {
+                config.output_hidden_states = Some(output_hidden_states);
+                config.output_attentions = Some(output_attentions);
+            }
+            Self::Longformer(config) => {
+                config.output_hidden_states = Some(output_hidden_states);
+                config.output_attentions = Some(output_attentions);
+            }
+            Self::Pegasus(config) => {
+                config.output_hidden_states = Some(output_hidden_states);
+                config.output_attentions = Some(output_attentions);
+            }
+            Self::GPTNeo(config) => {
+                config.output_hidden_states = Some(output_hidden_states);
+                config.output_attentions = Some(output_attentions);
+            }
+            Self::GPTBigCode(config) => {
+                config.output_hidden_states = Some(output_hidden_states);
+                config.output_attentions = Some(output_attentions);
+            }
+            Self::GPTNeoX(config) => {
+                config.output_hidden_states = Some(output_hidden_states);
+                config.output_attentions = Some(output_attentions);
+            }
+            Self::Falcon(config) => {
+                config.output_hidden_states = Some(output_hidden_states);
+                config.output_attentions = Some(output_attentions);
+            }
+            Self::Bloom(config) => {
+                config.output_hidden_states = Some(output_hidden_states);
+                config.output_attentions = Some(output_attentions);
+            }
+            Self::Llama(config) => {
+                config.output_hidden_states = Some(output_hidden_states);
+                config.output_attentions = Some(output_attentions);
+            }
+            Self::MBart(config) => {
+                config.output_hidden_states = Some(output_hidden_states);
+                config.output_attentions = Some(output_attentions);
+            }
+            Self::M2M100(config) => {
+                config.output_hidden_states = Some(output_hidden_states);
+                config.output_attentions = Some(output_attentions);
+            }
+            Self::FNet(config) => {
+                config.output_hidden_states = Some(output_hidden_states);
+                config.output_attentions = Some(output_attentions);
+            }
+        }
+    }
 }
 
 impl TryFrom<&ConfigOption> for BertConfig {
@@ -500,6 +684,15 @@ impl TokenizerOption {
                 }
                 TokenizerOption::T5(T5Tokenizer::from_file(vocab_path, lower_case)?)
             }
+            ModelType::ByT5 => {
+                if lower_case | strip_accents.is_some() | add_prefix_space.is_some() {
+                    return Err(RustBertError::InvalidConfigurationError(format!(
+                        "Lower casing, accents stripping and prefix space are not supported by the {:?} byte-level tokenizer",
+                        model_type
+                    )));
+                }
+                TokenizerOption::ByT5(ByT5Tokenizer::new())
+            }
             ModelType::XLMRoberta => {
                 if strip_accents.is_some() {
                     return Err(RustBertError::InvalidConfigurationError(format!(
@@ -558,7 +751,12 @@ impl TokenizerOption {
                 }
                 TokenizerOption::Reformer(ReformerTokenizer::from_file(vocab_path, lower_case)?)
             }
-            ModelType::GPT2 | ModelType::GPTNeo => TokenizerOption::GPT2(Gpt2Tokenizer::from_file(
+            ModelType::GPT2
+            | ModelType::GPTNeo
+            | ModelType::GPTBigCode
+            | ModelType::GPTNeoX
+            | ModelType::Falcon
+            | ModelType::Bloom => TokenizerOption::GPT2(Gpt2Tokenizer::from_file(
                 vocab_path,
                 merges_path.expect("No merges specified!"),
                 lower_case,
@@ -638,7 +836,53 @@ impl TokenizerOption {
                 lower_case,
                 strip_accents.unwrap_or(false),
             )?),
+            ModelType::Llama => {
+                if add_prefix_space.is_some() {
+                    return Err(RustBertError::InvalidConfigurationError(
+                        format!("Optional input `add_prefix_space` set to value {} but cannot be used by {:?}",
+                                add_prefix_space.unwrap(),
+                                model_type)));
+                }
+                if strip_accents.is_some() {
+                    return Err(RustBertError::InvalidConfigurationError(format!(
+                        "Optional input `strip_accents` set to value {} but cannot be used by {:?}",
+                        strip_accents.unwrap(),
+                        model_type
+                    )));
+                }
+                let mut vocab = SentencePieceVocab::from_file(vocab_path)?;
+                // BOS and EOS are regular pieces of the SentencePiece model, registered as special tokens to be
+                // skipped when decoding
+                for special_value in [
+                    SentencePieceVocab::bos_value(),
+                    SentencePieceVocab::eos_value(),
+                ] {
+                    SentencePieceVocab::_register_as_special_value(
+                        special_value,
+                        &vocab.values,
+                        &mut vocab.special_values,
+                    )?;
+                    vocab.special_indices.insert(
+                        vocab.special_values[special_value],
+                        special_value.to_string(),
+                    );
+                }
+                let model = SentencePieceBpeModel::from_file(vocab_path)?;
+                TokenizerOption::Llama(SentencePieceBpeTokenizer::from_existing_vocab_and_model(
+                    vocab, model, lower_case,
+                ))
+            }
             ModelType::Memnet => TokenizerOption::Memnet(MemnetTokenizer::build()?),
+            ModelType::Custom(_) => {
+                let registration = get_model_registration(model_type)?;
+                (registration.tokenizer_builder)(
+                    vocab_path,
+                    merges_path,
+                    lower_case,
+                    strip_accents,
+                    add_prefix_space,
+                )?
+            }
         };
         Ok(tokenizer)
     }
@@ -654,6 +898,7 @@ impl TokenizerOption {
             Self::XLMRoberta(_) => ModelType::XLMRoberta,
             Self::Marian(_) => ModelType::Marian,
             Self::T5(_) => ModelType::T5,
+            Self::ByT5(_) => ModelType::ByT5,
             Self::Albert(_) => ModelType::Albert,
             Self::XLNet(_) => ModelType::XLNet,
             Self::GPT2(_) => ModelType::GPT2,
@@ -664,6 +909,7 @@ impl TokenizerOption {
             Self::MBart50(_) => ModelType::MBart,
             Self::M2M100(_) => ModelType::M2M100,
             Self::FNet(_) => ModelType::FNet,
+            Self::Llama(_) => ModelType::Llama,
             Self::Memnet(_) => ModelType::Memnet
         }
     }
@@ -679,6 +925,11 @@ impl TokenizerOption {
     where
         S: AsRef<str> + Sync,
     {
+        let sanitized_texts = text_list
+            .iter()
+            .map(|text| sanitize_input(text.as_ref()))
+            .collect::<Vec<Cow<str>>>();
+        let text_list = sanitized_texts.as_slice();
         match *self {
             Self::Bert(ref tokenizer) => MultiThreadedTokenizer::encode_list(
                 tokenizer,
@@ -729,6 +980,13 @@ impl TokenizerOption {
                 truncation_strategy,
                 stride,
             ),
+            Self::ByT5(ref tokenizer) => MultiThreadedTokenizer::encode_list(
+                tokenizer,
+                text_list,
+                max_len,
+                truncation_strategy,
+                stride,
+            ),
             Self::XLMRoberta(ref tokenizer) => MultiThreadedTokenizer::encode_list(
                 tokenizer,
                 text_list,
@@ -806,6 +1064,13 @@ impl TokenizerOption {
                 truncation_strategy,
                 stride,
             ),
+            Self::Llama(ref tokenizer) => MultiThreadedTokenizer::encode_list(
+                tokenizer,
+                text_list,
+                max_len,
+                truncation_strategy,
+                stride,
+            ),
             Self::Memnet(ref tokenizer) => MultiThreadedTokenizer::encode_list(
                 tokenizer,
                 text_list,
@@ -824,6 +1089,15 @@ impl TokenizerOption {
         truncation_strategy: &TruncationStrategy,
         stride: usize,
     ) -> Vec<TokenizedInput> {
+        let sanitized_pairs = text_pair_list
+            .iter()
+            .map(|(text_1, text_2)| (sanitize_input(text_1), sanitize_input(text_2)))
+            .collect::<Vec<(Cow<str>, Cow<str>)>>();
+        let text_pair_list = sanitized_pairs
+            .iter()
+            .map(|(text_1, text_2)| (text_1.as_ref(), text_2.as_ref()))
+            .collect::<Vec<(&str, &str)>>();
+        let text_pair_list = text_pair_list.as_slice();
         match *self {
             Self::Bert(ref tokenizer) => MultiThreadedTokenizer::encode_pair_list(
                 tokenizer,
@@ -874,6 +1148,13 @@ impl TokenizerOption {
                 truncation_strategy,
                 stride,
             ),
+            Self::ByT5(ref tokenizer) => MultiThreadedTokenizer::encode_pair_list(
+                tokenizer,
+                text_pair_list,
+                max_len,
+                truncation_strategy,
+                stride,
+            ),
             Self::XLMRoberta(ref tokenizer) => MultiThreadedTokenizer::encode_pair_list(
                 tokenizer,
                 text_pair_list,
@@ -951,6 +1232,13 @@ impl TokenizerOption {
                 truncation_strategy,
                 stride,
             ),
+            Self::Llama(ref tokenizer) => MultiThreadedTokenizer::encode_pair_list(
+                tokenizer,
+                text_pair_list,
+                max_len,
+                truncation_strategy,
+                stride,
+            ),
             Self::Memnet(ref tokenizer) => MultiThreadedTokenizer::encode_pair_list(
                 tokenizer,
                 text_pair_list,
@@ -970,6 +1258,10 @@ impl TokenizerOption {
         truncation_strategy: &TruncationStrategy,
         stride: usize,
     ) -> TokenizedInput {
+        let sanitized_text_1 = sanitize_input(text_1);
+        let sanitized_text_2 = text_2.map(sanitize_input);
+        let text_1 = sanitized_text_1.as_ref();
+        let text_2 = sanitized_text_2.as_deref();
         match *self {
             Self::Bert(ref tokenizer) => {
                 tokenizer.encode(text_1, text_2, max_len, truncation_strategy, stride)
@@ -992,6 +1284,9 @@ impl TokenizerOption {
             Self::T5(ref tokenizer) => {
                 tokenizer.encode(text_1, text_2, max_len, truncation_strategy, stride)
             }
+            Self::ByT5(ref tokenizer) => {
+                tokenizer.encode(text_1, text_2, max_len, truncation_strategy, stride)
+            }
             Self::XLMRoberta(ref tokenizer) => {
                 tokenizer.encode(text_1, text_2, max_len, truncation_strategy, stride)
             }
@@ -1025,6 +1320,9 @@ impl TokenizerOption {
             Self::FNet(ref tokenizer) => {
                 tokenizer.encode(text_1, text_2, max_len, truncation_strategy, stride)
             }
+            Self::Llama(ref tokenizer) => {
+                tokenizer.encode(text_1, text_2, max_len, truncation_strategy, stride)
+            }
             Self::Memnet(ref tokenizer) => {
                 tokenizer.encode(text_1, text_2, max_len, truncation_strategy, stride)
             }
@@ -1033,6 +1331,8 @@ impl TokenizerOption {
 
     /// Interface method to tokenization
     pub fn tokenize(&self, text: &str) -> Vec<String> {
+        let sanitized_text = sanitize_input(text);
+        let text = sanitized_text.as_ref();
         match *self {
             Self::Bert(ref tokenizer) => tokenizer.tokenize(text),
             Self::Deberta(ref tokenizer) => tokenizer.tokenize(text),
@@ -1041,6 +1341,7 @@ impl TokenizerOption {
             Self::Bart(ref tokenizer) => tokenizer.tokenize(text),
             Self::Marian(ref tokenizer) => tokenizer.tokenize(text),
             Self::T5(ref tokenizer) => tokenizer.tokenize(text),
+            Self::ByT5(ref tokenizer) => tokenizer.tokenize(text),
             Self::XLMRoberta(ref tokenizer) => tokenizer.tokenize(text),
             Self::Albert(ref tokenizer) => tokenizer.tokenize(text),
             Self::XLNet(ref tokenizer) => tokenizer.tokenize(text),
@@ -1052,6 +1353,7 @@ impl TokenizerOption {
             Self::MBart50(ref tokenizer) => tokenizer.tokenize(text),
             Self::M2M100(ref tokenizer) => tokenizer.tokenize(text),
             Self::FNet(ref tokenizer) => tokenizer.tokenize(text),
+            Self::Llama(ref tokenizer) => tokenizer.tokenize(text),
             Self::Memnet(ref tokenizer) => tokenizer.tokenize(text),
 
         }
@@ -1059,6 +1361,8 @@ impl TokenizerOption {
 
     /// Interface method to tokenization
     pub fn tokenize_with_offsets(&self, text: &str) -> TokensWithOffsets {
+        let sanitized_text = sanitize_input(text);
+        let text = sanitized_text.as_ref();
         match *self {
             Self::Bert(ref tokenizer) => tokenizer.tokenize_with_offsets(text),
             Self::Deberta(ref tokenizer) => tokenizer.tokenize_with_offsets(text),
@@ -1067,6 +1371,7 @@ impl TokenizerOption {
             Self::Bart(ref tokenizer) => tokenizer.tokenize_with_offsets(text),
             Self::Marian(ref tokenizer) => tokenizer.tokenize_with_offsets(text),
             Self::T5(ref tokenizer) => tokenizer.tokenize_with_offsets(text),
+            Self::ByT5(ref tokenizer) => tokenizer.tokenize_with_offsets(text),
             Self::XLMRoberta(ref tokenizer) => tokenizer.tokenize_with_offsets(text),
             Self::Albert(ref tokenizer) => tokenizer.tokenize_with_offsets(text),
             Self::XLNet(ref tokenizer) => tokenizer.tokenize_with_offsets(text),
@@ -1078,6 +1383,7 @@ impl TokenizerOption {
             Self::MBart50(ref tokenizer) => tokenizer.tokenize_with_offsets(text),
             Self::M2M100(ref tokenizer) => tokenizer.tokenize_with_offsets(text),
             Self::FNet(ref tokenizer) => tokenizer.tokenize_with_offsets(text),
+            Self::Llama(ref tokenizer) => tokenizer.tokenize_with_offsets(text),
             Self::Memnet(ref tokenizer) => tokenizer.tokenize_with_offsets(text),
 
         }
@@ -1088,6 +1394,11 @@ impl TokenizerOption {
     where
         S: AsRef<str> + Sync,
     {
+        let sanitized_texts = text
+            .iter()
+            .map(|text| sanitize_input(text.as_ref()))
+            .collect::<Vec<Cow<str>>>();
+        let text = sanitized_texts.as_slice();
         match *self {
             Self::Bert(ref tokenizer) => MultiThreadedTokenizer::tokenize_list(tokenizer, text),
             Self::Deberta(ref tokenizer) => MultiThreadedTokenizer::tokenize_list(tokenizer, text),
@@ -1098,6 +1409,7 @@ impl TokenizerOption {
             Self::Bart(ref tokenizer) => MultiThreadedTokenizer::tokenize_list(tokenizer, text),
             Self::Marian(ref tokenizer) => MultiThreadedTokenizer::tokenize_list(tokenizer, text),
             Self::T5(ref tokenizer) => MultiThreadedTokenizer::tokenize_list(tokenizer, text),
+            Self::ByT5(ref tokenizer) => MultiThreadedTokenizer::tokenize_list(tokenizer, text),
             Self::XLMRoberta(ref tokenizer) => {
                 MultiThreadedTokenizer::tokenize_list(tokenizer, text)
             }
@@ -1115,6 +1427,7 @@ impl TokenizerOption {
             Self::MBart50(ref tokenizer) => MultiThreadedTokenizer::tokenize_list(tokenizer, text),
             Self::M2M100(ref tokenizer) => MultiThreadedTokenizer::tokenize_list(tokenizer, text),
             Self::FNet(ref tokenizer) => MultiThreadedTokenizer::tokenize_list(tokenizer, text),
+            Self::Llama(ref tokenizer) => MultiThreadedTokenizer::tokenize_list(tokenizer, text),
             Self::Memnet(ref tokenizer) => MultiThreadedTokenizer::tokenize_list(tokenizer, text),
         }
     }
@@ -1148,6 +1461,9 @@ impl TokenizerOption {
             Self::T5(ref tokenizer) => {
                 tokenizer.decode(token_ids, skip_special_tokens, clean_up_tokenization_spaces)
             }
+            Self::ByT5(ref tokenizer) => {
+                tokenizer.decode(token_ids, skip_special_tokens, clean_up_tokenization_spaces)
+            }
             Self::XLMRoberta(ref tokenizer) => {
                 tokenizer.decode(token_ids, skip_special_tokens, clean_up_tokenization_spaces)
             }
@@ -1181,6 +1497,9 @@ impl TokenizerOption {
             Self::FNet(ref tokenizer) => {
                 tokenizer.decode(token_ids, skip_special_tokens, clean_up_tokenization_spaces)
             }
+            Self::Llama(ref tokenizer) => {
+                tokenizer.decode(token_ids, skip_special_tokens, clean_up_tokenization_spaces)
+            }
             Self::Memnet(ref tokenizer) => {
                 tokenizer.decode(token_ids, skip_special_tokens, clean_up_tokenization_spaces)
             }
@@ -1226,6 +1545,10 @@ impl TokenizerOption {
                 token_ids_with_offsets_1,
                 token_ids_with_offsets_2,
             ),
+            Self::ByT5(ref tokenizer) => tokenizer.build_input_with_special_tokens(
+                token_ids_with_offsets_1,
+                token_ids_with_offsets_2,
+            ),
             Self::Albert(ref tokenizer) => tokenizer.build_input_with_special_tokens(
                 token_ids_with_offsets_1,
                 token_ids_with_offsets_2,
@@ -1266,6 +1589,10 @@ impl TokenizerOption {
                 token_ids_with_offsets_1,
                 token_ids_with_offsets_2,
             ),
+            Self::Llama(ref tokenizer) => tokenizer.build_input_with_special_tokens(
+                token_ids_with_offsets_1,
+                token_ids_with_offsets_2,
+            ),
             Self::Memnet(ref tokenizer) => tokenizer.build_input_with_special_tokens(
                 token_ids_with_offsets_1,
                 token_ids_with_offsets_2,
@@ -1296,6 +1623,7 @@ impl TokenizerOption {
             Self::Bart(ref tokenizer) => tokenizer.convert_tokens_to_ids(tokens),
             Self::Marian(ref tokenizer) => tokenizer.convert_tokens_to_ids(tokens),
             Self::T5(ref tokenizer) => tokenizer.convert_tokens_to_ids(tokens),
+            Self::ByT5(ref tokenizer) => tokenizer.convert_tokens_to_ids(tokens),
             Self::XLMRoberta(ref tokenizer) => tokenizer.convert_tokens_to_ids(tokens),
             Self::Albert(ref tokenizer) => tokenizer.convert_tokens_to_ids(tokens),
             Self::XLNet(ref tokenizer) => tokenizer.convert_tokens_to_ids(tokens),
@@ -1307,11 +1635,39 @@ impl TokenizerOption {
             Self::MBart50(ref tokenizer) => tokenizer.convert_tokens_to_ids(tokens),
             Self::M2M100(ref tokenizer) => tokenizer.convert_tokens_to_ids(tokens),
             Self::FNet(ref tokenizer) => tokenizer.convert_tokens_to_ids(tokens),
+            Self::Llama(ref tokenizer) => tokenizer.convert_tokens_to_ids(tokens),
             Self::Memnet(ref tokenizer) => tokenizer.convert_tokens_to_ids(tokens),
 
         }
     }
 
+    /// Interface method to retrieve the vocabulary mapping from token ids to token strings
+    pub fn get_vocab_indices(&self) -> &HashMap<i64, String> {
+        match *self {
+            Self::Bert(ref tokenizer) => MultiThreadedTokenizer::vocab(tokenizer).indices(),
+            Self::Deberta(ref tokenizer) => MultiThreadedTokenizer::vocab(tokenizer).indices(),
+            Self::DebertaV2(ref tokenizer) => MultiThreadedTokenizer::vocab(tokenizer).indices(),
+            Self::Roberta(ref tokenizer) => MultiThreadedTokenizer::vocab(tokenizer).indices(),
+            Self::Bart(ref tokenizer) => MultiThreadedTokenizer::vocab(tokenizer).indices(),
+            Self::Marian(ref tokenizer) => MultiThreadedTokenizer::vocab(tokenizer).indices(),
+            Self::T5(ref tokenizer) => MultiThreadedTokenizer::vocab(tokenizer).indices(),
+            Self::ByT5(ref tokenizer) => MultiThreadedTokenizer::vocab(tokenizer).indices(),
+            Self::XLMRoberta(ref tokenizer) => MultiThreadedTokenizer::vocab(tokenizer).indices(),
+            Self::Albert(ref tokenizer) => MultiThreadedTokenizer::vocab(tokenizer).indices(),
+            Self::XLNet(ref tokenizer) => MultiThreadedTokenizer::vocab(tokenizer).indices(),
+            Self::GPT2(ref tokenizer) => MultiThreadedTokenizer::vocab(tokenizer).indices(),
+            Self::OpenAiGpt(ref tokenizer) => MultiThreadedTokenizer::vocab(tokenizer).indices(),
+            Self::Reformer(ref tokenizer) => MultiThreadedTokenizer::vocab(tokenizer).indices(),
+            Self::ProphetNet(ref tokenizer) => MultiThreadedTokenizer::vocab(tokenizer).indices(),
+            Self::Pegasus(ref tokenizer) => MultiThreadedTokenizer::vocab(tokenizer).indices(),
+            Self::MBart50(ref tokenizer) => MultiThreadedTokenizer::vocab(tokenizer).indices(),
+            Self::M2M100(ref tokenizer) => MultiThreadedTokenizer::vocab(tokenizer).indices(),
+            Self::FNet(ref tokenizer) => MultiThreadedTokenizer::vocab(tokenizer).indices(),
+            Self::Llama(ref tokenizer) => MultiThreadedTokenizer::vocab(tokenizer).indices(),
+            Self::Memnet(ref tokenizer) => MultiThreadedTokenizer::vocab(tokenizer).indices(),
+        }
+    }
+
     /// Interface method
     pub fn get_unk_id(&self) -> i64 {
         match *self {
@@ -1347,6 +1703,10 @@ impl TokenizerOption {
                 .special_values
                 .get(T5Vocab::unknown_value())
                 .expect("UNK token not found in vocabulary"),
+            Self::ByT5(ref tokenizer) => *MultiThreadedTokenizer::vocab(tokenizer)
+                .special_values
+                .get(ByT5Vocab::unknown_value())
+                .expect("UNK token not found in vocabulary"),
             Self::Albert(ref tokenizer) => *MultiThreadedTokenizer::vocab(tokenizer)
                 .special_values
                 .get(AlbertVocab::unknown_value())
@@ -1387,6 +1747,10 @@ impl TokenizerOption {
                 .special_values
                 .get(FNetVocab::unknown_value())
                 .expect("UNK token not found in vocabulary"),
+            Self::Llama(ref tokenizer) => *MultiThreadedTokenizer::vocab(tokenizer)
+                .special_values
+                .get(SentencePieceVocab::unknown_value())
+                .expect("UNK token not found in vocabulary"),
             Self::Memnet(ref tokenizer) => *MultiThreadedTokenizer::vocab(tokenizer)
                 .special_values()
                 .get(MemnetVocab::unknown_value())
@@ -1445,6 +1809,12 @@ impl TokenizerOption {
                     .get(T5Vocab::pad_value())
                     .expect("PAD token not found in vocabulary"),
             ),
+            Self::ByT5(ref tokenizer) => Some(
+                *MultiThreadedTokenizer::vocab(tokenizer)
+                    .special_values
+                    .get(ByT5Vocab::pad_value())
+                    .expect("PAD token not found in vocabulary"),
+            ),
             Self::Albert(ref tokenizer) => Some(
                 *MultiThreadedTokenizer::vocab(tokenizer)
                     .special_values
@@ -1496,6 +1866,7 @@ impl TokenizerOption {
             Self::Reformer(_) => None,
             Self::GPT2(_) => None,
             Self::OpenAiGpt(_) => None,
+            Self::Llama(_) => None,
         }
     }
 
@@ -1582,10 +1953,12 @@ impl TokenizerOption {
             ),
             Self::Marian(_) => None,
             Self::T5(_) => None,
+            Self::ByT5(_) => None,
             Self::GPT2(_) => None,
             Self::OpenAiGpt(_) => None,
             Self::Reformer(_) => None,
             Self::Pegasus(_) => None,
+            Self::Llama(_) => None,
         }
     }
 
@@ -1652,11 +2025,18 @@ impl TokenizerOption {
                     .get(MemnetVocab::bos_value())
                     .expect("BOS token not found in vocabulary"),
             ),
+            Self::Llama(ref tokenizer) => Some(
+                *MultiThreadedTokenizer::vocab(tokenizer)
+                    .special_values
+                    .get(SentencePieceVocab::bos_value())
+                    .expect("BOS token not found in vocabulary"),
+            ),
             Self::MBart50(_) => Some(0),
             Self::FNet(_) => None,
             Self::Bert(_) => None,
             Self::Marian(_) => Some(0),
             Self::T5(_) => None,
+            Self::ByT5(_) => None,
             Self::ProphetNet(_) => None,
             Self::OpenAiGpt(_) => None,
             Self::Reformer(_) => None,
@@ -1739,6 +2119,12 @@ impl TokenizerOption {
                     .get(T5Vocab::eos_value())
                     .expect("EOS token not found in vocabulary"),
             ),
+            Self::ByT5(ref tokenizer) => Some(
+                *MultiThreadedTokenizer::vocab(tokenizer)
+                    .special_values
+                    .get(ByT5Vocab::eos_value())
+                    .expect("EOS token not found in vocabulary"),
+            ),
             Self::Reformer(ref tokenizer) => Some(
                 *MultiThreadedTokenizer::vocab(tokenizer)
                     .special_values
@@ -1757,6 +2143,12 @@ impl TokenizerOption {
                     .get(MultiThreadedTokenizer::vocab(tokenizer).eos_value())
                     .unwrap_or(&1),
             ),
+            Self::Llama(ref tokenizer) => Some(
+                *MultiThreadedTokenizer::vocab(tokenizer)
+                    .special_values
+                    .get(SentencePieceVocab::eos_value())
+                    .expect("EOS token not found in vocabulary"),
+            ),
             Self::FNet(_) => None,
             Self::Bert(_) => None,
             Self::ProphetNet(_) => None,
@@ -1764,3 +2156,241 @@ impl TokenizerOption {
         }
     }
 }
+
+/// # Hidden states and attention weights of a pipeline model for one input
+/// Returned by the pipelines configured with `output_hidden_states` or `output_attentions`, giving access to the
+/// intermediate layers of the model (e.g. to extract the embeddings of a given layer). The tensors are placed on
+/// the CPU.
+#[derive(Debug)]
+pub struct IntermediateOutputs {
+    /// Index of the input (for the token classification pipelines, inputs longer than the maximum length are split
+    /// in several spans, each with its own `IntermediateOutputs`)
+    pub input_index: usize,
+    /// Hidden states of each layer, of shape (*sequence_length*, *hidden_size*). Depending on the architecture, the
+    /// first element may be the output of the embeddings.
+    pub all_hidden_states: Option<Vec<Tensor>>,
+    /// Attention weights of each layer, of shape (*num_heads*, *sequence_length*, *sequence_length*) for most
+    /// architectures. The sequence dimensions are padded to the longest input of the batch.
+    pub all_attentions: Option<Vec<Tensor>>,
+}
+
+impl IntermediateOutputs {
+    /// Splits the batched hidden states and attention weights returned by a model in one output per input,
+    /// removing the padding from the hidden states
+    pub(crate) fn from_batch(
+        input_indices: &[usize],
+        sequence_lengths: &[i64],
+        all_hidden_states: Option<Vec<Tensor>>,
+        all_attentions: Option<Vec<Tensor>>,
+    ) -> Vec<IntermediateOutputs> {
+        let all_hidden_states = all_hidden_states.map(|hidden_states| {
+            hidden_states
+                .iter()
+                .map(|layer_hidden_states| layer_hidden_states.to(Device::Cpu))
+                .collect::<Vec<Tensor>>()
+        });
+        let all_attentions = all_attentions.map(|attentions| {
+            attentions
+                .iter()
+                .map(|layer_attentions| layer_attentions.to(Device::Cpu))
+                .collect::<Vec<Tensor>>()
+        });
+        input_indices
+            .iter()
+            .zip(sequence_lengths.iter())
+            .enumerate()
+            .map(|(batch_index, (input_index, sequence_length))| {
+                let batch_index = batch_index as i64;
+                IntermediateOutputs {
+                    input_index: *input_index,
+                    all_hidden_states: all_hidden_states.as_ref().map(|hidden_states| {
+                        hidden_states
+                            .iter()
+                            .map(|layer_hidden_states| {
+                                layer_hidden_states
+                                    .get(batch_index)
+                                    .narrow(0, 0, *sequence_length)
+                            })
+                            .collect()
+                    }),
+                    all_attentions: all_attentions.as_ref().map(|attentions| {
+                        attentions
+                            .iter()
+                            .map(|layer_attentions| layer_attentions.get(batch_index))
+                            .collect()
+                    }),
+                }
+            })
+            .collect()
+    }
+}
+
+/// Flattens the attention weights of the ALBERT layer groups in a single list of layers
+pub(crate) fn flatten_albert_attentions(
+    all_attentions: Option<Vec<Vec<Tensor>>>,
+) -> Option<Vec<Tensor>> {
+    all_attentions.map(|attentions| attentions.into_iter().flatten().collect())
+}
+
+/// Returns the content stream hidden states (converted to batch first) and attention weights of XLNet
+pub(crate) fn batch_first_xlnet_outputs(
+    all_hidden_states: Option<Vec<(Tensor, Option<Tensor>)>>,
+    all_attentions: Option<Vec<(Tensor, Option<Tensor>)>>,
+) -> (Option<Vec<Tensor>>, Option<Vec<Tensor>>) {
+    (
+        all_hidden_states.map(|hidden_states| {
+            hidden_states
+                .into_iter()
+                .map(|(content_hidden_states, _)| content_hidden_states.transpose(0, 1))
+                .collect()
+        }),
+        all_attentions.map(|attentions| {
+            attentions
+                .into_iter()
+                .map(|(content_attentions, _)| content_attentions)
+                .collect()
+        }),
+    )
+}
+
+/// # Common interface for the task pipelines
+/// Implemented by the pipelines processing a batch of independent inputs, allowing to build generic
+/// wrappers (caching, batching, retrying, tracing...) and to compose pipelines (e.g. translation followed
+/// by summarization) with `PipelineChain`. The arguments of a call (e.g. source and target languages for
+/// translation, number of answers for question answering) are provided as `Options`, `run` uses their
+/// default values.
+///
+/// The conversation and zero-shot classification pipelines require additional state or arguments for
+/// each call and do not implement this trait.
+///
+/// # Example
+///
+/// ```no_run
+/// # fn main() -> anyhow::Result<()> {
+/// use rust_bert::pipelines::common::{Pipeline, PipelineChain};
+/// use rust_bert::pipelines::sentiment::SentimentModel;
+/// use rust_bert::pipelines::summarization::SummarizationModel;
+/// use rust_bert::pipelines::translation::{Language, TranslationModelBuilder, TranslationOptions};
+///
+/// let summarization_model = SummarizationModel::new(Default::default())?;
+/// let sentiment_model = SentimentModel::new(Default::default())?;
+///
+/// let summary_sentiment = PipelineChain::new(summarization_model, sentiment_model);
+/// let output = summary_sentiment.run(&["This is a long text to summarize before classification"])?;
+///
+/// let translation_model = TranslationModelBuilder::new()
+///     .with_source_languages(vec![Language::French])
+///     .with_target_languages(vec![Language::English])
+///     .create_model()?;
+/// let translation_sentiment =
+///     PipelineChain::new(translation_model, SentimentModel::new(Default::default())?);
+/// let options = (
+///     TranslationOptions {
+///         source_language: Some(Language::French),
+///         target_language: Some(Language::English),
+///     },
+///     (),
+/// );
+/// let output = translation_sentiment.run_with_options(&["Ce film est excellent"], &options)?;
+/// # Ok(())
+/// # }
+/// ```
+pub trait Pipeline<Input, Output> {
+    /// Arguments of a call of the pipeline (`()` for the pipelines without arguments)
+    type Options: Default;
+
+    /// Runs the pipeline on a batch of inputs with the given options, returning one output per input
+    fn run_with_options(
+        &self,
+        inputs: &[Input],
+        options: &Self::Options,
+    ) -> Result<Vec<Output>, RustBertError>;
+
+    /// Runs the pipeline on a batch of inputs with the default options, returning one output per input
+    fn run(&self, inputs: &[Input]) -> Result<Vec<Output>, RustBertError> {
+        self.run_with_options(inputs, &Self::Options::default())
+    }
+}
+
+/// # Composition of two pipelines
+/// Runs the second pipeline on the outputs of the first one. The options of the chain are the pair of the options
+/// of the first and second pipelines.
+pub struct PipelineChain<A, B, Intermediate> {
+    first: A,
+    second: B,
+    intermediate: PhantomData<fn() -> Intermediate>,
+}
+
+impl<A, B, Intermediate> PipelineChain<A, B, Intermediate> {
+    /// Creates a new pipeline running `second` on the outputs of `first`
+    ///
+    /// # Arguments
+    ///
+    /// * `first` - First pipeline, processing the inputs
+    /// * `second` - Second pipeline, processing the outputs of the first pipeline
+    pub fn new(first: A, second: B) -> Self {
+        PipelineChain {
+            first,
+            second,
+            intermediate: PhantomData,
+        }
+    }
+}
+
+impl<Input, Intermediate, Output, A, B> Pipeline<Input, Output>
+    for PipelineChain<A, B, Intermediate>
+where
+    A: Pipeline<Input, Intermediate>,
+    B: Pipeline<Intermediate, Output>,
+{
+    type Options = (A::Options, B::Options);
+
+    fn run_with_options(
+        &self,
+        inputs: &[Input],
+        options: &Self::Options,
+    ) -> Result<Vec<Output>, RustBertError> {
+        let intermediate = self.first.run_with_options(inputs, &options.0)?;
+        self.second.run_with_options(&intermediate, &options.1)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    struct SuffixPipeline(&'static str);
+
+    impl Pipeline<String, String> for SuffixPipeline {
+        type Options = Option<String>;
+
+        fn run_with_options(
+            &self,
+            inputs: &[String],
+            options: &Option<String>,
+        ) -> Result<Vec<String>, RustBertError> {
+            let suffix = options.as_deref().unwrap_or(self.0);
+            Ok(inputs
+                .iter()
+                .map(|input| format!("{}{}", input, suffix))
+                .collect())
+        }
+    }
+
+    #[test]
+    fn test_chain_options() -> Result<(), RustBertError> {
+        let chain = PipelineChain::new(SuffixPipeline("-a"), SuffixPipeline("-b"));
+        let inputs = vec!["x".to_string(), "y".to_string()];
+
+        assert_eq!(chain.run(&inputs)?, ["x-a-b", "y-a-b"]);
+        assert_eq!(
+            chain.run_with_options(&inputs, &(Some("-c".to_string()), None))?,
+            ["x-c-b", "y-c-b"]
+        );
+        assert_eq!(
+            chain.run_with_options(&inputs, &(None, Some("-d".to_string())))?,
+            ["x-a-d", "y-a-d"]
+        );
+        Ok(())
+    }
+}
@@ -0,0 +1,321 @@
+// Copyright 2022 Guillaume Becquin
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//     http://www.apache.org/licenses/LICENSE-2.0
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! # Composite pipelines
+//! Ready-made combinations of pipelines for common multi-stage tasks:
+//! - `CrossLingualSummarizationModel`: translates the input to English, summarizes it and translates the summary back
+//! to the source language
+//! - `LanguageDetectionTranslationModel`: detects the language of each input with a sequence classification model and
+//! translates it to a target language
+//!
+//! All stages are loaded on the same device. Inputs are processed in chunks of `batch_size` texts, each chunk going
+//! through all the stages before the next one is processed, limiting the memory used by intermediate results.
+//!
+//! ```no_run
+//! # fn main() -> anyhow::Result<()> {
+//! use rust_bert::m2m_100::{
+//!     M2M100ConfigResources, M2M100MergesResources, M2M100ModelResources, M2M100SourceLanguages,
+//!     M2M100TargetLanguages, M2M100VocabResources,
+//! };
+//! use rust_bert::pipelines::common::ModelType;
+//! use rust_bert::pipelines::composite::{
+//!     CrossLingualSummarizationConfig, CrossLingualSummarizationModel,
+//! };
+//! use rust_bert::pipelines::translation::{Language, TranslationConfig};
+//! use rust_bert::resources::RemoteResource;
+//! use tch::Device;
+//!
+//! let translation_config = TranslationConfig::new(
+//!     ModelType::M2M100,
+//!     RemoteResource::from_pretrained(M2M100ModelResources::M2M100_418M),
+//!     RemoteResource::from_pretrained(M2M100ConfigResources::M2M100_418M),
+//!     RemoteResource::from_pretrained(M2M100VocabResources::M2M100_418M),
+//!     RemoteResource::from_pretrained(M2M100MergesResources::M2M100_418M),
+//!     M2M100SourceLanguages::M2M100_418M,
+//!     M2M100TargetLanguages::M2M100_418M,
+//!     Device::cuda_if_available(),
+//! );
+//! let config = CrossLingualSummarizationConfig::new(
+//!     translation_config,
+//!     Default::default(),
+//!     Device::cuda_if_available(),
+//! );
+//! let model = CrossLingualSummarizationModel::new(config)?;
+//!
+//! let input = ["Le télescope spatial James Webb a été lancé en décembre 2021..."];
+//! let output = model.summarize(&input, Language::French)?;
+//! # Ok(())
+//! # }
+//! ```
+
+use crate::common::error::RustBertError;
+use crate::pipelines::sequence_classification::{
+    SequenceClassificationConfig, SequenceClassificationModel,
+};
+use crate::pipelines::summarization::{SummarizationConfig, SummarizationModel};
+use crate::pipelines::translation::{Language, TranslationConfig, TranslationModel};
+use std::collections::HashMap;
+use tch::Device;
+
+const DEFAULT_BATCH_SIZE: usize = 16;
+
+/// # Configuration for cross-lingual summarization
+pub struct CrossLingualSummarizationConfig {
+    /// Translation configuration. The model must be able to translate from the source languages to English and back (e.g. M2M100 or mBART-50)
+    pub translation: TranslationConfig,
+    /// Summarization configuration for an English summarization model
+    pub summarization: SummarizationConfig,
+    /// Device shared by all stages
+    pub device: Device,
+    /// Number of texts processed by all stages at once
+    pub batch_size: usize,
+}
+
+impl CrossLingualSummarizationConfig {
+    /// Create a new `CrossLingualSummarizationConfig`. The device of the translation and summarization
+    /// configurations is overwritten by the device provided.
+    ///
+    /// # Arguments
+    ///
+    /// * `translation` - `TranslationConfig` for a model translating between the source languages and English
+    /// * `summarization` - `SummarizationConfig` for an English summarization model
+    /// * `device` - Device to load all models on
+    pub fn new(
+        translation: TranslationConfig,
+        summarization: SummarizationConfig,
+        device: Device,
+    ) -> CrossLingualSummarizationConfig {
+        CrossLingualSummarizationConfig {
+            translation,
+            summarization,
+            device,
+            batch_size: DEFAULT_BATCH_SIZE,
+        }
+    }
+}
+
+/// # Cross-lingual summarization model
+/// Summarizes texts in a language other than English by translating them to English, summarizing
+/// them with an English summarization model and translating the summaries back to the source language.
+pub struct CrossLingualSummarizationModel {
+    translation_model: TranslationModel,
+    summarization_model: SummarizationModel,
+    batch_size: usize,
+}
+
+impl CrossLingualSummarizationModel {
+    /// Build a new `CrossLingualSummarizationModel`
+    ///
+    /// # Arguments
+    ///
+    /// * `config` - `CrossLingualSummarizationConfig` containing the translation and summarization configurations
+    pub fn new(
+        config: CrossLingualSummarizationConfig,
+    ) -> Result<CrossLingualSummarizationModel, RustBertError> {
+        let mut translation_config = config.translation;
+        let mut summarization_config = config.summarization;
+        translation_config.device = config.device;
+        summarization_config.device = config.device;
+
+        let translation_model = TranslationModel::new(translation_config)?;
+        if !translation_model
+            .get_supported_source_languages()
+            .contains(&Language::English)
+            || !translation_model
+                .get_supported_target_languages()
+                .contains(&Language::English)
+        {
+            return Err(RustBertError::InvalidConfigurationError(
+                "The translation model must support English as a source and target language"
+                    .to_string(),
+            ));
+        }
+        let summarization_model = SummarizationModel::new(summarization_config)?;
+
+        Ok(CrossLingualSummarizationModel {
+            translation_model,
+            summarization_model,
+            batch_size: config.batch_size.max(1),
+        })
+    }
+
+    /// Summarize texts provided in the source language
+    ///
+    /// # Arguments
+    ///
+    /// * `texts` - `&[&str]` Array of texts to summarize.
+    /// * `source_language` - Language of the input texts. The summaries are returned in the same language.
+    ///
+    /// # Returns
+    /// * `Vec<String>` Summarized texts, in the source language
+    pub fn summarize<S>(
+        &self,
+        texts: &[S],
+        source_language: Language,
+    ) -> Result<Vec<String>, RustBertError>
+    where
+        S: AsRef<str> + Sync,
+    {
+        let mut output = Vec::with_capacity(texts.len());
+        for chunk in texts.chunks(self.batch_size) {
+            if source_language == Language::English {
+                output.extend(self.summarization_model.summarize(chunk));
+                continue;
+            }
+            let translated =
+                self.translation_model
+                    .translate(chunk, source_language, Language::English)?;
+            let summaries = self.summarization_model.summarize(&translated);
+            output.extend(self.translation_model.translate(
+                &summaries,
+                Language::English,
+                source_language,
+            )?);
+        }
+        Ok(output)
+    }
+}
+
+/// # Configuration for language detection followed by translation
+pub struct LanguageDetectionTranslationConfig {
+    /// Sequence classification configuration for the language detection model. The labels of the model must be ISO 639-1 language codes (e.g. `en`, `fr`)
+    pub detection: SequenceClassificationConfig,
+    /// Translation configuration for a multilingual translation model (e.g. M2M100 or mBART-50)
+    pub translation: TranslationConfig,
+    /// Device shared by all stages
+    pub device: Device,
+    /// Number of texts processed by all stages at once
+    pub batch_size: usize,
+}
+
+impl LanguageDetectionTranslationConfig {
+    /// Create a new `LanguageDetectionTranslationConfig`. The device of the detection and translation
+    /// configurations is overwritten by the device provided.
+    ///
+    /// # Arguments
+    ///
+    /// * `detection` - `SequenceClassificationConfig` for a language identification model with ISO 639-1 codes as labels
+    /// * `translation` - `TranslationConfig` for a multilingual translation model
+    /// * `device` - Device to load all models on
+    pub fn new(
+        detection: SequenceClassificationConfig,
+        translation: TranslationConfig,
+        device: Device,
+    ) -> LanguageDetectionTranslationConfig {
+        LanguageDetectionTranslationConfig {
+            detection,
+            translation,
+            device,
+            batch_size: DEFAULT_BATCH_SIZE,
+        }
+    }
+}
+
+/// # Language detection and translation model
+/// Detects the language of each input with a sequence classification model and translates
+/// it to the target language. Texts already in the target language are returned unchanged.
+pub struct LanguageDetectionTranslationModel {
+    detection_model: SequenceClassificationModel,
+    translation_model: TranslationModel,
+    batch_size: usize,
+}
+
+impl LanguageDetectionTranslationModel {
+    /// Build a new `LanguageDetectionTranslationModel`
+    ///
+    /// # Arguments
+    ///
+    /// * `config` - `LanguageDetectionTranslationConfig` containing the detection and translation configurations
+    pub fn new(
+        config: LanguageDetectionTranslationConfig,
+    ) -> Result<LanguageDetectionTranslationModel, RustBertError> {
+        let mut detection_config = config.detection;
+        let mut translation_config = config.translation;
+        detection_config.device = config.device;
+        translation_config.device = config.device;
+
+        let detection_model = SequenceClassificationModel::new(detection_config)?;
+        let translation_model = TranslationModel::new(translation_config)?;
+
+        Ok(LanguageDetectionTranslationModel {
+            detection_model,
+            translation_model,
+            batch_size: config.batch_size.max(1),
+        })
+    }
+
+    /// Maps a label of the detection model to one of the source languages supported by the translation model
+    fn get_language(&self, label: &str) -> Result<Language, RustBertError> {
+        self.translation_model
+            .get_supported_source_languages()
+            .iter()
+            .find(|language| language.get_iso_639_1_code() == label)
+            .copied()
+            .ok_or_else(|| {
+                RustBertError::ValueError(format!(
+                    "Detected language {} is not supported by the translation model",
+                    label
+                ))
+            })
+    }
+
+    /// Detect the language of the texts provided and translate them to the target language
+    ///
+    /// # Arguments
+    ///
+    /// * `texts` - `&[&str]` Array of texts to translate.
+    /// * `target_language` - Language to translate the texts to
+    ///
+    /// # Returns
+    /// * `Vec<(Language, String)>` Detected source language and translated text for each input
+    pub fn translate(
+        &self,
+        texts: &[&str],
+        target_language: Language,
+    ) -> Result<Vec<(Language, String)>, RustBertError> {
+        let mut output = Vec::with_capacity(texts.len());
+        for chunk in texts.chunks(self.batch_size) {
+            let languages = self
+                .detection_model
+                .predict(chunk)
+                .iter()
+                .map(|label| self.get_language(&label.text))
+                .collect::<Result<Vec<Language>, RustBertError>>()?;
+
+            let mut groups: HashMap<Language, Vec<usize>> = HashMap::new();
+            for (position, language) in languages.iter().enumerate() {
+                groups.entry(*language).or_default().push(position);
+            }
+
+            let mut translations = chunk
+                .iter()
+                .map(|text| text.to_string())
+                .collect::<Vec<String>>();
+            for (language, positions) in groups {
+                if language == target_language {
+                    continue;
+                }
+                let group_texts = positions
+                    .iter()
+                    .map(|&position| chunk[position])
+                    .collect::<Vec<&str>>();
+                let group_translations =
+                    self.translation_model
+                        .translate(&group_texts, language, target_language)?;
+                for (position, translation) in positions.into_iter().zip(group_translations) {
+                    translations[position] = translation;
+                }
+            }
+            output.extend(languages.into_iter().zip(translations));
+        }
+        Ok(output)
+    }
+}
@@ -0,0 +1,889 @@
+// Copyright 2022 Guillaume Becquin
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//     http://www.apache.org/licenses/LICENSE-2.0
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! # Validation of the model configurations
+//! A configuration inconsistent with the architecture of the model (e.g. edited by hand, or paired with the wrong
+//! tokenizer) usually builds a model without error and fails with a tensor shape error in the middle of a forward
+//! pass. `ConfigOption::validate` checks a configuration against the expectations of its architecture when the
+//! pipelines are created, and reports all the issues found at once:
+//! - the hidden size is divisible by the number of attention heads (of the encoder and decoder)
+//! - the number of key-value heads (grouped-query and multi-query attention) divides the number of attention heads
+//! - the ids of the tokenizer vocabulary fit in the embeddings (the embeddings may be larger than the vocabulary)
+//! - the requested maximum length does not exceed the number of position embeddings (for the architectures with
+//!   learned or precomputed position embeddings)
+//!
+//! The issues are aggregated in a single `RustBertError::InvalidConfigurationError`, for example:
+//!
+//! ```text
+//! Invalid configuration (2 issues):
+//! - num_attention_heads: the hidden size (768) is not divisible by the number of attention heads (10)
+//! - vocab_size: the tokenizer vocabulary contains ids up to 50264 but the model only has 30522 embeddings
+//! ```
+//!
+//! The sequence classification, token classification, zero-shot classification, natural language inference,
+//! question answering, spelling correction, sentence embeddings and text generation pipelines validate their
+//! configuration on creation. The validation can also be run on a configuration loaded manually:
+//!
+//! ```no_run
+//! # fn main() -> anyhow::Result<()> {
+//! use rust_bert::pipelines::common::{ConfigOption, ModelType};
+//!
+//! let config = ConfigOption::from_file(ModelType::Bert, "path/to/config.json")?;
+//! for issue in config.check(None, Some(512)) {
+//!     println!("{}", issue);
+//! }
+//! config.validate(None, Some(512))?;
+//! # Ok(())
+//! # }
+//! ```
+//!
+//! # Compatibility of a set of resources
+//! `compatibility_check` checks that a configuration, a tokenizer and model weights fit together before building the
+//! model, for example when assembling resources from different sources. It reports, in addition to the issues above:
+//! - tokenizer vocabularies that cannot be loaded (e.g. missing special tokens)
+//! - special token ids of the configuration (`pad_token_id`, `bos_token_id`, `eos_token_id`, `sep_token_id`,
+//!   `decoder_start_token_id`) outside of the embeddings, absent from the tokenizer vocabulary or different from the
+//!   special tokens of the tokenizer
+//! - input embeddings of the weights whose number of rows differs from the vocabulary size of the configuration
+//! - heads expected for a task (classification or question answering) absent from the weights, or classification
+//!   configurations without labels
+//!
+//! Only the names and shapes of the weights are used: the weights are read on the CPU and dropped without building
+//! the model or moving them to the target device.
+//!
+//! ```no_run
+//! # fn main() -> anyhow::Result<()> {
+//! use rust_bert::pipelines::common::ModelType;
+//! use rust_bert::pipelines::config_validation::{compatibility_check, CompatibilityResources};
+//! use rust_bert::pipelines::pretrained_registry::PretrainedTask;
+//! use rust_bert::resources::LocalResource;
+//! use std::path::PathBuf;
+//!
+//! let resources = CompatibilityResources::new(
+//!     ModelType::Roberta,
+//!     LocalResource::from(PathBuf::from("path/to/config.json")),
+//! )
+//! .with_tokenizer(
+//!     LocalResource::from(PathBuf::from("path/to/vocab.json")),
+//!     Some(LocalResource::from(PathBuf::from("path/to/merges.txt"))),
+//! )
+//! .with_weights(LocalResource::from(PathBuf::from("path/to/rust_model.ot")))
+//! .with_task(PretrainedTask::SequenceClassification);
+//! for issue in compatibility_check(&resources)? {
+//!     println!("{}", issue);
+//! }
+//! # Ok(())
+//! # }
+//! ```
+
+use crate::common::error::RustBertError;
+use crate::pipelines::common::{ConfigOption, ModelType, TokenizerOption};
+use crate::pipelines::pretrained_registry::PretrainedTask;
+use crate::resources::{Resource, ResourceProvider};
+use std::fmt;
+use std::fs;
+use std::io::Cursor;
+use tch::Tensor;
+
+/// # Issue found in a configuration
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ConfigIssue {
+    /// Name of the configuration field at fault
+    pub field: &'static str,
+    /// Human-readable description of the issue
+    pub message: String,
+}
+
+impl fmt::Display for ConfigIssue {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}: {}", self.field, self.message)
+    }
+}
+
+/// Dimensions of a configuration checked against the expectations of the architecture, with the name of their field
+struct ArchitectureDimensions {
+    hidden_size: (&'static str, i64),
+    attention_heads: Vec<(&'static str, i64)>,
+    key_value_heads: Option<(&'static str, i64)>,
+    vocab_size: (&'static str, i64),
+    max_positions: Option<(&'static str, i64)>,
+}
+
+impl ArchitectureDimensions {
+    fn new(config: &ConfigOption) -> ArchitectureDimensions {
+        match config {
+            ConfigOption::Bart(config) | ConfigOption::Marian(config) => ArchitectureDimensions {
+                hidden_size: ("d_model", config.d_model),
+                attention_heads: vec![
+                    ("encoder_attention_heads", config.encoder_attention_heads),
+                    ("decoder_attention_heads", config.decoder_attention_heads),
+                ],
+                key_value_heads: None,
+                vocab_size: ("vocab_size", config.vocab_size),
+                max_positions: Some(("max_position_embeddings", config.max_position_embeddings)),
+            },
+            ConfigOption::Pegasus(config)
+            | ConfigOption::MBart(config)
+            | ConfigOption::M2M100(config) => ArchitectureDimensions {
+                hidden_size: ("d_model", config.d_model),
+                attention_heads: vec![
+                    ("encoder_attention_heads", config.encoder_attention_heads),
+                    ("decoder_attention_heads", config.decoder_attention_heads),
+                ],
+                key_value_heads: None,
+                vocab_size: ("vocab_size", config.vocab_size),
+                max_positions: Some(("max_position_embeddings", config.max_position_embeddings)),
+            },
+            ConfigOption::Bert(config) | ConfigOption::Roberta(config) => ArchitectureDimensions {
+                hidden_size: ("hidden_size", config.hidden_size),
+                attention_heads: vec![("num_attention_heads", config.num_attention_heads)],
+                key_value_heads: None,
+                vocab_size: ("vocab_size", config.vocab_size),
+                max_positions: Some(("max_position_embeddings", config.max_position_embeddings)),
+            },
+            ConfigOption::DistilBert(config) => ArchitectureDimensions {
+                hidden_size: ("dim", config.dim),
+                attention_heads: vec![("n_heads", config.n_heads)],
+                key_value_heads: None,
+                vocab_size: ("vocab_size", config.vocab_size),
+                max_positions: Some(("max_position_embeddings", config.max_position_embeddings)),
+            },
+            // Without absolute position embeddings, DeBERTa only relies on relative positions
+            ConfigOption::Deberta(config) => ArchitectureDimensions {
+                hidden_size: ("hidden_size", config.hidden_size),
+                attention_heads: vec![("num_attention_heads", config.num_attention_heads)],
+                key_value_heads: None,
+                vocab_size: ("vocab_size", config.vocab_size),
+                max_positions: config
+                    .position_biased_input
+                    .unwrap_or(true)
+                    .then_some(("max_position_embeddings", config.max_position_embeddings)),
+            },
+            ConfigOption::DebertaV2(config) => ArchitectureDimensions {
+                hidden_size: ("hidden_size", config.hidden_size),
+                attention_heads: vec![("num_attention_heads", config.num_attention_heads)],
+                key_value_heads: None,
+                vocab_size: ("vocab_size", config.vocab_size),
+                max_positions: config
+                    .position_biased_input
+                    .unwrap_or(true)
+                    .then_some(("max_position_embeddings", config.max_position_embeddings)),
+            },
+            ConfigOption::Electra(config) => ArchitectureDimensions {
+                hidden_size: ("hidden_size", config.hidden_size),
+                attention_heads: vec![("num_attention_heads", config.num_attention_heads)],
+                key_value_heads: None,
+                vocab_size: ("vocab_size", config.vocab_size),
+                max_positions: Some(("max_position_embeddings", config.max_position_embeddings)),
+            },
+            ConfigOption::MobileBert(config) => ArchitectureDimensions {
+                hidden_size: ("hidden_size", config.hidden_size),
+                attention_heads: vec![("num_attention_heads", config.num_attention_heads)],
+                key_value_heads: None,
+                vocab_size: ("vocab_size", config.vocab_size),
+                max_positions: Some(("max_position_embeddings", config.max_position_embeddings)),
+            },
+            ConfigOption::Albert(config) => ArchitectureDimensions {
+                hidden_size: ("hidden_size", config.hidden_size),
+                attention_heads: vec![("num_attention_heads", config.num_attention_heads)],
+                key_value_heads: None,
+                vocab_size: ("vocab_size", config.vocab_size),
+                max_positions: Some(("max_position_embeddings", config.max_position_embeddings)),
+            },
+            ConfigOption::Longformer(config) => ArchitectureDimensions {
+                hidden_size: ("hidden_size", config.hidden_size),
+                attention_heads: vec![("num_attention_heads", config.num_attention_heads)],
+                key_value_heads: None,
+                vocab_size: ("vocab_size", config.vocab_size),
+                max_positions: Some(("max_position_embeddings", config.max_position_embeddings)),
+            },
+            ConfigOption::ProphetNet(config) => ArchitectureDimensions {
+                hidden_size: ("hidden_size", config.hidden_size),
+                attention_heads: vec![
+                    (
+                        "num_encoder_attention_heads",
+                        config.num_encoder_attention_heads,
+                    ),
+                    (
+                        "num_decoder_attention_heads",
+                        config.num_decoder_attention_heads,
+                    ),
+                ],
+                key_value_heads: None,
+                vocab_size: ("vocab_size", config.vocab_size),
+                max_positions: Some(("max_position_embeddings", config.max_position_embeddings)),
+            },
+            // T5 sets the size of the attention heads (`d_kv`) independently of the hidden size, and uses relative
+            // position buckets
+            ConfigOption::T5(config) => ArchitectureDimensions {
+                hidden_size: ("d_model", config.d_model),
+                attention_heads: vec![],
+                key_value_heads: None,
+                vocab_size: ("vocab_size", config.vocab_size),
+                max_positions: None,
+            },
+            ConfigOption::XLNet(config) => ArchitectureDimensions {
+                hidden_size: ("d_model", config.d_model),
+                attention_heads: vec![("n_head", config.n_head)],
+                key_value_heads: None,
+                vocab_size: ("vocab_size", config.vocab_size),
+                max_positions: None,
+            },
+            ConfigOption::GPT2(config) | ConfigOption::OpenAiGpt(config) => {
+                ArchitectureDimensions {
+                    hidden_size: ("n_embd", config.n_embd),
+                    attention_heads: vec![("n_head", config.n_head)],
+                    key_value_heads: None,
+                    vocab_size: ("vocab_size", config.vocab_size),
+                    max_positions: Some(("n_positions", config.n_positions)),
+                }
+            }
+            // Reformer sets the size of the attention heads (`attention_head_size`) independently of the hidden size
+            ConfigOption::Reformer(config) => ArchitectureDimensions {
+                hidden_size: ("hidden_size", config.hidden_size),
+                attention_heads: vec![],
+                key_value_heads: None,
+                vocab_size: ("vocab_size", config.vocab_size),
+                max_positions: Some(("max_position_embeddings", config.max_position_embeddings)),
+            },
+            ConfigOption::GPTNeo(config) => ArchitectureDimensions {
+                hidden_size: ("hidden_size", config.hidden_size),
+                attention_heads: vec![("num_heads", config.num_heads)],
+                key_value_heads: None,
+                vocab_size: ("vocab_size", config.vocab_size),
+                max_positions: Some(("max_position_embeddings", config.max_position_embeddings)),
+            },
+            // The single key and value head of the multi-query attention is shared by any number of heads
+            ConfigOption::GPTBigCode(config) => ArchitectureDimensions {
+                hidden_size: ("n_embd", config.n_embd),
+                attention_heads: vec![("n_head", config.n_head)],
+                key_value_heads: None,
+                vocab_size: ("vocab_size", config.vocab_size),
+                max_positions: Some(("n_positions", config.n_positions)),
+            },
+            // Rotary position embeddings are computed for any position
+            ConfigOption::GPTNeoX(config) => ArchitectureDimensions {
+                hidden_size: ("hidden_size", config.hidden_size),
+                attention_heads: vec![("num_attention_heads", config.num_attention_heads)],
+                key_value_heads: None,
+                vocab_size: ("vocab_size", config.vocab_size),
+                max_positions: None,
+            },
+            ConfigOption::Falcon(config) => ArchitectureDimensions {
+                hidden_size: ("hidden_size", config.hidden_size),
+                attention_heads: vec![("num_attention_heads", config.num_attention_heads)],
+                key_value_heads: config
+                    .num_kv_heads
+                    .map(|num_kv_heads| ("num_kv_heads", num_kv_heads)),
+                vocab_size: ("vocab_size", config.vocab_size),
+                max_positions: None,
+            },
+            // ALiBi attention biases do not rely on position embeddings
+            ConfigOption::Bloom(config) => ArchitectureDimensions {
+                hidden_size: ("hidden_size", config.hidden_size),
+                attention_heads: vec![("n_head", config.n_head)],
+                key_value_heads: None,
+                vocab_size: ("vocab_size", config.vocab_size),
+                max_positions: None,
+            },
+            ConfigOption::Llama(config) => ArchitectureDimensions {
+                hidden_size: ("hidden_size", config.hidden_size),
+                attention_heads: vec![("num_attention_heads", config.num_attention_heads)],
+                key_value_heads: config
+                    .num_key_value_heads
+                    .map(|num_key_value_heads| ("num_key_value_heads", num_key_value_heads)),
+                vocab_size: ("vocab_size", config.vocab_size),
+                max_positions: None,
+            },
+            // FNet mixes the tokens with Fourier transforms instead of attention
+            ConfigOption::FNet(config) => ArchitectureDimensions {
+                hidden_size: ("hidden_size", config.hidden_size),
+                attention_heads: vec![],
+                key_value_heads: None,
+                vocab_size: ("vocab_size", config.vocab_size),
+                max_positions: Some(("max_position_embeddings", config.max_position_embeddings)),
+            },
+        }
+    }
+}
+
+impl ConfigOption {
+    /// Checks the configuration against the expectations of its architecture and returns the issues found
+    ///
+    /// # Arguments
+    ///
+    /// * `tokenizer` - Optional tokenizer used with the model, whose vocabulary must fit in the embeddings
+    /// * `max_length` - Optional maximum length (in tokens) of the sequences processed by the model
+    ///
+    /// # Returns
+    ///
+    /// * `Vec<ConfigIssue>` Issues found in the configuration (empty if the configuration is valid)
+    pub fn check(
+        &self,
+        tokenizer: Option<&TokenizerOption>,
+        max_length: Option<i64>,
+    ) -> Vec<ConfigIssue> {
+        let dimensions = ArchitectureDimensions::new(self);
+        let mut issues = vec![];
+
+        let (hidden_size_field, hidden_size) = dimensions.hidden_size;
+        if hidden_size <= 0 {
+            issues.push(ConfigIssue {
+                field: hidden_size_field,
+                message: format!("the hidden size ({}) must be positive", hidden_size),
+            });
+        }
+        for &(heads_field, num_heads) in dimensions.attention_heads.iter() {
+            if num_heads <= 0 {
+                issues.push(ConfigIssue {
+                    field: heads_field,
+                    message: format!(
+                        "the number of attention heads ({}) must be positive",
+                        num_heads
+                    ),
+                });
+            } else if hidden_size > 0 && hidden_size % num_heads != 0 {
+                issues.push(ConfigIssue {
+                    field: heads_field,
+                    message: format!(
+                        "the hidden size ({}) is not divisible by the number of attention heads ({})",
+                        hidden_size, num_heads
+                    ),
+                });
+            }
+        }
+        if let (Some((key_value_heads_field, num_key_value_heads)), Some(&(_, num_heads))) = (
+            dimensions.key_value_heads,
+            dimensions.attention_heads.first(),
+        ) {
+            if num_key_value_heads <= 0 || num_heads % num_key_value_heads != 0 {
+                issues.push(ConfigIssue {
+                    field: key_value_heads_field,
+                    message: format!(
+                        "the number of key-value heads ({}) does not divide the number of attention heads ({})",
+                        num_key_value_heads, num_heads
+                    ),
+                });
+            }
+        }
+
+        let (vocab_size_field, vocab_size) = dimensions.vocab_size;
+        if vocab_size <= 0 {
+            issues.push(ConfigIssue {
+                field: vocab_size_field,
+                message: format!("the vocabulary size ({}) must be positive", vocab_size),
+            });
+        } else if let Some(max_id) =
+            tokenizer.and_then(|tokenizer| tokenizer.get_vocab_indices().keys().max().copied())
+        {
+            if max_id >= vocab_size {
+                issues.push(ConfigIssue {
+                    field: vocab_size_field,
+                    message: format!(
+                        "the tokenizer vocabulary contains ids up to {} but the model only has {} embeddings",
+                        max_id, vocab_size
+                    ),
+                });
+            }
+        }
+
+        if let (Some((max_positions_field, max_positions)), Some(max_length)) =
+            (dimensions.max_positions, max_length)
+        {
+            if max_length > max_positions {
+                issues.push(ConfigIssue {
+                    field: max_positions_field,
+                    message: format!(
+                        "the maximum length requested ({}) exceeds the number of position embeddings ({})",
+                        max_length, max_positions
+                    ),
+                });
+            }
+        }
+        issues
+    }
+
+    /// Validates the configuration against the expectations of its architecture (see `ConfigOption::check`)
+    ///
+    /// # Arguments
+    ///
+    /// * `tokenizer` - Optional tokenizer used with the model, whose vocabulary must fit in the embeddings
+    /// * `max_length` - Optional maximum length (in tokens) of the sequences processed by the model
+    ///
+    /// # Returns
+    ///
+    /// * `Result<(), RustBertError>` a `RustBertError::InvalidConfigurationError` listing all the issues found
+    pub fn validate(
+        &self,
+        tokenizer: Option<&TokenizerOption>,
+        max_length: Option<i64>,
+    ) -> Result<(), RustBertError> {
+        let issues = self.check(tokenizer, max_length);
+        if issues.is_empty() {
+            return Ok(());
+        }
+        let mut message = format!(
+            "Invalid configuration ({} issue{}):",
+            issues.len(),
+            if issues.len() > 1 { "s" } else { "" }
+        );
+        for issue in issues {
+            message.push_str(&format!("\n- {}", issue));
+        }
+        Err(RustBertError::InvalidConfigurationError(message))
+    }
+}
+
+/// Special token fields of the configurations checked against the tokenizer and the embeddings
+const SPECIAL_TOKEN_FIELDS: [&str; 5] = [
+    "pad_token_id",
+    "bos_token_id",
+    "eos_token_id",
+    "sep_token_id",
+    "decoder_start_token_id",
+];
+
+/// Names of the input embeddings variables of the supported architectures
+const INPUT_EMBEDDINGS_NAMES: [&str; 7] = [
+    "word_embeddings",
+    "word_embedding",
+    "embed_tokens",
+    "embed_in",
+    "shared",
+    "wte",
+    "tokens_embed",
+];
+
+/// # Resources checked for compatibility
+/// The configuration is required, the tokenizer and the weights are optional and checked when provided.
+pub struct CompatibilityResources {
+    /// Model type of the configuration and tokenizer
+    pub model_type: ModelType,
+    /// Config resource
+    pub config_resource: Box<dyn ResourceProvider + Send>,
+    /// Vocab resource (optional)
+    pub vocab_resource: Option<Box<dyn ResourceProvider + Send>>,
+    /// Merges resource (optional, for the tokenizers using merges)
+    pub merges_resource: Option<Box<dyn ResourceProvider + Send>>,
+    /// Model weights resource (optional)
+    pub model_resource: Option<Box<dyn ResourceProvider + Send>>,
+    /// Task the weights are expected to have a head for (optional)
+    pub task: Option<PretrainedTask>,
+}
+
+impl CompatibilityResources {
+    /// Creates a new set of resources to check, made of a configuration
+    ///
+    /// # Arguments
+    ///
+    /// * `model_type` - Model type of the configuration and tokenizer
+    /// * `config_resource` - The `ResourceProvider` pointing to the model configuration
+    pub fn new<R>(model_type: ModelType, config_resource: R) -> CompatibilityResources
+    where
+        R: ResourceProvider + Send + 'static,
+    {
+        CompatibilityResources {
+            model_type,
+            config_resource: Box::new(config_resource),
+            vocab_resource: None,
+            merges_resource: None,
+            model_resource: None,
+            task: None,
+        }
+    }
+
+    /// Adds the tokenizer vocabulary (and merges, for the tokenizers using them) to the resources checked
+    pub fn with_tokenizer<R>(mut self, vocab_resource: R, merges_resource: Option<R>) -> Self
+    where
+        R: ResourceProvider + Send + 'static,
+    {
+        self.vocab_resource = Some(Box::new(vocab_resource));
+        self.merges_resource = merges_resource
+            .map(|merges_resource| Box::new(merges_resource) as Box<dyn ResourceProvider + Send>);
+        self
+    }
+
+    /// Adds the model weights to the resources checked
+    pub fn with_weights<R>(mut self, model_resource: R) -> Self
+    where
+        R: ResourceProvider + Send + 'static,
+    {
+        self.model_resource = Some(Box::new(model_resource));
+        self
+    }
+
+    /// Sets the task the weights are expected to have a head for
+    pub fn with_task(mut self, task: PretrainedTask) -> Self {
+        self.task = Some(task);
+        self
+    }
+}
+
+/// Checks that a configuration, a tokenizer and model weights are compatible, without building the model
+///
+/// # Arguments
+///
+/// * `resources` - `CompatibilityResources` pointing to the configuration, and optionally the tokenizer files, the
+/// weights and the task expected
+///
+/// # Returns
+///
+/// * `Vec<ConfigIssue>` Issues found in the resources (empty if they are compatible). A `RustBertError` is returned
+/// only if a resource cannot be retrieved or read.
+pub fn compatibility_check(
+    resources: &CompatibilityResources,
+) -> Result<Vec<ConfigIssue>, RustBertError> {
+    let config_path = resources.config_resource.get_local_path()?;
+    let config_value: serde_json::Value =
+        match serde_json::from_str(&fs::read_to_string(&config_path)?) {
+            Ok(value) => value,
+            Err(error) => {
+                return Ok(vec![ConfigIssue {
+                    field: "config",
+                    message: format!("the configuration file is not valid JSON ({})", error),
+                }]);
+            }
+        };
+    let config = ConfigOption::from_file(resources.model_type, &config_path)?;
+
+    let tokenizer = match &resources.vocab_resource {
+        Some(vocab_resource) => {
+            let vocab_path = vocab_resource.get_local_path()?;
+            let merges_path = match &resources.merges_resource {
+                Some(merges_resource) => Some(merges_resource.get_local_path()?),
+                None => None,
+            };
+            Some(TokenizerOption::from_file(
+                resources.model_type,
+                vocab_path.to_str().unwrap(),
+                merges_path.as_ref().map(|path| path.to_str().unwrap()),
+                false,
+                None,
+                None,
+            ))
+        }
+        None => None,
+    };
+
+    let mut issues = vec![];
+    let tokenizer = match tokenizer {
+        Some(Ok(tokenizer)) => Some(tokenizer),
+        Some(Err(error)) => {
+            issues.push(ConfigIssue {
+                field: "tokenizer",
+                message: format!("the tokenizer cannot be loaded ({})", error),
+            });
+            None
+        }
+        None => None,
+    };
+    issues.extend(config.check(tokenizer.as_ref(), None));
+    issues.extend(check_special_tokens(
+        &config,
+        &config_value,
+        tokenizer.as_ref(),
+    ));
+
+    let task_head = resources.task.and_then(expected_head);
+    if let Some((task_name, _)) = task_head {
+        if matches!(
+            resources.task,
+            Some(PretrainedTask::SequenceClassification)
+                | Some(PretrainedTask::TokenClassification)
+        ) && config_value
+            .get("id2label")
+            .and_then(|id2label| id2label.as_object())
+            .map_or(true, |id2label| id2label.is_empty())
+        {
+            issues.push(ConfigIssue {
+                field: "id2label",
+                message: format!("the configuration has no labels for the {} head", task_name),
+            });
+        }
+    }
+
+    if let Some(model_resource) = &resources.model_resource {
+        let weights = match model_resource.get_resource()? {
+            Resource::PathBuf(path) => Tensor::load_multi(path)?,
+            Resource::Buffer(content) => Tensor::load_multi_from_stream(Cursor::new(content))?,
+        };
+        let weight_shapes = weights
+            .into_iter()
+            .map(|(name, tensor)| (name, tensor.size()))
+            .collect::<Vec<(String, Vec<i64>)>>();
+        issues.extend(check_weights(&config, &weight_shapes, task_head));
+    }
+    Ok(issues)
+}
+
+/// Checks the special token ids of the configuration against its embeddings and the tokenizer
+fn check_special_tokens(
+    config: &ConfigOption,
+    config_value: &serde_json::Value,
+    tokenizer: Option<&TokenizerOption>,
+) -> Vec<ConfigIssue> {
+    let (_, vocab_size) = ArchitectureDimensions::new(config).vocab_size;
+    let mut issues = vec![];
+    for &field in SPECIAL_TOKEN_FIELDS.iter() {
+        // The end of sequence may be given as a list of ids
+        let token_ids: Vec<i64> = match config_value.get(field) {
+            Some(serde_json::Value::Array(values)) => {
+                values.iter().filter_map(|value| value.as_i64()).collect()
+            }
+            Some(value) => value.as_i64().into_iter().collect(),
+            None => vec![],
+        };
+        for token_id in token_ids {
+            if token_id < 0 || (vocab_size > 0 && token_id >= vocab_size) {
+                issues.push(ConfigIssue {
+                    field,
+                    message: format!(
+                        "the special token id ({}) is outside of the embeddings ({})",
+                        token_id, vocab_size
+                    ),
+                });
+                continue;
+            }
+            if let Some(tokenizer) = tokenizer {
+                if !tokenizer.get_vocab_indices().contains_key(&token_id) {
+                    issues.push(ConfigIssue {
+                        field,
+                        message: format!(
+                            "the special token id ({}) is not in the tokenizer vocabulary",
+                            token_id
+                        ),
+                    });
+                }
+            }
+        }
+    }
+
+    if let Some(tokenizer) = tokenizer {
+        let tokenizer_special_ids = [
+            ("pad_token_id", tokenizer.get_pad_id()),
+            ("bos_token_id", tokenizer.get_bos_id()),
+            ("eos_token_id", tokenizer.get_eos_id()),
+        ];
+        for &(field, tokenizer_id) in tokenizer_special_ids.iter() {
+            if let (Some(config_id), Some(tokenizer_id)) = (
+                config_value.get(field).and_then(|value| value.as_i64()),
+                tokenizer_id,
+            ) {
+                if config_id != tokenizer_id {
+                    issues.push(ConfigIssue {
+                        field,
+                        message: format!(
+                            "the configuration special token id ({}) differs from the tokenizer special token id ({})",
+                            config_id, tokenizer_id
+                        ),
+                    });
+                }
+            }
+        }
+    }
+    issues
+}
+
+/// Name of the head expected for a task and the names of the variables of this head in the supported architectures
+fn expected_head(task: PretrainedTask) -> Option<(&'static str, &'static [&'static str])> {
+    match task {
+        PretrainedTask::SequenceClassification | PretrainedTask::ZeroShotClassification => Some((
+            "sequence classification",
+            &[
+                "classifier",
+                "classification_head",
+                "sequence_summary",
+                "score",
+            ],
+        )),
+        PretrainedTask::TokenClassification => Some(("token classification", &["classifier"])),
+        PretrainedTask::QuestionAnswering => Some((
+            "question answering",
+            &["qa_outputs", "start_logits", "end_logits"],
+        )),
+        _ => None,
+    }
+}
+
+/// Checks the names and shapes of the weights against the configuration and the head expected
+fn check_weights(
+    config: &ConfigOption,
+    weight_shapes: &[(String, Vec<i64>)],
+    task_head: Option<(&'static str, &'static [&'static str])>,
+) -> Vec<ConfigIssue> {
+    let (vocab_size_field, vocab_size) = ArchitectureDimensions::new(config).vocab_size;
+    let mut issues = vec![];
+
+    let input_embeddings = weight_shapes.iter().find(|(name, shape)| {
+        let mut parts = name.rsplit('.');
+        shape.len() == 2
+            && parts.next() == Some("weight")
+            && parts
+                .next()
+                .map_or(false, |part| INPUT_EMBEDDINGS_NAMES.contains(&part))
+    });
+    match input_embeddings {
+        Some((name, shape)) if shape[0] != vocab_size => issues.push(ConfigIssue {
+            field: vocab_size_field,
+            message: format!(
+                "the input embeddings of the weights ({}) have {} rows but the vocabulary size is {}",
+                name, shape[0], vocab_size
+            ),
+        }),
+        Some(_) => {}
+        None => issues.push(ConfigIssue {
+            field: "weights",
+            message: "no input embeddings found in the weights".to_string(),
+        }),
+    }
+
+    if let Some((task_name, head_names)) = task_head {
+        let has_head = weight_shapes
+            .iter()
+            .any(|(name, _)| name.split('.').any(|part| head_names.contains(&part)));
+        if !has_head {
+            issues.push(ConfigIssue {
+                field: "weights",
+                message: format!("the weights have no {} head", task_name),
+            });
+        }
+    }
+    issues
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::bert::BertConfig;
+    use crate::llama::LlamaConfig;
+
+    #[test]
+    fn issues_are_aggregated() {
+        let config = ConfigOption::Bert(BertConfig {
+            hidden_size: 768,
+            num_attention_heads: 10,
+            vocab_size: 0,
+            max_position_embeddings: 512,
+            ..Default::default()
+        });
+
+        let issues = config.check(None, Some(1024));
+        assert_eq!(
+            issues
+                .iter()
+                .map(|issue| issue.field)
+                .collect::<Vec<&str>>(),
+            vec![
+                "num_attention_heads",
+                "vocab_size",
+                "max_position_embeddings"
+            ]
+        );
+
+        match config.validate(None, Some(1024)) {
+            Err(RustBertError::InvalidConfigurationError(message)) => {
+                assert!(message.starts_with("Invalid configuration (3 issues):"));
+                assert!(message.contains("\n- num_attention_heads: the hidden size (768) is not divisible by the number of attention heads (10)"));
+            }
+            _ => panic!("Expected an invalid configuration error"),
+        }
+    }
+
+    #[test]
+    fn valid_configurations_pass() {
+        let bert_config = ConfigOption::Bert(BertConfig::default());
+        assert!(bert_config.validate(None, Some(512)).is_ok());
+
+        // Rotary position embeddings do not limit the length of the sequences
+        let llama_config = ConfigOption::Llama(LlamaConfig {
+            num_key_value_heads: Some(8),
+            ..Default::default()
+        });
+        assert!(llama_config.validate(None, Some(1_000_000)).is_ok());
+
+        let invalid_llama_config = ConfigOption::Llama(LlamaConfig {
+            num_key_value_heads: Some(5),
+            ..Default::default()
+        });
+        assert_eq!(
+            invalid_llama_config.check(None, None)[0].field,
+            "num_key_value_heads"
+        );
+    }
+
+    #[test]
+    fn weights_are_checked_against_the_configuration() {
+        let config = ConfigOption::Bert(BertConfig {
+            vocab_size: 30522,
+            ..Default::default()
+        });
+        let weight_shapes = vec![
+            (
+                "bert.embeddings.word_embeddings.weight".to_string(),
+                vec![28996, 768],
+            ),
+            (
+                "bert.embeddings.position_embeddings.weight".to_string(),
+                vec![512, 768],
+            ),
+            ("cls.predictions.bias".to_string(), vec![28996]),
+        ];
+
+        let issues = check_weights(
+            &config,
+            &weight_shapes,
+            expected_head(PretrainedTask::TokenClassification),
+        );
+        assert_eq!(issues.len(), 2);
+        assert_eq!(issues[0].field, "vocab_size");
+        assert!(issues[0].message.contains("have 28996 rows"));
+        assert_eq!(
+            issues[1].message,
+            "the weights have no token classification head"
+        );
+
+        let weight_shapes = vec![
+            (
+                "bert.embeddings.word_embeddings.weight".to_string(),
+                vec![30522, 768],
+            ),
+            ("classifier.weight".to_string(), vec![9, 768]),
+        ];
+        assert!(check_weights(
+            &config,
+            &weight_shapes,
+            expected_head(PretrainedTask::TokenClassification)
+        )
+        .is_empty());
+    }
+
+    #[test]
+    fn special_tokens_must_fit_in_the_embeddings() {
+        let config = ConfigOption::Bert(BertConfig {
+            vocab_size: 100,
+            ..Default::default()
+        });
+        let config_value = serde_json::json!({
+            "pad_token_id": 0,
+            "eos_token_id": [2, 120],
+            "decoder_start_token_id": -1
+        });
+
+        let issues = check_special_tokens(&config, &config_value, None);
+        assert_eq!(
+            issues
+                .iter()
+                .map(|issue| issue.field)
+                .collect::<Vec<&str>>(),
+            vec!["eos_token_id", "decoder_start_token_id"]
+        );
+    }
+}
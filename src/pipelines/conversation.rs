@@ -20,6 +20,8 @@
 //!
 //!
 //! The dependencies will be downloaded to the user's home directory, under ~/.cache/.rustbert/dialgpt-medium
+//! Instruction-tuned GPT-NeoX and Falcon checkpoints (e.g. Falcon-7B-Instruct, converted locally) can be used by setting
+//! the `model_type` of the `ConversationConfig` to `ModelType::GPTNeoX` or `ModelType::Falcon`.
 //!
 //! ```no_run
 //! # fn main() -> anyhow::Result<()> {
@@ -45,10 +47,12 @@
 //! The authors of this repository are not responsible for any generation
 //! from the 3rd party utilization of the pretrained system.
 use crate::common::error::RustBertError;
+use crate::falcon::FalconGenerator;
 use crate::gpt2::GPT2Generator;
+use crate::gpt_neox::GptNeoXGenerator;
 use crate::pipelines::common::{ModelType, TokenizerOption};
 use crate::pipelines::generation_utils::private_generation_utils::PrivateLanguageGenerator;
-use crate::pipelines::generation_utils::{GenerateConfig, LanguageGenerator};
+use crate::pipelines::generation_utils::{GenerateConfig, LanguageGenerator, NoRepeatNgramScope};
 use crate::resources::ResourceProvider;
 use std::collections::HashMap;
 use tch::{Device, Kind, Tensor};
@@ -104,6 +108,9 @@ pub struct ConversationConfig {
     pub num_beam_groups: Option<i64>,
     /// Diversity penalty for diverse beam search. High values will enforce more difference between beam groups (default: 5.5)
     pub diversity_penalty: Option<f64>,
+    /// Bias added to the logits of the given token ids at every generation step, nudging the vocabulary used by the
+    /// model. Positive values encourage the generation of a token and negative values discourage it (default: empty)
+    pub logit_bias: HashMap<i64, f64>,
     /// Device to place the model on (default: CUDA/GPU when available)
     pub device: Device,
 }
@@ -140,6 +147,7 @@ impl Default for ConversationConfig {
             num_return_sequences: 1,
             num_beam_groups: None,
             diversity_penalty: None,
+            logit_bias: HashMap::new(),
             device: Device::cuda_if_available(),
         }
     }
@@ -163,9 +171,18 @@ impl From<ConversationConfig> for GenerateConfig {
             repetition_penalty: config.repetition_penalty,
             length_penalty: config.length_penalty,
             no_repeat_ngram_size: config.no_repeat_ngram_size,
+            no_repeat_ngram_scope: NoRepeatNgramScope::Sequence,
             num_return_sequences: config.num_return_sequences,
             num_beam_groups: config.num_beam_groups,
             diversity_penalty: config.diversity_penalty,
+            penalty_alpha: None,
+            typical_p: None,
+            epsilon_cutoff: None,
+            eta_cutoff: None,
+            prefill_chunk_size: None,
+            stop_sequences: vec![],
+            logit_bias: config.logit_bias,
+            output_attentions: false,
             device: config.device,
         }
     }
@@ -678,14 +695,24 @@ impl Default for ConversationManager {
 pub enum ConversationOption {
     /// Conversation based on GPT2 model
     GPT2(GPT2Generator),
+    /// Conversation based on GPT-NeoX model
+    GPTNeoX(GptNeoXGenerator),
+    /// Conversation based on Falcon model
+    Falcon(FalconGenerator),
 }
 
 impl ConversationOption {
     pub fn new(config: ConversationConfig) -> Result<Self, RustBertError> {
         match config.model_type {
             ModelType::GPT2 => Ok(ConversationOption::GPT2(GPT2Generator::new(config.into())?)),
+            ModelType::GPTNeoX => Ok(ConversationOption::GPTNeoX(GptNeoXGenerator::new(
+                config.into(),
+            )?)),
+            ModelType::Falcon => Ok(ConversationOption::Falcon(FalconGenerator::new(
+                config.into(),
+            )?)),
             _ => Err(RustBertError::InvalidConfigurationError(
-                "GPT2 is currently the only supported model for conversation generation"
+                "GPT2, GPT-NeoX and Falcon are currently the only supported models for conversation generation"
                     .to_string(),
             )),
         }
@@ -696,12 +723,20 @@ impl ConversationOption {
             Self::GPT2(model_ref) => {
                 Ok(*model_ref.get_eos_ids().as_ref().unwrap().first().unwrap())
             }
+            Self::GPTNeoX(model_ref) => {
+                Ok(*model_ref.get_eos_ids().as_ref().unwrap().first().unwrap())
+            }
+            Self::Falcon(model_ref) => {
+                Ok(*model_ref.get_eos_ids().as_ref().unwrap().first().unwrap())
+            }
         }
     }
 
     pub fn get_tokenizer(&self) -> &TokenizerOption {
         match self {
             Self::GPT2(model_ref) => model_ref._get_tokenizer(),
+            Self::GPTNeoX(model_ref) => model_ref._get_tokenizer(),
+            Self::Falcon(model_ref) => model_ref._get_tokenizer(),
         }
     }
 
@@ -709,6 +744,8 @@ impl ConversationOption {
     pub fn model_type(&self) -> ModelType {
         match *self {
             Self::GPT2(_) => ModelType::GPT2,
+            Self::GPTNeoX(_) => ModelType::GPTNeoX,
+            Self::Falcon(_) => ModelType::Falcon,
         }
     }
 
@@ -724,6 +761,16 @@ impl ConversationOption {
                 .into_iter()
                 .map(|output| output.indices)
                 .collect(),
+            Self::GPTNeoX(ref model) => model
+                .generate_from_ids_and_past(input_ids, attention_mask, None)
+                .into_iter()
+                .map(|output| output.indices)
+                .collect(),
+            Self::Falcon(ref model) => model
+                .generate_from_ids_and_past(input_ids, attention_mask, None)
+                .into_iter()
+                .map(|output| output.indices)
+                .collect(),
         }
     }
 }
@@ -0,0 +1,309 @@
+// Copyright 2022 Guillaume Becquin
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//     http://www.apache.org/licenses/LICENSE-2.0
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! # Hierarchical Navigable Small World index
+//! Approximate nearest neighbor search over unit-norm vectors, using the inner product (cosine similarity) as
+//! similarity ([Malkov and Yashunin, 2016](https://arxiv.org/abs/1603.09320)). The vectors are organized in a
+//! hierarchy of proximity graphs: the search starts from the sparse upper layers and greedily descends to the
+//! bottom layer containing all vectors. The neighbors of a node are the closest nodes found during its insertion.
+
+use ordered_float::OrderedFloat;
+use serde::{Deserialize, Serialize};
+use std::cmp::Reverse;
+use std::collections::{BinaryHeap, HashSet};
+
+/// # Configuration of an HNSW index
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct HnswConfig {
+    /// Maximum number of neighbors of a node in the upper layers (twice this value for the bottom layer)
+    pub max_connections: usize,
+    /// Size of the candidates list when inserting a vector. Larger values improve the quality of the graph.
+    pub ef_construction: usize,
+    /// Size of the candidates list when searching. Larger values improve the recall of the search.
+    pub ef_search: usize,
+    /// Seed of the random generator drawing the layer of the inserted vectors
+    pub seed: u64,
+}
+
+impl Default for HnswConfig {
+    fn default() -> Self {
+        HnswConfig {
+            max_connections: 16,
+            ef_construction: 100,
+            ef_search: 64,
+            seed: 42,
+        }
+    }
+}
+
+struct Node {
+    /// Neighbors of the node for each layer it belongs to (from the bottom layer)
+    neighbors: Vec<Vec<usize>>,
+}
+
+/// # HNSW index over unit-norm vectors
+pub struct HnswIndex {
+    config: HnswConfig,
+    vectors: Vec<Vec<f32>>,
+    nodes: Vec<Node>,
+    entry_point: Option<usize>,
+    level_multiplier: f64,
+    rng_state: u64,
+}
+
+fn similarity(a: &[f32], b: &[f32]) -> f32 {
+    a.iter().zip(b.iter()).map(|(a, b)| a * b).sum()
+}
+
+impl HnswIndex {
+    /// Create a new empty `HnswIndex`
+    ///
+    /// # Arguments
+    ///
+    /// * `config` - `HnswConfig` with the graph and search parameters
+    pub fn new(config: HnswConfig) -> HnswIndex {
+        let max_connections = config.max_connections.max(2);
+        HnswIndex {
+            config: HnswConfig {
+                max_connections,
+                ..config
+            },
+            vectors: vec![],
+            nodes: vec![],
+            entry_point: None,
+            level_multiplier: 1.0 / (max_connections as f64).ln(),
+            rng_state: config.seed,
+        }
+    }
+
+    /// Returns the number of vectors in the index
+    pub fn len(&self) -> usize {
+        self.vectors.len()
+    }
+
+    /// Returns `true` if the index contains no vectors
+    pub fn is_empty(&self) -> bool {
+        self.vectors.is_empty()
+    }
+
+    /// Returns the vector with the given identifier
+    pub fn vector(&self, id: usize) -> &[f32] {
+        &self.vectors[id]
+    }
+
+    /// Uniform sample in (0, 1] from a SplitMix64 generator
+    fn next_uniform(&mut self) -> f64 {
+        self.rng_state = self.rng_state.wrapping_add(0x9E37_79B9_7F4A_7C15);
+        let mut z = self.rng_state;
+        z = (z ^ (z >> 30)).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94D0_49BB_1331_11EB);
+        z ^= z >> 31;
+        ((z >> 11) as f64 + 1.0) / (1u64 << 53) as f64
+    }
+
+    fn top_level(&self) -> usize {
+        self.entry_point
+            .map_or(0, |entry_point| self.nodes[entry_point].neighbors.len() - 1)
+    }
+
+    fn max_neighbors(&self, level: usize) -> usize {
+        if level == 0 {
+            2 * self.config.max_connections
+        } else {
+            self.config.max_connections
+        }
+    }
+
+    /// Best-first search of a layer, returning up to `ef` nodes sorted by decreasing similarity
+    fn search_layer(
+        &self,
+        query: &[f32],
+        entry_points: &[usize],
+        ef: usize,
+        level: usize,
+    ) -> Vec<(f32, usize)> {
+        let mut visited: HashSet<usize> = entry_points.iter().cloned().collect();
+        let mut candidates = BinaryHeap::new();
+        let mut results = BinaryHeap::new();
+        for &entry_point in entry_points {
+            let score = OrderedFloat(similarity(query, &self.vectors[entry_point]));
+            candidates.push((score, entry_point));
+            results.push(Reverse((score, entry_point)));
+        }
+        while let Some((score, node)) = candidates.pop() {
+            let worst = results.peek().map(|Reverse((score, _))| *score).unwrap();
+            if score < worst && results.len() >= ef {
+                break;
+            }
+            for &neighbor in &self.nodes[node].neighbors[level] {
+                if !visited.insert(neighbor) {
+                    continue;
+                }
+                let neighbor_score = OrderedFloat(similarity(query, &self.vectors[neighbor]));
+                let worst = results.peek().map(|Reverse((score, _))| *score).unwrap();
+                if results.len() < ef || neighbor_score > worst {
+                    candidates.push((neighbor_score, neighbor));
+                    results.push(Reverse((neighbor_score, neighbor)));
+                    if results.len() > ef {
+                        results.pop();
+                    }
+                }
+            }
+        }
+        let mut results = results
+            .into_iter()
+            .map(|Reverse((score, node))| (score.0, node))
+            .collect::<Vec<(f32, usize)>>();
+        results.sort_by(|a, b| b.0.partial_cmp(&a.0).unwrap());
+        results
+    }
+
+    /// Keeps the `max_neighbors` closest neighbors of a node at a given layer
+    fn prune(&mut self, node: usize, level: usize) {
+        let max_neighbors = self.max_neighbors(level);
+        if self.nodes[node].neighbors[level].len() <= max_neighbors {
+            return;
+        }
+        let vector = &self.vectors[node];
+        let mut scored = self.nodes[node].neighbors[level]
+            .iter()
+            .map(|&neighbor| (similarity(vector, &self.vectors[neighbor]), neighbor))
+            .collect::<Vec<(f32, usize)>>();
+        scored.sort_by(|a, b| b.0.partial_cmp(&a.0).unwrap());
+        self.nodes[node].neighbors[level] = scored
+            .into_iter()
+            .take(max_neighbors)
+            .map(|(_, neighbor)| neighbor)
+            .collect();
+    }
+
+    /// Adds a vector to the index, returning its identifier (insertion position)
+    ///
+    /// # Arguments
+    ///
+    /// * `vector` - Vector to insert, expected to have a unit norm
+    pub fn insert(&mut self, vector: Vec<f32>) -> usize {
+        let node = self.vectors.len();
+        let level = (-self.next_uniform().ln() * self.level_multiplier).floor() as usize;
+        self.vectors.push(vector);
+        self.nodes.push(Node {
+            neighbors: vec![vec![]; level + 1],
+        });
+
+        let entry_point = match self.entry_point {
+            Some(entry_point) => entry_point,
+            None => {
+                self.entry_point = Some(node);
+                return node;
+            }
+        };
+        let top_level = self.top_level();
+        let query = self.vectors[node].clone();
+
+        // Greedy descent through the layers above the level of the new node
+        let mut entry_points = vec![entry_point];
+        for current_level in (level + 1..=top_level).rev() {
+            entry_points = vec![self.search_layer(&query, &entry_points, 1, current_level)[0].1];
+        }
+        for current_level in (0..=level.min(top_level)).rev() {
+            let candidates = self.search_layer(
+                &query,
+                &entry_points,
+                self.config.ef_construction,
+                current_level,
+            );
+            let neighbors = candidates
+                .iter()
+                .take(self.max_neighbors(current_level))
+                .map(|(_, neighbor)| *neighbor)
+                .collect::<Vec<usize>>();
+            for &neighbor in &neighbors {
+                self.nodes[neighbor].neighbors[current_level].push(node);
+                self.prune(neighbor, current_level);
+            }
+            self.nodes[node].neighbors[current_level] = neighbors;
+            entry_points = candidates.into_iter().map(|(_, node)| node).collect();
+        }
+        if level > top_level {
+            self.entry_point = Some(node);
+        }
+        node
+    }
+
+    /// Searches the approximate nearest neighbors of a query vector
+    ///
+    /// # Arguments
+    ///
+    /// * `query` - Query vector, expected to have a unit norm
+    /// * `k` - Number of neighbors to return
+    ///
+    /// # Returns
+    ///
+    /// * `Vec<(f32, usize)>` similarity and identifier of up to `k` neighbors, sorted by decreasing similarity
+    pub fn search(&self, query: &[f32], k: usize) -> Vec<(f32, usize)> {
+        let entry_point = match self.entry_point {
+            Some(entry_point) => entry_point,
+            None => return vec![],
+        };
+        let mut entry_points = vec![entry_point];
+        for current_level in (1..=self.top_level()).rev() {
+            entry_points = vec![self.search_layer(query, &entry_points, 1, current_level)[0].1];
+        }
+        let mut results = self.search_layer(query, &entry_points, self.config.ef_search.max(k), 0);
+        results.truncate(k);
+        results
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn random_unit_vectors(index: &mut HnswIndex, count: usize, dimension: usize) -> Vec<Vec<f32>> {
+        (0..count)
+            .map(|_| {
+                let vector = (0..dimension)
+                    .map(|_| index.next_uniform() as f32 - 0.5)
+                    .collect::<Vec<f32>>();
+                let norm = similarity(&vector, &vector).sqrt();
+                vector.into_iter().map(|value| value / norm).collect()
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_hnsw_recall() {
+        let mut index = HnswIndex::new(Default::default());
+        let vectors = random_unit_vectors(&mut index, 500, 16);
+        for vector in vectors.iter() {
+            index.insert(vector.clone());
+        }
+        assert_eq!(index.len(), 500);
+
+        let mut found = 0;
+        for (query_index, query) in vectors.iter().enumerate().take(50) {
+            let mut exact = vectors
+                .iter()
+                .enumerate()
+                .map(|(index, vector)| (similarity(query, vector), index))
+                .collect::<Vec<(f32, usize)>>();
+            exact.sort_by(|a, b| b.0.partial_cmp(&a.0).unwrap());
+            let approximate = index.search(query, 10);
+            assert_eq!(approximate[0].1, query_index);
+            found += exact[..10]
+                .iter()
+                .filter(|(_, index)| approximate.iter().any(|(_, found)| found == index))
+                .count();
+        }
+        // Recall@10 over 50 queries
+        assert!(found as f64 / 500.0 > 0.9);
+    }
+}
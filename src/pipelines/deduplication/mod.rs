@@ -0,0 +1,350 @@
+// Copyright 2022 Guillaume Becquin
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//     http://www.apache.org/licenses/LICENSE-2.0
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! # Near-duplicate detection
+//! Finds the duplicates and near-duplicates of a corpus of texts. The texts are embedded with a sentence embeddings
+//! model and the pairs of texts with a cosine similarity above a threshold are linked. The clusters of near-duplicates
+//! are the connected components of the resulting graph: two texts belong to the same cluster if they are linked
+//! directly or through other texts of the cluster.
+//!
+//! The similar pairs are found either by an exact search (comparing all pairs of texts, by blocks of rows), or by an
+//! approximate nearest neighbors search using a Hierarchical Navigable Small World (HNSW) index, available with the
+//! `hnsw` feature. The approximate search scales to larger corpora, but only the `max_neighbors` most similar texts
+//! are considered for each text and some pairs above the threshold may be missed.
+//!
+//! ```no_run
+//! # fn main() -> anyhow::Result<()> {
+//! use rust_bert::pipelines::deduplication::{DeduplicationConfig, DeduplicationModel};
+//! use rust_bert::pipelines::sentence_embeddings::SentenceEmbeddingsModelType;
+//!
+//! let config = DeduplicationConfig::new(SentenceEmbeddingsModelType::AllMiniLmL12V2.into(), 0.9);
+//! let model = DeduplicationModel::new(config)?;
+//!
+//! let corpus = [
+//!     "The weather is lovely today.",
+//!     "It's so sunny outside!",
+//!     "The weather is lovely today!",
+//!     "He drove to the stadium.",
+//! ];
+//! let clusters = model.find_duplicates(&corpus)?;
+//! # Ok(())
+//! # }
+//! ```
+
+#[cfg(feature = "hnsw")]
+pub mod hnsw;
+
+use crate::common::error::RustBertError;
+use crate::pipelines::sentence_embeddings::{
+    Embedding, SentenceEmbeddingsConfig, SentenceEmbeddingsModel,
+};
+use serde::{Deserialize, Serialize};
+use tch::Tensor;
+
+#[cfg(feature = "hnsw")]
+use crate::pipelines::deduplication::hnsw::{HnswConfig, HnswIndex};
+
+/// # Nearest neighbors search used to find the similar pairs
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize, Default)]
+pub enum NeighborSearch {
+    /// Exact search over all pairs of texts
+    #[default]
+    Exact,
+    /// Approximate search with an HNSW index, considering the `max_neighbors` most similar texts of each text
+    #[cfg(feature = "hnsw")]
+    Hnsw {
+        config: HnswConfig,
+        max_neighbors: usize,
+    },
+}
+
+/// # Configuration for DeduplicationModel
+pub struct DeduplicationConfig {
+    /// Sentence embeddings model used to embed the corpus
+    pub embeddings_config: SentenceEmbeddingsConfig,
+    /// Minimum cosine similarity of near-duplicate texts
+    pub threshold: f32,
+    /// Nearest neighbors search (default: exact)
+    pub search: NeighborSearch,
+    /// Number of texts embedded at once (default: 64)
+    pub batch_size: usize,
+}
+
+impl DeduplicationConfig {
+    /// Instantiate a new deduplication configuration with an exact search
+    ///
+    /// # Arguments
+    ///
+    /// * `embeddings_config` - `SentenceEmbeddingsConfig` of the model used to embed the corpus
+    /// * `threshold` - Minimum cosine similarity of near-duplicate texts
+    pub fn new(embeddings_config: SentenceEmbeddingsConfig, threshold: f32) -> DeduplicationConfig {
+        DeduplicationConfig {
+            embeddings_config,
+            threshold,
+            search: NeighborSearch::Exact,
+            batch_size: 64,
+        }
+    }
+}
+
+/// # Pair of similar texts
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct SimilarPair {
+    /// Position of the first text in the corpus
+    pub first: usize,
+    /// Position of the second text in the corpus (greater than `first`)
+    pub second: usize,
+    /// Cosine similarity of the texts
+    pub similarity: f32,
+}
+
+/// # Cluster of near-duplicate texts
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct DuplicateCluster {
+    /// Positions of the texts of the cluster in the corpus, in increasing order
+    pub indices: Vec<usize>,
+    /// Pairs of texts of the cluster with a similarity above the threshold
+    pub pairs: Vec<SimilarPair>,
+}
+
+/// # DeduplicationModel for near-duplicate detection
+pub struct DeduplicationModel {
+    embeddings_model: SentenceEmbeddingsModel,
+    threshold: f32,
+    search: NeighborSearch,
+    batch_size: usize,
+}
+
+impl DeduplicationModel {
+    /// Build a new `DeduplicationModel`
+    ///
+    /// # Arguments
+    ///
+    /// * `config` - `DeduplicationConfig` object containing the sentence embeddings configuration, the similarity threshold and the search type
+    pub fn new(config: DeduplicationConfig) -> Result<DeduplicationModel, RustBertError> {
+        if !(-1.0..=1.0).contains(&config.threshold) {
+            return Err(RustBertError::InvalidConfigurationError(format!(
+                "The similarity threshold must be between -1 and 1, got {}",
+                config.threshold
+            )));
+        }
+        let embeddings_model = SentenceEmbeddingsModel::new(config.embeddings_config)?;
+        Ok(DeduplicationModel {
+            embeddings_model,
+            threshold: config.threshold,
+            search: config.search,
+            batch_size: config.batch_size.max(1),
+        })
+    }
+
+    /// Embeds a corpus and returns its clusters of near-duplicates
+    ///
+    /// # Arguments
+    ///
+    /// * `corpus` - Texts to deduplicate
+    ///
+    /// # Returns
+    ///
+    /// * `Vec<DuplicateCluster>` clusters of at least two texts, ordered by their first text
+    pub fn find_duplicates<S>(&self, corpus: &[S]) -> Result<Vec<DuplicateCluster>, RustBertError>
+    where
+        S: AsRef<str> + Sync,
+    {
+        let mut embeddings = Vec::with_capacity(corpus.len());
+        for batch in corpus.chunks(self.batch_size) {
+            embeddings.extend(self.embeddings_model.encode(batch)?);
+        }
+        Ok(find_duplicate_embeddings(
+            &embeddings,
+            self.threshold,
+            &self.search,
+        ))
+    }
+}
+
+/// Returns the clusters of near-duplicates of a set of embeddings
+///
+/// # Arguments
+///
+/// * `embeddings` - Embeddings of the corpus (normalized internally)
+/// * `threshold` - Minimum cosine similarity of near-duplicates
+/// * `search` - `NeighborSearch` used to find the similar pairs
+///
+/// # Returns
+///
+/// * `Vec<DuplicateCluster>` clusters of at least two embeddings, ordered by their first embedding
+pub fn find_duplicate_embeddings(
+    embeddings: &[Embedding],
+    threshold: f32,
+    search: &NeighborSearch,
+) -> Vec<DuplicateCluster> {
+    let embeddings = embeddings
+        .iter()
+        .map(|embedding| {
+            let norm = embedding
+                .iter()
+                .map(|value| value * value)
+                .sum::<f32>()
+                .sqrt()
+                .max(1e-12);
+            embedding.iter().map(|value| value / norm).collect()
+        })
+        .collect::<Vec<Vec<f32>>>();
+    let pairs = match search {
+        NeighborSearch::Exact => exact_similar_pairs(&embeddings, threshold),
+        #[cfg(feature = "hnsw")]
+        NeighborSearch::Hnsw {
+            config,
+            max_neighbors,
+        } => hnsw_similar_pairs(embeddings, threshold, *config, *max_neighbors),
+    };
+    cluster_pairs(pairs)
+}
+
+/// Number of rows compared at once by the exact search
+const EXACT_SEARCH_BLOCK_SIZE: usize = 1024;
+
+fn exact_similar_pairs(embeddings: &[Vec<f32>], threshold: f32) -> Vec<SimilarPair> {
+    if embeddings.is_empty() {
+        return vec![];
+    }
+    let dimension = embeddings[0].len() as i64;
+    let flat_embeddings = embeddings.concat();
+    let embeddings = Tensor::of_slice(&flat_embeddings).view((-1, dimension));
+    let num_embeddings = embeddings.size()[0];
+
+    let mut pairs = vec![];
+    for block_start in (0..num_embeddings).step_by(EXACT_SEARCH_BLOCK_SIZE) {
+        let block_length = (EXACT_SEARCH_BLOCK_SIZE as i64).min(num_embeddings - block_start);
+        let similarities = embeddings
+            .narrow(0, block_start, block_length)
+            .matmul(&embeddings.tr());
+        let matches = similarities.ge(threshold as f64).nonzero();
+        for match_index in 0..matches.size()[0] {
+            let row = matches.int64_value(&[match_index, 0]);
+            let column = matches.int64_value(&[match_index, 1]);
+            let first = (block_start + row) as usize;
+            let second = column as usize;
+            if second > first {
+                pairs.push(SimilarPair {
+                    first,
+                    second,
+                    similarity: similarities.double_value(&[row, column]) as f32,
+                });
+            }
+        }
+    }
+    pairs
+}
+
+#[cfg(feature = "hnsw")]
+fn hnsw_similar_pairs(
+    embeddings: Vec<Vec<f32>>,
+    threshold: f32,
+    config: HnswConfig,
+    max_neighbors: usize,
+) -> Vec<SimilarPair> {
+    let mut index = HnswIndex::new(config);
+    for embedding in embeddings {
+        index.insert(embedding);
+    }
+    let mut pairs = vec![];
+    for first in 0..index.len() {
+        // The text itself is expected to be returned as its own nearest neighbor
+        for (similarity, second) in index.search(index.vector(first), max_neighbors + 1) {
+            if similarity < threshold {
+                break;
+            }
+            if second != first {
+                pairs.push(SimilarPair {
+                    first: first.min(second),
+                    second: first.max(second),
+                    similarity,
+                });
+            }
+        }
+    }
+    // Pairs found from both of their texts
+    pairs.sort_by(|a, b| (a.first, a.second).cmp(&(b.first, b.second)));
+    pairs.dedup_by(|a, b| (a.first, a.second) == (b.first, b.second));
+    pairs
+}
+
+fn find_root(parents: &mut [usize], mut node: usize) -> usize {
+    while parents[node] != node {
+        parents[node] = parents[parents[node]];
+        node = parents[node];
+    }
+    node
+}
+
+/// Groups the similar pairs in connected components
+fn cluster_pairs(pairs: Vec<SimilarPair>) -> Vec<DuplicateCluster> {
+    let num_nodes = pairs.iter().map(|pair| pair.second + 1).max().unwrap_or(0);
+    let mut parents = (0..num_nodes).collect::<Vec<usize>>();
+    for pair in pairs.iter() {
+        let first_root = find_root(&mut parents, pair.first);
+        let second_root = find_root(&mut parents, pair.second);
+        if first_root != second_root {
+            // The smallest index is kept as root, so that the clusters are ordered by their first text
+            parents[first_root.max(second_root)] = first_root.min(second_root);
+        }
+    }
+
+    let mut clusters: Vec<DuplicateCluster> = vec![];
+    let mut cluster_positions = vec![None; num_nodes];
+    for pair in pairs {
+        let root = find_root(&mut parents, pair.first);
+        let position = *cluster_positions[root].get_or_insert_with(|| {
+            clusters.push(DuplicateCluster {
+                indices: vec![],
+                pairs: vec![],
+            });
+            clusters.len() - 1
+        });
+        clusters[position].pairs.push(pair);
+    }
+    for cluster in clusters.iter_mut() {
+        let mut indices = cluster
+            .pairs
+            .iter()
+            .flat_map(|pair| [pair.first, pair.second])
+            .collect::<Vec<usize>>();
+        indices.sort_unstable();
+        indices.dedup();
+        cluster.indices = indices;
+    }
+    clusters.sort_by_key(|cluster| cluster.indices[0]);
+    clusters
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_exact_duplicates() {
+        let embeddings = vec![
+            vec![1.0, 0.0, 0.0],
+            vec![0.0, 1.0, 0.0],
+            vec![0.99, 0.1, 0.0],
+            vec![0.0, 0.0, 1.0],
+            vec![0.0, 2.0, 0.05],
+            vec![0.95, 0.2, 0.1],
+        ];
+        let clusters = find_duplicate_embeddings(&embeddings, 0.97, &NeighborSearch::Exact);
+        assert_eq!(clusters.len(), 2);
+        assert_eq!(clusters[0].indices, vec![0, 2, 5]);
+        assert_eq!(clusters[1].indices, vec![1, 4]);
+        assert_eq!(clusters[1].pairs.len(), 1);
+        assert!(clusters[1].pairs[0].similarity > 0.99);
+    }
+}
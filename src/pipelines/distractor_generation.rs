@@ -0,0 +1,452 @@
+// Copyright 2022 Guillaume Becquin
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//     http://www.apache.org/licenses/LICENSE-2.0
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! # Answer-aware distractor generation
+//! Generates plausible wrong answers (distractors) for multiple-choice quiz questions, given a passage, a question
+//! and its correct answer. Candidates are generated by a sequence-to-sequence model (BART or T5) fine-tuned for
+//! distractor generation, with the correct answer banned from the generated sequences. The candidates are then
+//! filtered with sentence embeddings:
+//! - candidates too similar to the correct answer (e.g. near-synonyms, which would also be correct) are removed,
+//! - candidates too similar to a distractor already selected are removed, keeping the distractors distinct.
+//!
+//! The prompt given to the model is built from a template with `{passage}`, `{question}` and `{answer}`
+//! placeholders, which should match the format used to fine-tune the model.
+//!
+//! ```no_run
+//! # fn main() -> anyhow::Result<()> {
+//! use rust_bert::pipelines::common::ModelType;
+//! use rust_bert::pipelines::distractor_generation::{
+//!     DistractorGenerationConfig, DistractorGenerationModel, QuizInput,
+//! };
+//! use rust_bert::pipelines::generation_utils::GenerateConfig;
+//! use rust_bert::pipelines::sentence_embeddings::SentenceEmbeddingsModelType;
+//! use rust_bert::resources::LocalResource;
+//! use std::path::PathBuf;
+//!
+//! let generate_config = GenerateConfig {
+//!     model_resource: Box::new(LocalResource::from(PathBuf::from("path/to/rust_model.ot"))),
+//!     config_resource: Box::new(LocalResource::from(PathBuf::from("path/to/config.json"))),
+//!     vocab_resource: Box::new(LocalResource::from(PathBuf::from("path/to/spiece.model"))),
+//!     merges_resource: Box::new(LocalResource::from(PathBuf::from("path/to/spiece.model"))),
+//!     ..Default::default()
+//! };
+//! let config = DistractorGenerationConfig::new(
+//!     ModelType::T5,
+//!     generate_config,
+//!     SentenceEmbeddingsModelType::AllMiniLmL12V2.into(),
+//! );
+//! let model = DistractorGenerationModel::new(config)?;
+//!
+//! let input = QuizInput {
+//!     passage: "Paris is the capital and most populous city of France.".to_string(),
+//!     question: "What is the capital of France?".to_string(),
+//!     answer: "Paris".to_string(),
+//! };
+//! let distractors = model.generate(&[input])?;
+//! # Ok(())
+//! # }
+//! ```
+
+use crate::bart::BartGenerator;
+use crate::common::error::RustBertError;
+use crate::pipelines::common::{ModelType, TokenizerOption};
+use crate::pipelines::generation_utils::{GenerateConfig, GenerateOptions, LanguageGenerator};
+use crate::pipelines::sentence_embeddings::{
+    Embedding, SentenceEmbeddingsConfig, SentenceEmbeddingsModel,
+};
+use crate::t5::T5Generator;
+use serde::{Deserialize, Serialize};
+
+/// # Quiz question for which distractors are generated
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct QuizInput {
+    /// Passage the question is about
+    pub passage: String,
+    /// Question
+    pub question: String,
+    /// Correct answer
+    pub answer: String,
+}
+
+/// # Distractor generated for a quiz question
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Distractor {
+    /// Text of the distractor
+    pub text: String,
+    /// Cosine similarity between the embeddings of the distractor and of the correct answer
+    pub answer_similarity: f32,
+}
+
+/// # Configuration for distractor generation
+pub struct DistractorGenerationConfig {
+    /// Model type of the sequence-to-sequence generator (BART or T5)
+    pub model_type: ModelType,
+    /// Generation configuration, containing the resources of the generator
+    pub generate_config: GenerateConfig,
+    /// Sentence embeddings model used to filter the candidates
+    pub embeddings_config: SentenceEmbeddingsConfig,
+    /// Prompt template with `{passage}`, `{question}` and `{answer}` placeholders
+    pub prompt_template: String,
+    /// Optional separator splitting the generated sequences, for models generating several distractors at once (default: None)
+    pub separator: Option<String>,
+    /// Number of sequences generated per question (default: 10)
+    pub num_candidates: i64,
+    /// Maximum number of distractors returned per question (default: 3)
+    pub num_distractors: usize,
+    /// Candidates with a cosine similarity to the correct answer above this threshold are removed (default: 0.8)
+    pub answer_similarity_threshold: f32,
+    /// Candidates with a cosine similarity to a selected distractor above this threshold are removed (default: 0.9)
+    pub distractor_similarity_threshold: f32,
+}
+
+impl DistractorGenerationConfig {
+    /// Instantiate a new distractor generation configuration with default parameters
+    ///
+    /// # Arguments
+    ///
+    /// * `model_type` - `ModelType` of the generator (BART or T5)
+    /// * `generate_config` - `GenerateConfig` containing the resources of the generator and the decoding settings
+    /// * `embeddings_config` - `SentenceEmbeddingsConfig` of the model used to filter the candidates
+    pub fn new(
+        model_type: ModelType,
+        generate_config: GenerateConfig,
+        embeddings_config: SentenceEmbeddingsConfig,
+    ) -> DistractorGenerationConfig {
+        DistractorGenerationConfig {
+            model_type,
+            generate_config,
+            embeddings_config,
+            prompt_template: "question: {question} answer: {answer} context: {passage}".to_string(),
+            separator: None,
+            num_candidates: 10,
+            num_distractors: 3,
+            answer_similarity_threshold: 0.8,
+            distractor_similarity_threshold: 0.9,
+        }
+    }
+}
+
+/// # Abstraction that holds one particular distractor generation model, for any of the supported models
+pub enum DistractorGenerationOption {
+    /// Distractor generator based on BART model
+    Bart(BartGenerator),
+    /// Distractor generator based on T5 model
+    T5(T5Generator),
+}
+
+impl DistractorGenerationOption {
+    pub fn new(
+        model_type: ModelType,
+        generate_config: GenerateConfig,
+    ) -> Result<DistractorGenerationOption, RustBertError> {
+        match model_type {
+            ModelType::Bart => Ok(DistractorGenerationOption::Bart(BartGenerator::new(
+                generate_config,
+            )?)),
+            ModelType::T5 => Ok(DistractorGenerationOption::T5(T5Generator::new(
+                generate_config,
+            )?)),
+            _ => Err(RustBertError::InvalidConfigurationError(format!(
+                "Distractor generation not implemented for {:?}!",
+                model_type
+            ))),
+        }
+    }
+
+    /// Returns the `ModelType` for this DistractorGenerationOption
+    pub fn model_type(&self) -> ModelType {
+        match *self {
+            Self::Bart(_) => ModelType::Bart,
+            Self::T5(_) => ModelType::T5,
+        }
+    }
+
+    /// Interface method to access tokenizer
+    pub fn get_tokenizer(&self) -> &TokenizerOption {
+        match self {
+            Self::Bart(model_ref) => model_ref.get_tokenizer(),
+            Self::T5(model_ref) => model_ref.get_tokenizer(),
+        }
+    }
+
+    /// Interface method to generate() of the particular models.
+    pub fn generate<S>(
+        &self,
+        prompt_texts: Option<&[S]>,
+        generate_options: Option<GenerateOptions>,
+    ) -> Vec<String>
+    where
+        S: AsRef<str> + Sync,
+    {
+        match *self {
+            Self::Bart(ref model) => model
+                .generate(prompt_texts, generate_options)
+                .into_iter()
+                .map(|output| output.text)
+                .collect(),
+            Self::T5(ref model) => model
+                .generate(prompt_texts, generate_options)
+                .into_iter()
+                .map(|output| output.text)
+                .collect(),
+        }
+    }
+}
+
+/// # DistractorGenerationModel generating distractors for quiz questions
+pub struct DistractorGenerationModel {
+    generator: DistractorGenerationOption,
+    embeddings_model: SentenceEmbeddingsModel,
+    prompt_template: String,
+    separator: Option<String>,
+    num_candidates: i64,
+    num_beams: i64,
+    num_distractors: usize,
+    answer_similarity_threshold: f32,
+    distractor_similarity_threshold: f32,
+}
+
+impl DistractorGenerationModel {
+    /// Build a new `DistractorGenerationModel`
+    ///
+    /// # Arguments
+    ///
+    /// * `config` - `DistractorGenerationConfig` object containing the generator and sentence embeddings configurations and the filtering parameters
+    pub fn new(
+        config: DistractorGenerationConfig,
+    ) -> Result<DistractorGenerationModel, RustBertError> {
+        if config.num_candidates < 1 {
+            return Err(RustBertError::InvalidConfigurationError(
+                "At least one candidate must be generated per question".to_string(),
+            ));
+        }
+        let num_beams = config.generate_config.num_beams;
+        let generator = DistractorGenerationOption::new(config.model_type, config.generate_config)?;
+        let embeddings_model = SentenceEmbeddingsModel::new(config.embeddings_config)?;
+        Ok(DistractorGenerationModel {
+            generator,
+            embeddings_model,
+            prompt_template: config.prompt_template,
+            separator: config.separator,
+            num_candidates: config.num_candidates,
+            num_beams,
+            num_distractors: config.num_distractors,
+            answer_similarity_threshold: config.answer_similarity_threshold,
+            distractor_similarity_threshold: config.distractor_similarity_threshold,
+        })
+    }
+
+    /// Token sequences of the answer banned from the generation: the full answer and its words, with and without a
+    /// leading space (as encoded differently by byte-level BPE tokenizers)
+    fn banned_sequences(&self, answer: &str) -> Vec<Vec<i64>> {
+        let tokenizer = self.generator.get_tokenizer();
+        let mut spans = vec![answer.trim().to_string()];
+        let words = answer.split_whitespace().collect::<Vec<&str>>();
+        if words.len() > 1 {
+            spans.extend(
+                words
+                    .iter()
+                    .filter(|word| word.chars().filter(|c| c.is_alphanumeric()).count() > 3)
+                    .map(|word| word.to_string()),
+            );
+        }
+        let mut banned = vec![];
+        for span in spans {
+            for text in [span.clone(), format!(" {}", span)] {
+                let ids = tokenizer.convert_tokens_to_ids(&tokenizer.tokenize(&text));
+                if !ids.is_empty() && !banned.contains(&ids) {
+                    banned.push(ids);
+                }
+            }
+        }
+        banned
+    }
+
+    /// Generates distractors for quiz questions
+    ///
+    /// # Arguments
+    ///
+    /// * `inputs` - `&[QuizInput]` Quiz questions with their passage and correct answer
+    ///
+    /// # Returns
+    ///
+    /// * `Vec<Vec<Distractor>>` up to `num_distractors` distractors for each question, in decreasing generation score order
+    pub fn generate(&self, inputs: &[QuizInput]) -> Result<Vec<Vec<Distractor>>, RustBertError> {
+        let mut output = Vec::with_capacity(inputs.len());
+        for input in inputs {
+            let prompt = self
+                .prompt_template
+                .replace("{passage}", &input.passage)
+                .replace("{question}", &input.question)
+                .replace("{answer}", &input.answer);
+            // The banned sequences are specific to each answer: questions are generated one at a time
+            let banned_sequences = self.banned_sequences(&input.answer);
+            let generate_options = GenerateOptions {
+                num_beams: Some(self.num_beams.max(self.num_candidates)),
+                num_return_sequences: Some(self.num_candidates),
+                bad_word_ids: Some(&banned_sequences),
+                ..Default::default()
+            };
+            let generated = self
+                .generator
+                .generate(Some(&[prompt]), Some(generate_options));
+
+            let mut candidates: Vec<String> = vec![];
+            for sequence in generated {
+                let parts = match &self.separator {
+                    Some(separator) => sequence
+                        .split(separator.as_str())
+                        .map(String::from)
+                        .collect(),
+                    None => vec![sequence],
+                };
+                for part in parts {
+                    let candidate = normalize_candidate(&part);
+                    if is_valid_candidate(&candidate, &input.answer)
+                        && !candidates
+                            .iter()
+                            .any(|existing| existing.eq_ignore_ascii_case(&candidate))
+                    {
+                        candidates.push(candidate);
+                    }
+                }
+            }
+            if candidates.is_empty() {
+                output.push(vec![]);
+                continue;
+            }
+
+            let mut texts = vec![input.answer.as_str()];
+            texts.extend(candidates.iter().map(|candidate| candidate.as_str()));
+            let mut embeddings = self.embeddings_model.encode(&texts)?;
+            let answer_embedding = embeddings.remove(0);
+            output.push(select_distractors(
+                candidates,
+                &answer_embedding,
+                &embeddings,
+                self.num_distractors,
+                self.answer_similarity_threshold,
+                self.distractor_similarity_threshold,
+            ));
+        }
+        Ok(output)
+    }
+}
+
+fn normalize_candidate(text: &str) -> String {
+    text.trim()
+        .trim_end_matches(|c: char| c == '.' || c == ',' || c == ';')
+        .trim()
+        .to_string()
+}
+
+/// Rejects empty candidates and candidates containing (or contained in) the correct answer
+fn is_valid_candidate(candidate: &str, answer: &str) -> bool {
+    let candidate = candidate.to_lowercase();
+    let answer = answer.trim().to_lowercase();
+    !candidate.is_empty() && !candidate.contains(&answer) && !answer.contains(&candidate)
+}
+
+fn cosine_similarity(a: &[f32], b: &[f32]) -> f32 {
+    let dot: f32 = a.iter().zip(b.iter()).map(|(a, b)| a * b).sum();
+    let norm_a = a.iter().map(|a| a * a).sum::<f32>().sqrt();
+    let norm_b = b.iter().map(|b| b * b).sum::<f32>().sqrt();
+    dot / (norm_a * norm_b).max(1e-12)
+}
+
+/// Greedy selection of the candidates (in generation order) that are not near-synonyms of the answer and differ from
+/// the distractors already selected
+fn select_distractors(
+    candidates: Vec<String>,
+    answer_embedding: &[f32],
+    candidate_embeddings: &[Embedding],
+    num_distractors: usize,
+    answer_similarity_threshold: f32,
+    distractor_similarity_threshold: f32,
+) -> Vec<Distractor> {
+    let mut selected: Vec<(Distractor, &Embedding)> = vec![];
+    for (text, embedding) in candidates.into_iter().zip(candidate_embeddings.iter()) {
+        if selected.len() >= num_distractors {
+            break;
+        }
+        let answer_similarity = cosine_similarity(answer_embedding, embedding);
+        if answer_similarity > answer_similarity_threshold {
+            continue;
+        }
+        if selected.iter().any(|(_, selected_embedding)| {
+            cosine_similarity(selected_embedding, embedding) > distractor_similarity_threshold
+        }) {
+            continue;
+        }
+        selected.push((
+            Distractor {
+                text,
+                answer_similarity,
+            },
+            embedding,
+        ));
+    }
+    selected
+        .into_iter()
+        .map(|(distractor, _)| distractor)
+        .collect()
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_candidate_validation() {
+        assert_eq!(normalize_candidate(" Lyon. "), "Lyon");
+        assert!(is_valid_candidate("Lyon", "Paris"));
+        assert!(!is_valid_candidate("paris", "Paris"));
+        assert!(!is_valid_candidate("Paris, France", "Paris"));
+        assert!(!is_valid_candidate("", "Paris"));
+    }
+
+    #[test]
+    fn test_select_distractors() {
+        let candidates = vec![
+            "City of Light".to_string(),
+            "Lyon".to_string(),
+            "Lyon city".to_string(),
+            "Marseille".to_string(),
+            "Nice".to_string(),
+        ];
+        let answer_embedding = vec![1.0, 0.0, 0.0];
+        let candidate_embeddings = vec![
+            vec![0.95, 0.1, 0.0],
+            vec![0.5, 0.8, 0.0],
+            vec![0.5, 0.79, 0.05],
+            vec![0.5, 0.0, 0.8],
+            vec![0.5, 0.5, 0.5],
+        ];
+        let distractors = select_distractors(
+            candidates,
+            &answer_embedding,
+            &candidate_embeddings,
+            2,
+            0.8,
+            0.9,
+        );
+        assert_eq!(
+            distractors
+                .iter()
+                .map(|distractor| distractor.text.as_str())
+                .collect::<Vec<&str>>(),
+            vec!["Lyon", "Marseille"]
+        );
+        assert!(distractors
+            .iter()
+            .all(|distractor| distractor.answer_similarity < 0.8));
+    }
+}
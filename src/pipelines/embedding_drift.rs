@@ -0,0 +1,332 @@
+// Copyright 2022 Guillaume Becquin
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//     http://www.apache.org/licenses/LICENSE-2.0
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! # Embedding drift between model versions
+//! Compares the embeddings of a reference (e.g. production) sentence embeddings model and a candidate model on a probe
+//! corpus, to validate a model upgrade before replacing the encoder of a deployed index. The embeddings of two models
+//! usually live in different spaces (possibly of different dimensions) and cannot be compared directly, so the
+//! comparison relies on statistics that are invariant to a change of basis:
+//! - the cosine similarity of each probe text to a set of anchor texts, embedded by the same model. The mean anchor
+//! similarity of both models and its shift for each probe indicate how the similarity scale changes (e.g. to adjust
+//! retrieval thresholds), and the correlation of the similarity profiles indicates if the texts are ranked similarly.
+//! - the linear centered kernel alignment (CKA, [Similarity of Neural Network Representations Revisited](https://arxiv.org/abs/1905.00414)
+//! Kornblith, Norouzi, Lee, Hinton, 2019) of the probe embeddings, between 0 (unrelated representations) and 1
+//! (identical representations up to a rotation and a scaling).
+//!
+//! `DriftReport::from_embeddings` computes the same statistics for embeddings computed by another pipeline.
+//!
+//! ```no_run
+//! # fn main() -> anyhow::Result<()> {
+//! use rust_bert::pipelines::embedding_drift::EmbeddingDriftMonitor;
+//! use rust_bert::pipelines::sentence_embeddings::{
+//!     SentenceEmbeddingsBuilder, SentenceEmbeddingsModelType,
+//! };
+//!
+//! let reference_model =
+//!     SentenceEmbeddingsBuilder::remote(SentenceEmbeddingsModelType::AllDistilrobertaV1).create_model()?;
+//! let candidate_model =
+//!     SentenceEmbeddingsBuilder::remote(SentenceEmbeddingsModelType::AllMiniLmL12V2).create_model()?;
+//! let monitor = EmbeddingDriftMonitor::new(&reference_model, &candidate_model);
+//!
+//! let probes = [
+//!     "How do I reset my password?",
+//!     "The package was delivered to the wrong address.",
+//!     "Can I change the date of my booking?",
+//! ];
+//! let anchors = ["Account and login issues", "Shipping and delivery", "Reservations"];
+//! let report = monitor.compare(&probes, &anchors)?;
+//! println!(
+//!     "CKA: {:.3}, anchor similarity correlation: {:.3}",
+//!     report.cka, report.anchor_similarity_correlation
+//! );
+//! # Ok(())
+//! # }
+//! ```
+
+use crate::common::error::RustBertError;
+use crate::pipelines::sentence_embeddings::SentenceEmbeddingsModel;
+use serde::{Deserialize, Serialize};
+use tch::{Device, Kind, Tensor};
+
+/// # Distribution shift statistics between the embeddings of two models
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct DriftReport {
+    /// Number of probe texts
+    pub num_probes: usize,
+    /// Number of anchor texts
+    pub num_anchors: usize,
+    /// Mean cosine similarity of the probes to the anchors for the reference model
+    pub reference_mean_anchor_similarity: f64,
+    /// Mean cosine similarity of the probes to the anchors for the candidate model
+    pub candidate_mean_anchor_similarity: f64,
+    /// Change (candidate - reference) of the mean cosine similarity to the anchors of each probe
+    pub probe_similarity_shifts: Vec<f64>,
+    /// Pearson correlation of the probe to anchor cosine similarities of both models
+    pub anchor_similarity_correlation: f64,
+    /// Linear centered kernel alignment of the probe embeddings of both models (between 0 and 1)
+    pub cka: f64,
+}
+
+impl DriftReport {
+    /// Computes the drift statistics from embeddings of shape (*number of texts*, *embedding dimension*). The
+    /// reference and candidate embeddings may have different dimensions but must be computed for the same texts.
+    ///
+    /// # Arguments
+    ///
+    /// * `reference_probes` - Embeddings of the probe texts by the reference model
+    /// * `reference_anchors` - Embeddings of the anchor texts by the reference model
+    /// * `candidate_probes` - Embeddings of the probe texts by the candidate model
+    /// * `candidate_anchors` - Embeddings of the anchor texts by the candidate model
+    pub fn from_embeddings(
+        reference_probes: &Tensor,
+        reference_anchors: &Tensor,
+        candidate_probes: &Tensor,
+        candidate_anchors: &Tensor,
+    ) -> Result<DriftReport, RustBertError> {
+        let num_probes = reference_probes.size()[0];
+        let num_anchors = reference_anchors.size()[0];
+        if num_probes == 0 || num_anchors == 0 {
+            return Err(RustBertError::ValueError(
+                "At least one probe and one anchor are required to compute the embedding drift"
+                    .to_string(),
+            ));
+        }
+        if candidate_probes.size()[0] != num_probes || candidate_anchors.size()[0] != num_anchors {
+            return Err(RustBertError::ValueError(format!(
+                "The reference and candidate embeddings must be computed for the same texts, got {} and {} probes, {} and {} anchors",
+                num_probes,
+                candidate_probes.size()[0],
+                num_anchors,
+                candidate_anchors.size()[0]
+            )));
+        }
+
+        let reference_similarities = cosine_similarities(reference_probes, reference_anchors);
+        let candidate_similarities = cosine_similarities(candidate_probes, candidate_anchors);
+        let probe_similarity_shifts = (&candidate_similarities - &reference_similarities)
+            .mean_dim(&[1], false, Kind::Double)
+            .iter::<f64>()
+            .unwrap()
+            .collect::<Vec<f64>>();
+
+        Ok(DriftReport {
+            num_probes: num_probes as usize,
+            num_anchors: num_anchors as usize,
+            reference_mean_anchor_similarity: reference_similarities
+                .mean(Kind::Double)
+                .double_value(&[]),
+            candidate_mean_anchor_similarity: candidate_similarities
+                .mean(Kind::Double)
+                .double_value(&[]),
+            probe_similarity_shifts,
+            anchor_similarity_correlation: pearson_correlation(
+                &reference_similarities,
+                &candidate_similarities,
+            ),
+            cka: linear_cka(reference_probes, candidate_probes),
+        })
+    }
+}
+
+/// Cosine similarities of shape (*number of probes*, *number of anchors*), in double precision on the CPU
+fn cosine_similarities(probes: &Tensor, anchors: &Tensor) -> Tensor {
+    let normalize = |embeddings: &Tensor| {
+        let embeddings = embeddings.to_device(Device::Cpu).to_kind(Kind::Double);
+        let norms = embeddings
+            .norm_scalaropt_dim(2, &[1], true)
+            .clamp_min(1e-12);
+        embeddings / norms
+    };
+    normalize(probes).matmul(&normalize(anchors).tr())
+}
+
+fn pearson_correlation(x: &Tensor, y: &Tensor) -> f64 {
+    let x = x.flatten(0, -1);
+    let y = y.flatten(0, -1);
+    let x = &x - x.mean(Kind::Double);
+    let y = &y - y.mean(Kind::Double);
+    let covariance = (&x * &y).sum(Kind::Double).double_value(&[]);
+    let variances = (&x * &x).sum(Kind::Double).double_value(&[])
+        * (&y * &y).sum(Kind::Double).double_value(&[]);
+    covariance / variances.sqrt().max(1e-12)
+}
+
+/// Linear centered kernel alignment between two sets of embeddings of shape (*number of texts*, *embedding dimension*)
+/// computed for the same texts (the embedding dimensions may differ). The CKA is invariant to rotations and isotropic
+/// scalings of the embeddings, and equals 1 for identical representations.
+///
+/// # Arguments
+///
+/// * `x` - First set of embeddings
+/// * `y` - Second set of embeddings
+///
+/// # Returns
+///
+/// * `f64` CKA similarity between 0 and 1
+pub fn linear_cka(x: &Tensor, y: &Tensor) -> f64 {
+    let center = |embeddings: &Tensor| {
+        let embeddings = embeddings.to_device(Device::Cpu).to_kind(Kind::Double);
+        &embeddings - embeddings.mean_dim(&[0], true, Kind::Double)
+    };
+    let x = center(x);
+    let y = center(y);
+    let cross_covariance = y.tr().matmul(&x).norm().double_value(&[]);
+    let normalization =
+        x.tr().matmul(&x).norm().double_value(&[]) * y.tr().matmul(&y).norm().double_value(&[]);
+    cross_covariance * cross_covariance / normalization.max(1e-12)
+}
+
+/// # EmbeddingDriftMonitor comparing two sentence embeddings models
+pub struct EmbeddingDriftMonitor<'a> {
+    reference: &'a SentenceEmbeddingsModel,
+    candidate: &'a SentenceEmbeddingsModel,
+    batch_size: usize,
+}
+
+impl<'a> EmbeddingDriftMonitor<'a> {
+    /// Build a new `EmbeddingDriftMonitor`
+    ///
+    /// # Arguments
+    ///
+    /// * `reference` - Sentence embeddings model currently in use
+    /// * `candidate` - Sentence embeddings model to validate
+    pub fn new(
+        reference: &'a SentenceEmbeddingsModel,
+        candidate: &'a SentenceEmbeddingsModel,
+    ) -> EmbeddingDriftMonitor<'a> {
+        EmbeddingDriftMonitor {
+            reference,
+            candidate,
+            batch_size: 32,
+        }
+    }
+
+    /// Sets the number of texts embedded at once
+    ///
+    /// # Arguments
+    ///
+    /// * `batch_size` - Maximum number of texts in a batch (default: 32)
+    pub fn with_batch_size(mut self, batch_size: usize) -> Self {
+        self.batch_size = batch_size.max(1);
+        self
+    }
+
+    fn embed<S>(
+        &self,
+        model: &SentenceEmbeddingsModel,
+        texts: &[S],
+    ) -> Result<Tensor, RustBertError>
+    where
+        S: AsRef<str> + Sync,
+    {
+        let mut embeddings = Vec::with_capacity(texts.len());
+        for batch in texts.chunks(self.batch_size) {
+            embeddings.extend(model.encode(batch)?);
+        }
+        Ok(Tensor::of_slice(&embeddings.concat()).view((texts.len() as i64, -1)))
+    }
+
+    /// Embeds the probe and anchor texts with both models and computes the drift statistics
+    ///
+    /// # Arguments
+    ///
+    /// * `probes` - Probe corpus, representative of the texts embedded in production
+    /// * `anchors` - Anchor texts the probes are compared to (e.g. the corpus itself, cluster or label descriptions)
+    ///
+    /// # Returns
+    ///
+    /// * `DriftReport` containing the distribution shift statistics between the two models
+    pub fn compare<S1, S2>(
+        &self,
+        probes: &[S1],
+        anchors: &[S2],
+    ) -> Result<DriftReport, RustBertError>
+    where
+        S1: AsRef<str> + Sync,
+        S2: AsRef<str> + Sync,
+    {
+        if probes.is_empty() || anchors.is_empty() {
+            return Err(RustBertError::ValueError(
+                "At least one probe and one anchor are required to compute the embedding drift"
+                    .to_string(),
+            ));
+        }
+        DriftReport::from_embeddings(
+            &self.embed(self.reference, probes)?,
+            &self.embed(self.reference, anchors)?,
+            &self.embed(self.candidate, probes)?,
+            &self.embed(self.candidate, anchors)?,
+        )
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_linear_cka() {
+        tch::manual_seed(42);
+        let x = Tensor::randn(&[64, 8], (Kind::Float, Device::Cpu));
+
+        // Invariant to isotropic scaling, permutation of the dimensions and additional constant dimensions
+        let scaled = &x * 3.0;
+        let permuted = x.flip(&[1]);
+        let padded = Tensor::cat(
+            &[&x, &Tensor::ones(&[64, 4], (Kind::Float, Device::Cpu))],
+            1,
+        );
+        assert!((linear_cka(&x, &scaled) - 1.0).abs() < 1e-6);
+        assert!((linear_cka(&x, &permuted) - 1.0).abs() < 1e-6);
+        assert!((linear_cka(&x, &padded) - 1.0).abs() < 1e-6);
+
+        let unrelated = Tensor::randn(&[64, 8], (Kind::Float, Device::Cpu));
+        assert!(linear_cka(&x, &unrelated) < 0.5);
+    }
+
+    #[test]
+    fn test_drift_report() {
+        tch::manual_seed(42);
+        let probes = Tensor::randn(&[16, 8], (Kind::Float, Device::Cpu));
+        let anchors = Tensor::randn(&[4, 8], (Kind::Float, Device::Cpu));
+
+        // Rotated embeddings: same similarities, no drift
+        let rotation = Tensor::randn(&[8, 8], (Kind::Double, Device::Cpu))
+            .linalg_qr("reduced")
+            .0
+            .to_kind(Kind::Float);
+        let report = DriftReport::from_embeddings(
+            &probes,
+            &anchors,
+            &probes.matmul(&rotation),
+            &anchors.matmul(&rotation),
+        )
+        .unwrap();
+        assert_eq!(report.num_probes, 16);
+        assert_eq!(report.num_anchors, 4);
+        assert!(
+            (report.reference_mean_anchor_similarity - report.candidate_mean_anchor_similarity)
+                .abs()
+                < 1e-5
+        );
+        assert!(report
+            .probe_similarity_shifts
+            .iter()
+            .all(|shift| shift.abs() < 1e-5));
+        assert!((report.anchor_similarity_correlation - 1.0).abs() < 1e-5);
+        assert!((report.cka - 1.0).abs() < 1e-5);
+
+        assert!(
+            DriftReport::from_embeddings(&probes, &anchors, &probes.narrow(0, 0, 8), &anchors)
+                .is_err()
+        );
+    }
+}
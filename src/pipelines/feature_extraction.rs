@@ -0,0 +1,536 @@
+// Copyright 2022 Guillaume Becquin
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//     http://www.apache.org/licenses/LICENSE-2.0
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! # Feature extraction for downstream tabular models
+//! Turns texts into fixed-size feature vectors, e.g. to be appended to the tabular columns of a gradient-boosted
+//! model. The features are produced by a list of named `FeatureSource`s (pooled sentence embeddings, selected
+//! classification logits, or any other model implementing the trait, whatever its runtime), concatenated in the order
+//! of the sources.
+//!
+//! The layout of the feature vectors is described by a `FeatureSchema` listing the name, source and kind of each
+//! column. The schema can be serialized to JSON and stored alongside the features (e.g. in a feature store), and
+//! carries a fingerprint of the layout: features produced by an extractor with a different fingerprint must not be
+//! mixed with the stored features.
+//!
+//! ```no_run
+//! # fn main() -> anyhow::Result<()> {
+//! use rust_bert::pipelines::feature_extraction::{
+//!     EmbeddingFeatures, FeatureExtractor, FeatureSource, LogitFeatures,
+//! };
+//! use rust_bert::pipelines::sentence_embeddings::{
+//!     SentenceEmbeddingsModel, SentenceEmbeddingsModelType,
+//! };
+//! use rust_bert::pipelines::sequence_classification::SequenceClassificationModel;
+//!
+//! let embeddings_model =
+//!     SentenceEmbeddingsModel::new(SentenceEmbeddingsModelType::AllMiniLmL12V2.into())?;
+//! let sentiment_model = SequenceClassificationModel::new(Default::default())?;
+//!
+//! let sources: Vec<Box<dyn FeatureSource>> = vec![
+//!     Box::new(EmbeddingFeatures::new("minilm", embeddings_model)?),
+//!     Box::new(LogitFeatures::new("sentiment", sentiment_model, None)?),
+//! ];
+//! let extractor = FeatureExtractor::new("review_features", 1, sources)?;
+//! std::fs::write("review_features.json", extractor.schema().to_json()?)?;
+//!
+//! let features = extractor.extract(&["Great product, fast delivery."])?;
+//! # Ok(())
+//! # }
+//! ```
+
+use crate::common::error::RustBertError;
+use crate::pipelines::sentence_embeddings::SentenceEmbeddingsModel;
+use crate::pipelines::sequence_classification::SequenceClassificationModel;
+use serde::{Deserialize, Serialize};
+use std::collections::HashSet;
+use tch::Tensor;
+
+/// Version of the layout rules (column naming and ordering) applied by the `FeatureExtractor`
+pub const FEATURE_LAYOUT_VERSION: u32 = 1;
+
+/// # Kind of the features produced by a source
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum FeatureKind {
+    /// Pooled embedding dimensions
+    Embedding,
+    /// Classification logits
+    Logit,
+    /// Other features
+    Other,
+}
+
+/// # Column of the feature vectors
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct FeatureColumn {
+    /// Column name, `{source}.{feature}`
+    pub name: String,
+    /// Name of the source producing the column
+    pub source: String,
+    /// Kind of the feature
+    pub kind: FeatureKind,
+    /// Position of the column in the feature vectors
+    pub index: usize,
+}
+
+/// # Layout of the feature vectors
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct FeatureSchema {
+    /// Name of the feature set
+    pub name: String,
+    /// Version of the feature set, managed by the user
+    pub version: u32,
+    /// Version of the layout rules of the extractor (`FEATURE_LAYOUT_VERSION`)
+    pub layout_version: u32,
+    /// Hexadecimal fingerprint of the layout, stable across runs and platforms
+    pub fingerprint: String,
+    /// Columns of the feature vectors, in order
+    pub columns: Vec<FeatureColumn>,
+}
+
+impl FeatureSchema {
+    fn new(name: &str, version: u32, columns: Vec<FeatureColumn>) -> FeatureSchema {
+        let mut schema = FeatureSchema {
+            name: name.to_string(),
+            version,
+            layout_version: FEATURE_LAYOUT_VERSION,
+            fingerprint: String::new(),
+            columns,
+        };
+        schema.fingerprint = schema.compute_fingerprint();
+        schema
+    }
+
+    /// FNV-1a hash of the layout version and of the columns (the standard library hasher is not stable across
+    /// releases)
+    fn compute_fingerprint(&self) -> String {
+        let mut hash: u64 = 0xcbf2_9ce4_8422_2325;
+        let mut write = |bytes: &[u8]| {
+            for byte in bytes {
+                hash ^= *byte as u64;
+                hash = hash.wrapping_mul(0x0000_0100_0000_01b3);
+            }
+            // Separator, so that the concatenation of fields is unambiguous
+            hash ^= 0xff;
+            hash = hash.wrapping_mul(0x0000_0100_0000_01b3);
+        };
+        write(&self.layout_version.to_le_bytes());
+        for column in &self.columns {
+            write(column.name.as_bytes());
+            write(column.source.as_bytes());
+            write(format!("{:?}", column.kind).as_bytes());
+        }
+        format!("{:016x}", hash)
+    }
+
+    /// Returns the number of features
+    pub fn num_features(&self) -> usize {
+        self.columns.len()
+    }
+
+    /// Serializes the schema to JSON
+    pub fn to_json(&self) -> Result<String, RustBertError> {
+        serde_json::to_string_pretty(self)
+            .map_err(|error| RustBertError::ValueError(error.to_string()))
+    }
+
+    /// Reads a schema from JSON
+    ///
+    /// # Arguments
+    ///
+    /// * `json` - Serialized schema
+    pub fn from_json(json: &str) -> Result<FeatureSchema, RustBertError> {
+        serde_json::from_str(json).map_err(|error| RustBertError::ValueError(error.to_string()))
+    }
+
+    /// Checks that features following another schema (e.g. read from a feature store) can be mixed with the
+    /// features following this schema
+    ///
+    /// # Arguments
+    ///
+    /// * `other` - Schema of the other features
+    pub fn check_compatible(&self, other: &FeatureSchema) -> Result<(), RustBertError> {
+        if self.fingerprint != other.fingerprint || self.columns != other.columns {
+            return Err(RustBertError::ValueError(format!(
+                "Incompatible feature layouts: {} v{} (fingerprint {}) and {} v{} (fingerprint {})",
+                self.name,
+                self.version,
+                self.fingerprint,
+                other.name,
+                other.version,
+                other.fingerprint
+            )));
+        }
+        Ok(())
+    }
+}
+
+/// # Source of text features
+/// Implement this trait to extract features with a model that is not part of this crate (e.g. an ONNX or
+/// TorchScript model run with another runtime).
+pub trait FeatureSource {
+    /// Name of the source, prefixing its column names
+    fn name(&self) -> &str;
+
+    /// Kind of the features produced
+    fn kind(&self) -> FeatureKind;
+
+    /// Names of the features produced, in order. The number of names must not change over the lifetime of the source.
+    fn feature_names(&self) -> Vec<String>;
+
+    /// Computes the features of texts
+    ///
+    /// # Arguments
+    ///
+    /// * `texts` - Texts to featurize
+    ///
+    /// # Returns
+    ///
+    /// * `Vec<Vec<f32>>` feature vector of each text, with one value per feature name
+    fn extract(&self, texts: &[&str]) -> Result<Vec<Vec<f32>>, RustBertError>;
+}
+
+/// # Pooled sentence embeddings features
+pub struct EmbeddingFeatures {
+    name: String,
+    model: SentenceEmbeddingsModel,
+    dimension: usize,
+}
+
+impl EmbeddingFeatures {
+    /// Create a new `EmbeddingFeatures` source
+    ///
+    /// # Arguments
+    ///
+    /// * `name` - Name of the source
+    /// * `model` - `SentenceEmbeddingsModel` computing the embeddings
+    pub fn new(
+        name: &str,
+        model: SentenceEmbeddingsModel,
+    ) -> Result<EmbeddingFeatures, RustBertError> {
+        let dimension = model.encode(&["."])?[0].len();
+        Ok(EmbeddingFeatures {
+            name: name.to_string(),
+            model,
+            dimension,
+        })
+    }
+}
+
+impl FeatureSource for EmbeddingFeatures {
+    fn name(&self) -> &str {
+        &self.name
+    }
+
+    fn kind(&self) -> FeatureKind {
+        FeatureKind::Embedding
+    }
+
+    fn feature_names(&self) -> Vec<String> {
+        (0..self.dimension)
+            .map(|index| format!("dim_{}", index))
+            .collect()
+    }
+
+    fn extract(&self, texts: &[&str]) -> Result<Vec<Vec<f32>>, RustBertError> {
+        self.model.encode(texts)
+    }
+}
+
+/// # Classification logits features
+pub struct LogitFeatures {
+    name: String,
+    model: SequenceClassificationModel,
+    label_ids: Vec<i64>,
+    label_names: Vec<String>,
+}
+
+impl LogitFeatures {
+    /// Create a new `LogitFeatures` source
+    ///
+    /// # Arguments
+    ///
+    /// * `name` - Name of the source
+    /// * `model` - `SequenceClassificationModel` computing the logits
+    /// * `labels` - Optional labels whose logits are extracted, in order (default: all labels by class index)
+    pub fn new(
+        name: &str,
+        model: SequenceClassificationModel,
+        labels: Option<&[&str]>,
+    ) -> Result<LogitFeatures, RustBertError> {
+        let label_mapping = model.get_label_mapping();
+        let label_ids = match labels {
+            Some(labels) => labels
+                .iter()
+                .map(|label| {
+                    label_mapping
+                        .iter()
+                        .find(|(_, name)| name == label)
+                        .map(|(id, _)| *id)
+                        .ok_or_else(|| {
+                            RustBertError::InvalidConfigurationError(format!(
+                                "Label {} not found in the model label mapping",
+                                label
+                            ))
+                        })
+                })
+                .collect::<Result<Vec<i64>, RustBertError>>()?,
+            None => {
+                let mut label_ids = label_mapping.keys().cloned().collect::<Vec<i64>>();
+                label_ids.sort_unstable();
+                label_ids
+            }
+        };
+        let label_names = label_ids
+            .iter()
+            .map(|id| format!("logit_{}", sanitize(&label_mapping[id])))
+            .collect();
+        Ok(LogitFeatures {
+            name: name.to_string(),
+            model,
+            label_ids,
+            label_names,
+        })
+    }
+}
+
+impl FeatureSource for LogitFeatures {
+    fn name(&self) -> &str {
+        &self.name
+    }
+
+    fn kind(&self) -> FeatureKind {
+        FeatureKind::Logit
+    }
+
+    fn feature_names(&self) -> Vec<String> {
+        self.label_names.clone()
+    }
+
+    fn extract(&self, texts: &[&str]) -> Result<Vec<Vec<f32>>, RustBertError> {
+        let logits = self
+            .model
+            .predict_logits(texts)?
+            .index_select(1, &Tensor::of_slice(&self.label_ids));
+        Ok((0..logits.size()[0])
+            .map(|row| {
+                logits
+                    .get(row)
+                    .iter::<f64>()
+                    .unwrap()
+                    .map(|value| value as f32)
+                    .collect()
+            })
+            .collect())
+    }
+}
+
+/// Lowercases a label and replaces the characters other than ASCII alphanumerics by underscores
+fn sanitize(label: &str) -> String {
+    label
+        .chars()
+        .map(|c| {
+            if c.is_ascii_alphanumeric() {
+                c.to_ascii_lowercase()
+            } else {
+                '_'
+            }
+        })
+        .collect()
+}
+
+/// # FeatureExtractor producing fixed-size feature vectors from texts
+pub struct FeatureExtractor {
+    sources: Vec<Box<dyn FeatureSource>>,
+    schema: FeatureSchema,
+    batch_size: usize,
+}
+
+impl FeatureExtractor {
+    /// Build a new `FeatureExtractor`
+    ///
+    /// # Arguments
+    ///
+    /// * `name` - Name of the feature set
+    /// * `version` - Version of the feature set, to be incremented when the sources change without changing the layout (e.g. a fine-tuned model)
+    /// * `sources` - Feature sources, whose features are concatenated in order. Their names must be unique.
+    pub fn new(
+        name: &str,
+        version: u32,
+        sources: Vec<Box<dyn FeatureSource>>,
+    ) -> Result<FeatureExtractor, RustBertError> {
+        if sources.is_empty() {
+            return Err(RustBertError::InvalidConfigurationError(
+                "At least one feature source is required".to_string(),
+            ));
+        }
+        let mut source_names = HashSet::new();
+        let mut columns = vec![];
+        for source in sources.iter() {
+            if !source_names.insert(source.name().to_string()) {
+                return Err(RustBertError::InvalidConfigurationError(format!(
+                    "Duplicate feature source name: {}",
+                    source.name()
+                )));
+            }
+            for feature_name in source.feature_names() {
+                columns.push(FeatureColumn {
+                    name: format!("{}.{}", source.name(), feature_name),
+                    source: source.name().to_string(),
+                    kind: source.kind(),
+                    index: columns.len(),
+                });
+            }
+        }
+        Ok(FeatureExtractor {
+            sources,
+            schema: FeatureSchema::new(name, version, columns),
+            batch_size: 64,
+        })
+    }
+
+    /// Returns the layout of the feature vectors
+    pub fn schema(&self) -> &FeatureSchema {
+        &self.schema
+    }
+
+    /// Sets the number of texts processed by the sources at once (default: 64)
+    pub fn set_batch_size(&mut self, batch_size: usize) {
+        self.batch_size = batch_size.max(1);
+    }
+
+    /// Computes the feature vectors of texts
+    ///
+    /// # Arguments
+    ///
+    /// * `texts` - Texts to featurize
+    ///
+    /// # Returns
+    ///
+    /// * `Vec<Vec<f32>>` feature vector of each text, following the layout of `schema()`
+    pub fn extract<S>(&self, texts: &[S]) -> Result<Vec<Vec<f32>>, RustBertError>
+    where
+        S: AsRef<str>,
+    {
+        let texts = texts
+            .iter()
+            .map(|text| text.as_ref())
+            .collect::<Vec<&str>>();
+        let mut features = Vec::with_capacity(texts.len());
+        for batch in texts.chunks(self.batch_size) {
+            let mut batch_features =
+                vec![Vec::with_capacity(self.schema.num_features()); batch.len()];
+            for source in self.sources.iter() {
+                let num_features = self
+                    .schema
+                    .columns
+                    .iter()
+                    .filter(|column| column.source == source.name())
+                    .count();
+                let source_features = source.extract(batch)?;
+                if source_features.len() != batch.len() {
+                    return Err(RustBertError::ValueError(format!(
+                        "Feature source {} returned {} feature vectors for {} texts",
+                        source.name(),
+                        source_features.len(),
+                        batch.len()
+                    )));
+                }
+                for (row, values) in batch_features.iter_mut().zip(source_features) {
+                    if values.len() != num_features {
+                        return Err(RustBertError::ValueError(format!(
+                            "Feature source {} returned {} features, expected {}",
+                            source.name(),
+                            values.len(),
+                            num_features
+                        )));
+                    }
+                    row.extend(values);
+                }
+            }
+            features.extend(batch_features);
+        }
+        Ok(features)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    struct LengthFeatures {
+        name: String,
+    }
+
+    impl FeatureSource for LengthFeatures {
+        fn name(&self) -> &str {
+            &self.name
+        }
+
+        fn kind(&self) -> FeatureKind {
+            FeatureKind::Other
+        }
+
+        fn feature_names(&self) -> Vec<String> {
+            vec!["chars".to_string(), "words".to_string()]
+        }
+
+        fn extract(&self, texts: &[&str]) -> Result<Vec<Vec<f32>>, RustBertError> {
+            Ok(texts
+                .iter()
+                .map(|text| {
+                    vec![
+                        text.chars().count() as f32,
+                        text.split_whitespace().count() as f32,
+                    ]
+                })
+                .collect())
+        }
+    }
+
+    fn source(name: &str) -> Box<dyn FeatureSource> {
+        Box::new(LengthFeatures {
+            name: name.to_string(),
+        })
+    }
+
+    #[test]
+    fn test_feature_extraction() -> Result<(), RustBertError> {
+        let mut extractor = FeatureExtractor::new("test", 1, vec![source("a"), source("b")])?;
+        extractor.set_batch_size(1);
+        let schema = extractor.schema();
+        assert_eq!(
+            schema
+                .columns
+                .iter()
+                .map(|column| column.name.as_str())
+                .collect::<Vec<&str>>(),
+            vec!["a.chars", "a.words", "b.chars", "b.words"]
+        );
+
+        let features = extractor.extract(&["one two", "three"])?;
+        assert_eq!(
+            features,
+            vec![vec![7.0, 2.0, 7.0, 2.0], vec![5.0, 1.0, 5.0, 1.0]]
+        );
+
+        // The fingerprint only depends on the layout
+        let same_layout = FeatureExtractor::new("other", 2, vec![source("a"), source("b")])?;
+        assert!(schema.check_compatible(same_layout.schema()).is_ok());
+        let other_layout = FeatureExtractor::new("test", 1, vec![source("b"), source("a")])?;
+        assert!(schema.check_compatible(other_layout.schema()).is_err());
+
+        let deserialized = FeatureSchema::from_json(&schema.to_json()?)?;
+        assert_eq!(&deserialized, schema);
+
+        assert!(FeatureExtractor::new("test", 1, vec![source("a"), source("a")]).is_err());
+        Ok(())
+    }
+}
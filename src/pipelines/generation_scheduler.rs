@@ -0,0 +1,582 @@
+// Copyright 2022 Guillaume Becquin
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//     http://www.apache.org/licenses/LICENSE-2.0
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! # Generation scheduler with priority lanes
+//! `GenerationScheduler` batches text generation requests of two priority classes: `Priority::Interactive`
+//! (e.g. chat messages, where latency matters) and `Priority::Batch` (e.g. bulk jobs running in the background).
+//!
+//! Sequences are generated in segments of `segment_length` tokens, for at most `max_slots` sequences at a time.
+//! At each scheduling round (`GenerationScheduler::step`), the slots are assigned to the interactive sequences
+//! first, and the remaining slots to the batch sequences. A batch sequence that was running in the previous round is
+//! preempted when its slot is needed by an interactive request: it keeps the tokens generated so far and resumes
+//! (before newer batch requests) once a slot is available. `reserved_batch_slots` guarantees a minimum throughput
+//! to the batch lane when interactive requests saturate the scheduler.
+//!
+//! Shorter segments reduce the time an interactive request waits for a slot, at the cost of re-encoding the
+//! sequences at the start of each segment. Segments are generated with the decoding settings of the model:
+//! greedy decoding continues a sequence exactly as an uninterrupted generation would.
+//!
+//! ```no_run
+//! # fn main() -> anyhow::Result<()> {
+//! use rust_bert::pipelines::generation_scheduler::{
+//!     GenerationScheduler, Priority, SchedulerConfig,
+//! };
+//! use rust_bert::pipelines::text_generation::TextGenerationModel;
+//!
+//! let model = TextGenerationModel::new(Default::default())?;
+//! let mut scheduler = GenerationScheduler::new(model, SchedulerConfig::default())?;
+//!
+//! scheduler.submit("The quarterly report shows", Priority::Batch)?;
+//! scheduler.submit("Hello, how are you", Priority::Interactive)?;
+//! while !scheduler.is_idle() {
+//!     for completed in scheduler.step()? {
+//!         println!("{}: {}", completed.id, completed.text);
+//!     }
+//! }
+//! # Ok(())
+//! # }
+//! ```
+
+use crate::common::error::RustBertError;
+use crate::pipelines::generation_utils::GenerateOptions;
+use crate::pipelines::text_generation::TextGenerationModel;
+use std::collections::VecDeque;
+use tch::Tensor;
+
+/// # Priority class of a generation request
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Priority {
+    /// Latency-sensitive requests, scheduled before the batch requests
+    Interactive,
+    /// Background requests, preempted by the interactive requests
+    Batch,
+}
+
+/// # Segment generated for a sequence
+#[derive(Debug, Clone, PartialEq)]
+pub struct GeneratedSegment {
+    /// Generated token ids (excluding the end of sequence token)
+    pub token_ids: Vec<i64>,
+    /// True if the generation of the sequence is complete (end of sequence token generated)
+    pub finished: bool,
+}
+
+/// # Model continuing sequences segment by segment
+pub trait SegmentGenerator {
+    /// Converts a prompt to token ids
+    fn encode(&self, text: &str) -> Vec<i64>;
+
+    /// Converts the generated token ids to text
+    fn decode(&self, token_ids: &[i64]) -> String;
+
+    /// Continues each sequence by at most `max_new_tokens` tokens
+    fn generate_segment(
+        &self,
+        sequences: &[Vec<i64>],
+        max_new_tokens: i64,
+    ) -> Result<Vec<GeneratedSegment>, RustBertError>;
+}
+
+impl SegmentGenerator for TextGenerationModel {
+    fn encode(&self, text: &str) -> Vec<i64> {
+        let tokenizer = self.get_tokenizer();
+        tokenizer.convert_tokens_to_ids(&tokenizer.tokenize(text))
+    }
+
+    fn decode(&self, token_ids: &[i64]) -> String {
+        self.get_tokenizer().decode(token_ids, true, true)
+    }
+
+    fn generate_segment(
+        &self,
+        sequences: &[Vec<i64>],
+        max_new_tokens: i64,
+    ) -> Result<Vec<GeneratedSegment>, RustBertError> {
+        let model = self.get_model();
+        let eos_ids = model.get_eos_ids().cloned().unwrap_or_default();
+        let pad_id = model
+            .get_pad_id()
+            .or_else(|| eos_ids.first().copied())
+            .unwrap_or_else(|| self.get_tokenizer().get_unk_id());
+        let max_length = sequences.iter().map(Vec::len).max().unwrap_or(0);
+        if sequences.iter().any(Vec::is_empty) {
+            return Err(RustBertError::ValueError(
+                "Sequences to continue cannot be empty".to_string(),
+            ));
+        }
+
+        // Sequences are padded on the left, as for the generation from prompts
+        let mut input_ids = Vec::with_capacity(sequences.len() * max_length);
+        let mut attention_mask = Vec::with_capacity(sequences.len() * max_length);
+        for sequence in sequences {
+            let padding = max_length - sequence.len();
+            input_ids.extend(std::iter::repeat(pad_id).take(padding));
+            input_ids.extend(sequence);
+            attention_mask.extend(std::iter::repeat(0i64).take(padding));
+            attention_mask.extend(std::iter::repeat(1i64).take(sequence.len()));
+        }
+        let device = model.get_var_store().device();
+        let shape = (sequences.len() as i64, max_length as i64);
+        let input_ids = Tensor::of_slice(&input_ids).view(shape).to(device);
+        let attention_mask = Tensor::of_slice(&attention_mask).view(shape).to(device);
+
+        let generate_options = GenerateOptions {
+            max_new_tokens: Some(max_new_tokens),
+            num_return_sequences: Some(1),
+            ..Default::default()
+        };
+        let outputs = model.generate_from_ids_and_past(
+            input_ids,
+            Some(attention_mask),
+            Some(generate_options),
+        );
+        self.batch_completed();
+
+        Ok(outputs
+            .into_iter()
+            .map(|indices| {
+                let mut token_ids = vec![];
+                let mut finished = false;
+                for token_id in indices.into_iter().skip(max_length) {
+                    if eos_ids.contains(&token_id) {
+                        finished = true;
+                        break;
+                    }
+                    token_ids.push(token_id);
+                }
+                GeneratedSegment {
+                    token_ids,
+                    finished,
+                }
+            })
+            .collect())
+    }
+}
+
+/// # Configuration for a `GenerationScheduler`
+#[derive(Debug, Clone)]
+pub struct SchedulerConfig {
+    /// Maximum number of sequences generated at once
+    pub max_slots: usize,
+    /// Number of tokens generated for each sequence in a scheduling round
+    pub segment_length: i64,
+    /// Maximum number of tokens generated for a request
+    pub max_new_tokens: i64,
+    /// Number of slots kept for the batch requests, even if interactive requests are waiting
+    pub reserved_batch_slots: usize,
+}
+
+impl SchedulerConfig {
+    /// Instantiate a new scheduler configuration
+    ///
+    /// # Arguments
+    ///
+    /// * `max_slots` - Maximum number of sequences generated at once
+    /// * `segment_length` - Number of tokens generated for each sequence in a scheduling round
+    /// * `max_new_tokens` - Maximum number of tokens generated for a request
+    /// * `reserved_batch_slots` - Number of slots kept for the batch requests
+    pub fn new(
+        max_slots: usize,
+        segment_length: i64,
+        max_new_tokens: i64,
+        reserved_batch_slots: usize,
+    ) -> SchedulerConfig {
+        SchedulerConfig {
+            max_slots,
+            segment_length,
+            max_new_tokens,
+            reserved_batch_slots,
+        }
+    }
+}
+
+impl Default for SchedulerConfig {
+    fn default() -> SchedulerConfig {
+        SchedulerConfig::new(8, 16, 64, 0)
+    }
+}
+
+/// # Completed generation request
+#[derive(Debug, Clone, PartialEq)]
+pub struct CompletedGeneration {
+    /// Identifier returned by `GenerationScheduler::submit`
+    pub id: u64,
+    /// Priority class of the request
+    pub priority: Priority,
+    /// Generated text (excluding the prompt)
+    pub text: String,
+    /// Number of tokens generated
+    pub generated_tokens: usize,
+    /// Number of times the sequence lost its slot to a higher priority request
+    pub preemptions: usize,
+}
+
+/// # Scheduler statistics
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct SchedulerStatistics {
+    /// Number of scheduling rounds
+    pub rounds: usize,
+    /// Number of preemptions of running sequences
+    pub preemptions: usize,
+    /// Number of completed requests
+    pub completed: usize,
+}
+
+struct ScheduledSequence {
+    id: u64,
+    priority: Priority,
+    token_ids: Vec<i64>,
+    prompt_length: usize,
+    preemptions: usize,
+    running: bool,
+}
+
+impl ScheduledSequence {
+    fn generated_tokens(&self) -> usize {
+        self.token_ids.len() - self.prompt_length
+    }
+}
+
+/// # Generation scheduler with interactive and batch priority lanes
+pub struct GenerationScheduler<G> {
+    generator: G,
+    config: SchedulerConfig,
+    interactive: VecDeque<ScheduledSequence>,
+    batch: VecDeque<ScheduledSequence>,
+    next_id: u64,
+    statistics: SchedulerStatistics,
+}
+
+impl<G: SegmentGenerator> GenerationScheduler<G> {
+    /// Build a new `GenerationScheduler`
+    ///
+    /// # Arguments
+    ///
+    /// * `generator` - Model generating the sequences (e.g. a `TextGenerationModel`)
+    /// * `config` - `SchedulerConfig` with the number of slots and segment length
+    pub fn new(
+        generator: G,
+        config: SchedulerConfig,
+    ) -> Result<GenerationScheduler<G>, RustBertError> {
+        if config.max_slots == 0 || config.segment_length <= 0 || config.max_new_tokens <= 0 {
+            return Err(RustBertError::InvalidConfigurationError(
+                "The number of slots, segment length and maximum number of new tokens must be positive"
+                    .to_string(),
+            ));
+        }
+        if config.reserved_batch_slots >= config.max_slots {
+            return Err(RustBertError::InvalidConfigurationError(format!(
+                "The reserved batch slots ({}) must be fewer than the slots ({})",
+                config.reserved_batch_slots, config.max_slots
+            )));
+        }
+        Ok(GenerationScheduler {
+            generator,
+            config,
+            interactive: VecDeque::new(),
+            batch: VecDeque::new(),
+            next_id: 0,
+            statistics: SchedulerStatistics::default(),
+        })
+    }
+
+    /// Returns the generator
+    pub fn get_generator(&self) -> &G {
+        &self.generator
+    }
+
+    /// Returns the scheduler statistics
+    pub fn statistics(&self) -> SchedulerStatistics {
+        self.statistics
+    }
+
+    /// Returns the number of requests of a priority class not completed yet
+    pub fn pending(&self, priority: Priority) -> usize {
+        match priority {
+            Priority::Interactive => self.interactive.len(),
+            Priority::Batch => self.batch.len(),
+        }
+    }
+
+    /// Returns true if all requests are completed
+    pub fn is_idle(&self) -> bool {
+        self.interactive.is_empty() && self.batch.is_empty()
+    }
+
+    /// Submits a generation request
+    ///
+    /// # Arguments
+    ///
+    /// * `prompt` - Prompt to continue (cannot be empty)
+    /// * `priority` - Priority class of the request
+    ///
+    /// # Returns
+    ///
+    /// * `u64` Identifier of the request, returned with the `CompletedGeneration`
+    pub fn submit(&mut self, prompt: &str, priority: Priority) -> Result<u64, RustBertError> {
+        let token_ids = self.generator.encode(prompt);
+        if token_ids.is_empty() {
+            return Err(RustBertError::ValueError(
+                "The prompt of a generation request cannot be empty".to_string(),
+            ));
+        }
+        let id = self.next_id;
+        self.next_id += 1;
+        let sequence = ScheduledSequence {
+            id,
+            priority,
+            prompt_length: token_ids.len(),
+            token_ids,
+            preemptions: 0,
+            running: false,
+        };
+        match priority {
+            Priority::Interactive => self.interactive.push_back(sequence),
+            Priority::Batch => self.batch.push_back(sequence),
+        }
+        Ok(id)
+    }
+
+    /// Runs a scheduling round: assigns the slots, generates a segment for the scheduled sequences and returns the
+    /// requests completed in this round. On error, the scheduled sequences are returned to their queues unchanged.
+    pub fn step(&mut self) -> Result<Vec<CompletedGeneration>, RustBertError> {
+        if self.is_idle() {
+            return Ok(vec![]);
+        }
+        let reserved_batch_slots = self.config.reserved_batch_slots.min(self.batch.len());
+        let interactive_slots = self
+            .interactive
+            .len()
+            .min(self.config.max_slots - reserved_batch_slots);
+        let batch_slots = self
+            .batch
+            .len()
+            .min(self.config.max_slots - interactive_slots);
+
+        let mut scheduled = self
+            .interactive
+            .drain(..interactive_slots)
+            .chain(self.batch.drain(..batch_slots))
+            .collect::<Vec<ScheduledSequence>>();
+        for sequence in scheduled.iter_mut() {
+            sequence.running = false;
+        }
+        for sequence in self.interactive.iter_mut().chain(self.batch.iter_mut()) {
+            if sequence.running {
+                sequence.running = false;
+                sequence.preemptions += 1;
+                self.statistics.preemptions += 1;
+            }
+        }
+
+        let max_new_tokens = self.config.max_new_tokens as usize;
+        let segment_length = scheduled
+            .iter()
+            .map(|sequence| max_new_tokens - sequence.generated_tokens())
+            .max()
+            .unwrap_or(0)
+            .min(self.config.segment_length as usize);
+        let sequences = scheduled
+            .iter()
+            .map(|sequence| sequence.token_ids.clone())
+            .collect::<Vec<Vec<i64>>>();
+        let segments = match self
+            .generator
+            .generate_segment(&sequences, segment_length as i64)
+        {
+            Ok(segments) if segments.len() == scheduled.len() => segments,
+            result => {
+                self.requeue(scheduled);
+                return Err(match result {
+                    Err(error) => error,
+                    Ok(segments) => RustBertError::ValueError(format!(
+                        "Got {} segments for {} sequences",
+                        segments.len(),
+                        sequences.len()
+                    )),
+                });
+            }
+        };
+        self.statistics.rounds += 1;
+
+        let mut completed = vec![];
+        let mut running = vec![];
+        for (mut sequence, segment) in scheduled.into_iter().zip(segments) {
+            let remaining = max_new_tokens - sequence.generated_tokens();
+            let truncated = segment.token_ids.len() >= remaining;
+            sequence
+                .token_ids
+                .extend(segment.token_ids.into_iter().take(remaining));
+            if segment.finished || truncated {
+                completed.push(CompletedGeneration {
+                    id: sequence.id,
+                    priority: sequence.priority,
+                    text: self
+                        .generator
+                        .decode(&sequence.token_ids[sequence.prompt_length..]),
+                    generated_tokens: sequence.generated_tokens(),
+                    preemptions: sequence.preemptions,
+                });
+            } else {
+                sequence.running = true;
+                running.push(sequence);
+            }
+        }
+        self.statistics.completed += completed.len();
+        self.requeue(running);
+        Ok(completed)
+    }
+
+    /// Runs scheduling rounds until all submitted requests are completed
+    pub fn run_until_idle(&mut self) -> Result<Vec<CompletedGeneration>, RustBertError> {
+        let mut completed = vec![];
+        while !self.is_idle() {
+            completed.extend(self.step()?);
+        }
+        Ok(completed)
+    }
+
+    /// Returns sequences to the front of their queue, in order, so that they resume before newer requests
+    fn requeue(&mut self, sequences: Vec<ScheduledSequence>) {
+        for sequence in sequences.into_iter().rev() {
+            match sequence.priority {
+                Priority::Interactive => self.interactive.push_front(sequence),
+                Priority::Batch => self.batch.push_front(sequence),
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use std::cell::RefCell;
+
+    /// Encodes characters as token ids and appends `x` tokens, ending the sequences of prompts starting with `e`
+    /// after one token
+    struct CharacterGenerator {
+        rounds: RefCell<Vec<String>>,
+    }
+
+    impl CharacterGenerator {
+        fn new() -> CharacterGenerator {
+            CharacterGenerator {
+                rounds: RefCell::new(vec![]),
+            }
+        }
+    }
+
+    impl SegmentGenerator for CharacterGenerator {
+        fn encode(&self, text: &str) -> Vec<i64> {
+            text.chars().map(|c| c as i64).collect()
+        }
+
+        fn decode(&self, token_ids: &[i64]) -> String {
+            token_ids
+                .iter()
+                .map(|token_id| char::from_u32(*token_id as u32).unwrap())
+                .collect()
+        }
+
+        fn generate_segment(
+            &self,
+            sequences: &[Vec<i64>],
+            max_new_tokens: i64,
+        ) -> Result<Vec<GeneratedSegment>, RustBertError> {
+            self.rounds.borrow_mut().push(
+                sequences
+                    .iter()
+                    .map(|sequence| char::from_u32(sequence[0] as u32).unwrap())
+                    .collect(),
+            );
+            Ok(sequences
+                .iter()
+                .map(|sequence| {
+                    if sequence[0] == 'e' as i64 {
+                        GeneratedSegment {
+                            token_ids: vec!['x' as i64],
+                            finished: true,
+                        }
+                    } else {
+                        GeneratedSegment {
+                            token_ids: vec!['x' as i64; max_new_tokens as usize],
+                            finished: false,
+                        }
+                    }
+                })
+                .collect())
+        }
+    }
+
+    #[test]
+    fn interactive_requests_preempt_batch_sequences() -> Result<(), RustBertError> {
+        let mut scheduler =
+            GenerationScheduler::new(CharacterGenerator::new(), SchedulerConfig::new(2, 2, 6, 0))?;
+        scheduler.submit("a", Priority::Batch)?;
+        let preempted_id = scheduler.submit("b", Priority::Batch)?;
+        assert!(scheduler.step()?.is_empty());
+
+        scheduler.submit("i", Priority::Interactive)?;
+        assert!(scheduler.step()?.is_empty());
+        assert_eq!(scheduler.statistics().preemptions, 1);
+        assert_eq!(scheduler.pending(Priority::Batch), 2);
+
+        let completed = scheduler.run_until_idle()?;
+        assert_eq!(
+            *scheduler.get_generator().rounds.borrow(),
+            vec!["ab", "ia", "ia", "ib", "b"]
+        );
+        assert_eq!(completed.len(), 3);
+        let preempted = completed
+            .iter()
+            .find(|generation| generation.id == preempted_id)
+            .unwrap();
+        assert_eq!(preempted.text, "xxxxxx");
+        assert_eq!(preempted.generated_tokens, 6);
+        assert_eq!(preempted.preemptions, 1);
+        Ok(())
+    }
+
+    #[test]
+    fn reserved_batch_slots() -> Result<(), RustBertError> {
+        let mut scheduler =
+            GenerationScheduler::new(CharacterGenerator::new(), SchedulerConfig::new(2, 4, 4, 1))?;
+        for prompt in ["i", "j", "k"] {
+            scheduler.submit(prompt, Priority::Interactive)?;
+        }
+        scheduler.submit("a", Priority::Batch)?;
+        scheduler.step()?;
+        scheduler.step()?;
+        assert_eq!(*scheduler.get_generator().rounds.borrow(), vec!["ia", "jk"]);
+        assert!(scheduler.is_idle());
+        Ok(())
+    }
+
+    #[test]
+    fn finished_sequences_complete_early() -> Result<(), RustBertError> {
+        let mut scheduler =
+            GenerationScheduler::new(CharacterGenerator::new(), SchedulerConfig::default())?;
+        let id = scheduler.submit("end", Priority::Interactive)?;
+        let completed = scheduler.step()?;
+        assert_eq!(
+            completed,
+            vec![CompletedGeneration {
+                id,
+                priority: Priority::Interactive,
+                text: "x".to_string(),
+                generated_tokens: 1,
+                preemptions: 0,
+            }]
+        );
+        assert!(scheduler.submit("", Priority::Batch).is_err());
+        Ok(())
+    }
+}
@@ -66,17 +66,24 @@
 //! # ;
 //! ```
 
+use std::cell::RefCell;
+use std::collections::HashMap;
+
 use rust_tokenizers::tokenizer::Tokenizer;
 use rust_tokenizers::vocab::Vocab;
 use tch::kind::Kind::Int64;
 use tch::{no_grad, Device, Tensor};
 
 use crate::bart::LayerState as BartLayerState;
+use crate::bloom::LayerState as BloomLayerState;
+use crate::common::attention_mask::build_prefix_lm_attention_mask;
 use crate::common::error::RustBertError;
 use crate::common::resources::ResourceProvider;
 use crate::gpt_neo::LayerState as GPTNeoLayerState;
+use crate::gpt_neox::LayerState as GPTNeoXLayerState;
+use crate::llama::LayerState as LlamaLayerState;
 use crate::pipelines::generation_utils::private_generation_utils::{
-    InternalGenerateOptions, PrivateLanguageGenerator,
+    GenerationTimer, InternalGenerateOptions, PrivateLanguageGenerator,
 };
 use crate::prophetnet::LayerState as ProphetNetLayerState;
 use crate::reformer::LayerState as ReformerLayerState;
@@ -84,7 +91,11 @@ use crate::t5::LayerState as T5LayerState;
 use crate::xlnet::LayerState as XLNetLayerState;
 
 use self::ordered_float::OrderedFloat;
+use crate::pipelines::bad_words::SurfaceBadWords;
 use crate::pipelines::common::TokenizerOption;
+use crate::pipelines::grammar::{Grammar, GrammarConstraint};
+use crate::pipelines::logits_processor::LogitsProcessor;
+use crate::pipelines::watermark::Watermark;
 
 #[cfg(feature = "remote")]
 use crate::{
@@ -126,12 +137,47 @@ pub struct GenerateConfig {
     pub length_penalty: f64,
     /// Number of allowed repetitions of n-grams. Values higher than 0 turn on this feature (default: 3)
     pub no_repeat_ngram_size: i64,
+    /// Part of the sequence in which the n-grams may not be repeated: the whole sequence, a sliding window of
+    /// tokens, or the current sentence or paragraph (default: `NoRepeatNgramScope::Sequence`)
+    pub no_repeat_ngram_scope: NoRepeatNgramScope,
     /// Number of sequences to return for each prompt text (default: 1)
     pub num_return_sequences: i64,
     /// Number of beam groups for diverse beam generation. If provided and higher than 1, will split the beams into beam subgroups leading to more diverse generation.
     pub num_beam_groups: Option<i64>,
     /// Diversity penalty for diverse beam search. High values will enforce more difference between beam groups (default: 5.5)
     pub diversity_penalty: Option<f64>,
+    /// Degeneration penalty for [contrastive search, Su et al.](https://arxiv.org/abs/2202.06417). If provided and
+    /// higher than 0, greedy decoding (`do_sample` false, `num_beams` 1) picks among the `top_k` most likely tokens
+    /// the one maximizing `(1 - penalty_alpha) * probability - penalty_alpha * max similarity` of its hidden state
+    /// with the hidden states of the previous tokens. Values between 0.4 and 0.8 usually work well with a `top_k` of 4 to 10 (default: None)
+    pub penalty_alpha: Option<f64>,
+    /// Typical_p value for [locally typical sampling, Meister et al.](https://arxiv.org/abs/2202.00666). Keep the
+    /// tokens whose information content is the closest to the entropy of the distribution until their cumulative
+    /// probability reaches typical_p. Values between 0.2 and 0.95 usually work well (default: None)
+    pub typical_p: Option<f64>,
+    /// Epsilon cutoff for [truncation sampling, Hewitt et al.](https://arxiv.org/abs/2210.15191). Only sample the
+    /// tokens with a probability higher than epsilon_cutoff. Values between 3e-4 and 9e-4 usually work well (default: None)
+    pub epsilon_cutoff: Option<f64>,
+    /// Eta cutoff for [truncation sampling, Hewitt et al.](https://arxiv.org/abs/2210.15191). Only sample the tokens
+    /// with a probability higher than `min(eta_cutoff, sqrt(eta_cutoff) * exp(-entropy))`, adapting the cutoff to
+    /// the entropy of the distribution. Values between 3e-4 and 2e-3 usually work well (default: None)
+    pub eta_cutoff: Option<f64>,
+    /// Number of tokens of the segments of the prompt processed by each forward pass of the prefill, the cache
+    /// being extended after each segment. Bounds the activation memory required to ingest very long prompts, for
+    /// the decoder-only models with a cache (GPT2, GPT-Neo). If `None`, the prompt is processed in a single
+    /// forward pass (default: None)
+    pub prefill_chunk_size: Option<i64>,
+    /// Stop sequences. The generation of a sequence stops as soon as its generated text contains one of these
+    /// strings, and the returned text is truncated before it (default: empty)
+    pub stop_sequences: Vec<String>,
+    /// Bias added to the logits of the given token ids at every generation step (similar to the `logit_bias` of the
+    /// OpenAI API), nudging the vocabulary used by the model without retraining it. Positive values encourage the
+    /// generation of a token and negative values discourage it (values of -100 or lower effectively ban a token).
+    /// Replaced by `GenerateOptions::logit_bias` when provided (default: empty)
+    pub logit_bias: HashMap<i64, f64>,
+    /// Flag indicating if the model should be loaded with the output of its attention weights turned on. Required
+    /// to access the cross-attention weights of encoder-decoder models, for example to extract word alignments (default: false)
+    pub output_attentions: bool,
     /// Device to place the model on (default: CUDA/GPU when available)
     pub device: Device,
 }
@@ -155,9 +201,18 @@ impl Default for GenerateConfig {
             repetition_penalty: 1.0,
             length_penalty: 1.0,
             no_repeat_ngram_size: 3,
+            no_repeat_ngram_scope: NoRepeatNgramScope::Sequence,
             num_return_sequences: 1,
             num_beam_groups: None,
             diversity_penalty: None,
+            penalty_alpha: None,
+            typical_p: None,
+            epsilon_cutoff: None,
+            eta_cutoff: None,
+            prefill_chunk_size: None,
+            stop_sequences: vec![],
+            logit_bias: HashMap::new(),
+            output_attentions: false,
             device: Device::cuda_if_available(),
         }
     }
@@ -205,10 +260,115 @@ impl GenerateConfig {
                 assert_eq!(
                     self.num_beams % num_beam_groups_value,
                     0,
-                    "num_beam_groups must be a multiple of num_beam_groups"
+                    "num_beams must be a multiple of num_beam_groups"
                 )
             }
         }
+        if let Some(penalty_alpha) = self.penalty_alpha {
+            assert!(
+                (0f64..=1f64).contains(&penalty_alpha),
+                "penalty_alpha must be between 0 and 1"
+            );
+        }
+        validate_sampling_cutoffs(self.typical_p, self.epsilon_cutoff, self.eta_cutoff);
+        self.no_repeat_ngram_scope.validate();
+        if let Some(prefill_chunk_size) = self.prefill_chunk_size {
+            assert!(
+                prefill_chunk_size > 0,
+                "prefill_chunk_size must be strictly greater than 0"
+            );
+        }
+    }
+
+    /// Returns true if the configuration selects contrastive search, requiring a model exposing its hidden states
+    pub(crate) fn uses_contrastive_search(&self) -> bool {
+        uses_contrastive_search(
+            self.do_sample,
+            self.num_beams,
+            self.top_k,
+            self.penalty_alpha,
+        )
+    }
+}
+
+/// # Scope of the n-gram repetition constraint
+/// Part of the sequence in which the n-grams of size `no_repeat_ngram_size` may not be repeated. Restricting the
+/// scope allows long generated documents to repeat entities or phrases across sentences or paragraphs, while
+/// still preventing the degenerate repetitions within them.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum NoRepeatNgramScope {
+    /// The n-grams may not be repeated anywhere in the sequence
+    #[default]
+    Sequence,
+    /// The n-grams may not be repeated within the given number of last tokens of the sequence
+    Window(i64),
+    /// The n-grams may not be repeated within a sentence: the constraint is reset after each token ending with a
+    /// sentence-final punctuation (`.`, `!` or `?`, possibly followed by a closing quote or bracket) or
+    /// containing a line break
+    Sentence,
+    /// The n-grams may not be repeated within a paragraph: the constraint is reset after each token containing a
+    /// line break
+    Paragraph,
+}
+
+impl NoRepeatNgramScope {
+    fn validate(&self) {
+        if let NoRepeatNgramScope::Window(window) = self {
+            assert!(
+                *window > 0,
+                "the no_repeat_ngram_scope window must be strictly greater than 0"
+            );
+        }
+    }
+
+    /// Returns true if the n-gram repetition constraint is reset after a token with the given text
+    pub(crate) fn is_boundary(&self, token: &str) -> bool {
+        match self {
+            NoRepeatNgramScope::Sequence | NoRepeatNgramScope::Window(_) => false,
+            NoRepeatNgramScope::Sentence => {
+                token.contains('\n')
+                    || token
+                        .trim_end_matches(|c: char| {
+                            c.is_whitespace() || matches!(c, '"' | '\'' | ')' | ']')
+                        })
+                        .ends_with(|c: char| matches!(c, '.' | '!' | '?'))
+            }
+            NoRepeatNgramScope::Paragraph => token.contains('\n'),
+        }
+    }
+}
+
+fn uses_contrastive_search(
+    do_sample: bool,
+    num_beams: i64,
+    top_k: i64,
+    penalty_alpha: Option<f64>,
+) -> bool {
+    !do_sample & (num_beams == 1) & (top_k > 1) & penalty_alpha.map_or(false, |value| value > 0f64)
+}
+
+fn validate_sampling_cutoffs(
+    typical_p: Option<f64>,
+    epsilon_cutoff: Option<f64>,
+    eta_cutoff: Option<f64>,
+) {
+    if let Some(typical_p) = typical_p {
+        assert!(
+            (typical_p > 0f64) & (typical_p <= 1f64),
+            "typical_p must be strictly greater than 0 and lower than 1"
+        );
+    }
+    if let Some(epsilon_cutoff) = epsilon_cutoff {
+        assert!(
+            (0f64..1f64).contains(&epsilon_cutoff),
+            "epsilon_cutoff must be between 0 and 1 (excluded)"
+        );
+    }
+    if let Some(eta_cutoff) = eta_cutoff {
+        assert!(
+            (0f64..1f64).contains(&eta_cutoff),
+            "eta_cutoff must be between 0 and 1 (excluded)"
+        );
     }
 }
 
@@ -221,13 +381,177 @@ pub enum Cache {
     ReformerCache(Option<Vec<Option<ReformerLayerState>>>),
     ProphetNetCache(Option<Vec<(Option<ProphetNetLayerState>, Option<ProphetNetLayerState>)>>),
     GPTNeoCache(Option<Vec<Option<GPTNeoLayerState>>>),
+    LlamaCache(Option<Vec<Option<LlamaLayerState>>>),
+    GPTNeoXCache(Option<Vec<Option<GPTNeoXLayerState>>>),
+    BloomCache(Option<Vec<Option<BloomLayerState>>>),
     None,
 }
 
+impl Cache {
+    /// Returns the memory used by the cached states, in bytes
+    pub fn size_in_bytes(&self) -> usize {
+        fn tensor_bytes(tensor: &Tensor) -> usize {
+            tensor.numel() * tensor.kind().elt_size_in_bytes()
+        }
+        fn optional_tensor_bytes(tensor: Option<&Tensor>) -> usize {
+            tensor.map_or(0, tensor_bytes)
+        }
+        match self {
+            Cache::GPT2Cache(Some(layers)) => layers.iter().map(tensor_bytes).sum(),
+            Cache::BARTCache(Some(layers)) => layers
+                .iter()
+                .flat_map(|(self_attention, cross_attention)| {
+                    self_attention.iter().chain(cross_attention.iter())
+                })
+                .map(|state| tensor_bytes(&state.prev_key) + tensor_bytes(&state.prev_value))
+                .sum(),
+            Cache::T5Cache(Some(layers)) => layers
+                .iter()
+                .flat_map(|(self_attention, cross_attention)| {
+                    self_attention.iter().chain(cross_attention.iter())
+                })
+                .map(|state| tensor_bytes(&state.prev_key) + tensor_bytes(&state.prev_value))
+                .sum(),
+            Cache::ProphetNetCache(Some(layers)) => layers
+                .iter()
+                .flat_map(|(self_attention, cross_attention)| {
+                    self_attention.iter().chain(cross_attention.iter())
+                })
+                .map(|state| tensor_bytes(&state.prev_key) + tensor_bytes(&state.prev_value))
+                .sum(),
+            Cache::XLNetCache(Some(layers)) => layers
+                .iter()
+                .flatten()
+                .map(|state| tensor_bytes(&state.prev_content))
+                .sum(),
+            Cache::ReformerCache(Some(layers)) => layers
+                .iter()
+                .flatten()
+                .map(|state| {
+                    tensor_bytes(&state.prev_states)
+                        + optional_tensor_bytes(state.prev_buckets.as_ref())
+                })
+                .sum(),
+            Cache::GPTNeoCache(Some(layers)) => layers
+                .iter()
+                .flatten()
+                .map(|state| {
+                    tensor_bytes(&state.prev_key) + optional_tensor_bytes(state.prev_value.as_ref())
+                })
+                .sum(),
+            Cache::LlamaCache(Some(layers)) => layers
+                .iter()
+                .flatten()
+                .map(|state| tensor_bytes(&state.prev_key) + tensor_bytes(&state.prev_value))
+                .sum(),
+            Cache::GPTNeoXCache(Some(layers)) => layers
+                .iter()
+                .flatten()
+                .map(|state| tensor_bytes(&state.prev_key) + tensor_bytes(&state.prev_value))
+                .sum(),
+            Cache::BloomCache(Some(layers)) => layers
+                .iter()
+                .flatten()
+                .map(|state| tensor_bytes(&state.prev_key) + tensor_bytes(&state.prev_value))
+                .sum(),
+            _ => 0,
+        }
+    }
+}
+
+impl Clone for Cache {
+    fn clone(&self) -> Self {
+        match self {
+            Cache::GPT2Cache(layers) => Cache::GPT2Cache(
+                layers
+                    .as_ref()
+                    .map(|layers| layers.iter().map(|layer| layer.copy()).collect()),
+            ),
+            Cache::BARTCache(layers) => Cache::BARTCache(layers.clone()),
+            Cache::T5Cache(layers) => Cache::T5Cache(layers.clone()),
+            Cache::XLNetCache(layers) => Cache::XLNetCache(layers.clone()),
+            Cache::ReformerCache(layers) => Cache::ReformerCache(layers.clone()),
+            Cache::ProphetNetCache(layers) => Cache::ProphetNetCache(layers.clone()),
+            Cache::GPTNeoCache(layers) => Cache::GPTNeoCache(layers.clone()),
+            Cache::LlamaCache(layers) => Cache::LlamaCache(layers.clone()),
+            Cache::GPTNeoXCache(layers) => Cache::GPTNeoXCache(layers.clone()),
+            Cache::BloomCache(layers) => Cache::BloomCache(layers.clone()),
+            Cache::None => Cache::None,
+        }
+    }
+}
+
+/// # Cached prompt prefix
+/// Keys and values cache of a fixed prompt prefix (e.g. a long system prompt), computed once with
+/// `LanguageGenerator::encode_prefix` and reused by the generation calls receiving it in
+/// `GenerateOptions::prefix_cache`: the prompts of these calls are appended to the prefix, and only their tokens are
+/// processed by the model before generating.
+#[derive(Debug)]
+pub struct PrefixCache {
+    /// Token ids of the prefix
+    pub token_ids: Vec<i64>,
+    cache: Cache,
+    hidden_states: Option<Tensor>,
+}
+
+impl PrefixCache {
+    /// Returns the number of tokens of the prefix
+    pub fn len(&self) -> usize {
+        self.token_ids.len()
+    }
+
+    /// Returns true if the prefix has no tokens
+    pub fn is_empty(&self) -> bool {
+        self.token_ids.is_empty()
+    }
+
+    /// Returns the memory used by the cached states of the prefix, in bytes
+    pub fn size_in_bytes(&self) -> usize {
+        self.cache.size_in_bytes()
+    }
+}
+
+/// Decodes the indices of a generated sequence, truncating the text before the stop sequence completed by its last
+/// token (the indices of the sequences stopped by a stop sequence end with the token completing it)
+pub(crate) fn decode_before_stop_sequence(
+    tokenizer: &TokenizerOption,
+    indices: &[i64],
+    stop_sequences: &[String],
+) -> String {
+    let text = tokenizer.decode(indices, true, true);
+    let previous_length = match indices.split_last() {
+        Some((_, previous_indices)) if !stop_sequences.is_empty() => {
+            tokenizer.decode(previous_indices, true, true).len()
+        }
+        _ => return text,
+    };
+    if text.len() <= previous_length {
+        return text;
+    }
+    // Only the stop sequences ending in the text of the last token are searched, excluding the prompt
+    let stop_position = stop_sequences
+        .iter()
+        .filter_map(|stop_sequence| {
+            let search_start = (previous_length + 1).saturating_sub(stop_sequence.len());
+            let search_start =
+                (search_start..=text.len()).find(|&position| text.is_char_boundary(position))?;
+            text[search_start..]
+                .find(stop_sequence.as_str())
+                .map(|position| search_start + position)
+        })
+        .min();
+    match stop_position {
+        Some(stop_position) => text[..stop_position].to_string(),
+        None => text,
+    }
+}
+
 pub mod private_generation_utils {
+    use std::cell::RefCell;
     use std::cmp::{max, min};
     use std::collections::HashMap;
     use std::mem;
+    use std::time::Instant;
 
     use rust_tokenizers::tokenizer::{truncate_sequences, Tokenizer, TruncationStrategy};
     use rust_tokenizers::vocab::Vocab;
@@ -236,10 +560,16 @@ pub mod private_generation_utils {
     use tch::{nn, Device, Kind, Tensor};
 
     use crate::pipelines::common::TokenizerOption;
-    use crate::pipelines::generation_utils::{BeamHypotheses, Cache, GenerateConfig, LMHeadModel};
+    use crate::pipelines::generation_utils::{
+        BeamHypotheses, Cache, GenerateConfig, GenerateOptions, GeneratedTokenScores, LMHeadModel,
+        LMModelOutput, NoRepeatNgramScope, PrefixCache, uses_contrastive_search,
+    };
 
     use super::ordered_float::OrderedFloat;
+    use crate::common::error::RustBertError;
     use crate::common::kind::get_positive_infinity;
+    use crate::pipelines::logits_processor::LogitsProcessor;
+    use crate::pipelines::watermark::Watermark;
 
     pub struct InternalGenerateOptions<'a> {
         pub min_length: i64,
@@ -250,6 +580,8 @@ pub mod private_generation_utils {
         pub top_p: f64,
         pub repetition_penalty: f64,
         pub no_repeat_ngram_size: i64,
+        pub no_repeat_ngram_scope: NoRepeatNgramScope,
+        pub ngram_scope_boundaries: RefCell<HashMap<i64, bool>>,
         pub pad_token_id: Option<i64>,
         pub eos_token_ids: Option<Vec<i64>>,
         pub num_return_sequences: i64,
@@ -258,8 +590,46 @@ pub mod private_generation_utils {
         pub length_penalty: f64,
         pub num_beam_groups: Option<i64>,
         pub diversity_penalty: Option<f64>,
+        pub penalty_alpha: Option<f64>,
+        pub typical_p: Option<f64>,
+        pub epsilon_cutoff: Option<f64>,
+        pub eta_cutoff: Option<f64>,
         pub forced_bos_token_id: Option<i64>,
         pub bad_word_ids: Option<&'a Vec<Vec<i64>>>,
+        pub logit_bias: Option<&'a HashMap<i64, f64>>,
+        pub watermark: Option<&'a Watermark>,
+        pub logits_processors: Option<&'a [&'a dyn LogitsProcessor]>,
+        pub banned_tokens_fn: Option<&'a dyn Fn(&Tensor) -> Vec<i64>>,
+        pub top_logprobs: i64,
+        pub token_healing_ids: Option<Vec<Vec<i64>>>,
+        pub echo: bool,
+        pub token_callback: Option<&'a dyn Fn(&[Option<i64>]) -> bool>,
+        pub stop_sequences: Vec<String>,
+        pub prefix_cache: Option<&'a PrefixCache>,
+        pub decoder_attention_mask: Option<Tensor>,
+        pub item_max_lengths: Option<Vec<i64>>,
+        pub item_temperatures: Option<Tensor>,
+        pub item_stop_sequences: Option<Vec<Vec<String>>>,
+    }
+
+    impl<'a> InternalGenerateOptions<'a> {
+        /// Returns the stop sequences of a sequence of the batch
+        pub fn get_stop_sequences(&self, sequence_index: usize) -> &[String] {
+            match &self.item_stop_sequences {
+                Some(item_stop_sequences) => &item_stop_sequences[sequence_index],
+                None => &self.stop_sequences,
+            }
+        }
+
+        /// Returns true if any sequence of the batch has stop sequences
+        pub fn has_stop_sequences(&self) -> bool {
+            match &self.item_stop_sequences {
+                Some(item_stop_sequences) => item_stop_sequences
+                    .iter()
+                    .any(|stop_sequences| !stop_sequences.is_empty()),
+                None => !self.stop_sequences.is_empty(),
+            }
+        }
     }
 
     pub struct PreparedInput<'a> {
@@ -275,6 +645,135 @@ pub mod private_generation_utils {
         pub indices: Tensor,
         pub scores: Option<Vec<f64>>,
         pub token_scores: Option<Vec<Vec<f64>>>,
+        pub generated_token_scores: Option<Vec<GeneratedTokenScores>>,
+        pub prompt_token_scores: Option<Vec<Vec<f64>>>,
+    }
+
+    /// Measures the duration of the generation steps of a batch and the size of its cache
+    pub struct GenerationTimer {
+        step_start: Instant,
+        pub prefill_ms: Option<f64>,
+        pub decode_ms: Vec<f64>,
+        pub max_cache_bytes: usize,
+    }
+
+    impl GenerationTimer {
+        /// Starts the timer, before the encoding of the prompt
+        pub fn start() -> GenerationTimer {
+            GenerationTimer {
+                step_start: Instant::now(),
+                prefill_ms: None,
+                decode_ms: vec![],
+                max_cache_bytes: 0,
+            }
+        }
+
+        /// Records the end of a generation step
+        pub fn end_step(&mut self, next_tokens: &Tensor, cache: &Cache) {
+            // Reading the tokens waits for the device to complete the step
+            let _ = i64::from(next_tokens.sum(Int64));
+            let elapsed_ms = self.step_start.elapsed().as_secs_f64() * 1000.0;
+            match self.prefill_ms {
+                None => self.prefill_ms = Some(elapsed_ms),
+                Some(_) => self.decode_ms.push(elapsed_ms),
+            }
+            self.max_cache_bytes = self.max_cache_bytes.max(cache.size_in_bytes());
+            self.step_start = Instant::now();
+        }
+    }
+
+    /// Log-probabilities of the tokens selected for each sequence, with the most likely tokens at each step
+    pub struct TokenLogprobHistory {
+        token_logprobs: Tensor,
+        top_logprobs: Tensor,
+        top_token_ids: Tensor,
+        top_k: i64,
+    }
+
+    impl TokenLogprobHistory {
+        pub fn new(num_sequences: i64, top_k: i64, device: Device) -> TokenLogprobHistory {
+            TokenLogprobHistory {
+                token_logprobs: Tensor::zeros(&[num_sequences, 0], (Float, device)),
+                top_logprobs: Tensor::zeros(&[num_sequences, 0, top_k], (Float, device)),
+                top_token_ids: Tensor::zeros(&[num_sequences, 0, top_k], (Int64, device)),
+                top_k,
+            }
+        }
+
+        /// Appends a generation step, given the log-probabilities of the vocabulary and the selected token for each
+        /// sequence
+        pub fn push(&mut self, log_probabilities: &Tensor, tokens: &Tensor) {
+            let log_probabilities = log_probabilities.to_kind(Float);
+            let token_logprobs = log_probabilities.gather(1, &tokens.reshape(&[-1, 1]), false);
+            let (top_logprobs, top_token_ids) = log_probabilities.topk(self.top_k, -1, true, true);
+            self.token_logprobs = Tensor::cat(&[&self.token_logprobs, &token_logprobs], 1);
+            self.top_logprobs = Tensor::cat(&[&self.top_logprobs, &top_logprobs.unsqueeze(1)], 1);
+            self.top_token_ids =
+                Tensor::cat(&[&self.top_token_ids, &top_token_ids.unsqueeze(1)], 1);
+        }
+
+        /// Reorders the sequences (beam search)
+        pub fn reorder(&mut self, indices: &Tensor) {
+            self.token_logprobs = self.token_logprobs.index_select(0, indices);
+            self.top_logprobs = self.top_logprobs.index_select(0, indices);
+            self.top_token_ids = self.top_token_ids.index_select(0, indices);
+        }
+
+        /// Returns the log-probabilities of the tokens of a sequence and the most likely tokens at each step,
+        /// optionally followed by a last step (log-probabilities of the vocabulary and selected token)
+        pub fn get(
+            &self,
+            sequence_index: i64,
+            last_step: Option<(&Tensor, i64)>,
+        ) -> (Vec<f64>, Vec<Vec<(i64, f64)>>) {
+            let mut token_logprobs = self
+                .token_logprobs
+                .get(sequence_index)
+                .iter::<f64>()
+                .unwrap()
+                .collect::<Vec<f64>>();
+            let top_logprobs = self.top_logprobs.get(sequence_index);
+            let top_token_ids = self.top_token_ids.get(sequence_index);
+            let mut top_tokens = (0..token_logprobs.len() as i64)
+                .map(|step| Self::top_tokens(&top_logprobs.get(step), &top_token_ids.get(step)))
+                .collect::<Vec<Vec<(i64, f64)>>>();
+            if let Some((log_probabilities, token_id)) = last_step {
+                let log_probabilities = log_probabilities.to_kind(Float);
+                token_logprobs.push(log_probabilities.double_value(&[token_id]));
+                let (top_logprobs, top_token_ids) =
+                    log_probabilities.topk(self.top_k, -1, true, true);
+                top_tokens.push(Self::top_tokens(&top_logprobs, &top_token_ids));
+            }
+            (token_logprobs, top_tokens)
+        }
+
+        /// Pairs the most likely tokens of a step with their log-probability, skipping the masked tokens
+        fn top_tokens(top_logprobs: &Tensor, top_token_ids: &Tensor) -> Vec<(i64, f64)> {
+            top_token_ids
+                .iter::<i64>()
+                .unwrap()
+                .zip(top_logprobs.iter::<f64>().unwrap())
+                .filter(|(_, logprob)| *logprob > f64::NEG_INFINITY)
+                .collect()
+        }
+    }
+
+    /// Entropy of the distributions of the rows of `probabilities`, of shape (*batch size*, 1)
+    fn entropy(log_probabilities: &Tensor, probabilities: &Tensor) -> Tensor {
+        // The banned tokens (with a probability of 0 and a log-probability of -inf) do not contribute
+        (probabilities * log_probabilities.masked_fill(&probabilities.eq(0), 0))
+            .sum_dim_intlist(&[-1], true, Float)
+            .neg()
+    }
+
+    /// Masks the tokens of `indices_to_remove`, always keeping the `min_tokens_to_keep` most likely tokens
+    fn mask_tokens(logits: &mut Tensor, indices_to_remove: &Tensor, min_tokens_to_keep: i64) {
+        let vocab_size = *logits.size().last().unwrap();
+        let min_tokens_to_keep = min(max(min_tokens_to_keep, 1), vocab_size);
+        let (top_logits, _) = logits.topk(min_tokens_to_keep, -1, true, true);
+        let threshold = top_logits.narrow(-1, min_tokens_to_keep - 1, 1);
+        let indices_to_remove = indices_to_remove.logical_and(&logits.lt_tensor(&threshold));
+        let _ = logits.masked_fill_(&indices_to_remove, f64::NEG_INFINITY);
     }
 
     pub trait PrivateLanguageGenerator<T: LMHeadModel, V: Vocab, U: Tokenizer<V>> {
@@ -321,19 +820,254 @@ pub mod private_generation_utils {
             }
         }
 
+        /// Returns true if the model can process several new tokens on top of its cache, allowing to prefill
+        /// the prompt in chunks
+        fn supports_chunked_prefill(&self) -> bool {
+            false
+        }
+
+        /// Returns true if the model returns the hidden states of its last layer (`LMModelOutput::hidden_states`),
+        /// required by contrastive search
+        fn exposes_hidden_states(&self) -> bool {
+            true
+        }
+
+        /// Returns true if the BOS token should be prepended to the prompts, for models trained with a BOS token at
+        /// the start of every sequence (e.g. LLaMA)
+        fn add_bos_token(&self) -> bool {
+            false
+        }
+
+        /// Prefill: processes the prompt in a single batched forward pass, returning the logits for all the
+        /// positions of the prompt and the cache filled for all the layers and positions. If a
+        /// `prefill_chunk_size` is set and supported by the model, the prompt is processed in segments of at
+        /// most `prefill_chunk_size` tokens, each forward pass extending the cache built by the previous ones.
+        ///
+        /// # Arguments
+        ///
+        /// * `input_ids` - Prompt token ids of shape (*batch size*, *prompt length*)
+        /// * `encoder_outputs` - Optional encoder outputs for encoder-decoder models
+        /// * `attention_mask` - Attention mask of the prompt
+        fn prefill(
+            &self,
+            input_ids: &Tensor,
+            encoder_outputs: Option<&Tensor>,
+            attention_mask: &Tensor,
+        ) -> Result<LMModelOutput, RustBertError> {
+            let prompt_length = *input_ids.size().last().unwrap();
+            let chunk_size = match self.get_config().prefill_chunk_size {
+                Some(chunk_size)
+                    if self.supports_chunked_prefill() & (prompt_length > chunk_size) =>
+                {
+                    chunk_size
+                }
+                _ => {
+                    return self.decode_step(
+                        input_ids,
+                        encoder_outputs,
+                        Cache::None,
+                        attention_mask,
+                    )
+                }
+            };
+
+            self.extend_cache(input_ids, attention_mask, Cache::None, 0, chunk_size)
+        }
+
+        /// Prefill on top of the cache of a prompt prefix: the sequences start with the tokens of the prefix,
+        /// whose cache is expanded to the batch, and only the following tokens are processed by the model. The
+        /// logits are returned for the positions following the prefix. Falls back to `prefill` if no prefix
+        /// cache is provided.
+        ///
+        /// # Arguments
+        ///
+        /// * `input_ids` - Token ids of the prefix followed by the prompts
+        /// * `encoder_outputs` - Optional encoder outputs for encoder-decoder models
+        /// * `attention_mask` - Attention mask of the prefix followed by the prompts
+        /// * `prefix_cache` - Optional cache of the prefix
+        fn prefill_from_prefix(
+            &self,
+            input_ids: &Tensor,
+            encoder_outputs: Option<&Tensor>,
+            attention_mask: &Tensor,
+            prefix_cache: Option<&PrefixCache>,
+        ) -> Result<LMModelOutput, RustBertError> {
+            let prefix_cache = match prefix_cache {
+                Some(prefix_cache) => prefix_cache,
+                None => return self.prefill(input_ids, encoder_outputs, attention_mask),
+            };
+            let (batch_size, sequence_length) = input_ids.size2()?;
+            if sequence_length <= prefix_cache.len() as i64 {
+                return Err(RustBertError::ValueError(
+                    "The prompts following a cached prefix must contain at least one token".into(),
+                ));
+            }
+            let mut past = prefix_cache.cache.clone();
+            let _ = self.reorder_cache(
+                &mut past,
+                None,
+                &Tensor::zeros(&[batch_size], (Int64, input_ids.device())),
+            );
+            let chunk_size = self
+                .get_config()
+                .prefill_chunk_size
+                .unwrap_or(sequence_length);
+            let mut output = self.extend_cache(
+                input_ids,
+                attention_mask,
+                past,
+                prefix_cache.len() as i64,
+                chunk_size,
+            )?;
+            output.hidden_states = match (&prefix_cache.hidden_states, output.hidden_states) {
+                (Some(prefix_hidden_states), Some(hidden_states)) => Some(Tensor::cat(
+                    &[
+                        prefix_hidden_states.expand(&[batch_size, -1, -1], true),
+                        hidden_states,
+                    ],
+                    1,
+                )),
+                _ => None,
+            };
+            Ok(output)
+        }
+
+        /// Prefill of a generation call: processes the decoder prompt with its prefix-LM attention mask if one is
+        /// provided (encoder-decoder models), or the prompts on top of the prefix cache otherwise.
+        ///
+        /// # Arguments
+        ///
+        /// * `input_ids` - Token ids of the prompts (decoder start token and prompt for encoder-decoder models)
+        /// * `encoder_outputs` - Optional encoder outputs for encoder-decoder models
+        /// * `attention_mask` - Attention mask of the prompts (of the encoder inputs for encoder-decoder models)
+        /// * `gen_opt` - Generation options holding the decoder attention mask and the prefix cache
+        fn prefill_with_options(
+            &self,
+            input_ids: &Tensor,
+            encoder_outputs: Option<&Tensor>,
+            attention_mask: &Tensor,
+            gen_opt: &InternalGenerateOptions,
+        ) -> Result<LMModelOutput, RustBertError> {
+            match (&gen_opt.decoder_attention_mask, encoder_outputs) {
+                (Some(decoder_attention_mask), Some(encoder_outputs)) => {
+                    self.get_model().forward_with_decoder_attention_mask(
+                        input_ids,
+                        Cache::None,
+                        Some(attention_mask),
+                        encoder_outputs,
+                        decoder_attention_mask,
+                        false,
+                    )
+                }
+                _ => self.prefill_from_prefix(
+                    input_ids,
+                    encoder_outputs,
+                    attention_mask,
+                    gen_opt.prefix_cache,
+                ),
+            }
+        }
+
+        /// Runs the model on the tokens following the first `past_length` tokens of the sequences, whose states
+        /// are stored in `past`, in segments of at most `chunk_size` tokens extending the cache. Returns the
+        /// logits for the positions following the first `past_length` tokens.
+        fn extend_cache(
+            &self,
+            input_ids: &Tensor,
+            attention_mask: &Tensor,
+            mut past: Cache,
+            past_length: i64,
+            chunk_size: i64,
+        ) -> Result<LMModelOutput, RustBertError> {
+            let sequence_length = *input_ids.size().last().unwrap();
+            let position_ids = (attention_mask.to_kind(Int64).cumsum(-1, Int64) - 1)
+                .masked_fill(&attention_mask.eq(0), 1);
+            let mut lm_logits = Vec::new();
+            let mut hidden_states = Vec::new();
+            for chunk_start in (past_length..sequence_length).step_by(chunk_size as usize) {
+                let chunk_end = min(chunk_start + chunk_size, sequence_length);
+                let output = self.get_model().forward_t(
+                    Some(&input_ids.slice(1, chunk_start, chunk_end, 1)),
+                    past,
+                    Some(&attention_mask.slice(1, 0, chunk_end, 1)),
+                    None,
+                    Some(&position_ids.slice(1, chunk_start, chunk_end, 1)),
+                    None,
+                    None,
+                    None,
+                    false,
+                )?;
+                past = output.cache;
+                lm_logits.push(output.lm_logits);
+                if let Some(chunk_hidden_states) = output.hidden_states {
+                    hidden_states.push(chunk_hidden_states);
+                }
+            }
+            Ok(LMModelOutput {
+                lm_logits: Tensor::cat(&lm_logits, 1),
+                cache: past,
+                hidden_states: if hidden_states.is_empty() {
+                    None
+                } else {
+                    Some(Tensor::cat(&hidden_states, 1))
+                },
+            })
+        }
+
+        /// Decoding step: runs the model on the tokens not covered by the cache (the last generated token for
+        /// the models with a cache, the full sequence otherwise) and returns the logits and the updated cache.
+        ///
+        /// # Arguments
+        ///
+        /// * `input_ids` - Token ids of the sequences generated so far (including the prompt)
+        /// * `encoder_outputs` - Optional encoder outputs for encoder-decoder models
+        /// * `past` - Cache returned by the previous step (`prefill` or `decode_step`)
+        /// * `attention_mask` - Attention mask of the sequences generated so far
+        fn decode_step(
+            &self,
+            input_ids: &Tensor,
+            encoder_outputs: Option<&Tensor>,
+            past: Cache,
+            attention_mask: &Tensor,
+        ) -> Result<LMModelOutput, RustBertError> {
+            let prepared_input = self.prepare_inputs_for_generation(
+                input_ids.copy(),
+                encoder_outputs,
+                past,
+                attention_mask.copy(),
+            );
+            self.get_model().forward_t(
+                prepared_input.prepared_input.as_ref(),
+                prepared_input.prepared_past,
+                prepared_input.prepared_attention_mask.as_ref(),
+                None,
+                prepared_input.prepared_position_ids.as_ref(),
+                None,
+                prepared_input.prepared_encoder_output,
+                prepared_input.prepared_decoder_input.as_ref(),
+                false,
+            )
+        }
+
         fn encode_prompt_text<S>(
             &self,
             prompt_text: &[S],
             max_len: i64,
             pad_token_id: Option<i64>,
+            add_bos_token: bool,
         ) -> Tensor
         where
             S: AsRef<str> + Sync,
         {
+            let bos_token_id = self.get_bos_id().filter(|_| add_bos_token);
             let tokens = self._get_tokenizer().tokenize_list(prompt_text);
             let token_ids = tokens
                 .into_iter()
-                .map(|prompt_tokens| self._get_tokenizer().convert_tokens_to_ids(&prompt_tokens))
+                .map(|prompt_tokens| {
+                    let mut token_ids = bos_token_id.into_iter().collect::<Vec<i64>>();
+                    token_ids.extend(self._get_tokenizer().convert_tokens_to_ids(&prompt_tokens));
+                    token_ids
+                })
                 .collect::<Vec<Vec<i64>>>();
 
             let num_truncated_tokens = token_ids
@@ -421,11 +1155,72 @@ pub mod private_generation_utils {
             }
         }
 
+        /// Adds the bias of each token of `logit_bias` to its logits, for all sequences. The tokens outside of the
+        /// vocabulary are ignored.
+        fn apply_logit_bias(&self, next_token_logits: &mut Tensor, logit_bias: &HashMap<i64, f64>) {
+            let vocab_size = next_token_logits.size()[1];
+            let (token_ids, biases): (Vec<i64>, Vec<f64>) = logit_bias
+                .iter()
+                .filter(|(token_id, _)| (0..vocab_size).contains(*token_id))
+                .map(|(token_id, bias)| (*token_id, *bias))
+                .unzip();
+            if token_ids.is_empty() {
+                return;
+            }
+            let (kind, device) = (next_token_logits.kind(), next_token_logits.device());
+            let bias = Tensor::zeros(&[vocab_size], (kind, device)).index_copy(
+                0,
+                &Tensor::of_slice(&token_ids).to_device(device),
+                &Tensor::of_slice(&biases).to_kind(kind).to_device(device),
+            );
+            *next_token_logits += bias.unsqueeze(0);
+        }
+
+        /// Returns the position of the first token of the n-gram repetition scope of each hypothesis
+        fn get_ngram_scope_starts(
+            &self,
+            input_ids: &Tensor,
+            cur_len: i64,
+            gen_opt: &InternalGenerateOptions,
+        ) -> Vec<i64> {
+            let num_hypotheses = input_ids.size()[0] as usize;
+            match gen_opt.no_repeat_ngram_scope {
+                NoRepeatNgramScope::Sequence => vec![0; num_hypotheses],
+                NoRepeatNgramScope::Window(window) => {
+                    vec![(cur_len - window).max(0); num_hypotheses]
+                }
+                NoRepeatNgramScope::Sentence | NoRepeatNgramScope::Paragraph => {
+                    let input_ids = input_ids.to(Device::Cpu);
+                    let mut boundaries = gen_opt.ngram_scope_boundaries.borrow_mut();
+                    (0..num_hypotheses as i64)
+                        .map(|hypothesis_index| {
+                            let hypothesis_input_ids = input_ids
+                                .get(hypothesis_index)
+                                .iter::<i64>()
+                                .unwrap()
+                                .collect::<Vec<i64>>();
+                            hypothesis_input_ids
+                                .iter()
+                                .rposition(|token_id| {
+                                    *boundaries.entry(*token_id).or_insert_with(|| {
+                                        let token =
+                                            self._get_tokenizer().decode(&[*token_id], true, false);
+                                        gen_opt.no_repeat_ngram_scope.is_boundary(&token)
+                                    })
+                                })
+                                .map_or(0, |position| position as i64 + 1)
+                        })
+                        .collect()
+                }
+            }
+        }
+
         fn get_banned_tokens(
             &self,
             input_ids: &Tensor,
             no_repeat_ngram_size: i64,
             cur_len: i64,
+            scope_starts: &[i64],
         ) -> Vec<Vec<i64>> {
             //        Ported from hugging face's transformers and fairseq (https://github.com/pytorch/fairseq/blob/master/fairseq/sequence_generator.py)
             if cur_len + 1 < no_repeat_ngram_size {
@@ -435,9 +1230,16 @@ pub mod private_generation_utils {
                 let num_hypothesis = *input_ids.size().first().unwrap();
                 let mut banned_tokens: Vec<Vec<i64>> = Vec::with_capacity(num_hypothesis as usize);
                 for hypothesis_index in 0..num_hypothesis {
+                    // Only the n-grams starting after the beginning of the scope are considered
+                    let scope_start = scope_starts[hypothesis_index as usize];
+                    if cur_len + 1 < scope_start + no_repeat_ngram_size {
+                        banned_tokens.push(vec![]);
+                        continue;
+                    }
                     let hypothesis_input_ids = input_ids.get(hypothesis_index);
                     let mut generated_ngram: HashMap<Vec<i64>, Vec<i64>> = HashMap::new();
-                    let input: Vec<i64> = (0..hypothesis_input_ids.size1().unwrap()).collect();
+                    let input: Vec<i64> =
+                        (scope_start..hypothesis_input_ids.size1().unwrap()).collect();
                     let hypothesis_input_ids = hypothesis_input_ids
                         .iter::<i64>()
                         .unwrap()
@@ -523,6 +1325,68 @@ pub mod private_generation_utils {
             }
         }
 
+        /// Applies the typical, epsilon and eta filtering set in the generation options. The filters are
+        /// invariant to a constant shift of the logits of a row, and can be applied to the beam scores.
+        fn truncation_sampling_filtering(
+            &self,
+            logits: &mut Tensor,
+            gen_opt: &InternalGenerateOptions,
+            min_tokens_to_keep: i64,
+        ) {
+            if let Some(typical_p) = gen_opt.typical_p {
+                if typical_p < 1f64 {
+                    self.typical_filtering(logits, typical_p, min_tokens_to_keep);
+                }
+            }
+            if let Some(epsilon_cutoff) = gen_opt.epsilon_cutoff {
+                if epsilon_cutoff > 0f64 {
+                    let probabilities = logits.softmax(-1, Float);
+                    let indices_to_remove = probabilities.lt(epsilon_cutoff);
+                    mask_tokens(logits, &indices_to_remove, min_tokens_to_keep);
+                }
+            }
+            if let Some(eta_cutoff) = gen_opt.eta_cutoff {
+                if eta_cutoff > 0f64 {
+                    let log_probabilities = logits.log_softmax(-1, Float);
+                    let probabilities = log_probabilities.exp();
+                    let eta = (entropy(&log_probabilities, &probabilities).neg().exp()
+                        * eta_cutoff.sqrt())
+                    .clamp_max(eta_cutoff);
+                    let indices_to_remove = probabilities.lt_tensor(&eta);
+                    mask_tokens(logits, &indices_to_remove, min_tokens_to_keep);
+                }
+            }
+        }
+
+        /// Locally typical sampling introduced by Meister et al. (https://arxiv.org/abs/2202.00666): keeps the
+        /// tokens whose information content is the closest to the entropy of the distribution, until their
+        /// cumulative probability reaches `typical_p`
+        fn typical_filtering(&self, logits: &mut Tensor, typical_p: f64, min_tokens_to_keep: i64) {
+            let vocab_size = *logits.size().last().unwrap();
+            let log_probabilities = logits.log_softmax(-1, Float);
+            let probabilities = log_probabilities.exp();
+            let shifted_scores =
+                (log_probabilities.neg() - entropy(&log_probabilities, &probabilities)).abs();
+            let (sorted_scores, sorted_indices) = shifted_scores.sort(-1, false);
+            let cumulative_probabilities = probabilities
+                .gather(-1, &sorted_indices, false)
+                .cumsum(-1, Float);
+            let last_index = cumulative_probabilities
+                .lt(typical_p)
+                .sum_dim_intlist(&[-1], true, Int64)
+                .clamp_max(vocab_size - 1);
+            let sorted_indices_to_remove = sorted_scores
+                .gt_tensor(&sorted_scores.gather(-1, &last_index, false))
+                .to_kind(Int64);
+            let _ = sorted_indices_to_remove
+                .narrow(-1, 0, min(min_tokens_to_keep, vocab_size))
+                .fill_(0);
+            let indices_to_remove = sorted_indices_to_remove
+                .scatter(-1, &sorted_indices, &sorted_indices_to_remove)
+                .to_kind(Bool);
+            let _ = logits.masked_fill_(&indices_to_remove, f64::NEG_INFINITY);
+        }
+
         fn run_hamming_diversity_penalty(
             &self,
             scores: &mut Tensor,
@@ -582,6 +1446,192 @@ pub mod private_generation_utils {
             let _ = scores.subtract_(&mask);
         }
 
+        /// Masks the tokens returned by `banned_tokens_fn` for each sequence
+        fn apply_banned_tokens_function(
+            &self,
+            banned_tokens_fn: &dyn Fn(&Tensor) -> Vec<i64>,
+            input_ids: &Tensor,
+            scores: &mut Tensor,
+        ) {
+            for idx in 0..scores.size()[0] {
+                let banned_tokens = banned_tokens_fn(&input_ids.get(idx));
+                if !banned_tokens.is_empty() {
+                    let _ = scores.get(idx).index_fill_(
+                        0,
+                        &Tensor::of_slice(&banned_tokens).to_device(scores.device()),
+                        f64::NEG_INFINITY,
+                    );
+                }
+            }
+        }
+
+        /// Returns the stop sequences of the generation options, or of the configuration if not provided
+        fn get_stop_sequences(&self, generate_options: Option<GenerateOptions>) -> Vec<String> {
+            let stop_sequences = match generate_options.and_then(|opts| opts.stop_sequences) {
+                Some(stop_sequences) => stop_sequences
+                    .iter()
+                    .map(|stop_sequence| stop_sequence.to_string())
+                    .collect(),
+                None => self.get_config().stop_sequences.clone(),
+            };
+            stop_sequences
+                .into_iter()
+                .filter(|stop_sequence| !stop_sequence.is_empty())
+                .collect()
+        }
+
+        /// Returns the stop sequences of each of the `num_sequences` generated sequences: the stop sequences of the
+        /// `ItemGenerateOptions` of their input if provided, of the generation options or configuration otherwise
+        fn get_item_stop_sequences(
+            &self,
+            generate_options: Option<GenerateOptions>,
+            num_sequences: usize,
+        ) -> Vec<Vec<String>> {
+            let stop_sequences = self.get_stop_sequences(generate_options);
+            match generate_options.and_then(|opts| opts.item_options) {
+                Some(item_options) if !item_options.is_empty() => {
+                    let sequences_per_input = max(num_sequences / item_options.len(), 1);
+                    (0..num_sequences)
+                        .map(|sequence_index| {
+                            match item_options[sequence_index / sequences_per_input].stop_sequences
+                            {
+                                Some(item_stop_sequences) => item_stop_sequences
+                                    .iter()
+                                    .filter(|stop_sequence| !stop_sequence.is_empty())
+                                    .map(|stop_sequence| stop_sequence.to_string())
+                                    .collect(),
+                                None => stop_sequences.clone(),
+                            }
+                        })
+                        .collect()
+                }
+                _ => vec![stop_sequences; num_sequences],
+            }
+        }
+
+        fn contains_stop_sequence(&self, token_ids: &[i64], stop_sequences: &[String]) -> bool {
+            let text = self._get_tokenizer().decode(token_ids, true, true);
+            stop_sequences
+                .iter()
+                .any(|stop_sequence| text.contains(stop_sequence.as_str()))
+        }
+
+        /// Truncates the indices of a sequence after the generated token completing the first stop sequence
+        fn truncate_after_stop_sequence(
+            &self,
+            indices: &mut Vec<i64>,
+            generated_start: usize,
+            stop_sequences: &[String],
+        ) {
+            if (generated_start >= indices.len())
+                || !self.contains_stop_sequence(&indices[generated_start..], stop_sequences)
+            {
+                return;
+            }
+            if let Some(end) = (generated_start + 1..=indices.len()).find(|&end| {
+                self.contains_stop_sequence(&indices[generated_start..end], stop_sequences)
+            }) {
+                indices.truncate(end);
+            }
+        }
+
+        fn get_token_healing_ids(&self, input_ids: &Tensor) -> Vec<Vec<i64>> {
+            let vocab = self._get_tokenizer().get_vocab_indices();
+            let last_token_ids = input_ids
+                .select(1, -1)
+                .iter::<i64>()
+                .unwrap()
+                .collect::<Vec<i64>>();
+            // Tokens extending each of the trimmed tokens, indexed in a single pass over the vocabulary by
+            // looking up every prefix of the vocabulary tokens
+            let mut prefix_index: HashMap<&str, Vec<i64>> = last_token_ids
+                .iter()
+                .filter_map(|last_token_id| vocab.get(last_token_id))
+                .map(|last_token| (last_token.as_str(), vec![]))
+                .collect();
+            for (token_id, token) in vocab {
+                for end in token
+                    .char_indices()
+                    .map(|(position, _)| position)
+                    .skip(1)
+                    .chain(std::iter::once(token.len()))
+                {
+                    if let Some(token_ids) = prefix_index.get_mut(&token[..end]) {
+                        token_ids.push(*token_id);
+                    }
+                }
+            }
+            last_token_ids
+                .iter()
+                .map(|last_token_id| match vocab.get(last_token_id) {
+                    Some(last_token) => prefix_index[last_token.as_str()].clone(),
+                    None => vec![*last_token_id],
+                })
+                .collect()
+        }
+
+        fn apply_token_healing(
+            &self,
+            token_healing_ids: &[Vec<i64>],
+            num_beams: i64,
+            scores: &mut Tensor,
+        ) {
+            let mask = scores.new_full(
+                scores.size().as_slice(),
+                get_positive_infinity(scores.kind()).unwrap(),
+                (scores.kind(), scores.device()),
+            );
+            for idx in 0..scores.size()[0] {
+                let allowed_tokens = &token_healing_ids[(idx / num_beams) as usize];
+                let _ = mask.get(idx).index_fill_(
+                    0,
+                    &Tensor::of_slice(allowed_tokens.as_slice()).to(scores.device()),
+                    0,
+                );
+            }
+            let _ = scores.subtract_(&mask);
+        }
+
+        fn get_prompt_token_scores(
+            &self,
+            lm_logits: &Tensor,
+            input_ids: &Tensor,
+            attention_mask: &Tensor,
+            num_beams: i64,
+        ) -> Option<Vec<Vec<f64>>> {
+            let prompt_length = *input_ids.size().last().unwrap();
+            // Some models (e.g. XLNet) only return the logits for the position to predict
+            if lm_logits.size()[1] != prompt_length {
+                return None;
+            }
+            let prompt_token_scores = lm_logits
+                .slice(1, 0, prompt_length - 1, 1)
+                .log_softmax(-1, Kind::Float)
+                .gather(
+                    2,
+                    &input_ids.slice(1, 1, prompt_length, 1).unsqueeze(-1),
+                    false,
+                )
+                .squeeze_dim(-1);
+            let valid_positions = attention_mask
+                .slice(1, 0, prompt_length - 1, 1)
+                .ne(0)
+                .logical_and(&attention_mask.slice(1, 1, prompt_length, 1).ne(0));
+            Some(
+                (0..input_ids.size()[0])
+                    .step_by(num_beams as usize)
+                    .map(|row_index| {
+                        prompt_token_scores
+                            .get(row_index)
+                            .masked_select(&valid_positions.get(row_index))
+                            .iter::<f64>()
+                            .unwrap()
+                            .collect::<Vec<f64>>()
+                    })
+                    .collect(),
+            )
+        }
+
         fn split_bad_word_ids<'a>(
             &self,
             bad_word_ids: Option<&'a Vec<Vec<i64>>>,
@@ -730,6 +1780,113 @@ pub mod private_generation_utils {
             }
         }
 
+        /// Contrastive search step: the `top_k` most likely tokens are fed to the model, and the token maximizing
+        /// `(1 - penalty_alpha) * probability - penalty_alpha * degeneration penalty` is selected, the degeneration
+        /// penalty being the maximum cosine similarity between the hidden state of the candidate and the hidden
+        /// states of the previous tokens.
+        /// Returns the selected tokens and the logits of the model for the next step. The cache and the context
+        /// hidden states are updated with the selected tokens.
+        fn contrastive_search_step(
+            &self,
+            input_ids: &Tensor,
+            encoder_outputs: Option<&Tensor>,
+            past: &mut Cache,
+            attention_mask: &Tensor,
+            next_token_logits: &Tensor,
+            context_hidden_states: &mut Tensor,
+            top_k: i64,
+            penalty_alpha: f64,
+        ) -> Result<(Tensor, Tensor), RustBertError> {
+            let batch_size = next_token_logits.size()[0];
+            let device = next_token_logits.device();
+            let probabilities = next_token_logits.softmax(-1, Kind::Float);
+            let (top_k_probabilities, top_k_ids) = probabilities.topk(top_k, -1, true, true);
+
+            // Expand the inputs and cache to evaluate the top_k candidates of each sequence in a single pass
+            let expanded_indices = Tensor::arange(batch_size, (Int64, device))
+                .unsqueeze(1)
+                .repeat(&[1, top_k])
+                .view([-1]);
+            let mut candidate_past = std::mem::replace(past, Cache::None);
+            let candidate_encoder_outputs = self.reorder_cache(
+                &mut candidate_past,
+                encoder_outputs.map(|value| value.copy()),
+                &expanded_indices,
+            );
+            let candidate_input_ids = Tensor::cat(
+                &[
+                    input_ids.index_select(0, &expanded_indices),
+                    top_k_ids.view([-1, 1]),
+                ],
+                -1,
+            );
+            let candidate_attention_mask = if self.is_encoder_decoder() {
+                attention_mask.index_select(0, &expanded_indices)
+            } else {
+                Tensor::cat(
+                    &[
+                        attention_mask.index_select(0, &expanded_indices),
+                        Tensor::ones(&[batch_size * top_k, 1], (Int64, attention_mask.device())),
+                    ],
+                    -1,
+                )
+            };
+            let candidate_output = self.decode_step(
+                &candidate_input_ids,
+                candidate_encoder_outputs.as_ref(),
+                candidate_past,
+                &candidate_attention_mask,
+            )?;
+            let candidate_hidden_states = candidate_output
+                .hidden_states
+                .ok_or_else(|| {
+                    RustBertError::ValueError(
+                        "Contrastive search requires a model exposing its hidden states".into(),
+                    )
+                })?
+                .select(1, -1);
+
+            // Degeneration penalty: maximum cosine similarity with the previous hidden states
+            let normalized_candidates = &candidate_hidden_states
+                / candidate_hidden_states
+                    .norm_scalaropt_dim(2, &[-1], true)
+                    .clamp_min(1e-8);
+            let normalized_context = &*context_hidden_states
+                / context_hidden_states
+                    .norm_scalaropt_dim(2, &[-1], true)
+                    .clamp_min(1e-8);
+            let (degeneration_penalty, _) = normalized_candidates
+                .view([batch_size, top_k, -1])
+                .matmul(&normalized_context.transpose(1, 2))
+                .max_dim(-1, false);
+            let candidate_scores = (&top_k_probabilities * (1f64 - penalty_alpha)
+                - degeneration_penalty.to_kind(Kind::Float) * penalty_alpha)
+                .masked_fill(&top_k_probabilities.eq(0), f64::NEG_INFINITY);
+            let selected_candidates = candidate_scores.argmax(-1, false);
+            let selected_indices =
+                Tensor::arange(batch_size, (Int64, device)) * top_k + &selected_candidates;
+
+            let mut next_past = candidate_output.cache;
+            let _ = self.reorder_cache(&mut next_past, None, &selected_indices);
+            *past = next_past;
+            *context_hidden_states = Tensor::cat(
+                &[
+                    context_hidden_states.shallow_clone(),
+                    candidate_hidden_states
+                        .index_select(0, &selected_indices)
+                        .unsqueeze(1),
+                ],
+                1,
+            );
+            let next_token = top_k_ids
+                .gather(1, &selected_candidates.unsqueeze(-1), false)
+                .squeeze_dim(-1);
+            let next_logits = candidate_output
+                .lm_logits
+                .index_select(0, &selected_indices);
+            Ok((next_token, next_logits))
+        }
+
         fn generate_no_beam_search(
             &self,
             input_ids: Tensor,
@@ -740,6 +1897,7 @@ pub mod private_generation_utils {
             gen_opt: InternalGenerateOptions,
             prefix_allowed_tokens_fn: Option<&dyn Fn(i64, &Tensor) -> Vec<i64>>,
             output_scores: bool,
+            mut timer: Option<&mut GenerationTimer>,
         ) -> GeneratedOutputWithScores {
             let mut unfinished_sentences =
                 Tensor::ones(&[batch_size], (Int64, self.get_var_store().device()));
@@ -756,30 +1914,57 @@ pub mod private_generation_utils {
             let mut current_length = cur_len;
             let mut token_scores_output: Option<Vec<Tensor>> =
                 if output_scores { Some(vec![]) } else { None };
+            let mut token_logprob_history = if output_scores {
+                Some(TokenLogprobHistory::new(
+                    batch_size,
+                    gen_opt.top_logprobs,
+                    self.get_var_store().device(),
+                ))
+            } else {
+                None
+            };
+            let mut prompt_token_scores: Option<Vec<Vec<f64>>> = None;
+            let contrastive_search =
+                uses_contrastive_search(gen_opt.do_sample, 1, gen_opt.top_k, gen_opt.penalty_alpha);
+            let mut context_hidden_states: Option<Tensor> = None;
+            let mut contrastive_next_logits: Option<Tensor> = None;
+            let has_stop_sequences = gen_opt.has_stop_sequences();
+
+            while current_length < gen_opt.max_length {
+                outputs = match contrastive_next_logits.take() {
+                    // The model already processed the tokens selected by the last contrastive search step
+                    Some(next_logits) => next_logits,
+                    None => {
+                        let temp = if current_length == cur_len {
+                            self.prefill_with_options(
+                                &input_ids,
+                                encoder_outputs.as_ref(),
+                                &attention_mask,
+                                &gen_opt,
+                            )
+                        } else {
+                            self.decode_step(
+                                &input_ids,
+                                encoder_outputs.as_ref(),
+                                past,
+                                &attention_mask,
+                            )
+                        }
+                        .unwrap();
+                        past = temp.cache;
+                        if contrastive_search {
+                            context_hidden_states = Some(temp.hidden_states.expect(
+                                "Contrastive search requires a model exposing its hidden states",
+                            ));
+                        }
+                        temp.lm_logits
+                    }
+                };
 
-            while current_length < gen_opt.max_length {
-                let prepared_input = self.prepare_inputs_for_generation(
-                    input_ids.copy(),
-                    encoder_outputs.as_ref(),
-                    past,
-                    attention_mask.copy(),
-                );
-                let temp = self
-                    .get_model()
-                    .forward_t(
-                        prepared_input.prepared_input.as_ref(),
-                        prepared_input.prepared_past,
-                        prepared_input.prepared_attention_mask.as_ref(),
-                        None,
-                        prepared_input.prepared_position_ids.as_ref(),
-                        None,
-                        prepared_input.prepared_encoder_output,
-                        prepared_input.prepared_decoder_input.as_ref(),
-                        false,
-                    )
-                    .unwrap();
-                outputs = temp.lm_logits;
-                past = temp.cache;
+                if gen_opt.echo & (current_length == cur_len) {
+                    prompt_token_scores =
+                        self.get_prompt_token_scores(&outputs, &input_ids, &attention_mask, 1);
+                }
 
                 let mut next_token_logits = outputs.select(1, -1);
                 // Reduce probability for repeated inputs
@@ -793,6 +1978,20 @@ pub mod private_generation_utils {
                     )
                 }
 
+                if let Some(logit_bias) = gen_opt.logit_bias {
+                    self.apply_logit_bias(&mut next_token_logits, logit_bias);
+                }
+
+                if let Some(watermark) = gen_opt.watermark {
+                    watermark.process(&input_ids, &mut next_token_logits);
+                }
+
+                if let Some(logits_processors) = gen_opt.logits_processors {
+                    for logits_processor in logits_processors {
+                        logits_processor.process(&input_ids, &mut next_token_logits);
+                    }
+                }
+
                 // Get bad word_ids and set their probability to 0
                 if gen_opt.bad_word_ids.is_some() {
                     // Calculate static bad words masks if not set yet
@@ -812,12 +2011,24 @@ pub mod private_generation_utils {
                     );
                 }
 
+                // Ban the tokens completing a surface-level bad word
+                if let Some(banned_tokens_function) = gen_opt.banned_tokens_fn {
+                    self.apply_banned_tokens_function(
+                        banned_tokens_function,
+                        &input_ids,
+                        &mut next_token_logits,
+                    );
+                }
+
                 // Get banned tokens and set their probability to 0
                 if gen_opt.no_repeat_ngram_size > 0 {
+                    let scope_starts =
+                        self.get_ngram_scope_starts(&input_ids, current_length, &gen_opt);
                     let banned_tokens = self.get_banned_tokens(
                         &input_ids,
                         gen_opt.no_repeat_ngram_size as i64,
                         current_length as i64,
+                        &scope_starts,
                     );
                     for (batch_index, index_banned_token) in
                         (0..banned_tokens.len() as i64).zip(banned_tokens)
@@ -841,6 +2052,13 @@ pub mod private_generation_utils {
                     )
                 }
 
+                // Constrain the first generated token to extensions of the healed prompt token
+                if let Some(token_healing_ids) = &gen_opt.token_healing_ids {
+                    if current_length == cur_len {
+                        self.apply_token_healing(token_healing_ids, 1, &mut next_token_logits);
+                    }
+                }
+
                 // Do not allow eos token if min length is not reached
                 if (gen_opt.eos_token_ids.is_some()) & (current_length < gen_opt.min_length) {
                     let _ = next_token_logits.index_fill_(
@@ -860,7 +2078,9 @@ pub mod private_generation_utils {
 
                 // Top-k and top-p sampling
                 let next_token = if gen_opt.do_sample {
-                    if gen_opt.temperature > 1f64 {
+                    if let Some(item_temperatures) = &gen_opt.item_temperatures {
+                        next_token_logits /= item_temperatures;
+                    } else if gen_opt.temperature > 1f64 {
                         next_token_logits /= gen_opt.temperature;
                     }
                     self.top_k_top_p_filtering(
@@ -869,8 +2089,24 @@ pub mod private_generation_utils {
                         gen_opt.top_p,
                         1,
                     );
+                    self.truncation_sampling_filtering(&mut next_token_logits, &gen_opt, 1);
                     let probabilities = next_token_logits.softmax(-1, next_token_logits.kind());
                     probabilities.multinomial(1, false).squeeze_dim(1)
+                } else if contrastive_search {
+                    let (next_token, next_logits) = self
+                        .contrastive_search_step(
+                            &input_ids,
+                            encoder_outputs.as_ref(),
+                            &mut past,
+                            &attention_mask,
+                            &next_token_logits,
+                            context_hidden_states.as_mut().unwrap(),
+                            gen_opt.top_k,
+                            gen_opt.penalty_alpha.unwrap(),
+                        )
+                        .unwrap();
+                    contrastive_next_logits = Some(next_logits);
+                    next_token
                 } else {
                     next_token_logits.argmax(-1, false)
                 };
@@ -885,6 +2121,12 @@ pub mod private_generation_utils {
                             .masked_fill(&finished_mask, 0),
                     );
                 };
+                if let Some(token_logprob_history) = token_logprob_history.as_mut() {
+                    token_logprob_history.push(
+                        &next_token_logits.log_softmax(-1, next_token_logits.kind()),
+                        &next_token,
+                    );
+                }
 
                 // Add tokens to unfinished sentences
                 let tokens_to_add = match &gen_opt.eos_token_ids {
@@ -896,6 +2138,74 @@ pub mod private_generation_utils {
                 };
 
                 input_ids = Tensor::cat(&[input_ids, tokens_to_add.unsqueeze(-1)], -1);
+                if let Some(timer) = timer.as_mut() {
+                    timer.end_step(&tokens_to_add, &past);
+                }
+                if let Some(token_callback) = gen_opt.token_callback {
+                    let step_tokens = tokens_to_add
+                        .iter::<i64>()
+                        .unwrap()
+                        .zip(unfinished_sentences.iter::<i64>().unwrap())
+                        .map(|(token, unfinished)| match unfinished {
+                            0 => None,
+                            _ => Some(token),
+                        })
+                        .collect::<Vec<Option<i64>>>();
+                    if !token_callback(&step_tokens) {
+                        break;
+                    }
+                }
+                if has_stop_sequences {
+                    let generated_ids = input_ids.slice(1, cur_len, current_length + 1, 1);
+                    let unfinished = unfinished_sentences
+                        .iter::<i64>()
+                        .unwrap()
+                        .collect::<Vec<i64>>();
+                    for (sequence_index, unfinished) in unfinished.into_iter().enumerate() {
+                        let stop_sequences = gen_opt.get_stop_sequences(sequence_index);
+                        let sequence_index = sequence_index as i64;
+                        if (unfinished > 0)
+                            && !stop_sequences.is_empty()
+                            && self.contains_stop_sequence(
+                                &generated_ids
+                                    .get(sequence_index)
+                                    .iter::<i64>()
+                                    .unwrap()
+                                    .collect::<Vec<i64>>(),
+                                stop_sequences,
+                            )
+                        {
+                            let _ = unfinished_sentences.get(sequence_index).fill_(0);
+                            let _ = sentence_lengths
+                                .get(sequence_index)
+                                .fill_(current_length + 1);
+                        }
+                    }
+                    if i64::from(unfinished_sentences.max()) == 0 {
+                        break;
+                    }
+                }
+                // Sequences reaching the maximum length of their input
+                if let Some(item_max_lengths) = &gen_opt.item_max_lengths {
+                    let unfinished = unfinished_sentences
+                        .iter::<i64>()
+                        .unwrap()
+                        .collect::<Vec<i64>>();
+                    for (sequence_index, (unfinished, item_max_length)) in
+                        unfinished.into_iter().zip(item_max_lengths).enumerate()
+                    {
+                        if (unfinished > 0) && (current_length + 1 >= *item_max_length) {
+                            let sequence_index = sequence_index as i64;
+                            let _ = unfinished_sentences.get(sequence_index).fill_(0);
+                            let _ = sentence_lengths
+                                .get(sequence_index)
+                                .fill_(current_length + 1);
+                        }
+                    }
+                    if i64::from(unfinished_sentences.max()) == 0 {
+                        break;
+                    }
+                }
                 if gen_opt.eos_token_ids.is_some() {
                     for eos_token_id in gen_opt.eos_token_ids.as_ref().unwrap() {
                         let sentence_with_eos = tokens_to_add.eq(*eos_token_id).to_kind(Int64);
@@ -947,10 +2257,26 @@ pub mod private_generation_utils {
                     })
                     .collect()
             });
+            let generated_token_scores = token_logprob_history.map(|token_logprob_history| {
+                (0..batch_size)
+                    .map(|sequence_index| {
+                        let (token_logprobs, top_logprobs) =
+                            token_logprob_history.get(sequence_index, None);
+                        GeneratedTokenScores {
+                            token_logprobs,
+                            top_logprobs,
+                            sequence_score: scores_output.as_ref().unwrap()
+                                [sequence_index as usize],
+                        }
+                    })
+                    .collect()
+            });
             GeneratedOutputWithScores {
                 indices: input_ids,
                 scores: scores_output,
                 token_scores: token_scores_output,
+                generated_token_scores,
+                prompt_token_scores,
             }
         }
 
@@ -964,6 +2290,7 @@ pub mod private_generation_utils {
             gen_opt: InternalGenerateOptions,
             prefix_allowed_tokens_fn: Option<&dyn Fn(i64, &Tensor) -> Vec<i64>>,
             output_scores: bool,
+            mut timer: Option<&mut GenerationTimer>,
         ) -> GeneratedOutputWithScores {
             let num_beam_groups = gen_opt.num_beam_groups.unwrap_or(1);
             let num_sub_beams = gen_opt.num_beams / num_beam_groups;
@@ -972,10 +2299,12 @@ pub mod private_generation_utils {
                 self.split_bad_word_ids(gen_opt.bad_word_ids);
             let mut static_bad_words_mask: Option<Tensor> = None;
 
-            let mut hypotheses = (0..batch_size)
+            // Finished hypotheses of each beam group of each input (at `batch_index * num_beam_groups +
+            // beam_group_index`), so that the hypotheses of a group do not evict the ones of the other groups
+            let mut hypotheses = (0..batch_size * num_beam_groups)
                 .map(|_| {
                     BeamHypotheses::new(
-                        gen_opt.num_beams,
+                        num_sub_beams,
                         gen_opt.max_length,
                         gen_opt.length_penalty,
                         gen_opt.early_stopping,
@@ -1003,14 +2332,24 @@ pub mod private_generation_utils {
             );
             let mut saved_beam_scores: Option<Vec<Tensor>> =
                 if output_scores { Some(vec![]) } else { None };
+            let mut token_logprob_history = if output_scores {
+                Some(TokenLogprobHistory::new(
+                    batch_size * gen_opt.num_beams,
+                    gen_opt.top_logprobs,
+                    self.get_var_store().device(),
+                ))
+            } else {
+                None
+            };
             let mut current_tokens = Tensor::new();
 
             let mut past: Cache = Cache::None;
-            let mut done = vec![false; batch_size as usize];
+            let mut done = vec![false; (batch_size * num_beam_groups) as usize];
 
             let mut outputs: Tensor;
             let mut encoder_outputs = encoder_outputs;
             let mut current_length = cur_len;
+            let mut prompt_token_scores: Option<Vec<Vec<f64>>> = None;
 
             while current_length < gen_opt.max_length {
                 if num_beam_groups > 1 {
@@ -1019,29 +2358,36 @@ pub mod private_generation_utils {
                         (input_ids.kind(), input_ids.device()),
                     );
                 }
-                let prepared_input = self.prepare_inputs_for_generation(
-                    input_ids.copy(),
-                    encoder_outputs.as_ref(),
-                    past,
-                    attention_mask.copy(),
-                );
-                let temp = self
-                    .get_model()
-                    .forward_t(
-                        prepared_input.prepared_input.as_ref(),
-                        prepared_input.prepared_past,
-                        prepared_input.prepared_attention_mask.as_ref(),
-                        None,
-                        prepared_input.prepared_position_ids.as_ref(),
-                        None,
-                        prepared_input.prepared_encoder_output,
-                        prepared_input.prepared_decoder_input.as_ref(),
-                        false,
+                // Log-probabilities of the vocabulary for each beam, filled by the beam groups
+                let mut step_log_probabilities = token_logprob_history.as_ref().map(|_| {
+                    Tensor::zeros(
+                        &[batch_size * gen_opt.num_beams, vocab_size],
+                        (Float, input_ids.device()),
                     )
-                    .unwrap();
+                });
+                let temp = if current_length == cur_len {
+                    self.prefill_with_options(
+                        &input_ids,
+                        encoder_outputs.as_ref(),
+                        &attention_mask,
+                        &gen_opt,
+                    )
+                } else {
+                    self.decode_step(&input_ids, encoder_outputs.as_ref(), past, &attention_mask)
+                }
+                .unwrap();
                 outputs = temp.lm_logits;
                 past = temp.cache;
 
+                if gen_opt.echo & (current_length == cur_len) {
+                    prompt_token_scores = self.get_prompt_token_scores(
+                        &outputs,
+                        &input_ids,
+                        &attention_mask,
+                        gen_opt.num_beams,
+                    );
+                }
+
                 for beam_group_index in 0..num_beam_groups {
                     let group_start_index = beam_group_index * num_sub_beams;
                     let group_end_index = min(group_start_index + num_sub_beams, gen_opt.num_beams);
@@ -1084,6 +2430,26 @@ pub mod private_generation_utils {
                         )
                     }
 
+                    if let Some(logit_bias) = gen_opt.logit_bias {
+                        self.apply_logit_bias(&mut next_token_logits, logit_bias);
+                    }
+
+                    if let Some(watermark) = gen_opt.watermark {
+                        watermark.process(
+                            group_input_ids.as_ref().unwrap_or(&input_ids),
+                            &mut next_token_logits,
+                        );
+                    }
+
+                    if let Some(logits_processors) = gen_opt.logits_processors {
+                        for logits_processor in logits_processors {
+                            logits_processor.process(
+                                group_input_ids.as_ref().unwrap_or(&input_ids),
+                                &mut next_token_logits,
+                            );
+                        }
+                    }
+
                     if gen_opt.temperature > 1f64 {
                         next_token_logits /= gen_opt.temperature;
                     }
@@ -1124,12 +2490,27 @@ pub mod private_generation_utils {
                         );
                     }
 
+                    // Ban the tokens completing a surface-level bad word
+                    if let Some(banned_tokens_function) = gen_opt.banned_tokens_fn {
+                        self.apply_banned_tokens_function(
+                            banned_tokens_function,
+                            group_input_ids.as_ref().unwrap_or(&input_ids),
+                            &mut scores,
+                        );
+                    }
+
                     // Get repeated tokens and set their probability to 0
                     if gen_opt.no_repeat_ngram_size > 0 {
+                        let scope_starts = self.get_ngram_scope_starts(
+                            group_input_ids.as_ref().unwrap_or(&input_ids),
+                            current_length,
+                            &gen_opt,
+                        );
                         let banned_tokens = self.get_banned_tokens(
                             group_input_ids.as_ref().unwrap_or(&input_ids),
                             gen_opt.no_repeat_ngram_size,
                             current_length,
+                            &scope_starts,
                         );
                         for (batch_index, index_banned_token) in
                             (0..banned_tokens.len() as i64).zip(banned_tokens)
@@ -1161,11 +2542,31 @@ pub mod private_generation_utils {
                         self.apply_prefix_allowed_tokens_function(
                             prefix_allowed_tokens_function,
                             num_sub_beams,
-                            &input_ids,
+                            group_input_ids.as_ref().unwrap_or(&input_ids),
                             &mut scores,
                         )
                     }
 
+                    // Constrain the first generated token to extensions of the healed prompt token
+                    if let Some(token_healing_ids) = &gen_opt.token_healing_ids {
+                        if current_length == cur_len {
+                            self.apply_token_healing(token_healing_ids, group_size, &mut scores);
+                        }
+                    }
+
+                    if let Some(step_log_probabilities) = step_log_probabilities.as_mut() {
+                        match &batch_group_indices {
+                            Some(batch_group_indices) => {
+                                let _ = step_log_probabilities.index_copy_(
+                                    0,
+                                    batch_group_indices,
+                                    &scores.to_kind(Float),
+                                );
+                            }
+                            None => step_log_probabilities.copy_(&scores),
+                        }
+                    }
+
                     let mut next_scores: Tensor = &scores
                         + (if num_beam_groups > 1 {
                             beam_scores
@@ -1183,6 +2584,7 @@ pub mod private_generation_utils {
                             gen_opt.top_p,
                             2,
                         );
+                        self.truncation_sampling_filtering(&mut next_scores, &gen_opt, 2);
                         let _scores = next_scores
                             .contiguous()
                             .view((batch_size, group_size * vocab_size));
@@ -1225,45 +2627,64 @@ pub mod private_generation_utils {
                     for eos_idx in 0..eos_pos.size()[0] {
                         let eos_data = eos_pos.get(eos_idx);
                         let batch_index = eos_data.int64_value(&[0]);
-                        if !done[batch_index as usize] {
+                        let hypotheses_index =
+                            (batch_index * num_beam_groups + beam_group_index) as usize;
+                        if !done[hypotheses_index] {
                             let beam_index_pos = eos_data.int64_value(&[1]);
-                            let is_beam_token_worse_than_top_num_beams =
-                                beam_index_pos >= gen_opt.num_beams;
-                            if is_beam_token_worse_than_top_num_beams {
+                            let is_beam_token_worse_than_top_group_beams =
+                                beam_index_pos >= group_size;
+                            if is_beam_token_worse_than_top_group_beams {
                                 continue;
                             }
+                            // Position of the beam in the scores of the group
                             let effective_beam_id = effective_beam_ids_tensor
                                 .int64_value(&[batch_index, beam_index_pos]);
+                            // Position of the beam among all the beams
+                            let global_beam_id = batch_index * gen_opt.num_beams
+                                + group_start_index
+                                + beam_ids_tensor.int64_value(&[batch_index, beam_index_pos]);
                             let beam_token_score =
                                 next_scores.double_value(&[batch_index, beam_index_pos]);
                             let saved_beam_scores =
                                 saved_beam_scores.as_ref().map(|step_wise_scores| {
                                     Tensor::stack(step_wise_scores, 1)
-                                        .get(effective_beam_id)
+                                        .get(global_beam_id)
                                         .copy()
                                 });
-                            hypotheses[batch_index as usize].add(
-                                input_ids.get(effective_beam_id).copy(),
+                            let token_logprobs =
+                                token_logprob_history.as_ref().map(|token_logprob_history| {
+                                    let eos_token_id =
+                                        token_id_tensor.int64_value(&[batch_index, beam_index_pos]);
+                                    token_logprob_history.get(
+                                        global_beam_id,
+                                        Some((&scores.get(effective_beam_id), eos_token_id)),
+                                    )
+                                });
+                            hypotheses[hypotheses_index].add(
+                                input_ids.get(global_beam_id).copy(),
                                 beam_token_score,
                                 saved_beam_scores,
+                                token_logprobs,
                             );
                         }
                     }
 
                     for batch_index in 0..batch_size {
-                        if done[batch_index as usize] {
+                        let hypotheses_index =
+                            (batch_index * num_beam_groups + beam_group_index) as usize;
+                        if done[hypotheses_index] {
                             let _ = group_beam_scores
-                                .narrow(0, batch_index * gen_opt.num_beams, gen_opt.num_beams)
+                                .narrow(0, batch_index * group_size, group_size)
                                 .fill_(0f64);
                             let _ = group_beam_tokens
-                                .narrow(0, batch_index * gen_opt.num_beams, gen_opt.num_beams)
+                                .narrow(0, batch_index * group_size, group_size)
                                 .fill_(gen_opt.pad_token_id.unwrap());
                             let _ = group_beam_indices
-                                .narrow(0, batch_index * gen_opt.num_beams, gen_opt.num_beams)
+                                .narrow(0, batch_index * group_size, group_size)
                                 .fill_(0);
                             continue;
                         } else {
-                            done[batch_index as usize] |= hypotheses[batch_index as usize]
+                            done[hypotheses_index] |= hypotheses[hypotheses_index]
                                 .is_done(max_scores.double_value(&[batch_index]), current_length);
                         }
                     }
@@ -1303,6 +2724,9 @@ pub mod private_generation_utils {
                 if let Some(scores_output) = saved_beam_scores.as_mut() {
                     scores_output.push(beam_scores.copy());
                 }
+                if let Some(timer) = timer.as_mut() {
+                    timer.end_step(&beam_tokens, &past);
+                }
                 if done.iter().all(|&x| x) {
                     break;
                 }
@@ -1314,6 +2738,49 @@ pub mod private_generation_utils {
                     ],
                     -1,
                 );
+                if let Some(token_logprob_history) = token_logprob_history.as_mut() {
+                    token_logprob_history.reorder(&beam_indices);
+                    token_logprob_history.push(
+                        &step_log_probabilities
+                            .as_ref()
+                            .unwrap()
+                            .index_select(0, &beam_indices),
+                        &beam_tokens,
+                    );
+                }
+                // Beams completing a stop sequence are finished hypotheses, similarly to the end of sequence tokens
+                if !gen_opt.stop_sequences.is_empty() {
+                    let generated_ids = input_ids.slice(1, cur_len, current_length + 1, 1);
+                    for beam_index in 0..*input_ids.size().first().unwrap() {
+                        let hypotheses_index = (beam_index / num_sub_beams) as usize;
+                        if !done[hypotheses_index]
+                            && self.contains_stop_sequence(
+                                &generated_ids
+                                    .get(beam_index)
+                                    .iter::<i64>()
+                                    .unwrap()
+                                    .collect::<Vec<i64>>(),
+                                &gen_opt.stop_sequences,
+                            )
+                        {
+                            let saved_beam_scores =
+                                saved_beam_scores.as_ref().map(|step_wise_scores| {
+                                    Tensor::stack(step_wise_scores, 1).get(beam_index).copy()
+                                });
+                            let token_logprobs =
+                                token_logprob_history.as_ref().map(|token_logprob_history| {
+                                    token_logprob_history.get(beam_index, None)
+                                });
+                            hypotheses[hypotheses_index].add(
+                                input_ids.get(beam_index).copy(),
+                                beam_scores.double_value(&[beam_index]),
+                                saved_beam_scores,
+                                token_logprobs,
+                            );
+                            let _ = beam_scores.get(beam_index).fill_(-1e9);
+                        }
+                    }
+                }
                 encoder_outputs = self.reorder_cache(&mut past, encoder_outputs, &beam_indices);
 
                 if !self.is_encoder_decoder() {
@@ -1333,32 +2800,30 @@ pub mod private_generation_utils {
                 current_length += 1;
             }
 
-            let mut batch_index = 0i64;
-
             let mut saved_beam_scores = saved_beam_scores
                 .map(|step_wise_scores| Tensor::stack(&step_wise_scores, 1).split(1, 0));
-            loop {
-                if batch_index == batch_size {
-                    break;
-                }
-                if done[batch_index as usize] {
-                    batch_index += 1;
+            for (hypotheses_index, hypothesis) in hypotheses.iter_mut().enumerate() {
+                if done[hypotheses_index] {
                     continue;
                 }
-                for beam_index in 0..gen_opt.num_beams {
-                    let effective_beam_id = batch_index * gen_opt.num_beams + beam_index;
+                for beam_index in 0..num_sub_beams {
+                    let effective_beam_id = hypotheses_index as i64 * num_sub_beams + beam_index;
                     let beam_saved_token_scores = saved_beam_scores.as_mut().map(|saved_tokens| {
                         mem::replace(&mut saved_tokens[effective_beam_id as usize], Tensor::new())
                     });
+                    let token_logprobs =
+                        token_logprob_history.as_ref().map(|token_logprob_history| {
+                            token_logprob_history.get(effective_beam_id, None)
+                        });
                     let final_score = f64::from(beam_scores.get(effective_beam_id));
                     let final_tokens = input_ids.get(effective_beam_id);
-                    hypotheses[batch_index as usize].add(
+                    hypothesis.add(
                         final_tokens,
                         final_score,
                         beam_saved_token_scores,
+                        token_logprobs,
                     );
                 }
-                batch_index += 1;
             }
             let (output_batch_size, output_num_return_sequences_per_batch) = if gen_opt.do_sample {
                 (batch_size, 1)
@@ -1383,17 +2848,26 @@ pub mod private_generation_utils {
             } else {
                 None
             };
-            for (hypothesis_index, hypothesis) in hypotheses.iter().enumerate() {
-                let mut sorted_hypotheses = hypothesis.clone();
-                sorted_hypotheses
-                    .beams
-                    .sort_by_key(|(score, _, _)| OrderedFloat(*score));
+            let mut generated_token_scores = if output_scores {
+                Some(Vec::with_capacity(best_ids.len()))
+            } else {
+                None
+            };
+            // The hypotheses of all the beam groups of an input are ranked together
+            for (batch_index, batch_hypotheses) in
+                hypotheses.chunks(num_beam_groups as usize).enumerate()
+            {
+                let mut sorted_hypotheses = batch_hypotheses
+                    .iter()
+                    .flat_map(|hypothesis| hypothesis.clone().beams)
+                    .collect::<Vec<(f64, Tensor, Option<Tensor>, Option<GeneratedTokenScores>)>>();
+                sorted_hypotheses.sort_by_key(|(score, _, _, _)| OrderedFloat(*score));
                 for j in 0..output_num_return_sequences_per_batch {
                     let effective_batch_index =
-                        output_num_return_sequences_per_batch * hypothesis_index as i64 + j;
+                        output_num_return_sequences_per_batch * batch_index as i64 + j;
 
-                    let (best_score, best_hyp, best_token_scores) =
-                        sorted_hypotheses.beams.pop().unwrap();
+                    let (best_score, best_hyp, best_token_scores, best_token_logprobs) =
+                        sorted_hypotheses.pop().unwrap();
                     let _ = sentence_lengths.index_fill_(
                         0,
                         &Tensor::of_slice(&[effective_batch_index]).to(sentence_lengths.device()),
@@ -1412,6 +2886,9 @@ pub mod private_generation_utils {
                                 .collect::<Vec<f64>>(),
                         );
                     }
+                    if let Some(generated_token_scores) = &mut generated_token_scores {
+                        generated_token_scores.push(best_token_logprobs.unwrap());
+                    }
                 }
             }
             let sentence_max_length =
@@ -1450,6 +2927,8 @@ pub mod private_generation_utils {
                 indices: decoded,
                 scores: scores_output,
                 token_scores: token_scores_output,
+                generated_token_scores,
+                prompt_token_scores,
             }
         }
 
@@ -1471,19 +2950,91 @@ pub mod private_generation_utils {
 
 #[derive(Debug, Clone)]
 /// # Generated text output
-/// Contains generated text and an optional log-likelihood score for the generated sequence
+/// Contains generated text and an optional log-likelihood score for the generated sequence.
+/// If `echo` is set in the generation options, the log-probabilities of the prompt tokens are also returned.
 pub struct GeneratedTextOutput {
     pub text: String,
     pub score: Option<f64>,
+    pub generated_token_scores: Option<GeneratedTokenScores>,
+    pub prompt_token_scores: Option<Vec<f64>>,
+    pub telemetry: Option<GenerationTelemetry>,
 }
 
 #[derive(Debug, Clone)]
 /// # Generated indices output
-/// Contains generated indices and an optional log-likelihood score for the generated sequence and individual tokens
+/// Contains generated indices and an optional log-likelihood score for the generated sequence and individual tokens.
+/// If `echo` is set in the generation options, the log-probabilities of the prompt tokens are also returned.
 pub struct GeneratedIndicesOutput {
     pub indices: Vec<i64>,
     pub score: Option<f64>,
     pub token_scores: Option<Vec<f64>>,
+    pub generated_token_scores: Option<GeneratedTokenScores>,
+    pub prompt_token_scores: Option<Vec<f64>>,
+    pub telemetry: Option<GenerationTelemetry>,
+}
+
+#[derive(Debug, Clone, Default, PartialEq)]
+/// # Token-level scores of a generated sequence
+/// Returned with each generated sequence if `output_scores` is set in the generation options (e.g. for reranking or
+/// hallucination detection). The log-probabilities are computed from the scores the tokens were selected with, after
+/// the repetition penalty, temperature and other constraints of the generation.
+pub struct GeneratedTokenScores {
+    /// Log-probability of each generated token (up to the end of sequence token)
+    pub token_logprobs: Vec<f64>,
+    /// Most likely tokens (token id and log-probability) at each generation step, by decreasing log-probability.
+    /// Contains `GenerateOptions::top_logprobs` tokens per step (none if not set).
+    pub top_logprobs: Vec<Vec<(i64, f64)>>,
+    /// Cumulative score of the sequence (sum of the log-probabilities, normalized by the length penalty)
+    pub sequence_score: f64,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+/// # Token streamed during the generation
+/// Passed to the callback of `LanguageGenerator::generate_stream` as soon as the token is generated.
+pub struct StreamedToken {
+    /// Index of the generated sequence (in the order of the generated outputs)
+    pub sequence_index: usize,
+    /// Generated token id
+    pub token_id: i64,
+    /// Text added to the sequence by the token. May be empty for special tokens, or for tokens holding part of
+    /// a character (the text is then returned with the token completing the character).
+    pub text: String,
+    /// True if the token ends the sequence (end of sequence token)
+    pub finished: bool,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+/// # Generation telemetry
+/// Token counts, timings and cache size of the generation of a sequence, returned if `output_telemetry` is set in
+/// the generation options. The timings and cache size are measured for the batch the sequence was generated with,
+/// and are shared by the sequences generated together.
+pub struct GenerationTelemetry {
+    /// Number of prompt tokens (excluding padding)
+    pub prompt_tokens: usize,
+    /// Number of generated tokens (including the end of sequence token, excluding padding)
+    pub generated_tokens: usize,
+    /// Time to the first generated token (encoding of the prompt and first decoding step), in milliseconds
+    pub prefill_ms: f64,
+    /// Duration of each following decoding step, in milliseconds
+    pub decode_ms: Vec<f64>,
+    /// Maximum size of the cached states during the generation, in bytes
+    pub max_cache_bytes: usize,
+}
+
+impl GenerationTelemetry {
+    /// Returns the total generation time, in milliseconds
+    pub fn total_ms(&self) -> f64 {
+        self.prefill_ms + self.decode_ms.iter().sum::<f64>()
+    }
+
+    /// Returns the mean duration of the decoding steps following the first generated token, in milliseconds
+    pub fn mean_decode_ms(&self) -> Option<f64> {
+        if self.decode_ms.is_empty() {
+            None
+        } else {
+            Some(self.decode_ms.iter().sum::<f64>() / self.decode_ms.len() as f64)
+        }
+    }
 }
 
 #[derive(Clone, Copy, Default)]
@@ -1506,6 +3057,7 @@ pub struct GenerateOptions<'a> {
     pub num_return_sequences: Option<i64>,
     /// Number of beams for beam search
     pub num_beams: Option<i64>,
+    /// Number of beam groups for diverse beam search. If provided and higher than 1, will split the beams into beam subgroups leading to more diverse generation.
     pub num_beam_groups: Option<i64>,
     /// Sampling flag. If true, will perform top-k and/or nucleus sampling on generated tokens, otherwise greedy (deterministic) decoding
     pub do_sample: Option<bool>,
@@ -1521,18 +3073,107 @@ pub struct GenerateOptions<'a> {
     pub length_penalty: Option<f64>,
     /// Number of allowed repetitions of n-grams. Values higher than 0 turn on this feature
     pub no_repeat_ngram_size: Option<i64>,
+    /// Part of the sequence in which the n-grams may not be repeated (sequence, window, sentence or paragraph)
+    pub no_repeat_ngram_scope: Option<NoRepeatNgramScope>,
     /// Diversity penalty for diverse beam search. High values will enforce more difference between beam groups
     pub diversity_penalty: Option<f64>,
+    /// Degeneration penalty for contrastive search (greedy decoding with a `top_k` higher than 1). High values will
+    /// penalize more the tokens whose hidden state is similar to the hidden states of the previous tokens
+    pub penalty_alpha: Option<f64>,
+    /// Typical_p value for locally typical sampling. Keep the tokens whose information content is the closest to the
+    /// entropy of the distribution until their cumulative probability reaches typical_p
+    pub typical_p: Option<f64>,
+    /// Epsilon cutoff for truncation sampling. Only sample the tokens with a probability higher than epsilon_cutoff
+    pub epsilon_cutoff: Option<f64>,
+    /// Eta cutoff for truncation sampling. Only sample the tokens with a probability higher than
+    /// `min(eta_cutoff, sqrt(eta_cutoff) * exp(-entropy))`
+    pub eta_cutoff: Option<f64>,
     /// Decoder start token id
     pub decoder_start_token_id: Option<i64>,
     /// Forced first token generated
     pub forced_bos_token_id: Option<i64>,
+    /// Decoder prompt token ids of shape (*batch size*, *prompt_length*) following the decoder start token (T5 and
+    /// BART-based models). The decoder prompt is processed as the prefix of a prefix-LM: its positions attend to the
+    /// whole prompt (see `attention_mask::build_prefix_lm_attention_mask`), and the generated tokens attend causally
+    /// to the prompt and to the previous tokens. The generated sequences start with the decoder prompt.
+    pub decoder_prefix_ids: Option<&'a Tensor>,
+    /// Pre-computed encoder hidden states of shape (*batch size*, *source_sequence_length*, *hidden_size*)
+    /// (encoder-decoder models). When provided, the inputs are not encoded again and the input ids only define the
+    /// encoder attention mask, allowing the generation from inputs other than tokens (e.g. encoded images).
+    pub encoder_outputs: Option<&'a Tensor>,
     /// Function to control the generation process. The function should take a `batch_id` (i64) and a tensor of token_ids already generated and returns a `Vec<i64>` of allowed tokens.
     pub prefix_allowed_tokens_fn: Option<&'a dyn Fn(i64, &Tensor) -> Vec<i64>>,
     /// List of bad word ids (may be a sequence of word ids) that will be banned during the generation
     pub bad_word_ids: Option<&'a Vec<Vec<i64>>>,
+    /// Strings banned from the generated text, whatever their tokenization (unlike `bad_word_ids`): at each step,
+    /// the tokens whose text would complete one of these strings are masked. See the `bad_words` module for more
+    /// details.
+    pub bad_words: Option<&'a [&'a str]>,
+    /// Bias added to the logits of the given token ids at every generation step (similar to the `logit_bias` of the
+    /// OpenAI API). Positive values encourage the generation of a token and negative values discourage it, without
+    /// banning it as `bad_word_ids` do (values of -100 or lower effectively ban a token). Token ids outside of the
+    /// vocabulary are ignored. Replaces the logit bias of the generation configuration (an empty map disables it).
+    pub logit_bias: Option<&'a HashMap<i64, f64>>,
+    /// Statistical watermark applied to the generated text: a bias is added at each step to the logits of a
+    /// pseudo-random green list of tokens, seeded by the previous token. The texts generated can be checked for the
+    /// watermark with `Watermark::detect`. See the `watermark` module for more details.
+    pub watermark: Option<&'a Watermark>,
+    /// User-defined processors modifying the logits of the next token at each step, applied in order after the
+    /// built-in penalties and biases and before the hard constraints (bad words, n-gram repetition, prefix
+    /// constraint, minimum length). See the `logits_processor` module for more details.
+    pub logits_processors: Option<&'a [&'a dyn LogitsProcessor]>,
     /// Flag indicating if text generation scores should be returned
     pub output_scores: bool,
+    /// Number of most likely tokens returned at each step with their log-probability in the `GeneratedTokenScores`
+    /// of each sequence (similar to the `top_logprobs` of the OpenAI API). Requires `output_scores`.
+    pub top_logprobs: Option<i64>,
+    /// Token healing flag (decoder-only models). If true, the last token of the prompt is removed and the first
+    /// generated token is constrained to tokens starting with the removed token, avoiding artifacts when a prompt
+    /// ends in the middle of a word (e.g. a prompt ending with `http:` can be continued with `//`).
+    /// The regenerated token counts towards `max_new_tokens`.
+    pub token_healing: bool,
+    /// Echo flag (decoder-only models). If true, the log-probabilities of the prompt tokens (conditioned on the
+    /// preceding prompt tokens) are returned along with the generated sequence. The first prompt token has no
+    /// preceding context and is not scored. The scores are computed from the same forward pass as the first generated token.
+    pub echo: bool,
+    /// Telemetry flag. If true, the `GenerationTelemetry` (token counts, prefill and per-token decoding times, cache
+    /// size) is returned with each generated sequence. Measuring the timings synchronizes with the device at each step.
+    pub output_telemetry: bool,
+    /// Function called at each generation step with the token generated for each sequence (`None` for the sequences
+    /// already finished). The generation stops if the function returns false. Only called for greedy decoding and
+    /// sampling (`num_beams` = 1): see `LanguageGenerator::generate_stream` for a higher-level streaming API.
+    pub token_callback: Option<&'a dyn Fn(&[Option<i64>]) -> bool>,
+    /// Stop sequences, replacing the stop sequences of the generation configuration. The generation of a sequence
+    /// stops as soon as its generated text contains one of these strings, and the returned text is truncated
+    /// before it. The returned indices end with the token completing the stop sequence.
+    pub stop_sequences: Option<&'a [&'a str]>,
+    /// Grammar the generated text must follow (e.g. created from a JSON schema): at each step, the tokens that
+    /// would make the generated text invalid are masked, and the end of sequence is only allowed once the text is
+    /// complete. If `prefix_allowed_tokens_fn` is also provided, the tokens allowed by both are kept.
+    /// See the `grammar` module for more details.
+    pub grammar: Option<&'a Grammar>,
+    /// Cache of a prompt prefix computed with `LanguageGenerator::encode_prefix` (decoder-only models). The prompts
+    /// are appended to the prefix, whose tokens are not processed again, and the returned sequences start with the
+    /// prefix. The log-probabilities of the prompt tokens (`echo`) are not returned when a prefix cache is used.
+    pub prefix_cache: Option<&'a PrefixCache>,
+    /// Options of each input of the batch (one `ItemGenerateOptions` per prompt), overriding the options above for
+    /// this input. Only available for greedy decoding and sampling (`num_beams` = 1).
+    pub item_options: Option<&'a [ItemGenerateOptions<'a>]>,
+}
+
+/// # Generation options of a single input
+/// Overrides of the generation options for one input of a batched generation call, allowing requests with
+/// different settings to be generated in the same forward passes (e.g. requests of several users coalesced by a
+/// server). Options left as `None` default to the options of the batch.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct ItemGenerateOptions<'a> {
+    /// Maximum number of new tokens generated for this input. The batch is generated until the longest limit of
+    /// its inputs is reached.
+    pub max_new_tokens: Option<i64>,
+    /// Temperature for this input (sampling only). Must be strictly positive.
+    pub temperature: Option<f64>,
+    /// Stop sequences for this input, replacing the stop sequences of the batch
+    pub stop_sequences: Option<&'a [&'a str]>,
 }
 
 macro_rules! unpack_config {
@@ -1643,13 +3284,20 @@ pub trait LanguageGenerator<T: LMHeadModel, V: Vocab, U: Tokenizer<V>>:
         S: AsRef<str> + Sync,
     {
         let indices_outputs = self.generate_indices(prompt_texts, generate_options);
+        let stop_sequences = self.get_item_stop_sequences(generate_options, indices_outputs.len());
         let mut output = Vec::with_capacity(indices_outputs.len());
-        for generated_sequence in indices_outputs {
+        for (generated_sequence, stop_sequences) in indices_outputs.into_iter().zip(stop_sequences)
+        {
             output.push(GeneratedTextOutput {
-                text: self
-                    ._get_tokenizer()
-                    .decode(&generated_sequence.indices, true, true),
+                text: decode_before_stop_sequence(
+                    self._get_tokenizer(),
+                    &generated_sequence.indices,
+                    &stop_sequences,
+                ),
                 score: generated_sequence.score,
+                generated_token_scores: generated_sequence.generated_token_scores,
+                prompt_token_scores: generated_sequence.prompt_token_scores,
+                telemetry: generated_sequence.telemetry,
             });
         }
         output
@@ -1713,58 +3361,205 @@ pub trait LanguageGenerator<T: LMHeadModel, V: Vocab, U: Tokenizer<V>>:
     ///     (0..50255).collect()
     /// }
     ///
-    /// let generate_options = GenerateOptions {
-    ///     min_length: Some(32),
-    ///     max_length: Some(128),
-    ///     output_scores: true,
-    ///     prefix_allowed_tokens_fn: Some(&force_one_paragraph),
-    ///     ..Default::default()
-    /// };
+    /// let generate_options = GenerateOptions {
+    ///     min_length: Some(32),
+    ///     max_length: Some(128),
+    ///     output_scores: true,
+    ///     prefix_allowed_tokens_fn: Some(&force_one_paragraph),
+    ///     ..Default::default()
+    /// };
+    ///
+    /// let output = gpt2_generator.generate_indices(
+    ///     Some(&[input_context, second_input_context]),
+    ///     Some(generate_options),
+    /// );
+    /// # Ok(())
+    /// # }
+    /// ```
+    fn generate_indices<S>(
+        &self,
+        prompt_texts: Option<&[S]>,
+        generate_options: Option<GenerateOptions>,
+    ) -> Vec<GeneratedIndicesOutput>
+    where
+        S: AsRef<str> + Sync,
+    {
+        let eos_token_ids = self.get_eos_ids();
+
+        let config = self.get_config();
+        let max_length = unpack_config!(max_length, generate_options, config);
+        let encoding_max_len = if self.is_encoder_decoder() {
+            self.get_max_positions_embeddings()
+        } else {
+            max_length
+        };
+        let pad_token_id = match self.get_pad_id() {
+            Some(value) => Some(value),
+            None => eos_token_ids.as_ref().map(|eos_ids| eos_ids[0]),
+        };
+
+        // Prompts appended to a cached prefix do not start the sequence, the prefix holds the BOS token
+        let add_bos_token = self.add_bos_token()
+            && generate_options
+                .as_ref()
+                .map_or(true, |opts| opts.prefix_cache.is_none());
+
+        let input_ids = match prompt_texts {
+            Some(prompts) if !prompts.is_empty() => {
+                self.encode_prompt_text(prompts, encoding_max_len, pad_token_id, add_bos_token)
+            }
+            None => match self.get_bos_id() {
+                Some(bos_id) => {
+                    Tensor::ones(&[1, 1], (Int64, self.get_var_store().device())) * bos_id
+                }
+                None => panic!(
+                    "A model with a BOS token must be used to start generation with an empty input"
+                ),
+            },
+            _ => return Vec::new(),
+        };
+        self.generate_from_ids_and_past(input_ids, None, generate_options)
+    }
+
+    /// Generate text based on a vector of prompt texts, calling a function with each token as soon as it is
+    /// generated (e.g. to display the response of a chat model progressively).
+    /// Streaming is available for greedy decoding and sampling (`num_beams` = 1): the tokens of beam search
+    /// hypotheses are only final at the end of the generation.
+    ///
+    /// # Arguments
+    ///
+    /// * `prompt_texts` - `Option<Vec<&str>>` Optional vector of text prompts. An empty prompt to the model may be passed if the model implement a `bos_id`.
+    /// * `generate_options` - `Option<GenerateOptions>` Optional set of generate options. If not (or partially) provided, will use the settings provided when creating the generator
+    /// * `callback` - Function called with each `StreamedToken`. The generation stops if the function returns false.
+    ///
+    /// # Returns
+    /// * `Vec<TextOutput>` Vector of length *number_of_prompts* x *num_return_sequences* containing TextOutput with the generated texts, as returned by `generate`.
+    ///
+    /// # Example
+    ///
+    /// ```no_run
+    /// # fn main() -> anyhow::Result<()> {
+    /// use rust_bert::gpt2::GPT2Generator;
+    /// use rust_bert::pipelines::generation_utils::LanguageGenerator;
+    /// use std::io::Write;
+    ///
+    /// let gpt2_generator = GPT2Generator::new(Default::default())?;
+    /// let output = gpt2_generator.generate_stream(Some(&["The dog"]), None, |token| {
+    ///     print!("{}", token.text);
+    ///     std::io::stdout().flush().is_ok()
+    /// })?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    fn generate_stream<S, F>(
+        &self,
+        prompt_texts: Option<&[S]>,
+        generate_options: Option<GenerateOptions>,
+        callback: F,
+    ) -> Result<Vec<GeneratedTextOutput>, RustBertError>
+    where
+        S: AsRef<str> + Sync,
+        F: FnMut(&StreamedToken) -> bool,
+    {
+        let indices_outputs =
+            self.generate_indices_stream(prompt_texts, generate_options, callback)?;
+        let stop_sequences = self.get_item_stop_sequences(generate_options, indices_outputs.len());
+        let mut output = Vec::with_capacity(indices_outputs.len());
+        for (generated_sequence, stop_sequences) in indices_outputs.into_iter().zip(stop_sequences)
+        {
+            output.push(GeneratedTextOutput {
+                text: decode_before_stop_sequence(
+                    self._get_tokenizer(),
+                    &generated_sequence.indices,
+                    &stop_sequences,
+                ),
+                score: generated_sequence.score,
+                generated_token_scores: generated_sequence.generated_token_scores,
+                prompt_token_scores: generated_sequence.prompt_token_scores,
+                telemetry: generated_sequence.telemetry,
+            });
+        }
+        Ok(output)
+    }
+
+    /// Generate token indices based on a vector of prompt texts, calling a function with each token as soon as it
+    /// is generated (see `generate_stream`).
+    ///
+    /// # Arguments
     ///
-    /// let output = gpt2_generator.generate_indices(
-    ///     Some(&[input_context, second_input_context]),
-    ///     Some(generate_options),
-    /// );
-    /// # Ok(())
-    /// # }
-    /// ```
-    fn generate_indices<S>(
+    /// * `prompt_texts` - `Option<Vec<&str>>` Optional vector of text prompts. An empty prompt to the model may be passed if the model implement a `bos_id`.
+    /// * `generate_options` - `Option<GenerateOptions>` Optional set of generate options. If not (or partially) provided, will use the settings provided when creating the generator
+    /// * `callback` - Function called with each `StreamedToken`. The generation stops if the function returns false.
+    ///
+    /// # Returns
+    /// * `Vec<IndicesOutput>` Vector of length *number_of_prompts* x *num_return_sequences* containing IndicesOutput with the generated indices, as returned by `generate_indices`.
+    fn generate_indices_stream<S, F>(
         &self,
         prompt_texts: Option<&[S]>,
         generate_options: Option<GenerateOptions>,
-    ) -> Vec<GeneratedIndicesOutput>
+        callback: F,
+    ) -> Result<Vec<GeneratedIndicesOutput>, RustBertError>
     where
         S: AsRef<str> + Sync,
+        F: FnMut(&StreamedToken) -> bool,
     {
-        let eos_token_ids = self.get_eos_ids();
-
         let config = self.get_config();
-        let max_length = unpack_config!(max_length, generate_options, config);
-        let encoding_max_len = if self.is_encoder_decoder() {
-            self.get_max_positions_embeddings()
-        } else {
-            max_length
-        };
-        let pad_token_id = match self.get_pad_id() {
-            Some(value) => Some(value),
-            None => eos_token_ids.as_ref().map(|eos_ids| eos_ids[0]),
-        };
+        let num_beams = unpack_config!(num_beams, generate_options, config);
+        if num_beams > 1 {
+            return Err(RustBertError::InvalidConfigurationError(format!(
+                "Streaming is only available for greedy decoding and sampling, got num_beams = {}",
+                num_beams
+            )));
+        }
+        let tokenizer = self._get_tokenizer();
+        let eos_token_ids = self.get_eos_ids().cloned().unwrap_or_default();
 
-        let input_ids = match prompt_texts {
-            Some(prompts) if !prompts.is_empty() => {
-                self.encode_prompt_text(prompts, encoding_max_len, pad_token_id)
+        // Generated token ids and text already streamed, for each sequence
+        let sequences: RefCell<Vec<(Vec<i64>, String)>> = RefCell::new(vec![]);
+        let callback = RefCell::new(callback);
+        let token_callback = |step_tokens: &[Option<i64>]| -> bool {
+            let mut sequences = sequences.borrow_mut();
+            if sequences.is_empty() {
+                sequences.resize(step_tokens.len(), (vec![], String::new()));
             }
-            None => match self.get_bos_id() {
-                Some(bos_id) => {
-                    Tensor::ones(&[1, 1], (Int64, self.get_var_store().device())) * bos_id
+            let mut callback = callback.borrow_mut();
+            let mut keep_generating = true;
+            for (sequence_index, token_id) in step_tokens.iter().enumerate() {
+                let token_id = match token_id {
+                    Some(token_id) => *token_id,
+                    None => continue,
+                };
+                let (token_ids, streamed_text) = &mut sequences[sequence_index];
+                let finished = eos_token_ids.contains(&token_id);
+                let mut text = String::new();
+                if !finished {
+                    // The sequence is decoded again with the new token, as tokens may change the decoding of
+                    // the preceding tokens (e.g. byte-level tokens forming a character)
+                    token_ids.push(token_id);
+                    let decoded = tokenizer.decode(token_ids, true, true);
+                    if !decoded.ends_with('\u{FFFD}')
+                        && decoded.len() > streamed_text.len()
+                        && decoded.is_char_boundary(streamed_text.len())
+                    {
+                        text = decoded[streamed_text.len()..].to_string();
+                        *streamed_text = decoded;
+                    }
                 }
-                None => panic!(
-                    "A model with a BOS token must be used to start generation with an empty input"
-                ),
-            },
-            _ => return Vec::new(),
+                keep_generating &= (*callback)(&StreamedToken {
+                    sequence_index,
+                    token_id,
+                    text,
+                    finished,
+                });
+            }
+            keep_generating
         };
-        self.generate_from_ids_and_past(input_ids, None, generate_options)
+
+        let generate_options = GenerateOptions {
+            token_callback: Some(&token_callback),
+            ..generate_options.unwrap_or_default()
+        };
+        Ok(self.generate_indices(prompt_texts, Some(generate_options)))
     }
 
     /// Generate token indices given a list of indices (useful when the input has been pre-tokenized).
@@ -1840,18 +3635,80 @@ pub trait LanguageGenerator<T: LMHeadModel, V: Vocab, U: Tokenizer<V>>:
         let repetition_penalty = unpack_config!(repetition_penalty, generate_options, config);
         let length_penalty = unpack_config!(length_penalty, generate_options, config);
         let no_repeat_ngram_size = unpack_config!(no_repeat_ngram_size, generate_options, config);
+        let no_repeat_ngram_scope = unpack_config!(no_repeat_ngram_scope, generate_options, config);
+        no_repeat_ngram_scope.validate();
         let num_beam_groups = generate_options.map_or(config.num_beam_groups, |opts| {
             opts.num_beam_groups.or(config.num_beam_groups)
         });
         let diversity_penalty = generate_options.map_or(config.diversity_penalty, |opts| {
             opts.diversity_penalty.or(config.diversity_penalty)
         });
+        if let Some(num_beam_groups_value) = num_beam_groups {
+            if num_beam_groups_value > 1 {
+                assert_eq!(
+                    num_beams % num_beam_groups_value,
+                    0,
+                    "num_beams must be a multiple of num_beam_groups"
+                )
+            }
+        }
+        let penalty_alpha = generate_options.map_or(config.penalty_alpha, |opts| {
+            opts.penalty_alpha.or(config.penalty_alpha)
+        });
+        let typical_p =
+            generate_options.map_or(config.typical_p, |opts| opts.typical_p.or(config.typical_p));
+        let epsilon_cutoff = generate_options.map_or(config.epsilon_cutoff, |opts| {
+            opts.epsilon_cutoff.or(config.epsilon_cutoff)
+        });
+        let eta_cutoff = generate_options.map_or(config.eta_cutoff, |opts| {
+            opts.eta_cutoff.or(config.eta_cutoff)
+        });
+        validate_sampling_cutoffs(typical_p, epsilon_cutoff, eta_cutoff);
         let decoder_start_token_id = generate_options.and_then(|opts| opts.decoder_start_token_id);
         let forced_bos_token_id = generate_options.and_then(|opts| opts.forced_bos_token_id);
+        let decoder_prefix_ids = generate_options.and_then(|opts| opts.decoder_prefix_ids);
+        assert!(
+            decoder_prefix_ids.is_none() || self.is_encoder_decoder(),
+            "Decoder prompts are only available for encoder-decoder models"
+        );
+        let decoder_prefix_length = decoder_prefix_ids.map_or(0, |ids| *ids.size().last().unwrap());
+        assert!(
+            !uses_contrastive_search(do_sample, num_beams, top_k, penalty_alpha)
+                || self.exposes_hidden_states(),
+            "Contrastive search (penalty_alpha) is not available for models that do not expose their hidden states"
+        );
         let bad_word_ids = generate_options.and_then(|opts| opts.bad_word_ids);
+        let logit_bias = generate_options
+            .and_then(|opts| opts.logit_bias)
+            .or(Some(&config.logit_bias))
+            .filter(|logit_bias| !logit_bias.is_empty());
+        let watermark = generate_options.and_then(|opts| opts.watermark);
+        let logits_processors = generate_options.and_then(|opts| opts.logits_processors);
         let prefix_allowed_tokens_fn =
             generate_options.and_then(|opts| opts.prefix_allowed_tokens_fn);
+        let token_callback = generate_options.and_then(|opts| opts.token_callback);
+        let stop_sequences = self.get_stop_sequences(generate_options);
         let output_scores = generate_options.map_or(false, |opts| opts.output_scores);
+        let top_logprobs = generate_options
+            .and_then(|opts| opts.top_logprobs)
+            .map_or(0, |top_logprobs| {
+                top_logprobs.clamp(0, self.get_vocab_size())
+            });
+        let token_healing = generate_options.map_or(false, |opts| opts.token_healing);
+        let echo = generate_options.map_or(false, |opts| opts.echo);
+        let output_telemetry = matches!(generate_options, Some(opts) if opts.output_telemetry);
+        let prefix_cache = generate_options.and_then(|opts| opts.prefix_cache);
+        assert!(
+            prefix_cache.is_none() || !self.is_encoder_decoder(),
+            "Prefix caches are only available for decoder-only models"
+        );
+        let item_options = generate_options.and_then(|opts| opts.item_options);
+        if item_options.is_some() {
+            assert_eq!(
+                num_beams, 1,
+                "Per-input generation options are only available for greedy decoding and sampling"
+            );
+        }
 
         let pad_token_id = match self.get_pad_id() {
             Some(value) => Some(value),
@@ -1874,10 +3731,10 @@ pub trait LanguageGenerator<T: LMHeadModel, V: Vocab, U: Tokenizer<V>>:
             input_ids_len += 1;
         }
 
-        let cur_len = if !self.is_encoder_decoder() {
+        let mut cur_len = if !self.is_encoder_decoder() {
             *input_ids.size().last().unwrap()
         } else {
-            1
+            1 + decoder_prefix_length
         };
         let batch_size = *input_ids.size().first().unwrap();
 
@@ -1897,8 +3754,71 @@ pub trait LanguageGenerator<T: LMHeadModel, V: Vocab, U: Tokenizer<V>>:
             },
         };
 
+        // Token healing: the last prompt token is removed and regenerated as the first generated token.
+        // Skipped if any of the prompts would be left empty.
+        let (input_ids, attention_mask, token_healing_ids) = if token_healing
+            && !self.is_encoder_decoder()
+            && (i64::from(attention_mask.sum_dim_intlist(&[1], false, Int64).min()) > 1)
+        {
+            let token_healing_ids = self
+                .get_token_healing_ids(&input_ids)
+                .into_iter()
+                .flat_map(|allowed_tokens| {
+                    std::iter::repeat(allowed_tokens).take(effective_batch_mult as usize)
+                })
+                .collect::<Vec<Vec<i64>>>();
+            cur_len -= 1;
+            (
+                input_ids.slice(1, 0, cur_len, 1),
+                attention_mask.slice(1, 0, cur_len, 1),
+                Some(token_healing_ids),
+            )
+        } else {
+            (input_ids, attention_mask, None)
+        };
+
+        // The prompts are appended to the cached prefix, whose tokens are not processed again by the prefill
+        let (input_ids, attention_mask) = if let Some(prefix_cache) = prefix_cache {
+            let prefix_ids = Tensor::of_slice(&prefix_cache.token_ids)
+                .to(input_ids.device())
+                .unsqueeze(0)
+                .expand(&[batch_size, -1], true);
+            cur_len += prefix_cache.len() as i64;
+            (
+                Tensor::cat(&[&prefix_ids, &input_ids], 1),
+                Tensor::cat(&[&prefix_ids.ones_like(), &attention_mask], 1),
+            )
+        } else {
+            (input_ids, attention_mask)
+        };
+
+        let mut timer = if output_telemetry {
+            Some(GenerationTimer::start())
+        } else {
+            None
+        };
+        let prompt_lengths = if output_telemetry {
+            Some(
+                attention_mask
+                    .sum_dim_intlist(&[1], false, Int64)
+                    .iter::<i64>()
+                    .unwrap()
+                    .collect::<Vec<i64>>(),
+            )
+        } else {
+            None
+        };
+        let generated_tokens_start = if self.is_encoder_decoder() {
+            1 + decoder_prefix_length as usize
+        } else {
+            cur_len as usize
+        };
+
         let encoder_outputs = if self.is_encoder_decoder() {
-            let encoder_outputs = self.encode(&input_ids, Some(&attention_mask)).unwrap();
+            let encoder_outputs = match generate_options.and_then(|opts| opts.encoder_outputs) {
+                Some(encoder_outputs) => encoder_outputs.shallow_clone(),
+                None => self.encode(&input_ids, Some(&attention_mask)).unwrap(),
+            };
             let expanded_batch_indices = Tensor::arange(batch_size, (Int64, input_ids.device()))
                 .view((-1, 1))
                 .repeat(&[1, num_beams as i64 * effective_batch_mult])
@@ -1936,11 +3856,38 @@ pub trait LanguageGenerator<T: LMHeadModel, V: Vocab, U: Tokenizer<V>>:
                 self.get_decoder_start_id()
                     .expect("decoder start id must be specified for encoder decoders")
             });
-            let input_ids = Tensor::full(
+            let decoder_start_ids = Tensor::full(
                 &[effective_batch_size * num_beams as i64, 1],
                 decoder_start_token_id,
                 (Int64, input_ids.device()),
             );
+            let input_ids = match decoder_prefix_ids {
+                Some(decoder_prefix_ids) => {
+                    assert_eq!(
+                        decoder_prefix_ids.size()[0],
+                        batch_size,
+                        "One decoder prompt must be provided for each input"
+                    );
+                    let decoder_prefix_ids = decoder_prefix_ids
+                        .to(input_ids.device())
+                        .unsqueeze(1)
+                        .expand(
+                            &[
+                                batch_size,
+                                effective_batch_mult * num_beams as i64,
+                                decoder_prefix_length,
+                            ],
+                            true,
+                        )
+                        .contiguous()
+                        .view((
+                            effective_batch_size * num_beams as i64,
+                            decoder_prefix_length,
+                        ));
+                    Tensor::cat(&[decoder_start_ids, decoder_prefix_ids], 1)
+                }
+                None => decoder_start_ids,
+            };
             let attention_mask = if (num_return_sequences > 1) | (num_beams > 1) {
                 attention_mask
                     .unsqueeze(1)
@@ -1970,6 +3917,141 @@ pub trait LanguageGenerator<T: LMHeadModel, V: Vocab, U: Tokenizer<V>>:
             config.max_length
         };
 
+        // Per-input overrides, expanded to the sequences generated for each input
+        let (item_max_lengths, item_temperatures, item_stop_sequences) = match item_options {
+            Some(item_options) => {
+                assert_eq!(
+                    item_options.len() as i64,
+                    batch_size,
+                    "One `ItemGenerateOptions` must be provided for each input"
+                );
+                let item_max_lengths = item_options
+                    .iter()
+                    .flat_map(|opts| {
+                        let item_max_length = opts
+                            .max_new_tokens
+                            .map_or(max_length, |max_new_tokens| max_new_tokens + cur_len);
+                        std::iter::repeat(item_max_length).take(effective_batch_mult as usize)
+                    })
+                    .collect::<Vec<i64>>();
+                // The inputs without a temperature are scaled as the rest of the batch
+                let default_temperature = if temperature > 1f64 {
+                    temperature
+                } else {
+                    1f64
+                };
+                let item_temperatures = if item_options
+                    .iter()
+                    .any(|opts| opts.temperature.is_some())
+                {
+                    let temperatures = item_options
+                        .iter()
+                        .flat_map(|opts| {
+                            let temperature = opts.temperature.unwrap_or(default_temperature);
+                            assert!(temperature > 0f64, "temperature must be strictly positive");
+                            std::iter::repeat(temperature).take(effective_batch_mult as usize)
+                        })
+                        .collect::<Vec<f64>>();
+                    Some(
+                        Tensor::of_slice(&temperatures)
+                            .view((-1, 1))
+                            .to(input_ids.device()),
+                    )
+                } else {
+                    None
+                };
+                let item_stop_sequences =
+                    if item_options
+                        .iter()
+                        .any(|opts| opts.stop_sequences.is_some())
+                    {
+                        Some(self.get_item_stop_sequences(
+                            generate_options,
+                            effective_batch_size as usize,
+                        ))
+                    } else {
+                        None
+                    };
+                (
+                    Some(item_max_lengths),
+                    item_temperatures,
+                    item_stop_sequences,
+                )
+            }
+            None => (None, None, None),
+        };
+        // The batch is generated until the longest limit of its inputs is reached
+        let max_length = item_max_lengths
+            .as_ref()
+            .and_then(|item_max_lengths| item_max_lengths.iter().max().copied())
+            .unwrap_or(max_length);
+
+        let grammar_constraint = generate_options
+            .and_then(|opts| opts.grammar)
+            .map(|grammar| {
+                let eos_token_id = eos_token_ids.as_ref().and_then(|ids| ids.first().copied());
+                GrammarConstraint::new(grammar, self._get_tokenizer(), eos_token_id)
+            });
+        let grammar_allowed_tokens_fn = |batch_id: i64, token_ids: &Tensor| -> Vec<i64> {
+            let generated_ids = token_ids
+                .iter::<i64>()
+                .unwrap()
+                .skip(generated_tokens_start)
+                .collect::<Vec<i64>>();
+            let allowed_tokens = grammar_constraint
+                .as_ref()
+                .unwrap()
+                .allowed_tokens(&generated_ids);
+            match prefix_allowed_tokens_fn {
+                Some(prefix_allowed_tokens_function) => {
+                    let user_allowed_tokens = prefix_allowed_tokens_function(batch_id, token_ids);
+                    let intersection = allowed_tokens
+                        .iter()
+                        .filter(|token_id| user_allowed_tokens.contains(token_id))
+                        .copied()
+                        .collect::<Vec<i64>>();
+                    if intersection.is_empty() {
+                        allowed_tokens
+                    } else {
+                        intersection
+                    }
+                }
+                None => allowed_tokens,
+            }
+        };
+        let prefix_allowed_tokens_fn: Option<&dyn Fn(i64, &Tensor) -> Vec<i64>> =
+            match grammar_constraint {
+                Some(_) => Some(&grammar_allowed_tokens_fn),
+                None => prefix_allowed_tokens_fn,
+            };
+
+        let surface_bad_words = generate_options
+            .and_then(|opts| opts.bad_words)
+            .map(|bad_words| SurfaceBadWords::new(bad_words, self._get_tokenizer()));
+        let bad_words_banned_tokens_fn = |token_ids: &Tensor| -> Vec<i64> {
+            let generated_ids = token_ids
+                .iter::<i64>()
+                .unwrap()
+                .skip(generated_tokens_start)
+                .collect::<Vec<i64>>();
+            surface_bad_words
+                .as_ref()
+                .unwrap()
+                .banned_tokens(&generated_ids)
+        };
+        let banned_tokens_fn = surface_bad_words
+            .as_ref()
+            .map(|_| &bad_words_banned_tokens_fn as &dyn Fn(&Tensor) -> Vec<i64>);
+
+        // The decoder start token and prompt form the bidirectional prefix of the decoder, processed by the prefill
+        let decoder_attention_mask = if decoder_prefix_length > 0 {
+            let prefix_lengths = vec![cur_len; *input_ids.size().first().unwrap() as usize];
+            Some(build_prefix_lm_attention_mask(&input_ids.ones_like(), &prefix_lengths).unwrap())
+        } else {
+            None
+        };
+
+        let output_eos_token_ids = eos_token_ids.clone();
         let gen_opt = InternalGenerateOptions {
             min_length,
             max_length,
@@ -1979,6 +4061,8 @@ pub trait LanguageGenerator<T: LMHeadModel, V: Vocab, U: Tokenizer<V>>:
             top_p,
             repetition_penalty,
             no_repeat_ngram_size,
+            no_repeat_ngram_scope,
+            ngram_scope_boundaries: RefCell::new(HashMap::new()),
             pad_token_id,
             eos_token_ids,
             num_return_sequences,
@@ -1987,8 +4071,26 @@ pub trait LanguageGenerator<T: LMHeadModel, V: Vocab, U: Tokenizer<V>>:
             length_penalty,
             num_beam_groups,
             diversity_penalty,
+            penalty_alpha,
+            typical_p,
+            epsilon_cutoff,
+            eta_cutoff,
             forced_bos_token_id,
             bad_word_ids,
+            logit_bias,
+            watermark,
+            logits_processors,
+            banned_tokens_fn,
+            top_logprobs,
+            token_healing_ids,
+            echo: echo && !self.is_encoder_decoder() && prefix_cache.is_none(),
+            token_callback,
+            stop_sequences: stop_sequences.clone(),
+            prefix_cache,
+            decoder_attention_mask,
+            item_max_lengths: item_max_lengths.clone(),
+            item_temperatures,
+            item_stop_sequences: item_stop_sequences.clone(),
         };
 
         let generated_output_with_scores = no_grad(|| {
@@ -2002,6 +4104,7 @@ pub trait LanguageGenerator<T: LMHeadModel, V: Vocab, U: Tokenizer<V>>:
                     gen_opt,
                     prefix_allowed_tokens_fn,
                     output_scores,
+                    timer.as_mut(),
                 )
             } else {
                 self.generate_no_beam_search(
@@ -2013,23 +4116,43 @@ pub trait LanguageGenerator<T: LMHeadModel, V: Vocab, U: Tokenizer<V>>:
                     gen_opt,
                     prefix_allowed_tokens_fn,
                     output_scores,
+                    timer.as_mut(),
                 )
             }
         });
-        let (decoded, scores, mut token_scores) = (
+        let (decoded, scores, mut token_scores, mut generated_token_scores, prompt_token_scores) = (
             generated_output_with_scores.indices,
             generated_output_with_scores.scores,
             generated_output_with_scores.token_scores,
+            generated_output_with_scores.generated_token_scores,
+            generated_output_with_scores.prompt_token_scores,
         );
         let num_sequences = *decoded.size().first().unwrap();
+        let sequences_per_prompt = prompt_token_scores
+            .as_ref()
+            .map(|prompt_scores| num_sequences as usize / prompt_scores.len());
         let mut output = Vec::with_capacity(num_sequences as usize);
         for sequence_index in 0..num_sequences {
-            let indices = decoded
+            let mut indices = decoded
                 .as_ref()
                 .get(sequence_index)
                 .iter::<i64>()
                 .unwrap()
                 .collect::<Vec<i64>>();
+            if let Some(item_max_lengths) = &item_max_lengths {
+                indices.truncate(item_max_lengths[sequence_index as usize] as usize);
+            }
+            let stop_sequences = match &item_stop_sequences {
+                Some(item_stop_sequences) => &item_stop_sequences[sequence_index as usize],
+                None => &stop_sequences,
+            };
+            if !stop_sequences.is_empty() {
+                self.truncate_after_stop_sequence(
+                    &mut indices,
+                    generated_tokens_start,
+                    stop_sequences,
+                );
+            }
             let score = scores
                 .as_ref()
                 .map(|scores_value| scores_value[sequence_index as usize]);
@@ -2038,10 +4161,59 @@ pub trait LanguageGenerator<T: LMHeadModel, V: Vocab, U: Tokenizer<V>>:
                 .as_mut()
                 .map(|token_scores| std::mem::take(&mut token_scores[sequence_index as usize]));
 
+            // The scores of the steps following the end of sequence (or stop sequence) are dropped
+            let generated_token_scores = generated_token_scores.as_mut().map(|token_scores| {
+                let mut token_scores = std::mem::take(&mut token_scores[sequence_index as usize]);
+                let eos_token_ids = output_eos_token_ids.as_deref().unwrap_or(&[]);
+                let generated_length = indices
+                    .iter()
+                    .skip(generated_tokens_start)
+                    .position(|token_id| eos_token_ids.contains(token_id))
+                    .map_or(
+                        indices.len().saturating_sub(generated_tokens_start),
+                        |position| position + 1,
+                    );
+                token_scores.token_logprobs.truncate(generated_length);
+                token_scores.top_logprobs.truncate(generated_length);
+                token_scores
+            });
+
+            let prompt_token_scores = prompt_token_scores.as_ref().map(|prompt_scores| {
+                prompt_scores[sequence_index as usize / sequences_per_prompt.unwrap()].clone()
+            });
+
+            let telemetry =
+                timer
+                    .as_ref()
+                    .zip(prompt_lengths.as_ref())
+                    .map(|(timer, prompt_lengths)| {
+                        let sequences_per_input = num_sequences as usize / prompt_lengths.len();
+                        let eos_token_ids = output_eos_token_ids.as_deref().unwrap_or(&[]);
+                        let mut generated_tokens = 0;
+                        for token_id in indices.iter().skip(generated_tokens_start) {
+                            generated_tokens += 1;
+                            if eos_token_ids.contains(token_id) {
+                                break;
+                            }
+                        }
+                        GenerationTelemetry {
+                            prompt_tokens: prompt_lengths
+                                [sequence_index as usize / sequences_per_input]
+                                as usize,
+                            generated_tokens,
+                            prefill_ms: timer.prefill_ms.unwrap_or(0.0),
+                            decode_ms: timer.decode_ms.clone(),
+                            max_cache_bytes: timer.max_cache_bytes,
+                        }
+                    });
+
             output.push(GeneratedIndicesOutput {
                 indices,
                 score,
                 token_scores,
+                generated_token_scores,
+                prompt_token_scores,
+                telemetry,
             });
         }
         output
@@ -2083,6 +4255,262 @@ pub trait LanguageGenerator<T: LMHeadModel, V: Vocab, U: Tokenizer<V>>:
     /// # Ok(())
     /// # }
     /// ```
+    /// Scores continuations of prompt texts with the language model, returning the log-probability of each
+    /// token of the continuations conditioned on the prompt and the preceding continuation tokens.
+    /// For decoder-only models the continuation is appended to the prompt, for encoder-decoder models the prompt is
+    /// passed to the encoder and the continuation is scored by the decoder.
+    /// Useful to rank candidate answers (e.g. label names) for a prompt.
+    ///
+    /// # Arguments
+    ///
+    /// * `prompts` - Prompt texts
+    /// * `continuations` - Continuations to score, one for each prompt. The continuations are tokenized separately
+    /// from the prompts: a leading space should be included for tokenizers encoding word boundaries (e.g. GPT2).
+    ///
+    /// # Returns
+    /// * `Vec<Vec<f64>>` Log-probabilities of the tokens of each continuation
+    ///
+    /// # Example
+    ///
+    /// ```no_run
+    /// # fn main() -> anyhow::Result<()> {
+    /// use rust_bert::gpt2::GPT2Generator;
+    /// use rust_bert::pipelines::generation_utils::LanguageGenerator;
+    ///
+    /// let gpt2_generator = GPT2Generator::new(Default::default())?;
+    /// let prompt = "The capital of France is";
+    /// let scores = gpt2_generator.score_sequences(&[prompt, prompt], &[" Paris", " Berlin"])?;
+    /// let log_likelihoods = scores
+    ///     .iter()
+    ///     .map(|token_scores| token_scores.iter().sum::<f64>())
+    ///     .collect::<Vec<f64>>();
+    /// # Ok(())
+    /// # }
+    /// ```
+    fn score_sequences<S>(
+        &self,
+        prompts: &[S],
+        continuations: &[S],
+    ) -> Result<Vec<Vec<f64>>, RustBertError>
+    where
+        S: AsRef<str> + Sync,
+    {
+        if prompts.len() != continuations.len() {
+            return Err(RustBertError::ValueError(format!(
+                "Got {} prompts for {} continuations",
+                prompts.len(),
+                continuations.len()
+            )));
+        }
+        if prompts.is_empty() {
+            return Ok(Vec::new());
+        }
+        let tokenizer = self._get_tokenizer();
+        let device = self.get_var_store().device();
+        let pad_token_id = self
+            .get_pad_id()
+            .or_else(|| self.get_eos_ids().map(|eos_ids| eos_ids[0]))
+            .unwrap_or_else(|| tokenizer.get_unk_id());
+
+        let continuation_ids = tokenizer
+            .tokenize_list(continuations)
+            .into_iter()
+            .map(|tokens| tokenizer.convert_tokens_to_ids(&tokens))
+            .collect::<Vec<Vec<i64>>>();
+        if continuation_ids.iter().any(|ids| ids.is_empty()) {
+            return Err(RustBertError::ValueError(
+                "Continuations to score cannot be empty".to_string(),
+            ));
+        }
+
+        // Input and target ids, and position of the first continuation token in the targets for each sequence
+        let (input_ids, attention_mask, encoder_outputs, start_positions) =
+            if self.is_encoder_decoder() {
+                let encoder_input_ids = self.encode_prompt_text(
+                    prompts,
+                    self.get_max_positions_embeddings(),
+                    Some(pad_token_id),
+                    false,
+                );
+                let encoder_attention_mask = encoder_input_ids.ne(pad_token_id).to_kind(Int64);
+                let encoder_outputs =
+                    no_grad(|| self.encode(&encoder_input_ids, Some(&encoder_attention_mask)));
+                let decoder_start_id = self
+                    .get_decoder_start_id()
+                    .or_else(|| self.get_bos_id())
+                    .ok_or_else(|| {
+                        RustBertError::ValueError(
+                            "A decoder start token is required to score sequences".to_string(),
+                        )
+                    })?;
+                let max_len = continuation_ids.iter().map(|ids| ids.len()).max().unwrap();
+                let decoder_input_ids = continuation_ids
+                    .iter()
+                    .map(|ids| {
+                        let mut sequence = vec![decoder_start_id];
+                        sequence.extend(ids);
+                        sequence.extend(vec![pad_token_id; max_len - ids.len()]);
+                        Tensor::of_slice(&sequence)
+                    })
+                    .collect::<Vec<Tensor>>();
+                (
+                    Tensor::stack(&decoder_input_ids, 0).to(device),
+                    encoder_attention_mask,
+                    encoder_outputs,
+                    vec![0; continuation_ids.len()],
+                )
+            } else {
+                let bos_token_id = self.get_bos_id().filter(|_| self.add_bos_token());
+                let sequences = tokenizer
+                    .tokenize_list(prompts)
+                    .into_iter()
+                    .zip(continuation_ids.iter())
+                    .map(|(prompt_tokens, ids)| {
+                        let mut sequence = tokenizer.convert_tokens_to_ids(&prompt_tokens);
+                        if sequence.is_empty() {
+                            return Err(RustBertError::ValueError(
+                            "Prompts cannot be empty to score sequences with a decoder-only model"
+                                .to_string(),
+                        ));
+                        }
+                        if let Some(bos_token_id) = bos_token_id {
+                            sequence.insert(0, bos_token_id);
+                        }
+                        sequence.extend(ids);
+                        Ok(sequence)
+                    })
+                    .collect::<Result<Vec<Vec<i64>>, RustBertError>>()?;
+                let max_len = sequences.iter().map(|ids| ids.len()).max().unwrap();
+                // Sequences are padded on the left, the continuations are at the end of the inputs
+                let (input_ids, attention_mask): (Vec<Tensor>, Vec<Tensor>) = sequences
+                    .iter()
+                    .map(|sequence| {
+                        let padding_length = max_len - sequence.len();
+                        let mut input_ids = vec![pad_token_id; padding_length];
+                        input_ids.extend(sequence);
+                        let mut attention_mask = vec![0i64; padding_length];
+                        attention_mask.extend(vec![1; sequence.len()]);
+                        (
+                            Tensor::of_slice(&input_ids),
+                            Tensor::of_slice(&attention_mask),
+                        )
+                    })
+                    .unzip();
+                let start_positions = continuation_ids
+                    .iter()
+                    .map(|ids| max_len - 1 - ids.len())
+                    .collect::<Vec<usize>>();
+                (
+                    Tensor::stack(&input_ids, 0).to(device),
+                    Tensor::stack(&attention_mask, 0).to(device),
+                    None,
+                    start_positions,
+                )
+            };
+
+        let sequence_length = *input_ids.size().last().unwrap();
+        let lm_logits =
+            no_grad(|| self.prefill(&input_ids, encoder_outputs.as_ref(), &attention_mask))?
+                .lm_logits;
+        // Some models (e.g. XLNet) only return the logits for the position to predict
+        if lm_logits.size()[1] != sequence_length {
+            return Err(RustBertError::InvalidConfigurationError(
+                "Sequence scoring requires a model returning the logits for all positions"
+                    .to_string(),
+            ));
+        }
+
+        let token_scores = lm_logits
+            .slice(1, 0, sequence_length - 1, 1)
+            .log_softmax(-1, tch::Kind::Float)
+            .gather(
+                2,
+                &input_ids.slice(1, 1, sequence_length, 1).unsqueeze(-1),
+                false,
+            )
+            .squeeze_dim(-1);
+        Ok(continuation_ids
+            .iter()
+            .zip(start_positions)
+            .enumerate()
+            .map(|(sequence_index, (ids, start_position))| {
+                token_scores
+                    .get(sequence_index as i64)
+                    .slice(
+                        0,
+                        start_position as i64,
+                        (start_position + ids.len()) as i64,
+                        1,
+                    )
+                    .iter::<f64>()
+                    .unwrap()
+                    .collect::<Vec<f64>>()
+            })
+            .collect())
+    }
+
+    /// Computes the keys and values cache of a fixed prompt prefix (e.g. a long system prompt) to reuse it across
+    /// generation calls, avoiding to process the tokens of the prefix again for every prompt. The cache is passed
+    /// to the generation methods with `GenerateOptions::prefix_cache`: the prompts are then appended to the prefix,
+    /// and the generated sequences start with the prefix. Only available for decoder-only models supporting the
+    /// extension of their cache by several tokens (GPT2, GPT-Neo).
+    ///
+    /// # Arguments
+    ///
+    /// * `prefix` - Prefix text. The prompts are tokenized separately from the prefix: a leading space should be
+    /// included in the prompts for tokenizers encoding word boundaries (e.g. GPT2).
+    ///
+    /// # Returns
+    /// * `PrefixCache` Token ids and cache of the prefix
+    ///
+    /// # Example
+    ///
+    /// ```no_run
+    /// # fn main() -> anyhow::Result<()> {
+    /// use rust_bert::gpt2::GPT2Generator;
+    /// use rust_bert::pipelines::generation_utils::{GenerateOptions, LanguageGenerator};
+    ///
+    /// let gpt2_generator = GPT2Generator::new(Default::default())?;
+    /// let prefix_cache = gpt2_generator.encode_prefix("You are a helpful assistant.")?;
+    /// let generate_options = GenerateOptions {
+    ///     prefix_cache: Some(&prefix_cache),
+    ///     max_new_tokens: Some(32),
+    ///     ..Default::default()
+    /// };
+    /// let first_answer = gpt2_generator.generate(Some(&[" Hello!"]), Some(generate_options));
+    /// let second_answer =
+    ///     gpt2_generator.generate(Some(&[" What is the weather like?"]), Some(generate_options));
+    /// # Ok(())
+    /// # }
+    /// ```
+    fn encode_prefix(&self, prefix: &str) -> Result<PrefixCache, RustBertError> {
+        if self.is_encoder_decoder() || !self.supports_chunked_prefill() {
+            return Err(RustBertError::InvalidConfigurationError(
+                "Prefix caches are only available for decoder-only models able to extend their cache"
+                    .to_string(),
+            ));
+        }
+        let tokenizer = self._get_tokenizer();
+        let mut token_ids = tokenizer.convert_tokens_to_ids(&tokenizer.tokenize(prefix));
+        if token_ids.is_empty() {
+            return Err(RustBertError::ValueError(
+                "The prefix to cache cannot be empty".to_string(),
+            ));
+        }
+        if let Some(bos_token_id) = self.get_bos_id().filter(|_| self.add_bos_token()) {
+            token_ids.insert(0, bos_token_id);
+        }
+        let device = self.get_var_store().device();
+        let input_ids = Tensor::of_slice(&token_ids).to(device).unsqueeze(0);
+        let attention_mask = input_ids.ones_like();
+        let output = no_grad(|| self.prefill(&input_ids, None, &attention_mask))?;
+        Ok(PrefixCache {
+            token_ids,
+            cache: output.cache,
+            hidden_states: output.hidden_states,
+        })
+    }
+
     fn get_tokenizer(&self) -> &TokenizerOption {
         self._get_tokenizer()
     }
@@ -2106,7 +4534,7 @@ struct BeamHypotheses {
     length_penalty: f64,
     early_stopping: bool,
     num_beams: i64,
-    beams: Vec<(f64, Tensor, Option<Tensor>)>,
+    beams: Vec<(f64, Tensor, Option<Tensor>, Option<GeneratedTokenScores>)>,
     worst_score: f64,
 }
 
@@ -2120,16 +4548,17 @@ impl Clone for BeamHypotheses {
             beams: self
                 .beams
                 .iter()
-                .map(|(score, tensor, scores_tensor)| {
+                .map(|(score, tensor, scores_tensor, token_logprobs)| {
                     (
                         *score,
                         tensor.copy(),
                         scores_tensor
                             .as_ref()
                             .map(|scores_tensor| scores_tensor.copy()),
+                        token_logprobs.clone(),
                     )
                 })
-                .collect::<Vec<(f64, Tensor, Option<Tensor>)>>(),
+                .collect::<Vec<(f64, Tensor, Option<Tensor>, Option<GeneratedTokenScores>)>>(),
             worst_score: self.worst_score,
         }
     }
@@ -2161,6 +4590,7 @@ impl BeamHypotheses {
         hypothesis: Tensor,
         sum_log_probabilities: f64,
         token_scores: Option<Tensor>,
+        token_logprobs: Option<(Vec<f64>, Vec<Vec<(i64, f64)>>)>,
     ) {
         let score =
             sum_log_probabilities / ((hypothesis.size()[0] as f64).powf(self.length_penalty));
@@ -2176,20 +4606,27 @@ impl BeamHypotheses {
                     None,
                 )
             });
-            self.beams.push((score, hypothesis, token_scores));
+            let token_logprobs =
+                token_logprobs.map(|(token_logprobs, top_logprobs)| GeneratedTokenScores {
+                    token_logprobs,
+                    top_logprobs,
+                    sequence_score: score,
+                });
+            self.beams
+                .push((score, hypothesis, token_scores, token_logprobs));
             if self.len() > self.num_beams {
                 let (worst_score_position, _) = self
                     .beams
                     .iter()
                     .enumerate()
-                    .min_by_key(|(_, (score, _, _))| OrderedFloat(*score))
+                    .min_by_key(|(_, (score, _, _, _))| OrderedFloat(*score))
                     .unwrap();
                 let _ = self.beams.remove(worst_score_position);
             }
             self.worst_score = self
                 .beams
                 .iter()
-                .min_by_key(|(score, _, _)| OrderedFloat(*score))
+                .min_by_key(|(score, _, _, _)| OrderedFloat(*score))
                 .unwrap()
                 .0;
         }
@@ -2292,6 +4729,37 @@ pub trait LMHeadModel {
         decoder_input_ids: Option<&Tensor>,
         train: bool,
     ) -> Result<LMModelOutput, RustBertError>;
+
+    /// Forward pass through an encoder-decoder model with a decoder attention mask replacing the causal mask of the
+    /// decoder (e.g. a prefix-LM mask built with `attention_mask::build_prefix_lm_attention_mask`). Implemented by
+    /// the T5 and BART-based models, the other models return an error.
+    ///
+    /// # Arguments
+    ///
+    /// * `decoder_input_ids` - Decoder input tensor of shape (*batch size*, *target_sequence_length*), following the positions stored in the cache
+    /// * `cache` - Cache of the decoder (`Cache::None` if no position was processed yet)
+    /// * `attention_mask` - Optional encoder attention mask of shape (*batch size*, *source_sequence_length*)
+    /// * `encoder_outputs` - Encoder hidden states of shape (*batch size*, *source_sequence_length*, *hidden_size*)
+    /// * `decoder_attention_mask` - Decoder attention mask of shape (*batch size*, *sequence_length*, *sequence_length*) covering the cached positions and the decoder inputs (only the rows of the decoder inputs are used)
+    /// * `train` - boolean flag to turn on/off the dropout layers in the model. Should be set to false for inference.
+    ///
+    /// # Returns
+    ///
+    /// * `LMModelOutput` with the logits for the positions of the decoder inputs and the updated cache
+    fn forward_with_decoder_attention_mask(
+        &self,
+        _decoder_input_ids: &Tensor,
+        _cache: Cache,
+        _attention_mask: Option<&Tensor>,
+        _encoder_outputs: &Tensor,
+        _decoder_attention_mask: &Tensor,
+        _train: bool,
+    ) -> Result<LMModelOutput, RustBertError> {
+        Err(RustBertError::InvalidConfigurationError(
+            "Decoder attention masks are only supported by the T5 and BART-based models"
+                .to_string(),
+        ))
+    }
 }
 
 /// Container holding a language model output for generation tasks
@@ -2300,4 +4768,7 @@ pub struct LMModelOutput {
     pub lm_logits: Tensor,
     /// cached state for improved efficiency during decoding
     pub cache: Cache,
+    /// Hidden states of the last decoder layer for each position (shape (*batch size*, *sequence_length*, *hidden_size*)),
+    /// used by contrastive search. `None` if the model does not expose them.
+    pub hidden_states: Option<Tensor>,
 }
@@ -0,0 +1,1009 @@
+// Copyright 2022 Guillaume Becquin
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//     http://www.apache.org/licenses/LICENSE-2.0
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! # Grammar-constrained decoding
+//! Constrains the text generated by a language model to a context-free grammar, e.g. to extract structured
+//! information as JSON following a schema. At each generation step, the logits of the tokens that would make the
+//! generated text invalid are masked: the generated text is always a prefix of a sentence of the grammar, and the
+//! end of sequence token is only allowed once the sentence is complete.
+//!
+//! Grammars are written in an EBNF notation, the start rule being named `root`:
+//! - `name ::= expression` defines a rule (rules may span several lines and be referenced before being defined)
+//! - `"text"` matches a literal (with the `\"`, `\\`, `\n`, `\r`, `\t`, `\xHH` and `\uHHHH` escapes)
+//! - `[a-z_]` matches a character of a class, `[^"\\]` a character outside of a class, `.` any character
+//! - `( expression )` groups, `|` separates alternatives
+//! - `*`, `+` and `?` repeat the preceding item zero or more, one or more and zero or one times
+//! - `#` starts a comment
+//!
+//! Grammars can also be created from a JSON schema (`Grammar::from_json_schema`), supporting the `type`, `properties`,
+//! `required`, `items`, `enum`, `const`, `anyOf` and `oneOf` keywords. The object properties are generated with the
+//! required properties first, in alphabetical order. The generated JSON is compact (at most one space between
+//! tokens).
+//!
+//! The constraint is applied by setting `GenerateOptions::grammar`:
+//!
+//! ```no_run
+//! # fn main() -> anyhow::Result<()> {
+//! use rust_bert::gpt2::GPT2Generator;
+//! use rust_bert::pipelines::generation_utils::{GenerateOptions, LanguageGenerator};
+//! use rust_bert::pipelines::grammar::Grammar;
+//! use serde_json::json;
+//!
+//! let model = GPT2Generator::new(Default::default())?;
+//! let grammar = Grammar::from_json_schema(&json!({
+//!     "type": "object",
+//!     "properties": {
+//!         "name": {"type": "string"},
+//!         "age": {"type": "integer"}
+//!     },
+//!     "required": ["name", "age"]
+//! }))?;
+//!
+//! let generate_options = GenerateOptions {
+//!     grammar: Some(&grammar),
+//!     max_new_tokens: Some(32),
+//!     ..Default::default()
+//! };
+//! let output = model.generate(
+//!     Some(&["John is 32 years old. As JSON:"]),
+//!     Some(generate_options),
+//! );
+//! # Ok(())
+//! # }
+//! ```
+//!
+//! The tokens are matched against the grammar using their decoded text. Tokens holding part of a multi-byte
+//! character (byte-level BPE vocabularies) are never allowed, the characters being generated with complete tokens.
+
+use crate::common::error::RustBertError;
+use crate::pipelines::common::TokenizerOption;
+use serde_json::Value;
+use std::cell::RefCell;
+use std::collections::{HashMap, HashSet};
+
+/// Set of characters matched by a terminal symbol
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct CharClass {
+    ranges: Vec<(char, char)>,
+    negated: bool,
+}
+
+impl CharClass {
+    fn single(c: char) -> CharClass {
+        CharClass {
+            ranges: vec![(c, c)],
+            negated: false,
+        }
+    }
+
+    fn matches(&self, c: char) -> bool {
+        self.ranges
+            .iter()
+            .any(|(start, end)| (*start <= c) && (c <= *end))
+            != self.negated
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum Symbol {
+    Terminal(CharClass),
+    NonTerminal(usize),
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct Production {
+    lhs: usize,
+    rhs: Vec<Symbol>,
+}
+
+/// # Context-free grammar
+/// Created from an EBNF definition (`Grammar::new`) or a JSON schema (`Grammar::from_json_schema`).
+#[derive(Debug, Clone)]
+pub struct Grammar {
+    productions: Vec<Production>,
+    productions_by_lhs: Vec<Vec<usize>>,
+    nullable: Vec<bool>,
+    root: usize,
+}
+
+/// Rule names and productions collected while parsing an EBNF definition
+#[derive(Default)]
+struct GrammarBuilder {
+    names: Vec<String>,
+    ids: HashMap<String, usize>,
+    defined: Vec<bool>,
+    productions: Vec<Production>,
+}
+
+impl GrammarBuilder {
+    fn rule_id(&mut self, name: &str) -> usize {
+        if let Some(id) = self.ids.get(name) {
+            return *id;
+        }
+        let id = self.names.len();
+        self.names.push(name.to_string());
+        self.ids.insert(name.to_string(), id);
+        self.defined.push(false);
+        id
+    }
+
+    /// Creates a new anonymous rule, named after the rule it is defined in
+    fn anonymous_rule(&mut self, parent: usize) -> usize {
+        let name = format!("{}#{}", self.names[parent], self.names.len());
+        self.rule_id(&name)
+    }
+
+    fn define(&mut self, lhs: usize, alternatives: Vec<Vec<Symbol>>) {
+        self.defined[lhs] = true;
+        for rhs in alternatives {
+            self.productions.push(Production { lhs, rhs });
+        }
+    }
+
+    fn build(self) -> Result<Grammar, RustBertError> {
+        if let Some(undefined) = self
+            .defined
+            .iter()
+            .position(|defined| !defined)
+            .map(|id| &self.names[id])
+        {
+            return Err(RustBertError::InvalidConfigurationError(format!(
+                "Undefined grammar rule {}",
+                undefined
+            )));
+        }
+        let root = *self.ids.get("root").ok_or_else(|| {
+            RustBertError::InvalidConfigurationError(
+                "The grammar must define a root rule".to_string(),
+            )
+        })?;
+
+        let mut productions_by_lhs = vec![vec![]; self.names.len()];
+        for (index, production) in self.productions.iter().enumerate() {
+            productions_by_lhs[production.lhs].push(index);
+        }
+        let mut nullable = vec![false; self.names.len()];
+        let mut changed = true;
+        while changed {
+            changed = false;
+            for production in &self.productions {
+                if !nullable[production.lhs]
+                    && production.rhs.iter().all(|symbol| match symbol {
+                        Symbol::NonTerminal(id) => nullable[*id],
+                        Symbol::Terminal(_) => false,
+                    })
+                {
+                    nullable[production.lhs] = true;
+                    changed = true;
+                }
+            }
+        }
+        Ok(Grammar {
+            productions: self.productions,
+            productions_by_lhs,
+            nullable,
+            root,
+        })
+    }
+}
+
+fn grammar_error(message: &str, position: usize) -> RustBertError {
+    RustBertError::InvalidConfigurationError(format!(
+        "Invalid grammar at character {}: {}",
+        position, message
+    ))
+}
+
+/// Recursive descent parser of the EBNF notation
+struct EbnfParser {
+    chars: Vec<char>,
+    position: usize,
+    builder: GrammarBuilder,
+}
+
+impl EbnfParser {
+    fn peek(&self) -> Option<char> {
+        self.chars.get(self.position).copied()
+    }
+
+    fn next(&mut self) -> Result<char, RustBertError> {
+        let c = self
+            .peek()
+            .ok_or_else(|| grammar_error("unexpected end of grammar", self.position))?;
+        self.position += 1;
+        Ok(c)
+    }
+
+    fn expect(&mut self, expected: char) -> Result<(), RustBertError> {
+        match self.next()? {
+            c if c == expected => Ok(()),
+            c => Err(grammar_error(
+                &format!("expected `{}`, found `{}`", expected, c),
+                self.position - 1,
+            )),
+        }
+    }
+
+    fn skip_whitespace(&mut self) {
+        while let Some(c) = self.peek() {
+            if c == '#' {
+                while !matches!(self.peek(), None | Some('\n')) {
+                    self.position += 1;
+                }
+            } else if c.is_whitespace() {
+                self.position += 1;
+            } else {
+                break;
+            }
+        }
+    }
+
+    fn parse_name(&mut self) -> Option<String> {
+        let start = self.position;
+        while matches!(self.peek(), Some(c) if c.is_ascii_alphanumeric() || c == '_' || c == '-') {
+            self.position += 1;
+        }
+        if self.position > start {
+            Some(self.chars[start..self.position].iter().collect())
+        } else {
+            None
+        }
+    }
+
+    /// Checks if the next characters start a new rule (`name ::=`), without consuming them
+    fn at_rule_start(&mut self) -> bool {
+        let start = self.position;
+        let is_rule_start = self.parse_name().is_some() && {
+            self.skip_whitespace();
+            self.chars[self.position..].starts_with(&[':', ':', '='])
+        };
+        self.position = start;
+        is_rule_start
+    }
+
+    fn parse_rules(&mut self) -> Result<(), RustBertError> {
+        loop {
+            self.skip_whitespace();
+            if self.peek().is_none() {
+                return Ok(());
+            }
+            let name = self
+                .parse_name()
+                .ok_or_else(|| grammar_error("expected a rule name", self.position))?;
+            self.skip_whitespace();
+            for c in "::=".chars() {
+                self.expect(c)?;
+            }
+            let lhs = self.builder.rule_id(&name);
+            let alternatives = self.parse_alternatives(lhs)?;
+            self.builder.define(lhs, alternatives);
+        }
+    }
+
+    fn parse_alternatives(&mut self, rule: usize) -> Result<Vec<Vec<Symbol>>, RustBertError> {
+        let mut alternatives = vec![self.parse_sequence(rule)?];
+        while self.peek() == Some('|') {
+            self.position += 1;
+            alternatives.push(self.parse_sequence(rule)?);
+        }
+        Ok(alternatives)
+    }
+
+    fn parse_sequence(&mut self, rule: usize) -> Result<Vec<Symbol>, RustBertError> {
+        let mut sequence = vec![];
+        loop {
+            self.skip_whitespace();
+            if matches!(self.peek(), None | Some('|') | Some(')')) || self.at_rule_start() {
+                return Ok(sequence);
+            }
+            let item = self.parse_item(rule)?;
+            let item = match self.peek() {
+                Some(operator @ ('*' | '+' | '?')) => {
+                    self.position += 1;
+                    vec![self.repeat(rule, item, operator)]
+                }
+                _ => item,
+            };
+            sequence.extend(item);
+        }
+    }
+
+    /// Creates an anonymous rule repeating an item. Repetitions are left-recursive, parsed in linear time.
+    fn repeat(&mut self, rule: usize, item: Vec<Symbol>, operator: char) -> Symbol {
+        let id = self.builder.anonymous_rule(rule);
+        let recursion = || {
+            let mut recursion = vec![Symbol::NonTerminal(id)];
+            recursion.extend(item.iter().cloned());
+            recursion
+        };
+        let alternatives = match operator {
+            '*' => vec![vec![], recursion()],
+            '+' => vec![item.clone(), recursion()],
+            _ => vec![vec![], item.clone()],
+        };
+        self.builder.define(id, alternatives);
+        Symbol::NonTerminal(id)
+    }
+
+    fn parse_item(&mut self, rule: usize) -> Result<Vec<Symbol>, RustBertError> {
+        match self.peek() {
+            Some('"') => {
+                self.position += 1;
+                let mut literal = vec![];
+                loop {
+                    match self.next()? {
+                        '"' => break,
+                        '\\' => {
+                            literal.push(Symbol::Terminal(CharClass::single(self.parse_escape()?)))
+                        }
+                        c => literal.push(Symbol::Terminal(CharClass::single(c))),
+                    }
+                }
+                Ok(literal)
+            }
+            Some('[') => {
+                self.position += 1;
+                Ok(vec![Symbol::Terminal(self.parse_char_class()?)])
+            }
+            Some('.') => {
+                self.position += 1;
+                Ok(vec![Symbol::Terminal(CharClass {
+                    ranges: vec![],
+                    negated: true,
+                })])
+            }
+            Some('(') => {
+                self.position += 1;
+                let alternatives = self.parse_alternatives(rule)?;
+                self.skip_whitespace();
+                self.expect(')')?;
+                let id = self.builder.anonymous_rule(rule);
+                self.builder.define(id, alternatives);
+                Ok(vec![Symbol::NonTerminal(id)])
+            }
+            _ => match self.parse_name() {
+                Some(name) => Ok(vec![Symbol::NonTerminal(self.builder.rule_id(&name))]),
+                None => Err(grammar_error("unexpected character", self.position)),
+            },
+        }
+    }
+
+    fn parse_hex(&mut self, digits: usize) -> Result<char, RustBertError> {
+        let start = self.position;
+        let mut value = 0;
+        for _ in 0..digits {
+            value = value * 16
+                + self
+                    .next()?
+                    .to_digit(16)
+                    .ok_or_else(|| grammar_error("invalid hexadecimal escape", start))?;
+        }
+        char::from_u32(value).ok_or_else(|| grammar_error("invalid character escape", start))
+    }
+
+    fn parse_escape(&mut self) -> Result<char, RustBertError> {
+        Ok(match self.next()? {
+            'n' => '\n',
+            'r' => '\r',
+            't' => '\t',
+            'x' => self.parse_hex(2)?,
+            'u' => self.parse_hex(4)?,
+            c => c,
+        })
+    }
+
+    fn parse_class_char(&mut self) -> Result<char, RustBertError> {
+        match self.next()? {
+            '\\' => self.parse_escape(),
+            c => Ok(c),
+        }
+    }
+
+    fn parse_char_class(&mut self) -> Result<CharClass, RustBertError> {
+        let negated = self.peek() == Some('^');
+        if negated {
+            self.position += 1;
+        }
+        let mut ranges = vec![];
+        while self.peek() != Some(']') {
+            let start = self.parse_class_char()?;
+            let end = if (self.peek() == Some('-'))
+                && (self.chars.get(self.position + 1) != Some(&']'))
+            {
+                self.position += 1;
+                self.parse_class_char()?
+            } else {
+                start
+            };
+            ranges.push((start, end));
+        }
+        self.position += 1;
+        Ok(CharClass { ranges, negated })
+    }
+}
+
+/// Escapes a text as an EBNF literal
+fn literal(text: &str) -> String {
+    format!("\"{}\"", text.replace('\\', "\\\\").replace('"', "\\\""))
+}
+
+const JSON_RULES: &str = r#"
+ws ::= " "?
+value ::= object | array | string | number | boolean | null
+object ::= "{" ws ( string ws ":" ws value ws ( "," ws string ws ":" ws value ws )* )? "}"
+array ::= "[" ws ( value ws ( "," ws value ws )* )? "]"
+string ::= "\"" ( [^"\\\x00-\x1f] | "\\" ( ["\\/bfnrt] | "u" [0-9a-fA-F] [0-9a-fA-F] [0-9a-fA-F] [0-9a-fA-F] ) )* "\""
+integer ::= "-"? ( "0" | [1-9] [0-9]* )
+number ::= integer ( "." [0-9]+ )? ( [eE] [+-]? [0-9]+ )?
+boolean ::= "true" | "false"
+null ::= "null"
+"#;
+
+/// Returns the EBNF expression matching the values of a JSON schema
+fn schema_expression(schema: &Value) -> Result<String, RustBertError> {
+    let schema = match schema {
+        Value::Bool(true) => return Ok("value".to_string()),
+        Value::Object(schema) => schema,
+        _ => {
+            return Err(RustBertError::InvalidConfigurationError(format!(
+                "Unsupported JSON schema {}",
+                schema
+            )))
+        }
+    };
+    if schema.contains_key("$ref") {
+        return Err(RustBertError::InvalidConfigurationError(
+            "JSON schema references ($ref) are not supported".to_string(),
+        ));
+    }
+    if let Some(value) = schema.get("const") {
+        return Ok(literal(&value.to_string()));
+    }
+    if let Some(values) = schema.get("enum").and_then(Value::as_array) {
+        let alternatives = values
+            .iter()
+            .map(|value| literal(&value.to_string()))
+            .collect::<Vec<String>>();
+        return Ok(format!("( {} )", alternatives.join(" | ")));
+    }
+    if let Some(schemas) = schema
+        .get("anyOf")
+        .or_else(|| schema.get("oneOf"))
+        .and_then(Value::as_array)
+    {
+        let alternatives = schemas
+            .iter()
+            .map(schema_expression)
+            .collect::<Result<Vec<String>, RustBertError>>()?;
+        return Ok(format!("( {} )", alternatives.join(" | ")));
+    }
+    match schema.get("type") {
+        None => Ok("value".to_string()),
+        Some(Value::String(schema_type)) => type_expression(schema_type, schema),
+        Some(Value::Array(schema_types)) => {
+            let alternatives = schema_types
+                .iter()
+                .map(|schema_type| match schema_type.as_str() {
+                    Some(schema_type) => type_expression(schema_type, schema),
+                    None => Err(RustBertError::InvalidConfigurationError(format!(
+                        "Invalid JSON schema type {}",
+                        schema_type
+                    ))),
+                })
+                .collect::<Result<Vec<String>, RustBertError>>()?;
+            Ok(format!("( {} )", alternatives.join(" | ")))
+        }
+        Some(schema_type) => Err(RustBertError::InvalidConfigurationError(format!(
+            "Invalid JSON schema type {}",
+            schema_type
+        ))),
+    }
+}
+
+fn type_expression(
+    schema_type: &str,
+    schema: &serde_json::Map<String, Value>,
+) -> Result<String, RustBertError> {
+    Ok(match schema_type {
+        "string" | "integer" | "number" | "boolean" | "null" => schema_type.to_string(),
+        "array" => match schema.get("items") {
+            Some(items) => {
+                let item = schema_expression(items)?;
+                format!(
+                    "\"[\" ws ( {item} ws ( \",\" ws {item} ws )* )? \"]\"",
+                    item = item
+                )
+            }
+            None => "array".to_string(),
+        },
+        "object" => match schema.get("properties").and_then(Value::as_object) {
+            Some(properties) if !properties.is_empty() => object_expression(properties, schema)?,
+            _ => "object".to_string(),
+        },
+        _ => {
+            return Err(RustBertError::InvalidConfigurationError(format!(
+                "Unsupported JSON schema type {}",
+                schema_type
+            )))
+        }
+    })
+}
+
+fn object_expression(
+    properties: &serde_json::Map<String, Value>,
+    schema: &serde_json::Map<String, Value>,
+) -> Result<String, RustBertError> {
+    let required = schema
+        .get("required")
+        .and_then(Value::as_array)
+        .map(|required| required.iter().filter_map(Value::as_str).collect())
+        .unwrap_or_else(HashSet::new);
+    let mut names = properties.keys().collect::<Vec<&String>>();
+    names.sort();
+    let mut required_pairs = vec![];
+    let mut optional_pairs = vec![];
+    for name in names {
+        let property = &properties[name];
+        let pair = format!(
+            "{} ws \":\" ws {}",
+            literal(&Value::String(name.clone()).to_string()),
+            schema_expression(property)?
+        );
+        if required.contains(name.as_str()) {
+            required_pairs.push(pair);
+        } else {
+            optional_pairs.push(pair);
+        }
+    }
+
+    let optional_tail = |pairs: &[String]| {
+        pairs
+            .iter()
+            .map(|pair| format!(" ( ws \",\" ws {} )?", pair))
+            .collect::<String>()
+    };
+    let body = if required_pairs.is_empty() {
+        // Any of the optional properties can be the first one
+        let alternatives = (0..optional_pairs.len())
+            .map(|first| {
+                format!(
+                    "{}{}",
+                    optional_pairs[first],
+                    optional_tail(&optional_pairs[first + 1..])
+                )
+            })
+            .collect::<Vec<String>>();
+        format!("( {} )?", alternatives.join(" | "))
+    } else {
+        format!(
+            "{}{}",
+            required_pairs.join(" ws \",\" ws "),
+            optional_tail(&optional_pairs)
+        )
+    };
+    Ok(format!("\"{{\" ws {} ws \"}}\"", body))
+}
+
+impl Grammar {
+    /// Creates a new `Grammar` from its EBNF definition (see the module documentation for the notation).
+    /// The start rule is `root`.
+    ///
+    /// # Arguments
+    ///
+    /// * `definition` - EBNF definition of the grammar
+    ///
+    /// # Example
+    ///
+    /// ```no_run
+    /// # fn main() -> anyhow::Result<()> {
+    /// use rust_bert::pipelines::grammar::Grammar;
+    ///
+    /// let grammar = Grammar::new(
+    ///     r#"
+    ///     root ::= " " answer "."
+    ///     answer ::= "yes" | "no"
+    ///     "#,
+    /// )?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn new(definition: &str) -> Result<Grammar, RustBertError> {
+        let mut parser = EbnfParser {
+            chars: definition.chars().collect(),
+            position: 0,
+            builder: GrammarBuilder::default(),
+        };
+        parser.parse_rules()?;
+        parser.builder.build()
+    }
+
+    /// Creates a new `Grammar` matching any JSON value
+    pub fn json() -> Grammar {
+        Grammar::new(&format!("root ::= ws value\n{}", JSON_RULES)).unwrap()
+    }
+
+    /// Creates a new `Grammar` matching the JSON values valid for a JSON schema
+    ///
+    /// # Arguments
+    ///
+    /// * `schema` - JSON schema
+    pub fn from_json_schema(schema: &Value) -> Result<Grammar, RustBertError> {
+        Grammar::new(&format!(
+            "root ::= ws {}\n{}",
+            schema_expression(schema)?,
+            JSON_RULES
+        ))
+    }
+
+    /// Checks if a text is a complete sentence of the grammar
+    pub fn matches(&self, text: &str) -> bool {
+        let mut parser = EarleyParser::new(self);
+        text.chars().all(|c| parser.push(c)) && parser.is_complete()
+    }
+
+    /// Checks if a text can be completed into a sentence of the grammar
+    pub fn is_valid_prefix(&self, text: &str) -> bool {
+        let mut parser = EarleyParser::new(self);
+        text.chars().all(|c| parser.push(c))
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+struct EarleyItem {
+    production: usize,
+    dot: usize,
+    origin: usize,
+}
+
+/// Incremental Earley recognizer over the characters of a text. Characters can be removed (`pop`) to explore
+/// alternative continuations.
+struct EarleyParser<'a> {
+    grammar: &'a Grammar,
+    charts: Vec<Vec<EarleyItem>>,
+}
+
+impl<'a> EarleyParser<'a> {
+    fn new(grammar: &'a Grammar) -> EarleyParser<'a> {
+        let mut parser = EarleyParser {
+            grammar,
+            charts: vec![],
+        };
+        let start_items = grammar.productions_by_lhs[grammar.root]
+            .iter()
+            .map(|production| EarleyItem {
+                production: *production,
+                dot: 0,
+                origin: 0,
+            })
+            .collect();
+        let chart = parser.closure(start_items);
+        parser.charts.push(chart);
+        parser
+    }
+
+    /// Completes a chart with the predicted and completed items
+    fn closure(&self, items: Vec<EarleyItem>) -> Vec<EarleyItem> {
+        let position = self.charts.len();
+        let mut seen = items.iter().copied().collect::<HashSet<EarleyItem>>();
+        let mut chart = items;
+        let mut index = 0;
+        while index < chart.len() {
+            let item = chart[index];
+            index += 1;
+            let production = &self.grammar.productions[item.production];
+            let mut new_items = vec![];
+            match production.rhs.get(item.dot) {
+                Some(Symbol::NonTerminal(rule)) => {
+                    for predicted in &self.grammar.productions_by_lhs[*rule] {
+                        new_items.push(EarleyItem {
+                            production: *predicted,
+                            dot: 0,
+                            origin: position,
+                        });
+                    }
+                    if self.grammar.nullable[*rule] {
+                        new_items.push(EarleyItem {
+                            dot: item.dot + 1,
+                            ..item
+                        });
+                    }
+                }
+                Some(Symbol::Terminal(_)) => {}
+                // Empty completions (origin at the current position) are handled by the nullable prediction
+                None if item.origin < position => {
+                    for waiting in &self.charts[item.origin] {
+                        let waiting_production = &self.grammar.productions[waiting.production];
+                        if waiting_production.rhs.get(waiting.dot)
+                            == Some(&Symbol::NonTerminal(production.lhs))
+                        {
+                            new_items.push(EarleyItem {
+                                dot: waiting.dot + 1,
+                                ..*waiting
+                            });
+                        }
+                    }
+                }
+                None => {}
+            }
+            for new_item in new_items {
+                if seen.insert(new_item) {
+                    chart.push(new_item);
+                }
+            }
+        }
+        chart
+    }
+
+    /// Adds a character to the parsed text. Returns `false`, leaving the parser unchanged, if the text can no
+    /// longer be completed into a sentence of the grammar.
+    fn push(&mut self, c: char) -> bool {
+        let scanned = self
+            .charts
+            .last()
+            .unwrap()
+            .iter()
+            .filter(|item| {
+                matches!(
+                    self.grammar.productions[item.production].rhs.get(item.dot),
+                    Some(Symbol::Terminal(class)) if class.matches(c)
+                )
+            })
+            .map(|item| EarleyItem {
+                dot: item.dot + 1,
+                ..*item
+            })
+            .collect::<Vec<EarleyItem>>();
+        if scanned.is_empty() {
+            return false;
+        }
+        let chart = self.closure(scanned);
+        self.charts.push(chart);
+        true
+    }
+
+    fn pop(&mut self) {
+        if self.charts.len() > 1 {
+            self.charts.pop();
+        }
+    }
+
+    fn is_complete(&self) -> bool {
+        self.charts.last().unwrap().iter().any(|item| {
+            let production = &self.grammar.productions[item.production];
+            (item.origin == 0)
+                && (production.lhs == self.grammar.root)
+                && (item.dot == production.rhs.len())
+        })
+    }
+}
+
+/// Trie of the text of the vocabulary tokens
+#[derive(Default)]
+struct TokenTrie {
+    children: Vec<(char, TokenTrie)>,
+    token_ids: Vec<i64>,
+}
+
+impl TokenTrie {
+    fn new<'a>(tokens: impl Iterator<Item = (i64, &'a str)>) -> TokenTrie {
+        let mut root = TokenTrie::default();
+        for (token_id, text) in tokens {
+            let mut node = &mut root;
+            for c in text.chars() {
+                let index = match node.children.iter().position(|(child, _)| *child == c) {
+                    Some(index) => index,
+                    None => {
+                        node.children.push((c, TokenTrie::default()));
+                        node.children.len() - 1
+                    }
+                };
+                node = &mut node.children[index].1;
+            }
+            node.token_ids.push(token_id);
+        }
+        root
+    }
+
+    /// Collects the tokens keeping the parsed text valid. The branches of the trie are pruned as soon as a
+    /// character is rejected by the parser.
+    fn collect_allowed(&self, parser: &mut EarleyParser, allowed: &mut Vec<i64>) {
+        for (c, child) in &self.children {
+            if parser.push(*c) {
+                allowed.extend(&child.token_ids);
+                child.collect_allowed(parser, allowed);
+                parser.pop();
+            }
+        }
+    }
+}
+
+/// Returns the decoded text of the tokens of a vocabulary, as generated after other tokens (i.e. with the leading
+/// space of sentencepiece tokens). The special tokens and tokens holding part of a character are excluded.
+pub(crate) fn token_texts(tokenizer: &TokenizerOption) -> HashMap<i64, String> {
+    tokenizer
+        .get_vocab_indices()
+        .iter()
+        .filter(|(_, token)| !(token.starts_with('<') && token.ends_with('>')))
+        .map(|(token_id, token)| {
+            let text = tokenizer.decode(&[*token_id], false, false);
+            // Sentencepiece tokenizers remove the leading space of the decoded text
+            let text = if token.starts_with('▁') && !text.starts_with(' ') {
+                format!(" {}", text)
+            } else {
+                text
+            };
+            (*token_id, text)
+        })
+        .filter(|(_, text)| !text.is_empty() && !text.contains('\u{FFFD}'))
+        .collect()
+}
+
+/// # Grammar constraint for the generation
+/// Computes the tokens of a vocabulary that keep a generated text valid for a grammar. Created by the generation
+/// when `GenerateOptions::grammar` is set.
+pub struct GrammarConstraint<'a> {
+    grammar: &'a Grammar,
+    token_texts: HashMap<i64, String>,
+    vocabulary: TokenTrie,
+    eos_token_id: Option<i64>,
+    cache: RefCell<HashMap<String, Vec<i64>>>,
+}
+
+impl<'a> GrammarConstraint<'a> {
+    /// Creates a new `GrammarConstraint` for the vocabulary of a tokenizer. The special tokens and tokens holding
+    /// part of a character are excluded.
+    ///
+    /// # Arguments
+    ///
+    /// * `grammar` - `Grammar` the generated text must follow
+    /// * `tokenizer` - `TokenizerOption` of the generation model
+    /// * `eos_token_id` - Optional end of sequence token, allowed once the generated text is a complete sentence
+    pub fn new(
+        grammar: &'a Grammar,
+        tokenizer: &TokenizerOption,
+        eos_token_id: Option<i64>,
+    ) -> GrammarConstraint<'a> {
+        GrammarConstraint::from_tokens(grammar, token_texts(tokenizer), eos_token_id)
+    }
+
+    fn from_tokens(
+        grammar: &'a Grammar,
+        token_texts: HashMap<i64, String>,
+        eos_token_id: Option<i64>,
+    ) -> GrammarConstraint<'a> {
+        let vocabulary = TokenTrie::new(
+            token_texts
+                .iter()
+                .map(|(token_id, text)| (*token_id, text.as_str())),
+        );
+        GrammarConstraint {
+            grammar,
+            token_texts,
+            vocabulary,
+            eos_token_id,
+            cache: RefCell::new(HashMap::new()),
+        }
+    }
+
+    /// Returns the tokens allowed after the generated tokens. The generated text is the concatenation of the text
+    /// of the tokens (excluding special tokens). The end of sequence token is allowed if the text is a complete
+    /// sentence, or if no other token is allowed (to avoid masking all tokens).
+    ///
+    /// # Arguments
+    ///
+    /// * `generated_ids` - Tokens generated so far (excluding the prompt)
+    pub fn allowed_tokens(&self, generated_ids: &[i64]) -> Vec<i64> {
+        let generated_text = generated_ids
+            .iter()
+            .filter_map(|token_id| self.token_texts.get(token_id))
+            .map(String::as_str)
+            .collect::<String>();
+        if let Some(allowed) = self.cache.borrow().get(&generated_text) {
+            return allowed.clone();
+        }
+        let mut parser = EarleyParser::new(self.grammar);
+        let mut allowed = vec![];
+        let is_valid = generated_text.chars().all(|c| parser.push(c));
+        if is_valid {
+            self.vocabulary.collect_allowed(&mut parser, &mut allowed);
+            allowed.sort_unstable();
+        }
+        if let Some(eos_token_id) = self.eos_token_id {
+            if allowed.is_empty() || (is_valid && parser.is_complete()) {
+                allowed.push(eos_token_id);
+            }
+        }
+        self.cache
+            .borrow_mut()
+            .insert(generated_text, allowed.clone());
+        allowed
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_ebnf_grammar() {
+        let grammar = Grammar::new(
+            r#"
+            # Comma-separated list of lowercase words, repeated items
+            root ::= word ( ", " word )* "."?
+            word ::= [a-z]+ | "\"" [^"]* "\""
+            "#,
+        )
+        .unwrap();
+        assert!(grammar.matches("one"));
+        assert!(grammar.matches("one, two, \"Three 3\"."));
+        assert!(!grammar.matches("one,"));
+        assert!(grammar.is_valid_prefix("one,"));
+        assert!(!grammar.is_valid_prefix("One"));
+        assert!(!grammar.matches("one, two.."));
+
+        assert!(Grammar::new("root ::= undefined").is_err());
+        assert!(Grammar::new("rule ::= \"a\"").is_err());
+        assert!(Grammar::new("root ::= ( \"a\"").is_err());
+    }
+
+    #[test]
+    fn test_json_schema_grammar() {
+        let grammar = Grammar::from_json_schema(&json!({
+            "type": "object",
+            "properties": {
+                "name": {"type": "string"},
+                "age": {"type": "integer"},
+                "tags": {"type": "array", "items": {"enum": ["a", "b"]}},
+                "nickname": {"type": ["string", "null"]}
+            },
+            "required": ["name", "age"]
+        }))
+        .unwrap();
+        assert!(grammar.matches(r#"{"age": 32, "name": "John"}"#));
+        assert!(grammar.matches(r#" {"age":32,"name":"J\"o","nickname":null,"tags":["a", "b"]}"#));
+        assert!(!grammar.matches(r#"{"name": "John", "age": 32}"#));
+        assert!(!grammar.matches(r#"{"age": 3.5, "name": "John"}"#));
+        assert!(!grammar.matches(r#"{"age": 32, "name": "John", "tags": ["c"]}"#));
+        assert!(grammar.is_valid_prefix(r#"{"age": 3"#));
+
+        let optional = Grammar::from_json_schema(&json!({
+            "type": "object",
+            "properties": {"a": {"type": "boolean"}, "b": {"const": 1}}
+        }))
+        .unwrap();
+        assert!(optional.matches("{}"));
+        assert!(optional.matches(r#"{"b": 1}"#));
+        assert!(optional.matches(r#"{"a": true, "b": 1}"#));
+        assert!(!optional.matches(r#"{"a": true,}"#));
+
+        assert!(Grammar::json().matches(r#"[1, -2.5e3, {"a": [true, null]}, "é"]"#));
+        assert!(Grammar::from_json_schema(&json!({"$ref": "#/definitions/a"})).is_err());
+    }
+
+    #[test]
+    fn test_allowed_tokens() {
+        let grammar = Grammar::new(r#"root ::= " " ( "yes" | "no" ) "."?"#).unwrap();
+        let tokens = vec![" yes", " y", "es", " no", ".", " maybe", "no"]
+            .into_iter()
+            .enumerate()
+            .map(|(token_id, text)| (token_id as i64, text.to_string()))
+            .collect();
+        let constraint = GrammarConstraint::from_tokens(&grammar, tokens, Some(99));
+        assert_eq!(constraint.allowed_tokens(&[]), vec![0, 1, 3]);
+        assert_eq!(constraint.allowed_tokens(&[1]), vec![2]);
+        assert_eq!(constraint.allowed_tokens(&[1, 2]), vec![4, 99]);
+        assert_eq!(constraint.allowed_tokens(&[0, 4]), vec![99]);
+        // Special tokens are ignored, invalid texts only allow the end of sequence
+        assert_eq!(constraint.allowed_tokens(&[99, 0]), vec![4, 99]);
+        assert_eq!(constraint.allowed_tokens(&[5]), vec![99]);
+    }
+}
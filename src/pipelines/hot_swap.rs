@@ -0,0 +1,202 @@
+// Copyright 2022 Guillaume Becquin
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//     http://www.apache.org/licenses/LICENSE-2.0
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! # Hot swapping of pipelines
+//! `HotSwapPipeline` wraps a pipeline used by a long-running service and allows replacing it (e.g. with a model
+//! trained on more recent data, or to switch between two versions for A/B testing) without restarting the process.
+//!
+//! The replacement pipeline is fully loaded before being swapped in: requests keep being served by the current
+//! pipeline while the new weights are loaded, and no request observes a partially loaded model. Requests started
+//! before a swap complete with the pipeline they started with. The previous pipeline (and its weights) is freed
+//! once the last of these requests completes. Note that both models are held in memory during the loading of the
+//! replacement.
+//!
+//! ```no_run
+//! # fn main() -> anyhow::Result<()> {
+//! use rust_bert::pipelines::hot_swap::HotSwapPipeline;
+//! use rust_bert::pipelines::sentiment::{SentimentConfig, SentimentModel};
+//!
+//! let pipeline = HotSwapPipeline::new(SentimentModel::new(Default::default())?);
+//!
+//! // Serve requests
+//! let output = pipeline.current().predict(&["This is a great movie!"]);
+//!
+//! // Load an updated model in the background and swap it in once ready
+//! let reload = pipeline.reload_in_background(|| {
+//!     let updated_config: SentimentConfig = Default::default();
+//!     SentimentModel::new(updated_config)
+//! });
+//! let output = pipeline.current().predict(&["Still served during the reload"]);
+//! reload.join().unwrap()?;
+//! # Ok(())
+//! # }
+//! ```
+
+use crate::common::error::RustBertError;
+use crate::pipelines::common::Pipeline;
+use std::sync::{Arc, RwLock};
+use std::thread::JoinHandle;
+
+/// # Pipeline that can be replaced while serving requests
+/// Cloning a `HotSwapPipeline` returns a new handle to the same swappable pipeline: a swap performed through any
+/// handle is visible to all of them.
+pub struct HotSwapPipeline<P> {
+    pipeline: Arc<RwLock<Arc<P>>>,
+}
+
+impl<P> Clone for HotSwapPipeline<P> {
+    fn clone(&self) -> Self {
+        HotSwapPipeline {
+            pipeline: self.pipeline.clone(),
+        }
+    }
+}
+
+impl<P> HotSwapPipeline<P> {
+    /// Create a new `HotSwapPipeline` serving the pipeline provided
+    ///
+    /// # Arguments
+    ///
+    /// * `pipeline` - Initial pipeline
+    pub fn new(pipeline: P) -> HotSwapPipeline<P> {
+        HotSwapPipeline {
+            pipeline: Arc::new(RwLock::new(Arc::new(pipeline))),
+        }
+    }
+
+    /// Returns the pipeline currently served. The pipeline returned remains valid (and is not freed) after a swap,
+    /// so that a request can complete with the pipeline it started with.
+    pub fn current(&self) -> Arc<P> {
+        // The lock only guards the replacement of the pointer and cannot be left in an inconsistent state
+        self.pipeline
+            .read()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+            .clone()
+    }
+
+    /// Atomically replaces the pipeline served
+    ///
+    /// # Arguments
+    ///
+    /// * `pipeline` - New pipeline, or a pipeline previously returned by `swap` or `current` (e.g. to switch back
+    /// to a previous version)
+    ///
+    /// # Returns
+    ///
+    /// * `Arc<P>` Pipeline previously served. It is freed when this value and all in-flight requests using it are dropped.
+    pub fn swap(&self, pipeline: impl Into<Arc<P>>) -> Arc<P> {
+        let mut current = self
+            .pipeline
+            .write()
+            .unwrap_or_else(|poisoned| poisoned.into_inner());
+        std::mem::replace(&mut *current, pipeline.into())
+    }
+
+    /// Builds a new pipeline and swaps it in once fully loaded. The current pipeline keeps serving
+    /// requests during the loading, and is left in place if the loading fails.
+    ///
+    /// # Arguments
+    ///
+    /// * `builder` - Function building the new pipeline (e.g. `|| SentimentModel::new(config)`)
+    pub fn reload<F>(&self, builder: F) -> Result<(), RustBertError>
+    where
+        F: FnOnce() -> Result<P, RustBertError>,
+    {
+        let pipeline = builder()?;
+        self.swap(pipeline);
+        Ok(())
+    }
+}
+
+impl<P> HotSwapPipeline<P>
+where
+    P: Send + Sync + 'static,
+{
+    /// Builds a new pipeline in a background thread and swaps it in once fully loaded.
+    ///
+    /// # Arguments
+    ///
+    /// * `builder` - Function building the new pipeline (e.g. `move || SentimentModel::new(config)`)
+    ///
+    /// # Returns
+    ///
+    /// * `JoinHandle` of the background thread, returning the result of the loading
+    pub fn reload_in_background<F>(&self, builder: F) -> JoinHandle<Result<(), RustBertError>>
+    where
+        F: FnOnce() -> Result<P, RustBertError> + Send + 'static,
+    {
+        let handle = self.clone();
+        std::thread::spawn(move || handle.reload(builder))
+    }
+}
+
+impl<Input, Output, P> Pipeline<Input, Output> for HotSwapPipeline<P>
+where
+    P: Pipeline<Input, Output>,
+{
+    type Options = P::Options;
+
+    fn run_with_options(
+        &self,
+        inputs: &[Input],
+        options: &Self::Options,
+    ) -> Result<Vec<Output>, RustBertError> {
+        self.current().run_with_options(inputs, options)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    struct ConstantPipeline(&'static str);
+
+    impl Pipeline<&str, String> for ConstantPipeline {
+        type Options = ();
+
+        fn run_with_options(
+            &self,
+            inputs: &[&str],
+            _options: &(),
+        ) -> Result<Vec<String>, RustBertError> {
+            Ok(inputs.iter().map(|_| self.0.to_string()).collect())
+        }
+    }
+
+    #[test]
+    fn test_swap() {
+        let pipeline = HotSwapPipeline::new(ConstantPipeline("A"));
+        let in_flight = pipeline.current();
+
+        let previous = pipeline.swap(ConstantPipeline("B"));
+        assert_eq!(pipeline.run(&["input"]).unwrap(), ["B"]);
+        assert_eq!(in_flight.run(&["input"]).unwrap(), ["A"]);
+
+        pipeline.swap(previous);
+        assert_eq!(pipeline.run(&["input"]).unwrap(), ["A"]);
+    }
+
+    #[test]
+    fn test_reload() {
+        let pipeline = HotSwapPipeline::new(ConstantPipeline("A"));
+
+        let failed = pipeline.reload(|| Err(RustBertError::ValueError("failed".to_string())));
+        assert!(failed.is_err());
+        assert_eq!(pipeline.run(&["input"]).unwrap(), ["A"]);
+
+        pipeline
+            .reload_in_background(|| Ok(ConstantPipeline("B")))
+            .join()
+            .unwrap()
+            .unwrap();
+        assert_eq!(pipeline.run(&["input"]).unwrap(), ["B"]);
+    }
+}
@@ -0,0 +1,318 @@
+// Copyright 2022 Guillaume Becquin
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//     http://www.apache.org/licenses/LICENSE-2.0
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! # Image classification pipeline
+//! Classifies decoded images with a Vision Transformer (ViT) model. The images are provided as buffers of 8-bit
+//! pixels (grayscale, RGB or RGBA) of any size, resized to the model image size and normalized before the forward pass.
+//! The labels are read from the `id2label` mapping of the model configuration.
+//! The ViT base model fine-tuned on ImageNet-1k (1000 classes) is available as a preset
+//! (`ImageClassificationConfig::vit_base_patch16_224`): its configuration is downloaded from the original repository and
+//! its weights are converted locally from the PyTorch checkpoint (`python utils/convert_model.py path/to/pytorch_model.bin`).
+//!
+//! The decoding of image files is left to the caller (e.g. with the `image` crate):
+//!
+//! ```no_run
+//! # fn main() -> anyhow::Result<()> {
+//! use rust_bert::pipelines::image_classification::{
+//!     ImageClassificationConfig, ImageClassificationModel,
+//! };
+//! use rust_bert::resources::LocalResource;
+//! use rust_bert::vit::ImageBuffer;
+//! use std::path::PathBuf;
+//!
+//! let model = ImageClassificationModel::new(ImageClassificationConfig::vit_base_patch16_224(
+//!     LocalResource::from(PathBuf::from("path/to/rust_model.ot")),
+//! ))?;
+//!
+//! // Decoded 640x480 RGB image
+//! let pixels = vec![127u8; 640 * 480 * 3];
+//! let predictions = model.predict_top_k(&[ImageBuffer::rgb(&pixels, 640, 480)], 5)?;
+//! for label in &predictions[0] {
+//!     println!("{}: {:.3}", label.text, label.score);
+//! }
+//! # Ok(())
+//! # }
+//! ```
+
+use crate::common::error::RustBertError;
+use crate::pipelines::common::Pipeline;
+use crate::pipelines::sequence_classification::Label;
+use crate::resources::ResourceProvider;
+use crate::vit::{
+    ImageBuffer, ViTConfig, ViTForImageClassification, ViTImageProcessor, VIT_IMAGE_MEAN,
+    VIT_IMAGE_STD,
+};
+use crate::Config;
+use std::collections::HashMap;
+use tch::nn::VarStore;
+use tch::{no_grad, Device, Kind, Tensor};
+
+#[cfg(feature = "remote")]
+use crate::{resources::RemoteResource, vit::ViTConfigResources};
+
+/// # Configuration for ImageClassificationModel
+/// Contains information regarding the model to load, the image normalization and the device to place the model on.
+pub struct ImageClassificationConfig {
+    /// Model weights resource
+    pub model_resource: Box<dyn ResourceProvider + Send>,
+    /// Config resource
+    pub config_resource: Box<dyn ResourceProvider + Send>,
+    /// Per-channel (RGB) mean of the pixel values rescaled to [0, 1]
+    pub image_mean: [f64; 3],
+    /// Per-channel (RGB) standard deviation of the pixel values rescaled to [0, 1]
+    pub image_std: [f64; 3],
+    /// Device to place the model on (default: CUDA/GPU when available)
+    pub device: Device,
+}
+
+impl ImageClassificationConfig {
+    /// Instantiate a new image classification configuration, normalizing the images as the original ViT checkpoints
+    ///
+    /// # Arguments
+    ///
+    /// * `model_resource` - The `ResourceProvider` pointing to the model weights to load (e.g. model.ot)
+    /// * `config_resource` - The `ResourceProvider` pointing to the model configuration to load (e.g. config.json)
+    pub fn new<R>(model_resource: R, config_resource: R) -> ImageClassificationConfig
+    where
+        R: ResourceProvider + Send + 'static,
+    {
+        ImageClassificationConfig {
+            model_resource: Box::new(model_resource),
+            config_resource: Box::new(config_resource),
+            image_mean: VIT_IMAGE_MEAN,
+            image_std: VIT_IMAGE_STD,
+            device: Device::cuda_if_available(),
+        }
+    }
+
+    /// Sets the image normalization (`image_mean` and `image_std` of the checkpoint preprocessor configuration)
+    pub fn with_normalization(mut self, image_mean: [f64; 3], image_std: [f64; 3]) -> Self {
+        self.image_mean = image_mean;
+        self.image_std = image_std;
+        self
+    }
+}
+
+#[cfg(feature = "remote")]
+impl ImageClassificationConfig {
+    /// Instantiate the configuration of the ViT base model (patch size 16, 224x224 images) fine-tuned on ImageNet-1k.
+    /// The configuration is downloaded from the original repository of the model, the weights are provided as a
+    /// resource converted locally.
+    ///
+    /// # Arguments
+    ///
+    /// * `model_resource` - The `ResourceProvider` pointing to the converted weights of the model (e.g. rust_model.ot)
+    pub fn vit_base_patch16_224<R>(model_resource: R) -> ImageClassificationConfig
+    where
+        R: ResourceProvider + Send + 'static,
+    {
+        ImageClassificationConfig {
+            model_resource: Box::new(model_resource),
+            config_resource: Box::new(RemoteResource::from_pretrained(
+                ViTConfigResources::VIT_BASE_PATCH16_224,
+            )),
+            image_mean: VIT_IMAGE_MEAN,
+            image_std: VIT_IMAGE_STD,
+            device: Device::cuda_if_available(),
+        }
+    }
+}
+
+/// # ImageClassificationModel to classify images
+pub struct ImageClassificationModel {
+    model: ViTForImageClassification,
+    image_processor: ViTImageProcessor,
+    label_mapping: HashMap<i64, String>,
+    batch_size: usize,
+    var_store: VarStore,
+}
+
+impl ImageClassificationModel {
+    /// Build a new `ImageClassificationModel`
+    ///
+    /// # Arguments
+    ///
+    /// * `config` - `ImageClassificationConfig` object containing the resource references (model, config) and device placement (CPU/GPU)
+    ///
+    /// # Example
+    ///
+    /// ```no_run
+    /// # fn main() -> anyhow::Result<()> {
+    /// use rust_bert::pipelines::image_classification::{
+    ///     ImageClassificationConfig, ImageClassificationModel,
+    /// };
+    /// use rust_bert::resources::LocalResource;
+    /// use std::path::PathBuf;
+    ///
+    /// let model = ImageClassificationModel::new(ImageClassificationConfig::vit_base_patch16_224(
+    ///     LocalResource::from(PathBuf::from("path/to/rust_model.ot")),
+    /// ))?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn new(
+        config: ImageClassificationConfig,
+    ) -> Result<ImageClassificationModel, RustBertError> {
+        let config_path = config.config_resource.get_local_path()?;
+        let model_config = ViTConfig::from_file(config_path);
+        let label_mapping = model_config.id2label.clone().ok_or_else(|| {
+            RustBertError::InvalidConfigurationError(
+                "id2label must be provided for image classification models".to_string(),
+            )
+        })?;
+        if model_config.num_channels != 3 {
+            return Err(RustBertError::InvalidConfigurationError(format!(
+                "Image classification models must take RGB images, got {} channels",
+                model_config.num_channels
+            )));
+        }
+
+        let mut var_store = VarStore::new(config.device);
+        let model = ViTForImageClassification::new(&var_store.root(), &model_config);
+        crate::resources::load_weights(&config.model_resource, &mut var_store)?;
+        let image_processor = ViTImageProcessor::new(
+            model_config.image_size,
+            config.image_mean,
+            config.image_std,
+            config.device,
+        );
+
+        Ok(ImageClassificationModel {
+            model,
+            image_processor,
+            label_mapping,
+            batch_size: 32,
+            var_store,
+        })
+    }
+
+    /// Sets the number of images classified at once
+    ///
+    /// # Arguments
+    ///
+    /// * `batch_size` - Maximum number of images in a batch (default: 32)
+    pub fn set_batch_size(&mut self, batch_size: usize) {
+        self.batch_size = batch_size.max(1);
+    }
+
+    /// Returns the mapping of the label ids to their names
+    pub fn get_label_mapping(&self) -> &HashMap<i64, String> {
+        &self.label_mapping
+    }
+
+    /// Computes the probabilities of the labels, of shape (*number of images*, *number of labels*)
+    fn predict_probabilities(&self, images: &[ImageBuffer]) -> Result<Tensor, RustBertError> {
+        let mut probabilities = Vec::with_capacity(images.len() / self.batch_size + 1);
+        for batch in images.chunks(self.batch_size) {
+            let pixel_values = self.image_processor.preprocess(batch)?;
+            let logits = no_grad(|| self.model.forward_t(&pixel_values, false, false))?.logits;
+            probabilities.push(logits.softmax(-1, Kind::Float).to(Device::Cpu));
+        }
+        Ok(Tensor::cat(&probabilities, 0))
+    }
+
+    /// Classifies images, returning the `k` most likely labels of each image
+    ///
+    /// # Arguments
+    ///
+    /// * `images` - Slice of decoded images
+    /// * `k` - Number of labels returned for each image
+    ///
+    /// # Returns
+    ///
+    /// * `Vec<Vec<Label>>` containing the `k` labels with the highest score for each image, the most likely first.
+    /// The `sentence` field of the labels holds the index of the image.
+    pub fn predict_top_k(
+        &self,
+        images: &[ImageBuffer],
+        k: usize,
+    ) -> Result<Vec<Vec<Label>>, RustBertError> {
+        if images.is_empty() {
+            return Ok(vec![]);
+        }
+        let probabilities = self.predict_probabilities(images)?;
+        let k = (k as i64).min(probabilities.size()[1]);
+        let (scores, label_indices) = probabilities.topk(k, -1, true, true);
+
+        let mut output = Vec::with_capacity(images.len());
+        for image_index in 0..images.len() {
+            let scores = Vec::<f64>::from(scores.get(image_index as i64));
+            let label_indices = Vec::<i64>::from(label_indices.get(image_index as i64));
+            output.push(
+                label_indices
+                    .into_iter()
+                    .zip(scores.into_iter())
+                    .map(|(id, score)| Label {
+                        text: self.label_mapping.get(&id).cloned().unwrap_or_default(),
+                        score,
+                        id,
+                        sentence: image_index,
+                    })
+                    .collect::<Vec<Label>>(),
+            );
+        }
+        Ok(output)
+    }
+
+    /// Classifies images, returning the most likely label of each image
+    ///
+    /// # Arguments
+    ///
+    /// * `images` - Slice of decoded images
+    ///
+    /// # Returns
+    ///
+    /// * `Vec<Label>` containing the label with the highest score for each image
+    ///
+    /// # Example
+    ///
+    /// ```no_run
+    /// # fn main() -> anyhow::Result<()> {
+    /// use rust_bert::pipelines::image_classification::{
+    ///     ImageClassificationConfig, ImageClassificationModel,
+    /// };
+    /// use rust_bert::resources::LocalResource;
+    /// use rust_bert::vit::ImageBuffer;
+    /// use std::path::PathBuf;
+    ///
+    /// let model = ImageClassificationModel::new(ImageClassificationConfig::vit_base_patch16_224(
+    ///     LocalResource::from(PathBuf::from("path/to/rust_model.ot")),
+    /// ))?;
+    /// let pixels = vec![0u8; 32 * 32 * 3];
+    /// let labels = model.predict(&[ImageBuffer::rgb(&pixels, 32, 32)])?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn predict(&self, images: &[ImageBuffer]) -> Result<Vec<Label>, RustBertError> {
+        Ok(self
+            .predict_top_k(images, 1)?
+            .into_iter()
+            .filter_map(|labels| labels.into_iter().next())
+            .collect())
+    }
+
+    /// Returns the device on which the model is placed
+    pub fn device(&self) -> Device {
+        self.var_store.device()
+    }
+}
+
+impl<'a> Pipeline<ImageBuffer<'a>, Label> for ImageClassificationModel {
+    type Options = ();
+
+    fn run_with_options(
+        &self,
+        inputs: &[ImageBuffer<'a>],
+        _options: &(),
+    ) -> Result<Vec<Label>, RustBertError> {
+        self.predict(inputs)
+    }
+}
@@ -0,0 +1,243 @@
+// Copyright 2022 Guillaume Becquin
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//     http://www.apache.org/licenses/LICENSE-2.0
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! # Input length policies
+//! Controls how the pipelines handle the inputs longer than the maximum length of their model:
+//! - `Truncate`: the inputs are truncated to the maximum length of the model, the following tokens are ignored
+//! - `Error`: the batches containing an input longer than the maximum length are rejected with a
+//! `RustBertError::InputTooLongError` reporting the number of tokens of the input and the limit of the model
+//! - `Chunk`: the inputs are split in chunks of at most the maximum length of the model, processed separately and
+//! aggregated (averaged logits for the sequence classification, averaged embeddings for the sentence embeddings,
+//! concatenated labels for the token classification)
+//!
+//! The policy is set per pipeline with `set_input_length_policy`, and applied by the `Pipeline::run` entry point of
+//! the sequence classification and token classification pipelines and by `SentenceEmbeddingsModel::encode`. The
+//! sequence classification and sentence embeddings pipelines truncate the inputs by default, while the token
+//! classification pipeline splits them in chunks.
+//!
+//! ```no_run
+//! # fn main() -> anyhow::Result<()> {
+//! use rust_bert::pipelines::common::Pipeline;
+//! use rust_bert::pipelines::input_length::InputLengthPolicy;
+//! use rust_bert::pipelines::sequence_classification::SequenceClassificationModel;
+//! use rust_bert::RustBertError;
+//!
+//! let mut model = SequenceClassificationModel::new(Default::default())?;
+//! model.set_input_length_policy(InputLengthPolicy::Error);
+//! let long_review = "This movie is great! ".repeat(1000);
+//! match model.run(&[long_review.as_str()]) {
+//!     Err(RustBertError::InputTooLongError(message)) => println!("{}", message),
+//!     output => println!("{:?}", output?),
+//! }
+//! # Ok(())
+//! # }
+//! ```
+
+use crate::common::error::RustBertError;
+use crate::pipelines::common::TokenizerOption;
+use rust_tokenizers::{TokenIdsWithOffsets, TokenizedInput};
+use std::ops::Range;
+use tch::Tensor;
+
+/// # Handling of the inputs longer than the maximum length of the model
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum InputLengthPolicy {
+    /// Truncate the inputs to the maximum length of the model
+    #[default]
+    Truncate,
+    /// Reject the inputs longer than the maximum length of the model with a `RustBertError::InputTooLongError`
+    Error,
+    /// Split the inputs in chunks of at most the maximum length of the model and aggregate their outputs
+    Chunk,
+}
+
+/// Checks that an input fits in the maximum length of the model
+///
+/// # Arguments
+///
+/// * `input_index` - Position of the input in the batch
+/// * `num_tokens` - Number of tokens of the input (including the special tokens)
+/// * `max_length` - Maximum length of the model
+pub(crate) fn check_input_length(
+    input_index: usize,
+    num_tokens: usize,
+    max_length: usize,
+) -> Result<(), RustBertError> {
+    if num_tokens > max_length {
+        Err(RustBertError::InputTooLongError(format!(
+            "input {} has {} tokens, exceeding the maximum length of {} tokens of the model. Shorten the input, or \
+            set the input length policy of the pipeline to `InputLengthPolicy::Truncate` or \
+            `InputLengthPolicy::Chunk`",
+            input_index, num_tokens, max_length
+        )))
+    } else {
+        Ok(())
+    }
+}
+
+/// Checks that the encoded inputs were not truncated, the total number of tokens of an input being the number of
+/// tokens kept after truncation and of truncated tokens
+///
+/// # Arguments
+///
+/// * `tokenized_input` - Inputs encoded with a maximum length of `max_length`
+/// * `max_length` - Maximum length of the model
+pub(crate) fn check_input_lengths(
+    tokenized_input: &[TokenizedInput],
+    max_length: usize,
+) -> Result<(), RustBertError> {
+    for (input_index, input) in tokenized_input.iter().enumerate() {
+        check_input_length(
+            input_index,
+            input.token_ids.len() + input.num_truncated_tokens,
+            max_length,
+        )?;
+    }
+    Ok(())
+}
+
+/// Returns the token ranges of the successive chunks of an input, each chunk holding at most `chunk_length`
+/// tokens. An empty input is made of a single empty chunk.
+fn chunk_ranges(num_tokens: usize, chunk_length: usize) -> Vec<Range<usize>> {
+    if num_tokens == 0 {
+        return vec![0..0];
+    }
+    (0..num_tokens)
+        .step_by(chunk_length)
+        .map(|start| start..(start + chunk_length).min(num_tokens))
+        .collect()
+}
+
+/// Encodes a text as chunks of at most `max_length` tokens (including the special tokens added to each chunk)
+///
+/// # Arguments
+///
+/// * `tokenizer` - `TokenizerOption` of the model
+/// * `text` - Input text
+/// * `max_length` - Maximum length of the model
+fn encode_chunks(
+    tokenizer: &TokenizerOption,
+    text: &str,
+    max_length: usize,
+) -> Vec<TokenizedInput> {
+    let tokens = tokenizer.tokenize_with_offsets(text);
+    let token_ids = tokenizer.convert_tokens_to_ids(&tokens.tokens);
+    let sequence_added_tokens = tokenizer
+        .build_input_with_special_tokens(
+            TokenIdsWithOffsets {
+                ids: vec![],
+                offsets: vec![],
+                reference_offsets: vec![],
+                masks: vec![],
+            },
+            None,
+        )
+        .token_ids
+        .len();
+    let chunk_length = max_length.saturating_sub(sequence_added_tokens).max(1);
+
+    chunk_ranges(token_ids.len(), chunk_length)
+        .into_iter()
+        .map(|range| {
+            tokenizer.build_input_with_special_tokens(
+                TokenIdsWithOffsets {
+                    ids: token_ids[range.clone()].to_vec(),
+                    offsets: tokens.offsets[range.clone()].to_vec(),
+                    reference_offsets: tokens.reference_offsets[range.clone()].to_vec(),
+                    masks: tokens.masks[range].to_vec(),
+                },
+                None,
+            )
+        })
+        .collect()
+}
+
+/// Returns the chunks of the inputs along with the number of chunks of each input
+///
+/// # Arguments
+///
+/// * `tokenizer` - `TokenizerOption` of the model
+/// * `inputs` - Input texts
+/// * `max_length` - Maximum length of the model
+pub(crate) fn encode_chunks_list<S>(
+    tokenizer: &TokenizerOption,
+    inputs: &[S],
+    max_length: usize,
+) -> (Vec<TokenizedInput>, Vec<usize>)
+where
+    S: AsRef<str>,
+{
+    let mut chunks = Vec::new();
+    let mut num_chunks = Vec::with_capacity(inputs.len());
+    for input in inputs {
+        let input_chunks = encode_chunks(tokenizer, input.as_ref(), max_length);
+        num_chunks.push(input_chunks.len());
+        chunks.extend(input_chunks);
+    }
+    (chunks, num_chunks)
+}
+
+/// Averages the outputs of the chunks of each input. The chunks of an input are consecutive rows of `values`.
+///
+/// # Arguments
+///
+/// * `values` - Outputs of the chunks, of shape (*number of chunks*, *dimension*)
+/// * `num_chunks` - Number of chunks of each input
+pub(crate) fn mean_over_chunks(values: &Tensor, num_chunks: &[usize]) -> Tensor {
+    let mut start = 0;
+    let means = num_chunks
+        .iter()
+        .map(|num_chunks| {
+            let mean =
+                values
+                    .narrow(0, start, *num_chunks as i64)
+                    .mean_dim(&[0], false, values.kind());
+            start += *num_chunks as i64;
+            mean
+        })
+        .collect::<Vec<Tensor>>();
+    Tensor::stack(&means, 0)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_check_input_length() {
+        assert!(check_input_length(0, 512, 512).is_ok());
+        match check_input_length(3, 600, 512) {
+            Err(RustBertError::InputTooLongError(message)) => {
+                assert!(message.starts_with(
+                    "input 3 has 600 tokens, exceeding the maximum length of 512 tokens of the model"
+                ));
+            }
+            _ => panic!("Expected an InputTooLongError"),
+        }
+    }
+
+    #[test]
+    fn test_chunk_ranges() {
+        assert_eq!(chunk_ranges(0, 4), vec![0..0]);
+        assert_eq!(chunk_ranges(4, 4), vec![0..4]);
+        assert_eq!(chunk_ranges(10, 4), vec![0..4, 4..8, 8..10]);
+    }
+
+    #[test]
+    fn test_mean_over_chunks() {
+        let values = Tensor::of_slice(&[1.0f32, 2.0, 3.0, 4.0, 5.0, 6.0]).view((3, 2));
+        let means = mean_over_chunks(&values, &[2, 1]);
+        assert_eq!(
+            Vec::<Vec<f32>>::from(means),
+            vec![vec![2.0, 3.0], vec![5.0, 6.0]]
+        );
+    }
+}
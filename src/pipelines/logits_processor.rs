@@ -0,0 +1,71 @@
+// Copyright 2022 Guillaume Becquin
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//     http://www.apache.org/licenses/LICENSE-2.0
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! # User-defined logits processors
+//! Custom modification of the next token logits at each generation step, for decoding behaviours not covered by the
+//! `GenerateOptions` (e.g. a domain-specific penalty, a dynamic constraint or a custom watermark). Processors are
+//! passed to the generation with `GenerateOptions::logits_processors` and are applied in order, for all decoding
+//! strategies, after the built-in penalties and biases (repetition penalty, `logit_bias`, `watermark`) and before
+//! the hard constraints (bad words, n-gram repetition, `prefix_allowed_tokens_fn`, minimum length), temperature
+//! and sampling filters.
+//!
+//! The trait is implemented for closures taking the tokens generated so far and the logits to modify:
+//!
+//! ```no_run
+//! # fn main() -> anyhow::Result<()> {
+//! use rust_bert::gpt2::GPT2Generator;
+//! use rust_bert::pipelines::generation_utils::{GenerateOptions, LanguageGenerator};
+//! use rust_bert::pipelines::logits_processor::LogitsProcessor;
+//! use tch::Tensor;
+//!
+//! let model = GPT2Generator::new(Default::default())?;
+//!
+//! // Discourages the newline token (id 198) more strongly as the sequences grow
+//! let newline_penalty = |input_ids: &Tensor, next_token_logits: &mut Tensor| {
+//!     let sequence_length = input_ids.size()[1] as f64;
+//!     let _ = next_token_logits
+//!         .select(1, 198)
+//!         .g_sub_scalar_(0.1 * sequence_length);
+//! };
+//! let logits_processors: [&dyn LogitsProcessor; 1] = [&newline_penalty];
+//! let generate_options = GenerateOptions {
+//!     logits_processors: Some(&logits_processors),
+//!     ..Default::default()
+//! };
+//! let output = model.generate(Some(&["The dog"]), Some(generate_options));
+//! # Ok(())
+//! # }
+//! ```
+
+use tch::Tensor;
+
+/// # Logits processor
+/// Modifies the logits of the next token at each generation step
+pub trait LogitsProcessor {
+    /// Modifies the logits of the next token in place
+    ///
+    /// # Arguments
+    ///
+    /// * `input_ids` - Tokens generated so far (including the prompt for decoder-only models, the decoder tokens for
+    ///   encoder-decoder models), of shape (*batch size x number of beams*, *sequence_length*)
+    /// * `next_token_logits` - Logits of the next token, of shape (*batch size x number of beams*, *vocab_size*).
+    ///   Tokens can be banned by setting their logits to `f64::NEG_INFINITY`.
+    fn process(&self, input_ids: &Tensor, next_token_logits: &mut Tensor);
+}
+
+impl<F> LogitsProcessor for F
+where
+    F: Fn(&Tensor, &mut Tensor),
+{
+    fn process(&self, input_ids: &Tensor, next_token_logits: &mut Tensor) {
+        self(input_ids, next_token_logits)
+    }
+}
@@ -0,0 +1,331 @@
+// Copyright 2022 Guillaume Becquin
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//     http://www.apache.org/licenses/LICENSE-2.0
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! # Memory usage statistics and budget
+//! Reports the memory used by a pipeline for a batch of a given size and length, split between:
+//! - `weights`: memory used by the model variables (measured, reflects the current precision of the model)
+//! - `activations_peak`: estimated peak memory used by the intermediate activations of a forward pass
+//! - `kv_cache`: estimated memory used by the cached keys and values during generation
+//!
+//! The activations and cache sizes are estimated from the dimensions of the model (hidden size, number of layers,
+//! attention heads...) and are an order of magnitude rather than an exact measurement: they do not account for
+//! the memory fragmentation or the workspace of the backend.
+//!
+//! The sequence classification, token classification and text generation pipelines accept an optional memory budget
+//! (in bytes). When set, the batches predicted to exceed the budget are rejected with a
+//! `RustBertError::MemoryBudgetExceededError` by the `Pipeline::run` entry point before being processed, preventing
+//! out-of-memory crashes.
+//!
+//! ```no_run
+//! # fn main() -> anyhow::Result<()> {
+//! use rust_bert::pipelines::common::Pipeline;
+//! use rust_bert::pipelines::sequence_classification::SequenceClassificationModel;
+//!
+//! let mut model = SequenceClassificationModel::new(Default::default())?;
+//! let statistics = model.memory_statistics(32, 512);
+//! println!(
+//!     "weights: {} bytes, activations: {} bytes",
+//!     statistics.weights, statistics.activations_peak
+//! );
+//!
+//! model.set_memory_budget(Some(2 * 1024 * 1024 * 1024));
+//! let output = model.run(&["This is a great movie!"])?;
+//! # Ok(())
+//! # }
+//! ```
+
+use crate::common::error::RustBertError;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::fs::File;
+use std::io::BufReader;
+use std::path::Path;
+use tch::nn::VarStore;
+use tch::Kind;
+
+/// # Dimensions of a model used for the memory estimates
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct ModelDimensions {
+    /// Size of the hidden states
+    pub hidden_size: i64,
+    /// Number of layers (of the encoder for encoder-decoder models)
+    pub num_hidden_layers: i64,
+    /// Number of decoder layers (equal to `num_hidden_layers` for decoder-only models)
+    pub num_decoder_layers: i64,
+    /// Number of attention heads (0 for models without attention, e.g. FNet)
+    pub num_attention_heads: i64,
+    /// Size of the intermediate feed-forward layer
+    pub intermediate_size: i64,
+    /// Size of the vocabulary
+    pub vocab_size: i64,
+}
+
+impl ModelDimensions {
+    /// Reads the model dimensions from a configuration file (e.g. config.json). The different naming conventions
+    /// of the supported architectures are handled (e.g. `hidden_size`, `d_model` or `n_embd` for the hidden size).
+    ///
+    /// # Arguments
+    ///
+    /// * `path` - Path to the configuration file
+    pub fn from_file<P: AsRef<Path>>(path: P) -> Result<ModelDimensions, RustBertError> {
+        let reader = BufReader::new(File::open(path.as_ref())?);
+        let config: Value = serde_json::from_reader(reader).map_err(|error| {
+            RustBertError::InvalidConfigurationError(format!(
+                "Invalid configuration file {:?}: {}",
+                path.as_ref(),
+                error
+            ))
+        })?;
+
+        let get_value = |keys: &[&str]| keys.iter().find_map(|key| config[*key].as_i64());
+        let hidden_size =
+            get_value(&["hidden_size", "d_model", "n_embd", "dim"]).ok_or_else(|| {
+                RustBertError::InvalidConfigurationError(
+                    "Hidden size not found in the configuration".to_string(),
+                )
+            })?;
+        let num_hidden_layers = get_value(&[
+            "num_hidden_layers",
+            "num_encoder_layers",
+            "num_layers",
+            "n_layer",
+            "n_layers",
+            "encoder_layers",
+        ])
+        .or_else(|| {
+            config["attn_layers"]
+                .as_array()
+                .map(|layers| layers.len() as i64)
+        })
+        .ok_or_else(|| {
+            RustBertError::InvalidConfigurationError(
+                "Number of layers not found in the configuration".to_string(),
+            )
+        })?;
+        let num_decoder_layers =
+            get_value(&["num_decoder_layers", "decoder_layers"]).unwrap_or(num_hidden_layers);
+        let num_attention_heads = get_value(&[
+            "num_attention_heads",
+            "num_encoder_attention_heads",
+            "num_heads",
+            "n_head",
+            "n_heads",
+            "encoder_attention_heads",
+        ])
+        .unwrap_or(0);
+        let intermediate_size = get_value(&[
+            "intermediate_size",
+            "d_ff",
+            "ffn_dim",
+            "encoder_ffn_dim",
+            "hidden_dim",
+            "d_inner",
+            "n_inner",
+            "feed_forward_size",
+        ])
+        .unwrap_or(4 * hidden_size);
+        let vocab_size = get_value(&["vocab_size"]).unwrap_or(0);
+
+        Ok(ModelDimensions {
+            hidden_size,
+            num_hidden_layers,
+            num_decoder_layers,
+            num_attention_heads,
+            intermediate_size,
+            vocab_size,
+        })
+    }
+}
+
+/// # Memory usage of a pipeline, in bytes
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct MemoryStatistics {
+    /// Memory used by the model weights
+    pub weights: usize,
+    /// Estimated peak memory used by the activations of a forward pass
+    pub activations_peak: usize,
+    /// Estimated memory used by the keys and values cache (0 for pipelines without generation)
+    pub kv_cache: usize,
+}
+
+impl MemoryStatistics {
+    /// Returns the total memory usage (weights, activations peak and cache)
+    pub fn total(&self) -> usize {
+        self.weights + self.activations_peak + self.kv_cache
+    }
+
+    /// Checks that the total memory usage fits in a budget
+    ///
+    /// # Arguments
+    ///
+    /// * `budget` - Optional memory budget in bytes. All usages fit in a `None` budget.
+    pub fn check_budget(&self, budget: Option<usize>) -> Result<(), RustBertError> {
+        match budget {
+            Some(budget) if self.total() > budget => {
+                Err(RustBertError::MemoryBudgetExceededError(format!(
+                    "predicted usage of {} bytes (weights: {}, activations: {}, cache: {}) exceeds the budget of {} bytes",
+                    self.total(),
+                    self.weights,
+                    self.activations_peak,
+                    self.kv_cache,
+                    budget
+                )))
+            }
+            _ => Ok(()),
+        }
+    }
+}
+
+/// # Estimator of the memory usage of a model
+#[derive(Debug, Clone, Copy)]
+pub struct MemoryEstimator {
+    dimensions: ModelDimensions,
+}
+
+impl MemoryEstimator {
+    /// Create a new `MemoryEstimator` for a model of the given dimensions
+    pub fn new(dimensions: ModelDimensions) -> MemoryEstimator {
+        MemoryEstimator { dimensions }
+    }
+
+    /// Reads the model dimensions from a configuration file and creates a new `MemoryEstimator`
+    pub fn from_file<P: AsRef<Path>>(path: P) -> Result<MemoryEstimator, RustBertError> {
+        Ok(MemoryEstimator::new(ModelDimensions::from_file(path)?))
+    }
+
+    /// Returns the dimensions of the model
+    pub fn dimensions(&self) -> &ModelDimensions {
+        &self.dimensions
+    }
+
+    fn element_size(var_store: &VarStore) -> usize {
+        var_store
+            .variables()
+            .values()
+            .next()
+            .map_or(Kind::Float, |variable| variable.kind())
+            .elt_size_in_bytes()
+    }
+
+    /// Returns the memory used by the variables of a `VarStore`
+    pub fn weights_size(var_store: &VarStore) -> usize {
+        var_store
+            .variables()
+            .values()
+            .map(|variable| variable.numel() * variable.kind().elt_size_in_bytes())
+            .sum()
+    }
+
+    fn layer_activations(&self, batch_size: usize, sequence_length: usize) -> usize {
+        let hidden_size = self.dimensions.hidden_size as usize;
+        let num_heads = self.dimensions.num_attention_heads as usize;
+        let intermediate_size = self.dimensions.intermediate_size as usize;
+        // Layer input and output, query, key, value and context (hidden size each), feed-forward intermediate
+        // states, attention scores and probabilities. Activations of previous layers are freed in inference mode.
+        batch_size
+            * (sequence_length * (6 * hidden_size + intermediate_size)
+                + 2 * num_heads * sequence_length * sequence_length)
+    }
+
+    /// Estimates the memory usage for a forward pass on a batch of inputs
+    ///
+    /// # Arguments
+    ///
+    /// * `var_store` - `VarStore` holding the model weights
+    /// * `batch_size` - Number of sequences processed at once
+    /// * `sequence_length` - Length (in tokens) of the padded sequences
+    pub fn estimate(
+        &self,
+        var_store: &VarStore,
+        batch_size: usize,
+        sequence_length: usize,
+    ) -> MemoryStatistics {
+        MemoryStatistics {
+            weights: Self::weights_size(var_store),
+            activations_peak: self.layer_activations(batch_size, sequence_length)
+                * Self::element_size(var_store),
+            kv_cache: 0,
+        }
+    }
+
+    /// Estimates the memory usage for the generation of sequences up to a maximum length. The activations include
+    /// the language model logits computed for the full sequence.
+    ///
+    /// # Arguments
+    ///
+    /// * `var_store` - `VarStore` holding the model weights
+    /// * `batch_size` - Number of sequences generated at once (including beams and returned sequences)
+    /// * `sequence_length` - Maximum length (in tokens) of the generated sequences
+    pub fn estimate_generation(
+        &self,
+        var_store: &VarStore,
+        batch_size: usize,
+        sequence_length: usize,
+    ) -> MemoryStatistics {
+        self.estimate_generation_for_weights(
+            Self::weights_size(var_store),
+            Self::element_size(var_store),
+            batch_size,
+            sequence_length,
+        )
+    }
+
+    /// Estimates the memory usage for the generation of sequences up to a maximum length, for a model that is
+    /// not loaded (e.g. from the weights size of a pretrained checkpoint).
+    ///
+    /// # Arguments
+    ///
+    /// * `weights_size` - Memory used by the model weights, in bytes
+    /// * `element_size` - Size in bytes of the model elements (4 for single precision)
+    /// * `batch_size` - Number of sequences generated at once (including beams and returned sequences)
+    /// * `sequence_length` - Maximum length (in tokens) of the generated sequences
+    pub fn estimate_generation_for_weights(
+        &self,
+        weights_size: usize,
+        element_size: usize,
+        batch_size: usize,
+        sequence_length: usize,
+    ) -> MemoryStatistics {
+        let logits = batch_size * sequence_length * self.dimensions.vocab_size as usize;
+        let kv_cache = 2
+            * self.dimensions.num_decoder_layers as usize
+            * batch_size
+            * sequence_length
+            * self.dimensions.hidden_size as usize;
+        MemoryStatistics {
+            weights: weights_size,
+            activations_peak: (self.layer_activations(batch_size, sequence_length) + logits)
+                * element_size,
+            kv_cache: kv_cache * element_size,
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_check_budget() {
+        let statistics = MemoryStatistics {
+            weights: 1000,
+            activations_peak: 200,
+            kv_cache: 30,
+        };
+        assert_eq!(statistics.total(), 1230);
+        assert!(statistics.check_budget(None).is_ok());
+        assert!(statistics.check_budget(Some(1230)).is_ok());
+        assert!(matches!(
+            statistics.check_budget(Some(1229)),
+            Err(RustBertError::MemoryBudgetExceededError(_))
+        ));
+    }
+}
@@ -0,0 +1,251 @@
+// Copyright 2022 Guillaume Becquin
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//     http://www.apache.org/licenses/LICENSE-2.0
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! # GPU memory pool preallocation and maintenance
+//! Long-running generation servers process batches of varying sizes and lengths: the blocks cached by the CUDA
+//! allocator of Torch get split into pieces that no longer fit the larger batches, and a batch can fail with an
+//! out-of-memory error although enough memory is free in total. A `MemoryPool` mitigates this fragmentation:
+//! - `allocator_config`: settings of the CUDA caching allocator (e.g. `max_split_size_mb:128`, preventing the split
+//!   of large blocks), exported as the `PYTORCH_CUDA_ALLOC_CONF` environment variable. They are only read by Torch
+//!   before the first CUDA allocation of the process: the pool should be created before loading the models.
+//! - `preallocate`: memory (in bytes) reserved at startup, in chunks of `chunk_size` bytes. The chunks are released
+//!   to the caching allocator, which keeps them reserved for the process and serves the batches from them.
+//! - `maintenance_interval`: every `maintenance_interval` batches, the device is synchronized and the registered
+//!   maintenance hooks are called (e.g. to empty the allocator cache or defragment the memory through a binding
+//!   of the application, as these operations are not exposed by the Torch bindings).
+//!
+//! The text generation pipeline (and the `GenerationScheduler` built on it) accepts an optional memory pool,
+//! maintained after each generated batch. On CPU, the preallocation and device synchronization are skipped and only
+//! the hooks are called.
+//!
+//! ```no_run
+//! # fn main() -> anyhow::Result<()> {
+//! use rust_bert::pipelines::memory_pool::{MemoryPool, MemoryPoolConfig};
+//! use rust_bert::pipelines::text_generation::TextGenerationModel;
+//! use tch::Device;
+//!
+//! let config = MemoryPoolConfig {
+//!     allocator_config: Some("max_split_size_mb:128".to_string()),
+//!     preallocate: Some(8 * 1024 * 1024 * 1024),
+//!     maintenance_interval: Some(100),
+//!     ..Default::default()
+//! };
+//! let mut memory_pool = MemoryPool::new(Device::cuda_if_available(), config)?;
+//! memory_pool.add_hook(|device| println!("Maintenance of {:?}", device));
+//!
+//! let mut model = TextGenerationModel::new(Default::default())?;
+//! model.set_memory_pool(Some(memory_pool));
+//! let output = model.generate(&["The dog"], None);
+//! # Ok(())
+//! # }
+//! ```
+
+use crate::common::error::RustBertError;
+use std::env;
+use tch::{Cuda, Device, Kind, Tensor};
+
+/// Environment variable read by Torch for the settings of the CUDA caching allocator
+pub const CUDA_ALLOCATOR_CONFIG_VARIABLE: &str = "PYTORCH_CUDA_ALLOC_CONF";
+
+/// # Configuration for a `MemoryPool`
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct MemoryPoolConfig {
+    /// Settings of the CUDA caching allocator (e.g. `max_split_size_mb:128`). Ignored if the
+    /// `PYTORCH_CUDA_ALLOC_CONF` environment variable is already set (default: None)
+    pub allocator_config: Option<String>,
+    /// Memory to preallocate at startup, in bytes (default: None, no preallocation)
+    pub preallocate: Option<usize>,
+    /// Size of the chunks of the preallocated memory, in bytes. Chunks smaller than the allocator
+    /// `max_split_size_mb` can be split between several tensors (default: 64MiB)
+    pub chunk_size: usize,
+    /// Number of batches between two maintenances of the memory pool (default: None, no maintenance)
+    pub maintenance_interval: Option<usize>,
+}
+
+impl Default for MemoryPoolConfig {
+    fn default() -> MemoryPoolConfig {
+        MemoryPoolConfig {
+            allocator_config: None,
+            preallocate: None,
+            chunk_size: 64 * 1024 * 1024,
+            maintenance_interval: None,
+        }
+    }
+}
+
+/// # Memory pool of a device, preallocated at startup and maintained between batches
+pub struct MemoryPool {
+    device: Device,
+    config: MemoryPoolConfig,
+    hooks: Vec<Box<dyn Fn(Device) + Send>>,
+    preallocated: usize,
+    batches: usize,
+    maintenances: usize,
+}
+
+impl MemoryPool {
+    /// Build a new `MemoryPool`, exporting the allocator settings and preallocating the memory on the device
+    ///
+    /// # Arguments
+    ///
+    /// * `device` - Device of the pipeline
+    /// * `config` - `MemoryPoolConfig` with the preallocated memory and maintenance interval
+    pub fn new(device: Device, config: MemoryPoolConfig) -> Result<MemoryPool, RustBertError> {
+        if config.chunk_size == 0 {
+            return Err(RustBertError::InvalidConfigurationError(
+                "The size of the preallocated chunks must be positive".to_string(),
+            ));
+        }
+        if config.maintenance_interval == Some(0) {
+            return Err(RustBertError::InvalidConfigurationError(
+                "The maintenance interval must be positive".to_string(),
+            ));
+        }
+        if let Some(allocator_config) = &config.allocator_config {
+            if env::var_os(CUDA_ALLOCATOR_CONFIG_VARIABLE).is_none() {
+                env::set_var(CUDA_ALLOCATOR_CONFIG_VARIABLE, allocator_config);
+            }
+        }
+
+        let preallocated = match (device, config.preallocate) {
+            (Device::Cuda(_), Some(preallocate)) => {
+                // The chunks are allocated together so that they are backed by distinct blocks, then released
+                // to the caching allocator when dropped
+                let mut chunks = Vec::with_capacity(preallocate / config.chunk_size + 1);
+                let mut remaining = preallocate;
+                while remaining > 0 {
+                    let chunk_size = remaining.min(config.chunk_size);
+                    chunks.push(Tensor::f_empty(
+                        &[chunk_size as i64],
+                        (Kind::Uint8, device),
+                    )?);
+                    remaining -= chunk_size;
+                }
+                preallocate
+            }
+            _ => 0,
+        };
+
+        Ok(MemoryPool {
+            device,
+            config,
+            hooks: vec![],
+            preallocated,
+            batches: 0,
+            maintenances: 0,
+        })
+    }
+
+    /// Registers a hook called at each maintenance of the memory pool, with the device of the pool
+    ///
+    /// # Arguments
+    ///
+    /// * `hook` - Function called at each maintenance (e.g. emptying the cache of the allocator)
+    pub fn add_hook<F>(&mut self, hook: F)
+    where
+        F: Fn(Device) + Send + 'static,
+    {
+        self.hooks.push(Box::new(hook));
+    }
+
+    /// Returns the device of the memory pool
+    pub fn device(&self) -> Device {
+        self.device
+    }
+
+    /// Returns the memory preallocated at startup, in bytes (0 on CPU)
+    pub fn preallocated(&self) -> usize {
+        self.preallocated
+    }
+
+    /// Returns the number of maintenances run so far
+    pub fn maintenances(&self) -> usize {
+        self.maintenances
+    }
+
+    /// Records the completion of a batch, running a maintenance every `maintenance_interval` batches
+    ///
+    /// # Returns
+    ///
+    /// * `bool` true if a maintenance was run
+    pub fn batch_completed(&mut self) -> bool {
+        self.batches += 1;
+        match self.config.maintenance_interval {
+            Some(interval) if self.batches % interval == 0 => {
+                self.maintain();
+                true
+            }
+            _ => false,
+        }
+    }
+
+    /// Runs a maintenance: waits for the pending operations of the device and calls the registered hooks
+    pub fn maintain(&mut self) {
+        if let Device::Cuda(device_index) = self.device {
+            Cuda::synchronize(device_index as i64);
+        }
+        for hook in self.hooks.iter() {
+            hook(self.device);
+        }
+        self.maintenances += 1;
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::sync::Arc;
+
+    #[test]
+    fn hooks_are_called_every_interval() -> Result<(), RustBertError> {
+        let config = MemoryPoolConfig {
+            preallocate: Some(1024),
+            maintenance_interval: Some(3),
+            ..Default::default()
+        };
+        let mut memory_pool = MemoryPool::new(Device::Cpu, config)?;
+        assert_eq!(memory_pool.preallocated(), 0);
+
+        let calls = Arc::new(AtomicUsize::new(0));
+        let hook_calls = calls.clone();
+        memory_pool.add_hook(move |device| {
+            assert_eq!(device, Device::Cpu);
+            hook_calls.fetch_add(1, Ordering::SeqCst);
+        });
+
+        let maintained = (0..7)
+            .map(|_| memory_pool.batch_completed())
+            .collect::<Vec<bool>>();
+        assert_eq!(
+            maintained,
+            vec![false, false, true, false, false, true, false]
+        );
+        assert_eq!(calls.load(Ordering::SeqCst), 2);
+        assert_eq!(memory_pool.maintenances(), 2);
+        Ok(())
+    }
+
+    #[test]
+    fn invalid_configurations_are_rejected() {
+        let zero_interval = MemoryPoolConfig {
+            maintenance_interval: Some(0),
+            ..Default::default()
+        };
+        assert!(MemoryPool::new(Device::Cpu, zero_interval).is_err());
+
+        let zero_chunk_size = MemoryPoolConfig {
+            chunk_size: 0,
+            ..Default::default()
+        };
+        assert!(MemoryPool::new(Device::Cpu, zero_chunk_size).is_err());
+    }
+}
@@ -29,6 +29,10 @@
 //!     score: 0.9976,
 //!     start: 13,
 //!     end: 21,
+//!     byte_start: 13,
+//!     byte_end: 21,
+//!     utf16_start: 13,
+//!     utf16_end: 21,
 //!     answer: String::from("Amsterdam"),
 //! }]
 //! # ;
@@ -344,12 +348,16 @@
 //!             score: 0.9986,
 //!             label: String::from("I-PER"),
 //!             offset: Offset { begin: 11, end: 14 },
+//!             byte_offset: Offset { begin: 11, end: 14 },
+//!             utf16_offset: Offset { begin: 11, end: 14 },
 //!         },
 //!         Entity {
 //!             word: String::from("Paris"),
 //!             score: 0.9985,
 //!             label: String::from("I-LOC"),
 //!             offset: Offset { begin: 26, end: 31 },
+//!             byte_offset: Offset { begin: 26, end: 31 },
+//!             utf16_offset: Offset { begin: 26, end: 31 },
 //!         },
 //!     ],
 //!     [
@@ -358,12 +366,16 @@
 //!             score: 0.9988,
 //!             label: String::from("I-LOC"),
 //!             offset: Offset { begin: 0, end: 5 },
+//!             byte_offset: Offset { begin: 0, end: 5 },
+//!             utf16_offset: Offset { begin: 0, end: 5 },
 //!         },
 //!         Entity {
 //!             word: String::from("France"),
 //!             score: 0.9993,
 //!             label: String::from("I-LOC"),
 //!             offset: Offset { begin: 19, end: 25 },
+//!             byte_offset: Offset { begin: 19, end: 25 },
+//!             utf16_offset: Offset { begin: 19, end: 25 },
 //!         },
 //!     ],
 //! ]
@@ -438,17 +450,62 @@
 //! # ;
 //! ```
 
+pub mod bad_words;
+pub mod batch;
+#[cfg(feature = "cache")]
+pub mod cache;
+pub mod clustering;
+pub mod code_summarization;
 pub mod common;
+pub mod composite;
+pub mod config_validation;
 pub mod conversation;
+pub mod deduplication;
+pub mod distractor_generation;
+pub mod embedding_drift;
+pub mod feature_extraction;
+pub mod generation_scheduler;
 pub mod generation_utils;
+pub mod grammar;
+pub mod hot_swap;
+pub mod image_classification;
+pub mod input_length;
+pub mod logits_processor;
+pub mod memory;
+pub mod memory_pool;
+pub mod model_selection;
+pub mod multi_task;
+pub mod natural_language_inference;
 pub mod ner;
+pub mod ocr;
+pub mod outlier_detection;
 pub mod pos_tagging;
+pub mod pretrained_registry;
+pub mod prompt_classification;
+pub mod punctuation_restoration;
 pub mod question_answering;
+pub mod readability;
+pub mod registry;
+pub mod reward_model;
+pub mod sanitization;
+pub mod semantic_similarity;
+pub mod sentence_alignment;
 pub mod sentence_embeddings;
 pub mod sentiment;
 pub mod sequence_classification;
+pub mod shared_encoder;
+pub mod speech_recognition;
+pub mod spelling_correction;
+#[cfg(feature = "tokio")]
+pub mod streaming;
 pub mod summarization;
 pub mod text_generation;
+pub mod text_restoration;
+pub mod text_to_sql;
+pub mod tiny_random;
 pub mod token_classification;
+pub mod topic_modeling;
 pub mod translation;
+pub mod watermark;
 pub mod zero_shot_classification;
+pub mod zero_shot_image_classification;
@@ -0,0 +1,604 @@
+// Copyright 2022 Guillaume Becquin
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//     http://www.apache.org/licenses/LICENSE-2.0
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! # Hardware-aware model selection
+//! `ModelSelector` recommends, for a task and a hardware profile (RAM, VRAM and CPU threads), the largest
+//! registered pretrained checkpoint that fits, and creates the corresponding pipeline.
+//!
+//! The pretrained checkpoints are registered with their measured footprint: the size of the weights loaded in
+//! single precision (as reported by `MemoryEstimator::weights_size`) and the model dimensions used to estimate the
+//! activations and cache memory of a workload (see the `memory` module). A checkpoint fits if:
+//! - its weights, activations and cache for the workload fit in a fraction of the memory of the device (the VRAM
+//! when the profile has a GPU, the RAM otherwise),
+//! - on CPU, its number of parameters does not exceed a budget per CPU thread. Larger models would fit in memory
+//! but take (and consume) too much to run on few cores.
+//!
+//! Additional checkpoints (e.g. fine-tuned models) can be registered with `register_checkpoint`.
+//!
+//! ```no_run
+//! # fn main() -> anyhow::Result<()> {
+//! use rust_bert::pipelines::model_selection::{
+//!     HardwareProfile, ModelSelector, ModelSelectorConfig, SelectionTask,
+//! };
+//!
+//! // 8GB of RAM, no GPU and 4 CPU threads
+//! let profile = HardwareProfile::new(8 * 1024 * 1024 * 1024, None, 4);
+//! let selector = ModelSelector::new(profile, ModelSelectorConfig::default());
+//!
+//! let checkpoint = selector.recommend(SelectionTask::TextGeneration)?;
+//! println!("Selected {}", checkpoint.name);
+//! let model = selector.text_generation_model()?;
+//! let output = model.generate(&["The dog"], None);
+//! # Ok(())
+//! # }
+//! ```
+
+use crate::bart::{BartConfigResources, BartMergesResources, BartModelResources, BartVocabResources};
+use crate::common::error::RustBertError;
+use crate::gpt2::{Gpt2ConfigResources, Gpt2MergesResources, Gpt2ModelResources, Gpt2VocabResources};
+use crate::gpt_neo::{
+    GptNeoConfigResources, GptNeoMergesResources, GptNeoModelResources, GptNeoVocabResources,
+};
+use crate::pipelines::common::ModelType;
+use crate::pipelines::memory::{MemoryEstimator, MemoryStatistics, ModelDimensions};
+use crate::t5::{T5ConfigResources, T5ModelResources, T5VocabResources};
+use lazy_static::lazy_static;
+use serde::{Deserialize, Serialize};
+use std::sync::RwLock;
+use tch::Device;
+
+#[cfg(feature = "remote")]
+use crate::{
+    pipelines::summarization::{SummarizationConfig, SummarizationModel},
+    pipelines::text_generation::{TextGenerationConfig, TextGenerationModel},
+    resources::RemoteResource,
+};
+
+/// Size in bytes of the single precision weights the footprints are measured for
+const WEIGHTS_ELEMENT_SIZE: usize = 4;
+
+/// # Task a checkpoint is registered for
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum SelectionTask {
+    /// Text generation (`TextGenerationModel`)
+    TextGeneration,
+    /// Summarization (`SummarizationModel`)
+    Summarization,
+}
+
+/// # Pretrained checkpoint with its measured footprint
+/// The resources are the pretrained (name, url) pairs of the model resources structures
+/// (e.g. `Gpt2ModelResources::GPT2`).
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct PretrainedCheckpoint {
+    /// Unique name of the checkpoint
+    pub name: &'static str,
+    /// Task the checkpoint is used for
+    pub task: SelectionTask,
+    /// Model type
+    pub model_type: ModelType,
+    /// Number of parameters
+    pub parameters: usize,
+    /// Memory used by the weights loaded in single precision, in bytes
+    pub weights_size: usize,
+    /// Model dimensions, used to estimate the activations and cache memory
+    pub dimensions: ModelDimensions,
+    /// Model weights resource
+    pub model_resource: (&'static str, &'static str),
+    /// Config resource
+    pub config_resource: (&'static str, &'static str),
+    /// Vocab resource
+    pub vocab_resource: (&'static str, &'static str),
+    /// Optional merges resource (the vocab resource is used for tokenizers without merges, e.g. T5)
+    pub merges_resource: Option<(&'static str, &'static str)>,
+}
+
+#[allow(clippy::too_many_arguments)]
+fn checkpoint(
+    name: &'static str,
+    task: SelectionTask,
+    model_type: ModelType,
+    parameters: usize,
+    dimensions: (i64, i64, i64, i64, i64, i64),
+    model_resource: (&'static str, &'static str),
+    config_resource: (&'static str, &'static str),
+    vocab_resource: (&'static str, &'static str),
+    merges_resource: Option<(&'static str, &'static str)>,
+) -> PretrainedCheckpoint {
+    let (
+        hidden_size,
+        num_hidden_layers,
+        num_decoder_layers,
+        num_attention_heads,
+        intermediate_size,
+        vocab_size,
+    ) = dimensions;
+    PretrainedCheckpoint {
+        name,
+        task,
+        model_type,
+        parameters,
+        weights_size: parameters * WEIGHTS_ELEMENT_SIZE,
+        dimensions: ModelDimensions {
+            hidden_size,
+            num_hidden_layers,
+            num_decoder_layers,
+            num_attention_heads,
+            intermediate_size,
+            vocab_size,
+        },
+        model_resource,
+        config_resource,
+        vocab_resource,
+        merges_resource,
+    }
+}
+
+fn pretrained_checkpoints() -> Vec<PretrainedCheckpoint> {
+    use SelectionTask::{Summarization, TextGeneration};
+    vec![
+        checkpoint(
+            "distilgpt2",
+            TextGeneration,
+            ModelType::GPT2,
+            81_912_576,
+            (768, 6, 6, 12, 3072, 50257),
+            Gpt2ModelResources::DISTIL_GPT2,
+            Gpt2ConfigResources::DISTIL_GPT2,
+            Gpt2VocabResources::DISTIL_GPT2,
+            Some(Gpt2MergesResources::DISTIL_GPT2),
+        ),
+        checkpoint(
+            "gpt2",
+            TextGeneration,
+            ModelType::GPT2,
+            124_439_808,
+            (768, 12, 12, 12, 3072, 50257),
+            Gpt2ModelResources::GPT2,
+            Gpt2ConfigResources::GPT2,
+            Gpt2VocabResources::GPT2,
+            Some(Gpt2MergesResources::GPT2),
+        ),
+        checkpoint(
+            "gpt-neo-125M",
+            TextGeneration,
+            ModelType::GPTNeo,
+            125_198_592,
+            (768, 12, 12, 12, 3072, 50257),
+            GptNeoModelResources::GPT_NEO_125M,
+            GptNeoConfigResources::GPT_NEO_125M,
+            GptNeoVocabResources::GPT_NEO_125M,
+            Some(GptNeoMergesResources::GPT_NEO_125M),
+        ),
+        checkpoint(
+            "gpt2-medium",
+            TextGeneration,
+            ModelType::GPT2,
+            354_823_168,
+            (1024, 24, 24, 16, 4096, 50257),
+            Gpt2ModelResources::GPT2_MEDIUM,
+            Gpt2ConfigResources::GPT2_MEDIUM,
+            Gpt2VocabResources::GPT2_MEDIUM,
+            Some(Gpt2MergesResources::GPT2_MEDIUM),
+        ),
+        checkpoint(
+            "gpt2-large",
+            TextGeneration,
+            ModelType::GPT2,
+            774_030_080,
+            (1280, 36, 36, 20, 5120, 50257),
+            Gpt2ModelResources::GPT2_LARGE,
+            Gpt2ConfigResources::GPT2_LARGE,
+            Gpt2VocabResources::GPT2_LARGE,
+            Some(Gpt2MergesResources::GPT2_LARGE),
+        ),
+        checkpoint(
+            "gpt-neo-1.3B",
+            TextGeneration,
+            ModelType::GPTNeo,
+            1_315_575_808,
+            (2048, 24, 24, 16, 8192, 50257),
+            GptNeoModelResources::GPT_NEO_1_3B,
+            GptNeoConfigResources::GPT_NEO_1_3B,
+            GptNeoVocabResources::GPT_NEO_1_3B,
+            Some(GptNeoMergesResources::GPT_NEO_1_3B),
+        ),
+        checkpoint(
+            "gpt2-xl",
+            TextGeneration,
+            ModelType::GPT2,
+            1_557_611_200,
+            (1600, 48, 48, 25, 6400, 50257),
+            Gpt2ModelResources::GPT2_XL,
+            Gpt2ConfigResources::GPT2_XL,
+            Gpt2VocabResources::GPT2_XL,
+            Some(Gpt2MergesResources::GPT2_XL),
+        ),
+        checkpoint(
+            "gpt-neo-2.7B",
+            TextGeneration,
+            ModelType::GPTNeo,
+            2_651_307_520,
+            (2560, 32, 32, 20, 10240, 50257),
+            GptNeoModelResources::GPT_NEO_2_7B,
+            GptNeoConfigResources::GPT_NEO_2_7B,
+            GptNeoVocabResources::GPT_NEO_2_7B,
+            Some(GptNeoMergesResources::GPT_NEO_2_7B),
+        ),
+        checkpoint(
+            "t5-small",
+            Summarization,
+            ModelType::T5,
+            60_506_624,
+            (512, 6, 6, 8, 2048, 32128),
+            T5ModelResources::T5_SMALL,
+            T5ConfigResources::T5_SMALL,
+            T5VocabResources::T5_SMALL,
+            None,
+        ),
+        checkpoint(
+            "t5-base",
+            Summarization,
+            ModelType::T5,
+            222_903_552,
+            (768, 12, 12, 12, 3072, 32128),
+            T5ModelResources::T5_BASE,
+            T5ConfigResources::T5_BASE,
+            T5VocabResources::T5_BASE,
+            None,
+        ),
+        checkpoint(
+            "distilbart-cnn-6-6",
+            Summarization,
+            ModelType::Bart,
+            229_933_056,
+            (1024, 6, 6, 16, 4096, 50264),
+            BartModelResources::DISTILBART_CNN_6_6,
+            BartConfigResources::DISTILBART_CNN_6_6,
+            BartVocabResources::DISTILBART_CNN_6_6,
+            Some(BartMergesResources::DISTILBART_CNN_6_6),
+        ),
+        checkpoint(
+            "distilbart-cnn-12-6",
+            Summarization,
+            ModelType::Bart,
+            305_510_400,
+            (1024, 12, 6, 16, 4096, 50264),
+            BartModelResources::DISTILBART_CNN_12_6,
+            BartConfigResources::DISTILBART_CNN_12_6,
+            BartVocabResources::DISTILBART_CNN_12_6,
+            Some(BartMergesResources::DISTILBART_CNN_12_6),
+        ),
+        checkpoint(
+            "bart-large-cnn",
+            Summarization,
+            ModelType::Bart,
+            406_290_432,
+            (1024, 12, 12, 16, 4096, 50264),
+            BartModelResources::BART_CNN,
+            BartConfigResources::BART_CNN,
+            BartVocabResources::BART_CNN,
+            Some(BartMergesResources::BART_CNN),
+        ),
+    ]
+}
+
+lazy_static! {
+    static ref CHECKPOINT_REGISTRY: RwLock<Vec<PretrainedCheckpoint>> =
+        RwLock::new(pretrained_checkpoints());
+}
+
+/// Registers a pretrained checkpoint, making it available to the `ModelSelector`.
+///
+/// # Arguments
+///
+/// * `checkpoint` - `PretrainedCheckpoint` with its measured footprint. Fails if a checkpoint was already
+/// registered under the same name.
+pub fn register_checkpoint(checkpoint: PretrainedCheckpoint) -> Result<(), RustBertError> {
+    let mut registry = CHECKPOINT_REGISTRY.write().unwrap();
+    if registry
+        .iter()
+        .any(|registered| registered.name == checkpoint.name)
+    {
+        return Err(RustBertError::InvalidConfigurationError(format!(
+            "A checkpoint is already registered under the name {}",
+            checkpoint.name
+        )));
+    }
+    registry.push(checkpoint);
+    Ok(())
+}
+
+/// Returns the checkpoints registered for a task, sorted by increasing number of parameters
+pub fn registered_checkpoints(task: SelectionTask) -> Vec<PretrainedCheckpoint> {
+    let mut checkpoints = CHECKPOINT_REGISTRY
+        .read()
+        .unwrap()
+        .iter()
+        .filter(|checkpoint| checkpoint.task == task)
+        .copied()
+        .collect::<Vec<PretrainedCheckpoint>>();
+    checkpoints.sort_by_key(|checkpoint| checkpoint.parameters);
+    checkpoints
+}
+
+/// # Hardware the models are selected for
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct HardwareProfile {
+    /// Available RAM, in bytes
+    pub ram: usize,
+    /// Available VRAM of the GPU in bytes, `None` for CPU-only hardware
+    pub vram: Option<usize>,
+    /// Number of CPU threads
+    pub cpu_threads: usize,
+}
+
+impl HardwareProfile {
+    /// Create a new `HardwareProfile`
+    ///
+    /// # Arguments
+    ///
+    /// * `ram` - Available RAM, in bytes
+    /// * `vram` - Available VRAM of the GPU in bytes, `None` for CPU-only hardware
+    /// * `cpu_threads` - Number of CPU threads
+    pub fn new(ram: usize, vram: Option<usize>, cpu_threads: usize) -> HardwareProfile {
+        HardwareProfile {
+            ram,
+            vram,
+            cpu_threads,
+        }
+    }
+
+    /// Returns the device the models run on: the first GPU if the profile has VRAM, the CPU otherwise
+    pub fn device(&self) -> Device {
+        match self.vram {
+            Some(_) => Device::Cuda(0),
+            None => Device::Cpu,
+        }
+    }
+
+    /// Returns the memory available to the models: the VRAM if the profile has a GPU, the RAM otherwise
+    pub fn available_memory(&self) -> usize {
+        self.vram.unwrap_or(self.ram)
+    }
+}
+
+/// # Configuration for a `ModelSelector`
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct ModelSelectorConfig {
+    /// Number of sequences processed at once
+    pub batch_size: usize,
+    /// Maximum length (in tokens) of the sequences, including the generated tokens
+    pub sequence_length: usize,
+    /// Fraction of the device memory the model may use, the remainder being left to the backend and application
+    pub memory_fraction: f64,
+    /// Maximum number of parameters per CPU thread for models running on CPU
+    pub max_parameters_per_cpu_thread: usize,
+}
+
+impl ModelSelectorConfig {
+    /// Create a new `ModelSelectorConfig`
+    ///
+    /// # Arguments
+    ///
+    /// * `batch_size` - Number of sequences processed at once
+    /// * `sequence_length` - Maximum length (in tokens) of the sequences, including the generated tokens
+    /// * `memory_fraction` - Fraction of the device memory the model may use
+    /// * `max_parameters_per_cpu_thread` - Maximum number of parameters per CPU thread for models running on CPU
+    pub fn new(
+        batch_size: usize,
+        sequence_length: usize,
+        memory_fraction: f64,
+        max_parameters_per_cpu_thread: usize,
+    ) -> ModelSelectorConfig {
+        ModelSelectorConfig {
+            batch_size,
+            sequence_length,
+            memory_fraction,
+            max_parameters_per_cpu_thread,
+        }
+    }
+}
+
+impl Default for ModelSelectorConfig {
+    fn default() -> ModelSelectorConfig {
+        ModelSelectorConfig::new(1, 512, 0.8, 200_000_000)
+    }
+}
+
+/// # Selector of the largest pretrained checkpoint fitting a hardware profile
+pub struct ModelSelector {
+    profile: HardwareProfile,
+    config: ModelSelectorConfig,
+}
+
+impl ModelSelector {
+    /// Create a new `ModelSelector`
+    ///
+    /// # Arguments
+    ///
+    /// * `profile` - `HardwareProfile` of the hardware the models run on
+    /// * `config` - `ModelSelectorConfig` describing the workload and selection limits
+    pub fn new(profile: HardwareProfile, config: ModelSelectorConfig) -> ModelSelector {
+        ModelSelector { profile, config }
+    }
+
+    /// Returns the estimated memory usage of a checkpoint for the workload of the selector
+    pub fn footprint(&self, checkpoint: &PretrainedCheckpoint) -> MemoryStatistics {
+        MemoryEstimator::new(checkpoint.dimensions).estimate_generation_for_weights(
+            checkpoint.weights_size,
+            WEIGHTS_ELEMENT_SIZE,
+            self.config.batch_size,
+            self.config.sequence_length,
+        )
+    }
+
+    /// Checks if a checkpoint fits the hardware profile
+    pub fn fits(&self, checkpoint: &PretrainedCheckpoint) -> bool {
+        let memory_budget =
+            (self.profile.available_memory() as f64 * self.config.memory_fraction) as usize;
+        let within_cpu_budget = self.profile.vram.is_some()
+            || checkpoint.parameters
+                <= self.config.max_parameters_per_cpu_thread * self.profile.cpu_threads;
+        within_cpu_budget && self.footprint(checkpoint).total() <= memory_budget
+    }
+
+    /// Returns the checkpoints registered for a task that fit the hardware profile, sorted by increasing size
+    pub fn candidates(&self, task: SelectionTask) -> Vec<PretrainedCheckpoint> {
+        registered_checkpoints(task)
+            .into_iter()
+            .filter(|checkpoint| self.fits(checkpoint))
+            .collect()
+    }
+
+    /// Recommends the largest checkpoint registered for a task that fits the hardware profile.
+    /// Returns a `RustBertError::MemoryBudgetExceededError` if none of the checkpoints fit.
+    pub fn recommend(&self, task: SelectionTask) -> Result<PretrainedCheckpoint, RustBertError> {
+        self.candidates(task).pop().ok_or_else(|| {
+            RustBertError::MemoryBudgetExceededError(format!(
+                "No checkpoint registered for {:?} fits {} bytes of memory and {} CPU threads",
+                task,
+                self.profile.available_memory(),
+                self.profile.cpu_threads
+            ))
+        })
+    }
+
+    /// Returns a `TextGenerationConfig` for the recommended text generation checkpoint, on the profile device
+    #[cfg(feature = "remote")]
+    pub fn text_generation_config(&self) -> Result<TextGenerationConfig, RustBertError> {
+        let checkpoint = self.recommend(SelectionTask::TextGeneration)?;
+        let mut config = TextGenerationConfig::new(
+            checkpoint.model_type,
+            RemoteResource::from_pretrained(checkpoint.model_resource),
+            RemoteResource::from_pretrained(checkpoint.config_resource),
+            RemoteResource::from_pretrained(checkpoint.vocab_resource),
+            RemoteResource::from_pretrained(
+                checkpoint
+                    .merges_resource
+                    .unwrap_or(checkpoint.vocab_resource),
+            ),
+        );
+        config.device = self.profile.device();
+        Ok(config)
+    }
+
+    /// Creates a `TextGenerationModel` with the recommended text generation checkpoint
+    #[cfg(feature = "remote")]
+    pub fn text_generation_model(&self) -> Result<TextGenerationModel, RustBertError> {
+        TextGenerationModel::new(self.text_generation_config()?)
+    }
+
+    /// Returns a `SummarizationConfig` for the recommended summarization checkpoint, on the profile device
+    #[cfg(feature = "remote")]
+    pub fn summarization_config(&self) -> Result<SummarizationConfig, RustBertError> {
+        let checkpoint = self.recommend(SelectionTask::Summarization)?;
+        let mut config = SummarizationConfig::new(
+            checkpoint.model_type,
+            RemoteResource::from_pretrained(checkpoint.model_resource),
+            RemoteResource::from_pretrained(checkpoint.config_resource),
+            RemoteResource::from_pretrained(checkpoint.vocab_resource),
+            RemoteResource::from_pretrained(
+                checkpoint
+                    .merges_resource
+                    .unwrap_or(checkpoint.vocab_resource),
+            ),
+        );
+        config.device = self.profile.device();
+        Ok(config)
+    }
+
+    /// Creates a `SummarizationModel` with the recommended summarization checkpoint
+    #[cfg(feature = "remote")]
+    pub fn summarization_model(&self) -> Result<SummarizationModel, RustBertError> {
+        SummarizationModel::new(self.summarization_config()?)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    const GB: usize = 1024 * 1024 * 1024;
+
+    #[test]
+    fn test_recommend_largest_fitting_checkpoint() {
+        let gpu_selector = ModelSelector::new(
+            HardwareProfile::new(64 * GB, Some(5 * GB), 4),
+            ModelSelectorConfig::default(),
+        );
+        // gpt2-large (3.5GB with the activations and cache) fits in 80% of 5GB, gpt-neo-1.3B (5.3GB of weights)
+        // does not
+        assert_eq!(
+            gpu_selector
+                .recommend(SelectionTask::TextGeneration)
+                .unwrap()
+                .name,
+            "gpt2-large"
+        );
+        assert_eq!(gpu_selector.profile.device(), Device::Cuda(0));
+
+        // The memory is not a limit for this CPU hardware, the number of threads (600M parameters) is
+        let cpu_selector = ModelSelector::new(
+            HardwareProfile::new(64 * GB, None, 3),
+            ModelSelectorConfig::default(),
+        );
+        assert_eq!(
+            cpu_selector
+                .recommend(SelectionTask::TextGeneration)
+                .unwrap()
+                .name,
+            "gpt2-medium"
+        );
+        assert_eq!(
+            cpu_selector
+                .recommend(SelectionTask::Summarization)
+                .unwrap()
+                .name,
+            "bart-large-cnn"
+        );
+    }
+
+    #[test]
+    fn test_no_fitting_checkpoint() {
+        let selector = ModelSelector::new(
+            HardwareProfile::new(GB / 8, None, 8),
+            ModelSelectorConfig::default(),
+        );
+        assert!(selector
+            .candidates(SelectionTask::TextGeneration)
+            .is_empty());
+        assert!(matches!(
+            selector.recommend(SelectionTask::TextGeneration),
+            Err(RustBertError::MemoryBudgetExceededError(_))
+        ));
+    }
+
+    #[test]
+    fn test_register_checkpoint() {
+        let distilgpt2 = registered_checkpoints(SelectionTask::TextGeneration)[0];
+        assert_eq!(distilgpt2.name, "distilgpt2");
+        assert!(register_checkpoint(distilgpt2).is_err());
+
+        let fine_tuned = PretrainedCheckpoint {
+            name: "test-summarization-tiny",
+            task: SelectionTask::Summarization,
+            parameters: 1_000_000,
+            weights_size: 4_000_000,
+            ..distilgpt2
+        };
+        register_checkpoint(fine_tuned).unwrap();
+        assert_eq!(
+            registered_checkpoints(SelectionTask::Summarization)[0],
+            fine_tuned
+        );
+    }
+}
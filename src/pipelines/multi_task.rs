@@ -0,0 +1,707 @@
+// Copyright 2022 Guillaume Becquin
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//     http://www.apache.org/licenses/LICENSE-2.0
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! # Multi-task pipeline
+//! Runs several task heads (sequence classification, token classification and extractive question answering)
+//! on top of a single shared encoder. The encoder weights are loaded once, and each head is a linear layer
+//! with its own small set of weights. Requests for different tasks can be mixed in the same batch: the encoder
+//! processes all inputs in a single forward pass and the hidden states are routed to the head of each request.
+//!
+//! The heads must have been trained on top of the shared encoder (e.g. with a frozen backbone or multi-task
+//! fine-tuning). Their weights use the same variable names as the task-specific models of the
+//! Transformers library: `classifier.weight` and `classifier.bias` for the classification heads,
+//! `qa_outputs.weight` and `qa_outputs.bias` for question answering.
+//!
+//! BERT and RoBERTa-based (including XLM-RoBERTa) encoders are supported. Inputs longer than the maximum length
+//! are truncated (question answering contexts are not split in multiple spans).
+//!
+//! ```no_run
+//! # fn main() -> anyhow::Result<()> {
+//! use rust_bert::pipelines::common::ModelType;
+//! use rust_bert::pipelines::multi_task::{
+//!     MultiTaskConfig, MultiTaskModel, TaskHeadConfig, TaskInput, TaskOutput,
+//! };
+//! use rust_bert::pipelines::question_answering::QaInput;
+//! use rust_bert::resources::LocalResource;
+//! use std::collections::HashMap;
+//! use std::path::PathBuf;
+//!
+//! let resource = |path: &str| LocalResource {
+//!     local_path: PathBuf::from(path),
+//! };
+//! let mut config = MultiTaskConfig::new(
+//!     ModelType::Bert,
+//!     resource("path/to/encoder/rust_model.ot"),
+//!     resource("path/to/encoder/config.json"),
+//!     resource("path/to/encoder/vocab.txt"),
+//!     None,
+//!     false,
+//!     None,
+//!     None,
+//! );
+//! config.sequence_classification_head = Some(TaskHeadConfig::new(
+//!     resource("path/to/sentiment_head.ot"),
+//!     HashMap::from([(0, "NEGATIVE".to_string()), (1, "POSITIVE".to_string())]),
+//! ));
+//! config.question_answering_head = Some(TaskHeadConfig::new(
+//!     resource("path/to/qa_head.ot"),
+//!     HashMap::new(),
+//! ));
+//! let model = MultiTaskModel::new(config)?;
+//!
+//! let qa_input = QaInput {
+//!     question: "Where does Amy live ?".to_string(),
+//!     context: "Amy lives in Amsterdam".to_string(),
+//! };
+//! let outputs = model.predict(&[
+//!     TaskInput::SequenceClassification("This is a great movie!"),
+//!     TaskInput::QuestionAnswering(&qa_input),
+//! ])?;
+//! # Ok(())
+//! # }
+//! ```
+
+use crate::bert::{BertConfig, BertEmbeddings, BertModel};
+use crate::common::error::RustBertError;
+use crate::common::offsets::OffsetConverter;
+use crate::pipelines::common::{ModelType, TokenizerOption};
+use crate::pipelines::question_answering::{Answer, QaInput};
+use crate::pipelines::sequence_classification::Label;
+use crate::pipelines::token_classification::Token;
+use crate::resources::ResourceProvider;
+use crate::roberta::RobertaEmbeddings;
+use crate::Config;
+use rust_tokenizers::tokenizer::TruncationStrategy;
+use rust_tokenizers::{Mask, Offset, TokenizedInput};
+use std::borrow::Borrow;
+use std::collections::HashMap;
+use tch::nn::{Module, VarStore};
+use tch::{nn, no_grad, Device, Kind, Tensor};
+
+const MAX_ANSWER_LENGTH: i64 = 30;
+
+/// # Configuration for a task head of a `MultiTaskModel`
+pub struct TaskHeadConfig {
+    /// Head weights resource
+    pub weights_resource: Box<dyn ResourceProvider + Send>,
+    /// Mapping from label index to label name (ignored for question answering)
+    pub label_mapping: HashMap<i64, String>,
+}
+
+impl TaskHeadConfig {
+    /// Create a new `TaskHeadConfig`
+    ///
+    /// # Arguments
+    ///
+    /// * `weights_resource` - The `ResourceProvider` pointing to the head weights
+    /// * `label_mapping` - Mapping from label index to label name (ignored for question answering)
+    pub fn new<R>(weights_resource: R, label_mapping: HashMap<i64, String>) -> TaskHeadConfig
+    where
+        R: ResourceProvider + Send + 'static,
+    {
+        TaskHeadConfig {
+            weights_resource: Box::new(weights_resource),
+            label_mapping,
+        }
+    }
+}
+
+/// # Configuration for MultiTaskModel
+/// Contains information regarding the shared encoder, the task heads to load and device to place the model on.
+pub struct MultiTaskConfig {
+    /// Model type of the shared encoder
+    pub model_type: ModelType,
+    /// Encoder weights resource
+    pub model_resource: Box<dyn ResourceProvider + Send>,
+    /// Config resource
+    pub config_resource: Box<dyn ResourceProvider + Send>,
+    /// Vocab resource
+    pub vocab_resource: Box<dyn ResourceProvider + Send>,
+    /// Merges resource (default: None)
+    pub merges_resource: Option<Box<dyn ResourceProvider + Send>>,
+    /// Automatically lower case all input upon tokenization (assumes a lower-cased model)
+    pub lower_case: bool,
+    /// Flag indicating if the tokenizer should strip accents (normalization). Only used for BERT / ALBERT models
+    pub strip_accents: Option<bool>,
+    /// Flag indicating if the tokenizer should add a white space before each tokenized input (needed for some Roberta models)
+    pub add_prefix_space: Option<bool>,
+    /// Optional sequence classification head
+    pub sequence_classification_head: Option<TaskHeadConfig>,
+    /// Optional token classification head
+    pub token_classification_head: Option<TaskHeadConfig>,
+    /// Optional question answering head
+    pub question_answering_head: Option<TaskHeadConfig>,
+    /// Maximum sequence length for the inputs (default: 512)
+    pub max_length: usize,
+    /// Device to place the model on (default: CUDA/GPU when available)
+    pub device: Device,
+}
+
+impl MultiTaskConfig {
+    /// Instantiate a new multi-task configuration of the supplied type, without task heads.
+    ///
+    /// # Arguments
+    ///
+    /// * `model_type` - `ModelType` indicating the model type to load (must match with the actual data to be loaded!)
+    /// * model - The `ResourceProvider` pointing to the encoder weights to load (e.g.  model.ot)
+    /// * config - The `ResourceProvider` pointing to the model configuration to load (e.g. config.json)
+    /// * vocab - The `ResourceProvider` pointing to the tokenizer's vocabulary to load (e.g.  vocab.txt/vocab.json)
+    /// * merges - An optional `ResourceProvider` pointing to the tokenizer's merge file to load (e.g.  merges.txt), needed only for Roberta.
+    /// * lower_case - A `bool` indicating whether the tokenizer should lower case all input (in case of a lower-cased model)
+    pub fn new<R>(
+        model_type: ModelType,
+        model_resource: R,
+        config_resource: R,
+        vocab_resource: R,
+        merges_resource: Option<R>,
+        lower_case: bool,
+        strip_accents: impl Into<Option<bool>>,
+        add_prefix_space: impl Into<Option<bool>>,
+    ) -> MultiTaskConfig
+    where
+        R: ResourceProvider + Send + 'static,
+    {
+        MultiTaskConfig {
+            model_type,
+            model_resource: Box::new(model_resource),
+            config_resource: Box::new(config_resource),
+            vocab_resource: Box::new(vocab_resource),
+            merges_resource: merges_resource.map(|r| Box::new(r) as Box<_>),
+            lower_case,
+            strip_accents: strip_accents.into(),
+            add_prefix_space: add_prefix_space.into(),
+            sequence_classification_head: None,
+            token_classification_head: None,
+            question_answering_head: None,
+            max_length: 512,
+            device: Device::cuda_if_available(),
+        }
+    }
+}
+
+/// # Abstraction that holds the shared encoder of a `MultiTaskModel`
+pub enum MultiTaskEncoderOption {
+    /// BERT encoder
+    Bert(BertModel<BertEmbeddings>),
+    /// RoBERTa (or XLM-RoBERTa) encoder
+    Roberta(BertModel<RobertaEmbeddings>),
+}
+
+impl MultiTaskEncoderOption {
+    /// Instantiate a new shared encoder of the supplied type.
+    ///
+    /// # Arguments
+    ///
+    /// * `model_type` - `ModelType` indicating the model type to load (must match with the actual data to be loaded)
+    /// * `p` - `tch::nn::Path` path to the model file to load (e.g. model.ot)
+    /// * `config` - BERT configuration of the encoder
+    pub fn new<'p, P>(
+        model_type: ModelType,
+        p: P,
+        config: &BertConfig,
+    ) -> Result<Self, RustBertError>
+    where
+        P: Borrow<nn::Path<'p>>,
+    {
+        let p = p.borrow();
+        match model_type {
+            ModelType::Bert => Ok(MultiTaskEncoderOption::Bert(BertModel::new(
+                p / "bert",
+                config,
+            ))),
+            ModelType::Roberta | ModelType::XLMRoberta => Ok(MultiTaskEncoderOption::Roberta(
+                BertModel::new_with_optional_pooler(p / "roberta", config, false),
+            )),
+            _ => Err(RustBertError::InvalidConfigurationError(format!(
+                "Multi-task models not implemented for {:?}!",
+                model_type
+            ))),
+        }
+    }
+
+    /// Returns the `ModelType` for this MultiTaskEncoderOption
+    pub fn model_type(&self) -> ModelType {
+        match *self {
+            Self::Bert(_) => ModelType::Bert,
+            Self::Roberta(_) => ModelType::Roberta,
+        }
+    }
+
+    /// Runs the shared encoder, returning the hidden states of all tokens and the sequence representation
+    /// (pooled output if available, hidden state of the first token otherwise).
+    pub fn forward_t(
+        &self,
+        input_ids: &Tensor,
+        mask: &Tensor,
+        token_type_ids: &Tensor,
+        train: bool,
+    ) -> Result<(Tensor, Tensor), RustBertError> {
+        let output = match *self {
+            Self::Bert(ref model) => model.forward_t(
+                Some(input_ids),
+                Some(mask),
+                Some(token_type_ids),
+                None,
+                None,
+                None,
+                None,
+                train,
+            )?,
+            Self::Roberta(ref model) => model.forward_t(
+                Some(input_ids),
+                Some(mask),
+                None,
+                None,
+                None,
+                None,
+                None,
+                train,
+            )?,
+        };
+        let sequence_output = match output.pooled_output {
+            Some(pooled_output) => pooled_output,
+            None => output.hidden_state.select(1, 0),
+        };
+        Ok((output.hidden_state, sequence_output))
+    }
+}
+
+/// Linear task head with its own variable store
+struct TaskHead {
+    linear: nn::Linear,
+    label_mapping: HashMap<i64, String>,
+    _var_store: VarStore,
+}
+
+impl TaskHead {
+    fn new(
+        config: TaskHeadConfig,
+        name: &str,
+        hidden_size: i64,
+        num_outputs: i64,
+        device: Device,
+    ) -> Result<TaskHead, RustBertError> {
+        let mut var_store = VarStore::new(device);
+        let linear = nn::linear(
+            var_store.root() / name,
+            hidden_size,
+            num_outputs,
+            Default::default(),
+        );
+        crate::resources::load_weights(&config.weights_resource, &mut var_store)?;
+        Ok(TaskHead {
+            linear,
+            label_mapping: config.label_mapping,
+            _var_store: var_store,
+        })
+    }
+
+    fn get_label(&self, label_index: i64) -> String {
+        self.label_mapping
+            .get(&label_index)
+            .cloned()
+            .unwrap_or_else(|| label_index.to_string())
+    }
+}
+
+/// # Input of a `MultiTaskModel`, routed to the corresponding task head
+pub enum TaskInput<'a> {
+    /// Text to classify
+    SequenceClassification(&'a str),
+    /// Text to label at the token level (e.g. named entities)
+    TokenClassification(&'a str),
+    /// Question and context for extractive question answering
+    QuestionAnswering(&'a QaInput),
+}
+
+/// # Output of a `MultiTaskModel`, matching the task of the input
+#[derive(Debug, Clone)]
+pub enum TaskOutput {
+    /// Most likely label for the input text
+    SequenceClassification(Label),
+    /// Labelled tokens of the input text (excluding special tokens)
+    TokenClassification(Vec<Token>),
+    /// Best answer span in the context, if any
+    QuestionAnswering(Option<Answer>),
+}
+
+/// # MultiTaskModel running several task heads on a shared encoder
+pub struct MultiTaskModel {
+    tokenizer: TokenizerOption,
+    encoder: MultiTaskEncoderOption,
+    sequence_classification_head: Option<TaskHead>,
+    token_classification_head: Option<TaskHead>,
+    question_answering_head: Option<TaskHead>,
+    max_length: usize,
+    var_store: VarStore,
+}
+
+impl MultiTaskModel {
+    /// Build a new `MultiTaskModel`
+    ///
+    /// # Arguments
+    ///
+    /// * `config` - `MultiTaskConfig` object containing the resource references (encoder, heads, vocabulary, configuration) and device placement (CPU/GPU)
+    pub fn new(config: MultiTaskConfig) -> Result<MultiTaskModel, RustBertError> {
+        let config_path = config.config_resource.get_local_path()?;
+        let vocab_path = config.vocab_resource.get_local_path()?;
+        let merges_path = if let Some(merges_resource) = &config.merges_resource {
+            Some(merges_resource.get_local_path()?)
+        } else {
+            None
+        };
+        let device = config.device;
+
+        let tokenizer = TokenizerOption::from_file(
+            config.model_type,
+            vocab_path.to_str().unwrap(),
+            merges_path.as_deref().map(|path| path.to_str().unwrap()),
+            config.lower_case,
+            config.strip_accents,
+            config.add_prefix_space,
+        )?;
+        let mut var_store = VarStore::new(device);
+        let model_config = BertConfig::from_file(config_path);
+        let encoder =
+            MultiTaskEncoderOption::new(config.model_type, &var_store.root(), &model_config)?;
+        crate::resources::load_weights(&config.model_resource, &mut var_store)?;
+
+        let hidden_size = model_config.hidden_size;
+        let sequence_classification_head = config
+            .sequence_classification_head
+            .map(|head_config| {
+                let num_labels = head_config.label_mapping.len() as i64;
+                TaskHead::new(head_config, "classifier", hidden_size, num_labels, device)
+            })
+            .transpose()?;
+        let token_classification_head = config
+            .token_classification_head
+            .map(|head_config| {
+                let num_labels = head_config.label_mapping.len() as i64;
+                TaskHead::new(head_config, "classifier", hidden_size, num_labels, device)
+            })
+            .transpose()?;
+        let question_answering_head = config
+            .question_answering_head
+            .map(|head_config| TaskHead::new(head_config, "qa_outputs", hidden_size, 2, device))
+            .transpose()?;
+
+        Ok(MultiTaskModel {
+            tokenizer,
+            encoder,
+            sequence_classification_head,
+            token_classification_head,
+            question_answering_head,
+            max_length: config.max_length,
+            var_store,
+        })
+    }
+
+    fn get_head(&self, input: &TaskInput) -> Result<&TaskHead, RustBertError> {
+        let (head, task) = match input {
+            TaskInput::SequenceClassification(_) => (
+                &self.sequence_classification_head,
+                "sequence classification",
+            ),
+            TaskInput::TokenClassification(_) => {
+                (&self.token_classification_head, "token classification")
+            }
+            TaskInput::QuestionAnswering(_) => {
+                (&self.question_answering_head, "question answering")
+            }
+        };
+        head.as_ref().ok_or_else(|| {
+            RustBertError::InvalidConfigurationError(format!(
+                "No {} head was provided for this model",
+                task
+            ))
+        })
+    }
+
+    fn encode(&self, input: &TaskInput) -> TokenizedInput {
+        match input {
+            TaskInput::SequenceClassification(text) | TaskInput::TokenClassification(text) => self
+                .tokenizer
+                .encode_list(
+                    &[*text],
+                    self.max_length,
+                    &TruncationStrategy::LongestFirst,
+                    0,
+                )
+                .pop()
+                .unwrap(),
+            TaskInput::QuestionAnswering(qa_input) => self
+                .tokenizer
+                .encode_pair_list(
+                    &[(qa_input.question.as_str(), qa_input.context.as_str())],
+                    self.max_length,
+                    &TruncationStrategy::OnlySecond,
+                    0,
+                )
+                .pop()
+                .unwrap(),
+        }
+    }
+
+    /// Runs the task heads for a batch of (possibly mixed) task inputs
+    ///
+    /// # Arguments
+    ///
+    /// * `inputs` - `&[TaskInput]` Array of inputs, each routed to the head of its task
+    ///
+    /// # Returns
+    ///
+    /// * `Vec<TaskOutput>` containing the output of each input, in the same order. Fails if no head was provided for the task of an input.
+    pub fn predict(&self, inputs: &[TaskInput]) -> Result<Vec<TaskOutput>, RustBertError> {
+        if inputs.is_empty() {
+            return Ok(vec![]);
+        }
+        for input in inputs {
+            self.get_head(input)?;
+        }
+
+        let tokenized_inputs = inputs
+            .iter()
+            .map(|input| self.encode(input))
+            .collect::<Vec<TokenizedInput>>();
+        let max_len = tokenized_inputs
+            .iter()
+            .map(|input| input.token_ids.len())
+            .max()
+            .unwrap();
+        let pad_id = self
+            .tokenizer
+            .get_pad_id()
+            .expect("The Tokenizer used for multi-task models should contain a PAD id");
+        let device = self.var_store.device();
+
+        let mut input_ids = Vec::with_capacity(tokenized_inputs.len());
+        let mut token_type_ids = Vec::with_capacity(tokenized_inputs.len());
+        let mut attention_masks = Vec::with_capacity(tokenized_inputs.len());
+        for input in tokenized_inputs.iter() {
+            let mut ids = input.token_ids.clone();
+            ids.resize(max_len, pad_id);
+            input_ids.push(Tensor::of_slice(&ids));
+            let mut segment_ids = input
+                .segment_ids
+                .iter()
+                .map(|&segment_id| segment_id as i64)
+                .collect::<Vec<i64>>();
+            segment_ids.resize(max_len, 0);
+            token_type_ids.push(Tensor::of_slice(&segment_ids));
+            let mut attention_mask = vec![1i64; input.token_ids.len()];
+            attention_mask.resize(max_len, 0);
+            attention_masks.push(Tensor::of_slice(&attention_mask));
+        }
+        let input_ids = Tensor::stack(&input_ids, 0).to(device);
+        let token_type_ids = Tensor::stack(&token_type_ids, 0).to(device);
+        let attention_masks = Tensor::stack(&attention_masks, 0).to(device);
+
+        no_grad(|| {
+            let (hidden_states, sequence_output) =
+                self.encoder
+                    .forward_t(&input_ids, &attention_masks, &token_type_ids, false)?;
+
+            let mut outputs = Vec::with_capacity(inputs.len());
+            for (input_index, (input, tokenized_input)) in
+                inputs.iter().zip(tokenized_inputs.iter()).enumerate()
+            {
+                let head = self.get_head(input)?;
+                let output = match input {
+                    TaskInput::SequenceClassification(_) => {
+                        TaskOutput::SequenceClassification(Self::decode_sequence_classification(
+                            head,
+                            &sequence_output.get(input_index as i64),
+                            input_index,
+                        ))
+                    }
+                    TaskInput::TokenClassification(text) => {
+                        TaskOutput::TokenClassification(Self::decode_token_classification(
+                            head,
+                            &hidden_states.get(input_index as i64),
+                            tokenized_input,
+                            text,
+                            input_index,
+                        ))
+                    }
+                    TaskInput::QuestionAnswering(qa_input) => {
+                        TaskOutput::QuestionAnswering(Self::decode_question_answering(
+                            head,
+                            &hidden_states.get(input_index as i64),
+                            tokenized_input,
+                            &qa_input.context,
+                        ))
+                    }
+                };
+                outputs.push(output);
+            }
+            Ok(outputs)
+        })
+    }
+
+    fn decode_sequence_classification(
+        head: &TaskHead,
+        sequence_output: &Tensor,
+        input_index: usize,
+    ) -> Label {
+        let scores = head
+            .linear
+            .forward(sequence_output)
+            .softmax(-1, Kind::Float);
+        let label_index = scores.argmax(-1, false).int64_value(&[]);
+        Label {
+            text: head.get_label(label_index),
+            score: scores.double_value(&[label_index]),
+            id: label_index,
+            sentence: input_index,
+        }
+    }
+
+    fn decode_token_classification(
+        head: &TaskHead,
+        hidden_states: &Tensor,
+        tokenized_input: &TokenizedInput,
+        text: &str,
+        input_index: usize,
+    ) -> Vec<Token> {
+        let (scores, label_indices) = head
+            .linear
+            .forward(hidden_states)
+            .softmax(-1, Kind::Float)
+            .max_dim(-1, false);
+        let original_chars = text.chars().collect::<Vec<char>>();
+        let offset_converter = OffsetConverter::new(text);
+
+        let mut tokens = vec![];
+        let mut word_index = 0u16;
+        for (position, (offset, mask)) in tokenized_input
+            .token_offsets
+            .iter()
+            .zip(tokenized_input.mask.iter())
+            .enumerate()
+        {
+            let offset = match (offset, mask) {
+                (_, Mask::Special) | (None, _) => continue,
+                (Some(offset), _) => *offset,
+            };
+            if *mask != Mask::Continuation {
+                word_index += 1;
+            }
+            let end_char = (offset.end as usize).min(original_chars.len());
+            let begin_char = (offset.begin as usize).min(end_char);
+            let label_index = label_indices.int64_value(&[position as i64]);
+            tokens.push(Token {
+                text: original_chars[begin_char..end_char].iter().collect(),
+                score: scores.double_value(&[position as i64]),
+                label: head.get_label(label_index),
+                label_index,
+                sentence: input_index,
+                index: position as u16,
+                word_index,
+                offset: Some(offset),
+                byte_offset: Some(offset_converter.to_byte_offset(offset)),
+                utf16_offset: Some(offset_converter.to_utf16_offset(offset)),
+                mask: *mask,
+            });
+        }
+        tokens
+    }
+
+    fn decode_question_answering(
+        head: &TaskHead,
+        hidden_states: &Tensor,
+        tokenized_input: &TokenizedInput,
+        context: &str,
+    ) -> Option<Answer> {
+        // Only the tokens of the context (second sequence) can be part of the answer
+        let context_positions = tokenized_input
+            .segment_ids
+            .iter()
+            .zip(tokenized_input.token_offsets.iter())
+            .zip(tokenized_input.mask.iter())
+            .map(|((segment_id, offset), mask)| {
+                *segment_id == 1 && offset.is_some() && *mask != Mask::Special
+            })
+            .collect::<Vec<bool>>();
+        if !context_positions.iter().any(|is_context| *is_context) {
+            return None;
+        }
+        let num_tokens = context_positions.len() as i64;
+        let device = hidden_states.device();
+        let p_mask = Tensor::of_slice(&context_positions)
+            .logical_not()
+            .to(device);
+
+        let logits = head
+            .linear
+            .forward(&hidden_states.slice(0, 0, num_tokens, 1));
+        let start_scores = logits
+            .select(-1, 0)
+            .masked_fill(&p_mask, f64::NEG_INFINITY)
+            .softmax(-1, Kind::Float);
+        let end_scores = logits
+            .select(-1, 1)
+            .masked_fill(&p_mask, f64::NEG_INFINITY)
+            .softmax(-1, Kind::Float);
+        let span_scores = start_scores
+            .unsqueeze(-1)
+            .matmul(&end_scores.unsqueeze(0))
+            .triu(0)
+            .tril(MAX_ANSWER_LENGTH - 1);
+        let flat_index = span_scores.view(-1).argmax(0, false).int64_value(&[]);
+        let (start_position, end_position) = (flat_index / num_tokens, flat_index % num_tokens);
+        let score = span_scores.double_value(&[start_position, end_position]);
+
+        let start = tokenized_input.token_offsets[start_position as usize]?.begin;
+        let end = tokenized_input.token_offsets[end_position as usize]?.end;
+        let char_offset = Offset::new(start, end);
+        let offset_converter = OffsetConverter::new(context);
+        let byte_offset = offset_converter.to_byte_offset(char_offset);
+        let utf16_offset = offset_converter.to_utf16_offset(char_offset);
+        Some(Answer {
+            score,
+            start: start as usize,
+            end: end as usize,
+            byte_start: byte_offset.begin as usize,
+            byte_end: byte_offset.end as usize,
+            utf16_start: utf16_offset.begin as usize,
+            utf16_end: utf16_offset.end as usize,
+            answer: context[byte_offset.begin as usize..byte_offset.end as usize].to_string(),
+        })
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    #[ignore] // no need to run, compilation is enough to verify it is Send
+    fn test() {
+        let config = MultiTaskConfig::new(
+            ModelType::Bert,
+            crate::resources::LocalResource {
+                local_path: Default::default(),
+            },
+            crate::resources::LocalResource {
+                local_path: Default::default(),
+            },
+            crate::resources::LocalResource {
+                local_path: Default::default(),
+            },
+            None,
+            false,
+            None,
+            None,
+        );
+        let _: Box<dyn Send> = Box::new(MultiTaskModel::new(config));
+    }
+}
@@ -0,0 +1,325 @@
+// Copyright 2022 Guillaume Becquin
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//     http://www.apache.org/licenses/LICENSE-2.0
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! # Natural Language Inference pipeline
+//! Predicts if a premise entails, contradicts or is neutral to a hypothesis, using a model fine-tuned on a Natural
+//! Language Inference dataset (e.g. MNLI or XNLI). The model architectures and pretrained models supported are the
+//! ones of the zero-shot classification pipeline, and the default model is a BART model fine-tuned on MNLI.
+//!
+//! The position of the entailment, neutral and contradiction classes is read from the label dictionary (`id2label`)
+//! of the model configuration. Models trained on a binary task (entailment / not entailment) are supported, the
+//! non-entailment class being reported as a contradiction with a probability of 0 for the neutral class.
+//! The premise-hypothesis pairs are processed by batches of `batch_size` (default: 32).
+//!
+//! ```no_run
+//! # fn main() -> anyhow::Result<()> {
+//! use rust_bert::pipelines::natural_language_inference::NaturalLanguageInferenceModel;
+//!
+//! let nli_model = NaturalLanguageInferenceModel::new(Default::default())?;
+//!
+//! let output = nli_model.predict(&[
+//!     ("A soccer game with multiple males playing.", "Some men are playing a sport."),
+//!     ("A man inspects the uniform of a figure.", "The man is sleeping."),
+//! ])?;
+//! # Ok(())
+//! # }
+//! ```
+//!
+//! outputs:
+//! ```no_run
+//! # use rust_bert::pipelines::natural_language_inference::{InferenceLabel, InferencePrediction};
+//! let output = [
+//!     InferencePrediction {
+//!         label: InferenceLabel::Entailment,
+//!         entailment: 0.985,
+//!         neutral: 0.013,
+//!         contradiction: 0.002,
+//!     },
+//!     InferencePrediction {
+//!         label: InferenceLabel::Contradiction,
+//!         entailment: 0.001,
+//!         neutral: 0.004,
+//!         contradiction: 0.995,
+//!     },
+//! ]
+//! .to_vec();
+//! ```
+
+use crate::common::error::RustBertError;
+use crate::pipelines::common::{ConfigOption, ModelType, Pipeline, TokenizerOption};
+use crate::pipelines::zero_shot_classification::{
+    InferenceLabelIndices, ZeroShotClassificationConfig, ZeroShotClassificationOption,
+};
+use rust_tokenizers::tokenizer::TruncationStrategy;
+use rust_tokenizers::TokenizedInput;
+use serde::{Deserialize, Serialize};
+use tch::kind::Kind::{Bool, Float};
+use tch::nn::VarStore;
+use tch::{no_grad, Tensor};
+
+/// # Configuration for NaturalLanguageInferenceModel
+/// Identical to the zero-shot classification configuration: any model fine-tuned for Natural Language Inference
+/// supported by the zero-shot classification pipeline can be used.
+pub type NaturalLanguageInferenceConfig = ZeroShotClassificationConfig;
+
+/// # Relation between a premise and a hypothesis
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum InferenceLabel {
+    /// The premise entails the hypothesis
+    Entailment,
+    /// The premise neither entails nor contradicts the hypothesis
+    Neutral,
+    /// The premise contradicts the hypothesis
+    Contradiction,
+}
+
+/// # Output of the Natural Language Inference pipeline
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct InferencePrediction {
+    /// Most likely relation between the premise and the hypothesis
+    pub label: InferenceLabel,
+    /// Probability of entailment
+    pub entailment: f64,
+    /// Probability of the neutral relation (0 for models without a neutral class)
+    pub neutral: f64,
+    /// Probability of contradiction
+    pub contradiction: f64,
+}
+
+/// # NaturalLanguageInferenceModel for premise-hypothesis classification
+pub struct NaturalLanguageInferenceModel {
+    tokenizer: TokenizerOption,
+    nli_classifier: ZeroShotClassificationOption,
+    label_indices: InferenceLabelIndices,
+    batch_size: usize,
+    max_length: usize,
+    var_store: VarStore,
+}
+
+impl NaturalLanguageInferenceModel {
+    /// Build a new `NaturalLanguageInferenceModel`
+    ///
+    /// # Arguments
+    ///
+    /// * `config` - `NaturalLanguageInferenceConfig` object containing the resource references (model, vocabulary, configuration) and device placement (CPU/GPU)
+    ///
+    /// # Example
+    ///
+    /// ```no_run
+    /// # fn main() -> anyhow::Result<()> {
+    /// use rust_bert::pipelines::natural_language_inference::NaturalLanguageInferenceModel;
+    ///
+    /// let model = NaturalLanguageInferenceModel::new(Default::default())?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn new(
+        config: NaturalLanguageInferenceConfig,
+    ) -> Result<NaturalLanguageInferenceModel, RustBertError> {
+        let config_path = config.config_resource.get_local_path()?;
+        let vocab_path = config.vocab_resource.get_local_path()?;
+        let merges_path = if let Some(merges_resource) = &config.merges_resource {
+            Some(merges_resource.get_local_path()?)
+        } else {
+            None
+        };
+        let device = config.device;
+
+        let tokenizer = TokenizerOption::from_file(
+            config.model_type,
+            vocab_path.to_str().unwrap(),
+            merges_path.as_deref().map(|path| path.to_str().unwrap()),
+            config.lower_case,
+            config.strip_accents,
+            config.add_prefix_space,
+        )?;
+        let mut var_store = VarStore::new(device);
+        let model_config = ConfigOption::from_file(config.model_type, config_path)?;
+        model_config.validate(Some(&tokenizer), None)?;
+        let label_indices =
+            InferenceLabelIndices::from_label_mapping(model_config.get_label_mapping())?;
+        let nli_classifier =
+            ZeroShotClassificationOption::new(config.model_type, &var_store.root(), &model_config)?;
+        crate::resources::load_weights(&config.model_resource, &mut var_store)?;
+        Ok(NaturalLanguageInferenceModel {
+            tokenizer,
+            nli_classifier,
+            label_indices,
+            batch_size: 32,
+            max_length: 512,
+            var_store,
+        })
+    }
+
+    /// Sets the number of premise-hypothesis pairs processed at once
+    ///
+    /// # Arguments
+    ///
+    /// * `batch_size` - Maximum number of pairs in a batch (default: 32)
+    pub fn set_batch_size(&mut self, batch_size: usize) {
+        self.batch_size = batch_size.max(1);
+    }
+
+    /// Sets the maximum length of the encoded pairs. Longer pairs are truncated, starting with the longest sequence.
+    ///
+    /// # Arguments
+    ///
+    /// * `max_length` - Maximum length in tokens of a premise-hypothesis pair (default: 512)
+    pub fn set_max_length(&mut self, max_length: usize) {
+        self.max_length = max_length;
+    }
+
+    fn prepare_for_model(&self, pairs: &[(&str, &str)]) -> (Tensor, Tensor, Option<Tensor>) {
+        let tokenized_input: Vec<TokenizedInput> = self.tokenizer.encode_pair_list(
+            pairs,
+            self.max_length,
+            &TruncationStrategy::LongestFirst,
+            0,
+        );
+        let max_len = tokenized_input
+            .iter()
+            .map(|input| input.token_ids.len())
+            .max()
+            .unwrap();
+        let pad_id = self
+            .tokenizer
+            .get_pad_id()
+            .expect("The Tokenizer used for natural language inference should contain a PAD id");
+        let device = self.var_store.device();
+
+        // Only the models trained with segment embeddings for sentence pairs use the token type ids
+        let use_token_type_ids = matches!(
+            self.nli_classifier.model_type(),
+            ModelType::Bert | ModelType::Albert
+        );
+        let mut input_ids = Vec::with_capacity(tokenized_input.len());
+        let mut token_type_ids = Vec::with_capacity(tokenized_input.len());
+        for mut input in tokenized_input {
+            input.token_ids.resize(max_len, pad_id);
+            input_ids.push(Tensor::of_slice(&input.token_ids));
+            if use_token_type_ids {
+                let mut segment_ids = input
+                    .segment_ids
+                    .iter()
+                    .map(|&segment_id| segment_id as i64)
+                    .collect::<Vec<i64>>();
+                segment_ids.resize(max_len, 0);
+                token_type_ids.push(Tensor::of_slice(&segment_ids));
+            }
+        }
+        let input_ids = Tensor::stack(&input_ids, 0).to(device);
+        let mask = input_ids.ne(pad_id).to_kind(Bool);
+        let token_type_ids = if use_token_type_ids {
+            Some(Tensor::stack(&token_type_ids, 0).to(device))
+        } else {
+            None
+        };
+        (input_ids, mask, token_type_ids)
+    }
+
+    /// Predicts the relation between premises and hypotheses
+    ///
+    /// # Arguments
+    ///
+    /// * `pairs` - `&[(premise, hypothesis)]` Array of premise-hypothesis pairs
+    ///
+    /// # Returns
+    ///
+    /// * `Vec<InferencePrediction>` containing the most likely relation and the probability of each relation for each pair
+    ///
+    /// # Example
+    ///
+    /// ```no_run
+    /// # fn main() -> anyhow::Result<()> {
+    /// use rust_bert::pipelines::natural_language_inference::NaturalLanguageInferenceModel;
+    ///
+    /// let nli_model = NaturalLanguageInferenceModel::new(Default::default())?;
+    /// let output = nli_model.predict(&[(
+    ///     "The prime minister announced a stimulus package.",
+    ///     "The government is taking economic measures.",
+    /// )])?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn predict<P, H>(&self, pairs: &[(P, H)]) -> Result<Vec<InferencePrediction>, RustBertError>
+    where
+        P: AsRef<str>,
+        H: AsRef<str>,
+    {
+        let mut predictions = Vec::with_capacity(pairs.len());
+        for batch in pairs.chunks(self.batch_size) {
+            let batch = batch
+                .iter()
+                .map(|(premise, hypothesis)| (premise.as_ref(), hypothesis.as_ref()))
+                .collect::<Vec<(&str, &str)>>();
+            let (input_ids, mask, token_type_ids) = self.prepare_for_model(&batch);
+            let probabilities = no_grad(|| {
+                self.nli_classifier
+                    .forward_t(
+                        Some(&input_ids),
+                        Some(&mask),
+                        token_type_ids.as_ref(),
+                        None,
+                        None,
+                        false,
+                    )
+                    .softmax(-1, Float)
+            });
+            let class_probabilities = |index: i64| {
+                probabilities
+                    .select(1, index)
+                    .iter::<f64>()
+                    .unwrap()
+                    .collect::<Vec<f64>>()
+            };
+            let entailment = class_probabilities(self.label_indices.entailment);
+            let contradiction = class_probabilities(self.label_indices.contradiction);
+            let neutral = match self.label_indices.neutral {
+                Some(neutral) => class_probabilities(neutral),
+                None => vec![0f64; batch.len()],
+            };
+            predictions.extend(entailment.into_iter().zip(neutral).zip(contradiction).map(
+                |((entailment, neutral), contradiction)| {
+                    let label = if entailment >= neutral && entailment >= contradiction {
+                        InferenceLabel::Entailment
+                    } else if neutral >= contradiction {
+                        InferenceLabel::Neutral
+                    } else {
+                        InferenceLabel::Contradiction
+                    };
+                    InferencePrediction {
+                        label,
+                        entailment,
+                        neutral,
+                        contradiction,
+                    }
+                },
+            ));
+        }
+        Ok(predictions)
+    }
+}
+
+impl<P, H> Pipeline<(P, H), InferencePrediction> for NaturalLanguageInferenceModel
+where
+    P: AsRef<str>,
+    H: AsRef<str>,
+{
+    type Options = ();
+
+    fn run_with_options(
+        &self,
+        inputs: &[(P, H)],
+        _options: &(),
+    ) -> Result<Vec<InferencePrediction>, RustBertError> {
+        self.predict(inputs)
+    }
+}
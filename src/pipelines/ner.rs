@@ -51,12 +51,16 @@
 //!             score: 0.9986,
 //!             label: String::from("I-PER"),
 //!             offset: Offset { begin: 11, end: 14 },
+//!             byte_offset: Offset { begin: 11, end: 14 },
+//!             utf16_offset: Offset { begin: 11, end: 14 },
 //!         },
 //!         Entity {
 //!             word: String::from("Paris"),
 //!             score: 0.9985,
 //!             label: String::from("I-LOC"),
 //!             offset: Offset { begin: 26, end: 31 },
+//!             byte_offset: Offset { begin: 26, end: 31 },
+//!             utf16_offset: Offset { begin: 26, end: 31 },
 //!         },
 //!     ],
 //!     [
@@ -65,12 +69,16 @@
 //!             score: 0.9988,
 //!             label: String::from("I-LOC"),
 //!             offset: Offset { begin: 0, end: 5 },
+//!             byte_offset: Offset { begin: 0, end: 5 },
+//!             utf16_offset: Offset { begin: 0, end: 5 },
 //!         },
 //!         Entity {
 //!             word: String::from("France"),
 //!             score: 0.9993,
 //!             label: String::from("I-LOC"),
 //!             offset: Offset { begin: 19, end: 25 },
+//!             byte_offset: Offset { begin: 19, end: 25 },
+//!             utf16_offset: Offset { begin: 19, end: 25 },
 //!         },
 //!     ],
 //! ]
@@ -127,6 +135,8 @@
 //! Dutch| XLM_ROBERTA_NER_NL |
 
 use crate::common::error::RustBertError;
+use crate::pipelines::common::Pipeline;
+use crate::pipelines::shared_encoder::SharedEncoder;
 use crate::pipelines::token_classification::{
     Token, TokenClassificationConfig, TokenClassificationModel,
 };
@@ -142,8 +152,12 @@ pub struct Entity {
     pub score: f64,
     /// Entity label (e.g. ORG, LOC...)
     pub label: String,
-    /// Token offsets
+    /// Entity offsets (in characters of the original string)
     pub offset: Offset,
+    /// Entity offsets in bytes of the original string
+    pub byte_offset: Offset,
+    /// Entity offsets in UTF-16 code units of the original string
+    pub utf16_offset: Offset,
 }
 
 //type alias for some backward compatibility
@@ -178,6 +192,22 @@ impl NERModel {
         })
     }
 
+    /// Build a new `NERModel` sharing the encoder weights of a `SharedEncoder`
+    ///
+    /// # Arguments
+    ///
+    /// * `ner_config` - `NERConfig` object containing the resource references (task head weights, vocabulary, configuration) and device placement (CPU/GPU)
+    /// * `shared_encoder` - `SharedEncoder` holding the encoder weights
+    pub fn new_with_shared_encoder(
+        ner_config: NERConfig,
+        shared_encoder: &SharedEncoder,
+    ) -> Result<NERModel, RustBertError> {
+        let model = TokenClassificationModel::new_with_shared_encoder(ner_config, shared_encoder)?;
+        Ok(NERModel {
+            token_classification_model: model,
+        })
+    }
+
     /// Extract entities from a text
     ///
     /// # Arguments
@@ -216,6 +246,8 @@ impl NERModel {
                     .filter(|token| token.label != "O")
                     .map(|token| Entity {
                         offset: token.offset.unwrap(),
+                        byte_offset: token.byte_offset.unwrap(),
+                        utf16_offset: token.utf16_offset.unwrap(),
                         word: token.text,
                         score: token.score,
                         label: token.label,
@@ -264,12 +296,16 @@ impl NERModel {
     ///         score: 0.9747,
     ///         label: String::from("PER"),
     ///         offset: Offset { begin: 6, end: 16 },
+    ///         byte_offset: Offset { begin: 6, end: 16 },
+    ///         utf16_offset: Offset { begin: 6, end: 16 },
     ///     },
     ///     Entity {
     ///         word: String::from("Acme Corp"),
     ///         score: 0.8847,
     ///         label: String::from("I-LOC"),
     ///         offset: Offset { begin: 23, end: 32 },
+    ///         byte_offset: Offset { begin: 23, end: 32 },
+    ///         utf16_offset: Offset { begin: 23, end: 32 },
     ///     },
     /// ]]
     /// # ;
@@ -362,6 +398,14 @@ impl<'a> EntityBuilder<'a> {
                     begin: entity_tokens.first()?.offset?.begin,
                     end: entity_tokens.last()?.offset?.end,
                 },
+                byte_offset: Offset {
+                    begin: entity_tokens.first()?.byte_offset?.begin,
+                    end: entity_tokens.last()?.byte_offset?.end,
+                },
+                utf16_offset: Offset {
+                    begin: entity_tokens.first()?.utf16_offset?.begin,
+                    end: entity_tokens.last()?.utf16_offset?.end,
+                },
             })
         } else {
             None
@@ -406,6 +450,21 @@ impl Token {
     }
 }
 
+impl<S> Pipeline<S, Vec<Entity>> for NERModel
+where
+    S: AsRef<str>,
+{
+    type Options = ();
+
+    fn run_with_options(
+        &self,
+        inputs: &[S],
+        _options: &(),
+    ) -> Result<Vec<Vec<Entity>>, RustBertError> {
+        Ok(self.predict(inputs))
+    }
+}
+
 #[cfg(test)]
 mod test {
     use super::*;
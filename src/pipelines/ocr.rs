@@ -0,0 +1,506 @@
+// Copyright 2022 Guillaume Becquin
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//     http://www.apache.org/licenses/LICENSE-2.0
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! # Optical character recognition pipeline
+//! Converts images of text lines or documents to text with a vision encoder-decoder model:
+//! - TrOCR checkpoints read single text lines (printed or handwritten). They use a byte-level BPE tokenizer
+//! (`vocab.json` and `merges.txt`).
+//! - Donut checkpoints read full documents. They use a SentencePiece tokenizer (`sentencepiece.bpe.model`) extended
+//! with the task and field tokens listed in `added_tokens.json`, and the decoder is prompted with a task prompt
+//! (e.g. `<s_cord-v2>` for receipt parsing or `<s_docvqa><s_question>What is the date?</s_question><s_answer>` for
+//! document question answering). The field tokens are kept in the output text (e.g. `<s_nm>Coffee</s_nm>`).
+//!
+//! The images are resized to the model image size (and padded for Donut to preserve their aspect ratio) and the
+//! text is generated with beam search. No pre-trained checkpoint is hosted with the crate, the weights can be
+//! converted with the Python utility scripts and loaded as local resources:
+//!
+//! ```no_run
+//! # fn main() -> anyhow::Result<()> {
+//! use rust_bert::pipelines::ocr::{OcrConfig, OcrModel};
+//! use rust_bert::resources::LocalResource;
+//! use rust_bert::vit::ImageBuffer;
+//! use std::path::PathBuf;
+//!
+//! let config = OcrConfig::new_sentencepiece(
+//!     LocalResource::from(PathBuf::from("path/to/rust_model.ot")),
+//!     LocalResource::from(PathBuf::from("path/to/config.json")),
+//!     LocalResource::from(PathBuf::from("path/to/sentencepiece.bpe.model")),
+//! )
+//! .with_added_tokens(LocalResource::from(PathBuf::from("path/to/added_tokens.json")))
+//! .with_task_prompt("<s_cord-v2>");
+//! let model = OcrModel::new(config)?;
+//!
+//! // Decoded 1280x1920 RGB image
+//! let pixels = vec![255u8; 1280 * 1920 * 3];
+//! let output = model.recognize(&[ImageBuffer::rgb(&pixels, 1280, 1920)])?;
+//! # Ok(())
+//! # }
+//! ```
+
+use crate::common::error::RustBertError;
+use crate::pipelines::common::{ModelType, TokenizerOption};
+use crate::pipelines::generation_utils::{
+    GenerateConfig, GenerateOptions, LanguageGenerator, NoRepeatNgramScope,
+};
+use crate::pipelines::logits_processor::LogitsProcessor;
+use crate::resources::{LocalResource, ResourceProvider};
+use crate::vision_encoder_decoder::{
+    VisionEncoderConfig, VisionEncoderDecoderConfig, VisionEncoderDecoderGenerator,
+};
+use crate::vit::{ImageBuffer, ViTImageProcessor, VIT_IMAGE_MEAN, VIT_IMAGE_STD};
+use crate::Config;
+use std::collections::HashMap;
+use std::fs::File;
+use std::io::BufReader;
+use tch::{Device, Tensor};
+
+/// # Configuration for OcrModel
+/// Contains information regarding the model and tokenizer to load, the task prompt, the generation settings and the
+/// device to place the model on.
+pub struct OcrConfig {
+    /// Model weights resource
+    pub model_resource: Box<dyn ResourceProvider + Send>,
+    /// Config resource
+    pub config_resource: Box<dyn ResourceProvider + Send>,
+    /// Vocab resource (`vocab.json` for TrOCR, `sentencepiece.bpe.model` for Donut)
+    pub vocab_resource: Box<dyn ResourceProvider + Send>,
+    /// Merges resource (`merges.txt`, TrOCR only)
+    pub merges_resource: Option<Box<dyn ResourceProvider + Send>>,
+    /// Tokens added to the vocabulary (`added_tokens.json`, mapping the tokens to their ids)
+    pub added_tokens_resource: Option<Box<dyn ResourceProvider + Send>>,
+    /// Prompt of the decoder, made of added tokens and text (Donut only, default: None)
+    pub task_prompt: Option<String>,
+    /// Number of beams for beam search (default: 4)
+    pub num_beams: i64,
+    /// Maximum number of tokens of the generated sequences, including the task prompt (default: 128)
+    pub max_length: i64,
+    /// Device to place the model on (default: CUDA/GPU when available)
+    pub device: Device,
+}
+
+impl OcrConfig {
+    /// Instantiate a new OCR configuration for a model using a byte-level BPE tokenizer (TrOCR)
+    ///
+    /// # Arguments
+    ///
+    /// * `model_resource` - The `ResourceProvider` pointing to the model weights
+    /// * `config_resource` - The `ResourceProvider` pointing to the model configuration
+    /// * `vocab_resource` - The `ResourceProvider` pointing to the tokenizer vocabulary (`vocab.json`)
+    /// * `merges_resource` - The `ResourceProvider` pointing to the tokenizer merges (`merges.txt`)
+    pub fn new_bpe<R>(
+        model_resource: R,
+        config_resource: R,
+        vocab_resource: R,
+        merges_resource: R,
+    ) -> OcrConfig
+    where
+        R: ResourceProvider + Send + 'static,
+    {
+        OcrConfig {
+            merges_resource: Some(Box::new(merges_resource)),
+            ..OcrConfig::new_sentencepiece(model_resource, config_resource, vocab_resource)
+        }
+    }
+
+    /// Instantiate a new OCR configuration for a model using a SentencePiece tokenizer (Donut)
+    ///
+    /// # Arguments
+    ///
+    /// * `model_resource` - The `ResourceProvider` pointing to the model weights
+    /// * `config_resource` - The `ResourceProvider` pointing to the model configuration
+    /// * `vocab_resource` - The `ResourceProvider` pointing to the SentencePiece model (`sentencepiece.bpe.model`)
+    pub fn new_sentencepiece<R>(
+        model_resource: R,
+        config_resource: R,
+        vocab_resource: R,
+    ) -> OcrConfig
+    where
+        R: ResourceProvider + Send + 'static,
+    {
+        OcrConfig {
+            model_resource: Box::new(model_resource),
+            config_resource: Box::new(config_resource),
+            vocab_resource: Box::new(vocab_resource),
+            merges_resource: None,
+            added_tokens_resource: None,
+            task_prompt: None,
+            num_beams: 4,
+            max_length: 128,
+            device: Device::cuda_if_available(),
+        }
+    }
+
+    /// Sets the tokens added to the vocabulary (`added_tokens.json`)
+    pub fn with_added_tokens<R>(mut self, added_tokens_resource: R) -> Self
+    where
+        R: ResourceProvider + Send + 'static,
+    {
+        self.added_tokens_resource = Some(Box::new(added_tokens_resource));
+        self
+    }
+
+    /// Sets the prompt of the decoder (e.g. `<s_cord-v2>`). The added tokens of the prompt must be listed in the
+    /// added tokens resource.
+    pub fn with_task_prompt(mut self, task_prompt: &str) -> Self {
+        self.task_prompt = Some(task_prompt.to_string());
+        self
+    }
+
+    /// Sets the number of beams for beam search
+    pub fn with_num_beams(mut self, num_beams: i64) -> Self {
+        self.num_beams = num_beams;
+        self
+    }
+
+    /// Sets the maximum number of tokens of the generated sequences
+    pub fn with_max_length(mut self, max_length: i64) -> Self {
+        self.max_length = max_length;
+        self
+    }
+}
+
+/// # OcrModel to convert images to text
+pub struct OcrModel {
+    model: VisionEncoderDecoderGenerator,
+    image_processor: ViTImageProcessor,
+    added_tokens: HashMap<String, i64>,
+    added_token_ids: HashMap<i64, String>,
+    prompt_ids: Vec<i64>,
+    eos_token_id: i64,
+    pad_token_id: i64,
+}
+
+impl OcrModel {
+    /// Build a new `OcrModel`
+    ///
+    /// # Arguments
+    ///
+    /// * `config` - `OcrConfig` object containing the resource references (model, config, tokenizer), the task
+    /// prompt, the generation settings and device placement (CPU/GPU)
+    ///
+    /// # Example
+    ///
+    /// ```no_run
+    /// # fn main() -> anyhow::Result<()> {
+    /// use rust_bert::pipelines::ocr::{OcrConfig, OcrModel};
+    /// use rust_bert::resources::LocalResource;
+    /// use std::path::PathBuf;
+    ///
+    /// let config = OcrConfig::new_bpe(
+    ///     LocalResource::from(PathBuf::from("path/to/rust_model.ot")),
+    ///     LocalResource::from(PathBuf::from("path/to/config.json")),
+    ///     LocalResource::from(PathBuf::from("path/to/vocab.json")),
+    ///     LocalResource::from(PathBuf::from("path/to/merges.txt")),
+    /// );
+    /// let model = OcrModel::new(config)?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn new(config: OcrConfig) -> Result<OcrModel, RustBertError> {
+        let config_path = config.config_resource.get_local_path()?;
+        let model_config = VisionEncoderDecoderConfig::from_file(&config_path);
+        model_config.validate()?;
+
+        let vocab_path = config.vocab_resource.get_local_path()?;
+        let tokenizer = match &config.merges_resource {
+            Some(merges_resource) => TokenizerOption::from_file(
+                ModelType::Roberta,
+                vocab_path.to_str().unwrap(),
+                Some(merges_resource.get_local_path()?.to_str().unwrap()),
+                false,
+                None,
+                false,
+            )?,
+            None => TokenizerOption::from_file(
+                ModelType::XLMRoberta,
+                vocab_path.to_str().unwrap(),
+                None,
+                false,
+                None,
+                None,
+            )?,
+        };
+        let added_tokens: HashMap<String, i64> = match &config.added_tokens_resource {
+            Some(added_tokens_resource) => {
+                let file = File::open(added_tokens_resource.get_local_path()?)?;
+                serde_json::from_reader(BufReader::new(file)).map_err(|e| {
+                    RustBertError::InvalidConfigurationError(format!(
+                        "Invalid added tokens file: {}",
+                        e
+                    ))
+                })?
+            }
+            None => HashMap::new(),
+        };
+        let added_token_ids = added_tokens
+            .iter()
+            .map(|(token, id)| (*id, token.clone()))
+            .collect::<HashMap<i64, String>>();
+
+        let decoder_config = &model_config.decoder;
+        let eos_token_id = model_config
+            .eos_token_id
+            .or(decoder_config.eos_token_id)
+            .unwrap_or(2);
+        let pad_token_id = model_config
+            .pad_token_id
+            .or(decoder_config.pad_token_id)
+            .unwrap_or(1);
+        let prompt_ids = match &config.task_prompt {
+            Some(task_prompt) => {
+                let prompt_ids = split_added_tokens(task_prompt, &added_tokens)
+                    .into_iter()
+                    .flat_map(|(text, added_token_id)| match added_token_id {
+                        Some(id) => vec![id],
+                        None => tokenizer.convert_tokens_to_ids(&tokenizer.tokenize(text)),
+                    })
+                    .collect::<Vec<i64>>();
+                if prompt_ids.is_empty() {
+                    return Err(RustBertError::ValueError(
+                        "The task prompt must not be empty".to_string(),
+                    ));
+                }
+                prompt_ids
+            }
+            None => vec![],
+        };
+
+        let (height, width) = model_config.encoder.image_size();
+        let image_processor =
+            ViTImageProcessor::new(height, VIT_IMAGE_MEAN, VIT_IMAGE_STD, config.device)
+                .with_image_size(height, width)
+                .with_padding(matches!(
+                    model_config.encoder,
+                    VisionEncoderConfig::DonutSwin(_)
+                ));
+
+        // The tokenizer is created above: the merges resource of the generation configuration is not read
+        let merges_resource = config
+            .merges_resource
+            .unwrap_or_else(|| Box::new(LocalResource::from(vocab_path.clone())));
+        let generate_config = GenerateConfig {
+            model_resource: config.model_resource,
+            config_resource: config.config_resource,
+            vocab_resource: config.vocab_resource,
+            merges_resource,
+            min_length: 0,
+            max_length: config.max_length,
+            do_sample: false,
+            early_stopping: true,
+            num_beams: config.num_beams,
+            temperature: 1.0,
+            top_k: 0,
+            top_p: 1.0,
+            repetition_penalty: 1.0,
+            length_penalty: 1.0,
+            no_repeat_ngram_size: 0,
+            no_repeat_ngram_scope: NoRepeatNgramScope::Sequence,
+            num_return_sequences: 1,
+            num_beam_groups: None,
+            diversity_penalty: None,
+            penalty_alpha: None,
+            typical_p: None,
+            epsilon_cutoff: None,
+            eta_cutoff: None,
+            prefill_chunk_size: None,
+            stop_sequences: vec![],
+            logit_bias: HashMap::new(),
+            output_attentions: false,
+            device: config.device,
+        };
+        let model = VisionEncoderDecoderGenerator::new_with_tokenizer(generate_config, tokenizer)?;
+
+        Ok(OcrModel {
+            model,
+            image_processor,
+            added_tokens,
+            added_token_ids,
+            prompt_ids,
+            eos_token_id,
+            pad_token_id,
+        })
+    }
+
+    /// Returns the tokens added to the vocabulary, mapped to their ids
+    pub fn get_added_tokens(&self) -> &HashMap<String, i64> {
+        &self.added_tokens
+    }
+
+    /// Converts images to text
+    ///
+    /// # Arguments
+    ///
+    /// * `images` - Slice of decoded images
+    ///
+    /// # Returns
+    ///
+    /// * `Vec<String>` containing the text read in each image, without the task prompt. The added tokens generated
+    /// (e.g. the field tokens of Donut) are kept in the text.
+    ///
+    /// # Example
+    ///
+    /// ```no_run
+    /// # fn main() -> anyhow::Result<()> {
+    /// # use rust_bert::pipelines::ocr::{OcrConfig, OcrModel};
+    /// # use rust_bert::resources::LocalResource;
+    /// # use std::path::PathBuf;
+    /// use rust_bert::vit::ImageBuffer;
+    /// # let config = OcrConfig::new_bpe(
+    /// #     LocalResource::from(PathBuf::from("path/to/rust_model.ot")),
+    /// #     LocalResource::from(PathBuf::from("path/to/config.json")),
+    /// #     LocalResource::from(PathBuf::from("path/to/vocab.json")),
+    /// #     LocalResource::from(PathBuf::from("path/to/merges.txt")),
+    /// # );
+    /// let model = OcrModel::new(config)?;
+    ///
+    /// // Decoded 640x120 grayscale image of a text line
+    /// let pixels = vec![255u8; 640 * 120];
+    /// let image = ImageBuffer {
+    ///     pixels: &pixels,
+    ///     width: 640,
+    ///     height: 120,
+    ///     channels: 1,
+    /// };
+    /// let text = model.recognize(&[image])?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn recognize(&self, images: &[ImageBuffer]) -> Result<Vec<String>, RustBertError> {
+        if images.is_empty() {
+            return Ok(vec![]);
+        }
+        let pixel_values = self.image_processor.preprocess(images)?;
+
+        // The decoder starts with the first token of the prompt, and the following ones are forced
+        let prompt_ids = &self.prompt_ids;
+        let force_prompt = |input_ids: &Tensor, next_token_logits: &mut Tensor| {
+            let length = input_ids.size()[1] as usize;
+            if length < prompt_ids.len() {
+                let _ = next_token_logits.fill_(f64::NEG_INFINITY);
+                let _ = next_token_logits
+                    .narrow(1, prompt_ids[length], 1)
+                    .fill_(0.0);
+            }
+        };
+        let logits_processors: [&dyn LogitsProcessor; 1] = [&force_prompt];
+        let generate_options = GenerateOptions {
+            decoder_start_token_id: prompt_ids.first().copied(),
+            logits_processors: Some(&logits_processors),
+            ..Default::default()
+        };
+        let output = self
+            .model
+            .generate_from_images(&pixel_values, Some(generate_options))?;
+
+        let prompt_length = self.prompt_ids.len().max(1);
+        Ok(output
+            .into_iter()
+            .map(|generated| {
+                let token_ids = generated
+                    .indices
+                    .into_iter()
+                    .skip(prompt_length)
+                    .take_while(|token_id| *token_id != self.eos_token_id)
+                    .filter(|token_id| *token_id != self.pad_token_id)
+                    .collect::<Vec<i64>>();
+                self.decode(&token_ids)
+            })
+            .collect())
+    }
+
+    /// Decodes the generated tokens, inserting the added tokens verbatim
+    fn decode(&self, token_ids: &[i64]) -> String {
+        let tokenizer = self.model.get_tokenizer();
+        let mut text = String::new();
+        let mut start = 0;
+        for (position, token_id) in token_ids.iter().enumerate() {
+            if let Some(added_token) = self.added_token_ids.get(token_id) {
+                text.push_str(&tokenizer.decode(&token_ids[start..position], true, true));
+                text.push_str(added_token);
+                start = position + 1;
+            }
+        }
+        text.push_str(&tokenizer.decode(&token_ids[start..], true, true));
+        text.trim().to_string()
+    }
+}
+
+/// Splits a text in pieces of regular text and added tokens (returned with their id), the longest added tokens first
+fn split_added_tokens<'a>(
+    text: &'a str,
+    added_tokens: &HashMap<String, i64>,
+) -> Vec<(&'a str, Option<i64>)> {
+    let mut sorted_tokens = added_tokens.iter().collect::<Vec<(&String, &i64)>>();
+    sorted_tokens.sort_by(|(token_a, _), (token_b, _)| token_b.len().cmp(&token_a.len()));
+
+    let mut pieces = vec![];
+    let mut text_start = 0;
+    let mut position = 0;
+    while position < text.len() {
+        let matched_token = sorted_tokens
+            .iter()
+            .find(|(token, _)| !token.is_empty() && text[position..].starts_with(token.as_str()));
+        match matched_token {
+            Some((token, id)) => {
+                if text_start < position {
+                    pieces.push((&text[text_start..position], None));
+                }
+                pieces.push((&text[position..position + token.len()], Some(**id)));
+                position += token.len();
+                text_start = position;
+            }
+            None => {
+                position += text[position..].chars().next().unwrap().len_utf8();
+            }
+        }
+    }
+    if text_start < text.len() {
+        pieces.push((&text[text_start..], None));
+    }
+    pieces
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_split_added_tokens() {
+        let added_tokens: HashMap<String, i64> = [
+            ("<s_docvqa>".to_string(), 57523),
+            ("<s_question>".to_string(), 57524),
+            ("</s_question>".to_string(), 57525),
+            ("<s_answer>".to_string(), 57526),
+        ]
+        .iter()
+        .cloned()
+        .collect();
+
+        let pieces = split_added_tokens(
+            "<s_docvqa><s_question>Quelle est la date ?</s_question><s_answer>",
+            &added_tokens,
+        );
+        assert_eq!(
+            pieces,
+            vec![
+                ("<s_docvqa>", Some(57523)),
+                ("<s_question>", Some(57524)),
+                ("Quelle est la date ?", None),
+                ("</s_question>", Some(57525)),
+                ("<s_answer>", Some(57526)),
+            ]
+        );
+        assert_eq!(
+            split_added_tokens("no added token", &added_tokens),
+            vec![("no added token", None)]
+        );
+    }
+}
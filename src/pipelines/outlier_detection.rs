@@ -0,0 +1,407 @@
+// Copyright 2022 Guillaume Becquin
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//     http://www.apache.org/licenses/LICENSE-2.0
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! # Outlier detection for text streams
+//! Monitors the inputs of a deployed pipeline and flags the texts that differ from the texts seen so far, e.g. to
+//! detect a drift of the user inputs. The embeddings of the texts are summarized by a rolling centroid and covariance
+//! (exponentially weighted, so that older texts are progressively forgotten), and each new text is scored by the
+//! Mahalanobis distance of its embedding to the centroid.
+//!
+//! As the squared Mahalanobis distance of normally distributed embeddings follows a chi-squared distribution with as
+//! many degrees of freedom as embedding dimensions, the distances are standardized into a z-score
+//! `(distance² - dimension) / sqrt(2 * dimension)`, and texts with a z-score above a threshold are flagged. No text
+//! is flagged until `warmup` texts have been observed.
+//!
+//! `RollingStatistics` can be used directly to monitor embeddings computed by another pipeline.
+//!
+//! ```no_run
+//! # fn main() -> anyhow::Result<()> {
+//! use rust_bert::pipelines::outlier_detection::{OutlierDetector, OutlierDetectorConfig};
+//! use rust_bert::pipelines::sentence_embeddings::SentenceEmbeddingsModelType;
+//!
+//! let config = OutlierDetectorConfig::new(SentenceEmbeddingsModelType::AllMiniLmL12V2.into());
+//! let mut detector = OutlierDetector::new(config)?;
+//!
+//! // Texts received by the service
+//! let scores = detector.observe(&["What are your opening hours?", "Can I return an item?"])?;
+//! for score in scores {
+//!     if score.is_outlier {
+//!         println!("Unusual input (z-score: {:.1})", score.z_score);
+//!     }
+//! }
+//! # Ok(())
+//! # }
+//! ```
+
+use crate::common::error::RustBertError;
+use crate::pipelines::sentence_embeddings::{
+    Embedding, SentenceEmbeddingsConfig, SentenceEmbeddingsModel,
+};
+use serde::{Deserialize, Serialize};
+use tch::{Device, Kind, Tensor};
+
+/// # Outlier score of a text
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct OutlierScore {
+    /// Mahalanobis distance of the embedding to the rolling centroid
+    pub distance: f64,
+    /// Standardized squared distance (chi-squared approximation)
+    pub z_score: f64,
+    /// Flag indicating if the z-score exceeds the threshold (always `false` during the warmup)
+    pub is_outlier: bool,
+}
+
+/// # Exponentially weighted centroid and covariance of embeddings
+/// The contribution of each embedding decays by a factor `1 - decay` for every embedding observed after it. Until
+/// `1 / decay` embeddings are observed, all embeddings are weighted equally.
+pub struct RollingStatistics {
+    decay: f64,
+    shrinkage: f64,
+    count: usize,
+    mean: Option<Tensor>,
+    covariance: Option<Tensor>,
+}
+
+impl RollingStatistics {
+    /// Create new empty `RollingStatistics`
+    ///
+    /// # Arguments
+    ///
+    /// * `decay` - Weight of the most recent embedding (between 0 and 1)
+    /// * `shrinkage` - Shrinkage of the covariance towards a scaled identity (between 0 and 1), regularizing the
+    /// covariance estimated from few embeddings
+    pub fn new(decay: f64, shrinkage: f64) -> RollingStatistics {
+        RollingStatistics {
+            decay,
+            shrinkage,
+            count: 0,
+            mean: None,
+            covariance: None,
+        }
+    }
+
+    /// Returns the number of embeddings observed
+    pub fn count(&self) -> usize {
+        self.count
+    }
+
+    /// Forgets all embeddings observed
+    pub fn reset(&mut self) {
+        self.count = 0;
+        self.mean = None;
+        self.covariance = None;
+    }
+
+    /// Returns the rolling centroid, if any embedding was observed
+    pub fn mean(&self) -> Option<&Tensor> {
+        self.mean.as_ref()
+    }
+
+    /// Adds embeddings to the statistics, in order
+    ///
+    /// # Arguments
+    ///
+    /// * `embeddings` - Embeddings of shape (*batch size*, *dimension*)
+    pub fn update(&mut self, embeddings: &Tensor) {
+        let embeddings = embeddings.to_device(Device::Cpu).to_kind(Kind::Double);
+        for index in 0..embeddings.size()[0] {
+            let embedding = embeddings.get(index);
+            self.count += 1;
+            let (mean, covariance) = match (self.mean.take(), self.covariance.take()) {
+                (Some(mean), Some(covariance)) => {
+                    let weight = self.decay.max(1.0 / self.count as f64);
+                    let delta = &embedding - &mean;
+                    let mean = mean + &delta * weight;
+                    let covariance = (covariance + delta.outer(&delta) * weight) * (1.0 - weight);
+                    (mean, covariance)
+                }
+                _ => {
+                    let dimension = embedding.size()[0];
+                    let covariance =
+                        Tensor::zeros(&[dimension, dimension], (Kind::Double, Device::Cpu));
+                    (embedding, covariance)
+                }
+            };
+            self.mean = Some(mean);
+            self.covariance = Some(covariance);
+        }
+    }
+
+    /// Computes the Mahalanobis distance of embeddings to the rolling centroid
+    ///
+    /// # Arguments
+    ///
+    /// * `embeddings` - Embeddings of shape (*batch size*, *dimension*)
+    ///
+    /// # Returns
+    ///
+    /// * `Option<Vec<f64>>` distance of each embedding, `None` if fewer than 2 embeddings were observed
+    pub fn mahalanobis(&self, embeddings: &Tensor) -> Option<Vec<f64>> {
+        let (mean, covariance) = match (&self.mean, &self.covariance) {
+            (Some(mean), Some(covariance)) if self.count > 1 => (mean, covariance),
+            _ => return None,
+        };
+        let dimension = covariance.size()[0];
+        let scale = (covariance.trace().double_value(&[]) / dimension as f64).max(1e-12);
+        let regularized = covariance * (1.0 - self.shrinkage)
+            + Tensor::eye(dimension, (Kind::Double, Device::Cpu)) * (self.shrinkage * scale);
+        let precision = regularized.inverse();
+
+        let delta = embeddings.to_device(Device::Cpu).to_kind(Kind::Double) - mean;
+        let squared_distances = (delta.matmul(&precision) * &delta)
+            .sum_dim_intlist(&[1], false, Kind::Double)
+            .clamp_min(0.0);
+        Some(
+            squared_distances
+                .sqrt()
+                .iter::<f64>()
+                .unwrap()
+                .collect::<Vec<f64>>(),
+        )
+    }
+}
+
+/// # Configuration for OutlierDetector
+pub struct OutlierDetectorConfig {
+    /// Sentence embeddings model used to embed the texts
+    pub embeddings_config: SentenceEmbeddingsConfig,
+    /// Weight of the most recent text in the rolling statistics (default: 0.001, i.e. a memory of about 1000 texts)
+    pub decay: f64,
+    /// Shrinkage of the covariance towards a scaled identity (default: 0.1)
+    pub shrinkage: f64,
+    /// Z-score above which a text is flagged as an outlier (default: 3.0)
+    pub threshold: f64,
+    /// Number of texts observed before flagging outliers (default: 100)
+    pub warmup: usize,
+    /// Flag indicating if the texts flagged as outliers are added to the rolling statistics (default: false, the
+    /// outliers do not shift the statistics)
+    pub update_with_outliers: bool,
+}
+
+impl OutlierDetectorConfig {
+    /// Instantiate a new outlier detection configuration with default parameters
+    ///
+    /// # Arguments
+    ///
+    /// * `embeddings_config` - `SentenceEmbeddingsConfig` of the model used to embed the texts
+    pub fn new(embeddings_config: SentenceEmbeddingsConfig) -> OutlierDetectorConfig {
+        OutlierDetectorConfig {
+            embeddings_config,
+            decay: 0.001,
+            shrinkage: 0.1,
+            threshold: 3.0,
+            warmup: 100,
+            update_with_outliers: false,
+        }
+    }
+}
+
+/// # OutlierDetector flagging unusual texts in a stream
+pub struct OutlierDetector {
+    embeddings_model: SentenceEmbeddingsModel,
+    statistics: RollingStatistics,
+    threshold: f64,
+    warmup: usize,
+    update_with_outliers: bool,
+}
+
+impl OutlierDetector {
+    /// Build a new `OutlierDetector`
+    ///
+    /// # Arguments
+    ///
+    /// * `config` - `OutlierDetectorConfig` object containing the sentence embeddings configuration and the detection parameters
+    pub fn new(config: OutlierDetectorConfig) -> Result<OutlierDetector, RustBertError> {
+        if !(config.decay > 0.0 && config.decay <= 1.0) {
+            return Err(RustBertError::InvalidConfigurationError(format!(
+                "The decay must be in (0, 1], got {}",
+                config.decay
+            )));
+        }
+        if !(config.shrinkage > 0.0 && config.shrinkage <= 1.0) {
+            return Err(RustBertError::InvalidConfigurationError(format!(
+                "The shrinkage must be in (0, 1], got {}",
+                config.shrinkage
+            )));
+        }
+        let embeddings_model = SentenceEmbeddingsModel::new(config.embeddings_config)?;
+        Ok(OutlierDetector {
+            embeddings_model,
+            statistics: RollingStatistics::new(config.decay, config.shrinkage),
+            threshold: config.threshold,
+            warmup: config.warmup,
+            update_with_outliers: config.update_with_outliers,
+        })
+    }
+
+    /// Returns the rolling statistics of the texts observed
+    pub fn statistics(&self) -> &RollingStatistics {
+        &self.statistics
+    }
+
+    /// Forgets all texts observed
+    pub fn reset(&mut self) {
+        self.statistics.reset();
+    }
+
+    fn score_embeddings(&self, embeddings: &Tensor) -> Vec<OutlierScore> {
+        let num_embeddings = embeddings.size()[0] as usize;
+        let dimension = embeddings.size()[1] as f64;
+        match self.statistics.mahalanobis(embeddings) {
+            Some(distances) => distances
+                .into_iter()
+                .map(|distance| {
+                    let z_score = (distance * distance - dimension) / (2.0 * dimension).sqrt();
+                    OutlierScore {
+                        distance,
+                        z_score,
+                        is_outlier: self.statistics.count() >= self.warmup
+                            && z_score > self.threshold,
+                    }
+                })
+                .collect(),
+            None => vec![
+                OutlierScore {
+                    distance: 0.0,
+                    z_score: 0.0,
+                    is_outlier: false,
+                };
+                num_embeddings
+            ],
+        }
+    }
+
+    /// Scores texts against the texts observed so far, without adding them to the statistics
+    ///
+    /// # Arguments
+    ///
+    /// * `texts` - Texts to score
+    ///
+    /// # Returns
+    ///
+    /// * `Vec<OutlierScore>` outlier score of each text
+    pub fn score<S>(&self, texts: &[S]) -> Result<Vec<OutlierScore>, RustBertError>
+    where
+        S: AsRef<str> + Sync,
+    {
+        if texts.is_empty() {
+            return Ok(vec![]);
+        }
+        let embeddings = self.embeddings_model.encode_as_tensor(texts)?.embeddings;
+        Ok(self.score_embeddings(&embeddings))
+    }
+
+    /// Scores texts against the texts observed so far, then adds them to the statistics (excluding the outliers,
+    /// unless `update_with_outliers` is set)
+    ///
+    /// # Arguments
+    ///
+    /// * `texts` - Texts received, in order
+    ///
+    /// # Returns
+    ///
+    /// * `Vec<OutlierScore>` outlier score of each text
+    pub fn observe<S>(&mut self, texts: &[S]) -> Result<Vec<OutlierScore>, RustBertError>
+    where
+        S: AsRef<str> + Sync,
+    {
+        if texts.is_empty() {
+            return Ok(vec![]);
+        }
+        let embeddings = self.embeddings_model.encode_as_tensor(texts)?.embeddings;
+        let scores = self.score_embeddings(&embeddings);
+        self.update(&embeddings, &scores);
+        Ok(scores)
+    }
+
+    /// Scores embeddings computed by another model against the embeddings observed so far, then adds them to the
+    /// statistics (excluding the outliers, unless `update_with_outliers` is set)
+    ///
+    /// # Arguments
+    ///
+    /// * `embeddings` - Embeddings received, in order
+    ///
+    /// # Returns
+    ///
+    /// * `Vec<OutlierScore>` outlier score of each embedding
+    pub fn observe_embeddings(&mut self, embeddings: &[Embedding]) -> Vec<OutlierScore> {
+        if embeddings.is_empty() {
+            return vec![];
+        }
+        let embeddings = Tensor::of_slice(&embeddings.concat()).view((embeddings.len() as i64, -1));
+        let scores = self.score_embeddings(&embeddings);
+        self.update(&embeddings, &scores);
+        scores
+    }
+
+    fn update(&mut self, embeddings: &Tensor, scores: &[OutlierScore]) {
+        if self.update_with_outliers {
+            self.statistics.update(embeddings);
+        } else {
+            let inliers = scores
+                .iter()
+                .enumerate()
+                .filter(|(_, score)| !score.is_outlier)
+                .map(|(index, _)| index as i64)
+                .collect::<Vec<i64>>();
+            if !inliers.is_empty() {
+                self.statistics.update(
+                    &embeddings
+                        .index_select(0, &Tensor::of_slice(&inliers).to(embeddings.device())),
+                );
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn sample(index: usize) -> [f32; 3] {
+        let t = index as f32;
+        [(t * 1.3).sin(), (t * 0.7).cos(), (t * 2.9).sin() * 0.5]
+    }
+
+    #[test]
+    fn test_rolling_statistics() {
+        let mut statistics = RollingStatistics::new(0.001, 0.1);
+        let samples = (0..200).flat_map(sample).collect::<Vec<f32>>();
+        statistics.update(&Tensor::of_slice(&samples).view((-1, 3)));
+        assert_eq!(statistics.count(), 200);
+
+        // Equal weights before 1 / decay samples: the centroid is the average
+        let expected_mean =
+            Tensor::of_slice(&samples)
+                .view((-1, 3))
+                .mean_dim(&[0], false, Kind::Double);
+        assert!(
+            (statistics.mean().unwrap() - expected_mean)
+                .abs()
+                .max()
+                .double_value(&[])
+                < 1e-6
+        );
+
+        let queries = Tensor::of_slice(&[0.1f32, 0.2, 0.0, 10.0, -10.0, 5.0]).view((2, 3));
+        let distances = statistics.mahalanobis(&queries).unwrap();
+        assert!(distances[0] < 2.0);
+        assert!(distances[1] > 10.0);
+    }
+
+    #[test]
+    fn test_empty_statistics() {
+        let statistics = RollingStatistics::new(0.01, 0.1);
+        assert!(statistics
+            .mahalanobis(&Tensor::of_slice(&[0.0f32, 1.0]).view((1, 2)))
+            .is_none());
+    }
+}
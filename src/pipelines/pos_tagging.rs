@@ -82,6 +82,7 @@
 //! To run the pipeline for another language, change the POSModel configuration from its default (see the NER pipeline for an illustration).
 
 use crate::common::error::RustBertError;
+use crate::pipelines::common::Pipeline;
 use crate::pipelines::token_classification::{TokenClassificationConfig, TokenClassificationModel};
 use serde::{Deserialize, Serialize};
 
@@ -136,6 +137,8 @@ impl Default for POSConfig {
                 device: Device::cuda_if_available(),
                 label_aggregation_function: LabelAggregationOption::First,
                 batch_size: 64,
+                output_hidden_states: false,
+                output_attentions: false,
             },
         }
     }
@@ -235,6 +238,21 @@ impl POSModel {
     }
 }
 
+impl<S> Pipeline<S, Vec<POSTag>> for POSModel
+where
+    S: AsRef<str>,
+{
+    type Options = ();
+
+    fn run_with_options(
+        &self,
+        inputs: &[S],
+        _options: &(),
+    ) -> Result<Vec<Vec<POSTag>>, RustBertError> {
+        Ok(self.predict(inputs))
+    }
+}
+
 #[cfg(test)]
 mod test {
     use super::*;
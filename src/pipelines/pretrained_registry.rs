@@ -0,0 +1,757 @@
+// Copyright 2022 Guillaume Becquin
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//     http://www.apache.org/licenses/LICENSE-2.0
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! # Registry of pretrained models
+//! Lists the pretrained checkpoints available for the models of this crate, with the tasks and languages they
+//! support, their (approximate) number of parameters and the location of their resources (weights,
+//! configuration, vocabulary...). The registry is defined as data: the checkpoints of the crate are read from
+//! the JSON file `pretrained_registry.json` embedded in the library, and additional checkpoints can be loaded from
+//! a file with the same format (`load_pretrained_registry`) or registered (`register_pretrained`) at runtime,
+//! without recompiling the crate.
+//!
+//! Each checkpoint is registered under a unique name, and its resources are identified by their role: `model`,
+//! `config`, `vocab`, `merges` (BPE tokenizers), `spm` (Marian tokenizers) and `modules_config`,
+//! `sentence_embeddings_config`, `tokenizer_config`, `pooling_config`, `dense`, `dense_config` (sentence
+//! embeddings). Checkpoints whose weights are not hosted in the Rust format (to be converted locally from the
+//! PyTorch weights) have no `model` resource. Every `*Resources` constant of the model modules (e.g.
+//! `Gpt2ModelResources::GPT2`) has an identical entry in the registry, checked by the tests of this module, with the
+//! exception of the CLIP and ViT resources whose model types are not used by the pipelines.
+//!
+//! ```no_run
+//! # fn main() -> anyhow::Result<()> {
+//! use rust_bert::pipelines::common::ModelType;
+//! use rust_bert::pipelines::pretrained_registry::{
+//!     find_pretrained, get_pretrained, PretrainedQuery, PretrainedTask,
+//! };
+//! use rust_bert::pipelines::text_generation::{TextGenerationConfig, TextGenerationModel};
+//!
+//! // Text generation checkpoints for English with at most 500M parameters, by increasing size
+//! let checkpoints = find_pretrained(&PretrainedQuery {
+//!     task: Some(PretrainedTask::TextGeneration),
+//!     language: Some("en".to_string()),
+//!     max_parameters: Some(500_000_000),
+//!     ..Default::default()
+//! });
+//!
+//! let gpt2 = get_pretrained("gpt2").unwrap();
+//! let config = TextGenerationConfig::new(
+//!     ModelType::GPT2,
+//!     gpt2.remote_resource("model")?,
+//!     gpt2.remote_resource("config")?,
+//!     gpt2.remote_resource("vocab")?,
+//!     gpt2.remote_resource("merges")?,
+//! );
+//! let model = TextGenerationModel::new(config)?;
+//! # Ok(())
+//! # }
+//! ```
+
+use crate::common::error::RustBertError;
+use crate::pipelines::common::ModelType;
+use lazy_static::lazy_static;
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashSet};
+use std::fs;
+use std::path::Path;
+use std::sync::RwLock;
+
+#[cfg(feature = "remote")]
+use crate::resources::RemoteResource;
+
+/// Registry of the pretrained checkpoints of the crate
+const PRETRAINED_REGISTRY_DATA: &str = include_str!("pretrained_registry.json");
+
+/// Language code of the multilingual checkpoints (ISO 639-2 code for multiple languages)
+const MULTILINGUAL: &str = "mul";
+
+/// # Task a pretrained checkpoint is trained (or fine-tuned) for
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum PretrainedTask {
+    /// Base checkpoint trained with a language modeling objective, to be fine-tuned
+    LanguageModeling,
+    /// Sequence classification (e.g. sentiment analysis)
+    SequenceClassification,
+    /// Token classification (e.g. named entity recognition, part of speech tagging)
+    TokenClassification,
+    /// Extractive question answering
+    QuestionAnswering,
+    /// Zero-shot classification (natural language inference)
+    ZeroShotClassification,
+    /// Translation
+    Translation,
+    /// Summarization
+    Summarization,
+    /// Text generation
+    TextGeneration,
+    /// Conversation
+    Conversation,
+    /// Sentence embeddings
+    SentenceEmbeddings,
+}
+
+/// # Location of a pretrained resource
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct PretrainedResource {
+    /// Local subdirectory of the cache root where the resource is saved
+    pub cache_subdir: String,
+    /// Remote url of the resource
+    pub url: String,
+}
+
+/// # Pretrained checkpoint of the registry
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct PretrainedModel {
+    /// Unique name of the checkpoint
+    pub name: String,
+    /// Model type
+    pub model_type: ModelType,
+    /// Tasks the checkpoint can be used for
+    pub tasks: Vec<PretrainedTask>,
+    /// ISO 639-1 codes of the languages supported, `mul` for multilingual checkpoints
+    pub languages: Vec<String>,
+    /// Approximate number of parameters, if known
+    #[serde(default)]
+    pub parameters: Option<usize>,
+    /// Resources of the checkpoint, by role (`model`, `config`, `vocab`, `merges`...). The `model` resource is
+    /// missing for the checkpoints without weights in the Rust format.
+    pub resources: BTreeMap<String, PretrainedResource>,
+}
+
+impl PretrainedModel {
+    /// Returns the resource of the checkpoint with the given role (e.g. `model`, `vocab`).
+    /// Fails if the checkpoint has no resource for this role.
+    pub fn resource(&self, role: &str) -> Result<&PretrainedResource, RustBertError> {
+        self.resources.get(role).ok_or_else(|| {
+            RustBertError::InvalidConfigurationError(format!(
+                "The pretrained model {} has no {} resource",
+                self.name, role
+            ))
+        })
+    }
+
+    /// Returns a `RemoteResource` for the resource of the checkpoint with the given role
+    #[cfg(feature = "remote")]
+    pub fn remote_resource(&self, role: &str) -> Result<RemoteResource, RustBertError> {
+        let resource = self.resource(role)?;
+        Ok(RemoteResource::new(&resource.url, &resource.cache_subdir))
+    }
+
+    /// Checks if the checkpoint supports a language (ISO 639-1 code). Multilingual checkpoints support all
+    /// languages.
+    pub fn supports_language(&self, language: &str) -> bool {
+        self.languages
+            .iter()
+            .any(|supported| supported == language || supported == MULTILINGUAL)
+    }
+}
+
+/// # Query for pretrained checkpoints
+/// The checkpoints returned match all the criteria set.
+#[derive(Debug, Clone, Default)]
+pub struct PretrainedQuery {
+    /// Task the checkpoints can be used for
+    pub task: Option<PretrainedTask>,
+    /// Language (ISO 639-1 code) supported by the checkpoints
+    pub language: Option<String>,
+    /// Model type of the checkpoints
+    pub model_type: Option<ModelType>,
+    /// Maximum number of parameters. Checkpoints of unknown size are excluded if set.
+    pub max_parameters: Option<usize>,
+}
+
+impl PretrainedQuery {
+    fn matches(&self, model: &PretrainedModel) -> bool {
+        let task_matches = match self.task {
+            Some(task) => model.tasks.contains(&task),
+            None => true,
+        };
+        let language_matches = match &self.language {
+            Some(language) => model.supports_language(language),
+            None => true,
+        };
+        let model_type_matches = match self.model_type {
+            Some(model_type) => model.model_type == model_type,
+            None => true,
+        };
+        let size_matches = match (self.max_parameters, model.parameters) {
+            (Some(max_parameters), Some(parameters)) => parameters <= max_parameters,
+            (Some(_), None) => false,
+            (None, _) => true,
+        };
+        task_matches && language_matches && model_type_matches && size_matches
+    }
+}
+
+fn parse_registry(data: &str) -> Result<Vec<PretrainedModel>, RustBertError> {
+    let models: Vec<PretrainedModel> = serde_json::from_str(data).map_err(|error| {
+        RustBertError::InvalidConfigurationError(format!("Invalid pretrained registry: {}", error))
+    })?;
+    let mut names = HashSet::new();
+    for model in &models {
+        if !names.insert(model.name.as_str()) {
+            return Err(RustBertError::InvalidConfigurationError(format!(
+                "The pretrained model {} is defined more than once",
+                model.name
+            )));
+        }
+    }
+    Ok(models)
+}
+
+lazy_static! {
+    static ref PRETRAINED_REGISTRY: RwLock<Vec<PretrainedModel>> = RwLock::new(
+        parse_registry(PRETRAINED_REGISTRY_DATA).expect("Invalid embedded pretrained registry")
+    );
+}
+
+fn register_all(models: Vec<PretrainedModel>) -> Result<(), RustBertError> {
+    let mut registry = PRETRAINED_REGISTRY.write().unwrap();
+    if let Some(model) = models.iter().find(|model| {
+        registry
+            .iter()
+            .any(|registered| registered.name == model.name)
+    }) {
+        return Err(RustBertError::InvalidConfigurationError(format!(
+            "A pretrained model is already registered under the name {}",
+            model.name
+        )));
+    }
+    registry.extend(models);
+    Ok(())
+}
+
+/// Registers a pretrained checkpoint.
+///
+/// # Arguments
+///
+/// * `model` - `PretrainedModel` to register. Fails if a checkpoint was already registered under the same name.
+pub fn register_pretrained(model: PretrainedModel) -> Result<(), RustBertError> {
+    register_all(vec![model])
+}
+
+/// Registers the pretrained checkpoints of a JSON registry file (a list of `PretrainedModel`, with the format of
+/// the registry embedded in the crate).
+///
+/// # Arguments
+///
+/// * `path` - Path to the registry file. Fails without registering any checkpoint if the file is invalid or if
+/// one of its checkpoints was already registered.
+pub fn load_pretrained_registry<P: AsRef<Path>>(path: P) -> Result<(), RustBertError> {
+    let data = fs::read_to_string(path.as_ref())?;
+    register_all(parse_registry(&data)?)
+}
+
+/// Returns the pretrained checkpoint registered under a name
+pub fn get_pretrained(name: &str) -> Option<PretrainedModel> {
+    PRETRAINED_REGISTRY
+        .read()
+        .unwrap()
+        .iter()
+        .find(|model| model.name == name)
+        .cloned()
+}
+
+/// Returns the registered checkpoints matching a query, sorted by increasing number of parameters (checkpoints of
+/// unknown size last)
+pub fn find_pretrained(query: &PretrainedQuery) -> Vec<PretrainedModel> {
+    let mut models = PRETRAINED_REGISTRY
+        .read()
+        .unwrap()
+        .iter()
+        .filter(|model| query.matches(model))
+        .cloned()
+        .collect::<Vec<PretrainedModel>>();
+    models.sort_by_key(|model| model.parameters.unwrap_or(usize::MAX));
+    models
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::albert::{AlbertConfigResources, AlbertModelResources, AlbertVocabResources};
+    use crate::bart::{
+        BartConfigResources, BartMergesResources, BartModelResources, BartVocabResources,
+    };
+    use crate::bert::{BertConfigResources, BertModelResources, BertVocabResources};
+    use crate::deberta::{
+        DebertaConfigResources, DebertaMergesResources, DebertaModelResources,
+        DebertaVocabResources,
+    };
+    use crate::deberta_v2::{
+        DebertaV2ConfigResources, DebertaV2ModelResources, DebertaV2VocabResources,
+    };
+    use crate::distilbert::{
+        DistilBertConfigResources, DistilBertModelResources, DistilBertVocabResources,
+    };
+    use crate::electra::{ElectraConfigResources, ElectraModelResources, ElectraVocabResources};
+    use crate::fnet::{FNetConfigResources, FNetModelResources, FNetVocabResources};
+    use crate::gpt2::{
+        Gpt2ConfigResources, Gpt2MergesResources, Gpt2ModelResources, Gpt2VocabResources,
+    };
+    use crate::gpt_neo::{
+        GptNeoConfigResources, GptNeoMergesResources, GptNeoModelResources, GptNeoVocabResources,
+    };
+    use crate::longformer::{
+        LongformerConfigResources, LongformerMergesResources, LongformerModelResources,
+        LongformerVocabResources,
+    };
+    use crate::m2m_100::{
+        M2M100ConfigResources, M2M100MergesResources, M2M100ModelResources, M2M100VocabResources,
+    };
+    use crate::marian::{
+        MarianConfigResources, MarianModelResources, MarianSpmResources, MarianVocabResources,
+    };
+    use crate::mbart::{MBartConfigResources, MBartModelResources, MBartVocabResources};
+    use crate::mobilebert::{
+        MobileBertConfigResources, MobileBertModelResources, MobileBertVocabResources,
+    };
+    use crate::openai_gpt::{
+        OpenAiGptConfigResources, OpenAiGptMergesResources, OpenAiGptModelResources,
+        OpenAiGptVocabResources,
+    };
+    use crate::pegasus::{PegasusConfigResources, PegasusModelResources, PegasusVocabResources};
+    use crate::pipelines::sentence_embeddings::{
+        SentenceEmbeddingsConfigResources, SentenceEmbeddingsDenseConfigResources,
+        SentenceEmbeddingsDenseResources, SentenceEmbeddingsModulesConfigResources,
+        SentenceEmbeddingsPoolingConfigResources, SentenceEmbeddingsTokenizerConfigResources,
+    };
+    use crate::prophetnet::{
+        ProphetNetConfigResources, ProphetNetModelResources, ProphetNetVocabResources,
+    };
+    use crate::reformer::{ReformerConfigResources, ReformerModelResources, ReformerVocabResources};
+    use crate::roberta::{
+        RobertaConfigResources, RobertaMergesResources, RobertaModelResources,
+        RobertaVocabResources,
+    };
+    use crate::t5::{T5ConfigResources, T5MergesResources, T5ModelResources, T5VocabResources};
+    use crate::xlnet::{XLNetConfigResources, XLNetModelResources, XLNetVocabResources};
+    use std::io::Write;
+
+    /// `*Resources` constants of the crate, with the checkpoint and role of their entry in the registry
+    #[rustfmt::skip]
+    const RESOURCE_CONSTANTS: &[(&str, &str, (&str, &str))] = &[
+        ("albert-base-v2", "model", AlbertModelResources::ALBERT_BASE_V2),
+        ("albert-base-v2", "config", AlbertConfigResources::ALBERT_BASE_V2),
+        ("albert-base-v2", "vocab", AlbertVocabResources::ALBERT_BASE_V2),
+        ("paraphrase-albert-small-v2", "model", AlbertModelResources::PARAPHRASE_ALBERT_SMALL_V2),
+        ("paraphrase-albert-small-v2", "config", AlbertConfigResources::PARAPHRASE_ALBERT_SMALL_V2),
+        ("paraphrase-albert-small-v2", "vocab", AlbertVocabResources::PARAPHRASE_ALBERT_SMALL_V2),
+        ("paraphrase-albert-small-v2", "modules_config", SentenceEmbeddingsModulesConfigResources::PARAPHRASE_ALBERT_SMALL_V2),
+        ("paraphrase-albert-small-v2", "pooling_config", SentenceEmbeddingsPoolingConfigResources::PARAPHRASE_ALBERT_SMALL_V2),
+        ("paraphrase-albert-small-v2", "sentence_embeddings_config", SentenceEmbeddingsConfigResources::PARAPHRASE_ALBERT_SMALL_V2),
+        ("paraphrase-albert-small-v2", "tokenizer_config", SentenceEmbeddingsTokenizerConfigResources::PARAPHRASE_ALBERT_SMALL_V2),
+        ("bart", "model", BartModelResources::BART),
+        ("bart", "config", BartConfigResources::BART),
+        ("bart", "vocab", BartVocabResources::BART),
+        ("bart", "merges", BartMergesResources::BART),
+        ("bart-cnn", "model", BartModelResources::BART_CNN),
+        ("bart-cnn", "config", BartConfigResources::BART_CNN),
+        ("bart-cnn", "vocab", BartVocabResources::BART_CNN),
+        ("bart-cnn", "merges", BartMergesResources::BART_CNN),
+        ("bart-xsum", "model", BartModelResources::BART_XSUM),
+        ("bart-xsum", "config", BartConfigResources::BART_XSUM),
+        ("bart-xsum", "vocab", BartVocabResources::BART_XSUM),
+        ("bart-xsum", "merges", BartMergesResources::BART_XSUM),
+        ("bart-large-mnli", "model", BartModelResources::BART_MNLI),
+        ("bart-large-mnli", "config", BartConfigResources::BART_MNLI),
+        ("bart-large-mnli", "vocab", BartVocabResources::BART_MNLI),
+        ("bart-large-mnli", "merges", BartMergesResources::BART_MNLI),
+        ("distilbart-cnn-6-6", "model", BartModelResources::DISTILBART_CNN_6_6),
+        ("distilbart-cnn-6-6", "config", BartConfigResources::DISTILBART_CNN_6_6),
+        ("distilbart-cnn-6-6", "vocab", BartVocabResources::DISTILBART_CNN_6_6),
+        ("distilbart-cnn-6-6", "merges", BartMergesResources::DISTILBART_CNN_6_6),
+        ("distilbart-cnn-12-6", "model", BartModelResources::DISTILBART_CNN_12_6),
+        ("distilbart-cnn-12-6", "config", BartConfigResources::DISTILBART_CNN_12_6),
+        ("distilbart-cnn-12-6", "vocab", BartVocabResources::DISTILBART_CNN_12_6),
+        ("distilbart-cnn-12-6", "merges", BartMergesResources::DISTILBART_CNN_12_6),
+        ("bert", "model", BertModelResources::BERT),
+        ("bert", "config", BertConfigResources::BERT),
+        ("bert", "vocab", BertVocabResources::BERT),
+        ("bert-ner", "model", BertModelResources::BERT_NER),
+        ("bert-ner", "config", BertConfigResources::BERT_NER),
+        ("bert-ner", "vocab", BertVocabResources::BERT_NER),
+        ("bert-qa", "model", BertModelResources::BERT_QA),
+        ("bert-qa", "config", BertConfigResources::BERT_QA),
+        ("bert-qa", "vocab", BertVocabResources::BERT_QA),
+        ("bert-base-nli-mean-tokens", "model", BertModelResources::BERT_BASE_NLI_MEAN_TOKENS),
+        ("bert-base-nli-mean-tokens", "config", BertConfigResources::BERT_BASE_NLI_MEAN_TOKENS),
+        ("bert-base-nli-mean-tokens", "vocab", BertVocabResources::BERT_BASE_NLI_MEAN_TOKENS),
+        ("bert-base-nli-mean-tokens", "modules_config", SentenceEmbeddingsModulesConfigResources::BERT_BASE_NLI_MEAN_TOKENS),
+        ("bert-base-nli-mean-tokens", "pooling_config", SentenceEmbeddingsPoolingConfigResources::BERT_BASE_NLI_MEAN_TOKENS),
+        ("bert-base-nli-mean-tokens", "sentence_embeddings_config", SentenceEmbeddingsConfigResources::BERT_BASE_NLI_MEAN_TOKENS),
+        ("bert-base-nli-mean-tokens", "tokenizer_config", SentenceEmbeddingsTokenizerConfigResources::BERT_BASE_NLI_MEAN_TOKENS),
+        ("all-mini-lm-l12-v2", "model", BertModelResources::ALL_MINI_LM_L12_V2),
+        ("all-mini-lm-l12-v2", "config", BertConfigResources::ALL_MINI_LM_L12_V2),
+        ("all-mini-lm-l12-v2", "vocab", BertVocabResources::ALL_MINI_LM_L12_V2),
+        ("all-mini-lm-l12-v2", "modules_config", SentenceEmbeddingsModulesConfigResources::ALL_MINI_LM_L12_V2),
+        ("all-mini-lm-l12-v2", "pooling_config", SentenceEmbeddingsPoolingConfigResources::ALL_MINI_LM_L12_V2),
+        ("all-mini-lm-l12-v2", "sentence_embeddings_config", SentenceEmbeddingsConfigResources::ALL_MINI_LM_L12_V2),
+        ("all-mini-lm-l12-v2", "tokenizer_config", SentenceEmbeddingsTokenizerConfigResources::ALL_MINI_LM_L12_V2),
+        ("deberta-base", "model", DebertaModelResources::DEBERTA_BASE),
+        ("deberta-base", "config", DebertaConfigResources::DEBERTA_BASE),
+        ("deberta-base", "vocab", DebertaVocabResources::DEBERTA_BASE),
+        ("deberta-base", "merges", DebertaMergesResources::DEBERTA_BASE),
+        ("deberta-base-mnli", "model", DebertaModelResources::DEBERTA_BASE_MNLI),
+        ("deberta-base-mnli", "config", DebertaConfigResources::DEBERTA_BASE_MNLI),
+        ("deberta-base-mnli", "vocab", DebertaVocabResources::DEBERTA_BASE_MNLI),
+        ("deberta-base-mnli", "merges", DebertaMergesResources::DEBERTA_BASE_MNLI),
+        ("deberta-v3-base", "model", DebertaV2ModelResources::DEBERTA_V3_BASE),
+        ("deberta-v3-base", "config", DebertaV2ConfigResources::DEBERTA_V3_BASE),
+        ("deberta-v3-base", "vocab", DebertaV2VocabResources::DEBERTA_V3_BASE),
+        ("mdeberta-v3-base-mnli-xnli", "config", DebertaV2ConfigResources::MDEBERTA_V3_BASE_MNLI_XNLI),
+        ("mdeberta-v3-base-mnli-xnli", "vocab", DebertaV2VocabResources::MDEBERTA_V3_BASE_MNLI_XNLI),
+        ("reward-model-deberta-v3-base", "config", DebertaV2ConfigResources::REWARD_MODEL_DEBERTA_V3_BASE),
+        ("reward-model-deberta-v3-base", "vocab", DebertaV2VocabResources::REWARD_MODEL_DEBERTA_V3_BASE),
+        ("reward-model-deberta-v3-large-v2", "config", DebertaV2ConfigResources::REWARD_MODEL_DEBERTA_V3_LARGE_V2),
+        ("reward-model-deberta-v3-large-v2", "vocab", DebertaV2VocabResources::REWARD_MODEL_DEBERTA_V3_LARGE_V2),
+        ("distilbert-sst2", "model", DistilBertModelResources::DISTIL_BERT_SST2),
+        ("distilbert-sst2", "config", DistilBertConfigResources::DISTIL_BERT_SST2),
+        ("distilbert-sst2", "vocab", DistilBertVocabResources::DISTIL_BERT_SST2),
+        ("distilbert", "model", DistilBertModelResources::DISTIL_BERT),
+        ("distilbert", "config", DistilBertConfigResources::DISTIL_BERT),
+        ("distilbert", "vocab", DistilBertVocabResources::DISTIL_BERT),
+        ("distilbert-qa", "model", DistilBertModelResources::DISTIL_BERT_SQUAD),
+        ("distilbert-qa", "config", DistilBertConfigResources::DISTIL_BERT_SQUAD),
+        ("distilbert-qa", "vocab", DistilBertVocabResources::DISTIL_BERT_SQUAD),
+        ("distiluse-base-multilingual-cased", "model", DistilBertModelResources::DISTILUSE_BASE_MULTILINGUAL_CASED),
+        ("distiluse-base-multilingual-cased", "config", DistilBertConfigResources::DISTILUSE_BASE_MULTILINGUAL_CASED),
+        ("distiluse-base-multilingual-cased", "vocab", DistilBertVocabResources::DISTILUSE_BASE_MULTILINGUAL_CASED),
+        ("distiluse-base-multilingual-cased", "modules_config", SentenceEmbeddingsModulesConfigResources::DISTILUSE_BASE_MULTILINGUAL_CASED),
+        ("distiluse-base-multilingual-cased", "dense", SentenceEmbeddingsDenseResources::DISTILUSE_BASE_MULTILINGUAL_CASED),
+        ("distiluse-base-multilingual-cased", "dense_config", SentenceEmbeddingsDenseConfigResources::DISTILUSE_BASE_MULTILINGUAL_CASED),
+        ("distiluse-base-multilingual-cased", "pooling_config", SentenceEmbeddingsPoolingConfigResources::DISTILUSE_BASE_MULTILINGUAL_CASED),
+        ("distiluse-base-multilingual-cased", "sentence_embeddings_config", SentenceEmbeddingsConfigResources::DISTILUSE_BASE_MULTILINGUAL_CASED),
+        ("distiluse-base-multilingual-cased", "tokenizer_config", SentenceEmbeddingsTokenizerConfigResources::DISTILUSE_BASE_MULTILINGUAL_CASED),
+        ("electra-base-generator", "model", ElectraModelResources::BASE_GENERATOR),
+        ("electra-base-generator", "config", ElectraConfigResources::BASE_GENERATOR),
+        ("electra-base-generator", "vocab", ElectraVocabResources::BASE_GENERATOR),
+        ("electra-base-discriminator", "model", ElectraModelResources::BASE_DISCRIMINATOR),
+        ("electra-base-discriminator", "config", ElectraConfigResources::BASE_DISCRIMINATOR),
+        ("electra-base-discriminator", "vocab", ElectraVocabResources::BASE_DISCRIMINATOR),
+        ("fnet-base", "model", FNetModelResources::BASE),
+        ("fnet-base", "config", FNetConfigResources::BASE),
+        ("fnet-base", "vocab", FNetVocabResources::BASE),
+        ("fnet-base-sst2", "model", FNetModelResources::BASE_SST2),
+        ("fnet-base-sst2", "config", FNetConfigResources::BASE_SST2),
+        ("fnet-base-sst2", "vocab", FNetVocabResources::BASE_SST2),
+        ("gpt2", "model", Gpt2ModelResources::GPT2),
+        ("gpt2", "config", Gpt2ConfigResources::GPT2),
+        ("gpt2", "vocab", Gpt2VocabResources::GPT2),
+        ("gpt2", "merges", Gpt2MergesResources::GPT2),
+        ("gpt2-medium", "model", Gpt2ModelResources::GPT2_MEDIUM),
+        ("gpt2-medium", "config", Gpt2ConfigResources::GPT2_MEDIUM),
+        ("gpt2-medium", "vocab", Gpt2VocabResources::GPT2_MEDIUM),
+        ("gpt2-medium", "merges", Gpt2MergesResources::GPT2_MEDIUM),
+        ("gpt2-large", "model", Gpt2ModelResources::GPT2_LARGE),
+        ("gpt2-large", "config", Gpt2ConfigResources::GPT2_LARGE),
+        ("gpt2-large", "vocab", Gpt2VocabResources::GPT2_LARGE),
+        ("gpt2-large", "merges", Gpt2MergesResources::GPT2_LARGE),
+        ("gpt2-xl", "model", Gpt2ModelResources::GPT2_XL),
+        ("gpt2-xl", "config", Gpt2ConfigResources::GPT2_XL),
+        ("gpt2-xl", "vocab", Gpt2VocabResources::GPT2_XL),
+        ("gpt2-xl", "merges", Gpt2MergesResources::GPT2_XL),
+        ("distilgpt2", "model", Gpt2ModelResources::DISTIL_GPT2),
+        ("distilgpt2", "config", Gpt2ConfigResources::DISTIL_GPT2),
+        ("distilgpt2", "vocab", Gpt2VocabResources::DISTIL_GPT2),
+        ("distilgpt2", "merges", Gpt2MergesResources::DISTIL_GPT2),
+        ("dialogpt-medium", "model", Gpt2ModelResources::DIALOGPT_MEDIUM),
+        ("dialogpt-medium", "config", Gpt2ConfigResources::DIALOGPT_MEDIUM),
+        ("dialogpt-medium", "vocab", Gpt2VocabResources::DIALOGPT_MEDIUM),
+        ("dialogpt-medium", "merges", Gpt2MergesResources::DIALOGPT_MEDIUM),
+        ("gpt-neo-125M", "model", GptNeoModelResources::GPT_NEO_125M),
+        ("gpt-neo-125M", "config", GptNeoConfigResources::GPT_NEO_125M),
+        ("gpt-neo-125M", "vocab", GptNeoVocabResources::GPT_NEO_125M),
+        ("gpt-neo-125M", "merges", GptNeoMergesResources::GPT_NEO_125M),
+        ("gpt-neo-1_3B", "model", GptNeoModelResources::GPT_NEO_1_3B),
+        ("gpt-neo-1_3B", "config", GptNeoConfigResources::GPT_NEO_1_3B),
+        ("gpt-neo-1_3B", "vocab", GptNeoVocabResources::GPT_NEO_1_3B),
+        ("gpt-neo-1_3B", "merges", GptNeoMergesResources::GPT_NEO_1_3B),
+        ("gpt-neo-2_7B", "model", GptNeoModelResources::GPT_NEO_2_7B),
+        ("gpt-neo-2_7B", "config", GptNeoConfigResources::GPT_NEO_2_7B),
+        ("gpt-neo-2_7B", "vocab", GptNeoVocabResources::GPT_NEO_2_7B),
+        ("gpt-neo-2_7B", "merges", GptNeoMergesResources::GPT_NEO_2_7B),
+        ("longformer-base-4096", "model", LongformerModelResources::LONGFORMER_BASE_4096),
+        ("longformer-base-4096", "config", LongformerConfigResources::LONGFORMER_BASE_4096),
+        ("longformer-base-4096", "vocab", LongformerVocabResources::LONGFORMER_BASE_4096),
+        ("longformer-base-4096", "merges", LongformerMergesResources::LONGFORMER_BASE_4096),
+        ("longformer-base-4096-squad1", "model", LongformerModelResources::LONGFORMER_BASE_SQUAD1),
+        ("longformer-base-4096-squad1", "config", LongformerConfigResources::LONGFORMER_BASE_SQUAD1),
+        ("longformer-base-4096-squad1", "vocab", LongformerVocabResources::LONGFORMER_BASE_SQUAD1),
+        ("longformer-base-4096-squad1", "merges", LongformerMergesResources::LONGFORMER_BASE_SQUAD1),
+        ("m2m100-418m", "model", M2M100ModelResources::M2M100_418M),
+        ("m2m100-418m", "config", M2M100ConfigResources::M2M100_418M),
+        ("m2m100-418m", "vocab", M2M100VocabResources::M2M100_418M),
+        ("m2m100-418m", "merges", M2M100MergesResources::M2M100_418M),
+        ("m2m100-1_2b", "model", M2M100ModelResources::M2M100_1_2B),
+        ("m2m100-1_2b", "config", M2M100ConfigResources::M2M100_1_2B),
+        ("m2m100-1_2b", "vocab", M2M100VocabResources::M2M100_1_2B),
+        ("m2m100-1_2b", "merges", M2M100MergesResources::M2M100_1_2B),
+        ("marian-mt-en-ROMANCE", "model", MarianModelResources::ENGLISH2ROMANCE),
+        ("marian-mt-en-ROMANCE", "config", MarianConfigResources::ENGLISH2ROMANCE),
+        ("marian-mt-en-ROMANCE", "vocab", MarianVocabResources::ENGLISH2ROMANCE),
+        ("marian-mt-en-ROMANCE", "spm", MarianSpmResources::ENGLISH2ROMANCE),
+        ("marian-mt-ROMANCE-en", "model", MarianModelResources::ROMANCE2ENGLISH),
+        ("marian-mt-ROMANCE-en", "config", MarianConfigResources::ROMANCE2ENGLISH),
+        ("marian-mt-ROMANCE-en", "vocab", MarianVocabResources::ROMANCE2ENGLISH),
+        ("marian-mt-ROMANCE-en", "spm", MarianSpmResources::ROMANCE2ENGLISH),
+        ("marian-mt-en-de", "model", MarianModelResources::ENGLISH2GERMAN),
+        ("marian-mt-en-de", "config", MarianConfigResources::ENGLISH2GERMAN),
+        ("marian-mt-en-de", "vocab", MarianVocabResources::ENGLISH2GERMAN),
+        ("marian-mt-en-de", "spm", MarianSpmResources::ENGLISH2GERMAN),
+        ("marian-mt-de-en", "model", MarianModelResources::GERMAN2ENGLISH),
+        ("marian-mt-de-en", "config", MarianConfigResources::GERMAN2ENGLISH),
+        ("marian-mt-de-en", "vocab", MarianVocabResources::GERMAN2ENGLISH),
+        ("marian-mt-de-en", "spm", MarianSpmResources::GERMAN2ENGLISH),
+        ("marian-mt-en-ru", "model", MarianModelResources::ENGLISH2RUSSIAN),
+        ("marian-mt-en-ru", "config", MarianConfigResources::ENGLISH2RUSSIAN),
+        ("marian-mt-en-ru", "vocab", MarianVocabResources::ENGLISH2RUSSIAN),
+        ("marian-mt-en-ru", "spm", MarianSpmResources::ENGLISH2RUSSIAN),
+        ("marian-mt-ru-en", "model", MarianModelResources::RUSSIAN2ENGLISH),
+        ("marian-mt-ru-en", "config", MarianConfigResources::RUSSIAN2ENGLISH),
+        ("marian-mt-ru-en", "vocab", MarianVocabResources::RUSSIAN2ENGLISH),
+        ("marian-mt-ru-en", "spm", MarianSpmResources::RUSSIAN2ENGLISH),
+        ("marian-mt-fr-de", "model", MarianModelResources::FRENCH2GERMAN),
+        ("marian-mt-fr-de", "config", MarianConfigResources::FRENCH2GERMAN),
+        ("marian-mt-fr-de", "vocab", MarianVocabResources::FRENCH2GERMAN),
+        ("marian-mt-fr-de", "spm", MarianSpmResources::FRENCH2GERMAN),
+        ("marian-mt-de-fr", "model", MarianModelResources::GERMAN2FRENCH),
+        ("marian-mt-de-fr", "config", MarianConfigResources::GERMAN2FRENCH),
+        ("marian-mt-de-fr", "vocab", MarianVocabResources::GERMAN2FRENCH),
+        ("marian-mt-de-fr", "spm", MarianSpmResources::GERMAN2FRENCH),
+        ("marian-mt-en-nl", "model", MarianModelResources::ENGLISH2DUTCH),
+        ("marian-mt-en-nl", "config", MarianConfigResources::ENGLISH2DUTCH),
+        ("marian-mt-en-nl", "vocab", MarianVocabResources::ENGLISH2DUTCH),
+        ("marian-mt-en-nl", "spm", MarianSpmResources::ENGLISH2DUTCH),
+        ("marian-mt-nl-en", "model", MarianModelResources::DUTCH2ENGLISH),
+        ("marian-mt-nl-en", "config", MarianConfigResources::DUTCH2ENGLISH),
+        ("marian-mt-nl-en", "vocab", MarianVocabResources::DUTCH2ENGLISH),
+        ("marian-mt-nl-en", "spm", MarianSpmResources::DUTCH2ENGLISH),
+        ("marian-mt-en-zh", "model", MarianModelResources::ENGLISH2CHINESE),
+        ("marian-mt-en-zh", "config", MarianConfigResources::ENGLISH2CHINESE),
+        ("marian-mt-en-zh", "vocab", MarianVocabResources::ENGLISH2CHINESE),
+        ("marian-mt-en-zh", "spm", MarianSpmResources::ENGLISH2CHINESE),
+        ("marian-mt-zh-en", "model", MarianModelResources::CHINESE2ENGLISH),
+        ("marian-mt-zh-en", "config", MarianConfigResources::CHINESE2ENGLISH),
+        ("marian-mt-zh-en", "vocab", MarianVocabResources::CHINESE2ENGLISH),
+        ("marian-mt-zh-en", "spm", MarianSpmResources::CHINESE2ENGLISH),
+        ("marian-mt-en-sv", "model", MarianModelResources::ENGLISH2SWEDISH),
+        ("marian-mt-en-sv", "config", MarianConfigResources::ENGLISH2SWEDISH),
+        ("marian-mt-en-sv", "vocab", MarianVocabResources::ENGLISH2SWEDISH),
+        ("marian-mt-en-sv", "spm", MarianSpmResources::ENGLISH2SWEDISH),
+        ("marian-mt-sv-en", "model", MarianModelResources::SWEDISH2ENGLISH),
+        ("marian-mt-sv-en", "config", MarianConfigResources::SWEDISH2ENGLISH),
+        ("marian-mt-sv-en", "vocab", MarianVocabResources::SWEDISH2ENGLISH),
+        ("marian-mt-sv-en", "spm", MarianSpmResources::SWEDISH2ENGLISH),
+        ("marian-mt-ar-en", "model", MarianModelResources::ARABIC2ENGLISH),
+        ("marian-mt-ar-en", "config", MarianConfigResources::ARABIC2ENGLISH),
+        ("marian-mt-ar-en", "vocab", MarianVocabResources::ARABIC2ENGLISH),
+        ("marian-mt-ar-en", "spm", MarianSpmResources::ARABIC2ENGLISH),
+        ("marian-mt-en-ar", "model", MarianModelResources::ENGLISH2ARABIC),
+        ("marian-mt-en-ar", "config", MarianConfigResources::ENGLISH2ARABIC),
+        ("marian-mt-en-ar", "vocab", MarianVocabResources::ENGLISH2ARABIC),
+        ("marian-mt-en-ar", "spm", MarianSpmResources::ENGLISH2ARABIC),
+        ("marian-mt-hi-en", "model", MarianModelResources::HINDI2ENGLISH),
+        ("marian-mt-hi-en", "config", MarianConfigResources::HINDI2ENGLISH),
+        ("marian-mt-hi-en", "vocab", MarianVocabResources::HINDI2ENGLISH),
+        ("marian-mt-hi-en", "spm", MarianSpmResources::HINDI2ENGLISH),
+        ("marian-mt-en-hi", "model", MarianModelResources::ENGLISH2HINDI),
+        ("marian-mt-en-hi", "config", MarianConfigResources::ENGLISH2HINDI),
+        ("marian-mt-en-hi", "vocab", MarianVocabResources::ENGLISH2HINDI),
+        ("marian-mt-en-hi", "spm", MarianSpmResources::ENGLISH2HINDI),
+        ("marian-mt-he-en", "model", MarianModelResources::HEBREW2ENGLISH),
+        ("marian-mt-he-en", "config", MarianConfigResources::HEBREW2ENGLISH),
+        ("marian-mt-he-en", "vocab", MarianVocabResources::HEBREW2ENGLISH),
+        ("marian-mt-he-en", "spm", MarianSpmResources::HEBREW2ENGLISH),
+        ("marian-mt-en-he", "model", MarianModelResources::ENGLISH2HEBREW),
+        ("marian-mt-en-he", "config", MarianConfigResources::ENGLISH2HEBREW),
+        ("marian-mt-en-he", "vocab", MarianVocabResources::ENGLISH2HEBREW),
+        ("marian-mt-en-he", "spm", MarianSpmResources::ENGLISH2HEBREW),
+        ("mbart-50-many-to-many-mmt", "model", MBartModelResources::MBART50_MANY_TO_MANY),
+        ("mbart-50-many-to-many-mmt", "config", MBartConfigResources::MBART50_MANY_TO_MANY),
+        ("mbart-50-many-to-many-mmt", "vocab", MBartVocabResources::MBART50_MANY_TO_MANY),
+        ("mobilebert-uncased", "model", MobileBertModelResources::MOBILEBERT_UNCASED),
+        ("mobilebert-uncased", "config", MobileBertConfigResources::MOBILEBERT_UNCASED),
+        ("mobilebert-uncased", "vocab", MobileBertVocabResources::MOBILEBERT_UNCASED),
+        ("mobilebert-finetuned-pos", "model", MobileBertModelResources::MOBILEBERT_ENGLISH_POS),
+        ("mobilebert-finetuned-pos", "config", MobileBertConfigResources::MOBILEBERT_ENGLISH_POS),
+        ("mobilebert-finetuned-pos", "vocab", MobileBertVocabResources::MOBILEBERT_ENGLISH_POS),
+        ("openai-gpt", "model", OpenAiGptModelResources::GPT),
+        ("openai-gpt", "config", OpenAiGptConfigResources::GPT),
+        ("openai-gpt", "vocab", OpenAiGptVocabResources::GPT),
+        ("openai-gpt", "merges", OpenAiGptMergesResources::GPT),
+        ("pegasus-cnn_dailymail", "model", PegasusModelResources::CNN_DAILYMAIL),
+        ("pegasus-cnn_dailymail", "config", PegasusConfigResources::CNN_DAILYMAIL),
+        ("pegasus-cnn_dailymail", "vocab", PegasusVocabResources::CNN_DAILYMAIL),
+        ("all-distilroberta-v1", "modules_config", SentenceEmbeddingsModulesConfigResources::ALL_DISTILROBERTA_V1),
+        ("all-distilroberta-v1", "pooling_config", SentenceEmbeddingsPoolingConfigResources::ALL_DISTILROBERTA_V1),
+        ("all-distilroberta-v1", "sentence_embeddings_config", SentenceEmbeddingsConfigResources::ALL_DISTILROBERTA_V1),
+        ("all-distilroberta-v1", "tokenizer_config", SentenceEmbeddingsTokenizerConfigResources::ALL_DISTILROBERTA_V1),
+        ("all-distilroberta-v1", "model", RobertaModelResources::ALL_DISTILROBERTA_V1),
+        ("all-distilroberta-v1", "config", RobertaConfigResources::ALL_DISTILROBERTA_V1),
+        ("all-distilroberta-v1", "vocab", RobertaVocabResources::ALL_DISTILROBERTA_V1),
+        ("all-distilroberta-v1", "merges", RobertaMergesResources::ALL_DISTILROBERTA_V1),
+        ("sentence-t5-base", "modules_config", SentenceEmbeddingsModulesConfigResources::SENTENCE_T5_BASE),
+        ("sentence-t5-base", "dense", SentenceEmbeddingsDenseResources::SENTENCE_T5_BASE),
+        ("sentence-t5-base", "dense_config", SentenceEmbeddingsDenseConfigResources::SENTENCE_T5_BASE),
+        ("sentence-t5-base", "pooling_config", SentenceEmbeddingsPoolingConfigResources::SENTENCE_T5_BASE),
+        ("sentence-t5-base", "sentence_embeddings_config", SentenceEmbeddingsConfigResources::SENTENCE_T5_BASE),
+        ("sentence-t5-base", "tokenizer_config", SentenceEmbeddingsTokenizerConfigResources::SENTENCE_T5_BASE),
+        ("sentence-t5-base", "model", T5ModelResources::SENTENCE_T5_BASE),
+        ("sentence-t5-base", "config", T5ConfigResources::SENTENCE_T5_BASE),
+        ("sentence-t5-base", "vocab", T5VocabResources::SENTENCE_T5_BASE),
+        ("prophetnet-large-uncased", "model", ProphetNetModelResources::PROPHETNET_LARGE_UNCASED),
+        ("prophetnet-large-uncased", "config", ProphetNetConfigResources::PROPHETNET_LARGE_UNCASED),
+        ("prophetnet-large-uncased", "vocab", ProphetNetVocabResources::PROPHETNET_LARGE_UNCASED),
+        ("prophetnet-large-uncased-cnndm", "model", ProphetNetModelResources::PROPHETNET_LARGE_CNN_DM),
+        ("prophetnet-large-uncased-cnndm", "config", ProphetNetConfigResources::PROPHETNET_LARGE_CNN_DM),
+        ("prophetnet-large-uncased-cnndm", "vocab", ProphetNetVocabResources::PROPHETNET_LARGE_CNN_DM),
+        ("reformer-crime-punishment", "model", ReformerModelResources::CRIME_AND_PUNISHMENT),
+        ("reformer-crime-punishment", "config", ReformerConfigResources::CRIME_AND_PUNISHMENT),
+        ("reformer-crime-punishment", "vocab", ReformerVocabResources::CRIME_AND_PUNISHMENT),
+        ("roberta", "model", RobertaModelResources::ROBERTA),
+        ("roberta", "config", RobertaConfigResources::ROBERTA),
+        ("roberta", "vocab", RobertaVocabResources::ROBERTA),
+        ("roberta", "merges", RobertaMergesResources::ROBERTA),
+        ("distilroberta-base", "model", RobertaModelResources::DISTILROBERTA_BASE),
+        ("distilroberta-base", "config", RobertaConfigResources::DISTILROBERTA_BASE),
+        ("distilroberta-base", "vocab", RobertaVocabResources::DISTILROBERTA_BASE),
+        ("distilroberta-base", "merges", RobertaMergesResources::DISTILROBERTA_BASE),
+        ("roberta-qa", "model", RobertaModelResources::ROBERTA_QA),
+        ("roberta-qa", "config", RobertaConfigResources::ROBERTA_QA),
+        ("roberta-qa", "vocab", RobertaVocabResources::ROBERTA_QA),
+        ("roberta-qa", "merges", RobertaMergesResources::ROBERTA_QA),
+        ("xlm-roberta-ner-en", "model", RobertaModelResources::XLM_ROBERTA_NER_EN),
+        ("xlm-roberta-ner-en", "config", RobertaConfigResources::XLM_ROBERTA_NER_EN),
+        ("xlm-roberta-ner-en", "vocab", RobertaVocabResources::XLM_ROBERTA_NER_EN),
+        ("xlm-roberta-ner-de", "model", RobertaModelResources::XLM_ROBERTA_NER_DE),
+        ("xlm-roberta-ner-de", "config", RobertaConfigResources::XLM_ROBERTA_NER_DE),
+        ("xlm-roberta-ner-de", "vocab", RobertaVocabResources::XLM_ROBERTA_NER_DE),
+        ("xlm-roberta-ner-nl", "model", RobertaModelResources::XLM_ROBERTA_NER_NL),
+        ("xlm-roberta-ner-nl", "config", RobertaConfigResources::XLM_ROBERTA_NER_NL),
+        ("xlm-roberta-ner-nl", "vocab", RobertaVocabResources::XLM_ROBERTA_NER_NL),
+        ("xlm-roberta-ner-es", "model", RobertaModelResources::XLM_ROBERTA_NER_ES),
+        ("xlm-roberta-ner-es", "config", RobertaConfigResources::XLM_ROBERTA_NER_ES),
+        ("xlm-roberta-ner-es", "vocab", RobertaVocabResources::XLM_ROBERTA_NER_ES),
+        ("xlm-roberta-large-xnli", "config", RobertaConfigResources::XLM_ROBERTA_LARGE_XNLI),
+        ("xlm-roberta-large-xnli", "vocab", RobertaVocabResources::XLM_ROBERTA_LARGE_XNLI),
+        ("t5-small", "model", T5ModelResources::T5_SMALL),
+        ("t5-small", "config", T5ConfigResources::T5_SMALL),
+        ("t5-small", "vocab", T5VocabResources::T5_SMALL),
+        ("t5-base", "model", T5ModelResources::T5_BASE),
+        ("t5-base", "config", T5ConfigResources::T5_BASE),
+        ("t5-base", "vocab", T5VocabResources::T5_BASE),
+        ("codet5-base-multi-sum", "config", T5ConfigResources::CODET5_BASE_MULTI_SUM),
+        ("codet5-base-multi-sum", "vocab", T5VocabResources::CODET5_BASE_MULTI_SUM),
+        ("codet5-base-multi-sum", "merges", T5MergesResources::CODET5_BASE_MULTI_SUM),
+        ("flan-t5-small", "config", T5ConfigResources::FLAN_T5_SMALL),
+        ("flan-t5-small", "vocab", T5VocabResources::FLAN_T5_SMALL),
+        ("flan-t5-base", "config", T5ConfigResources::FLAN_T5_BASE),
+        ("flan-t5-base", "vocab", T5VocabResources::FLAN_T5_BASE),
+        ("flan-t5-large", "config", T5ConfigResources::FLAN_T5_LARGE),
+        ("flan-t5-large", "vocab", T5VocabResources::FLAN_T5_LARGE),
+        ("xlnet-base-cased", "model", XLNetModelResources::XLNET_BASE_CASED),
+        ("xlnet-base-cased", "config", XLNetConfigResources::XLNET_BASE_CASED),
+        ("xlnet-base-cased", "vocab", XLNetVocabResources::XLNET_BASE_CASED),
+    ];
+
+    fn as_pair(resource: &PretrainedResource) -> (&str, &str) {
+        (resource.cache_subdir.as_str(), resource.url.as_str())
+    }
+
+    #[test]
+    fn test_registry_matches_resources() {
+        for (name, role, constant) in RESOURCE_CONSTANTS {
+            let model = get_pretrained(name)
+                .unwrap_or_else(|| panic!("{} missing from the pretrained registry", name));
+            assert_eq!(
+                as_pair(model.resource(role).unwrap()),
+                *constant,
+                "{} {} resource",
+                name,
+                role
+            );
+        }
+        assert_eq!(get_pretrained("gpt2").unwrap().model_type, ModelType::GPT2);
+        assert!(get_pretrained("gpt2").unwrap().resource("spm").is_err());
+
+        // Every resource of the embedded registry has a constant
+        let models = parse_registry(PRETRAINED_REGISTRY_DATA).unwrap();
+        for model in &models {
+            assert!(model.resources.contains_key("config"), "{}", model.name);
+            for role in model.resources.keys() {
+                assert!(
+                    RESOURCE_CONSTANTS.iter().any(
+                        |(name, constant_role, _)| *name == model.name && constant_role == role
+                    ),
+                    "No constant for the {} {} resource",
+                    model.name,
+                    role
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn test_find_pretrained() {
+        let names = |query: &PretrainedQuery| {
+            find_pretrained(query)
+                .into_iter()
+                .map(|model| model.name)
+                .collect::<Vec<String>>()
+        };
+        assert_eq!(
+            names(&PretrainedQuery {
+                task: Some(PretrainedTask::TextGeneration),
+                model_type: Some(ModelType::GPT2),
+                max_parameters: Some(500_000_000),
+                ..Default::default()
+            }),
+            vec!["distilgpt2", "gpt2", "gpt2-medium"]
+        );
+
+        let german_ner = names(&PretrainedQuery {
+            task: Some(PretrainedTask::TokenClassification),
+            language: Some("de".to_string()),
+            ..Default::default()
+        });
+        assert_eq!(german_ner, vec!["xlm-roberta-ner-de"]);
+
+        // Multilingual checkpoints support any language
+        let french_translation = names(&PretrainedQuery {
+            task: Some(PretrainedTask::Translation),
+            language: Some("fr".to_string()),
+            ..Default::default()
+        });
+        assert!(french_translation.contains(&"m2m100-418m".to_string()));
+        assert!(french_translation.contains(&"marian-mt-en-ROMANCE".to_string()));
+        assert!(!french_translation.contains(&"marian-mt-en-de".to_string()));
+    }
+
+    #[test]
+    fn test_load_pretrained_registry() {
+        let mut custom = get_pretrained("distilbert-sst2").unwrap();
+        custom.name = "test-registry-sentiment".to_string();
+        custom.languages = vec!["fr".to_string()];
+
+        let mut file = tempfile::NamedTempFile::new().unwrap();
+        file.write_all(serde_json::to_string(&vec![&custom]).unwrap().as_bytes())
+            .unwrap();
+        load_pretrained_registry(file.path()).unwrap();
+        assert_eq!(
+            get_pretrained("test-registry-sentiment"),
+            Some(custom.clone())
+        );
+
+        // Names must be unique
+        assert!(load_pretrained_registry(file.path()).is_err());
+        assert!(register_pretrained(custom).is_err());
+    }
+}
@@ -0,0 +1,321 @@
+// Copyright 2022 Guillaume Becquin
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//     http://www.apache.org/licenses/LICENSE-2.0
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! # Prompt-based few-shot classification pipeline
+//! Classifies texts with a language model, without a classification head fine-tuned for the label set. The input is
+//! inserted in a prompt template, and each label is scored by the likelihood of its verbalizations as a continuation
+//! of the prompt (computed with `LanguageGenerator::score_sequences`). The label probabilities are obtained by a
+//! softmax over the label scores.
+//!
+//! This covers two common approaches:
+//! - pattern-verbalizer pairs ([Schick and Schütze, 2021](https://arxiv.org/abs/2001.07676)): the template is a cloze
+//! pattern and each label is verbalized by one or more words (e.g. `" great"` and `" good"` for a positive review). The
+//! probabilities of the verbalizers of a label are summed.
+//! - instruction prompts: the template describes the task and the labels are scored as answers. The token
+//! log-probabilities of multi-token answers can be averaged with `length_normalization` to avoid favoring short answers.
+//!
+//! Few-shot examples can be included in the template.
+//!
+//! ```no_run
+//! # fn main() -> anyhow::Result<()> {
+//! use rust_bert::pipelines::prompt_classification::{
+//!     PromptClassificationConfig, PromptClassificationModel, PromptLabel,
+//! };
+//!
+//! let config = PromptClassificationConfig::new(
+//!     Default::default(),
+//!     "Review: {} All in all, the movie was",
+//!     vec![
+//!         PromptLabel::new("positive", &[" great", " good"]),
+//!         PromptLabel::new("negative", &[" terrible", " bad"]),
+//!     ],
+//! );
+//! let model = PromptClassificationModel::new(config)?;
+//!
+//! let output = model.predict(&["I would watch it again!", "A waste of time."])?;
+//! # Ok(())
+//! # }
+//! ```
+//!
+//! Instruction prompts:
+//!
+//! ```no_run
+//! # fn main() -> anyhow::Result<()> {
+//! use rust_bert::pipelines::prompt_classification::{
+//!     PromptClassificationConfig, PromptClassificationModel, PromptLabel,
+//! };
+//!
+//! let mut config = PromptClassificationConfig::new(
+//!     Default::default(),
+//!     "Classify the topic of the news article.\nArticle: {}\nTopic:",
+//!     vec![
+//!         PromptLabel::new("politics", &[" politics"]),
+//!         PromptLabel::new("sports", &[" sports"]),
+//!         PromptLabel::new("science", &[" science and technology"]),
+//!     ],
+//! );
+//! config.length_normalization = true;
+//! let model = PromptClassificationModel::new(config)?;
+//!
+//! let output = model.predict_all_labels(&["The striker scored twice in the final."])?;
+//! # Ok(())
+//! # }
+//! ```
+
+use crate::common::error::RustBertError;
+use crate::pipelines::common::Pipeline;
+use crate::pipelines::sequence_classification::Label;
+use crate::pipelines::text_generation::{TextGenerationConfig, TextGenerationOption};
+
+/// # Label of a prompt-based classifier
+#[derive(Debug, Clone)]
+pub struct PromptLabel {
+    /// Name of the label, returned in the predictions
+    pub name: String,
+    /// Verbalizations of the label, scored as continuations of the prompt. A leading space should be included for
+    /// tokenizers encoding word boundaries (e.g. GPT2).
+    pub verbalizers: Vec<String>,
+}
+
+impl PromptLabel {
+    /// Create a new `PromptLabel`
+    ///
+    /// # Arguments
+    ///
+    /// * `name` - Name of the label
+    /// * `verbalizers` - Words or phrases verbalizing the label (at least one)
+    pub fn new<S, V>(name: S, verbalizers: &[V]) -> PromptLabel
+    where
+        S: Into<String>,
+        V: AsRef<str>,
+    {
+        PromptLabel {
+            name: name.into(),
+            verbalizers: verbalizers
+                .iter()
+                .map(|verbalizer| verbalizer.as_ref().to_string())
+                .collect(),
+        }
+    }
+}
+
+/// # Configuration for prompt-based classification
+pub struct PromptClassificationConfig {
+    /// Language model configuration (model type, resources and device). The generation options are not used.
+    pub model_config: TextGenerationConfig,
+    /// Prompt template, the first `{}` is replaced by the input text
+    pub template: String,
+    /// Labels and their verbalizations
+    pub labels: Vec<PromptLabel>,
+    /// Average the token log-probabilities of the verbalizations instead of summing them
+    pub length_normalization: bool,
+}
+
+impl PromptClassificationConfig {
+    /// Create a new `PromptClassificationConfig` (without length normalization)
+    ///
+    /// # Arguments
+    ///
+    /// * `model_config` - `TextGenerationConfig` of the language model used for scoring
+    /// * `template` - Prompt template, the first `{}` is replaced by the input text
+    /// * `labels` - Labels and their verbalizations
+    pub fn new<S>(
+        model_config: TextGenerationConfig,
+        template: S,
+        labels: Vec<PromptLabel>,
+    ) -> PromptClassificationConfig
+    where
+        S: Into<String>,
+    {
+        PromptClassificationConfig {
+            model_config,
+            template: template.into(),
+            labels,
+            length_normalization: false,
+        }
+    }
+}
+
+/// # Prompt-based classification model
+pub struct PromptClassificationModel {
+    model: TextGenerationOption,
+    template: String,
+    labels: Vec<PromptLabel>,
+    length_normalization: bool,
+}
+
+impl PromptClassificationModel {
+    /// Build a new `PromptClassificationModel`
+    ///
+    /// # Arguments
+    ///
+    /// * `config` - `PromptClassificationConfig` containing the language model configuration, prompt template and labels
+    pub fn new(
+        config: PromptClassificationConfig,
+    ) -> Result<PromptClassificationModel, RustBertError> {
+        if !config.template.contains("{}") {
+            return Err(RustBertError::InvalidConfigurationError(
+                "The prompt template must contain a `{}` placeholder for the input".to_string(),
+            ));
+        }
+        if config.labels.is_empty() {
+            return Err(RustBertError::InvalidConfigurationError(
+                "At least one label must be provided".to_string(),
+            ));
+        }
+        if let Some(label) = config
+            .labels
+            .iter()
+            .find(|label| label.verbalizers.is_empty())
+        {
+            return Err(RustBertError::InvalidConfigurationError(format!(
+                "No verbalizer provided for label {}",
+                label.name
+            )));
+        }
+
+        let model = TextGenerationOption::new(config.model_config)?;
+        Ok(PromptClassificationModel {
+            model,
+            template: config.template,
+            labels: config.labels,
+            length_normalization: config.length_normalization,
+        })
+    }
+
+    /// Returns the log-score of each label for an input
+    fn label_scores(&self, input: &str) -> Result<Vec<f64>, RustBertError> {
+        let prompt = self.template.replacen("{}", input, 1);
+        let (label_indices, continuations): (Vec<usize>, Vec<&str>) = self
+            .labels
+            .iter()
+            .enumerate()
+            .flat_map(|(label_index, label)| {
+                label
+                    .verbalizers
+                    .iter()
+                    .map(move |verbalizer| (label_index, verbalizer.as_str()))
+            })
+            .unzip();
+        let prompts = vec![prompt.as_str(); continuations.len()];
+        let token_scores = self.model.score_sequences(&prompts, &continuations)?;
+
+        // Log of the summed probabilities of the verbalizers of each label
+        let mut verbalizer_scores = vec![Vec::new(); self.labels.len()];
+        for (label_index, scores) in label_indices.into_iter().zip(token_scores) {
+            let score = scores.iter().sum::<f64>();
+            verbalizer_scores[label_index].push(if self.length_normalization {
+                score / scores.len() as f64
+            } else {
+                score
+            });
+        }
+        Ok(verbalizer_scores
+            .into_iter()
+            .map(|scores| log_sum_exp(&scores))
+            .collect())
+    }
+
+    /// Computes the probability of all labels for each input
+    ///
+    /// # Arguments
+    ///
+    /// * `inputs` - Texts to classify
+    ///
+    /// # Returns
+    ///
+    /// * `Vec<Vec<Label>>` containing the labels (in the order of the configuration) and their probability for each input
+    pub fn predict_all_labels<S>(&self, inputs: &[S]) -> Result<Vec<Vec<Label>>, RustBertError>
+    where
+        S: AsRef<str>,
+    {
+        inputs
+            .iter()
+            .enumerate()
+            .map(|(sentence, input)| {
+                let scores = self.label_scores(input.as_ref())?;
+                let normalization = log_sum_exp(&scores);
+                Ok(self
+                    .labels
+                    .iter()
+                    .zip(scores)
+                    .enumerate()
+                    .map(|(id, (label, score))| Label {
+                        text: label.name.clone(),
+                        score: (score - normalization).exp(),
+                        id: id as i64,
+                        sentence,
+                    })
+                    .collect())
+            })
+            .collect()
+    }
+
+    /// Classifies the inputs, returning the most likely label for each input
+    ///
+    /// # Arguments
+    ///
+    /// * `inputs` - Texts to classify
+    ///
+    /// # Returns
+    ///
+    /// * `Vec<Label>` containing the most likely label and its probability for each input
+    pub fn predict<S>(&self, inputs: &[S]) -> Result<Vec<Label>, RustBertError>
+    where
+        S: AsRef<str>,
+    {
+        Ok(self
+            .predict_all_labels(inputs)?
+            .into_iter()
+            .map(|labels| {
+                labels
+                    .into_iter()
+                    .max_by(|a, b| a.score.partial_cmp(&b.score).unwrap())
+                    .unwrap()
+            })
+            .collect())
+    }
+}
+
+fn log_sum_exp(values: &[f64]) -> f64 {
+    let max = values.iter().cloned().fold(f64::NEG_INFINITY, f64::max);
+    if max == f64::NEG_INFINITY {
+        return max;
+    }
+    max + values
+        .iter()
+        .map(|value| (value - max).exp())
+        .sum::<f64>()
+        .ln()
+}
+
+impl<S> Pipeline<S, Label> for PromptClassificationModel
+where
+    S: AsRef<str>,
+{
+    type Options = ();
+
+    fn run_with_options(&self, inputs: &[S], _options: &()) -> Result<Vec<Label>, RustBertError> {
+        self.predict(inputs)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_log_sum_exp() {
+        let values = [0.5f64.ln(), 0.25f64.ln()];
+        assert!((log_sum_exp(&values) - 0.75f64.ln()).abs() < 1e-12);
+        assert_eq!(log_sum_exp(&[f64::NEG_INFINITY]), f64::NEG_INFINITY);
+    }
+}
@@ -0,0 +1,572 @@
+// Copyright 2022 Guillaume Becquin
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//     http://www.apache.org/licenses/LICENSE-2.0
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! # Punctuation restoration pipeline
+//! Inserts punctuation (`.`, `,`, `?`, `!`) in unpunctuated transcripts, such as the output of speech recognition
+//! systems. A token classification model predicts, for each word, the punctuation mark following it (e.g.
+//! `0`, `.`, `,`, `?` labels of the fullstop-punctuation models). Labels are used as punctuation marks unless a
+//! mapping is provided (e.g. `PERIOD` -> `.`), and only the configured punctuation marks are inserted. The first
+//! word of each sentence is capitalized.
+//!
+//! ```no_run
+//! # fn main() -> anyhow::Result<()> {
+//! use rust_bert::pipelines::common::ModelType;
+//! use rust_bert::pipelines::punctuation_restoration::{
+//!     PunctuationRestorationConfig, PunctuationRestorationModel,
+//! };
+//! use rust_bert::pipelines::token_classification::{
+//!     LabelAggregationOption, TokenClassificationConfig,
+//! };
+//! use rust_bert::resources::LocalResource;
+//! use std::path::PathBuf;
+//!
+//! let token_classification_config = TokenClassificationConfig::new(
+//!     ModelType::XLMRoberta,
+//!     LocalResource::from(PathBuf::from("path/to/rust_model.ot")),
+//!     LocalResource::from(PathBuf::from("path/to/config.json")),
+//!     LocalResource::from(PathBuf::from("path/to/sentencepiece.bpe.model")),
+//!     None,
+//!     false,
+//!     None,
+//!     None,
+//!     LabelAggregationOption::Last,
+//! );
+//! let model =
+//!     PunctuationRestorationModel::new(PunctuationRestorationConfig::new(token_classification_config))?;
+//!
+//! let output = model.restore(&["hello how are you i am fine thank you"]);
+//! # Ok(())
+//! # }
+//! ```
+//!
+//! For live captioning, a `PunctuationStream` punctuates a transcript arriving in chunks. A word is only
+//! punctuated once a few words following it (`lookahead`) have been received, since the punctuation of a word
+//! depends on the next words. The previously finalized words are kept as left context for the next predictions.
+//!
+//! ```no_run
+//! # fn main() -> anyhow::Result<()> {
+//! # use rust_bert::pipelines::common::ModelType;
+//! # use rust_bert::pipelines::punctuation_restoration::{
+//! #     PunctuationRestorationConfig, PunctuationRestorationModel, PunctuationStream,
+//! # };
+//! # use rust_bert::pipelines::token_classification::{
+//! #     LabelAggregationOption, TokenClassificationConfig,
+//! # };
+//! # use rust_bert::resources::LocalResource;
+//! # use std::path::PathBuf;
+//! # let token_classification_config = TokenClassificationConfig::new(
+//! #     ModelType::XLMRoberta,
+//! #     LocalResource::from(PathBuf::from("path/to/rust_model.ot")),
+//! #     LocalResource::from(PathBuf::from("path/to/config.json")),
+//! #     LocalResource::from(PathBuf::from("path/to/sentencepiece.bpe.model")),
+//! #     None,
+//! #     false,
+//! #     None,
+//! #     None,
+//! #     LabelAggregationOption::Last,
+//! # );
+//! # let model =
+//! #     PunctuationRestorationModel::new(PunctuationRestorationConfig::new(token_classification_config))?;
+//! let mut stream = PunctuationStream::new(&model, 4, 32);
+//! let mut captions = String::new();
+//! for chunk in ["hello how are", "you i am fine", "thank you"] {
+//!     captions.push_str(&stream.push(chunk));
+//! }
+//! captions.push_str(&stream.flush());
+//! # Ok(())
+//! # }
+//! ```
+
+use crate::common::error::RustBertError;
+use crate::pipelines::common::Pipeline;
+use crate::pipelines::token_classification::{
+    Token, TokenClassificationConfig, TokenClassificationModel,
+};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+
+/// # Punctuation mark inserted by a `PunctuationRestorationModel`
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct InsertedPunctuation {
+    /// Punctuation mark
+    pub punctuation: String,
+    /// Position (in characters of the input string) at which the punctuation mark is inserted
+    pub position: usize,
+    /// Confidence score
+    pub score: f64,
+}
+
+/// # Output of the punctuation restoration pipeline for one input
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct PunctuationRestorationOutput {
+    /// Punctuated text
+    pub text: String,
+    /// Punctuation marks inserted, in order of appearance in the input
+    pub insertions: Vec<InsertedPunctuation>,
+}
+
+/// # Configuration for PunctuationRestorationModel
+pub struct PunctuationRestorationConfig {
+    /// Configuration of the token classification model predicting the punctuation following each word. The label
+    /// of the last sub-token of each word should be used (`LabelAggregationOption::Last`).
+    pub token_classification_config: TokenClassificationConfig,
+    /// Punctuation marks inserted, predictions of other labels are ignored (default: `.`, `,`, `?`, `!`)
+    pub punctuation: Vec<String>,
+    /// Mapping from the model labels to punctuation marks, for labels that are not punctuation marks (default: empty)
+    pub label_mapping: HashMap<String, String>,
+    /// Capitalize the first word of the text and of each sentence (default: true)
+    pub capitalize_sentences: bool,
+    /// Predictions with a score below this threshold are ignored (default: 0.0)
+    pub min_score: f64,
+}
+
+impl PunctuationRestorationConfig {
+    /// Instantiate a new punctuation restoration configuration
+    ///
+    /// # Arguments
+    ///
+    /// * `token_classification_config` - `TokenClassificationConfig` of the model predicting the punctuation
+    pub fn new(
+        token_classification_config: TokenClassificationConfig,
+    ) -> PunctuationRestorationConfig {
+        PunctuationRestorationConfig {
+            token_classification_config,
+            punctuation: vec![
+                ".".to_string(),
+                ",".to_string(),
+                "?".to_string(),
+                "!".to_string(),
+            ],
+            label_mapping: HashMap::new(),
+            capitalize_sentences: true,
+            min_score: 0.0,
+        }
+    }
+}
+
+fn ends_sentence(punctuation: &str) -> bool {
+    punctuation.ends_with(|c| matches!(c, '.' | '?' | '!'))
+}
+
+fn capitalize(word: &str) -> String {
+    let mut chars = word.chars();
+    match chars.next() {
+        Some(first) => first.to_uppercase().chain(chars).collect(),
+        None => String::new(),
+    }
+}
+
+/// Rules turning the labels predicted for the words of a text into punctuation marks
+struct PunctuationRules {
+    punctuation: Vec<String>,
+    label_mapping: HashMap<String, String>,
+    capitalize_sentences: bool,
+    min_score: f64,
+}
+
+impl PunctuationRules {
+    fn punctuation_for(&self, token: &Token) -> Option<&str> {
+        if token.score < self.min_score {
+            return None;
+        }
+        let punctuation = self.label_mapping.get(&token.label).unwrap_or(&token.label);
+        self.punctuation
+            .iter()
+            .find(|mark| *mark == punctuation)
+            .map(|mark| mark.as_str())
+    }
+
+    /// Returns the punctuation mark predicted after each word (given by its byte offsets in the text the tokens
+    /// were predicted for), from the label of the last token of the word
+    fn word_predictions(
+        &self,
+        word_offsets: &[(usize, usize)],
+        tokens: &[Token],
+    ) -> Vec<Option<(String, f64)>> {
+        let mut predictions = vec![None; word_offsets.len()];
+        let mut tokens = tokens
+            .iter()
+            .filter(|token| token.byte_offset.is_some())
+            .collect::<Vec<&Token>>();
+        tokens.sort_by_key(|token| token.byte_offset.unwrap().begin);
+        let mut word_index = 0;
+        for token in tokens {
+            let begin = token.byte_offset.unwrap().begin as usize;
+            while word_index < word_offsets.len() && word_offsets[word_index].1 <= begin {
+                word_index += 1;
+            }
+            if word_index == word_offsets.len() {
+                break;
+            }
+            if begin >= word_offsets[word_index].0 {
+                predictions[word_index] = self
+                    .punctuation_for(token)
+                    .map(|punctuation| (punctuation.to_string(), token.score));
+            }
+        }
+        predictions
+    }
+
+    /// Returns the punctuated word and the punctuation mark inserted after it, if any
+    fn punctuate_word(
+        &self,
+        word: &str,
+        prediction: Option<&(String, f64)>,
+        sentence_start: &mut bool,
+    ) -> (String, Option<String>) {
+        let mut punctuated = if *sentence_start && self.capitalize_sentences {
+            capitalize(word)
+        } else {
+            word.to_string()
+        };
+        let existing_punctuation = word.ends_with(|c: char| c.is_ascii_punctuation());
+        let inserted = match prediction {
+            Some((punctuation, _)) if !existing_punctuation => {
+                punctuated.push_str(punctuation);
+                Some(punctuation.clone())
+            }
+            _ => None,
+        };
+        *sentence_start = ends_sentence(&punctuated);
+        (punctuated, inserted)
+    }
+
+    fn restore(&self, text: &str, tokens: &[Token]) -> PunctuationRestorationOutput {
+        let mut word_offsets = Vec::new();
+        let mut word_char_ends = Vec::new();
+        let mut word_begin: Option<usize> = None;
+        for (char_index, (byte_index, character)) in text.char_indices().enumerate() {
+            if character.is_whitespace() {
+                if let Some(begin) = word_begin.take() {
+                    word_offsets.push((begin, byte_index));
+                    word_char_ends.push(char_index);
+                }
+            } else if word_begin.is_none() {
+                word_begin = Some(byte_index);
+            }
+        }
+        if let Some(begin) = word_begin {
+            word_offsets.push((begin, text.len()));
+            word_char_ends.push(text.chars().count());
+        }
+
+        let predictions = self.word_predictions(&word_offsets, tokens);
+        let mut punctuated_text = String::with_capacity(text.len() + word_offsets.len());
+        let mut insertions = Vec::new();
+        let mut cursor = 0;
+        let mut sentence_start = true;
+        for (((begin, end), char_end), prediction) in word_offsets
+            .iter()
+            .zip(word_char_ends.iter())
+            .zip(predictions.iter())
+        {
+            punctuated_text.push_str(&text[cursor..*begin]);
+            let (punctuated, inserted) = self.punctuate_word(
+                &text[*begin..*end],
+                prediction.as_ref(),
+                &mut sentence_start,
+            );
+            punctuated_text.push_str(&punctuated);
+            if let (Some(punctuation), Some((_, score))) = (inserted, prediction) {
+                insertions.push(InsertedPunctuation {
+                    punctuation,
+                    position: *char_end,
+                    score: *score,
+                });
+            }
+            cursor = *end;
+        }
+        punctuated_text.push_str(&text[cursor..]);
+        PunctuationRestorationOutput {
+            text: punctuated_text,
+            insertions,
+        }
+    }
+}
+
+/// # PunctuationRestorationModel to insert punctuation in transcripts
+pub struct PunctuationRestorationModel {
+    token_classification_model: TokenClassificationModel,
+    rules: PunctuationRules,
+}
+
+impl PunctuationRestorationModel {
+    /// Build a new `PunctuationRestorationModel`
+    ///
+    /// # Arguments
+    ///
+    /// * `config` - `PunctuationRestorationConfig` object containing the token classification model configuration and the punctuation settings
+    pub fn new(
+        config: PunctuationRestorationConfig,
+    ) -> Result<PunctuationRestorationModel, RustBertError> {
+        let token_classification_model =
+            TokenClassificationModel::new(config.token_classification_config)?;
+        Ok(PunctuationRestorationModel {
+            token_classification_model,
+            rules: PunctuationRules {
+                punctuation: config.punctuation,
+                label_mapping: config.label_mapping,
+                capitalize_sentences: config.capitalize_sentences,
+                min_score: config.min_score,
+            },
+        })
+    }
+
+    /// Inserts punctuation in texts
+    ///
+    /// # Arguments
+    ///
+    /// * `input` - `&[&str]` Array of unpunctuated texts (e.g. speech recognition transcripts).
+    ///
+    /// # Returns
+    ///
+    /// * `Vec<PunctuationRestorationOutput>` containing the punctuated texts and the punctuation marks inserted
+    pub fn restore<S>(&self, input: &[S]) -> Vec<PunctuationRestorationOutput>
+    where
+        S: AsRef<str>,
+    {
+        self.token_classification_model
+            .predict(input, true, false)
+            .into_iter()
+            .zip(input.iter())
+            .map(|(tokens, text)| self.rules.restore(text.as_ref(), &tokens))
+            .collect()
+    }
+
+    /// Returns the punctuation predicted after each word of a sequence of words
+    fn predict_words(&self, words: &[&str]) -> Vec<Option<(String, f64)>> {
+        let mut text = String::new();
+        let mut word_offsets = Vec::with_capacity(words.len());
+        for word in words {
+            if !text.is_empty() {
+                text.push(' ');
+            }
+            word_offsets.push((text.len(), text.len() + word.len()));
+            text.push_str(word);
+        }
+        let tokens = self
+            .token_classification_model
+            .predict(&[text], true, false)
+            .pop()
+            .unwrap_or_default();
+        self.rules.word_predictions(&word_offsets, &tokens)
+    }
+}
+
+impl<S> Pipeline<S, PunctuationRestorationOutput> for PunctuationRestorationModel
+where
+    S: AsRef<str>,
+{
+    type Options = ();
+
+    fn run_with_options(
+        &self,
+        inputs: &[S],
+        _options: &(),
+    ) -> Result<Vec<PunctuationRestorationOutput>, RustBertError> {
+        Ok(self.restore(inputs))
+    }
+}
+
+/// # Streaming punctuation restoration
+/// Punctuates a transcript received in chunks (e.g. the successive results of a streaming speech recognition
+/// system). Words are finalized, and returned, once `lookahead` words following them have been received.
+pub struct PunctuationStream<'a> {
+    model: &'a PunctuationRestorationModel,
+    lookahead: usize,
+    left_context: usize,
+    context_words: Vec<String>,
+    pending_words: Vec<String>,
+    sentence_start: bool,
+    started: bool,
+}
+
+impl<'a> PunctuationStream<'a> {
+    /// Creates a new punctuation stream
+    ///
+    /// # Arguments
+    ///
+    /// * `model` - `PunctuationRestorationModel` used to predict the punctuation
+    /// * `lookahead` - Number of words that must follow a word before it is punctuated and returned. Larger values
+    /// improve the predictions at the cost of latency.
+    /// * `left_context` - Maximum number of finalized words given as context to the model
+    pub fn new(
+        model: &'a PunctuationRestorationModel,
+        lookahead: usize,
+        left_context: usize,
+    ) -> PunctuationStream<'a> {
+        PunctuationStream {
+            model,
+            lookahead,
+            left_context,
+            context_words: Vec::new(),
+            pending_words: Vec::new(),
+            sentence_start: true,
+            started: false,
+        }
+    }
+
+    /// Returns the words received but not finalized yet
+    pub fn pending_words(&self) -> &[String] {
+        &self.pending_words
+    }
+
+    /// Adds a chunk of transcript to the stream
+    ///
+    /// # Arguments
+    ///
+    /// * `chunk` - Words of the transcript following the previous chunks
+    ///
+    /// # Returns
+    ///
+    /// * `String` - Newly finalized punctuated text (possibly empty), starting with a space if text was already
+    /// returned, to be appended to the previous outputs
+    pub fn push(&mut self, chunk: &str) -> String {
+        self.pending_words
+            .extend(chunk.split_whitespace().map(|word| word.to_string()));
+        if self.pending_words.len() > self.lookahead {
+            self.finalize(self.pending_words.len() - self.lookahead)
+        } else {
+            String::new()
+        }
+    }
+
+    /// Finalizes all pending words, e.g. at the end of an utterance
+    ///
+    /// # Returns
+    ///
+    /// * `String` - Punctuated text of the pending words, to be appended to the previous outputs
+    pub fn flush(&mut self) -> String {
+        self.finalize(self.pending_words.len())
+    }
+
+    /// Clears the stream, starting a new transcript
+    pub fn reset(&mut self) {
+        self.context_words.clear();
+        self.pending_words.clear();
+        self.sentence_start = true;
+        self.started = false;
+    }
+
+    fn finalize(&mut self, num_words: usize) -> String {
+        if num_words == 0 {
+            return String::new();
+        }
+        let words = self
+            .context_words
+            .iter()
+            .chain(self.pending_words.iter())
+            .map(|word| word.as_str())
+            .collect::<Vec<&str>>();
+        let predictions = self.model.predict_words(&words);
+        let context_length = self.context_words.len();
+
+        let mut output = String::new();
+        for (word, prediction) in self.pending_words[..num_words]
+            .iter()
+            .zip(predictions[context_length..].iter())
+        {
+            if self.started {
+                output.push(' ');
+            }
+            let (punctuated, _) = self.model.rules.punctuate_word(
+                word,
+                prediction.as_ref(),
+                &mut self.sentence_start,
+            );
+            output.push_str(&punctuated);
+            self.started = true;
+        }
+
+        self.context_words
+            .extend(self.pending_words.drain(..num_words));
+        let excess = self.context_words.len().saturating_sub(self.left_context);
+        self.context_words.drain(..excess);
+        output
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use rust_tokenizers::{Mask, Offset};
+
+    fn rules() -> PunctuationRules {
+        PunctuationRules {
+            punctuation: vec![".".to_string(), ",".to_string(), "?".to_string()],
+            label_mapping: [("PERIOD".to_string(), ".".to_string())]
+                .iter()
+                .cloned()
+                .collect(),
+            capitalize_sentences: true,
+            min_score: 0.5,
+        }
+    }
+
+    fn token(begin: usize, end: usize, label: &str, score: f64) -> Token {
+        Token {
+            text: String::new(),
+            score,
+            label: label.to_string(),
+            label_index: 0,
+            sentence: 0,
+            index: 0,
+            word_index: 0,
+            offset: Some(Offset::new(begin as u32, end as u32)),
+            byte_offset: Some(Offset::new(begin as u32, end as u32)),
+            utf16_offset: None,
+            mask: Mask::None,
+        }
+    }
+
+    #[test]
+    fn word_predictions_use_last_token() {
+        // "dont stop" tokenized as "don", "t", "stop"
+        let tokens = vec![
+            token(0, 3, ",", 0.9),
+            token(3, 4, "0", 0.9),
+            token(5, 9, "PERIOD", 0.9),
+        ];
+        let predictions = rules().word_predictions(&[(0, 4), (5, 9)], &tokens);
+        assert_eq!(predictions, vec![None, Some((".".to_string(), 0.9))]);
+    }
+
+    #[test]
+    fn punctuation_inserted() {
+        let text = "hello how are you  i am fine";
+        let tokens = vec![
+            token(0, 5, ",", 0.9),
+            token(6, 9, "0", 0.9),
+            token(10, 13, "0", 0.9),
+            token(14, 17, "?", 0.8),
+            token(19, 20, "0", 0.9),
+            token(21, 23, "!", 0.9),
+            token(24, 28, ".", 0.4),
+        ];
+        let output = rules().restore(text, &tokens);
+        assert_eq!(output.text, "Hello, how are you?  I am fine");
+        assert_eq!(
+            output.insertions,
+            vec![
+                InsertedPunctuation {
+                    punctuation: ",".to_string(),
+                    position: 5,
+                    score: 0.9,
+                },
+                InsertedPunctuation {
+                    punctuation: "?".to_string(),
+                    position: 17,
+                    score: 0.8,
+                },
+            ]
+        );
+    }
+}
@@ -38,6 +38,10 @@
 //!     score: 0.9976,
 //!     start: 13,
 //!     end: 21,
+//!     byte_start: 13,
+//!     byte_end: 21,
+//!     utf16_start: 13,
+//!     utf16_end: 21,
 //!     answer: String::from("Amsterdam"),
 //! }]
 //! # ;
@@ -46,12 +50,13 @@
 use crate::albert::AlbertForQuestionAnswering;
 use crate::bert::BertForQuestionAnswering;
 use crate::common::error::RustBertError;
+use crate::common::offsets::OffsetConverter;
 use crate::deberta::DebertaForQuestionAnswering;
 use crate::distilbert::DistilBertForQuestionAnswering;
 use crate::fnet::FNetForQuestionAnswering;
 use crate::longformer::LongformerForQuestionAnswering;
 use crate::mobilebert::MobileBertForQuestionAnswering;
-use crate::pipelines::common::{ConfigOption, ModelType, TokenizerOption};
+use crate::pipelines::common::{ConfigOption, ModelType, Pipeline, TokenizerOption};
 use crate::reformer::ReformerForQuestionAnswering;
 use crate::resources::ResourceProvider;
 use crate::roberta::RobertaForQuestionAnswering;
@@ -101,6 +106,14 @@ pub struct Answer {
     pub start: usize,
     /// End position of answer span
     pub end: usize,
+    /// Start position of answer span in bytes of the context
+    pub byte_start: usize,
+    /// End position of answer span in bytes of the context
+    pub byte_end: usize,
+    /// Start position of answer span in UTF-16 code units of the context
+    pub utf16_start: usize,
+    /// End position of answer span in UTF-16 code units of the context
+    pub utf16_end: usize,
     /// Answer span
     pub answer: String,
 }
@@ -386,13 +399,13 @@ impl QuestionAnsweringOption {
                 }
             }
             ModelType::XLMRoberta => {
-                if let ConfigOption::Bert(config) = config {
+                if let ConfigOption::Roberta(config) = config {
                     Ok(QuestionAnsweringOption::XLMRoberta(
                         RobertaForQuestionAnswering::new(p, config),
                     ))
                 } else {
                     Err(RustBertError::InvalidConfigurationError(
-                        "You can only supply a BertConfig for Roberta!".to_string(),
+                        "You can only supply a RobertaConfig for XLMRoberta!".to_string(),
                     ))
                 }
             }
@@ -583,7 +596,6 @@ impl QuestionAnsweringModel {
     ) -> Result<QuestionAnsweringModel, RustBertError> {
         let config_path = question_answering_config.config_resource.get_local_path()?;
         let vocab_path = question_answering_config.vocab_resource.get_local_path()?;
-        let weights_path = question_answering_config.model_resource.get_local_path()?;
         let merges_path = if let Some(merges_resource) = &question_answering_config.merges_resource
         {
             Some(merges_resource.get_local_path()?)
@@ -608,11 +620,15 @@ impl QuestionAnsweringModel {
             .expect("The Tokenizer used for Question Answering should contain a SEP id");
         let mut var_store = VarStore::new(device);
         let mut model_config =
-            ConfigOption::from_file(question_answering_config.model_type, config_path);
+            ConfigOption::from_file(question_answering_config.model_type, config_path)?;
 
         if let ConfigOption::DistilBert(ref mut config) = model_config {
             config.sinusoidal_pos_embds = false;
         };
+        model_config.validate(
+            Some(&tokenizer),
+            Some(question_answering_config.max_seq_length as i64),
+        )?;
 
         let qa_model = QuestionAnsweringOption::new(
             question_answering_config.model_type,
@@ -635,7 +651,7 @@ impl QuestionAnsweringModel {
             )));
         }
 
-        var_store.load(weights_path)?;
+        crate::resources::load_weights(&question_answering_config.model_resource, &mut var_store)?;
         Ok(QuestionAnsweringModel {
             tokenizer,
             pad_idx,
@@ -735,6 +751,7 @@ impl QuestionAnsweringModel {
                 for (example_id, max_feature_id) in example_index_to_feature_end_position {
                     let mut answers: Vec<Answer> = vec![];
                     let example = &qa_inputs[example_id];
+                    let offset_converter = OffsetConverter::new(&example.context);
                     for feature_idx in feature_id_start..max_feature_id {
                         let feature = &batch_features[feature_idx as usize];
                         let p_mask = (Tensor::of_slice(&feature.p_mask) - 1)
@@ -763,11 +780,18 @@ impl QuestionAnsweringModel {
                                 .take(end_pos)
                                 .skip(start_pos)
                                 .collect::<String>();
+                            let char_offset = Offset::new(start_pos as u32, end_pos as u32);
+                            let byte_offset = offset_converter.to_byte_offset(char_offset);
+                            let utf16_offset = offset_converter.to_utf16_offset(char_offset);
 
                             answers.push(Answer {
                                 score: scores[idx],
                                 start: start_pos,
                                 end: end_pos,
+                                byte_start: byte_offset.begin as usize,
+                                byte_end: byte_offset.end as usize,
+                                utf16_start: utf16_offset.begin as usize,
+                                utf16_end: utf16_offset.end as usize,
                                 answer,
                             });
                         }
@@ -996,6 +1020,36 @@ pub fn squad_processor(file_path: PathBuf) -> Vec<QaInput> {
     qa_inputs
 }
 
+/// # Options of the question answering pipeline when run as a `Pipeline`
+#[derive(Debug, Clone, Copy)]
+pub struct QuestionAnsweringOptions {
+    /// Number of answers returned for each input (default: 1)
+    pub top_k: i64,
+    /// Batch size for the model forward pass (default: 32)
+    pub batch_size: usize,
+}
+
+impl Default for QuestionAnsweringOptions {
+    fn default() -> Self {
+        QuestionAnsweringOptions {
+            top_k: 1,
+            batch_size: 32,
+        }
+    }
+}
+
+impl Pipeline<QaInput, Vec<Answer>> for QuestionAnsweringModel {
+    type Options = QuestionAnsweringOptions;
+
+    fn run_with_options(
+        &self,
+        inputs: &[QaInput],
+        options: &QuestionAnsweringOptions,
+    ) -> Result<Vec<Vec<Answer>>, RustBertError> {
+        Ok(self.predict(inputs, options.top_k, options.batch_size))
+    }
+}
+
 #[cfg(test)]
 mod test {
     use super::*;
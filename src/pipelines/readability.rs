@@ -0,0 +1,494 @@
+// Copyright 2022 Guillaume Becquin
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//     http://www.apache.org/licenses/LICENSE-2.0
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! # Readability and text statistics
+//! Computes statistics (characters, words, sentences, syllables, type-token ratio) and classic readability
+//! formulas (Flesch reading ease, Flesch-Kincaid grade, Gunning fog, Coleman-Liau, automated readability index and
+//! SMOG) for texts, for instance to build content scoring systems. Syllables are counted with an English heuristic
+//! (groups of vowels, ignoring silent final "e"), the formulas being designed for English texts.
+//!
+//! With a (small) causal language model, the perplexity of the texts is also reported: a model-based measure of
+//! how predictable, and generally how easy to read, a text is. Texts are split in windows of words scored
+//! independently, the first word of each window being used as prompt. The number of tokens of the texts is then
+//! also reported, for the language model tokenizer.
+//!
+//! ```no_run
+//! # fn main() -> anyhow::Result<()> {
+//! use rust_bert::pipelines::readability::ReadabilityModel;
+//!
+//! let model = ReadabilityModel::new(Default::default())?;
+//!
+//! let input = ["The cat sat on the mat. It was happy."];
+//! let reports = model.analyze(&input)?;
+//! let perplexity = reports[0].perplexity;
+//! # Ok(())
+//! # }
+//! ```
+//!
+//! The statistics and formulas are also available without language model:
+//! ```
+//! use rust_bert::pipelines::readability::{ReadabilityScores, TextStatistics};
+//!
+//! let statistics = TextStatistics::from_text("The cat sat on the mat.");
+//! let scores = ReadabilityScores::from_statistics(&statistics);
+//! assert_eq!(statistics.words, 6);
+//! assert!(scores.flesch_reading_ease > 100.0);
+//! ```
+
+use crate::common::error::RustBertError;
+use crate::pipelines::common::Pipeline;
+use crate::pipelines::text_generation::{TextGenerationConfig, TextGenerationModel};
+use serde::{Deserialize, Serialize};
+use std::collections::HashSet;
+
+#[cfg(feature = "remote")]
+use crate::{
+    gpt2::{Gpt2ConfigResources, Gpt2MergesResources, Gpt2ModelResources, Gpt2VocabResources},
+    pipelines::common::ModelType,
+    resources::RemoteResource,
+};
+
+/// Counts the syllables of an English word, from its groups of vowels (ignoring a silent final "e")
+///
+/// # Example
+///
+/// ```
+/// use rust_bert::pipelines::readability::count_syllables;
+///
+/// assert_eq!(count_syllables("cat"), 1);
+/// assert_eq!(count_syllables("table"), 2);
+/// assert_eq!(count_syllables("readability"), 5);
+/// ```
+pub fn count_syllables(word: &str) -> usize {
+    let letters = word
+        .chars()
+        .filter(|c| c.is_alphabetic())
+        .flat_map(char::to_lowercase)
+        .collect::<Vec<char>>();
+    if letters.is_empty() {
+        return 0;
+    }
+    let is_vowel = |c: char| matches!(c, 'a' | 'e' | 'i' | 'o' | 'u' | 'y');
+    let mut syllables = 0;
+    let mut previous_vowel = false;
+    for &letter in &letters {
+        let vowel = is_vowel(letter);
+        if vowel && !previous_vowel {
+            syllables += 1;
+        }
+        previous_vowel = vowel;
+    }
+    let length = letters.len();
+    if syllables > 1
+        && letters[length - 1] == 'e'
+        && !is_vowel(letters[length - 2])
+        && !(letters[length - 2] == 'l' && length > 2 && !is_vowel(letters[length - 3]))
+    {
+        syllables -= 1;
+    }
+    syllables.max(1)
+}
+
+/// # Surface statistics of a text
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct TextStatistics {
+    /// Number of characters (including whitespace and punctuation)
+    pub characters: usize,
+    /// Number of letters and digits in words
+    pub letters: usize,
+    /// Number of words
+    pub words: usize,
+    /// Number of distinct (lower-cased) words
+    pub unique_words: usize,
+    /// Number of sentences
+    pub sentences: usize,
+    /// Number of syllables
+    pub syllables: usize,
+    /// Number of words with three syllables or more
+    pub polysyllabic_words: usize,
+}
+
+impl TextStatistics {
+    /// Computes the statistics of a text. Words are separated by whitespace (surrounding punctuation is ignored)
+    /// and sentences are ended by `.`, `!` or `?`.
+    ///
+    /// # Arguments
+    ///
+    /// * `text` - Text to compute the statistics of
+    pub fn from_text(text: &str) -> TextStatistics {
+        let mut letters = 0;
+        let mut words = 0;
+        let mut syllables = 0;
+        let mut polysyllabic_words = 0;
+        let mut unique_words = HashSet::new();
+        for word in text.split_whitespace() {
+            let word = word.trim_matches(|c: char| !c.is_alphanumeric());
+            if word.is_empty() {
+                continue;
+            }
+            words += 1;
+            letters += word.chars().filter(|c| c.is_alphanumeric()).count();
+            let word_syllables = count_syllables(word);
+            syllables += word_syllables;
+            if word_syllables >= 3 {
+                polysyllabic_words += 1;
+            }
+            unique_words.insert(word.to_lowercase());
+        }
+        let sentences = text
+            .split(|c| matches!(c, '.' | '!' | '?'))
+            .filter(|sentence| sentence.chars().any(char::is_alphanumeric))
+            .count();
+        TextStatistics {
+            characters: text.chars().count(),
+            letters,
+            words,
+            unique_words: unique_words.len(),
+            sentences,
+            syllables,
+            polysyllabic_words,
+        }
+    }
+
+    /// Returns the ratio of distinct words to words (0 for texts without words)
+    pub fn type_token_ratio(&self) -> f64 {
+        if self.words == 0 {
+            0.0
+        } else {
+            self.unique_words as f64 / self.words as f64
+        }
+    }
+
+    /// Returns the average number of letters per word
+    pub fn average_word_length(&self) -> f64 {
+        self.letters as f64 / self.words.max(1) as f64
+    }
+
+    /// Returns the average number of words per sentence
+    pub fn average_sentence_length(&self) -> f64 {
+        self.words as f64 / self.sentences.max(1) as f64
+    }
+
+    /// Returns the average number of syllables per word
+    pub fn average_syllables_per_word(&self) -> f64 {
+        self.syllables as f64 / self.words.max(1) as f64
+    }
+}
+
+/// # Classic readability formulas
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ReadabilityScores {
+    /// Flesch reading ease (higher is easier, 60-70 for plain English)
+    pub flesch_reading_ease: f64,
+    /// Flesch-Kincaid grade level (US school grade)
+    pub flesch_kincaid_grade: f64,
+    /// Gunning fog index (years of formal education)
+    pub gunning_fog: f64,
+    /// Coleman-Liau index (US school grade)
+    pub coleman_liau_index: f64,
+    /// Automated readability index (US school grade)
+    pub automated_readability_index: f64,
+    /// SMOG index (years of education)
+    pub smog_index: f64,
+}
+
+impl ReadabilityScores {
+    /// Computes the readability formulas from the statistics of a text
+    ///
+    /// # Arguments
+    ///
+    /// * `statistics` - `TextStatistics` of the text
+    pub fn from_statistics(statistics: &TextStatistics) -> ReadabilityScores {
+        let words = statistics.words.max(1) as f64;
+        let sentences = statistics.sentences.max(1) as f64;
+        let words_per_sentence = words / sentences;
+        let syllables_per_word = statistics.syllables as f64 / words;
+        let letters_per_word = statistics.letters as f64 / words;
+        ReadabilityScores {
+            flesch_reading_ease: 206.835 - 1.015 * words_per_sentence - 84.6 * syllables_per_word,
+            flesch_kincaid_grade: 0.39 * words_per_sentence + 11.8 * syllables_per_word - 15.59,
+            gunning_fog: 0.4
+                * (words_per_sentence + 100.0 * statistics.polysyllabic_words as f64 / words),
+            coleman_liau_index: 0.0588 * 100.0 * letters_per_word
+                - 0.296 * 100.0 / words_per_sentence
+                - 15.8,
+            automated_readability_index: 4.71 * letters_per_word + 0.5 * words_per_sentence - 21.43,
+            smog_index: 1.043 * (statistics.polysyllabic_words as f64 * 30.0 / sentences).sqrt()
+                + 3.1291,
+        }
+    }
+}
+
+/// # Readability report of a text
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ReadabilityReport {
+    /// Surface statistics
+    pub statistics: TextStatistics,
+    /// Readability formulas
+    pub scores: ReadabilityScores,
+    /// Number of tokens of the language model tokenizer (None without language model)
+    pub num_tokens: Option<usize>,
+    /// Perplexity under the language model (None without language model, or for texts with less than two words)
+    pub perplexity: Option<f64>,
+}
+
+/// # Configuration for ReadabilityModel
+pub struct ReadabilityConfig {
+    /// Configuration of the causal language model computing the perplexity (None to only compute the statistics)
+    pub language_model_config: Option<TextGenerationConfig>,
+    /// Maximum number of words of the windows scored by the language model (default: 256)
+    pub window_words: usize,
+    /// Number of windows scored at once (default: 8)
+    pub batch_size: usize,
+}
+
+impl ReadabilityConfig {
+    /// Instantiate a new readability configuration
+    ///
+    /// # Arguments
+    ///
+    /// * `language_model_config` - Optional `TextGenerationConfig` of the causal language model computing the perplexity
+    pub fn new(language_model_config: Option<TextGenerationConfig>) -> ReadabilityConfig {
+        ReadabilityConfig {
+            language_model_config,
+            window_words: 256,
+            batch_size: 8,
+        }
+    }
+}
+
+#[cfg(feature = "remote")]
+impl Default for ReadabilityConfig {
+    /// Provides a DistilGPT2 language model
+    fn default() -> ReadabilityConfig {
+        ReadabilityConfig::new(Some(TextGenerationConfig::new(
+            ModelType::GPT2,
+            RemoteResource::from_pretrained(Gpt2ModelResources::DISTIL_GPT2),
+            RemoteResource::from_pretrained(Gpt2ConfigResources::DISTIL_GPT2),
+            RemoteResource::from_pretrained(Gpt2VocabResources::DISTIL_GPT2),
+            RemoteResource::from_pretrained(Gpt2MergesResources::DISTIL_GPT2),
+        )))
+    }
+}
+
+/// Splits the words of a text in windows of at most `window_words` words, returned as (prompt, continuation)
+/// pairs: the first word of the window and the following words (with a leading space).
+fn scoring_windows(text: &str, window_words: usize) -> Vec<(String, String)> {
+    let words = text.split_whitespace().collect::<Vec<&str>>();
+    words
+        .chunks(window_words.max(2))
+        .filter(|window| window.len() > 1)
+        .map(|window| (window[0].to_string(), format!(" {}", window[1..].join(" "))))
+        .collect()
+}
+
+/// # ReadabilityModel computing text statistics, readability formulas and language model perplexity
+pub struct ReadabilityModel {
+    language_model: Option<TextGenerationModel>,
+    window_words: usize,
+    batch_size: usize,
+}
+
+impl ReadabilityModel {
+    /// Build a new `ReadabilityModel`
+    ///
+    /// # Arguments
+    ///
+    /// * `config` - `ReadabilityConfig` object containing the optional language model configuration
+    ///
+    /// # Example
+    ///
+    /// ```no_run
+    /// # fn main() -> anyhow::Result<()> {
+    /// use rust_bert::pipelines::readability::ReadabilityModel;
+    ///
+    /// let model = ReadabilityModel::new(Default::default())?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn new(config: ReadabilityConfig) -> Result<ReadabilityModel, RustBertError> {
+        let language_model = match config.language_model_config {
+            Some(language_model_config) => Some(TextGenerationModel::new(language_model_config)?),
+            None => None,
+        };
+        Ok(ReadabilityModel {
+            language_model,
+            window_words: config.window_words,
+            batch_size: config.batch_size.max(1),
+        })
+    }
+
+    /// Analyzes the readability of texts
+    ///
+    /// # Arguments
+    ///
+    /// * `input` - `&[&str]` Array of texts to analyze
+    ///
+    /// # Returns
+    ///
+    /// * `Vec<ReadabilityReport>` containing the statistics, readability formulas and perplexity of each text
+    pub fn analyze<S>(&self, input: &[S]) -> Result<Vec<ReadabilityReport>, RustBertError>
+    where
+        S: AsRef<str> + Sync,
+    {
+        let (num_tokens, perplexities) = match &self.language_model {
+            Some(language_model) => {
+                let num_tokens = language_model
+                    .get_tokenizer()
+                    .tokenize_list(input)
+                    .into_iter()
+                    .map(|tokens| Some(tokens.len()))
+                    .collect::<Vec<Option<usize>>>();
+                (num_tokens, self.perplexities(language_model, input)?)
+            }
+            None => (vec![None; input.len()], vec![None; input.len()]),
+        };
+        Ok(input
+            .iter()
+            .zip(num_tokens.into_iter())
+            .zip(perplexities.into_iter())
+            .map(|((text, num_tokens), perplexity)| {
+                let statistics = TextStatistics::from_text(text.as_ref());
+                let scores = ReadabilityScores::from_statistics(&statistics);
+                ReadabilityReport {
+                    statistics,
+                    scores,
+                    num_tokens,
+                    perplexity,
+                }
+            })
+            .collect())
+    }
+
+    fn perplexities<S>(
+        &self,
+        language_model: &TextGenerationModel,
+        input: &[S],
+    ) -> Result<Vec<Option<f64>>, RustBertError>
+    where
+        S: AsRef<str>,
+    {
+        let windows = input
+            .iter()
+            .enumerate()
+            .flat_map(|(text_index, text)| {
+                scoring_windows(text.as_ref(), self.window_words)
+                    .into_iter()
+                    .map(move |window| (text_index, window))
+            })
+            .collect::<Vec<(usize, (String, String))>>();
+
+        let mut log_likelihoods = vec![0f64; input.len()];
+        let mut token_counts = vec![0usize; input.len()];
+        for batch in windows.chunks(self.batch_size) {
+            let prompts = batch
+                .iter()
+                .map(|(_, (prompt, _))| prompt.as_str())
+                .collect::<Vec<&str>>();
+            let continuations = batch
+                .iter()
+                .map(|(_, (_, continuation))| continuation.as_str())
+                .collect::<Vec<&str>>();
+            let scores = language_model.score_sequences(&prompts, &continuations)?;
+            for ((text_index, _), token_scores) in batch.iter().zip(scores.iter()) {
+                log_likelihoods[*text_index] += token_scores.iter().sum::<f64>();
+                token_counts[*text_index] += token_scores.len();
+            }
+        }
+        Ok(log_likelihoods
+            .into_iter()
+            .zip(token_counts.into_iter())
+            .map(|(log_likelihood, token_count)| {
+                if token_count > 0 {
+                    Some((-log_likelihood / token_count as f64).exp())
+                } else {
+                    None
+                }
+            })
+            .collect())
+    }
+}
+
+impl<S> Pipeline<S, ReadabilityReport> for ReadabilityModel
+where
+    S: AsRef<str> + Sync,
+{
+    type Options = ();
+
+    fn run_with_options(
+        &self,
+        inputs: &[S],
+        _options: &(),
+    ) -> Result<Vec<ReadabilityReport>, RustBertError> {
+        self.analyze(inputs)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    #[ignore] // no need to run, compilation is enough to verify it is Send
+    fn test() {
+        let config = ReadabilityConfig::default();
+        let _: Box<dyn Send> = Box::new(ReadabilityModel::new(config));
+    }
+
+    #[test]
+    fn syllables_counted() {
+        assert_eq!(count_syllables("the"), 1);
+        assert_eq!(count_syllables("make"), 1);
+        assert_eq!(count_syllables("little"), 2);
+        assert_eq!(count_syllables("beautiful"), 3);
+        assert_eq!(count_syllables("Education,"), 4);
+        assert_eq!(count_syllables("42"), 0);
+    }
+
+    #[test]
+    fn statistics_and_scores() {
+        let statistics = TextStatistics::from_text("The cat sat on the mat. The cat was happy!");
+        assert_eq!(
+            statistics,
+            TextStatistics {
+                characters: 42,
+                letters: 31,
+                words: 10,
+                unique_words: 7,
+                sentences: 2,
+                syllables: 11,
+                polysyllabic_words: 0,
+            }
+        );
+        assert!((statistics.type_token_ratio() - 0.7).abs() < 1e-9);
+
+        let scores = ReadabilityScores::from_statistics(&statistics);
+        assert!((scores.flesch_reading_ease - 108.7).abs() < 1e-6);
+        assert!((scores.flesch_kincaid_grade - (-0.66)).abs() < 1e-6);
+        assert!((scores.gunning_fog - 2.0).abs() < 1e-6);
+    }
+
+    #[test]
+    fn windows_split_words() {
+        assert_eq!(
+            scoring_windows("a b c d e", 2),
+            vec![
+                ("a".to_string(), " b".to_string()),
+                ("c".to_string(), " d".to_string()),
+            ]
+        );
+        assert_eq!(
+            scoring_windows("the cat  sat", 256),
+            vec![("the".to_string(), " cat sat".to_string())]
+        );
+        assert!(scoring_windows("alone", 256).is_empty());
+    }
+}
@@ -0,0 +1,244 @@
+// Copyright 2022 Guillaume Becquin
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//     http://www.apache.org/licenses/LICENSE-2.0
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! # Model registry for custom architectures
+//!
+//! Allows external crates to add their own architectures to the pipelines without modifying this crate.
+//! A custom architecture is registered under a unique name with constructors for its configuration,
+//! tokenizer and task-specific models. The `ModelType::Custom` returned by the registration can then be used
+//! in the pipeline configurations in place of the built-in model types.
+//!
+//! Custom architectures are currently supported by the sequence classification (and sentiment analysis)
+//! and token classification (and named entity recognition) pipelines. The configuration and tokenizer
+//! constructors return one of the existing `ConfigOption` and `TokenizerOption` variants.
+//!
+//! ```no_run
+//! # fn main() -> anyhow::Result<()> {
+//! use rust_bert::bert::{BertConfig, BertForSequenceClassification};
+//! use rust_bert::pipelines::common::{ConfigOption, TokenizerOption};
+//! use rust_bert::pipelines::registry::{register_model, CustomModel, ModelRegistration};
+//! use rust_bert::{Config, RustBertError};
+//! use rust_tokenizers::tokenizer::BertTokenizer;
+//! use std::convert::TryFrom;
+//! use std::path::Path;
+//! use tch::{nn, Tensor};
+//!
+//! struct MyClassifier(BertForSequenceClassification);
+//!
+//! impl CustomModel for MyClassifier {
+//!     fn forward_t(
+//!         &self,
+//!         input_ids: Option<&Tensor>,
+//!         mask: Option<&Tensor>,
+//!         token_type_ids: Option<&Tensor>,
+//!         position_ids: Option<&Tensor>,
+//!         input_embeds: Option<&Tensor>,
+//!         train: bool,
+//!     ) -> Result<Tensor, RustBertError> {
+//!         Ok(self
+//!             .0
+//!             .forward_t(
+//!                 input_ids,
+//!                 mask,
+//!                 token_type_ids,
+//!                 position_ids,
+//!                 input_embeds,
+//!                 train,
+//!             )
+//!             .logits)
+//!     }
+//! }
+//!
+//! let model_type = register_model(
+//!     "my_classifier",
+//!     ModelRegistration {
+//!         config_builder: Box::new(|path: &Path| ConfigOption::Bert(BertConfig::from_file(path))),
+//!         tokenizer_builder: Box::new(
+//!             |vocab_path: &str,
+//!              _merges_path: Option<&str>,
+//!              lower_case: bool,
+//!              strip_accents: Option<bool>,
+//!              _add_prefix_space: Option<bool>|
+//!              -> Result<TokenizerOption, RustBertError> {
+//!                 Ok(TokenizerOption::Bert(BertTokenizer::from_file(
+//!                     vocab_path,
+//!                     lower_case,
+//!                     strip_accents.unwrap_or(lower_case),
+//!                 )?))
+//!             },
+//!         ),
+//!         sequence_classification_builder: Some(Box::new(
+//!             |p: &nn::Path, config: &ConfigOption| -> Result<Box<dyn CustomModel>, RustBertError> {
+//!                 let config = BertConfig::try_from(config)?;
+//!                 Ok(Box::new(MyClassifier(BertForSequenceClassification::new(
+//!                     p, &config,
+//!                 ))))
+//!             },
+//!         )),
+//!         token_classification_builder: None,
+//!     },
+//! )?;
+//! // `model_type` can now be used to create a `SequenceClassificationConfig`
+//! # Ok(())
+//! # }
+//! ```
+
+use crate::pipelines::common::{ConfigOption, ModelType, TokenizerOption};
+use crate::RustBertError;
+use lazy_static::lazy_static;
+use std::collections::HashMap;
+use std::path::Path;
+use std::sync::{Arc, RwLock};
+use tch::{nn, Tensor};
+
+/// # Common interface for the models of custom architectures
+/// The forward pass returns the logits expected by the pipeline the model is registered for:
+/// (*batch size*, *num_labels*) for sequence classification and
+/// (*batch size*, *sequence_length*, *num_labels*) for token classification.
+/// Models must be `Sync` so that the pipelines can be shared between threads (e.g. by a `HotSwapPipeline`).
+pub trait CustomModel: Send + Sync {
+    fn forward_t(
+        &self,
+        input_ids: Option<&Tensor>,
+        mask: Option<&Tensor>,
+        token_type_ids: Option<&Tensor>,
+        position_ids: Option<&Tensor>,
+        input_embeds: Option<&Tensor>,
+        train: bool,
+    ) -> Result<Tensor, RustBertError>;
+}
+
+/// Constructor for the configuration of a custom architecture, from the path to the configuration file
+pub type ConfigBuilder = Box<dyn Fn(&Path) -> ConfigOption + Send + Sync>;
+
+/// Constructor for the tokenizer of a custom architecture, taking the vocabulary path, optional merges path,
+/// lower case, strip accents and add prefix space settings
+pub type TokenizerBuilder = Box<
+    dyn Fn(
+            &str,
+            Option<&str>,
+            bool,
+            Option<bool>,
+            Option<bool>,
+        ) -> Result<TokenizerOption, RustBertError>
+        + Send
+        + Sync,
+>;
+
+/// Constructor for a task-specific model of a custom architecture, from the variable store path and configuration
+pub type ModelBuilder = Box<
+    dyn Fn(&nn::Path, &ConfigOption) -> Result<Box<dyn CustomModel>, RustBertError> + Send + Sync,
+>;
+
+/// # Registration of a custom architecture
+pub struct ModelRegistration {
+    /// Configuration constructor
+    pub config_builder: ConfigBuilder,
+    /// Tokenizer constructor
+    pub tokenizer_builder: TokenizerBuilder,
+    /// Optional model constructor for the sequence classification pipeline
+    pub sequence_classification_builder: Option<ModelBuilder>,
+    /// Optional model constructor for the token classification pipeline
+    pub token_classification_builder: Option<ModelBuilder>,
+}
+
+lazy_static! {
+    static ref MODEL_REGISTRY: RwLock<HashMap<&'static str, Arc<ModelRegistration>>> =
+        RwLock::new(HashMap::new());
+}
+
+/// Registers a custom architecture under the name provided.
+///
+/// # Arguments
+///
+/// * `name` - Unique name of the custom architecture
+/// * `registration` - `ModelRegistration` with the constructors for the custom architecture
+///
+/// # Returns
+///
+/// * `ModelType::Custom` to use in the pipeline configurations. Fails if an architecture was already registered under this name.
+pub fn register_model(
+    name: &'static str,
+    registration: ModelRegistration,
+) -> Result<ModelType, RustBertError> {
+    let mut registry = MODEL_REGISTRY.write().unwrap();
+    if registry.contains_key(name) {
+        return Err(RustBertError::InvalidConfigurationError(format!(
+            "A model type is already registered under the name {}",
+            name
+        )));
+    }
+    registry.insert(name, Arc::new(registration));
+    Ok(ModelType::Custom(name))
+}
+
+/// Returns the registration of a custom architecture
+pub(crate) fn get_model_registration(
+    model_type: ModelType,
+) -> Result<Arc<ModelRegistration>, RustBertError> {
+    let name = match model_type {
+        ModelType::Custom(name) => name,
+        _ => {
+            return Err(RustBertError::InvalidConfigurationError(format!(
+                "{:?} is not a custom model type",
+                model_type
+            )));
+        }
+    };
+    MODEL_REGISTRY
+        .read()
+        .unwrap()
+        .get(name)
+        .cloned()
+        .ok_or_else(|| {
+            RustBertError::InvalidConfigurationError(format!(
+                "No model type registered under the name {}",
+                name
+            ))
+        })
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::bert::BertConfig;
+    use crate::Config;
+
+    fn dummy_registration() -> ModelRegistration {
+        ModelRegistration {
+            config_builder: Box::new(|path: &Path| ConfigOption::Bert(BertConfig::from_file(path))),
+            tokenizer_builder: Box::new(
+                |_: &str,
+                 _: Option<&str>,
+                 _: bool,
+                 _: Option<bool>,
+                 _: Option<bool>|
+                 -> Result<TokenizerOption, RustBertError> {
+                    Err(RustBertError::InvalidConfigurationError(
+                        "Tokenizer not available".to_string(),
+                    ))
+                },
+            ),
+            sequence_classification_builder: None,
+            token_classification_builder: None,
+        }
+    }
+
+    #[test]
+    fn test_register_model() {
+        let model_type = register_model("test_registry_model", dummy_registration()).unwrap();
+        assert_eq!(model_type, ModelType::Custom("test_registry_model"));
+        assert!(get_model_registration(model_type).is_ok());
+        assert!(register_model("test_registry_model", dummy_registration()).is_err());
+        assert!(get_model_registration(ModelType::Custom("test_unregistered_model")).is_err());
+        assert!(get_model_registration(ModelType::Bert).is_err());
+    }
+}
@@ -0,0 +1,333 @@
+// Copyright 2022 Guillaume Becquin
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//     http://www.apache.org/licenses/LICENSE-2.0
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! # Reward model pipeline
+//! Scores (prompt, response) pairs with a reward model, i.e. a sequence classification model with a single scalar
+//! output trained on human preference comparisons. The prompt and the response are encoded together as a pair of texts
+//! and the raw model output is returned as the reward: a higher reward indicates a response preferred by the model.
+//! Rewards are not calibrated and are only comparable between responses scored by the same model.
+//!
+//! Typical uses are the reranking of candidate generations (best-of-n sampling) and the filtering of low-quality
+//! responses. The OpenAssistant DeBERTa-v3 reward models (English) are available as presets: their configuration and
+//! vocabulary are downloaded from the original repositories, and the weights are converted locally from the PyTorch
+//! checkpoint (`python utils/convert_model.py path/to/pytorch_model.bin`), no converted checkpoint being hosted with the crate.
+//!
+//! ```no_run
+//! # fn main() -> anyhow::Result<()> {
+//! use rust_bert::pipelines::reward_model::{RewardModel, RewardModelConfig, RewardModelType};
+//! use rust_bert::resources::LocalResource;
+//! use std::path::PathBuf;
+//!
+//! let reward_model = RewardModel::new(RewardModelConfig::from_model_type(
+//!     RewardModelType::DebertaV3Base,
+//!     LocalResource::from(PathBuf::from("path/to/rust_model.ot")),
+//! ))?;
+//!
+//! let prompt = "Explain nuclear fusion like I am five.";
+//! let ranked_responses = reward_model.rank(
+//!     prompt,
+//!     &[
+//!         "Nuclear fusion is when two small atoms squeeze together into a bigger one and release a lot of energy, like the sun does.",
+//!         "I don't know.",
+//!     ],
+//! )?;
+//! let best_response = ranked_responses[0].index;
+//! # Ok(())
+//! # }
+//! ```
+//!
+//! Any sequence classification checkpoint with a single regression output can be used:
+//!
+//! ```no_run
+//! # fn main() -> anyhow::Result<()> {
+//! use rust_bert::pipelines::common::ModelType;
+//! use rust_bert::pipelines::reward_model::{RewardModel, RewardModelConfig};
+//! use rust_bert::pipelines::sequence_classification::SequenceClassificationConfig;
+//! use rust_bert::resources::LocalResource;
+//! use std::path::PathBuf;
+//!
+//! let model_config = SequenceClassificationConfig::new(
+//!     ModelType::Roberta,
+//!     LocalResource::from(PathBuf::from("path/to/reward-model/rust_model.ot")),
+//!     LocalResource::from(PathBuf::from("path/to/reward-model/config.json")),
+//!     LocalResource::from(PathBuf::from("path/to/reward-model/vocab.json")),
+//!     Some(LocalResource::from(PathBuf::from(
+//!         "path/to/reward-model/merges.txt",
+//!     ))),
+//!     false,
+//!     None,
+//!     None,
+//! );
+//! let reward_model = RewardModel::new(RewardModelConfig::new(model_config))?;
+//!
+//! let rewards = reward_model.score(&[(
+//!     "What is the capital of France?",
+//!     "The capital of France is Paris.",
+//! )])?;
+//! # Ok(())
+//! # }
+//! ```
+
+use crate::common::error::RustBertError;
+use crate::pipelines::common::Pipeline;
+use crate::pipelines::semantic_similarity::CrossEncoder;
+use crate::pipelines::sequence_classification::SequenceClassificationConfig;
+use std::cmp::Ordering;
+
+#[cfg(feature = "remote")]
+use crate::{
+    deberta_v2::{DebertaV2ConfigResources, DebertaV2VocabResources},
+    pipelines::common::ModelType,
+    resources::{RemoteResource, ResourceProvider},
+};
+#[cfg(feature = "remote")]
+use tch::Device;
+
+/// # Configuration for RewardModel
+pub struct RewardModelConfig {
+    /// Sequence classification model with a single regression output
+    pub model: SequenceClassificationConfig,
+    /// Maximum number of (prompt, response) pairs in a batch
+    pub batch_size: usize,
+}
+
+impl RewardModelConfig {
+    /// Instantiate a new reward model configuration with a batch size of 32
+    ///
+    /// # Arguments
+    ///
+    /// * `model` - `SequenceClassificationConfig` of the reward model. The model must have a single output.
+    pub fn new(model: SequenceClassificationConfig) -> RewardModelConfig {
+        RewardModelConfig {
+            model,
+            batch_size: 32,
+        }
+    }
+}
+
+/// # Pretrained reward models
+/// OpenAssistant DeBERTa-v3 reward models trained on human preference comparisons (English).
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum RewardModelType {
+    /// DeBERTa-v3 base reward model (<https://huggingface.co/OpenAssistant/reward-model-deberta-v3-base>)
+    DebertaV3Base,
+    /// DeBERTa-v3 large reward model (<https://huggingface.co/OpenAssistant/reward-model-deberta-v3-large-v2>)
+    DebertaV3LargeV2,
+}
+
+#[cfg(feature = "remote")]
+impl RewardModelConfig {
+    /// Instantiate the configuration of a pretrained reward model with a batch size of 32. The configuration and
+    /// vocabulary are downloaded from the original repository of the model, the weights are provided as a resource
+    /// converted locally.
+    ///
+    /// # Arguments
+    ///
+    /// * `model_type` - `RewardModelType` pretrained reward model to load
+    /// * `model_resource` - The `ResourceProvider` pointing to the converted weights of the model (e.g. rust_model.ot)
+    pub fn from_model_type<R>(model_type: RewardModelType, model_resource: R) -> RewardModelConfig
+    where
+        R: ResourceProvider + Send + 'static,
+    {
+        let (config_resource, vocab_resource) = match model_type {
+            RewardModelType::DebertaV3Base => (
+                DebertaV2ConfigResources::REWARD_MODEL_DEBERTA_V3_BASE,
+                DebertaV2VocabResources::REWARD_MODEL_DEBERTA_V3_BASE,
+            ),
+            RewardModelType::DebertaV3LargeV2 => (
+                DebertaV2ConfigResources::REWARD_MODEL_DEBERTA_V3_LARGE_V2,
+                DebertaV2VocabResources::REWARD_MODEL_DEBERTA_V3_LARGE_V2,
+            ),
+        };
+        RewardModelConfig::new(SequenceClassificationConfig {
+            model_type: ModelType::DebertaV2,
+            model_resource: Box::new(model_resource),
+            config_resource: Box::new(RemoteResource::from_pretrained(config_resource)),
+            vocab_resource: Box::new(RemoteResource::from_pretrained(vocab_resource)),
+            merges_resource: None,
+            lower_case: false,
+            strip_accents: None,
+            add_prefix_space: None,
+            device: Device::cuda_if_available(),
+            output_hidden_states: false,
+            output_attentions: false,
+        })
+    }
+}
+
+/// # Response ranked by its reward
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct RankedResponse {
+    /// Position of the response in the input
+    pub index: usize,
+    /// Raw reward of the response
+    pub reward: f64,
+}
+
+/// # RewardModel for the scoring of (prompt, response) pairs
+pub struct RewardModel {
+    scorer: CrossEncoder,
+    batch_size: usize,
+}
+
+impl RewardModel {
+    /// Build a new `RewardModel`
+    ///
+    /// # Arguments
+    ///
+    /// * `config` - `RewardModelConfig` object containing the model configuration and the batch size
+    ///
+    /// # Example
+    ///
+    /// ```no_run
+    /// # fn main() -> anyhow::Result<()> {
+    /// use rust_bert::pipelines::reward_model::{RewardModel, RewardModelConfig, RewardModelType};
+    /// use rust_bert::resources::LocalResource;
+    /// use std::path::PathBuf;
+    ///
+    /// let model = RewardModel::new(RewardModelConfig::from_model_type(
+    ///     RewardModelType::DebertaV3Base,
+    ///     LocalResource::from(PathBuf::from("path/to/rust_model.ot")),
+    /// ))?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn new(config: RewardModelConfig) -> Result<RewardModel, RustBertError> {
+        let scorer = CrossEncoder::new(config.model)?;
+        Ok(RewardModel {
+            scorer,
+            batch_size: config.batch_size.max(1),
+        })
+    }
+
+    /// Sets the number of pairs scored at once
+    ///
+    /// # Arguments
+    ///
+    /// * `batch_size` - Maximum number of (prompt, response) pairs in a batch (default: 32)
+    pub fn set_batch_size(&mut self, batch_size: usize) {
+        self.batch_size = batch_size.max(1);
+    }
+
+    /// Scores (prompt, response) pairs
+    ///
+    /// # Arguments
+    ///
+    /// * `pairs` - `&[(prompt, response)]` Array of prompts and responses to score
+    ///
+    /// # Returns
+    ///
+    /// * `Vec<f64>` containing the raw reward of each pair
+    ///
+    /// # Example
+    ///
+    /// ```no_run
+    /// # fn main() -> anyhow::Result<()> {
+    /// use rust_bert::pipelines::reward_model::{RewardModel, RewardModelConfig, RewardModelType};
+    /// use rust_bert::resources::LocalResource;
+    /// use std::path::PathBuf;
+    ///
+    /// let reward_model = RewardModel::new(RewardModelConfig::from_model_type(
+    ///     RewardModelType::DebertaV3Base,
+    ///     LocalResource::from(PathBuf::from("path/to/rust_model.ot")),
+    /// ))?;
+    /// let rewards = reward_model.score(&[
+    ///     ("How do I boil an egg?", "Place the egg in boiling water for 8 to 10 minutes."),
+    ///     ("How do I boil an egg?", "Eggs are laid by hens."),
+    /// ])?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn score<S1, S2>(&self, pairs: &[(S1, S2)]) -> Result<Vec<f64>, RustBertError>
+    where
+        S1: AsRef<str>,
+        S2: AsRef<str>,
+    {
+        let mut rewards = Vec::with_capacity(pairs.len());
+        for batch in pairs.chunks(self.batch_size) {
+            let batch = batch
+                .iter()
+                .map(|(prompt, response)| (prompt.as_ref(), response.as_ref()))
+                .collect::<Vec<(&str, &str)>>();
+            rewards.extend(self.scorer.score(&batch)?);
+        }
+        Ok(rewards)
+    }
+
+    /// Ranks candidate responses to a prompt by decreasing reward
+    ///
+    /// # Arguments
+    ///
+    /// * `prompt` - Prompt the responses answer
+    /// * `responses` - `&[response]` Array of candidate responses
+    ///
+    /// # Returns
+    ///
+    /// * `Vec<RankedResponse>` containing the index and reward of each response, the highest reward first
+    pub fn rank<S>(
+        &self,
+        prompt: &str,
+        responses: &[S],
+    ) -> Result<Vec<RankedResponse>, RustBertError>
+    where
+        S: AsRef<str>,
+    {
+        let pairs = responses
+            .iter()
+            .map(|response| (prompt, response.as_ref()))
+            .collect::<Vec<(&str, &str)>>();
+        Ok(rank_rewards(self.score(&pairs)?))
+    }
+}
+
+fn rank_rewards(rewards: Vec<f64>) -> Vec<RankedResponse> {
+    let mut ranked_responses = rewards
+        .into_iter()
+        .enumerate()
+        .map(|(index, reward)| RankedResponse { index, reward })
+        .collect::<Vec<RankedResponse>>();
+    ranked_responses.sort_by(|a, b| b.reward.partial_cmp(&a.reward).unwrap_or(Ordering::Equal));
+    ranked_responses
+}
+
+impl<S1, S2> Pipeline<(S1, S2), f64> for RewardModel
+where
+    S1: AsRef<str>,
+    S2: AsRef<str>,
+{
+    type Options = ();
+
+    fn run_with_options(
+        &self,
+        inputs: &[(S1, S2)],
+        _options: &(),
+    ) -> Result<Vec<f64>, RustBertError> {
+        self.score(inputs)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_rank_rewards() {
+        let ranked_responses = rank_rewards(vec![-1.5, 2.0, 0.25, 2.0]);
+        let indices = ranked_responses
+            .iter()
+            .map(|response| response.index)
+            .collect::<Vec<usize>>();
+        // The sort is stable: responses with equal rewards keep their input order
+        assert_eq!(indices, vec![1, 3, 2, 0]);
+        assert_eq!(ranked_responses[0].reward, 2.0);
+        assert!(rank_rewards(vec![]).is_empty());
+    }
+}
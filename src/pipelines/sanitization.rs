@@ -0,0 +1,192 @@
+// Copyright 2022 Guillaume Becquin
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//     http://www.apache.org/licenses/LICENSE-2.0
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! # Input sanitization
+//! Guards the tokenization of untrusted inputs. The texts tokenized by the pipelines (through `TokenizerOption`) are
+//! sanitized by the `InputSanitizer` of the process before their tokenization:
+//! - huge inputs are truncated to `max_characters` characters, the pipelines truncating the inputs to the maximum
+//! length of the model afterwards anyway
+//! - runs of the same character longer than `max_repetitions` (e.g. a word made of thousands of identical letters,
+//! whose byte-pair encoding is quadratic in its length) are broken: the extra repetitions are replaced with spaces
+//!
+//! Both preserve the position of the remaining characters, so that the offsets returned by the tokenizers remain
+//! valid for the original text. Inputs received as bytes (e.g. the body of a request) should be converted with
+//! `InputSanitizer::sanitize_bytes`, which replaces invalid UTF-8 sequences with the replacement character.
+//!
+//! The sanitizer of the process is set with `set_input_sanitizer`:
+//!
+//! ```no_run
+//! use rust_bert::pipelines::sanitization::{set_input_sanitizer, InputSanitizer};
+//!
+//! set_input_sanitizer(InputSanitizer {
+//!     max_characters: Some(100_000),
+//!     ..Default::default()
+//! });
+//! ```
+//!
+//! A fuzz target checking that the tokenizers do not panic on arbitrary inputs is available in the `fuzz`
+//! directory (`cargo +nightly fuzz run tokenizers`).
+
+use lazy_static::lazy_static;
+use std::borrow::Cow;
+use std::sync::RwLock;
+
+/// # Sanitization of the texts tokenized by the pipelines
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct InputSanitizer {
+    /// Maximum number of characters of an input, the following characters are dropped. `None` disables the limit.
+    pub max_characters: Option<usize>,
+    /// Maximum number of consecutive repetitions of a non-whitespace character, the following repetitions are
+    /// replaced with spaces. `None` disables the limit.
+    pub max_repetitions: Option<usize>,
+}
+
+impl InputSanitizer {
+    /// Creates a new `InputSanitizer`.
+    ///
+    /// # Arguments
+    ///
+    /// * `max_characters` - Optional maximum number of characters of an input
+    /// * `max_repetitions` - Optional maximum number of consecutive repetitions of a non-whitespace character
+    pub fn new(max_characters: Option<usize>, max_repetitions: Option<usize>) -> InputSanitizer {
+        InputSanitizer {
+            max_characters,
+            max_repetitions,
+        }
+    }
+
+    /// Returns an `InputSanitizer` leaving the inputs unchanged
+    pub fn disabled() -> InputSanitizer {
+        InputSanitizer::new(None, None)
+    }
+
+    /// Sanitizes a text, borrowing it if it is left unchanged.
+    ///
+    /// # Arguments
+    ///
+    /// * `text` - Input text
+    pub fn sanitize<'a>(&self, text: &'a str) -> Cow<'a, str> {
+        let text = match self.max_characters {
+            Some(max_characters) => match text.char_indices().nth(max_characters) {
+                Some((end, _)) => &text[..end],
+                None => text,
+            },
+            None => text,
+        };
+        let max_repetitions = match self.max_repetitions {
+            Some(max_repetitions) if has_long_repetition(text, max_repetitions) => max_repetitions,
+            _ => return Cow::Borrowed(text),
+        };
+
+        let mut sanitized = String::with_capacity(text.len());
+        let mut previous = None;
+        let mut repetitions = 0;
+        for character in text.chars() {
+            if previous == Some(character) {
+                repetitions += 1;
+            } else {
+                previous = Some(character);
+                repetitions = 1;
+            }
+            if (repetitions > max_repetitions) && !character.is_whitespace() {
+                sanitized.push(' ');
+            } else {
+                sanitized.push(character);
+            }
+        }
+        Cow::Owned(sanitized)
+    }
+
+    /// Converts an input received as bytes to a sanitized text, replacing the invalid UTF-8 sequences with the
+    /// replacement character (`U+FFFD`).
+    ///
+    /// # Arguments
+    ///
+    /// * `bytes` - Input bytes
+    pub fn sanitize_bytes(&self, bytes: &[u8]) -> String {
+        self.sanitize(&String::from_utf8_lossy(bytes)).into_owned()
+    }
+}
+
+/// Checks if a text contains a run of the same non-whitespace character longer than `max_repetitions`
+fn has_long_repetition(text: &str, max_repetitions: usize) -> bool {
+    let mut previous = None;
+    let mut repetitions = 0;
+    for character in text.chars() {
+        if previous == Some(character) {
+            repetitions += 1;
+            if (repetitions > max_repetitions) && !character.is_whitespace() {
+                return true;
+            }
+        } else {
+            previous = Some(character);
+            repetitions = 1;
+        }
+    }
+    false
+}
+
+impl Default for InputSanitizer {
+    fn default() -> InputSanitizer {
+        InputSanitizer::new(Some(1_000_000), Some(100))
+    }
+}
+
+lazy_static! {
+    static ref INPUT_SANITIZER: RwLock<InputSanitizer> = RwLock::new(InputSanitizer::default());
+}
+
+/// Sets the `InputSanitizer` applied to the texts tokenized by the pipelines of the process, replacing the default
+/// sanitizer (inputs truncated to 1,000,000 characters, repetitions limited to 100 characters).
+///
+/// # Arguments
+///
+/// * `sanitizer` - `InputSanitizer` to apply (`InputSanitizer::disabled()` leaves the inputs unchanged)
+pub fn set_input_sanitizer(sanitizer: InputSanitizer) {
+    *INPUT_SANITIZER.write().unwrap() = sanitizer;
+}
+
+/// Returns the `InputSanitizer` of the process
+pub fn input_sanitizer() -> InputSanitizer {
+    INPUT_SANITIZER.read().unwrap().clone()
+}
+
+/// Sanitizes a text with the `InputSanitizer` of the process
+pub(crate) fn sanitize_input(text: &str) -> Cow<str> {
+    INPUT_SANITIZER.read().unwrap().sanitize(text)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_input_sanitizer() {
+        let sanitizer = InputSanitizer::new(Some(12), Some(3));
+        assert!(matches!(
+            sanitizer.sanitize("Hello world"),
+            Cow::Borrowed(_)
+        ));
+        assert_eq!(
+            sanitizer.sanitize("Hello world, how are you?"),
+            "Hello world,"
+        );
+        assert_eq!(sanitizer.sanitize("Nooooo!"), "Nooo  !");
+        assert_eq!(sanitizer.sanitize("a       b"), "a       b");
+        assert_eq!(sanitizer.sanitize("ééééé"), "ééé  ");
+        assert_eq!(
+            sanitizer.sanitize_bytes(b"Hello \xF0\x90\x80world"),
+            "Hello \u{FFFD}world"
+        );
+        let sanitizer = InputSanitizer::disabled();
+        assert_eq!(sanitizer.sanitize("Nooooo!"), "Nooooo!");
+    }
+}
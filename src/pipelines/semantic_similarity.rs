@@ -0,0 +1,452 @@
+// Copyright 2022 Guillaume Becquin
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//     http://www.apache.org/licenses/LICENSE-2.0
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! # Semantic Textual Similarity pipeline
+//! Scores the similarity of pairs of texts on a 0-1 scale. Two types of models are supported, selected by the
+//! `SimilarityModelConfig` of the configuration:
+//! - bi-encoders: both texts are embedded independently by a sentence embeddings model and the raw score is the cosine
+//! similarity of the embeddings. This is the fastest option when the same texts are compared to many others.
+//! - cross-encoders: both texts are encoded together by a sequence classification model with a single regression
+//! output (e.g. a cross-encoder trained on STS-B). These are generally more accurate but require a forward pass per pair.
+//!
+//! The raw scores are mapped to the 0-1 scale by a `ScoreCalibration`. By default, negative cosine similarities are
+//! clamped to 0 for bi-encoders, and a sigmoid is applied to the output of cross-encoders. Models with a different
+//! output range (e.g. STS-B regression on a 0-5 scale) can use a linear calibration.
+//!
+//! ```no_run
+//! # fn main() -> anyhow::Result<()> {
+//! use rust_bert::pipelines::semantic_similarity::SemanticSimilarityModel;
+//!
+//! let similarity_model = SemanticSimilarityModel::new(Default::default())?;
+//!
+//! let scores = similarity_model.predict(&[
+//!     ("A man is playing a guitar.", "A person plays an instrument."),
+//!     ("A man is playing a guitar.", "A chef is cooking pasta."),
+//! ])?;
+//! # Ok(())
+//! # }
+//! ```
+//!
+//! Cross-encoder with a 0-5 regression output:
+//!
+//! ```no_run
+//! # fn main() -> anyhow::Result<()> {
+//! use rust_bert::pipelines::common::ModelType;
+//! use rust_bert::pipelines::semantic_similarity::{
+//!     ScoreCalibration, SemanticSimilarityConfig, SemanticSimilarityModel, SimilarityModelConfig,
+//! };
+//! use rust_bert::pipelines::sequence_classification::SequenceClassificationConfig;
+//! use rust_bert::resources::LocalResource;
+//! use std::path::PathBuf;
+//!
+//! let cross_encoder_config = SequenceClassificationConfig::new(
+//!     ModelType::Roberta,
+//!     LocalResource::from(PathBuf::from("path/to/stsb-roberta/rust_model.ot")),
+//!     LocalResource::from(PathBuf::from("path/to/stsb-roberta/config.json")),
+//!     LocalResource::from(PathBuf::from("path/to/stsb-roberta/vocab.json")),
+//!     Some(LocalResource::from(PathBuf::from(
+//!         "path/to/stsb-roberta/merges.txt",
+//!     ))),
+//!     false,
+//!     None,
+//!     None,
+//! );
+//! let config = SemanticSimilarityConfig::new(
+//!     SimilarityModelConfig::CrossEncoder(cross_encoder_config),
+//!     ScoreCalibration::Linear { min: 0.0, max: 5.0 },
+//! );
+//! let similarity_model = SemanticSimilarityModel::new(config)?;
+//! # Ok(())
+//! # }
+//! ```
+
+use crate::common::error::RustBertError;
+use crate::pipelines::common::{ConfigOption, ModelType, Pipeline, TokenizerOption};
+use crate::pipelines::sentence_embeddings::{
+    SentenceEmbeddingsConfig, SentenceEmbeddingsModel, SentenceEmbeddingsModelOuput,
+};
+use crate::pipelines::sequence_classification::{
+    SequenceClassificationConfig, SequenceClassificationOption,
+};
+use rust_tokenizers::tokenizer::TruncationStrategy;
+use rust_tokenizers::TokenizedInput;
+use serde::{Deserialize, Serialize};
+use tch::kind::Kind::{Bool, Float};
+use tch::nn::VarStore;
+use tch::{no_grad, Tensor};
+
+#[cfg(feature = "remote")]
+use crate::pipelines::sentence_embeddings::SentenceEmbeddingsModelType;
+
+/// # Mapping of the raw similarity scores to the 0-1 scale
+/// The calibrated scores are clipped to the 0-1 range.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub enum ScoreCalibration {
+    /// Linear mapping of `[min, max]` to `[0, 1]`. For cosine similarities, `min: 0.0` clamps negative scores to 0
+    /// and `min: -1.0` rescales the full cosine range.
+    Linear { min: f64, max: f64 },
+    /// Logistic function, for cross-encoders trained with a binary cross-entropy loss
+    Sigmoid,
+}
+
+impl ScoreCalibration {
+    /// Maps a raw similarity score to the 0-1 scale
+    pub fn calibrate(&self, score: f64) -> f64 {
+        let score = match *self {
+            ScoreCalibration::Linear { min, max } => (score - min) / (max - min),
+            ScoreCalibration::Sigmoid => 1.0 / (1.0 + (-score).exp()),
+        };
+        score.clamp(0.0, 1.0)
+    }
+}
+
+/// # Model used to score the pairs of texts
+pub enum SimilarityModelConfig {
+    /// Sentence embeddings model, scoring pairs by the cosine similarity of their embeddings
+    BiEncoder(SentenceEmbeddingsConfig),
+    /// Sequence classification model with a single output, scoring both texts of a pair together
+    CrossEncoder(SequenceClassificationConfig),
+}
+
+/// # Configuration for SemanticSimilarityModel
+pub struct SemanticSimilarityConfig {
+    /// Bi-encoder or cross-encoder model configuration
+    pub model: SimilarityModelConfig,
+    /// Calibration of the raw scores (default: clamping of negative cosine similarities for bi-encoders, sigmoid for cross-encoders)
+    pub calibration: Option<ScoreCalibration>,
+}
+
+impl SemanticSimilarityConfig {
+    /// Instantiate a new semantic similarity configuration
+    ///
+    /// # Arguments
+    ///
+    /// * `model` - `SimilarityModelConfig` with the bi-encoder or cross-encoder model configuration
+    /// * `calibration` - Optional `ScoreCalibration` of the raw scores. If `None`, the default calibration for the model type is used.
+    pub fn new(
+        model: SimilarityModelConfig,
+        calibration: impl Into<Option<ScoreCalibration>>,
+    ) -> SemanticSimilarityConfig {
+        SemanticSimilarityConfig {
+            model,
+            calibration: calibration.into(),
+        }
+    }
+}
+
+#[cfg(feature = "remote")]
+impl Default for SemanticSimilarityConfig {
+    /// Provides a bi-encoder using the all-MiniLM-L12-v2 sentence embeddings model
+    fn default() -> SemanticSimilarityConfig {
+        SemanticSimilarityConfig::new(
+            SimilarityModelConfig::BiEncoder(SentenceEmbeddingsModelType::AllMiniLmL12V2.into()),
+            None,
+        )
+    }
+}
+
+/// Sequence classification model with a single output scoring pairs of texts encoded together (also used by the
+/// `reward_model` pipeline)
+pub(crate) struct CrossEncoder {
+    tokenizer: TokenizerOption,
+    classifier: SequenceClassificationOption,
+    max_length: usize,
+    var_store: VarStore,
+}
+
+impl CrossEncoder {
+    pub(crate) fn new(config: SequenceClassificationConfig) -> Result<CrossEncoder, RustBertError> {
+        let config_path = config.config_resource.get_local_path()?;
+        let vocab_path = config.vocab_resource.get_local_path()?;
+        let merges_path = if let Some(merges_resource) = &config.merges_resource {
+            Some(merges_resource.get_local_path()?)
+        } else {
+            None
+        };
+
+        let tokenizer = TokenizerOption::from_file(
+            config.model_type,
+            vocab_path.to_str().unwrap(),
+            merges_path.as_deref().map(|path| path.to_str().unwrap()),
+            config.lower_case,
+            config.strip_accents,
+            config.add_prefix_space,
+        )?;
+        let mut var_store = VarStore::new(config.device);
+        let model_config = ConfigOption::from_file(config.model_type, config_path)?;
+        model_config.validate(Some(&tokenizer), None)?;
+        let max_length = model_config
+            .get_max_len()
+            .map(|v| v as usize)
+            .unwrap_or(usize::MAX);
+        let classifier =
+            SequenceClassificationOption::new(config.model_type, &var_store.root(), &model_config)?;
+        crate::resources::load_weights(&config.model_resource, &mut var_store)?;
+        Ok(CrossEncoder {
+            tokenizer,
+            classifier,
+            max_length,
+            var_store,
+        })
+    }
+
+    pub(crate) fn score(&self, pairs: &[(&str, &str)]) -> Result<Vec<f64>, RustBertError> {
+        let tokenized_input: Vec<TokenizedInput> = self.tokenizer.encode_pair_list(
+            pairs,
+            self.max_length,
+            &TruncationStrategy::LongestFirst,
+            0,
+        );
+        let max_len = tokenized_input
+            .iter()
+            .map(|input| input.token_ids.len())
+            .max()
+            .unwrap();
+        let pad_id = self
+            .tokenizer
+            .get_pad_id()
+            .expect("The Tokenizer used for cross-encoders should contain a PAD id");
+        let device = self.var_store.device();
+
+        // Only the models trained with segment embeddings for sentence pairs use the token type ids
+        let use_token_type_ids = matches!(
+            self.classifier.model_type(),
+            ModelType::Bert | ModelType::Albert
+        );
+        let mut input_ids = Vec::with_capacity(tokenized_input.len());
+        let mut token_type_ids = Vec::with_capacity(tokenized_input.len());
+        for mut input in tokenized_input {
+            input.token_ids.resize(max_len, pad_id);
+            input_ids.push(Tensor::of_slice(&input.token_ids));
+            if use_token_type_ids {
+                let mut segment_ids = input
+                    .segment_ids
+                    .iter()
+                    .map(|&segment_id| segment_id as i64)
+                    .collect::<Vec<i64>>();
+                segment_ids.resize(max_len, 0);
+                token_type_ids.push(Tensor::of_slice(&segment_ids));
+            }
+        }
+        let input_ids = Tensor::stack(&input_ids, 0).to(device);
+        let mask = input_ids.ne(pad_id).to_kind(Bool);
+        let token_type_ids = if use_token_type_ids {
+            Some(Tensor::stack(&token_type_ids, 0).to(device))
+        } else {
+            None
+        };
+
+        let logits = no_grad(|| {
+            self.classifier.forward_t(
+                Some(&input_ids),
+                Some(&mask),
+                token_type_ids.as_ref(),
+                None,
+                None,
+                false,
+            )
+        });
+        let num_outputs = logits.size()[1];
+        if num_outputs != 1 {
+            return Err(RustBertError::ValueError(format!(
+                "Cross-encoders must have a single regression output, got {} outputs",
+                num_outputs
+            )));
+        }
+        Ok(logits
+            .select(1, 0)
+            .to_kind(Float)
+            .iter::<f64>()
+            .unwrap()
+            .collect::<Vec<f64>>())
+    }
+}
+
+#[allow(clippy::large_enum_variant)]
+enum SimilarityScorer {
+    BiEncoder(SentenceEmbeddingsModel),
+    CrossEncoder(CrossEncoder),
+}
+
+/// # SemanticSimilarityModel for the similarity of pairs of texts
+pub struct SemanticSimilarityModel {
+    scorer: SimilarityScorer,
+    calibration: ScoreCalibration,
+    batch_size: usize,
+}
+
+impl SemanticSimilarityModel {
+    /// Build a new `SemanticSimilarityModel`
+    ///
+    /// # Arguments
+    ///
+    /// * `config` - `SemanticSimilarityConfig` object containing the model configuration and the score calibration
+    ///
+    /// # Example
+    ///
+    /// ```no_run
+    /// # fn main() -> anyhow::Result<()> {
+    /// use rust_bert::pipelines::semantic_similarity::SemanticSimilarityModel;
+    ///
+    /// let model = SemanticSimilarityModel::new(Default::default())?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn new(config: SemanticSimilarityConfig) -> Result<SemanticSimilarityModel, RustBertError> {
+        let (scorer, default_calibration) = match config.model {
+            SimilarityModelConfig::BiEncoder(model_config) => (
+                SimilarityScorer::BiEncoder(SentenceEmbeddingsModel::new(model_config)?),
+                ScoreCalibration::Linear { min: 0.0, max: 1.0 },
+            ),
+            SimilarityModelConfig::CrossEncoder(model_config) => (
+                SimilarityScorer::CrossEncoder(CrossEncoder::new(model_config)?),
+                ScoreCalibration::Sigmoid,
+            ),
+        };
+        if let Some(ScoreCalibration::Linear { min, max }) = config.calibration {
+            if max <= min {
+                return Err(RustBertError::InvalidConfigurationError(format!(
+                    "Invalid linear calibration range: min ({}) must be lower than max ({})",
+                    min, max
+                )));
+            }
+        }
+        Ok(SemanticSimilarityModel {
+            scorer,
+            calibration: config.calibration.unwrap_or(default_calibration),
+            batch_size: 32,
+        })
+    }
+
+    /// Sets the number of pairs scored at once
+    ///
+    /// # Arguments
+    ///
+    /// * `batch_size` - Maximum number of pairs in a batch (default: 32)
+    pub fn set_batch_size(&mut self, batch_size: usize) {
+        self.batch_size = batch_size.max(1);
+    }
+
+    /// Returns the raw (uncalibrated) scores of a batch of pairs: cosine similarities for bi-encoders and model
+    /// outputs for cross-encoders
+    fn raw_scores(&self, pairs: &[(&str, &str)]) -> Result<Vec<f64>, RustBertError> {
+        match &self.scorer {
+            SimilarityScorer::BiEncoder(model) => {
+                let (first_texts, second_texts): (Vec<&str>, Vec<&str>) =
+                    pairs.iter().cloned().unzip();
+                let SentenceEmbeddingsModelOuput {
+                    embeddings: first_embeddings,
+                    ..
+                } = model.encode_as_tensor(&first_texts)?;
+                let SentenceEmbeddingsModelOuput {
+                    embeddings: second_embeddings,
+                    ..
+                } = model.encode_as_tensor(&second_texts)?;
+                let norms = first_embeddings.norm_scalaropt_dim(2, &[1], false)
+                    * second_embeddings.norm_scalaropt_dim(2, &[1], false);
+                let cosine_similarities =
+                    (first_embeddings * second_embeddings).sum_dim_intlist(&[1], false, Float)
+                        / norms.clamp_min(1e-12);
+                Ok(cosine_similarities
+                    .iter::<f64>()
+                    .unwrap()
+                    .collect::<Vec<f64>>())
+            }
+            SimilarityScorer::CrossEncoder(model) => model.score(pairs),
+        }
+    }
+
+    /// Scores the similarity of pairs of texts
+    ///
+    /// # Arguments
+    ///
+    /// * `pairs` - `&[(text, text)]` Array of pairs of texts to compare
+    ///
+    /// # Returns
+    ///
+    /// * `Vec<f64>` containing the calibrated similarity (between 0 and 1) of each pair
+    ///
+    /// # Example
+    ///
+    /// ```no_run
+    /// # fn main() -> anyhow::Result<()> {
+    /// use rust_bert::pipelines::semantic_similarity::SemanticSimilarityModel;
+    ///
+    /// let similarity_model = SemanticSimilarityModel::new(Default::default())?;
+    /// let scores = similarity_model.predict(&[(
+    ///     "The cat sits on the mat.",
+    ///     "A cat is sitting on a rug.",
+    /// )])?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn predict<S1, S2>(&self, pairs: &[(S1, S2)]) -> Result<Vec<f64>, RustBertError>
+    where
+        S1: AsRef<str>,
+        S2: AsRef<str>,
+    {
+        let mut scores = Vec::with_capacity(pairs.len());
+        for batch in pairs.chunks(self.batch_size) {
+            let batch = batch
+                .iter()
+                .map(|(first, second)| (first.as_ref(), second.as_ref()))
+                .collect::<Vec<(&str, &str)>>();
+            scores.extend(
+                self.raw_scores(&batch)?
+                    .into_iter()
+                    .map(|score| self.calibration.calibrate(score)),
+            );
+        }
+        Ok(scores)
+    }
+}
+
+impl<S1, S2> Pipeline<(S1, S2), f64> for SemanticSimilarityModel
+where
+    S1: AsRef<str>,
+    S2: AsRef<str>,
+{
+    type Options = ();
+
+    fn run_with_options(
+        &self,
+        inputs: &[(S1, S2)],
+        _options: &(),
+    ) -> Result<Vec<f64>, RustBertError> {
+        self.predict(inputs)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_calibration() {
+        let clamp = ScoreCalibration::Linear { min: 0.0, max: 1.0 };
+        assert_eq!(clamp.calibrate(0.8), 0.8);
+        assert_eq!(clamp.calibrate(-0.3), 0.0);
+
+        let rescale = ScoreCalibration::Linear {
+            min: -1.0,
+            max: 1.0,
+        };
+        assert_eq!(rescale.calibrate(0.0), 0.5);
+        assert_eq!(rescale.calibrate(-1.0), 0.0);
+
+        let stsb = ScoreCalibration::Linear { min: 0.0, max: 5.0 };
+        assert_eq!(stsb.calibrate(4.0), 0.8);
+        assert_eq!(stsb.calibrate(5.3), 1.0);
+
+        assert_eq!(ScoreCalibration::Sigmoid.calibrate(0.0), 0.5);
+        assert!(ScoreCalibration::Sigmoid.calibrate(10.0) > 0.99);
+    }
+}
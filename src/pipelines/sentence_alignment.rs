@@ -0,0 +1,389 @@
+// Copyright 2022 Guillaume Becquin
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//     http://www.apache.org/licenses/LICENSE-2.0
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! # Sentence alignment of parallel documents
+//! Aligns the sentences of a source document with the sentences of its translation, for example to build a
+//! translation memory from parallel documents. The sentences of both documents are embedded by a multilingual sentence
+//! embeddings model and each pair of sentences is scored with the ratio margin of their cosine similarity
+//! ([Margin-based Parallel Corpus Mining with Multilingual Sentence Embeddings](https://arxiv.org/abs/1811.01136)
+//! Artetxe, Schwenk, 2019): the similarity is divided by the average similarity of both sentences to their `k` nearest
+//! neighbours in the other document, which penalizes generic sentences similar to many others (hubs).
+//!
+//! Two alignment strategies are available:
+//! - `AlignmentStrategy::Monotonic` (default): dynamic programming alignment preserving the order of the sentences,
+//! maximizing the total margin of the aligned pairs. Sentences without a translation in the other document are skipped.
+//! - `AlignmentStrategy::Greedy`: pairs are selected by decreasing margin, each sentence being aligned at most once.
+//! The order of the sentences is not enforced, which suits loosely parallel documents (e.g. reordered paragraphs).
+//!
+//! Only one-to-one alignments are produced, pairs with a margin below the threshold are discarded.
+//!
+//! ```no_run
+//! # fn main() -> anyhow::Result<()> {
+//! use rust_bert::pipelines::sentence_alignment::SentenceAligner;
+//! use rust_bert::pipelines::sentence_embeddings::{
+//!     SentenceEmbeddingsBuilder, SentenceEmbeddingsModelType,
+//! };
+//!
+//! let model = SentenceEmbeddingsBuilder::remote(
+//!     SentenceEmbeddingsModelType::DistiluseBaseMultilingualCased,
+//! )
+//! .create_model()?;
+//! let aligner = SentenceAligner::new(&model);
+//!
+//! let source = [
+//!     "The meeting is postponed.",
+//!     "It will take place next Monday.",
+//!     "Please confirm your attendance.",
+//! ];
+//! let target = [
+//!     "La réunion est reportée.",
+//!     "Elle aura lieu lundi prochain.",
+//!     "Merci de confirmer votre présence.",
+//! ];
+//! for alignment in aligner.align(&source, &target)? {
+//!     println!(
+//!         "{} ||| {} ({:.3})",
+//!         source[alignment.source_index], target[alignment.target_index], alignment.score
+//!     );
+//! }
+//! # Ok(())
+//! # }
+//! ```
+
+use crate::common::error::RustBertError;
+use crate::pipelines::sentence_embeddings::SentenceEmbeddingsModel;
+use serde::{Deserialize, Serialize};
+use std::cmp::Ordering;
+use tch::{Kind, Tensor};
+
+/// # Strategy used to select the aligned sentence pairs
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum AlignmentStrategy {
+    /// Pairs selected by decreasing margin, without order constraint
+    Greedy,
+    /// Dynamic programming alignment preserving the order of the sentences
+    Monotonic,
+}
+
+/// # Aligned sentence pair
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct SentenceAlignment {
+    /// Index of the sentence in the source document
+    pub source_index: usize,
+    /// Index of the sentence in the target document
+    pub target_index: usize,
+    /// Ratio margin of the cosine similarity of the sentences (values above 1 indicate a pair more similar than the
+    /// nearest neighbours of its sentences)
+    pub score: f64,
+}
+
+/// Computes the ratio margin scores of embeddings of shape (*number of source sentences*, *embedding dimension*) and
+/// (*number of target sentences*, *embedding dimension*).
+///
+/// # Arguments
+///
+/// * `source` - Embeddings of the source sentences
+/// * `target` - Embeddings of the target sentences
+/// * `neighbors` - Number of nearest neighbours used to estimate the average similarity of each sentence (capped by
+/// the number of sentences of the other document)
+///
+/// # Returns
+///
+/// * `Tensor` of shape (*number of source sentences*, *number of target sentences*) containing the margin of each pair
+pub fn margin_scores(source: &Tensor, target: &Tensor, neighbors: usize) -> Tensor {
+    let normalize = |embeddings: &Tensor| {
+        let embeddings = embeddings.to_kind(Kind::Double);
+        &embeddings
+            / embeddings
+                .norm_scalaropt_dim(2, &[-1], true)
+                .clamp_min(1e-12)
+    };
+    let similarities = normalize(source).matmul(&normalize(target).tr());
+    let (num_source, num_target) = (similarities.size()[0], similarities.size()[1]);
+    let neighbors = neighbors.max(1) as i64;
+
+    let source_neighborhood = similarities
+        .topk(neighbors.min(num_target), 1, true, false)
+        .0
+        .mean_dim(&[1], true, Kind::Double);
+    let target_neighborhood = similarities
+        .topk(neighbors.min(num_source), 0, true, false)
+        .0
+        .mean_dim(&[0], true, Kind::Double);
+    &similarities / ((source_neighborhood + target_neighborhood) / 2.0).clamp_min(1e-12)
+}
+
+/// Selects the pairs by decreasing margin, each sentence being aligned at most once
+fn align_greedy(margins: &[Vec<f64>], threshold: f64) -> Vec<SentenceAlignment> {
+    let mut candidates = margins
+        .iter()
+        .enumerate()
+        .flat_map(|(source_index, row)| {
+            row.iter()
+                .enumerate()
+                .filter(|(_, score)| **score >= threshold)
+                .map(move |(target_index, score)| SentenceAlignment {
+                    source_index,
+                    target_index,
+                    score: *score,
+                })
+        })
+        .collect::<Vec<SentenceAlignment>>();
+    candidates.sort_by(|a, b| b.score.partial_cmp(&a.score).unwrap_or(Ordering::Equal));
+
+    let num_targets = margins.first().map_or(0, Vec::len);
+    let mut source_aligned = vec![false; margins.len()];
+    let mut target_aligned = vec![false; num_targets];
+    let mut alignments = Vec::new();
+    for candidate in candidates {
+        if !source_aligned[candidate.source_index] && !target_aligned[candidate.target_index] {
+            source_aligned[candidate.source_index] = true;
+            target_aligned[candidate.target_index] = true;
+            alignments.push(candidate);
+        }
+    }
+    alignments.sort_by_key(|alignment| alignment.source_index);
+    alignments
+}
+
+/// Finds the order-preserving alignment maximizing the total margin of the aligned pairs. Skipping a sentence has no
+/// cost and only pairs with a margin above the threshold can be aligned.
+fn align_monotonic(margins: &[Vec<f64>], threshold: f64) -> Vec<SentenceAlignment> {
+    let num_sources = margins.len();
+    let num_targets = margins.first().map_or(0, Vec::len);
+    let is_candidate = |i: usize, j: usize| margins[i - 1][j - 1] >= threshold;
+
+    // scores[i][j]: best total margin aligning the first i source and the first j target sentences
+    let mut scores = vec![vec![0f64; num_targets + 1]; num_sources + 1];
+    for i in 1..=num_sources {
+        for j in 1..=num_targets {
+            let mut best = scores[i - 1][j].max(scores[i][j - 1]);
+            if is_candidate(i, j) {
+                best = best.max(scores[i - 1][j - 1] + margins[i - 1][j - 1]);
+            }
+            scores[i][j] = best;
+        }
+    }
+
+    let mut alignments = Vec::new();
+    let (mut i, mut j) = (num_sources, num_targets);
+    while i > 0 && j > 0 {
+        if is_candidate(i, j) && scores[i][j] == scores[i - 1][j - 1] + margins[i - 1][j - 1] {
+            alignments.push(SentenceAlignment {
+                source_index: i - 1,
+                target_index: j - 1,
+                score: margins[i - 1][j - 1],
+            });
+            i -= 1;
+            j -= 1;
+        } else if scores[i][j] == scores[i - 1][j] {
+            i -= 1;
+        } else {
+            j -= 1;
+        }
+    }
+    alignments.reverse();
+    alignments
+}
+
+/// Aligns sentences from their embeddings of shape (*number of sentences*, *embedding dimension*)
+///
+/// # Arguments
+///
+/// * `source` - Embeddings of the source sentences
+/// * `target` - Embeddings of the target sentences, of the same dimension as the source embeddings
+/// * `strategy` - Strategy used to select the aligned pairs
+/// * `neighbors` - Number of nearest neighbours used by the margin scores
+/// * `threshold` - Minimum margin of an aligned pair
+///
+/// # Returns
+///
+/// * `Vec<SentenceAlignment>` aligned pairs, sorted by source index
+pub fn align_embeddings(
+    source: &Tensor,
+    target: &Tensor,
+    strategy: AlignmentStrategy,
+    neighbors: usize,
+    threshold: f64,
+) -> Result<Vec<SentenceAlignment>, RustBertError> {
+    let (source_shape, target_shape) = (source.size(), target.size());
+    if source_shape.len() != 2 || target_shape.len() != 2 || source_shape[1] != target_shape[1] {
+        return Err(RustBertError::ValueError(format!(
+            "Expected source and target embeddings of shape (number of sentences, embedding dimension) with the same dimension, got {:?} and {:?}",
+            source_shape, target_shape
+        )));
+    }
+    if source_shape[0] == 0 || target_shape[0] == 0 {
+        return Ok(vec![]);
+    }
+    let margins = Vec::<Vec<f64>>::from(margin_scores(source, target, neighbors));
+    Ok(match strategy {
+        AlignmentStrategy::Greedy => align_greedy(&margins, threshold),
+        AlignmentStrategy::Monotonic => align_monotonic(&margins, threshold),
+    })
+}
+
+/// # SentenceAligner aligning the sentences of parallel documents
+pub struct SentenceAligner<'a> {
+    model: &'a SentenceEmbeddingsModel,
+    strategy: AlignmentStrategy,
+    neighbors: usize,
+    threshold: f64,
+    batch_size: usize,
+}
+
+impl<'a> SentenceAligner<'a> {
+    /// Build a new `SentenceAligner` with the monotonic strategy, 4 nearest neighbours and a margin threshold of 1.0
+    ///
+    /// # Arguments
+    ///
+    /// * `model` - Multilingual sentence embeddings model, embedding translations close to each other
+    pub fn new(model: &'a SentenceEmbeddingsModel) -> SentenceAligner<'a> {
+        SentenceAligner {
+            model,
+            strategy: AlignmentStrategy::Monotonic,
+            neighbors: 4,
+            threshold: 1.0,
+            batch_size: 32,
+        }
+    }
+
+    /// Sets the strategy used to select the aligned pairs (default: `AlignmentStrategy::Monotonic`)
+    pub fn with_strategy(mut self, strategy: AlignmentStrategy) -> Self {
+        self.strategy = strategy;
+        self
+    }
+
+    /// Sets the number of nearest neighbours used by the margin scores (default: 4)
+    pub fn with_neighbors(mut self, neighbors: usize) -> Self {
+        self.neighbors = neighbors.max(1);
+        self
+    }
+
+    /// Sets the minimum margin of an aligned pair (default: 1.0). Higher values favor precision over recall when
+    /// building translation memories.
+    pub fn with_threshold(mut self, threshold: f64) -> Self {
+        self.threshold = threshold;
+        self
+    }
+
+    /// Sets the number of sentences embedded at once (default: 32)
+    pub fn with_batch_size(mut self, batch_size: usize) -> Self {
+        self.batch_size = batch_size.max(1);
+        self
+    }
+
+    fn embed<S>(&self, sentences: &[S]) -> Result<Tensor, RustBertError>
+    where
+        S: AsRef<str> + Sync,
+    {
+        let mut embeddings = Vec::with_capacity(sentences.len());
+        for batch in sentences.chunks(self.batch_size) {
+            embeddings.extend(self.model.encode(batch)?);
+        }
+        Ok(Tensor::of_slice(&embeddings.concat()).view((sentences.len() as i64, -1)))
+    }
+
+    /// Aligns the sentences of a source document with the sentences of its translation
+    ///
+    /// # Arguments
+    ///
+    /// * `source` - Sentences of the source document, in order
+    /// * `target` - Sentences of the target document, in order
+    ///
+    /// # Returns
+    ///
+    /// * `Vec<SentenceAlignment>` aligned pairs, sorted by source index
+    pub fn align<S1, S2>(
+        &self,
+        source: &[S1],
+        target: &[S2],
+    ) -> Result<Vec<SentenceAlignment>, RustBertError>
+    where
+        S1: AsRef<str> + Sync,
+        S2: AsRef<str> + Sync,
+    {
+        if source.is_empty() || target.is_empty() {
+            return Ok(vec![]);
+        }
+        align_embeddings(
+            &self.embed(source)?,
+            &self.embed(target)?,
+            self.strategy,
+            self.neighbors,
+            self.threshold,
+        )
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use tch::Device;
+
+    fn aligned_pairs(alignments: &[SentenceAlignment]) -> Vec<(usize, usize)> {
+        alignments
+            .iter()
+            .map(|alignment| (alignment.source_index, alignment.target_index))
+            .collect()
+    }
+
+    #[test]
+    fn test_margin_scores() {
+        let source = Tensor::of_slice(&[1f32, 0.0, 0.0, 1.0]).view([2, 2]);
+        let target = Tensor::of_slice(&[0f32, 2.0, 3.0, 0.0, 1.0, 1.0]).view([3, 2]);
+        let margins = Vec::<Vec<f64>>::from(margin_scores(&source, &target, 1));
+
+        // With a single neighbour, the best match of a sentence in both directions has a margin of 1
+        assert!((margins[0][1] - 1.0).abs() < 1e-6);
+        assert!((margins[1][0] - 1.0).abs() < 1e-6);
+        // The third target sentence is equally similar to both source sentences, its similarity is scaled up
+        assert!((margins[0][2] - 2.0 * (2f64.sqrt() - 1.0)).abs() < 1e-6);
+        assert!(margins[0][0].abs() < 1e-6);
+    }
+
+    #[test]
+    fn test_alignment_strategies() {
+        tch::manual_seed(42);
+        let source = Tensor::randn(&[4, 16], (Kind::Float, Device::Cpu));
+        let noise = Tensor::randn(&[4, 16], (Kind::Float, Device::Cpu)) * 0.1;
+        let translations = &source + noise;
+        let inserted = Tensor::randn(&[1, 16], (Kind::Float, Device::Cpu));
+
+        // The target document has an additional sentence
+        let target = Tensor::cat(
+            &[
+                translations.narrow(0, 0, 2),
+                inserted,
+                translations.narrow(0, 2, 2),
+            ],
+            0,
+        );
+        let alignments =
+            align_embeddings(&source, &target, AlignmentStrategy::Monotonic, 2, 1.0).unwrap();
+        assert_eq!(
+            aligned_pairs(&alignments),
+            vec![(0, 0), (1, 1), (2, 3), (3, 4)]
+        );
+
+        // Reordered target sentences are only recovered by the greedy strategy
+        let target = translations.index_select(0, &Tensor::of_slice(&[1i64, 0, 3, 2]));
+        let alignments =
+            align_embeddings(&source, &target, AlignmentStrategy::Greedy, 2, 1.0).unwrap();
+        assert_eq!(
+            aligned_pairs(&alignments),
+            vec![(0, 1), (1, 0), (2, 3), (3, 2)]
+        );
+        let alignments =
+            align_embeddings(&source, &target, AlignmentStrategy::Monotonic, 2, 1.0).unwrap();
+        assert_eq!(alignments.len(), 2);
+    }
+}
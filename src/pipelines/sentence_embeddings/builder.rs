@@ -5,7 +5,8 @@ use tch::Device;
 
 use crate::pipelines::common::ModelType;
 use crate::pipelines::sentence_embeddings::{
-    SentenceEmbeddingsConfig, SentenceEmbeddingsModel, SentenceEmbeddingsModulesConfig,
+    SentenceEmbeddingsConfig, SentenceEmbeddingsLayers, SentenceEmbeddingsModel,
+    SentenceEmbeddingsModulesConfig,
 };
 use crate::{Config, RustBertError};
 
@@ -21,6 +22,7 @@ use crate::{
 /// (configuration and weights).
 pub struct SentenceEmbeddingsBuilder<T> {
     device: Device,
+    layers: SentenceEmbeddingsLayers,
     inner: T,
 }
 
@@ -29,6 +31,12 @@ impl<T> SentenceEmbeddingsBuilder<T> {
         self.device = device;
         self
     }
+
+    /// Sets the transformer hidden layers to pool (by default, the output of the last layer)
+    pub fn with_layers(mut self, layers: SentenceEmbeddingsLayers) -> Self {
+        self.layers = layers;
+        self
+    }
 }
 
 pub struct Local {
@@ -46,6 +54,7 @@ impl SentenceEmbeddingsBuilder<Local> {
     pub fn local<P: Into<PathBuf>>(model_dir: P) -> Self {
         Self {
             device: Device::cuda_if_available(),
+            layers: SentenceEmbeddingsLayers::Last,
             inner: Local {
                 model_dir: model_dir.into(),
             },
@@ -106,6 +115,7 @@ impl SentenceEmbeddingsBuilder<Local> {
             tokenizer_config_resource: tokenizer_config.into(),
             tokenizer_vocab_resource: tokenizer_vocab.into(),
             tokenizer_merges_resource: tokenizer_merges.map(|r| r.into()),
+            layers: self.layers,
             device: self.device,
         };
 
@@ -123,6 +133,7 @@ impl SentenceEmbeddingsBuilder<Remote> {
     pub fn remote(model_type: SentenceEmbeddingsModelType) -> Self {
         Self {
             device: Device::cuda_if_available(),
+            layers: SentenceEmbeddingsLayers::Last,
             inner: Remote {
                 config: SentenceEmbeddingsConfig::from(model_type),
             },
@@ -179,7 +190,8 @@ impl SentenceEmbeddingsBuilder<Remote> {
         self
     }
 
-    pub fn create_model(self) -> Result<SentenceEmbeddingsModel, RustBertError> {
+    pub fn create_model(mut self) -> Result<SentenceEmbeddingsModel, RustBertError> {
+        self.inner.config.layers = self.layers;
         SentenceEmbeddingsModel::new(self.inner.config)
     }
 }
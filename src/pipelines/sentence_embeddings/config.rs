@@ -53,10 +53,70 @@ pub struct SentenceEmbeddingsConfig {
     pub tokenizer_vocab_resource: Box<dyn ResourceProvider + Send>,
     /// Optional transformer's tokenizer merges resource
     pub tokenizer_merges_resource: Option<Box<dyn ResourceProvider + Send>>,
+    /// Transformer hidden layers used to compute the embeddings
+    pub layers: SentenceEmbeddingsLayers,
     /// Device to place the transformer model on
     pub device: Device,
 }
 
+/// # Selection of the transformer hidden layers to pool
+///
+/// Layers are indexed from the output of the first transformer layer (0) to the output of the last layer
+/// (*num_layers - 1*). Negative indices count from the last layer, e.g. `-2` for the second-to-last layer.
+/// The output of the last layer includes the final layer normalization for models applying one (T5).
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub enum SentenceEmbeddingsLayers {
+    /// Output of the last layer
+    #[default]
+    Last,
+    /// Output of a single layer
+    Layer(i64),
+    /// Concatenation of the outputs of several layers along the hidden dimension. The size of the embeddings is
+    /// multiplied by the number of layers.
+    Concat(Vec<i64>),
+    /// Average of the outputs of several layers
+    Mean(Vec<i64>),
+}
+
+impl SentenceEmbeddingsLayers {
+    /// Concatenation of the last four layers, as commonly used for BERT features extraction
+    pub fn concat_last_four() -> Self {
+        SentenceEmbeddingsLayers::Concat(vec![-4, -3, -2, -1])
+    }
+
+    /// Resolves the layers selected to positive indices, checking them against the number of layers of the model
+    pub(crate) fn layer_indices(&self, num_layers: usize) -> Result<Vec<usize>, RustBertError> {
+        let indices = match self {
+            Self::Last => vec![-1],
+            Self::Layer(index) => vec![*index],
+            Self::Concat(indices) | Self::Mean(indices) => indices.clone(),
+        };
+        if indices.is_empty() {
+            return Err(RustBertError::InvalidConfigurationError(
+                "At least one layer must be selected for the sentence embeddings".to_string(),
+            ));
+        }
+        indices
+            .into_iter()
+            .map(|index| {
+                let resolved = if index < 0 {
+                    num_layers as i64 + index
+                } else {
+                    index
+                };
+                if (0..num_layers as i64).contains(&resolved) {
+                    Ok(resolved as usize)
+                } else {
+                    Err(RustBertError::InvalidConfigurationError(format!(
+                        "Layer {} out of range for a transformer with {} layers",
+                        index, num_layers
+                    )))
+                }
+            })
+            .collect()
+    }
+}
+
 #[cfg(feature = "remote")]
 impl From<SentenceEmbeddingsModelType> for SentenceEmbeddingsConfig {
     fn from(model_type: SentenceEmbeddingsModelType) -> Self {
@@ -91,6 +151,7 @@ impl From<SentenceEmbeddingsModelType> for SentenceEmbeddingsConfig {
                     DistilBertVocabResources::DISTILUSE_BASE_MULTILINGUAL_CASED,
                 )),
                 tokenizer_merges_resource: None,
+                layers: SentenceEmbeddingsLayers::Last,
                 device: Device::cuda_if_available(),
             },
 
@@ -120,6 +181,7 @@ impl From<SentenceEmbeddingsModelType> for SentenceEmbeddingsConfig {
                     BertVocabResources::BERT_BASE_NLI_MEAN_TOKENS,
                 )),
                 tokenizer_merges_resource: None,
+                layers: SentenceEmbeddingsLayers::Last,
                 device: Device::cuda_if_available(),
             },
 
@@ -149,6 +211,7 @@ impl From<SentenceEmbeddingsModelType> for SentenceEmbeddingsConfig {
                     BertVocabResources::ALL_MINI_LM_L12_V2,
                 )),
                 tokenizer_merges_resource: None,
+                layers: SentenceEmbeddingsLayers::Last,
                 device: Device::cuda_if_available(),
             },
 
@@ -180,6 +243,7 @@ impl From<SentenceEmbeddingsModelType> for SentenceEmbeddingsConfig {
                 tokenizer_merges_resource: Some(Box::new(RemoteResource::from_pretrained(
                     RobertaMergesResources::ALL_DISTILROBERTA_V1,
                 ))),
+                layers: SentenceEmbeddingsLayers::Last,
                 device: Device::cuda_if_available(),
             },
 
@@ -209,6 +273,7 @@ impl From<SentenceEmbeddingsModelType> for SentenceEmbeddingsConfig {
                     AlbertVocabResources::PARAPHRASE_ALBERT_SMALL_V2,
                 )),
                 tokenizer_merges_resource: None,
+                layers: SentenceEmbeddingsLayers::Last,
                 device: Device::cuda_if_available(),
             },
 
@@ -242,6 +307,7 @@ impl From<SentenceEmbeddingsModelType> for SentenceEmbeddingsConfig {
                     T5VocabResources::SENTENCE_T5_BASE,
                 )),
                 tokenizer_merges_resource: None,
+                layers: SentenceEmbeddingsLayers::Last,
                 device: Device::cuda_if_available(),
             },
         }
@@ -27,6 +27,22 @@
 //! # Ok(())
 //! # }
 //! ```
+//!
+//! By default, the output of the last transformer layer is pooled. Other layers (or a combination of layers,
+//! e.g. the concatenation of the last four layers) can be selected with `SentenceEmbeddingsLayers`:
+//!
+//! ```no_run
+//! use rust_bert::pipelines::sentence_embeddings::{
+//!     SentenceEmbeddingsBuilder, SentenceEmbeddingsLayers,
+//! };
+//!
+//! # fn main() -> anyhow::Result<()> {
+//! let model = SentenceEmbeddingsBuilder::local("local/path/to/bert-base-nli-mean-tokens")
+//!     .with_layers(SentenceEmbeddingsLayers::concat_last_four())
+//!     .create_model()?;
+//! # Ok(())
+//! # }
+//! ```
 
 pub mod builder;
 mod config;
@@ -36,13 +52,13 @@ mod resources;
 
 pub use builder::SentenceEmbeddingsBuilder;
 pub use config::{
-    SentenceEmbeddingsConfig, SentenceEmbeddingsModuleConfig, SentenceEmbeddingsModuleType,
-    SentenceEmbeddingsModulesConfig, SentenceEmbeddingsSentenceBertConfig,
-    SentenceEmbeddingsTokenizerConfig,
+    SentenceEmbeddingsConfig, SentenceEmbeddingsLayers, SentenceEmbeddingsModuleConfig,
+    SentenceEmbeddingsModuleType, SentenceEmbeddingsModulesConfig,
+    SentenceEmbeddingsSentenceBertConfig, SentenceEmbeddingsTokenizerConfig,
 };
 pub use pipeline::{
     SentenceEmbeddingsModel, SentenceEmbeddingsModelOuput, SentenceEmbeddingsOption,
-    SentenceEmbeddingsTokenizerOuput,
+    SentenceEmbeddingsTokenizerOuput, SentenceEmbeddingsTransformerOutput,
 };
 
 pub use resources::{
@@ -2,17 +2,21 @@ use std::borrow::Borrow;
 use std::convert::TryInto;
 
 use rust_tokenizers::tokenizer::TruncationStrategy;
+use rust_tokenizers::TokenizedInput;
 use tch::{nn, Tensor};
 
 use crate::albert::AlbertForSentenceEmbeddings;
 use crate::bert::BertForSentenceEmbeddings;
 use crate::distilbert::DistilBertForSentenceEmbeddings;
-use crate::pipelines::common::{ConfigOption, ModelType, TokenizerOption};
+use crate::pipelines::common::{ConfigOption, ModelType, Pipeline, TokenizerOption};
+use crate::pipelines::input_length::{
+    check_input_lengths, encode_chunks_list, mean_over_chunks, InputLengthPolicy,
+};
 use crate::pipelines::sentence_embeddings::layers::{Dense, DenseConfig, Pooling, PoolingConfig};
 use crate::pipelines::sentence_embeddings::{
     AttentionHead, AttentionLayer, AttentionOutput, Embedding, SentenceEmbeddingsConfig,
-    SentenceEmbeddingsModulesConfig, SentenceEmbeddingsSentenceBertConfig,
-    SentenceEmbeddingsTokenizerConfig,
+    SentenceEmbeddingsLayers, SentenceEmbeddingsModulesConfig,
+    SentenceEmbeddingsSentenceBertConfig, SentenceEmbeddingsTokenizerConfig,
 };
 use crate::roberta::RobertaForSentenceEmbeddings;
 use crate::t5::T5ForSentenceEmbeddings;
@@ -80,6 +84,22 @@ impl SentenceEmbeddingsOption {
         tokens_ids: &Tensor,
         tokens_masks: &Tensor,
     ) -> Result<(Tensor, Option<Vec<Tensor>>), RustBertError> {
+        self.forward_with_hidden_states(tokens_ids, tokens_masks)
+            .map(|transformer_output| {
+                (
+                    transformer_output.hidden_state,
+                    transformer_output.all_attentions,
+                )
+            })
+    }
+
+    /// Interface method to forward() of the particular transformer models, also returning the outputs of all
+    /// layers (batch first, one per layer) if the model is configured to output hidden states.
+    pub fn forward_with_hidden_states(
+        &self,
+        tokens_ids: &Tensor,
+        tokens_masks: &Tensor,
+    ) -> Result<SentenceEmbeddingsTransformerOutput, RustBertError> {
         match self {
             Self::Bert(transformer) => transformer
                 .forward_t(
@@ -92,19 +112,17 @@ impl SentenceEmbeddingsOption {
                     None,
                     false,
                 )
-                .map(|transformer_output| {
-                    (
-                        transformer_output.hidden_state,
-                        transformer_output.all_attentions,
-                    )
+                .map(|transformer_output| SentenceEmbeddingsTransformerOutput {
+                    hidden_state: transformer_output.hidden_state,
+                    all_hidden_states: transformer_output.all_hidden_states,
+                    all_attentions: transformer_output.all_attentions,
                 }),
             Self::DistilBert(transformer) => transformer
                 .forward_t(Some(tokens_ids), Some(tokens_masks), None, false)
-                .map(|transformer_output| {
-                    (
-                        transformer_output.hidden_state,
-                        transformer_output.all_attentions,
-                    )
+                .map(|transformer_output| SentenceEmbeddingsTransformerOutput {
+                    hidden_state: transformer_output.hidden_state,
+                    all_hidden_states: transformer_output.all_hidden_states,
+                    all_attentions: transformer_output.all_attentions,
                 }),
             Self::Roberta(transformer) => transformer
                 .forward_t(
@@ -117,11 +135,10 @@ impl SentenceEmbeddingsOption {
                     None,
                     false,
                 )
-                .map(|transformer_output| {
-                    (
-                        transformer_output.hidden_state,
-                        transformer_output.all_attentions,
-                    )
+                .map(|transformer_output| SentenceEmbeddingsTransformerOutput {
+                    hidden_state: transformer_output.hidden_state,
+                    all_hidden_states: transformer_output.all_hidden_states,
+                    all_attentions: transformer_output.all_attentions,
                 }),
             Self::Albert(transformer) => transformer
                 .forward_t(
@@ -133,20 +150,38 @@ impl SentenceEmbeddingsOption {
                     false,
                 )
                 .map(|transformer_output| {
-                    (
-                        transformer_output.hidden_state,
-                        transformer_output.all_attentions.map(|attentions| {
-                            attentions
-                                .into_iter()
-                                .map(|tensors| {
-                                    let num_inner_groups = tensors.len() as f64;
-                                    tensors.into_iter().sum::<Tensor>() / num_inner_groups
-                                })
-                                .collect()
-                        }),
-                    )
+                    let hidden_state = transformer_output.hidden_state;
+                    // ALBERT stores the inputs of the layers: shift them to the layers outputs
+                    let all_hidden_states =
+                        transformer_output
+                            .all_hidden_states
+                            .map(|mut hidden_states| {
+                                hidden_states.remove(0);
+                                hidden_states.push(hidden_state.copy());
+                                hidden_states
+                            });
+                    let all_attentions = transformer_output.all_attentions.map(|attentions| {
+                        attentions
+                            .into_iter()
+                            .map(|tensors| {
+                                let num_inner_groups = tensors.len() as f64;
+                                tensors.into_iter().sum::<Tensor>() / num_inner_groups
+                            })
+                            .collect()
+                    });
+                    SentenceEmbeddingsTransformerOutput {
+                        hidden_state,
+                        all_hidden_states,
+                        all_attentions,
+                    }
+                }),
+            Self::T5(transformer) => transformer
+                .forward_with_hidden_states(tokens_ids, tokens_masks)
+                .map(|transformer_output| SentenceEmbeddingsTransformerOutput {
+                    hidden_state: transformer_output.hidden_state,
+                    all_hidden_states: transformer_output.all_hidden_states,
+                    all_attentions: transformer_output.all_attentions,
                 }),
-            Self::T5(transformer) => transformer.forward(tokens_ids, tokens_masks),
         }
     }
 }
@@ -162,12 +197,15 @@ pub struct SentenceEmbeddingsModel {
     sentence_bert_config: SentenceEmbeddingsSentenceBertConfig,
     tokenizer: TokenizerOption,
     tokenizer_truncation_strategy: TruncationStrategy,
+    input_length_policy: InputLengthPolicy,
     var_store: nn::VarStore,
     transformer: SentenceEmbeddingsOption,
     transformer_config: ConfigOption,
     pooling_layer: Pooling,
     dense_layer: Option<Dense>,
     normalize_embeddings: bool,
+    layers: SentenceEmbeddingsLayers,
+    layer_indices: Vec<usize>,
 }
 
 impl SentenceEmbeddingsModel {
@@ -189,6 +227,7 @@ impl SentenceEmbeddingsModel {
             pooling_config_resource,
             dense_config_resource,
             dense_weights_resource,
+            layers,
             device,
         } = config;
 
@@ -224,16 +263,20 @@ impl SentenceEmbeddingsModel {
         // Setup transformer
 
         let mut var_store = nn::VarStore::new(device);
-        let transformer_config = ConfigOption::from_file(
+        let mut transformer_config = ConfigOption::from_file(
             transformer_type,
             transformer_config_resource.get_local_path()?,
-        );
+        )?;
+        transformer_config.validate(Some(&tokenizer), None)?;
+        if layers != SentenceEmbeddingsLayers::Last {
+            Self::enable_hidden_states(&mut transformer_config);
+        }
         let transformer = SentenceEmbeddingsOption::new(
             transformer_type,
             &var_store.root(),
             &transformer_config,
         )?;
-        var_store.load(transformer_weights_resource.get_local_path()?)?;
+        crate::resources::load_weights(&transformer_weights_resource, &mut var_store)?;
 
         // Setup pooling layer
 
@@ -254,18 +297,74 @@ impl SentenceEmbeddingsModel {
             None
         };
 
+        if dense_layer.is_some()
+            && matches!(&layers, SentenceEmbeddingsLayers::Concat(indices) if indices.len() > 1)
+        {
+            return Err(RustBertError::InvalidConfigurationError(
+                "Layers concatenation is not supported for models with a dense layer, as it changes the size of the embeddings".to_string(),
+            ));
+        }
+
         let normalize_embeddings = modules.has_normalization();
 
-        Ok(Self {
+        let mut model = Self {
             tokenizer,
             sentence_bert_config,
             tokenizer_truncation_strategy: TruncationStrategy::LongestFirst,
+            input_length_policy: InputLengthPolicy::Truncate,
             var_store,
             transformer,
             transformer_config,
             pooling_layer,
             dense_layer,
             normalize_embeddings,
+            layers,
+            layer_indices: Vec::new(),
+        };
+        model.layer_indices = model.layers.layer_indices(model.nb_layers())?;
+        Ok(model)
+    }
+
+    fn enable_hidden_states(transformer_config: &mut ConfigOption) {
+        match transformer_config {
+            ConfigOption::Bert(config) | ConfigOption::Roberta(config) => {
+                config.output_hidden_states = Some(true)
+            }
+            ConfigOption::DistilBert(config) => config.output_hidden_states = Some(true),
+            ConfigOption::Albert(config) => config.output_hidden_states = Some(true),
+            ConfigOption::T5(config) => config.output_hidden_states = Some(true),
+            _ => {}
+        }
+    }
+
+    /// Selects and combines the transformer layers outputs to pool
+    fn combine_layers(
+        &self,
+        hidden_state: Tensor,
+        all_hidden_states: Option<Vec<Tensor>>,
+    ) -> Result<Tensor, RustBertError> {
+        if self.layers == SentenceEmbeddingsLayers::Last {
+            return Ok(hidden_state);
+        }
+        let mut layers_outputs = all_hidden_states.ok_or_else(|| {
+            RustBertError::InvalidConfigurationError("No hidden states outputted".into())
+        })?;
+        // The final hidden state includes the final layer normalization of the models applying one
+        layers_outputs.pop();
+        layers_outputs.push(hidden_state);
+
+        let selected_outputs = self
+            .layer_indices
+            .iter()
+            .map(|&index| &layers_outputs[index])
+            .collect::<Vec<&Tensor>>();
+        Ok(match self.layers {
+            SentenceEmbeddingsLayers::Concat(_) => Tensor::cat(&selected_outputs, -1),
+            SentenceEmbeddingsLayers::Mean(_) => {
+                let kind = selected_outputs[0].kind();
+                Tensor::stack(&selected_outputs, 0).mean_dim(&[0], false, kind)
+            }
+            _ => selected_outputs[0].shallow_clone(),
         })
     }
 
@@ -274,6 +373,17 @@ impl SentenceEmbeddingsModel {
         self.tokenizer_truncation_strategy = truncation_strategy;
     }
 
+    /// Sets the handling of the inputs longer than the maximum sequence length of the model by `encode`.
+    ///
+    /// # Arguments
+    ///
+    /// * `input_length_policy` - `InputLengthPolicy` (default: `InputLengthPolicy::Truncate`, following the
+    /// tokenizer's truncation strategy). With `InputLengthPolicy::Chunk`, the embeddings of the chunks of an input
+    /// are averaged.
+    pub fn set_input_length_policy(&mut self, input_length_policy: InputLengthPolicy) {
+        self.input_length_policy = input_length_policy;
+    }
+
     /// Tokenizes the inputs
     pub fn tokenize<S>(&self, inputs: &[S]) -> SentenceEmbeddingsTokenizerOuput
     where
@@ -285,7 +395,13 @@ impl SentenceEmbeddingsModel {
             &self.tokenizer_truncation_strategy,
             0,
         );
+        self.pad_tokenized_input(tokenized_input)
+    }
 
+    fn pad_tokenized_input(
+        &self,
+        tokenized_input: Vec<TokenizedInput>,
+    ) -> SentenceEmbeddingsTokenizerOuput {
         let max_len = tokenized_input
             .iter()
             .map(|input| input.token_ids.len())
@@ -333,15 +449,29 @@ impl SentenceEmbeddingsModel {
     where
         S: AsRef<str> + Sync,
     {
+        self.encode_tokenized(self.tokenize(inputs))
+    }
+
+    fn encode_tokenized(
+        &self,
+        tokenized_input: SentenceEmbeddingsTokenizerOuput,
+    ) -> Result<SentenceEmbeddingsModelOuput, RustBertError> {
         let SentenceEmbeddingsTokenizerOuput {
             tokens_ids,
             tokens_masks,
-        } = self.tokenize(inputs);
+        } = tokenized_input;
         let tokens_ids = Tensor::stack(&tokens_ids, 0).to(self.var_store.device());
         let tokens_masks = Tensor::stack(&tokens_masks, 0).to(self.var_store.device());
 
-        let (tokens_embeddings, all_attentions) =
-            tch::no_grad(|| self.transformer.forward(&tokens_ids, &tokens_masks))?;
+        let SentenceEmbeddingsTransformerOutput {
+            hidden_state,
+            all_hidden_states,
+            all_attentions,
+        } = tch::no_grad(|| {
+            self.transformer
+                .forward_with_hidden_states(&tokens_ids, &tokens_masks)
+        })?;
+        let tokens_embeddings = self.combine_layers(hidden_state, all_hidden_states)?;
 
         let mean_pool =
             tch::no_grad(|| self.pooling_layer.forward(tokens_embeddings, &tokens_masks));
@@ -351,11 +481,7 @@ impl SentenceEmbeddingsModel {
             mean_pool
         };
         let maybe_normalized = if self.normalize_embeddings {
-            let norm = &maybe_linear
-                .norm_scalaropt_dim(2, &[1], true)
-                .clamp_min(1e-12)
-                .expand_as(&maybe_linear);
-            maybe_linear / norm
+            normalize(maybe_linear)
         } else {
             maybe_linear
         };
@@ -366,12 +492,39 @@ impl SentenceEmbeddingsModel {
         })
     }
 
-    /// Computes sentence embeddings.
+    /// Computes sentence embeddings, handling the long inputs following the input length policy of the model.
     pub fn encode<S>(&self, inputs: &[S]) -> Result<Vec<Embedding>, RustBertError>
     where
         S: AsRef<str> + Sync,
     {
-        let SentenceEmbeddingsModelOuput { embeddings, .. } = self.encode_as_tensor(inputs)?;
+        let max_length = self.sentence_bert_config.max_seq_length;
+        let embeddings = match self.input_length_policy {
+            InputLengthPolicy::Truncate => self.encode_as_tensor(inputs)?.embeddings,
+            InputLengthPolicy::Error => {
+                let tokenized_input = self.tokenizer.encode_list(
+                    inputs,
+                    max_length,
+                    &TruncationStrategy::LongestFirst,
+                    0,
+                );
+                check_input_lengths(&tokenized_input, max_length)?;
+                self.encode_tokenized(self.pad_tokenized_input(tokenized_input))?
+                    .embeddings
+            }
+            InputLengthPolicy::Chunk => {
+                let (tokenized_input, num_chunks) =
+                    encode_chunks_list(&self.tokenizer, inputs, max_length);
+                let chunk_embeddings = self
+                    .encode_tokenized(self.pad_tokenized_input(tokenized_input))?
+                    .embeddings;
+                let embeddings = mean_over_chunks(&chunk_embeddings, &num_chunks);
+                if self.normalize_embeddings {
+                    normalize(embeddings)
+                } else {
+                    embeddings
+                }
+            }
+        };
         Ok(Vec::from(embeddings))
     }
 
@@ -382,7 +535,7 @@ impl SentenceEmbeddingsModel {
             (Bert(_), _) => unreachable!(),
             (DistilBert(_), ConfigOption::DistilBert(conf)) => conf.n_layers as usize,
             (DistilBert(_), _) => unreachable!(),
-            (Roberta(_), ConfigOption::Bert(conf)) => conf.num_hidden_layers as usize,
+            (Roberta(_), ConfigOption::Roberta(conf)) => conf.num_hidden_layers as usize,
             (Roberta(_), _) => unreachable!(),
             (Albert(_), ConfigOption::Albert(conf)) => conf.num_hidden_layers as usize,
             (Albert(_), _) => unreachable!(),
@@ -448,14 +601,45 @@ impl SentenceEmbeddingsModel {
     }
 }
 
+/// Normalizes the embeddings (L2 norm)
+fn normalize(embeddings: Tensor) -> Tensor {
+    let norm = &embeddings
+        .norm_scalaropt_dim(2, &[1], true)
+        .clamp_min(1e-12)
+        .expand_as(&embeddings);
+    embeddings / norm
+}
+
 /// Container for the SentenceEmbeddings tokenizer output.
 pub struct SentenceEmbeddingsTokenizerOuput {
     pub tokens_ids: Vec<Tensor>,
     pub tokens_masks: Vec<Tensor>,
 }
 
+/// Container for the output of the transformer of a SentenceEmbeddings model.
+pub struct SentenceEmbeddingsTransformerOutput {
+    pub hidden_state: Tensor,
+    pub all_hidden_states: Option<Vec<Tensor>>,
+    pub all_attentions: Option<Vec<Tensor>>,
+}
+
 /// Container for the SentenceEmbeddings model output.
 pub struct SentenceEmbeddingsModelOuput {
     pub embeddings: Tensor,
     pub all_attentions: Option<Vec<Tensor>>,
 }
+
+impl<S> Pipeline<S, Embedding> for SentenceEmbeddingsModel
+where
+    S: AsRef<str> + Sync,
+{
+    type Options = ();
+
+    fn run_with_options(
+        &self,
+        inputs: &[S],
+        _options: &(),
+    ) -> Result<Vec<Embedding>, RustBertError> {
+        self.encode(inputs)
+    }
+}
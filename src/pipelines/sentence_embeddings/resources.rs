@@ -16,6 +16,7 @@ pub struct SentenceEmbeddingsConfigResources;
 /// # Pretrained tokenizer config files for sentence embeddings
 pub struct SentenceEmbeddingsTokenizerConfigResources;
 
+#[cfg_attr(feature = "uniffi", derive(uniffi::Enum))]
 pub enum SentenceEmbeddingsModelType {
     DistiluseBaseMultilingualCased,
     BertBaseNliMeanTokens,
@@ -55,9 +55,11 @@
 //! ```
 
 use crate::common::error::RustBertError;
+use crate::pipelines::common::{IntermediateOutputs, Pipeline};
 use crate::pipelines::sequence_classification::{
-    SequenceClassificationConfig, SequenceClassificationModel,
+    Label, SequenceClassificationConfig, SequenceClassificationModel,
 };
+use crate::pipelines::shared_encoder::SharedEncoder;
 use serde::{Deserialize, Serialize};
 
 #[derive(Debug, PartialEq, Serialize, Deserialize)]
@@ -107,6 +109,23 @@ impl SentimentModel {
         })
     }
 
+    /// Build a new `SentimentModel` sharing the encoder weights of a `SharedEncoder`
+    ///
+    /// # Arguments
+    ///
+    /// * `sentiment_config` - `SentimentConfig` object containing the resource references (task head weights, vocabulary, configuration) and device placement (CPU/GPU)
+    /// * `shared_encoder` - `SharedEncoder` holding the encoder weights
+    pub fn new_with_shared_encoder(
+        sentiment_config: SentimentConfig,
+        shared_encoder: &SharedEncoder,
+    ) -> Result<SentimentModel, RustBertError> {
+        let sequence_classification_model =
+            SequenceClassificationModel::new_with_shared_encoder(sentiment_config, shared_encoder)?;
+        Ok(SentimentModel {
+            sequence_classification_model,
+        })
+    }
+
     /// Extract sentiment form an array of text inputs
     ///
     /// # Arguments
@@ -139,6 +158,33 @@ impl SentimentModel {
         S: AsRef<[&'a str]>,
     {
         let labels = self.sequence_classification_model.predict(input);
+        Self::get_sentiments(labels)
+    }
+
+    /// Extracts sentiment from an array of text inputs, also returning the hidden states and attention weights of
+    /// all layers of the model for each input (see `SequenceClassificationModel::predict_with_intermediate_outputs`)
+    ///
+    /// # Arguments
+    ///
+    /// * `input` - `&[&str]` Array of texts to extract the sentiment from.
+    ///
+    /// # Returns
+    ///
+    /// * `(Vec<Sentiment>, Vec<IntermediateOutputs>)` containing the sentiments and intermediate outputs for the input texts
+    pub fn predict_with_intermediate_outputs<'a, S>(
+        &self,
+        input: S,
+    ) -> (Vec<Sentiment>, Vec<IntermediateOutputs>)
+    where
+        S: AsRef<[&'a str]>,
+    {
+        let (labels, intermediate_outputs) = self
+            .sequence_classification_model
+            .predict_with_intermediate_outputs(input);
+        (Self::get_sentiments(labels), intermediate_outputs)
+    }
+
+    fn get_sentiments(labels: Vec<Label>) -> Vec<Sentiment> {
         let mut sentiments = Vec::with_capacity(labels.len());
         for label in labels {
             let polarity = if label.id == 1 {
@@ -154,6 +200,26 @@ impl SentimentModel {
         sentiments
     }
 }
+
+impl<S> Pipeline<S, Sentiment> for SentimentModel
+where
+    S: AsRef<str>,
+{
+    type Options = ();
+
+    fn run_with_options(
+        &self,
+        inputs: &[S],
+        _options: &(),
+    ) -> Result<Vec<Sentiment>, RustBertError> {
+        let inputs = inputs
+            .iter()
+            .map(|input| input.as_ref())
+            .collect::<Vec<&str>>();
+        Ok(self.predict(&inputs))
+    }
+}
+
 #[cfg(test)]
 mod test {
     use super::*;
@@ -66,7 +66,16 @@ use crate::distilbert::DistilBertModelClassifier;
 use crate::fnet::FNetForSequenceClassification;
 use crate::longformer::LongformerForSequenceClassification;
 use crate::mobilebert::MobileBertForSequenceClassification;
-use crate::pipelines::common::{ConfigOption, ModelType, TokenizerOption};
+use crate::pipelines::common::{
+    batch_first_xlnet_outputs, flatten_albert_attentions, ConfigOption, IntermediateOutputs,
+    ModelType, Pipeline, TokenizerOption,
+};
+use crate::pipelines::input_length::{
+    check_input_lengths, encode_chunks_list, mean_over_chunks, InputLengthPolicy,
+};
+use crate::pipelines::memory::{MemoryEstimator, MemoryStatistics};
+use crate::pipelines::registry::{get_model_registration, CustomModel};
+use crate::pipelines::shared_encoder::SharedEncoder;
 use crate::reformer::ReformerForSequenceClassification;
 use crate::resources::ResourceProvider;
 use crate::roberta::RobertaForSequenceClassification;
@@ -121,6 +130,10 @@ pub struct SequenceClassificationConfig {
     pub add_prefix_space: Option<bool>,
     /// Device to place the model on (default: CUDA/GPU when available)
     pub device: Device,
+    /// Flag indicating if the hidden states of all layers should be returned by `predict_with_intermediate_outputs` (default: false)
+    pub output_hidden_states: bool,
+    /// Flag indicating if the attention weights of all layers should be returned by `predict_with_intermediate_outputs` (default: false)
+    pub output_attentions: bool,
 }
 
 impl SequenceClassificationConfig {
@@ -157,6 +170,8 @@ impl SequenceClassificationConfig {
             strip_accents: strip_accents.into(),
             add_prefix_space: add_prefix_space.into(),
             device: Device::cuda_if_available(),
+            output_hidden_states: false,
+            output_attentions: false,
         }
     }
 }
@@ -207,6 +222,8 @@ pub enum SequenceClassificationOption {
     Longformer(LongformerForSequenceClassification),
     /// FNet for Sequence Classification
     FNet(FNetForSequenceClassification),
+    /// Custom architecture for Sequence Classification (see `registry::register_model`)
+    Custom(&'static str, Box<dyn CustomModel>),
 }
 
 impl SequenceClassificationOption {
@@ -283,24 +300,24 @@ impl SequenceClassificationOption {
                 }
             }
             ModelType::Roberta => {
-                if let ConfigOption::Bert(config) = config {
+                if let ConfigOption::Roberta(config) = config {
                     Ok(SequenceClassificationOption::Roberta(
                         RobertaForSequenceClassification::new(p, config),
                     ))
                 } else {
                     Err(RustBertError::InvalidConfigurationError(
-                        "You can only supply a BertConfig for Roberta!".to_string(),
+                        "You can only supply a RobertaConfig for Roberta!".to_string(),
                     ))
                 }
             }
             ModelType::XLMRoberta => {
-                if let ConfigOption::Bert(config) = config {
+                if let ConfigOption::Roberta(config) = config {
                     Ok(SequenceClassificationOption::XLMRoberta(
                         RobertaForSequenceClassification::new(p, config),
                     ))
                 } else {
                     Err(RustBertError::InvalidConfigurationError(
-                        "You can only supply a BertConfig for Roberta!".to_string(),
+                        "You can only supply a RobertaConfig for XLMRoberta!".to_string(),
                     ))
                 }
             }
@@ -370,6 +387,19 @@ impl SequenceClassificationOption {
                     ))
                 }
             }
+            ModelType::Custom(name) => {
+                let registration = get_model_registration(model_type)?;
+                match &registration.sequence_classification_builder {
+                    Some(builder) => Ok(SequenceClassificationOption::Custom(
+                        name,
+                        builder(p.borrow(), config)?,
+                    )),
+                    None => Err(RustBertError::InvalidConfigurationError(format!(
+                        "Sequence Classification not implemented for {:?}!",
+                        model_type
+                    ))),
+                }
+            }
             _ => Err(RustBertError::InvalidConfigurationError(format!(
                 "Sequence Classification not implemented for {:?}!",
                 model_type
@@ -393,6 +423,7 @@ impl SequenceClassificationOption {
             Self::Reformer(_) => ModelType::Reformer,
             Self::Longformer(_) => ModelType::Longformer,
             Self::FNet(_) => ModelType::FNet,
+            Self::Custom(name, _) => ModelType::Custom(name),
         }
     }
 
@@ -406,9 +437,37 @@ impl SequenceClassificationOption {
         input_embeds: Option<&Tensor>,
         train: bool,
     ) -> Tensor {
+        self.forward_t_with_intermediate_outputs(
+            input_ids,
+            mask,
+            token_type_ids,
+            position_ids,
+            input_embeds,
+            train,
+        )
+        .0
+    }
+
+    /// Interface method to forward_t() of the particular models, also returning the hidden states and attention
+    /// weights of all layers if the model configuration requests them (`output_hidden_states` and
+    /// `output_attentions`).
+    ///
+    /// # Returns
+    ///
+    /// * `(Tensor, Option<Vec<Tensor>>, Option<Vec<Tensor>>)` - logits, hidden states and attention weights of
+    /// shape (*batch size*, ...). For encoder-decoder models, the decoder hidden states and attentions are returned.
+    pub fn forward_t_with_intermediate_outputs(
+        &self,
+        input_ids: Option<&Tensor>,
+        mask: Option<&Tensor>,
+        token_type_ids: Option<&Tensor>,
+        position_ids: Option<&Tensor>,
+        input_embeds: Option<&Tensor>,
+        train: bool,
+    ) -> (Tensor, Option<Vec<Tensor>>, Option<Vec<Tensor>>) {
         match *self {
             Self::Bart(ref model) => {
-                model
+                let output = model
                     .forward_t(
                         input_ids.expect("`input_ids` must be provided for BART models"),
                         mask,
@@ -417,22 +476,30 @@ impl SequenceClassificationOption {
                         None,
                         train,
                     )
-                    .decoder_output
+                    .expect("Error in BART forward_t");
+                (
+                    output.decoder_output,
+                    output.all_decoder_hidden_states,
+                    output.all_decoder_attentions,
+                )
             }
             Self::Bert(ref model) => {
-                model
-                    .forward_t(
-                        input_ids,
-                        mask,
-                        token_type_ids,
-                        position_ids,
-                        input_embeds,
-                        train,
-                    )
-                    .logits
+                let output = model.forward_t(
+                    input_ids,
+                    mask,
+                    token_type_ids,
+                    position_ids,
+                    input_embeds,
+                    train,
+                );
+                (
+                    output.logits,
+                    output.all_hidden_states,
+                    output.all_attentions,
+                )
             }
             Self::Deberta(ref model) => {
-                model
+                let output = model
                     .forward_t(
                         input_ids,
                         mask,
@@ -441,11 +508,15 @@ impl SequenceClassificationOption {
                         input_embeds,
                         train,
                     )
-                    .expect("Error in Deberta forward_t")
-                    .logits
+                    .expect("Error in Deberta forward_t");
+                (
+                    output.logits,
+                    output.all_hidden_states,
+                    output.all_attentions,
+                )
             }
             Self::DebertaV2(ref model) => {
-                model
+                let output = model
                     .forward_t(
                         input_ids,
                         mask,
@@ -454,85 +525,126 @@ impl SequenceClassificationOption {
                         input_embeds,
                         train,
                     )
-                    .expect("Error in Deberta V2 forward_t")
-                    .logits
+                    .expect("Error in Deberta V2 forward_t");
+                (
+                    output.logits,
+                    output.all_hidden_states,
+                    output.all_attentions,
+                )
             }
             Self::DistilBert(ref model) => {
-                model
+                let output = model
                     .forward_t(input_ids, mask, input_embeds, train)
-                    .expect("Error in distilbert forward_t")
-                    .logits
+                    .expect("Error in distilbert forward_t");
+                (
+                    output.logits,
+                    output.all_hidden_states,
+                    output.all_attentions,
+                )
             }
             Self::MobileBert(ref model) => {
-                model
+                let output = model
                     .forward_t(input_ids, None, None, input_embeds, mask, train)
-                    .expect("Error in mobilebert forward_t")
-                    .logits
+                    .expect("Error in mobilebert forward_t");
+                (
+                    output.logits,
+                    output.all_hidden_states,
+                    output.all_attentions,
+                )
             }
             Self::Roberta(ref model) | Self::XLMRoberta(ref model) => {
-                model
-                    .forward_t(
-                        input_ids,
-                        mask,
-                        token_type_ids,
-                        position_ids,
-                        input_embeds,
-                        train,
-                    )
-                    .logits
+                let output = model.forward_t(
+                    input_ids,
+                    mask,
+                    token_type_ids,
+                    position_ids,
+                    input_embeds,
+                    train,
+                );
+                (
+                    output.logits,
+                    output.all_hidden_states,
+                    output.all_attentions,
+                )
             }
             Self::Albert(ref model) => {
-                model
-                    .forward_t(
-                        input_ids,
-                        mask,
-                        token_type_ids,
-                        position_ids,
-                        input_embeds,
-                        train,
-                    )
-                    .logits
+                let output = model.forward_t(
+                    input_ids,
+                    mask,
+                    token_type_ids,
+                    position_ids,
+                    input_embeds,
+                    train,
+                );
+                (
+                    output.logits,
+                    output.all_hidden_states,
+                    flatten_albert_attentions(output.all_attentions),
+                )
             }
             Self::XLNet(ref model) => {
-                model
+                let output = model.forward_t(
+                    input_ids,
+                    mask,
+                    None,
+                    None,
+                    None,
+                    token_type_ids,
+                    input_embeds,
+                    train,
+                );
+                let (all_hidden_states, all_attentions) =
+                    batch_first_xlnet_outputs(output.all_hidden_states, output.all_attentions);
+                (output.logits, all_hidden_states, all_attentions)
+            }
+            Self::Reformer(ref model) => {
+                let output = model
+                    .forward_t(input_ids, None, None, mask, None, train)
+                    .expect("Error in Reformer forward pass.");
+                (
+                    output.logits,
+                    output.all_hidden_states,
+                    output.all_attentions,
+                )
+            }
+            Self::Longformer(ref model) => {
+                let output = model
                     .forward_t(
                         input_ids,
                         mask,
                         None,
-                        None,
-                        None,
                         token_type_ids,
+                        position_ids,
                         input_embeds,
                         train,
                     )
-                    .logits
+                    .expect("Error in Longformer forward pass.");
+                (
+                    output.logits,
+                    output.all_hidden_states,
+                    output.all_attentions,
+                )
             }
-            Self::Reformer(ref model) => {
-                model
-                    .forward_t(input_ids, None, None, mask, None, train)
-                    .expect("Error in Reformer forward pass.")
-                    .logits
+            Self::FNet(ref model) => {
+                let output = model
+                    .forward_t(input_ids, token_type_ids, position_ids, input_embeds, train)
+                    .expect("Error in FNet forward pass.");
+                (output.logits, output.all_hidden_states, None)
             }
-            Self::Longformer(ref model) => {
+            Self::Custom(_, ref model) => (
                 model
                     .forward_t(
                         input_ids,
                         mask,
-                        None,
                         token_type_ids,
                         position_ids,
                         input_embeds,
                         train,
                     )
-                    .expect("Error in Longformer forward pass.")
-                    .logits
-            }
-            Self::FNet(ref model) => {
-                model
-                    .forward_t(input_ids, token_type_ids, position_ids, input_embeds, train)
-                    .expect("Error in FNet forward pass.")
-                    .logits
-            }
+                    .expect("Error in custom model forward pass."),
+                None,
+                None,
+            ),
         }
     }
 }
@@ -544,6 +656,9 @@ pub struct SequenceClassificationModel {
     label_mapping: HashMap<i64, String>,
     var_store: VarStore,
     max_length: usize,
+    memory_estimator: MemoryEstimator,
+    memory_budget: Option<usize>,
+    input_length_policy: InputLengthPolicy,
 }
 
 impl SequenceClassificationModel {
@@ -565,10 +680,30 @@ impl SequenceClassificationModel {
     /// ```
     pub fn new(
         config: SequenceClassificationConfig,
+    ) -> Result<SequenceClassificationModel, RustBertError> {
+        Self::new_impl(config, None)
+    }
+
+    /// Build a new `SequenceClassificationModel` sharing the encoder weights of a `SharedEncoder`. Only the task
+    /// head weights are read from the model resource of the configuration.
+    ///
+    /// # Arguments
+    ///
+    /// * `config` - `SequenceClassificationConfig` object containing the resource references (task head weights, vocabulary, configuration) and device placement (CPU/GPU)
+    /// * `shared_encoder` - `SharedEncoder` holding the encoder weights. Its model type and device must match the configuration.
+    pub fn new_with_shared_encoder(
+        config: SequenceClassificationConfig,
+        shared_encoder: &SharedEncoder,
+    ) -> Result<SequenceClassificationModel, RustBertError> {
+        Self::new_impl(config, Some(shared_encoder))
+    }
+
+    fn new_impl(
+        config: SequenceClassificationConfig,
+        shared_encoder: Option<&SharedEncoder>,
     ) -> Result<SequenceClassificationModel, RustBertError> {
         let config_path = config.config_resource.get_local_path()?;
         let vocab_path = config.vocab_resource.get_local_path()?;
-        let weights_path = config.model_resource.get_local_path()?;
         let merges_path = if let Some(merges_resource) = &config.merges_resource {
             Some(merges_resource.get_local_path()?)
         } else {
@@ -585,7 +720,10 @@ impl SequenceClassificationModel {
             config.add_prefix_space,
         )?;
         let mut var_store = VarStore::new(device);
-        let model_config = ConfigOption::from_file(config.model_type, config_path);
+        let memory_estimator = MemoryEstimator::from_file(&config_path)?;
+        let mut model_config = ConfigOption::from_file(config.model_type, config_path)?;
+        model_config.set_output_options(config.output_hidden_states, config.output_attentions);
+        model_config.validate(Some(&tokenizer), None)?;
         let max_length = model_config
             .get_max_len()
             .map(|v| v as usize)
@@ -593,17 +731,60 @@ impl SequenceClassificationModel {
         let sequence_classifier =
             SequenceClassificationOption::new(config.model_type, &var_store.root(), &model_config)?;
         let label_mapping = model_config.get_label_mapping().clone();
-        var_store.load(weights_path)?;
+        match shared_encoder {
+            Some(shared_encoder) => shared_encoder.load_into(
+                config.model_type,
+                &mut var_store,
+                &config.model_resource.get_local_path()?,
+            )?,
+            None => crate::resources::load_weights(&config.model_resource, &mut var_store)?,
+        }
         Ok(SequenceClassificationModel {
             tokenizer,
             sequence_classifier,
             label_mapping,
             var_store,
             max_length,
+            memory_estimator,
+            memory_budget: None,
+            input_length_policy: InputLengthPolicy::Truncate,
         })
     }
 
-    fn prepare_for_model<'a, S>(&self, input: S) -> Tensor
+    /// Returns the memory usage statistics of the model for a batch of inputs
+    ///
+    /// # Arguments
+    ///
+    /// * `batch_size` - Number of texts classified at once
+    /// * `sequence_length` - Length (in tokens) of the longest text of the batch
+    pub fn memory_statistics(&self, batch_size: usize, sequence_length: usize) -> MemoryStatistics {
+        self.memory_estimator.estimate(
+            &self.var_store,
+            batch_size,
+            sequence_length.min(self.max_length),
+        )
+    }
+
+    /// Sets the memory budget (in bytes) of the model. Batches predicted to exceed it are rejected by `Pipeline::run`.
+    ///
+    /// # Arguments
+    ///
+    /// * `memory_budget` - Optional memory budget in bytes (default: None, no limit)
+    pub fn set_memory_budget(&mut self, memory_budget: Option<usize>) {
+        self.memory_budget = memory_budget;
+    }
+
+    /// Sets the handling of the inputs longer than the maximum length of the model by `Pipeline::run`.
+    ///
+    /// # Arguments
+    ///
+    /// * `input_length_policy` - `InputLengthPolicy` (default: `InputLengthPolicy::Truncate`). With
+    /// `InputLengthPolicy::Chunk`, the logits of the chunks of an input are averaged.
+    pub fn set_input_length_policy(&mut self, input_length_policy: InputLengthPolicy) {
+        self.input_length_policy = input_length_policy;
+    }
+
+    fn prepare_for_model<'a, S>(&self, input: S) -> (Tensor, Vec<i64>)
     where
         S: AsRef<[&'a str]>,
     {
@@ -613,11 +794,44 @@ impl SequenceClassificationModel {
             &TruncationStrategy::LongestFirst,
             0,
         );
-        let max_len = tokenized_input
+        self.pad_tokenized_input(tokenized_input)
+    }
+
+    /// Prepares the inputs following the input length policy of the model, returning the number of chunks of
+    /// each input for the `InputLengthPolicy::Chunk` policy
+    fn prepare_for_model_with_policy(
+        &self,
+        input: &[&str],
+    ) -> Result<(Tensor, Option<Vec<usize>>), RustBertError> {
+        match self.input_length_policy {
+            InputLengthPolicy::Truncate => Ok((self.prepare_for_model(input).0, None)),
+            InputLengthPolicy::Error => {
+                let tokenized_input = self.tokenizer.encode_list(
+                    input,
+                    self.max_length,
+                    &TruncationStrategy::LongestFirst,
+                    0,
+                );
+                check_input_lengths(&tokenized_input, self.max_length)?;
+                Ok((self.pad_tokenized_input(tokenized_input).0, None))
+            }
+            InputLengthPolicy::Chunk => {
+                let (tokenized_input, num_chunks) =
+                    encode_chunks_list(&self.tokenizer, input, self.max_length);
+                Ok((
+                    self.pad_tokenized_input(tokenized_input).0,
+                    Some(num_chunks),
+                ))
+            }
+        }
+    }
+
+    fn pad_tokenized_input(&self, tokenized_input: Vec<TokenizedInput>) -> (Tensor, Vec<i64>) {
+        let sequence_lengths = tokenized_input
             .iter()
-            .map(|input| input.token_ids.len())
-            .max()
-            .unwrap();
+            .map(|input| input.token_ids.len() as i64)
+            .collect::<Vec<i64>>();
+        let max_len = *sequence_lengths.iter().max().unwrap() as usize;
         let pad_id = self
             .tokenizer
             .get_pad_id()
@@ -629,7 +843,15 @@ impl SequenceClassificationModel {
                 Tensor::of_slice(&(input.token_ids))
             })
             .collect::<Vec<_>>();
-        Tensor::stack(tokenized_input_tensors.as_slice(), 0).to(self.var_store.device())
+        let input_tensor =
+            Tensor::stack(tokenized_input_tensors.as_slice(), 0).to(self.var_store.device());
+        (input_tensor, sequence_lengths)
+    }
+
+    fn check_memory_budget(&self, input_tensor: &Tensor) -> Result<(), RustBertError> {
+        let (batch_size, sequence_length) = input_tensor.size2()?;
+        self.memory_statistics(batch_size as usize, sequence_length as usize)
+            .check_budget(self.memory_budget)
     }
 
     /// Classify texts
@@ -662,18 +884,107 @@ impl SequenceClassificationModel {
     where
         S: AsRef<[&'a str]>,
     {
-        let input_tensor = self.prepare_for_model(input.as_ref());
-        let output = no_grad(|| {
-            let output = self.sequence_classifier.forward_t(
-                Some(&input_tensor),
-                None,
-                None,
-                None,
-                None,
-                false,
-            );
-            output.softmax(-1, Kind::Float).detach().to(Device::Cpu)
+        let (input_tensor, _) = self.prepare_for_model(input.as_ref());
+        self.classify(&input_tensor)
+    }
+
+    /// Classify texts, also returning the hidden states and attention weights of all layers of the model for each
+    /// input, as requested by the `output_hidden_states` and `output_attentions` flags of the configuration.
+    ///
+    /// # Arguments
+    ///
+    /// * `input` - `&[&str]` Array of texts to classify.
+    ///
+    /// # Returns
+    ///
+    /// * `(Vec<Label>, Vec<IntermediateOutputs>)` containing labels and intermediate outputs for input texts
+    ///
+    /// # Example
+    ///
+    /// ```no_run
+    /// # fn main() -> anyhow::Result<()> {
+    /// # use rust_bert::pipelines::sequence_classification::{SequenceClassificationConfig, SequenceClassificationModel};
+    /// let config = SequenceClassificationConfig {
+    ///     output_hidden_states: true,
+    ///     ..Default::default()
+    /// };
+    /// let sequence_classification_model = SequenceClassificationModel::new(config)?;
+    /// let (labels, intermediate_outputs) = sequence_classification_model
+    ///     .predict_with_intermediate_outputs(&["This is a great movie!"]);
+    /// let last_layer_embeddings = intermediate_outputs[0]
+    ///     .all_hidden_states
+    ///     .as_ref()
+    ///     .unwrap()
+    ///     .last();
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn predict_with_intermediate_outputs<'a, S>(
+        &self,
+        input: S,
+    ) -> (Vec<Label>, Vec<IntermediateOutputs>)
+    where
+        S: AsRef<[&'a str]>,
+    {
+        let (input_tensor, sequence_lengths) = self.prepare_for_model(input.as_ref());
+        let (logits, all_hidden_states, all_attentions) = self.forward(&input_tensor);
+        let input_indices = (0..sequence_lengths.len()).collect::<Vec<usize>>();
+        let intermediate_outputs = no_grad(|| {
+            IntermediateOutputs::from_batch(
+                &input_indices,
+                &sequence_lengths,
+                all_hidden_states,
+                all_attentions,
+            )
         });
+        (self.get_labels(&logits), intermediate_outputs)
+    }
+
+    fn forward(&self, input_tensor: &Tensor) -> (Tensor, Option<Vec<Tensor>>, Option<Vec<Tensor>>) {
+        no_grad(|| {
+            self.sequence_classifier
+                .forward_t_with_intermediate_outputs(
+                    Some(input_tensor),
+                    None,
+                    None,
+                    None,
+                    None,
+                    false,
+                )
+        })
+    }
+
+    /// Returns the mapping from class indices to label names of the model
+    pub fn get_label_mapping(&self) -> &HashMap<i64, String> {
+        &self.label_mapping
+    }
+
+    /// Computes the raw classification logits of texts
+    ///
+    /// # Arguments
+    ///
+    /// * `input` - `&[&str]` Array of texts to classify.
+    ///
+    /// # Returns
+    ///
+    /// * `Tensor` of shape (*number of texts*, *number of labels*) on the CPU, the columns following the class indices of the label mapping
+    pub fn predict_logits<'a, S>(&self, input: S) -> Result<Tensor, RustBertError>
+    where
+        S: AsRef<[&'a str]>,
+    {
+        let (input_tensor, _) = self.prepare_for_model(input.as_ref());
+        self.check_memory_budget(&input_tensor)?;
+        let (logits, _, _) = self.forward(&input_tensor);
+        Ok(logits.detach().to(Device::Cpu))
+    }
+
+    fn classify(&self, input_tensor: &Tensor) -> Vec<Label> {
+        let (logits, _, _) = self.forward(input_tensor);
+        self.get_labels(&logits)
+    }
+
+    fn get_labels(&self, logits: &Tensor) -> Vec<Label> {
+        let output = logits.softmax(-1, Kind::Float).detach().to(Device::Cpu);
         let label_indices = output.as_ref().argmax(-1, true).squeeze_dim(1);
         let scores = output
             .gather(1, &label_indices.unsqueeze(-1), false)
@@ -731,7 +1042,8 @@ impl SequenceClassificationModel {
         input: &[&str],
         threshold: f64,
     ) -> Result<Vec<Vec<Label>>, RustBertError> {
-        let input_tensor = self.prepare_for_model(input);
+        let (input_tensor, _) = self.prepare_for_model(input);
+        self.check_memory_budget(&input_tensor)?;
         let output = no_grad(|| {
             let output = self.sequence_classifier.forward_t(
                 Some(&input_tensor),
@@ -776,6 +1088,29 @@ impl SequenceClassificationModel {
     }
 }
 
+impl<S> Pipeline<S, Label> for SequenceClassificationModel
+where
+    S: AsRef<str>,
+{
+    type Options = ();
+
+    fn run_with_options(&self, inputs: &[S], _options: &()) -> Result<Vec<Label>, RustBertError> {
+        let inputs = inputs
+            .iter()
+            .map(|input| input.as_ref())
+            .collect::<Vec<&str>>();
+        let (input_tensor, num_chunks) = self.prepare_for_model_with_policy(&inputs)?;
+        self.check_memory_budget(&input_tensor)?;
+        match num_chunks {
+            Some(num_chunks) => {
+                let (logits, _, _) = self.forward(&input_tensor);
+                Ok(self.get_labels(&mean_over_chunks(&logits, &num_chunks)))
+            }
+            None => Ok(self.classify(&input_tensor)),
+        }
+    }
+}
+
 #[cfg(test)]
 mod test {
     use super::*;
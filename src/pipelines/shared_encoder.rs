@@ -0,0 +1,217 @@
+// Copyright 2022 Guillaume Becquin
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//     http://www.apache.org/licenses/LICENSE-2.0
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! # Encoder weights sharing across pipelines
+//! Loads the weights of a backbone encoder once and shares them between several pipeline instances, each with
+//! its own task head (e.g. sentiment analysis and named entities recognition on top of the same BERT weights).
+//! The pipelines built from a `SharedEncoder` point to the memory of the shared weights instead of holding a copy:
+//! only the task head weights are loaded for each pipeline.
+//!
+//! The heads must have been trained on top of the shared encoder weights (e.g. with a frozen backbone). The task
+//! head weights are read from the `model_resource` of the pipeline configuration: this can be a file containing only
+//! the head variables (e.g. `classifier.weight` and `classifier.bias`) or a full checkpoint, in which case the
+//! encoder variables it contains are ignored.
+//!
+//! ```no_run
+//! # fn main() -> anyhow::Result<()> {
+//! use rust_bert::pipelines::common::ModelType;
+//! use rust_bert::pipelines::ner::NERModel;
+//! use rust_bert::pipelines::sentiment::{SentimentConfig, SentimentModel};
+//! use rust_bert::pipelines::shared_encoder::SharedEncoder;
+//! use rust_bert::pipelines::token_classification::TokenClassificationConfig;
+//! use rust_bert::resources::LocalResource;
+//! use std::path::PathBuf;
+//! use tch::Device;
+//!
+//! let resource = |path: &str| LocalResource {
+//!     local_path: PathBuf::from(path),
+//! };
+//! let shared_encoder = SharedEncoder::new(
+//!     ModelType::Bert,
+//!     resource("path/to/encoder/rust_model.ot"),
+//!     Device::cuda_if_available(),
+//! )?;
+//!
+//! let sentiment_config = SentimentConfig::new(
+//!     ModelType::Bert,
+//!     resource("path/to/sentiment_head.ot"),
+//!     resource("path/to/sentiment/config.json"),
+//!     resource("path/to/encoder/vocab.txt"),
+//!     None,
+//!     false,
+//!     None,
+//!     None,
+//! );
+//! let ner_config = TokenClassificationConfig {
+//!     model_type: ModelType::Bert,
+//!     model_resource: Box::new(resource("path/to/ner_head.ot")),
+//!     config_resource: Box::new(resource("path/to/ner/config.json")),
+//!     vocab_resource: Box::new(resource("path/to/encoder/vocab.txt")),
+//!     ..Default::default()
+//! };
+//!
+//! let sentiment_model = SentimentModel::new_with_shared_encoder(sentiment_config, &shared_encoder)?;
+//! let ner_model = NERModel::new_with_shared_encoder(ner_config, &shared_encoder)?;
+//! # Ok(())
+//! # }
+//! ```
+
+use crate::common::error::RustBertError;
+use crate::pipelines::common::ModelType;
+use crate::resources::ResourceProvider;
+use std::collections::HashMap;
+use std::path::Path;
+use std::sync::Arc;
+use tch::nn::VarStore;
+use tch::{no_grad, Device, Tensor};
+
+/// # Encoder weights shared by several pipelines
+/// Holds the variables of a backbone encoder in a reference-counted `VarStore`. Cloning a `SharedEncoder` is cheap
+/// and does not copy the weights.
+#[derive(Clone)]
+pub struct SharedEncoder {
+    model_type: ModelType,
+    prefix: &'static str,
+    var_store: Arc<VarStore>,
+}
+
+impl SharedEncoder {
+    /// Load the encoder variables of a checkpoint
+    ///
+    /// # Arguments
+    ///
+    /// * `model_type` - `ModelType` of the encoder. Only the variables of the base model (e.g. `bert.*` for BERT) are loaded.
+    /// * `model_resource` - The `ResourceProvider` pointing to the weights to load (e.g. model.ot)
+    /// * `device` - Device to place the encoder weights on. The pipelines sharing the encoder must use the same device.
+    pub fn new<R>(
+        model_type: ModelType,
+        model_resource: R,
+        device: Device,
+    ) -> Result<SharedEncoder, RustBertError>
+    where
+        R: ResourceProvider,
+    {
+        let prefix = Self::get_prefix(model_type)?;
+        let weights_path = model_resource.get_local_path()?;
+
+        let mut var_store = VarStore::new(device);
+        for (name, tensor) in Tensor::load_multi_with_device(&weights_path, Device::Cpu)? {
+            if !Self::is_encoder_variable(prefix, &name) {
+                continue;
+            }
+            // Variables names cannot contain the path separator and are created under nested paths
+            let mut components = name.split('.').collect::<Vec<&str>>();
+            let variable_name = components.pop().unwrap();
+            let path = components
+                .into_iter()
+                .fold(var_store.root(), |path, component| path.sub(component));
+            path.var_copy(variable_name, &tensor);
+        }
+        if var_store.variables().is_empty() {
+            return Err(RustBertError::InvalidConfigurationError(format!(
+                "No encoder variable (prefixed by `{}.`) found in {:?}",
+                prefix, weights_path
+            )));
+        }
+        var_store.freeze();
+
+        Ok(SharedEncoder {
+            model_type,
+            prefix,
+            var_store: Arc::new(var_store),
+        })
+    }
+
+    /// Returns the `ModelType` of the shared encoder
+    pub fn model_type(&self) -> ModelType {
+        self.model_type
+    }
+
+    /// Returns the device the shared encoder weights are placed on
+    pub fn device(&self) -> Device {
+        self.var_store.device()
+    }
+
+    fn get_prefix(model_type: ModelType) -> Result<&'static str, RustBertError> {
+        match model_type {
+            ModelType::Bert => Ok("bert"),
+            ModelType::Roberta | ModelType::XLMRoberta => Ok("roberta"),
+            ModelType::DistilBert => Ok("distilbert"),
+            ModelType::Albert => Ok("albert"),
+            ModelType::Deberta | ModelType::DebertaV2 => Ok("deberta"),
+            ModelType::MobileBert => Ok("mobilebert"),
+            ModelType::Electra => Ok("electra"),
+            ModelType::Longformer => Ok("longformer"),
+            ModelType::FNet => Ok("fnet"),
+            ModelType::XLNet => Ok("transformer"),
+            _ => Err(RustBertError::InvalidConfigurationError(format!(
+                "Encoder sharing not implemented for {:?}!",
+                model_type
+            ))),
+        }
+    }
+
+    fn is_encoder_variable(prefix: &str, name: &str) -> bool {
+        name.strip_prefix(prefix)
+            .map_or(false, |suffix| suffix.starts_with('.'))
+    }
+
+    /// Sets the variables of a pipeline `VarStore`: the encoder variables point to the shared weights, and the
+    /// remaining (task head) variables are loaded from `head_weights_path`.
+    pub(crate) fn load_into(
+        &self,
+        model_type: ModelType,
+        var_store: &mut VarStore,
+        head_weights_path: &Path,
+    ) -> Result<(), RustBertError> {
+        if model_type != self.model_type {
+            return Err(RustBertError::InvalidConfigurationError(format!(
+                "The pipeline model type ({:?}) does not match the shared encoder model type ({:?})",
+                model_type, self.model_type
+            )));
+        }
+        if var_store.device() != self.device() {
+            return Err(RustBertError::InvalidConfigurationError(format!(
+                "The pipeline device ({:?}) does not match the shared encoder device ({:?})",
+                var_store.device(),
+                self.device()
+            )));
+        }
+
+        let shared_variables = self.var_store.variables();
+        let head_variables = Tensor::load_multi_with_device(head_weights_path, Device::Cpu)?
+            .into_iter()
+            .filter(|(name, _)| !Self::is_encoder_variable(self.prefix, name))
+            .collect::<HashMap<String, Tensor>>();
+
+        for (name, mut variable) in var_store.variables() {
+            if let Some(shared_variable) = shared_variables.get(&name) {
+                if variable.size() != shared_variable.size() {
+                    return Err(RustBertError::ValueError(format!(
+                        "Shape mismatch for variable {}: expected {:?}, got {:?} in the shared encoder",
+                        name,
+                        variable.size(),
+                        shared_variable.size()
+                    )));
+                }
+                no_grad(|| variable.set_data(shared_variable));
+            } else if let Some(head_variable) = head_variables.get(&name) {
+                no_grad(|| variable.f_copy_(head_variable))?;
+            } else {
+                return Err(RustBertError::ValueError(format!(
+                    "Variable {} not found in the shared encoder or head weights",
+                    name
+                )));
+            }
+        }
+        Ok(())
+    }
+}
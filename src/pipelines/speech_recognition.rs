@@ -0,0 +1,717 @@
+// Copyright 2022 The OpenAI Authors and The HuggingFace Inc. team. All rights reserved.
+// Copyright 2022 Guillaume Becquin
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//     http://www.apache.org/licenses/LICENSE-2.0
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! # Speech recognition pipeline
+//! Transcribes (or translates to English) 16kHz mono audio with a Whisper model. The audio is converted to a log-mel
+//! spectrogram and processed by windows of 30 seconds. The text of each window is generated with a greedy decoding
+//! following the Whisper rules: special tokens are suppressed, and when timestamps are requested they are generated in
+//! increasing pairs delimiting the segments of speech.
+//!
+//! Long audio is transcribed sequentially: when timestamps are enabled and the last segment of a window is cut, the
+//! next window starts at the end of the last complete segment, otherwise the audio is split in consecutive
+//! 30 seconds windows.
+//!
+//! No pre-trained checkpoint is hosted with the crate, the Whisper weights can be converted with the Python utility
+//! scripts and loaded as local resources:
+//!
+//! ```no_run
+//! # fn main() -> anyhow::Result<()> {
+//! use rust_bert::pipelines::speech_recognition::{
+//!     SpeechRecognitionConfig, SpeechRecognitionModel,
+//! };
+//! use rust_bert::resources::LocalResource;
+//! use std::path::PathBuf;
+//!
+//! let config = SpeechRecognitionConfig::new(
+//!     LocalResource::from(PathBuf::from("path/to/rust_model.ot")),
+//!     LocalResource::from(PathBuf::from("path/to/config.json")),
+//!     LocalResource::from(PathBuf::from("path/to/vocab.json")),
+//!     LocalResource::from(PathBuf::from("path/to/merges.txt")),
+//! )
+//! .with_language("en")
+//! .with_timestamps(true);
+//! let model = SpeechRecognitionModel::new(config)?;
+//!
+//! // 16kHz mono samples in [-1, 1]
+//! let audio: Vec<f32> = vec![0f32; 16000 * 45];
+//! let output = model.transcribe(&[audio])?;
+//! for segment in &output[0].segments {
+//!     println!("[{:.2} - {:.2}]{}", segment.start, segment.end, segment.text);
+//! }
+//! # Ok(())
+//! # }
+//! ```
+
+use crate::common::error::RustBertError;
+use crate::resources::ResourceProvider;
+use crate::whisper::{
+    WhisperConfig, WhisperFeatureExtractor, WhisperForConditionalGeneration, WhisperSpecialTokens,
+    WHISPER_CHUNK_SAMPLES, WHISPER_SAMPLING_RATE,
+};
+use crate::Config;
+use rust_tokenizers::tokenizer::{Gpt2Tokenizer, Tokenizer};
+use serde::{Deserialize, Serialize};
+use tch::nn::VarStore;
+use tch::{no_grad, Device, Kind, Tensor};
+
+/// Maximum time (in seconds) of the first timestamp of a window
+const MAX_INITIAL_TIMESTAMP: f64 = 1.0;
+
+/// # Task performed by the speech recognition model
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum SpeechRecognitionTask {
+    /// Transcription in the language of the audio
+    Transcribe,
+    /// Translation to English
+    Translate,
+}
+
+/// # Configuration for speech recognition
+/// Contains information regarding the Whisper checkpoint, the language of the audio and the decoding options.
+pub struct SpeechRecognitionConfig {
+    /// Model weights resource
+    pub model_resource: Box<dyn ResourceProvider + Send>,
+    /// Config resource
+    pub config_resource: Box<dyn ResourceProvider + Send>,
+    /// Vocab resource (`vocab.json`)
+    pub vocab_resource: Box<dyn ResourceProvider + Send>,
+    /// Merges resource (`merges.txt`)
+    pub merges_resource: Box<dyn ResourceProvider + Send>,
+    /// Language code of the audio (e.g. `en`). Detected for every window if not provided (default: None)
+    pub language: Option<String>,
+    /// Task performed by the model (default: transcription)
+    pub task: SpeechRecognitionTask,
+    /// Flag indicating if the segments should be delimited with timestamps (default: false)
+    pub return_timestamps: bool,
+    /// Maximum number of tokens generated per window (default: half of the decoder maximum positions)
+    pub max_new_tokens: Option<i64>,
+    /// Device to place the model on (default: CUDA/GPU when available)
+    pub device: Device,
+}
+
+impl SpeechRecognitionConfig {
+    /// Instantiate a new speech recognition configuration with default decoding options
+    ///
+    /// # Arguments
+    ///
+    /// * `model_resource` - The `ResourceProvider` pointing to the model weights
+    /// * `config_resource` - The `ResourceProvider` pointing to the model configuration
+    /// * `vocab_resource` - The `ResourceProvider` pointing to the tokenizer vocabulary (`vocab.json`)
+    /// * `merges_resource` - The `ResourceProvider` pointing to the tokenizer merges (`merges.txt`)
+    pub fn new<R>(
+        model_resource: R,
+        config_resource: R,
+        vocab_resource: R,
+        merges_resource: R,
+    ) -> SpeechRecognitionConfig
+    where
+        R: ResourceProvider + Send + 'static,
+    {
+        SpeechRecognitionConfig {
+            model_resource: Box::new(model_resource),
+            config_resource: Box::new(config_resource),
+            vocab_resource: Box::new(vocab_resource),
+            merges_resource: Box::new(merges_resource),
+            language: None,
+            task: SpeechRecognitionTask::Transcribe,
+            return_timestamps: false,
+            max_new_tokens: None,
+            device: Device::cuda_if_available(),
+        }
+    }
+
+    /// Sets the language of the audio, skipping the language detection
+    pub fn with_language(mut self, language: &str) -> Self {
+        self.language = Some(language.to_string());
+        self
+    }
+
+    /// Sets the task performed by the model
+    pub fn with_task(mut self, task: SpeechRecognitionTask) -> Self {
+        self.task = task;
+        self
+    }
+
+    /// Enables or disables the prediction of segment timestamps
+    pub fn with_timestamps(mut self, return_timestamps: bool) -> Self {
+        self.return_timestamps = return_timestamps;
+        self
+    }
+}
+
+/// # Segment of a transcription
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct TranscriptionSegment {
+    /// Start of the segment (in seconds from the start of the audio)
+    pub start: f64,
+    /// End of the segment (in seconds from the start of the audio)
+    pub end: f64,
+    /// Text of the segment
+    pub text: String,
+}
+
+/// # Transcription of an audio input
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Transcription {
+    /// Full text of the transcription
+    pub text: String,
+    /// Language of the audio (provided in the configuration or detected on the first window), None for
+    /// English-only models
+    pub language: Option<String>,
+    /// Segments of the transcription. Without timestamps, a segment is returned for each 30 seconds window.
+    pub segments: Vec<TranscriptionSegment>,
+}
+
+/// Converts 16-bit PCM samples to floating point samples in [-1, 1]
+pub fn pcm_to_f32(samples: &[i16]) -> Vec<f32> {
+    samples
+        .iter()
+        .map(|sample| *sample as f32 / 32768.0)
+        .collect()
+}
+
+/// Segment of the tokens generated for a window, delimited by timestamp tokens
+#[derive(Debug, PartialEq)]
+struct WindowSegment {
+    start: Option<f64>,
+    end: Option<f64>,
+    tokens: Vec<i64>,
+}
+
+/// Splits the tokens generated for a window into segments: a timestamp token opens a segment, the next one closes it
+fn split_segments(tokens: &[i64], special_tokens: &WhisperSpecialTokens) -> Vec<WindowSegment> {
+    let mut segments = vec![];
+    let mut current: Option<WindowSegment> = None;
+    for token in tokens {
+        match (special_tokens.timestamp(*token), current.take()) {
+            (Some(time), None) => {
+                current = Some(WindowSegment {
+                    start: Some(time),
+                    end: None,
+                    tokens: vec![],
+                })
+            }
+            (Some(time), Some(mut segment)) => {
+                segment.end = Some(time);
+                segments.push(segment);
+            }
+            (None, segment) => {
+                let mut segment = segment.unwrap_or(WindowSegment {
+                    start: None,
+                    end: None,
+                    tokens: vec![],
+                });
+                segment.tokens.push(*token);
+                current = Some(segment);
+            }
+        }
+    }
+    segments.extend(current);
+    segments
+}
+
+/// Masks the logits of the tokens violating the Whisper timestamp rules:
+/// - timestamps come in pairs (the end of a segment, directly followed by the start of the next one), except before
+/// the end of text token
+/// - timestamps are non-decreasing
+/// - the first token is a timestamp, of at most `MAX_INITIAL_TIMESTAMP`
+/// - if the total probability of the timestamps exceeds the probability of any text token, a timestamp is generated
+fn apply_timestamp_rules(
+    logits: &mut Tensor,
+    generated: &[i64],
+    special_tokens: &WhisperSpecialTokens,
+) {
+    let timestamp_begin = special_tokens.timestamp_begin;
+    let vocab_size = logits.size()[0];
+    let mask = |start: i64, end: i64| {
+        if start < end {
+            let _ = logits.slice(0, start, end, 1).fill_(f64::NEG_INFINITY);
+        }
+    };
+
+    let last_was_timestamp = generated
+        .last()
+        .map_or(false, |token| *token >= timestamp_begin);
+    let penultimate_was_timestamp =
+        generated.len() < 2 || generated[generated.len() - 2] >= timestamp_begin;
+    if last_was_timestamp {
+        if penultimate_was_timestamp {
+            mask(timestamp_begin, vocab_size);
+        } else {
+            mask(0, special_tokens.end_of_text);
+        }
+    }
+
+    if let Some(last_timestamp) = generated
+        .iter()
+        .rev()
+        .find(|token| **token >= timestamp_begin)
+    {
+        let min_timestamp = if last_was_timestamp && !penultimate_was_timestamp {
+            *last_timestamp
+        } else {
+            *last_timestamp + 1
+        };
+        mask(timestamp_begin, min_timestamp);
+    }
+
+    if generated.is_empty() {
+        mask(0, timestamp_begin);
+        mask(
+            timestamp_begin + (MAX_INITIAL_TIMESTAMP / 0.02).round() as i64 + 1,
+            vocab_size,
+        );
+    }
+
+    let log_probabilities = logits.log_softmax(-1, Kind::Float);
+    let timestamp_log_probability = f64::from(
+        log_probabilities
+            .slice(0, timestamp_begin, vocab_size, 1)
+            .logsumexp(&[0], false),
+    );
+    let max_text_log_probability =
+        f64::from(log_probabilities.slice(0, 0, timestamp_begin, 1).max());
+    if timestamp_log_probability > max_text_log_probability {
+        mask(0, timestamp_begin);
+    }
+}
+
+/// # Speech recognition model
+/// Whisper model with its tokenizer and feature extractor, transcribing audio of arbitrary length
+pub struct SpeechRecognitionModel {
+    model: WhisperForConditionalGeneration,
+    tokenizer: Gpt2Tokenizer,
+    feature_extractor: WhisperFeatureExtractor,
+    special_tokens: WhisperSpecialTokens,
+    language_token: Option<i64>,
+    task: SpeechRecognitionTask,
+    return_timestamps: bool,
+    max_new_tokens: i64,
+    suppress_tokens: Vec<i64>,
+    begin_suppress_tokens: Vec<i64>,
+    var_store: VarStore,
+}
+
+impl SpeechRecognitionModel {
+    /// Build a new `SpeechRecognitionModel`
+    ///
+    /// # Arguments
+    ///
+    /// * `config` - `SpeechRecognitionConfig` object containing the resource references and decoding options
+    ///
+    /// # Example
+    ///
+    /// ```no_run
+    /// # fn main() -> anyhow::Result<()> {
+    /// use rust_bert::pipelines::speech_recognition::{
+    ///     SpeechRecognitionConfig, SpeechRecognitionModel, SpeechRecognitionTask,
+    /// };
+    /// use rust_bert::resources::LocalResource;
+    /// use std::path::PathBuf;
+    ///
+    /// let config = SpeechRecognitionConfig::new(
+    ///     LocalResource::from(PathBuf::from("path/to/rust_model.ot")),
+    ///     LocalResource::from(PathBuf::from("path/to/config.json")),
+    ///     LocalResource::from(PathBuf::from("path/to/vocab.json")),
+    ///     LocalResource::from(PathBuf::from("path/to/merges.txt")),
+    /// )
+    /// .with_language("fr")
+    /// .with_task(SpeechRecognitionTask::Translate);
+    /// let model = SpeechRecognitionModel::new(config)?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn new(config: SpeechRecognitionConfig) -> Result<SpeechRecognitionModel, RustBertError> {
+        let config_path = config.config_resource.get_local_path()?;
+        let vocab_path = config.vocab_resource.get_local_path()?;
+        let merges_path = config.merges_resource.get_local_path()?;
+        let device = config.device;
+
+        let tokenizer = Gpt2Tokenizer::from_file(
+            vocab_path.to_str().unwrap(),
+            merges_path.to_str().unwrap(),
+            false,
+        )?;
+        let model_config = WhisperConfig::from_file(config_path);
+        let special_tokens = WhisperSpecialTokens::from_config(&model_config);
+
+        let language_token = match config.language.as_deref() {
+            Some(language) if special_tokens.is_multilingual() => {
+                Some(special_tokens.language_token(language)?)
+            }
+            Some("en") | None => None,
+            Some(language) => {
+                return Err(RustBertError::InvalidConfigurationError(format!(
+                    "English-only Whisper models cannot transcribe language {}",
+                    language
+                )));
+            }
+        };
+        if config.task == SpeechRecognitionTask::Translate && !special_tokens.is_multilingual() {
+            return Err(RustBertError::InvalidConfigurationError(
+                "English-only Whisper models cannot translate".into(),
+            ));
+        }
+
+        // Leave room for the decoder prompt (start of transcript, language, task and timestamps tokens)
+        let max_new_tokens = config
+            .max_new_tokens
+            .unwrap_or(model_config.max_target_positions / 2)
+            .min(model_config.max_target_positions - 4);
+
+        let mut var_store = VarStore::new(device);
+        let model = WhisperForConditionalGeneration::new(&var_store.root(), &model_config);
+        crate::resources::load_weights(&config.model_resource, &mut var_store)?;
+
+        Ok(SpeechRecognitionModel {
+            model,
+            tokenizer,
+            feature_extractor: WhisperFeatureExtractor::new(model_config.num_mel_bins, device),
+            special_tokens,
+            language_token,
+            task: config.task,
+            return_timestamps: config.return_timestamps,
+            max_new_tokens,
+            suppress_tokens: model_config.suppress_tokens.unwrap_or_default(),
+            begin_suppress_tokens: model_config.begin_suppress_tokens.unwrap_or_default(),
+            var_store,
+        })
+    }
+
+    /// Transcribes a batch of audio inputs
+    ///
+    /// # Arguments
+    ///
+    /// * `audio` - Slice of 16kHz mono audio inputs with samples in [-1, 1] (see `pcm_to_f32` for 16-bit PCM audio)
+    ///
+    /// # Returns
+    ///
+    /// * `Vec<Transcription>` containing the text, language and segments of each input
+    ///
+    /// # Example
+    ///
+    /// ```no_run
+    /// # fn main() -> anyhow::Result<()> {
+    /// # use rust_bert::pipelines::speech_recognition::{pcm_to_f32, SpeechRecognitionConfig, SpeechRecognitionModel};
+    /// # use rust_bert::resources::LocalResource;
+    /// # use std::path::PathBuf;
+    /// # let config = SpeechRecognitionConfig::new(
+    /// #     LocalResource::from(PathBuf::from("path/to/rust_model.ot")),
+    /// #     LocalResource::from(PathBuf::from("path/to/config.json")),
+    /// #     LocalResource::from(PathBuf::from("path/to/vocab.json")),
+    /// #     LocalResource::from(PathBuf::from("path/to/merges.txt")),
+    /// # );
+    /// let model = SpeechRecognitionModel::new(config)?;
+    /// let pcm: Vec<i16> = vec![0; 16000 * 10];
+    /// let output = model.transcribe(&[pcm_to_f32(&pcm)])?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn transcribe<S>(&self, audio: &[S]) -> Result<Vec<Transcription>, RustBertError>
+    where
+        S: AsRef<[f32]>,
+    {
+        audio
+            .iter()
+            .map(|samples| no_grad(|| self.transcribe_single(samples.as_ref())))
+            .collect()
+    }
+
+    fn transcribe_single(&self, audio: &[f32]) -> Result<Transcription, RustBertError> {
+        let mut segments = vec![];
+        let mut language = None;
+        let mut seek = 0;
+        while seek < audio.len() {
+            let window = &audio[seek..audio.len().min(seek + WHISPER_CHUNK_SAMPLES)];
+            let window_start = seek as f64 / WHISPER_SAMPLING_RATE as f64;
+            let window_duration = window.len() as f64 / WHISPER_SAMPLING_RATE as f64;
+
+            let input_features = self.feature_extractor.extract(&[window])?;
+            let encoder_output = self.model.encode(&input_features)?;
+
+            let language_token = match self.language_token {
+                Some(language_token) => Some(language_token),
+                None if self.special_tokens.is_multilingual() => {
+                    Some(self.detect_language(&encoder_output)?)
+                }
+                None => None,
+            };
+            if language.is_none() {
+                language = language_token
+                    .and_then(|token| self.special_tokens.language_code(token))
+                    .map(String::from);
+            }
+
+            let generated = self.generate(&encoder_output, &self.prompt(language_token))?;
+            let mut window_segments = split_segments(&generated, &self.special_tokens);
+
+            // A cut final segment is decoded again in the next window, starting after the last complete segment
+            let last_complete_end = window_segments
+                .iter()
+                .rev()
+                .find_map(|segment| segment.end)
+                .unwrap_or(0.0);
+            let cut_final_segment = window_segments.last().map_or(false, |segment| {
+                segment.end.is_none() && !segment.tokens.is_empty()
+            });
+            let consumed_samples =
+                if self.return_timestamps && cut_final_segment && last_complete_end > 0.0 {
+                    window_segments.pop();
+                    (last_complete_end * WHISPER_SAMPLING_RATE as f64) as usize
+                } else {
+                    window.len()
+                };
+
+            for segment in window_segments {
+                let text = self.tokenizer.decode(&segment.tokens, true, false);
+                if text.trim().is_empty() {
+                    continue;
+                }
+                segments.push(TranscriptionSegment {
+                    start: window_start + segment.start.unwrap_or(0.0),
+                    end: window_start + segment.end.unwrap_or(window_duration),
+                    text,
+                });
+            }
+            seek += consumed_samples.min(window.len()).max(1);
+        }
+
+        let text = segments
+            .iter()
+            .map(|segment| segment.text.as_str())
+            .collect::<String>()
+            .trim()
+            .to_string();
+        Ok(Transcription {
+            text,
+            language,
+            segments,
+        })
+    }
+
+    fn prompt(&self, language_token: Option<i64>) -> Vec<i64> {
+        let mut prompt = vec![self.special_tokens.start_of_transcript];
+        if let Some(language_token) = language_token {
+            prompt.push(language_token);
+            prompt.push(match self.task {
+                SpeechRecognitionTask::Transcribe => self.special_tokens.transcribe,
+                SpeechRecognitionTask::Translate => self.special_tokens.translate,
+            });
+        }
+        if !self.return_timestamps {
+            prompt.push(self.special_tokens.no_timestamps);
+        }
+        prompt
+    }
+
+    /// Returns the most likely language token after the start of transcript token
+    fn detect_language(&self, encoder_output: &Tensor) -> Result<i64, RustBertError> {
+        let decoder_input_ids = Tensor::of_slice(&[self.special_tokens.start_of_transcript])
+            .view([1, 1])
+            .to(self.var_store.device());
+        let logits = self
+            .model
+            .forward_t(None, &decoder_input_ids, Some(encoder_output), None, false)?
+            .decoder_output
+            .get(0)
+            .get(-1);
+        let first_language = self.special_tokens.start_of_transcript + 1;
+        let language_logits = logits.slice(
+            0,
+            first_language,
+            first_language + self.special_tokens.num_languages,
+            1,
+        );
+        Ok(first_language + i64::from(language_logits.argmax(0, false)))
+    }
+
+    /// Greedy decoding of a window, returning the generated tokens (without the prompt and end of text token)
+    fn generate(&self, encoder_output: &Tensor, prompt: &[i64]) -> Result<Vec<i64>, RustBertError> {
+        let device = self.var_store.device();
+        let special_tokens = &self.special_tokens;
+        let mut generated = vec![];
+        let mut decoder_input_ids = Tensor::of_slice(prompt).unsqueeze(0).to(device);
+        let mut cache = None;
+        for _ in 0..self.max_new_tokens {
+            let output = self.model.forward_t(
+                None,
+                &decoder_input_ids,
+                Some(encoder_output),
+                cache,
+                false,
+            )?;
+            cache = output.cache;
+            let mut logits = output.decoder_output.get(0).get(-1).to_kind(Kind::Float);
+
+            // Special tokens other than the end of text and timestamps are never generated
+            let _ = logits
+                .slice(
+                    0,
+                    special_tokens.end_of_text + 1,
+                    special_tokens.timestamp_begin,
+                    1,
+                )
+                .fill_(f64::NEG_INFINITY);
+            if !self.suppress_tokens.is_empty() {
+                let _ = logits.index_fill_(
+                    0,
+                    &Tensor::of_slice(&self.suppress_tokens).to(device),
+                    f64::NEG_INFINITY,
+                );
+            }
+            if generated.is_empty() && !self.begin_suppress_tokens.is_empty() {
+                let _ = logits.index_fill_(
+                    0,
+                    &Tensor::of_slice(&self.begin_suppress_tokens).to(device),
+                    f64::NEG_INFINITY,
+                );
+            }
+            if self.return_timestamps {
+                apply_timestamp_rules(&mut logits, &generated, special_tokens);
+            } else {
+                let vocab_size = logits.size()[0];
+                let _ = logits
+                    .slice(0, special_tokens.timestamp_begin, vocab_size, 1)
+                    .fill_(f64::NEG_INFINITY);
+            }
+
+            let next_token = i64::from(logits.argmax(0, false));
+            if next_token == special_tokens.end_of_text {
+                break;
+            }
+            generated.push(next_token);
+            decoder_input_ids = Tensor::of_slice(&[next_token]).view([1, 1]).to(device);
+        }
+        Ok(generated)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn special_tokens() -> WhisperSpecialTokens {
+        WhisperSpecialTokens::from_config(&WhisperConfig::default())
+    }
+
+    fn allowed(logits: &Tensor, token: i64) -> bool {
+        f64::from(logits.get(token)).is_finite()
+    }
+
+    #[test]
+    fn timestamp_rules() {
+        let special_tokens = special_tokens();
+        let timestamp_begin = special_tokens.timestamp_begin;
+        let vocab_size = WhisperConfig::default().vocab_size;
+
+        // The first token is a timestamp of at most 1 second
+        let mut logits = Tensor::zeros(&[vocab_size], (Kind::Float, Device::Cpu));
+        apply_timestamp_rules(&mut logits, &[], &special_tokens);
+        assert!(!allowed(&logits, 100));
+        assert!(allowed(&logits, timestamp_begin));
+        assert!(allowed(&logits, timestamp_begin + 50));
+        assert!(!allowed(&logits, timestamp_begin + 51));
+
+        // Timestamps cannot decrease
+        let mut logits = Tensor::zeros(&[vocab_size], (Kind::Float, Device::Cpu));
+        logits.get(100).fill_(100.0);
+        apply_timestamp_rules(&mut logits, &[timestamp_begin + 10, 100], &special_tokens);
+        assert!(allowed(&logits, 100));
+        assert!(!allowed(&logits, timestamp_begin + 10));
+        assert!(allowed(&logits, timestamp_begin + 11));
+
+        // A single timestamp after text closes the segment: the next token is a timestamp or the end of text
+        let mut logits = Tensor::zeros(&[vocab_size], (Kind::Float, Device::Cpu));
+        let generated = [timestamp_begin, 100, timestamp_begin + 20];
+        apply_timestamp_rules(&mut logits, &generated, &special_tokens);
+        assert!(!allowed(&logits, 100));
+        assert!(allowed(&logits, special_tokens.end_of_text));
+        assert!(!allowed(&logits, timestamp_begin + 19));
+        assert!(allowed(&logits, timestamp_begin + 20));
+
+        // Two consecutive timestamps are followed by text
+        let mut logits = Tensor::zeros(&[vocab_size], (Kind::Float, Device::Cpu));
+        logits.get(100).fill_(100.0);
+        let generated = [
+            timestamp_begin,
+            100,
+            timestamp_begin + 20,
+            timestamp_begin + 20,
+        ];
+        apply_timestamp_rules(&mut logits, &generated, &special_tokens);
+        assert!(allowed(&logits, 100));
+        assert!(!allowed(&logits, timestamp_begin + 30));
+    }
+
+    #[test]
+    fn timestamps_forced_when_more_likely() {
+        let special_tokens = special_tokens();
+        let timestamp_begin = special_tokens.timestamp_begin;
+        let vocab_size = WhisperConfig::default().vocab_size;
+
+        // The probability mass of the 1501 timestamps exceeds the probability of the most likely text token
+        let mut logits = Tensor::zeros(&[vocab_size], (Kind::Float, Device::Cpu));
+        logits.get(100).fill_(5.0);
+        apply_timestamp_rules(&mut logits, &[timestamp_begin, 100], &special_tokens);
+        assert!(!allowed(&logits, 100));
+        assert!(allowed(&logits, timestamp_begin + 1));
+    }
+
+    #[test]
+    fn window_segments() {
+        let special_tokens = special_tokens();
+        let timestamp = |seconds: f64| special_tokens.timestamp_begin + (seconds / 0.02) as i64;
+
+        let tokens = [
+            timestamp(0.0),
+            10,
+            11,
+            timestamp(2.0),
+            timestamp(2.0),
+            12,
+            timestamp(4.5),
+            timestamp(5.0),
+            13,
+        ];
+        let segments = split_segments(&tokens, &special_tokens);
+        assert_eq!(segments.len(), 3);
+        assert_eq!(
+            segments[0],
+            WindowSegment {
+                start: Some(0.0),
+                end: Some(2.0),
+                tokens: vec![10, 11],
+            }
+        );
+        assert_eq!(segments[1].start, Some(2.0));
+        assert_eq!(segments[1].end, Some(4.5));
+        assert_eq!(segments[2].start, Some(5.0));
+        assert_eq!(segments[2].end, None);
+        assert_eq!(segments[2].tokens, vec![13]);
+
+        // Without timestamps, the window forms a single segment
+        let segments = split_segments(&[10, 11, 12], &special_tokens);
+        assert_eq!(
+            segments,
+            vec![WindowSegment {
+                start: None,
+                end: None,
+                tokens: vec![10, 11, 12],
+            }]
+        );
+    }
+
+    #[test]
+    fn pcm_conversion() {
+        assert_eq!(pcm_to_f32(&[0, 16384, -32768]), vec![0.0, 0.5, -1.0]);
+    }
+}
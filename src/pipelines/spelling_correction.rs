@@ -0,0 +1,1272 @@
+// Copyright 2022 Guillaume Becquin
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//     http://www.apache.org/licenses/LICENSE-2.0
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! # Spelling correction pipeline
+//! Lightweight typo correction combining a candidate generator with masked language model rescoring:
+//! 1. Words missing from the vocabulary are flagged as misspelled.
+//! 2. Candidate corrections are generated from the vocabulary words within a maximum (Damerau-Levenshtein) edit
+//! distance of the misspelled word.
+//! 3. Each candidate is scored by a masked language model (BERT, DistilBERT or RoBERTa) in the context of the
+//! sentence, with the misspelled word replaced by as many mask tokens as the candidate has sub-tokens. The
+//! log-likelihood of the candidate is penalized by its edit distance, and the confidence of a correction is the
+//! softmax of these scores over the candidates.
+//!
+//! The vocabulary defaults to the whole-word entries of the masked language model tokenizer. Word-piece vocabularies
+//! only contain the most frequent words of a language: providing a proper word list (`word_list`) avoids flagging
+//! rare but correctly spelled words.
+//!
+//! An optional sequence-to-sequence corrector (BART or T5 fine-tuned for spelling or grammar correction) can be
+//! added. Its corrections are aligned with the input words and added to the candidates, allowing to correct
+//! real-word errors (e.g. "their" for "there") that are not detected by the vocabulary lookup. These proposals are
+//! scored against the original word by the masked language model and only kept if they score higher.
+//!
+//! ```no_run
+//! # fn main() -> anyhow::Result<()> {
+//! use rust_bert::pipelines::spelling_correction::SpellingCorrectionModel;
+//!
+//! let model = SpellingCorrectionModel::new(Default::default())?;
+//!
+//! let input = ["The weather is realy nice todya."];
+//! let output = model.correct(&input)?;
+//! # Ok(())
+//! # }
+//! ```
+//!
+//! Example output:
+//! ```no_run
+//! # use rust_bert::pipelines::spelling_correction::{
+//! #     CorrectionSource, SpellingCorrection, SpellingCorrectionOutput,
+//! # };
+//! # use rust_tokenizers::Offset;
+//! # let output =
+//! [SpellingCorrectionOutput {
+//!     text: "The weather is really nice today.".to_string(),
+//!     corrections: vec![
+//!         SpellingCorrection {
+//!             original: "realy".to_string(),
+//!             correction: "really".to_string(),
+//!             offset: Offset { begin: 15, end: 20 },
+//!             confidence: 0.9873,
+//!             edit_distance: 1,
+//!             source: CorrectionSource::Vocabulary,
+//!         },
+//!         SpellingCorrection {
+//!             original: "todya".to_string(),
+//!             correction: "today".to_string(),
+//!             offset: Offset { begin: 26, end: 31 },
+//!             confidence: 0.9951,
+//!             edit_distance: 1,
+//!             source: CorrectionSource::Vocabulary,
+//!         },
+//!     ],
+//! }]
+//! # ;
+//! ```
+
+use crate::bart::BartGenerator;
+use crate::bert::BertForMaskedLM;
+use crate::common::error::RustBertError;
+use crate::distilbert::DistilBertModelMaskedLM;
+use crate::pipelines::common::{ConfigOption, ModelType, Pipeline, TokenizerOption};
+use crate::pipelines::generation_utils::{GenerateConfig, LanguageGenerator};
+use crate::resources::ResourceProvider;
+use crate::roberta::RobertaForMaskedLM;
+use crate::t5::T5Generator;
+use rust_tokenizers::{Mask, Offset, TokenIdsWithOffsets};
+use serde::{Deserialize, Serialize};
+use std::borrow::Borrow;
+use std::collections::{HashMap, HashSet};
+use tch::nn::VarStore;
+use tch::{nn, no_grad, Device, Kind, Tensor};
+
+#[cfg(feature = "remote")]
+use crate::{
+    distilbert::{DistilBertConfigResources, DistilBertModelResources, DistilBertVocabResources},
+    resources::RemoteResource,
+};
+
+/// # Origin of a spelling correction
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum CorrectionSource {
+    /// Out-of-vocabulary word corrected with a vocabulary candidate
+    Vocabulary,
+    /// Correction proposed by the sequence-to-sequence corrector
+    Seq2Seq,
+}
+
+/// # Correction of a single word
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct SpellingCorrection {
+    /// Original (misspelled) word
+    pub original: String,
+    /// Corrected word
+    pub correction: String,
+    /// Offsets of the original word (in characters of the input string)
+    pub offset: Offset,
+    /// Confidence of the correction (probability of the selected candidate among all candidates for this word)
+    pub confidence: f64,
+    /// Edit distance between the original and the corrected word
+    pub edit_distance: usize,
+    /// Origin of the correction
+    pub source: CorrectionSource,
+}
+
+/// # Output of the spelling correction pipeline for one input
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct SpellingCorrectionOutput {
+    /// Input text with all corrections applied
+    pub text: String,
+    /// Corrections applied, in order of appearance in the input
+    pub corrections: Vec<SpellingCorrection>,
+}
+
+/// Returns the (optimal string alignment) Damerau-Levenshtein distance between two strings, counting insertions,
+/// deletions, substitutions and transpositions of adjacent characters.
+///
+/// # Arguments
+///
+/// * `source` - First string
+/// * `target` - Second string
+///
+/// # Example
+///
+/// ```
+/// use rust_bert::pipelines::spelling_correction::edit_distance;
+///
+/// assert_eq!(edit_distance("todya", "today"), 1);
+/// assert_eq!(edit_distance("recieve", "receive"), 1);
+/// assert_eq!(edit_distance("kitten", "sitting"), 3);
+/// ```
+pub fn edit_distance(source: &str, target: &str) -> usize {
+    let source = source.chars().collect::<Vec<char>>();
+    let target = target.chars().collect::<Vec<char>>();
+    let width = target.len() + 1;
+    let mut distances = vec![0usize; (source.len() + 1) * width];
+    for i in 0..=source.len() {
+        distances[i * width] = i;
+    }
+    for (j, distance) in distances.iter_mut().enumerate().take(width) {
+        *distance = j;
+    }
+    for i in 1..=source.len() {
+        for j in 1..=target.len() {
+            let cost = usize::from(source[i - 1] != target[j - 1]);
+            let mut distance = (distances[(i - 1) * width + j] + 1)
+                .min(distances[i * width + j - 1] + 1)
+                .min(distances[(i - 1) * width + j - 1] + cost);
+            if i > 1 && j > 1 && source[i - 1] == target[j - 2] && source[i - 2] == target[j - 1] {
+                distance = distance.min(distances[(i - 2) * width + j - 2] + 1);
+            }
+            distances[i * width + j] = distance;
+        }
+    }
+    distances[source.len() * width + target.len()]
+}
+
+/// # Vocabulary of correctly spelled words
+/// Words are stored lower-cased and indexed by length to prune the candidate search.
+#[derive(Debug, Clone, Default)]
+pub struct SpellingVocabulary {
+    words: HashSet<String>,
+    words_by_length: HashMap<usize, Vec<String>>,
+}
+
+impl SpellingVocabulary {
+    /// Builds a vocabulary from a word list. Entries that are not purely alphabetic are ignored.
+    ///
+    /// # Arguments
+    ///
+    /// * `words` - Correctly spelled words
+    pub fn from_words<S: AsRef<str>>(words: &[S]) -> SpellingVocabulary {
+        let mut vocabulary = SpellingVocabulary::default();
+        for word in words {
+            vocabulary.insert(word.as_ref());
+        }
+        vocabulary
+    }
+
+    /// Builds a vocabulary from the whole-word entries of a tokenizer vocabulary. For word-piece vocabularies,
+    /// continuation pieces (prefixed with `##`) are skipped. For byte-level BPE and SentencePiece vocabularies, only
+    /// the pieces starting a word (prefixed with `Ġ` or `▁`) are kept.
+    ///
+    /// # Arguments
+    ///
+    /// * `tokenizer` - `TokenizerOption` whose vocabulary is used
+    pub fn from_tokenizer(tokenizer: &TokenizerOption) -> SpellingVocabulary {
+        let tokens = tokenizer.get_vocab_indices().values();
+        let word_piece = tokenizer
+            .get_vocab_indices()
+            .values()
+            .any(|token| token.starts_with("##"));
+        let mut vocabulary = SpellingVocabulary::default();
+        for token in tokens {
+            if word_piece {
+                if !token.starts_with("##") {
+                    vocabulary.insert(token);
+                }
+            } else if let Some(word) = token.strip_prefix('Ġ').or_else(|| token.strip_prefix('▁'))
+            {
+                vocabulary.insert(word);
+            }
+        }
+        vocabulary
+    }
+
+    fn insert(&mut self, word: &str) {
+        if word.is_empty() || !word.chars().all(char::is_alphabetic) {
+            return;
+        }
+        let word = word.to_lowercase();
+        if self.words.insert(word.clone()) {
+            self.words_by_length
+                .entry(word.chars().count())
+                .or_insert_with(Vec::new)
+                .push(word);
+        }
+    }
+
+    /// Returns the number of words in the vocabulary
+    pub fn len(&self) -> usize {
+        self.words.len()
+    }
+
+    /// Returns true if the vocabulary contains no words
+    pub fn is_empty(&self) -> bool {
+        self.words.is_empty()
+    }
+
+    /// Returns true if the vocabulary contains the word (case-insensitive)
+    pub fn contains(&self, word: &str) -> bool {
+        self.words.contains(&word.to_lowercase())
+    }
+
+    /// Returns the vocabulary words within a maximum edit distance of a word, excluding the word itself.
+    ///
+    /// # Arguments
+    ///
+    /// * `word` - Word to find candidates for
+    /// * `max_distance` - Maximum edit distance of the candidates
+    ///
+    /// # Returns
+    ///
+    /// * `Vec<(String, usize)>` - Lower-cased candidates and their edit distance, sorted by increasing distance
+    pub fn candidates(&self, word: &str, max_distance: usize) -> Vec<(String, usize)> {
+        let word = word.to_lowercase();
+        let length = word.chars().count();
+        let mut candidates = Vec::new();
+        for candidate_length in length.saturating_sub(max_distance)..=length + max_distance {
+            if let Some(words) = self.words_by_length.get(&candidate_length) {
+                for candidate in words {
+                    let distance = edit_distance(&word, candidate);
+                    if distance > 0 && distance <= max_distance {
+                        candidates.push((candidate.clone(), distance));
+                    }
+                }
+            }
+        }
+        candidates.sort_by(|a, b| a.1.cmp(&b.1).then_with(|| a.0.cmp(&b.0)));
+        candidates
+    }
+}
+
+/// Applies the casing pattern of the original word (all capitals or capitalized) to a correction.
+fn match_case(original: &str, correction: &str) -> String {
+    let mut original_chars = original.chars();
+    match original_chars.next() {
+        Some(first) if first.is_uppercase() => {
+            if original.chars().count() > 1 && original_chars.all(|c| !c.is_lowercase()) {
+                correction.to_uppercase()
+            } else {
+                let mut correction_chars = correction.chars();
+                match correction_chars.next() {
+                    Some(c) => c.to_uppercase().chain(correction_chars).collect(),
+                    None => String::new(),
+                }
+            }
+        }
+        _ => correction.to_string(),
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct WordSpan {
+    text: String,
+    offset: Offset,
+    byte_begin: usize,
+    byte_end: usize,
+}
+
+/// Splits a text into words (maximal runs of alphabetic characters).
+fn split_words(text: &str) -> Vec<WordSpan> {
+    let mut words = Vec::new();
+    let mut current: Option<(usize, usize)> = None;
+    let mut char_count = 0;
+    for (char_index, (byte_index, character)) in text.char_indices().enumerate() {
+        if character.is_alphabetic() {
+            if current.is_none() {
+                current = Some((char_index, byte_index));
+            }
+        } else if let Some((char_begin, byte_begin)) = current.take() {
+            words.push(WordSpan {
+                text: text[byte_begin..byte_index].to_string(),
+                offset: Offset::new(char_begin as u32, char_index as u32),
+                byte_begin,
+                byte_end: byte_index,
+            });
+        }
+        char_count = char_index + 1;
+    }
+    if let Some((char_begin, byte_begin)) = current {
+        words.push(WordSpan {
+            text: text[byte_begin..].to_string(),
+            offset: Offset::new(char_begin as u32, char_count as u32),
+            byte_begin,
+            byte_end: text.len(),
+        });
+    }
+    words
+}
+
+/// Aligns the words of a corrected sequence with the words of its source, returning the (source, target) index
+/// pairs of the substituted words. Words are matched case-insensitively with a longest common subsequence, and
+/// the unmatched words between two matches are paired one-to-one when both sides have the same number of words
+/// (insertions and deletions are not considered spelling corrections).
+fn align_words<S: AsRef<str>>(source: &[S], target: &[S]) -> Vec<(usize, usize)> {
+    let source = source
+        .iter()
+        .map(|word| word.as_ref().to_lowercase())
+        .collect::<Vec<String>>();
+    let target = target
+        .iter()
+        .map(|word| word.as_ref().to_lowercase())
+        .collect::<Vec<String>>();
+    let (n, m) = (source.len(), target.len());
+    let mut lcs = vec![vec![0usize; m + 1]; n + 1];
+    for i in (0..n).rev() {
+        for j in (0..m).rev() {
+            lcs[i][j] = if source[i] == target[j] {
+                lcs[i + 1][j + 1] + 1
+            } else {
+                lcs[i + 1][j].max(lcs[i][j + 1])
+            };
+        }
+    }
+
+    let mut pairs = Vec::new();
+    let mut flush_gap = |source_gap: (usize, usize), target_gap: (usize, usize)| {
+        if source_gap.1 - source_gap.0 == target_gap.1 - target_gap.0 {
+            for k in 0..source_gap.1 - source_gap.0 {
+                pairs.push((source_gap.0 + k, target_gap.0 + k));
+            }
+        }
+    };
+    let (mut i, mut j) = (0, 0);
+    let (mut gap_i, mut gap_j) = (0, 0);
+    loop {
+        if i < n && j < m && source[i] == target[j] {
+            flush_gap((gap_i, i), (gap_j, j));
+            i += 1;
+            j += 1;
+            gap_i = i;
+            gap_j = j;
+        } else if i < n && (j == m || lcs[i + 1][j] >= lcs[i][j + 1]) {
+            i += 1;
+        } else if j < m {
+            j += 1;
+        } else {
+            flush_gap((gap_i, i), (gap_j, j));
+            break;
+        }
+    }
+    pairs
+}
+
+/// # Configuration for the optional sequence-to-sequence corrector
+pub struct SpellingSeq2SeqConfig {
+    /// Model type of the corrector (BART or T5)
+    pub model_type: ModelType,
+    /// Generation configuration, containing the resources of the corrector
+    pub generate_config: GenerateConfig,
+    /// Optional prefix prepended to the inputs (e.g. a task prefix for T5 models, default: None)
+    pub prefix: Option<String>,
+}
+
+impl SpellingSeq2SeqConfig {
+    /// Instantiate a new sequence-to-sequence corrector configuration
+    ///
+    /// # Arguments
+    ///
+    /// * `model_type` - `ModelType` of the corrector (BART or T5)
+    /// * `generate_config` - `GenerateConfig` containing the resources of the corrector and the decoding settings
+    pub fn new(model_type: ModelType, generate_config: GenerateConfig) -> SpellingSeq2SeqConfig {
+        SpellingSeq2SeqConfig {
+            model_type,
+            generate_config,
+            prefix: None,
+        }
+    }
+}
+
+/// # Configuration for SpellingCorrectionModel
+/// Contains information regarding the masked language model to load, the vocabulary and the scoring settings.
+pub struct SpellingCorrectionConfig {
+    /// Masked language model type (BERT, DistilBERT, RoBERTa or XLM-RoBERTa)
+    pub model_type: ModelType,
+    /// Model weights resource (default: pretrained DistilBERT model)
+    pub model_resource: Box<dyn ResourceProvider + Send>,
+    /// Config resource (default: pretrained DistilBERT model)
+    pub config_resource: Box<dyn ResourceProvider + Send>,
+    /// Vocab resource (default: pretrained DistilBERT model)
+    pub vocab_resource: Box<dyn ResourceProvider + Send>,
+    /// Merges resource (default: None)
+    pub merges_resource: Option<Box<dyn ResourceProvider + Send>>,
+    /// Automatically lower case all input upon tokenization (assumes a lower-cased model)
+    pub lower_case: bool,
+    /// Flag indicating if the tokenizer should strip accents (normalization). Only used for BERT / ALBERT models
+    pub strip_accents: Option<bool>,
+    /// Flag indicating if the tokenizer should add a white space before each tokenized input (needed for some Roberta models)
+    pub add_prefix_space: Option<bool>,
+    /// Device to place the model on (default: CUDA/GPU when available)
+    pub device: Device,
+    /// Optional list of correctly spelled words (default: None, the whole words of the tokenizer vocabulary are used)
+    pub word_list: Option<Vec<String>>,
+    /// Maximum edit distance of the vocabulary candidates (default: 2)
+    pub max_edit_distance: usize,
+    /// Maximum number of vocabulary candidates scored per misspelled word (default: 20)
+    pub max_candidates: usize,
+    /// Penalty per edit subtracted from the masked language model log-likelihood of a candidate (default: 2.0)
+    pub distance_penalty: f64,
+    /// Corrections with a confidence below this threshold are not applied (default: 0.5)
+    pub min_confidence: f64,
+    /// Number of masked sequences scored at once (default: 32)
+    pub batch_size: usize,
+    /// Optional sequence-to-sequence corrector (default: None)
+    pub seq2seq_config: Option<SpellingSeq2SeqConfig>,
+}
+
+impl SpellingCorrectionConfig {
+    /// Instantiate a new spelling correction configuration of the supplied type.
+    ///
+    /// # Arguments
+    ///
+    /// * `model_type` - `ModelType` indicating the masked language model type to load (must match with the actual data to be loaded!)
+    /// * model - The `ResourceProvider` pointing to the model to load (e.g.  model.ot)
+    /// * config - The `ResourceProvider` pointing to the model configuration to load (e.g. config.json)
+    /// * vocab - The `ResourceProvider` pointing to the tokenizer's vocabulary to load (e.g.  vocab.txt/vocab.json)
+    /// * merges - An optional `ResourceProvider` pointing to the tokenizer's merge file to load (e.g.  merges.txt), needed only for Roberta.
+    /// * lower_case - A `bool` indicating whether the tokenizer should lower case all input (in case of a lower-cased model)
+    pub fn new<R>(
+        model_type: ModelType,
+        model_resource: R,
+        config_resource: R,
+        vocab_resource: R,
+        merges_resource: Option<R>,
+        lower_case: bool,
+        strip_accents: impl Into<Option<bool>>,
+        add_prefix_space: impl Into<Option<bool>>,
+    ) -> SpellingCorrectionConfig
+    where
+        R: ResourceProvider + Send + 'static,
+    {
+        SpellingCorrectionConfig {
+            model_type,
+            model_resource: Box::new(model_resource),
+            config_resource: Box::new(config_resource),
+            vocab_resource: Box::new(vocab_resource),
+            merges_resource: merges_resource.map(|r| Box::new(r) as Box<_>),
+            lower_case,
+            strip_accents: strip_accents.into(),
+            add_prefix_space: add_prefix_space.into(),
+            device: Device::cuda_if_available(),
+            word_list: None,
+            max_edit_distance: 2,
+            max_candidates: 20,
+            distance_penalty: 2.0,
+            min_confidence: 0.5,
+            batch_size: 32,
+            seq2seq_config: None,
+        }
+    }
+}
+
+#[cfg(feature = "remote")]
+impl Default for SpellingCorrectionConfig {
+    /// Provides a DistilBERT (uncased) masked language model
+    fn default() -> SpellingCorrectionConfig {
+        SpellingCorrectionConfig::new(
+            ModelType::DistilBert,
+            RemoteResource::from_pretrained(DistilBertModelResources::DISTIL_BERT),
+            RemoteResource::from_pretrained(DistilBertConfigResources::DISTIL_BERT),
+            RemoteResource::from_pretrained(DistilBertVocabResources::DISTIL_BERT),
+            None,
+            true,
+            None,
+            None,
+        )
+    }
+}
+
+#[allow(clippy::large_enum_variant)]
+/// # Abstraction that holds one particular masked language model, for any of the supported models
+pub enum MaskedLanguageOption {
+    /// Bert for Masked Language Modeling
+    Bert(BertForMaskedLM),
+    /// DistilBert for Masked Language Modeling
+    DistilBert(DistilBertModelMaskedLM),
+    /// Roberta for Masked Language Modeling
+    Roberta(RobertaForMaskedLM),
+    /// XLMRoberta for Masked Language Modeling
+    XLMRoberta(RobertaForMaskedLM),
+}
+
+impl MaskedLanguageOption {
+    /// Instantiate a new masked language model of the supplied type.
+    ///
+    /// # Arguments
+    ///
+    /// * `model_type` - `ModelType` indicating the model type to load (must match with the actual data to be loaded)
+    /// * `p` - `tch::nn::Path` path to the model file to load (e.g. model.ot)
+    /// * `config` - A configuration (the model type of the configuration must be compatible with the value for
+    /// `model_type`)
+    pub fn new<'p, P>(
+        model_type: ModelType,
+        p: P,
+        config: &ConfigOption,
+    ) -> Result<Self, RustBertError>
+    where
+        P: Borrow<nn::Path<'p>>,
+    {
+        match (model_type, config) {
+            (ModelType::Bert, ConfigOption::Bert(config)) => {
+                Ok(MaskedLanguageOption::Bert(BertForMaskedLM::new(p, config)))
+            }
+            (ModelType::DistilBert, ConfigOption::DistilBert(config)) => Ok(
+                MaskedLanguageOption::DistilBert(DistilBertModelMaskedLM::new(p, config)),
+            ),
+            (ModelType::Roberta, ConfigOption::Bert(config)) => Ok(MaskedLanguageOption::Roberta(
+                RobertaForMaskedLM::new(p, config),
+            )),
+            (ModelType::XLMRoberta, ConfigOption::Bert(config)) => Ok(
+                MaskedLanguageOption::XLMRoberta(RobertaForMaskedLM::new(p, config)),
+            ),
+            (ModelType::Bert, _) | (ModelType::Roberta, _) | (ModelType::XLMRoberta, _) => {
+                Err(RustBertError::InvalidConfigurationError(format!(
+                    "You can only supply a BertConfig for {:?}!",
+                    model_type
+                )))
+            }
+            (ModelType::DistilBert, _) => Err(RustBertError::InvalidConfigurationError(
+                "You can only supply a DistilBertConfig for DistilBert!".to_string(),
+            )),
+            _ => Err(RustBertError::InvalidConfigurationError(format!(
+                "Spelling correction not implemented for {:?}!",
+                model_type
+            ))),
+        }
+    }
+
+    /// Returns the `ModelType` for this MaskedLanguageOption
+    pub fn model_type(&self) -> ModelType {
+        match *self {
+            Self::Bert(_) => ModelType::Bert,
+            Self::DistilBert(_) => ModelType::DistilBert,
+            Self::Roberta(_) => ModelType::Roberta,
+            Self::XLMRoberta(_) => ModelType::XLMRoberta,
+        }
+    }
+
+    /// Returns the mask token of the tokenizer associated with this model type
+    pub fn mask_token(&self) -> &'static str {
+        match *self {
+            Self::Bert(_) | Self::DistilBert(_) => "[MASK]",
+            Self::Roberta(_) | Self::XLMRoberta(_) => "<mask>",
+        }
+    }
+
+    /// Interface method to forward_t() of the particular models, returning the prediction scores of shape
+    /// (*batch size*, *sequence_length*, *vocab_size*)
+    pub fn forward_t(
+        &self,
+        input_ids: &Tensor,
+        mask: &Tensor,
+        train: bool,
+    ) -> Result<Tensor, RustBertError> {
+        Ok(match *self {
+            Self::Bert(ref model) => {
+                model
+                    .forward_t(
+                        Some(input_ids),
+                        Some(mask),
+                        None,
+                        None,
+                        None,
+                        None,
+                        None,
+                        train,
+                    )
+                    .prediction_scores
+            }
+            Self::DistilBert(ref model) => {
+                model
+                    .forward_t(Some(input_ids), Some(mask), None, train)?
+                    .prediction_scores
+            }
+            Self::Roberta(ref model) | Self::XLMRoberta(ref model) => {
+                model
+                    .forward_t(
+                        Some(input_ids),
+                        Some(mask),
+                        None,
+                        None,
+                        None,
+                        None,
+                        None,
+                        train,
+                    )
+                    .prediction_scores
+            }
+        })
+    }
+}
+
+/// # Abstraction that holds one particular sequence-to-sequence corrector, for any of the supported models
+pub enum SpellingSeq2SeqOption {
+    /// Corrector based on BART model
+    Bart(BartGenerator),
+    /// Corrector based on T5 model
+    T5(T5Generator),
+}
+
+impl SpellingSeq2SeqOption {
+    pub fn new(
+        model_type: ModelType,
+        generate_config: GenerateConfig,
+    ) -> Result<SpellingSeq2SeqOption, RustBertError> {
+        match model_type {
+            ModelType::Bart => Ok(SpellingSeq2SeqOption::Bart(BartGenerator::new(
+                generate_config,
+            )?)),
+            ModelType::T5 => Ok(SpellingSeq2SeqOption::T5(T5Generator::new(
+                generate_config,
+            )?)),
+            ModelType::ByT5 => Ok(SpellingSeq2SeqOption::T5(T5Generator::new_byt5(
+                generate_config,
+            )?)),
+            _ => Err(RustBertError::InvalidConfigurationError(format!(
+                "Sequence-to-sequence spelling correction not implemented for {:?}!",
+                model_type
+            ))),
+        }
+    }
+
+    /// Returns the `ModelType` for this SpellingSeq2SeqOption
+    pub fn model_type(&self) -> ModelType {
+        match *self {
+            Self::Bart(_) => ModelType::Bart,
+            Self::T5(ref model) => model.get_tokenizer().model_type(),
+        }
+    }
+
+    /// Interface method to generate() of the particular models.
+    pub fn generate<S>(&self, prompt_texts: Option<&[S]>) -> Vec<String>
+    where
+        S: AsRef<str> + Sync,
+    {
+        match *self {
+            Self::Bart(ref model) => model
+                .generate(prompt_texts, None)
+                .into_iter()
+                .map(|output| output.text)
+                .collect(),
+            Self::T5(ref model) => model
+                .generate(prompt_texts, None)
+                .into_iter()
+                .map(|output| output.text)
+                .collect(),
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+struct Candidate {
+    word: String,
+    distance: usize,
+    source: CorrectionSource,
+}
+
+/// Misspelled (or possibly misspelled) word and its candidate corrections
+#[derive(Debug, Clone)]
+struct Proposal {
+    text_index: usize,
+    word: WordSpan,
+    candidates: Vec<Candidate>,
+    /// The original word is part of the candidates and may be kept
+    keep_original: bool,
+}
+
+/// Masked sequence scoring the candidates of a proposal having the same number of sub-tokens
+struct MaskedInput {
+    proposal_index: usize,
+    token_ids: Vec<i64>,
+    mask_position: usize,
+    candidates: Vec<(usize, Vec<i64>)>,
+}
+
+/// # SpellingCorrectionModel to detect and correct misspelled words
+pub struct SpellingCorrectionModel {
+    tokenizer: TokenizerOption,
+    masked_lm: MaskedLanguageOption,
+    seq2seq: Option<(SpellingSeq2SeqOption, Option<String>)>,
+    vocabulary: SpellingVocabulary,
+    mask_id: i64,
+    max_length: usize,
+    max_edit_distance: usize,
+    max_candidates: usize,
+    distance_penalty: f64,
+    min_confidence: f64,
+    batch_size: usize,
+    var_store: VarStore,
+}
+
+impl SpellingCorrectionModel {
+    /// Build a new `SpellingCorrectionModel`
+    ///
+    /// # Arguments
+    ///
+    /// * `config` - `SpellingCorrectionConfig` object containing the resource references (model, vocabulary, configuration), scoring settings and device placement (CPU/GPU)
+    ///
+    /// # Example
+    ///
+    /// ```no_run
+    /// # fn main() -> anyhow::Result<()> {
+    /// use rust_bert::pipelines::spelling_correction::SpellingCorrectionModel;
+    ///
+    /// let model = SpellingCorrectionModel::new(Default::default())?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn new(config: SpellingCorrectionConfig) -> Result<SpellingCorrectionModel, RustBertError> {
+        let config_path = config.config_resource.get_local_path()?;
+        let vocab_path = config.vocab_resource.get_local_path()?;
+        let merges_path = if let Some(merges_resource) = &config.merges_resource {
+            Some(merges_resource.get_local_path()?)
+        } else {
+            None
+        };
+        let device = config.device;
+
+        let tokenizer = TokenizerOption::from_file(
+            config.model_type,
+            vocab_path.to_str().unwrap(),
+            merges_path.as_deref().map(|path| path.to_str().unwrap()),
+            config.lower_case,
+            config.strip_accents,
+            config.add_prefix_space,
+        )?;
+        let mut var_store = VarStore::new(device);
+        let model_config = ConfigOption::from_file(config.model_type, config_path)?;
+        model_config.validate(Some(&tokenizer), None)?;
+        let max_length = model_config
+            .get_max_len()
+            .map(|v| v as usize)
+            .unwrap_or(usize::MAX);
+        let masked_lm =
+            MaskedLanguageOption::new(config.model_type, &var_store.root(), &model_config)?;
+        crate::resources::load_weights(&config.model_resource, &mut var_store)?;
+
+        let mask_id = tokenizer.convert_tokens_to_ids(&[masked_lm.mask_token()])[0];
+        let vocabulary = match &config.word_list {
+            Some(word_list) => SpellingVocabulary::from_words(word_list),
+            None => SpellingVocabulary::from_tokenizer(&tokenizer),
+        };
+        let seq2seq = match config.seq2seq_config {
+            Some(seq2seq_config) => Some((
+                SpellingSeq2SeqOption::new(
+                    seq2seq_config.model_type,
+                    seq2seq_config.generate_config,
+                )?,
+                seq2seq_config.prefix,
+            )),
+            None => None,
+        };
+
+        Ok(SpellingCorrectionModel {
+            tokenizer,
+            masked_lm,
+            seq2seq,
+            vocabulary,
+            mask_id,
+            max_length,
+            max_edit_distance: config.max_edit_distance,
+            max_candidates: config.max_candidates,
+            distance_penalty: config.distance_penalty,
+            min_confidence: config.min_confidence,
+            batch_size: config.batch_size.max(1),
+            var_store,
+        })
+    }
+
+    /// Returns the vocabulary used to detect misspelled words
+    pub fn get_vocabulary(&self) -> &SpellingVocabulary {
+        &self.vocabulary
+    }
+
+    /// Corrects the spelling of texts
+    ///
+    /// # Arguments
+    ///
+    /// * `input` - `&[&str]` Array of texts to correct.
+    ///
+    /// # Returns
+    ///
+    /// * `Vec<SpellingCorrectionOutput>` containing the corrected texts and the corrections applied, with their confidence
+    ///
+    /// # Example
+    ///
+    /// ```no_run
+    /// # fn main() -> anyhow::Result<()> {
+    /// # use rust_bert::pipelines::spelling_correction::SpellingCorrectionModel;
+    /// let model = SpellingCorrectionModel::new(Default::default())?;
+    ///
+    /// let input = [
+    ///     "I will recieve the package tomorow.",
+    ///     "The commitee approved the proposal.",
+    /// ];
+    /// let output = model.correct(&input)?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn correct<S>(&self, input: &[S]) -> Result<Vec<SpellingCorrectionOutput>, RustBertError>
+    where
+        S: AsRef<str>,
+    {
+        let words = input
+            .iter()
+            .map(|text| split_words(text.as_ref()))
+            .collect::<Vec<Vec<WordSpan>>>();
+        let proposals = self.build_proposals(input, &words);
+        let masked_inputs = proposals
+            .iter()
+            .enumerate()
+            .flat_map(|(proposal_index, proposal)| {
+                self.build_masked_inputs(
+                    proposal_index,
+                    proposal,
+                    input[proposal.text_index].as_ref(),
+                )
+            })
+            .collect::<Vec<MaskedInput>>();
+        let scores = self.score_candidates(&proposals, &masked_inputs)?;
+
+        let mut corrections: Vec<Vec<SpellingCorrection>> = vec![vec![]; input.len()];
+        for (proposal, proposal_scores) in proposals.iter().zip(scores.iter()) {
+            if let Some(correction) = self.select_correction(proposal, proposal_scores) {
+                corrections[proposal.text_index].push(correction);
+            }
+        }
+
+        Ok(input
+            .iter()
+            .zip(words.iter())
+            .zip(corrections.into_iter())
+            .map(|((text, words), mut corrections)| {
+                corrections.sort_by_key(|correction| correction.offset.begin);
+                let mut corrected_text = text.as_ref().to_string();
+                for correction in corrections.iter().rev() {
+                    let word = words
+                        .iter()
+                        .find(|word| word.offset == correction.offset)
+                        .unwrap();
+                    corrected_text
+                        .replace_range(word.byte_begin..word.byte_end, &correction.correction);
+                }
+                SpellingCorrectionOutput {
+                    text: corrected_text,
+                    corrections,
+                }
+            })
+            .collect())
+    }
+
+    fn build_proposals<S>(&self, input: &[S], words: &[Vec<WordSpan>]) -> Vec<Proposal>
+    where
+        S: AsRef<str>,
+    {
+        let mut proposals: HashMap<(usize, usize), Proposal> = HashMap::new();
+        for (text_index, text_words) in words.iter().enumerate() {
+            for (word_index, word) in text_words.iter().enumerate() {
+                if self.vocabulary.contains(&word.text) {
+                    continue;
+                }
+                let mut candidates = self
+                    .vocabulary
+                    .candidates(&word.text, self.max_edit_distance);
+                candidates.truncate(self.max_candidates);
+                if candidates.is_empty() {
+                    continue;
+                }
+                proposals.insert(
+                    (text_index, word_index),
+                    Proposal {
+                        text_index,
+                        word: word.clone(),
+                        candidates: candidates
+                            .into_iter()
+                            .map(|(candidate, distance)| Candidate {
+                                word: match_case(&word.text, &candidate),
+                                distance,
+                                source: CorrectionSource::Vocabulary,
+                            })
+                            .collect(),
+                        keep_original: false,
+                    },
+                );
+            }
+        }
+
+        if let Some((seq2seq, prefix)) = &self.seq2seq {
+            let prompts = input
+                .iter()
+                .map(|text| match prefix {
+                    Some(prefix) => format!("{}{}", prefix, text.as_ref()),
+                    None => text.as_ref().to_string(),
+                })
+                .collect::<Vec<String>>();
+            let outputs = seq2seq.generate(Some(prompts.as_slice()));
+            for (text_index, (text_words, output)) in words.iter().zip(outputs.iter()).enumerate() {
+                let source = text_words
+                    .iter()
+                    .map(|word| word.text.as_str())
+                    .collect::<Vec<&str>>();
+                let output_words = split_words(output);
+                let target = output_words
+                    .iter()
+                    .map(|word| word.text.as_str())
+                    .collect::<Vec<&str>>();
+                for (source_index, target_index) in align_words(&source, &target) {
+                    let word = &text_words[source_index];
+                    if word.text.to_lowercase() == target[target_index].to_lowercase() {
+                        continue;
+                    }
+                    let candidate = Candidate {
+                        word: match_case(&word.text, &target[target_index].to_lowercase()),
+                        distance: edit_distance(
+                            &word.text.to_lowercase(),
+                            &target[target_index].to_lowercase(),
+                        ),
+                        source: CorrectionSource::Seq2Seq,
+                    };
+                    let proposal =
+                        proposals
+                            .entry((text_index, source_index))
+                            .or_insert_with(|| Proposal {
+                                text_index,
+                                word: word.clone(),
+                                candidates: vec![Candidate {
+                                    word: word.text.clone(),
+                                    distance: 0,
+                                    source: CorrectionSource::Seq2Seq,
+                                }],
+                                keep_original: true,
+                            });
+                    match proposal.candidates.iter_mut().find(|existing| {
+                        existing.word.to_lowercase() == candidate.word.to_lowercase()
+                    }) {
+                        Some(existing) => existing.source = CorrectionSource::Seq2Seq,
+                        None => proposal.candidates.push(candidate),
+                    }
+                }
+            }
+        }
+
+        let mut proposals = proposals.into_values().collect::<Vec<Proposal>>();
+        proposals.sort_by_key(|proposal| (proposal.text_index, proposal.word.offset.begin));
+        proposals
+    }
+
+    fn build_masked_inputs(
+        &self,
+        proposal_index: usize,
+        proposal: &Proposal,
+        text: &str,
+    ) -> Vec<MaskedInput> {
+        let prefix = &text[..proposal.word.byte_begin];
+        let suffix = &text[proposal.word.byte_end..];
+        let preceded_by_space = prefix.ends_with(char::is_whitespace);
+        let prefix_ids = self
+            .tokenizer
+            .convert_tokens_to_ids(&self.tokenizer.tokenize(prefix.trim_end()));
+        let suffix_ids = self
+            .tokenizer
+            .convert_tokens_to_ids(&self.tokenizer.tokenize(suffix));
+
+        let mut candidates_by_length: HashMap<usize, Vec<(usize, Vec<i64>)>> = HashMap::new();
+        for (candidate_index, candidate) in proposal.candidates.iter().enumerate() {
+            let candidate_text = if preceded_by_space {
+                format!(" {}", candidate.word)
+            } else {
+                candidate.word.clone()
+            };
+            let candidate_ids = self
+                .tokenizer
+                .convert_tokens_to_ids(&self.tokenizer.tokenize(&candidate_text));
+            if !candidate_ids.is_empty() {
+                candidates_by_length
+                    .entry(candidate_ids.len())
+                    .or_insert_with(Vec::new)
+                    .push((candidate_index, candidate_ids));
+            }
+        }
+
+        let num_special_tokens = self
+            .tokenizer
+            .build_input_with_special_tokens(
+                TokenIdsWithOffsets {
+                    ids: vec![],
+                    offsets: vec![],
+                    reference_offsets: vec![],
+                    masks: vec![],
+                },
+                None,
+            )
+            .token_ids
+            .len();
+        candidates_by_length
+            .into_iter()
+            .filter_map(|(num_masks, candidates)| {
+                let budget = self
+                    .max_length
+                    .checked_sub(num_special_tokens + num_masks)?;
+                let prefix_length = prefix_ids
+                    .len()
+                    .min((budget / 2).max(budget.saturating_sub(suffix_ids.len())));
+                let suffix_length = suffix_ids.len().min(budget - prefix_length);
+                let ids = prefix_ids[prefix_ids.len() - prefix_length..]
+                    .iter()
+                    .copied()
+                    .chain(std::iter::repeat(self.mask_id).take(num_masks))
+                    .chain(suffix_ids[..suffix_length].iter().copied())
+                    .collect::<Vec<i64>>();
+                let num_tokens = ids.len();
+                let token_ids = self
+                    .tokenizer
+                    .build_input_with_special_tokens(
+                        TokenIdsWithOffsets {
+                            ids,
+                            offsets: vec![None; num_tokens],
+                            reference_offsets: vec![vec![]; num_tokens],
+                            masks: vec![Mask::None; num_tokens],
+                        },
+                        None,
+                    )
+                    .token_ids;
+                let mask_position = token_ids.iter().position(|id| *id == self.mask_id)?;
+                Some(MaskedInput {
+                    proposal_index,
+                    token_ids,
+                    mask_position,
+                    candidates,
+                })
+            })
+            .collect::<Vec<MaskedInput>>()
+    }
+
+    /// Returns the masked language model log-likelihood of the candidates of each proposal
+    fn score_candidates(
+        &self,
+        proposals: &[Proposal],
+        masked_inputs: &[MaskedInput],
+    ) -> Result<Vec<Vec<Option<f64>>>, RustBertError> {
+        let mut scores = proposals
+            .iter()
+            .map(|proposal| vec![None; proposal.candidates.len()])
+            .collect::<Vec<Vec<Option<f64>>>>();
+        let pad_id = self.tokenizer.get_pad_id().unwrap_or(0);
+        for batch in masked_inputs.chunks(self.batch_size) {
+            let max_len = batch
+                .iter()
+                .map(|masked_input| masked_input.token_ids.len())
+                .max()
+                .unwrap_or(0);
+            let (input_ids, attention_mask): (Vec<Tensor>, Vec<Tensor>) = batch
+                .iter()
+                .map(|masked_input| {
+                    let mut token_ids = masked_input.token_ids.clone();
+                    let mut attention_mask = vec![1i64; token_ids.len()];
+                    token_ids.resize(max_len, pad_id);
+                    attention_mask.resize(max_len, 0);
+                    (
+                        Tensor::of_slice(&token_ids),
+                        Tensor::of_slice(&attention_mask),
+                    )
+                })
+                .unzip();
+            let input_ids = Tensor::stack(&input_ids, 0).to(self.var_store.device());
+            let attention_mask = Tensor::stack(&attention_mask, 0).to(self.var_store.device());
+
+            let prediction_scores =
+                no_grad(|| self.masked_lm.forward_t(&input_ids, &attention_mask, false))?;
+            for (batch_index, masked_input) in batch.iter().enumerate() {
+                let num_masks = masked_input.candidates[0].1.len() as i64;
+                let log_probs = prediction_scores
+                    .get(batch_index as i64)
+                    .narrow(0, masked_input.mask_position as i64, num_masks)
+                    .log_softmax(-1, Kind::Float)
+                    .to(Device::Cpu);
+                for (candidate_index, candidate_ids) in &masked_input.candidates {
+                    let log_likelihood = candidate_ids
+                        .iter()
+                        .enumerate()
+                        .map(|(position, id)| log_probs.double_value(&[position as i64, *id]))
+                        .sum::<f64>();
+                    scores[masked_input.proposal_index][*candidate_index] = Some(log_likelihood);
+                }
+            }
+        }
+        Ok(scores)
+    }
+
+    fn select_correction(
+        &self,
+        proposal: &Proposal,
+        log_likelihoods: &[Option<f64>],
+    ) -> Option<SpellingCorrection> {
+        let scores = proposal
+            .candidates
+            .iter()
+            .zip(log_likelihoods.iter())
+            .filter_map(|(candidate, log_likelihood)| {
+                log_likelihood.map(|log_likelihood| {
+                    (
+                        candidate,
+                        log_likelihood - self.distance_penalty * candidate.distance as f64,
+                    )
+                })
+            })
+            .collect::<Vec<(&Candidate, f64)>>();
+        let max_score = scores
+            .iter()
+            .map(|(_, score)| *score)
+            .fold(f64::NEG_INFINITY, f64::max);
+        let normalization = scores
+            .iter()
+            .map(|(_, score)| (score - max_score).exp())
+            .sum::<f64>();
+        let (best_candidate, best_score) = scores
+            .iter()
+            .max_by(|a, b| a.1.partial_cmp(&b.1).unwrap())?;
+        let confidence = (best_score - max_score).exp() / normalization;
+        if (proposal.keep_original && best_candidate.distance == 0)
+            || confidence < self.min_confidence
+        {
+            return None;
+        }
+        Some(SpellingCorrection {
+            original: proposal.word.text.clone(),
+            correction: best_candidate.word.clone(),
+            offset: proposal.word.offset,
+            confidence,
+            edit_distance: best_candidate.distance,
+            source: best_candidate.source,
+        })
+    }
+}
+
+impl<S> Pipeline<S, SpellingCorrectionOutput> for SpellingCorrectionModel
+where
+    S: AsRef<str>,
+{
+    type Options = ();
+
+    fn run_with_options(
+        &self,
+        inputs: &[S],
+        _options: &(),
+    ) -> Result<Vec<SpellingCorrectionOutput>, RustBertError> {
+        self.correct(inputs)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    #[ignore] // no need to run, compilation is enough to verify it is Send
+    fn test() {
+        let config = SpellingCorrectionConfig::default();
+        let _: Box<dyn Send> = Box::new(SpellingCorrectionModel::new(config));
+    }
+
+    #[test]
+    fn edit_distance_counts_transpositions() {
+        assert_eq!(edit_distance("", "abc"), 3);
+        assert_eq!(edit_distance("abc", "abc"), 0);
+        assert_eq!(edit_distance("teh", "the"), 1);
+        assert_eq!(edit_distance("acress", "actress"), 1);
+        assert_eq!(edit_distance("kitten", "sitting"), 3);
+    }
+
+    #[test]
+    fn vocabulary_candidates_sorted_by_distance() {
+        let vocabulary =
+            SpellingVocabulary::from_words(&["the", "then", "than", "tea", "a1", "cat"]);
+        assert_eq!(vocabulary.len(), 5);
+        assert!(vocabulary.contains("The"));
+        assert!(!vocabulary.contains("a1"));
+        assert_eq!(
+            vocabulary.candidates("teh", 1),
+            vec![("tea".to_string(), 1), ("the".to_string(), 1)]
+        );
+        assert_eq!(
+            vocabulary.candidates("then", 2),
+            vec![
+                ("than".to_string(), 1),
+                ("the".to_string(), 1),
+                ("tea".to_string(), 2)
+            ]
+        );
+    }
+
+    #[test]
+    fn words_split_with_offsets() {
+        let words = split_words("Héllo, wörld!");
+        assert_eq!(words.len(), 2);
+        assert_eq!(words[0].text, "Héllo");
+        assert_eq!(words[0].offset, Offset::new(0, 5));
+        assert_eq!(words[1].text, "wörld");
+        assert_eq!(words[1].offset, Offset::new(7, 12));
+        assert_eq!(
+            &"Héllo, wörld!"[words[1].byte_begin..words[1].byte_end],
+            "wörld"
+        );
+    }
+
+    #[test]
+    fn word_alignment_pairs_substitutions() {
+        assert_eq!(
+            align_words(&["I", "lovee", "my", "catt"], &["I", "love", "my", "cat"]),
+            vec![(1, 1), (3, 3)]
+        );
+        assert_eq!(
+            align_words(&["see", "you", "their"], &["see", "you", "over", "there"]),
+            vec![]
+        );
+    }
+
+    #[test]
+    fn correction_case_matches_original() {
+        assert_eq!(match_case("Teh", "the"), "The");
+        assert_eq!(match_case("TEH", "the"), "THE");
+        assert_eq!(match_case("teh", "the"), "the");
+        assert_eq!(match_case("I", "a"), "A");
+    }
+}
@@ -0,0 +1,332 @@
+// Copyright 2022 Guillaume Becquin
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//     http://www.apache.org/licenses/LICENSE-2.0
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! # Asynchronous streaming of pipeline inputs and outputs (requires the `tokio` feature)
+//! `PipelineStream` consumes an asynchronous stream of inputs (e.g. messages received from a queue), groups them
+//! into batches and returns an asynchronous stream of outputs, in the order of the inputs.
+//!
+//! A batch is processed as soon as it reaches `max_batch_size` inputs, or `max_latency` after the arrival of its
+//! first input, whichever comes first: a steady flow of inputs is processed in full batches, while isolated inputs
+//! are not delayed by more than `max_latency`.
+//!
+//! The streams are backpressure-aware: at most `max_pending_batches` batches wait for the model, and the outputs
+//! not yet consumed are bounded by the batch size. When the model (or the consumer of the outputs) falls behind,
+//! the input stream is no longer polled, so that the ingestion slows down instead of buffering without bound.
+//!
+//! Following the recommendation for async code, the model is created and run on a dedicated thread: the pipeline
+//! is passed as a function creating it. The stream must be created from within a tokio runtime.
+//!
+//! ```no_run
+//! # async fn run() -> anyhow::Result<()> {
+//! use rust_bert::pipelines::sentiment::SentimentModel;
+//! use rust_bert::pipelines::streaming::{PipelineStream, StreamingConfig};
+//! use tokio_stream::StreamExt;
+//!
+//! let inputs = tokio_stream::iter(vec![
+//!     "This is a great movie!".to_string(),
+//!     "I did not like this movie.".to_string(),
+//! ]);
+//! let mut outputs = PipelineStream::spawn(
+//!     || SentimentModel::new(Default::default()),
+//!     inputs,
+//!     StreamingConfig::default(),
+//! );
+//! while let Some(output) = outputs.next().await {
+//!     println!("{:?}", output?);
+//! }
+//! # Ok(())
+//! # }
+//! ```
+//!
+//! If a batch fails, an error is returned for each of its inputs and the following batches are processed.
+
+use crate::common::error::RustBertError;
+use crate::pipelines::common::Pipeline;
+use std::pin::Pin;
+use std::task::{Context, Poll};
+use std::thread;
+use std::time::Duration;
+use tokio::sync::mpsc;
+use tokio::time::{timeout_at, Instant};
+use tokio_stream::wrappers::ReceiverStream;
+use tokio_stream::{Stream, StreamExt};
+
+/// # Configuration for a `PipelineStream`
+#[derive(Debug, Clone)]
+pub struct StreamingConfig {
+    /// Maximum number of inputs processed in a batch
+    pub max_batch_size: usize,
+    /// Maximum time between the arrival of the first input of a batch and the processing of the batch
+    pub max_latency: Duration,
+    /// Maximum number of batches waiting to be processed before the input stream stops being polled
+    pub max_pending_batches: usize,
+}
+
+impl StreamingConfig {
+    /// Instantiate a new streaming configuration
+    ///
+    /// # Arguments
+    ///
+    /// * `max_batch_size` - Maximum number of inputs processed in a batch
+    /// * `max_latency` - Maximum time between the arrival of the first input of a batch and its processing
+    /// * `max_pending_batches` - Maximum number of batches waiting to be processed
+    pub fn new(
+        max_batch_size: usize,
+        max_latency: Duration,
+        max_pending_batches: usize,
+    ) -> StreamingConfig {
+        StreamingConfig {
+            max_batch_size,
+            max_latency,
+            max_pending_batches,
+        }
+    }
+}
+
+impl Default for StreamingConfig {
+    fn default() -> StreamingConfig {
+        StreamingConfig::new(32, Duration::from_millis(10), 1)
+    }
+}
+
+/// Returns a copy of an error, reported for each input of a failed batch (`RustBertError` is not `Clone`)
+fn duplicate_error(error: &RustBertError) -> RustBertError {
+    match error {
+        RustBertError::IOError(message) => RustBertError::IOError(message.clone()),
+        RustBertError::TchError(message) => RustBertError::TchError(message.clone()),
+        RustBertError::TokenizerError(message) => RustBertError::TokenizerError(message.clone()),
+        RustBertError::InvalidConfigurationError(message) => {
+            RustBertError::InvalidConfigurationError(message.clone())
+        }
+        RustBertError::ValueError(message) => RustBertError::ValueError(message.clone()),
+        RustBertError::MemoryBudgetExceededError(message) => {
+            RustBertError::MemoryBudgetExceededError(message.clone())
+        }
+        RustBertError::ResourceIntegrityError(message) => {
+            RustBertError::ResourceIntegrityError(message.clone())
+        }
+        RustBertError::InputTooLongError(message) => {
+            RustBertError::InputTooLongError(message.clone())
+        }
+        #[allow(unreachable_patterns)]
+        error => RustBertError::IOError(error.to_string()),
+    }
+}
+
+/// Groups the inputs into batches of at most `max_batch_size` inputs, sent at the latest `max_latency` after
+/// the arrival of their first input
+async fn batch_inputs<S, I>(
+    mut inputs: S,
+    batch_sender: mpsc::Sender<Vec<I>>,
+    max_batch_size: usize,
+    max_latency: Duration,
+) where
+    S: Stream<Item = I> + Unpin,
+{
+    while let Some(first_input) = inputs.next().await {
+        let deadline = Instant::now() + max_latency;
+        let mut batch = vec![first_input];
+        let mut finished = false;
+        while batch.len() < max_batch_size {
+            match timeout_at(deadline, inputs.next()).await {
+                Ok(Some(input)) => batch.push(input),
+                Ok(None) => {
+                    finished = true;
+                    break;
+                }
+                Err(_) => break,
+            }
+        }
+        // Waits for the model to catch up if too many batches are pending
+        if batch_sender.send(batch).await.is_err() || finished {
+            return;
+        }
+    }
+}
+
+/// # Asynchronous stream of pipeline outputs
+/// Returns the output of each input of the input stream, in order. The stream ends once all inputs have been
+/// processed, or after returning the error raised by the creation of the pipeline.
+pub struct PipelineStream<O> {
+    outputs: ReceiverStream<Result<O, RustBertError>>,
+}
+
+impl<O> PipelineStream<O>
+where
+    O: Send + 'static,
+{
+    /// Creates the pipeline on a dedicated thread and starts processing the input stream. Must be called from
+    /// within a tokio runtime.
+    ///
+    /// # Arguments
+    ///
+    /// * `create_pipeline` - Function creating the pipeline, called on the pipeline thread
+    /// * `inputs` - Stream of inputs
+    /// * `config` - `StreamingConfig` with the batching and buffering settings
+    pub fn spawn<F, P, S, I>(
+        create_pipeline: F,
+        inputs: S,
+        config: StreamingConfig,
+    ) -> PipelineStream<O>
+    where
+        F: FnOnce() -> Result<P, RustBertError> + Send + 'static,
+        P: Pipeline<I, O>,
+        S: Stream<Item = I> + Unpin + Send + 'static,
+        I: Send + 'static,
+    {
+        let max_batch_size = config.max_batch_size.max(1);
+        let (batch_sender, mut batch_receiver) =
+            mpsc::channel::<Vec<I>>(config.max_pending_batches.max(1));
+        let (output_sender, output_receiver) = mpsc::channel(max_batch_size);
+
+        tokio::spawn(batch_inputs(
+            inputs,
+            batch_sender,
+            max_batch_size,
+            config.max_latency,
+        ));
+
+        thread::spawn(move || {
+            let pipeline = match create_pipeline() {
+                Ok(pipeline) => pipeline,
+                Err(error) => {
+                    let _ = output_sender.blocking_send(Err(error));
+                    return;
+                }
+            };
+            while let Some(batch) = batch_receiver.blocking_recv() {
+                let outputs = match pipeline.run(&batch) {
+                    Ok(outputs) => outputs.into_iter().map(Ok).collect::<Vec<_>>(),
+                    Err(error) => (0..batch.len())
+                        .map(|_| Err(duplicate_error(&error)))
+                        .collect::<Vec<_>>(),
+                };
+                for output in outputs {
+                    // The output stream was dropped
+                    if output_sender.blocking_send(output).is_err() {
+                        return;
+                    }
+                }
+            }
+        });
+
+        PipelineStream {
+            outputs: ReceiverStream::new(output_receiver),
+        }
+    }
+}
+
+impl<O> Stream for PipelineStream<O> {
+    type Item = Result<O, RustBertError>;
+
+    fn poll_next(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        Pin::new(&mut self.outputs).poll_next(cx)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use std::sync::{Arc, Mutex};
+
+    struct UppercasePipeline {
+        batch_sizes: Arc<Mutex<Vec<usize>>>,
+    }
+
+    impl Pipeline<String, String> for UppercasePipeline {
+        type Options = ();
+
+        fn run_with_options(
+            &self,
+            inputs: &[String],
+            _options: &(),
+        ) -> Result<Vec<String>, RustBertError> {
+            if inputs.iter().any(|input| input == "fail") {
+                return Err(RustBertError::ValueError("invalid input".to_string()));
+            }
+            self.batch_sizes.lock().unwrap().push(inputs.len());
+            Ok(inputs.iter().map(|input| input.to_uppercase()).collect())
+        }
+    }
+
+    fn inputs(values: &[&str]) -> Vec<String> {
+        values.iter().map(|value| value.to_string()).collect()
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn outputs_in_input_order() {
+        let batch_sizes = Arc::new(Mutex::new(vec![]));
+        let pipeline_batch_sizes = batch_sizes.clone();
+        let outputs = PipelineStream::spawn(
+            move || {
+                Ok(UppercasePipeline {
+                    batch_sizes: pipeline_batch_sizes,
+                })
+            },
+            tokio_stream::iter(inputs(&["a", "b", "c", "d", "e"])),
+            StreamingConfig::new(2, Duration::from_secs(1), 1),
+        )
+        .collect::<Vec<_>>()
+        .await
+        .into_iter()
+        .collect::<Result<Vec<String>, RustBertError>>()
+        .unwrap();
+
+        assert_eq!(outputs, inputs(&["A", "B", "C", "D", "E"]));
+        assert_eq!(*batch_sizes.lock().unwrap(), vec![2, 2, 1]);
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn partial_batch_after_max_latency() {
+        let (input_sender, input_receiver) = mpsc::channel(4);
+        let mut outputs = PipelineStream::spawn(
+            || {
+                Ok(UppercasePipeline {
+                    batch_sizes: Arc::new(Mutex::new(vec![])),
+                })
+            },
+            ReceiverStream::new(input_receiver),
+            StreamingConfig::new(32, Duration::from_millis(20), 1),
+        );
+
+        input_sender.send("a".to_string()).await.unwrap();
+        let output = tokio::time::timeout(Duration::from_secs(5), outputs.next())
+            .await
+            .unwrap();
+        assert_eq!(output.unwrap().unwrap(), "A");
+
+        input_sender.send("fail".to_string()).await.unwrap();
+        drop(input_sender);
+        assert!(matches!(
+            outputs.next().await,
+            Some(Err(RustBertError::ValueError(_)))
+        ));
+        assert!(outputs.next().await.is_none());
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn pipeline_creation_error() {
+        let mut outputs = PipelineStream::<String>::spawn(
+            || -> Result<UppercasePipeline, RustBertError> {
+                Err(RustBertError::InvalidConfigurationError(
+                    "missing weights".to_string(),
+                ))
+            },
+            tokio_stream::iter(inputs(&["a"])),
+            StreamingConfig::default(),
+        );
+        assert!(matches!(
+            outputs.next().await,
+            Some(Err(RustBertError::InvalidConfigurationError(_)))
+        ));
+        assert!(outputs.next().await.is_none());
+    }
+}
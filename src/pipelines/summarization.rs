@@ -62,13 +62,17 @@
 //! # ;
 //! ```
 
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
 use tch::Device;
 
 use crate::bart::BartGenerator;
 use crate::common::error::RustBertError;
 use crate::pegasus::PegasusConditionalGenerator;
-use crate::pipelines::common::ModelType;
-use crate::pipelines::generation_utils::{GenerateConfig, LanguageGenerator};
+use crate::pipelines::common::{ModelType, Pipeline};
+use crate::pipelines::generation_utils::{
+    GenerateConfig, GenerateOptions, LanguageGenerator, NoRepeatNgramScope,
+};
 use crate::prophetnet::ProphetNetConditionalGenerator;
 use crate::resources::ResourceProvider;
 use crate::t5::T5Generator;
@@ -121,6 +125,9 @@ pub struct SummarizationConfig {
     pub num_beam_groups: Option<i64>,
     /// Diversity penalty for diverse beam search. High values will enforce more difference between beam groups (default: 5.5)
     pub diversity_penalty: Option<f64>,
+    /// Bias added to the logits of the given token ids at every generation step, nudging the vocabulary used by the
+    /// model. Positive values encourage the generation of a token and negative values discourage it (default: empty)
+    pub logit_bias: HashMap<i64, f64>,
     /// Device to place the model on (default: CUDA/GPU when available)
     pub device: Device,
 }
@@ -165,6 +172,7 @@ impl SummarizationConfig {
             num_return_sequences: 1,
             num_beam_groups: None,
             diversity_penalty: None,
+            logit_bias: HashMap::new(),
             device: Device::cuda_if_available(),
         }
     }
@@ -201,9 +209,18 @@ impl From<SummarizationConfig> for GenerateConfig {
             repetition_penalty: config.repetition_penalty,
             length_penalty: config.length_penalty,
             no_repeat_ngram_size: config.no_repeat_ngram_size,
+            no_repeat_ngram_scope: NoRepeatNgramScope::Sequence,
             num_return_sequences: config.num_return_sequences,
             num_beam_groups: config.num_beam_groups,
             diversity_penalty: config.diversity_penalty,
+            penalty_alpha: None,
+            typical_p: None,
+            epsilon_cutoff: None,
+            eta_cutoff: None,
+            prefill_chunk_size: None,
+            stop_sequences: vec![],
+            logit_bias: config.logit_bias,
+            output_attentions: false,
             device: config.device,
         }
     }
@@ -228,6 +245,9 @@ impl SummarizationOption {
                 config.into(),
             )?)),
             ModelType::T5 => Ok(SummarizationOption::T5(T5Generator::new(config.into())?)),
+            ModelType::ByT5 => Ok(SummarizationOption::T5(T5Generator::new_byt5(
+                config.into(),
+            )?)),
             ModelType::ProphetNet => Ok(SummarizationOption::ProphetNet(
                 ProphetNetConditionalGenerator::new(config.into())?,
             )),
@@ -245,7 +265,7 @@ impl SummarizationOption {
     pub fn model_type(&self) -> ModelType {
         match *self {
             Self::Bart(_) => ModelType::Bart,
-            Self::T5(_) => ModelType::T5,
+            Self::T5(ref model) => model.get_tokenizer().model_type(),
             Self::ProphetNet(_) => ModelType::ProphetNet,
             Self::Pegasus(_) => ModelType::Pegasus,
         }
@@ -253,27 +273,39 @@ impl SummarizationOption {
 
     /// Interface method to generate() of the particular models.
     pub fn generate<S>(&self, prompt_texts: Option<&[S]>) -> Vec<String>
+    where
+        S: AsRef<str> + Sync,
+    {
+        self.generate_with_options(prompt_texts, None)
+    }
+
+    /// Interface method to generate() of the particular models, with generation options overriding the configuration.
+    pub fn generate_with_options<S>(
+        &self,
+        prompt_texts: Option<&[S]>,
+        generate_options: Option<GenerateOptions>,
+    ) -> Vec<String>
     where
         S: AsRef<str> + Sync,
     {
         match *self {
             Self::Bart(ref model) => model
-                .generate(prompt_texts, None)
+                .generate(prompt_texts, generate_options)
                 .into_iter()
                 .map(|output| output.text)
                 .collect(),
             Self::T5(ref model) => model
-                .generate(prompt_texts, None)
+                .generate(prompt_texts, generate_options)
                 .into_iter()
                 .map(|output| output.text)
                 .collect(),
             Self::ProphetNet(ref model) => model
-                .generate(prompt_texts, None)
+                .generate(prompt_texts, generate_options)
                 .into_iter()
                 .map(|output| output.text)
                 .collect(),
             Self::Pegasus(ref model) => model
-                .generate(prompt_texts, None)
+                .generate(prompt_texts, generate_options)
                 .into_iter()
                 .map(|output| output.text)
                 .collect(),
@@ -281,6 +313,249 @@ impl SummarizationOption {
     }
 }
 
+/// # Format of structured summaries
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub enum SummaryFormat {
+    /// Free text summary
+    Text,
+    /// Bullet list. Summaries written as prose are split into one bullet per sentence.
+    Bullets {
+        /// Optional maximum number of bullets kept
+        max_bullets: Option<usize>,
+    },
+    /// Key facts extracted as `key: value` lines, for instruction-tuned sequence-to-sequence models (e.g. Flan-T5)
+    KeyFacts {
+        /// Keys of the facts to extract
+        keys: Vec<String>,
+    },
+    /// JSON object, for instruction-tuned sequence-to-sequence models
+    Json {
+        /// Keys the JSON object must contain
+        required_keys: Vec<String>,
+    },
+}
+
+impl SummaryFormat {
+    /// Returns the default prompt template of the format, `None` for the formats post-processing a plain summary
+    pub fn default_prompt_template(&self) -> Option<&'static str> {
+        match self {
+            SummaryFormat::Text | SummaryFormat::Bullets { .. } => None,
+            SummaryFormat::KeyFacts { .. } => Some(
+                "Extract the following facts from the text, one per line as \"key: value\": {keys}.\n\nText: {text}",
+            ),
+            SummaryFormat::Json { .. } => {
+                Some("Summarize the text as a JSON object with the keys {keys}.\n\nText: {text}")
+            }
+        }
+    }
+
+    /// Returns the decoding preset of the format, overriding the `SummarizationConfig`. Key facts and JSON outputs
+    /// are short and repeat separators, so the minimum length and n-gram repetition constraints are lifted.
+    pub fn decoding_preset(&self) -> GenerateOptions<'static> {
+        match self {
+            SummaryFormat::Text | SummaryFormat::Bullets { .. } => GenerateOptions {
+                num_return_sequences: Some(1),
+                ..Default::default()
+            },
+            SummaryFormat::KeyFacts { .. } | SummaryFormat::Json { .. } => GenerateOptions {
+                min_length: Some(0),
+                no_repeat_ngram_size: Some(0),
+                num_return_sequences: Some(1),
+                ..Default::default()
+            },
+        }
+    }
+
+    fn keys(&self) -> &[String] {
+        match self {
+            SummaryFormat::Text | SummaryFormat::Bullets { .. } => &[],
+            SummaryFormat::KeyFacts { keys } => keys,
+            SummaryFormat::Json { required_keys } => required_keys,
+        }
+    }
+
+    /// Parses a generated summary into the format, returning `None` if the output does not follow it
+    ///
+    /// # Arguments
+    ///
+    /// * `output` - Generated summary
+    pub fn parse(&self, output: &str) -> Option<StructuredSummary> {
+        // Pegasus models generate `<n>` as line separator
+        let output = output.replace("<n>", "\n");
+        let output = output.trim();
+        if output.is_empty() {
+            return None;
+        }
+        match self {
+            SummaryFormat::Text => Some(StructuredSummary::Text(output.to_string())),
+            SummaryFormat::Bullets { max_bullets } => {
+                let mut bullets = parse_bullets(output);
+                if let Some(max_bullets) = max_bullets {
+                    bullets.truncate(*max_bullets);
+                }
+                if bullets.is_empty() {
+                    None
+                } else {
+                    Some(StructuredSummary::Bullets(bullets))
+                }
+            }
+            SummaryFormat::KeyFacts { keys } => {
+                parse_key_facts(output, keys).map(StructuredSummary::KeyFacts)
+            }
+            SummaryFormat::Json { required_keys } => {
+                parse_json(output, required_keys).map(StructuredSummary::Json)
+            }
+        }
+    }
+}
+
+/// Splits a summary into bullets: list items if the summary is a list, sentences otherwise
+fn parse_bullets(output: &str) -> Vec<String> {
+    let lines = output
+        .lines()
+        .map(|line| line.trim())
+        .filter(|line| !line.is_empty())
+        .collect::<Vec<&str>>();
+    let list_items = lines
+        .iter()
+        .filter_map(|line| strip_list_marker(line))
+        .collect::<Vec<&str>>();
+    if !list_items.is_empty() && list_items.len() == lines.len() {
+        return list_items
+            .into_iter()
+            .map(|item| item.trim().to_string())
+            .filter(|item| !item.is_empty())
+            .collect();
+    }
+
+    let mut sentences = vec![];
+    let mut current = String::new();
+    let mut chars = output.chars().peekable();
+    while let Some(c) = chars.next() {
+        current.push(c);
+        let end_of_sentence = matches!(c, '.' | '!' | '?')
+            && chars.peek().filter(|next| !next.is_whitespace()).is_none();
+        if end_of_sentence || c == '\n' {
+            let sentence = current.trim();
+            if !sentence.is_empty() {
+                sentences.push(sentence.to_string());
+            }
+            current.clear();
+        }
+    }
+    let sentence = current.trim();
+    if !sentence.is_empty() {
+        sentences.push(sentence.to_string());
+    }
+    sentences
+}
+
+/// Removes the bullet (`-`, `*`, `•`) or number (`1.`, `1)`) marker of a list item
+fn strip_list_marker(line: &str) -> Option<&str> {
+    for marker in ["- ", "* ", "• "] {
+        if let Some(item) = line.strip_prefix(marker) {
+            return Some(item);
+        }
+    }
+    let digits = line.chars().take_while(|c| c.is_ascii_digit()).count();
+    if digits > 0 {
+        let rest = &line[digits..];
+        if let Some(item) = rest.strip_prefix(". ").or_else(|| rest.strip_prefix(") ")) {
+            return Some(item);
+        }
+    }
+    None
+}
+
+/// Reads `key: value` pairs separated by new lines or semicolons, returning the values of the keys in order if they
+/// are all present
+fn parse_key_facts(output: &str, keys: &[String]) -> Option<Vec<(String, String)>> {
+    let pairs = output
+        .split(|c: char| c == '\n' || c == ';')
+        .filter_map(|part| {
+            let part = strip_list_marker(part.trim()).unwrap_or_else(|| part.trim());
+            part.split_once(':')
+                .map(|(key, value)| (key.trim().to_lowercase(), value.trim()))
+        })
+        .collect::<Vec<(String, &str)>>();
+    keys.iter()
+        .map(|key| {
+            let normalized_key = key.trim().to_lowercase();
+            pairs
+                .iter()
+                .find(|(candidate, value)| *candidate == normalized_key && !value.is_empty())
+                .map(|(_, value)| (key.clone(), value.to_string()))
+        })
+        .collect()
+}
+
+/// Reads the JSON object contained in the output. Vocabularies without curly braces (e.g. T5) produce the object
+/// content only, which is wrapped in braces.
+fn parse_json(output: &str, required_keys: &[String]) -> Option<serde_json::Value> {
+    let candidate = match (output.find('{'), output.rfind('}')) {
+        (Some(start), Some(end)) if start < end => output[start..=end].to_string(),
+        _ => format!("{{{}}}", output),
+    };
+    let value = serde_json::from_str::<serde_json::Value>(&candidate).ok()?;
+    let object = value.as_object()?;
+    if required_keys.iter().all(|key| object.contains_key(key)) {
+        Some(value)
+    } else {
+        None
+    }
+}
+
+/// # Structured summary parsed from the model output
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub enum StructuredSummary {
+    /// Free text summary
+    Text(String),
+    /// Bullet list items
+    Bullets(Vec<String>),
+    /// Key facts, in the order of the requested keys
+    KeyFacts(Vec<(String, String)>),
+    /// JSON object
+    Json(serde_json::Value),
+}
+
+/// # Configuration for structured summaries
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct StructuredSummaryConfig {
+    /// Format of the summaries
+    pub format: SummaryFormat,
+    /// Optional prompt template with `{text}` and `{keys}` placeholders (default: `SummaryFormat::default_prompt_template`).
+    /// The summarization prefix of the model (e.g. `summarize: ` for T5) is only applied without template.
+    pub prompt_template: Option<String>,
+    /// Number of additional generations (with sampling) for the outputs that could not be parsed (default: 2)
+    pub max_retries: usize,
+}
+
+impl StructuredSummaryConfig {
+    /// Instantiate a new structured summary configuration with the default template and retries of the format
+    ///
+    /// # Arguments
+    ///
+    /// * `format` - `SummaryFormat` of the summaries
+    pub fn new(format: SummaryFormat) -> StructuredSummaryConfig {
+        StructuredSummaryConfig {
+            prompt_template: format.default_prompt_template().map(String::from),
+            format,
+            max_retries: 2,
+        }
+    }
+}
+
+/// # Structured summary output
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct StructuredSummaryOutput {
+    /// Parsed summary, `None` if no generation could be parsed
+    pub summary: Option<StructuredSummary>,
+    /// Raw output of the last generation
+    pub raw: String,
+    /// Number of generations performed
+    pub attempts: usize,
+}
+
 /// # SummarizationModel to perform summarization
 pub struct SummarizationModel {
     model: SummarizationOption,
@@ -375,6 +650,106 @@ impl SummarizationModel {
             }
         }
     }
+    /// Summarize texts into a structured format (bullet list, key facts or JSON object). The outputs that cannot be
+    /// parsed are generated again with sampling, up to `max_retries` times.
+    ///
+    /// # Arguments
+    ///
+    /// * `texts` - `&[&str]` Array of texts to summarize.
+    /// * `config` - `StructuredSummaryConfig` with the format, prompt template and number of retries
+    ///
+    /// # Returns
+    /// * `Vec<StructuredSummaryOutput>` Structured summaries
+    ///
+    /// # Example
+    ///
+    /// ```no_run
+    /// # fn main() -> anyhow::Result<()> {
+    /// use rust_bert::pipelines::summarization::{
+    ///     StructuredSummaryConfig, SummarizationModel, SummaryFormat,
+    /// };
+    /// let model = SummarizationModel::new(Default::default())?;
+    ///
+    /// let config = StructuredSummaryConfig::new(SummaryFormat::Bullets {
+    ///     max_bullets: Some(3),
+    /// });
+    /// let input = ["The presence of water vapour was confirmed in the atmosphere of K2-18b..."];
+    /// let output = model.summarize_structured(&input, &config);
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn summarize_structured<S>(
+        &self,
+        texts: &[S],
+        config: &StructuredSummaryConfig,
+    ) -> Vec<StructuredSummaryOutput>
+    where
+        S: AsRef<str> + Sync,
+    {
+        let keys = config.format.keys().join(", ");
+        let prompts = texts
+            .iter()
+            .map(|text| match (&config.prompt_template, &self.prefix) {
+                (Some(template), _) => template
+                    .replace("{keys}", &keys)
+                    .replace("{text}", text.as_ref()),
+                (None, Some(prefix)) => format!("{}{}", prefix, text.as_ref()),
+                (None, None) => text.as_ref().to_string(),
+            })
+            .collect::<Vec<String>>();
+
+        let mut outputs = vec![
+            StructuredSummaryOutput {
+                summary: None,
+                raw: String::new(),
+                attempts: 0,
+            };
+            texts.len()
+        ];
+        let mut pending = (0..texts.len()).collect::<Vec<usize>>();
+        for attempt in 0..=config.max_retries {
+            if pending.is_empty() {
+                break;
+            }
+            let generate_options = if attempt == 0 {
+                config.format.decoding_preset()
+            } else {
+                GenerateOptions {
+                    do_sample: Some(true),
+                    num_beams: Some(1),
+                    temperature: Some(0.7),
+                    top_p: Some(0.9),
+                    ..config.format.decoding_preset()
+                }
+            };
+            let pending_prompts = pending
+                .iter()
+                .map(|index| prompts[*index].as_str())
+                .collect::<Vec<&str>>();
+            let generated = self
+                .model
+                .generate_with_options(Some(&pending_prompts), Some(generate_options));
+            for (index, raw) in pending.iter().zip(generated) {
+                let output = &mut outputs[*index];
+                output.summary = config.format.parse(&raw);
+                output.raw = raw;
+                output.attempts += 1;
+            }
+            pending.retain(|index| outputs[*index].summary.is_none());
+        }
+        outputs
+    }
+}
+
+impl<S> Pipeline<S, String> for SummarizationModel
+where
+    S: AsRef<str> + Sync,
+{
+    type Options = ();
+
+    fn run_with_options(&self, inputs: &[S], _options: &()) -> Result<Vec<String>, RustBertError> {
+        Ok(self.summarize(inputs))
+    }
 }
 
 #[cfg(test)]
@@ -387,4 +762,63 @@ mod test {
         let config = SummarizationConfig::default();
         let _: Box<dyn Send> = Box::new(SummarizationModel::new(config));
     }
+
+    #[test]
+    fn test_parse_bullets() {
+        let format = SummaryFormat::Bullets { max_bullets: None };
+        let expected = StructuredSummary::Bullets(vec![
+            "Water was found on K2-18b.".to_string(),
+            "It is 110 light-years from Earth.".to_string(),
+        ]);
+        assert_eq!(
+            format.parse(" Water was found on K2-18b. It is 110 light-years from Earth."),
+            Some(expected.clone())
+        );
+        assert_eq!(
+            format.parse("- Water was found on K2-18b.\n- It is 110 light-years from Earth."),
+            Some(expected.clone())
+        );
+        assert_eq!(
+            format.parse("Water was found on K2-18b.<n>It is 110 light-years from Earth."),
+            Some(expected)
+        );
+        assert_eq!(
+            SummaryFormat::Bullets {
+                max_bullets: Some(1)
+            }
+            .parse("1. First point\n2. Second point"),
+            Some(StructuredSummary::Bullets(vec!["First point".to_string()]))
+        );
+        assert_eq!(format.parse("  "), None);
+    }
+
+    #[test]
+    fn test_parse_key_facts() {
+        let format = SummaryFormat::KeyFacts {
+            keys: vec!["Planet".to_string(), "distance".to_string()],
+        };
+        assert_eq!(
+            format.parse("planet: K2-18b; Distance: 110 light-years"),
+            Some(StructuredSummary::KeyFacts(vec![
+                ("Planet".to_string(), "K2-18b".to_string()),
+                ("distance".to_string(), "110 light-years".to_string()),
+            ]))
+        );
+        assert_eq!(format.parse("planet: K2-18b"), None);
+    }
+
+    #[test]
+    fn test_parse_json() {
+        let format = SummaryFormat::Json {
+            required_keys: vec!["planet".to_string()],
+        };
+        let expected = StructuredSummary::Json(serde_json::json!({"planet": "K2-18b"}));
+        assert_eq!(
+            format.parse("Summary: {\"planet\": \"K2-18b\"}"),
+            Some(expected.clone())
+        );
+        assert_eq!(format.parse("\"planet\": \"K2-18b\""), Some(expected));
+        assert_eq!(format.parse("{\"star\": \"red dwarf\"}"), None);
+        assert_eq!(format.parse("K2-18b"), None);
+    }
 }
@@ -21,6 +21,7 @@
 //! - GPT-Neo
 //! - XLNet
 //! - Reformer
+//! - GPT-BigCode (StarCoder), also generating code between a prefix and a suffix with `TextGenerationModel::fill_in_the_middle`
 //!
 //! Two APIs exist to build text generation models:
 //! - `TextGenerationModel` is a high-level module that exposes text generation capabilities with a set of reasonable defaults
@@ -31,15 +32,28 @@
 //!
 //! Customized text generation models models can be loaded by overwriting the resources in the configuration.
 //! The dependencies will be downloaded to the user's home directory, e.g. under ~/.cache/.rustbert/gpt2
-use tch::Device;
+use std::collections::HashMap;
+use std::sync::Mutex;
+use tch::nn::VarStore;
+use tch::{Device, Tensor};
 
+use crate::bloom::BloomGenerator;
 use crate::common::error::RustBertError;
+use crate::falcon::FalconGenerator;
 use crate::gpt2::GPT2Generator;
+use crate::gpt_bigcode::GptBigCodeGenerator;
 use crate::gpt_neo::GptNeoGenerator;
+use crate::gpt_neox::GptNeoXGenerator;
+use crate::llama::LlamaGenerator;
 use crate::openai_gpt::OpenAIGenerator;
-use crate::pipelines::common::{ModelType, TokenizerOption};
+use crate::pipelines::common::{ConfigOption, ModelType, Pipeline, TokenizerOption};
 use crate::pipelines::generation_utils::private_generation_utils::PrivateLanguageGenerator;
-use crate::pipelines::generation_utils::{GenerateConfig, GenerateOptions, LanguageGenerator};
+use crate::pipelines::generation_utils::{
+    decode_before_stop_sequence, GenerateConfig, GenerateOptions, LanguageGenerator,
+    NoRepeatNgramScope, StreamedToken,
+};
+use crate::pipelines::memory::{MemoryEstimator, MemoryStatistics};
+use crate::pipelines::memory_pool::MemoryPool;
 use crate::reformer::ReformerGenerator;
 use crate::resources::ResourceProvider;
 use crate::xlnet::XLNetGenerator;
@@ -86,12 +100,39 @@ pub struct TextGenerationConfig {
     pub length_penalty: f64,
     /// Number of allowed repetitions of n-grams. Values higher than 0 turn on this feature and will prevent repeats of n-grams with a length equal or greater to this value (default: 0)
     pub no_repeat_ngram_size: i64,
+    /// Part of the text in which the n-grams may not be repeated: the whole text, a sliding window of tokens, or the
+    /// current sentence or paragraph, allowing long texts to repeat entities across sentences or paragraphs
+    /// (default: `NoRepeatNgramScope::Sequence`)
+    pub no_repeat_ngram_scope: NoRepeatNgramScope,
     /// Number of sequences to return for each prompt text (default: 1)
     pub num_return_sequences: i64,
     /// Number of beam groups for diverse beam generation. If provided and higher than 1, will split the beams into beam subgroups leading to more diverse generation.
     pub num_beam_groups: Option<i64>,
     /// Diversity penalty for diverse beam search. High values will enforce more difference between beam groups (default: 5.5)
     pub diversity_penalty: Option<f64>,
+    /// Degeneration penalty for contrastive search. If provided and higher than 0, greedy decoding with a `top_k`
+    /// higher than 1 re-ranks the `top_k` most likely tokens by penalizing the tokens whose hidden state is similar
+    /// to the hidden states of the previous tokens (default: None)
+    pub penalty_alpha: Option<f64>,
+    /// Typical_p value for locally typical sampling. Keep the tokens whose information content is the closest to the
+    /// entropy of the distribution until their cumulative probability reaches typical_p (default: None)
+    pub typical_p: Option<f64>,
+    /// Epsilon cutoff for truncation sampling. Only sample the tokens with a probability higher than epsilon_cutoff
+    /// (default: None)
+    pub epsilon_cutoff: Option<f64>,
+    /// Eta cutoff for truncation sampling. Only sample the tokens with a probability higher than
+    /// `min(eta_cutoff, sqrt(eta_cutoff) * exp(-entropy))` (default: None)
+    pub eta_cutoff: Option<f64>,
+    /// Number of tokens of the segments of the prompt processed by each forward pass of the prefill. Bounds the
+    /// activation memory required to ingest very long prompts. If `None`, the prompt is processed in a single
+    /// forward pass (default: None)
+    pub prefill_chunk_size: Option<i64>,
+    /// Stop sequences. The generation of a text stops as soon as it contains one of these strings, and the returned
+    /// text is truncated before it (default: empty)
+    pub stop_sequences: Vec<String>,
+    /// Bias added to the logits of the given token ids at every generation step, nudging the vocabulary used by the
+    /// model. Positive values encourage the generation of a token and negative values discourage it (default: empty)
+    pub logit_bias: HashMap<i64, f64>,
     /// Device to place the model on (default: CUDA/GPU when available)
     pub device: Device,
 }
@@ -133,9 +174,17 @@ impl TextGenerationConfig {
             repetition_penalty: 1.0,
             length_penalty: 1.0,
             no_repeat_ngram_size: 0,
+            no_repeat_ngram_scope: NoRepeatNgramScope::Sequence,
             num_return_sequences: 1,
             num_beam_groups: None,
             diversity_penalty: None,
+            penalty_alpha: None,
+            typical_p: None,
+            epsilon_cutoff: None,
+            eta_cutoff: None,
+            prefill_chunk_size: None,
+            stop_sequences: vec![],
+            logit_bias: HashMap::new(),
             device: Device::cuda_if_available(),
         }
     }
@@ -172,14 +221,52 @@ impl From<TextGenerationConfig> for GenerateConfig {
             repetition_penalty: config.repetition_penalty,
             length_penalty: config.length_penalty,
             no_repeat_ngram_size: config.no_repeat_ngram_size,
+            no_repeat_ngram_scope: config.no_repeat_ngram_scope,
             num_return_sequences: config.num_return_sequences,
             num_beam_groups: config.num_beam_groups,
             diversity_penalty: config.diversity_penalty,
+            penalty_alpha: config.penalty_alpha,
+            typical_p: config.typical_p,
+            epsilon_cutoff: config.epsilon_cutoff,
+            eta_cutoff: config.eta_cutoff,
+            prefill_chunk_size: config.prefill_chunk_size,
+            stop_sequences: config.stop_sequences,
+            logit_bias: config.logit_bias,
+            output_attentions: false,
             device: config.device,
         }
     }
 }
 
+/// # Special tokens of a fill-in-the-middle prompt
+/// Code generation models trained with a fill-in-the-middle objective generate the code between a prefix and a
+/// suffix from the prompt `<prefix token>{prefix}<suffix token>{suffix}<middle token>`. The tokens must be
+/// entries of the model vocabulary.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct FillInTheMiddleTokens<'a> {
+    /// Token preceding the prefix
+    pub prefix: &'a str,
+    /// Token preceding the suffix
+    pub suffix: &'a str,
+    /// Token preceding the generated middle
+    pub middle: &'a str,
+}
+
+impl FillInTheMiddleTokens<'static> {
+    /// Fill-in-the-middle tokens of the StarCoder models
+    pub const STARCODER: FillInTheMiddleTokens<'static> = FillInTheMiddleTokens {
+        prefix: "<fim_prefix>",
+        suffix: "<fim_suffix>",
+        middle: "<fim_middle>",
+    };
+    /// Fill-in-the-middle tokens of the SantaCoder models
+    pub const SANTACODER: FillInTheMiddleTokens<'static> = FillInTheMiddleTokens {
+        prefix: "<fim-prefix>",
+        suffix: "<fim-suffix>",
+        middle: "<fim-middle>",
+    };
+}
+
 /// # Abstraction that holds one particular text generation model, for any of the supported models
 pub enum TextGenerationOption {
     /// Text Generator based on GPT2 model
@@ -188,6 +275,16 @@ pub enum TextGenerationOption {
     GPT(OpenAIGenerator),
     /// Text Generator based on GPT-Neo model
     GPTNeo(GptNeoGenerator),
+    /// Text Generator based on GPT-BigCode model
+    GPTBigCode(GptBigCodeGenerator),
+    /// Text Generator based on LLaMA model
+    Llama(LlamaGenerator),
+    /// Text Generator based on GPT-NeoX model
+    GPTNeoX(GptNeoXGenerator),
+    /// Text Generator based on Falcon model
+    Falcon(FalconGenerator),
+    /// Text Generator based on BLOOM model
+    Bloom(BloomGenerator),
     /// Text Generator based on XLNet model
     XLNet(XLNetGenerator),
     /// Text Generator based on Reformer model
@@ -212,6 +309,21 @@ impl TextGenerationOption {
             ModelType::GPTNeo => Ok(TextGenerationOption::GPTNeo(GptNeoGenerator::new(
                 config.into(),
             )?)),
+            ModelType::GPTBigCode => Ok(TextGenerationOption::GPTBigCode(
+                GptBigCodeGenerator::new(config.into())?,
+            )),
+            ModelType::Llama => Ok(TextGenerationOption::Llama(LlamaGenerator::new(
+                config.into(),
+            )?)),
+            ModelType::GPTNeoX => Ok(TextGenerationOption::GPTNeoX(GptNeoXGenerator::new(
+                config.into(),
+            )?)),
+            ModelType::Falcon => Ok(TextGenerationOption::Falcon(FalconGenerator::new(
+                config.into(),
+            )?)),
+            ModelType::Bloom => Ok(TextGenerationOption::Bloom(BloomGenerator::new(
+                config.into(),
+            )?)),
             _ => Err(RustBertError::InvalidConfigurationError(format!(
                 "Text generation not implemented for {:?}!",
                 config.model_type
@@ -225,6 +337,11 @@ impl TextGenerationOption {
             Self::GPT(_) => ModelType::OpenAiGpt,
             Self::GPT2(_) => ModelType::GPT2,
             Self::GPTNeo(_) => ModelType::GPTNeo,
+            Self::GPTBigCode(_) => ModelType::GPTBigCode,
+            Self::Llama(_) => ModelType::Llama,
+            Self::GPTNeoX(_) => ModelType::GPTNeoX,
+            Self::Falcon(_) => ModelType::Falcon,
+            Self::Bloom(_) => ModelType::Bloom,
             Self::XLNet(_) => ModelType::XLNet,
             Self::Reformer(_) => ModelType::Reformer,
         }
@@ -236,6 +353,11 @@ impl TextGenerationOption {
             Self::GPT(model_ref) => model_ref._get_tokenizer(),
             Self::GPT2(model_ref) => model_ref._get_tokenizer(),
             Self::GPTNeo(model_ref) => model_ref._get_tokenizer(),
+            Self::GPTBigCode(model_ref) => model_ref._get_tokenizer(),
+            Self::Llama(model_ref) => model_ref._get_tokenizer(),
+            Self::GPTNeoX(model_ref) => model_ref._get_tokenizer(),
+            Self::Falcon(model_ref) => model_ref._get_tokenizer(),
+            Self::Bloom(model_ref) => model_ref._get_tokenizer(),
             Self::XLNet(model_ref) => model_ref._get_tokenizer(),
             Self::Reformer(model_ref) => model_ref._get_tokenizer(),
         }
@@ -272,6 +394,31 @@ impl TextGenerationOption {
                 .into_iter()
                 .map(|output| output.indices)
                 .collect(),
+            Self::GPTBigCode(ref model) => model
+                .generate_indices(prompt_texts, generate_options)
+                .into_iter()
+                .map(|output| output.indices)
+                .collect(),
+            Self::Llama(ref model) => model
+                .generate_indices(prompt_texts, generate_options)
+                .into_iter()
+                .map(|output| output.indices)
+                .collect(),
+            Self::GPTNeoX(ref model) => model
+                .generate_indices(prompt_texts, generate_options)
+                .into_iter()
+                .map(|output| output.indices)
+                .collect(),
+            Self::Falcon(ref model) => model
+                .generate_indices(prompt_texts, generate_options)
+                .into_iter()
+                .map(|output| output.indices)
+                .collect(),
+            Self::Bloom(ref model) => model
+                .generate_indices(prompt_texts, generate_options)
+                .into_iter()
+                .map(|output| output.indices)
+                .collect(),
             Self::XLNet(ref model) => model
                 .generate_indices(prompt_texts, generate_options)
                 .into_iter()
@@ -285,11 +432,179 @@ impl TextGenerationOption {
         }
     }
 
+    /// Interface method to generate_indices_stream() of the particular models.
+    pub fn generate_indices_stream<S, F>(
+        &self,
+        prompt_texts: Option<&[S]>,
+        min_length: Option<i64>,
+        max_length: Option<i64>,
+        callback: F,
+    ) -> Result<Vec<Vec<i64>>, RustBertError>
+    where
+        S: AsRef<str> + Sync,
+        F: FnMut(&StreamedToken) -> bool,
+    {
+        let generate_options = Some(GenerateOptions {
+            min_length,
+            max_length,
+            ..Default::default()
+        });
+        let outputs = match *self {
+            Self::GPT(ref model) => {
+                model.generate_indices_stream(prompt_texts, generate_options, callback)?
+            }
+            Self::GPT2(ref model) => {
+                model.generate_indices_stream(prompt_texts, generate_options, callback)?
+            }
+            Self::GPTNeo(ref model) => {
+                model.generate_indices_stream(prompt_texts, generate_options, callback)?
+            }
+            Self::GPTBigCode(ref model) => {
+                model.generate_indices_stream(prompt_texts, generate_options, callback)?
+            }
+            Self::Llama(ref model) => {
+                model.generate_indices_stream(prompt_texts, generate_options, callback)?
+            }
+            Self::GPTNeoX(ref model) => {
+                model.generate_indices_stream(prompt_texts, generate_options, callback)?
+            }
+            Self::Falcon(ref model) => {
+                model.generate_indices_stream(prompt_texts, generate_options, callback)?
+            }
+            Self::Bloom(ref model) => {
+                model.generate_indices_stream(prompt_texts, generate_options, callback)?
+            }
+            Self::XLNet(ref model) => {
+                model.generate_indices_stream(prompt_texts, generate_options, callback)?
+            }
+            Self::Reformer(ref model) => {
+                model.generate_indices_stream(prompt_texts, generate_options, callback)?
+            }
+        };
+        Ok(outputs.into_iter().map(|output| output.indices).collect())
+    }
+
+    /// Interface method to generate_from_ids_and_past() of the particular models, returning the generated indices
+    /// (including the input indices)
+    pub fn generate_from_ids_and_past(
+        &self,
+        input_ids: Tensor,
+        attention_mask: Option<Tensor>,
+        generate_options: Option<GenerateOptions>,
+    ) -> Vec<Vec<i64>> {
+        let outputs = match *self {
+            Self::GPT(ref model) => {
+                model.generate_from_ids_and_past(input_ids, attention_mask, generate_options)
+            }
+            Self::GPT2(ref model) => {
+                model.generate_from_ids_and_past(input_ids, attention_mask, generate_options)
+            }
+            Self::GPTNeo(ref model) => {
+                model.generate_from_ids_and_past(input_ids, attention_mask, generate_options)
+            }
+            Self::GPTBigCode(ref model) => {
+                model.generate_from_ids_and_past(input_ids, attention_mask, generate_options)
+            }
+            Self::Llama(ref model) => {
+                model.generate_from_ids_and_past(input_ids, attention_mask, generate_options)
+            }
+            Self::GPTNeoX(ref model) => {
+                model.generate_from_ids_and_past(input_ids, attention_mask, generate_options)
+            }
+            Self::Falcon(ref model) => {
+                model.generate_from_ids_and_past(input_ids, attention_mask, generate_options)
+            }
+            Self::Bloom(ref model) => {
+                model.generate_from_ids_and_past(input_ids, attention_mask, generate_options)
+            }
+            Self::XLNet(ref model) => {
+                model.generate_from_ids_and_past(input_ids, attention_mask, generate_options)
+            }
+            Self::Reformer(ref model) => {
+                model.generate_from_ids_and_past(input_ids, attention_mask, generate_options)
+            }
+        };
+        outputs.into_iter().map(|output| output.indices).collect()
+    }
+
+    /// Interface method to score_sequences() of the particular models.
+    pub fn score_sequences<S>(
+        &self,
+        prompts: &[S],
+        continuations: &[S],
+    ) -> Result<Vec<Vec<f64>>, RustBertError>
+    where
+        S: AsRef<str> + Sync,
+    {
+        match *self {
+            Self::GPT(ref model) => model.score_sequences(prompts, continuations),
+            Self::GPT2(ref model) => model.score_sequences(prompts, continuations),
+            Self::GPTNeo(ref model) => model.score_sequences(prompts, continuations),
+            Self::GPTBigCode(ref model) => model.score_sequences(prompts, continuations),
+            Self::Llama(ref model) => model.score_sequences(prompts, continuations),
+            Self::GPTNeoX(ref model) => model.score_sequences(prompts, continuations),
+            Self::Falcon(ref model) => model.score_sequences(prompts, continuations),
+            Self::Bloom(ref model) => model.score_sequences(prompts, continuations),
+            Self::XLNet(ref model) => model.score_sequences(prompts, continuations),
+            Self::Reformer(ref model) => model.score_sequences(prompts, continuations),
+        }
+    }
+
+    pub(crate) fn get_eos_ids(&self) -> Option<&Vec<i64>> {
+        match self {
+            Self::GPT(model_ref) => model_ref.get_eos_ids(),
+            Self::GPT2(model_ref) => model_ref.get_eos_ids(),
+            Self::GPTNeo(model_ref) => model_ref.get_eos_ids(),
+            Self::GPTBigCode(model_ref) => model_ref.get_eos_ids(),
+            Self::Llama(model_ref) => model_ref.get_eos_ids(),
+            Self::GPTNeoX(model_ref) => model_ref.get_eos_ids(),
+            Self::Falcon(model_ref) => model_ref.get_eos_ids(),
+            Self::Bloom(model_ref) => model_ref.get_eos_ids(),
+            Self::XLNet(model_ref) => model_ref.get_eos_ids(),
+            Self::Reformer(model_ref) => model_ref.get_eos_ids(),
+        }
+    }
+
+    pub(crate) fn get_pad_id(&self) -> Option<i64> {
+        match self {
+            Self::GPT(model_ref) => model_ref.get_pad_id(),
+            Self::GPT2(model_ref) => model_ref.get_pad_id(),
+            Self::GPTNeo(model_ref) => model_ref.get_pad_id(),
+            Self::GPTBigCode(model_ref) => model_ref.get_pad_id(),
+            Self::Llama(model_ref) => model_ref.get_pad_id(),
+            Self::GPTNeoX(model_ref) => model_ref.get_pad_id(),
+            Self::Falcon(model_ref) => model_ref.get_pad_id(),
+            Self::Bloom(model_ref) => model_ref.get_pad_id(),
+            Self::XLNet(model_ref) => model_ref.get_pad_id(),
+            Self::Reformer(model_ref) => model_ref.get_pad_id(),
+        }
+    }
+
+    pub(crate) fn get_var_store(&self) -> &VarStore {
+        match self {
+            Self::GPT(model_ref) => model_ref.get_var_store(),
+            Self::GPT2(model_ref) => model_ref.get_var_store(),
+            Self::GPTNeo(model_ref) => model_ref.get_var_store(),
+            Self::GPTBigCode(model_ref) => model_ref.get_var_store(),
+            Self::Llama(model_ref) => model_ref.get_var_store(),
+            Self::GPTNeoX(model_ref) => model_ref.get_var_store(),
+            Self::Falcon(model_ref) => model_ref.get_var_store(),
+            Self::Bloom(model_ref) => model_ref.get_var_store(),
+            Self::XLNet(model_ref) => model_ref.get_var_store(),
+            Self::Reformer(model_ref) => model_ref.get_var_store(),
+        }
+    }
+
     pub fn half(&mut self) {
         match self {
             Self::GPT(model_ref) => model_ref.half(),
             Self::GPT2(model_ref) => model_ref.half(),
             Self::GPTNeo(model_ref) => model_ref.half(),
+            Self::GPTBigCode(model_ref) => model_ref.half(),
+            Self::Llama(model_ref) => model_ref.half(),
+            Self::GPTNeoX(model_ref) => model_ref.half(),
+            Self::Falcon(model_ref) => model_ref.half(),
+            Self::Bloom(model_ref) => model_ref.half(),
             Self::XLNet(model_ref) => model_ref.half(),
             Self::Reformer(model_ref) => model_ref.half(),
         }
@@ -300,6 +615,11 @@ impl TextGenerationOption {
             Self::GPT(model_ref) => model_ref.float(),
             Self::GPT2(model_ref) => model_ref.float(),
             Self::GPTNeo(model_ref) => model_ref.float(),
+            Self::GPTBigCode(model_ref) => model_ref.float(),
+            Self::Llama(model_ref) => model_ref.float(),
+            Self::GPTNeoX(model_ref) => model_ref.float(),
+            Self::Falcon(model_ref) => model_ref.float(),
+            Self::Bloom(model_ref) => model_ref.float(),
             Self::XLNet(model_ref) => model_ref.float(),
             Self::Reformer(model_ref) => model_ref.float(),
         }
@@ -310,6 +630,11 @@ impl TextGenerationOption {
             Self::GPT(model_ref) => model_ref.set_device(device),
             Self::GPT2(model_ref) => model_ref.set_device(device),
             Self::GPTNeo(model_ref) => model_ref.set_device(device),
+            Self::GPTBigCode(model_ref) => model_ref.set_device(device),
+            Self::Llama(model_ref) => model_ref.set_device(device),
+            Self::GPTNeoX(model_ref) => model_ref.set_device(device),
+            Self::Falcon(model_ref) => model_ref.set_device(device),
+            Self::Bloom(model_ref) => model_ref.set_device(device),
             Self::XLNet(model_ref) => model_ref.set_device(device),
             Self::Reformer(model_ref) => model_ref.set_device(device),
         }
@@ -323,6 +648,11 @@ pub struct TextGenerationModel {
     prefix_length: Option<i64>,
     min_length: i64,
     max_length: i64,
+    sequences_per_input: i64,
+    memory_estimator: MemoryEstimator,
+    memory_budget: Option<usize>,
+    memory_pool: Option<Mutex<MemoryPool>>,
+    stop_sequences: Vec<String>,
 }
 
 impl TextGenerationModel {
@@ -366,7 +696,25 @@ with people, even a bishop, begging for his blessing. <eod> </s> <eos>"
 
         let min_length = generation_config.min_length;
         let max_length = generation_config.max_length;
+        // Each prompt is expanded to `num_beams` (times `num_return_sequences` when sampling) sequences
+        let sequences_per_input = generation_config.num_beams.max(1)
+            * if generation_config.do_sample {
+                generation_config.num_return_sequences.max(1)
+            } else {
+                1
+            };
+        let model_type = generation_config.model_type;
+        let config_path = generation_config.config_resource.get_local_path()?;
+        let memory_estimator = MemoryEstimator::from_file(&config_path)?;
+        let stop_sequences = generation_config
+            .stop_sequences
+            .iter()
+            .filter(|stop_sequence| !stop_sequence.is_empty())
+            .cloned()
+            .collect();
         let model = TextGenerationOption::new(generation_config)?;
+        ConfigOption::from_file(model_type, config_path)?
+            .validate(Some(model.get_tokenizer()), Some(max_length))?;
         let prefix_length = prefix
             .as_ref()
             .map(|prefix| model.get_tokenizer().tokenize(prefix).len() as i64);
@@ -377,9 +725,83 @@ with people, even a bishop, begging for his blessing. <eod> </s> <eos>"
             prefix_length,
             min_length,
             max_length,
+            sequences_per_input,
+            memory_estimator,
+            memory_budget: None,
+            memory_pool: None,
+            stop_sequences,
         })
     }
 
+    /// Returns the memory usage statistics of the model for the generation of a batch of texts, including
+    /// the keys and values cache for sequences of the maximum length.
+    ///
+    /// # Arguments
+    ///
+    /// * `batch_size` - Number of prompts processed at once
+    pub fn memory_statistics(&self, batch_size: usize) -> MemoryStatistics {
+        let sequence_length = self.max_length + self.prefix_length.unwrap_or(0);
+        self.memory_estimator.estimate_generation(
+            self.model.get_var_store(),
+            batch_size * self.sequences_per_input as usize,
+            sequence_length as usize,
+        )
+    }
+
+    /// Sets the memory budget (in bytes) of the model. Batches predicted to exceed it are rejected by `Pipeline::run`.
+    ///
+    /// # Arguments
+    ///
+    /// * `memory_budget` - Optional memory budget in bytes (default: None, no limit)
+    pub fn set_memory_budget(&mut self, memory_budget: Option<usize>) {
+        self.memory_budget = memory_budget;
+    }
+
+    /// Sets the memory pool of the model, maintained after each generated batch (see `MemoryPool`)
+    ///
+    /// # Arguments
+    ///
+    /// * `memory_pool` - Optional `MemoryPool` created on the device of the model (default: None)
+    pub fn set_memory_pool(&mut self, memory_pool: Option<MemoryPool>) {
+        self.memory_pool = memory_pool.map(Mutex::new);
+    }
+
+    /// Records the completion of a batch in the memory pool, if any
+    pub(crate) fn batch_completed(&self) {
+        if let Some(memory_pool) = &self.memory_pool {
+            if let Ok(mut memory_pool) = memory_pool.lock() {
+                memory_pool.batch_completed();
+            }
+        }
+    }
+
+    /// Returns a reference to the text generation model tokenizer
+    pub fn get_tokenizer(&self) -> &TokenizerOption {
+        self.model.get_tokenizer()
+    }
+
+    pub(crate) fn get_model(&self) -> &TextGenerationOption {
+        &self.model
+    }
+
+    /// Scores continuations of prompt texts, returning the log-probability of each token of the continuations
+    /// (see `LanguageGenerator::score_sequences`)
+    ///
+    /// # Arguments
+    ///
+    /// * `prompts` - Prompt texts (cannot be empty)
+    /// * `continuations` - Continuations to score, one for each prompt
+    pub fn score_sequences<S>(
+        &self,
+        prompts: &[S],
+        continuations: &[S],
+    ) -> Result<Vec<Vec<f64>>, RustBertError>
+    where
+        S: AsRef<str> + Sync,
+    {
+        self.model.score_sequences(prompts, continuations)
+    }
+
     pub fn half(&mut self) {
         self.model.half();
     }
@@ -422,14 +844,7 @@ with people, even a bishop, begging for his blessing. <eod> </s> <eos>"
     where
         S: AsRef<str> + Sync,
     {
-        let (prefix, prefix_length) = match (prefix.into(), &self.prefix) {
-            (Some(query_prefix), _) => (
-                Some(query_prefix),
-                Some(self.model.get_tokenizer().tokenize(query_prefix).len() as i64),
-            ),
-            (None, Some(pipeline_prefix)) => (Some(pipeline_prefix.as_str()), self.prefix_length),
-            (None, None) => (None, None),
-        };
+        let (prefix, prefix_length) = self.get_prefix_and_length(prefix.into());
         let generated_indices = match (prefix, prefix_length) {
             (None, _) => self.model.generate_indices(Some(texts), None, None),
             (Some(prefix), Some(prefix_length)) => {
@@ -446,19 +861,246 @@ with people, even a bishop, begging for his blessing. <eod> </s> <eos>"
             }
             _ => panic!("Prefix length not defined but prefix provided!"),
         };
+        self.batch_completed();
+
+        self.decode_without_prefix(generated_indices, prefix_length)
+    }
 
+    /// Generate texts from provided prompts, calling a function with each token as soon as it is generated
+    /// (see `LanguageGenerator::generate_stream`). Streaming requires a model configured with `num_beams` = 1.
+    ///
+    /// # Arguments
+    ///
+    /// * `input` - `&[&str]` Array of prompts.
+    /// * `prefix` - `impl Into<Option<&'a str>>`: Optional string to pass as a prefix for generation. Will be excluded from generated sequences.
+    /// * `callback` - Function called with each `StreamedToken`. The generation stops if the function returns false.
+    ///
+    /// # Returns
+    /// * `Vec<String>` Generated texts, as returned by `generate`
+    ///
+    /// # Example
+    ///
+    /// ```no_run
+    /// # fn main() -> anyhow::Result<()> {
+    /// use rust_bert::pipelines::text_generation::{TextGenerationConfig, TextGenerationModel};
+    ///
+    /// let config = TextGenerationConfig {
+    ///     num_beams: 1,
+    ///     ..Default::default()
+    /// };
+    /// let model = TextGenerationModel::new(config)?;
+    ///
+    /// let output = model.generate_stream(&["The dog"], None, |token| {
+    ///     print!("{}", token.text);
+    ///     true
+    /// })?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn generate_stream<'a, S, F>(
+        &self,
+        texts: &[S],
+        prefix: impl Into<Option<&'a str>>,
+        callback: F,
+    ) -> Result<Vec<String>, RustBertError>
+    where
+        S: AsRef<str> + Sync,
+        F: FnMut(&StreamedToken) -> bool,
+    {
+        let (prefix, prefix_length) = self.get_prefix_and_length(prefix.into());
+        let generated_indices = match (prefix, prefix_length) {
+            (None, _) => self
+                .model
+                .generate_indices_stream(Some(texts), None, None, callback)?,
+            (Some(prefix), Some(prefix_length)) => {
+                let texts = texts
+                    .iter()
+                    .map(|text| format!("{} {}", prefix, text.as_ref()))
+                    .collect::<Vec<String>>();
+                self.model.generate_indices_stream(
+                    Some(&texts),
+                    Some(self.min_length + prefix_length),
+                    Some(self.max_length + prefix_length),
+                    callback,
+                )?
+            }
+            _ => panic!("Prefix length not defined but prefix provided!"),
+        };
+        self.batch_completed();
+        Ok(self.decode_without_prefix(generated_indices, prefix_length))
+    }
+
+    /// Generates the code between prefixes and suffixes with a model trained with a fill-in-the-middle objective
+    /// (e.g. StarCoder). The prompts are built from the token ids of the texts and of the special tokens, which are
+    /// therefore not split by tokenizers unaware of them. The generation of each middle stops at the end of sequence
+    /// token or at the next fill-in-the-middle token, and is limited to `max_length` new tokens.
+    ///
+    /// # Arguments
+    ///
+    /// * `inputs` - `&[(S, S)]` Pairs of prefix and suffix texts
+    /// * `fim_tokens` - `FillInTheMiddleTokens` of the model vocabulary
+    ///
+    /// # Returns
+    /// * `Result<Vec<String>, RustBertError>` Generated middles (`num_return_sequences` for each input when sampling)
+    ///
+    /// # Example
+    ///
+    /// ```no_run
+    /// # fn main() -> anyhow::Result<()> {
+    /// use rust_bert::pipelines::text_generation::{FillInTheMiddleTokens, TextGenerationModel};
+    /// # let config = Default::default();
+    /// let model = TextGenerationModel::new(config)?;
+    ///
+    /// let output = model.fill_in_the_middle(
+    ///     &[("def add(a, b):\n    ", "\n\nprint(add(1, 2))")],
+    ///     &FillInTheMiddleTokens::STARCODER,
+    /// )?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn fill_in_the_middle<S>(
+        &self,
+        inputs: &[(S, S)],
+        fim_tokens: &FillInTheMiddleTokens,
+    ) -> Result<Vec<String>, RustBertError>
+    where
+        S: AsRef<str>,
+    {
+        if inputs.is_empty() {
+            return Ok(Vec::new());
+        }
+        let tokenizer = self.model.get_tokenizer();
+        let unk_id = tokenizer.get_unk_id();
+        let fim_ids = [fim_tokens.prefix, fim_tokens.suffix, fim_tokens.middle]
+            .iter()
+            .map(|token| match tokenizer.convert_tokens_to_ids(&[token])[0] {
+                token_id if token_id == unk_id => Err(RustBertError::ValueError(format!(
+                    "The fill-in-the-middle token {} is not in the vocabulary",
+                    token
+                ))),
+                token_id => Ok(token_id),
+            })
+            .collect::<Result<Vec<i64>, RustBertError>>()?;
+
+        let prompts = inputs
+            .iter()
+            .map(|(prefix, suffix)| {
+                let mut token_ids = vec![fim_ids[0]];
+                token_ids
+                    .extend(tokenizer.convert_tokens_to_ids(&tokenizer.tokenize(prefix.as_ref())));
+                token_ids.push(fim_ids[1]);
+                token_ids
+                    .extend(tokenizer.convert_tokens_to_ids(&tokenizer.tokenize(suffix.as_ref())));
+                token_ids.push(fim_ids[2]);
+                token_ids
+            })
+            .collect::<Vec<Vec<i64>>>();
+
+        // Prompts are padded on the left so that the middles are generated from the same position
+        let prompt_length = prompts.iter().map(Vec::len).max().unwrap();
+        let pad_id = self
+            .model
+            .get_pad_id()
+            .or_else(|| self.model.get_eos_ids().map(|eos_ids| eos_ids[0]))
+            .unwrap_or(unk_id);
+        let (input_ids, attention_mask): (Vec<Tensor>, Vec<Tensor>) = prompts
+            .iter()
+            .map(|token_ids| {
+                let padding_length = prompt_length - token_ids.len();
+                let mut padded_ids = vec![pad_id; padding_length];
+                padded_ids.extend(token_ids);
+                let mut mask = vec![0i64; padding_length];
+                mask.extend(vec![1i64; token_ids.len()]);
+                (Tensor::of_slice(&padded_ids), Tensor::of_slice(&mask))
+            })
+            .unzip();
+        let device = self.model.get_var_store().device();
+        let input_ids = Tensor::stack(&input_ids, 0).to(device);
+        let attention_mask = Tensor::stack(&attention_mask, 0).to(device);
+
+        let generate_options = GenerateOptions {
+            max_new_tokens: Some(self.max_length),
+            ..Default::default()
+        };
+        let generated_indices = self.model.generate_from_ids_and_past(
+            input_ids,
+            Some(attention_mask),
+            Some(generate_options),
+        );
+        self.batch_completed();
+
+        Ok(generated_indices
+            .into_iter()
+            .map(|generated_sequence| {
+                let middle = &generated_sequence[prompt_length..];
+                let middle_length = middle
+                    .iter()
+                    .position(|token_id| fim_ids.contains(token_id))
+                    .unwrap_or(middle.len());
+                decode_before_stop_sequence(
+                    tokenizer,
+                    &middle[..middle_length],
+                    &self.stop_sequences,
+                )
+            })
+            .collect())
+    }
+
+    fn get_prefix_and_length<'a>(
+        &'a self,
+        prefix: Option<&'a str>,
+    ) -> (Option<&'a str>, Option<i64>) {
+        match (prefix, &self.prefix) {
+            (Some(query_prefix), _) => (
+                Some(query_prefix),
+                Some(self.model.get_tokenizer().tokenize(query_prefix).len() as i64),
+            ),
+            (None, Some(pipeline_prefix)) => (Some(pipeline_prefix.as_str()), self.prefix_length),
+            (None, None) => (None, None),
+        }
+    }
+
+    fn decode_without_prefix(
+        &self,
+        generated_indices: Vec<Vec<i64>>,
+        prefix_length: Option<i64>,
+    ) -> Vec<String> {
         let mut output = Vec::with_capacity(generated_indices.len());
         for generated_sequence in generated_indices {
-            output.push(self.model.get_tokenizer().decode(
+            output.push(decode_before_stop_sequence(
+                self.model.get_tokenizer(),
                 &generated_sequence[prefix_length.unwrap_or(0) as usize..],
-                true,
-                true,
+                &self.stop_sequences,
             ));
         }
         output
     }
 }
 
+/// # Options of the text generation pipeline when run as a `Pipeline`
+#[derive(Debug, Clone, Default)]
+pub struct TextGenerationOptions {
+    /// Prefix prepended to the inputs, replacing the prefix of the pipeline if provided (default: None)
+    pub prefix: Option<String>,
+}
+
+impl<S> Pipeline<S, String> for TextGenerationModel
+where
+    S: AsRef<str> + Sync,
+{
+    type Options = TextGenerationOptions;
+
+    fn run_with_options(
+        &self,
+        inputs: &[S],
+        options: &TextGenerationOptions,
+    ) -> Result<Vec<String>, RustBertError> {
+        self.memory_statistics(inputs.len())
+            .check_budget(self.memory_budget)?;
+        Ok(self.generate(inputs, options.prefix.as_deref()))
+    }
+}
+
 #[cfg(test)]
 mod test {
     use super::*;
@@ -0,0 +1,638 @@
+// Copyright 2022 Guillaume Becquin
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//     http://www.apache.org/licenses/LICENSE-2.0
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! # Casing, diacritics and punctuation restoration pipeline
+//! Restores the capitalization, diacritics and punctuation of lower-cased, unpunctuated text such as the output of
+//! speech recognition systems (e.g. "je suis alle a paris" -> "Je suis allé à Paris."). A token classification
+//! model predicts a restoration label for each word, which is applied to the original text (the whitespace of the
+//! input is preserved).
+//!
+//! Labels are made of three optional parts, in order:
+//! - a casing code: `L` (lower case), `C` (capitalized), `U` (upper case) or `M` (mixed case, e.g. "iPhone", taken
+//! from the lexicon),
+//! - a `D` flag indicating that the word carries diacritics (restored from the lexicon),
+//! - the punctuation inserted after the word (e.g. `.`, `,`, `?`).
+//!
+//! For example `C.` capitalizes a word and appends a period, `LD` restores the diacritics of a lower-cased word
+//! and `O` (or `0`) leaves the word unchanged. Models trained with another label set can be used by providing a
+//! mapping from their labels to `RestorationLabel`s.
+//!
+//! Diacritics are not predicted character by character: the model decides whether a word carries diacritics, and
+//! the accented form is looked up in a `WordLexicon` built from a list of correctly spelled words. This resolves
+//! the ambiguous words (e.g. French "a"/"à", "ou"/"où") in context while keeping the label set small.
+//!
+//! ```no_run
+//! # fn main() -> anyhow::Result<()> {
+//! use rust_bert::pipelines::common::ModelType;
+//! use rust_bert::pipelines::text_restoration::{
+//!     TextRestorationConfig, TextRestorationModel, WordLexicon,
+//! };
+//! use rust_bert::pipelines::token_classification::{
+//!     LabelAggregationOption, TokenClassificationConfig,
+//! };
+//! use rust_bert::resources::LocalResource;
+//! use std::path::PathBuf;
+//!
+//! let token_classification_config = TokenClassificationConfig::new(
+//!     ModelType::Bert,
+//!     LocalResource::from(PathBuf::from("path/to/rust_model.ot")),
+//!     LocalResource::from(PathBuf::from("path/to/config.json")),
+//!     LocalResource::from(PathBuf::from("path/to/vocab.txt")),
+//!     None,
+//!     true,
+//!     true,
+//!     None,
+//!     LabelAggregationOption::First,
+//! );
+//! let mut config = TextRestorationConfig::new(token_classification_config);
+//! config.lexicon = Some(WordLexicon::from_words(&["allé", "à", "où", "Paris"]));
+//! let model = TextRestorationModel::new(config)?;
+//!
+//! let output = model.restore(&["je suis alle a paris"]);
+//! # Ok(())
+//! # }
+//! ```
+
+use crate::common::error::RustBertError;
+use crate::pipelines::common::Pipeline;
+use crate::pipelines::token_classification::{
+    Token, TokenClassificationConfig, TokenClassificationModel,
+};
+use rust_tokenizers::Offset;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+
+/// Accented lower case letters and their base letter
+const DIACRITIC_LETTERS: &[(&str, char)] = &[
+    ("àáâãäåāăą", 'a'),
+    ("çćĉċč", 'c'),
+    ("ďđ", 'd'),
+    ("èéêëēĕėęě", 'e'),
+    ("ĝğġģ", 'g'),
+    ("ĥħ", 'h'),
+    ("ìíîïĩīĭįı", 'i'),
+    ("ĵ", 'j'),
+    ("ķ", 'k'),
+    ("ĺļľŀł", 'l'),
+    ("ñńņňŉ", 'n'),
+    ("òóôõöøōŏő", 'o'),
+    ("ŕŗř", 'r'),
+    ("śŝşšș", 's'),
+    ("ţťŧț", 't'),
+    ("ùúûüũūŭůűų", 'u'),
+    ("ŵ", 'w'),
+    ("ýÿŷ", 'y'),
+    ("źżž", 'z'),
+];
+
+/// Returns the lower-cased word without its diacritics (Latin-1 and Latin Extended-A letters)
+///
+/// # Example
+///
+/// ```
+/// use rust_bert::pipelines::text_restoration::strip_diacritics;
+///
+/// assert_eq!(strip_diacritics("Allé"), "alle");
+/// assert_eq!(strip_diacritics("Łódź"), "lodz");
+/// ```
+pub fn strip_diacritics(word: &str) -> String {
+    word.to_lowercase()
+        .chars()
+        .map(|character| {
+            DIACRITIC_LETTERS
+                .iter()
+                .find(|(letters, _)| letters.contains(character))
+                .map(|(_, base)| *base)
+                .unwrap_or(character)
+        })
+        .collect()
+}
+
+/// # Casing of a restored word
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum Casing {
+    /// All letters in lower case
+    Lower,
+    /// First letter in upper case
+    Capitalized,
+    /// All letters in upper case
+    Upper,
+    /// Mixed case form taken from the lexicon (e.g. "iPhone", "McDonald")
+    Mixed,
+}
+
+impl Casing {
+    fn from_code(code: char) -> Option<Casing> {
+        match code {
+            'L' => Some(Casing::Lower),
+            'C' => Some(Casing::Capitalized),
+            'U' => Some(Casing::Upper),
+            'M' => Some(Casing::Mixed),
+            _ => None,
+        }
+    }
+
+    /// Returns the casing pattern of a word (`None` for words without letters)
+    pub fn of(word: &str) -> Option<Casing> {
+        let mut letters = word.chars().filter(|c| c.is_alphabetic());
+        let first = letters.next()?;
+        let rest = letters.collect::<Vec<char>>();
+        Some(if first.is_uppercase() {
+            if rest.iter().all(|c| c.is_lowercase()) {
+                Casing::Capitalized
+            } else if rest.iter().all(|c| c.is_uppercase()) && !rest.is_empty() {
+                Casing::Upper
+            } else {
+                Casing::Mixed
+            }
+        } else if rest.iter().all(|c| c.is_lowercase()) {
+            Casing::Lower
+        } else {
+            Casing::Mixed
+        })
+    }
+}
+
+/// # Restoration predicted for a word
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct RestorationLabel {
+    /// Casing of the word (`None` keeps the casing of the input)
+    pub casing: Option<Casing>,
+    /// The word carries diacritics, restored from the lexicon
+    pub diacritics: bool,
+    /// Punctuation inserted after the word
+    pub punctuation: Option<String>,
+}
+
+impl RestorationLabel {
+    /// Parses a label made of an optional casing code (`L`, `C`, `U` or `M`), an optional diacritics flag (`D`)
+    /// and optional punctuation. `O`, `0` and unknown labels leave the word unchanged.
+    ///
+    /// # Arguments
+    ///
+    /// * `label` - Label predicted by the token classification model
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use rust_bert::pipelines::text_restoration::{Casing, RestorationLabel};
+    ///
+    /// let label = RestorationLabel::parse("CD?");
+    /// assert_eq!(label.casing, Some(Casing::Capitalized));
+    /// assert!(label.diacritics);
+    /// assert_eq!(label.punctuation.as_deref(), Some("?"));
+    /// ```
+    pub fn parse(label: &str) -> RestorationLabel {
+        if matches!(label, "O" | "0") {
+            return RestorationLabel::default();
+        }
+        let mut chars = label.chars().peekable();
+        let casing = chars.peek().and_then(|code| Casing::from_code(*code));
+        if casing.is_some() {
+            chars.next();
+        }
+        let diacritics = chars.peek() == Some(&'D');
+        if diacritics {
+            chars.next();
+        }
+        let punctuation = chars.collect::<String>();
+        if punctuation
+            .chars()
+            .any(|c| c.is_alphanumeric() || c.is_whitespace())
+        {
+            return RestorationLabel::default();
+        }
+        RestorationLabel {
+            casing,
+            diacritics,
+            punctuation: if punctuation.is_empty() {
+                None
+            } else {
+                Some(punctuation)
+            },
+        }
+    }
+}
+
+/// # Lexicon of word surface forms
+/// Maps words, without casing and diacritics, to their correctly accented and cased forms. Used to restore the
+/// diacritics and mixed casing of words.
+#[derive(Debug, Clone, Default)]
+pub struct WordLexicon {
+    forms: HashMap<String, Vec<String>>,
+}
+
+impl WordLexicon {
+    /// Builds a lexicon from a list of words. When several forms share the same key (e.g. "a" and "à"), the
+    /// earlier forms of the list are preferred: lists sorted by decreasing frequency should be used.
+    ///
+    /// # Arguments
+    ///
+    /// * `words` - Correctly accented and cased words
+    pub fn from_words<S: AsRef<str>>(words: &[S]) -> WordLexicon {
+        let mut forms: HashMap<String, Vec<String>> = HashMap::new();
+        for word in words {
+            let word = word.as_ref().trim();
+            if word.is_empty() {
+                continue;
+            }
+            let entry = forms.entry(strip_diacritics(word)).or_insert_with(Vec::new);
+            if !entry.iter().any(|form| form == word) {
+                entry.push(word.to_string());
+            }
+        }
+        WordLexicon { forms }
+    }
+
+    /// Returns the number of distinct keys (words without casing and diacritics) in the lexicon
+    pub fn len(&self) -> usize {
+        self.forms.len()
+    }
+
+    /// Returns true if the lexicon contains no words
+    pub fn is_empty(&self) -> bool {
+        self.forms.is_empty()
+    }
+
+    /// Returns the known forms of a word, ignoring its casing and diacritics
+    pub fn forms(&self, word: &str) -> &[String] {
+        self.forms
+            .get(&strip_diacritics(word))
+            .map(|forms| forms.as_slice())
+            .unwrap_or(&[])
+    }
+
+    /// Returns the preferred accented form of a word, if any
+    pub fn diacritized(&self, word: &str) -> Option<&str> {
+        self.forms(word)
+            .iter()
+            .find(|form| form.to_lowercase() != strip_diacritics(form))
+            .map(|form| form.as_str())
+    }
+
+    /// Returns the preferred mixed case form of a word with the same diacritics, if any
+    pub fn mixed_case(&self, word: &str) -> Option<&str> {
+        let lower_case = word.to_lowercase();
+        self.forms(word)
+            .iter()
+            .find(|form| {
+                Casing::of(form) == Some(Casing::Mixed) && form.to_lowercase() == lower_case
+            })
+            .map(|form| form.as_str())
+    }
+}
+
+fn apply_casing(word: &str, casing: Casing, lexicon: Option<&WordLexicon>) -> String {
+    match casing {
+        Casing::Lower => word.to_lowercase(),
+        Casing::Upper => word.to_uppercase(),
+        Casing::Capitalized => {
+            let mut chars = word.chars();
+            match chars.next() {
+                Some(first) => first
+                    .to_uppercase()
+                    .chain(chars.flat_map(char::to_lowercase))
+                    .collect(),
+                None => String::new(),
+            }
+        }
+        Casing::Mixed => match lexicon.and_then(|lexicon| lexicon.mixed_case(word)) {
+            Some(form) => form.to_string(),
+            None => apply_casing(word, Casing::Capitalized, None),
+        },
+    }
+}
+
+/// # Word restored by a `TextRestorationModel`
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct RestoredWord {
+    /// Word in the input text
+    pub original: String,
+    /// Restored word, including the inserted punctuation
+    pub restored: String,
+    /// Label predicted by the model
+    pub label: String,
+    /// Confidence score of the label
+    pub score: f64,
+    /// Word offsets (in characters of the input string)
+    pub offset: Offset,
+}
+
+/// # Output of the text restoration pipeline for one input
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct TextRestorationOutput {
+    /// Restored text
+    pub text: String,
+    /// Words of the input, with their restored form and label
+    pub words: Vec<RestoredWord>,
+}
+
+/// # Configuration for TextRestorationModel
+pub struct TextRestorationConfig {
+    /// Configuration of the token classification model predicting the restoration labels
+    pub token_classification_config: TokenClassificationConfig,
+    /// Lexicon used to restore diacritics and mixed casing (default: None)
+    pub lexicon: Option<WordLexicon>,
+    /// Restoration of the labels that do not follow the default label format (default: empty)
+    pub label_mapping: HashMap<String, RestorationLabel>,
+    /// Capitalize the first word of the text and of each sentence, whatever the predicted casing (default: true)
+    pub capitalize_sentences: bool,
+    /// Labels with a score below this threshold are ignored (default: 0.0)
+    pub min_score: f64,
+}
+
+impl TextRestorationConfig {
+    /// Instantiate a new text restoration configuration
+    ///
+    /// # Arguments
+    ///
+    /// * `token_classification_config` - `TokenClassificationConfig` of the model predicting the restoration labels.
+    /// The label of the first sub-token of each word should be used (`LabelAggregationOption::First`).
+    pub fn new(token_classification_config: TokenClassificationConfig) -> TextRestorationConfig {
+        TextRestorationConfig {
+            token_classification_config,
+            lexicon: None,
+            label_mapping: HashMap::new(),
+            capitalize_sentences: true,
+            min_score: 0.0,
+        }
+    }
+}
+
+/// Rules turning the labels predicted for the words of a text into the restored text
+struct RestorationRules {
+    lexicon: Option<WordLexicon>,
+    label_mapping: HashMap<String, RestorationLabel>,
+    capitalize_sentences: bool,
+    min_score: f64,
+}
+
+impl RestorationRules {
+    fn restoration_label(&self, token: &Token) -> RestorationLabel {
+        if token.score < self.min_score {
+            return RestorationLabel::default();
+        }
+        match self.label_mapping.get(&token.label) {
+            Some(label) => label.clone(),
+            None => RestorationLabel::parse(&token.label),
+        }
+    }
+
+    fn restore_word(&self, word: &str, label: &RestorationLabel, sentence_start: bool) -> String {
+        let mut restored = word.to_string();
+        if label.diacritics {
+            if let Some(form) = self
+                .lexicon
+                .as_ref()
+                .and_then(|lexicon| lexicon.diacritized(word))
+            {
+                restored = match Casing::of(word) {
+                    Some(casing) if label.casing.is_none() => {
+                        apply_casing(form, casing, self.lexicon.as_ref())
+                    }
+                    _ => form.to_string(),
+                };
+            }
+        }
+        if let Some(casing) = label.casing {
+            restored = apply_casing(&restored, casing, self.lexicon.as_ref());
+        }
+        if sentence_start
+            && self.capitalize_sentences
+            && matches!(Casing::of(&restored), Some(Casing::Lower))
+        {
+            restored = apply_casing(&restored, Casing::Capitalized, None);
+        }
+        restored
+    }
+
+    fn restore(&self, text: &str, tokens: &[Token]) -> TextRestorationOutput {
+        let mut tokens = tokens
+            .iter()
+            .filter(|token| token.byte_offset.is_some() && token.offset.is_some())
+            .collect::<Vec<&Token>>();
+        tokens.sort_by_key(|token| token.byte_offset.unwrap().begin);
+
+        let mut restored_text = String::with_capacity(text.len() + text.len() / 8);
+        let mut words = Vec::with_capacity(tokens.len());
+        let mut cursor = 0usize;
+        let mut sentence_start = true;
+        for token in tokens {
+            let byte_offset = token.byte_offset.unwrap();
+            let (begin, end) = (byte_offset.begin as usize, byte_offset.end as usize);
+            if begin < cursor || end > text.len() {
+                continue;
+            }
+            let separator = &text[cursor..begin];
+            if separator.contains(|c| matches!(c, '.' | '?' | '!')) {
+                sentence_start = true;
+            }
+            restored_text.push_str(separator);
+            cursor = end;
+
+            let original = &text[begin..end];
+            if !original.chars().any(char::is_alphanumeric) {
+                if original.contains(|c| matches!(c, '.' | '?' | '!')) {
+                    sentence_start = true;
+                }
+                restored_text.push_str(original);
+                continue;
+            }
+            let label = self.restoration_label(token);
+            let mut restored = self.restore_word(original, &label, sentence_start);
+            sentence_start = false;
+            let followed_by_punctuation = matches!(
+                text[end..].chars().next(),
+                Some(c) if !c.is_alphanumeric() && !c.is_whitespace()
+            );
+            if let Some(punctuation) = &label.punctuation {
+                if !followed_by_punctuation {
+                    restored.push_str(punctuation);
+                    if punctuation.contains(|c| matches!(c, '.' | '?' | '!')) {
+                        sentence_start = true;
+                    }
+                }
+            }
+            restored_text.push_str(&restored);
+            words.push(RestoredWord {
+                original: original.to_string(),
+                restored,
+                label: token.label.clone(),
+                score: token.score,
+                offset: token.offset.unwrap(),
+            });
+        }
+        restored_text.push_str(&text[cursor..]);
+        TextRestorationOutput {
+            text: restored_text,
+            words,
+        }
+    }
+}
+
+/// # TextRestorationModel to restore the casing, diacritics and punctuation of texts
+pub struct TextRestorationModel {
+    token_classification_model: TokenClassificationModel,
+    rules: RestorationRules,
+}
+
+impl TextRestorationModel {
+    /// Build a new `TextRestorationModel`
+    ///
+    /// # Arguments
+    ///
+    /// * `config` - `TextRestorationConfig` object containing the token classification model configuration and the restoration settings
+    pub fn new(config: TextRestorationConfig) -> Result<TextRestorationModel, RustBertError> {
+        let token_classification_model =
+            TokenClassificationModel::new(config.token_classification_config)?;
+        Ok(TextRestorationModel {
+            token_classification_model,
+            rules: RestorationRules {
+                lexicon: config.lexicon,
+                label_mapping: config.label_mapping,
+                capitalize_sentences: config.capitalize_sentences,
+                min_score: config.min_score,
+            },
+        })
+    }
+
+    /// Restores the casing, diacritics and punctuation of texts
+    ///
+    /// # Arguments
+    ///
+    /// * `input` - `&[&str]` Array of texts to restore (e.g. lower-cased speech recognition transcripts).
+    ///
+    /// # Returns
+    ///
+    /// * `Vec<TextRestorationOutput>` containing the restored texts and the labels predicted for each word
+    pub fn restore<S>(&self, input: &[S]) -> Vec<TextRestorationOutput>
+    where
+        S: AsRef<str>,
+    {
+        self.token_classification_model
+            .predict(input, true, false)
+            .into_iter()
+            .zip(input.iter())
+            .map(|(tokens, text)| self.rules.restore(text.as_ref(), &tokens))
+            .collect()
+    }
+}
+
+impl<S> Pipeline<S, TextRestorationOutput> for TextRestorationModel
+where
+    S: AsRef<str>,
+{
+    type Options = ();
+
+    fn run_with_options(
+        &self,
+        inputs: &[S],
+        _options: &(),
+    ) -> Result<Vec<TextRestorationOutput>, RustBertError> {
+        Ok(self.restore(inputs))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use rust_tokenizers::Mask;
+
+    fn tokens(text: &str, labels: &[&str]) -> Vec<Token> {
+        let mut byte_begin = 0;
+        let mut char_begin = 0;
+        text.split(' ')
+            .zip(labels.iter())
+            .enumerate()
+            .map(|(index, (word, label))| {
+                let byte_end = byte_begin + word.len();
+                let char_end = char_begin + word.chars().count();
+                let token = Token {
+                    text: word.to_string(),
+                    score: 0.9,
+                    label: label.to_string(),
+                    label_index: 0,
+                    sentence: 0,
+                    index: index as u16,
+                    word_index: index as u16,
+                    offset: Some(Offset::new(char_begin as u32, char_end as u32)),
+                    byte_offset: Some(Offset::new(byte_begin as u32, byte_end as u32)),
+                    utf16_offset: None,
+                    mask: Mask::None,
+                };
+                byte_begin = byte_end + 1;
+                char_begin = char_end + 1;
+                token
+            })
+            .collect()
+    }
+
+    fn rules(lexicon: Option<WordLexicon>) -> RestorationRules {
+        RestorationRules {
+            lexicon,
+            label_mapping: HashMap::new(),
+            capitalize_sentences: true,
+            min_score: 0.0,
+        }
+    }
+
+    #[test]
+    fn labels_parsed() {
+        assert_eq!(RestorationLabel::parse("O"), RestorationLabel::default());
+        assert_eq!(RestorationLabel::parse("PER"), RestorationLabel::default());
+        assert_eq!(
+            RestorationLabel::parse("U,"),
+            RestorationLabel {
+                casing: Some(Casing::Upper),
+                diacritics: false,
+                punctuation: Some(",".to_string()),
+            }
+        );
+        assert_eq!(
+            RestorationLabel::parse("."),
+            RestorationLabel {
+                casing: None,
+                diacritics: false,
+                punctuation: Some(".".to_string()),
+            }
+        );
+        assert_eq!(Casing::of("iPhone"), Some(Casing::Mixed));
+        assert_eq!(Casing::of("NASA"), Some(Casing::Upper));
+        assert_eq!(Casing::of("A"), Some(Casing::Capitalized));
+        assert_eq!(Casing::of("42"), None);
+    }
+
+    #[test]
+    fn casing_and_punctuation_restored() {
+        let text = "hello  how are you i use an iphone";
+        let labels = ["C,", "", "L", "L", "L?", "U", "L", "L", "M."];
+        let output =
+            rules(Some(WordLexicon::from_words(&["iPhone"]))).restore(text, &tokens(text, &labels));
+        assert_eq!(output.text, "Hello,  how are you? I use an iPhone.");
+        assert_eq!(output.words.len(), 8);
+        assert_eq!(output.words[7].original, "iphone");
+        assert_eq!(output.words[7].restored, "iPhone.");
+    }
+
+    #[test]
+    fn diacritics_restored_from_lexicon() {
+        let lexicon = WordLexicon::from_words(&["a", "à", "allé", "ou", "où", "Paris"]);
+        assert_eq!(lexicon.len(), 4);
+        assert_eq!(lexicon.diacritized("a"), Some("à"));
+        assert_eq!(lexicon.diacritized("ou"), Some("où"));
+        assert_eq!(lexicon.diacritized("paris"), None);
+
+        let text = "il a ete a paris";
+        let labels = ["L", "L", "LD", "LD", "C."];
+        let output = rules(Some(lexicon)).restore(text, &tokens(text, &labels));
+        assert_eq!(output.text, "Il a ete à Paris.");
+    }
+}
@@ -0,0 +1,722 @@
+// Copyright 2022 Guillaume Becquin
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//     http://www.apache.org/licenses/LICENSE-2.0
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! # Text-to-SQL generation pipeline
+//! Translates natural language questions into SQL queries over a database schema, with a sequence-to-sequence model
+//! (BART or T5) fine-tuned for text-to-SQL (e.g. on Spider). The schema is serialized into the prompt
+//! (`db_name | table_1 : column_1 , column_2 | table_2 : ...` by default) so that the model can ground the query in
+//! the actual tables and columns.
+//!
+//! The decoding can be constrained to valid SQL tokens: outside string literals, every word generated must be a
+//! prefix of a SQL keyword, of a table or column of the schema (possibly qualified as `table.column`), of a table
+//! alias (`t1`, `t1.column`) or of a number. The allowed words are stored in a trie, and the vocabulary tokens that
+//! keep the query valid are computed at each step.
+//!
+//! The generated queries are parsed to list the tables and columns they reference and check their validity.
+//!
+//! ```no_run
+//! # fn main() -> anyhow::Result<()> {
+//! use rust_bert::pipelines::common::ModelType;
+//! use rust_bert::pipelines::generation_utils::GenerateConfig;
+//! use rust_bert::pipelines::text_to_sql::{DatabaseSchema, TextToSqlConfig, TextToSqlModel};
+//! use rust_bert::resources::LocalResource;
+//! use std::path::PathBuf;
+//!
+//! let generate_config = GenerateConfig {
+//!     model_resource: Box::new(LocalResource::from(PathBuf::from("path/to/rust_model.ot"))),
+//!     config_resource: Box::new(LocalResource::from(PathBuf::from("path/to/config.json"))),
+//!     vocab_resource: Box::new(LocalResource::from(PathBuf::from("path/to/spiece.model"))),
+//!     merges_resource: Box::new(LocalResource::from(PathBuf::from("path/to/spiece.model"))),
+//!     max_length: 128,
+//!     num_beams: 4,
+//!     ..Default::default()
+//! };
+//! let model = TextToSqlModel::new(TextToSqlConfig::new(ModelType::T5, generate_config))?;
+//!
+//! let schema = DatabaseSchema::new("concert_singer")
+//!     .with_table("singer", &["singer_id", "name", "country", "age"])
+//!     .with_table("concert", &["concert_id", "concert_name", "year"]);
+//! let output = model.generate(&["How many singers are from France?"], &schema);
+//! // output[0].sql: "select count(*) from singer where country = 'France'"
+//! # Ok(())
+//! # }
+//! ```
+
+use crate::bart::BartGenerator;
+use crate::common::error::RustBertError;
+use crate::pipelines::common::{ModelType, TokenizerOption};
+use crate::pipelines::generation_utils::{GenerateConfig, GenerateOptions, LanguageGenerator};
+use crate::t5::T5Generator;
+use serde::{Deserialize, Serialize};
+use std::cell::RefCell;
+use std::collections::HashMap;
+use tch::Tensor;
+
+const SQL_KEYWORDS: &[&str] = &[
+    "select",
+    "from",
+    "where",
+    "group",
+    "by",
+    "order",
+    "having",
+    "limit",
+    "offset",
+    "join",
+    "inner",
+    "left",
+    "right",
+    "outer",
+    "cross",
+    "on",
+    "as",
+    "and",
+    "or",
+    "not",
+    "in",
+    "like",
+    "between",
+    "is",
+    "null",
+    "distinct",
+    "count",
+    "sum",
+    "avg",
+    "min",
+    "max",
+    "asc",
+    "desc",
+    "union",
+    "intersect",
+    "except",
+    "all",
+    "any",
+    "exists",
+    "case",
+    "when",
+    "then",
+    "else",
+    "end",
+    "with",
+    "cast",
+];
+
+/// Characters allowed outside of string literals, besides identifiers
+const SQL_PUNCTUATION: &[char] = &[
+    '*', '(', ')', ',', ';', '=', '<', '>', '!', '+', '-', '/', '%',
+];
+
+/// # Table of a database schema
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct TableSchema {
+    /// Table name
+    pub name: String,
+    /// Column names
+    pub columns: Vec<String>,
+}
+
+/// # Database schema the queries are generated for
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct DatabaseSchema {
+    /// Database name
+    pub name: String,
+    /// Tables of the database
+    pub tables: Vec<TableSchema>,
+}
+
+impl DatabaseSchema {
+    /// Create a new empty `DatabaseSchema`
+    ///
+    /// # Arguments
+    ///
+    /// * `name` - Database name
+    pub fn new(name: &str) -> DatabaseSchema {
+        DatabaseSchema {
+            name: name.to_string(),
+            tables: vec![],
+        }
+    }
+
+    /// Adds a table to the schema
+    ///
+    /// # Arguments
+    ///
+    /// * `name` - Table name
+    /// * `columns` - Column names
+    pub fn with_table<S>(mut self, name: &str, columns: &[S]) -> DatabaseSchema
+    where
+        S: AsRef<str>,
+    {
+        self.tables.push(TableSchema {
+            name: name.to_string(),
+            columns: columns
+                .iter()
+                .map(|column| column.as_ref().to_string())
+                .collect(),
+        });
+        self
+    }
+
+    /// Serializes the schema as `db_name | table_1 : column_1 , column_2 | table_2 : ...`
+    pub fn serialize(&self) -> String {
+        let mut serialized = self.name.clone();
+        for table in &self.tables {
+            serialized.push_str(&format!(
+                " | {} : {}",
+                table.name,
+                table.columns.join(" , ")
+            ));
+        }
+        serialized
+    }
+}
+
+#[derive(Default)]
+struct TrieNode {
+    children: HashMap<char, TrieNode>,
+    is_word: bool,
+}
+
+/// # Trie of the words allowed in the queries
+#[derive(Default)]
+struct Trie {
+    root: TrieNode,
+}
+
+impl Trie {
+    fn insert(&mut self, word: &str) {
+        let mut node = &mut self.root;
+        for c in word.chars() {
+            node = node.children.entry(c).or_default();
+        }
+        node.is_word = true;
+    }
+
+    fn find(&self, prefix: &str) -> Option<&TrieNode> {
+        let mut node = &self.root;
+        for c in prefix.chars() {
+            node = node.children.get(&c)?;
+        }
+        Some(node)
+    }
+
+    fn contains(&self, word: &str) -> bool {
+        matches!(self.find(word), Some(node) if node.is_word)
+    }
+
+    fn has_prefix(&self, prefix: &str) -> bool {
+        self.find(prefix).is_some()
+    }
+}
+
+/// # Words allowed in the SQL queries over a schema
+struct SqlGrammar {
+    words: Trie,
+    columns: Trie,
+    /// Lowercased table names mapped to their schema name
+    tables: HashMap<String, String>,
+    /// Lowercased column names mapped to their schema name
+    column_names: HashMap<String, String>,
+}
+
+fn is_identifier_char(c: char) -> bool {
+    c.is_ascii_alphanumeric() || c == '_' || c == '.'
+}
+
+/// Checks if a word is (or starts, when `partial` is set) a number
+fn is_number(word: &str, partial: bool) -> bool {
+    let mut digits = 0;
+    let mut dots = 0;
+    for c in word.chars() {
+        match c {
+            '0'..='9' => digits += 1,
+            '.' => dots += 1,
+            _ => return false,
+        }
+    }
+    dots <= 1 && (digits > 0 || partial) && (partial || !word.ends_with('.'))
+}
+
+/// Splits a table alias (`t1`, `t1.column`) into its alias and remainder
+fn split_alias(word: &str) -> Option<(&str, Option<&str>)> {
+    let rest = word.strip_prefix('t')?;
+    let digits = rest.chars().take_while(|c| c.is_ascii_digit()).count();
+    let (alias_digits, remainder) = rest.split_at(digits);
+    match remainder.strip_prefix('.') {
+        Some(column) if !alias_digits.is_empty() => Some((&word[..=digits], Some(column))),
+        None if remainder.is_empty() => Some((&word[..=digits], None)),
+        _ => None,
+    }
+}
+
+impl SqlGrammar {
+    fn new(schema: &DatabaseSchema) -> SqlGrammar {
+        let mut words = Trie::default();
+        let mut columns = Trie::default();
+        let mut tables = HashMap::new();
+        let mut column_names = HashMap::new();
+        for keyword in SQL_KEYWORDS {
+            words.insert(keyword);
+        }
+        for table in &schema.tables {
+            let table_name = table.name.to_lowercase();
+            words.insert(&table_name);
+            tables.insert(table_name.clone(), table.name.clone());
+            for column in &table.columns {
+                let column_name = column.to_lowercase();
+                words.insert(&column_name);
+                words.insert(&format!("{}.{}", table_name, column_name));
+                columns.insert(&column_name);
+                column_names.insert(column_name, column.clone());
+            }
+        }
+        SqlGrammar {
+            words,
+            columns,
+            tables,
+            column_names,
+        }
+    }
+
+    /// Checks if a lowercased word is allowed in a query
+    fn is_complete(&self, word: &str) -> bool {
+        if self.words.contains(word) || is_number(word, false) {
+            return true;
+        }
+        match split_alias(word) {
+            Some((alias, None)) => alias.len() > 1,
+            Some((_, Some(column))) => self.columns.contains(column),
+            None => false,
+        }
+    }
+
+    /// Checks if a lowercased word can be completed into an allowed word
+    fn is_valid_prefix(&self, word: &str) -> bool {
+        if self.words.has_prefix(word) || is_number(word, true) {
+            return true;
+        }
+        match split_alias(word) {
+            Some((_, None)) => true,
+            Some((_, Some(column))) => self.columns.has_prefix(column),
+            None => false,
+        }
+    }
+}
+
+/// State of the scan of a query: current string literal and partial word
+#[derive(Debug, Clone, Default, PartialEq, Eq, Hash)]
+struct ScanState {
+    literal: Option<char>,
+    word: String,
+}
+
+impl ScanState {
+    /// Adds a character to the scanned query, returning `false` if the query becomes invalid
+    fn push(&mut self, c: char, grammar: &SqlGrammar) -> bool {
+        if let Some(quote) = self.literal {
+            if c == quote {
+                self.literal = None;
+            }
+            return true;
+        }
+        if is_identifier_char(c) {
+            self.word.push(c.to_ascii_lowercase());
+            return grammar.is_valid_prefix(&self.word);
+        }
+        let valid = self.end_word(grammar);
+        if c == '\'' || c == '"' {
+            self.literal = Some(c);
+            valid
+        } else {
+            valid && (c.is_whitespace() || SQL_PUNCTUATION.contains(&c))
+        }
+    }
+
+    fn end_word(&mut self, grammar: &SqlGrammar) -> bool {
+        let valid = self.word.is_empty() || grammar.is_complete(&self.word);
+        self.word.clear();
+        valid
+    }
+
+    /// Checks if the query can end in this state
+    fn can_end(&self, grammar: &SqlGrammar) -> bool {
+        self.literal.is_none() && (self.word.is_empty() || grammar.is_complete(&self.word))
+    }
+}
+
+/// Returns the identifiers of the vocabulary pieces keeping the query valid from a scan state
+fn allowed_pieces(
+    state: &ScanState,
+    pieces: &[(i64, String)],
+    grammar: &SqlGrammar,
+    eos_id: Option<i64>,
+) -> Vec<i64> {
+    let mut allowed = pieces
+        .iter()
+        .filter(|(_, piece)| {
+            let mut state = state.clone();
+            piece.chars().all(|c| state.push(c, grammar))
+        })
+        .map(|(id, _)| *id)
+        .collect::<Vec<i64>>();
+    if let Some(eos_id) = eos_id {
+        // The end of sequence is also allowed if no piece is, to avoid masking all tokens
+        if allowed.is_empty() || state.can_end(grammar) {
+            allowed.push(eos_id);
+        }
+    }
+    allowed
+}
+
+/// # SQL query generated for a question
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SqlQuery {
+    /// SQL query, with normalized whitespace
+    pub sql: String,
+    /// Tables referenced by the query (schema names, in order of appearance)
+    pub tables: Vec<String>,
+    /// Columns referenced by the query (schema names, in order of appearance)
+    pub columns: Vec<String>,
+    /// Flag indicating if the query is a `SELECT` (or `WITH`) statement with balanced parentheses and closed string
+    /// literals, only referencing the schema tables and columns
+    pub is_valid: bool,
+}
+
+fn parse_sql(sql: &str, grammar: &SqlGrammar) -> SqlQuery {
+    let sql = sql.split_whitespace().collect::<Vec<&str>>().join(" ");
+    let mut tables: Vec<String> = vec![];
+    let mut columns: Vec<String> = vec![];
+    let mut is_valid = true;
+    let mut depth = 0i64;
+    let mut state = ScanState::default();
+    let mut words = vec![];
+    for c in sql.chars().chain(std::iter::once(' ')) {
+        if state.literal.is_none() {
+            if !is_identifier_char(c) && !state.word.is_empty() {
+                words.push(state.word.clone());
+            }
+            match c {
+                '(' => depth += 1,
+                ')' => depth -= 1,
+                _ => {}
+            }
+            if depth < 0 {
+                is_valid = false;
+            }
+        }
+        is_valid &= state.push(c, grammar);
+    }
+    is_valid &= depth == 0 && state.literal.is_none();
+    is_valid &= matches!(
+        words.first().map(|word| word.as_str()),
+        Some("select") | Some("with")
+    );
+
+    let add = |names: &mut Vec<String>, name: Option<&String>| {
+        if let Some(name) = name {
+            if !names.contains(name) {
+                names.push(name.clone());
+            }
+        }
+    };
+    for word in &words {
+        match word.split_once('.') {
+            Some((table, column)) => {
+                add(&mut tables, grammar.tables.get(table));
+                add(&mut columns, grammar.column_names.get(column));
+            }
+            None => {
+                add(&mut tables, grammar.tables.get(word));
+                add(&mut columns, grammar.column_names.get(word));
+            }
+        }
+    }
+    SqlQuery {
+        sql,
+        tables,
+        columns,
+        is_valid,
+    }
+}
+
+/// # Configuration for text-to-SQL generation
+pub struct TextToSqlConfig {
+    /// Model type of the sequence-to-sequence generator (BART or T5)
+    pub model_type: ModelType,
+    /// Generation configuration, containing the resources of the generator
+    pub generate_config: GenerateConfig,
+    /// Prompt template with `{question}` and `{schema}` placeholders (default: `{question} | {schema}`)
+    pub prompt_template: String,
+    /// Flag indicating if the decoding is constrained to valid SQL tokens (default: true)
+    pub constrained: bool,
+}
+
+impl TextToSqlConfig {
+    /// Instantiate a new text-to-SQL configuration with default parameters
+    ///
+    /// # Arguments
+    ///
+    /// * `model_type` - `ModelType` of the generator (BART or T5)
+    /// * `generate_config` - `GenerateConfig` containing the resources of the generator and the decoding settings
+    pub fn new(model_type: ModelType, generate_config: GenerateConfig) -> TextToSqlConfig {
+        TextToSqlConfig {
+            model_type,
+            generate_config,
+            prompt_template: "{question} | {schema}".to_string(),
+            constrained: true,
+        }
+    }
+}
+
+/// # Abstraction that holds one particular text-to-SQL model, for any of the supported models
+pub enum TextToSqlOption {
+    /// Text-to-SQL generator based on BART model
+    Bart(BartGenerator),
+    /// Text-to-SQL generator based on T5 model
+    T5(T5Generator),
+}
+
+impl TextToSqlOption {
+    pub fn new(
+        model_type: ModelType,
+        generate_config: GenerateConfig,
+    ) -> Result<TextToSqlOption, RustBertError> {
+        match model_type {
+            ModelType::Bart => Ok(TextToSqlOption::Bart(BartGenerator::new(generate_config)?)),
+            ModelType::T5 => Ok(TextToSqlOption::T5(T5Generator::new(generate_config)?)),
+            _ => Err(RustBertError::InvalidConfigurationError(format!(
+                "Text-to-SQL generation not implemented for {:?}!",
+                model_type
+            ))),
+        }
+    }
+
+    /// Returns the `ModelType` for this TextToSqlOption
+    pub fn model_type(&self) -> ModelType {
+        match *self {
+            Self::Bart(_) => ModelType::Bart,
+            Self::T5(_) => ModelType::T5,
+        }
+    }
+
+    /// Interface method to access tokenizer
+    pub fn get_tokenizer(&self) -> &TokenizerOption {
+        match self {
+            Self::Bart(model_ref) => model_ref.get_tokenizer(),
+            Self::T5(model_ref) => model_ref.get_tokenizer(),
+        }
+    }
+
+    /// Interface method to generate() of the particular models.
+    pub fn generate<S>(
+        &self,
+        prompt_texts: Option<&[S]>,
+        generate_options: Option<GenerateOptions>,
+    ) -> Vec<String>
+    where
+        S: AsRef<str> + Sync,
+    {
+        match *self {
+            Self::Bart(ref model) => model
+                .generate(prompt_texts, generate_options)
+                .into_iter()
+                .map(|output| output.text)
+                .collect(),
+            Self::T5(ref model) => model
+                .generate(prompt_texts, generate_options)
+                .into_iter()
+                .map(|output| output.text)
+                .collect(),
+        }
+    }
+}
+
+/// # TextToSqlModel generating SQL queries from natural language questions
+pub struct TextToSqlModel {
+    generator: TextToSqlOption,
+    prompt_template: String,
+    constrained: bool,
+    /// Text of the vocabulary tokens (with a leading space for word-initial tokens), excluding special tokens
+    pieces: Vec<(i64, String)>,
+}
+
+impl TextToSqlModel {
+    /// Build a new `TextToSqlModel`
+    ///
+    /// # Arguments
+    ///
+    /// * `config` - `TextToSqlConfig` object containing the generator configuration, prompt template and decoding constraint flag
+    pub fn new(config: TextToSqlConfig) -> Result<TextToSqlModel, RustBertError> {
+        let generator = TextToSqlOption::new(config.model_type, config.generate_config)?;
+        let mut pieces = generator
+            .get_tokenizer()
+            .get_vocab_indices()
+            .iter()
+            .filter(|(_, token)| !(token.starts_with('<') && token.ends_with('>')))
+            .map(|(id, token)| (*id, token.replace('▁', " ").replace('Ġ', " ")))
+            .collect::<Vec<(i64, String)>>();
+        pieces.sort_unstable();
+        Ok(TextToSqlModel {
+            generator,
+            prompt_template: config.prompt_template,
+            constrained: config.constrained,
+            pieces,
+        })
+    }
+
+    /// Generates SQL queries answering questions over a database schema
+    ///
+    /// # Arguments
+    ///
+    /// * `questions` - Natural language questions
+    /// * `schema` - `DatabaseSchema` the questions are about
+    ///
+    /// # Returns
+    ///
+    /// * `Vec<SqlQuery>` parsed SQL query for each question
+    pub fn generate<S>(&self, questions: &[S], schema: &DatabaseSchema) -> Vec<SqlQuery>
+    where
+        S: AsRef<str>,
+    {
+        let serialized_schema = schema.serialize();
+        let prompts = questions
+            .iter()
+            .map(|question| {
+                self.prompt_template
+                    .replace("{schema}", &serialized_schema)
+                    .replace("{question}", question.as_ref())
+            })
+            .collect::<Vec<String>>();
+
+        let grammar = SqlGrammar::new(schema);
+        let tokenizer = self.generator.get_tokenizer();
+        let eos_id = tokenizer.get_eos_id();
+        let cache: RefCell<HashMap<ScanState, Vec<i64>>> = RefCell::new(HashMap::new());
+        let constraint = |_batch_id: i64, generated_ids: &Tensor| -> Vec<i64> {
+            let generated_ids = generated_ids.iter::<i64>().unwrap().collect::<Vec<i64>>();
+            let mut state = ScanState::default();
+            for c in tokenizer.decode(&generated_ids, true, false).chars() {
+                state.push(c, &grammar);
+            }
+            if let Some(allowed) = cache.borrow().get(&state) {
+                return allowed.clone();
+            }
+            let allowed = allowed_pieces(&state, &self.pieces, &grammar, eos_id);
+            cache.borrow_mut().insert(state, allowed.clone());
+            allowed
+        };
+        let generate_options = GenerateOptions {
+            num_return_sequences: Some(1),
+            prefix_allowed_tokens_fn: if self.constrained {
+                Some(&constraint)
+            } else {
+                None
+            },
+            ..Default::default()
+        };
+
+        self.generator
+            .generate(Some(&prompts), Some(generate_options))
+            .into_iter()
+            .map(|sql| parse_sql(&sql, &grammar))
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn schema() -> DatabaseSchema {
+        DatabaseSchema::new("concert_singer")
+            .with_table("singer", &["Singer_ID", "Name", "Country"])
+            .with_table("concert", &["concert_ID", "Year"])
+    }
+
+    #[test]
+    fn test_schema_serialization() {
+        assert_eq!(
+            schema().serialize(),
+            "concert_singer | singer : Singer_ID , Name , Country | concert : concert_ID , Year"
+        );
+    }
+
+    #[test]
+    fn test_parse_sql() {
+        let grammar = SqlGrammar::new(&schema());
+        let query = parse_sql(
+            "SELECT count(*) FROM singer AS T1  WHERE T1.country = 'Côte d''Ivoire' AND year > 2.5",
+            &grammar,
+        );
+        assert!(query.is_valid);
+        assert_eq!(
+            query.sql,
+            "SELECT count(*) FROM singer AS T1 WHERE T1.country = 'Côte d''Ivoire' AND year > 2.5"
+        );
+        assert_eq!(query.tables, vec!["singer"]);
+        assert_eq!(query.columns, vec!["Country", "Year"]);
+
+        assert!(!parse_sql("SELECT age FROM singer", &grammar).is_valid);
+        assert!(!parse_sql("SELECT name FROM singer WHERE (year > 1", &grammar).is_valid);
+        assert!(!parse_sql("SELECT name FROM singer WHERE name = 'Bob", &grammar).is_valid);
+        assert!(!parse_sql("name FROM singer", &grammar).is_valid);
+    }
+
+    #[test]
+    fn test_allowed_pieces() {
+        let grammar = SqlGrammar::new(&schema());
+        let pieces = vec![
+            (0, " sing".to_string()),
+            (1, "er".to_string()),
+            (2, " singers".to_string()),
+            (3, " age".to_string()),
+            (4, " '".to_string()),
+            (5, "ab".to_string()),
+            (6, " 1".to_string()),
+            (7, ".name".to_string()),
+            (8, "}".to_string()),
+        ];
+        let mut state = ScanState::default();
+        for c in "select name from".chars() {
+            assert!(state.push(c, &grammar));
+        }
+        assert_eq!(
+            allowed_pieces(&state, &pieces, &grammar, Some(100)),
+            vec![0, 4, 6, 100]
+        );
+
+        // The query cannot end with an unfinished word
+        for c in " sing".chars() {
+            assert!(state.push(c, &grammar));
+        }
+        assert_eq!(
+            allowed_pieces(&state, &pieces, &grammar, Some(100)),
+            vec![1]
+        );
+
+        for c in "er as t1 where t1".chars() {
+            assert!(state.push(c, &grammar));
+        }
+        assert_eq!(
+            allowed_pieces(&state, &pieces, &grammar, Some(100)),
+            vec![0, 4, 6, 7, 100]
+        );
+
+        // Anything goes in a string literal, except ending the query
+        for c in " '".chars() {
+            assert!(state.push(c, &grammar));
+        }
+        assert_eq!(
+            allowed_pieces(&state, &pieces, &grammar, Some(100)),
+            (0..9).collect::<Vec<i64>>()
+        );
+    }
+}
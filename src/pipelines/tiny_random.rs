@@ -0,0 +1,1066 @@
+// Copyright 2022 Guillaume Becquin
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//     http://www.apache.org/licenses/LICENSE-2.0
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! # Tiny randomly-initialized models
+//! Generates the resources of a tiny model of any architecture (2 layers, hidden size of 32, 4 attention heads)
+//! with random weights, without downloading anything. The models do not produce meaningful outputs, but load and
+//! run in the pipelines as the pretrained models do, in a fraction of a second: they are meant for fast integration
+//! tests and to smoke-test the wiring of an application offline.
+//!
+//! `TinyRandomModel::new` writes to a directory:
+//! - a configuration (`config.json`) of the architecture with tiny dimensions, whose vocabulary size and special
+//!   token ids match the generated tokenizer, and with labels for the classification tasks
+//! - a tokenizer vocabulary in the format expected by the architecture (a WordPiece `vocab.txt`, a byte-level BPE
+//!   `vocab.json` and `merges.txt` or a SentencePiece `spiece.model`), made of the special tokens and of the
+//!   printable ASCII characters. Text is therefore tokenized character by character.
+//! - the weights (`rust_model.ot`) of the model built for the task, randomly initialized from a seed
+//!
+//! The weights include the head of the task requested: a classification head for the sequence classification,
+//! zero-shot classification and token classification tasks, a span prediction head for question answering, and a
+//! language modeling head for the text generation, conversation, summarization and translation tasks. The
+//! architectures not supported by a task are rejected with the error returned by the pipelines.
+//!
+//! ```no_run
+//! # fn main() -> anyhow::Result<()> {
+//! use rust_bert::pipelines::common::ModelType;
+//! use rust_bert::pipelines::pretrained_registry::PretrainedTask;
+//! use rust_bert::pipelines::sequence_classification::{
+//!     SequenceClassificationConfig, SequenceClassificationModel,
+//! };
+//! use rust_bert::pipelines::tiny_random::{TinyRandomConfig, TinyRandomModel};
+//!
+//! let tiny_model = TinyRandomModel::new(
+//!     TinyRandomConfig::new(ModelType::Roberta, PretrainedTask::SequenceClassification)
+//!         .with_labels(&["negative", "positive"]),
+//!     "path/to/tiny-random-roberta",
+//! )?;
+//! let model = SequenceClassificationModel::new(SequenceClassificationConfig::new(
+//!     ModelType::Roberta,
+//!     tiny_model.model_resource,
+//!     tiny_model.config_resource,
+//!     tiny_model.vocab_resource,
+//!     tiny_model.merges_resource,
+//!     false,
+//!     None,
+//!     None,
+//! ))?;
+//! let labels = model.predict(["This is a smoke test"]);
+//! # Ok(())
+//! # }
+//! ```
+
+use crate::albert::AlbertConfig;
+use crate::bart::{BartConfig, BartForConditionalGeneration};
+use crate::bert::BertConfig;
+use crate::bloom::{BloomConfig, BloomForCausalLM};
+use crate::common::error::RustBertError;
+use crate::deberta::DebertaConfig;
+use crate::deberta_v2::DebertaV2Config;
+use crate::distilbert::DistilBertConfig;
+use crate::electra::ElectraConfig;
+use crate::falcon::{FalconConfig, FalconForCausalLM};
+use crate::fnet::FNetConfig;
+use crate::gpt2::{GPT2LMHeadModel, Gpt2Config};
+use crate::gpt_bigcode::{GptBigCodeConfig, GptBigCodeForCausalLM};
+use crate::gpt_neo::{GptNeoConfig, GptNeoForCausalLM};
+use crate::gpt_neox::{GptNeoXConfig, GptNeoXForCausalLM};
+use crate::llama::{LlamaConfig, LlamaForCausalLM};
+use crate::longformer::LongformerConfig;
+use crate::m2m_100::M2M100ForConditionalGeneration;
+use crate::marian::MarianForConditionalGeneration;
+use crate::mbart::{MBartConfig, MBartForConditionalGeneration};
+use crate::mobilebert::MobileBertConfig;
+use crate::openai_gpt::OpenAIGPTLMHeadModel;
+use crate::pegasus::PegasusForConditionalGeneration;
+use crate::pipelines::common::{ConfigOption, ModelType, TokenizerOption};
+use crate::pipelines::pretrained_registry::PretrainedTask;
+use crate::pipelines::question_answering::QuestionAnsweringOption;
+use crate::pipelines::sequence_classification::SequenceClassificationOption;
+use crate::pipelines::token_classification::TokenClassificationOption;
+use crate::pipelines::zero_shot_classification::ZeroShotClassificationOption;
+use crate::prophetnet::{ProphetNetConfig, ProphetNetForConditionalGeneration};
+use crate::reformer::{ReformerConfig, ReformerModelWithLMHead};
+use crate::resources::LocalResource;
+use crate::t5::{T5Config, T5ForConditionalGeneration};
+use crate::training::{CONFIG_NAME, WEIGHTS_NAME};
+use crate::xlnet::{XLNetConfig, XLNetLMHeadModel};
+use std::collections::HashMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+use tch::{nn, Device};
+
+const HIDDEN_SIZE: i64 = 32;
+const NUM_LAYERS: i64 = 2;
+const NUM_ATTENTION_HEADS: i64 = 4;
+const INTERMEDIATE_SIZE: i64 = 37;
+const MAX_POSITIONS: i64 = 512;
+
+/// SentencePiece word boundary marker
+const WORD_BOUNDARY: &str = "\u{2581}";
+
+/// # Configuration of a tiny randomly-initialized model
+pub struct TinyRandomConfig {
+    /// Model type (architecture and tokenizer) of the model
+    pub model_type: ModelType,
+    /// Task the model is built for, defining the head included in the weights
+    pub task: PretrainedTask,
+    /// Labels of the classification heads (default: `LABEL_0` and `LABEL_1`, or `contradiction`, `neutral` and
+    /// `entailment` for zero-shot classification)
+    pub labels: Vec<String>,
+    /// Seed of the random initialization of the weights (default: 42)
+    pub seed: i64,
+}
+
+impl TinyRandomConfig {
+    /// Creates the configuration of a tiny model
+    ///
+    /// # Arguments
+    ///
+    /// * `model_type` - Model type of the tiny model
+    /// * `task` - Task the model is built for
+    ///
+    /// # Example
+    ///
+    /// ```no_run
+    /// use rust_bert::pipelines::common::ModelType;
+    /// use rust_bert::pipelines::pretrained_registry::PretrainedTask;
+    /// use rust_bert::pipelines::tiny_random::TinyRandomConfig;
+    ///
+    /// let config = TinyRandomConfig::new(ModelType::GPT2, PretrainedTask::TextGeneration).with_seed(0);
+    /// ```
+    pub fn new(model_type: ModelType, task: PretrainedTask) -> TinyRandomConfig {
+        let labels: &[&str] = match task {
+            PretrainedTask::ZeroShotClassification => &["contradiction", "neutral", "entailment"],
+            _ => &["LABEL_0", "LABEL_1"],
+        };
+        TinyRandomConfig {
+            model_type,
+            task,
+            labels: labels.iter().map(|label| label.to_string()).collect(),
+            seed: 42,
+        }
+    }
+
+    /// Sets the labels of the classification heads
+    pub fn with_labels<S: AsRef<str>>(mut self, labels: &[S]) -> Self {
+        self.labels = labels
+            .iter()
+            .map(|label| label.as_ref().to_string())
+            .collect();
+        self
+    }
+
+    /// Sets the seed of the random initialization of the weights
+    pub fn with_seed(mut self, seed: i64) -> Self {
+        self.seed = seed;
+        self
+    }
+}
+
+/// # Resources of a tiny randomly-initialized model
+/// Local resources written by `TinyRandomModel::new`, to be passed to the pipeline configurations.
+pub struct TinyRandomModel {
+    /// Model type of the model
+    pub model_type: ModelType,
+    /// Model weights resource
+    pub model_resource: LocalResource,
+    /// Config resource
+    pub config_resource: LocalResource,
+    /// Vocab resource (the SentencePiece model for the architectures using one, not read by the byte-level ByT5
+    /// tokenizer)
+    pub vocab_resource: LocalResource,
+    /// Merges resource, for the byte-level BPE tokenizers and the SentencePiece model of Marian and M2M100
+    pub merges_resource: Option<LocalResource>,
+}
+
+impl TinyRandomModel {
+    /// Generates a tiny randomly-initialized model and writes its resources to a directory
+    ///
+    /// # Arguments
+    ///
+    /// * `config` - `TinyRandomConfig` with the model type and task of the model
+    /// * `directory` - Output directory (created if needed). Existing resources are overwritten.
+    ///
+    /// # Returns
+    ///
+    /// * `TinyRandomModel` pointing to the resources written. A `RustBertError::InvalidConfigurationError` is
+    /// returned if the architecture is not supported by the task.
+    ///
+    /// # Example
+    ///
+    /// ```no_run
+    /// # fn main() -> anyhow::Result<()> {
+    /// use rust_bert::pipelines::common::ModelType;
+    /// use rust_bert::pipelines::pretrained_registry::PretrainedTask;
+    /// use rust_bert::pipelines::text_generation::{TextGenerationConfig, TextGenerationModel};
+    /// use rust_bert::pipelines::tiny_random::{TinyRandomConfig, TinyRandomModel};
+    ///
+    /// let tiny_model = TinyRandomModel::new(
+    ///     TinyRandomConfig::new(ModelType::GPT2, PretrainedTask::TextGeneration),
+    ///     "path/to/tiny-random-gpt2",
+    /// )?;
+    /// let model = TextGenerationModel::new(TextGenerationConfig {
+    ///     model_type: ModelType::GPT2,
+    ///     model_resource: Box::new(tiny_model.model_resource),
+    ///     config_resource: Box::new(tiny_model.config_resource),
+    ///     vocab_resource: Box::new(tiny_model.vocab_resource),
+    ///     merges_resource: Box::new(tiny_model.merges_resource.unwrap()),
+    ///     max_length: 16,
+    ///     ..Default::default()
+    /// })?;
+    /// let output = model.generate(&["Hello"], None);
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn new<P: AsRef<Path>>(
+        config: TinyRandomConfig,
+        directory: P,
+    ) -> Result<TinyRandomModel, RustBertError> {
+        let directory = directory.as_ref();
+        fs::create_dir_all(directory)?;
+
+        let (vocab_path, merges_path) = write_tokenizer_files(config.model_type, directory)?;
+        let tokenizer = TokenizerOption::from_file(
+            config.model_type,
+            vocab_path.to_str().unwrap(),
+            merges_path.as_ref().map(|path| path.to_str().unwrap()),
+            false,
+            None,
+            None,
+        )?;
+        let vocab_size = tokenizer
+            .get_vocab_indices()
+            .keys()
+            .max()
+            .map_or(0, |max_id| max_id + 1);
+
+        let config_path = directory.join(CONFIG_NAME);
+        let config_value = tiny_config(&config, vocab_size, &SpecialTokenIds::new(&tokenizer))?;
+        let mut content = serde_json::to_string_pretty(&config_value)
+            .map_err(|error| RustBertError::IOError(error.to_string()))?;
+        content.push('\n');
+        fs::write(&config_path, content)?;
+
+        let model_config = ConfigOption::from_file(config.model_type, &config_path)?;
+        tch::manual_seed(config.seed);
+        let var_store = nn::VarStore::new(Device::Cpu);
+        build_task_model(
+            config.model_type,
+            config.task,
+            &var_store.root(),
+            &model_config,
+        )?;
+        let model_path = directory.join(WEIGHTS_NAME);
+        var_store.save(&model_path)?;
+
+        Ok(TinyRandomModel {
+            model_type: config.model_type,
+            model_resource: LocalResource::from(model_path),
+            config_resource: LocalResource::from(config_path),
+            vocab_resource: LocalResource::from(vocab_path),
+            merges_resource: merges_path.map(LocalResource::from),
+        })
+    }
+}
+
+/// Special token ids of the tokenizer set in the configuration. The ids not defined by the tokenizer fall back to
+/// the end of sequence (or unknown) token, as the pretrained models lacking them usually do.
+struct SpecialTokenIds {
+    pad: i64,
+    bos: i64,
+    eos: i64,
+    sep: i64,
+}
+
+impl SpecialTokenIds {
+    fn new(tokenizer: &TokenizerOption) -> SpecialTokenIds {
+        let unk = tokenizer.get_unk_id();
+        let eos = tokenizer
+            .get_eos_id()
+            .or_else(|| tokenizer.get_sep_id())
+            .unwrap_or(unk);
+        SpecialTokenIds {
+            pad: tokenizer.get_pad_id().unwrap_or(unk),
+            bos: tokenizer.get_bos_id().unwrap_or(eos),
+            eos,
+            sep: tokenizer.get_sep_id().unwrap_or(eos),
+        }
+    }
+}
+
+/// Builds the tiny configuration of the architecture, serialized as expected by `ConfigOption::from_file`
+fn tiny_config(
+    config: &TinyRandomConfig,
+    vocab_size: i64,
+    special_token_ids: &SpecialTokenIds,
+) -> Result<serde_json::Value, RustBertError> {
+    let id2label: HashMap<i64, String> = config
+        .labels
+        .iter()
+        .enumerate()
+        .map(|(id, label)| (id as i64, label.clone()))
+        .collect();
+    let label2id: HashMap<String, i64> = id2label
+        .iter()
+        .map(|(id, label)| (label.clone(), *id))
+        .collect();
+    let id2label = Some(id2label);
+    let label2id = Some(label2id);
+    let generation = matches!(
+        config.task,
+        PretrainedTask::TextGeneration
+            | PretrainedTask::Conversation
+            | PretrainedTask::Summarization
+            | PretrainedTask::Translation
+    );
+
+    let config_value = match config.model_type {
+        ModelType::Bert | ModelType::Roberta | ModelType::XLMRoberta => {
+            serde_json::to_value(BertConfig {
+                hidden_size: HIDDEN_SIZE,
+                intermediate_size: INTERMEDIATE_SIZE,
+                max_position_embeddings: MAX_POSITIONS,
+                num_attention_heads: NUM_ATTENTION_HEADS,
+                num_hidden_layers: NUM_LAYERS,
+                vocab_size,
+                id2label,
+                label2id,
+                ..Default::default()
+            })
+        }
+        ModelType::DistilBert => serde_json::to_value(DistilBertConfig {
+            dim: HIDDEN_SIZE,
+            hidden_dim: INTERMEDIATE_SIZE,
+            max_position_embeddings: MAX_POSITIONS,
+            n_heads: NUM_ATTENTION_HEADS,
+            n_layers: NUM_LAYERS,
+            vocab_size,
+            id2label,
+            label2id,
+            ..Default::default()
+        }),
+        ModelType::Deberta => serde_json::to_value(DebertaConfig {
+            hidden_size: HIDDEN_SIZE,
+            intermediate_size: INTERMEDIATE_SIZE,
+            max_position_embeddings: MAX_POSITIONS,
+            num_attention_heads: NUM_ATTENTION_HEADS,
+            num_hidden_layers: NUM_LAYERS,
+            vocab_size,
+            pooler_hidden_size: Some(HIDDEN_SIZE),
+            pad_token_id: Some(special_token_ids.pad),
+            id2label,
+            label2id,
+            ..Default::default()
+        }),
+        ModelType::DebertaV2 => serde_json::to_value(DebertaV2Config {
+            hidden_size: HIDDEN_SIZE,
+            intermediate_size: INTERMEDIATE_SIZE,
+            max_position_embeddings: MAX_POSITIONS,
+            num_attention_heads: NUM_ATTENTION_HEADS,
+            num_hidden_layers: NUM_LAYERS,
+            vocab_size,
+            pooler_hidden_size: Some(HIDDEN_SIZE),
+            pad_token_id: Some(special_token_ids.pad),
+            id2label,
+            label2id,
+            ..Default::default()
+        }),
+        ModelType::Electra => serde_json::to_value(ElectraConfig {
+            embedding_size: HIDDEN_SIZE,
+            hidden_size: HIDDEN_SIZE,
+            intermediate_size: INTERMEDIATE_SIZE,
+            max_position_embeddings: MAX_POSITIONS,
+            num_attention_heads: NUM_ATTENTION_HEADS,
+            num_hidden_layers: NUM_LAYERS,
+            vocab_size,
+            pad_token_id: special_token_ids.pad,
+            id2label,
+            label2id,
+            ..Default::default()
+        }),
+        // The bottleneck layers project the hidden states to a smaller size for the attention
+        ModelType::MobileBert => serde_json::to_value(MobileBertConfig {
+            embedding_size: HIDDEN_SIZE / 2,
+            hidden_size: HIDDEN_SIZE,
+            intermediate_size: INTERMEDIATE_SIZE,
+            max_position_embeddings: MAX_POSITIONS,
+            num_attention_heads: NUM_ATTENTION_HEADS,
+            num_hidden_layers: NUM_LAYERS,
+            vocab_size,
+            intra_bottleneck_size: Some(HIDDEN_SIZE / 2),
+            num_feedforward_networks: Some(2),
+            pad_token_idx: Some(special_token_ids.pad),
+            id2label,
+            label2id,
+            ..Default::default()
+        }),
+        ModelType::Albert => serde_json::to_value(AlbertConfig {
+            embedding_size: HIDDEN_SIZE / 2,
+            hidden_size: HIDDEN_SIZE,
+            intermediate_size: INTERMEDIATE_SIZE,
+            max_position_embeddings: MAX_POSITIONS,
+            num_attention_heads: NUM_ATTENTION_HEADS,
+            num_hidden_layers: NUM_LAYERS,
+            vocab_size,
+            bos_token_id: special_token_ids.bos,
+            eos_token_id: special_token_ids.eos,
+            pad_token_id: special_token_ids.pad,
+            id2label,
+            label2id,
+            ..Default::default()
+        }),
+        ModelType::Longformer => serde_json::to_value(LongformerConfig {
+            attention_window: vec![4; NUM_LAYERS as usize],
+            hidden_size: HIDDEN_SIZE,
+            intermediate_size: INTERMEDIATE_SIZE,
+            max_position_embeddings: MAX_POSITIONS,
+            num_attention_heads: NUM_ATTENTION_HEADS,
+            num_hidden_layers: NUM_LAYERS,
+            vocab_size,
+            sep_token_id: special_token_ids.sep,
+            pad_token_id: Some(special_token_ids.pad),
+            id2label,
+            label2id,
+            ..Default::default()
+        }),
+        ModelType::FNet => serde_json::to_value(FNetConfig {
+            hidden_size: HIDDEN_SIZE,
+            intermediate_size: INTERMEDIATE_SIZE,
+            max_position_embeddings: MAX_POSITIONS,
+            num_hidden_layers: NUM_LAYERS,
+            vocab_size,
+            pad_token_id: Some(special_token_ids.pad),
+            bos_token_id: Some(special_token_ids.bos),
+            eos_token_id: Some(special_token_ids.eos),
+            id2label,
+            label2id,
+            ..Default::default()
+        }),
+        ModelType::Bart | ModelType::Marian => {
+            let marian = matches!(config.model_type, ModelType::Marian);
+            serde_json::to_value(BartConfig {
+                d_model: HIDDEN_SIZE,
+                encoder_layers: NUM_LAYERS,
+                decoder_layers: NUM_LAYERS,
+                num_hidden_layers: NUM_LAYERS,
+                encoder_attention_heads: NUM_ATTENTION_HEADS,
+                decoder_attention_heads: NUM_ATTENTION_HEADS,
+                encoder_ffn_dim: INTERMEDIATE_SIZE,
+                decoder_ffn_dim: INTERMEDIATE_SIZE,
+                max_position_embeddings: MAX_POSITIONS,
+                vocab_size,
+                bos_token_id: Some(special_token_ids.bos),
+                eos_token_id: Some(special_token_ids.eos),
+                pad_token_id: Some(special_token_ids.pad),
+                decoder_start_token_id: Some(if marian {
+                    special_token_ids.pad
+                } else {
+                    special_token_ids.eos
+                }),
+                // Marian uses sinusoidal position embeddings and no embeddings normalization
+                static_position_embeddings: Some(marian),
+                normalize_embedding: Some(!marian),
+                scale_embedding: Some(marian),
+                num_labels: Some(config.labels.len() as i64),
+                id2label,
+                label2id,
+                ..Default::default()
+            })
+        }
+        ModelType::MBart | ModelType::M2M100 | ModelType::Pegasus => {
+            let decoder_start_token_id = match config.model_type {
+                ModelType::Pegasus => special_token_ids.pad,
+                _ => special_token_ids.eos,
+            };
+            serde_json::to_value(MBartConfig {
+                d_model: HIDDEN_SIZE,
+                encoder_layers: NUM_LAYERS,
+                decoder_layers: NUM_LAYERS,
+                encoder_attention_heads: NUM_ATTENTION_HEADS,
+                decoder_attention_heads: NUM_ATTENTION_HEADS,
+                encoder_ffn_dim: INTERMEDIATE_SIZE,
+                decoder_ffn_dim: INTERMEDIATE_SIZE,
+                max_position_embeddings: MAX_POSITIONS,
+                vocab_size,
+                bos_token_id: Some(special_token_ids.bos),
+                eos_token_id: Some(special_token_ids.eos),
+                pad_token_id: Some(special_token_ids.pad),
+                decoder_start_token_id: Some(decoder_start_token_id),
+                id2label,
+                label2id,
+                ..Default::default()
+            })
+        }
+        ModelType::ProphetNet => serde_json::to_value(ProphetNetConfig {
+            hidden_size: HIDDEN_SIZE,
+            encoder_ffn_dim: INTERMEDIATE_SIZE,
+            decoder_ffn_dim: INTERMEDIATE_SIZE,
+            num_encoder_layers: NUM_LAYERS,
+            num_decoder_layers: NUM_LAYERS,
+            num_encoder_attention_heads: NUM_ATTENTION_HEADS,
+            num_decoder_attention_heads: NUM_ATTENTION_HEADS,
+            max_position_embeddings: MAX_POSITIONS,
+            vocab_size,
+            bos_token_id: special_token_ids.bos,
+            eos_token_id: special_token_ids.eos,
+            pad_token_id: special_token_ids.pad,
+            decoder_start_token_id: special_token_ids.pad,
+            id2label,
+            label2id,
+            ..Default::default()
+        }),
+        ModelType::T5 | ModelType::ByT5 => serde_json::to_value(T5Config {
+            d_model: HIDDEN_SIZE,
+            d_ff: INTERMEDIATE_SIZE,
+            d_kv: HIDDEN_SIZE / NUM_ATTENTION_HEADS,
+            num_heads: NUM_ATTENTION_HEADS,
+            num_layers: NUM_LAYERS,
+            num_decoder_layers: Some(NUM_LAYERS),
+            vocab_size,
+            eos_token_id: Some(special_token_ids.eos),
+            pad_token_id: Some(special_token_ids.pad),
+            decoder_start_token_id: Some(special_token_ids.pad),
+            ..Default::default()
+        }),
+        ModelType::XLNet => serde_json::to_value(XLNetConfig {
+            d_model: HIDDEN_SIZE,
+            d_inner: INTERMEDIATE_SIZE,
+            d_head: HIDDEN_SIZE / NUM_ATTENTION_HEADS,
+            n_head: NUM_ATTENTION_HEADS,
+            n_layer: NUM_LAYERS,
+            vocab_size,
+            bos_token_id: special_token_ids.bos,
+            eos_token_id: special_token_ids.eos,
+            pad_token_id: special_token_ids.pad,
+            id2label,
+            label2id,
+            ..Default::default()
+        }),
+        // The axial position embeddings factorize the positions (16 x 32) and split the hidden size (16 + 16)
+        ModelType::Reformer => serde_json::to_value(ReformerConfig {
+            attention_head_size: HIDDEN_SIZE / NUM_ATTENTION_HEADS,
+            attn_layers: ReformerConfig::default()
+                .attn_layers
+                .into_iter()
+                .take(NUM_LAYERS as usize)
+                .collect(),
+            axial_pos_embds_dim: vec![HIDDEN_SIZE / 2, HIDDEN_SIZE / 2],
+            axial_pos_shape: vec![16, MAX_POSITIONS / 16],
+            feed_forward_size: INTERMEDIATE_SIZE,
+            hidden_size: HIDDEN_SIZE,
+            is_decoder: generation,
+            max_position_embeddings: MAX_POSITIONS,
+            num_attention_heads: NUM_ATTENTION_HEADS,
+            num_buckets: serde_json::Value::from(4),
+            local_attn_chunk_length: Some(4),
+            lsh_attn_chunk_length: Some(4),
+            num_hidden_layers: NUM_LAYERS,
+            vocab_size,
+            eos_token_id: special_token_ids.eos,
+            pad_token_id: special_token_ids.pad,
+            id2label,
+            label2id,
+            ..Default::default()
+        }),
+        ModelType::GPT2 | ModelType::OpenAiGpt => serde_json::to_value(Gpt2Config {
+            n_ctx: MAX_POSITIONS,
+            n_embd: HIDDEN_SIZE,
+            n_head: NUM_ATTENTION_HEADS,
+            n_layer: NUM_LAYERS,
+            n_positions: MAX_POSITIONS,
+            vocab_size,
+            ..Default::default()
+        }),
+        // Alternates global and local attention layers
+        ModelType::GPTNeo => {
+            let attention_layers = GptNeoConfig::default()
+                .attention_layers
+                .into_iter()
+                .take(NUM_LAYERS as usize)
+                .collect::<Vec<_>>();
+            serde_json::to_value(GptNeoConfig {
+                attention_types: vec![(attention_layers.clone(), 1)],
+                attention_layers,
+                hidden_size: HIDDEN_SIZE,
+                intermediate_size: Some(INTERMEDIATE_SIZE),
+                max_position_embeddings: MAX_POSITIONS,
+                num_heads: NUM_ATTENTION_HEADS,
+                num_layers: NUM_LAYERS,
+                vocab_size,
+                bos_token_id: special_token_ids.bos,
+                eos_token_id: special_token_ids.eos,
+                ..Default::default()
+            })
+        }
+        ModelType::GPTBigCode => serde_json::to_value(GptBigCodeConfig {
+            n_embd: HIDDEN_SIZE,
+            n_inner: Some(INTERMEDIATE_SIZE),
+            n_head: NUM_ATTENTION_HEADS,
+            n_layer: NUM_LAYERS,
+            n_positions: MAX_POSITIONS,
+            vocab_size,
+            bos_token_id: special_token_ids.bos,
+            eos_token_id: special_token_ids.eos,
+            ..Default::default()
+        }),
+        ModelType::GPTNeoX => serde_json::to_value(GptNeoXConfig {
+            hidden_size: HIDDEN_SIZE,
+            intermediate_size: INTERMEDIATE_SIZE,
+            max_position_embeddings: MAX_POSITIONS,
+            num_attention_heads: NUM_ATTENTION_HEADS,
+            num_hidden_layers: NUM_LAYERS,
+            vocab_size,
+            bos_token_id: special_token_ids.bos,
+            eos_token_id: special_token_ids.eos,
+            ..Default::default()
+        }),
+        ModelType::Falcon => serde_json::to_value(FalconConfig {
+            hidden_size: HIDDEN_SIZE,
+            num_attention_heads: NUM_ATTENTION_HEADS,
+            num_hidden_layers: NUM_LAYERS,
+            vocab_size,
+            bos_token_id: special_token_ids.bos,
+            eos_token_id: special_token_ids.eos,
+            ..Default::default()
+        }),
+        ModelType::Bloom => serde_json::to_value(BloomConfig {
+            hidden_size: HIDDEN_SIZE,
+            n_head: NUM_ATTENTION_HEADS,
+            n_layer: NUM_LAYERS,
+            vocab_size,
+            bos_token_id: special_token_ids.bos,
+            eos_token_id: special_token_ids.eos,
+            pad_token_id: Some(special_token_ids.pad),
+            ..Default::default()
+        }),
+        ModelType::Llama => serde_json::to_value(LlamaConfig {
+            hidden_size: HIDDEN_SIZE,
+            intermediate_size: INTERMEDIATE_SIZE,
+            max_position_embeddings: MAX_POSITIONS,
+            num_attention_heads: NUM_ATTENTION_HEADS,
+            num_hidden_layers: NUM_LAYERS,
+            vocab_size,
+            bos_token_id: special_token_ids.bos,
+            eos_token_id: special_token_ids.eos,
+            ..Default::default()
+        }),
+        ModelType::Memnet | ModelType::Custom(_) => {
+            return Err(unsupported_model_type(config.model_type));
+        }
+    };
+    config_value.map_err(|error| RustBertError::InvalidConfigurationError(error.to_string()))
+}
+
+/// Builds the model of the task in the variable store, with the variable names expected by the pipelines
+fn build_task_model(
+    model_type: ModelType,
+    task: PretrainedTask,
+    p: &nn::Path,
+    config: &ConfigOption,
+) -> Result<(), RustBertError> {
+    match task {
+        PretrainedTask::SequenceClassification => {
+            SequenceClassificationOption::new(model_type, p, config)?;
+        }
+        PretrainedTask::ZeroShotClassification => {
+            ZeroShotClassificationOption::new(model_type, p, config)?;
+        }
+        PretrainedTask::TokenClassification => {
+            TokenClassificationOption::new(model_type, p, config)?;
+        }
+        PretrainedTask::QuestionAnswering => {
+            QuestionAnsweringOption::new(model_type, p, config)?;
+        }
+        PretrainedTask::TextGeneration
+        | PretrainedTask::Conversation
+        | PretrainedTask::Summarization
+        | PretrainedTask::Translation => build_generation_model(model_type, p, config)?,
+        PretrainedTask::LanguageModeling | PretrainedTask::SentenceEmbeddings => {
+            return Err(RustBertError::InvalidConfigurationError(format!(
+                "Tiny random models are not available for the {:?} task",
+                task
+            )));
+        }
+    }
+    Ok(())
+}
+
+/// Builds the language modeling model used by the generation pipelines
+fn build_generation_model(
+    model_type: ModelType,
+    p: &nn::Path,
+    config: &ConfigOption,
+) -> Result<(), RustBertError> {
+    match (model_type, config) {
+        (ModelType::GPT2, ConfigOption::GPT2(config)) => {
+            GPT2LMHeadModel::new(p, config);
+        }
+        (ModelType::OpenAiGpt, ConfigOption::OpenAiGpt(config)) => {
+            OpenAIGPTLMHeadModel::new(p, config);
+        }
+        (ModelType::GPTNeo, ConfigOption::GPTNeo(config)) => {
+            GptNeoForCausalLM::new(p, config)?;
+        }
+        (ModelType::GPTBigCode, ConfigOption::GPTBigCode(config)) => {
+            GptBigCodeForCausalLM::new(p, config)?;
+        }
+        (ModelType::GPTNeoX, ConfigOption::GPTNeoX(config)) => {
+            GptNeoXForCausalLM::new(p, config)?;
+        }
+        (ModelType::Falcon, ConfigOption::Falcon(config)) => {
+            FalconForCausalLM::new(p, config)?;
+        }
+        (ModelType::Bloom, ConfigOption::Bloom(config)) => {
+            BloomForCausalLM::new(p, config)?;
+        }
+        (ModelType::Llama, ConfigOption::Llama(config)) => {
+            LlamaForCausalLM::new(p, config)?;
+        }
+        (ModelType::XLNet, ConfigOption::XLNet(config)) => {
+            XLNetLMHeadModel::new(p, config);
+        }
+        (ModelType::Reformer, ConfigOption::Reformer(config)) => {
+            ReformerModelWithLMHead::new(p, config)?;
+        }
+        (ModelType::T5, ConfigOption::T5(config)) | (ModelType::ByT5, ConfigOption::T5(config)) => {
+            T5ForConditionalGeneration::new(p, config);
+        }
+        (ModelType::Bart, ConfigOption::Bart(config)) => {
+            BartForConditionalGeneration::new(p, config);
+        }
+        (ModelType::Marian, ConfigOption::Marian(config)) => {
+            MarianForConditionalGeneration::new(p, config);
+        }
+        (ModelType::MBart, ConfigOption::MBart(config)) => {
+            MBartForConditionalGeneration::new(p, config);
+        }
+        (ModelType::M2M100, ConfigOption::M2M100(config)) => {
+            M2M100ForConditionalGeneration::new(p, config);
+        }
+        (ModelType::Pegasus, ConfigOption::Pegasus(config)) => {
+            PegasusForConditionalGeneration::new(p, config);
+        }
+        (ModelType::ProphetNet, ConfigOption::ProphetNet(config)) => {
+            ProphetNetForConditionalGeneration::new(p, config)?;
+        }
+        _ => {
+            return Err(RustBertError::InvalidConfigurationError(format!(
+                "Text generation not implemented for {:?}!",
+                model_type
+            )));
+        }
+    }
+    Ok(())
+}
+
+fn unsupported_model_type(model_type: ModelType) -> RustBertError {
+    RustBertError::InvalidConfigurationError(format!(
+        "Tiny random models are not available for {:?}",
+        model_type
+    ))
+}
+
+/// Writes the tokenizer files of the architecture, returning the paths of the vocabulary and of the merges (or
+/// SentencePiece model for Marian and M2M100)
+fn write_tokenizer_files(
+    model_type: ModelType,
+    directory: &Path,
+) -> Result<(PathBuf, Option<PathBuf>), RustBertError> {
+    let vocab_txt_path = directory.join("vocab.txt");
+    let vocab_json_path = directory.join("vocab.json");
+    let merges_path = directory.join("merges.txt");
+    let sentencepiece_path = directory.join("spiece.model");
+
+    let paths = match model_type {
+        ModelType::Bert | ModelType::DistilBert | ModelType::Electra | ModelType::MobileBert => {
+            write_wordpiece_vocab(
+                &vocab_txt_path,
+                &["[PAD]", "[UNK]", "[CLS]", "[SEP]", "[MASK]"],
+            )?;
+            (vocab_txt_path, None)
+        }
+        ModelType::ProphetNet => {
+            write_wordpiece_vocab(
+                &vocab_txt_path,
+                &["[PAD]", "[UNK]", "[CLS]", "[SEP]", "[MASK]", "[X_SEP]"],
+            )?;
+            (vocab_txt_path, None)
+        }
+        ModelType::Roberta | ModelType::Longformer | ModelType::Bart => {
+            write_byte_level_bpe_vocab(
+                &vocab_json_path,
+                &merges_path,
+                &["<s>", "<pad>", "</s>", "<unk>"],
+                &["<mask>"],
+                false,
+            )?;
+            (vocab_json_path, Some(merges_path))
+        }
+        ModelType::Deberta => {
+            write_byte_level_bpe_vocab(
+                &vocab_json_path,
+                &merges_path,
+                &["[PAD]", "[CLS]", "[SEP]", "[UNK]"],
+                &["[MASK]"],
+                false,
+            )?;
+            (vocab_json_path, Some(merges_path))
+        }
+        ModelType::GPT2
+        | ModelType::GPTNeo
+        | ModelType::GPTBigCode
+        | ModelType::GPTNeoX
+        | ModelType::Falcon
+        | ModelType::Bloom => {
+            write_byte_level_bpe_vocab(
+                &vocab_json_path,
+                &merges_path,
+                &[],
+                &["<|endoftext|>"],
+                false,
+            )?;
+            (vocab_json_path, Some(merges_path))
+        }
+        ModelType::OpenAiGpt => {
+            write_byte_level_bpe_vocab(&vocab_json_path, &merges_path, &[], &["<unk>"], true)?;
+            (vocab_json_path, Some(merges_path))
+        }
+        ModelType::T5 | ModelType::ByT5 => {
+            write_sentencepiece_model(&sentencepiece_path, &["<pad>", "</s>", "<unk>"], &[])?;
+            (sentencepiece_path, None)
+        }
+        ModelType::Albert | ModelType::FNet => {
+            write_sentencepiece_model(
+                &sentencepiece_path,
+                &["<pad>", "<unk>", "[CLS]", "[SEP]", "[MASK]"],
+                &[],
+            )?;
+            (sentencepiece_path, None)
+        }
+        ModelType::XLNet => {
+            write_sentencepiece_model(
+                &sentencepiece_path,
+                &[
+                    "<unk>", "<s>", "</s>", "<cls>", "<sep>", "<pad>", "<mask>", "<eod>", "<eop>",
+                ],
+                &[],
+            )?;
+            (sentencepiece_path, None)
+        }
+        ModelType::DebertaV2 => {
+            write_sentencepiece_model(
+                &sentencepiece_path,
+                &["[PAD]", "[CLS]", "[SEP]", "[UNK]"],
+                &["[MASK]"],
+            )?;
+            (sentencepiece_path, None)
+        }
+        // The Pegasus vocabulary adds its own special tokens before the pieces
+        ModelType::Pegasus => {
+            write_sentencepiece_model(&sentencepiece_path, &["<pad>", "</s>", "<unk>"], &[])?;
+            (sentencepiece_path, None)
+        }
+        // The XLM-RoBERTa and MBart vocabularies replace the first 3 pieces by their own special tokens
+        ModelType::XLMRoberta | ModelType::MBart | ModelType::Reformer | ModelType::Llama => {
+            write_sentencepiece_model(&sentencepiece_path, &["<unk>", "<s>", "</s>"], &[])?;
+            (sentencepiece_path, None)
+        }
+        ModelType::Marian => {
+            let pieces =
+                write_sentencepiece_model(&sentencepiece_path, &["<unk>", "<s>", "</s>"], &[])?;
+            write_json_vocab(
+                &vocab_json_path,
+                &["</s>", "<unk>"],
+                &pieces[3..],
+                &["<pad>"],
+            )?;
+            (vocab_json_path, Some(sentencepiece_path))
+        }
+        ModelType::M2M100 => {
+            let pieces =
+                write_sentencepiece_model(&sentencepiece_path, &["<unk>", "<s>", "</s>"], &[])?;
+            write_json_vocab(
+                &vocab_json_path,
+                &["<s>", "<pad>", "</s>", "<unk>"],
+                &pieces[3..],
+                &[],
+            )?;
+            (vocab_json_path, Some(sentencepiece_path))
+        }
+        ModelType::Memnet | ModelType::Custom(_) => {
+            return Err(unsupported_model_type(model_type));
+        }
+    };
+    Ok(paths)
+}
+
+/// Printable ASCII characters, forming the base vocabulary of the tiny tokenizers
+fn ascii_characters() -> impl Iterator<Item = String> {
+    (b'!'..=b'~').map(|byte| (byte as char).to_string())
+}
+
+/// Writes a WordPiece vocabulary with the characters as words and as word continuations (`##` prefix)
+fn write_wordpiece_vocab(path: &Path, special_tokens: &[&str]) -> Result<(), RustBertError> {
+    let tokens = special_tokens
+        .iter()
+        .map(|token| token.to_string())
+        .chain(ascii_characters())
+        .chain(ascii_characters().map(|character| format!("##{}", character)))
+        .collect::<Vec<String>>();
+    fs::write(path, tokens.join("\n") + "\n")?;
+    Ok(())
+}
+
+/// Writes a byte-level BPE vocabulary made of the 256 byte symbols (with `</w>` end of word variants of the printable
+/// characters for OpenAI GPT, whose BPE is not byte-level) and an empty merges file
+fn write_byte_level_bpe_vocab(
+    vocab_path: &Path,
+    merges_path: &Path,
+    leading_special_tokens: &[&str],
+    trailing_special_tokens: &[&str],
+    end_of_word_suffix: bool,
+) -> Result<(), RustBertError> {
+    // Bytes are mapped to printable characters as in the GPT-2 byte encoder
+    let mut shifted_bytes = 0;
+    let mut tokens = (0u32..=255)
+        .map(|byte| {
+            let printable = matches!(byte, 33..=126 | 161..=172 | 174..=255);
+            let code_point = if printable {
+                byte
+            } else {
+                shifted_bytes += 1;
+                255 + shifted_bytes
+            };
+            char::from_u32(code_point).unwrap().to_string()
+        })
+        .collect::<Vec<String>>();
+    if end_of_word_suffix {
+        tokens.extend(ascii_characters().map(|character| format!("{}</w>", character)));
+    }
+    write_json_vocab(
+        vocab_path,
+        leading_special_tokens,
+        &tokens,
+        trailing_special_tokens,
+    )?;
+    fs::write(merges_path, "#version: 0.2\n")?;
+    Ok(())
+}
+
+/// Writes a JSON vocabulary mapping the tokens to their position
+fn write_json_vocab(
+    path: &Path,
+    leading_special_tokens: &[&str],
+    tokens: &[String],
+    trailing_special_tokens: &[&str],
+) -> Result<(), RustBertError> {
+    let vocab = leading_special_tokens
+        .iter()
+        .map(|token| token.to_string())
+        .chain(tokens.iter().cloned())
+        .chain(
+            trailing_special_tokens
+                .iter()
+                .map(|token| token.to_string()),
+        )
+        .enumerate()
+        .map(|(id, token)| (token, serde_json::Value::from(id)))
+        .collect::<serde_json::Map<String, serde_json::Value>>();
+    let content =
+        serde_json::to_string(&vocab).map_err(|error| RustBertError::IOError(error.to_string()))?;
+    fs::write(path, content)?;
+    Ok(())
+}
+
+/// Writes a SentencePiece model made of the special tokens, the word boundary marker and the characters, returning
+/// its pieces. The model protobuf is encoded directly: only the pieces (`ModelProto.pieces`, with their `piece`,
+/// `score` and `type` fields) are read by the tokenizers.
+fn write_sentencepiece_model(
+    path: &Path,
+    leading_special_tokens: &[&str],
+    trailing_special_tokens: &[&str],
+) -> Result<Vec<String>, RustBertError> {
+    let pieces = leading_special_tokens
+        .iter()
+        .map(|token| token.to_string())
+        .chain(std::iter::once(WORD_BOUNDARY.to_string()))
+        .chain(ascii_characters())
+        .chain(
+            trailing_special_tokens
+                .iter()
+                .map(|token| token.to_string()),
+        )
+        .collect::<Vec<String>>();
+
+    let mut model = vec![];
+    for (index, piece) in pieces.iter().enumerate() {
+        let special = leading_special_tokens.contains(&piece.as_str())
+            || trailing_special_tokens.contains(&piece.as_str());
+        // Piece types: 1 (normal), 2 (unknown), 3 (control)
+        let (score, piece_type) = match piece.as_str() {
+            "<unk>" | "[UNK]" => (0f32, 2),
+            _ if special => (0f32, 3),
+            _ => (-(index as f32), 1),
+        };
+        let mut message = vec![0x0a];
+        write_varint(piece.len() as u64, &mut message);
+        message.extend_from_slice(piece.as_bytes());
+        message.push(0x15);
+        message.extend_from_slice(&score.to_le_bytes());
+        message.push(0x18);
+        write_varint(piece_type, &mut message);
+
+        model.push(0x0a);
+        write_varint(message.len() as u64, &mut model);
+        model.extend(message);
+    }
+    fs::write(path, model)?;
+    Ok(pieces)
+}
+
+/// Encodes an unsigned integer as a protobuf varint
+fn write_varint(mut value: u64, buffer: &mut Vec<u8>) {
+    while value >= 0x80 {
+        buffer.push((value as u8 & 0x7f) | 0x80);
+        value >>= 7;
+    }
+    buffer.push(value as u8);
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn varint_encoding() {
+        let mut buffer = vec![];
+        write_varint(1, &mut buffer);
+        write_varint(300, &mut buffer);
+        assert_eq!(buffer, vec![0x01, 0xac, 0x02]);
+    }
+
+    #[test]
+    fn sentencepiece_model_encoding() -> anyhow::Result<()> {
+        let directory = tempfile::tempdir()?;
+        let path = directory.path().join("spiece.model");
+        let pieces = write_sentencepiece_model(&path, &["<unk>"], &[])?;
+        assert_eq!(pieces.len(), 2 + 94);
+        assert_eq!(pieces[1], WORD_BOUNDARY);
+
+        // Piece message of the unknown token: piece, score and type fields
+        let content = fs::read(&path)?;
+        assert_eq!(
+            &content[..17],
+            &[0x0a, 14, 0x0a, 5, b'<', b'u', b'n', b'k', b'>', 0x15, 0, 0, 0, 0, 0x18, 2, 0x0a]
+        );
+        Ok(())
+    }
+}
@@ -61,6 +61,8 @@
 //!         index: 0,
 //!         word_index: 0,
 //!         offset: None,
+//!         byte_offset: None,
+//!         utf16_offset: None,
 //!         mask: Mask::Special,
 //!     },
 //!     Token {
@@ -72,6 +74,8 @@
 //!         index: 1,
 //!         word_index: 1,
 //!         offset: Some(Offset { begin: 0, end: 2 }),
+//!         byte_offset: Some(Offset { begin: 0, end: 2 }),
+//!         utf16_offset: Some(Offset { begin: 0, end: 2 }),
 //!         mask: Mask::None,
 //!     },
 //!     Token {
@@ -83,6 +87,8 @@
 //!         index: 2,
 //!         word_index: 2,
 //!         offset: Some(Offset { begin: 3, end: 7 }),
+//!         byte_offset: Some(Offset { begin: 3, end: 7 }),
+//!         utf16_offset: Some(Offset { begin: 3, end: 7 }),
 //!         mask: Mask::None,
 //!     },
 //!     Token {
@@ -94,6 +100,8 @@
 //!         index: 3,
 //!         word_index: 3,
 //!         offset: Some(Offset { begin: 8, end: 10 }),
+//!         byte_offset: Some(Offset { begin: 8, end: 10 }),
+//!         utf16_offset: Some(Offset { begin: 8, end: 10 }),
 //!         mask: Mask::None,
 //!     },
 //!     Token {
@@ -105,6 +113,8 @@
 //!         index: 4,
 //!         word_index: 4,
 //!         offset: Some(Offset { begin: 11, end: 17 }),
+//!         byte_offset: Some(Offset { begin: 11, end: 18 }),
+//!         utf16_offset: Some(Offset { begin: 11, end: 17 }),
 //!         mask: Mask::None,
 //!     }, // ...
 //! ]
@@ -114,18 +124,26 @@
 use crate::albert::AlbertForTokenClassification;
 use crate::bert::BertForTokenClassification;
 use crate::common::error::RustBertError;
+use crate::common::offsets::OffsetConverter;
 use crate::deberta::DebertaForTokenClassification;
 use crate::distilbert::DistilBertForTokenClassification;
 use crate::electra::ElectraForTokenClassification;
 use crate::fnet::FNetForTokenClassification;
 use crate::longformer::LongformerForTokenClassification;
 use crate::mobilebert::MobileBertForTokenClassification;
-use crate::pipelines::common::{ConfigOption, ModelType, TokenizerOption};
+use crate::pipelines::common::{
+    batch_first_xlnet_outputs, flatten_albert_attentions, ConfigOption, IntermediateOutputs,
+    ModelType, Pipeline, TokenizerOption,
+};
+use crate::pipelines::input_length::{check_input_lengths, InputLengthPolicy};
+use crate::pipelines::memory::{MemoryEstimator, MemoryStatistics};
+use crate::pipelines::registry::{get_model_registration, CustomModel};
+use crate::pipelines::shared_encoder::SharedEncoder;
 use crate::resources::ResourceProvider;
 use crate::roberta::RobertaForTokenClassification;
 use crate::xlnet::XLNetForTokenClassification;
 use ordered_float::OrderedFloat;
-use rust_tokenizers::tokenizer::Tokenizer;
+use rust_tokenizers::tokenizer::{Tokenizer, TruncationStrategy};
 use rust_tokenizers::{
     ConsolidatableTokens, ConsolidatedTokenIterator, Mask, Offset, TokenIdsWithOffsets, TokenTrait,
     TokenizedInput,
@@ -161,8 +179,12 @@ pub struct Token {
     pub index: u16,
     /// Token word position index
     pub word_index: u16,
-    /// Token offsets
+    /// Token offsets (in characters of the original string)
     pub offset: Option<Offset>,
+    /// Token offsets in bytes of the original string
+    pub byte_offset: Option<Offset>,
+    /// Token offsets in UTF-16 code units of the original string
+    pub utf16_offset: Option<Offset>,
     /// Token mask
     pub mask: Mask,
 }
@@ -241,6 +263,10 @@ pub struct TokenClassificationConfig {
     pub label_aggregation_function: LabelAggregationOption,
     /// Batch size for predictions
     pub batch_size: usize,
+    /// Flag indicating if the hidden states of all layers should be returned by `predict_with_intermediate_outputs` (default: false)
+    pub output_hidden_states: bool,
+    /// Flag indicating if the attention weights of all layers should be returned by `predict_with_intermediate_outputs` (default: false)
+    pub output_attentions: bool,
 }
 
 impl TokenClassificationConfig {
@@ -280,6 +306,8 @@ impl TokenClassificationConfig {
             device: Device::cuda_if_available(),
             label_aggregation_function,
             batch_size: 64,
+            output_hidden_states: false,
+            output_attentions: false,
         }
     }
 }
@@ -329,6 +357,8 @@ pub enum TokenClassificationOption {
     Longformer(LongformerForTokenClassification),
     /// FNet for Token Classification
     FNet(FNetForTokenClassification),
+    /// Custom architecture for Token Classification (see `registry::register_model`)
+    Custom(&'static str, Box<dyn CustomModel>),
 }
 
 impl TokenClassificationOption {
@@ -481,6 +511,19 @@ impl TokenClassificationOption {
                     ))
                 }
             }
+            ModelType::Custom(name) => {
+                let registration = get_model_registration(model_type)?;
+                match &registration.token_classification_builder {
+                    Some(builder) => Ok(TokenClassificationOption::Custom(
+                        name,
+                        builder(p.borrow(), config)?,
+                    )),
+                    None => Err(RustBertError::InvalidConfigurationError(format!(
+                        "Token classification not implemented for {:?}!",
+                        model_type
+                    ))),
+                }
+            }
             _ => Err(RustBertError::InvalidConfigurationError(format!(
                 "Token classification not implemented for {:?}!",
                 model_type
@@ -503,9 +546,12 @@ impl TokenClassificationOption {
             Self::XLNet(_) => ModelType::XLNet,
             Self::Longformer(_) => ModelType::Longformer,
             Self::FNet(_) => ModelType::FNet,
+            Self::Custom(name, _) => ModelType::Custom(name),
         }
     }
 
+    /// Returns the logits, and the hidden states and attention weights of all layers if requested by the model
+    /// configuration
     fn forward_t(
         &self,
         input_ids: Option<&Tensor>,
@@ -514,22 +560,25 @@ impl TokenClassificationOption {
         position_ids: Option<&Tensor>,
         input_embeds: Option<&Tensor>,
         train: bool,
-    ) -> Tensor {
+    ) -> (Tensor, Option<Vec<Tensor>>, Option<Vec<Tensor>>) {
         match *self {
             Self::Bert(ref model) => {
-                model
-                    .forward_t(
-                        input_ids,
-                        mask,
-                        token_type_ids,
-                        position_ids,
-                        input_embeds,
-                        train,
-                    )
-                    .logits
+                let output = model.forward_t(
+                    input_ids,
+                    mask,
+                    token_type_ids,
+                    position_ids,
+                    input_embeds,
+                    train,
+                );
+                (
+                    output.logits,
+                    output.all_hidden_states,
+                    output.all_attentions,
+                )
             }
             Self::Deberta(ref model) => {
-                model
+                let output = model
                     .forward_t(
                         input_ids,
                         mask,
@@ -538,11 +587,15 @@ impl TokenClassificationOption {
                         input_embeds,
                         train,
                     )
-                    .expect("Error in DeBERTa forward_t")
-                    .logits
+                    .expect("Error in DeBERTa forward_t");
+                (
+                    output.logits,
+                    output.all_hidden_states,
+                    output.all_attentions,
+                )
             }
             Self::DebertaV2(ref model) => {
-                model
+                let output = model
                     .forward_t(
                         input_ids,
                         mask,
@@ -551,91 +604,131 @@ impl TokenClassificationOption {
                         input_embeds,
                         train,
                     )
-                    .expect("Error in DeBERTa V2 forward_t")
-                    .logits
+                    .expect("Error in DeBERTa V2 forward_t");
+                (
+                    output.logits,
+                    output.all_hidden_states,
+                    output.all_attentions,
+                )
             }
             Self::DistilBert(ref model) => {
-                model
+                let output = model
                     .forward_t(input_ids, mask, input_embeds, train)
-                    .expect("Error in distilbert forward_t")
-                    .logits
+                    .expect("Error in distilbert forward_t");
+                (
+                    output.logits,
+                    output.all_hidden_states,
+                    output.all_attentions,
+                )
             }
             Self::MobileBert(ref model) => {
-                model
+                let output = model
                     .forward_t(input_ids, None, None, input_embeds, mask, train)
-                    .expect("Error in mobilebert forward_t")
-                    .logits
+                    .expect("Error in mobilebert forward_t");
+                (
+                    output.logits,
+                    output.all_hidden_states,
+                    output.all_attentions,
+                )
             }
             Self::Roberta(ref model) | Self::XLMRoberta(ref model) => {
-                model
-                    .forward_t(
-                        input_ids,
-                        mask,
-                        token_type_ids,
-                        position_ids,
-                        input_embeds,
-                        train,
-                    )
-                    .logits
+                let output = model.forward_t(
+                    input_ids,
+                    mask,
+                    token_type_ids,
+                    position_ids,
+                    input_embeds,
+                    train,
+                );
+                (
+                    output.logits,
+                    output.all_hidden_states,
+                    output.all_attentions,
+                )
             }
             Self::Electra(ref model) => {
-                model
-                    .forward_t(
-                        input_ids,
-                        mask,
-                        token_type_ids,
-                        position_ids,
-                        input_embeds,
-                        train,
-                    )
-                    .logits
+                let output = model.forward_t(
+                    input_ids,
+                    mask,
+                    token_type_ids,
+                    position_ids,
+                    input_embeds,
+                    train,
+                );
+                (
+                    output.logits,
+                    output.all_hidden_states,
+                    output.all_attentions,
+                )
             }
             Self::Albert(ref model) => {
-                model
-                    .forward_t(
-                        input_ids,
-                        mask,
-                        token_type_ids,
-                        position_ids,
-                        input_embeds,
-                        train,
-                    )
-                    .logits
+                let output = model.forward_t(
+                    input_ids,
+                    mask,
+                    token_type_ids,
+                    position_ids,
+                    input_embeds,
+                    train,
+                );
+                (
+                    output.logits,
+                    output.all_hidden_states,
+                    flatten_albert_attentions(output.all_attentions),
+                )
             }
             Self::XLNet(ref model) => {
-                model
+                let output = model.forward_t(
+                    input_ids,
+                    mask,
+                    None,
+                    None,
+                    None,
+                    token_type_ids,
+                    input_embeds,
+                    train,
+                );
+                let (all_hidden_states, all_attentions) =
+                    batch_first_xlnet_outputs(output.all_hidden_states, output.all_attentions);
+                (output.logits, all_hidden_states, all_attentions)
+            }
+            Self::Longformer(ref model) => {
+                let output = model
                     .forward_t(
                         input_ids,
                         mask,
                         None,
-                        None,
-                        None,
                         token_type_ids,
+                        position_ids,
                         input_embeds,
                         train,
                     )
-                    .logits
+                    .expect("Error in longformer forward_t");
+                (
+                    output.logits,
+                    output.all_hidden_states,
+                    output.all_attentions,
+                )
             }
-            Self::Longformer(ref model) => {
+            Self::FNet(ref model) => {
+                let output = model
+                    .forward_t(input_ids, token_type_ids, position_ids, input_embeds, train)
+                    .expect("Error in fnet forward_t");
+                (output.logits, output.all_hidden_states, None)
+            }
+            Self::Custom(_, ref model) => (
                 model
                     .forward_t(
                         input_ids,
                         mask,
-                        None,
                         token_type_ids,
                         position_ids,
                         input_embeds,
                         train,
                     )
-                    .expect("Error in longformer forward_t")
-                    .logits
-            }
-            Self::FNet(ref model) => {
-                model
-                    .forward_t(input_ids, token_type_ids, position_ids, input_embeds, train)
-                    .expect("Error in fnet forward_t")
-                    .logits
-            }
+                    .expect("Error in custom model forward_t"),
+                None,
+                None,
+            ),
         }
     }
 }
@@ -649,6 +742,9 @@ pub struct TokenClassificationModel {
     label_aggregation_function: LabelAggregationOption,
     max_length: usize,
     batch_size: usize,
+    memory_estimator: MemoryEstimator,
+    memory_budget: Option<usize>,
+    input_length_policy: InputLengthPolicy,
 }
 
 impl TokenClassificationModel {
@@ -670,10 +766,30 @@ impl TokenClassificationModel {
     /// ```
     pub fn new(
         config: TokenClassificationConfig,
+    ) -> Result<TokenClassificationModel, RustBertError> {
+        Self::new_impl(config, None)
+    }
+
+    /// Build a new `TokenClassificationModel` sharing the encoder weights of a `SharedEncoder`. Only the task
+    /// head weights are read from the model resource of the configuration.
+    ///
+    /// # Arguments
+    ///
+    /// * `config` - `TokenClassificationConfig` object containing the resource references (task head weights, vocabulary, configuration) and device placement (CPU/GPU)
+    /// * `shared_encoder` - `SharedEncoder` holding the encoder weights. Its model type and device must match the configuration.
+    pub fn new_with_shared_encoder(
+        config: TokenClassificationConfig,
+        shared_encoder: &SharedEncoder,
+    ) -> Result<TokenClassificationModel, RustBertError> {
+        Self::new_impl(config, Some(shared_encoder))
+    }
+
+    fn new_impl(
+        config: TokenClassificationConfig,
+        shared_encoder: Option<&SharedEncoder>,
     ) -> Result<TokenClassificationModel, RustBertError> {
         let config_path = config.config_resource.get_local_path()?;
         let vocab_path = config.vocab_resource.get_local_path()?;
-        let weights_path = config.model_resource.get_local_path()?;
         let merges_path = if let Some(merges_resource) = &config.merges_resource {
             Some(merges_resource.get_local_path()?)
         } else {
@@ -691,7 +807,10 @@ impl TokenClassificationModel {
             config.add_prefix_space,
         )?;
         let mut var_store = VarStore::new(device);
-        let model_config = ConfigOption::from_file(config.model_type, config_path);
+        let memory_estimator = MemoryEstimator::from_file(&config_path)?;
+        let mut model_config = ConfigOption::from_file(config.model_type, config_path)?;
+        model_config.set_output_options(config.output_hidden_states, config.output_attentions);
+        model_config.validate(Some(&tokenizer), None)?;
         let max_length = model_config
             .get_max_len()
             .map(|v| v as usize)
@@ -700,7 +819,14 @@ impl TokenClassificationModel {
             TokenClassificationOption::new(config.model_type, &var_store.root(), &model_config)?;
         let label_mapping = model_config.get_label_mapping().clone();
         let batch_size = config.batch_size;
-        var_store.load(weights_path)?;
+        match shared_encoder {
+            Some(shared_encoder) => shared_encoder.load_into(
+                config.model_type,
+                &mut var_store,
+                &config.model_resource.get_local_path()?,
+            )?,
+            None => crate::resources::load_weights(&config.model_resource, &mut var_store)?,
+        }
         Ok(TokenClassificationModel {
             tokenizer,
             token_sequence_classifier,
@@ -709,9 +835,48 @@ impl TokenClassificationModel {
             label_aggregation_function,
             max_length,
             batch_size,
+            memory_estimator,
+            memory_budget: None,
+            input_length_policy: InputLengthPolicy::Chunk,
         })
     }
 
+    /// Returns the memory usage statistics of the model for a batch of inputs
+    ///
+    /// # Arguments
+    ///
+    /// * `batch_size` - Number of texts labelled at once. Texts longer than the maximum length of the model are split in several sequences.
+    /// * `sequence_length` - Length (in tokens) of the longest text of the batch
+    pub fn memory_statistics(&self, batch_size: usize, sequence_length: usize) -> MemoryStatistics {
+        // Inputs are processed in batches of at most `self.batch_size` sequences of at most `self.max_length` tokens
+        let num_sequences = batch_size * ((sequence_length.max(1) - 1) / self.max_length + 1);
+        self.memory_estimator.estimate(
+            &self.var_store,
+            num_sequences.min(self.batch_size),
+            sequence_length.min(self.max_length),
+        )
+    }
+
+    /// Sets the memory budget (in bytes) of the model. Batches predicted to exceed it are rejected by `Pipeline::run`.
+    ///
+    /// # Arguments
+    ///
+    /// * `memory_budget` - Optional memory budget in bytes (default: None, no limit)
+    pub fn set_memory_budget(&mut self, memory_budget: Option<usize>) {
+        self.memory_budget = memory_budget;
+    }
+
+    /// Sets the handling of the inputs longer than the maximum length of the model by `Pipeline::run`.
+    ///
+    /// # Arguments
+    ///
+    /// * `input_length_policy` - `InputLengthPolicy` (default: `InputLengthPolicy::Chunk`, the inputs are split in
+    /// overlapping spans labelled separately). With `InputLengthPolicy::Truncate`, the tokens beyond the maximum
+    /// length of the model are not labelled.
+    pub fn set_input_length_policy(&mut self, input_length_policy: InputLengthPolicy) {
+        self.input_length_policy = input_length_policy;
+    }
+
     fn generate_features<S>(&self, input: S, example_index: usize) -> Vec<InputFeature>
     where
         S: AsRef<str>,
@@ -865,13 +1030,90 @@ impl TokenClassificationModel {
     where
         S: AsRef<str>,
     {
-        let mut features: Vec<InputFeature> = input
+        let features = self.generate_input_features(input);
+        self.predict_features(
+            input,
+            features,
+            consolidate_sub_tokens,
+            return_special,
+            false,
+        )
+        .0
+    }
+
+    /// Classify tokens in a text sequence, also returning the hidden states and attention weights of all layers of
+    /// the model, as requested by the `output_hidden_states` and `output_attentions` flags of the configuration.
+    /// Inputs longer than the maximum length of the model are split in several spans, each with its own
+    /// `IntermediateOutputs` (referencing the index of the input).
+    ///
+    /// # Arguments
+    ///
+    /// * `input` - `&[&str]` Array of texts to extract entities from.
+    /// * `consolidate_subtokens` - bool flag indicating if subtokens should be consolidated at the token level
+    /// * `return_special` - bool flag indicating if labels for special tokens should be returned
+    ///
+    /// # Returns
+    ///
+    /// * `(Vec<Vec<Token>>, Vec<IntermediateOutputs>)` containing the tokens for the input texts and the intermediate
+    /// outputs for each span
+    pub fn predict_with_intermediate_outputs<S>(
+        &self,
+        input: &[S],
+        consolidate_sub_tokens: bool,
+        return_special: bool,
+    ) -> (Vec<Vec<Token>>, Vec<IntermediateOutputs>)
+    where
+        S: AsRef<str>,
+    {
+        let features = self.generate_input_features(input);
+        self.predict_features(
+            input,
+            features,
+            consolidate_sub_tokens,
+            return_special,
+            true,
+        )
+    }
+
+    fn generate_input_features<S>(&self, input: &[S]) -> Vec<InputFeature>
+    where
+        S: AsRef<str>,
+    {
+        input
             .iter()
             .enumerate()
             .flat_map(|(example_index, example)| self.generate_features(example, example_index))
-            .collect();
+            .collect()
+    }
 
+    fn check_memory_budget(&self, features: &[InputFeature]) -> Result<(), RustBertError> {
+        let sequence_length = features
+            .iter()
+            .map(|feature| feature.input_ids.len())
+            .max()
+            .unwrap_or(0);
+        self.memory_estimator
+            .estimate(
+                &self.var_store,
+                features.len().min(self.batch_size),
+                sequence_length,
+            )
+            .check_budget(self.memory_budget)
+    }
+
+    fn predict_features<S>(
+        &self,
+        input: &[S],
+        mut features: Vec<InputFeature>,
+        consolidate_sub_tokens: bool,
+        return_special: bool,
+        return_intermediate_outputs: bool,
+    ) -> (Vec<Vec<Token>>, Vec<IntermediateOutputs>)
+    where
+        S: AsRef<str>,
+    {
         let mut example_tokens_map: Vec<Vec<Token>> = vec![Vec::new(); input.len()];
+        let mut intermediate_outputs = Vec::new();
         let mut start = 0usize;
         let len_features = features.len();
 
@@ -880,25 +1122,41 @@ impl TokenClassificationModel {
 
             no_grad(|| {
                 let batch_features = &mut features[start..end];
+                let sequence_lengths = batch_features
+                    .iter()
+                    .map(|feature| feature.input_ids.len() as i64)
+                    .collect::<Vec<i64>>();
                 let (input_ids, attention_masks) = self.pad_features(batch_features);
-                let output = self.token_sequence_classifier.forward_t(
-                    Some(&input_ids),
-                    Some(&attention_masks),
-                    None,
-                    None,
-                    None,
-                    false,
-                );
+                let (output, all_hidden_states, all_attentions) =
+                    self.token_sequence_classifier.forward_t(
+                        Some(&input_ids),
+                        Some(&attention_masks),
+                        None,
+                        None,
+                        None,
+                        false,
+                    );
+                if return_intermediate_outputs {
+                    let input_indices = batch_features
+                        .iter()
+                        .map(|feature| feature.example_index)
+                        .collect::<Vec<usize>>();
+                    intermediate_outputs.extend(IntermediateOutputs::from_batch(
+                        &input_indices,
+                        &sequence_lengths,
+                        all_hidden_states,
+                        all_attentions,
+                    ));
+                }
                 let score = output.exp() / output.exp().sum_dim_intlist(&[-1], true, Kind::Float);
                 let label_indices = score.argmax(-1, true);
                 for sentence_idx in 0..label_indices.size()[0] {
                     let labels = label_indices.get(sentence_idx);
-                    let feature = &features[sentence_idx as usize];
+                    let feature = &features[start + sentence_idx as usize];
                     let sentence_reference_flag = &feature.reference_feature;
-                    let original_chars = input[feature.example_index]
-                        .as_ref()
-                        .chars()
-                        .collect::<Vec<char>>();
+                    let original_text = input[feature.example_index].as_ref();
+                    let original_chars = original_text.chars().collect::<Vec<char>>();
+                    let offset_converter = OffsetConverter::new(original_text);
                     let mut word_idx: u16 = 0;
                     for position_idx in sentence_reference_flag
                         .iter()
@@ -916,6 +1174,7 @@ impl TokenClassificationModel {
                         let token = {
                             self.decode_token(
                                 &original_chars,
+                                &offset_converter,
                                 feature,
                                 &input_ids,
                                 &labels,
@@ -936,7 +1195,7 @@ impl TokenClassificationModel {
         if consolidate_sub_tokens {
             self.consolidate_tokens(&mut tokens, &self.label_aggregation_function);
         }
-        tokens
+        (tokens, intermediate_outputs)
     }
 
     fn pad_features(&self, features: &mut [InputFeature]) -> (Tensor, Tensor) {
@@ -981,6 +1240,7 @@ impl TokenClassificationModel {
     fn decode_token(
         &self,
         original_sentence_chars: &[char],
+        offset_converter: &OffsetConverter,
         sentence_tokens: &InputFeature,
         input_tensor: &Tensor,
         labels: &Tensor,
@@ -1019,6 +1279,7 @@ impl TokenClassificationModel {
             Some(offsets) => {
                 let (start_char, end_char) = (offsets.begin as usize, offsets.end as usize);
                 let end_char = min(end_char, original_sentence_chars.len());
+                let start_char = min(start_char, end_char);
                 let text = original_sentence_chars[start_char..end_char]
                     .iter()
                     .collect();
@@ -1039,6 +1300,8 @@ impl TokenClassificationModel {
             index: position_idx as u16,
             word_index,
             offset: offsets.to_owned(),
+            byte_offset: offsets.map(|offset| offset_converter.to_byte_offset(offset)),
+            utf16_offset: offsets.map(|offset| offset_converter.to_utf16_offset(offset)),
             mask: sentence_tokens.mask[position_idx as usize],
         }
     }
@@ -1060,25 +1323,20 @@ impl TokenClassificationModel {
                     let sentence = (sub_tokens[0]).sentence;
                     let index = (sub_tokens[0]).index;
                     let word_index = (sub_tokens[0]).word_index;
-                    let offset_start = sub_tokens
-                        .first()
-                        .unwrap()
-                        .offset
-                        .as_ref()
-                        .map(|offset| offset.begin);
-                    let offset_end = sub_tokens
-                        .last()
-                        .unwrap()
-                        .offset
-                        .as_ref()
-                        .map(|offset| offset.end);
-                    let offset = if let (Some(offset_start), Some(offset_end)) =
-                        (offset_start, offset_end)
-                    {
-                        Some(Offset::new(offset_start, offset_end))
-                    } else {
-                        None
+                    let merge_offsets = |get_offset: fn(&Token) -> Option<Offset>| {
+                        let offset_start =
+                            get_offset(sub_tokens.first().unwrap()).map(|offset| offset.begin);
+                        let offset_end =
+                            get_offset(sub_tokens.last().unwrap()).map(|offset| offset.end);
+                        if let (Some(offset_start), Some(offset_end)) = (offset_start, offset_end) {
+                            Some(Offset::new(offset_start, offset_end))
+                        } else {
+                            None
+                        }
                     };
+                    let offset = merge_offsets(|token| token.offset);
+                    let byte_offset = merge_offsets(|token| token.byte_offset);
+                    let utf16_offset = merge_offsets(|token| token.utf16_offset);
                     let mut text = String::new();
                     let mut score = 1f64;
                     for current_sub_token in sub_tokens.iter() {
@@ -1098,6 +1356,8 @@ impl TokenClassificationModel {
                         index,
                         word_index,
                         offset,
+                        byte_offset,
+                        utf16_offset,
                         mask: Default::default(),
                     };
                     tokens_to_replace.push(((cursor, cursor + sub_tokens.len()), token));
@@ -1143,3 +1403,69 @@ impl TokenClassificationModel {
         }
     }
 }
+
+/// # Options of the token classification pipeline when run as a `Pipeline`
+#[derive(Debug, Clone, Copy)]
+pub struct TokenClassificationOptions {
+    /// Merge the sub-tokens of each word into a single token (default: true)
+    pub consolidate_sub_tokens: bool,
+    /// Return the special tokens, e.g. CLS or SEP (default: false)
+    pub return_special: bool,
+}
+
+impl Default for TokenClassificationOptions {
+    fn default() -> Self {
+        TokenClassificationOptions {
+            consolidate_sub_tokens: true,
+            return_special: false,
+        }
+    }
+}
+
+impl<S> Pipeline<S, Vec<Token>> for TokenClassificationModel
+where
+    S: AsRef<str>,
+{
+    type Options = TokenClassificationOptions;
+
+    fn run_with_options(
+        &self,
+        inputs: &[S],
+        options: &TokenClassificationOptions,
+    ) -> Result<Vec<Vec<Token>>, RustBertError> {
+        if self.input_length_policy == InputLengthPolicy::Error {
+            let texts = inputs
+                .iter()
+                .map(|input| input.as_ref())
+                .collect::<Vec<&str>>();
+            let tokenized_input = self.tokenizer.encode_list(
+                &texts,
+                self.max_length,
+                &TruncationStrategy::LongestFirst,
+                0,
+            );
+            check_input_lengths(&tokenized_input, self.max_length)?;
+        }
+        let mut features = self.generate_input_features(inputs);
+        if self.input_length_policy == InputLengthPolicy::Truncate {
+            // Only the first span of each input is labelled, up to its last token
+            features.dedup_by_key(|feature| feature.example_index);
+            for feature in features.iter_mut() {
+                feature
+                    .reference_feature
+                    .iter_mut()
+                    .for_each(|reference| *reference = true);
+            }
+        }
+        self.check_memory_budget(&features)?;
+        Ok(self
+            .predict_features(
+                inputs,
+                features,
+                options.consolidate_sub_tokens,
+                options.return_special,
+                false,
+            )
+            .0)
+    }
+}
@@ -0,0 +1,445 @@
+// Copyright 2022 Guillaume Becquin
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//     http://www.apache.org/licenses/LICENSE-2.0
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! # Topic modeling pipeline
+//! Groups a corpus of documents into topics and describes each topic with keywords, following the approach of
+//! [BERTopic](https://arxiv.org/abs/2203.05794):
+//! 1. the documents are embedded with a sentence embeddings model
+//! 2. the dimension of the embeddings is reduced with a principal component analysis (PCA)
+//! 3. the reduced embeddings are clustered with k-means (k-means++ initialization with a fixed seed)
+//! 4. the words of each topic are scored with a class-based TF-IDF (c-TF-IDF): all documents of a topic are treated
+//! as a single document, and the words frequent in a topic but rare in the others get the highest scores.
+//!
+//! ```no_run
+//! # fn main() -> anyhow::Result<()> {
+//! use rust_bert::pipelines::sentence_embeddings::SentenceEmbeddingsModelType;
+//! use rust_bert::pipelines::topic_modeling::{TopicModel, TopicModelingConfig};
+//!
+//! let config = TopicModelingConfig::new(SentenceEmbeddingsModelType::AllMiniLmL12V2.into(), 2);
+//! let model = TopicModel::new(config)?;
+//!
+//! let documents = [
+//!     "The striker scored twice in the final.",
+//!     "The goalkeeper saved a penalty in the last minute.",
+//!     "The central bank raised interest rates.",
+//!     "Inflation slowed down for the third month in a row.",
+//! ];
+//! let output = model.fit(&documents)?;
+//! for topic in output.topics {
+//!     println!("{}: {} documents", topic.label, topic.size);
+//! }
+//! # Ok(())
+//! # }
+//! ```
+
+use crate::common::error::RustBertError;
+use crate::pipelines::clustering::kmeans;
+use crate::pipelines::sentence_embeddings::{SentenceEmbeddingsConfig, SentenceEmbeddingsModel};
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, HashSet};
+use tch::{Kind, Tensor};
+
+/// Common English words excluded from the topic keywords by default
+pub const ENGLISH_STOP_WORDS: &[&str] = &[
+    "a",
+    "about",
+    "above",
+    "after",
+    "again",
+    "against",
+    "all",
+    "am",
+    "an",
+    "and",
+    "any",
+    "are",
+    "as",
+    "at",
+    "be",
+    "because",
+    "been",
+    "before",
+    "being",
+    "below",
+    "between",
+    "both",
+    "but",
+    "by",
+    "can",
+    "could",
+    "did",
+    "do",
+    "does",
+    "doing",
+    "down",
+    "during",
+    "each",
+    "few",
+    "for",
+    "from",
+    "further",
+    "had",
+    "has",
+    "have",
+    "having",
+    "he",
+    "her",
+    "here",
+    "hers",
+    "herself",
+    "him",
+    "himself",
+    "his",
+    "how",
+    "i",
+    "if",
+    "in",
+    "into",
+    "is",
+    "it",
+    "its",
+    "itself",
+    "just",
+    "me",
+    "more",
+    "most",
+    "my",
+    "myself",
+    "no",
+    "nor",
+    "not",
+    "now",
+    "of",
+    "off",
+    "on",
+    "once",
+    "only",
+    "or",
+    "other",
+    "our",
+    "ours",
+    "ourselves",
+    "out",
+    "over",
+    "own",
+    "same",
+    "she",
+    "should",
+    "so",
+    "some",
+    "such",
+    "than",
+    "that",
+    "the",
+    "their",
+    "theirs",
+    "them",
+    "themselves",
+    "then",
+    "there",
+    "these",
+    "they",
+    "this",
+    "those",
+    "through",
+    "to",
+    "too",
+    "under",
+    "until",
+    "up",
+    "very",
+    "was",
+    "we",
+    "were",
+    "what",
+    "when",
+    "where",
+    "which",
+    "while",
+    "who",
+    "whom",
+    "why",
+    "will",
+    "with",
+    "would",
+    "you",
+    "your",
+    "yours",
+    "yourself",
+    "yourselves",
+];
+
+/// # Configuration for TopicModel
+pub struct TopicModelingConfig {
+    /// Sentence embeddings model used to embed the documents
+    pub embeddings_config: SentenceEmbeddingsConfig,
+    /// Number of topics
+    pub num_topics: usize,
+    /// Number of principal components kept before clustering (default: 16). If `None`, the full embeddings are clustered.
+    pub reduced_dimension: Option<usize>,
+    /// Number of keywords describing each topic (default: 10)
+    pub num_keywords: usize,
+    /// Words excluded from the keywords (lower-cased, default: `ENGLISH_STOP_WORDS`)
+    pub stop_words: HashSet<String>,
+    /// Maximum number of k-means iterations (default: 100)
+    pub max_iterations: usize,
+    /// Seed of the k-means initialization (default: 42)
+    pub seed: u64,
+    /// Number of documents embedded at once (default: 64)
+    pub batch_size: usize,
+}
+
+impl TopicModelingConfig {
+    /// Instantiate a new topic modeling configuration
+    ///
+    /// # Arguments
+    ///
+    /// * `embeddings_config` - `SentenceEmbeddingsConfig` of the model used to embed the documents
+    /// * `num_topics` - Number of topics
+    pub fn new(
+        embeddings_config: SentenceEmbeddingsConfig,
+        num_topics: usize,
+    ) -> TopicModelingConfig {
+        TopicModelingConfig {
+            embeddings_config,
+            num_topics,
+            reduced_dimension: Some(16),
+            num_keywords: 10,
+            stop_words: ENGLISH_STOP_WORDS
+                .iter()
+                .map(|word| word.to_string())
+                .collect(),
+            max_iterations: 100,
+            seed: 42,
+            batch_size: 64,
+        }
+    }
+}
+
+/// # Topic found in a corpus
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Topic {
+    /// Topic identifier
+    pub id: usize,
+    /// Label of the topic, made of its identifier and first four keywords (e.g. `0_match_goal_striker_penalty`)
+    pub label: String,
+    /// Keywords of the topic and their c-TF-IDF score, by decreasing score
+    pub keywords: Vec<(String, f64)>,
+    /// Number of documents assigned to the topic
+    pub size: usize,
+}
+
+/// # Output of the topic modeling pipeline
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct TopicModelingOutput {
+    /// Topic identifier of each document
+    pub assignments: Vec<usize>,
+    /// Topics, by identifier
+    pub topics: Vec<Topic>,
+}
+
+/// # TopicModel for the discovery of topics in a corpus
+pub struct TopicModel {
+    embeddings_model: SentenceEmbeddingsModel,
+    num_topics: usize,
+    reduced_dimension: Option<usize>,
+    num_keywords: usize,
+    stop_words: HashSet<String>,
+    max_iterations: usize,
+    seed: u64,
+    batch_size: usize,
+}
+
+impl TopicModel {
+    /// Build a new `TopicModel`
+    ///
+    /// # Arguments
+    ///
+    /// * `config` - `TopicModelingConfig` object containing the sentence embeddings configuration and the topic modeling parameters
+    pub fn new(config: TopicModelingConfig) -> Result<TopicModel, RustBertError> {
+        if config.num_topics == 0 {
+            return Err(RustBertError::InvalidConfigurationError(
+                "The number of topics must be greater than 0".to_string(),
+            ));
+        }
+        let embeddings_model = SentenceEmbeddingsModel::new(config.embeddings_config)?;
+        Ok(TopicModel {
+            embeddings_model,
+            num_topics: config.num_topics,
+            reduced_dimension: config.reduced_dimension,
+            num_keywords: config.num_keywords,
+            stop_words: config.stop_words,
+            max_iterations: config.max_iterations,
+            seed: config.seed,
+            batch_size: config.batch_size.max(1),
+        })
+    }
+
+    /// Finds the topics of a corpus
+    ///
+    /// # Arguments
+    ///
+    /// * `documents` - Documents of the corpus (at least as many as topics)
+    ///
+    /// # Returns
+    ///
+    /// * `TopicModelingOutput` containing the topic of each document and the keywords of each topic
+    pub fn fit<S>(&self, documents: &[S]) -> Result<TopicModelingOutput, RustBertError>
+    where
+        S: AsRef<str> + Sync,
+    {
+        if documents.len() < self.num_topics {
+            return Err(RustBertError::ValueError(format!(
+                "At least {} documents are needed to find {} topics, got {}",
+                self.num_topics,
+                self.num_topics,
+                documents.len()
+            )));
+        }
+        let mut embeddings = Vec::with_capacity(documents.len());
+        for batch in documents.chunks(self.batch_size) {
+            embeddings.push(self.embeddings_model.encode_as_tensor(batch)?.embeddings);
+        }
+        let embeddings = Tensor::cat(&embeddings, 0).to_kind(Kind::Float);
+        let embeddings = match self.reduced_dimension {
+            Some(dimension) => pca(&embeddings, dimension as i64),
+            None => embeddings,
+        };
+        let assignments = kmeans(
+            &embeddings,
+            self.num_topics as i64,
+            self.max_iterations,
+            self.seed,
+        )
+        .iter::<i64>()
+        .unwrap()
+        .map(|topic| topic as usize)
+        .collect::<Vec<usize>>();
+
+        let keywords = class_tf_idf(
+            documents,
+            &assignments,
+            self.num_topics,
+            self.num_keywords,
+            &self.stop_words,
+        );
+        let topics = keywords
+            .into_iter()
+            .enumerate()
+            .map(|(id, keywords)| {
+                let label = std::iter::once(id.to_string())
+                    .chain(keywords.iter().take(4).map(|(word, _)| word.clone()))
+                    .collect::<Vec<String>>()
+                    .join("_");
+                Topic {
+                    id,
+                    label,
+                    keywords,
+                    size: assignments.iter().filter(|topic| **topic == id).count(),
+                }
+            })
+            .collect();
+        Ok(TopicModelingOutput {
+            assignments,
+            topics,
+        })
+    }
+}
+
+/// Projects the rows of a matrix on its `dimension` first principal components
+fn pca(data: &Tensor, dimension: i64) -> Tensor {
+    let (num_samples, num_features) = data.size2().unwrap();
+    if dimension >= num_samples.min(num_features) {
+        return data.shallow_clone();
+    }
+    let centered = data - data.mean_dim(&[0], true, Kind::Float);
+    let (_, _, components) = centered.svd(true, true);
+    centered.matmul(&components.narrow(1, 0, dimension))
+}
+
+/// Scores the words of each class with a class-based TF-IDF, returning the `num_keywords` best words of each class
+fn class_tf_idf<S>(
+    documents: &[S],
+    assignments: &[usize],
+    num_classes: usize,
+    num_keywords: usize,
+    stop_words: &HashSet<String>,
+) -> Vec<Vec<(String, f64)>>
+where
+    S: AsRef<str>,
+{
+    let mut class_counts: Vec<HashMap<String, usize>> = vec![HashMap::new(); num_classes];
+    for (document, class) in documents.iter().zip(assignments) {
+        for word in document
+            .as_ref()
+            .split(|character: char| !character.is_alphanumeric())
+            .map(|word| word.to_lowercase())
+            .filter(|word| {
+                word.chars().count() > 1
+                    && !word.chars().all(|character| character.is_numeric())
+                    && !stop_words.contains(word)
+            })
+        {
+            *class_counts[*class].entry(word).or_insert(0) += 1;
+        }
+    }
+
+    let mut word_frequencies: HashMap<&str, usize> = HashMap::new();
+    for counts in class_counts.iter() {
+        for (word, count) in counts {
+            *word_frequencies.entry(word.as_str()).or_insert(0) += count;
+        }
+    }
+    let average_words = word_frequencies.values().sum::<usize>() as f64 / num_classes.max(1) as f64;
+
+    class_counts
+        .iter()
+        .map(|counts| {
+            let class_words = counts.values().sum::<usize>().max(1) as f64;
+            let mut scores = counts
+                .iter()
+                .map(|(word, count)| {
+                    let term_frequency = *count as f64 / class_words;
+                    let inverse_frequency =
+                        (1.0 + average_words / word_frequencies[word.as_str()] as f64).ln();
+                    (word.clone(), term_frequency * inverse_frequency)
+                })
+                .collect::<Vec<(String, f64)>>();
+            // Ties broken alphabetically for deterministic outputs
+            scores.sort_by(|a, b| b.1.partial_cmp(&a.1).unwrap().then_with(|| a.0.cmp(&b.0)));
+            scores.truncate(num_keywords);
+            scores
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_class_tf_idf() {
+        let documents = [
+            "The striker scored a goal.",
+            "A late goal for the striker!",
+            "Interest rates and inflation.",
+            "Inflation is rising.",
+        ];
+        let stop_words = ENGLISH_STOP_WORDS
+            .iter()
+            .map(|word| word.to_string())
+            .collect();
+        let keywords = class_tf_idf(&documents, &[0, 0, 1, 1], 2, 2, &stop_words);
+        assert_eq!(keywords[0][0].0, "goal");
+        assert_eq!(keywords[0][1].0, "striker");
+        assert_eq!(keywords[1][0].0, "inflation");
+    }
+}
@@ -0,0 +1,437 @@
+// Copyright 2022 Guillaume Becquin
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//     http://www.apache.org/licenses/LICENSE-2.0
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use crate::pipelines::logits_processor::LogitsProcessor;
+use serde::{Deserialize, Serialize};
+use std::collections::HashSet;
+use tch::Tensor;
+
+/// Default logit bonus given to the tokens of the glossary target terms
+pub const DEFAULT_GLOSSARY_BONUS: f64 = 5.0;
+
+/// # Entry of a translation glossary
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct GlossaryEntry {
+    /// Term to look for in the source texts
+    pub source_term: String,
+    /// Translation of the term expected in the translated texts
+    pub target_term: String,
+    /// If false, the source term is matched whatever its case and the case of the target term follows the
+    /// capitalization of the source occurrence (e.g. `Cat` or `CAT` for a `cat` entry at the start of a sentence
+    /// or in an all-caps title). If true, the source term is matched and the target term is used as written.
+    pub case_sensitive: bool,
+}
+
+impl GlossaryEntry {
+    /// Creates a new `GlossaryEntry`
+    ///
+    /// # Arguments
+    ///
+    /// * `source_term` - Term to look for in the source texts
+    /// * `target_term` - Translation of the term expected in the translated texts
+    /// * `case_sensitive` - Flag indicating if the source term should be matched and the target term used with their exact case
+    pub fn new(source_term: &str, target_term: &str, case_sensitive: bool) -> GlossaryEntry {
+        GlossaryEntry {
+            source_term: source_term.to_string(),
+            target_term: target_term.to_string(),
+            case_sensitive,
+        }
+    }
+}
+
+/// # Constraint put on the glossary target terms
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum GlossaryConstraint {
+    /// The tokens of the target terms receive a logit bonus, leaving the model free to use another translation
+    Soft,
+    /// The target terms must appear in the translation: the end of sequence is banned until all terms were
+    /// generated, a term is completed once its first token is generated, and the missing terms are forced when the
+    /// remaining length only allows to generate them. The logit bonus lets the model place the terms before this.
+    Hard,
+}
+
+/// # Translation glossary
+/// Terminology applied with `TranslationModel::translate_with_glossary`: the target terms of the entries whose
+/// source term appears in a text (as a whole word) are encouraged or enforced in its translation. Terms already
+/// present in the translation are not encouraged further.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Glossary {
+    /// Entries of the glossary
+    pub entries: Vec<GlossaryEntry>,
+    /// Constraint put on the target terms
+    pub constraint: GlossaryConstraint,
+    /// Bonus added to the logits of the next token of the missing target terms at each step
+    pub bonus: f64,
+}
+
+impl Glossary {
+    /// Creates a new `Glossary` with the default logit bonus
+    ///
+    /// # Arguments
+    ///
+    /// * `entries` - Entries of the glossary
+    /// * `constraint` - `GlossaryConstraint` put on the target terms
+    ///
+    /// # Example
+    ///
+    /// ```no_run
+    /// use rust_bert::pipelines::translation::{Glossary, GlossaryConstraint, GlossaryEntry};
+    ///
+    /// let glossary = Glossary::new(
+    ///     vec![
+    ///         GlossaryEntry::new("floating point", "virgule flottante", false),
+    ///         GlossaryEntry::new("CPU", "CPU", true),
+    ///     ],
+    ///     GlossaryConstraint::Soft,
+    /// );
+    /// ```
+    pub fn new(entries: Vec<GlossaryEntry>, constraint: GlossaryConstraint) -> Glossary {
+        Glossary {
+            entries,
+            constraint,
+            bonus: DEFAULT_GLOSSARY_BONUS,
+        }
+    }
+
+    /// Returns the target terms applying to a source text, one list of accepted case variants (preferred variant
+    /// first) for each entry whose source term appears in the text.
+    ///
+    /// # Arguments
+    ///
+    /// * `text` - Source text
+    ///
+    /// # Returns
+    ///
+    /// * `Vec<Vec<String>>` Case variants of the target terms of the matched entries
+    ///
+    /// # Example
+    ///
+    /// ```no_run
+    /// use rust_bert::pipelines::translation::{Glossary, GlossaryConstraint, GlossaryEntry};
+    ///
+    /// let glossary = Glossary::new(
+    ///     vec![GlossaryEntry::new("cat", "chat", false)],
+    ///     GlossaryConstraint::Soft,
+    /// );
+    /// let terms = glossary.get_target_terms("Cats and dogs. Cat food.");
+    /// assert_eq!(terms, vec![vec!["Chat".to_string(), "chat".to_string()]]);
+    /// ```
+    pub fn get_target_terms(&self, text: &str) -> Vec<Vec<String>> {
+        let text = text.chars().collect::<Vec<char>>();
+        self.entries
+            .iter()
+            .filter_map(|entry| {
+                let source_term = entry.source_term.chars().collect::<Vec<char>>();
+                find_term(&text, &source_term, entry.case_sensitive).map(|start| {
+                    let occurrence = text[start..start + source_term.len()]
+                        .iter()
+                        .collect::<String>();
+                    get_case_variants(entry, &occurrence)
+                })
+            })
+            .collect()
+    }
+}
+
+/// Returns the start of the first occurrence of a term delimited by word boundaries in a text
+fn find_term(text: &[char], term: &[char], case_sensitive: bool) -> Option<usize> {
+    if term.is_empty() || term.len() > text.len() {
+        return None;
+    }
+    (0..=text.len() - term.len()).find(|&start| {
+        let end = start + term.len();
+        (start == 0 || !text[start - 1].is_alphanumeric())
+            && (end == text.len() || !text[end].is_alphanumeric())
+            && text[start..end]
+                .iter()
+                .zip(term)
+                .all(|(text_char, term_char)| {
+                    if case_sensitive {
+                        text_char == term_char
+                    } else {
+                        text_char.to_lowercase().eq(term_char.to_lowercase())
+                    }
+                })
+    })
+}
+
+fn capitalize(text: &str) -> String {
+    let mut chars = text.chars();
+    match chars.next() {
+        Some(first) => first.to_uppercase().chain(chars).collect(),
+        None => String::new(),
+    }
+}
+
+fn starts_uppercase(text: &str) -> bool {
+    text.chars().next().map_or(false, char::is_uppercase)
+}
+
+fn is_all_uppercase(text: &str) -> bool {
+    let mut letters = text.chars().filter(|c| c.is_alphabetic()).peekable();
+    letters.peek().is_some() && letters.all(char::is_uppercase)
+}
+
+/// Returns the accepted case variants of the target term of an entry, given the case of the source occurrence.
+/// The preferred variant comes first, followed by the target term as written and its capitalized form (e.g. for a
+/// term moved to the start of the translated sentence).
+fn get_case_variants(entry: &GlossaryEntry, occurrence: &str) -> Vec<String> {
+    let target_term = entry.target_term.as_str();
+    if entry.case_sensitive {
+        return vec![target_term.to_string()];
+    }
+    let mut variants = Vec::with_capacity(3);
+    if occurrence.chars().filter(|c| c.is_alphabetic()).count() > 1
+        && is_all_uppercase(occurrence)
+        && !is_all_uppercase(&entry.source_term)
+    {
+        variants.push(target_term.to_uppercase());
+    } else if starts_uppercase(occurrence) && !starts_uppercase(&entry.source_term) {
+        variants.push(capitalize(target_term));
+    }
+    variants.push(target_term.to_string());
+    variants.push(capitalize(target_term));
+
+    let mut seen = HashSet::new();
+    variants.retain(|variant| seen.insert(variant.clone()));
+    variants
+}
+
+/// Returns true if a sequence contains the tokens of a term
+fn contains_tokens(sequence: &[i64], term: &[i64]) -> bool {
+    sequence.windows(term.len()).any(|window| window == term)
+}
+
+/// # Logits processor applying the glossary terms during the generation
+/// Holds, for each input of the batch, the token ids of the case variants of its target terms.
+pub(crate) struct GlossaryLogitsProcessor {
+    terms: Vec<Vec<Vec<Vec<i64>>>>,
+    constraint: GlossaryConstraint,
+    bonus: f64,
+    eos_token_ids: Vec<i64>,
+    max_length: i64,
+}
+
+impl GlossaryLogitsProcessor {
+    /// Creates a new `GlossaryLogitsProcessor`
+    ///
+    /// # Arguments
+    ///
+    /// * `terms` - For each input, the token ids of the case variants of each target term (preferred variant first). Empty variants are ignored.
+    /// * `constraint` - `GlossaryConstraint` put on the target terms
+    /// * `bonus` - Bonus added to the logits of the next token of the missing target terms
+    /// * `eos_token_ids` - End of sequence token ids, banned while terms are missing with `GlossaryConstraint::Hard`
+    /// * `max_length` - Maximum length of the generated sequences, used to force the missing terms with `GlossaryConstraint::Hard`
+    pub(crate) fn new(
+        terms: Vec<Vec<Vec<Vec<i64>>>>,
+        constraint: GlossaryConstraint,
+        bonus: f64,
+        eos_token_ids: Vec<i64>,
+        max_length: i64,
+    ) -> GlossaryLogitsProcessor {
+        let terms = terms
+            .into_iter()
+            .map(|input_terms| {
+                input_terms
+                    .into_iter()
+                    .map(|variants| {
+                        variants
+                            .into_iter()
+                            .filter(|variant| !variant.is_empty())
+                            .collect::<Vec<Vec<i64>>>()
+                    })
+                    .filter(|variants| !variants.is_empty())
+                    .collect()
+            })
+            .collect();
+        GlossaryLogitsProcessor {
+            terms,
+            constraint,
+            bonus,
+            eos_token_ids,
+            max_length,
+        }
+    }
+}
+
+impl LogitsProcessor for GlossaryLogitsProcessor {
+    fn process(&self, input_ids: &Tensor, next_token_logits: &mut Tensor) {
+        if self.terms.is_empty() {
+            return;
+        }
+        let sequences = Vec::<Vec<i64>>::from(input_ids);
+        // The sequences of an input (beams and returned sequences) are contiguous
+        let sequences_per_input = (sequences.len() / self.terms.len()).max(1);
+        let remaining_length = self.max_length - input_ids.size()[1];
+
+        for (row, sequence) in sequences.iter().enumerate() {
+            let terms = match self.terms.get(row / sequences_per_input) {
+                Some(terms) => terms,
+                None => continue,
+            };
+            let missing_terms = terms
+                .iter()
+                .filter(|variants| {
+                    !variants
+                        .iter()
+                        .any(|variant| contains_tokens(sequence, variant))
+                })
+                .collect::<Vec<&Vec<Vec<i64>>>>();
+            if missing_terms.is_empty() {
+                continue;
+            }
+            // Next token of the longest partially generated term, if any
+            let continuation = missing_terms
+                .iter()
+                .flat_map(|variants| variants.iter())
+                .filter_map(|variant| {
+                    (1..variant.len())
+                        .rev()
+                        .find(|&length| sequence.ends_with(&variant[..length]))
+                        .map(|length| (length, variant[length]))
+                })
+                .max_by_key(|(length, _)| *length)
+                .map(|(_, token)| token);
+
+            let row = row as i64;
+            if self.constraint == GlossaryConstraint::Hard {
+                let forced_token = continuation.or_else(|| {
+                    let required_length = missing_terms
+                        .iter()
+                        .map(|variants| variants[0].len() as i64)
+                        .sum::<i64>();
+                    if remaining_length <= required_length {
+                        Some(missing_terms[0][0][0])
+                    } else {
+                        None
+                    }
+                });
+                if let Some(token) = forced_token {
+                    let token_logit = next_token_logits.double_value(&[row, token]);
+                    let _ = next_token_logits.get(row).fill_(f64::NEG_INFINITY);
+                    let _ = next_token_logits.get(row).get(token).fill_(token_logit);
+                    continue;
+                }
+                for eos_token_id in &self.eos_token_ids {
+                    let _ = next_token_logits
+                        .get(row)
+                        .get(*eos_token_id)
+                        .fill_(f64::NEG_INFINITY);
+                }
+            }
+
+            let mut boosted_tokens = missing_terms
+                .iter()
+                .flat_map(|variants| variants.iter().map(|variant| variant[0]))
+                .collect::<HashSet<i64>>();
+            boosted_tokens.extend(continuation);
+            for token in boosted_tokens {
+                let _ = next_token_logits
+                    .get(row)
+                    .get(token)
+                    .g_add_scalar_(self.bonus);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use tch::Kind;
+
+    #[test]
+    fn glossary_case_handling() {
+        let glossary = Glossary::new(
+            vec![
+                GlossaryEntry::new("cat", "chat", false),
+                GlossaryEntry::new("hard drive", "disque dur", false),
+                GlossaryEntry::new("Apple", "Apple", true),
+                GlossaryEntry::new("dog", "chien", false),
+            ],
+            GlossaryConstraint::Soft,
+        );
+
+        // whole words only, the case of the occurrence is propagated to the target term
+        assert_eq!(
+            glossary.get_target_terms("Concatenate the HARD DRIVE files of my cat. An apple."),
+            vec![
+                vec!["chat".to_string(), "Chat".to_string()],
+                vec![
+                    "DISQUE DUR".to_string(),
+                    "disque dur".to_string(),
+                    "Disque dur".to_string()
+                ],
+            ]
+        );
+        assert_eq!(
+            glossary.get_target_terms("Apple sells Dog food"),
+            vec![
+                vec!["Apple".to_string()],
+                vec!["Chien".to_string(), "chien".to_string()],
+            ]
+        );
+        assert!(glossary.get_target_terms("").is_empty());
+    }
+
+    #[test]
+    fn glossary_logits_processor() {
+        // input 0 requires the term [5, 6] (or its variant [7]), input 1 has no term
+        let terms = vec![vec![vec![vec![5, 6], vec![7]]], vec![]];
+        let soft =
+            GlossaryLogitsProcessor::new(terms.clone(), GlossaryConstraint::Soft, 2.0, vec![1], 10);
+        let input_ids = Tensor::of_slice(&[0i64, 3, 5, 0, 3, 4]).view([2, 3]);
+        let mut logits = Tensor::zeros(&[2, 10], (Kind::Float, tch::Device::Cpu));
+        soft.process(&input_ids, &mut logits);
+        let logits = Vec::<Vec<f32>>::from(&logits);
+        assert_eq!(logits[0], [0., 0., 0., 0., 0., 2., 2., 2., 0., 0.]);
+        assert_eq!(logits[1], [0f32; 10]);
+
+        // the term is completed once started
+        let hard =
+            GlossaryLogitsProcessor::new(terms.clone(), GlossaryConstraint::Hard, 2.0, vec![1], 10);
+        let mut logits = Tensor::zeros(&[2, 10], (Kind::Float, tch::Device::Cpu));
+        hard.process(&input_ids, &mut logits);
+        let logits = Vec::<Vec<f32>>::from(&logits);
+        assert_eq!(logits[0][6], 0.);
+        assert_eq!(
+            logits[0].iter().filter(|logit| logit.is_infinite()).count(),
+            9
+        );
+        assert_eq!(logits[1], [0f32; 10]);
+
+        // the end of sequence is banned while the term is missing, and the term is forced when running out of length
+        let input_ids = Tensor::of_slice(&[0i64, 3, 4, 0, 3, 4]).view([2, 3]);
+        let mut logits = Tensor::zeros(&[2, 10], (Kind::Float, tch::Device::Cpu));
+        hard.process(&input_ids, &mut logits);
+        let logits = Vec::<Vec<f32>>::from(&logits);
+        assert_eq!(logits[0][1], f32::NEG_INFINITY);
+        assert_eq!(logits[0][5], 2.);
+        assert_eq!(logits[1], [0f32; 10]);
+
+        let short_hard =
+            GlossaryLogitsProcessor::new(terms, GlossaryConstraint::Hard, 2.0, vec![1], 5);
+        let mut logits = Tensor::zeros(&[2, 10], (Kind::Float, tch::Device::Cpu));
+        short_hard.process(&input_ids, &mut logits);
+        let logits = Vec::<Vec<f32>>::from(&logits);
+        assert_eq!(logits[0][5], 0.);
+        assert_eq!(
+            logits[0].iter().filter(|logit| logit.is_infinite()).count(),
+            9
+        );
+
+        // no bonus once the term was generated
+        let input_ids = Tensor::of_slice(&[0i64, 7, 4, 0, 3, 4]).view([2, 3]);
+        let mut logits = Tensor::zeros(&[2, 10], (Kind::Float, tch::Device::Cpu));
+        hard.process(&input_ids, &mut logits);
+        assert_eq!(Vec::<Vec<f32>>::from(&logits)[0], [0f32; 10]);
+    }
+}
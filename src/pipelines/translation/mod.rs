@@ -86,10 +86,26 @@
 //!     Ok(())
 //! }
 //! ```
+//!
+//! Word alignments between the source texts and their translations can be extracted from the cross-attention weights
+//! of the model with `TranslationModel::translate_with_alignments`, for example to project tags or entities onto the
+//! translated text. This requires loading the model with `output_attentions` set to true (`TranslationConfig` field or
+//! `TranslationModelBuilder::with_output_attentions`).
+//!
+//! A terminology can be applied to the translations with `TranslationModel::translate_with_glossary`: the target terms
+//! of the `Glossary` entries whose source term appears in a text receive a logit bonus during its translation
+//! (`GlossaryConstraint::Soft`) or must appear in it (`GlossaryConstraint::Hard`). The capitalization of the source
+//! occurrence is carried over to the target term unless the entry is case-sensitive.
 
+mod glossary;
 mod translation_builder;
 mod translation_pipeline;
+mod word_alignment;
 
-pub use translation_pipeline::{Language, TranslationConfig, TranslationModel, TranslationOption};
+pub use glossary::{Glossary, GlossaryConstraint, GlossaryEntry, DEFAULT_GLOSSARY_BONUS};
+pub use translation_pipeline::{
+    Language, TranslationConfig, TranslationModel, TranslationOption, TranslationOptions,
+};
+pub use word_alignment::{AlignedTranslation, AlignmentMethod, WordAlignment};
 
 pub use translation_builder::TranslationModelBuilder;
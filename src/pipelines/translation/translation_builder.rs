@@ -67,6 +67,7 @@ pub struct TranslationModelBuilder {
     target_languages: Option<Vec<Language>>,
     device: Option<Device>,
     model_size: Option<ModelSize>,
+    output_attentions: bool,
 }
 
 impl Default for TranslationModelBuilder {
@@ -97,6 +98,7 @@ impl TranslationModelBuilder {
             target_languages: None,
             device: None,
             model_size: None,
+            output_attentions: false,
         }
     }
 
@@ -150,6 +152,28 @@ impl TranslationModelBuilder {
         self
     }
 
+    /// Load the model with the output of its attention weights turned on, required to extract word alignments
+    /// with `TranslationModel::translate_with_alignments`
+    ///
+    /// # Returns
+    /// * `TranslationModelBuilder` Translation model builder
+    ///
+    /// # Example
+    ///
+    /// ```no_run
+    /// use rust_bert::pipelines::translation::TranslationModelBuilder;
+    /// fn main() -> anyhow::Result<()> {
+    ///     let model = TranslationModelBuilder::new()
+    ///         .with_output_attentions()
+    ///         .create_model();
+    ///     Ok(())
+    /// }
+    /// ```
+    pub fn with_output_attentions(&mut self) -> &mut Self {
+        self.output_attentions = true;
+        self
+    }
+
     /// Use a medium-sized translation model (Marian-based)
     ///
     /// # Returns
@@ -378,7 +402,7 @@ impl TranslationModelBuilder {
             }
         };
 
-        let translation_config = TranslationConfig::new(
+        let mut translation_config = TranslationConfig::new(
             translation_resources.model_type,
             translation_resources.model_resource,
             translation_resources.config_resource,
@@ -388,6 +412,7 @@ impl TranslationModelBuilder {
             translation_resources.target_languages,
             device,
         );
+        translation_config.output_attentions = self.output_attentions;
         TranslationModel::new(translation_config)
     }
 }
@@ -11,19 +11,28 @@
 // See the License for the specific language governing permissions and
 // limitations under the License.
 
-use tch::Device;
+use tch::{no_grad, Device, Kind, Tensor};
 
 use crate::common::error::RustBertError;
 use crate::m2m_100::M2M100Generator;
 use crate::marian::MarianGenerator;
 use crate::mbart::MBartGenerator;
-use crate::pipelines::common::ModelType;
+use crate::pipelines::common::{ModelType, Pipeline};
 use crate::pipelines::generation_utils::private_generation_utils::PrivateLanguageGenerator;
-use crate::pipelines::generation_utils::{GenerateConfig, GenerateOptions, LanguageGenerator};
+use crate::pipelines::generation_utils::{
+    GenerateConfig, GenerateOptions, LanguageGenerator, NoRepeatNgramScope,
+};
+use crate::pipelines::logits_processor::LogitsProcessor;
+use crate::pipelines::translation::glossary::{Glossary, GlossaryLogitsProcessor};
+use crate::pipelines::translation::word_alignment::{
+    get_argmax_alignments, get_optimal_transport_alignments, get_token_words, get_word_attention,
+    split_words, AlignedTranslation, AlignmentMethod,
+};
 use crate::resources::ResourceProvider;
 use crate::t5::T5Generator;
+use rust_tokenizers::tokenizer::TruncationStrategy;
 use serde::{Deserialize, Serialize};
-use std::collections::HashSet;
+use std::collections::{HashMap, HashSet};
 use std::fmt;
 use std::fmt::{Debug, Display};
 
@@ -415,6 +424,12 @@ pub struct TranslationConfig {
     pub num_beam_groups: Option<i64>,
     /// Diversity penalty for diverse beam search. High values will enforce more difference between beam groups (default: 5.5)
     pub diversity_penalty: Option<f64>,
+    /// Flag indicating if the model should output its attention weights, required to extract word alignments
+    /// with `TranslationModel::translate_with_alignments` (default: false)
+    pub output_attentions: bool,
+    /// Bias added to the logits of the given token ids at every generation step, nudging the vocabulary used by the
+    /// model. Positive values encourage the generation of a token and negative values discourage it (default: empty)
+    pub logit_bias: HashMap<i64, f64>,
 }
 
 impl TranslationConfig {
@@ -498,6 +513,8 @@ impl TranslationConfig {
             num_return_sequences: 1,
             num_beam_groups: None,
             diversity_penalty: None,
+            output_attentions: false,
+            logit_bias: HashMap::new(),
         }
     }
 }
@@ -520,9 +537,18 @@ impl From<TranslationConfig> for GenerateConfig {
             repetition_penalty: config.repetition_penalty,
             length_penalty: config.length_penalty,
             no_repeat_ngram_size: config.no_repeat_ngram_size,
+            no_repeat_ngram_scope: NoRepeatNgramScope::Sequence,
             num_return_sequences: config.num_return_sequences,
             num_beam_groups: config.num_beam_groups,
             diversity_penalty: config.diversity_penalty,
+            penalty_alpha: None,
+            typical_p: None,
+            epsilon_cutoff: None,
+            eta_cutoff: None,
+            prefill_chunk_size: None,
+            stop_sequences: vec![],
+            logit_bias: config.logit_bias,
+            output_attentions: config.output_attentions,
             device: config.device,
         }
     }
@@ -732,40 +758,288 @@ impl TranslationOption {
     where
         S: AsRef<str> + Sync,
     {
+        self.generate_with_logits_processors(prompt_texts, forced_bos_token_id, None)
+    }
+
+    fn generate_with_logits_processors<S>(
+        &self,
+        prompt_texts: Option<&[S]>,
+        forced_bos_token_id: Option<i64>,
+        logits_processors: Option<&[&dyn LogitsProcessor]>,
+    ) -> Vec<String>
+    where
+        S: AsRef<str> + Sync,
+    {
+        let generate_options = GenerateOptions {
+            forced_bos_token_id,
+            logits_processors,
+            ..Default::default()
+        };
         match *self {
             Self::Marian(ref model) => model
-                .generate(prompt_texts, None)
+                .generate(prompt_texts, Some(generate_options))
                 .into_iter()
                 .map(|output| output.text)
                 .collect(),
             Self::T5(ref model) => model
-                .generate(prompt_texts, None)
+                .generate(prompt_texts, Some(generate_options))
                 .into_iter()
                 .map(|output| output.text)
                 .collect(),
+            Self::MBart(ref model) => model
+                .generate(prompt_texts, Some(generate_options))
+                .into_iter()
+                .map(|output| output.text)
+                .collect(),
+            Self::M2M100(ref model) => model
+                .generate(prompt_texts, Some(generate_options))
+                .into_iter()
+                .map(|output| output.text)
+                .collect(),
+        }
+    }
+
+    /// Creates the logits processor applying the glossary target terms matched in each source text
+    fn get_glossary_logits_processor<S>(
+        &self,
+        texts: &[S],
+        glossary: &Glossary,
+    ) -> GlossaryLogitsProcessor
+    where
+        S: AsRef<str>,
+    {
+        let (tokenizer, eos_token_ids, max_length) = match *self {
+            Self::Marian(ref model) => (
+                model._get_tokenizer(),
+                model.get_eos_ids(),
+                model.get_config().max_length,
+            ),
+            Self::T5(ref model) => (
+                model._get_tokenizer(),
+                model.get_eos_ids(),
+                model.get_config().max_length,
+            ),
+            Self::MBart(ref model) => (
+                model._get_tokenizer(),
+                model.get_eos_ids(),
+                model.get_config().max_length,
+            ),
+            Self::M2M100(ref model) => (
+                model._get_tokenizer(),
+                model.get_eos_ids(),
+                model.get_config().max_length,
+            ),
+        };
+        let terms = texts
+            .iter()
+            .map(|text| {
+                glossary
+                    .get_target_terms(text.as_ref())
+                    .iter()
+                    .map(|variants| {
+                        variants
+                            .iter()
+                            .map(|variant| {
+                                tokenizer.convert_tokens_to_ids(&tokenizer.tokenize(variant))
+                            })
+                            .collect()
+                    })
+                    .collect()
+            })
+            .collect();
+        GlossaryLogitsProcessor::new(
+            terms,
+            glossary.constraint,
+            glossary.bonus,
+            eos_token_ids.cloned().unwrap_or_default(),
+            max_length,
+        )
+    }
+
+    /// Returns true if the model was loaded with the output of its attention weights turned on
+    fn output_attentions(&self) -> bool {
+        match *self {
+            Self::Marian(ref model) => model.get_config().output_attentions,
+            Self::T5(ref model) => model.get_config().output_attentions,
+            Self::MBart(ref model) => model.get_config().output_attentions,
+            Self::M2M100(ref model) => model.get_config().output_attentions,
+        }
+    }
+
+    /// Extracts the word alignments between a source text and its translation from the cross-attention
+    /// weights of the model, averaged over the decoder layers and attention heads. The translation is fed to
+    /// the decoder (teacher forcing) after the decoder start token and the optional forced BOS token.
+    fn align_words(
+        &self,
+        source_text: &str,
+        prefix: &str,
+        translation: String,
+        forced_bos_token_id: Option<i64>,
+        alignment_method: AlignmentMethod,
+    ) -> Result<AlignedTranslation, RustBertError> {
+        let (tokenizer, decoder_start_id, max_length, device) = match *self {
+            Self::Marian(ref model) => (
+                model._get_tokenizer(),
+                model.get_decoder_start_id(),
+                model.get_max_positions_embeddings(),
+                model.get_var_store().device(),
+            ),
+            Self::T5(ref model) => (
+                model._get_tokenizer(),
+                model.get_decoder_start_id(),
+                model.get_max_positions_embeddings(),
+                model.get_var_store().device(),
+            ),
+            Self::MBart(ref model) => (
+                model._get_tokenizer(),
+                model.get_decoder_start_id(),
+                model.get_max_positions_embeddings(),
+                model.get_var_store().device(),
+            ),
+            Self::M2M100(ref model) => (
+                model._get_tokenizer(),
+                model.get_decoder_start_id(),
+                model.get_max_positions_embeddings(),
+                model.get_var_store().device(),
+            ),
+        };
+        let decoder_start_id = decoder_start_id.ok_or_else(|| {
+            RustBertError::ValueError(
+                "A decoder start token is required to extract word alignments".to_string(),
+            )
+        })?;
+
+        let source = tokenizer
+            .encode_list(
+                &[format!("{}{}", prefix, source_text)],
+                max_length as usize,
+                &TruncationStrategy::LongestFirst,
+                0,
+            )
+            .pop()
+            .unwrap();
+        let target = tokenizer
+            .encode_list(
+                &[translation.as_str()],
+                max_length as usize,
+                &TruncationStrategy::LongestFirst,
+                0,
+            )
+            .pop()
+            .unwrap();
+
+        let mut decoder_input_ids = vec![decoder_start_id];
+        decoder_input_ids.extend(forced_bos_token_id);
+        decoder_input_ids.extend(&target.token_ids);
+        let input_ids = Tensor::of_slice(&source.token_ids).unsqueeze(0).to(device);
+        let decoder_input_ids = Tensor::of_slice(&decoder_input_ids).unsqueeze(0).to(device);
+
+        let cross_attentions = no_grad(|| match *self {
+            Self::Marian(ref model) => {
+                model
+                    .get_model()
+                    .forward_t(
+                        Some(&input_ids),
+                        None,
+                        None,
+                        Some(&decoder_input_ids),
+                        None,
+                        None,
+                        false,
+                    )
+                    .all_decoder_cross_attentions
+            }
+            Self::T5(ref model) => {
+                model
+                    .get_model()
+                    .forward_t(
+                        Some(&input_ids),
+                        None,
+                        None,
+                        Some(&decoder_input_ids),
+                        None,
+                        None,
+                        None,
+                        None,
+                        false,
+                    )
+                    .all_decoder_cross_attentions
+            }
             Self::MBart(ref model) => {
-                let generate_options = GenerateOptions {
-                    forced_bos_token_id,
-                    ..Default::default()
-                };
                 model
-                    .generate(prompt_texts, Some(generate_options))
-                    .into_iter()
-                    .map(|output| output.text)
-                    .collect()
+                    .get_model()
+                    .forward_t(
+                        Some(&input_ids),
+                        None,
+                        None,
+                        Some(&decoder_input_ids),
+                        None,
+                        None,
+                        false,
+                    )
+                    .all_decoder_cross_attentions
             }
             Self::M2M100(ref model) => {
-                let generate_options = GenerateOptions {
-                    forced_bos_token_id,
-                    ..Default::default()
-                };
                 model
-                    .generate(prompt_texts, Some(generate_options))
-                    .into_iter()
-                    .map(|output| output.text)
-                    .collect()
+                    .get_model()
+                    .forward_t(
+                        Some(&input_ids),
+                        None,
+                        None,
+                        Some(&decoder_input_ids),
+                        None,
+                        None,
+                        false,
+                    )
+                    .all_decoder_cross_attentions
             }
-        }
+        })
+        .ok_or_else(|| {
+            RustBertError::InvalidConfigurationError(
+                "Word alignments require a model loaded with `output_attentions` set to true"
+                    .to_string(),
+            )
+        })?;
+
+        // Rows of the decoder positions predicting the target tokens, skipping the forced BOS token
+        let start = forced_bos_token_id.is_some() as i64;
+        let token_attention = Tensor::stack(&cross_attentions, 0)
+            .mean_dim(&[0, 2], false, Kind::Float)
+            .get(0)
+            .slice(0, start, start + target.token_ids.len() as i64, 1);
+
+        let (source_words, source_word_offsets) = split_words(source_text);
+        let (target_words, target_word_offsets) = split_words(&translation);
+        let source_token_words = get_token_words(
+            &source.token_offsets,
+            &source.special_tokens_mask,
+            prefix.chars().count() as u32,
+            &source_word_offsets,
+        );
+        let target_token_words = get_token_words(
+            &target.token_offsets,
+            &target.special_tokens_mask,
+            0,
+            &target_word_offsets,
+        );
+        let word_attention = get_word_attention(
+            &token_attention,
+            &target_token_words,
+            &source_token_words,
+            target_words.len(),
+            source_words.len(),
+        );
+        let alignments = match alignment_method {
+            AlignmentMethod::Argmax => get_argmax_alignments(&word_attention),
+            AlignmentMethod::OptimalTransport => get_optimal_transport_alignments(&word_attention),
+        };
+
+        Ok(AlignedTranslation {
+            text: translation,
+            source_words,
+            target_words,
+            alignments,
+        })
     }
 }
 
@@ -905,6 +1179,202 @@ impl TranslationModel {
             None => self.model.generate(Some(texts), forced_bos_token_id),
         })
     }
+
+    /// Translates texts provided and extracts the alignments between the words of the source texts and the
+    /// words of their translations from the cross-attention weights of the model, for example to project
+    /// annotations (tags, entities, formatting) from a source text onto its translation. Words are obtained by
+    /// splitting the texts on whitespaces. Requires a model loaded with `output_attentions` set to true in
+    /// its `TranslationConfig`.
+    ///
+    /// # Arguments
+    /// * `texts` - `&[&str]` Array of texts to translate.
+    /// * `source_language` - Optional source language of the texts
+    /// * `target_language` - Optional target language to translate to
+    /// * `alignment_method` - `AlignmentMethod` used to extract the word alignments from the attention weights
+    ///
+    /// # Returns
+    /// * `Vec<AlignedTranslation>` Translated texts with their word alignments
+    ///
+    /// # Example
+    ///
+    /// ```no_run
+    /// # fn main() -> anyhow::Result<()> {
+    /// use rust_bert::pipelines::translation::{AlignmentMethod, Language, TranslationModelBuilder};
+    ///
+    /// let model = TranslationModelBuilder::new()
+    ///     .with_source_languages(vec![Language::English])
+    ///     .with_target_languages(vec![Language::French])
+    ///     .with_output_attentions()
+    ///     .create_model()?;
+    ///
+    /// let input = ["The <b>black</b> cat sleeps"];
+    /// let output = model.translate_with_alignments(
+    ///     &input,
+    ///     None,
+    ///     Language::French,
+    ///     AlignmentMethod::OptimalTransport,
+    /// )?;
+    /// for alignment in &output[0].alignments {
+    ///     println!(
+    ///         "{} -> {}",
+    ///         output[0].source_words[alignment.source_word],
+    ///         output[0].target_words[alignment.target_word]
+    ///     );
+    /// }
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn translate_with_alignments<S>(
+        &self,
+        texts: &[S],
+        source_language: impl Into<Option<Language>>,
+        target_language: impl Into<Option<Language>>,
+        alignment_method: AlignmentMethod,
+    ) -> Result<Vec<AlignedTranslation>, RustBertError>
+    where
+        S: AsRef<str> + Sync,
+    {
+        if !self.model.output_attentions() {
+            return Err(RustBertError::InvalidConfigurationError(
+                "Word alignments require a model loaded with `output_attentions` set to true"
+                    .to_string(),
+            ));
+        }
+        let (prefix, forced_bos_token_id) = self.model.validate_and_get_prefix_and_forced_bos_id(
+            source_language.into().as_ref(),
+            target_language.into().as_ref(),
+            &self.supported_source_languages,
+            &self.supported_target_languages,
+        )?;
+        if texts.is_empty() {
+            return Ok(Vec::new());
+        }
+
+        let prefix = prefix.unwrap_or_default();
+        let prompts = texts
+            .iter()
+            .map(|text| format!("{}{}", prefix, text.as_ref()))
+            .collect::<Vec<String>>();
+        let translations = self.model.generate(Some(&prompts), forced_bos_token_id);
+        let num_return_sequences = translations.len() / texts.len();
+        translations
+            .into_iter()
+            .enumerate()
+            .map(|(index, translation)| {
+                self.model.align_words(
+                    texts[index / num_return_sequences].as_ref(),
+                    &prefix,
+                    translation,
+                    forced_bos_token_id,
+                    alignment_method,
+                )
+            })
+            .collect()
+    }
+
+    /// Translates texts provided following the terminology of a glossary: the target terms of the entries whose
+    /// source term appears in a text receive a logit bonus during the generation of its translation, or are
+    /// enforced with `GlossaryConstraint::Hard`. The case of the source occurrence is propagated to the target
+    /// term for the entries that are not case-sensitive.
+    ///
+    /// # Arguments
+    /// * `texts` - `&[&str]` Array of texts to translate.
+    /// * `source_language` - Optional source language of the texts
+    /// * `target_language` - Optional target language to translate to
+    /// * `glossary` - `Glossary` applied to the translations
+    ///
+    /// # Returns
+    /// * `Vec<String>` Translated texts
+    ///
+    /// # Example
+    ///
+    /// ```no_run
+    /// # fn main() -> anyhow::Result<()> {
+    /// use rust_bert::pipelines::translation::{
+    ///     Glossary, GlossaryConstraint, GlossaryEntry, Language, TranslationModelBuilder,
+    /// };
+    ///
+    /// let model = TranslationModelBuilder::new()
+    ///     .with_source_languages(vec![Language::English])
+    ///     .with_target_languages(vec![Language::French])
+    ///     .create_model()?;
+    ///
+    /// let glossary = Glossary::new(
+    ///     vec![GlossaryEntry::new("hard drive", "disque dur", false)],
+    ///     GlossaryConstraint::Hard,
+    /// );
+    /// let input = ["Hard drive failures are rare."];
+    /// let output = model.translate_with_glossary(&input, None, Language::French, &glossary)?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn translate_with_glossary<S>(
+        &self,
+        texts: &[S],
+        source_language: impl Into<Option<Language>>,
+        target_language: impl Into<Option<Language>>,
+        glossary: &Glossary,
+    ) -> Result<Vec<String>, RustBertError>
+    where
+        S: AsRef<str> + Sync,
+    {
+        let (prefix, forced_bos_token_id) = self.model.validate_and_get_prefix_and_forced_bos_id(
+            source_language.into().as_ref(),
+            target_language.into().as_ref(),
+            &self.supported_source_languages,
+            &self.supported_target_languages,
+        )?;
+        if texts.is_empty() {
+            return Ok(Vec::new());
+        }
+
+        let glossary_processor = self.model.get_glossary_logits_processor(texts, glossary);
+        let logits_processors: [&dyn LogitsProcessor; 1] = [&glossary_processor];
+        let prefix = prefix.unwrap_or_default();
+        let prompts = texts
+            .iter()
+            .map(|text| format!("{}{}", prefix, text.as_ref()))
+            .collect::<Vec<String>>();
+        Ok(self.model.generate_with_logits_processors(
+            Some(&prompts),
+            forced_bos_token_id,
+            Some(&logits_processors),
+        ))
+    }
+
+    /// Returns the set of source languages supported by the model
+    pub fn get_supported_source_languages(&self) -> &HashSet<Language> {
+        &self.supported_source_languages
+    }
+
+    /// Returns the set of target languages supported by the model
+    pub fn get_supported_target_languages(&self) -> &HashSet<Language> {
+        &self.supported_target_languages
+    }
+}
+
+/// # Options of the translation pipeline when run as a `Pipeline`
+#[derive(Debug, Clone, Copy, Default)]
+pub struct TranslationOptions {
+    /// Source language of the inputs, required by the models translating from several languages (default: None)
+    pub source_language: Option<Language>,
+    /// Target language, required by the models translating to several languages (default: None)
+    pub target_language: Option<Language>,
+}
+
+impl<S> Pipeline<S, String> for TranslationModel
+where
+    S: AsRef<str> + Sync,
+{
+    type Options = TranslationOptions;
+
+    fn run_with_options(
+        &self,
+        inputs: &[S],
+        options: &TranslationOptions,
+    ) -> Result<Vec<String>, RustBertError> {
+        self.translate(inputs, options.source_language, options.target_language)
+    }
 }
 
 #[cfg(test)]
@@ -0,0 +1,282 @@
+// Copyright 2022 Guillaume Becquin
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//     http://www.apache.org/licenses/LICENSE-2.0
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use rust_tokenizers::Offset;
+use serde::{Deserialize, Serialize};
+use std::cmp::Ordering;
+use tch::{Kind, Tensor};
+
+/// Number of Sinkhorn iterations used to balance the attention matrix for `AlignmentMethod::OptimalTransport`
+const SINKHORN_ITERATIONS: usize = 20;
+
+/// # Method used to extract word alignments from the cross-attention weights
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum AlignmentMethod {
+    /// Each target word is aligned to the source word it attends to the most
+    Argmax,
+    /// The attention matrix is balanced into a transport plan between the source and target words (Sinkhorn
+    /// iterations) and the word pairs that are the best match of each other are kept. Leaves unaligned the words
+    /// without a clear counterpart rather than forcing an alignment for every target word.
+    OptimalTransport,
+}
+
+/// # Alignment between a word of the source text and a word of its translation
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct WordAlignment {
+    /// Index of the word in the source words
+    pub source_word: usize,
+    /// Index of the word in the target words
+    pub target_word: usize,
+    /// Share of the attention of the target word directed to the source word
+    pub score: f64,
+}
+
+/// # Translation with its word alignments to the source text
+/// Words are obtained by splitting the source and translated texts on whitespaces.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AlignedTranslation {
+    /// Translated text
+    pub text: String,
+    /// Words of the source text
+    pub source_words: Vec<String>,
+    /// Words of the translated text
+    pub target_words: Vec<String>,
+    /// Alignments between source and target words
+    pub alignments: Vec<WordAlignment>,
+}
+
+/// Splits a text into whitespace-separated words, returning the words with their character offsets
+pub(crate) fn split_words(text: &str) -> (Vec<String>, Vec<(u32, u32)>) {
+    let mut words = Vec::new();
+    let mut offsets = Vec::new();
+    let mut current_word = String::new();
+    let mut word_start = 0u32;
+    for (position, character) in text.chars().enumerate() {
+        if character.is_whitespace() {
+            if !current_word.is_empty() {
+                words.push(std::mem::take(&mut current_word));
+                offsets.push((word_start, position as u32));
+            }
+        } else {
+            if current_word.is_empty() {
+                word_start = position as u32;
+            }
+            current_word.push(character);
+        }
+    }
+    if !current_word.is_empty() {
+        offsets.push((word_start, word_start + current_word.chars().count() as u32));
+        words.push(current_word);
+    }
+    (words, offsets)
+}
+
+/// Maps each token to the index of the word it belongs to. Offsets are shifted by `shift` characters to skip
+/// the prefix added to the text before tokenization: the tokens of the prefix and the special tokens (without
+/// offsets, or flagged in the special tokens mask) are not mapped to any word.
+pub(crate) fn get_token_words(
+    token_offsets: &[Option<Offset>],
+    special_tokens_mask: &[i8],
+    shift: u32,
+    word_offsets: &[(u32, u32)],
+) -> Vec<Option<usize>> {
+    token_offsets
+        .iter()
+        .enumerate()
+        .map(|(token_index, offset)| {
+            if special_tokens_mask.get(token_index).copied().unwrap_or(0) == 1 {
+                return None;
+            }
+            let offset = offset.as_ref()?;
+            if offset.end <= shift {
+                return None;
+            }
+            let (begin, end) = (offset.begin.saturating_sub(shift), offset.end - shift);
+            word_offsets
+                .iter()
+                .position(|(word_begin, word_end)| (*word_begin < end) & (begin < *word_end))
+        })
+        .collect()
+}
+
+/// Aggregates token-level attention weights of shape (*target tokens*, *source tokens*) into word-level weights
+/// of shape (*target words*, *source words*). The attention is averaged over the sub-tokens of a target word and
+/// summed over the sub-tokens of a source word, tokens that are not part of any word are discarded and the
+/// weights of each target word are normalized to sum to one.
+pub(crate) fn get_word_attention(
+    token_attention: &Tensor,
+    target_token_words: &[Option<usize>],
+    source_token_words: &[Option<usize>],
+    num_target_words: usize,
+    num_source_words: usize,
+) -> Tensor {
+    let mut target_counts = vec![0f32; num_target_words];
+    for word in target_token_words.iter().flatten() {
+        target_counts[*word] += 1.0;
+    }
+    let mut target_pooling = vec![0f32; num_target_words * target_token_words.len()];
+    for (token_index, word) in target_token_words.iter().enumerate() {
+        if let Some(word) = word {
+            target_pooling[word * target_token_words.len() + token_index] =
+                1.0 / target_counts[*word];
+        }
+    }
+    let mut source_pooling = vec![0f32; source_token_words.len() * num_source_words];
+    for (token_index, word) in source_token_words.iter().enumerate() {
+        if let Some(word) = word {
+            source_pooling[token_index * num_source_words + word] = 1.0;
+        }
+    }
+
+    let device = token_attention.device();
+    let target_pooling = Tensor::of_slice(&target_pooling)
+        .view([num_target_words as i64, target_token_words.len() as i64])
+        .to(device);
+    let source_pooling = Tensor::of_slice(&source_pooling)
+        .view([source_token_words.len() as i64, num_source_words as i64])
+        .to(device);
+    let word_attention = target_pooling
+        .matmul(&token_attention.to_kind(Kind::Float))
+        .matmul(&source_pooling);
+    &word_attention
+        / word_attention
+            .sum_dim_intlist(&[1], true, Kind::Float)
+            .clamp_min(1e-9)
+}
+
+/// Aligns each target word to the source word with the highest attention weight
+pub(crate) fn get_argmax_alignments(word_attention: &Tensor) -> Vec<WordAlignment> {
+    Vec::<Vec<f64>>::from(word_attention)
+        .iter()
+        .enumerate()
+        .filter_map(|(target_word, weights)| {
+            weights
+                .iter()
+                .enumerate()
+                .max_by(|a, b| a.1.partial_cmp(b.1).unwrap_or(Ordering::Equal))
+                .filter(|(_, score)| **score > 0.0)
+                .map(|(source_word, score)| WordAlignment {
+                    source_word,
+                    target_word,
+                    score: *score,
+                })
+        })
+        .collect()
+}
+
+/// Balances the attention weights into a transport plan with uniform marginals over the source and target
+/// words, and keeps the word pairs that are the mutual best match in the plan
+pub(crate) fn get_optimal_transport_alignments(word_attention: &Tensor) -> Vec<WordAlignment> {
+    let (num_target_words, num_source_words) = word_attention.size2().unwrap();
+    if (num_target_words == 0) | (num_source_words == 0) {
+        return Vec::new();
+    }
+    let mut plan = word_attention.to_kind(Kind::Float) + 1e-9;
+    for _ in 0..SINKHORN_ITERATIONS {
+        plan = &plan / (plan.sum_dim_intlist(&[1], true, Kind::Float) * num_target_words as f64);
+        plan = &plan / (plan.sum_dim_intlist(&[0], true, Kind::Float) * num_source_words as f64);
+    }
+
+    let best_source_words = Vec::<i64>::from(&plan.argmax(1, false));
+    let best_target_words = Vec::<i64>::from(&plan.argmax(0, false));
+    let plan = Vec::<Vec<f64>>::from(&plan);
+    best_source_words
+        .iter()
+        .enumerate()
+        .filter(|(target_word, source_word)| {
+            best_target_words[**source_word as usize] == *target_word as i64
+        })
+        .map(|(target_word, source_word)| {
+            let row_mass = plan[target_word].iter().sum::<f64>();
+            WordAlignment {
+                source_word: *source_word as usize,
+                target_word,
+                score: plan[target_word][*source_word as usize] / row_mass,
+            }
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_split_words() {
+        let (words, offsets) = split_words("  Le chat  noir ");
+        assert_eq!(words, vec!["Le", "chat", "noir"]);
+        assert_eq!(offsets, vec![(2, 4), (5, 9), (11, 15)]);
+    }
+
+    #[test]
+    fn test_word_attention() {
+        //    Tokens: prefix, "Le", "ch", "at" and EOS for the source, "The", "c", "at" for the target
+        let source_offsets = vec![
+            Some(Offset::new(0, 7)),
+            Some(Offset::new(7, 9)),
+            Some(Offset::new(10, 12)),
+            Some(Offset::new(12, 14)),
+            None,
+        ];
+        let (_, source_word_offsets) = split_words("Le chat");
+        let source_token_words =
+            get_token_words(&source_offsets, &[0, 0, 0, 0, 1], 7, &source_word_offsets);
+        assert_eq!(
+            source_token_words,
+            vec![None, Some(0), Some(1), Some(1), None]
+        );
+
+        let target_token_words = vec![Some(0), Some(1), Some(1)];
+        let token_attention = Tensor::of_slice(&[
+            0.1f32, 0.7, 0.1, 0.0, 0.1, //
+            0.1, 0.1, 0.4, 0.2, 0.2, //
+            0.2, 0.2, 0.2, 0.4, 0.0,
+        ])
+        .view([3, 5]);
+        let word_attention = get_word_attention(
+            &token_attention,
+            &target_token_words,
+            &source_token_words,
+            2,
+            2,
+        );
+        let word_attention = Vec::<Vec<f64>>::from(&word_attention);
+        assert!((word_attention[0][0] - 0.875).abs() < 1e-6);
+        assert!((word_attention[0][1] - 0.125).abs() < 1e-6);
+        assert!((word_attention[1][0] - 0.2).abs() < 1e-6);
+        assert!((word_attention[1][1] - 0.8).abs() < 1e-6);
+    }
+
+    #[test]
+    fn test_alignments() {
+        //    The second target word attends to the first source word only marginally more than to the
+        //    second one, and is not the best match of any source word
+        let word_attention = Tensor::of_slice(&[0.9f32, 0.1, 0.55, 0.45, 0.1, 0.9]).view([3, 2]);
+
+        let argmax_alignments = get_argmax_alignments(&word_attention);
+        let pairs = argmax_alignments
+            .iter()
+            .map(|alignment| (alignment.target_word, alignment.source_word))
+            .collect::<Vec<(usize, usize)>>();
+        assert_eq!(pairs, vec![(0, 0), (1, 0), (2, 1)]);
+        assert!((argmax_alignments[0].score - 0.9).abs() < 1e-6);
+
+        let optimal_transport_alignments = get_optimal_transport_alignments(&word_attention);
+        let pairs = optimal_transport_alignments
+            .iter()
+            .map(|alignment| (alignment.target_word, alignment.source_word))
+            .collect::<Vec<(usize, usize)>>();
+        assert_eq!(pairs, vec![(0, 0), (2, 1)]);
+        assert!(optimal_transport_alignments
+            .iter()
+            .all(|alignment| (alignment.score > 0.0) & (alignment.score <= 1.0)));
+    }
+}
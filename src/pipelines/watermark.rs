@@ -0,0 +1,228 @@
+// Copyright 2022 Guillaume Becquin
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//     http://www.apache.org/licenses/LICENSE-2.0
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! # Watermarking of generated text
+//! Statistical watermark of [Kirchenbauer et al., 2023](https://arxiv.org/abs/2301.10226), allowing to check whether
+//! a text was generated by a model using a given hashing key. At each generation step, the vocabulary is split
+//! pseudo-randomly into a *green list* (a fraction `green_list_fraction` of the tokens) and a *red list*, seeded by
+//! the hashing key and the previous token, and a bias is added to the logits of the green tokens. A watermarked
+//! text contains many more green tokens than expected by chance, which is detected with a one-proportion z-test
+//! without access to the model: only the hashing key and the tokenizer are required.
+//!
+//! The green list of each token is derived by hashing the key, the previous token and the token itself, so that it
+//! does not depend on the size of the vocabulary of the model. The watermark is applied with
+//! `GenerateOptions::watermark` for all decoding strategies (`Watermark` also implements `LogitsProcessor`):
+//!
+//! ```no_run
+//! # fn main() -> anyhow::Result<()> {
+//! use rust_bert::gpt2::GPT2Generator;
+//! use rust_bert::pipelines::generation_utils::{GenerateOptions, LanguageGenerator};
+//! use rust_bert::pipelines::watermark::Watermark;
+//!
+//! let model = GPT2Generator::new(Default::default())?;
+//! let watermark = Watermark::new(15485863);
+//! let generate_options = GenerateOptions {
+//!     watermark: Some(&watermark),
+//!     max_new_tokens: Some(200),
+//!     ..Default::default()
+//! };
+//! let output = model.generate(Some(&["The history of the city"]), Some(generate_options));
+//!
+//! let detection = watermark.detect_text(model.get_tokenizer(), &output[0].text);
+//! let is_watermarked = detection.z_score > 4.0;
+//! # Ok(())
+//! # }
+//! ```
+//!
+//! Short texts cannot be reliably detected: about 200 tokens are needed with the default settings. Low-entropy
+//! generations (e.g. greedy decoding of code or factual answers) are less affected by the bias and carry a weaker
+//! watermark.
+
+use crate::pipelines::common::TokenizerOption;
+use crate::pipelines::logits_processor::LogitsProcessor;
+use tch::{Kind, Tensor};
+
+/// # Watermark for text generation
+/// Green list watermark applied to the logits during generation, and detector of the watermarked texts
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Watermark {
+    /// Key seeding the green lists. The same key must be used for the generation and the detection.
+    pub hashing_key: u64,
+    /// Fraction of the vocabulary in the green list at each step (default: 0.25)
+    pub green_list_fraction: f64,
+    /// Bias added to the logits of the green tokens (default: 2.0)
+    pub bias: f64,
+}
+
+/// # Result of the watermark detection
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct WatermarkDetection {
+    /// Number of tokens scored (all the tokens of the text except the first one)
+    pub num_tokens_scored: usize,
+    /// Number of scored tokens in the green list of their previous token
+    pub num_green_tokens: usize,
+    /// Fraction of green tokens among the scored tokens
+    pub green_fraction: f64,
+    /// z-score of the number of green tokens under the hypothesis of a text written without the watermark. Values
+    /// higher than 4 indicate a watermarked text with a very low false positive rate.
+    pub z_score: f64,
+}
+
+impl Watermark {
+    /// Creates a new `Watermark` with the default green list fraction (0.25) and bias (2.0)
+    ///
+    /// # Arguments
+    ///
+    /// * `hashing_key` - Key seeding the green lists
+    pub fn new(hashing_key: u64) -> Watermark {
+        Watermark {
+            hashing_key,
+            green_list_fraction: 0.25,
+            bias: 2.0,
+        }
+    }
+
+    /// Returns true if a token is in the green list of the previous token
+    pub fn is_green(&self, previous_token_id: i64, token_id: i64) -> bool {
+        let seed = mix(self.hashing_key ^ mix(previous_token_id as u64));
+        let value = mix(seed ^ token_id as u64);
+        (value as f64) < self.green_list_fraction * (u64::MAX as f64)
+    }
+
+    /// Scores a sequence of token ids for the presence of the watermark
+    ///
+    /// # Arguments
+    ///
+    /// * `token_ids` - Token ids of the text to check
+    ///
+    /// # Returns
+    ///
+    /// * `WatermarkDetection` number of green tokens and z-score of the sequence
+    pub fn detect(&self, token_ids: &[i64]) -> WatermarkDetection {
+        let num_tokens_scored = token_ids.len().saturating_sub(1);
+        let num_green_tokens = token_ids
+            .windows(2)
+            .filter(|bigram| self.is_green(bigram[0], bigram[1]))
+            .count();
+        let (green_fraction, z_score) = if num_tokens_scored > 0 {
+            let expected_fraction = self.green_list_fraction;
+            let num_tokens = num_tokens_scored as f64;
+            (
+                num_green_tokens as f64 / num_tokens,
+                (num_green_tokens as f64 - expected_fraction * num_tokens)
+                    / (num_tokens * expected_fraction * (1.0 - expected_fraction)).sqrt(),
+            )
+        } else {
+            (0.0, 0.0)
+        };
+        WatermarkDetection {
+            num_tokens_scored,
+            num_green_tokens,
+            green_fraction,
+            z_score,
+        }
+    }
+
+    /// Scores a text for the presence of the watermark, after tokenizing it with the tokenizer of the model used
+    /// for the generation
+    ///
+    /// # Arguments
+    ///
+    /// * `tokenizer` - Tokenizer of the model used for the generation
+    /// * `text` - Text to check
+    ///
+    /// # Returns
+    ///
+    /// * `WatermarkDetection` number of green tokens and z-score of the text
+    pub fn detect_text(&self, tokenizer: &TokenizerOption, text: &str) -> WatermarkDetection {
+        let token_ids = tokenizer.convert_tokens_to_ids(&tokenizer.tokenize(text));
+        self.detect(&token_ids)
+    }
+}
+
+impl LogitsProcessor for Watermark {
+    /// Adds the watermark bias to the logits of the green tokens of each sequence
+    fn process(&self, input_ids: &Tensor, next_token_logits: &mut Tensor) {
+        let vocab_size = *next_token_logits.size().last().unwrap();
+        let previous_token_ids = Vec::<i64>::from(input_ids.select(1, -1));
+        let green_mask = previous_token_ids
+            .iter()
+            .flat_map(|previous_token_id| {
+                (0..vocab_size).map(move |token_id| self.is_green(*previous_token_id, token_id))
+            })
+            .collect::<Vec<bool>>();
+        let green_mask = Tensor::of_slice(&green_mask)
+            .view([previous_token_ids.len() as i64, vocab_size])
+            .to_device(next_token_logits.device());
+        *next_token_logits += green_mask.to_kind(next_token_logits.kind()) * self.bias;
+    }
+}
+
+/// SplitMix64 finalizer, mapping a 64-bit integer to a pseudo-random 64-bit integer
+fn mix(value: u64) -> u64 {
+    let mut value = value.wrapping_add(0x9E37_79B9_7F4A_7C15);
+    value = (value ^ (value >> 30)).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+    value = (value ^ (value >> 27)).wrapping_mul(0x94D0_49BB_1331_11EB);
+    value ^ (value >> 31)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use tch::Device;
+
+    #[test]
+    fn green_list() {
+        let watermark = Watermark::new(15485863);
+        let logits = Tensor::zeros(&[2, 10_000], (Kind::Float, Device::Cpu));
+        let mut biased_logits = logits.copy();
+        watermark.process(
+            &Tensor::of_slice(&[3i64, 7, 5, 11]).view([2, 2]),
+            &mut biased_logits,
+        );
+
+        // About a quarter of the vocabulary is green, with a different green list for each previous token
+        let green_mask = biased_logits.gt(0.0);
+        let green_fraction = f64::from(green_mask.to_kind(Kind::Float).mean(Kind::Float));
+        assert!((green_fraction - 0.25).abs() < 0.02);
+        assert!(!green_mask.get(0).equal(&green_mask.get(1)));
+        assert_eq!(f64::from(biased_logits.max()), 2.0);
+        assert_eq!(
+            bool::from(green_mask.get(1).get(42)),
+            watermark.is_green(11, 42)
+        );
+    }
+
+    #[test]
+    fn detection() {
+        let watermark = Watermark::new(15485863);
+        // Sequence made of green tokens only
+        let mut token_ids = vec![0i64];
+        for _ in 0..50 {
+            let previous_token_id = *token_ids.last().unwrap();
+            let next_token_id = (0..)
+                .find(|token_id| watermark.is_green(previous_token_id, *token_id))
+                .unwrap();
+            token_ids.push(next_token_id);
+        }
+        let detection = watermark.detect(&token_ids);
+        assert_eq!(detection.num_tokens_scored, 50);
+        assert_eq!(detection.num_green_tokens, 50);
+        assert!(detection.z_score > 4.0);
+
+        // The watermark is not detected with another key
+        let detection = Watermark::new(42).detect(&token_ids);
+        assert!(detection.z_score < 4.0);
+
+        let detection = watermark.detect(&(1000..1200).collect::<Vec<i64>>());
+        assert!(detection.z_score.abs() < 4.0);
+    }
+}
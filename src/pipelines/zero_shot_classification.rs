@@ -102,11 +102,13 @@ use crate::albert::AlbertForSequenceClassification;
 use crate::bart::BartForSequenceClassification;
 use crate::bert::BertForSequenceClassification;
 use crate::deberta::DebertaForSequenceClassification;
+use crate::deberta_v2::DebertaV2ForSequenceClassification;
 use crate::distilbert::DistilBertModelClassifier;
 use crate::longformer::LongformerForSequenceClassification;
 use crate::mobilebert::MobileBertForSequenceClassification;
 use crate::pipelines::common::{ConfigOption, ModelType, TokenizerOption};
 use crate::pipelines::sequence_classification::Label;
+use crate::pipelines::translation::{Language, TranslationModel};
 use crate::resources::ResourceProvider;
 use crate::roberta::RobertaForSequenceClassification;
 use crate::xlnet::XLNetForSequenceClassification;
@@ -122,7 +124,9 @@ use tch::{nn, no_grad, Device, Tensor};
 #[cfg(feature = "remote")]
 use crate::{
     bart::{BartConfigResources, BartMergesResources, BartModelResources, BartVocabResources},
+    deberta_v2::{DebertaV2ConfigResources, DebertaV2VocabResources},
     resources::RemoteResource,
+    roberta::{RobertaConfigResources, RobertaVocabResources},
 };
 
 /// # Configuration for ZeroShotClassificationModel
@@ -212,15 +216,214 @@ impl Default for ZeroShotClassificationConfig {
     }
 }
 
+/// # Pretrained multilingual zero-shot classification models
+/// Models fine-tuned on multilingual Natural Language Inference datasets (XNLI). These models can classify
+/// inputs against labels (and label templates) provided in a different language.
+///
+/// No converted checkpoint is hosted for these models: the configuration and vocabulary are downloaded from
+/// the original repositories, and the weights are converted locally from the PyTorch checkpoint with
+/// `python utils/convert_model.py path/to/pytorch_model.bin` (see `ZeroShotClassificationConfig::from_model_type`).
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ZeroShotClassificationModelType {
+    /// XLM-RoBERTa large fine-tuned on MNLI and XNLI (<https://huggingface.co/joeddav/xlm-roberta-large-xnli>)
+    XlmRobertaLargeXnli,
+    /// mDeBERTa-v3 base fine-tuned on MNLI and XNLI (<https://huggingface.co/MoritzLaurer/mDeBERTa-v3-base-mnli-xnli>)
+    MDebertaV3BaseMnliXnli,
+}
+
+#[cfg(feature = "remote")]
+impl ZeroShotClassificationConfig {
+    /// Instantiate the configuration of a multilingual zero-shot classification model. The configuration and
+    /// vocabulary are downloaded from the original repository of the model, the weights are provided as a
+    /// resource converted locally.
+    ///
+    /// # Arguments
+    ///
+    /// * `model_type` - `ZeroShotClassificationModelType` pretrained model to load
+    /// * `model_resource` - The `ResourceProvider` pointing to the converted weights of the model (e.g. rust_model.ot)
+    ///
+    /// # Example
+    ///
+    /// ```no_run
+    /// # fn main() -> anyhow::Result<()> {
+    /// use rust_bert::pipelines::zero_shot_classification::{
+    ///     ZeroShotClassificationConfig, ZeroShotClassificationModelType,
+    /// };
+    /// use rust_bert::resources::LocalResource;
+    /// use std::path::PathBuf;
+    ///
+    /// let config = ZeroShotClassificationConfig::from_model_type(
+    ///     ZeroShotClassificationModelType::MDebertaV3BaseMnliXnli,
+    ///     LocalResource {
+    ///         local_path: PathBuf::from("path/to/rust_model.ot"),
+    ///     },
+    /// );
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn from_model_type<R>(
+        model_type: ZeroShotClassificationModelType,
+        model_resource: R,
+    ) -> ZeroShotClassificationConfig
+    where
+        R: ResourceProvider + Send + 'static,
+    {
+        let (model_type, config_resource, vocab_resource) = match model_type {
+            ZeroShotClassificationModelType::XlmRobertaLargeXnli => (
+                ModelType::XLMRoberta,
+                RemoteResource::from_pretrained(RobertaConfigResources::XLM_ROBERTA_LARGE_XNLI),
+                RemoteResource::from_pretrained(RobertaVocabResources::XLM_ROBERTA_LARGE_XNLI),
+            ),
+            ZeroShotClassificationModelType::MDebertaV3BaseMnliXnli => (
+                ModelType::DebertaV2,
+                RemoteResource::from_pretrained(
+                    DebertaV2ConfigResources::MDEBERTA_V3_BASE_MNLI_XNLI,
+                ),
+                RemoteResource::from_pretrained(
+                    DebertaV2VocabResources::MDEBERTA_V3_BASE_MNLI_XNLI,
+                ),
+            ),
+        };
+        ZeroShotClassificationConfig {
+            model_type,
+            model_resource: Box::new(model_resource),
+            config_resource: Box::new(config_resource),
+            vocab_resource: Box::new(vocab_resource),
+            merges_resource: None,
+            lower_case: false,
+            strip_accents: None,
+            add_prefix_space: None,
+            device: Device::cuda_if_available(),
+        }
+    }
+}
+
+/// Returns a label template (building the hypothesis from a label) in the language provided.
+/// This can be passed as the `template` argument of the predictions when the labels are given in a language
+/// other than English. Falls back to the default English template `This example is about {}.` for languages
+/// without a localized template.
+///
+/// # Arguments
+///
+/// * `language` - Language of the labels
+///
+/// # Example
+///
+/// ```no_run
+/// # fn main() -> anyhow::Result<()> {
+/// use rust_bert::pipelines::translation::Language;
+/// use rust_bert::pipelines::zero_shot_classification::{
+///     get_localized_template, ZeroShotClassificationConfig, ZeroShotClassificationModel,
+///     ZeroShotClassificationModelType,
+/// };
+/// use rust_bert::resources::LocalResource;
+/// use std::path::PathBuf;
+///
+/// let model = ZeroShotClassificationModel::new(ZeroShotClassificationConfig::from_model_type(
+///     ZeroShotClassificationModelType::MDebertaV3BaseMnliXnli,
+///     LocalResource {
+///         local_path: PathBuf::from("path/to/rust_model.ot"),
+///     },
+/// ))?;
+/// let output = model.predict(
+///     &["Who are you voting for in 2020?"],
+///     &["politique", "santé publique", "économie", "sport"],
+///     Some(get_localized_template(Language::French)),
+///     128,
+/// );
+/// # Ok(())
+/// # }
+/// ```
+pub fn get_localized_template(language: Language) -> Box<dyn Fn(&str) -> String> {
+    let template = match language {
+        Language::French => "Cet exemple parle de {}.",
+        Language::German => "Dieses Beispiel handelt von {}.",
+        Language::Spanish => "Este ejemplo trata sobre {}.",
+        Language::Italian => "Questo esempio riguarda {}.",
+        Language::Portuguese => "Este exemplo é sobre {}.",
+        Language::Dutch => "Dit voorbeeld gaat over {}.",
+        Language::Russian => "Этот пример про {}.",
+        Language::ChineseMandarin => "这个例子是关于{}的。",
+        Language::Japanese => "この例は{}に関するものです。",
+        _ => "This example is about {}.",
+    };
+    Box::new(move |label: &str| template.replace("{}", label))
+}
+
+/// # Translation of the candidate labels
+/// Translates the candidate labels to the language of the inputs before classification. The predicted labels
+/// are returned in their original language.
+pub struct LabelTranslation<'a> {
+    /// Translation model used to translate the labels
+    pub translation_model: &'a TranslationModel,
+    /// Language of the labels provided
+    pub source_language: Language,
+    /// Language of the inputs to classify, to translate the labels to
+    pub target_language: Language,
+}
+
+impl<'a> LabelTranslation<'a> {
+    fn translate_labels(&self, labels: &[&str]) -> Result<Vec<String>, RustBertError> {
+        if self.source_language == self.target_language {
+            return Ok(labels.iter().map(|label| label.to_string()).collect());
+        }
+        self.translation_model
+            .translate(labels, self.source_language, self.target_language)
+    }
+}
+
+/// Position of the inference classes in the model output
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) struct InferenceLabelIndices {
+    pub(crate) entailment: i64,
+    pub(crate) neutral: Option<i64>,
+    pub(crate) contradiction: i64,
+}
+
+impl InferenceLabelIndices {
+    pub(crate) fn from_label_mapping<'a, I>(
+        labels: I,
+    ) -> Result<InferenceLabelIndices, RustBertError>
+    where
+        I: IntoIterator<Item = (&'a i64, &'a String)>,
+    {
+        let mut entailment = None;
+        let mut neutral = None;
+        let mut contradiction = None;
+        for (id, label) in labels {
+            let label = label.to_lowercase();
+            if label.starts_with("entail") {
+                entailment = Some(*id);
+            } else if label.starts_with("neutral") {
+                neutral = Some(*id);
+            } else if label.starts_with("contradict") || label.starts_with("not_entail") {
+                contradiction = Some(*id);
+            }
+        }
+        match (entailment, contradiction) {
+            (Some(entailment), Some(contradiction)) => Ok(InferenceLabelIndices {
+                entailment,
+                neutral,
+                contradiction,
+            }),
+            _ => Err(RustBertError::InvalidConfigurationError(
+                "The label dictionary (id2label) of the model must contain an entailment and a contradiction (or not_entailment) label".to_string(),
+            )),
+        }
+    }
+}
+
 /// # Abstraction that holds one particular zero shot classification model, for any of the supported models
 /// The models are using a classification architecture that should be trained on Natural Language Inference.
-/// The models should output a Tensor of size > 2 in the label dimension, with the first logit corresponding
-/// to contradiction and the last logit corresponding to entailment.
+/// The models should output a Tensor of size >= 2 in the label dimension, with the position of the entailment and
+/// contradiction (or not_entailment) logits given by the label dictionary (`id2label`) of the configuration.
 pub enum ZeroShotClassificationOption {
     /// Bart for Sequence Classification
     Bart(BartForSequenceClassification),
     /// DeBERTa for Sequence Classification
     Deberta(DebertaForSequenceClassification),
+    /// DeBERTa V2 for Sequence Classification
+    DebertaV2(DebertaV2ForSequenceClassification),
     /// Bert for Sequence Classification
     Bert(BertForSequenceClassification),
     /// DistilBert for Sequence Classification
@@ -279,6 +482,17 @@ impl ZeroShotClassificationOption {
                     ))
                 }
             }
+            ModelType::DebertaV2 => {
+                if let ConfigOption::DebertaV2(config) = config {
+                    Ok(ZeroShotClassificationOption::DebertaV2(
+                        DebertaV2ForSequenceClassification::new(p, config),
+                    ))
+                } else {
+                    Err(RustBertError::InvalidConfigurationError(
+                        "You can only supply a DebertaV2Config for DeBERTa V2!".to_string(),
+                    ))
+                }
+            }
             ModelType::Bert => {
                 if let ConfigOption::Bert(config) = config {
                     Ok(ZeroShotClassificationOption::Bert(
@@ -313,24 +527,24 @@ impl ZeroShotClassificationOption {
                 }
             }
             ModelType::Roberta => {
-                if let ConfigOption::Bert(config) = config {
+                if let ConfigOption::Roberta(config) = config {
                     Ok(ZeroShotClassificationOption::Roberta(
                         RobertaForSequenceClassification::new(p, config),
                     ))
                 } else {
                     Err(RustBertError::InvalidConfigurationError(
-                        "You can only supply a BertConfig for Roberta!".to_string(),
+                        "You can only supply a RobertaConfig for Roberta!".to_string(),
                     ))
                 }
             }
             ModelType::XLMRoberta => {
-                if let ConfigOption::Bert(config) = config {
+                if let ConfigOption::Roberta(config) = config {
                     Ok(ZeroShotClassificationOption::XLMRoberta(
                         RobertaForSequenceClassification::new(p, config),
                     ))
                 } else {
                     Err(RustBertError::InvalidConfigurationError(
-                        "You can only supply a BertConfig for Roberta!".to_string(),
+                        "You can only supply a RobertaConfig for XLMRoberta!".to_string(),
                     ))
                 }
             }
@@ -379,6 +593,7 @@ impl ZeroShotClassificationOption {
         match *self {
             Self::Bart(_) => ModelType::Bart,
             Self::Deberta(_) => ModelType::Deberta,
+            Self::DebertaV2(_) => ModelType::DebertaV2,
             Self::Bert(_) => ModelType::Bert,
             Self::Roberta(_) => ModelType::Roberta,
             Self::XLMRoberta(_) => ModelType::Roberta,
@@ -411,6 +626,7 @@ impl ZeroShotClassificationOption {
                         None,
                         train,
                     )
+                    .expect("Error in BART forward_t")
                     .decoder_output
             }
             Self::Bert(ref model) => {
@@ -438,6 +654,19 @@ impl ZeroShotClassificationOption {
                     .expect("Error in DeBERTa forward_t")
                     .logits
             }
+            Self::DebertaV2(ref model) => {
+                model
+                    .forward_t(
+                        input_ids,
+                        mask,
+                        token_type_ids,
+                        position_ids,
+                        input_embeds,
+                        train,
+                    )
+                    .expect("Error in DeBERTa V2 forward_t")
+                    .logits
+            }
             Self::DistilBert(ref model) => {
                 model
                     .forward_t(input_ids, mask, input_embeds, train)
@@ -510,6 +739,7 @@ impl ZeroShotClassificationOption {
 pub struct ZeroShotClassificationModel {
     tokenizer: TokenizerOption,
     zero_shot_classifier: ZeroShotClassificationOption,
+    label_indices: InferenceLabelIndices,
     var_store: VarStore,
 }
 
@@ -535,7 +765,6 @@ impl ZeroShotClassificationModel {
     ) -> Result<ZeroShotClassificationModel, RustBertError> {
         let config_path = config.config_resource.get_local_path()?;
         let vocab_path = config.vocab_resource.get_local_path()?;
-        let weights_path = config.model_resource.get_local_path()?;
         let merges_path = if let Some(merges_resource) = &config.merges_resource {
             Some(merges_resource.get_local_path()?)
         } else {
@@ -552,13 +781,17 @@ impl ZeroShotClassificationModel {
             config.add_prefix_space,
         )?;
         let mut var_store = VarStore::new(device);
-        let model_config = ConfigOption::from_file(config.model_type, config_path);
+        let model_config = ConfigOption::from_file(config.model_type, config_path)?;
+        model_config.validate(Some(&tokenizer), None)?;
+        let label_indices =
+            InferenceLabelIndices::from_label_mapping(model_config.get_label_mapping())?;
         let zero_shot_classifier =
             ZeroShotClassificationOption::new(config.model_type, &var_store.root(), &model_config)?;
-        var_store.load(weights_path)?;
+        crate::resources::load_weights(&config.model_resource, &mut var_store)?;
         Ok(ZeroShotClassificationModel {
             tokenizer,
             zero_shot_classifier,
+            label_indices,
             var_store,
         })
     }
@@ -713,7 +946,9 @@ impl ZeroShotClassificationModel {
             output.view((num_inputs as i64, labels.as_ref().len() as i64, -1i64))
         });
 
-        let scores = output.softmax(1, Float).select(-1, -1);
+        let scores = output
+            .softmax(1, Float)
+            .select(-1, self.label_indices.entailment);
         let label_indices = scores.as_ref().argmax(-1, true).squeeze_dim(1);
         let scores = scores
             .gather(1, &label_indices.unsqueeze(-1), false)
@@ -853,7 +1088,16 @@ impl ZeroShotClassificationModel {
             );
             output.view((num_inputs as i64, labels.as_ref().len() as i64, -1i64))
         });
-        let scores = output.slice(-1, 0, 3, 2).softmax(-1, Float).select(-1, -1);
+        // Entailment probability of each label against contradiction only
+        let scores = Tensor::stack(
+            &[
+                output.select(-1, self.label_indices.contradiction),
+                output.select(-1, self.label_indices.entailment),
+            ],
+            -1,
+        )
+        .softmax(-1, Float)
+        .select(-1, 1);
 
         let mut output_labels = vec![];
         for sentence_idx in 0..num_inputs {
@@ -878,10 +1122,142 @@ impl ZeroShotClassificationModel {
         }
         output_labels
     }
+
+    /// Zero shot classification with 1 (and exactly 1) true label, with labels provided in a language different from the inputs.
+    /// The labels are translated to the language of the inputs before classification.
+    ///
+    /// # Arguments
+    ///
+    /// * `input` - `&[&str]` Array of texts to classify.
+    /// * `labels` - `&[&str]` Possible labels for the inputs, in the source language of the `label_translation`.
+    /// * `label_translation` - `LabelTranslation` with the translation model and languages used to translate the labels.
+    /// * `template` - `Option<Box<dyn Fn(&str) -> String>>` closure to build label propositions from the translated labels. If None, will default to the localized template for the language of the inputs (see `get_localized_template`).
+    /// * `max_length` -`usize` Maximum sequence length for the inputs. If needed, the input sequence will be truncated before the label template.
+    ///
+    /// # Returns
+    ///
+    /// * `Vec<Label>` containing with the most likely label (in its original language) for each input sentence.
+    ///
+    /// # Example
+    ///
+    /// ```no_run
+    /// # fn main() -> anyhow::Result<()> {
+    /// use rust_bert::pipelines::translation::{Language, TranslationModelBuilder};
+    /// use rust_bert::pipelines::zero_shot_classification::{
+    ///     LabelTranslation, ZeroShotClassificationConfig, ZeroShotClassificationModel,
+    ///     ZeroShotClassificationModelType,
+    /// };
+    /// use rust_bert::resources::LocalResource;
+    /// use std::path::PathBuf;
+    ///
+    /// let model = ZeroShotClassificationModel::new(ZeroShotClassificationConfig::from_model_type(
+    ///     ZeroShotClassificationModelType::XlmRobertaLargeXnli,
+    ///     LocalResource {
+    ///         local_path: PathBuf::from("path/to/rust_model.ot"),
+    ///     },
+    /// ))?;
+    /// let translation_model = TranslationModelBuilder::new()
+    ///     .with_source_languages(vec![Language::English])
+    ///     .with_target_languages(vec![Language::French])
+    ///     .create_model()?;
+    /// let label_translation = LabelTranslation {
+    ///     translation_model: &translation_model,
+    ///     source_language: Language::English,
+    ///     target_language: Language::French,
+    /// };
+    ///
+    /// let output = model.predict_with_label_translation(
+    ///     &["Pour qui allez-vous voter en 2020 ?"],
+    ///     &["politics", "public health", "economics", "sports"],
+    ///     &label_translation,
+    ///     None,
+    ///     128,
+    /// )?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn predict_with_label_translation<'a, S, T>(
+        &self,
+        inputs: S,
+        labels: T,
+        label_translation: &LabelTranslation,
+        template: Option<Box<dyn Fn(&str) -> String>>,
+        max_length: usize,
+    ) -> Result<Vec<Label>, RustBertError>
+    where
+        S: AsRef<[&'a str]>,
+        T: AsRef<[&'a str]>,
+    {
+        let translated_labels = label_translation.translate_labels(labels.as_ref())?;
+        let translated_labels = translated_labels
+            .iter()
+            .map(|label| label.as_str())
+            .collect::<Vec<&str>>();
+        let template =
+            template.unwrap_or_else(|| get_localized_template(label_translation.target_language));
+
+        let mut output = self.predict(
+            inputs.as_ref(),
+            translated_labels.as_slice(),
+            Some(template),
+            max_length,
+        );
+        for label in output.iter_mut() {
+            label.text = labels.as_ref()[label.id as usize].to_string();
+        }
+        Ok(output)
+    }
+
+    /// Zero shot multi-label classification with 0, 1 or no true label, with labels provided in a language different from the inputs.
+    /// The labels are translated to the language of the inputs before classification.
+    ///
+    /// # Arguments
+    ///
+    /// * `input` - `&[&str]` Array of texts to classify.
+    /// * `labels` - `&[&str]` Possible labels for the inputs, in the source language of the `label_translation`.
+    /// * `label_translation` - `LabelTranslation` with the translation model and languages used to translate the labels.
+    /// * `template` - `Option<Box<dyn Fn(&str) -> String>>` closure to build label propositions from the translated labels. If None, will default to the localized template for the language of the inputs (see `get_localized_template`).
+    /// * `max_length` -`usize` Maximum sequence length for the inputs. If needed, the input sequence will be truncated before the label template.
+    ///
+    /// # Returns
+    ///
+    /// * `Vec<Vec<Label>>` containing a vector of labels (in their original language) and their probability for each input text
+    pub fn predict_multilabel_with_label_translation<'a, S, T>(
+        &self,
+        inputs: S,
+        labels: T,
+        label_translation: &LabelTranslation,
+        template: Option<Box<dyn Fn(&str) -> String>>,
+        max_length: usize,
+    ) -> Result<Vec<Vec<Label>>, RustBertError>
+    where
+        S: AsRef<[&'a str]>,
+        T: AsRef<[&'a str]>,
+    {
+        let translated_labels = label_translation.translate_labels(labels.as_ref())?;
+        let translated_labels = translated_labels
+            .iter()
+            .map(|label| label.as_str())
+            .collect::<Vec<&str>>();
+        let template =
+            template.unwrap_or_else(|| get_localized_template(label_translation.target_language));
+
+        let mut output = self.predict_multilabel(
+            inputs.as_ref(),
+            translated_labels.as_slice(),
+            Some(template),
+            max_length,
+        );
+        for label in output.iter_mut().flatten() {
+            label.text = labels.as_ref()[label.id as usize].to_string();
+        }
+        Ok(output)
+    }
 }
 #[cfg(test)]
 mod test {
     use super::*;
+    use std::collections::HashMap;
 
     #[test]
     #[ignore] // no need to run, compilation is enough to verify it is Send
@@ -889,4 +1265,56 @@ mod test {
         let config = ZeroShotClassificationConfig::default();
         let _: Box<dyn Send> = Box::new(ZeroShotClassificationModel::new(config));
     }
+
+    #[test]
+    fn test_label_indices() {
+        let mnli_labels: HashMap<i64, String> = [
+            (0, "contradiction".to_string()),
+            (1, "neutral".to_string()),
+            (2, "entailment".to_string()),
+        ]
+        .into_iter()
+        .collect();
+        assert_eq!(
+            InferenceLabelIndices::from_label_mapping(&mnli_labels).unwrap(),
+            InferenceLabelIndices {
+                entailment: 2,
+                neutral: Some(1),
+                contradiction: 0,
+            }
+        );
+
+        let binary_labels: HashMap<i64, String> = [
+            (0, "ENTAILMENT".to_string()),
+            (1, "NOT_ENTAILMENT".to_string()),
+        ]
+        .into_iter()
+        .collect();
+        assert_eq!(
+            InferenceLabelIndices::from_label_mapping(&binary_labels).unwrap(),
+            InferenceLabelIndices {
+                entailment: 0,
+                neutral: None,
+                contradiction: 1,
+            }
+        );
+
+        let sentiment_labels: HashMap<i64, String> =
+            vec![(0, "NEGATIVE".to_string()), (1, "POSITIVE".to_string())]
+                .into_iter()
+                .collect();
+        assert!(InferenceLabelIndices::from_label_mapping(&sentiment_labels).is_err());
+    }
+
+    #[test]
+    fn test_localized_template() {
+        assert_eq!(
+            get_localized_template(Language::French)("sport"),
+            "Cet exemple parle de sport."
+        );
+        assert_eq!(
+            get_localized_template(Language::Swahili)("sports"),
+            "This example is about sports."
+        );
+    }
 }
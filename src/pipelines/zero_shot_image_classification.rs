@@ -0,0 +1,403 @@
+// Copyright 2022 Guillaume Becquin
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//     http://www.apache.org/licenses/LICENSE-2.0
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! # Zero-shot image classification pipeline
+//! Classifies decoded images against a list of candidate labels provided at inference time, using a CLIP model.
+//! The candidate labels are inserted in a template (by default `a photo of a {}.`) and embedded by the text tower, the
+//! images are embedded by the vision tower, and the label probabilities are the softmax of the scaled cosine
+//! similarities of the image and label embeddings in the shared space.
+//! The CLIP ViT-B/32 model is available as a preset (`ZeroShotImageClassificationConfig::clip_vit_base_patch32`): its
+//! configuration and tokenizer files are downloaded from the original repository and its weights are converted locally
+//! from the PyTorch checkpoint (`python utils/convert_model.py path/to/pytorch_model.bin`).
+//!
+//! ```no_run
+//! # fn main() -> anyhow::Result<()> {
+//! use rust_bert::pipelines::zero_shot_image_classification::{
+//!     ZeroShotImageClassificationConfig, ZeroShotImageClassificationModel,
+//! };
+//! use rust_bert::resources::LocalResource;
+//! use rust_bert::vit::ImageBuffer;
+//! use std::path::PathBuf;
+//!
+//! let model = ZeroShotImageClassificationModel::new(
+//!     ZeroShotImageClassificationConfig::clip_vit_base_patch32(LocalResource::from(PathBuf::from(
+//!         "path/to/rust_model.ot",
+//!     ))),
+//! )?;
+//!
+//! // Decoded 640x480 RGB image
+//! let pixels = vec![127u8; 640 * 480 * 3];
+//! let candidate_labels = &["cat", "dog", "car"];
+//! let predictions = model.predict(
+//!     &[ImageBuffer::rgb(&pixels, 640, 480)],
+//!     candidate_labels,
+//!     None,
+//! )?;
+//! for label in &predictions[0] {
+//!     println!("{}: {:.3}", label.text, label.score);
+//! }
+//! # Ok(())
+//! # }
+//! ```
+//!
+//! The image and text embeddings can also be computed separately, for example to index images for text-to-image
+//! search with `encode_images` and `encode_texts`.
+
+use crate::clip::{ClipConfig, ClipModel, ClipTokenizer, CLIP_IMAGE_MEAN, CLIP_IMAGE_STD};
+use crate::common::error::RustBertError;
+use crate::pipelines::sentence_embeddings::Embedding;
+use crate::pipelines::sequence_classification::Label;
+use crate::resources::ResourceProvider;
+use crate::vit::{ImageBuffer, ViTImageProcessor};
+use crate::Config;
+use std::cmp::Ordering;
+use tch::nn::VarStore;
+use tch::{no_grad, Device, Kind, Tensor};
+
+#[cfg(feature = "remote")]
+use crate::{
+    clip::{ClipConfigResources, ClipMergesResources, ClipVocabResources},
+    resources::RemoteResource,
+};
+
+/// # Configuration for ZeroShotImageClassificationModel
+/// Contains information regarding the model to load and the device to place the model on.
+pub struct ZeroShotImageClassificationConfig {
+    /// Model weights resource
+    pub model_resource: Box<dyn ResourceProvider + Send>,
+    /// Config resource
+    pub config_resource: Box<dyn ResourceProvider + Send>,
+    /// Vocab resource
+    pub vocab_resource: Box<dyn ResourceProvider + Send>,
+    /// Merges resource
+    pub merges_resource: Box<dyn ResourceProvider + Send>,
+    /// Device to place the model on (default: CUDA/GPU when available)
+    pub device: Device,
+}
+
+impl ZeroShotImageClassificationConfig {
+    /// Instantiate a new zero-shot image classification configuration
+    ///
+    /// # Arguments
+    ///
+    /// * `model_resource` - The `ResourceProvider` pointing to the model weights to load (e.g. model.ot)
+    /// * `config_resource` - The `ResourceProvider` pointing to the model configuration to load (e.g. config.json)
+    /// * `vocab_resource` - The `ResourceProvider` pointing to the tokenizer's vocabulary to load (e.g. vocab.json)
+    /// * `merges_resource` - The `ResourceProvider` pointing to the tokenizer's merge file to load (e.g. merges.txt)
+    pub fn new<R>(
+        model_resource: R,
+        config_resource: R,
+        vocab_resource: R,
+        merges_resource: R,
+    ) -> ZeroShotImageClassificationConfig
+    where
+        R: ResourceProvider + Send + 'static,
+    {
+        ZeroShotImageClassificationConfig {
+            model_resource: Box::new(model_resource),
+            config_resource: Box::new(config_resource),
+            vocab_resource: Box::new(vocab_resource),
+            merges_resource: Box::new(merges_resource),
+            device: Device::cuda_if_available(),
+        }
+    }
+}
+
+#[cfg(feature = "remote")]
+impl ZeroShotImageClassificationConfig {
+    /// Instantiate the configuration of the CLIP ViT-B/32 model. The configuration and tokenizer files are downloaded
+    /// from the original repository of the model, the weights are provided as a resource converted locally.
+    ///
+    /// # Arguments
+    ///
+    /// * `model_resource` - The `ResourceProvider` pointing to the converted weights of the model (e.g. rust_model.ot)
+    pub fn clip_vit_base_patch32<R>(model_resource: R) -> ZeroShotImageClassificationConfig
+    where
+        R: ResourceProvider + Send + 'static,
+    {
+        ZeroShotImageClassificationConfig {
+            model_resource: Box::new(model_resource),
+            config_resource: Box::new(RemoteResource::from_pretrained(
+                ClipConfigResources::CLIP_VIT_BASE_PATCH32,
+            )),
+            vocab_resource: Box::new(RemoteResource::from_pretrained(
+                ClipVocabResources::CLIP_VIT_BASE_PATCH32,
+            )),
+            merges_resource: Box::new(RemoteResource::from_pretrained(
+                ClipMergesResources::CLIP_VIT_BASE_PATCH32,
+            )),
+            device: Device::cuda_if_available(),
+        }
+    }
+}
+
+/// Default template building the text embedded for a candidate label
+fn default_template(label: &str) -> String {
+    format!("a photo of a {}.", label)
+}
+
+/// # ZeroShotImageClassificationModel to classify images against arbitrary labels
+pub struct ZeroShotImageClassificationModel {
+    model: ClipModel,
+    tokenizer: ClipTokenizer,
+    image_processor: ViTImageProcessor,
+    max_length: usize,
+    batch_size: usize,
+    var_store: VarStore,
+}
+
+impl ZeroShotImageClassificationModel {
+    /// Build a new `ZeroShotImageClassificationModel`
+    ///
+    /// # Arguments
+    ///
+    /// * `config` - `ZeroShotImageClassificationConfig` object containing the resource references (model, config, vocab, merges) and device placement (CPU/GPU)
+    ///
+    /// # Example
+    ///
+    /// ```no_run
+    /// # fn main() -> anyhow::Result<()> {
+    /// use rust_bert::pipelines::zero_shot_image_classification::{
+    ///     ZeroShotImageClassificationConfig, ZeroShotImageClassificationModel,
+    /// };
+    /// use rust_bert::resources::LocalResource;
+    /// use std::path::PathBuf;
+    ///
+    /// let model = ZeroShotImageClassificationModel::new(
+    ///     ZeroShotImageClassificationConfig::clip_vit_base_patch32(LocalResource::from(PathBuf::from(
+    ///         "path/to/rust_model.ot",
+    ///     ))),
+    /// )?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn new(
+        config: ZeroShotImageClassificationConfig,
+    ) -> Result<ZeroShotImageClassificationModel, RustBertError> {
+        let config_path = config.config_resource.get_local_path()?;
+        let vocab_path = config.vocab_resource.get_local_path()?;
+        let merges_path = config.merges_resource.get_local_path()?;
+        let model_config = ClipConfig::from_file(config_path);
+        if model_config.vision_config.num_channels != 3 {
+            return Err(RustBertError::InvalidConfigurationError(format!(
+                "Image classification models must take RGB images, got {} channels",
+                model_config.vision_config.num_channels
+            )));
+        }
+
+        let tokenizer =
+            ClipTokenizer::from_file(vocab_path.to_str().unwrap(), merges_path.to_str().unwrap())?;
+        let mut var_store = VarStore::new(config.device);
+        let model = ClipModel::new(&var_store.root(), &model_config);
+        crate::resources::load_weights(&config.model_resource, &mut var_store)?;
+        let image_processor = ViTImageProcessor::new(
+            model_config.vision_config.image_size,
+            CLIP_IMAGE_MEAN,
+            CLIP_IMAGE_STD,
+            config.device,
+        )
+        .with_center_crop(true);
+
+        Ok(ZeroShotImageClassificationModel {
+            model,
+            tokenizer,
+            image_processor,
+            max_length: model_config.text_config.max_position_embeddings as usize,
+            batch_size: 32,
+            var_store,
+        })
+    }
+
+    /// Sets the number of images or texts embedded at once
+    ///
+    /// # Arguments
+    ///
+    /// * `batch_size` - Maximum number of images or texts in a batch (default: 32)
+    pub fn set_batch_size(&mut self, batch_size: usize) {
+        self.batch_size = batch_size.max(1);
+    }
+
+    /// Computes the normalized image embeddings, of shape (*number of images*, *projection_dim*)
+    fn image_features(&self, images: &[ImageBuffer]) -> Result<Tensor, RustBertError> {
+        let mut features = Vec::with_capacity(images.len() / self.batch_size + 1);
+        for batch in images.chunks(self.batch_size) {
+            let pixel_values = self.image_processor.preprocess(batch)?;
+            let embeddings = no_grad(|| self.model.get_image_features(&pixel_values, false))?;
+            features.push(normalize(&embeddings).to(Device::Cpu));
+        }
+        Ok(Tensor::cat(&features, 0))
+    }
+
+    /// Computes the normalized text embeddings, of shape (*number of texts*, *projection_dim*)
+    fn text_features<S: AsRef<str>>(&self, texts: &[S]) -> Result<Tensor, RustBertError> {
+        let device = self.var_store.device();
+        let mut features = Vec::with_capacity(texts.len() / self.batch_size + 1);
+        for batch in texts.chunks(self.batch_size) {
+            let token_ids = batch
+                .iter()
+                .map(|text| self.tokenizer.encode(text.as_ref(), self.max_length))
+                .collect::<Vec<Vec<i64>>>();
+            let sequence_length = token_ids.iter().map(Vec::len).max().unwrap_or(0);
+            // Sequences are padded with the end of text token, the text embedding is taken at its first occurrence
+            let (input_ids, attention_mask): (Vec<Tensor>, Vec<Tensor>) = token_ids
+                .iter()
+                .map(|ids| {
+                    let mut padded_ids = ids.clone();
+                    padded_ids.resize(sequence_length, self.tokenizer.eos_token_id());
+                    let mut mask = vec![1i64; ids.len()];
+                    mask.resize(sequence_length, 0);
+                    (Tensor::of_slice(&padded_ids), Tensor::of_slice(&mask))
+                })
+                .unzip();
+            let input_ids = Tensor::stack(&input_ids, 0).to(device);
+            let attention_mask = Tensor::stack(&attention_mask, 0).to(device);
+            let embeddings = no_grad(|| {
+                self.model
+                    .get_text_features(&input_ids, Some(&attention_mask), false)
+            })?;
+            features.push(normalize(&embeddings).to(Device::Cpu));
+        }
+        Ok(Tensor::cat(&features, 0))
+    }
+
+    /// Embeds images in the shared image-text space
+    ///
+    /// # Arguments
+    ///
+    /// * `images` - Slice of decoded images
+    ///
+    /// # Returns
+    ///
+    /// * `Vec<Embedding>` normalized embedding of each image
+    pub fn encode_images(&self, images: &[ImageBuffer]) -> Result<Vec<Embedding>, RustBertError> {
+        if images.is_empty() {
+            return Ok(vec![]);
+        }
+        Ok(Vec::<Embedding>::from(
+            self.image_features(images)?.to_kind(Kind::Float),
+        ))
+    }
+
+    /// Embeds texts in the shared image-text space
+    ///
+    /// # Arguments
+    ///
+    /// * `texts` - Slice of texts (truncated to the maximum length of the text encoder)
+    ///
+    /// # Returns
+    ///
+    /// * `Vec<Embedding>` normalized embedding of each text
+    pub fn encode_texts<S: AsRef<str>>(
+        &self,
+        texts: &[S],
+    ) -> Result<Vec<Embedding>, RustBertError> {
+        if texts.is_empty() {
+            return Ok(vec![]);
+        }
+        Ok(Vec::<Embedding>::from(
+            self.text_features(texts)?.to_kind(Kind::Float),
+        ))
+    }
+
+    /// Classifies images against a list of candidate labels
+    ///
+    /// # Arguments
+    ///
+    /// * `images` - Slice of decoded images
+    /// * `labels` - Candidate labels, one of which is assumed to describe each image
+    /// * `template` - Optional closure building the text embedded for a label (default: `a photo of a {}.`)
+    ///
+    /// # Returns
+    ///
+    /// * `Vec<Vec<Label>>` containing the candidate labels of each image sorted by decreasing probability. The `id`
+    /// field of the labels holds the index of the candidate label and the `sentence` field the index of the image.
+    ///
+    /// # Example
+    ///
+    /// ```no_run
+    /// # fn main() -> anyhow::Result<()> {
+    /// use rust_bert::pipelines::zero_shot_image_classification::{
+    ///     ZeroShotImageClassificationConfig, ZeroShotImageClassificationModel,
+    /// };
+    /// use rust_bert::resources::LocalResource;
+    /// use rust_bert::vit::ImageBuffer;
+    /// use std::path::PathBuf;
+    ///
+    /// let model = ZeroShotImageClassificationModel::new(
+    ///     ZeroShotImageClassificationConfig::clip_vit_base_patch32(LocalResource::from(PathBuf::from(
+    ///         "path/to/rust_model.ot",
+    ///     ))),
+    /// )?;
+    /// let pixels = vec![0u8; 32 * 32 * 3];
+    /// let predictions = model.predict(
+    ///     &[ImageBuffer::rgb(&pixels, 32, 32)],
+    ///     &["day", "night"],
+    ///     Some(Box::new(|label: &str| format!("a photo taken at {}.", label))),
+    /// )?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn predict<S: AsRef<str>>(
+        &self,
+        images: &[ImageBuffer],
+        labels: &[S],
+        template: Option<Box<dyn Fn(&str) -> String>>,
+    ) -> Result<Vec<Vec<Label>>, RustBertError> {
+        if labels.is_empty() {
+            return Err(RustBertError::ValueError(
+                "At least one candidate label must be provided".to_string(),
+            ));
+        }
+        if images.is_empty() {
+            return Ok(vec![]);
+        }
+        let template = template.unwrap_or_else(|| Box::new(default_template));
+        let texts = labels
+            .iter()
+            .map(|label| template(label.as_ref()))
+            .collect::<Vec<String>>();
+
+        let text_features = self.text_features(&texts)?;
+        let image_features = self.image_features(images)?;
+        let probabilities = (image_features.matmul(&text_features.tr()) * self.model.logit_scale())
+            .softmax(-1, Kind::Float);
+
+        let mut output = Vec::with_capacity(images.len());
+        for image_index in 0..images.len() {
+            let scores = Vec::<f64>::from(probabilities.get(image_index as i64));
+            let mut image_labels = scores
+                .into_iter()
+                .enumerate()
+                .map(|(label_index, score)| Label {
+                    text: labels[label_index].as_ref().to_string(),
+                    score,
+                    id: label_index as i64,
+                    sentence: image_index,
+                })
+                .collect::<Vec<Label>>();
+            image_labels.sort_by(|a, b| b.score.partial_cmp(&a.score).unwrap_or(Ordering::Equal));
+            output.push(image_labels);
+        }
+        Ok(output)
+    }
+
+    /// Returns the device on which the model is placed
+    pub fn device(&self) -> Device {
+        self.var_store.device()
+    }
+}
+
+fn normalize(embeddings: &Tensor) -> Tensor {
+    embeddings
+        / embeddings
+            .norm_scalaropt_dim(2, &[-1], true)
+            .clamp_min(1e-12)
+}
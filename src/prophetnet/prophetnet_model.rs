@@ -631,6 +631,7 @@ impl LMHeadModel for ProphetNetForConditionalGeneration {
         Ok(LMModelOutput {
             lm_logits: base_model_output.logits,
             cache: Cache::ProphetNetCache(base_model_output.next_decoder_cache),
+            hidden_states: None,
         })
     }
 }
@@ -959,14 +960,19 @@ impl ProphetNetConditionalGenerator {
         tokenizer: TokenizerOption,
     ) -> Result<ProphetNetConditionalGenerator, RustBertError> {
         let config_path = generate_config.config_resource.get_local_path()?;
-        let weights_path = generate_config.model_resource.get_local_path()?;
         let device = generate_config.device;
 
         generate_config.validate();
+        if generate_config.uses_contrastive_search() {
+            return Err(RustBertError::InvalidConfigurationError(
+                "Contrastive search (penalty_alpha) is not available for ProphetNet, which does not expose its hidden states"
+                    .into(),
+            ));
+        }
         let mut var_store = nn::VarStore::new(device);
         let config = ProphetNetConfig::from_file(config_path);
         let model = ProphetNetForConditionalGeneration::new(&var_store.root(), &config)?;
-        var_store.load(weights_path)?;
+        crate::resources::load_weights(&generate_config.model_resource, &mut var_store)?;
 
         let bos_token_id = Some(config.bos_token_id);
         let eos_token_ids = Some(vec![config.eos_token_id]);
@@ -1035,6 +1041,9 @@ impl
     fn get_max_positions_embeddings(&self) -> i64 {
         self.max_position_embeddings
     }
+    fn exposes_hidden_states(&self) -> bool {
+        false
+    }
 
     fn encode(&self, input_ids: &Tensor, attention_mask: Option<&Tensor>) -> Option<Tensor> {
         Some(
@@ -1077,6 +1086,7 @@ impl
         prompt_text: &[S],
         max_len: i64,
         pad_token_id: Option<i64>,
+        _add_bos_token: bool,
     ) -> Tensor
     where
         S: AsRef<str> + Sync,
@@ -683,6 +683,7 @@ impl LMHeadModel for ReformerModelWithLMHead {
         Ok(LMModelOutput {
             lm_logits: output.logits,
             cache: Cache::ReformerCache(output.next_cache),
+            hidden_states: None,
         })
     }
 }
@@ -1085,14 +1086,19 @@ impl ReformerGenerator {
         tokenizer: TokenizerOption,
     ) -> Result<ReformerGenerator, RustBertError> {
         let config_path = generate_config.config_resource.get_local_path()?;
-        let weights_path = generate_config.model_resource.get_local_path()?;
         let device = generate_config.device;
 
         generate_config.validate();
+        if generate_config.uses_contrastive_search() {
+            return Err(RustBertError::InvalidConfigurationError(
+                "Contrastive search (penalty_alpha) is not available for Reformer, which does not expose its hidden states"
+                    .into(),
+            ));
+        }
         let mut var_store = nn::VarStore::new(device);
         let config = ReformerConfig::from_file(config_path);
         let model = ReformerModelWithLMHead::new(&var_store.root(), &config)?;
-        var_store.load(weights_path)?;
+        crate::resources::load_weights(&generate_config.model_resource, &mut var_store)?;
 
         let bos_token_id = tokenizer.get_bos_id();
         let eos_token_ids = tokenizer.get_eos_id().map(|id| vec![id]);
@@ -1157,6 +1163,9 @@ impl PrivateLanguageGenerator<ReformerModelWithLMHead, ReformerVocab, ReformerTo
     fn get_max_positions_embeddings(&self) -> i64 {
         self.max_position_embeddings
     }
+    fn exposes_hidden_states(&self) -> bool {
+        false
+    }
 
     fn prepare_inputs_for_generation<'a>(
         &self,
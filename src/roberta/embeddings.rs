@@ -26,7 +26,7 @@ pub struct RobertaEmbeddings {
     word_embeddings: nn::Embedding,
     position_embeddings: nn::Embedding,
     token_type_embeddings: nn::Embedding,
-    layer_norm: nn::LayerNorm,
+    layer_norm: Option<nn::LayerNorm>,
     dropout: Dropout,
     padding_index: i64,
 }
@@ -104,12 +104,14 @@ impl BertEmbedding for RobertaEmbeddings {
             Default::default(),
         );
 
-        let layer_norm_config = nn::LayerNormConfig {
-            eps: 1e-12,
-            ..Default::default()
-        };
-        let layer_norm: nn::LayerNorm =
-            nn::layer_norm(p / "LayerNorm", vec![config.hidden_size], layer_norm_config);
+        // Pre-norm models (XLM-RoBERTa-XL) normalize the embeddings in the first layer of the encoder
+        let layer_norm = (!config.is_pre_layer_norm()).then(|| {
+            let layer_norm_config = nn::LayerNormConfig {
+                eps: 1e-12,
+                ..Default::default()
+            };
+            nn::layer_norm(p / "LayerNorm", vec![config.hidden_size], layer_norm_config)
+        });
         let dropout: Dropout = Dropout::new(config.hidden_dropout_prob);
         RobertaEmbeddings {
             word_embeddings,
@@ -210,8 +212,10 @@ impl BertEmbedding for RobertaEmbeddings {
 
         let input_embeddings: Tensor =
             input_embeddings + position_embeddings + token_type_embeddings;
-        Ok(input_embeddings
-            .apply(&self.layer_norm)
-            .apply_t(&self.dropout, train))
+        let input_embeddings = match &self.layer_norm {
+            Some(layer_norm) => input_embeddings.apply(layer_norm),
+            None => input_embeddings,
+        };
+        Ok(input_embeddings.apply_t(&self.dropout, train))
     }
 }
@@ -15,7 +15,9 @@ use crate::bert::{BertConfig, BertModel};
 use crate::common::activations::_gelu;
 use crate::common::dropout::Dropout;
 use crate::common::linear::{linear_no_bias, LinearNoBias};
+use crate::common::pruning::PrunableModel;
 use crate::roberta::embeddings::RobertaEmbeddings;
+use crate::RustBertError;
 use std::borrow::Borrow;
 use tch::nn::Init;
 use tch::{nn, Tensor};
@@ -111,6 +113,11 @@ impl RobertaConfigResources {
         "xlm-roberta-ner-es/config",
         "https://huggingface.co/xlm-roberta-large-finetuned-conll02-spanish/resolve/main/config.json",
     );
+    /// Shared under MIT license by Joe Davison at <https://huggingface.co/joeddav/xlm-roberta-large-xnli>. Modified with conversion to C-array format.
+    pub const XLM_ROBERTA_LARGE_XNLI: (&'static str, &'static str) = (
+        "xlm-roberta-large-xnli/config",
+        "https://huggingface.co/joeddav/xlm-roberta-large-xnli/resolve/main/config.json",
+    );
     /// Shared under Apache 2.0 licenseat <https://huggingface.co/sentence-transformers/all-distilroberta-v1>. Modified with conversion to C-array format.
     pub const ALL_DISTILROBERTA_V1: (&'static str, &'static str) = (
         "all-distilroberta-v1/config",
@@ -154,6 +161,11 @@ impl RobertaVocabResources {
         "xlm-roberta-ner-es/spiece",
         "https://huggingface.co/xlm-roberta-large-finetuned-conll02-spanish/resolve/main/sentencepiece.bpe.model",
     );
+    /// Shared under MIT license by Joe Davison at <https://huggingface.co/joeddav/xlm-roberta-large-xnli>. Modified with conversion to C-array format.
+    pub const XLM_ROBERTA_LARGE_XNLI: (&'static str, &'static str) = (
+        "xlm-roberta-large-xnli/spiece",
+        "https://huggingface.co/joeddav/xlm-roberta-large-xnli/resolve/main/sentencepiece.bpe.model",
+    );
     /// Shared under Apache 2.0 licenseat <https://huggingface.co/sentence-transformers/all-distilroberta-v1>. Modified with conversion to C-array format.
     pub const ALL_DISTILROBERTA_V1: (&'static str, &'static str) = (
         "all-distilroberta-v1/vocab",
@@ -373,6 +385,36 @@ impl RobertaForMaskedLM {
     }
 }
 
+impl PrunableModel for RobertaForMaskedLM {
+    fn num_layers(&self) -> usize {
+        self.roberta.num_layers()
+    }
+
+    fn drop_top_layers(&mut self, num_layers: usize) -> Result<(), RustBertError> {
+        self.roberta.drop_top_layers(num_layers)
+    }
+
+    fn retain_attention_heads(
+        &mut self,
+        layer_index: usize,
+        heads: &[i64],
+    ) -> Result<(), RustBertError> {
+        self.roberta.retain_attention_heads(layer_index, heads)
+    }
+
+    fn num_attention_heads(&self, layer_index: usize) -> Result<usize, RustBertError> {
+        self.roberta.num_attention_heads(layer_index)
+    }
+
+    fn set_head_mask(
+        &mut self,
+        layer_index: usize,
+        head_mask: Option<Tensor>,
+    ) -> Result<(), RustBertError> {
+        self.roberta.set_head_mask(layer_index, head_mask)
+    }
+}
+
 pub struct RobertaClassificationHead {
     dense: nn::Linear,
     dropout: Dropout,
@@ -554,6 +596,36 @@ impl RobertaForSequenceClassification {
     }
 }
 
+impl PrunableModel for RobertaForSequenceClassification {
+    fn num_layers(&self) -> usize {
+        self.roberta.num_layers()
+    }
+
+    fn drop_top_layers(&mut self, num_layers: usize) -> Result<(), RustBertError> {
+        self.roberta.drop_top_layers(num_layers)
+    }
+
+    fn retain_attention_heads(
+        &mut self,
+        layer_index: usize,
+        heads: &[i64],
+    ) -> Result<(), RustBertError> {
+        self.roberta.retain_attention_heads(layer_index, heads)
+    }
+
+    fn num_attention_heads(&self, layer_index: usize) -> Result<usize, RustBertError> {
+        self.roberta.num_attention_heads(layer_index)
+    }
+
+    fn set_head_mask(
+        &mut self,
+        layer_index: usize,
+        head_mask: Option<Tensor>,
+    ) -> Result<(), RustBertError> {
+        self.roberta.set_head_mask(layer_index, head_mask)
+    }
+}
+
 /// # RoBERTa for multiple choices
 /// Multiple choices model using a RoBERTa base model and a linear classifier.
 /// Input should be in the form `<s> Context </s> Possible choice </s>`. The choice is made along the batch axis,
@@ -699,6 +771,36 @@ impl RobertaForMultipleChoice {
     }
 }
 
+impl PrunableModel for RobertaForMultipleChoice {
+    fn num_layers(&self) -> usize {
+        self.roberta.num_layers()
+    }
+
+    fn drop_top_layers(&mut self, num_layers: usize) -> Result<(), RustBertError> {
+        self.roberta.drop_top_layers(num_layers)
+    }
+
+    fn retain_attention_heads(
+        &mut self,
+        layer_index: usize,
+        heads: &[i64],
+    ) -> Result<(), RustBertError> {
+        self.roberta.retain_attention_heads(layer_index, heads)
+    }
+
+    fn num_attention_heads(&self, layer_index: usize) -> Result<usize, RustBertError> {
+        self.roberta.num_attention_heads(layer_index)
+    }
+
+    fn set_head_mask(
+        &mut self,
+        layer_index: usize,
+        head_mask: Option<Tensor>,
+    ) -> Result<(), RustBertError> {
+        self.roberta.set_head_mask(layer_index, head_mask)
+    }
+}
+
 /// # RoBERTa for token classification (e.g. NER, POS)
 /// Token-level classifier predicting a label for each token provided. Note that because of bpe tokenization, the labels predicted are
 /// not necessarily aligned with words in the sentence.
@@ -847,6 +949,36 @@ impl RobertaForTokenClassification {
     }
 }
 
+impl PrunableModel for RobertaForTokenClassification {
+    fn num_layers(&self) -> usize {
+        self.roberta.num_layers()
+    }
+
+    fn drop_top_layers(&mut self, num_layers: usize) -> Result<(), RustBertError> {
+        self.roberta.drop_top_layers(num_layers)
+    }
+
+    fn retain_attention_heads(
+        &mut self,
+        layer_index: usize,
+        heads: &[i64],
+    ) -> Result<(), RustBertError> {
+        self.roberta.retain_attention_heads(layer_index, heads)
+    }
+
+    fn num_attention_heads(&self, layer_index: usize) -> Result<usize, RustBertError> {
+        self.roberta.num_attention_heads(layer_index)
+    }
+
+    fn set_head_mask(
+        &mut self,
+        layer_index: usize,
+        head_mask: Option<Tensor>,
+    ) -> Result<(), RustBertError> {
+        self.roberta.set_head_mask(layer_index, head_mask)
+    }
+}
+
 /// # RoBERTa for question answering
 /// Extractive question-answering model based on a RoBERTa language model. Identifies the segment of a context that answers a provided question.
 /// Please note that a significant amount of pre- and post-processing is required to perform end-to-end question answering.
@@ -992,6 +1124,36 @@ impl RobertaForQuestionAnswering {
     }
 }
 
+impl PrunableModel for RobertaForQuestionAnswering {
+    fn num_layers(&self) -> usize {
+        self.roberta.num_layers()
+    }
+
+    fn drop_top_layers(&mut self, num_layers: usize) -> Result<(), RustBertError> {
+        self.roberta.drop_top_layers(num_layers)
+    }
+
+    fn retain_attention_heads(
+        &mut self,
+        layer_index: usize,
+        heads: &[i64],
+    ) -> Result<(), RustBertError> {
+        self.roberta.retain_attention_heads(layer_index, heads)
+    }
+
+    fn num_attention_heads(&self, layer_index: usize) -> Result<usize, RustBertError> {
+        self.roberta.num_attention_heads(layer_index)
+    }
+
+    fn set_head_mask(
+        &mut self,
+        layer_index: usize,
+        head_mask: Option<Tensor>,
+    ) -> Result<(), RustBertError> {
+        self.roberta.set_head_mask(layer_index, head_mask)
+    }
+}
+
 /// # RoBERTa for sentence embeddings
 /// Transformer usable in [`SentenceEmbeddingsModel`](crate::pipelines::sentence_embeddings::SentenceEmbeddingsModel).
 pub type RobertaForSentenceEmbeddings = BertModel<RobertaEmbeddings>;
@@ -11,7 +11,7 @@
 // limitations under the License.
 
 use crate::common::dropout::Dropout;
-use crate::t5::layer_norm::T5LayerNorm;
+use crate::common::normalization::{NormConfig, RMSNorm};
 use crate::t5::T5Config;
 use std::borrow::Borrow;
 use tch::nn::LinearConfig;
@@ -297,7 +297,7 @@ impl T5Attention {
 
 pub struct T5LayerSelfAttention {
     self_attention: T5Attention,
-    layer_norm: T5LayerNorm,
+    layer_norm: RMSNorm,
     dropout: Dropout,
 }
 
@@ -325,8 +325,14 @@ impl T5LayerSelfAttention {
             has_relative_attention_bias,
         );
 
-        let layer_norm =
-            T5LayerNorm::new(p / "layer_norm", config.d_model, config.layer_norm_epsilon);
+        let layer_norm = RMSNorm::new(
+            p / "layer_norm",
+            config.d_model,
+            NormConfig {
+                eps: config.layer_norm_epsilon,
+                ..Default::default()
+            },
+        );
         let dropout = Dropout::new(config.dropout_rate);
 
         T5LayerSelfAttention {
@@ -363,7 +369,7 @@ impl T5LayerSelfAttention {
 
 pub struct T5LayerCrossAttention {
     encoder_decoder_attention: T5Attention,
-    layer_norm: T5LayerNorm,
+    layer_norm: RMSNorm,
     dropout: Dropout,
 }
 
@@ -391,8 +397,14 @@ impl T5LayerCrossAttention {
             has_relative_attention_bias,
         );
 
-        let layer_norm =
-            T5LayerNorm::new(p / "layer_norm", config.d_model, config.layer_norm_epsilon);
+        let layer_norm = RMSNorm::new(
+            p / "layer_norm",
+            config.d_model,
+            NormConfig {
+                eps: config.layer_norm_epsilon,
+                ..Default::default()
+            },
+        );
         let dropout = Dropout::new(config.dropout_rate);
 
         T5LayerCrossAttention {
@@ -0,0 +1,341 @@
+// Copyright 2021 Google Research and The HuggingFace Inc. team.
+// Copyright 2022 Guillaume Becquin
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//     http://www.apache.org/licenses/LICENSE-2.0
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use rust_tokenizers::error::TokenizerError;
+use rust_tokenizers::tokenizer::{MultiThreadedTokenizer, Tokenizer};
+use rust_tokenizers::vocab::Vocab;
+use rust_tokenizers::{
+    Mask, Offset, OffsetSize, Token, TokenIdsWithOffsets, TokenIdsWithSpecialTokens, TokenRef,
+};
+use std::collections::HashMap;
+
+/// Number of sentinel tokens (`<extra_id_0>` to `<extra_id_124>`) of the ByT5 vocabulary
+pub const BYT5_NUM_EXTRA_IDS: i64 = 125;
+
+/// Id of the first byte (the bytes follow the padding, end of sequence and unknown tokens)
+const BYTE_OFFSET: i64 = 3;
+
+/// # ByT5 vocabulary
+/// Fixed vocabulary made of the 3 special tokens (`<pad>`, `</s>`, `<unk>`), the 256 byte values and the sentinel tokens
+/// used for span corruption, numbered in reverse order from the end of the vocabulary as for T5 (`<extra_id_0>` = 383).
+/// Each byte is represented by the character of the same code point (`0x41` by `A`, `0xC3` by `Ã`).
+#[derive(Debug, Clone)]
+pub struct ByT5Vocab {
+    pub values: HashMap<String, i64>,
+    pub indices: HashMap<i64, String>,
+    pub special_values: HashMap<String, i64>,
+    pub special_indices: HashMap<i64, String>,
+}
+
+impl ByT5Vocab {
+    pub fn new() -> ByT5Vocab {
+        let mut special_values = HashMap::new();
+        special_values.insert(Self::pad_value().to_string(), 0);
+        special_values.insert(Self::eos_value().to_string(), 1);
+        special_values.insert(Self::unknown_value().to_string(), 2);
+        let vocab_size = BYTE_OFFSET + 256 + BYT5_NUM_EXTRA_IDS;
+        for extra_id in 0..BYT5_NUM_EXTRA_IDS {
+            special_values.insert(
+                format!("<extra_id_{}>", extra_id),
+                vocab_size - extra_id - 1,
+            );
+        }
+
+        let mut values = special_values.clone();
+        for byte in 0..=255u8 {
+            values.insert(char::from(byte).to_string(), byte as i64 + BYTE_OFFSET);
+        }
+        let indices = values.iter().map(|(k, v)| (*v, k.clone())).collect();
+        let special_indices = special_values
+            .iter()
+            .map(|(k, v)| (*v, k.clone()))
+            .collect();
+
+        ByT5Vocab {
+            values,
+            indices,
+            special_values,
+            special_indices,
+        }
+    }
+
+    pub fn eos_value() -> &'static str {
+        "</s>"
+    }
+
+    pub fn pad_value() -> &'static str {
+        "<pad>"
+    }
+}
+
+impl Default for ByT5Vocab {
+    fn default() -> Self {
+        ByT5Vocab::new()
+    }
+}
+
+impl Vocab for ByT5Vocab {
+    fn unknown_value() -> &'static str {
+        "<unk>"
+    }
+
+    fn get_unknown_value(&self) -> &'static str {
+        "<unk>"
+    }
+
+    fn values(&self) -> &HashMap<String, i64> {
+        &self.values
+    }
+
+    fn indices(&self) -> &HashMap<i64, String> {
+        &self.indices
+    }
+
+    fn special_values(&self) -> &HashMap<String, i64> {
+        &self.special_values
+    }
+
+    fn special_indices(&self) -> &HashMap<i64, String> {
+        &self.special_indices
+    }
+
+    /// The ByT5 vocabulary is fixed: no file is read and the path is ignored
+    fn from_file(_path: &str) -> Result<ByT5Vocab, TokenizerError> {
+        Ok(ByT5Vocab::new())
+    }
+
+    fn token_to_id(&self, token: &str) -> i64 {
+        self._token_to_id(
+            token,
+            &self.values,
+            &self.special_values,
+            Self::unknown_value(),
+        )
+    }
+
+    fn id_to_token(&self, id: &i64) -> String {
+        self._id_to_token(
+            id,
+            &self.indices,
+            &self.special_indices,
+            Self::unknown_value(),
+        )
+    }
+}
+
+/// # ByT5 tokenizer
+/// Byte-level tokenizer of the ByT5 models: the text is split in the bytes of its UTF-8 encoding, without
+/// SentencePiece model, normalization or vocabulary file. Misspellings, code, URLs or any script are encoded without
+/// unknown tokens. Special tokens (`</s>`, `<extra_id_0>`...) appearing in the text are kept as single tokens.
+/// The bytes of multi-byte characters are marked with the `Begin` and `Continuation` masks and share the offsets of their character.
+pub struct ByT5Tokenizer {
+    vocab: ByT5Vocab,
+    eos_token_id: i64,
+}
+
+impl ByT5Tokenizer {
+    /// Create a new instance of a `ByT5Tokenizer`
+    ///
+    /// # Example
+    ///
+    /// ```no_run
+    /// use rust_bert::t5::ByT5Tokenizer;
+    /// use rust_tokenizers::tokenizer::{Tokenizer, TruncationStrategy};
+    ///
+    /// let tokenizer = ByT5Tokenizer::new();
+    /// let encoded = tokenizer.encode(
+    ///     "Ths sentnce has typos",
+    ///     None,
+    ///     128,
+    ///     &TruncationStrategy::LongestFirst,
+    ///     0,
+    /// );
+    /// ```
+    pub fn new() -> ByT5Tokenizer {
+        let vocab = ByT5Vocab::new();
+        let eos_token_id = vocab.token_to_id(ByT5Vocab::eos_value());
+        ByT5Tokenizer {
+            vocab,
+            eos_token_id,
+        }
+    }
+
+    fn get_special_token(&self, text: &str) -> Option<&str> {
+        if !text.starts_with('<') {
+            return None;
+        }
+        self.vocab
+            .special_values
+            .keys()
+            .find(|special_token| text.starts_with(special_token.as_str()))
+            .map(String::as_str)
+    }
+
+    fn ends_with_eos(&self, tokens: &TokenIdsWithOffsets) -> bool {
+        tokens.ids.last() == Some(&self.eos_token_id)
+    }
+}
+
+impl Default for ByT5Tokenizer {
+    fn default() -> Self {
+        ByT5Tokenizer::new()
+    }
+}
+
+impl Tokenizer<ByT5Vocab> for ByT5Tokenizer {
+    fn vocab(&self) -> &ByT5Vocab {
+        &self.vocab
+    }
+
+    fn tokenize_to_tokens(&self, text: TokenRef) -> Vec<Token> {
+        let mut tokens = Vec::with_capacity(text.text.len());
+        let mut skipped_characters = 0;
+        for (char_position, (byte_position, character)) in text.text.char_indices().enumerate() {
+            if skipped_characters > 0 {
+                skipped_characters -= 1;
+                continue;
+            }
+            let begin = text.offset.begin + char_position as OffsetSize;
+            if let Some(special_token) = self.get_special_token(&text.text[byte_position..]) {
+                let length = special_token.chars().count();
+                tokens.push(Token {
+                    text: special_token.to_string(),
+                    offset: Offset::new(begin, begin + length as OffsetSize),
+                    reference_offsets: text.reference_offsets
+                        [char_position..char_position + length]
+                        .to_vec(),
+                    mask: Mask::Special,
+                });
+                skipped_characters = length - 1;
+                continue;
+            }
+            let mut buffer = [0u8; 4];
+            let bytes = character.encode_utf8(&mut buffer).as_bytes();
+            for (byte_index, byte) in bytes.iter().enumerate() {
+                tokens.push(Token {
+                    text: char::from(*byte).to_string(),
+                    offset: Offset::new(begin, begin + 1),
+                    reference_offsets: vec![text.reference_offsets[char_position]],
+                    mask: match (bytes.len(), byte_index) {
+                        (1, _) => Mask::None,
+                        (_, 0) => Mask::Begin,
+                        _ => Mask::Continuation,
+                    },
+                });
+            }
+        }
+        tokens
+    }
+
+    fn convert_tokens_to_string(&self, tokens: Vec<String>) -> String {
+        let mut bytes = Vec::with_capacity(tokens.len());
+        for token in tokens {
+            let mut characters = token.chars();
+            match (characters.next(), characters.next()) {
+                (Some(character), None) if (character as u32) < 256 => bytes.push(character as u8),
+                _ => bytes.extend_from_slice(token.as_bytes()),
+            }
+        }
+        String::from_utf8_lossy(&bytes).into_owned()
+    }
+
+    /// The bytes are decoded to the exact original text, no clean-up of the spaces is performed
+    fn clean_up_tokenization(&self, input_string: String) -> String {
+        input_string
+    }
+
+    fn build_input_with_special_tokens(
+        &self,
+        mut tokens_ids_with_offsets_1: TokenIdsWithOffsets,
+        tokens_ids_with_offsets_2: Option<TokenIdsWithOffsets>,
+    ) -> TokenIdsWithSpecialTokens {
+        let mut token_segment_ids: Vec<i8> = vec![0; tokens_ids_with_offsets_1.ids.len()];
+        let mut special_tokens_mask: Vec<i8> = vec![0; tokens_ids_with_offsets_1.ids.len()];
+
+        if !self.ends_with_eos(&tokens_ids_with_offsets_1) {
+            token_segment_ids.push(0);
+            special_tokens_mask.push(1);
+            tokens_ids_with_offsets_1.ids.push(self.eos_token_id);
+            tokens_ids_with_offsets_1.offsets.push(None);
+            tokens_ids_with_offsets_1.reference_offsets.push(vec![]);
+            tokens_ids_with_offsets_1.masks.push(Mask::Special);
+        }
+        if let Some(tokens_ids_with_offsets_2_value) = tokens_ids_with_offsets_2 {
+            let length = tokens_ids_with_offsets_2_value.ids.len();
+            let ends_with_eos = self.ends_with_eos(&tokens_ids_with_offsets_2_value);
+            token_segment_ids.extend(vec![1; length]);
+            special_tokens_mask.extend(vec![0; length]);
+            tokens_ids_with_offsets_1
+                .ids
+                .extend(tokens_ids_with_offsets_2_value.ids);
+            tokens_ids_with_offsets_1
+                .offsets
+                .extend(tokens_ids_with_offsets_2_value.offsets);
+            tokens_ids_with_offsets_1
+                .reference_offsets
+                .extend(tokens_ids_with_offsets_2_value.reference_offsets);
+            tokens_ids_with_offsets_1
+                .masks
+                .extend(tokens_ids_with_offsets_2_value.masks);
+            if !ends_with_eos {
+                token_segment_ids.push(1);
+                special_tokens_mask.push(1);
+                tokens_ids_with_offsets_1.ids.push(self.eos_token_id);
+                tokens_ids_with_offsets_1.offsets.push(None);
+                tokens_ids_with_offsets_1.reference_offsets.push(vec![]);
+                tokens_ids_with_offsets_1.masks.push(Mask::Special);
+            }
+        };
+
+        TokenIdsWithSpecialTokens {
+            token_ids: tokens_ids_with_offsets_1.ids,
+            segment_ids: token_segment_ids,
+            special_tokens_mask,
+            token_offsets: tokens_ids_with_offsets_1.offsets,
+            reference_offsets: tokens_ids_with_offsets_1.reference_offsets,
+            mask: tokens_ids_with_offsets_1.masks,
+        }
+    }
+}
+
+impl MultiThreadedTokenizer<ByT5Vocab> for ByT5Tokenizer {}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use rust_tokenizers::tokenizer::TruncationStrategy;
+
+    #[test]
+    fn byte_level_round_trip() {
+        let tokenizer = ByT5Tokenizer::new();
+        let text = "héllo <extra_id_0> 👍";
+        let encoded = tokenizer.encode(text, None, 128, &TruncationStrategy::LongestFirst, 0);
+
+        //    h, é (2 bytes), l, l, o, space, <extra_id_0>, space, 👍 (4 bytes), </s>
+        assert_eq!(encoded.token_ids.len(), 14);
+        assert_eq!(&encoded.token_ids[..3], &[104 + 3, 0xC3 + 3, 0xA9 + 3]);
+        assert_eq!(encoded.token_ids[7], 383);
+        assert_eq!(*encoded.token_ids.last().unwrap(), 1);
+        assert_eq!(encoded.mask[1], Mask::Begin);
+        assert_eq!(encoded.mask[2], Mask::Continuation);
+        assert_eq!(encoded.token_offsets[2], Some(Offset { begin: 1, end: 2 }));
+
+        assert_eq!(
+            tokenizer.decode(&encoded.token_ids, false, true),
+            format!("{}</s>", text)
+        );
+        assert_eq!(
+            tokenizer.decode(&encoded.token_ids, true, true),
+            "héllo  👍"
+        );
+    }
+}
@@ -11,13 +11,13 @@
 // limitations under the License.
 
 use crate::common::activations::TensorFunction;
+use crate::common::attention_mask::get_query_attention_mask;
 use crate::common::dropout::Dropout;
 use crate::common::embeddings::process_ids_embeddings_pair;
+use crate::common::normalization::{NormConfig, RMSNorm};
 use crate::t5::attention::{LayerState, T5LayerCrossAttention, T5LayerSelfAttention};
-use crate::t5::layer_norm::T5LayerNorm;
 use crate::t5::t5_model::FeedForwardProj;
 use crate::t5::T5Config;
-use crate::Activation::gelu_new;
 use crate::RustBertError;
 use std::borrow::{Borrow, BorrowMut};
 use tch::nn::LinearConfig;
@@ -27,6 +27,7 @@ pub struct T5DenseReluDense {
     wi: nn::Linear,
     wo: nn::Linear,
     dropout: Dropout,
+    activation: TensorFunction,
 }
 
 impl T5DenseReluDense {
@@ -42,14 +43,22 @@ impl T5DenseReluDense {
         let wi = nn::linear(p / "wi", config.d_model, config.d_ff, linear_config);
         let wo = nn::linear(p / "wo", config.d_ff, config.d_model, linear_config);
         let dropout = Dropout::new(config.dropout_rate);
-
-        T5DenseReluDense { wi, wo, dropout }
+        let activation = config
+            .feed_forward_proj
+            .unwrap_or(FeedForwardProj::Relu)
+            .activation()
+            .get_function();
+
+        T5DenseReluDense {
+            wi,
+            wo,
+            dropout,
+            activation,
+        }
     }
 
     pub fn forward_t(&self, hidden_states: &Tensor, train: bool) -> Tensor {
-        hidden_states
-            .apply(&self.wi)
-            .relu()
+        self.activation.get_fn()(&hidden_states.apply(&self.wi))
             .apply_t(&self.dropout, train)
             .apply(&self.wo)
     }
@@ -77,7 +86,11 @@ impl T5DenseGatedGeluDense {
         let wi_1 = nn::linear(p / "wi_1", config.d_model, config.d_ff, linear_config);
         let wo = nn::linear(p / "wo", config.d_ff, config.d_model, linear_config);
         let dropout = Dropout::new(config.dropout_rate);
-        let activation = gelu_new.get_function();
+        let activation = config
+            .feed_forward_proj
+            .unwrap_or(FeedForwardProj::GatedGelu)
+            .activation()
+            .get_function();
 
         T5DenseGatedGeluDense {
             wi_0,
@@ -107,13 +120,14 @@ impl T5FeedForwardLayer {
     where
         P: Borrow<nn::Path<'p>>,
     {
-        match config.feed_forward_proj.unwrap_or(FeedForwardProj::Relu) {
-            FeedForwardProj::Relu => {
-                T5FeedForwardLayer::T5DenseReluDense(T5DenseReluDense::new(p, config))
-            }
-            FeedForwardProj::GatedGelu => {
-                T5FeedForwardLayer::T5DenseGatedGeluDense(T5DenseGatedGeluDense::new(p, config))
-            }
+        if config
+            .feed_forward_proj
+            .unwrap_or(FeedForwardProj::Relu)
+            .is_gated()
+        {
+            T5FeedForwardLayer::T5DenseGatedGeluDense(T5DenseGatedGeluDense::new(p, config))
+        } else {
+            T5FeedForwardLayer::T5DenseReluDense(T5DenseReluDense::new(p, config))
         }
     }
 
@@ -131,7 +145,7 @@ impl T5FeedForwardLayer {
 
 pub struct T5LayerFF {
     forward_layer: T5FeedForwardLayer,
-    layer_norm: T5LayerNorm,
+    layer_norm: RMSNorm,
     dropout: Dropout,
 }
 
@@ -143,8 +157,14 @@ impl T5LayerFF {
         let p = p.borrow();
 
         let forward_layer = T5FeedForwardLayer::new(p / "DenseReluDense", config);
-        let layer_norm =
-            T5LayerNorm::new(p / "layer_norm", config.d_model, config.layer_norm_epsilon);
+        let layer_norm = RMSNorm::new(
+            p / "layer_norm",
+            config.d_model,
+            NormConfig {
+                eps: config.layer_norm_epsilon,
+                ..Default::default()
+            },
+        );
         let dropout = Dropout::new(config.dropout_rate);
 
         T5LayerFF {
@@ -300,7 +320,7 @@ impl T5Block {
 
 pub struct T5Stack {
     blocks: Vec<T5Block>,
-    final_layer_norm: T5LayerNorm,
+    final_layer_norm: RMSNorm,
     dropout: Dropout,
     output_attentions: bool,
     output_hidden_states: bool,
@@ -323,9 +343,14 @@ impl T5Stack {
         let p = p.borrow();
         let dropout = Dropout::new(config.dropout_rate);
 
+        let num_layers = if is_decoder {
+            config.num_decoder_layers.unwrap_or(config.num_layers)
+        } else {
+            config.num_layers
+        };
         let mut blocks: Vec<T5Block> = vec![];
         let p_layers = p / "block";
-        for layer_index in 0..config.num_layers {
+        for layer_index in 0..num_layers {
             blocks.push(T5Block::new(
                 &p_layers / layer_index,
                 config,
@@ -336,10 +361,13 @@ impl T5Stack {
             ));
         }
 
-        let final_layer_norm = T5LayerNorm::new(
+        let final_layer_norm = RMSNorm::new(
             p / "final_layer_norm",
             config.d_model,
-            config.layer_norm_epsilon,
+            NormConfig {
+                eps: config.layer_norm_epsilon,
+                ..Default::default()
+            },
         );
 
         T5Stack {
@@ -400,7 +428,17 @@ impl T5Stack {
             None => calculated_attention_mask.as_ref().unwrap(),
         };
         let extended_attention_mask = match attention_mask.dim() {
-            3 => attention_mask.unsqueeze(1),
+            4 => get_query_attention_mask(
+                attention_mask,
+                sequence_length,
+                mask_seq_length - sequence_length,
+            )?,
+            3 => get_query_attention_mask(
+                attention_mask,
+                sequence_length,
+                mask_seq_length - sequence_length,
+            )?
+            .unsqueeze(1),
             2 => {
                 if self.is_decoder {
                     let seq_ids = Tensor::arange(
@@ -420,7 +458,7 @@ impl T5Stack {
             }
             _ => {
                 return Err(RustBertError::ValueError(
-                    "Invalid attention mask dimension, must be 2 or 3".into(),
+                    "Invalid attention mask dimension, must be 2, 3 or 4".into(),
                 ));
             }
         };
@@ -469,6 +507,12 @@ impl T5Stack {
         } else {
             None
         };
+        let mut all_cross_attentions: Option<Vec<Tensor>> =
+            if self.output_attentions & encoder_hidden_states.is_some() {
+                Some(Vec::with_capacity(self.blocks.len()))
+            } else {
+                None
+            };
         let mut next_cache: Option<Vec<(Option<LayerState>, Option<LayerState>)>> =
             if self.store_cache {
                 if old_layer_states.is_some() {
@@ -481,7 +525,6 @@ impl T5Stack {
             };
         let mut position_bias = None;
         let mut encoder_decoder_position_bias = None;
-        let mut attention_weights: Option<Tensor>;
         let mut hidden_state = input_embeddings.apply_t(&self.dropout, train);
 
         for (layer_idx, layer) in self.blocks.iter().enumerate() {
@@ -504,12 +547,20 @@ impl T5Stack {
                 encoder_decoder_position_bias = block_output.cross_attention_position_bias;
             }
             hidden_state = block_output.hidden_states;
-            attention_weights = block_output.cross_attention_weights;
             if let Some(hidden_states) = all_hidden_states.borrow_mut() {
                 hidden_states.push(hidden_state.as_ref().copy().transpose(0, 1));
             };
             if let Some(attentions) = all_attentions.borrow_mut() {
-                attentions.push(attention_weights.as_ref().unwrap().copy());
+                attentions.push(block_output.self_attention_weights.as_ref().unwrap().copy());
+            };
+            if let Some(cross_attentions) = all_cross_attentions.borrow_mut() {
+                cross_attentions.push(
+                    block_output
+                        .cross_attention_weights
+                        .as_ref()
+                        .unwrap()
+                        .copy(),
+                );
             };
             if let Some(value) = &mut next_cache {
                 value[layer_idx] = block_output.cache
@@ -524,6 +575,7 @@ impl T5Stack {
             hidden_state,
             all_hidden_states,
             all_attentions,
+            all_cross_attentions,
             next_cache,
         })
     }
@@ -542,5 +594,6 @@ pub struct T5StackOutput {
     pub hidden_state: Tensor,
     pub all_hidden_states: Option<Vec<Tensor>>,
     pub all_attentions: Option<Vec<Tensor>>,
+    pub all_cross_attentions: Option<Vec<Tensor>>,
     pub next_cache: Option<Vec<(Option<LayerState>, Option<LayerState>)>>,
 }
@@ -3,6 +3,13 @@
 //! Implementation of the T5 language model ([Exploring the Limits of Transfer Learning with a Unified Text-to-Text Transformer](https://arxiv.org/abs/1910.10683) Raffel, Shazeer, Roberts, Lee, Narang, Matena, Zhou, Li, Liu, 2019).
 //! The base model is implemented in the `t5_model::T5Model` struct. This model includes a language model head: `t5_model::T5ForConditionalGeneration`
 //! implementing the common `generation_utils::LMHeadModel` trait shared between the models used for generation (see `pipelines` for more information).
+//! The T5 v1.1 variants, including the instruction-tuned Flan-T5 and the multilingual mT5 checkpoints, are supported through their configuration:
+//! gated feed-forward layers (`feed_forward_proj: "gated-gelu"`), a language model head not tied to the embeddings (`tie_word_embeddings: false`)
+//! and an optional different number of decoder layers (`num_decoder_layers`). Configuration and SentencePiece resources are available
+//! for Flan-T5 (e.g. `T5ConfigResources::FLAN_T5_BASE`). No converted Flan-T5 weights are hosted with the crate: they are converted locally from the
+//! PyTorch checkpoint (`python utils/convert_model.py path/to/pytorch_model.bin`).
+//! ByT5 checkpoints share the T5 v1.1 architecture and operate on the UTF-8 bytes of the text: they are loaded with the byte-level `ByT5Tokenizer`,
+//! which requires no SentencePiece model (`T5Generator::new_byt5`, or `ModelType::ByT5` in the summarization and spelling correction pipelines).
 //!
 //! # Model set-up and pre-trained weights loading
 //!
@@ -10,7 +17,8 @@
 //! All models expect the following resources:
 //! - Configuration file expected to have a structure following the [Transformers library](https://github.com/huggingface/transformers)
 //! - Model weights are expected to have a structure and parameter names following the [Transformers library](https://github.com/huggingface/transformers). A conversion using the Python utility scripts is required to convert the `.bin` weights to the `.ot` format.
-//! - `T5Tokenizer` using a `spiece.model` sentence piece model
+//! - `T5Tokenizer` using a `spiece.model` sentence piece model (CodeT5 checkpoints use a RoBERTa byte-level BPE tokenizer
+//! with `vocab.json` and `merges.txt` files instead, see `pipelines::code_summarization`)
 //!
 //! Pretrained models for a number of language pairs are available and can be downloaded using RemoteResources.
 //!
@@ -49,13 +57,14 @@
 //! ```
 
 mod attention;
+mod byt5_tokenizer;
 mod encoder;
-mod layer_norm;
 mod t5_model;
 
 pub use attention::LayerState;
+pub use byt5_tokenizer::{ByT5Tokenizer, ByT5Vocab, BYT5_NUM_EXTRA_IDS};
 pub use t5_model::{
-    T5Config, T5ConfigResources, T5ForConditionalGeneration, T5ForSentenceEmbeddings, T5Generator,
-    T5Model, T5ModelOutput, T5ModelResources, T5Prefix, T5SourceLanguages, T5TargetLanguages,
-    T5VocabResources,
+    T5Config, T5ConfigResources, T5EncoderOutput, T5ForConditionalGeneration,
+    T5ForSentenceEmbeddings, T5Generator, T5MergesResources, T5Model, T5ModelOutput,
+    T5ModelResources, T5Prefix, T5SourceLanguages, T5TargetLanguages, T5VocabResources,
 };
@@ -18,6 +18,7 @@ use serde::{Deserialize, Serialize};
 use tch::nn::{embedding, LinearConfig};
 use tch::{nn, Tensor};
 
+use crate::common::activations::Activation;
 use crate::pipelines::common::{ModelType, TokenizerOption};
 use crate::pipelines::generation_utils::private_generation_utils::{
     PreparedInput, PrivateLanguageGenerator,
@@ -27,6 +28,7 @@ use crate::pipelines::generation_utils::{
 };
 use crate::pipelines::translation::Language;
 use crate::t5::attention::LayerState;
+use crate::t5::byt5_tokenizer::ByT5Tokenizer;
 use crate::t5::encoder::T5Stack;
 use crate::{Config, RustBertError};
 
@@ -39,6 +41,9 @@ pub struct T5ConfigResources;
 /// # T5 Pretrained model vocab files
 pub struct T5VocabResources;
 
+/// # T5 Pretrained model merges files (for checkpoints using a byte-level BPE tokenizer, such as CodeT5)
+pub struct T5MergesResources;
+
 /// # T5 optional prefixes
 pub struct T5Prefix;
 
@@ -82,6 +87,26 @@ impl T5ConfigResources {
         "sentence-t5-base/config",
         "https://huggingface.co/sentence-transformers/sentence-t5-base/resolve/main/config.json",
     );
+    /// Shared under BSD-3-Clause license by the Salesforce team at <https://huggingface.co/Salesforce/codet5-base-multi-sum>.
+    pub const CODET5_BASE_MULTI_SUM: (&'static str, &'static str) = (
+        "codet5-base-multi-sum/config",
+        "https://huggingface.co/Salesforce/codet5-base-multi-sum/resolve/main/config.json",
+    );
+    /// Shared under Apache 2.0 license by the Google team at <https://huggingface.co/google/flan-t5-small>.
+    pub const FLAN_T5_SMALL: (&'static str, &'static str) = (
+        "flan-t5-small/config",
+        "https://huggingface.co/google/flan-t5-small/resolve/main/config.json",
+    );
+    /// Shared under Apache 2.0 license by the Google team at <https://huggingface.co/google/flan-t5-base>.
+    pub const FLAN_T5_BASE: (&'static str, &'static str) = (
+        "flan-t5-base/config",
+        "https://huggingface.co/google/flan-t5-base/resolve/main/config.json",
+    );
+    /// Shared under Apache 2.0 license by the Google team at <https://huggingface.co/google/flan-t5-large>.
+    pub const FLAN_T5_LARGE: (&'static str, &'static str) = (
+        "flan-t5-large/config",
+        "https://huggingface.co/google/flan-t5-large/resolve/main/config.json",
+    );
 }
 
 impl T5VocabResources {
@@ -100,6 +125,34 @@ impl T5VocabResources {
         "sentence-t5-base/spiece",
         "https://huggingface.co/sentence-transformers/sentence-t5-base/resolve/main/spiece.model",
     );
+    /// Shared under BSD-3-Clause license by the Salesforce team at <https://huggingface.co/Salesforce/codet5-base-multi-sum>.
+    pub const CODET5_BASE_MULTI_SUM: (&'static str, &'static str) = (
+        "codet5-base-multi-sum/vocab",
+        "https://huggingface.co/Salesforce/codet5-base-multi-sum/resolve/main/vocab.json",
+    );
+    /// Shared under Apache 2.0 license by the Google team at <https://huggingface.co/google/flan-t5-small>.
+    pub const FLAN_T5_SMALL: (&'static str, &'static str) = (
+        "flan-t5-small/spiece",
+        "https://huggingface.co/google/flan-t5-small/resolve/main/spiece.model",
+    );
+    /// Shared under Apache 2.0 license by the Google team at <https://huggingface.co/google/flan-t5-base>.
+    pub const FLAN_T5_BASE: (&'static str, &'static str) = (
+        "flan-t5-base/spiece",
+        "https://huggingface.co/google/flan-t5-base/resolve/main/spiece.model",
+    );
+    /// Shared under Apache 2.0 license by the Google team at <https://huggingface.co/google/flan-t5-large>.
+    pub const FLAN_T5_LARGE: (&'static str, &'static str) = (
+        "flan-t5-large/spiece",
+        "https://huggingface.co/google/flan-t5-large/resolve/main/spiece.model",
+    );
+}
+
+impl T5MergesResources {
+    /// Shared under BSD-3-Clause license by the Salesforce team at <https://huggingface.co/Salesforce/codet5-base-multi-sum>.
+    pub const CODET5_BASE_MULTI_SUM: (&'static str, &'static str) = (
+        "codet5-base-multi-sum/merges",
+        "https://huggingface.co/Salesforce/codet5-base-multi-sum/resolve/main/merges.txt",
+    );
 }
 
 const T5LANGUAGES: [Language; 3] = [Language::English, Language::French, Language::German];
@@ -120,8 +173,35 @@ impl T5Prefix {
 pub enum FeedForwardProj {
     /// ReLU
     Relu,
-    /// Gated geLU
+    /// geLU
+    Gelu,
+    /// Gated geLU (T5 v1.1, Flan-T5, mT5)
     GatedGelu,
+    /// Gated ReLU
+    GatedRelu,
+    /// Gated SiLU
+    GatedSilu,
+}
+
+impl FeedForwardProj {
+    /// Returns the activation function applied by the feed-forward layer
+    pub fn activation(&self) -> Activation {
+        match self {
+            FeedForwardProj::Relu | FeedForwardProj::GatedRelu => Activation::relu,
+            FeedForwardProj::Gelu => Activation::gelu,
+            // Gated-gelu models are trained with the tanh approximation
+            FeedForwardProj::GatedGelu => Activation::gelu_new,
+            FeedForwardProj::GatedSilu => Activation::swish,
+        }
+    }
+
+    /// Returns true if the feed-forward layer is gated (separate activation and linear projections)
+    pub fn is_gated(&self) -> bool {
+        matches!(
+            self,
+            FeedForwardProj::GatedGelu | FeedForwardProj::GatedRelu | FeedForwardProj::GatedSilu
+        )
+    }
 }
 
 #[derive(Debug, Serialize, Deserialize, Clone)]
@@ -140,6 +220,8 @@ pub struct T5Config {
     pub layer_norm_epsilon: f64,
     pub num_heads: i64,
     pub num_layers: i64,
+    /// Number of decoder layers, equal to `num_layers` if not provided
+    pub num_decoder_layers: Option<i64>,
     pub output_past: Option<bool>,
     pub pad_token_id: Option<i64>,
     pub relative_attention_num_buckets: i64,
@@ -218,6 +300,7 @@ impl Default for T5Config {
             layer_norm_epsilon: 1e-6,
             num_heads: 8,
             num_layers: 6,
+            num_decoder_layers: None,
             output_past: None,
             pad_token_id: Some(0),
             relative_attention_num_buckets: 32,
@@ -314,6 +397,7 @@ impl T5Model {
     /// * `encoder_outputs` - Optional tuple made of a tensor of shape (*batch size*, *source_sequence_length*, *encoder_hidden_dim*) and optional vectors of tensors of length *num_encoder_layers* with shape (*batch size*, *source_sequence_length*, *hidden_size*).
     /// These correspond to the encoder last hidden state and optional hidden states/attention weights for encoder layers. When provided, the encoder hidden state will not be recalculated. Useful for generation tasks.
     /// * `decoder_attention_mask` - Optional attention mask of shape (*batch size*, *target_sequence_length*) for the decoder positions. Positions with a mask with value 0 will be masked.
+    ///   Masks of shape (*batch size*, *target_sequence_length*, *target_sequence_length*) replace the causal mask (e.g. prefix-LM masks built with `build_prefix_lm_attention_mask`).
     /// * `input_embeds` - Optional input tensor of shape (*batch size*, *source_sequence_length*, *embeddings dimension*). This or `input_ids` must be provided.
     /// * `decoder_input_embeds` - Optional input tensor of shape (*batch size*, *target_sequence_length*, *embeddings dimension*). This or `decoder_input_ids` must be provided.
     /// * `old_layer_states` - Optional vector of length `num_layers` containing tuples of optional `LayerStates` containing the last calculated key and value pairs for the decoder. This avoids recomputing attention weights at past positions and speeds up decoding.
@@ -430,6 +514,7 @@ impl T5Model {
             next_cache: decoder_output.next_cache,
             all_decoder_hidden_states: decoder_output.all_hidden_states,
             all_decoder_attentions: decoder_output.all_attentions,
+            all_decoder_cross_attentions: decoder_output.all_cross_attentions,
             all_encoder_hidden_states,
             all_encoder_attentions,
         }
@@ -643,6 +728,7 @@ impl LMHeadModel for T5ForConditionalGeneration {
     ///   - `lm_logits` - `Tensor` of shape (*batch size*, *sequence_length*, *vocab_size*) representing the logits for each vocab item and position
     ///   - `cache` - `T5Cache` made of `Option<Vec<(Option<Vec<&LayerState, &LayerState>>)>>` of length *n_layer* containing the encoder past keys and values for
     ///      both the self attention and the encoder cross attention of each layer of the decoder.
+    ///   - `hidden_states` - `Option<Tensor>` of shape (*batch size*, *sequence_length*, *hidden_size*) representing the activations of the last decoder hidden state
     ///
     /// # Example
     ///
@@ -736,6 +822,55 @@ impl LMHeadModel for T5ForConditionalGeneration {
         Ok(LMModelOutput {
             lm_logits,
             cache: Cache::T5Cache(base_model_output.next_cache),
+            hidden_states: Some(base_model_output.decoder_output),
+        })
+    }
+
+    fn forward_with_decoder_attention_mask(
+        &self,
+        decoder_input_ids: &Tensor,
+        cache: Cache,
+        attention_mask: Option<&Tensor>,
+        encoder_outputs: &Tensor,
+        decoder_attention_mask: &Tensor,
+        train: bool,
+    ) -> Result<LMModelOutput, RustBertError> {
+        let cached_layer_states = match cache {
+            Cache::T5Cache(cached_layer_states) => cached_layer_states,
+            Cache::None => None,
+            _ => {
+                return Err(RustBertError::ValueError(
+                    "Cache not compatible with T5 Model".into(),
+                ));
+            }
+        };
+        let base_model_output = self.base_model.forward_t(
+            None,
+            attention_mask,
+            Some(encoder_outputs),
+            Some(decoder_input_ids),
+            Some(decoder_attention_mask),
+            None,
+            None,
+            cached_layer_states,
+            train,
+        );
+
+        let lm_logits = if self.tie_word_embeddings {
+            base_model_output
+                .decoder_output
+                .linear::<Tensor>(&self.base_model.embeddings.ws, None)
+                * (self.model_dim.powf(-0.5))
+        } else {
+            base_model_output
+                .decoder_output
+                .apply(self.lm_head.as_ref().unwrap())
+        };
+
+        Ok(LMModelOutput {
+            lm_logits,
+            cache: Cache::T5Cache(base_model_output.next_cache),
+            hidden_states: Some(base_model_output.decoder_output),
         })
     }
 }
@@ -801,6 +936,31 @@ impl T5ForSentenceEmbeddings {
         input_ids: &Tensor,
         mask: &Tensor,
     ) -> Result<(Tensor, Option<Vec<Tensor>>), RustBertError> {
+        let transformer_output = self.forward_with_hidden_states(input_ids, mask)?;
+        Ok((
+            transformer_output.hidden_state,
+            transformer_output.all_attentions,
+        ))
+    }
+
+    /// Forward pass through the model, also returning the hidden states of all encoder layers
+    ///
+    /// # Arguments
+    ///
+    /// * `input_ids` - Input of shape (*batch size*, *source_sequence_length*).
+    /// * `mask` - Attention mask of shape (*batch size*, *source_sequence_length*) for the encoder positions. Positions with a mask with value 0 will be masked.
+    ///
+    /// # Returns
+    ///
+    /// * `T5EncoderOutput` containing:
+    ///   - `hidden_state` - `Tensor` of shape (*batch size*, *target_sequence_length*, *hidden_size*) representing the activations of the last encoder hidden state
+    ///   - `all_hidden_states` - `Option<Vec<Tensor>>` of length *num_encoder_layers* of shape (*batch size*, *target_sequence_length*, *hidden_size*) representing the hidden states of all layers of the encoder (before the final layer normalization)
+    ///   - `all_attentions` - `Option<Vec<Tensor>>` of length *num_encoder_layers* of shape (*batch size*, *target_sequence_length*, *hidden_size*)  representing attention weights for all layers of the encoder
+    pub fn forward_with_hidden_states(
+        &self,
+        input_ids: &Tensor,
+        mask: &Tensor,
+    ) -> Result<T5EncoderOutput, RustBertError> {
         let transformer_output = self.encoder.forward_t(
             Some(input_ids),
             Some(mask),
@@ -811,13 +971,31 @@ impl T5ForSentenceEmbeddings {
             None,
             false,
         )?;
-        Ok((
-            transformer_output.hidden_state,
-            transformer_output.all_attentions,
-        ))
+        // The encoder stack returns sequence-first hidden states
+        let all_hidden_states = transformer_output.all_hidden_states.map(|hidden_states| {
+            hidden_states
+                .into_iter()
+                .map(|hidden_state| hidden_state.transpose(0, 1))
+                .collect()
+        });
+        Ok(T5EncoderOutput {
+            hidden_state: transformer_output.hidden_state,
+            all_hidden_states,
+            all_attentions: transformer_output.all_attentions,
+        })
     }
 }
 
+/// Container holding the output of a T5 encoder
+pub struct T5EncoderOutput {
+    /// Hidden state of the last layer of the encoder
+    pub hidden_state: Tensor,
+    /// Hidden states for all layers of the encoder (batch first, before the final layer normalization)
+    pub all_hidden_states: Option<Vec<Tensor>>,
+    /// Attention weights for all layers of the encoder
+    pub all_attentions: Option<Vec<Tensor>>,
+}
+
 /// Container holding a T5 model output. The decoder output may hold the hidden state of
 /// the last layer of the decoder, or may hold logits for a custom head module after the
 /// decoder (e.g. for language modeling tasks)
@@ -833,6 +1011,8 @@ pub struct T5ModelOutput {
     pub all_decoder_hidden_states: Option<Vec<Tensor>>,
     /// Attention weights for all layers of the decoder
     pub all_decoder_attentions: Option<Vec<Tensor>>,
+    /// Cross-attention weights (decoder over encoder states) for all layers of the decoder
+    pub all_decoder_cross_attentions: Option<Vec<Tensor>>,
     /// Hidden states for all layers of the encoder
     pub all_encoder_hidden_states: Option<Vec<Tensor>>,
     /// Attention weights for all layers of the encoder
@@ -869,20 +1049,28 @@ impl T5Generator {
         Self::new_with_tokenizer(generate_config, tokenizer)
     }
 
+    /// Build a new `T5Generator` for a ByT5 checkpoint, with the byte-level `ByT5Tokenizer`.
+    /// The vocabulary resource of the `GenerateConfig` is not used.
+    pub fn new_byt5(generate_config: GenerateConfig) -> Result<T5Generator, RustBertError> {
+        Self::new_with_tokenizer(generate_config, TokenizerOption::ByT5(ByT5Tokenizer::new()))
+    }
+
     pub fn new_with_tokenizer(
         generate_config: GenerateConfig,
         tokenizer: TokenizerOption,
     ) -> Result<T5Generator, RustBertError> {
         let config_path = generate_config.config_resource.get_local_path()?;
-        let weights_path = generate_config.model_resource.get_local_path()?;
         let device = generate_config.device;
 
         generate_config.validate();
         let mut var_store = nn::VarStore::new(device);
 
-        let config = T5Config::from_file(config_path);
+        let mut config = T5Config::from_file(config_path);
+        if generate_config.output_attentions {
+            config.output_attentions = Some(true);
+        }
         let model = T5ForConditionalGeneration::new(&var_store.root(), &config);
-        var_store.load(weights_path)?;
+        crate::resources::load_weights(&generate_config.model_resource, &mut var_store)?;
 
         let bos_token_id = Some(config.bos_token_id.unwrap_or(-1));
         let eos_token_ids = Some(match config.eos_token_id {
@@ -987,6 +1175,7 @@ impl PrivateLanguageGenerator<T5ForConditionalGeneration, T5Vocab, T5Tokenizer>
         prompt_text: &[S],
         max_len: i64,
         pad_token_id: Option<i64>,
+        _add_bos_token: bool,
     ) -> Tensor
     where
         S: AsRef<str> + Sync,
@@ -0,0 +1,617 @@
+// Copyright 2022 Guillaume Becquin
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//     http://www.apache.org/licenses/LICENSE-2.0
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! # Datasets
+//! Streaming loaders of training examples, read lazily from JSON lines (`JsonlDataset`), CSV (`CsvDataset`) or
+//! Parquet (`ParquetDataset`, behind the `parquet` feature) files so that datasets larger than the memory can be
+//! used for fine-tuning. The loaders are iterators of `Example` (a map from field names to JSON values) and can be
+//! combined with the standard iterator adapters (e.g. `filter`, `take`, `chain`).
+//!
+//! - `ShuffleBuffer` shuffles a stream of examples with a bounded buffer: a larger buffer gives a better shuffle at
+//! the cost of memory, a buffer as large as the dataset giving a uniform shuffle
+//! - Collators (`Collator` trait) turn a list of examples into a batch of tensors. The `TokenizingCollator`
+//! tokenizes the text (or text pair) fields of the examples on the fly and pads them to the longest sequence of the
+//! batch, along with their labels
+//! - `DataLoader` groups the examples into batches and collates them
+//!
+//! ```no_run
+//! # fn main() -> anyhow::Result<()> {
+//! use rust_bert::pipelines::common::{ModelType, TokenizerOption};
+//! use rust_bert::training::datasets::{DataLoader, JsonlDataset, ShuffleBuffer, TokenizingCollator};
+//!
+//! let tokenizer = TokenizerOption::from_file(
+//!     ModelType::Bert,
+//!     "path/to/vocab.txt",
+//!     None,
+//!     true,
+//!     None,
+//!     None,
+//! )?;
+//! // Lines such as {"text": "A great movie", "label": "positive"}
+//! let examples = ShuffleBuffer::new(JsonlDataset::open("path/to/train.jsonl")?, 10_000, 42);
+//! let collator = TokenizingCollator::new(&tokenizer, "text", 128)
+//!     .with_label_field("label")
+//!     .with_label_mapping([("negative".to_string(), 0), ("positive".to_string(), 1)].into());
+//!
+//! for batch in DataLoader::new(examples, &collator, 32) {
+//!     let batch = batch?;
+//!     // batch.input_ids, batch.attention_mask, batch.token_type_ids, batch.labels
+//! }
+//! # Ok(())
+//! # }
+//! ```
+
+use crate::common::error::RustBertError;
+use crate::pipelines::common::TokenizerOption;
+use rust_tokenizers::tokenizer::TruncationStrategy;
+use serde_json::{Map, Value};
+use std::collections::HashMap;
+use std::fs::File;
+use std::io::{BufRead, BufReader, Lines, Read};
+use std::path::Path;
+use tch::Tensor;
+
+/// # Training example
+/// Fields of an example, by name. The values read from CSV files are strings.
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct Example {
+    pub fields: Map<String, Value>,
+}
+
+impl Example {
+    /// Returns the value of a field
+    pub fn get(&self, field: &str) -> Option<&Value> {
+        self.fields.get(field)
+    }
+
+    /// Returns the text of a field, failing if the field is missing or is not a string
+    pub fn get_text(&self, field: &str) -> Result<&str, RustBertError> {
+        match self.get(field) {
+            Some(Value::String(text)) => Ok(text),
+            Some(value) => Err(RustBertError::ValueError(format!(
+                "Field {} is not a string: {}",
+                field, value
+            ))),
+            None => Err(RustBertError::ValueError(format!(
+                "Field {} missing from the example",
+                field
+            ))),
+        }
+    }
+
+    /// Returns the label id of a field: integer values are used as is, and strings are looked up in the label
+    /// mapping if provided, parsed as integers otherwise.
+    ///
+    /// # Arguments
+    ///
+    /// * `field` - Name of the label field
+    /// * `label_mapping` - Optional mapping from label names to label ids
+    pub fn get_label(
+        &self,
+        field: &str,
+        label_mapping: Option<&HashMap<String, i64>>,
+    ) -> Result<i64, RustBertError> {
+        let label = match self.get(field) {
+            Some(Value::Number(label)) => label.as_i64(),
+            Some(Value::Bool(label)) => Some(*label as i64),
+            Some(Value::String(label)) => match label_mapping {
+                Some(label_mapping) => label_mapping.get(label).copied(),
+                None => label.trim().parse::<i64>().ok(),
+            },
+            Some(_) => None,
+            None => {
+                return Err(RustBertError::ValueError(format!(
+                    "Field {} missing from the example",
+                    field
+                )));
+            }
+        };
+        label.ok_or_else(|| {
+            RustBertError::ValueError(format!(
+                "Invalid label for field {}: {}",
+                field, self.fields[field]
+            ))
+        })
+    }
+}
+
+/// # JSON lines dataset
+/// Reads one example per line, each line holding a JSON object. Empty lines are skipped.
+pub struct JsonlDataset<R> {
+    lines: Lines<R>,
+    line_number: usize,
+}
+
+impl JsonlDataset<BufReader<File>> {
+    /// Opens a JSON lines file
+    pub fn open<P: AsRef<Path>>(path: P) -> Result<JsonlDataset<BufReader<File>>, RustBertError> {
+        Ok(JsonlDataset::from_reader(BufReader::new(File::open(path)?)))
+    }
+}
+
+impl<R: BufRead> JsonlDataset<R> {
+    /// Reads JSON lines from a buffered reader
+    pub fn from_reader(reader: R) -> JsonlDataset<R> {
+        JsonlDataset {
+            lines: reader.lines(),
+            line_number: 0,
+        }
+    }
+}
+
+impl<R: BufRead> Iterator for JsonlDataset<R> {
+    type Item = Result<Example, RustBertError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            let line = match self.lines.next()? {
+                Ok(line) => line,
+                Err(error) => return Some(Err(error.into())),
+            };
+            self.line_number += 1;
+            if line.trim().is_empty() {
+                continue;
+            }
+            return Some(match serde_json::from_str::<Value>(&line) {
+                Ok(Value::Object(fields)) => Ok(Example { fields }),
+                Ok(_) => Err(RustBertError::ValueError(format!(
+                    "Line {} is not a JSON object",
+                    self.line_number
+                ))),
+                Err(error) => Err(RustBertError::ValueError(format!(
+                    "Invalid JSON on line {}: {}",
+                    self.line_number, error
+                ))),
+            });
+        }
+    }
+}
+
+/// # CSV dataset
+/// Reads one example per record, the first record holding the names of the fields
+pub struct CsvDataset<R> {
+    field_names: Vec<String>,
+    records: csv::StringRecordsIntoIter<R>,
+}
+
+impl CsvDataset<File> {
+    /// Opens a CSV file
+    ///
+    /// # Arguments
+    ///
+    /// * `path` - Path of the file
+    /// * `delimiter` - Field delimiter (e.g. `b','` for CSV or `b'\t'` for TSV files)
+    pub fn open<P: AsRef<Path>>(path: P, delimiter: u8) -> Result<CsvDataset<File>, RustBertError> {
+        CsvDataset::from_reader(File::open(path)?, delimiter)
+    }
+}
+
+impl<R: Read> CsvDataset<R> {
+    /// Reads CSV records from a reader
+    ///
+    /// # Arguments
+    ///
+    /// * `reader` - Reader of the CSV data
+    /// * `delimiter` - Field delimiter (e.g. `b','` for CSV or `b'\t'` for TSV files)
+    pub fn from_reader(reader: R, delimiter: u8) -> Result<CsvDataset<R>, RustBertError> {
+        let mut reader = csv::ReaderBuilder::new()
+            .delimiter(delimiter)
+            .from_reader(reader);
+        let field_names = reader
+            .headers()
+            .map_err(|error| RustBertError::IOError(error.to_string()))?
+            .iter()
+            .map(String::from)
+            .collect();
+        Ok(CsvDataset {
+            field_names,
+            records: reader.into_records(),
+        })
+    }
+}
+
+impl<R: Read> Iterator for CsvDataset<R> {
+    type Item = Result<Example, RustBertError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let record = match self.records.next()? {
+            Ok(record) => record,
+            Err(error) => return Some(Err(RustBertError::ValueError(error.to_string()))),
+        };
+        let fields = self
+            .field_names
+            .iter()
+            .zip(record.iter())
+            .map(|(name, value)| (name.clone(), Value::String(value.to_string())))
+            .collect();
+        Some(Ok(Example { fields }))
+    }
+}
+
+/// # Parquet dataset
+/// Reads one example per row. Integer, floating point, boolean and string columns are converted to the
+/// corresponding JSON values, the other columns to their string representation.
+#[cfg(feature = "parquet")]
+pub struct ParquetDataset {
+    rows: parquet::record::reader::RowIter<'static>,
+}
+
+#[cfg(feature = "parquet")]
+impl ParquetDataset {
+    /// Opens a Parquet file
+    pub fn open<P: AsRef<Path>>(path: P) -> Result<ParquetDataset, RustBertError> {
+        let reader = parquet::file::serialized_reader::SerializedFileReader::new(File::open(path)?)
+            .map_err(|error| RustBertError::IOError(error.to_string()))?;
+        Ok(ParquetDataset {
+            rows: reader.into_iter(),
+        })
+    }
+
+    fn convert_field(field: &parquet::record::Field) -> Value {
+        use parquet::record::Field;
+        match field {
+            Field::Null => Value::Null,
+            Field::Bool(value) => Value::Bool(*value),
+            Field::Byte(value) => Value::from(*value),
+            Field::Short(value) => Value::from(*value),
+            Field::Int(value) => Value::from(*value),
+            Field::Long(value) => Value::from(*value),
+            Field::UByte(value) => Value::from(*value),
+            Field::UShort(value) => Value::from(*value),
+            Field::UInt(value) => Value::from(*value),
+            Field::ULong(value) => Value::from(*value),
+            Field::Float(value) => Value::from(*value as f64),
+            Field::Double(value) => Value::from(*value),
+            Field::Str(value) => Value::String(value.clone()),
+            _ => Value::String(field.to_string()),
+        }
+    }
+}
+
+#[cfg(feature = "parquet")]
+impl Iterator for ParquetDataset {
+    type Item = Result<Example, RustBertError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let row = self.rows.next()?;
+        let fields = row
+            .get_column_iter()
+            .map(|(name, field)| (name.clone(), Self::convert_field(field)))
+            .collect();
+        Some(Ok(Example { fields }))
+    }
+}
+
+/// # Shuffling buffer
+/// Shuffles a stream of items with a bounded buffer: the buffer is filled with the first items of the stream, and
+/// each returned item is drawn at random from the buffer and replaced by the next item of the stream.
+pub struct ShuffleBuffer<I: Iterator> {
+    iterator: I,
+    buffer: Vec<I::Item>,
+    buffer_size: usize,
+    random_state: u64,
+}
+
+impl<I: Iterator> ShuffleBuffer<I> {
+    /// Creates a new `ShuffleBuffer`
+    ///
+    /// # Arguments
+    ///
+    /// * `iterator` - Stream of items to shuffle
+    /// * `buffer_size` - Maximum number of items in the buffer
+    /// * `seed` - Seed of the random draws, giving the same order for the same stream
+    pub fn new(iterator: I, buffer_size: usize, seed: u64) -> ShuffleBuffer<I> {
+        ShuffleBuffer {
+            iterator,
+            buffer: Vec::with_capacity(buffer_size),
+            buffer_size: buffer_size.max(1),
+            random_state: seed,
+        }
+    }
+
+    // SplitMix64 generator
+    fn next_random(&mut self) -> u64 {
+        self.random_state = self.random_state.wrapping_add(0x9E37_79B9_7F4A_7C15);
+        let mut value = self.random_state;
+        value = (value ^ (value >> 30)).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+        value = (value ^ (value >> 27)).wrapping_mul(0x94D0_49BB_1331_11EB);
+        value ^ (value >> 31)
+    }
+}
+
+impl<I: Iterator> Iterator for ShuffleBuffer<I> {
+    type Item = I::Item;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        while self.buffer.len() < self.buffer_size {
+            match self.iterator.next() {
+                Some(item) => self.buffer.push(item),
+                None => break,
+            }
+        }
+        if self.buffer.is_empty() {
+            return None;
+        }
+        let index = (self.next_random() % self.buffer.len() as u64) as usize;
+        Some(self.buffer.swap_remove(index))
+    }
+}
+
+/// # Collator
+/// Turns a list of examples into a batch
+pub trait Collator {
+    /// Batch created from the examples
+    type Batch;
+
+    /// Creates a batch from a list of examples
+    fn collate(&self, examples: &[Example]) -> Result<Self::Batch, RustBertError>;
+}
+
+/// # Batch of tokenized examples
+/// Tensors of shape (*batch size*, *sequence_length*), padded to the longest sequence of the batch, on the CPU
+pub struct TokenizedBatch {
+    /// Token ids of the examples
+    pub input_ids: Tensor,
+    /// Mask of the tokens that are not padding
+    pub attention_mask: Tensor,
+    /// Segment ids of the tokens (0 for the first text, 1 for the second text of pairs)
+    pub token_type_ids: Tensor,
+    /// Label ids of the examples, of shape (*batch size*), if a label field is set
+    pub labels: Option<Tensor>,
+}
+
+/// # Tokenizing collator
+/// Tokenizes the text (or text pair) of the examples, truncating them to a maximum length, and pads them to the
+/// longest sequence of the batch
+pub struct TokenizingCollator<'a> {
+    tokenizer: &'a TokenizerOption,
+    text_field: String,
+    text_pair_field: Option<String>,
+    label_field: Option<String>,
+    label_mapping: Option<HashMap<String, i64>>,
+    max_length: usize,
+}
+
+impl<'a> TokenizingCollator<'a> {
+    /// Creates a new `TokenizingCollator` for single texts without labels
+    ///
+    /// # Arguments
+    ///
+    /// * `tokenizer` - Tokenizer of the model
+    /// * `text_field` - Name of the text field
+    /// * `max_length` - Maximum number of tokens of an example (including the special tokens)
+    pub fn new(
+        tokenizer: &'a TokenizerOption,
+        text_field: &str,
+        max_length: usize,
+    ) -> TokenizingCollator<'a> {
+        TokenizingCollator {
+            tokenizer,
+            text_field: text_field.to_string(),
+            text_pair_field: None,
+            label_field: None,
+            label_mapping: None,
+            max_length,
+        }
+    }
+
+    /// Sets the field of the second text, for text pair tasks (e.g. natural language inference)
+    pub fn with_text_pair_field(mut self, text_pair_field: &str) -> Self {
+        self.text_pair_field = Some(text_pair_field.to_string());
+        self
+    }
+
+    /// Sets the field of the labels, returned in `TokenizedBatch::labels`
+    pub fn with_label_field(mut self, label_field: &str) -> Self {
+        self.label_field = Some(label_field.to_string());
+        self
+    }
+
+    /// Sets the mapping from label names to label ids, for string labels
+    pub fn with_label_mapping(mut self, label_mapping: HashMap<String, i64>) -> Self {
+        self.label_mapping = Some(label_mapping);
+        self
+    }
+}
+
+impl Collator for TokenizingCollator<'_> {
+    type Batch = TokenizedBatch;
+
+    fn collate(&self, examples: &[Example]) -> Result<TokenizedBatch, RustBertError> {
+        if examples.is_empty() {
+            return Err(RustBertError::ValueError(
+                "Cannot collate an empty list of examples".to_string(),
+            ));
+        }
+        let mut tokenized_inputs = Vec::with_capacity(examples.len());
+        for example in examples {
+            let text_pair = match &self.text_pair_field {
+                Some(text_pair_field) => Some(example.get_text(text_pair_field)?),
+                None => None,
+            };
+            tokenized_inputs.push(self.tokenizer.encode_pair(
+                example.get_text(&self.text_field)?,
+                text_pair,
+                self.max_length,
+                &TruncationStrategy::LongestFirst,
+                0,
+            ));
+        }
+
+        let max_length = tokenized_inputs
+            .iter()
+            .map(|input| input.token_ids.len())
+            .max()
+            .unwrap();
+        let pad_id = self.tokenizer.get_pad_id().ok_or_else(|| {
+            RustBertError::ValueError("The tokenizer does not have a padding token".to_string())
+        })?;
+        let mut input_ids = Vec::with_capacity(examples.len());
+        let mut attention_mask = Vec::with_capacity(examples.len());
+        let mut token_type_ids = Vec::with_capacity(examples.len());
+        for mut input in tokenized_inputs {
+            let mut mask = vec![1i64; input.token_ids.len()];
+            mask.resize(max_length, 0);
+            input.token_ids.resize(max_length, pad_id);
+            let mut segment_ids = input
+                .segment_ids
+                .iter()
+                .map(|segment_id| *segment_id as i64)
+                .collect::<Vec<i64>>();
+            segment_ids.resize(max_length, 0);
+            input_ids.push(Tensor::of_slice(&input.token_ids));
+            attention_mask.push(Tensor::of_slice(&mask));
+            token_type_ids.push(Tensor::of_slice(&segment_ids));
+        }
+
+        let labels = match &self.label_field {
+            Some(label_field) => Some(Tensor::of_slice(
+                &examples
+                    .iter()
+                    .map(|example| example.get_label(label_field, self.label_mapping.as_ref()))
+                    .collect::<Result<Vec<i64>, RustBertError>>()?,
+            )),
+            None => None,
+        };
+
+        Ok(TokenizedBatch {
+            input_ids: Tensor::stack(&input_ids, 0),
+            attention_mask: Tensor::stack(&attention_mask, 0),
+            token_type_ids: Tensor::stack(&token_type_ids, 0),
+            labels,
+        })
+    }
+}
+
+/// # Data loader
+/// Groups a stream of examples into batches and collates them. The last batch may be smaller than the batch size.
+pub struct DataLoader<'a, I, C> {
+    examples: I,
+    collator: &'a C,
+    batch_size: usize,
+}
+
+impl<'a, I, C> DataLoader<'a, I, C>
+where
+    I: Iterator<Item = Result<Example, RustBertError>>,
+    C: Collator,
+{
+    /// Creates a new `DataLoader`
+    ///
+    /// # Arguments
+    ///
+    /// * `examples` - Stream of examples (e.g. a dataset, optionally shuffled with a `ShuffleBuffer`)
+    /// * `collator` - Collator creating the batches
+    /// * `batch_size` - Number of examples per batch
+    pub fn new(examples: I, collator: &'a C, batch_size: usize) -> DataLoader<'a, I, C> {
+        DataLoader {
+            examples,
+            collator,
+            batch_size: batch_size.max(1),
+        }
+    }
+}
+
+impl<'a, I, C> Iterator for DataLoader<'a, I, C>
+where
+    I: Iterator<Item = Result<Example, RustBertError>>,
+    C: Collator,
+{
+    type Item = Result<C::Batch, RustBertError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let mut examples = Vec::with_capacity(self.batch_size);
+        for example in self.examples.by_ref().take(self.batch_size) {
+            match example {
+                Ok(example) => examples.push(example),
+                Err(error) => return Some(Err(error)),
+            }
+        }
+        if examples.is_empty() {
+            None
+        } else {
+            Some(self.collator.collate(&examples))
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use std::io::Cursor;
+
+    #[test]
+    fn jsonl_dataset() {
+        let data = "{\"text\": \"A great movie\", \"label\": 1}\n\n{\"text\": \"Boring\", \"label\": \"negative\"}\n[1, 2]\n";
+        let examples = JsonlDataset::from_reader(Cursor::new(data)).collect::<Vec<_>>();
+        assert_eq!(examples.len(), 3);
+
+        let example = examples[0].as_ref().unwrap();
+        assert_eq!(example.get_text("text").unwrap(), "A great movie");
+        assert_eq!(example.get_label("label", None).unwrap(), 1);
+        assert!(example.get_text("label").is_err());
+
+        let label_mapping = HashMap::from([("negative".to_string(), 0)]);
+        let example = examples[1].as_ref().unwrap();
+        assert_eq!(example.get_label("label", Some(&label_mapping)).unwrap(), 0);
+        assert!(example.get_label("label", None).is_err());
+        assert!(examples[2].is_err());
+    }
+
+    #[test]
+    fn csv_dataset() -> anyhow::Result<()> {
+        let data = "text\tlabel\nA great movie\t1\n\"Boring, too long\"\t0\n";
+        let examples =
+            CsvDataset::from_reader(Cursor::new(data), b'\t')?.collect::<Result<Vec<_>, _>>()?;
+        assert_eq!(examples.len(), 2);
+        assert_eq!(examples[1].get_text("text")?, "Boring, too long");
+        assert_eq!(examples[1].get_label("label", None)?, 0);
+        Ok(())
+    }
+
+    #[test]
+    fn shuffle_buffer() {
+        let shuffled = ShuffleBuffer::new(0..100, 10, 42).collect::<Vec<i32>>();
+        assert_ne!(shuffled, (0..100).collect::<Vec<i32>>());
+        assert_eq!(
+            shuffled,
+            ShuffleBuffer::new(0..100, 10, 42).collect::<Vec<i32>>()
+        );
+        // Items are only drawn from the buffer: an item is returned at most `buffer_size - 1` positions early
+        for (position, item) in shuffled.iter().enumerate() {
+            assert!(*item as usize <= position + 9);
+        }
+        let mut sorted = shuffled;
+        sorted.sort_unstable();
+        assert_eq!(sorted, (0..100).collect::<Vec<i32>>());
+    }
+
+    #[test]
+    fn data_loader() {
+        struct CountCollator;
+        impl Collator for CountCollator {
+            type Batch = usize;
+            fn collate(&self, examples: &[Example]) -> Result<usize, RustBertError> {
+                Ok(examples.len())
+            }
+        }
+
+        let data = "{\"text\": \"a\"}\n".repeat(10);
+        let batch_sizes = DataLoader::new(
+            JsonlDataset::from_reader(Cursor::new(data)),
+            &CountCollator,
+            4,
+        )
+        .collect::<Result<Vec<usize>, RustBertError>>()
+        .unwrap();
+        assert_eq!(batch_sizes, vec![4, 4, 2]);
+    }
+}
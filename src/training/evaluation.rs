@@ -0,0 +1,600 @@
+// Copyright 2022 Guillaume Becquin
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//     http://www.apache.org/licenses/LICENSE-2.0
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! # Evaluation
+//! Standard metrics computed on a validation set during fine-tuning. The evaluators accumulate the predictions of
+//! the model batch by batch and compute their metrics over the full validation set:
+//! - `ClassificationEvaluator`: accuracy and macro-averaged precision, recall and F1 score (sequence classification)
+//! - `TokenClassificationEvaluator`: precision, recall and F1 score of the predicted entities, an entity being
+//! correct if both its span and its type match (named entity recognition, BIO tagging scheme)
+//! - `QuestionAnsweringEvaluator`: exact match and token F1 score of the predicted answers against a list of
+//! reference answers, after the normalization of the SQuAD evaluation script
+//!
+//! The evaluators implement the `Evaluator` trait, used by the `Trainer` to evaluate the model at the end of each
+//! epoch. All metrics are between 0 and 1.
+//!
+//! ```no_run
+//! # fn main() -> anyhow::Result<()> {
+//! use rust_bert::training::evaluation::{ClassificationEvaluator, Evaluator};
+//! use tch::Tensor;
+//!
+//! let mut evaluator = ClassificationEvaluator::new(2);
+//! # let batches: Vec<(Tensor, Tensor)> = vec![];
+//! for (logits, labels) in batches {
+//!     evaluator.add_batch(&logits, &labels)?;
+//! }
+//! let metrics = evaluator.compute();
+//! println!("F1 score: {}", metrics["f1"]);
+//! # Ok(())
+//! # }
+//! ```
+
+use crate::common::error::RustBertError;
+use std::collections::{HashMap, HashSet};
+use tch::{Kind, Tensor};
+
+/// # Evaluator
+/// Accumulates the predictions of a model on a validation set and computes metrics from them
+pub trait Evaluator {
+    /// Computes the metrics over the predictions accumulated since the last reset, by name
+    fn compute(&self) -> HashMap<String, f64>;
+
+    /// Clears the accumulated predictions
+    fn reset(&mut self);
+}
+
+/// # Classification metrics
+/// Precision, recall and F1 score are averaged over the labels present in the references or in the predictions
+/// (macro-average), a label without any prediction having a precision of 0.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ClassificationMetrics {
+    /// Fraction of correct predictions
+    pub accuracy: f64,
+    /// Macro-averaged precision
+    pub precision: f64,
+    /// Macro-averaged recall
+    pub recall: f64,
+    /// Macro-averaged F1 score
+    pub f1: f64,
+    /// F1 score of each label, indexed by label id
+    pub f1_per_label: Vec<f64>,
+}
+
+/// # Precision, recall and F1 score
+/// Used for the entities of token classification
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct PrecisionRecallMetrics {
+    /// Fraction of the predicted items that are correct
+    pub precision: f64,
+    /// Fraction of the reference items that are predicted
+    pub recall: f64,
+    /// Harmonic mean of the precision and recall
+    pub f1: f64,
+}
+
+impl PrecisionRecallMetrics {
+    fn from_counts(true_positives: usize, predicted: usize, actual: usize) -> Self {
+        let precision = if predicted > 0 {
+            true_positives as f64 / predicted as f64
+        } else {
+            0.0
+        };
+        let recall = if actual > 0 {
+            true_positives as f64 / actual as f64
+        } else {
+            0.0
+        };
+        PrecisionRecallMetrics {
+            precision,
+            recall,
+            f1: f1_score(precision, recall),
+        }
+    }
+}
+
+/// # Question answering metrics
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct QuestionAnsweringMetrics {
+    /// Fraction of the answers exactly matching one of their references after normalization
+    pub exact_match: f64,
+    /// Token F1 score of the answers with their best matching reference, averaged over the answers
+    pub f1: f64,
+}
+
+fn f1_score(precision: f64, recall: f64) -> f64 {
+    if precision + recall > 0.0 {
+        2.0 * precision * recall / (precision + recall)
+    } else {
+        0.0
+    }
+}
+
+/// Converts the logits (or the label ids) of a batch to a flat list of label ids
+fn to_label_ids(predictions: &Tensor) -> Vec<i64> {
+    let predictions = match predictions.kind() {
+        Kind::Float | Kind::Double | Kind::Half | Kind::BFloat16 => predictions.argmax(-1, false),
+        _ => predictions.shallow_clone(),
+    };
+    Vec::<i64>::from(predictions.to_kind(Kind::Int64).flatten(0, -1))
+}
+
+/// # Evaluator for sequence classification
+/// Accumulates the confusion matrix of the predicted labels
+pub struct ClassificationEvaluator {
+    num_labels: usize,
+    // confusion_matrix[label][prediction]
+    confusion_matrix: Vec<Vec<usize>>,
+}
+
+impl ClassificationEvaluator {
+    /// Creates a new `ClassificationEvaluator`
+    ///
+    /// # Arguments
+    ///
+    /// * `num_labels` - Number of labels of the classifier
+    pub fn new(num_labels: usize) -> ClassificationEvaluator {
+        ClassificationEvaluator {
+            num_labels,
+            confusion_matrix: vec![vec![0; num_labels]; num_labels],
+        }
+    }
+
+    /// Adds the predictions of a batch
+    ///
+    /// # Arguments
+    ///
+    /// * `predictions` - Logits of shape (*batch size*, *num_labels*) or predicted label ids of shape (*batch size*)
+    /// * `labels` - Reference label ids of shape (*batch size*)
+    pub fn add_batch(
+        &mut self,
+        predictions: &Tensor,
+        labels: &Tensor,
+    ) -> Result<(), RustBertError> {
+        let predictions = to_label_ids(predictions);
+        let labels = Vec::<i64>::from(labels.to_kind(Kind::Int64).flatten(0, -1));
+        if predictions.len() != labels.len() {
+            return Err(RustBertError::ValueError(format!(
+                "Got {} predictions for {} labels",
+                predictions.len(),
+                labels.len()
+            )));
+        }
+        for (prediction, label) in predictions.into_iter().zip(labels.into_iter()) {
+            if !(0..self.num_labels as i64).contains(&prediction)
+                || !(0..self.num_labels as i64).contains(&label)
+            {
+                return Err(RustBertError::ValueError(format!(
+                    "Label ids must be between 0 and {}, got prediction {} and label {}",
+                    self.num_labels - 1,
+                    prediction,
+                    label
+                )));
+            }
+            self.confusion_matrix[label as usize][prediction as usize] += 1;
+        }
+        Ok(())
+    }
+
+    /// Computes the classification metrics of the accumulated predictions
+    pub fn compute_metrics(&self) -> ClassificationMetrics {
+        let total = self
+            .confusion_matrix
+            .iter()
+            .map(|row| row.iter().sum::<usize>())
+            .sum::<usize>();
+        let correct = (0..self.num_labels)
+            .map(|label| self.confusion_matrix[label][label])
+            .sum::<usize>();
+
+        let mut f1_per_label = Vec::with_capacity(self.num_labels);
+        let (mut precision_sum, mut recall_sum, mut f1_sum, mut present_labels) =
+            (0.0, 0.0, 0.0, 0usize);
+        for (label, label_row) in self.confusion_matrix.iter().enumerate() {
+            let actual = label_row.iter().sum::<usize>();
+            let predicted = self
+                .confusion_matrix
+                .iter()
+                .map(|row| row[label])
+                .sum::<usize>();
+            let metrics = PrecisionRecallMetrics::from_counts(label_row[label], predicted, actual);
+            f1_per_label.push(metrics.f1);
+            if actual + predicted > 0 {
+                precision_sum += metrics.precision;
+                recall_sum += metrics.recall;
+                f1_sum += metrics.f1;
+                present_labels += 1;
+            }
+        }
+        let present_labels = present_labels.max(1) as f64;
+
+        ClassificationMetrics {
+            accuracy: if total > 0 {
+                correct as f64 / total as f64
+            } else {
+                0.0
+            },
+            precision: precision_sum / present_labels,
+            recall: recall_sum / present_labels,
+            f1: f1_sum / present_labels,
+            f1_per_label,
+        }
+    }
+}
+
+impl Evaluator for ClassificationEvaluator {
+    /// Returns `accuracy`, `precision`, `recall` and `f1`
+    fn compute(&self) -> HashMap<String, f64> {
+        let metrics = self.compute_metrics();
+        [
+            ("accuracy".to_string(), metrics.accuracy),
+            ("precision".to_string(), metrics.precision),
+            ("recall".to_string(), metrics.recall),
+            ("f1".to_string(), metrics.f1),
+        ]
+        .into()
+    }
+
+    fn reset(&mut self) {
+        self.confusion_matrix = vec![vec![0; self.num_labels]; self.num_labels];
+    }
+}
+
+/// Extracts the entities (start, end (exclusive), entity type) of a sequence of BIO tags. An `I-` tag following a
+/// tag of another type starts a new entity, tags without prefix (IO scheme) are treated as `I-` tags.
+fn extract_entities(tags: &[String]) -> HashSet<(usize, usize, String)> {
+    let mut entities = HashSet::new();
+    let mut current: Option<(usize, String)> = None;
+    for (position, tag) in tags.iter().enumerate() {
+        let (prefix, entity_type) = match tag.split_once('-') {
+            Some((prefix, entity_type)) => (prefix, entity_type),
+            None if tag == "O" => ("O", ""),
+            None => ("I", tag.as_str()),
+        };
+        let continues_entity = prefix == "I"
+            && current
+                .as_ref()
+                .map_or(false, |(_, current_type)| current_type == entity_type);
+        if !continues_entity {
+            if let Some((start, current_type)) = current.take() {
+                entities.insert((start, position, current_type));
+            }
+            if prefix != "O" {
+                current = Some((position, entity_type.to_string()));
+            }
+        }
+    }
+    if let Some((start, current_type)) = current {
+        entities.insert((start, tags.len(), current_type));
+    }
+    entities
+}
+
+/// # Evaluator for token classification
+/// Computes the precision, recall and F1 score of the entities predicted by a token classification model
+/// (e.g. named entity recognition) from BIO tags (`B-PER`, `I-PER`, `O`...)
+pub struct TokenClassificationEvaluator {
+    label_mapping: HashMap<i64, String>,
+    ignore_label_id: i64,
+    true_positives: usize,
+    predicted: usize,
+    actual: usize,
+}
+
+impl TokenClassificationEvaluator {
+    /// Creates a new `TokenClassificationEvaluator`
+    ///
+    /// # Arguments
+    ///
+    /// * `label_mapping` - Mapping from label ids to BIO tags (e.g. the `id2label` of the model configuration)
+    pub fn new(label_mapping: HashMap<i64, String>) -> TokenClassificationEvaluator {
+        TokenClassificationEvaluator {
+            label_mapping,
+            ignore_label_id: -100,
+            true_positives: 0,
+            predicted: 0,
+            actual: 0,
+        }
+    }
+
+    /// Sets the label id of the tokens to ignore (sub-tokens, padding and special tokens), -100 by default
+    pub fn with_ignore_label_id(mut self, ignore_label_id: i64) -> Self {
+        self.ignore_label_id = ignore_label_id;
+        self
+    }
+
+    fn get_tags(&self, label_ids: &[i64]) -> Result<Vec<String>, RustBertError> {
+        label_ids
+            .iter()
+            .map(|label_id| {
+                self.label_mapping.get(label_id).cloned().ok_or_else(|| {
+                    RustBertError::ValueError(format!("Unknown label id {}", label_id))
+                })
+            })
+            .collect()
+    }
+
+    /// Adds the predicted and reference tags of a sequence
+    pub fn add_sequence<S: AsRef<str>>(&mut self, predictions: &[S], labels: &[S]) {
+        let predictions = predictions
+            .iter()
+            .map(|tag| tag.as_ref().to_string())
+            .collect::<Vec<String>>();
+        let labels = labels
+            .iter()
+            .map(|tag| tag.as_ref().to_string())
+            .collect::<Vec<String>>();
+        let predicted_entities = extract_entities(&predictions);
+        let actual_entities = extract_entities(&labels);
+        self.true_positives += predicted_entities.intersection(&actual_entities).count();
+        self.predicted += predicted_entities.len();
+        self.actual += actual_entities.len();
+    }
+
+    /// Adds the predictions of a batch. The tokens with the ignored label id are skipped.
+    ///
+    /// # Arguments
+    ///
+    /// * `predictions` - Logits of shape (*batch size*, *sequence_length*, *num_labels*) or predicted label ids of
+    /// shape (*batch size*, *sequence_length*)
+    /// * `labels` - Reference label ids of shape (*batch size*, *sequence_length*)
+    pub fn add_batch(
+        &mut self,
+        predictions: &Tensor,
+        labels: &Tensor,
+    ) -> Result<(), RustBertError> {
+        let (batch_size, sequence_length) = labels.size2()?;
+        let predictions = to_label_ids(predictions);
+        let labels = Vec::<i64>::from(labels.to_kind(Kind::Int64).flatten(0, -1));
+        if predictions.len() != labels.len() {
+            return Err(RustBertError::ValueError(format!(
+                "Got {} predictions for {} labels",
+                predictions.len(),
+                labels.len()
+            )));
+        }
+        for sequence_index in 0..batch_size as usize {
+            let offset = sequence_index * sequence_length as usize;
+            let (sequence_predictions, sequence_labels): (Vec<i64>, Vec<i64>) = predictions
+                [offset..offset + sequence_length as usize]
+                .iter()
+                .zip(labels[offset..offset + sequence_length as usize].iter())
+                .filter(|(_, label)| **label != self.ignore_label_id)
+                .unzip();
+            let predicted_tags = self.get_tags(&sequence_predictions)?;
+            let actual_tags = self.get_tags(&sequence_labels)?;
+            self.add_sequence(&predicted_tags, &actual_tags);
+        }
+        Ok(())
+    }
+
+    /// Computes the entity metrics of the accumulated predictions
+    pub fn compute_metrics(&self) -> PrecisionRecallMetrics {
+        PrecisionRecallMetrics::from_counts(self.true_positives, self.predicted, self.actual)
+    }
+}
+
+impl Evaluator for TokenClassificationEvaluator {
+    /// Returns `precision`, `recall` and `f1`
+    fn compute(&self) -> HashMap<String, f64> {
+        let metrics = self.compute_metrics();
+        [
+            ("precision".to_string(), metrics.precision),
+            ("recall".to_string(), metrics.recall),
+            ("f1".to_string(), metrics.f1),
+        ]
+        .into()
+    }
+
+    fn reset(&mut self) {
+        self.true_positives = 0;
+        self.predicted = 0;
+        self.actual = 0;
+    }
+}
+
+/// Lower-cases the answer and removes its punctuation, articles and extra white spaces, returning its tokens
+fn normalize_answer(answer: &str) -> Vec<String> {
+    answer
+        .to_lowercase()
+        .chars()
+        .filter(|character| !character.is_ascii_punctuation())
+        .collect::<String>()
+        .split_whitespace()
+        .filter(|token| !matches!(*token, "a" | "an" | "the"))
+        .map(|token| token.to_string())
+        .collect()
+}
+
+fn token_f1(prediction: &[String], reference: &[String]) -> f64 {
+    if prediction.is_empty() || reference.is_empty() {
+        return if prediction == reference { 1.0 } else { 0.0 };
+    }
+    let mut reference_counts: HashMap<&str, usize> = HashMap::new();
+    for token in reference {
+        *reference_counts.entry(token).or_insert(0) += 1;
+    }
+    let mut common = 0;
+    for token in prediction {
+        if let Some(count) = reference_counts.get_mut(token.as_str()) {
+            if *count > 0 {
+                *count -= 1;
+                common += 1;
+            }
+        }
+    }
+    f1_score(
+        common as f64 / prediction.len() as f64,
+        common as f64 / reference.len() as f64,
+    )
+}
+
+/// # Evaluator for question answering
+/// Computes the exact match and token F1 score of the predicted answers, with the normalization of the SQuAD
+/// evaluation script (lower case, without punctuation and articles). Each answer is compared with its best
+/// matching reference.
+#[derive(Default)]
+pub struct QuestionAnsweringEvaluator {
+    exact_match_sum: f64,
+    f1_sum: f64,
+    count: usize,
+}
+
+impl QuestionAnsweringEvaluator {
+    /// Creates a new `QuestionAnsweringEvaluator`
+    pub fn new() -> QuestionAnsweringEvaluator {
+        Default::default()
+    }
+
+    /// Adds a predicted answer
+    ///
+    /// # Arguments
+    ///
+    /// * `prediction` - Predicted answer text
+    /// * `references` - Reference answers of the question (at least one)
+    pub fn add_answer<S: AsRef<str>>(
+        &mut self,
+        prediction: &str,
+        references: &[S],
+    ) -> Result<(), RustBertError> {
+        if references.is_empty() {
+            return Err(RustBertError::ValueError(
+                "At least one reference answer is required".to_string(),
+            ));
+        }
+        let prediction = normalize_answer(prediction);
+        let (mut exact_match, mut f1): (f64, f64) = (0.0, 0.0);
+        for reference in references {
+            let reference = normalize_answer(reference.as_ref());
+            if prediction == reference {
+                exact_match = 1.0;
+            }
+            f1 = f1.max(token_f1(&prediction, &reference));
+        }
+        self.exact_match_sum += exact_match;
+        self.f1_sum += f1;
+        self.count += 1;
+        Ok(())
+    }
+
+    /// Computes the question answering metrics of the accumulated answers
+    pub fn compute_metrics(&self) -> QuestionAnsweringMetrics {
+        let count = self.count.max(1) as f64;
+        QuestionAnsweringMetrics {
+            exact_match: self.exact_match_sum / count,
+            f1: self.f1_sum / count,
+        }
+    }
+}
+
+impl Evaluator for QuestionAnsweringEvaluator {
+    /// Returns `exact_match` and `f1`
+    fn compute(&self) -> HashMap<String, f64> {
+        let metrics = self.compute_metrics();
+        [
+            ("exact_match".to_string(), metrics.exact_match),
+            ("f1".to_string(), metrics.f1),
+        ]
+        .into()
+    }
+
+    fn reset(&mut self) {
+        *self = Default::default();
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn classification_metrics() {
+        let mut evaluator = ClassificationEvaluator::new(3);
+        let logits = Tensor::of_slice(&[
+            0.9f32, 0.1, 0.0, //
+            0.2, 0.7, 0.1, //
+            0.1, 0.8, 0.1, //
+            0.6, 0.3, 0.1,
+        ])
+        .view([4, 3]);
+        evaluator
+            .add_batch(&logits, &Tensor::of_slice(&[0i64, 1, 0, 0]))
+            .unwrap();
+
+        let metrics = evaluator.compute_metrics();
+        assert!((metrics.accuracy - 0.75).abs() < 1e-9);
+        // label 0: precision 1, recall 2/3; label 1: precision 1/2, recall 1; label 2 absent
+        assert!((metrics.precision - 0.75).abs() < 1e-9);
+        assert!((metrics.recall - 5.0 / 6.0).abs() < 1e-9);
+        assert!((metrics.f1_per_label[0] - 0.8).abs() < 1e-9);
+        assert!((metrics.f1 - (0.8 + 2.0 / 3.0) / 2.0).abs() < 1e-9);
+        assert_eq!(metrics.f1_per_label[2], 0.0);
+
+        evaluator.reset();
+        assert_eq!(evaluator.compute()["accuracy"], 0.0);
+        assert!(evaluator
+            .add_batch(&Tensor::of_slice(&[3i64]), &Tensor::of_slice(&[0i64]))
+            .is_err());
+    }
+
+    #[test]
+    fn entity_metrics() {
+        let label_mapping: HashMap<i64, String> = [
+            (0, "O".to_string()),
+            (1, "B-PER".to_string()),
+            (2, "I-PER".to_string()),
+            (3, "B-LOC".to_string()),
+            (4, "I-LOC".to_string()),
+        ]
+        .into();
+        let mut evaluator = TokenClassificationEvaluator::new(label_mapping);
+        // Reference: [John Smith]PER visited [New York]LOC, the sub-token of Smith is ignored
+        let labels = Tensor::of_slice(&[1i64, 2, -100, 0, 3, 4]).view([1, 6]);
+        // Prediction: [John Smith]PER visited [New]LOC [York]PER
+        let predictions = Tensor::of_slice(&[1i64, 2, 0, 0, 3, 2]).view([1, 6]);
+        evaluator.add_batch(&predictions, &labels).unwrap();
+
+        let metrics = evaluator.compute_metrics();
+        assert!((metrics.precision - 1.0 / 3.0).abs() < 1e-9);
+        assert!((metrics.recall - 0.5).abs() < 1e-9);
+        assert!((metrics.f1 - 0.4).abs() < 1e-9);
+    }
+
+    #[test]
+    fn entity_extraction() {
+        let tags = ["I-PER", "I-PER", "B-PER", "I-LOC", "O", "LOC"]
+            .iter()
+            .map(|tag| tag.to_string())
+            .collect::<Vec<String>>();
+        let entities = extract_entities(&tags);
+        assert_eq!(entities.len(), 4);
+        assert!(entities.contains(&(0, 2, "PER".to_string())));
+        assert!(entities.contains(&(2, 3, "PER".to_string())));
+        assert!(entities.contains(&(3, 4, "LOC".to_string())));
+        assert!(entities.contains(&(5, 6, "LOC".to_string())));
+    }
+
+    #[test]
+    fn question_answering_metrics() {
+        let mut evaluator = QuestionAnsweringEvaluator::new();
+        evaluator
+            .add_answer("The Eiffel Tower!", &["eiffel tower", "Tour Eiffel"])
+            .unwrap();
+        evaluator
+            .add_answer("in Paris, France", &["Paris"])
+            .unwrap();
+        assert!(evaluator.add_answer("Paris", &[] as &[&str]).is_err());
+
+        let metrics = evaluator.compute_metrics();
+        assert!((metrics.exact_match - 0.5).abs() < 1e-9);
+        // Second answer: precision 1/3, recall 1
+        assert!((metrics.f1 - (1.0 + 0.5) / 2.0).abs() < 1e-9);
+    }
+}
@@ -0,0 +1,306 @@
+// Copyright 2022 Guillaume Becquin
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//     http://www.apache.org/licenses/LICENSE-2.0
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use crate::training::optimizer::TrainingOptimizer;
+use crate::RustBertError;
+use tch::{nn, no_grad, Kind, Tensor};
+
+/// # Configuration for the dynamic loss scaling
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct GradScalerConfig {
+    /// Initial loss scale (default: 65536)
+    pub init_scale: f64,
+    /// Factor applied to the scale after `growth_interval` steps without overflow (default: 2.0)
+    pub growth_factor: f64,
+    /// Factor applied to the scale after a step with an overflow (default: 0.5)
+    pub backoff_factor: f64,
+    /// Number of consecutive steps without overflow before the scale grows (default: 2000)
+    pub growth_interval: i64,
+}
+
+impl Default for GradScalerConfig {
+    fn default() -> Self {
+        GradScalerConfig {
+            init_scale: 65536.0,
+            growth_factor: 2.0,
+            backoff_factor: 0.5,
+            growth_interval: 2000,
+        }
+    }
+}
+
+/// # Dynamic loss scaler
+/// Multiplies the loss by a scale factor before the backward pass so that small gradients do not underflow in half
+/// precision. The scale is reduced when the gradients overflow (the corresponding step being skipped), and
+/// increased after `growth_interval` steps without overflow.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct GradScaler {
+    config: GradScalerConfig,
+    scale: f64,
+    growth_tracker: i64,
+}
+
+impl GradScaler {
+    /// Creates a new `GradScaler`
+    ///
+    /// # Arguments
+    ///
+    /// * `config` - `GradScalerConfig` loss scaling settings
+    pub fn new(config: GradScalerConfig) -> GradScaler {
+        GradScaler {
+            config,
+            scale: config.init_scale,
+            growth_tracker: 0,
+        }
+    }
+
+    /// Returns the current loss scale
+    pub fn scale(&self) -> f64 {
+        self.scale
+    }
+
+    /// Multiplies a loss by the current scale
+    pub fn scale_loss(&self, loss: &Tensor) -> Tensor {
+        loss * self.scale
+    }
+
+    /// Updates the scale after a step
+    ///
+    /// # Arguments
+    ///
+    /// * `found_inf` - Flag indicating if the gradients of the step overflowed
+    pub fn update(&mut self, found_inf: bool) {
+        if found_inf {
+            self.scale *= self.config.backoff_factor;
+            self.growth_tracker = 0;
+        } else {
+            self.growth_tracker += 1;
+            if self.growth_tracker == self.config.growth_interval {
+                self.scale *= self.config.growth_factor;
+                self.growth_tracker = 0;
+            }
+        }
+    }
+}
+
+/// # Mixed precision training
+/// Trains a model in half precision with full precision master weights and dynamic loss scaling
+/// ([Micikevicius et al., 2017](https://arxiv.org/abs/1710.03740)), as the automatic mixed precision of PyTorch.
+/// The variables of the model are converted to half precision, halving the memory of the weights and activations
+/// and speeding up the forward and backward passes on GPUs with tensor cores. A full precision copy of the
+/// trainable variables (the master weights) is kept in a separate variable store, updated by the optimizer from the
+/// unscaled gradients and copied back to the model after each step.
+///
+/// The optimizer must be created from `MixedPrecision::master_var_store`, and can be any `nn::Optimizer` of `tch` or
+/// an `Adam8bit` optimizer.
+pub struct MixedPrecision {
+    scaler: GradScaler,
+    master_var_store: nn::VarStore,
+    // Pairs of (half precision model variable, full precision master variable)
+    variables: Vec<(Tensor, Tensor)>,
+}
+
+impl MixedPrecision {
+    /// Converts a model to half precision and creates its full precision master weights
+    ///
+    /// # Arguments
+    ///
+    /// * `var_store` - Variable store of the model, with its weights loaded
+    /// * `config` - `GradScalerConfig` loss scaling settings
+    ///
+    /// # Example
+    ///
+    /// ```no_run
+    /// # fn main() -> anyhow::Result<()> {
+    /// use rust_bert::bert::{BertConfig, BertForSequenceClassification};
+    /// use rust_bert::training::{Adam8bit, Adam8bitConfig, MixedPrecision};
+    /// use rust_bert::Config;
+    /// use std::path::Path;
+    /// use tch::{nn, Device, Kind, Tensor};
+    ///
+    /// let config = BertConfig::from_file(Path::new("path/to/config.json"));
+    /// let mut vs = nn::VarStore::new(Device::Cuda(0));
+    /// let model = BertForSequenceClassification::new(vs.root(), &config);
+    /// vs.load("path/to/model.ot")?;
+    ///
+    /// let mut mixed_precision = MixedPrecision::new(&mut vs, Default::default())?;
+    /// let mut optimizer = Adam8bit::new(mixed_precision.master_var_store(), Adam8bitConfig::new(2e-5));
+    ///
+    /// let input_ids = Tensor::of_slice(&[101i64, 2023, 3185, 2003, 2307, 102])
+    ///     .view([1, -1])
+    ///     .to(vs.device());
+    /// let labels = Tensor::of_slice(&[1i64]).to(vs.device());
+    /// let output = model.forward_t(Some(&input_ids), None, None, None, None, true);
+    /// let loss = output
+    ///     .logits
+    ///     .to_kind(Kind::Float)
+    ///     .cross_entropy_for_logits(&labels);
+    /// mixed_precision.backward(&loss);
+    /// let step_applied = mixed_precision.step(&mut optimizer);
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn new(
+        var_store: &mut nn::VarStore,
+        config: GradScalerConfig,
+    ) -> Result<MixedPrecision, RustBertError> {
+        let mut trainable_variables = var_store
+            .variables()
+            .into_iter()
+            .filter(|(_, variable)| variable.requires_grad())
+            .collect::<Vec<(String, Tensor)>>();
+        if trainable_variables.is_empty() {
+            return Err(RustBertError::ValueError(
+                "The variable store does not contain any trainable variable".to_string(),
+            ));
+        }
+        trainable_variables.sort_by(|(name_a, _), (name_b, _)| name_a.cmp(name_b));
+
+        let master_var_store = nn::VarStore::new(var_store.device());
+        let mut variables = Vec::with_capacity(trainable_variables.len());
+        for (name, variable) in trainable_variables {
+            let mut path = master_var_store.root();
+            let mut segments = name.split('.').collect::<Vec<&str>>();
+            let variable_name = segments.pop().unwrap();
+            for segment in segments {
+                path = &path / segment;
+            }
+            let master_variable =
+                no_grad(|| path.var_copy(variable_name, &variable.to_kind(Kind::Float)));
+            // Creates the gradient of the master variable, overwritten with the gradients of the model at each step
+            (master_variable.sum(Kind::Float) * 0.0).backward();
+            variables.push((variable, master_variable));
+        }
+        var_store.half();
+
+        Ok(MixedPrecision {
+            scaler: GradScaler::new(config),
+            master_var_store,
+            variables,
+        })
+    }
+
+    /// Returns the variable store holding the full precision master weights, to create the optimizer from. It can
+    /// also be saved to keep a full precision checkpoint of the model.
+    pub fn master_var_store(&self) -> &nn::VarStore {
+        &self.master_var_store
+    }
+
+    /// Returns the loss scaler
+    pub fn scaler(&self) -> &GradScaler {
+        &self.scaler
+    }
+
+    /// Runs the backward pass of the scaled loss
+    pub fn backward(&self, loss: &Tensor) {
+        self.scaler.scale_loss(loss).backward();
+    }
+
+    /// Updates the weights from the gradients of the last backward pass: the gradients are unscaled into the master
+    /// weights, updated by the optimizer and copied back to the model. The step is skipped if the gradients
+    /// overflowed. The gradients of the model and of the optimizer are reset.
+    ///
+    /// # Arguments
+    ///
+    /// * `optimizer` - Optimizer created from the master variable store
+    ///
+    /// # Returns
+    ///
+    /// * `bool` Flag indicating if the weights were updated (false if the gradients overflowed)
+    pub fn step<O: TrainingOptimizer>(&mut self, optimizer: &mut O) -> bool {
+        let scale = self.scaler.scale();
+        let found_inf = no_grad(|| {
+            let mut found_inf = false;
+            for (variable, master_variable) in self.variables.iter() {
+                let mut master_grad = master_variable.grad();
+                let grad = variable.grad();
+                if grad.defined() {
+                    master_grad.copy_(&(grad.to_kind(Kind::Float) / scale));
+                    found_inf |= !bool::from(master_grad.isfinite().all());
+                } else {
+                    let _ = master_grad.zero_();
+                }
+            }
+            found_inf
+        });
+
+        if !found_inf {
+            optimizer.step();
+            no_grad(|| {
+                for (variable, master_variable) in self.variables.iter_mut() {
+                    variable.copy_(master_variable);
+                }
+            });
+        }
+        self.scaler.update(found_inf);
+        optimizer.zero_grad();
+        for (variable, _) in self.variables.iter_mut() {
+            variable.zero_grad();
+        }
+        !found_inf
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use tch::nn::OptimizerConfig;
+    use tch::Device;
+
+    #[test]
+    fn grad_scaler() {
+        let mut scaler = GradScaler::new(GradScalerConfig {
+            growth_interval: 2,
+            ..Default::default()
+        });
+        scaler.update(true);
+        assert_eq!(scaler.scale(), 32768.0);
+        scaler.update(false);
+        assert_eq!(scaler.scale(), 32768.0);
+        scaler.update(false);
+        assert_eq!(scaler.scale(), 65536.0);
+    }
+
+    #[test]
+    fn mixed_precision_step() -> anyhow::Result<()> {
+        let mut vs = nn::VarStore::new(Device::Cpu);
+        let weight = (&vs.root() / "layer").ones("weight", &[4]);
+        let config = GradScalerConfig {
+            init_scale: 1024.0,
+            ..Default::default()
+        };
+        let mut mixed_precision = MixedPrecision::new(&mut vs, config)?;
+        let mut optimizer = nn::Sgd::default().build(mixed_precision.master_var_store(), 0.1)?;
+        assert_eq!(weight.kind(), Kind::Half);
+        assert!(mixed_precision
+            .master_var_store()
+            .variables()
+            .contains_key("layer.weight"));
+
+        let loss = (weight.to_kind(Kind::Float) - 3.0)
+            .square()
+            .sum(Kind::Float);
+        mixed_precision.backward(&loss);
+        assert!(mixed_precision.step(&mut optimizer));
+        // w - 0.1 * 2 * (w - 3) = 1.4, rounded to half precision
+        assert!((f64::from(weight.get(0)) - 1.4).abs() < 1e-3);
+        let master_weight = &mixed_precision.master_var_store().variables()["layer.weight"];
+        assert!(master_weight.allclose(&Tensor::of_slice(&[1.4f32; 4]), 1e-6, 1e-6, false));
+
+        // The gradients overflow in half precision: the step is skipped and the scale reduced
+        let loss = (weight.to_kind(Kind::Float) * 1e4).sum(Kind::Float);
+        mixed_precision.backward(&loss);
+        assert!(!mixed_precision.step(&mut optimizer));
+        assert_eq!(mixed_precision.scaler().scale(), 512.0);
+        assert!((f64::from(weight.get(0)) - 1.4).abs() < 1e-3);
+        Ok(())
+    }
+}
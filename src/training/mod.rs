@@ -0,0 +1,74 @@
+//! # Training utilities
+//!
+//! Building blocks for the fine-tuning of the models of this crate, reducing the memory needed to train
+//! medium-sized models on consumer GPUs:
+//! - Mixed precision training: `MixedPrecision` trains the model in half precision, with full precision master
+//! weights updated by the optimizer and a dynamic loss scaling (`GradScaler`) preventing the underflow of the half
+//! precision gradients
+//! - 8-bit Adam optimizer: `Adam8bit` stores the Adam moment estimates with a block-wise 8-bit quantization, using
+//! 2 bytes of optimizer state per parameter instead of 8
+//! - Datasets (`datasets` module): streaming loaders of JSON lines, CSV and Parquet files, shuffling buffer and
+//! collators tokenizing the examples on the fly into batches of tensors
+//! - Evaluation (`evaluation` module): classification, entity and question answering metrics computed on a
+//! validation set
+//! - Training loop: the `Trainer` runs the optimizer over the training set and evaluates the model at the end of each
+//! epoch, saving the weights of the epoch with the best validation metric. The learning rate can follow a schedule
+//! (`LearningRateScheduler`: linear or cosine decay with warmup, one-cycle) and the training can stop early when the
+//! validation metric stops improving
+//! - Weight averaging: `ExponentialMovingAverage` maintains an exponential moving average of the weights during
+//! training, and `CheckpointAverager` / `average_checkpoints` average the weights of the last checkpoints. The
+//! averaged weights are exported with their configuration by `save_pretrained`
+//!
+//! Mixed precision training and the optimizers work with the `nn::VarStore` of any model. The optimizers implement
+//! the `TrainingOptimizer` trait, also implemented for the `nn::Optimizer` of `tch`.
+//!
+//! ```no_run
+//! # fn main() -> anyhow::Result<()> {
+//! use rust_bert::gpt2::{GPT2LMHeadModel, Gpt2Config};
+//! use rust_bert::pipelines::generation_utils::{Cache, LMHeadModel};
+//! use rust_bert::training::{Adam8bit, Adam8bitConfig, MixedPrecision};
+//! use rust_bert::Config;
+//! use std::path::Path;
+//! use tch::{nn, Device, Kind, Tensor};
+//!
+//! let config = Gpt2Config::from_file(Path::new("path/to/config.json"));
+//! let mut vs = nn::VarStore::new(Device::Cuda(0));
+//! let model = GPT2LMHeadModel::new(vs.root(), &config);
+//! vs.load("path/to/model.ot")?;
+//!
+//! let mut mixed_precision = MixedPrecision::new(&mut vs, Default::default())?;
+//! let mut optimizer = Adam8bit::new(mixed_precision.master_var_store(), Adam8bitConfig::new(5e-5));
+//! # let batches: Vec<(Tensor, Tensor)> = vec![];
+//! for (input_ids, labels) in batches {
+//!     let logits = model
+//!         .forward_t(Some(&input_ids), Cache::None, None, None, None, None, None, None, true)?
+//!         .lm_logits;
+//!     let loss = logits
+//!         .to_kind(Kind::Float)
+//!         .view([-1, config.vocab_size])
+//!         .cross_entropy_for_logits(&labels.view([-1]));
+//!     mixed_precision.backward(&loss);
+//!     mixed_precision.step(&mut optimizer);
+//! }
+//! // Full precision weights of the fine-tuned model
+//! mixed_precision.master_var_store().save("path/to/fine_tuned_model.ot")?;
+//! # Ok(())
+//! # }
+//! ```
+
+pub mod datasets;
+pub mod evaluation;
+mod mixed_precision;
+mod optimizer;
+mod scheduler;
+mod trainer;
+mod weight_averaging;
+
+pub use mixed_precision::{GradScaler, GradScalerConfig, MixedPrecision};
+pub use optimizer::{Adam8bit, Adam8bitConfig, TrainingOptimizer};
+pub use scheduler::LearningRateScheduler;
+pub use trainer::{EpochSummary, Trainer, TrainerConfig, TrainingSummary};
+pub use weight_averaging::{
+    average_checkpoints, save_pretrained, CheckpointAverager, ExponentialMovingAverage,
+    CONFIG_NAME, WEIGHTS_NAME,
+};
@@ -0,0 +1,379 @@
+// Copyright 2022 Guillaume Becquin
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//     http://www.apache.org/licenses/LICENSE-2.0
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use tch::{nn, no_grad, Kind, Tensor};
+
+/// # Optimizer used by the training utilities
+/// Common interface of the optimizers of `tch` (`nn::Optimizer`) and of this crate (`Adam8bit`)
+pub trait TrainingOptimizer {
+    /// Updates the parameters from their gradients
+    fn step(&mut self);
+
+    /// Resets the gradients of the parameters to zero
+    fn zero_grad(&mut self);
+
+    /// Sets the learning rate of the optimizer
+    fn set_learning_rate(&mut self, learning_rate: f64);
+}
+
+impl TrainingOptimizer for nn::Optimizer {
+    fn step(&mut self) {
+        nn::Optimizer::step(self)
+    }
+
+    fn zero_grad(&mut self) {
+        nn::Optimizer::zero_grad(self)
+    }
+
+    fn set_learning_rate(&mut self, learning_rate: f64) {
+        self.set_lr(learning_rate)
+    }
+}
+
+/// # Configuration for the 8-bit Adam optimizer
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Adam8bitConfig {
+    /// Learning rate (default: 1e-3)
+    pub learning_rate: f64,
+    /// Decay rate of the first moment estimate (default: 0.9)
+    pub beta1: f64,
+    /// Decay rate of the second moment estimate (default: 0.999)
+    pub beta2: f64,
+    /// Term added to the denominator for numerical stability (default: 1e-8)
+    pub eps: f64,
+    /// Decoupled weight decay, as in AdamW (default: 0.0)
+    pub weight_decay: f64,
+    /// Number of values sharing a quantization scale (default: 2048)
+    pub block_size: i64,
+    /// Parameters with fewer elements keep their moment estimates in full precision (default: 4096)
+    pub min_quantized_size: i64,
+}
+
+impl Adam8bitConfig {
+    /// Creates a new `Adam8bitConfig` with the default settings and the given learning rate
+    ///
+    /// # Arguments
+    ///
+    /// * `learning_rate` - Learning rate of the optimizer
+    pub fn new(learning_rate: f64) -> Adam8bitConfig {
+        Adam8bitConfig {
+            learning_rate,
+            ..Default::default()
+        }
+    }
+}
+
+impl Default for Adam8bitConfig {
+    fn default() -> Self {
+        Adam8bitConfig {
+            learning_rate: 1e-3,
+            beta1: 0.9,
+            beta2: 0.999,
+            eps: 1e-8,
+            weight_decay: 0.0,
+            block_size: 2048,
+            min_quantized_size: 4096,
+        }
+    }
+}
+
+/// Block-wise 8-bit quantization of a tensor: each block of values is scaled by its maximum absolute value
+struct QuantizedTensor {
+    values: Tensor,
+    scales: Tensor,
+    shape: Vec<i64>,
+    signed: bool,
+}
+
+impl QuantizedTensor {
+    /// Quantizes a tensor, to signed 8-bit integers if `signed` and to unsigned 8-bit integers otherwise (the
+    /// values are then expected to be non-negative, and are rounded up)
+    fn new(tensor: &Tensor, block_size: i64, signed: bool) -> QuantizedTensor {
+        let num_elements = tensor.numel() as i64;
+        let num_blocks = (num_elements + block_size - 1) / block_size;
+        let blocks = tensor
+            .to_kind(Kind::Float)
+            .flatten(0, -1)
+            .constant_pad_nd(&[0, num_blocks * block_size - num_elements])
+            .view([num_blocks, block_size]);
+        let scales = blocks.abs().amax(&[1], true).clamp_min(1e-12);
+        let values = if signed {
+            (blocks / &scales * 127.0).round().to_kind(Kind::Int8)
+        } else {
+            (blocks / &scales * 255.0)
+                .ceil()
+                .clamp(0.0, 255.0)
+                .to_kind(Kind::Uint8)
+        };
+        QuantizedTensor {
+            values,
+            scales,
+            shape: tensor.size(),
+            signed,
+        }
+    }
+
+    fn dequantize(&self) -> Tensor {
+        let max_value = if self.signed { 127.0 } else { 255.0 };
+        let num_elements = self.shape.iter().product::<i64>();
+        (self.values.to_kind(Kind::Float) * &self.scales / max_value)
+            .flatten(0, -1)
+            .narrow(0, 0, num_elements)
+            .view(self.shape.as_slice())
+    }
+
+    fn num_bytes(&self) -> usize {
+        self.values.numel() + 4 * self.scales.numel()
+    }
+}
+
+/// Moment estimate of a parameter, quantized for the large parameters
+enum MomentState {
+    Full(Tensor),
+    Quantized(QuantizedTensor),
+}
+
+impl MomentState {
+    fn new(tensor: Tensor, quantize: bool, block_size: i64, signed: bool) -> MomentState {
+        if quantize {
+            MomentState::Quantized(QuantizedTensor::new(&tensor, block_size, signed))
+        } else {
+            MomentState::Full(tensor)
+        }
+    }
+
+    fn get(&self) -> Tensor {
+        match self {
+            MomentState::Full(tensor) => tensor.shallow_clone(),
+            MomentState::Quantized(quantized) => quantized.dequantize(),
+        }
+    }
+
+    fn num_bytes(&self) -> usize {
+        match self {
+            MomentState::Full(tensor) => 4 * tensor.numel(),
+            MomentState::Quantized(quantized) => quantized.num_bytes(),
+        }
+    }
+}
+
+struct AdamState {
+    // Number of updates of the parameter, which may differ between parameters if some did not receive gradients
+    step: i32,
+    exp_avg: MomentState,
+    // Square root of the second moment estimate, extending the range of the quantized values
+    exp_avg_sqrt: MomentState,
+}
+
+/// # 8-bit Adam optimizer
+/// Adam optimizer ([Kingma and Ba, 2014](https://arxiv.org/abs/1412.6980)) with decoupled weight decay, storing
+/// its moment estimates with a block-wise 8-bit quantization ([Dettmers et al., 2021](https://arxiv.org/abs/2110.02861)).
+/// The optimizer state takes about a quarter of the memory of the full precision Adam state (2 bytes per parameter
+/// instead of 8), making the fine-tuning of medium-sized models possible on consumer GPUs.
+///
+/// The moment estimates are dequantized and updated in full precision at each step. The second moment is stored as
+/// its square root, rounded up so that the quantization never increases the size of an update. The parameters
+/// with fewer than `min_quantized_size` elements (biases, layer normalization weights) keep a full precision state.
+pub struct Adam8bit {
+    config: Adam8bitConfig,
+    parameters: Vec<Tensor>,
+    states: Vec<Option<AdamState>>,
+}
+
+impl Adam8bit {
+    /// Creates a new `Adam8bit` optimizer for the trainable variables of a variable store
+    ///
+    /// # Arguments
+    ///
+    /// * `var_store` - Variable store holding the parameters to optimize
+    /// * `config` - `Adam8bitConfig` optimizer settings
+    ///
+    /// # Example
+    ///
+    /// ```no_run
+    /// use rust_bert::training::{Adam8bit, Adam8bitConfig, TrainingOptimizer};
+    /// use tch::{nn, Device, Kind, Tensor};
+    ///
+    /// let vs = nn::VarStore::new(Device::cuda_if_available());
+    /// let linear = nn::linear(vs.root(), 1024, 1024, Default::default());
+    /// let mut optimizer = Adam8bit::new(&vs, Adam8bitConfig::new(5e-5));
+    ///
+    /// let input = Tensor::randn(&[8, 1024], (Kind::Float, vs.device()));
+    /// let loss = input.apply(&linear).square().mean(Kind::Float);
+    /// loss.backward();
+    /// optimizer.step();
+    /// optimizer.zero_grad();
+    /// ```
+    pub fn new(var_store: &nn::VarStore, config: Adam8bitConfig) -> Adam8bit {
+        let parameters = var_store.trainable_variables();
+        let states = parameters.iter().map(|_| None).collect();
+        Adam8bit {
+            config,
+            parameters,
+            states,
+        }
+    }
+
+    /// Returns the current learning rate
+    pub fn learning_rate(&self) -> f64 {
+        self.config.learning_rate
+    }
+
+    /// Returns the memory used by the moment estimates, in bytes
+    pub fn state_size_bytes(&self) -> usize {
+        self.states
+            .iter()
+            .flatten()
+            .map(|state| state.exp_avg.num_bytes() + state.exp_avg_sqrt.num_bytes())
+            .sum()
+    }
+}
+
+impl TrainingOptimizer for Adam8bit {
+    fn step(&mut self) {
+        let config = self.config;
+        no_grad(|| {
+            for (parameter, state) in self.parameters.iter_mut().zip(self.states.iter_mut()) {
+                let grad = parameter.grad();
+                if !grad.defined() {
+                    continue;
+                }
+                let grad = grad.to_kind(Kind::Float);
+                let (step, exp_avg, exp_avg_sq) = match state {
+                    Some(state) => (
+                        state.step + 1,
+                        state.exp_avg.get(),
+                        state.exp_avg_sqrt.get().square(),
+                    ),
+                    None => (1, grad.zeros_like(), grad.zeros_like()),
+                };
+                let bias_correction1 = 1.0 - config.beta1.powi(step);
+                let bias_correction2 = 1.0 - config.beta2.powi(step);
+                let exp_avg = exp_avg * config.beta1 + &grad * (1.0 - config.beta1);
+                let exp_avg_sqrt =
+                    (exp_avg_sq * config.beta2 + grad.square() * (1.0 - config.beta2)).sqrt();
+
+                let update = (&exp_avg / bias_correction1)
+                    / (&exp_avg_sqrt / bias_correction2.sqrt() + config.eps)
+                    * config.learning_rate;
+                if config.weight_decay > 0.0 {
+                    let _ =
+                        parameter.g_mul_scalar_(1.0 - config.learning_rate * config.weight_decay);
+                }
+                let _ = parameter.g_sub_(&update.to_kind(parameter.kind()));
+
+                let quantize = parameter.numel() as i64 >= config.min_quantized_size;
+                *state = Some(AdamState {
+                    step,
+                    exp_avg: MomentState::new(exp_avg, quantize, config.block_size, true),
+                    exp_avg_sqrt: MomentState::new(
+                        exp_avg_sqrt,
+                        quantize,
+                        config.block_size,
+                        false,
+                    ),
+                });
+            }
+        });
+    }
+
+    fn zero_grad(&mut self) {
+        for parameter in self.parameters.iter_mut() {
+            parameter.zero_grad();
+        }
+    }
+
+    fn set_learning_rate(&mut self, learning_rate: f64) {
+        self.config.learning_rate = learning_rate;
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use tch::nn::OptimizerConfig;
+    use tch::Device;
+
+    #[test]
+    fn quantization() {
+        let tensor = Tensor::randn(&[3, 1000], (Kind::Float, Device::Cpu));
+        let quantized = QuantizedTensor::new(&tensor, 256, true);
+        let max_error = f64::from((quantized.dequantize() - &tensor).abs().max());
+        assert!(max_error <= f64::from(tensor.abs().max()) / 254.0 + 1e-6);
+        assert_eq!(quantized.num_bytes(), 3072 + 4 * 12);
+
+        // The unsigned quantization never underestimates a value
+        let tensor = tensor.abs();
+        let quantized = QuantizedTensor::new(&tensor, 256, false);
+        assert!(f64::from((quantized.dequantize() - &tensor).min()) >= -1e-6);
+    }
+
+    #[test]
+    fn adam_8bit() -> anyhow::Result<()> {
+        let target = Tensor::randn(&[64, 128], (Kind::Float, Device::Cpu));
+        let mut losses = vec![];
+        for quantized in [true, false] {
+            let vs = nn::VarStore::new(Device::Cpu);
+            let weight = vs.root().zeros("weight", &[64, 128]);
+            let bias = vs.root().zeros("bias", &[128]);
+            let mut optimizer: Box<dyn TrainingOptimizer> = if quantized {
+                Box::new(Adam8bit::new(&vs, Adam8bitConfig::new(0.05)))
+            } else {
+                Box::new(nn::Adam::default().build(&vs, 0.05)?)
+            };
+            for _ in 0..200 {
+                let loss = (&weight + &bias - &target).square().mean(Kind::Float);
+                optimizer.zero_grad();
+                loss.backward();
+                optimizer.step();
+            }
+            losses.push(f64::from(
+                (&weight + &bias - &target).square().mean(Kind::Float),
+            ));
+        }
+        assert!(losses[0] < 0.01);
+        assert!((losses[0] - losses[1]).abs() < 0.01);
+
+        // The state of the weight is quantized, the state of the bias is kept in full precision
+        let vs = nn::VarStore::new(Device::Cpu);
+        let weight = vs.root().ones("weight", &[64, 128]);
+        let bias = vs.root().ones("bias", &[128]);
+        let mut optimizer = Adam8bit::new(&vs, Adam8bitConfig::default());
+        (&weight + &bias).sum(Kind::Float).backward();
+        optimizer.step();
+        assert_eq!(
+            optimizer.state_size_bytes(),
+            2 * (8192 + 4 * 4) + 2 * 4 * 128
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn adam_8bit_bias_correction_per_parameter() {
+        // The first update of a parameter has the size of the learning rate, even if other parameters were
+        // updated before it received a gradient
+        let vs = nn::VarStore::new(Device::Cpu);
+        let first = vs.root().zeros("first", &[4]);
+        let second = vs.root().zeros("second", &[4]);
+        let mut optimizer = Adam8bit::new(&vs, Adam8bitConfig::new(0.1));
+        for _ in 0..3 {
+            optimizer.zero_grad();
+            first.sum(Kind::Float).backward();
+            optimizer.step();
+        }
+        optimizer.zero_grad();
+        second.sum(Kind::Float).backward();
+        optimizer.step();
+
+        let expected = Tensor::full(&[4], -0.1, (Kind::Float, Device::Cpu));
+        assert!(second.allclose(&expected, 1e-4, 1e-6, false));
+    }
+}
@@ -0,0 +1,234 @@
+// Copyright 2022 Guillaume Becquin
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//     http://www.apache.org/licenses/LICENSE-2.0
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use std::f64::consts::PI;
+
+/// # Learning rate schedule
+/// Learning rate as a function of the optimizer step. `total_steps` is the number of optimizer steps of the training
+/// run (number of epochs times number of batches per epoch): the learning rate of the last step is kept afterwards.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum LearningRateScheduler {
+    /// Linear increase from 0 to `learning_rate` during `warmup_steps`, followed by a linear decrease to 0
+    LinearWithWarmup {
+        learning_rate: f64,
+        warmup_steps: usize,
+        total_steps: usize,
+    },
+    /// Linear increase from 0 to `learning_rate` during `warmup_steps`, followed by a cosine decrease to
+    /// `min_learning_rate`
+    CosineWithWarmup {
+        learning_rate: f64,
+        warmup_steps: usize,
+        total_steps: usize,
+        min_learning_rate: f64,
+    },
+    /// One-cycle policy ([Super-Convergence](https://arxiv.org/abs/1708.07120) Smith, Topin, 2017): cosine increase
+    /// from `max_learning_rate / div_factor` to `max_learning_rate` during the first `pct_start` fraction of the
+    /// steps, followed by a cosine decrease to `max_learning_rate / (div_factor * final_div_factor)`
+    OneCycle {
+        max_learning_rate: f64,
+        total_steps: usize,
+        pct_start: f64,
+        div_factor: f64,
+        final_div_factor: f64,
+    },
+}
+
+impl LearningRateScheduler {
+    /// Creates a linear schedule with warmup
+    ///
+    /// # Arguments
+    ///
+    /// * `learning_rate` - Peak learning rate, reached at the end of the warmup
+    /// * `warmup_steps` - Number of warmup steps
+    /// * `total_steps` - Total number of optimizer steps
+    pub fn linear_with_warmup(
+        learning_rate: f64,
+        warmup_steps: usize,
+        total_steps: usize,
+    ) -> LearningRateScheduler {
+        LearningRateScheduler::LinearWithWarmup {
+            learning_rate,
+            warmup_steps,
+            total_steps,
+        }
+    }
+
+    /// Creates a cosine schedule with warmup, decaying to 0
+    ///
+    /// # Arguments
+    ///
+    /// * `learning_rate` - Peak learning rate, reached at the end of the warmup
+    /// * `warmup_steps` - Number of warmup steps
+    /// * `total_steps` - Total number of optimizer steps
+    pub fn cosine_with_warmup(
+        learning_rate: f64,
+        warmup_steps: usize,
+        total_steps: usize,
+    ) -> LearningRateScheduler {
+        LearningRateScheduler::CosineWithWarmup {
+            learning_rate,
+            warmup_steps,
+            total_steps,
+            min_learning_rate: 0.0,
+        }
+    }
+
+    /// Creates a one-cycle schedule with the default settings of PyTorch's `OneCycleLR` (30% of increasing steps,
+    /// initial learning rate of `max_learning_rate / 25` and final learning rate of `max_learning_rate / 25e4`)
+    ///
+    /// # Arguments
+    ///
+    /// * `max_learning_rate` - Peak learning rate
+    /// * `total_steps` - Total number of optimizer steps
+    pub fn one_cycle(max_learning_rate: f64, total_steps: usize) -> LearningRateScheduler {
+        LearningRateScheduler::OneCycle {
+            max_learning_rate,
+            total_steps,
+            pct_start: 0.3,
+            div_factor: 25.0,
+            final_div_factor: 1e4,
+        }
+    }
+
+    /// Returns the learning rate of an optimizer step
+    ///
+    /// # Arguments
+    ///
+    /// * `step` - Index of the optimizer step, starting at 0
+    pub fn learning_rate(&self, step: usize) -> f64 {
+        match *self {
+            LearningRateScheduler::LinearWithWarmup {
+                learning_rate,
+                warmup_steps,
+                total_steps,
+            } => {
+                if step < warmup_steps {
+                    learning_rate * step as f64 / warmup_steps as f64
+                } else {
+                    let decay_steps = total_steps.saturating_sub(warmup_steps).max(1);
+                    let remaining = total_steps.saturating_sub(step);
+                    learning_rate * remaining as f64 / decay_steps as f64
+                }
+            }
+            LearningRateScheduler::CosineWithWarmup {
+                learning_rate,
+                warmup_steps,
+                total_steps,
+                min_learning_rate,
+            } => {
+                if step < warmup_steps {
+                    learning_rate * step as f64 / warmup_steps as f64
+                } else {
+                    let progress = progress(
+                        step - warmup_steps,
+                        total_steps.saturating_sub(warmup_steps),
+                    );
+                    cosine_annealing(learning_rate, min_learning_rate, progress)
+                }
+            }
+            LearningRateScheduler::OneCycle {
+                max_learning_rate,
+                total_steps,
+                pct_start,
+                div_factor,
+                final_div_factor,
+            } => {
+                let initial_learning_rate = max_learning_rate / div_factor;
+                let final_learning_rate = initial_learning_rate / final_div_factor;
+                let increasing_steps = ((total_steps as f64 * pct_start) as usize).max(1);
+                if step < increasing_steps {
+                    cosine_annealing(
+                        initial_learning_rate,
+                        max_learning_rate,
+                        progress(step, increasing_steps),
+                    )
+                } else {
+                    cosine_annealing(
+                        max_learning_rate,
+                        final_learning_rate,
+                        progress(
+                            step - increasing_steps,
+                            total_steps.saturating_sub(increasing_steps),
+                        ),
+                    )
+                }
+            }
+        }
+    }
+}
+
+/// Fraction of a phase of `num_steps` steps completed at `step`, in [0, 1]
+fn progress(step: usize, num_steps: usize) -> f64 {
+    if num_steps == 0 {
+        1.0
+    } else {
+        (step as f64 / num_steps as f64).min(1.0)
+    }
+}
+
+/// Cosine interpolation from `start` to `end`
+fn cosine_annealing(start: f64, end: f64, progress: f64) -> f64 {
+    end + (start - end) * (1.0 + (PI * progress).cos()) / 2.0
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn assert_close(value: f64, expected: f64) {
+        assert!((value - expected).abs() < 1e-9, "{} != {}", value, expected);
+    }
+
+    #[test]
+    fn linear_with_warmup() {
+        let scheduler = LearningRateScheduler::linear_with_warmup(1.0, 10, 110);
+        assert_close(scheduler.learning_rate(0), 0.0);
+        assert_close(scheduler.learning_rate(5), 0.5);
+        assert_close(scheduler.learning_rate(10), 1.0);
+        assert_close(scheduler.learning_rate(60), 0.5);
+        assert_close(scheduler.learning_rate(110), 0.0);
+        assert_close(scheduler.learning_rate(200), 0.0);
+    }
+
+    #[test]
+    fn cosine_with_warmup() {
+        let scheduler = LearningRateScheduler::CosineWithWarmup {
+            learning_rate: 1.0,
+            warmup_steps: 10,
+            total_steps: 110,
+            min_learning_rate: 0.1,
+        };
+        assert_close(scheduler.learning_rate(5), 0.5);
+        assert_close(scheduler.learning_rate(10), 1.0);
+        assert_close(scheduler.learning_rate(60), 0.55);
+        assert_close(scheduler.learning_rate(110), 0.1);
+        assert_close(scheduler.learning_rate(500), 0.1);
+    }
+
+    #[test]
+    fn one_cycle() {
+        let scheduler = LearningRateScheduler::one_cycle(1.0, 100);
+        assert_close(scheduler.learning_rate(0), 0.04);
+        assert_close(scheduler.learning_rate(15), 0.52);
+        assert_close(scheduler.learning_rate(30), 1.0);
+        assert_close(scheduler.learning_rate(100), 0.04 / 1e4);
+        let learning_rates: Vec<f64> = (0..=100)
+            .map(|step| scheduler.learning_rate(step))
+            .collect();
+        assert!(learning_rates[..30]
+            .windows(2)
+            .all(|pair| pair[0] < pair[1]));
+        assert!(learning_rates[30..]
+            .windows(2)
+            .all(|pair| pair[0] > pair[1]));
+    }
+}
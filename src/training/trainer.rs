@@ -0,0 +1,585 @@
+// Copyright 2022 Guillaume Becquin
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//     http://www.apache.org/licenses/LICENSE-2.0
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use crate::common::error::RustBertError;
+use crate::training::evaluation::Evaluator;
+use crate::training::{
+    CheckpointAverager, ExponentialMovingAverage, LearningRateScheduler, MixedPrecision,
+    TrainingOptimizer,
+};
+use std::collections::HashMap;
+use std::path::PathBuf;
+use tch::{nn, no_grad, Tensor};
+
+/// # Configuration for the `Trainer`
+#[derive(Debug, Clone, PartialEq)]
+pub struct TrainerConfig {
+    /// Number of passes over the training set (default: 3)
+    pub num_epochs: usize,
+    /// Name of the validation metric used to select the best checkpoint, as returned by the evaluator (default: `f1`)
+    pub metric_for_best_model: String,
+    /// Flag indicating if a higher value of the metric is better (default: true)
+    pub greater_is_better: bool,
+    /// Path of the file where the weights of the best epoch are saved. The best epoch is only reported if not set
+    /// (default: None)
+    pub best_checkpoint_path: Option<PathBuf>,
+    /// Learning rate schedule applied before each optimizer step. The learning rate of the optimizer is left
+    /// unchanged if not set (default: None)
+    pub scheduler: Option<LearningRateScheduler>,
+    /// Number of epochs without improvement of the validation metric after which the training stops. The training
+    /// runs for all epochs if not set (default: None)
+    pub early_stopping_patience: Option<usize>,
+    /// Minimum change of the validation metric counted as an improvement for early stopping (default: 0.0)
+    pub early_stopping_threshold: f64,
+}
+
+impl TrainerConfig {
+    /// Creates a new `TrainerConfig` with the default settings
+    ///
+    /// # Arguments
+    ///
+    /// * `num_epochs` - Number of passes over the training set
+    pub fn new(num_epochs: usize) -> TrainerConfig {
+        TrainerConfig {
+            num_epochs,
+            ..Default::default()
+        }
+    }
+}
+
+impl Default for TrainerConfig {
+    fn default() -> TrainerConfig {
+        TrainerConfig {
+            num_epochs: 3,
+            metric_for_best_model: "f1".to_string(),
+            greater_is_better: true,
+            best_checkpoint_path: None,
+            scheduler: None,
+            early_stopping_patience: None,
+            early_stopping_threshold: 0.0,
+        }
+    }
+}
+
+/// # Training and validation results of an epoch
+#[derive(Debug, Clone, PartialEq)]
+pub struct EpochSummary {
+    /// Index of the epoch, starting at 0
+    pub epoch: usize,
+    /// Average training loss over the batches of the epoch
+    pub training_loss: f64,
+    /// Number of optimizer steps of the epoch
+    pub num_steps: usize,
+    /// Learning rate of the last optimizer step of the epoch, if a scheduler is set
+    pub learning_rate: Option<f64>,
+    /// Validation metrics at the end of the epoch
+    pub metrics: HashMap<String, f64>,
+}
+
+/// # Results of a training run
+#[derive(Debug, Clone, PartialEq)]
+pub struct TrainingSummary {
+    /// Summary of each epoch
+    pub epochs: Vec<EpochSummary>,
+    /// Index of the epoch with the best validation metric
+    pub best_epoch: Option<usize>,
+    /// Flag indicating if the training was stopped before the last epoch by early stopping
+    pub stopped_early: bool,
+}
+
+impl TrainingSummary {
+    /// Returns the summary of the epoch with the best validation metric
+    pub fn best(&self) -> Option<&EpochSummary> {
+        self.best_epoch.map(|best_epoch| &self.epochs[best_epoch])
+    }
+}
+
+/// # Trainer
+/// Fine-tuning loop running the optimizer over the training set and evaluating the model on a validation set at the
+/// end of each epoch. The weights of the epoch with the best validation metric are saved to
+/// `TrainerConfig::best_checkpoint_path`. The learning rate can follow a `LearningRateScheduler`, and the training
+/// stops early when the validation metric does not improve for `TrainerConfig::early_stopping_patience` epochs.
+///
+/// The trainer works with any model: the loss of a training batch and the predictions on a validation batch are
+/// computed by closures, so that the model, its inputs and its task head are left to the caller. With
+/// `with_mixed_precision`, the backward pass and the optimizer steps go through `MixedPrecision`, the best
+/// checkpoint saving the full precision master weights. The trainer can also maintain an exponential moving average
+/// of the weights (`with_ema`) or keep the checkpoints of the last epochs for averaging (`with_checkpoint_averager`).
+pub struct Trainer<'a, O: TrainingOptimizer> {
+    var_store: &'a nn::VarStore,
+    optimizer: O,
+    mixed_precision: Option<MixedPrecision>,
+    ema: Option<ExponentialMovingAverage>,
+    checkpoint_averager: Option<CheckpointAverager>,
+    config: TrainerConfig,
+    step: usize,
+}
+
+impl<'a, O: TrainingOptimizer> Trainer<'a, O> {
+    /// Creates a new `Trainer`
+    ///
+    /// # Arguments
+    ///
+    /// * `var_store` - Variable store of the model to train
+    /// * `optimizer` - Optimizer of the variables of the model (of the master weights for mixed precision training)
+    /// * `config` - `TrainerConfig` training settings
+    pub fn new(var_store: &'a nn::VarStore, optimizer: O, config: TrainerConfig) -> Trainer<'a, O> {
+        Trainer {
+            var_store,
+            optimizer,
+            mixed_precision: None,
+            ema: None,
+            checkpoint_averager: None,
+            config,
+            step: 0,
+        }
+    }
+
+    /// Trains the model in mixed precision. The optimizer must be created from `MixedPrecision::master_var_store`.
+    pub fn with_mixed_precision(mut self, mixed_precision: MixedPrecision) -> Self {
+        self.mixed_precision = Some(mixed_precision);
+        self
+    }
+
+    /// Updates an exponential moving average of the weights after each optimizer step. It must be created from the
+    /// variable store of the model, or from `MixedPrecision::master_var_store` for mixed precision training.
+    pub fn with_ema(mut self, ema: ExponentialMovingAverage) -> Self {
+        self.ema = Some(ema);
+        self
+    }
+
+    /// Adds a checkpoint of the weights to the `CheckpointAverager` at the end of each epoch (the full precision
+    /// master weights for mixed precision training)
+    pub fn with_checkpoint_averager(mut self, checkpoint_averager: CheckpointAverager) -> Self {
+        self.checkpoint_averager = Some(checkpoint_averager);
+        self
+    }
+
+    /// Returns the exponential moving average of the weights, if set
+    pub fn ema(&self) -> Option<&ExponentialMovingAverage> {
+        self.ema.as_ref()
+    }
+
+    /// Returns the averager of the checkpoints of the last epochs, if set
+    pub fn checkpoint_averager(&self) -> Option<&CheckpointAverager> {
+        self.checkpoint_averager.as_ref()
+    }
+
+    /// Returns the optimizer
+    pub fn optimizer(&mut self) -> &mut O {
+        &mut self.optimizer
+    }
+
+    fn checkpoint_var_store(&self) -> &nn::VarStore {
+        match &self.mixed_precision {
+            Some(mixed_precision) => mixed_precision.master_var_store(),
+            None => self.var_store,
+        }
+    }
+
+    fn training_step(&mut self, loss: &Tensor) -> Option<f64> {
+        let learning_rate = self
+            .config
+            .scheduler
+            .map(|scheduler| scheduler.learning_rate(self.step));
+        if let Some(learning_rate) = learning_rate {
+            self.optimizer.set_learning_rate(learning_rate);
+        }
+        self.step += 1;
+        match &mut self.mixed_precision {
+            Some(mixed_precision) => {
+                mixed_precision.backward(loss);
+                mixed_precision.step(&mut self.optimizer);
+            }
+            None => {
+                self.optimizer.zero_grad();
+                loss.backward();
+                self.optimizer.step();
+            }
+        }
+        if let Some(ema) = &mut self.ema {
+            ema.update();
+        }
+        learning_rate
+    }
+
+    /// Trains the model for the configured number of epochs, evaluating it at the end of each epoch. The scheduler
+    /// steps continue from the previous calls.
+    ///
+    /// # Arguments
+    ///
+    /// * `train_batches` - Function returning the training batches for an epoch (e.g. a `DataLoader` over a shuffled
+    /// dataset, re-opened at each epoch)
+    /// * `training_loss` - Function computing the loss of a training batch (with the model in training mode)
+    /// * `validation_batches` - Function returning the validation batches
+    /// * `predict` - Function adding the predictions of the model on a validation batch to the evaluator (with the
+    /// model in evaluation mode). It runs without gradient tracking.
+    /// * `evaluator` - `Evaluator` computing the validation metrics, reset at the start of each evaluation
+    ///
+    /// # Returns
+    ///
+    /// * `TrainingSummary` Training loss and validation metrics of each epoch, with the best epoch
+    ///
+    /// # Example
+    ///
+    /// ```no_run
+    /// # fn main() -> anyhow::Result<()> {
+    /// use rust_bert::bert::{BertConfig, BertForSequenceClassification};
+    /// use rust_bert::pipelines::common::{ModelType, TokenizerOption};
+    /// use rust_bert::training::datasets::{
+    ///     DataLoader, JsonlDataset, ShuffleBuffer, TokenizedBatch, TokenizingCollator,
+    /// };
+    /// use rust_bert::training::evaluation::ClassificationEvaluator;
+    /// use rust_bert::training::{LearningRateScheduler, Trainer, TrainerConfig};
+    /// use rust_bert::Config;
+    /// use std::path::{Path, PathBuf};
+    /// use tch::{nn, nn::OptimizerConfig, Device};
+    ///
+    /// let config = BertConfig::from_file(Path::new("path/to/config.json"));
+    /// let mut vs = nn::VarStore::new(Device::cuda_if_available());
+    /// let model = BertForSequenceClassification::new(vs.root(), &config);
+    /// vs.load("path/to/model.ot")?;
+    /// let tokenizer = TokenizerOption::from_file(
+    ///     ModelType::Bert,
+    ///     "path/to/vocab.txt",
+    ///     None,
+    ///     true,
+    ///     None,
+    ///     None,
+    /// )?;
+    /// let collator = TokenizingCollator::new(&tokenizer, "text", 128).with_label_field("label");
+    ///
+    /// let optimizer = nn::AdamW::default().build(&vs, 2e-5)?;
+    /// let trainer_config = TrainerConfig {
+    ///     best_checkpoint_path: Some(PathBuf::from("path/to/best_model.ot")),
+    ///     scheduler: Some(LearningRateScheduler::linear_with_warmup(2e-5, 100, 3 * 1000)),
+    ///     early_stopping_patience: Some(2),
+    ///     ..TrainerConfig::new(3)
+    /// };
+    /// let mut trainer = Trainer::new(&vs, optimizer, trainer_config);
+    /// let mut evaluator = ClassificationEvaluator::new(2);
+    ///
+    /// let summary = trainer.train(
+    ///     || {
+    ///         let examples = ShuffleBuffer::new(JsonlDataset::open("path/to/train.jsonl")?, 10_000, 42);
+    ///         Ok(DataLoader::new(examples, &collator, 32))
+    ///     },
+    ///     |batch: &TokenizedBatch| {
+    ///         let output = model.forward_t(
+    ///             Some(&batch.input_ids.to(vs.device())),
+    ///             Some(&batch.attention_mask.to(vs.device())),
+    ///             None,
+    ///             None,
+    ///             None,
+    ///             true,
+    ///         );
+    ///         Ok(output
+    ///             .logits
+    ///             .cross_entropy_for_logits(&batch.labels.as_ref().unwrap().to(vs.device())))
+    ///     },
+    ///     || Ok(DataLoader::new(JsonlDataset::open("path/to/validation.jsonl")?, &collator, 64)),
+    ///     |batch: &TokenizedBatch, evaluator: &mut ClassificationEvaluator| {
+    ///         let output = model.forward_t(
+    ///             Some(&batch.input_ids.to(vs.device())),
+    ///             Some(&batch.attention_mask.to(vs.device())),
+    ///             None,
+    ///             None,
+    ///             None,
+    ///             false,
+    ///         );
+    ///         evaluator.add_batch(&output.logits, batch.labels.as_ref().unwrap())
+    ///     },
+    ///     &mut evaluator,
+    /// )?;
+    /// println!("Best epoch: {:?}", summary.best());
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn train<B, T, TI, L, V, VI, P, E>(
+        &mut self,
+        mut train_batches: T,
+        mut training_loss: L,
+        mut validation_batches: V,
+        mut predict: P,
+        evaluator: &mut E,
+    ) -> Result<TrainingSummary, RustBertError>
+    where
+        T: FnMut() -> Result<TI, RustBertError>,
+        TI: IntoIterator<Item = Result<B, RustBertError>>,
+        L: FnMut(&B) -> Result<Tensor, RustBertError>,
+        V: FnMut() -> Result<VI, RustBertError>,
+        VI: IntoIterator<Item = Result<B, RustBertError>>,
+        P: FnMut(&B, &mut E) -> Result<(), RustBertError>,
+        E: Evaluator,
+    {
+        let mut summary = TrainingSummary {
+            epochs: Vec::with_capacity(self.config.num_epochs),
+            best_epoch: None,
+            stopped_early: false,
+        };
+        let mut best_metric: Option<f64> = None;
+        let mut epochs_without_improvement = 0;
+
+        for epoch in 0..self.config.num_epochs {
+            let mut loss_sum = 0.0;
+            let mut num_steps = 0;
+            let mut learning_rate = None;
+            for batch in train_batches()? {
+                let loss = training_loss(&batch?)?;
+                loss_sum += loss.double_value(&[]);
+                learning_rate = self.training_step(&loss);
+                num_steps += 1;
+            }
+            if let Some(checkpoint_averager) = &mut self.checkpoint_averager {
+                let var_store = match &self.mixed_precision {
+                    Some(mixed_precision) => mixed_precision.master_var_store(),
+                    None => self.var_store,
+                };
+                checkpoint_averager.add_checkpoint(var_store);
+            }
+
+            evaluator.reset();
+            no_grad(|| -> Result<(), RustBertError> {
+                for batch in validation_batches()? {
+                    predict(&batch?, evaluator)?;
+                }
+                Ok(())
+            })?;
+            let metrics = evaluator.compute();
+
+            let metric = *metrics
+                .get(&self.config.metric_for_best_model)
+                .ok_or_else(|| {
+                    RustBertError::InvalidConfigurationError(format!(
+                        "The evaluator does not compute the metric {}, available metrics: {:?}",
+                        self.config.metric_for_best_model,
+                        metrics.keys().collect::<Vec<&String>>()
+                    ))
+                })?;
+            let greater_is_better = self.config.greater_is_better;
+            let improvement = |threshold: f64| {
+                best_metric.map_or(true, |best_metric| {
+                    if greater_is_better {
+                        metric > best_metric + threshold
+                    } else {
+                        metric < best_metric - threshold
+                    }
+                })
+            };
+            if improvement(self.config.early_stopping_threshold) {
+                epochs_without_improvement = 0;
+            } else {
+                epochs_without_improvement += 1;
+            }
+            if improvement(0.0) {
+                best_metric = Some(metric);
+                summary.best_epoch = Some(epoch);
+                if let Some(best_checkpoint_path) = &self.config.best_checkpoint_path {
+                    self.checkpoint_var_store().save(best_checkpoint_path)?;
+                }
+            }
+
+            summary.epochs.push(EpochSummary {
+                epoch,
+                training_loss: if num_steps > 0 {
+                    loss_sum / num_steps as f64
+                } else {
+                    0.0
+                },
+                num_steps,
+                learning_rate,
+                metrics,
+            });
+
+            if let Some(patience) = self.config.early_stopping_patience {
+                if epochs_without_improvement >= patience && epoch + 1 < self.config.num_epochs {
+                    summary.stopped_early = true;
+                    break;
+                }
+            }
+        }
+        Ok(summary)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::training::evaluation::ClassificationEvaluator;
+    use tch::nn::{Module, OptimizerConfig};
+    use tch::Device;
+
+    type Batches = Vec<Result<(Tensor, Tensor), RustBertError>>;
+
+    #[test]
+    fn trainer_selects_best_epoch() -> anyhow::Result<()> {
+        tch::manual_seed(0);
+        let vs = nn::VarStore::new(Device::Cpu);
+        let classifier = nn::linear(vs.root(), 2, 2, Default::default());
+        let optimizer = nn::Sgd::default().build(&vs, 0.5)?;
+        let checkpoint_path = std::env::temp_dir().join("rust_bert_trainer_best_model.ot");
+        let config = TrainerConfig {
+            metric_for_best_model: "accuracy".to_string(),
+            best_checkpoint_path: Some(checkpoint_path.clone()),
+            ..TrainerConfig::new(10)
+        };
+        let mut trainer = Trainer::new(&vs, optimizer, config);
+
+        // Linearly separable points, labelled by the sign of their first coordinate
+        let inputs = Tensor::of_slice(&[1.0f32, 0.5, 2.0, -1.0, -1.0, 0.3, -2.0, 1.0]).view([4, 2]);
+        let labels = Tensor::of_slice(&[1i64, 1, 0, 0]);
+        let batches = || -> Result<Batches, RustBertError> {
+            Ok(vec![Ok((inputs.shallow_clone(), labels.shallow_clone()))])
+        };
+        let mut evaluator = ClassificationEvaluator::new(2);
+
+        let summary = trainer.train(
+            batches,
+            |(inputs, labels): &(Tensor, Tensor)| {
+                Ok(classifier.forward(inputs).cross_entropy_for_logits(labels))
+            },
+            batches,
+            |(inputs, labels): &(Tensor, Tensor), evaluator: &mut ClassificationEvaluator| {
+                evaluator.add_batch(&classifier.forward(inputs), labels)
+            },
+            &mut evaluator,
+        )?;
+
+        assert_eq!(summary.epochs.len(), 10);
+        assert!(summary.epochs.iter().all(|epoch| epoch.num_steps == 1));
+        assert!(summary.epochs[9].training_loss < summary.epochs[0].training_loss);
+        let best = summary.best().unwrap();
+        assert_eq!(best.metrics["accuracy"], 1.0);
+        assert!(summary.epochs[..best.epoch]
+            .iter()
+            .all(|epoch| epoch.metrics["accuracy"] < 1.0));
+        assert!(checkpoint_path.exists());
+        std::fs::remove_file(checkpoint_path)?;
+        Ok(())
+    }
+
+    #[test]
+    fn trainer_updates_weight_averages() -> anyhow::Result<()> {
+        let vs = nn::VarStore::new(Device::Cpu);
+        let classifier = nn::linear(vs.root(), 2, 2, Default::default());
+        let optimizer = nn::Sgd::default().build(&vs, 0.1)?;
+        let config = TrainerConfig {
+            metric_for_best_model: "accuracy".to_string(),
+            ..TrainerConfig::new(3)
+        };
+        let mut trainer = Trainer::new(&vs, optimizer, config)
+            .with_ema(ExponentialMovingAverage::new(&vs, 0.9)?)
+            .with_checkpoint_averager(CheckpointAverager::new(2));
+        let inputs = Tensor::of_slice(&[1.0f32, 0.5, -1.0, -1.0]).view([2, 2]);
+        let labels = Tensor::of_slice(&[1i64, 0]);
+        let batches = || -> Result<Batches, RustBertError> {
+            Ok(vec![
+                Ok((inputs.shallow_clone(), labels.shallow_clone())),
+                Ok((inputs.shallow_clone(), labels.shallow_clone())),
+            ])
+        };
+
+        trainer.train(
+            batches,
+            |(inputs, labels): &(Tensor, Tensor)| {
+                Ok(classifier.forward(inputs).cross_entropy_for_logits(labels))
+            },
+            batches,
+            |(inputs, labels): &(Tensor, Tensor), evaluator: &mut ClassificationEvaluator| {
+                evaluator.add_batch(&classifier.forward(inputs), labels)
+            },
+            &mut ClassificationEvaluator::new(2),
+        )?;
+
+        let ema = trainer.ema().unwrap();
+        assert_eq!(ema.num_updates(), 6);
+        let averaged_weight = &ema.var_store().variables()["weight"];
+        assert!(!averaged_weight.allclose(&classifier.ws, 1e-6, 1e-6, false));
+        assert_eq!(trainer.checkpoint_averager().unwrap().len(), 2);
+        Ok(())
+    }
+
+    #[test]
+    fn trainer_stops_early() -> anyhow::Result<()> {
+        let vs = nn::VarStore::new(Device::Cpu);
+        let classifier = nn::linear(vs.root(), 2, 2, Default::default());
+        let optimizer = nn::Sgd::default().build(&vs, 0.1)?;
+        let config = TrainerConfig {
+            metric_for_best_model: "accuracy".to_string(),
+            scheduler: Some(LearningRateScheduler::linear_with_warmup(0.1, 2, 10)),
+            early_stopping_patience: Some(2),
+            ..TrainerConfig::new(10)
+        };
+        let mut trainer = Trainer::new(&vs, optimizer, config);
+        let inputs = Tensor::of_slice(&[1.0f32, 0.5, -1.0, -1.0]).view([2, 2]);
+        let labels = Tensor::of_slice(&[1i64, 0]);
+        let batches = || -> Result<Batches, RustBertError> {
+            Ok(vec![Ok((inputs.shallow_clone(), labels.shallow_clone()))])
+        };
+
+        // Constant predictions: the validation accuracy never improves after the first epoch
+        let summary = trainer.train(
+            batches,
+            |(inputs, labels): &(Tensor, Tensor)| {
+                Ok(classifier.forward(inputs).cross_entropy_for_logits(labels))
+            },
+            batches,
+            |(_, labels): &(Tensor, Tensor), evaluator: &mut ClassificationEvaluator| {
+                evaluator.add_batch(&Tensor::of_slice(&[1i64, 1]), labels)
+            },
+            &mut ClassificationEvaluator::new(2),
+        )?;
+
+        assert!(summary.stopped_early);
+        assert_eq!(summary.epochs.len(), 3);
+        assert_eq!(summary.best_epoch, Some(0));
+        let learning_rates: Vec<f64> = summary
+            .epochs
+            .iter()
+            .map(|epoch| epoch.learning_rate.unwrap())
+            .collect();
+        assert_eq!(learning_rates, vec![0.0, 0.05, 0.1]);
+        Ok(())
+    }
+
+    #[test]
+    fn trainer_rejects_unknown_metric() {
+        let vs = nn::VarStore::new(Device::Cpu);
+        let classifier = nn::linear(vs.root(), 2, 2, Default::default());
+        let optimizer = nn::Sgd::default().build(&vs, 0.1).unwrap();
+        let config = TrainerConfig {
+            metric_for_best_model: "exact_match".to_string(),
+            ..TrainerConfig::new(1)
+        };
+        let mut trainer = Trainer::new(&vs, optimizer, config);
+        let inputs = Tensor::of_slice(&[1.0f32, 0.5]).view([1, 2]);
+        let labels = Tensor::of_slice(&[1i64]);
+        let batches = || -> Result<Batches, RustBertError> {
+            Ok(vec![Ok((inputs.shallow_clone(), labels.shallow_clone()))])
+        };
+
+        let result = trainer.train(
+            batches,
+            |(inputs, labels): &(Tensor, Tensor)| {
+                Ok(classifier.forward(inputs).cross_entropy_for_logits(labels))
+            },
+            batches,
+            |(inputs, labels): &(Tensor, Tensor), evaluator: &mut ClassificationEvaluator| {
+                evaluator.add_batch(&classifier.forward(inputs), labels)
+            },
+            &mut ClassificationEvaluator::new(2),
+        );
+        assert!(matches!(
+            result,
+            Err(RustBertError::InvalidConfigurationError(_))
+        ));
+    }
+}
@@ -0,0 +1,524 @@
+// Copyright 2022 Guillaume Becquin
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//     http://www.apache.org/licenses/LICENSE-2.0
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use crate::RustBertError;
+use serde::Serialize;
+use std::collections::{HashMap, VecDeque};
+use std::fs;
+use std::path::Path;
+use tch::{nn, no_grad, Device, Kind, Tensor};
+
+/// Name of the weights file written by `save_pretrained`, matching the pretrained model resources
+pub const WEIGHTS_NAME: &str = "rust_model.ot";
+/// Name of the configuration file written by `save_pretrained`, matching the pretrained model resources
+pub const CONFIG_NAME: &str = "config.json";
+
+/// # Saves a model and its configuration to a directory
+/// Writes the variables of the model to `rust_model.ot` and its configuration to `config.json` in `directory`
+/// (created if needed). The directory can then be loaded with `LocalResource`s as the pretrained models. The
+/// vocabulary files are not written and should be copied from the original model.
+///
+/// # Arguments
+///
+/// * `var_store` - Variable store holding the weights to save (e.g. `ExponentialMovingAverage::var_store`)
+/// * `config` - Configuration of the model
+/// * `directory` - Path of the output directory
+///
+/// # Example
+///
+/// ```no_run
+/// # fn main() -> anyhow::Result<()> {
+/// use rust_bert::bart::{BartConfig, BartForConditionalGeneration};
+/// use rust_bert::training::save_pretrained;
+/// use rust_bert::Config;
+/// use std::path::Path;
+/// use tch::{nn, Device};
+///
+/// let config = BartConfig::from_file(Path::new("path/to/config.json"));
+/// let vs = nn::VarStore::new(Device::cuda_if_available());
+/// let model = BartForConditionalGeneration::new(vs.root(), &config);
+/// // ... fine-tuning
+/// save_pretrained(&vs, &config, "path/to/fine_tuned_model")?;
+/// # Ok(())
+/// # }
+/// ```
+pub fn save_pretrained<C, P>(
+    var_store: &nn::VarStore,
+    config: &C,
+    directory: P,
+) -> Result<(), RustBertError>
+where
+    C: Serialize,
+    P: AsRef<Path>,
+{
+    let directory = directory.as_ref();
+    fs::create_dir_all(directory)?;
+    var_store.save(directory.join(WEIGHTS_NAME))?;
+    let mut content = serde_json::to_string_pretty(config)
+        .map_err(|error| RustBertError::IOError(error.to_string()))?;
+    content.push('\n');
+    fs::write(directory.join(CONFIG_NAME), content)?;
+    Ok(())
+}
+
+// Creates a full precision, non-trainable copy of a variable in a variable store
+fn average_var_copy(var_store: &nn::VarStore, name: &str, tensor: &Tensor) -> Tensor {
+    let mut path = var_store.root();
+    let mut segments = name.split('.').collect::<Vec<&str>>();
+    let variable_name = segments.pop().unwrap();
+    for segment in segments {
+        path = &path / segment;
+    }
+    let mut variable = path.zeros_no_train(variable_name, &tensor.size());
+    no_grad(|| variable.copy_(tensor));
+    variable
+}
+
+fn is_floating_point(kind: Kind) -> bool {
+    matches!(
+        kind,
+        Kind::Half | Kind::BFloat16 | Kind::Float | Kind::Double
+    )
+}
+
+/// # Exponential moving average of the model weights
+/// Maintains an exponential moving average of the variables of a model during training: after each optimizer step,
+/// `average = decay * average + (1 - decay) * weight`. Evaluating or exporting the averaged weights instead of the
+/// last weights often improves the final quality, in particular for sequence-to-sequence models.
+///
+/// The averages are kept in full precision in a separate variable store with the same variable names as the model,
+/// that can be saved (`save_pretrained`) or copied back to the model (`copy_to`). With mixed precision training, the
+/// average should be created from `MixedPrecision::master_var_store`. It can be updated by the `Trainer` with
+/// `Trainer::with_ema`.
+pub struct ExponentialMovingAverage {
+    decay: f64,
+    warmup: bool,
+    num_updates: usize,
+    var_store: nn::VarStore,
+    // Pairs of (model variable, averaged variable)
+    variables: Vec<(Tensor, Tensor)>,
+}
+
+impl ExponentialMovingAverage {
+    /// Creates a new `ExponentialMovingAverage`, initialized with the current weights of the model
+    ///
+    /// # Arguments
+    ///
+    /// * `var_store` - Variable store of the model
+    /// * `decay` - Decay of the average, between 0 and 1 (typically 0.999 or 0.9999)
+    ///
+    /// # Example
+    ///
+    /// ```no_run
+    /// # fn main() -> anyhow::Result<()> {
+    /// use rust_bert::bart::{BartConfig, BartForConditionalGeneration};
+    /// use rust_bert::training::ExponentialMovingAverage;
+    /// use rust_bert::Config;
+    /// use std::path::Path;
+    /// use tch::{nn, Device};
+    ///
+    /// let config = BartConfig::from_file(Path::new("path/to/config.json"));
+    /// let mut vs = nn::VarStore::new(Device::cuda_if_available());
+    /// let model = BartForConditionalGeneration::new(vs.root(), &config);
+    /// vs.load("path/to/model.ot")?;
+    ///
+    /// let mut ema = ExponentialMovingAverage::new(&vs, 0.999)?.with_warmup();
+    /// // After each optimizer step
+    /// ema.update();
+    /// // Export of the averaged weights
+    /// ema.save_pretrained(&config, "path/to/averaged_model")?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn new(
+        var_store: &nn::VarStore,
+        decay: f64,
+    ) -> Result<ExponentialMovingAverage, RustBertError> {
+        if !(0.0..=1.0).contains(&decay) {
+            return Err(RustBertError::InvalidConfigurationError(format!(
+                "The decay of the moving average must be between 0 and 1, got {}",
+                decay
+            )));
+        }
+        let mut model_variables = var_store
+            .variables()
+            .into_iter()
+            .collect::<Vec<(String, Tensor)>>();
+        if model_variables.is_empty() {
+            return Err(RustBertError::ValueError(
+                "The variable store does not contain any variable".to_string(),
+            ));
+        }
+        model_variables.sort_by(|(name_a, _), (name_b, _)| name_a.cmp(name_b));
+
+        let average_var_store = nn::VarStore::new(var_store.device());
+        let mut variables = Vec::with_capacity(model_variables.len());
+        for (name, variable) in model_variables {
+            let average = average_var_copy(&average_var_store, &name, &variable);
+            variables.push((variable, average));
+        }
+
+        Ok(ExponentialMovingAverage {
+            decay,
+            warmup: false,
+            num_updates: 0,
+            var_store: average_var_store,
+            variables,
+        })
+    }
+
+    /// Increases the decay progressively over the first updates, as `min(decay, (1 + n) / (10 + n))` after `n`
+    /// updates, so that the average is not dominated by the initial weights when training from scratch.
+    pub fn with_warmup(mut self) -> Self {
+        self.warmup = true;
+        self
+    }
+
+    /// Returns the decay applied at the next update
+    pub fn decay(&self) -> f64 {
+        if self.warmup {
+            let num_updates = self.num_updates as f64;
+            self.decay.min((1.0 + num_updates) / (10.0 + num_updates))
+        } else {
+            self.decay
+        }
+    }
+
+    /// Returns the number of updates of the average
+    pub fn num_updates(&self) -> usize {
+        self.num_updates
+    }
+
+    /// Updates the average with the current weights of the model, to be called after each optimizer step.
+    /// Variables that are not floating point (e.g. integer buffers) are copied.
+    pub fn update(&mut self) {
+        let decay = self.decay();
+        no_grad(|| {
+            for (variable, average) in self.variables.iter_mut() {
+                if is_floating_point(variable.kind()) {
+                    let updated = &*average * decay + variable.to_kind(Kind::Float) * (1.0 - decay);
+                    average.copy_(&updated);
+                } else {
+                    average.copy_(variable);
+                }
+            }
+        });
+        self.num_updates += 1;
+    }
+
+    /// Returns the variable store holding the averaged weights, with the variable names of the model
+    pub fn var_store(&self) -> &nn::VarStore {
+        &self.var_store
+    }
+
+    /// Copies the averaged weights to a variable store (e.g. the model, to evaluate the averaged weights). The
+    /// weights are converted to the kind of the variables of the target.
+    ///
+    /// # Arguments
+    ///
+    /// * `var_store` - Variable store with the variable names of the averaged model
+    pub fn copy_to(&self, var_store: &mut nn::VarStore) -> Result<(), RustBertError> {
+        Ok(var_store.copy(&self.var_store)?)
+    }
+
+    /// Saves the averaged weights and the configuration of the model to a directory (see `save_pretrained`)
+    ///
+    /// # Arguments
+    ///
+    /// * `config` - Configuration of the model
+    /// * `directory` - Path of the output directory
+    pub fn save_pretrained<C, P>(&self, config: &C, directory: P) -> Result<(), RustBertError>
+    where
+        C: Serialize,
+        P: AsRef<Path>,
+    {
+        save_pretrained(&self.var_store, config, directory)
+    }
+}
+
+/// Running sum of named tensors, in full precision on the CPU
+#[derive(Default)]
+struct WeightSum {
+    sums: HashMap<String, Tensor>,
+    count: usize,
+}
+
+impl WeightSum {
+    fn add<'a>(&mut self, tensors: impl IntoIterator<Item = (&'a String, &'a Tensor)>) {
+        no_grad(|| {
+            for (name, tensor) in tensors {
+                let tensor = tensor.to_device(Device::Cpu).to_kind(Kind::Double);
+                match self.sums.get_mut(name) {
+                    Some(sum) => *sum += tensor,
+                    None => {
+                        self.sums.insert(name.clone(), tensor);
+                    }
+                }
+            }
+        });
+        self.count += 1;
+    }
+
+    fn copy_average_to(&self, var_store: &mut nn::VarStore) -> Result<(), RustBertError> {
+        if self.count == 0 {
+            return Err(RustBertError::ValueError(
+                "No checkpoint to average".to_string(),
+            ));
+        }
+        let mut variables = var_store.variables();
+        for name in variables.keys() {
+            if !self.sums.contains_key(name) {
+                return Err(RustBertError::ValueError(format!(
+                    "Variable {} not found in the averaged checkpoints",
+                    name
+                )));
+            }
+        }
+        no_grad(|| {
+            for (name, variable) in variables.iter_mut() {
+                let average = &self.sums[name] / self.count as f64;
+                variable.copy_(
+                    &average
+                        .to_kind(variable.kind())
+                        .to_device(variable.device()),
+                );
+            }
+        });
+        Ok(())
+    }
+}
+
+/// # Average of the last checkpoints of a training run
+/// Keeps copies of the weights of the last `num_checkpoints` checkpoints (e.g. the end of the last epochs) in CPU
+/// memory. Their average, copied to the model with `average_into`, is usually better than the last checkpoint. The
+/// `Trainer` adds a checkpoint at the end of each epoch with `Trainer::with_checkpoint_averager`.
+///
+/// Each checkpoint holds a full copy of the weights: checkpoints saved to files can instead be averaged with
+/// `average_checkpoints`, loading one file at a time.
+pub struct CheckpointAverager {
+    num_checkpoints: usize,
+    checkpoints: VecDeque<HashMap<String, Tensor>>,
+}
+
+impl CheckpointAverager {
+    /// Creates a new `CheckpointAverager`
+    ///
+    /// # Arguments
+    ///
+    /// * `num_checkpoints` - Number of last checkpoints averaged
+    pub fn new(num_checkpoints: usize) -> CheckpointAverager {
+        CheckpointAverager {
+            num_checkpoints,
+            checkpoints: VecDeque::with_capacity(num_checkpoints),
+        }
+    }
+
+    /// Returns the number of checkpoints currently kept
+    pub fn len(&self) -> usize {
+        self.checkpoints.len()
+    }
+
+    /// Returns true if no checkpoint was added
+    pub fn is_empty(&self) -> bool {
+        self.checkpoints.is_empty()
+    }
+
+    /// Adds a copy of the current weights of a model, dropping the oldest checkpoint if `num_checkpoints` are
+    /// already kept
+    ///
+    /// # Arguments
+    ///
+    /// * `var_store` - Variable store of the model
+    pub fn add_checkpoint(&mut self, var_store: &nn::VarStore) {
+        if self.num_checkpoints == 0 {
+            return;
+        }
+        if self.checkpoints.len() == self.num_checkpoints {
+            self.checkpoints.pop_front();
+        }
+        let checkpoint = no_grad(|| {
+            var_store
+                .variables()
+                .into_iter()
+                .map(|(name, variable)| (name, variable.to_device(Device::Cpu).copy()))
+                .collect::<HashMap<String, Tensor>>()
+        });
+        self.checkpoints.push_back(checkpoint);
+    }
+
+    /// Copies the average of the checkpoints kept to a variable store
+    ///
+    /// # Arguments
+    ///
+    /// * `var_store` - Variable store with the variable names of the checkpoints
+    pub fn average_into(&self, var_store: &mut nn::VarStore) -> Result<(), RustBertError> {
+        let mut weight_sum = WeightSum::default();
+        for checkpoint in self.checkpoints.iter() {
+            weight_sum.add(checkpoint);
+        }
+        weight_sum.copy_average_to(var_store)
+    }
+}
+
+/// # Averages checkpoint files
+/// Loads the average of the weights saved in several checkpoint files (e.g. the checkpoints of the last epochs of a
+/// training run) into a variable store. The files are loaded one at a time. The result can be exported with
+/// `save_pretrained`.
+///
+/// # Arguments
+///
+/// * `var_store` - Variable store of the model, whose variables must all be present in the checkpoints
+/// * `checkpoint_paths` - Paths of the checkpoint files
+///
+/// # Example
+///
+/// ```no_run
+/// # fn main() -> anyhow::Result<()> {
+/// use rust_bert::t5::{T5Config, T5ForConditionalGeneration};
+/// use rust_bert::training::{average_checkpoints, save_pretrained};
+/// use rust_bert::Config;
+/// use std::path::Path;
+/// use tch::{nn, Device};
+///
+/// let config = T5Config::from_file(Path::new("path/to/config.json"));
+/// let mut vs = nn::VarStore::new(Device::Cpu);
+/// let _model = T5ForConditionalGeneration::new(vs.root(), &config);
+/// average_checkpoints(
+///     &mut vs,
+///     &["path/to/epoch_8.ot", "path/to/epoch_9.ot", "path/to/epoch_10.ot"],
+/// )?;
+/// save_pretrained(&vs, &config, "path/to/averaged_model")?;
+/// # Ok(())
+/// # }
+/// ```
+pub fn average_checkpoints<P: AsRef<Path>>(
+    var_store: &mut nn::VarStore,
+    checkpoint_paths: &[P],
+) -> Result<(), RustBertError> {
+    let mut weight_sum = WeightSum::default();
+    for checkpoint_path in checkpoint_paths {
+        let checkpoint = Tensor::load_multi(checkpoint_path)?;
+        weight_sum.add(checkpoint.iter().map(|(name, tensor)| (name, tensor)));
+    }
+    weight_sum.copy_average_to(var_store)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn exponential_moving_average() -> anyhow::Result<()> {
+        let vs = nn::VarStore::new(Device::Cpu);
+        let mut weight = (&vs.root() / "layer").ones("weight", &[2]);
+        let mut ema = ExponentialMovingAverage::new(&vs, 0.9)?;
+
+        no_grad(|| weight.copy_(&Tensor::of_slice(&[2.0f32, 3.0])));
+        ema.update();
+        let average = &ema.var_store().variables()["layer.weight"];
+        assert!(average.allclose(&Tensor::of_slice(&[1.1f32, 1.2]), 1e-6, 1e-6, false));
+
+        ema.update();
+        let average = &ema.var_store().variables()["layer.weight"];
+        assert!(average.allclose(&Tensor::of_slice(&[1.19f32, 1.38]), 1e-6, 1e-6, false));
+        assert_eq!(ema.num_updates(), 2);
+
+        let mut target_vs = nn::VarStore::new(Device::Cpu);
+        let target_weight = (&target_vs.root() / "layer").zeros("weight", &[2]);
+        ema.copy_to(&mut target_vs)?;
+        assert!(target_weight.allclose(average, 1e-6, 1e-6, false));
+        Ok(())
+    }
+
+    #[test]
+    fn exponential_moving_average_warmup() -> anyhow::Result<()> {
+        let vs = nn::VarStore::new(Device::Cpu);
+        let _ = vs.root().ones("weight", &[2]);
+        let mut ema = ExponentialMovingAverage::new(&vs, 0.999)?.with_warmup();
+        assert_eq!(ema.decay(), 0.1);
+        ema.update();
+        assert!((ema.decay() - 2.0 / 11.0).abs() < 1e-12);
+
+        assert!(ExponentialMovingAverage::new(&vs, 1.5).is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn checkpoint_averager_keeps_last_checkpoints() -> anyhow::Result<()> {
+        let mut vs = nn::VarStore::new(Device::Cpu);
+        let mut weight = vs.root().zeros("weight", &[2]);
+        let mut averager = CheckpointAverager::new(2);
+
+        for value in [1.0f32, 2.0, 4.0] {
+            no_grad(|| weight.copy_(&Tensor::of_slice(&[value, -value])));
+            averager.add_checkpoint(&vs);
+        }
+        assert_eq!(averager.len(), 2);
+
+        averager.average_into(&mut vs)?;
+        assert!(weight.allclose(&Tensor::of_slice(&[3.0f32, -3.0]), 1e-6, 1e-6, false));
+        Ok(())
+    }
+
+    #[test]
+    fn average_checkpoint_files() -> anyhow::Result<()> {
+        let mut vs = nn::VarStore::new(Device::Cpu);
+        let mut weight = (&vs.root() / "layer").zeros("weight", &[2]);
+        let checkpoint_paths = [
+            std::env::temp_dir().join("rust_bert_average_checkpoint_0.ot"),
+            std::env::temp_dir().join("rust_bert_average_checkpoint_1.ot"),
+        ];
+        for (value, checkpoint_path) in [1.0f32, 2.0].iter().zip(checkpoint_paths.iter()) {
+            no_grad(|| weight.copy_(&Tensor::of_slice(&[*value, *value])));
+            vs.save(checkpoint_path)?;
+        }
+
+        average_checkpoints(&mut vs, &checkpoint_paths)?;
+        assert!(weight.allclose(&Tensor::of_slice(&[1.5f32, 1.5]), 1e-6, 1e-6, false));
+
+        let mut other_vs = nn::VarStore::new(Device::Cpu);
+        let _ = other_vs.root().zeros("bias", &[2]);
+        assert!(matches!(
+            average_checkpoints(&mut other_vs, &checkpoint_paths),
+            Err(RustBertError::ValueError(_))
+        ));
+        for checkpoint_path in checkpoint_paths {
+            std::fs::remove_file(checkpoint_path)?;
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn save_pretrained_directory() -> anyhow::Result<()> {
+        let vs = nn::VarStore::new(Device::Cpu);
+        let _ = (&vs.root() / "layer").ones("weight", &[2]);
+        let mut config = HashMap::new();
+        config.insert("hidden_size", 2);
+        let directory = std::env::temp_dir().join("rust_bert_save_pretrained");
+
+        save_pretrained(&vs, &config, &directory)?;
+
+        let mut loaded_vs = nn::VarStore::new(Device::Cpu);
+        let loaded_weight = (&loaded_vs.root() / "layer").zeros("weight", &[2]);
+        loaded_vs.load(directory.join(WEIGHTS_NAME))?;
+        assert!(loaded_weight.allclose(
+            &Tensor::ones(&[2], (Kind::Float, Device::Cpu)),
+            1e-6,
+            1e-6,
+            false
+        ));
+        let loaded_config: HashMap<String, i64> =
+            serde_json::from_str(&fs::read_to_string(directory.join(CONFIG_NAME))?)?;
+        assert_eq!(loaded_config["hidden_size"], 2);
+        fs::remove_dir_all(directory)?;
+        Ok(())
+    }
+}
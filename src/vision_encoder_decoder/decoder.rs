@@ -0,0 +1,487 @@
+// Copyright 2021 The Fairseq Authors, Microsoft Research and The HuggingFace Inc. team. All rights reserved.
+// Copyright 2022 Guillaume Becquin
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//     http://www.apache.org/licenses/LICENSE-2.0
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use crate::bart::{BartDecoderOutput, _make_causal_mask};
+use crate::common::activations::{Activation, TensorFunction};
+use crate::common::dropout::Dropout;
+use crate::vision_encoder_decoder::attention::{LayerState, TextDecoderAttention};
+use crate::{Config, RustBertError};
+use serde::{Deserialize, Serialize};
+use std::borrow::{Borrow, BorrowMut};
+use tch::nn::EmbeddingConfig;
+use tch::{nn, Kind, Tensor};
+
+/// Offset of the learned position embeddings of the TrOCR and MBart decoders
+const POSITION_OFFSET: i64 = 2;
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+/// # Text decoder configuration
+/// Defines the text decoder of the vision encoder-decoder models: a TrOCR decoder (`model_type` "trocr",
+/// post-normalization layers) or an MBart decoder (`model_type` "mbart", pre-normalization layers as used by Donut)
+pub struct TextDecoderConfig {
+    pub model_type: Option<String>,
+    pub vocab_size: i64,
+    pub d_model: i64,
+    pub decoder_layers: i64,
+    pub decoder_attention_heads: i64,
+    pub decoder_ffn_dim: i64,
+    pub activation_function: Option<Activation>,
+    pub max_position_embeddings: i64,
+    pub dropout: f64,
+    pub attention_dropout: f64,
+    pub activation_dropout: f64,
+    pub scale_embedding: Option<bool>,
+    /// Flag indicating if the embeddings are normalized (TrOCR, always true for MBart)
+    pub layernorm_embedding: Option<bool>,
+    /// Flag indicating if the position embeddings are learned (TrOCR). Only learned position embeddings are supported.
+    pub use_learned_position_embeddings: Option<bool>,
+    /// Dimension of the encoder hidden states attended by the cross-attention, if different from `d_model`
+    pub cross_attention_hidden_size: Option<i64>,
+    pub pad_token_id: Option<i64>,
+    pub bos_token_id: Option<i64>,
+    pub eos_token_id: Option<i64>,
+    pub decoder_start_token_id: Option<i64>,
+    pub output_attentions: Option<bool>,
+    pub output_hidden_states: Option<bool>,
+    pub output_past: Option<bool>,
+}
+
+impl Config for TextDecoderConfig {}
+
+impl Default for TextDecoderConfig {
+    fn default() -> Self {
+        TextDecoderConfig {
+            model_type: Some("trocr".to_string()),
+            vocab_size: 50265,
+            d_model: 1024,
+            decoder_layers: 12,
+            decoder_attention_heads: 16,
+            decoder_ffn_dim: 4096,
+            activation_function: Some(Activation::gelu),
+            max_position_embeddings: 512,
+            dropout: 0.1,
+            attention_dropout: 0.0,
+            activation_dropout: 0.0,
+            scale_embedding: Some(false),
+            layernorm_embedding: Some(true),
+            use_learned_position_embeddings: Some(true),
+            cross_attention_hidden_size: None,
+            pad_token_id: Some(1),
+            bos_token_id: Some(0),
+            eos_token_id: Some(2),
+            decoder_start_token_id: Some(2),
+            output_attentions: None,
+            output_hidden_states: None,
+            output_past: None,
+        }
+    }
+}
+
+impl TextDecoderConfig {
+    /// Returns true for the MBart decoders, normalizing the inputs of the sub-layers
+    pub fn is_pre_layer_norm(&self) -> bool {
+        self.model_type.as_deref() == Some("mbart")
+    }
+}
+
+pub struct TextDecoderLayer {
+    self_attention: TextDecoderAttention,
+    encoder_attention: TextDecoderAttention,
+    self_attention_layer_norm: nn::LayerNorm,
+    encoder_attention_layer_norm: nn::LayerNorm,
+    dropout: Dropout,
+    activation_dropout: Dropout,
+    activation: TensorFunction,
+    fc1: nn::Linear,
+    fc2: nn::Linear,
+    final_layer_norm: nn::LayerNorm,
+    pre_layer_norm: bool,
+}
+
+impl TextDecoderLayer {
+    pub fn new<'p, P>(p: P, config: &TextDecoderConfig) -> TextDecoderLayer
+    where
+        P: Borrow<nn::Path<'p>>,
+    {
+        let p = p.borrow();
+
+        let layer_norm_config = nn::LayerNormConfig {
+            eps: 1e-5,
+            ..Default::default()
+        };
+        let output_attention = config.output_attentions.unwrap_or(false);
+        let self_attention = TextDecoderAttention::new(
+            p / "self_attn",
+            config.d_model,
+            config.d_model,
+            config.decoder_attention_heads,
+            config.attention_dropout,
+            false,
+            true,
+            output_attention,
+        );
+        let encoder_attention = TextDecoderAttention::new(
+            p / "encoder_attn",
+            config.d_model,
+            config.cross_attention_hidden_size.unwrap_or(config.d_model),
+            config.decoder_attention_heads,
+            config.attention_dropout,
+            true,
+            true,
+            output_attention,
+        );
+        let self_attention_layer_norm = nn::layer_norm(
+            p / "self_attn_layer_norm",
+            vec![config.d_model],
+            layer_norm_config,
+        );
+        let encoder_attention_layer_norm = nn::layer_norm(
+            p / "encoder_attn_layer_norm",
+            vec![config.d_model],
+            layer_norm_config,
+        );
+
+        let dropout = Dropout::new(config.dropout);
+        let activation_dropout = Dropout::new(config.activation_dropout);
+        let activation = config
+            .activation_function
+            .unwrap_or(Activation::gelu)
+            .get_function();
+        let fc1 = nn::linear(
+            p / "fc1",
+            config.d_model,
+            config.decoder_ffn_dim,
+            Default::default(),
+        );
+        let fc2 = nn::linear(
+            p / "fc2",
+            config.decoder_ffn_dim,
+            config.d_model,
+            Default::default(),
+        );
+
+        let final_layer_norm = nn::layer_norm(
+            p / "final_layer_norm",
+            vec![config.d_model],
+            layer_norm_config,
+        );
+
+        TextDecoderLayer {
+            self_attention,
+            encoder_attention,
+            self_attention_layer_norm,
+            encoder_attention_layer_norm,
+            dropout,
+            activation_dropout,
+            activation,
+            fc1,
+            fc2,
+            final_layer_norm,
+            pre_layer_norm: config.is_pre_layer_norm(),
+        }
+    }
+
+    /// Applies the layer normalization to the input of a sub-layer (pre-normalization)
+    fn normalize_input(&self, x: &Tensor, layer_norm: &nn::LayerNorm) -> Tensor {
+        if self.pre_layer_norm {
+            x.apply(layer_norm)
+        } else {
+            x.shallow_clone()
+        }
+    }
+
+    /// Applies the layer normalization to the output of a sub-layer (post-normalization)
+    fn normalize_output(&self, x: Tensor, layer_norm: &nn::LayerNorm) -> Tensor {
+        if self.pre_layer_norm {
+            x
+        } else {
+            x.apply(layer_norm)
+        }
+    }
+
+    pub fn forward_t(
+        &self,
+        x: &Tensor,
+        encoder_hidden_states: &Tensor,
+        decoder_attention_mask: Option<&Tensor>,
+        layer_states: (Option<LayerState>, Option<LayerState>),
+        train: bool,
+    ) -> (
+        Tensor,
+        Option<Tensor>,
+        Option<Tensor>,
+        (Option<LayerState>, Option<LayerState>),
+    ) {
+        let output = self.normalize_input(x, &self.self_attention_layer_norm);
+        let (output, attention_weights, new_self_layer_states) = self.self_attention.forward_t(
+            &output,
+            None,
+            decoder_attention_mask,
+            layer_states.0,
+            train,
+        );
+        let output = self.normalize_output(
+            output.apply_t(&self.dropout, train) + x,
+            &self.self_attention_layer_norm,
+        );
+
+        let output1 = self.normalize_input(&output, &self.encoder_attention_layer_norm);
+        let (output1, cross_attention_weights, new_encoder_layer_states) =
+            self.encoder_attention.forward_t(
+                &output1,
+                Some(encoder_hidden_states),
+                None,
+                layer_states.1,
+                train,
+            );
+        let output1 = self.normalize_output(
+            output1.apply_t(&self.dropout, train) + output,
+            &self.encoder_attention_layer_norm,
+        );
+
+        let output2 = self.normalize_input(&output1, &self.final_layer_norm);
+        let output2 = (self.activation.get_fn())(&output2.apply(&self.fc1));
+        let output2 = output2
+            .apply_t(&self.activation_dropout, train)
+            .apply(&self.fc2)
+            .apply_t(&self.dropout, train);
+        let output2 = self.normalize_output(output2 + output1, &self.final_layer_norm);
+        (
+            output2,
+            attention_weights,
+            cross_attention_weights,
+            (new_self_layer_states, new_encoder_layer_states),
+        )
+    }
+}
+
+/// # Text decoder
+/// Transformer decoder with learned position embeddings, attending to the encoded image
+/// It is made of the following blocks:
+/// - `embed_tokens`: token embeddings, optionally scaled by the square root of the hidden size
+/// - `embed_positions`: learned position embeddings (offset by 2 positions)
+/// - `layernorm_embedding`: optional normalization of the embeddings
+/// - `layers`: decoder layers with self-attention and cross-attention over the image features
+/// - `layer_norm`: final layer normalization (MBart decoders)
+pub struct TextDecoder {
+    embed_tokens: nn::Embedding,
+    embed_positions: nn::Embedding,
+    layernorm_embedding: Option<nn::LayerNorm>,
+    layer_norm: Option<nn::LayerNorm>,
+    dropout: Dropout,
+    layers: Vec<TextDecoderLayer>,
+    embed_scale: f64,
+    output_attentions: bool,
+    output_hidden_states: bool,
+    output_past: bool,
+}
+
+impl TextDecoder {
+    pub fn new<'p, P>(p: P, config: &TextDecoderConfig) -> TextDecoder
+    where
+        P: Borrow<nn::Path<'p>>,
+    {
+        let p = p.borrow();
+        let output_past = config.output_past.unwrap_or(true);
+        let output_attentions = config.output_attentions.unwrap_or(false);
+        let output_hidden_states = config.output_hidden_states.unwrap_or(false);
+
+        let embed_tokens = nn::embedding(
+            p / "embed_tokens",
+            config.vocab_size,
+            config.d_model,
+            EmbeddingConfig {
+                padding_idx: config.pad_token_id.unwrap_or(1),
+                ..Default::default()
+            },
+        );
+        let embed_positions = nn::embedding(
+            p / "embed_positions",
+            config.max_position_embeddings + POSITION_OFFSET,
+            config.d_model,
+            Default::default(),
+        );
+        let layernorm_embedding =
+            if config.is_pre_layer_norm() || config.layernorm_embedding.unwrap_or(true) {
+                Some(nn::layer_norm(
+                    p / "layernorm_embedding",
+                    vec![config.d_model],
+                    Default::default(),
+                ))
+            } else {
+                None
+            };
+        let layer_norm = if config.is_pre_layer_norm() {
+            Some(nn::layer_norm(
+                p / "layer_norm",
+                vec![config.d_model],
+                Default::default(),
+            ))
+        } else {
+            None
+        };
+
+        let embed_scale = if config.scale_embedding.unwrap_or(false) {
+            (config.d_model as f64).sqrt()
+        } else {
+            1.0
+        };
+
+        let mut layers: Vec<TextDecoderLayer> = vec![];
+        let p_layers = p / "layers";
+        for layer_index in 0..config.decoder_layers {
+            layers.push(TextDecoderLayer::new(&p_layers / layer_index, config));
+        }
+
+        TextDecoder {
+            embed_tokens,
+            embed_positions,
+            layernorm_embedding,
+            layer_norm,
+            dropout: Dropout::new(config.dropout),
+            layers,
+            embed_scale,
+            output_attentions,
+            output_hidden_states,
+            output_past,
+        }
+    }
+
+    /// Forward pass through the decoder
+    ///
+    /// # Arguments
+    ///
+    /// * `input_ids` - Decoder input tensor of shape (*batch size*, *target_sequence_length*)
+    /// * `encoder_hidden_states` - Encoded image of shape (*batch size*, *num_patches*, *encoder_hidden_size*)
+    /// * `old_layer_states` - Optional vector of length *num_layers* containing tuples of optional `LayerStates` containing the last calculated key and value pairs for the decoder self attention and encoder cross attention.
+    /// * `train` - boolean flag to turn on/off the dropout layers in the model. Should be set to false for inference.
+    pub fn forward_t(
+        &self,
+        input_ids: &Tensor,
+        encoder_hidden_states: &Tensor,
+        old_layer_states: Option<Vec<(Option<LayerState>, Option<LayerState>)>>,
+        train: bool,
+    ) -> Result<TextDecoderOutput, RustBertError> {
+        let past_key_values_length = if let Some(old_layer_states_values) = &old_layer_states {
+            if let Some(old_value_state) = &old_layer_states_values[0].0 {
+                old_value_state.prev_key.size()[2]
+            } else {
+                0
+            }
+        } else {
+            0
+        };
+        let sequence_length = input_ids.size()[1];
+        let max_positions = self.embed_positions.ws.size()[0] - POSITION_OFFSET;
+        if past_key_values_length + sequence_length > max_positions {
+            return Err(RustBertError::InputTooLongError(format!(
+                "The text decoder accepts at most {} tokens, got {}",
+                max_positions,
+                past_key_values_length + sequence_length
+            )));
+        }
+
+        let x = input_ids.apply(&self.embed_tokens) * self.embed_scale;
+        let positions = Tensor::arange_start(
+            past_key_values_length + POSITION_OFFSET,
+            past_key_values_length + sequence_length + POSITION_OFFSET,
+            (Kind::Int64, input_ids.device()),
+        )
+        .apply(&self.embed_positions);
+        let mut x = x + positions;
+        if let Some(layernorm_embedding) = &self.layernorm_embedding {
+            x = x.apply(layernorm_embedding);
+        }
+
+        let decoder_attention_mask = if sequence_length > 1 {
+            Some(_make_causal_mask(
+                input_ids.size().as_slice(),
+                x.kind(),
+                x.device(),
+                past_key_values_length,
+            ))
+        } else {
+            None
+        };
+
+        let mut hidden_state = x.apply_t(&self.dropout, train);
+
+        let mut all_hidden_states: Option<Vec<Tensor>> = if self.output_hidden_states {
+            Some(Vec::with_capacity(self.layers.len()))
+        } else {
+            None
+        };
+        let mut all_attentions: Option<Vec<Tensor>> = if self.output_attentions {
+            Some(Vec::with_capacity(self.layers.len()))
+        } else {
+            None
+        };
+        let mut all_cross_attentions: Option<Vec<Tensor>> = if self.output_attentions {
+            Some(Vec::with_capacity(self.layers.len()))
+        } else {
+            None
+        };
+        let mut next_decoder_cache: Option<Vec<(Option<LayerState>, Option<LayerState>)>> =
+            if self.output_past {
+                if old_layer_states.is_some() {
+                    old_layer_states
+                } else {
+                    Some(vec![(None, None); self.layers.len()])
+                }
+            } else {
+                None
+            };
+
+        for (layer_idx, layer) in self.layers.iter().enumerate() {
+            let layer_state = match &mut next_decoder_cache {
+                Some(values) => std::mem::take(&mut values[layer_idx]),
+                None => (None, None),
+            };
+            let (output, attention_weights, cross_attention_weights, new_layer_state) = layer
+                .forward_t(
+                    &hidden_state,
+                    encoder_hidden_states,
+                    decoder_attention_mask.as_ref(),
+                    layer_state,
+                    train,
+                );
+            hidden_state = output;
+            if let Some(hidden_states) = all_hidden_states.borrow_mut() {
+                hidden_states.push(hidden_state.copy());
+            };
+            if let Some(attentions) = all_attentions.borrow_mut() {
+                attentions.push(attention_weights.unwrap());
+            };
+            if let Some(cross_attentions) = all_cross_attentions.borrow_mut() {
+                cross_attentions.push(cross_attention_weights.unwrap());
+            };
+            if let Some(value) = &mut next_decoder_cache {
+                value[layer_idx] = new_layer_state
+            };
+        }
+        if let Some(layer_norm) = &self.layer_norm {
+            hidden_state = hidden_state.apply(layer_norm);
+        }
+
+        Ok(TextDecoderOutput {
+            hidden_state,
+            encoder_attention_mask: None,
+            next_decoder_cache,
+            all_hidden_states,
+            all_attentions,
+            all_cross_attentions,
+        })
+    }
+}
+
+/// Container holding a text decoder output
+pub type TextDecoderOutput = BartDecoderOutput;
@@ -0,0 +1,66 @@
+//! # Vision encoder-decoder models (TrOCR, Donut)
+//!
+//! Implementation of image-to-text models made of an image encoder and an auto-regressive text decoder attending to the
+//! image features, used for optical character recognition and document understanding:
+//! - TrOCR ([TrOCR: Transformer-based Optical Character Recognition with Pre-trained Models](https://arxiv.org/abs/2109.10282) Li, Lv, Chen, Cui, Lu, Florencio, Zhang, Li, Wei, 2021):
+//! ViT encoder and TrOCR (post-layer normalization) decoder
+//! - Donut ([OCR-free Document Understanding Transformer](https://arxiv.org/abs/2111.15664) Kim, Hong, Yim, Nam, Park, Yim, Hwang, Yun, Han, Park, 2021):
+//! Swin Transformer encoder and MBart (pre-layer normalization) decoder. The decoder is prompted with task tokens (e.g. `<s_cord-v2>`).
+//!
+//! The base model is implemented in the `vision_encoder_decoder_model::VisionEncoderDecoderModel` struct. The generation
+//! from images is implemented in `VisionEncoderDecoderGenerator`, sharing the generation utilities of the text models.
+//! Images are converted to the model inputs by the `vit::ViTImageProcessor`.
+//! A ready-to-use pipeline is available in `pipelines::ocr`.
+//!
+//! DeiT image encoders and decoders with sinusoidal position embeddings are not supported.
+//!
+//! # Model set-up and pre-trained weights loading
+//!
+//! All models expect the following resources:
+//! - Configuration file expected to have a structure following the [Transformers library](https://github.com/huggingface/transformers),
+//! with the `encoder` and `decoder` configurations nested
+//! - Model weights are expected to have a structure and parameter names following the [Transformers library](https://github.com/huggingface/transformers)
+//! (`encoder.*`, `decoder.model.decoder.*` and `decoder.output_projection` or `decoder.lm_head`). A conversion using the Python utility scripts is required to convert the `.bin` weights to the `.ot` format.
+//! - `RobertaTokenizer` using a `vocab.json` vocabulary and `merges.txt` merges file (TrOCR), or `XLMRobertaTokenizer` using a `sentencepiece.bpe.model` SentencePiece model (Donut)
+//!
+//! ```no_run
+//! # fn main() -> anyhow::Result<()> {
+//! #
+//! use rust_bert::pipelines::generation_utils::GenerateConfig;
+//! use rust_bert::resources::LocalResource;
+//! use rust_bert::vision_encoder_decoder::VisionEncoderDecoderGenerator;
+//! use rust_bert::vit::{ImageBuffer, ViTImageProcessor, VIT_IMAGE_MEAN, VIT_IMAGE_STD};
+//! use std::path::PathBuf;
+//! use tch::Device;
+//!
+//! let generate_config = GenerateConfig {
+//!     model_resource: Box::new(LocalResource::from(PathBuf::from("path/to/rust_model.ot"))),
+//!     config_resource: Box::new(LocalResource::from(PathBuf::from("path/to/config.json"))),
+//!     vocab_resource: Box::new(LocalResource::from(PathBuf::from("path/to/vocab.json"))),
+//!     merges_resource: Box::new(LocalResource::from(PathBuf::from("path/to/merges.txt"))),
+//!     device: Device::cuda_if_available(),
+//!     ..Default::default()
+//! };
+//! let generator = VisionEncoderDecoderGenerator::new(generate_config)?;
+//!
+//! let image_processor =
+//!     ViTImageProcessor::new(384, VIT_IMAGE_MEAN, VIT_IMAGE_STD, Device::cuda_if_available());
+//! let pixels = vec![255u8; 640 * 120 * 3];
+//! let pixel_values = image_processor.preprocess(&[ImageBuffer::rgb(&pixels, 640, 120)])?;
+//! let output = generator.generate_from_images(&pixel_values, None)?;
+//! # Ok(())
+//! # }
+//! ```
+
+mod attention;
+mod decoder;
+mod swin;
+mod vision_encoder_decoder_model;
+
+pub use attention::LayerState;
+pub use decoder::{TextDecoder, TextDecoderConfig, TextDecoderOutput};
+pub use swin::{DonutImageSize, DonutSwinConfig, DonutSwinModel, DonutSwinModelOutput};
+pub use vision_encoder_decoder_model::{
+    VisionEncoderConfig, VisionEncoderDecoderConfig, VisionEncoderDecoderGenerator,
+    VisionEncoderDecoderModel, VisionEncoderDecoderModelOutput,
+};
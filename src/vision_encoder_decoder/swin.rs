@@ -0,0 +1,825 @@
+// Copyright 2022 NAVER Corp., The Microsoft Research Asia Swin Transformer Team and The HuggingFace Inc. team.
+// Copyright 2022 Guillaume Becquin
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//     http://www.apache.org/licenses/LICENSE-2.0
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use crate::common::activations::{Activation, TensorFunction};
+use crate::common::dropout::Dropout;
+use crate::{Config, RustBertError};
+use serde::{Deserialize, Serialize};
+use std::borrow::{Borrow, BorrowMut};
+use tch::nn::{ConvConfig, Init};
+use tch::{nn, Device, Kind, Tensor};
+
+/// # Image size of the Donut Swin models
+/// Either a single value for square images or the `[height, width]` of rectangular images
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq)]
+#[serde(untagged)]
+pub enum DonutImageSize {
+    Square(i64),
+    Rectangle([i64; 2]),
+}
+
+impl DonutImageSize {
+    /// Returns the height and width of the images
+    pub fn height_width(&self) -> (i64, i64) {
+        match *self {
+            DonutImageSize::Square(size) => (size, size),
+            DonutImageSize::Rectangle([height, width]) => (height, width),
+        }
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+/// # Donut Swin model configuration
+/// Defines the Swin Transformer encoder of the Donut models (e.g. image and window sizes, depth and number of heads of
+/// each stage...)
+pub struct DonutSwinConfig {
+    /// Height and width of the images seen during training
+    pub image_size: DonutImageSize,
+    /// Height and width of the (square) patches embedded as tokens
+    pub patch_size: i64,
+    pub num_channels: i64,
+    /// Dimension of the patch embeddings, doubled by the patch merging after each stage
+    pub embed_dim: i64,
+    /// Number of layers of each stage
+    pub depths: Vec<i64>,
+    /// Number of attention heads of each stage
+    pub num_heads: Vec<i64>,
+    /// Height and width of the windows in which the self-attention is computed
+    pub window_size: i64,
+    /// Ratio of the feed-forward hidden dimension to the embedding dimension
+    pub mlp_ratio: f64,
+    pub qkv_bias: Option<bool>,
+    pub hidden_act: Activation,
+    pub hidden_dropout_prob: f64,
+    pub attention_probs_dropout_prob: f64,
+    pub layer_norm_eps: Option<f64>,
+    pub output_attentions: Option<bool>,
+    pub output_hidden_states: Option<bool>,
+}
+
+impl Config for DonutSwinConfig {}
+
+impl Default for DonutSwinConfig {
+    fn default() -> Self {
+        DonutSwinConfig {
+            image_size: DonutImageSize::Rectangle([2560, 1920]),
+            patch_size: 4,
+            num_channels: 3,
+            embed_dim: 128,
+            depths: vec![2, 2, 14, 2],
+            num_heads: vec![4, 8, 16, 32],
+            window_size: 10,
+            mlp_ratio: 4.0,
+            qkv_bias: Some(true),
+            hidden_act: Activation::gelu,
+            hidden_dropout_prob: 0.0,
+            attention_probs_dropout_prob: 0.0,
+            layer_norm_eps: Some(1e-5),
+            output_attentions: None,
+            output_hidden_states: None,
+        }
+    }
+}
+
+impl DonutSwinConfig {
+    /// Dimension of the hidden states output by the last stage
+    pub fn hidden_size(&self) -> i64 {
+        self.embed_dim * 2i64.pow(self.depths.len().saturating_sub(1) as u32)
+    }
+}
+
+/// Splits a tensor of shape (*batch size*, *height*, *width*, *channels*) in non-overlapping windows of shape
+/// (*window_size*, *window_size*, *channels*). The height and width must be multiples of the window size.
+fn window_partition(input: &Tensor, window_size: i64) -> Tensor {
+    let (batch_size, height, width, channels) = input.size4().unwrap();
+    input
+        .reshape(&[
+            batch_size,
+            height / window_size,
+            window_size,
+            width / window_size,
+            window_size,
+            channels,
+        ])
+        .permute(&[0, 1, 3, 2, 4, 5])
+        .contiguous()
+        .view([-1, window_size, window_size, channels])
+}
+
+/// Merges windows of shape (*num_windows x batch size*, *window_size*, *window_size*, *channels*) back to a tensor of
+/// shape (*batch size*, *height*, *width*, *channels*)
+fn window_reverse(windows: &Tensor, window_size: i64, height: i64, width: i64) -> Tensor {
+    let channels = windows.size()[3];
+    windows
+        .reshape(&[
+            -1,
+            height / window_size,
+            width / window_size,
+            window_size,
+            window_size,
+            channels,
+        ])
+        .permute(&[0, 1, 3, 2, 4, 5])
+        .contiguous()
+        .view([-1, height, width, channels])
+}
+
+/// Index of the relative position bias of each pair of positions of a window, of shape (*window_size²*, *window_size²*)
+fn relative_position_index(window_size: i64, device: Device) -> Tensor {
+    let num_positions = window_size * window_size;
+    let index = (0..num_positions)
+        .flat_map(|i| {
+            (0..num_positions).map(move |j| {
+                let relative_height = i / window_size - j / window_size + window_size - 1;
+                let relative_width = i % window_size - j % window_size + window_size - 1;
+                relative_height * (2 * window_size - 1) + relative_width
+            })
+        })
+        .collect::<Vec<i64>>();
+    Tensor::of_slice(&index)
+        .view([num_positions, num_positions])
+        .to(device)
+}
+
+/// Attention mask of the shifted windows of shape (*num_windows*, *window_size²*, *window_size²*), preventing the
+/// attention between positions that are not adjacent in the image before the cyclic shift
+fn shifted_window_mask(
+    height: i64,
+    width: i64,
+    window_size: i64,
+    shift_size: i64,
+    kind: Kind,
+    device: Device,
+) -> Tensor {
+    let image_mask = Tensor::zeros(&[1, height, width, 1], (kind, device));
+    let height_slices = [
+        (0, height - window_size),
+        (height - window_size, height - shift_size),
+        (height - shift_size, height),
+    ];
+    let width_slices = [
+        (0, width - window_size),
+        (width - window_size, width - shift_size),
+        (width - shift_size, width),
+    ];
+    let mut region = 0;
+    for (height_start, height_end) in height_slices.iter() {
+        for (width_start, width_end) in width_slices.iter() {
+            let _ = image_mask
+                .slice(1, *height_start, *height_end, 1)
+                .slice(2, *width_start, *width_end, 1)
+                .fill_(region as f64);
+            region += 1;
+        }
+    }
+    let mask_windows =
+        window_partition(&image_mask, window_size).view([-1, window_size * window_size]);
+    let attention_mask = mask_windows.unsqueeze(1) - mask_windows.unsqueeze(2);
+    attention_mask.ne(0.0).to_kind(kind).g_mul_scalar(-100.0)
+}
+
+/// # Donut Swin window self-attention
+/// Multi-head self-attention within the windows, with a learned bias for each relative position of a window
+pub struct DonutSwinSelfAttention {
+    num_attention_heads: i64,
+    attention_head_size: i64,
+    query: nn::Linear,
+    key: nn::Linear,
+    value: nn::Linear,
+    relative_position_bias_table: Tensor,
+    relative_position_index: Tensor,
+    output: nn::Linear,
+    attention_dropout: Dropout,
+    dropout: Dropout,
+    output_attentions: bool,
+}
+
+impl DonutSwinSelfAttention {
+    pub fn new<'p, P>(
+        p: P,
+        config: &DonutSwinConfig,
+        dim: i64,
+        num_heads: i64,
+        window_size: i64,
+    ) -> DonutSwinSelfAttention
+    where
+        P: Borrow<nn::Path<'p>>,
+    {
+        assert_eq!(
+            dim % num_heads,
+            0,
+            "Hidden size not a multiple of the number of attention heads"
+        );
+        let p = p.borrow();
+        let p_self = p / "self";
+
+        let linear_config = nn::LinearConfig {
+            bias: config.qkv_bias.unwrap_or(true),
+            ..Default::default()
+        };
+        let query = nn::linear(&p_self / "query", dim, dim, linear_config);
+        let key = nn::linear(&p_self / "key", dim, dim, linear_config);
+        let value = nn::linear(&p_self / "value", dim, dim, linear_config);
+        let relative_position_bias_table = p_self.var(
+            "relative_position_bias_table",
+            &[(2 * window_size - 1) * (2 * window_size - 1), num_heads],
+            Init::Const(0.0),
+        );
+        let output = nn::linear(p / "output" / "dense", dim, dim, Default::default());
+
+        DonutSwinSelfAttention {
+            num_attention_heads: num_heads,
+            attention_head_size: dim / num_heads,
+            query,
+            key,
+            value,
+            relative_position_bias_table,
+            relative_position_index: relative_position_index(window_size, p.device()),
+            output,
+            attention_dropout: Dropout::new(config.attention_probs_dropout_prob),
+            dropout: Dropout::new(config.hidden_dropout_prob),
+            output_attentions: config.output_attentions.unwrap_or(false),
+        }
+    }
+
+    fn split_heads(&self, x: Tensor, batch_size: i64) -> Tensor {
+        x.view((
+            batch_size,
+            -1,
+            self.num_attention_heads,
+            self.attention_head_size,
+        ))
+        .transpose(1, 2)
+    }
+
+    /// Forward pass through the attention
+    ///
+    /// # Arguments
+    ///
+    /// * `hidden_states` - Windows of shape (*num_windows x batch size*, *window_size²*, *dim*)
+    /// * `attention_mask` - Optional mask of the shifted windows of shape (*num_windows*, *window_size²*, *window_size²*)
+    /// * `train` - boolean flag to turn on/off the dropout layers in the model. Should be set to false for inference.
+    pub fn forward_t(
+        &self,
+        hidden_states: &Tensor,
+        attention_mask: Option<&Tensor>,
+        train: bool,
+    ) -> (Tensor, Option<Tensor>) {
+        let (batch_size, num_positions, dim) = hidden_states.size3().unwrap();
+        let query = self.split_heads(hidden_states.apply(&self.query), batch_size);
+        let key = self.split_heads(hidden_states.apply(&self.key), batch_size);
+        let value = self.split_heads(hidden_states.apply(&self.value), batch_size);
+
+        let relative_position_bias = self
+            .relative_position_bias_table
+            .index_select(0, &self.relative_position_index.view(-1))
+            .view([num_positions, num_positions, -1])
+            .permute(&[2, 0, 1])
+            .unsqueeze(0);
+        let mut attention_scores = query.matmul(&key.transpose(-1, -2))
+            / (self.attention_head_size as f64).sqrt()
+            + relative_position_bias.to_kind(query.kind());
+
+        if let Some(attention_mask) = attention_mask {
+            let num_windows = attention_mask.size()[0];
+            attention_scores = (attention_scores.reshape(&[
+                batch_size / num_windows,
+                num_windows,
+                self.num_attention_heads,
+                num_positions,
+                num_positions,
+            ]) + attention_mask.unsqueeze(1).unsqueeze(0))
+            .view((
+                batch_size,
+                self.num_attention_heads,
+                num_positions,
+                num_positions,
+            ));
+        }
+
+        let attention_probs = attention_scores.softmax(-1, attention_scores.kind());
+        let context = attention_probs
+            .apply_t(&self.attention_dropout, train)
+            .matmul(&value)
+            .transpose(1, 2)
+            .contiguous()
+            .view([batch_size, num_positions, dim])
+            .apply(&self.output)
+            .apply_t(&self.dropout, train);
+
+        let attention_probs = if self.output_attentions {
+            Some(attention_probs)
+        } else {
+            None
+        };
+        (context, attention_probs)
+    }
+}
+
+/// # Donut Swin layer
+/// Pre-normalization transformer layer computing the self-attention within (optionally shifted) windows, followed by
+/// a feed-forward network
+pub struct DonutSwinLayer {
+    attention: DonutSwinSelfAttention,
+    layernorm_before: nn::LayerNorm,
+    layernorm_after: nn::LayerNorm,
+    intermediate: nn::Linear,
+    output: nn::Linear,
+    activation: TensorFunction,
+    dropout: Dropout,
+    window_size: i64,
+    shift_size: i64,
+}
+
+impl DonutSwinLayer {
+    pub fn new<'p, P>(
+        p: P,
+        config: &DonutSwinConfig,
+        dim: i64,
+        num_heads: i64,
+        input_resolution: (i64, i64),
+        shift_size: i64,
+    ) -> DonutSwinLayer
+    where
+        P: Borrow<nn::Path<'p>>,
+    {
+        let p = p.borrow();
+
+        // Windows larger than the input are not partitioned, nor shifted
+        let min_resolution = input_resolution.0.min(input_resolution.1);
+        let (window_size, shift_size) = if min_resolution <= config.window_size {
+            (min_resolution, 0)
+        } else {
+            (config.window_size, shift_size)
+        };
+
+        let layer_norm_config = nn::LayerNormConfig {
+            eps: config.layer_norm_eps.unwrap_or(1e-5),
+            ..Default::default()
+        };
+        let attention =
+            DonutSwinSelfAttention::new(p / "attention", config, dim, num_heads, window_size);
+        let layernorm_before = nn::layer_norm(p / "layernorm_before", vec![dim], layer_norm_config);
+        let layernorm_after = nn::layer_norm(p / "layernorm_after", vec![dim], layer_norm_config);
+        let intermediate_size = (config.mlp_ratio * dim as f64) as i64;
+        let intermediate = nn::linear(
+            p / "intermediate" / "dense",
+            dim,
+            intermediate_size,
+            Default::default(),
+        );
+        let output = nn::linear(
+            p / "output" / "dense",
+            intermediate_size,
+            dim,
+            Default::default(),
+        );
+
+        DonutSwinLayer {
+            attention,
+            layernorm_before,
+            layernorm_after,
+            intermediate,
+            output,
+            activation: config.hidden_act.get_function(),
+            dropout: Dropout::new(config.hidden_dropout_prob),
+            window_size,
+            shift_size,
+        }
+    }
+
+    /// Forward pass through the layer
+    ///
+    /// # Arguments
+    ///
+    /// * `hidden_states` - Patch sequence of shape (*batch size*, *height x width*, *dim*)
+    /// * `input_dimensions` - Height and width of the patch grid
+    /// * `train` - boolean flag to turn on/off the dropout layers in the model. Should be set to false for inference.
+    pub fn forward_t(
+        &self,
+        hidden_states: &Tensor,
+        input_dimensions: (i64, i64),
+        train: bool,
+    ) -> (Tensor, Option<Tensor>) {
+        let (height, width) = input_dimensions;
+        let (batch_size, _, channels) = hidden_states.size3().unwrap();
+
+        // Pads the patch grid to a multiple of the window size
+        let pad_right = (self.window_size - width % self.window_size) % self.window_size;
+        let pad_bottom = (self.window_size - height % self.window_size) % self.window_size;
+        let mut windows = hidden_states
+            .apply(&self.layernorm_before)
+            .view([batch_size, height, width, channels]);
+        if pad_right > 0 || pad_bottom > 0 {
+            windows = windows.constant_pad_nd(&[0, 0, 0, pad_right, 0, pad_bottom]);
+        }
+        let (padded_height, padded_width) = (height + pad_bottom, width + pad_right);
+
+        let attention_mask = if self.shift_size > 0 {
+            windows = windows.roll(&[-self.shift_size, -self.shift_size], &[1, 2]);
+            Some(shifted_window_mask(
+                padded_height,
+                padded_width,
+                self.window_size,
+                self.shift_size,
+                windows.kind(),
+                windows.device(),
+            ))
+        } else {
+            None
+        };
+
+        let windows = window_partition(&windows, self.window_size).view([
+            -1,
+            self.window_size * self.window_size,
+            channels,
+        ]);
+        let (attention_output, attention_weights) =
+            self.attention
+                .forward_t(&windows, attention_mask.as_ref(), train);
+
+        let mut attention_output = window_reverse(
+            &attention_output.view([-1, self.window_size, self.window_size, channels]),
+            self.window_size,
+            padded_height,
+            padded_width,
+        );
+        if self.shift_size > 0 {
+            attention_output = attention_output.roll(&[self.shift_size, self.shift_size], &[1, 2]);
+        }
+        if pad_right > 0 || pad_bottom > 0 {
+            attention_output = attention_output
+                .narrow(1, 0, height)
+                .narrow(2, 0, width)
+                .contiguous();
+        }
+        let hidden_states =
+            attention_output.view([batch_size, height * width, channels]) + hidden_states;
+
+        let mlp_output = self.activation.get_fn()(
+            &hidden_states
+                .apply(&self.layernorm_after)
+                .apply(&self.intermediate),
+        )
+        .apply(&self.output)
+        .apply_t(&self.dropout, train);
+        (mlp_output + hidden_states, attention_weights)
+    }
+}
+
+/// # Donut Swin patch merging
+/// Downsampling between two stages: the features of each 2x2 group of patches are concatenated, normalized and
+/// projected to twice the input dimension
+pub struct DonutSwinPatchMerging {
+    norm: nn::LayerNorm,
+    reduction: nn::Linear,
+}
+
+impl DonutSwinPatchMerging {
+    pub fn new<'p, P>(p: P, dim: i64) -> DonutSwinPatchMerging
+    where
+        P: Borrow<nn::Path<'p>>,
+    {
+        let p = p.borrow();
+
+        let norm = nn::layer_norm(p / "norm", vec![4 * dim], Default::default());
+        let reduction = nn::linear(
+            p / "reduction",
+            4 * dim,
+            2 * dim,
+            nn::LinearConfig {
+                bias: false,
+                ..Default::default()
+            },
+        );
+        DonutSwinPatchMerging { norm, reduction }
+    }
+
+    /// Merges a patch sequence of shape (*batch size*, *height x width*, *dim*) into a sequence of shape
+    /// (*batch size*, *ceil(height / 2) x ceil(width / 2)*, *2 x dim*)
+    pub fn forward(&self, hidden_states: &Tensor, input_dimensions: (i64, i64)) -> Tensor {
+        let (height, width) = input_dimensions;
+        let (batch_size, _, channels) = hidden_states.size3().unwrap();
+        let mut hidden_states = hidden_states.view([batch_size, height, width, channels]);
+        if height % 2 == 1 || width % 2 == 1 {
+            hidden_states = hidden_states.constant_pad_nd(&[0, 0, 0, width % 2, 0, height % 2]);
+        }
+        let (padded_height, padded_width) = (height + height % 2, width + width % 2);
+        let patches = |height_start: i64, width_start: i64| {
+            hidden_states
+                .slice(1, height_start, padded_height, 2)
+                .slice(2, width_start, padded_width, 2)
+        };
+        Tensor::cat(
+            &[patches(0, 0), patches(1, 0), patches(0, 1), patches(1, 1)],
+            -1,
+        )
+        .view([batch_size, -1, 4 * channels])
+        .apply(&self.norm)
+        .apply(&self.reduction)
+    }
+}
+
+/// # Donut Swin stage
+/// Sequence of Swin layers alternating regular and shifted windows, followed by an optional patch merging
+pub struct DonutSwinStage {
+    blocks: Vec<DonutSwinLayer>,
+    downsample: Option<DonutSwinPatchMerging>,
+}
+
+impl DonutSwinStage {
+    pub fn new<'p, P>(
+        p: P,
+        config: &DonutSwinConfig,
+        stage_index: usize,
+        input_resolution: (i64, i64),
+    ) -> DonutSwinStage
+    where
+        P: Borrow<nn::Path<'p>>,
+    {
+        let p = p.borrow();
+
+        let dim = config.embed_dim * 2i64.pow(stage_index as u32);
+        let p_blocks = p / "blocks";
+        let blocks = (0..config.depths[stage_index])
+            .map(|block_index| {
+                DonutSwinLayer::new(
+                    &p_blocks / block_index,
+                    config,
+                    dim,
+                    config.num_heads[stage_index],
+                    input_resolution,
+                    if block_index % 2 == 0 {
+                        0
+                    } else {
+                        config.window_size / 2
+                    },
+                )
+            })
+            .collect::<Vec<DonutSwinLayer>>();
+        let downsample = if stage_index < config.depths.len() - 1 {
+            Some(DonutSwinPatchMerging::new(p / "downsample", dim))
+        } else {
+            None
+        };
+        DonutSwinStage { blocks, downsample }
+    }
+}
+
+/// # Donut Swin Transformer encoder
+/// Hierarchical vision transformer used as the image encoder of the Donut models
+/// It is made of the following blocks:
+/// - `embeddings`: patch embeddings (strided convolution) followed by a layer normalization
+/// - `stages`: Swin stages of windowed self-attention layers, the resolution of the patch grid being halved and the
+/// hidden dimension doubled between two stages
+///
+/// The images are padded to a multiple of the patch size, and the patch grids to a multiple of the window size: any
+/// image size can be processed. There is no final layer normalization.
+pub struct DonutSwinModel {
+    projection: nn::Conv2D,
+    norm: nn::LayerNorm,
+    dropout: Dropout,
+    stages: Vec<DonutSwinStage>,
+    patch_size: i64,
+    num_channels: i64,
+    output_attentions: bool,
+    output_hidden_states: bool,
+}
+
+impl DonutSwinModel {
+    /// Build a new `DonutSwinModel`
+    ///
+    /// # Arguments
+    ///
+    /// * `p` - Variable store path for the root of the Swin model
+    /// * `config` - `DonutSwinConfig` object defining the model architecture
+    ///
+    /// # Example
+    ///
+    /// ```no_run
+    /// use rust_bert::vision_encoder_decoder::{DonutSwinConfig, DonutSwinModel};
+    /// use rust_bert::Config;
+    /// use std::path::Path;
+    /// use tch::{nn, Device};
+    ///
+    /// let config_path = Path::new("path/to/config.json");
+    /// let device = Device::Cpu;
+    /// let p = nn::VarStore::new(device);
+    /// let config = DonutSwinConfig::from_file(config_path);
+    /// let swin_model = DonutSwinModel::new(&p.root() / "encoder", &config);
+    /// ```
+    pub fn new<'p, P>(p: P, config: &DonutSwinConfig) -> DonutSwinModel
+    where
+        P: Borrow<nn::Path<'p>>,
+    {
+        let p = p.borrow();
+
+        let p_embeddings = p / "embeddings";
+        let projection = nn::conv2d(
+            &p_embeddings / "patch_embeddings" / "projection",
+            config.num_channels,
+            config.embed_dim,
+            config.patch_size,
+            ConvConfig {
+                stride: config.patch_size,
+                ..Default::default()
+            },
+        );
+        let norm = nn::layer_norm(
+            &p_embeddings / "norm",
+            vec![config.embed_dim],
+            Default::default(),
+        );
+
+        let (image_height, image_width) = config.image_size.height_width();
+        let mut input_resolution = (
+            (image_height + config.patch_size - 1) / config.patch_size,
+            (image_width + config.patch_size - 1) / config.patch_size,
+        );
+        let p_stages = p / "encoder" / "layers";
+        let mut stages = Vec::with_capacity(config.depths.len());
+        for stage_index in 0..config.depths.len() {
+            stages.push(DonutSwinStage::new(
+                &p_stages / stage_index,
+                config,
+                stage_index,
+                input_resolution,
+            ));
+            input_resolution = ((input_resolution.0 + 1) / 2, (input_resolution.1 + 1) / 2);
+        }
+
+        DonutSwinModel {
+            projection,
+            norm,
+            dropout: Dropout::new(config.hidden_dropout_prob),
+            stages,
+            patch_size: config.patch_size,
+            num_channels: config.num_channels,
+            output_attentions: config.output_attentions.unwrap_or(false),
+            output_hidden_states: config.output_hidden_states.unwrap_or(false),
+        }
+    }
+
+    /// Forward pass through the model
+    ///
+    /// # Arguments
+    ///
+    /// * `pixel_values` - Normalized images of shape (*batch size*, *num_channels*, *height*, *width*)
+    /// * `train` - boolean flag to turn on/off the dropout layers in the model. Should be set to false for inference.
+    ///
+    /// # Returns
+    ///
+    /// * `DonutSwinModelOutput` containing:
+    ///   - `hidden_state` - `Tensor` of shape (*batch size*, *num_patches*, *hidden_size*) of the last stage
+    ///   - `all_hidden_states` - `Option<Vec<Tensor>>` of length *num_layers + 1* with the input of each layer and the final output
+    ///   - `all_attentions` - `Option<Vec<Tensor>>` of length *num_layers* with shape (*batch size x num_windows*, *num_heads*, *window_size²*, *window_size²*)
+    ///
+    /// # Example
+    ///
+    /// ```no_run
+    /// # use rust_bert::vision_encoder_decoder::{DonutSwinConfig, DonutSwinModel};
+    /// # use rust_bert::Config;
+    /// # use std::path::Path;
+    /// # use tch::{nn, no_grad, Device, Kind, Tensor};
+    /// # fn main() -> anyhow::Result<()> {
+    /// # let config_path = Path::new("path/to/config.json");
+    /// # let device = Device::Cpu;
+    /// # let vs = nn::VarStore::new(device);
+    /// # let config = DonutSwinConfig::from_file(config_path);
+    /// # let swin_model = DonutSwinModel::new(&vs.root(), &config);
+    /// let pixel_values = Tensor::rand(&[1, 3, 2560, 1920], (Kind::Float, device));
+    /// let model_output = no_grad(|| swin_model.forward_t(&pixel_values, false))?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn forward_t(
+        &self,
+        pixel_values: &Tensor,
+        train: bool,
+    ) -> Result<DonutSwinModelOutput, RustBertError> {
+        let input_shape = pixel_values.size();
+        if input_shape.len() != 4 || input_shape[1] != self.num_channels {
+            return Err(RustBertError::ValueError(format!(
+                "Expected pixel values of shape (batch size, {}, height, width), got {:?}",
+                self.num_channels, input_shape
+            )));
+        }
+        let (height, width) = (input_shape[2], input_shape[3]);
+        let pad_right = (self.patch_size - width % self.patch_size) % self.patch_size;
+        let pad_bottom = (self.patch_size - height % self.patch_size) % self.patch_size;
+        let pixel_values = if pad_right > 0 || pad_bottom > 0 {
+            pixel_values.constant_pad_nd(&[0, pad_right, 0, pad_bottom])
+        } else {
+            pixel_values.shallow_clone()
+        };
+
+        let patch_embeddings = pixel_values.apply(&self.projection);
+        let mut input_dimensions = (patch_embeddings.size()[2], patch_embeddings.size()[3]);
+        let mut hidden_state = patch_embeddings
+            .flatten(2, -1)
+            .transpose(1, 2)
+            .apply(&self.norm)
+            .apply_t(&self.dropout, train);
+
+        let mut all_hidden_states: Option<Vec<Tensor>> = if self.output_hidden_states {
+            Some(vec![])
+        } else {
+            None
+        };
+        let mut all_attentions: Option<Vec<Tensor>> = if self.output_attentions {
+            Some(vec![])
+        } else {
+            None
+        };
+
+        for stage in &self.stages {
+            for block in &stage.blocks {
+                if let Some(hidden_states) = all_hidden_states.borrow_mut() {
+                    hidden_states.push(hidden_state.copy());
+                };
+                let (output, attention_weights) =
+                    block.forward_t(&hidden_state, input_dimensions, train);
+                hidden_state = output;
+                if let Some(attentions) = all_attentions.borrow_mut() {
+                    attentions.push(attention_weights.unwrap());
+                };
+            }
+            if let Some(downsample) = &stage.downsample {
+                hidden_state = downsample.forward(&hidden_state, input_dimensions);
+                input_dimensions = ((input_dimensions.0 + 1) / 2, (input_dimensions.1 + 1) / 2);
+            }
+        }
+        if let Some(hidden_states) = all_hidden_states.borrow_mut() {
+            hidden_states.push(hidden_state.copy());
+        };
+
+        Ok(DonutSwinModelOutput {
+            hidden_state,
+            all_hidden_states,
+            all_attentions,
+        })
+    }
+}
+
+/// Container for the Donut Swin model output.
+pub struct DonutSwinModelOutput {
+    /// Last hidden states from the model
+    pub hidden_state: Tensor,
+    /// Hidden states for all intermediate layers
+    pub all_hidden_states: Option<Vec<Tensor>>,
+    /// Attention weights for all intermediate layers
+    pub all_attentions: Option<Vec<Tensor>>,
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn window_partition_round_trip() {
+        let input = Tensor::arange(2 * 4 * 6 * 3, (Kind::Float, Device::Cpu)).view([2, 4, 6, 3]);
+        let windows = window_partition(&input, 2);
+        assert_eq!(windows.size(), vec![12, 2, 2, 3]);
+        // The second window of the first image holds the patches of rows 0-1 and columns 2-3
+        assert!(windows
+            .get(1)
+            .equal(&input.get(0).narrow(0, 0, 2).narrow(1, 2, 2)));
+        assert!(window_reverse(&windows, 2, 4, 6).equal(&input));
+    }
+
+    #[test]
+    fn shifted_window_mask_blocks_distant_regions() {
+        let mask = shifted_window_mask(4, 4, 2, 1, Kind::Float, Device::Cpu);
+        assert_eq!(mask.size(), vec![4, 4, 4]);
+        // The first window is not affected by the shift, the last window mixes 4 regions of the image
+        assert_eq!(mask.get(0).abs().sum(Kind::Float).double_value(&[]), 0.0);
+        let last_window = mask.get(3);
+        assert_eq!(
+            last_window.diag(0).abs().sum(Kind::Float).double_value(&[]),
+            0.0
+        );
+        assert_eq!(
+            last_window.eq(-100.0).sum(Kind::Int64).int64_value(&[]),
+            4 * 4 - 4
+        );
+    }
+
+    #[test]
+    fn relative_position_index_range() {
+        let index = relative_position_index(3, Device::Cpu);
+        assert_eq!(index.size(), vec![9, 9]);
+        assert_eq!(index.min().int64_value(&[]), 0);
+        assert_eq!(index.max().int64_value(&[]), 24);
+        // Identical positions share the center of the bias table
+        assert_eq!(Vec::<i64>::from(index.diag(0)), vec![12; 9]);
+    }
+}
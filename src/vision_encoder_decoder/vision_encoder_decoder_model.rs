@@ -0,0 +1,775 @@
+// Copyright 2021 The HuggingFace Inc. team.
+// Copyright 2022 Guillaume Becquin
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//     http://www.apache.org/licenses/LICENSE-2.0
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use crate::bart::BartModelOutput;
+use crate::pipelines::common::{ModelType, TokenizerOption};
+use crate::pipelines::generation_utils::private_generation_utils::{
+    PreparedInput, PrivateLanguageGenerator,
+};
+use crate::pipelines::generation_utils::{
+    Cache, GenerateConfig, GenerateOptions, GeneratedIndicesOutput, LMHeadModel, LMModelOutput,
+    LanguageGenerator,
+};
+use crate::vision_encoder_decoder::attention::LayerState;
+use crate::vision_encoder_decoder::decoder::{TextDecoder, TextDecoderConfig};
+use crate::vision_encoder_decoder::swin::{DonutSwinConfig, DonutSwinModel};
+use crate::vit::{ViTConfig, ViTModel};
+use crate::{Config, RustBertError};
+use rust_tokenizers::tokenizer::RobertaTokenizer;
+use rust_tokenizers::vocab::RobertaVocab;
+use serde::de::Error;
+use serde::{Deserialize, Deserializer, Serialize};
+use std::borrow::Borrow;
+use tch::{nn, no_grad, Kind, Tensor};
+
+#[derive(Debug, Serialize, Clone)]
+#[serde(tag = "model_type")]
+/// # Image encoder configuration
+/// Configuration of the image encoder of a vision encoder-decoder model, selected by its `model_type`
+pub enum VisionEncoderConfig {
+    /// ViT encoder (`model_type` "vit", TrOCR base and large checkpoints)
+    #[serde(rename = "vit")]
+    ViT(ViTConfig),
+    /// Swin Transformer encoder (`model_type` "donut-swin", Donut checkpoints)
+    #[serde(rename = "donut-swin")]
+    DonutSwin(DonutSwinConfig),
+}
+
+impl<'de> Deserialize<'de> for VisionEncoderConfig {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        // The configuration is not deserialized as an internally tagged enum, which does not support the integer
+        // keys of the `id2label` mapping
+        let value = serde_json::Value::deserialize(deserializer)?;
+        let model_type = value
+            .get("model_type")
+            .and_then(|model_type| model_type.as_str())
+            .unwrap_or_default()
+            .to_string();
+        match model_type.as_str() {
+            "vit" => serde_json::from_value(value)
+                .map(VisionEncoderConfig::ViT)
+                .map_err(D::Error::custom),
+            "donut-swin" | "swin" => serde_json::from_value(value)
+                .map(VisionEncoderConfig::DonutSwin)
+                .map_err(D::Error::custom),
+            _ => Err(D::Error::custom(format!(
+                "Unsupported image encoder `{}` (expected `vit` or `donut-swin`)",
+                model_type
+            ))),
+        }
+    }
+}
+
+impl VisionEncoderConfig {
+    /// Dimension of the hidden states output by the encoder
+    pub fn hidden_size(&self) -> i64 {
+        match self {
+            VisionEncoderConfig::ViT(config) => config.hidden_size,
+            VisionEncoderConfig::DonutSwin(config) => config.hidden_size(),
+        }
+    }
+
+    /// Height and width of the images seen during training
+    pub fn image_size(&self) -> (i64, i64) {
+        match self {
+            VisionEncoderConfig::ViT(config) => (config.image_size, config.image_size),
+            VisionEncoderConfig::DonutSwin(config) => config.image_size.height_width(),
+        }
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+/// # Vision encoder-decoder model configuration
+/// Defines the image encoder and text decoder of the model, and the special tokens used for the generation
+pub struct VisionEncoderDecoderConfig {
+    pub encoder: VisionEncoderConfig,
+    pub decoder: TextDecoderConfig,
+    pub decoder_start_token_id: Option<i64>,
+    pub pad_token_id: Option<i64>,
+    pub eos_token_id: Option<i64>,
+}
+
+impl Config for VisionEncoderDecoderConfig {}
+
+impl VisionEncoderDecoderConfig {
+    /// Checks that the text decoder is supported (TrOCR or MBart decoder with learned position embeddings)
+    pub fn validate(&self) -> Result<(), RustBertError> {
+        match self.decoder.model_type.as_deref() {
+            Some("trocr") | Some("mbart") | None => {}
+            Some(model_type) => {
+                return Err(RustBertError::InvalidConfigurationError(format!(
+                    "Unsupported text decoder `{}` (expected `trocr` or `mbart`)",
+                    model_type
+                )));
+            }
+        }
+        if !self.decoder.use_learned_position_embeddings.unwrap_or(true) {
+            return Err(RustBertError::InvalidConfigurationError(
+                "Only text decoders with learned position embeddings are supported".into(),
+            ));
+        }
+        Ok(())
+    }
+}
+
+enum VisionEncoder {
+    ViT(ViTModel),
+    DonutSwin(DonutSwinModel),
+}
+
+/// # Vision encoder-decoder model
+/// Image encoder with a text decoder attending to the encoded image, used for optical character recognition (TrOCR)
+/// and document understanding (Donut).
+/// It is made of the following blocks:
+/// - `encoder`: `ViTModel` or `DonutSwinModel` image encoder
+/// - `enc_to_dec_proj`: optional projection of the image features to the decoder hidden size (if they differ and the
+/// decoder cross-attention does not project them)
+/// - `decoder`: `TextDecoder` with self-attention and cross-attention over the image features. Caching is implemented
+/// for the decoder to avoid recalculating static states (encoder key/values and previously calculated decoder key/values)
+/// - `lm_head`: linear layer without bias projecting the decoder hidden states to the vocabulary
+pub struct VisionEncoderDecoderModel {
+    encoder: VisionEncoder,
+    enc_to_dec_proj: Option<nn::Linear>,
+    decoder: TextDecoder,
+    lm_head: nn::Linear,
+}
+
+impl VisionEncoderDecoderModel {
+    /// Build a new `VisionEncoderDecoderModel`
+    ///
+    /// # Arguments
+    ///
+    /// * `p` - Variable store path for the root of the model
+    /// * `config` - `VisionEncoderDecoderConfig` object defining the model architecture
+    ///
+    /// # Example
+    ///
+    /// ```no_run
+    /// use rust_bert::vision_encoder_decoder::{
+    ///     VisionEncoderDecoderConfig, VisionEncoderDecoderModel,
+    /// };
+    /// use rust_bert::Config;
+    /// use std::path::Path;
+    /// use tch::{nn, Device};
+    ///
+    /// let config_path = Path::new("path/to/config.json");
+    /// let device = Device::Cpu;
+    /// let p = nn::VarStore::new(device);
+    /// let config = VisionEncoderDecoderConfig::from_file(config_path);
+    /// let model = VisionEncoderDecoderModel::new(&p.root(), &config);
+    /// ```
+    pub fn new<'p, P>(p: P, config: &VisionEncoderDecoderConfig) -> VisionEncoderDecoderModel
+    where
+        P: Borrow<nn::Path<'p>>,
+    {
+        let p = p.borrow();
+
+        let encoder = match &config.encoder {
+            VisionEncoderConfig::ViT(encoder_config) => {
+                VisionEncoder::ViT(ViTModel::new(p / "encoder", encoder_config))
+            }
+            VisionEncoderConfig::DonutSwin(encoder_config) => {
+                VisionEncoder::DonutSwin(DonutSwinModel::new(p / "encoder", encoder_config))
+            }
+        };
+        let encoder_hidden_size = config.encoder.hidden_size();
+        let enc_to_dec_proj = if encoder_hidden_size != config.decoder.d_model
+            && config.decoder.cross_attention_hidden_size.is_none()
+        {
+            Some(nn::linear(
+                p / "enc_to_dec_proj",
+                encoder_hidden_size,
+                config.decoder.d_model,
+                Default::default(),
+            ))
+        } else {
+            None
+        };
+
+        let p_decoder = p / "decoder";
+        let decoder = TextDecoder::new(&p_decoder / "model" / "decoder", &config.decoder);
+        let lm_head_name = if config.decoder.is_pre_layer_norm() {
+            "lm_head"
+        } else {
+            "output_projection"
+        };
+        let lm_head = nn::linear(
+            &p_decoder / lm_head_name,
+            config.decoder.d_model,
+            config.decoder.vocab_size,
+            nn::LinearConfig {
+                bias: false,
+                ..Default::default()
+            },
+        );
+
+        VisionEncoderDecoderModel {
+            encoder,
+            enc_to_dec_proj,
+            decoder,
+            lm_head,
+        }
+    }
+
+    fn encode_t(
+        &self,
+        pixel_values: &Tensor,
+        train: bool,
+    ) -> Result<(Tensor, Option<Vec<Tensor>>, Option<Vec<Tensor>>), RustBertError> {
+        let (hidden_state, all_hidden_states, all_attentions) = match &self.encoder {
+            VisionEncoder::ViT(encoder) => {
+                let output = encoder.forward_t(pixel_values, false, train)?;
+                (
+                    output.hidden_state,
+                    output.all_hidden_states,
+                    output.all_attentions,
+                )
+            }
+            VisionEncoder::DonutSwin(encoder) => {
+                let output = encoder.forward_t(pixel_values, train)?;
+                (
+                    output.hidden_state,
+                    output.all_hidden_states,
+                    output.all_attentions,
+                )
+            }
+        };
+        let hidden_state = match &self.enc_to_dec_proj {
+            Some(enc_to_dec_proj) => hidden_state.apply(enc_to_dec_proj),
+            None => hidden_state,
+        };
+        Ok((hidden_state, all_hidden_states, all_attentions))
+    }
+
+    /// Encodes images of shape (*batch size*, *num_channels*, *height*, *width*), returning the image features of
+    /// shape (*batch size*, *num_patches*, *hidden_size*) attended by the decoder
+    pub fn encode(&self, pixel_values: &Tensor, train: bool) -> Result<Tensor, RustBertError> {
+        Ok(self.encode_t(pixel_values, train)?.0)
+    }
+
+    /// Forward pass through the model
+    ///
+    /// # Arguments
+    ///
+    /// * `pixel_values` - Optional normalized images of shape (*batch size*, *num_channels*, *height*, *width*). Must be provided when the encoder output is not provided.
+    /// * `decoder_input_ids` - Input tensor of shape (*batch size*, *target_sequence_length*), starting with the decoder start token (or the task prompt for Donut)
+    /// * `encoder_output` - Optional tensor of shape (*batch size*, *num_patches*, *hidden_size*). When provided, the encoder hidden state will not be recalculated. Useful for generation tasks.
+    /// * `layer_states` - Optional vector of length *num_layers* containing tuples of optional `LayerStates` containing the last calculated key and value pairs for the decoder self attention and encoder cross attention.
+    /// * `train` - boolean flag to turn on/off the dropout layers in the model. Should be set to false for inference.
+    ///
+    /// # Returns
+    ///
+    /// * `VisionEncoderDecoderModelOutput` containing:
+    ///   - `decoder_output` - `Tensor` of shape (*batch size*, *target_sequence_length*, *vocab_size*) representing the logits for each vocabulary item and position
+    ///   - `encoder_hidden_state` - `Option<Tensor>` of shape (*batch size*, *num_patches*, *hidden_size*) representing the image features if they were not provided, otherwise None
+    ///   - `cache` - `Option<Vec<(Option<LayerState>, Option<LayerState>)>>` of length *n_layer* containing the past keys and values for both the self attention and the encoder cross attention of each layer of the decoder.
+    ///   - `all_encoder_hidden_states` - `Option<Vec<Tensor>>` with the hidden states of the encoder layers
+    ///   - `all_encoder_attentions` - `Option<Vec<Tensor>>` with the attention weights of the encoder layers
+    ///   - `all_decoder_hidden_states` - `Option<Vec<Tensor>>` of length *num_decoder_layers* with shape (*batch size*, *target_sequence_length*, *hidden_size*)
+    ///   - `all_decoder_attentions` - `Option<Vec<Tensor>>` of length *num_decoder_layers* with shape (*batch size*, *num_heads*, *target_sequence_length*, *target_sequence_length*)
+    ///
+    /// # Example
+    ///
+    /// ```no_run
+    /// # use tch::{nn, Device, Tensor, no_grad};
+    /// # use rust_bert::Config;
+    /// # use std::path::Path;
+    /// # use tch::kind::Kind::Float;
+    /// # use rust_bert::vision_encoder_decoder::{VisionEncoderDecoderConfig, VisionEncoderDecoderModel};
+    /// # let config_path = Path::new("path/to/config.json");
+    /// # let device = Device::Cpu;
+    /// # let vs = nn::VarStore::new(device);
+    /// # let config = VisionEncoderDecoderConfig::from_file(config_path);
+    /// # let model = VisionEncoderDecoderModel::new(&vs.root(), &config);
+    /// let pixel_values = Tensor::rand(&[1, 3, 384, 384], (Float, device));
+    /// let decoder_input_ids = Tensor::of_slice(&[2i64]).unsqueeze(0);
+    ///
+    /// let model_output = no_grad(|| {
+    ///     model.forward_t(Some(&pixel_values), &decoder_input_ids, None, None, false)
+    /// });
+    /// ```
+    pub fn forward_t(
+        &self,
+        pixel_values: Option<&Tensor>,
+        decoder_input_ids: &Tensor,
+        encoder_output: Option<&Tensor>,
+        layer_states: Option<Vec<(Option<LayerState>, Option<LayerState>)>>,
+        train: bool,
+    ) -> Result<VisionEncoderDecoderModelOutput, RustBertError> {
+        let (calc_hidden_states, all_encoder_hidden_states, all_encoder_attentions) =
+            match (encoder_output, pixel_values) {
+                (Some(_), _) => (None, None, None),
+                (None, Some(pixel_values)) => {
+                    let (hidden_state, all_hidden_states, all_attentions) =
+                        self.encode_t(pixel_values, train)?;
+                    (Some(hidden_state), all_hidden_states, all_attentions)
+                }
+                (None, None) => {
+                    return Err(RustBertError::ValueError(
+                        "Either the pixel values or the encoder output must be provided".into(),
+                    ));
+                }
+            };
+        let encoder_output = encoder_output.unwrap_or_else(|| calc_hidden_states.as_ref().unwrap());
+
+        let decoder_output =
+            self.decoder
+                .forward_t(decoder_input_ids, encoder_output, layer_states, train)?;
+
+        Ok(VisionEncoderDecoderModelOutput {
+            decoder_output: decoder_output.hidden_state.apply(&self.lm_head),
+            encoder_hidden_state: calc_hidden_states,
+            cache: decoder_output.next_decoder_cache,
+            all_decoder_hidden_states: decoder_output.all_hidden_states,
+            all_decoder_attentions: decoder_output.all_attentions,
+            all_decoder_cross_attentions: decoder_output.all_cross_attentions,
+            all_encoder_hidden_states,
+            all_encoder_attentions,
+        })
+    }
+}
+
+impl LMHeadModel for VisionEncoderDecoderModel {
+    /// Forward pass through the model for generation. The image features must be provided as `encoder_outputs`:
+    /// the encoder of the model does not process token ids.
+    ///
+    /// # Arguments
+    ///
+    /// * `input_ids` - Unused, the images are encoded beforehand
+    /// * `layer_past` - `Cache::BARTCache` containing the past keys and values of the decoder layers
+    /// * `attention_mask` - Unused, all the image patches are attended
+    /// * `token_type_ids` - Unused
+    /// * `position_ids` - Unused
+    /// * `input_embeds` - Unused
+    /// * `encoder_outputs` - Image features of shape (*batch size*, *num_patches*, *hidden_size*)
+    /// * `decoder_input_ids` - Decoder input tensor of shape (*batch size*, *target_sequence_length*)
+    /// * `train` - boolean flag to turn on/off the dropout layers in the model. Should be set to false for inference.
+    ///
+    /// # Returns
+    ///
+    /// * `LMModelOutput` containing:
+    ///   - `lm_logits` - `Tensor` of shape (*batch size*, *target_sequence_length*, *vocab_size*) representing the logits for each vocab item and position
+    ///   - `cache` - `Cache::BARTCache` containing the past keys and values of the decoder layers
+    fn forward_t(
+        &self,
+        _input_ids: Option<&Tensor>,
+        cache: Cache,
+        _attention_mask: Option<&Tensor>,
+        _token_type_ids: Option<&Tensor>,
+        _position_ids: Option<&Tensor>,
+        _input_embeds: Option<&Tensor>,
+        encoder_outputs: Option<&Tensor>,
+        decoder_input_ids: Option<&Tensor>,
+        train: bool,
+    ) -> Result<LMModelOutput, RustBertError> {
+        let layer_states = match cache {
+            Cache::BARTCache(cached_layer_states) => cached_layer_states,
+            Cache::None => None,
+            _ => {
+                return Err(RustBertError::ValueError(
+                    "Cache not compatible with the vision encoder-decoder model".into(),
+                ));
+            }
+        };
+        let encoder_outputs = encoder_outputs.ok_or_else(|| {
+            RustBertError::ValueError(
+                "The image features must be provided as encoder outputs for the generation".into(),
+            )
+        })?;
+        let decoder_input_ids = decoder_input_ids.ok_or_else(|| {
+            RustBertError::ValueError(
+                "Decoder input ids must be provided for the generation".into(),
+            )
+        })?;
+
+        let decoder_output =
+            self.decoder
+                .forward_t(decoder_input_ids, encoder_outputs, layer_states, train)?;
+        Ok(LMModelOutput {
+            lm_logits: decoder_output.hidden_state.apply(&self.lm_head),
+            cache: Cache::BARTCache(decoder_output.next_decoder_cache),
+            hidden_states: Some(decoder_output.hidden_state),
+        })
+    }
+}
+
+/// Container holding a vision encoder-decoder model output
+pub type VisionEncoderDecoderModelOutput = BartModelOutput;
+
+/// # Language generation model based on the vision encoder-decoder architecture
+/// Generates text from images with the generation utilities shared with the text models (greedy decoding, sampling,
+/// beam search...). The images are encoded first, see `VisionEncoderDecoderGenerator::generate_from_images`: the
+/// generation from text prompts is not supported.
+pub struct VisionEncoderDecoderGenerator {
+    model: VisionEncoderDecoderModel,
+    tokenizer: TokenizerOption,
+    var_store: nn::VarStore,
+    generate_config: GenerateConfig,
+    bos_token_id: Option<i64>,
+    eos_token_ids: Option<Vec<i64>>,
+    pad_token_id: Option<i64>,
+    is_encoder_decoder: bool,
+    vocab_size: i64,
+    decoder_start_id: Option<i64>,
+    max_position_embeddings: i64,
+}
+
+impl VisionEncoderDecoderGenerator {
+    /// Build a new `VisionEncoderDecoderGenerator` with a byte-level BPE tokenizer (TrOCR checkpoints)
+    ///
+    /// # Arguments
+    ///
+    /// * `generate_config` - `GenerateConfig` object containing the resource references (model, configuration,
+    /// vocabulary `vocab.json` and merges `merges.txt`), the generation options and the device placement
+    ///
+    /// # Example
+    ///
+    /// ```no_run
+    /// # fn main() -> anyhow::Result<()> {
+    /// use rust_bert::pipelines::generation_utils::GenerateConfig;
+    /// use rust_bert::resources::LocalResource;
+    /// use rust_bert::vision_encoder_decoder::VisionEncoderDecoderGenerator;
+    /// use std::path::PathBuf;
+    ///
+    /// let generate_config = GenerateConfig {
+    ///     model_resource: Box::new(LocalResource::from(PathBuf::from("path/to/rust_model.ot"))),
+    ///     config_resource: Box::new(LocalResource::from(PathBuf::from("path/to/config.json"))),
+    ///     vocab_resource: Box::new(LocalResource::from(PathBuf::from("path/to/vocab.json"))),
+    ///     merges_resource: Box::new(LocalResource::from(PathBuf::from("path/to/merges.txt"))),
+    ///     num_beams: 4,
+    ///     max_length: 64,
+    ///     ..Default::default()
+    /// };
+    /// let generator = VisionEncoderDecoderGenerator::new(generate_config)?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn new(
+        generate_config: GenerateConfig,
+    ) -> Result<VisionEncoderDecoderGenerator, RustBertError> {
+        let vocab_path = generate_config.vocab_resource.get_local_path()?;
+        let merges_path = generate_config.merges_resource.get_local_path()?;
+
+        let tokenizer = TokenizerOption::from_file(
+            ModelType::Roberta,
+            vocab_path.to_str().unwrap(),
+            Some(merges_path.to_str().unwrap()),
+            false,
+            None,
+            false,
+        )?;
+
+        Self::new_with_tokenizer(generate_config, tokenizer)
+    }
+
+    pub fn new_with_tokenizer(
+        generate_config: GenerateConfig,
+        tokenizer: TokenizerOption,
+    ) -> Result<VisionEncoderDecoderGenerator, RustBertError> {
+        let config_path = generate_config.config_resource.get_local_path()?;
+        let device = generate_config.device;
+
+        generate_config.validate();
+        let mut config = VisionEncoderDecoderConfig::from_file(config_path);
+        config.validate()?;
+        if generate_config.output_attentions {
+            config.decoder.output_attentions = Some(true);
+        }
+        let mut var_store = nn::VarStore::new(device);
+        let model = VisionEncoderDecoderModel::new(&var_store.root(), &config);
+        crate::resources::load_weights(&generate_config.model_resource, &mut var_store)?;
+
+        let decoder_config = &config.decoder;
+        let bos_token_id = Some(decoder_config.bos_token_id.unwrap_or(0));
+        let eos_token_ids = Some(vec![config
+            .eos_token_id
+            .or(decoder_config.eos_token_id)
+            .unwrap_or(2)]);
+        let pad_token_id = Some(
+            config
+                .pad_token_id
+                .or(decoder_config.pad_token_id)
+                .unwrap_or(1),
+        );
+        let vocab_size = decoder_config.vocab_size;
+        let is_encoder_decoder = true;
+        let decoder_start_id = config
+            .decoder_start_token_id
+            .or(decoder_config.decoder_start_token_id)
+            .or(bos_token_id);
+        let max_position_embeddings = decoder_config.max_position_embeddings;
+
+        Ok(VisionEncoderDecoderGenerator {
+            model,
+            tokenizer,
+            var_store,
+            generate_config,
+            bos_token_id,
+            eos_token_ids,
+            pad_token_id,
+            is_encoder_decoder,
+            vocab_size,
+            decoder_start_id,
+            max_position_embeddings,
+        })
+    }
+
+    /// Encodes images of shape (*batch size*, *num_channels*, *height*, *width*), returning the image features
+    /// attended by the decoder
+    pub fn encode_images(&self, pixel_values: &Tensor) -> Result<Tensor, RustBertError> {
+        no_grad(|| {
+            self.model
+                .encode(&pixel_values.to(self.var_store.device()), false)
+        })
+    }
+
+    /// Generates token indices from images. The images are encoded once, and the decoder starts from the decoder
+    /// start token (or `GenerateOptions::decoder_start_token_id`, e.g. the task token of Donut checkpoints).
+    ///
+    /// # Arguments
+    ///
+    /// * `pixel_values` - Normalized images of shape (*batch size*, *num_channels*, *height*, *width*) (see `ViTImageProcessor`)
+    /// * `generate_options` - `Option<GenerateOptions>` Optional set of generate options. If not (or partially) provided, will use the settings provided when creating the generator
+    ///
+    /// # Returns
+    /// * `Vec<GeneratedIndicesOutput>` of length *batch size* x *num_return_sequences*, with the generated indices starting with the decoder start token
+    ///
+    /// # Example
+    ///
+    /// ```no_run
+    /// # fn main() -> anyhow::Result<()> {
+    /// # use rust_bert::pipelines::generation_utils::GenerateConfig;
+    /// # use rust_bert::vision_encoder_decoder::VisionEncoderDecoderGenerator;
+    /// use tch::{Device, Kind, Tensor};
+    /// # let generate_config: GenerateConfig = Default::default();
+    /// let generator = VisionEncoderDecoderGenerator::new(generate_config)?;
+    /// let pixel_values = Tensor::rand(&[2, 3, 384, 384], (Kind::Float, Device::Cpu));
+    /// let output = generator.generate_from_images(&pixel_values, None)?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn generate_from_images(
+        &self,
+        pixel_values: &Tensor,
+        generate_options: Option<GenerateOptions>,
+    ) -> Result<Vec<GeneratedIndicesOutput>, RustBertError> {
+        let encoder_outputs = self.encode_images(pixel_values)?;
+        let (batch_size, num_patches, _) = encoder_outputs.size3()?;
+        // The input ids only define the encoder attention mask: all the image patches are attended
+        let input_ids = Tensor::ones(
+            &[batch_size, num_patches],
+            (Kind::Int64, encoder_outputs.device()),
+        );
+        let attention_mask = input_ids.ones_like();
+        let generate_options = GenerateOptions {
+            encoder_outputs: Some(&encoder_outputs),
+            ..generate_options.unwrap_or_default()
+        };
+        Ok(
+            self.generate_from_ids_and_past(
+                input_ids,
+                Some(attention_mask),
+                Some(generate_options),
+            ),
+        )
+    }
+}
+
+impl PrivateLanguageGenerator<VisionEncoderDecoderModel, RobertaVocab, RobertaTokenizer>
+    for VisionEncoderDecoderGenerator
+{
+    fn get_model(&self) -> &VisionEncoderDecoderModel {
+        &self.model
+    }
+    fn _get_tokenizer(&self) -> &TokenizerOption {
+        &self.tokenizer
+    }
+    fn get_var_store(&self) -> &nn::VarStore {
+        &self.var_store
+    }
+    fn get_var_store_mut(&mut self) -> &mut nn::VarStore {
+        &mut self.var_store
+    }
+    fn get_config(&self) -> &GenerateConfig {
+        &self.generate_config
+    }
+    fn get_bos_id(&self) -> Option<i64> {
+        self.bos_token_id
+    }
+    fn get_eos_ids(&self) -> Option<&Vec<i64>> {
+        self.eos_token_ids.as_ref()
+    }
+    fn get_pad_id(&self) -> Option<i64> {
+        self.pad_token_id
+    }
+    fn is_encoder_decoder(&self) -> bool {
+        self.is_encoder_decoder
+    }
+    fn get_vocab_size(&self) -> i64 {
+        self.vocab_size
+    }
+    fn get_decoder_start_id(&self) -> Option<i64> {
+        self.decoder_start_id
+    }
+    fn get_max_positions_embeddings(&self) -> i64 {
+        self.max_position_embeddings
+    }
+
+    fn encode(&self, _input_ids: &Tensor, _attention_mask: Option<&Tensor>) -> Option<Tensor> {
+        panic!(
+            "The vision encoder-decoder models generate from images, see `generate_from_images`"
+        );
+    }
+
+    fn prepare_inputs_for_generation<'a>(
+        &self,
+        input_ids: Tensor,
+        encoder_outputs: Option<&'a Tensor>,
+        past: Cache,
+        attention_mask: Tensor,
+    ) -> PreparedInput<'a> {
+        match past {
+            Cache::BARTCache(past) => PreparedInput {
+                prepared_input: None,
+                prepared_attention_mask: Some(attention_mask),
+                prepared_encoder_output: encoder_outputs,
+                prepared_decoder_input: Some(input_ids.narrow(1, -1, 1)),
+                prepared_position_ids: None,
+                prepared_past: Cache::BARTCache(past),
+            },
+            Cache::None => PreparedInput {
+                prepared_input: None,
+                prepared_attention_mask: Some(attention_mask),
+                prepared_encoder_output: encoder_outputs,
+                prepared_decoder_input: Some(input_ids),
+                prepared_position_ids: None,
+                prepared_past: Cache::BARTCache(None),
+            },
+            _ => panic!("Cache type incompatible with the vision encoder-decoder model"),
+        }
+    }
+
+    fn reorder_cache(
+        &self,
+        past: &mut Cache,
+        encoder_outputs: Option<Tensor>,
+        beam_indices: &Tensor,
+    ) -> Option<Tensor> {
+        let encoder_outputs = encoder_outputs.map(|value| value.index_select(0, beam_indices));
+        match past {
+            Cache::BARTCache(old_cache_option) => match old_cache_option {
+                Some(old_cache) => {
+                    for (self_layer_state, encoder_layer_state) in old_cache.iter_mut() {
+                        if self_layer_state.is_some() {
+                            self_layer_state
+                                .as_mut()
+                                .unwrap()
+                                .reorder_cache(beam_indices)
+                        };
+                        if encoder_layer_state.is_some() {
+                            encoder_layer_state
+                                .as_mut()
+                                .unwrap()
+                                .reorder_cache(beam_indices)
+                        };
+                    }
+                }
+                None => {}
+            },
+            Cache::None => {}
+            _ => {
+                panic!("Invalid cache for the vision encoder-decoder model");
+            }
+        };
+        encoder_outputs
+    }
+}
+
+impl LanguageGenerator<VisionEncoderDecoderModel, RobertaVocab, RobertaTokenizer>
+    for VisionEncoderDecoderGenerator
+{
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::vision_encoder_decoder::DonutImageSize;
+
+    #[test]
+    fn encoder_configuration_from_model_type() -> anyhow::Result<()> {
+        let trocr_encoder: VisionEncoderConfig = serde_json::from_str(
+            r#"{"model_type": "vit", "hidden_size": 768, "num_hidden_layers": 12, "num_attention_heads": 12,
+            "intermediate_size": 3072, "hidden_act": "gelu", "hidden_dropout_prob": 0.0,
+            "attention_probs_dropout_prob": 0.0, "image_size": 384, "patch_size": 16, "num_channels": 3,
+            "qkv_bias": false, "id2label": {"0": "LABEL_0", "1": "LABEL_1"}}"#,
+        )?;
+        assert_eq!(trocr_encoder.hidden_size(), 768);
+        assert_eq!(trocr_encoder.image_size(), (384, 384));
+
+        let donut_encoder: VisionEncoderConfig = serde_json::from_str(
+            r#"{"model_type": "donut-swin", "image_size": [2560, 1920], "patch_size": 4, "num_channels": 3,
+            "embed_dim": 128, "depths": [2, 2, 14, 2], "num_heads": [4, 8, 16, 32], "window_size": 10,
+            "mlp_ratio": 4.0, "hidden_act": "gelu", "hidden_dropout_prob": 0.0,
+            "attention_probs_dropout_prob": 0.0}"#,
+        )?;
+        assert_eq!(donut_encoder.hidden_size(), 1024);
+        assert_eq!(donut_encoder.image_size(), (2560, 1920));
+
+        assert!(serde_json::from_str::<VisionEncoderConfig>(r#"{"model_type": "deit"}"#).is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn donut_forward_shapes() -> anyhow::Result<()> {
+        let config = VisionEncoderDecoderConfig {
+            encoder: VisionEncoderConfig::DonutSwin(DonutSwinConfig {
+                image_size: DonutImageSize::Rectangle([64, 48]),
+                embed_dim: 8,
+                depths: vec![2, 2],
+                num_heads: vec![2, 4],
+                window_size: 4,
+                ..Default::default()
+            }),
+            decoder: TextDecoderConfig {
+                model_type: Some("mbart".to_string()),
+                vocab_size: 30,
+                d_model: 16,
+                decoder_layers: 2,
+                decoder_attention_heads: 2,
+                decoder_ffn_dim: 32,
+                max_position_embeddings: 16,
+                ..Default::default()
+            },
+            decoder_start_token_id: Some(0),
+            pad_token_id: Some(1),
+            eos_token_id: Some(2),
+        };
+        config.validate()?;
+        let vs = nn::VarStore::new(tch::Device::Cpu);
+        let model = VisionEncoderDecoderModel::new(&vs.root(), &config);
+
+        let pixel_values = Tensor::rand(&[2, 3, 64, 48], (Kind::Float, tch::Device::Cpu));
+        let decoder_input_ids = Tensor::of_slice(&[0i64, 5, 6, 0, 7, 8]).view((2, 3));
+        let output = no_grad(|| {
+            model.forward_t(Some(&pixel_values), &decoder_input_ids, None, None, false)
+        })?;
+
+        // 64x48 images, patches of 4 pixels merged once: 8x6 positions of dimension 16
+        assert_eq!(output.encoder_hidden_state.unwrap().size(), vec![2, 48, 16]);
+        assert_eq!(output.decoder_output.size(), vec![2, 3, 30]);
+        assert_eq!(output.cache.unwrap().len(), 2);
+        Ok(())
+    }
+}
@@ -0,0 +1,145 @@
+// Copyright 2021 Google AI, Ross Wightman, The HuggingFace Inc. team. All rights reserved.
+// Copyright 2022 Guillaume Becquin
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//     http://www.apache.org/licenses/LICENSE-2.0
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use crate::common::dropout::Dropout;
+use crate::vit::ViTConfig;
+use std::borrow::Borrow;
+use tch::{nn, Tensor};
+
+/// # ViT self-attention
+/// Multi-head self-attention over the patch sequence. No attention mask is required as all images of a batch are
+/// resized to the same number of patches.
+pub struct ViTSelfAttention {
+    num_attention_heads: i64,
+    attention_head_size: i64,
+    dropout: Dropout,
+    output_attentions: bool,
+    query: nn::Linear,
+    key: nn::Linear,
+    value: nn::Linear,
+}
+
+impl ViTSelfAttention {
+    pub fn new<'p, P>(p: P, config: &ViTConfig) -> ViTSelfAttention
+    where
+        P: Borrow<nn::Path<'p>>,
+    {
+        assert_eq!(
+            config.hidden_size % config.num_attention_heads,
+            0,
+            "Hidden size not a multiple of the number of attention heads"
+        );
+        let p = p.borrow();
+
+        let linear_config = nn::LinearConfig {
+            bias: config.qkv_bias.unwrap_or(true),
+            ..Default::default()
+        };
+        let query = nn::linear(
+            p / "query",
+            config.hidden_size,
+            config.hidden_size,
+            linear_config,
+        );
+        let key = nn::linear(
+            p / "key",
+            config.hidden_size,
+            config.hidden_size,
+            linear_config,
+        );
+        let value = nn::linear(
+            p / "value",
+            config.hidden_size,
+            config.hidden_size,
+            linear_config,
+        );
+
+        ViTSelfAttention {
+            num_attention_heads: config.num_attention_heads,
+            attention_head_size: config.hidden_size / config.num_attention_heads,
+            dropout: Dropout::new(config.attention_probs_dropout_prob),
+            output_attentions: config.output_attentions.unwrap_or(false),
+            query,
+            key,
+            value,
+        }
+    }
+
+    fn split_heads(&self, x: Tensor, bs: i64) -> Tensor {
+        x.view((bs, -1, self.num_attention_heads, self.attention_head_size))
+            .transpose(1, 2)
+    }
+
+    pub fn forward_t(&self, hidden_states: &Tensor, train: bool) -> (Tensor, Option<Tensor>) {
+        let bs = hidden_states.size()[0];
+
+        let query_layer = self.split_heads(hidden_states.apply(&self.query), bs);
+        let key_layer = self.split_heads(hidden_states.apply(&self.key), bs);
+        let value_layer = self.split_heads(hidden_states.apply(&self.value), bs);
+        let query_layer: Tensor = query_layer / (self.attention_head_size as f64).sqrt();
+
+        let scores = query_layer.matmul(&key_layer.transpose(-1, -2));
+        let weights = scores
+            .softmax(-1, scores.kind())
+            .apply_t(&self.dropout, train);
+        let context = weights
+            .matmul(&value_layer)
+            .transpose(1, 2)
+            .contiguous()
+            .view((bs, -1, self.num_attention_heads * self.attention_head_size));
+
+        if self.output_attentions {
+            (context, Some(weights))
+        } else {
+            (context, None)
+        }
+    }
+}
+
+/// # ViT attention block
+/// Self-attention followed by an output projection. The residual connection and the layer normalization are
+/// applied by the encoder layer (pre-normalization).
+pub struct ViTAttention {
+    attention: ViTSelfAttention,
+    dense: nn::Linear,
+    dropout: Dropout,
+}
+
+impl ViTAttention {
+    pub fn new<'p, P>(p: P, config: &ViTConfig) -> ViTAttention
+    where
+        P: Borrow<nn::Path<'p>>,
+    {
+        let p = p.borrow();
+
+        let attention = ViTSelfAttention::new(p / "attention", config);
+        let dense = nn::linear(
+            p / "output" / "dense",
+            config.hidden_size,
+            config.hidden_size,
+            Default::default(),
+        );
+        let dropout = Dropout::new(config.hidden_dropout_prob);
+
+        ViTAttention {
+            attention,
+            dense,
+            dropout,
+        }
+    }
+
+    pub fn forward_t(&self, hidden_states: &Tensor, train: bool) -> (Tensor, Option<Tensor>) {
+        let (context, attention_weights) = self.attention.forward_t(hidden_states, train);
+        let output = context.apply(&self.dense).apply_t(&self.dropout, train);
+        (output, attention_weights)
+    }
+}
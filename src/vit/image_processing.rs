@@ -0,0 +1,322 @@
+// Copyright 2022 Guillaume Becquin
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//     http://www.apache.org/licenses/LICENSE-2.0
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use crate::RustBertError;
+use tch::{Device, Kind, Tensor};
+
+/// Per-channel mean used to normalize the images of the original ViT checkpoints
+pub const VIT_IMAGE_MEAN: [f64; 3] = [0.5, 0.5, 0.5];
+/// Per-channel standard deviation used to normalize the images of the original ViT checkpoints
+pub const VIT_IMAGE_STD: [f64; 3] = [0.5, 0.5, 0.5];
+
+/// # Decoded image
+/// Borrowed buffer of 8-bit pixels stored row by row with interleaved channels (e.g. `RGBRGB...`), as provided by
+/// most image decoding libraries. Grayscale (1 channel), RGB (3 channels) and RGBA (4 channels, the alpha channel is
+/// ignored) images are supported.
+#[derive(Debug, Clone, Copy)]
+pub struct ImageBuffer<'a> {
+    pub pixels: &'a [u8],
+    pub width: usize,
+    pub height: usize,
+    pub channels: usize,
+}
+
+impl<'a> ImageBuffer<'a> {
+    /// Creates an RGB `ImageBuffer` from interleaved `RGBRGB...` pixels
+    pub fn rgb(pixels: &'a [u8], width: usize, height: usize) -> ImageBuffer<'a> {
+        ImageBuffer {
+            pixels,
+            width,
+            height,
+            channels: 3,
+        }
+    }
+
+    /// Converts the image to a float RGB tensor of shape (3, *height*, *width*) with values in [0, 255]
+    fn to_rgb_tensor(self) -> Result<Tensor, RustBertError> {
+        if self.pixels.len() != self.width * self.height * self.channels {
+            return Err(RustBertError::ValueError(format!(
+                "Expected {} bytes for a {}x{} image with {} channels, got {}",
+                self.width * self.height * self.channels,
+                self.width,
+                self.height,
+                self.channels,
+                self.pixels.len()
+            )));
+        }
+        if self.width == 0 || self.height == 0 {
+            return Err(RustBertError::ValueError("Empty image".into()));
+        }
+        let image = Tensor::of_slice(self.pixels).view([
+            self.height as i64,
+            self.width as i64,
+            self.channels as i64,
+        ]);
+        let image = match self.channels {
+            1 => image.expand(&[-1, -1, 3], false),
+            3 => image,
+            4 => image.narrow(2, 0, 3),
+            channels => {
+                return Err(RustBertError::ValueError(format!(
+                    "Unsupported number of image channels: {} (expected 1, 3 or 4)",
+                    channels
+                )));
+            }
+        };
+        Ok(image.permute(&[2, 0, 1]).to_kind(Kind::Float))
+    }
+}
+
+/// # ViT image processor
+/// Converts decoded images to the pixel values expected by the ViT models: the images are resized to the model image
+/// size with a bilinear interpolation, rescaled to [0, 1] and normalized with a per-channel mean and standard
+/// deviation. With center cropping enabled, the shortest side of the images is resized to the model image size
+/// (preserving the aspect ratio) and the central square is kept. With padding enabled, the images are resized to fit
+/// in the model image size (preserving the aspect ratio) and padded with black pixels, as done for document images.
+pub struct ViTImageProcessor {
+    height: i64,
+    width: i64,
+    center_crop: bool,
+    pad: bool,
+    mean: Tensor,
+    std: Tensor,
+    device: Device,
+}
+
+impl ViTImageProcessor {
+    /// Creates a new `ViTImageProcessor`
+    ///
+    /// # Arguments
+    ///
+    /// * `image_size` - Height and width of the processed images (`image_size` of the model configuration)
+    /// * `mean` - Per-channel (RGB) mean of the rescaled pixel values
+    /// * `std` - Per-channel (RGB) standard deviation of the rescaled pixel values
+    /// * `device` - Device on which the images are processed
+    ///
+    /// # Example
+    ///
+    /// ```no_run
+    /// use rust_bert::vit::{ViTImageProcessor, VIT_IMAGE_MEAN, VIT_IMAGE_STD};
+    /// use tch::Device;
+    ///
+    /// let image_processor = ViTImageProcessor::new(224, VIT_IMAGE_MEAN, VIT_IMAGE_STD, Device::Cpu);
+    /// ```
+    pub fn new(
+        image_size: i64,
+        mean: [f64; 3],
+        std: [f64; 3],
+        device: Device,
+    ) -> ViTImageProcessor {
+        ViTImageProcessor {
+            height: image_size,
+            width: image_size,
+            center_crop: false,
+            pad: false,
+            mean: Tensor::of_slice(&mean)
+                .to_kind(Kind::Float)
+                .view([1, 3, 1, 1])
+                .to(device),
+            std: Tensor::of_slice(&std)
+                .to_kind(Kind::Float)
+                .view([1, 3, 1, 1])
+                .to(device),
+            device,
+        }
+    }
+
+    /// Resizes the shortest side of the images and crops their center instead of resizing them to a square
+    /// (default: false). CLIP checkpoints are trained on center cropped images.
+    pub fn with_center_crop(mut self, center_crop: bool) -> Self {
+        self.center_crop = center_crop;
+        self
+    }
+
+    /// Sets a non-square size for the processed images (e.g. `[2560, 1920]` for Donut checkpoints)
+    pub fn with_image_size(mut self, height: i64, width: i64) -> Self {
+        self.height = height;
+        self.width = width;
+        self
+    }
+
+    /// Resizes the images to fit in the image size and pads them evenly on both sides with black pixels instead of
+    /// stretching them (default: false). Donut checkpoints are trained on padded document images.
+    pub fn with_padding(mut self, pad: bool) -> Self {
+        self.pad = pad;
+        self
+    }
+
+    /// Resizes an image tensor of shape (1, 3, *height*, *width*) to the processed image size
+    fn resize(&self, image: Tensor) -> Tensor {
+        if !self.center_crop && !self.pad {
+            return image.upsample_bilinear2d(
+                &[self.height, self.width],
+                false,
+                None::<f64>,
+                None::<f64>,
+            );
+        }
+        let (height, width) = (image.size()[2], image.size()[3]);
+        let height_scale = self.height as f64 / height as f64;
+        let width_scale = self.width as f64 / width as f64;
+        if self.pad {
+            let scale = height_scale.min(width_scale);
+            let resized_height = ((height as f64 * scale).round() as i64).clamp(1, self.height);
+            let resized_width = ((width as f64 * scale).round() as i64).clamp(1, self.width);
+            let (pad_width, pad_height) =
+                (self.width - resized_width, self.height - resized_height);
+            return image
+                .upsample_bilinear2d(
+                    &[resized_height, resized_width],
+                    false,
+                    None::<f64>,
+                    None::<f64>,
+                )
+                .constant_pad_nd(&[
+                    pad_width / 2,
+                    pad_width - pad_width / 2,
+                    pad_height / 2,
+                    pad_height - pad_height / 2,
+                ]);
+        }
+        let scale = height_scale.max(width_scale);
+        let resized_height = ((height as f64 * scale).round() as i64).max(self.height);
+        let resized_width = ((width as f64 * scale).round() as i64).max(self.width);
+        image
+            .upsample_bilinear2d(
+                &[resized_height, resized_width],
+                false,
+                None::<f64>,
+                None::<f64>,
+            )
+            .narrow(2, (resized_height - self.height) / 2, self.height)
+            .narrow(3, (resized_width - self.width) / 2, self.width)
+    }
+
+    /// Processes a batch of images
+    ///
+    /// # Arguments
+    ///
+    /// * `images` - Slice of decoded images of any size
+    ///
+    /// # Returns
+    ///
+    /// * `Tensor` of shape (*batch size*, 3, *height*, *width*) of the processed image size
+    ///
+    /// # Example
+    ///
+    /// ```no_run
+    /// # use rust_bert::vit::{ImageBuffer, ViTImageProcessor, VIT_IMAGE_MEAN, VIT_IMAGE_STD};
+    /// # use tch::Device;
+    /// # fn main() -> anyhow::Result<()> {
+    /// let image_processor = ViTImageProcessor::new(224, VIT_IMAGE_MEAN, VIT_IMAGE_STD, Device::Cpu);
+    /// let pixels = vec![255u8; 640 * 480 * 3];
+    /// let pixel_values = image_processor.preprocess(&[ImageBuffer::rgb(&pixels, 640, 480)])?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn preprocess(&self, images: &[ImageBuffer]) -> Result<Tensor, RustBertError> {
+        let images = images
+            .iter()
+            .map(|image| Ok(self.resize(image.to_rgb_tensor()?.to(self.device).unsqueeze(0))))
+            .collect::<Result<Vec<Tensor>, RustBertError>>()?;
+        let pixel_values = Tensor::cat(&images, 0).clamp(0.0, 255.0) / 255.0;
+        Ok((pixel_values - &self.mean) / &self.std)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_preprocess() -> anyhow::Result<()> {
+        let image_processor = ViTImageProcessor::new(8, VIT_IMAGE_MEAN, VIT_IMAGE_STD, Device::Cpu);
+
+        // White RGB, black grayscale and red RGBA images of different sizes
+        let white = vec![255u8; 4 * 6 * 3];
+        let black = vec![0u8; 10 * 10];
+        let red = [255u8, 0, 0, 128].repeat(16 * 12);
+        let pixel_values = image_processor.preprocess(&[
+            ImageBuffer::rgb(&white, 4, 6),
+            ImageBuffer {
+                pixels: &black,
+                width: 10,
+                height: 10,
+                channels: 1,
+            },
+            ImageBuffer {
+                pixels: &red,
+                width: 16,
+                height: 12,
+                channels: 4,
+            },
+        ])?;
+
+        assert_eq!(pixel_values.size(), vec![3, 3, 8, 8]);
+        let channel_means =
+            Vec::<f64>::from(pixel_values.mean_dim(&[2, 3], false, Kind::Float).view(-1));
+        let expected = [1.0, 1.0, 1.0, -1.0, -1.0, -1.0, 1.0, -1.0, -1.0];
+        for (value, expected) in channel_means.iter().zip(expected.iter()) {
+            assert!((value - expected).abs() < 1e-5);
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn test_center_crop() -> anyhow::Result<()> {
+        let image_processor = ViTImageProcessor::new(4, VIT_IMAGE_MEAN, VIT_IMAGE_STD, Device::Cpu)
+            .with_center_crop(true);
+
+        // 12x4 image: black left and right thirds, white center
+        let pixels = [[0u8; 4], [255u8; 4], [0u8; 4]].concat().repeat(4);
+        let pixel_values = image_processor.preprocess(&[ImageBuffer {
+            pixels: &pixels,
+            width: 12,
+            height: 4,
+            channels: 1,
+        }])?;
+
+        assert_eq!(pixel_values.size(), vec![1, 3, 4, 4]);
+        assert!((pixel_values.min().double_value(&[]) - 1.0).abs() < 1e-5);
+        Ok(())
+    }
+
+    #[test]
+    fn test_padding() -> anyhow::Result<()> {
+        let image_processor = ViTImageProcessor::new(8, VIT_IMAGE_MEAN, VIT_IMAGE_STD, Device::Cpu)
+            .with_image_size(8, 6)
+            .with_padding(true);
+
+        // White 12x4 image, resized to 6x2 and padded with 3 rows on the top and bottom
+        let pixels = vec![255u8; 12 * 4];
+        let pixel_values = image_processor.preprocess(&[ImageBuffer {
+            pixels: &pixels,
+            width: 12,
+            height: 4,
+            channels: 1,
+        }])?;
+
+        assert_eq!(pixel_values.size(), vec![1, 3, 8, 6]);
+        assert!((pixel_values.narrow(2, 3, 2).min().double_value(&[]) - 1.0).abs() < 1e-5);
+        assert!((pixel_values.narrow(2, 0, 3).max().double_value(&[]) + 1.0).abs() < 1e-5);
+        assert!((pixel_values.narrow(2, 5, 3).max().double_value(&[]) + 1.0).abs() < 1e-5);
+        Ok(())
+    }
+
+    #[test]
+    fn test_invalid_buffer() {
+        let image_processor = ViTImageProcessor::new(8, VIT_IMAGE_MEAN, VIT_IMAGE_STD, Device::Cpu);
+        let pixels = vec![0u8; 10];
+        assert!(image_processor
+            .preprocess(&[ImageBuffer::rgb(&pixels, 2, 2)])
+            .is_err());
+    }
+}
@@ -0,0 +1,62 @@
+//! # Vision Transformer (ViT)
+//!
+//! Implementation of the Vision Transformer image model ([An Image is Worth 16x16 Words: Transformers for Image Recognition at Scale](https://arxiv.org/abs/2010.11929) Dosovitskiy, Beyer, Kolesnikov, Weissenborn, Zhai, Unterthiner, Dehghani, Minderer, Heigold, Gelly, Uszkoreit, Houlsby, 2020).
+//! The base model is implemented in the `vit_model::ViTModel` struct. An image classification head is implemented in `vit_model::ViTForImageClassification`.
+//! The images are split in non-overlapping patches embedded by a strided convolution, a learned class token is prepended to the patch sequence and
+//! learned position embeddings are added. The position embeddings can be interpolated to process images of a size different from the training size.
+//! The `ViTImageProcessor` converts decoded images to the model inputs (resizing and normalization).
+//! A ready-to-use pipeline is available in `pipelines::image_classification`.
+//!
+//! # Model set-up and pre-trained weights loading
+//!
+//! All models expect the following resources:
+//! - Configuration file expected to have a structure following the [Transformers library](https://github.com/huggingface/transformers)
+//! - Model weights are expected to have a structure and parameter names following the [Transformers library](https://github.com/huggingface/transformers). A conversion using the Python utility scripts is required to convert the `.bin` weights to the `.ot` format.
+//!
+//! The configuration of the ViT base checkpoint can be downloaded using RemoteResources. No converted weights are hosted with the crate: they are
+//! converted locally from the PyTorch checkpoint (`python utils/convert_model.py path/to/pytorch_model.bin`).
+//!
+//! ```no_run
+//! # fn main() -> anyhow::Result<()> {
+//! #
+//! use tch::{nn, no_grad, Device};
+//! # use std::path::PathBuf;
+//! use rust_bert::resources::{LocalResource, RemoteResource, ResourceProvider};
+//! use rust_bert::vit::{
+//!     ImageBuffer, ViTConfig, ViTConfigResources, ViTForImageClassification, ViTImageProcessor,
+//!     VIT_IMAGE_MEAN, VIT_IMAGE_STD,
+//! };
+//! use rust_bert::Config;
+//!
+//! let config_resource = RemoteResource::from_pretrained(ViTConfigResources::VIT_BASE_PATCH16_224);
+//! let weights_resource = LocalResource {
+//!     local_path: PathBuf::from("path/to/rust_model.ot"),
+//! };
+//! let config_path = config_resource.get_local_path()?;
+//! let weights_path = weights_resource.get_local_path()?;
+//!
+//! let device = Device::cuda_if_available();
+//! let mut vs = nn::VarStore::new(device);
+//! let config = ViTConfig::from_file(config_path);
+//! let vit_model = ViTForImageClassification::new(&vs.root(), &config);
+//! vs.load(weights_path)?;
+//!
+//! let image_processor =
+//!     ViTImageProcessor::new(config.image_size, VIT_IMAGE_MEAN, VIT_IMAGE_STD, device);
+//! let pixels = vec![127u8; 640 * 480 * 3];
+//! let pixel_values = image_processor.preprocess(&[ImageBuffer::rgb(&pixels, 640, 480)])?;
+//! let output = no_grad(|| vit_model.forward_t(&pixel_values, false, false))?;
+//! # Ok(())
+//! # }
+//! ```
+
+mod attention;
+mod image_processing;
+mod vit_model;
+
+pub use attention::{ViTAttention, ViTSelfAttention};
+pub use image_processing::{ImageBuffer, ViTImageProcessor, VIT_IMAGE_MEAN, VIT_IMAGE_STD};
+pub use vit_model::{
+    ViTConfig, ViTConfigResources, ViTEmbeddings, ViTForImageClassification,
+    ViTImageClassificationOutput, ViTLayer, ViTModel, ViTModelOutput,
+};
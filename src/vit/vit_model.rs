@@ -0,0 +1,528 @@
+// Copyright 2021 Google AI, Ross Wightman, The HuggingFace Inc. team. All rights reserved.
+// Copyright 2022 Guillaume Becquin
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//     http://www.apache.org/licenses/LICENSE-2.0
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use crate::common::activations::{Activation, TensorFunction};
+use crate::common::dropout::Dropout;
+use crate::vit::attention::ViTAttention;
+use crate::{Config, RustBertError};
+use serde::{Deserialize, Serialize};
+use std::borrow::{Borrow, BorrowMut};
+use std::collections::HashMap;
+use tch::nn::{ConvConfig, Init};
+use tch::{nn, Tensor};
+
+/// # ViT Pretrained model config files
+pub struct ViTConfigResources;
+
+impl ViTConfigResources {
+    /// Shared under Apache 2.0 license by the Google team at <https://huggingface.co/google/vit-base-patch16-224>. Modified with conversion to C-array format.
+    pub const VIT_BASE_PATCH16_224: (&'static str, &'static str) = (
+        "vit-base-patch16-224/config",
+        "https://huggingface.co/google/vit-base-patch16-224/resolve/main/config.json",
+    );
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+/// # ViT model configuration
+/// Defines the ViT model architecture (e.g. number of layers, hidden layer size, image and patch sizes...)
+pub struct ViTConfig {
+    pub hidden_size: i64,
+    pub num_hidden_layers: i64,
+    pub num_attention_heads: i64,
+    pub intermediate_size: i64,
+    pub hidden_act: Activation,
+    pub hidden_dropout_prob: f64,
+    pub attention_probs_dropout_prob: f64,
+    pub layer_norm_eps: Option<f64>,
+    /// Height and width of the (square) images seen during training
+    pub image_size: i64,
+    /// Height and width of the (square) patches embedded as tokens
+    pub patch_size: i64,
+    pub num_channels: i64,
+    pub qkv_bias: Option<bool>,
+    pub id2label: Option<HashMap<i64, String>>,
+    pub label2id: Option<HashMap<String, i64>>,
+    pub output_attentions: Option<bool>,
+    pub output_hidden_states: Option<bool>,
+}
+
+impl Config for ViTConfig {}
+
+impl Default for ViTConfig {
+    fn default() -> Self {
+        ViTConfig {
+            hidden_size: 768,
+            num_hidden_layers: 12,
+            num_attention_heads: 12,
+            intermediate_size: 3072,
+            hidden_act: Activation::gelu,
+            hidden_dropout_prob: 0.0,
+            attention_probs_dropout_prob: 0.0,
+            layer_norm_eps: Some(1e-12),
+            image_size: 224,
+            patch_size: 16,
+            num_channels: 3,
+            qkv_bias: Some(true),
+            id2label: None,
+            label2id: None,
+            output_attentions: None,
+            output_hidden_states: None,
+        }
+    }
+}
+
+/// # ViT embeddings
+/// Splits the images in non-overlapping patches projected by a strided convolution, prepends a learned class token
+/// and adds learned position embeddings. The position embeddings are learned for a grid of
+/// `image_size / patch_size` patches and can be interpolated for images of a different size.
+pub struct ViTEmbeddings {
+    cls_token: Tensor,
+    position_embeddings: Tensor,
+    projection: nn::Conv2D,
+    dropout: Dropout,
+    image_size: i64,
+    patch_size: i64,
+    num_channels: i64,
+}
+
+impl ViTEmbeddings {
+    pub fn new<'p, P>(p: P, config: &ViTConfig) -> ViTEmbeddings
+    where
+        P: Borrow<nn::Path<'p>>,
+    {
+        let p = p.borrow();
+
+        let num_patches = (config.image_size / config.patch_size).pow(2);
+        let cls_token = p.var("cls_token", &[1, 1, config.hidden_size], Init::Const(0.0));
+        let position_embeddings = p.var(
+            "position_embeddings",
+            &[1, num_patches + 1, config.hidden_size],
+            Init::Const(0.0),
+        );
+        let projection = nn::conv2d(
+            p / "patch_embeddings" / "projection",
+            config.num_channels,
+            config.hidden_size,
+            config.patch_size,
+            ConvConfig {
+                stride: config.patch_size,
+                ..Default::default()
+            },
+        );
+
+        ViTEmbeddings {
+            cls_token,
+            position_embeddings,
+            projection,
+            dropout: Dropout::new(config.hidden_dropout_prob),
+            image_size: config.image_size,
+            patch_size: config.patch_size,
+            num_channels: config.num_channels,
+        }
+    }
+
+    /// Bicubic interpolation of the patch position embeddings to a grid of `height` x `width` patches
+    fn interpolate_position_embeddings(&self, height: i64, width: i64) -> Tensor {
+        let num_positions = self.position_embeddings.size()[1] - 1;
+        let grid_size = self.image_size / self.patch_size;
+        if height == grid_size && width == grid_size {
+            return self.position_embeddings.shallow_clone();
+        }
+        let hidden_size = self.position_embeddings.size()[2];
+        let class_position_embedding = self.position_embeddings.narrow(1, 0, 1);
+        let patch_position_embeddings = self
+            .position_embeddings
+            .narrow(1, 1, num_positions)
+            .view([1, grid_size, grid_size, hidden_size])
+            .permute(&[0, 3, 1, 2])
+            .upsample_bicubic2d(&[height, width], false, None::<f64>, None::<f64>)
+            .permute(&[0, 2, 3, 1])
+            .reshape(&[1, -1, hidden_size]);
+        Tensor::cat(&[class_position_embedding, patch_position_embeddings], 1)
+    }
+
+    /// Forward pass through the embeddings
+    ///
+    /// # Arguments
+    ///
+    /// * `pixel_values` - Normalized images of shape (*batch size*, *num_channels*, *height*, *width*)
+    /// * `interpolate_pos_encoding` - Interpolate the position embeddings for images of a size different from the
+    /// training `image_size`. If false, the images must have the training size.
+    /// * `train` - boolean flag to turn on/off the dropout layers in the model. Should be set to false for inference.
+    pub fn forward_t(
+        &self,
+        pixel_values: &Tensor,
+        interpolate_pos_encoding: bool,
+        train: bool,
+    ) -> Result<Tensor, RustBertError> {
+        let input_shape = pixel_values.size();
+        if input_shape.len() != 4 || input_shape[1] != self.num_channels {
+            return Err(RustBertError::ValueError(format!(
+                "Expected pixel values of shape (batch size, {}, height, width), got {:?}",
+                self.num_channels, input_shape
+            )));
+        }
+        let (height, width) = (input_shape[2], input_shape[3]);
+        if !interpolate_pos_encoding && (height != self.image_size || width != self.image_size) {
+            return Err(RustBertError::ValueError(format!(
+                "Input image size ({}x{}) does not match the model image size ({}x{}), use interpolate_pos_encoding to embed images of a different size",
+                height, width, self.image_size, self.image_size
+            )));
+        }
+        if height < self.patch_size || width < self.patch_size {
+            return Err(RustBertError::ValueError(format!(
+                "Input image size ({}x{}) is smaller than the patch size ({})",
+                height, width, self.patch_size
+            )));
+        }
+
+        let batch_size = input_shape[0];
+        let patch_embeddings = pixel_values
+            .apply(&self.projection)
+            .flatten(2, -1)
+            .transpose(1, 2);
+        let cls_tokens = self.cls_token.expand(&[batch_size, -1, -1], true);
+        let embeddings = Tensor::cat(&[cls_tokens, patch_embeddings], 1);
+
+        let position_embeddings =
+            self.interpolate_position_embeddings(height / self.patch_size, width / self.patch_size);
+        Ok((embeddings + position_embeddings).apply_t(&self.dropout, train))
+    }
+}
+
+/// # ViT encoder layer
+/// Pre-normalization transformer layer: `x + attention(ln_before(x))` followed by `x + mlp(ln_after(x))`
+pub struct ViTLayer {
+    attention: ViTAttention,
+    layernorm_before: nn::LayerNorm,
+    layernorm_after: nn::LayerNorm,
+    intermediate: nn::Linear,
+    output: nn::Linear,
+    activation: TensorFunction,
+    dropout: Dropout,
+}
+
+impl ViTLayer {
+    pub fn new<'p, P>(p: P, config: &ViTConfig) -> ViTLayer
+    where
+        P: Borrow<nn::Path<'p>>,
+    {
+        let p = p.borrow();
+
+        let layer_norm_config = nn::LayerNormConfig {
+            eps: config.layer_norm_eps.unwrap_or(1e-12),
+            ..Default::default()
+        };
+        let attention = ViTAttention::new(p / "attention", config);
+        let layernorm_before = nn::layer_norm(
+            p / "layernorm_before",
+            vec![config.hidden_size],
+            layer_norm_config,
+        );
+        let layernorm_after = nn::layer_norm(
+            p / "layernorm_after",
+            vec![config.hidden_size],
+            layer_norm_config,
+        );
+        let intermediate = nn::linear(
+            p / "intermediate" / "dense",
+            config.hidden_size,
+            config.intermediate_size,
+            Default::default(),
+        );
+        let output = nn::linear(
+            p / "output" / "dense",
+            config.intermediate_size,
+            config.hidden_size,
+            Default::default(),
+        );
+
+        ViTLayer {
+            attention,
+            layernorm_before,
+            layernorm_after,
+            intermediate,
+            output,
+            activation: config.hidden_act.get_function(),
+            dropout: Dropout::new(config.hidden_dropout_prob),
+        }
+    }
+
+    pub fn forward_t(&self, hidden_states: &Tensor, train: bool) -> (Tensor, Option<Tensor>) {
+        let (attention_output, attention_weights) = self
+            .attention
+            .forward_t(&hidden_states.apply(&self.layernorm_before), train);
+        let hidden_states = attention_output + hidden_states;
+
+        let mlp_output = self.activation.get_fn()(
+            &hidden_states
+                .apply(&self.layernorm_after)
+                .apply(&self.intermediate),
+        )
+        .apply(&self.output)
+        .apply_t(&self.dropout, train);
+        (mlp_output + hidden_states, attention_weights)
+    }
+}
+
+/// # ViT Base model
+/// Base architecture for ViT models. Task-specific models will be built from this common base model
+/// It is made of the following blocks:
+/// - `embeddings`: patch, class token and position embeddings
+/// - `layers`: pre-normalization transformer layers
+/// - `layernorm`: final layer normalization
+pub struct ViTModel {
+    embeddings: ViTEmbeddings,
+    layers: Vec<ViTLayer>,
+    layernorm: nn::LayerNorm,
+    output_attentions: bool,
+    output_hidden_states: bool,
+}
+
+impl ViTModel {
+    /// Build a new `ViTModel`
+    ///
+    /// # Arguments
+    ///
+    /// * `p` - Variable store path for the root of the ViT model
+    /// * `config` - `ViTConfig` object defining the model architecture
+    ///
+    /// # Example
+    ///
+    /// ```no_run
+    /// use rust_bert::vit::{ViTConfig, ViTModel};
+    /// use rust_bert::Config;
+    /// use std::path::Path;
+    /// use tch::{nn, Device};
+    ///
+    /// let config_path = Path::new("path/to/config.json");
+    /// let device = Device::Cpu;
+    /// let p = nn::VarStore::new(device);
+    /// let config = ViTConfig::from_file(config_path);
+    /// let vit_model = ViTModel::new(&p.root() / "vit", &config);
+    /// ```
+    pub fn new<'p, P>(p: P, config: &ViTConfig) -> ViTModel
+    where
+        P: Borrow<nn::Path<'p>>,
+    {
+        let p = p.borrow();
+
+        let embeddings = ViTEmbeddings::new(p / "embeddings", config);
+        let p_layers = p / "encoder" / "layer";
+        let layers = (0..config.num_hidden_layers)
+            .map(|layer_index| ViTLayer::new(&p_layers / layer_index, config))
+            .collect::<Vec<ViTLayer>>();
+        let layernorm = nn::layer_norm(
+            p / "layernorm",
+            vec![config.hidden_size],
+            nn::LayerNormConfig {
+                eps: config.layer_norm_eps.unwrap_or(1e-12),
+                ..Default::default()
+            },
+        );
+
+        ViTModel {
+            embeddings,
+            layers,
+            layernorm,
+            output_attentions: config.output_attentions.unwrap_or(false),
+            output_hidden_states: config.output_hidden_states.unwrap_or(false),
+        }
+    }
+
+    /// Forward pass through the model
+    ///
+    /// # Arguments
+    ///
+    /// * `pixel_values` - Normalized images of shape (*batch size*, *num_channels*, *height*, *width*)
+    /// * `interpolate_pos_encoding` - Interpolate the position embeddings for images of a size different from the
+    /// training `image_size`
+    /// * `train` - boolean flag to turn on/off the dropout layers in the model. Should be set to false for inference.
+    ///
+    /// # Returns
+    ///
+    /// * `ViTModelOutput` containing:
+    ///   - `hidden_state` - `Tensor` of shape (*batch size*, *num_patches + 1*, *hidden_size*), the class token first
+    ///   - `all_hidden_states` - `Option<Vec<Tensor>>` of length *num_hidden_layers + 1* with shape (*batch size*, *num_patches + 1*, *hidden_size*)
+    ///   - `all_attentions` - `Option<Vec<Tensor>>` of length *num_hidden_layers* with shape (*batch size*, *num_heads*, *num_patches + 1*, *num_patches + 1*)
+    ///
+    /// # Example
+    ///
+    /// ```no_run
+    /// # use rust_bert::vit::{ViTConfig, ViTModel};
+    /// # use rust_bert::Config;
+    /// # use std::path::Path;
+    /// # use tch::{nn, no_grad, Device, Kind, Tensor};
+    /// # fn main() -> anyhow::Result<()> {
+    /// # let config_path = Path::new("path/to/config.json");
+    /// # let device = Device::Cpu;
+    /// # let vs = nn::VarStore::new(device);
+    /// # let config = ViTConfig::from_file(config_path);
+    /// # let vit_model = ViTModel::new(&vs.root(), &config);
+    /// let pixel_values = Tensor::rand(&[2, 3, 224, 224], (Kind::Float, device));
+    /// let model_output = no_grad(|| vit_model.forward_t(&pixel_values, false, false))?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn forward_t(
+        &self,
+        pixel_values: &Tensor,
+        interpolate_pos_encoding: bool,
+        train: bool,
+    ) -> Result<ViTModelOutput, RustBertError> {
+        let mut hidden_state =
+            self.embeddings
+                .forward_t(pixel_values, interpolate_pos_encoding, train)?;
+
+        let mut all_hidden_states: Option<Vec<Tensor>> = if self.output_hidden_states {
+            Some(vec![])
+        } else {
+            None
+        };
+        let mut all_attentions: Option<Vec<Tensor>> = if self.output_attentions {
+            Some(vec![])
+        } else {
+            None
+        };
+
+        for layer in &self.layers {
+            if let Some(hidden_states) = all_hidden_states.borrow_mut() {
+                hidden_states.push(hidden_state.copy());
+            };
+            let (output, attention_weights) = layer.forward_t(&hidden_state, train);
+            hidden_state = output;
+            if let Some(attentions) = all_attentions.borrow_mut() {
+                attentions.push(attention_weights.unwrap());
+            };
+        }
+        if let Some(hidden_states) = all_hidden_states.borrow_mut() {
+            hidden_states.push(hidden_state.copy());
+        };
+
+        Ok(ViTModelOutput {
+            hidden_state: hidden_state.apply(&self.layernorm),
+            all_hidden_states,
+            all_attentions,
+        })
+    }
+}
+
+/// # ViT for image classification
+/// ViT model with a linear classification head on the final hidden state of the class token.
+/// It is made of the following blocks:
+/// - `vit`: Base ViTModel
+/// - `classifier`: linear layer mapping the class token to the labels
+pub struct ViTForImageClassification {
+    vit: ViTModel,
+    classifier: nn::Linear,
+}
+
+impl ViTForImageClassification {
+    /// Build a new `ViTForImageClassification`
+    ///
+    /// # Arguments
+    ///
+    /// * `p` - Variable store path for the root of the ViT model
+    /// * `config` - `ViTConfig` object defining the model architecture and the labels (`id2label`)
+    ///
+    /// # Example
+    ///
+    /// ```no_run
+    /// use rust_bert::vit::{ViTConfig, ViTForImageClassification};
+    /// use rust_bert::Config;
+    /// use std::path::Path;
+    /// use tch::{nn, Device};
+    ///
+    /// let config_path = Path::new("path/to/config.json");
+    /// let device = Device::Cpu;
+    /// let p = nn::VarStore::new(device);
+    /// let config = ViTConfig::from_file(config_path);
+    /// let vit_model = ViTForImageClassification::new(&p.root(), &config);
+    /// ```
+    pub fn new<'p, P>(p: P, config: &ViTConfig) -> ViTForImageClassification
+    where
+        P: Borrow<nn::Path<'p>>,
+    {
+        let p = p.borrow();
+
+        let vit = ViTModel::new(p / "vit", config);
+        let num_labels = config
+            .id2label
+            .as_ref()
+            .expect("num_labels not provided in configuration")
+            .len() as i64;
+        let classifier = nn::linear(
+            p / "classifier",
+            config.hidden_size,
+            num_labels,
+            Default::default(),
+        );
+
+        ViTForImageClassification { vit, classifier }
+    }
+
+    /// Forward pass through the model
+    ///
+    /// # Arguments
+    ///
+    /// * `pixel_values` - Normalized images of shape (*batch size*, *num_channels*, *height*, *width*)
+    /// * `interpolate_pos_encoding` - Interpolate the position embeddings for images of a size different from the
+    /// training `image_size`
+    /// * `train` - boolean flag to turn on/off the dropout layers in the model. Should be set to false for inference.
+    ///
+    /// # Returns
+    ///
+    /// * `ViTImageClassificationOutput` containing:
+    ///   - `logits` - `Tensor` of shape (*batch size*, *num_labels*)
+    ///   - `all_hidden_states` - `Option<Vec<Tensor>>` of length *num_hidden_layers + 1* with shape (*batch size*, *num_patches + 1*, *hidden_size*)
+    ///   - `all_attentions` - `Option<Vec<Tensor>>` of length *num_hidden_layers* with shape (*batch size*, *num_heads*, *num_patches + 1*, *num_patches + 1*)
+    pub fn forward_t(
+        &self,
+        pixel_values: &Tensor,
+        interpolate_pos_encoding: bool,
+        train: bool,
+    ) -> Result<ViTImageClassificationOutput, RustBertError> {
+        let base_model_output =
+            self.vit
+                .forward_t(pixel_values, interpolate_pos_encoding, train)?;
+        let logits = base_model_output
+            .hidden_state
+            .select(1, 0)
+            .apply(&self.classifier);
+
+        Ok(ViTImageClassificationOutput {
+            logits,
+            all_hidden_states: base_model_output.all_hidden_states,
+            all_attentions: base_model_output.all_attentions,
+        })
+    }
+}
+
+/// Container for the ViT model output.
+pub struct ViTModelOutput {
+    /// Last hidden states from the model, after the final layer normalization
+    pub hidden_state: Tensor,
+    /// Hidden states for all intermediate layers
+    pub all_hidden_states: Option<Vec<Tensor>>,
+    /// Attention weights for all intermediate layers
+    pub all_attentions: Option<Vec<Tensor>>,
+}
+
+/// Container for the ViT image classification model output.
+pub struct ViTImageClassificationOutput {
+    /// Logits for each label
+    pub logits: Tensor,
+    /// Hidden states for all intermediate layers
+    pub all_hidden_states: Option<Vec<Tensor>>,
+    /// Attention weights for all intermediate layers
+    pub all_attentions: Option<Vec<Tensor>>,
+}
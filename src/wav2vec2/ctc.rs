@@ -0,0 +1,381 @@
+// Copyright 2022 Guillaume Becquin
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//     http://www.apache.org/licenses/LICENSE-2.0
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use crate::RustBertError;
+use std::cmp::Ordering;
+use std::collections::HashMap;
+use std::fs;
+use std::path::Path;
+use tch::{Kind, Tensor};
+
+/// Token used as CTC blank (and padding) by the Wav2Vec2 vocabularies
+pub const WAV2VEC2_BLANK_TOKEN: &str = "<pad>";
+/// Token separating the words in the Wav2Vec2 vocabularies
+pub const WAV2VEC2_WORD_DELIMITER_TOKEN: &str = "|";
+/// Special tokens of the Wav2Vec2 vocabularies that are never part of a transcription
+const SKIPPED_TOKENS: [&str; 4] = ["<s>", "</s>", "<pad>", "<unk>"];
+
+/// Returns the frame-level log-probabilities of each sequence, truncated to its number of valid frames
+fn get_log_probabilities(logits: &Tensor, lengths: Option<&[i64]>) -> Vec<Vec<Vec<f32>>> {
+    Vec::<Vec<Vec<f32>>>::from(&logits.log_softmax(-1, Kind::Float))
+        .into_iter()
+        .enumerate()
+        .map(|(index, mut frames)| {
+            if let Some(length) = lengths.and_then(|lengths| lengths.get(index)) {
+                frames.truncate(*length as usize);
+            }
+            frames
+        })
+        .collect()
+}
+
+/// Greedy CTC decoding: selects the most likely token for every frame, merges the consecutive repetitions and
+/// removes the blank tokens.
+///
+/// # Arguments
+///
+/// * `logits` - `Tensor` of shape (*batch size*, *num frames*, *vocab size*)
+/// * `blank_id` - Id of the CTC blank token
+/// * `lengths` - Optional number of valid frames of each sequence, the following frames are ignored
+///
+/// # Returns
+///
+/// * `Vec<Vec<i64>>` token ids of each sequence
+pub fn ctc_greedy_decode(logits: &Tensor, blank_id: i64, lengths: Option<&[i64]>) -> Vec<Vec<i64>> {
+    let predictions = Vec::<Vec<i64>>::from(&logits.argmax(-1, false));
+    predictions
+        .into_iter()
+        .enumerate()
+        .map(|(index, mut frames)| {
+            if let Some(length) = lengths.and_then(|lengths| lengths.get(index)) {
+                frames.truncate(*length as usize);
+            }
+            let mut tokens = Vec::new();
+            let mut previous = None;
+            for token in frames {
+                if (Some(token) != previous) & (token != blank_id) {
+                    tokens.push(token);
+                }
+                previous = Some(token);
+            }
+            tokens
+        })
+        .collect()
+}
+
+fn log_add_exp(a: f32, b: f32) -> f32 {
+    if a == f32::NEG_INFINITY {
+        return b;
+    }
+    if b == f32::NEG_INFINITY {
+        return a;
+    }
+    let max = a.max(b);
+    max + ((a - max).exp() + (b - max).exp()).ln()
+}
+
+/// Log-probabilities of a prefix ending with a blank token and ending with a non-blank token
+#[derive(Clone, Copy)]
+struct PrefixScore {
+    blank: f32,
+    non_blank: f32,
+}
+
+impl PrefixScore {
+    const EMPTY: PrefixScore = PrefixScore {
+        blank: f32::NEG_INFINITY,
+        non_blank: f32::NEG_INFINITY,
+    };
+
+    fn total(&self) -> f32 {
+        log_add_exp(self.blank, self.non_blank)
+    }
+}
+
+fn prefix_beam_search(frames: &[Vec<f32>], blank_id: i64, beam_size: usize) -> Vec<i64> {
+    let mut beams: Vec<(Vec<i64>, PrefixScore)> = vec![(
+        Vec::new(),
+        PrefixScore {
+            blank: 0.0,
+            non_blank: f32::NEG_INFINITY,
+        },
+    )];
+    for frame in frames {
+        let mut candidates: HashMap<Vec<i64>, PrefixScore> = HashMap::new();
+        for (prefix, score) in beams.iter() {
+            for (token, log_probability) in frame.iter().enumerate() {
+                let token = token as i64;
+                if token == blank_id {
+                    let entry = candidates
+                        .entry(prefix.clone())
+                        .or_insert(PrefixScore::EMPTY);
+                    entry.blank = log_add_exp(entry.blank, score.total() + log_probability);
+                    continue;
+                }
+                let mut extended_prefix = prefix.clone();
+                extended_prefix.push(token);
+                if prefix.last() == Some(&token) {
+                    // a repeated token is only a new token if separated by a blank, otherwise it is merged
+                    let entry = candidates
+                        .entry(extended_prefix)
+                        .or_insert(PrefixScore::EMPTY);
+                    entry.non_blank = log_add_exp(entry.non_blank, score.blank + log_probability);
+                    let entry = candidates
+                        .entry(prefix.clone())
+                        .or_insert(PrefixScore::EMPTY);
+                    entry.non_blank =
+                        log_add_exp(entry.non_blank, score.non_blank + log_probability);
+                } else {
+                    let entry = candidates
+                        .entry(extended_prefix)
+                        .or_insert(PrefixScore::EMPTY);
+                    entry.non_blank = log_add_exp(entry.non_blank, score.total() + log_probability);
+                }
+            }
+        }
+        beams = candidates.into_iter().collect();
+        beams.sort_by(|(prefix_a, score_a), (prefix_b, score_b)| {
+            score_b
+                .total()
+                .partial_cmp(&score_a.total())
+                .unwrap_or(Ordering::Equal)
+                .then_with(|| prefix_a.cmp(prefix_b))
+        });
+        beams.truncate(beam_size);
+    }
+    beams.into_iter().next().map(|(prefix, _)| prefix).unwrap()
+}
+
+/// Prefix beam search CTC decoding: keeps the `beam_size` most likely label sequences (prefixes) at every frame,
+/// summing the probabilities of all the frame alignments leading to the same prefix.
+///
+/// # Arguments
+///
+/// * `logits` - `Tensor` of shape (*batch size*, *num frames*, *vocab size*)
+/// * `blank_id` - Id of the CTC blank token
+/// * `beam_size` - Number of prefixes kept at every frame
+/// * `lengths` - Optional number of valid frames of each sequence, the following frames are ignored
+///
+/// # Returns
+///
+/// * `Vec<Vec<i64>>` token ids of each sequence
+pub fn ctc_beam_search_decode(
+    logits: &Tensor,
+    blank_id: i64,
+    beam_size: usize,
+    lengths: Option<&[i64]>,
+) -> Vec<Vec<i64>> {
+    get_log_probabilities(logits, lengths)
+        .iter()
+        .map(|frames| prefix_beam_search(frames, blank_id, beam_size.max(1)))
+        .collect()
+}
+
+/// # Wav2Vec2 CTC decoder
+/// Converts the predictions of a `Wav2Vec2ForCTC` model to text using the character vocabulary of the
+/// checkpoint (`vocab.json`). The word delimiter token is converted to a space and the special tokens are skipped.
+pub struct Wav2Vec2CtcDecoder {
+    id_to_token: HashMap<i64, String>,
+    blank_id: i64,
+}
+
+impl Wav2Vec2CtcDecoder {
+    /// Creates a new `Wav2Vec2CtcDecoder` from a vocabulary mapping the tokens to their ids. The vocabulary should
+    /// contain the `<pad>` blank token and the `|` word delimiter token.
+    ///
+    /// # Arguments
+    ///
+    /// * `vocab` - Mapping from tokens to ids
+    ///
+    /// # Example
+    ///
+    /// ```no_run
+    /// use rust_bert::wav2vec2::Wav2Vec2CtcDecoder;
+    /// use std::collections::HashMap;
+    /// # fn main() -> anyhow::Result<()> {
+    /// let vocab: HashMap<String, i64> = [("<pad>", 0), ("|", 1), ("A", 2)]
+    ///     .iter()
+    ///     .map(|(token, id)| (token.to_string(), *id))
+    ///     .collect();
+    /// let decoder = Wav2Vec2CtcDecoder::new(vocab)?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn new(vocab: HashMap<String, i64>) -> Result<Wav2Vec2CtcDecoder, RustBertError> {
+        for token in &[WAV2VEC2_BLANK_TOKEN, WAV2VEC2_WORD_DELIMITER_TOKEN] {
+            if !vocab.contains_key(*token) {
+                return Err(RustBertError::TokenizerError(format!(
+                    "The vocabulary does not contain the {} token",
+                    token
+                )));
+            }
+        }
+        let blank_id = vocab[WAV2VEC2_BLANK_TOKEN];
+        let id_to_token = vocab.into_iter().map(|(token, id)| (id, token)).collect();
+        Ok(Wav2Vec2CtcDecoder {
+            id_to_token,
+            blank_id,
+        })
+    }
+
+    /// Creates a new `Wav2Vec2CtcDecoder` from the `vocab.json` vocabulary file of a checkpoint
+    ///
+    /// # Arguments
+    ///
+    /// * `path` - Path to the vocabulary file
+    ///
+    /// # Example
+    ///
+    /// ```no_run
+    /// use rust_bert::wav2vec2::Wav2Vec2CtcDecoder;
+    /// # fn main() -> anyhow::Result<()> {
+    /// let decoder = Wav2Vec2CtcDecoder::from_file("path/to/vocab.json")?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn from_file<P: AsRef<Path>>(path: P) -> Result<Wav2Vec2CtcDecoder, RustBertError> {
+        let path = path.as_ref();
+        let vocab: HashMap<String, i64> = serde_json::from_str(&fs::read_to_string(path)?)
+            .map_err(|error| {
+                RustBertError::TokenizerError(format!(
+                    "Invalid vocabulary file {:?}: {}",
+                    path, error
+                ))
+            })?;
+        Wav2Vec2CtcDecoder::new(vocab)
+    }
+
+    /// Returns the id of the CTC blank token
+    pub fn blank_id(&self) -> i64 {
+        self.blank_id
+    }
+
+    /// Converts a sequence of token ids (after CTC merging of repetitions and removal of blanks) to text
+    pub fn decode(&self, token_ids: &[i64]) -> String {
+        let text = token_ids
+            .iter()
+            .filter_map(|token_id| self.id_to_token.get(token_id))
+            .filter(|token| !SKIPPED_TOKENS.contains(&token.as_str()))
+            .map(|token| {
+                if token == WAV2VEC2_WORD_DELIMITER_TOKEN {
+                    " "
+                } else {
+                    token.as_str()
+                }
+            })
+            .collect::<String>();
+        text.split_whitespace().collect::<Vec<&str>>().join(" ")
+    }
+
+    /// Transcribes the output of a `Wav2Vec2ForCTC` model with greedy CTC decoding
+    ///
+    /// # Arguments
+    ///
+    /// * `logits` - `Tensor` of shape (*batch size*, *num frames*, *vocab size*)
+    /// * `lengths` - Optional number of valid frames of each sequence, e.g. from the frame attention mask
+    ///
+    /// # Returns
+    ///
+    /// * `Vec<String>` transcription of each sequence
+    pub fn decode_greedy(&self, logits: &Tensor, lengths: Option<&[i64]>) -> Vec<String> {
+        ctc_greedy_decode(logits, self.blank_id, lengths)
+            .iter()
+            .map(|token_ids| self.decode(token_ids))
+            .collect()
+    }
+
+    /// Transcribes the output of a `Wav2Vec2ForCTC` model with prefix beam search CTC decoding
+    ///
+    /// # Arguments
+    ///
+    /// * `logits` - `Tensor` of shape (*batch size*, *num frames*, *vocab size*)
+    /// * `beam_size` - Number of prefixes kept at every frame
+    /// * `lengths` - Optional number of valid frames of each sequence, e.g. from the frame attention mask
+    ///
+    /// # Returns
+    ///
+    /// * `Vec<String>` transcription of each sequence
+    pub fn decode_beam_search(
+        &self,
+        logits: &Tensor,
+        beam_size: usize,
+        lengths: Option<&[i64]>,
+    ) -> Vec<String> {
+        ctc_beam_search_decode(logits, self.blank_id, beam_size, lengths)
+            .iter()
+            .map(|token_ids| self.decode(token_ids))
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn get_decoder() -> Wav2Vec2CtcDecoder {
+        let vocab = [("<pad>", 0), ("<s>", 1), ("|", 2), ("A", 3), ("B", 4)]
+            .iter()
+            .map(|(token, id)| (token.to_string(), *id))
+            .collect();
+        Wav2Vec2CtcDecoder::new(vocab).unwrap()
+    }
+
+    fn one_hot_logits(frames: &[i64], vocab_size: i64) -> Tensor {
+        Tensor::of_slice(frames)
+            .one_hot(vocab_size)
+            .to_kind(Kind::Float)
+            * 10.0
+    }
+
+    #[test]
+    fn greedy_decoding() {
+        let decoder = get_decoder();
+        //    "AA" requires a blank between the two tokens, repetitions are merged otherwise
+        let logits = Tensor::stack(
+            &[
+                one_hot_logits(&[3, 3, 0, 3, 2, 2, 4, 4, 0, 1, 2], 5),
+                one_hot_logits(&[4, 0, 0, 4, 4, 2, 3, 3, 3, 3, 3], 5),
+            ],
+            0,
+        );
+        assert_eq!(
+            ctc_greedy_decode(&logits, 0, None),
+            vec![vec![3, 3, 2, 4, 1, 2], vec![4, 4, 2, 3]]
+        );
+        assert_eq!(
+            decoder.decode_greedy(&logits, Some(&[11, 5][..])),
+            vec!["AA B".to_string(), "BB".to_string()]
+        );
+    }
+
+    #[test]
+    fn beam_search_decoding() {
+        //    The most likely path is blank-blank (0.36), but the paths "A-", "-A" and "AA" all collapse to "A"
+        //    for a total probability of 0.64
+        let probabilities = Tensor::of_slice(&[0.6f32, 0.4, 0.6, 0.4]).view([1, 2, 2]);
+        let logits = probabilities.log();
+        assert_eq!(ctc_greedy_decode(&logits, 0, None), vec![Vec::<i64>::new()]);
+        assert_eq!(ctc_beam_search_decode(&logits, 0, 4, None), vec![vec![1]]);
+
+        let decoder = get_decoder();
+        let logits = one_hot_logits(&[3, 0, 3, 2, 4], 5).unsqueeze(0);
+        assert_eq!(decoder.decode_beam_search(&logits, 3, None), vec!["AA B"]);
+    }
+
+    #[test]
+    fn invalid_vocabulary() {
+        let vocab = [("<pad>", 0), ("A", 1)]
+            .iter()
+            .map(|(token, id)| (token.to_string(), *id))
+            .collect();
+        assert!(Wav2Vec2CtcDecoder::new(vocab).is_err());
+    }
+}
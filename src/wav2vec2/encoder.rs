@@ -0,0 +1,487 @@
+// Copyright 2021 The Fairseq Authors and the HuggingFace Inc. team. All rights reserved.
+// Copyright 2022 Guillaume Becquin
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//     http://www.apache.org/licenses/LICENSE-2.0
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use crate::bart::{BartAttention, BartEncoderOutput};
+use crate::common::activations::TensorFunction;
+use crate::common::dropout::Dropout;
+use crate::wav2vec2::wav2vec2_model::FeatureExtractorNorm;
+use crate::wav2vec2::Wav2Vec2Config;
+use std::borrow::{Borrow, BorrowMut};
+use tch::nn::{ConvConfig, Init};
+use tch::{nn, Tensor};
+
+enum ConvLayerNorm {
+    None,
+    Group { weight: Tensor, bias: Tensor },
+    Layer(nn::LayerNorm),
+}
+
+/// # Convolution layer of the Wav2Vec2 feature encoder
+/// 1D convolution followed by an optional group or layer normalization and the activation
+pub struct Wav2Vec2ConvLayer {
+    conv: nn::Conv1D,
+    norm: ConvLayerNorm,
+    activation: TensorFunction,
+}
+
+impl Wav2Vec2ConvLayer {
+    pub fn new<'p, P>(p: P, config: &Wav2Vec2Config, layer_index: usize) -> Wav2Vec2ConvLayer
+    where
+        P: Borrow<nn::Path<'p>>,
+    {
+        let p = p.borrow();
+        let input_dim = if layer_index > 0 {
+            config.conv_dim[layer_index - 1]
+        } else {
+            1
+        };
+        let output_dim = config.conv_dim[layer_index];
+        let conv = nn::conv1d(
+            p / "conv",
+            input_dim,
+            output_dim,
+            config.conv_kernel[layer_index],
+            ConvConfig {
+                stride: config.conv_stride[layer_index],
+                bias: config.conv_bias,
+                ..Default::default()
+            },
+        );
+        let norm = match config.feat_extract_norm {
+            FeatureExtractorNorm::Group if layer_index == 0 => {
+                let p_norm = p / "layer_norm";
+                ConvLayerNorm::Group {
+                    weight: p_norm.var("weight", &[output_dim], Init::Const(1.0)),
+                    bias: p_norm.var("bias", &[output_dim], Init::Const(0.0)),
+                }
+            }
+            FeatureExtractorNorm::Group => ConvLayerNorm::None,
+            FeatureExtractorNorm::Layer => ConvLayerNorm::Layer(nn::layer_norm(
+                p / "layer_norm",
+                vec![output_dim],
+                Default::default(),
+            )),
+        };
+        Wav2Vec2ConvLayer {
+            conv,
+            norm,
+            activation: config.feat_extract_activation.get_function(),
+        }
+    }
+
+    pub fn forward(&self, x: &Tensor) -> Tensor {
+        let x = x.apply(&self.conv);
+        let x = match &self.norm {
+            ConvLayerNorm::None => x,
+            // One group per channel: each channel is normalized over time
+            ConvLayerNorm::Group { weight, bias } => {
+                x.group_norm(x.size()[1], Some(weight), Some(bias), 1e-5, true)
+            }
+            ConvLayerNorm::Layer(layer_norm) => {
+                x.transpose(-2, -1).apply(layer_norm).transpose(-2, -1)
+            }
+        };
+        self.activation.get_fn()(&x)
+    }
+}
+
+/// # Wav2Vec2 feature encoder
+/// Stack of convolution layers turning the raw waveform into latent speech representations, one frame every
+/// 20ms (320 samples at 16kHz) for the default architecture
+pub struct Wav2Vec2FeatureEncoder {
+    conv_layers: Vec<Wav2Vec2ConvLayer>,
+    kernels_and_strides: Vec<(i64, i64)>,
+}
+
+impl Wav2Vec2FeatureEncoder {
+    pub fn new<'p, P>(p: P, config: &Wav2Vec2Config) -> Wav2Vec2FeatureEncoder
+    where
+        P: Borrow<nn::Path<'p>>,
+    {
+        let p_layers = p.borrow() / "conv_layers";
+        let conv_layers = (0..config.conv_dim.len())
+            .map(|layer_index| {
+                Wav2Vec2ConvLayer::new(&p_layers / layer_index as i64, config, layer_index)
+            })
+            .collect();
+        let kernels_and_strides = config
+            .conv_kernel
+            .iter()
+            .copied()
+            .zip(config.conv_stride.iter().copied())
+            .collect();
+        Wav2Vec2FeatureEncoder {
+            conv_layers,
+            kernels_and_strides,
+        }
+    }
+
+    /// Returns the number of frames produced for a waveform of `input_length` samples (0 if the waveform is
+    /// shorter than the receptive field of the convolutions)
+    pub fn get_output_length(&self, input_length: i64) -> i64 {
+        self.kernels_and_strides
+            .iter()
+            .fold(input_length, |length, (kernel, stride)| {
+                if length < *kernel {
+                    0
+                } else {
+                    (length - kernel) / stride + 1
+                }
+            })
+    }
+
+    /// Forward pass through the feature encoder
+    ///
+    /// # Arguments
+    ///
+    /// * `input_values` - Waveforms of shape (*batch size*, *num samples*)
+    ///
+    /// # Returns
+    ///
+    /// * `Tensor` of shape (*batch size*, *conv_dim*, *num frames*)
+    pub fn forward(&self, input_values: &Tensor) -> Tensor {
+        let mut hidden_states = input_values.unsqueeze(1);
+        for conv_layer in &self.conv_layers {
+            hidden_states = conv_layer.forward(&hidden_states);
+        }
+        hidden_states
+    }
+}
+
+/// # Wav2Vec2 feature projection
+/// Normalizes the latent speech representations and projects them to the hidden size of the transformer
+pub struct Wav2Vec2FeatureProjection {
+    layer_norm: nn::LayerNorm,
+    projection: nn::Linear,
+    dropout: Dropout,
+}
+
+impl Wav2Vec2FeatureProjection {
+    pub fn new<'p, P>(p: P, config: &Wav2Vec2Config) -> Wav2Vec2FeatureProjection
+    where
+        P: Borrow<nn::Path<'p>>,
+    {
+        let p = p.borrow();
+        let feature_dim = *config.conv_dim.last().unwrap();
+        let layer_norm = nn::layer_norm(
+            p / "layer_norm",
+            vec![feature_dim],
+            nn::LayerNormConfig {
+                eps: config.layer_norm_eps,
+                ..Default::default()
+            },
+        );
+        let projection = nn::linear(
+            p / "projection",
+            feature_dim,
+            config.hidden_size,
+            Default::default(),
+        );
+        Wav2Vec2FeatureProjection {
+            layer_norm,
+            projection,
+            dropout: Dropout::new(config.feat_proj_dropout),
+        }
+    }
+
+    pub fn forward_t(&self, features: &Tensor, train: bool) -> Tensor {
+        features
+            .apply(&self.layer_norm)
+            .apply(&self.projection)
+            .apply_t(&self.dropout, train)
+    }
+}
+
+/// # Wav2Vec2 convolutional position embeddings
+/// Grouped convolution over time (with weight normalization) added to the projected features
+pub struct Wav2Vec2PositionalConvEmbedding {
+    weight_g: Tensor,
+    weight_v: Tensor,
+    bias: Tensor,
+    kernel_size: i64,
+    groups: i64,
+    activation: TensorFunction,
+}
+
+impl Wav2Vec2PositionalConvEmbedding {
+    pub fn new<'p, P>(p: P, config: &Wav2Vec2Config) -> Wav2Vec2PositionalConvEmbedding
+    where
+        P: Borrow<nn::Path<'p>>,
+    {
+        let p = p.borrow() / "conv";
+        let kernel_size = config.num_conv_pos_embeddings;
+        let groups = config.num_conv_pos_embedding_groups;
+        let weight_g = p.var("weight_g", &[1, 1, kernel_size], Init::Const(1.0));
+        let weight_v = p.var(
+            "weight_v",
+            &[config.hidden_size, config.hidden_size / groups, kernel_size],
+            Init::KaimingUniform,
+        );
+        let bias = p.var("bias", &[config.hidden_size], Init::Const(0.0));
+        Wav2Vec2PositionalConvEmbedding {
+            weight_g,
+            weight_v,
+            bias,
+            kernel_size,
+            groups,
+            activation: config.feat_extract_activation.get_function(),
+        }
+    }
+
+    pub fn forward(&self, hidden_states: &Tensor) -> Tensor {
+        // Weight normalization over all dimensions but the kernel positions
+        let weight =
+            &self.weight_v * (&self.weight_g / Tensor::norm_except_dim(&self.weight_v, 2, 2));
+        let mut position_embeddings = hidden_states.transpose(1, 2).conv1d(
+            &weight,
+            Some(&self.bias),
+            &[1],
+            &[self.kernel_size / 2],
+            &[1],
+            self.groups,
+        );
+        if self.kernel_size % 2 == 0 {
+            // Even kernels produce one extra frame
+            let num_frames = position_embeddings.size()[2];
+            position_embeddings = position_embeddings.slice(2, 0, num_frames - 1, 1);
+        }
+        self.activation.get_fn()(&position_embeddings).transpose(1, 2)
+    }
+}
+
+pub struct Wav2Vec2FeedForward {
+    intermediate_dense: nn::Linear,
+    output_dense: nn::Linear,
+    activation: TensorFunction,
+    intermediate_dropout: Dropout,
+    output_dropout: Dropout,
+}
+
+impl Wav2Vec2FeedForward {
+    pub fn new<'p, P>(p: P, config: &Wav2Vec2Config) -> Wav2Vec2FeedForward
+    where
+        P: Borrow<nn::Path<'p>>,
+    {
+        let p = p.borrow();
+        let intermediate_dense = nn::linear(
+            p / "intermediate_dense",
+            config.hidden_size,
+            config.intermediate_size,
+            Default::default(),
+        );
+        let output_dense = nn::linear(
+            p / "output_dense",
+            config.intermediate_size,
+            config.hidden_size,
+            Default::default(),
+        );
+        Wav2Vec2FeedForward {
+            intermediate_dense,
+            output_dense,
+            activation: config.hidden_act.get_function(),
+            intermediate_dropout: Dropout::new(config.activation_dropout),
+            output_dropout: Dropout::new(config.hidden_dropout),
+        }
+    }
+
+    pub fn forward_t(&self, hidden_states: &Tensor, train: bool) -> Tensor {
+        self.activation.get_fn()(&hidden_states.apply(&self.intermediate_dense))
+            .apply_t(&self.intermediate_dropout, train)
+            .apply(&self.output_dense)
+            .apply_t(&self.output_dropout, train)
+    }
+}
+
+/// # Wav2Vec2 transformer layer
+/// Post-normalization layer, or pre-normalization layer for the architectures with `do_stable_layer_norm`
+pub struct Wav2Vec2EncoderLayer {
+    attention: BartAttention,
+    dropout: Dropout,
+    layer_norm: nn::LayerNorm,
+    feed_forward: Wav2Vec2FeedForward,
+    final_layer_norm: nn::LayerNorm,
+    pre_layer_norm: bool,
+}
+
+impl Wav2Vec2EncoderLayer {
+    pub fn new<'p, P>(p: P, config: &Wav2Vec2Config) -> Wav2Vec2EncoderLayer
+    where
+        P: Borrow<nn::Path<'p>>,
+    {
+        let p = p.borrow();
+        let layer_norm_config = nn::LayerNormConfig {
+            eps: config.layer_norm_eps,
+            ..Default::default()
+        };
+        let attention = BartAttention::new(
+            p / "attention",
+            config.hidden_size,
+            config.num_attention_heads,
+            config.attention_dropout,
+            false,
+            false,
+            config.output_attentions.unwrap_or(false),
+        );
+        let layer_norm = nn::layer_norm(
+            p / "layer_norm",
+            vec![config.hidden_size],
+            layer_norm_config,
+        );
+        let feed_forward = Wav2Vec2FeedForward::new(p / "feed_forward", config);
+        let final_layer_norm = nn::layer_norm(
+            p / "final_layer_norm",
+            vec![config.hidden_size],
+            layer_norm_config,
+        );
+        Wav2Vec2EncoderLayer {
+            attention,
+            dropout: Dropout::new(config.hidden_dropout),
+            layer_norm,
+            feed_forward,
+            final_layer_norm,
+            pre_layer_norm: config.do_stable_layer_norm,
+        }
+    }
+
+    pub fn forward_t(
+        &self,
+        hidden_states: &Tensor,
+        attention_mask: Option<&Tensor>,
+        train: bool,
+    ) -> (Tensor, Option<Tensor>) {
+        if self.pre_layer_norm {
+            let (attention_output, attention_weights, _) = self.attention.forward_t(
+                &hidden_states.apply(&self.layer_norm),
+                None,
+                attention_mask,
+                None,
+                train,
+            );
+            let hidden_states = hidden_states + attention_output.apply_t(&self.dropout, train);
+            let output = &hidden_states
+                + self
+                    .feed_forward
+                    .forward_t(&hidden_states.apply(&self.final_layer_norm), train);
+            (output, attention_weights)
+        } else {
+            let (attention_output, attention_weights, _) =
+                self.attention
+                    .forward_t(hidden_states, None, attention_mask, None, train);
+            let hidden_states = (hidden_states + attention_output.apply_t(&self.dropout, train))
+                .apply(&self.layer_norm);
+            let output = (&hidden_states + self.feed_forward.forward_t(&hidden_states, train))
+                .apply(&self.final_layer_norm);
+            (output, attention_weights)
+        }
+    }
+}
+
+/// # Wav2Vec2 transformer encoder
+/// Adds the convolutional position embeddings to the projected features and contextualizes them with a stack of
+/// transformer layers
+pub struct Wav2Vec2Encoder {
+    pos_conv_embed: Wav2Vec2PositionalConvEmbedding,
+    layer_norm: nn::LayerNorm,
+    dropout: Dropout,
+    layers: Vec<Wav2Vec2EncoderLayer>,
+    pre_layer_norm: bool,
+    output_attentions: bool,
+    output_hidden_states: bool,
+}
+
+impl Wav2Vec2Encoder {
+    pub fn new<'p, P>(p: P, config: &Wav2Vec2Config) -> Wav2Vec2Encoder
+    where
+        P: Borrow<nn::Path<'p>>,
+    {
+        let p = p.borrow();
+        let pos_conv_embed = Wav2Vec2PositionalConvEmbedding::new(p / "pos_conv_embed", config);
+        let layer_norm = nn::layer_norm(
+            p / "layer_norm",
+            vec![config.hidden_size],
+            nn::LayerNormConfig {
+                eps: config.layer_norm_eps,
+                ..Default::default()
+            },
+        );
+        let p_layers = p / "layers";
+        let layers = (0..config.num_hidden_layers)
+            .map(|layer_index| Wav2Vec2EncoderLayer::new(&p_layers / layer_index, config))
+            .collect();
+        Wav2Vec2Encoder {
+            pos_conv_embed,
+            layer_norm,
+            dropout: Dropout::new(config.hidden_dropout),
+            layers,
+            pre_layer_norm: config.do_stable_layer_norm,
+            output_attentions: config.output_attentions.unwrap_or(false),
+            output_hidden_states: config.output_hidden_states.unwrap_or(false),
+        }
+    }
+
+    /// Forward pass through the encoder
+    ///
+    /// # Arguments
+    ///
+    /// * `hidden_states` - Projected features of shape (*batch size*, *num frames*, *hidden_size*)
+    /// * `attention_mask` - Optional additive attention mask of shape (*batch size*, 1, *num frames*, *num frames*)
+    /// * `train` - boolean flag to turn on/off the dropout layers in the model. Should be set to false for inference.
+    pub fn forward_t(
+        &self,
+        hidden_states: &Tensor,
+        attention_mask: Option<&Tensor>,
+        train: bool,
+    ) -> Wav2Vec2EncoderOutput {
+        let mut hidden_state = hidden_states + self.pos_conv_embed.forward(hidden_states);
+        if !self.pre_layer_norm {
+            hidden_state = hidden_state.apply(&self.layer_norm);
+        }
+        hidden_state = hidden_state.apply_t(&self.dropout, train);
+
+        let mut all_hidden_states: Option<Vec<Tensor>> = if self.output_hidden_states {
+            Some(vec![])
+        } else {
+            None
+        };
+        let mut all_attentions: Option<Vec<Tensor>> = if self.output_attentions {
+            Some(vec![])
+        } else {
+            None
+        };
+
+        for layer in &self.layers {
+            if let Some(hidden_states) = all_hidden_states.borrow_mut() {
+                hidden_states.push(hidden_state.copy());
+            };
+            let (output, attention_weights) = layer.forward_t(&hidden_state, attention_mask, train);
+            hidden_state = output;
+            if let Some(attentions) = all_attentions.borrow_mut() {
+                attentions.push(attention_weights.unwrap());
+            };
+        }
+        if self.pre_layer_norm {
+            hidden_state = hidden_state.apply(&self.layer_norm);
+        }
+        if let Some(hidden_states) = all_hidden_states.borrow_mut() {
+            hidden_states.push(hidden_state.copy());
+        };
+
+        Wav2Vec2EncoderOutput {
+            hidden_state,
+            all_hidden_states,
+            all_attentions,
+        }
+    }
+}
+
+/// Container holding a Wav2Vec2 encoder output
+pub type Wav2Vec2EncoderOutput = BartEncoderOutput;
@@ -0,0 +1,164 @@
+// Copyright 2021 The HuggingFace Inc. team. All rights reserved.
+// Copyright 2022 Guillaume Becquin
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//     http://www.apache.org/licenses/LICENSE-2.0
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use crate::RustBertError;
+use tch::{Device, Tensor};
+
+/// Sampling rate (in Hz) expected by the Wav2Vec2 models
+pub const WAV2VEC2_SAMPLING_RATE: usize = 16000;
+
+/// # Wav2Vec2 feature extractor
+/// Converts a batch of 16kHz mono PCM waveforms to the input values expected by the Wav2Vec2 models. Each waveform
+/// is normalized to zero mean and unit variance (ignoring the padding), and the batch is padded with zeros to
+/// the length of the longest waveform.
+pub struct Wav2Vec2FeatureExtractor {
+    do_normalize: bool,
+    device: Device,
+}
+
+impl Wav2Vec2FeatureExtractor {
+    /// Creates a new `Wav2Vec2FeatureExtractor`
+    ///
+    /// # Arguments
+    ///
+    /// * `do_normalize` - Flag indicating if the waveforms should be normalized to zero mean and unit variance (true for most checkpoints)
+    /// * `device` - Device on which the input values are created
+    ///
+    /// # Example
+    ///
+    /// ```no_run
+    /// use rust_bert::wav2vec2::Wav2Vec2FeatureExtractor;
+    /// use tch::Device;
+    ///
+    /// let feature_extractor = Wav2Vec2FeatureExtractor::new(true, Device::Cpu);
+    /// ```
+    pub fn new(do_normalize: bool, device: Device) -> Wav2Vec2FeatureExtractor {
+        Wav2Vec2FeatureExtractor {
+            do_normalize,
+            device,
+        }
+    }
+
+    /// Computes the input values of a batch of waveforms
+    ///
+    /// # Arguments
+    ///
+    /// * `audio` - Slice of 16kHz mono waveforms with samples in [-1, 1]
+    ///
+    /// # Returns
+    ///
+    /// * `Tensor` input values of shape (*batch size*, *max num samples*)
+    /// * `Tensor` attention mask of shape (*batch size*, *max num samples*), with 0 for the padding samples. Models
+    /// using `FeatureExtractorNorm::Group` were trained without attention mask and should not receive it: the zero
+    /// padding is then processed as silence.
+    ///
+    /// # Example
+    ///
+    /// ```no_run
+    /// # use rust_bert::wav2vec2::Wav2Vec2FeatureExtractor;
+    /// # use tch::Device;
+    /// # fn main() -> anyhow::Result<()> {
+    /// let feature_extractor = Wav2Vec2FeatureExtractor::new(true, Device::Cpu);
+    /// let audio = vec![0f32; 16000];
+    /// let (input_values, attention_mask) = feature_extractor.extract(&[audio.as_slice()])?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn extract(&self, audio: &[&[f32]]) -> Result<(Tensor, Tensor), RustBertError> {
+        if audio.is_empty() {
+            return Err(RustBertError::ValueError(
+                "At least one waveform is required".to_string(),
+            ));
+        }
+        let max_length = audio.iter().map(|samples| samples.len()).max().unwrap();
+        let mut input_values = Vec::with_capacity(audio.len() * max_length);
+        let mut attention_mask = Vec::with_capacity(audio.len() * max_length);
+        for samples in audio {
+            if samples.is_empty() {
+                return Err(RustBertError::ValueError(
+                    "Cannot extract features from an empty waveform".to_string(),
+                ));
+            }
+            if self.do_normalize {
+                input_values.extend(normalize(samples));
+            } else {
+                input_values.extend_from_slice(samples);
+            }
+            input_values.resize(input_values.len() + max_length - samples.len(), 0f32);
+            attention_mask.resize(attention_mask.len() + samples.len(), 1i64);
+            attention_mask.resize(attention_mask.len() + max_length - samples.len(), 0i64);
+        }
+        let shape = [audio.len() as i64, max_length as i64];
+        let input_values = Tensor::of_slice(&input_values).view(shape).to(self.device);
+        let attention_mask = Tensor::of_slice(&attention_mask)
+            .view(shape)
+            .to(self.device);
+        Ok((input_values, attention_mask))
+    }
+}
+
+/// Normalizes a waveform to zero mean and unit variance
+fn normalize(samples: &[f32]) -> Vec<f32> {
+    let num_samples = samples.len() as f64;
+    let mean = samples.iter().map(|sample| *sample as f64).sum::<f64>() / num_samples;
+    let variance = samples
+        .iter()
+        .map(|sample| (*sample as f64 - mean).powi(2))
+        .sum::<f64>()
+        / num_samples;
+    let std = (variance + 1e-7).sqrt();
+    samples
+        .iter()
+        .map(|sample| ((*sample as f64 - mean) / std) as f32)
+        .collect()
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use tch::Kind;
+
+    #[test]
+    fn normalized_padded_batch() -> anyhow::Result<()> {
+        let feature_extractor = Wav2Vec2FeatureExtractor::new(true, Device::Cpu);
+        let sine: Vec<f32> = (0..1000)
+            .map(|index| {
+                (2.0 * std::f32::consts::PI * 440.0 * index as f32 / WAV2VEC2_SAMPLING_RATE as f32)
+                    .sin()
+                    * 0.3
+                    + 0.1
+            })
+            .collect();
+        let short = vec![0.5f32, -0.5, 0.5, -0.5];
+        let (input_values, attention_mask) =
+            feature_extractor.extract(&[sine.as_slice(), short.as_slice()])?;
+        assert_eq!(input_values.size(), vec![2, 1000]);
+        assert_eq!(attention_mask.size(), vec![2, 1000]);
+        assert_eq!(
+            Vec::<i64>::from(attention_mask.sum_dim_intlist(&[1], false, Kind::Int64)),
+            vec![1000, 4]
+        );
+
+        // each waveform is normalized independently of the padding
+        let sine_values = input_values.get(0);
+        assert!(f64::from(sine_values.mean(Kind::Float)).abs() < 1e-4);
+        assert!((f64::from(sine_values.std(false)) - 1.0).abs() < 1e-3);
+        let short_values = Vec::<f32>::from(input_values.get(1).narrow(0, 0, 6));
+        assert!((short_values[0] - 1.0).abs() < 1e-4);
+        assert!((short_values[1] + 1.0).abs() < 1e-4);
+        assert_eq!(short_values[4..], [0.0, 0.0]);
+
+        let empty: Vec<f32> = Vec::new();
+        assert!(feature_extractor.extract(&[empty.as_slice()]).is_err());
+        Ok(())
+    }
+}
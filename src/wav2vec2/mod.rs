@@ -0,0 +1,70 @@
+//! # Wav2Vec2 (Baevski et al.)
+//!
+//! Implementation of the Wav2Vec2 speech model ([wav2vec 2.0: A Framework for Self-Supervised Learning of Speech Representations](https://arxiv.org/abs/2006.11477) Baevski, Zhou, Mohamed, Auli, 2020).
+//! The base model is implemented in the `wav2vec2_model::Wav2Vec2Model` struct. The model also includes a connectionist temporal classification (CTC) head for speech recognition: `wav2vec2_model::Wav2Vec2ForCTC`.
+//! The model takes raw 16kHz waveforms normalized by the `Wav2Vec2FeatureExtractor` and predicts a character distribution for every 20ms frame.
+//! These predictions are converted to text by the `Wav2Vec2CtcDecoder`, using greedy or prefix beam search CTC decoding.
+//!
+//! # Model set-up and pre-trained weights loading
+//!
+//! All models expect the following resources:
+//! - Configuration file expected to have a structure following the [Transformers library](https://github.com/huggingface/transformers)
+//! - Model weights are expected to have a structure and parameter names following the [Transformers library](https://github.com/huggingface/transformers). A conversion using the Python utility scripts is required to convert the `.bin` weights to the `.ot` format.
+//! - `Wav2Vec2CtcDecoder` using the `vocab.json` character vocabulary of the checkpoint
+//!
+//! No pre-trained checkpoint is hosted with the crate, the resources are provided as local files:
+//!
+//! ```no_run
+//! # fn main() -> anyhow::Result<()> {
+//! #
+//! use tch::{nn, no_grad, Device};
+//! # use std::path::PathBuf;
+//! use rust_bert::resources::{LocalResource, ResourceProvider};
+//! use rust_bert::wav2vec2::{
+//!     Wav2Vec2Config, Wav2Vec2CtcDecoder, Wav2Vec2FeatureExtractor, Wav2Vec2ForCTC,
+//! };
+//! use rust_bert::Config;
+//!
+//! let config_resource = LocalResource {
+//!     local_path: PathBuf::from("path/to/config.json"),
+//! };
+//! let vocab_resource = LocalResource {
+//!     local_path: PathBuf::from("path/to/vocab.json"),
+//! };
+//! let weights_resource = LocalResource {
+//!     local_path: PathBuf::from("path/to/model.ot"),
+//! };
+//! let config_path = config_resource.get_local_path()?;
+//! let vocab_path = vocab_resource.get_local_path()?;
+//! let weights_path = weights_resource.get_local_path()?;
+//!
+//! let device = Device::cuda_if_available();
+//! let mut vs = nn::VarStore::new(device);
+//! let config = Wav2Vec2Config::from_file(config_path);
+//! let wav2vec2_model = Wav2Vec2ForCTC::new(&vs.root(), &config);
+//! vs.load(weights_path)?;
+//!
+//! let feature_extractor = Wav2Vec2FeatureExtractor::new(true, device);
+//! let decoder = Wav2Vec2CtcDecoder::from_file(vocab_path)?;
+//! let audio = vec![0f32; 16000];
+//! let (input_values, _) = feature_extractor.extract(&[audio.as_slice()])?;
+//! let output = no_grad(|| wav2vec2_model.forward_t(&input_values, None, false))?;
+//! let transcriptions = decoder.decode_beam_search(&output.logits, 8, None);
+//! # Ok(())
+//! # }
+//! ```
+
+mod ctc;
+mod encoder;
+mod feature_extraction;
+mod wav2vec2_model;
+
+pub use ctc::{
+    ctc_beam_search_decode, ctc_greedy_decode, Wav2Vec2CtcDecoder, WAV2VEC2_BLANK_TOKEN,
+    WAV2VEC2_WORD_DELIMITER_TOKEN,
+};
+pub use feature_extraction::{Wav2Vec2FeatureExtractor, WAV2VEC2_SAMPLING_RATE};
+pub use wav2vec2_model::{
+    FeatureExtractorNorm, Wav2Vec2Config, Wav2Vec2CtcOutput, Wav2Vec2ForCTC, Wav2Vec2Model,
+    Wav2Vec2ModelOutput,
+};
@@ -0,0 +1,389 @@
+// Copyright 2021 The Fairseq Authors and the HuggingFace Inc. team. All rights reserved.
+// Copyright 2022 Guillaume Becquin
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//     http://www.apache.org/licenses/LICENSE-2.0
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use crate::bart::_expand_mask;
+use crate::common::activations::Activation;
+use crate::common::dropout::Dropout;
+use crate::wav2vec2::encoder::{Wav2Vec2Encoder, Wav2Vec2FeatureEncoder, Wav2Vec2FeatureProjection};
+use crate::{Config, RustBertError};
+use serde::{Deserialize, Serialize};
+use std::borrow::Borrow;
+use tch::{nn, Kind, Tensor};
+
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+/// # Normalization of the convolution layers of the Wav2Vec2 feature encoder
+pub enum FeatureExtractorNorm {
+    /// Group normalization of the first convolution layer only (e.g. `wav2vec2-base-960h`). These models are
+    /// trained without attention mask: batches should be padded with zeros and the attention mask omitted.
+    Group,
+    /// Layer normalization of all convolution layers (e.g. `wav2vec2-large-960h-lv60-self`)
+    Layer,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+/// # Wav2Vec2 model configuration
+/// Defines the Wav2Vec2 model architecture (e.g. convolution layers of the feature encoder, number of transformer
+/// layers, hidden layer size, CTC vocabulary size...)
+pub struct Wav2Vec2Config {
+    pub vocab_size: i64,
+    pub hidden_size: i64,
+    pub num_hidden_layers: i64,
+    pub num_attention_heads: i64,
+    pub intermediate_size: i64,
+    pub hidden_act: Activation,
+    pub hidden_dropout: f64,
+    pub activation_dropout: f64,
+    pub attention_dropout: f64,
+    pub feat_proj_dropout: f64,
+    pub final_dropout: f64,
+    pub layer_norm_eps: f64,
+    pub feat_extract_norm: FeatureExtractorNorm,
+    pub feat_extract_activation: Activation,
+    /// Number of channels of each convolution layer of the feature encoder
+    pub conv_dim: Vec<i64>,
+    /// Stride of each convolution layer of the feature encoder
+    pub conv_stride: Vec<i64>,
+    /// Kernel size of each convolution layer of the feature encoder
+    pub conv_kernel: Vec<i64>,
+    pub conv_bias: bool,
+    /// Kernel size of the convolutional position embeddings
+    pub num_conv_pos_embeddings: i64,
+    pub num_conv_pos_embedding_groups: i64,
+    /// Pre-normalization of the transformer layers (large checkpoints) instead of post-normalization
+    pub do_stable_layer_norm: bool,
+    /// Id of the padding token, used as the CTC blank token
+    pub pad_token_id: Option<i64>,
+    pub bos_token_id: Option<i64>,
+    pub eos_token_id: Option<i64>,
+    pub output_attentions: Option<bool>,
+    pub output_hidden_states: Option<bool>,
+}
+
+impl Config for Wav2Vec2Config {}
+
+impl Default for Wav2Vec2Config {
+    fn default() -> Self {
+        Wav2Vec2Config {
+            vocab_size: 32,
+            hidden_size: 768,
+            num_hidden_layers: 12,
+            num_attention_heads: 12,
+            intermediate_size: 3072,
+            hidden_act: Activation::gelu,
+            hidden_dropout: 0.1,
+            activation_dropout: 0.1,
+            attention_dropout: 0.1,
+            feat_proj_dropout: 0.0,
+            final_dropout: 0.1,
+            layer_norm_eps: 1e-5,
+            feat_extract_norm: FeatureExtractorNorm::Group,
+            feat_extract_activation: Activation::gelu,
+            conv_dim: vec![512; 7],
+            conv_stride: vec![5, 2, 2, 2, 2, 2, 2],
+            conv_kernel: vec![10, 3, 3, 3, 3, 2, 2],
+            conv_bias: false,
+            num_conv_pos_embeddings: 128,
+            num_conv_pos_embedding_groups: 16,
+            do_stable_layer_norm: false,
+            pad_token_id: Some(0),
+            bos_token_id: Some(1),
+            eos_token_id: Some(2),
+            output_attentions: None,
+            output_hidden_states: None,
+        }
+    }
+}
+
+/// # Wav2Vec2 Base model
+/// Base architecture for Wav2Vec2 model. Usually complemented with a task-specific head, such as a CTC head.
+/// It is made of the following blocks:
+/// - `feature_extractor`: `Wav2Vec2FeatureEncoder` convolution layers embedding the raw waveform
+/// - `feature_projection`: `Wav2Vec2FeatureProjection` normalization and projection of the features
+/// - `encoder`: `Wav2Vec2Encoder` convolutional position embeddings and transformer layers
+pub struct Wav2Vec2Model {
+    feature_extractor: Wav2Vec2FeatureEncoder,
+    feature_projection: Wav2Vec2FeatureProjection,
+    encoder: Wav2Vec2Encoder,
+}
+
+impl Wav2Vec2Model {
+    /// Build a new `Wav2Vec2Model`
+    ///
+    /// # Arguments
+    ///
+    /// * `p` - Variable store path for the root of the Wav2Vec2 model
+    /// * `config` - `Wav2Vec2Config` object defining the model architecture
+    ///
+    /// # Example
+    ///
+    /// ```no_run
+    /// use rust_bert::wav2vec2::{Wav2Vec2Config, Wav2Vec2Model};
+    /// use rust_bert::Config;
+    /// use std::path::Path;
+    /// use tch::{nn, Device};
+    ///
+    /// let config_path = Path::new("path/to/config.json");
+    /// let device = Device::Cpu;
+    /// let p = nn::VarStore::new(device);
+    /// let config = Wav2Vec2Config::from_file(config_path);
+    /// let wav2vec2: Wav2Vec2Model = Wav2Vec2Model::new(&p.root() / "wav2vec2", &config);
+    /// ```
+    pub fn new<'p, P>(p: P, config: &Wav2Vec2Config) -> Wav2Vec2Model
+    where
+        P: Borrow<nn::Path<'p>>,
+    {
+        let p = p.borrow();
+        let feature_extractor = Wav2Vec2FeatureEncoder::new(p / "feature_extractor", config);
+        let feature_projection = Wav2Vec2FeatureProjection::new(p / "feature_projection", config);
+        let encoder = Wav2Vec2Encoder::new(p / "encoder", config);
+        Wav2Vec2Model {
+            feature_extractor,
+            feature_projection,
+            encoder,
+        }
+    }
+
+    /// Returns the number of frames produced by the model for a waveform of `num_samples` samples
+    pub fn get_num_frames(&self, num_samples: i64) -> i64 {
+        self.feature_extractor.get_output_length(num_samples)
+    }
+
+    /// Forward pass through the model
+    ///
+    /// # Arguments
+    ///
+    /// * `input_values` - Normalized waveforms of shape (*batch size*, *num samples*), see `Wav2Vec2FeatureExtractor`
+    /// * `attention_mask` - Optional mask of shape (*batch size*, *num samples*), with 0 for the padding samples
+    /// * `train` - boolean flag to turn on/off the dropout layers in the model. Should be set to false for inference.
+    ///
+    /// # Returns
+    ///
+    /// * `Wav2Vec2ModelOutput` containing:
+    ///   - `hidden_state` - `Tensor` of shape (*batch size*, *num frames*, *hidden_size*)
+    ///   - `frame_attention_mask` - `Option<Tensor>` of shape (*batch size*, *num frames*) masking the frames computed from padding samples
+    ///   - `all_hidden_states` - `Option<Vec<Tensor>>` of length *num_hidden_layers + 1* with shape (*batch size*, *num frames*, *hidden_size*)
+    ///   - `all_attentions` - `Option<Vec<Tensor>>` of length *num_hidden_layers* with shape (*batch size*, *num heads*, *num frames*, *num frames*)
+    ///
+    /// # Example
+    ///
+    /// ```no_run
+    /// # use rust_bert::wav2vec2::{Wav2Vec2Config, Wav2Vec2Model};
+    /// # use rust_bert::Config;
+    /// # use std::path::Path;
+    /// # use tch::{nn, no_grad, Device, Kind, Tensor};
+    /// # fn main() -> anyhow::Result<()> {
+    /// # let config_path = Path::new("path/to/config.json");
+    /// # let device = Device::Cpu;
+    /// # let vs = nn::VarStore::new(device);
+    /// # let config = Wav2Vec2Config::from_file(config_path);
+    /// # let wav2vec2_model = Wav2Vec2Model::new(&vs.root(), &config);
+    /// let input_values = Tensor::rand(&[2, 16000], (Kind::Float, device));
+    ///
+    /// let model_output = no_grad(|| wav2vec2_model.forward_t(&input_values, None, false))?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn forward_t(
+        &self,
+        input_values: &Tensor,
+        attention_mask: Option<&Tensor>,
+        train: bool,
+    ) -> Result<Wav2Vec2ModelOutput, RustBertError> {
+        if input_values.dim() != 2 {
+            return Err(RustBertError::ValueError(format!(
+                "Expected input values of shape (batch size, num samples), got {:?}",
+                input_values.size()
+            )));
+        }
+        let num_samples = input_values.size()[1];
+        let num_frames = self.get_num_frames(num_samples);
+        if num_frames == 0 {
+            return Err(RustBertError::ValueError(format!(
+                "The audio is too short ({} samples) to produce any frame",
+                num_samples
+            )));
+        }
+
+        let features = self.feature_extractor.forward(input_values).transpose(1, 2);
+        let mut hidden_states = self.feature_projection.forward_t(&features, train);
+
+        let frame_attention_mask = attention_mask.map(|mask| {
+            let num_frames_per_input =
+                Vec::<i64>::from(&mask.sum_dim_intlist(&[1], false, Kind::Int64))
+                    .into_iter()
+                    .map(|length| self.get_num_frames(length))
+                    .collect::<Vec<i64>>();
+            Tensor::arange(num_frames, (Kind::Int64, hidden_states.device()))
+                .unsqueeze(0)
+                .lt_tensor(
+                    &Tensor::of_slice(&num_frames_per_input)
+                        .to(hidden_states.device())
+                        .unsqueeze(1),
+                )
+        });
+        let extended_attention_mask = frame_attention_mask.as_ref().map(|mask| {
+            hidden_states = hidden_states.masked_fill(&mask.logical_not().unsqueeze(-1), 0.0);
+            _expand_mask(mask, None, hidden_states.kind())
+        });
+
+        let encoder_output =
+            self.encoder
+                .forward_t(&hidden_states, extended_attention_mask.as_ref(), train);
+
+        Ok(Wav2Vec2ModelOutput {
+            hidden_state: encoder_output.hidden_state,
+            frame_attention_mask,
+            all_hidden_states: encoder_output.all_hidden_states,
+            all_attentions: encoder_output.all_attentions,
+        })
+    }
+}
+
+/// # Wav2Vec2 model for connectionist temporal classification (CTC)
+/// Wav2Vec2 model with a linear head predicting a distribution over the CTC vocabulary (characters and blank
+/// token) for each frame. The predictions are turned into text with `Wav2Vec2CtcDecoder`.
+/// It is made of the following blocks:
+/// - `wav2vec2`: Base Wav2Vec2Model
+/// - `lm_head`: Linear layer projecting the hidden states to the vocabulary
+pub struct Wav2Vec2ForCTC {
+    wav2vec2: Wav2Vec2Model,
+    dropout: Dropout,
+    lm_head: nn::Linear,
+}
+
+impl Wav2Vec2ForCTC {
+    /// Build a new `Wav2Vec2ForCTC`
+    ///
+    /// # Arguments
+    ///
+    /// * `p` - Variable store path for the root of the Wav2Vec2 model
+    /// * `config` - `Wav2Vec2Config` object defining the model architecture
+    ///
+    /// # Example
+    ///
+    /// ```no_run
+    /// use rust_bert::wav2vec2::{Wav2Vec2Config, Wav2Vec2ForCTC};
+    /// use rust_bert::Config;
+    /// use std::path::Path;
+    /// use tch::{nn, Device};
+    ///
+    /// let config_path = Path::new("path/to/config.json");
+    /// let device = Device::Cpu;
+    /// let p = nn::VarStore::new(device);
+    /// let config = Wav2Vec2Config::from_file(config_path);
+    /// let wav2vec2: Wav2Vec2ForCTC = Wav2Vec2ForCTC::new(&p.root(), &config);
+    /// ```
+    pub fn new<'p, P>(p: P, config: &Wav2Vec2Config) -> Wav2Vec2ForCTC
+    where
+        P: Borrow<nn::Path<'p>>,
+    {
+        let p = p.borrow();
+        let wav2vec2 = Wav2Vec2Model::new(p / "wav2vec2", config);
+        let lm_head = nn::linear(
+            p / "lm_head",
+            config.hidden_size,
+            config.vocab_size,
+            Default::default(),
+        );
+        Wav2Vec2ForCTC {
+            wav2vec2,
+            dropout: Dropout::new(config.final_dropout),
+            lm_head,
+        }
+    }
+
+    /// Returns the number of frames produced by the model for a waveform of `num_samples` samples
+    pub fn get_num_frames(&self, num_samples: i64) -> i64 {
+        self.wav2vec2.get_num_frames(num_samples)
+    }
+
+    /// Forward pass through the model
+    ///
+    /// # Arguments
+    ///
+    /// * `input_values` - Normalized waveforms of shape (*batch size*, *num samples*), see `Wav2Vec2FeatureExtractor`
+    /// * `attention_mask` - Optional mask of shape (*batch size*, *num samples*), with 0 for the padding samples
+    /// * `train` - boolean flag to turn on/off the dropout layers in the model. Should be set to false for inference.
+    ///
+    /// # Returns
+    ///
+    /// * `Wav2Vec2CtcOutput` containing:
+    ///   - `logits` - `Tensor` of shape (*batch size*, *num frames*, *vocab_size*)
+    ///   - `frame_attention_mask` - `Option<Tensor>` of shape (*batch size*, *num frames*) masking the frames computed from padding samples
+    ///   - `all_hidden_states` - `Option<Vec<Tensor>>` of length *num_hidden_layers + 1* with shape (*batch size*, *num frames*, *hidden_size*)
+    ///   - `all_attentions` - `Option<Vec<Tensor>>` of length *num_hidden_layers* with shape (*batch size*, *num heads*, *num frames*, *num frames*)
+    ///
+    /// # Example
+    ///
+    /// ```no_run
+    /// # use rust_bert::wav2vec2::{Wav2Vec2Config, Wav2Vec2ForCTC};
+    /// # use rust_bert::Config;
+    /// # use std::path::Path;
+    /// # use tch::{nn, no_grad, Device, Kind, Tensor};
+    /// # fn main() -> anyhow::Result<()> {
+    /// # let config_path = Path::new("path/to/config.json");
+    /// # let device = Device::Cpu;
+    /// # let vs = nn::VarStore::new(device);
+    /// # let config = Wav2Vec2Config::from_file(config_path);
+    /// # let wav2vec2_model = Wav2Vec2ForCTC::new(&vs.root(), &config);
+    /// let input_values = Tensor::rand(&[2, 16000], (Kind::Float, device));
+    ///
+    /// let model_output = no_grad(|| wav2vec2_model.forward_t(&input_values, None, false))?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn forward_t(
+        &self,
+        input_values: &Tensor,
+        attention_mask: Option<&Tensor>,
+        train: bool,
+    ) -> Result<Wav2Vec2CtcOutput, RustBertError> {
+        let base_model_output = self
+            .wav2vec2
+            .forward_t(input_values, attention_mask, train)?;
+        let logits = base_model_output
+            .hidden_state
+            .apply_t(&self.dropout, train)
+            .apply(&self.lm_head);
+        Ok(Wav2Vec2CtcOutput {
+            logits,
+            frame_attention_mask: base_model_output.frame_attention_mask,
+            all_hidden_states: base_model_output.all_hidden_states,
+            all_attentions: base_model_output.all_attentions,
+        })
+    }
+}
+
+/// Container for the Wav2Vec2 model output.
+pub struct Wav2Vec2ModelOutput {
+    /// Last hidden states from the model
+    pub hidden_state: Tensor,
+    /// Mask of the frames computed from audio samples (true) rather than padding, if an attention mask was provided
+    pub frame_attention_mask: Option<Tensor>,
+    /// Hidden states for all intermediate layers
+    pub all_hidden_states: Option<Vec<Tensor>>,
+    /// Attention weights for all intermediate layers
+    pub all_attentions: Option<Vec<Tensor>>,
+}
+
+/// Container for the Wav2Vec2 CTC model output.
+pub struct Wav2Vec2CtcOutput {
+    /// Logits over the CTC vocabulary for each frame
+    pub logits: Tensor,
+    /// Mask of the frames computed from audio samples (true) rather than padding, if an attention mask was provided
+    pub frame_attention_mask: Option<Tensor>,
+    /// Hidden states for all intermediate layers
+    pub all_hidden_states: Option<Vec<Tensor>>,
+    /// Attention weights for all intermediate layers
+    pub all_attentions: Option<Vec<Tensor>>,
+}
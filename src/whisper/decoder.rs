@@ -0,0 +1,331 @@
+// Copyright 2022 The OpenAI Authors and The HuggingFace Inc. team. All rights reserved.
+// Copyright 2022 Guillaume Becquin
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//     http://www.apache.org/licenses/LICENSE-2.0
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use crate::bart::{BartDecoderOutput, _make_causal_mask};
+use crate::common::activations::{Activation, TensorFunction};
+use crate::common::dropout::Dropout;
+use crate::whisper::attention::{LayerState, WhisperAttention};
+use crate::whisper::WhisperConfig;
+use crate::RustBertError;
+use std::borrow::{Borrow, BorrowMut};
+use tch::nn::EmbeddingConfig;
+use tch::{nn, Tensor};
+
+pub struct WhisperDecoderLayer {
+    self_attention: WhisperAttention,
+    encoder_attention: WhisperAttention,
+    self_attention_layer_norm: nn::LayerNorm,
+    encoder_attention_layer_norm: nn::LayerNorm,
+    dropout: Dropout,
+    activation_dropout: Dropout,
+    activation: TensorFunction,
+    fc1: nn::Linear,
+    fc2: nn::Linear,
+    final_layer_norm: nn::LayerNorm,
+}
+
+impl WhisperDecoderLayer {
+    pub fn new<'p, P>(p: P, config: &WhisperConfig) -> WhisperDecoderLayer
+    where
+        P: Borrow<nn::Path<'p>>,
+    {
+        let p = p.borrow();
+
+        let layer_norm_config = nn::LayerNormConfig {
+            eps: 1e-5,
+            ..Default::default()
+        };
+        let output_attention = config.output_attentions.unwrap_or(false);
+        let self_attention = WhisperAttention::new(
+            p / "self_attn",
+            config.d_model,
+            config.decoder_attention_heads,
+            config.attention_dropout,
+            false,
+            true,
+            output_attention,
+        );
+        let encoder_attention = WhisperAttention::new(
+            p / "encoder_attn",
+            config.d_model,
+            config.decoder_attention_heads,
+            config.attention_dropout,
+            true,
+            true,
+            output_attention,
+        );
+        let self_attention_layer_norm = nn::layer_norm(
+            p / "self_attn_layer_norm",
+            vec![config.d_model],
+            layer_norm_config,
+        );
+        let encoder_attention_layer_norm = nn::layer_norm(
+            p / "encoder_attn_layer_norm",
+            vec![config.d_model],
+            layer_norm_config,
+        );
+
+        let dropout = Dropout::new(config.dropout);
+        let activation_dropout = Dropout::new(config.activation_dropout);
+        let activation = config
+            .activation_function
+            .unwrap_or(Activation::gelu)
+            .get_function();
+        let fc1 = nn::linear(
+            p / "fc1",
+            config.d_model,
+            config.decoder_ffn_dim,
+            Default::default(),
+        );
+        let fc2 = nn::linear(
+            p / "fc2",
+            config.decoder_ffn_dim,
+            config.d_model,
+            Default::default(),
+        );
+
+        let final_layer_norm = nn::layer_norm(
+            p / "final_layer_norm",
+            vec![config.d_model],
+            layer_norm_config,
+        );
+
+        WhisperDecoderLayer {
+            self_attention,
+            encoder_attention,
+            self_attention_layer_norm,
+            encoder_attention_layer_norm,
+            dropout,
+            activation_dropout,
+            activation,
+            fc1,
+            fc2,
+            final_layer_norm,
+        }
+    }
+
+    pub fn forward_t(
+        &self,
+        x: &Tensor,
+        encoder_hidden_states: &Tensor,
+        decoder_attention_mask: Option<&Tensor>,
+        layer_states: (Option<LayerState>, Option<LayerState>),
+        train: bool,
+    ) -> (
+        Tensor,
+        Option<Tensor>,
+        Option<Tensor>,
+        (Option<LayerState>, Option<LayerState>),
+    ) {
+        let output = x.apply(&self.self_attention_layer_norm);
+
+        let (output, attention_weights, new_self_layer_states) = self.self_attention.forward_t(
+            &output,
+            None,
+            decoder_attention_mask,
+            layer_states.0,
+            train,
+        );
+        let output: Tensor = output.apply_t(&self.dropout, train) + x;
+
+        let output1 = output.apply(&self.encoder_attention_layer_norm);
+        let (output1, cross_attention_weights, new_encoder_layer_states) =
+            self.encoder_attention.forward_t(
+                &output1,
+                Some(encoder_hidden_states),
+                None,
+                layer_states.1,
+                train,
+            );
+        let output1: Tensor = output1.apply_t(&self.dropout, train) + output;
+
+        let output2 = output1.apply(&self.final_layer_norm);
+        let output2 = (self.activation.get_fn())(&output2.apply(&self.fc1));
+        let output2 = output2
+            .apply_t(&self.activation_dropout, train)
+            .apply(&self.fc2)
+            .apply_t(&self.dropout, train);
+        let output2: Tensor = output2 + output1;
+        (
+            output2,
+            attention_weights,
+            cross_attention_weights,
+            (new_self_layer_states, new_encoder_layer_states),
+        )
+    }
+}
+
+/// # Whisper decoder
+/// Transformer decoder with learned position embeddings, attending to the encoded audio
+pub struct WhisperDecoder {
+    embed_positions: nn::Embedding,
+    dropout: Dropout,
+    layer_norm: nn::LayerNorm,
+    layers: Vec<WhisperDecoderLayer>,
+    output_attentions: bool,
+    output_hidden_states: bool,
+    output_past: bool,
+}
+
+impl WhisperDecoder {
+    pub fn new<'p, P>(p: P, config: &WhisperConfig) -> WhisperDecoder
+    where
+        P: Borrow<nn::Path<'p>>,
+    {
+        let p = p.borrow();
+        let output_past = config.output_past.unwrap_or(true);
+        let output_attentions = config.output_attentions.unwrap_or(false);
+        let output_hidden_states = config.output_hidden_states.unwrap_or(false);
+
+        let embed_positions = nn::embedding(
+            p / "embed_positions",
+            config.max_target_positions,
+            config.d_model,
+            EmbeddingConfig::default(),
+        );
+
+        let dropout = Dropout::new(config.dropout);
+        let layer_norm = nn::layer_norm(p / "layer_norm", vec![config.d_model], Default::default());
+
+        let mut layers: Vec<WhisperDecoderLayer> = vec![];
+        let p_layers = p / "layers";
+        for layer_index in 0..config.decoder_layers {
+            layers.push(WhisperDecoderLayer::new(&p_layers / layer_index, config));
+        }
+
+        WhisperDecoder {
+            embed_positions,
+            dropout,
+            layer_norm,
+            layers,
+            output_attentions,
+            output_hidden_states,
+            output_past,
+        }
+    }
+
+    pub fn forward_t(
+        &self,
+        input_ids: &Tensor,
+        encoder_hidden_states: &Tensor,
+        embeddings: &nn::Embedding,
+        old_layer_states: Option<Vec<(Option<LayerState>, Option<LayerState>)>>,
+        train: bool,
+    ) -> Result<WhisperDecoderOutput, RustBertError> {
+        let past_key_values_length = if let Some(old_layer_states_values) = &old_layer_states {
+            if let Some(old_value_state) = &old_layer_states_values[0].0 {
+                old_value_state.prev_key.size()[2]
+            } else {
+                0
+            }
+        } else {
+            0
+        };
+        let sequence_length = input_ids.size()[1];
+        let max_positions = self.embed_positions.ws.size()[0];
+        if past_key_values_length + sequence_length > max_positions {
+            return Err(RustBertError::InputTooLongError(format!(
+                "The Whisper decoder accepts at most {} tokens, got {}",
+                max_positions,
+                past_key_values_length + sequence_length
+            )));
+        }
+
+        let x = input_ids.apply(embeddings);
+        let positions = self.embed_positions.ws.slice(
+            0,
+            past_key_values_length,
+            past_key_values_length + sequence_length,
+            1,
+        );
+        let x = x + positions;
+
+        let decoder_attention_mask = if sequence_length > 1 {
+            Some(_make_causal_mask(
+                input_ids.size().as_slice(),
+                x.kind(),
+                x.device(),
+                past_key_values_length,
+            ))
+        } else {
+            None
+        };
+
+        let mut hidden_state = x.apply_t(&self.dropout, train);
+
+        let mut all_hidden_states: Option<Vec<Tensor>> = if self.output_hidden_states {
+            Some(Vec::with_capacity(self.layers.len()))
+        } else {
+            None
+        };
+        let mut all_attentions: Option<Vec<Tensor>> = if self.output_attentions {
+            Some(Vec::with_capacity(self.layers.len()))
+        } else {
+            None
+        };
+        let mut all_cross_attentions: Option<Vec<Tensor>> = if self.output_attentions {
+            Some(Vec::with_capacity(self.layers.len()))
+        } else {
+            None
+        };
+        let mut next_decoder_cache: Option<Vec<(Option<LayerState>, Option<LayerState>)>> =
+            if self.output_past {
+                if old_layer_states.is_some() {
+                    old_layer_states
+                } else {
+                    Some(vec![(None, None); self.layers.len()])
+                }
+            } else {
+                None
+            };
+
+        for (layer_idx, layer) in self.layers.iter().enumerate() {
+            let layer_state = match &mut next_decoder_cache {
+                Some(values) => std::mem::take(&mut values[layer_idx]),
+                None => (None, None),
+            };
+            let (output, attention_weights, cross_attention_weights, new_layer_state) = layer
+                .forward_t(
+                    &hidden_state,
+                    encoder_hidden_states,
+                    decoder_attention_mask.as_ref(),
+                    layer_state,
+                    train,
+                );
+            hidden_state = output;
+            if let Some(hidden_states) = all_hidden_states.borrow_mut() {
+                hidden_states.push(hidden_state.copy());
+            };
+            if let Some(attentions) = all_attentions.borrow_mut() {
+                attentions.push(attention_weights.unwrap());
+            };
+            if let Some(cross_attentions) = all_cross_attentions.borrow_mut() {
+                cross_attentions.push(cross_attention_weights.unwrap());
+            };
+            if let Some(value) = &mut next_decoder_cache {
+                value[layer_idx] = new_layer_state
+            };
+        }
+
+        Ok(WhisperDecoderOutput {
+            hidden_state: hidden_state.apply(&self.layer_norm),
+            encoder_attention_mask: None,
+            next_decoder_cache,
+            all_hidden_states,
+            all_attentions,
+            all_cross_attentions,
+        })
+    }
+}
+
+/// Container holding a Whisper decoder output
+pub type WhisperDecoderOutput = BartDecoderOutput;
@@ -0,0 +1,246 @@
+// Copyright 2022 The OpenAI Authors and The HuggingFace Inc. team. All rights reserved.
+// Copyright 2022 Guillaume Becquin
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//     http://www.apache.org/licenses/LICENSE-2.0
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use crate::bart::BartEncoderOutput;
+use crate::common::activations::{Activation, TensorFunction, _gelu};
+use crate::common::dropout::Dropout;
+use crate::whisper::attention::WhisperAttention;
+use crate::whisper::WhisperConfig;
+use crate::RustBertError;
+use std::borrow::{Borrow, BorrowMut};
+use tch::nn::{ConvConfig, EmbeddingConfig};
+use tch::{nn, Tensor};
+
+pub struct WhisperEncoderLayer {
+    self_attention: WhisperAttention,
+    self_attention_layer_norm: nn::LayerNorm,
+    dropout: Dropout,
+    activation_dropout: Dropout,
+    activation: TensorFunction,
+    fc1: nn::Linear,
+    fc2: nn::Linear,
+    final_layer_norm: nn::LayerNorm,
+}
+
+impl WhisperEncoderLayer {
+    pub fn new<'p, P>(p: P, config: &WhisperConfig) -> WhisperEncoderLayer
+    where
+        P: Borrow<nn::Path<'p>>,
+    {
+        let p = p.borrow();
+
+        let layer_norm_config = nn::LayerNormConfig {
+            eps: 1e-5,
+            ..Default::default()
+        };
+        let output_attention = config.output_attentions.unwrap_or(false);
+        let self_attention = WhisperAttention::new(
+            p / "self_attn",
+            config.d_model,
+            config.encoder_attention_heads,
+            config.attention_dropout,
+            false,
+            false,
+            output_attention,
+        );
+        let self_attention_layer_norm = nn::layer_norm(
+            p / "self_attn_layer_norm",
+            vec![config.d_model],
+            layer_norm_config,
+        );
+        let dropout = Dropout::new(config.dropout);
+        let activation_dropout = Dropout::new(config.activation_dropout);
+        let activation = config
+            .activation_function
+            .unwrap_or(Activation::gelu)
+            .get_function();
+        let fc1 = nn::linear(
+            p / "fc1",
+            config.d_model,
+            config.encoder_ffn_dim,
+            Default::default(),
+        );
+        let fc2 = nn::linear(
+            p / "fc2",
+            config.encoder_ffn_dim,
+            config.d_model,
+            Default::default(),
+        );
+
+        let final_layer_norm = nn::layer_norm(
+            p / "final_layer_norm",
+            vec![config.d_model],
+            layer_norm_config,
+        );
+
+        WhisperEncoderLayer {
+            self_attention,
+            self_attention_layer_norm,
+            dropout,
+            activation_dropout,
+            activation,
+            fc1,
+            fc2,
+            final_layer_norm,
+        }
+    }
+
+    pub fn forward_t(&self, x: &Tensor, train: bool) -> (Tensor, Option<Tensor>) {
+        let output = x.apply(&self.self_attention_layer_norm);
+        let (output, attention_weights, _) = self
+            .self_attention
+            .forward_t(&output, None, None, None, train);
+        let output: Tensor = output.apply_t(&self.dropout, train) + x;
+
+        let residual = output.copy();
+        let output = output.apply(&self.final_layer_norm);
+        let output = (self.activation.get_fn())(&output.apply(&self.fc1));
+        let output = output
+            .apply_t(&self.activation_dropout, train)
+            .apply(&self.fc2)
+            .apply_t(&self.dropout, train);
+        let output = output + residual;
+        (output, attention_weights)
+    }
+}
+
+/// # Whisper encoder
+/// Two convolution layers (the second one halving the number of frames) embed the log-mel spectrogram, followed by
+/// transformer layers with learned position embeddings
+pub struct WhisperEncoder {
+    conv1: nn::Conv1D,
+    conv2: nn::Conv1D,
+    embed_positions: nn::Embedding,
+    dropout: Dropout,
+    layer_norm: nn::LayerNorm,
+    layers: Vec<WhisperEncoderLayer>,
+    output_attentions: bool,
+    output_hidden_states: bool,
+}
+
+impl WhisperEncoder {
+    pub fn new<'p, P>(p: P, config: &WhisperConfig) -> WhisperEncoder
+    where
+        P: Borrow<nn::Path<'p>>,
+    {
+        let p = p.borrow();
+        let output_attentions = config.output_attentions.unwrap_or(false);
+        let output_hidden_states = config.output_hidden_states.unwrap_or(false);
+
+        let conv1 = nn::conv1d(
+            p / "conv1",
+            config.num_mel_bins,
+            config.d_model,
+            3,
+            ConvConfig {
+                padding: 1,
+                ..Default::default()
+            },
+        );
+        let conv2 = nn::conv1d(
+            p / "conv2",
+            config.d_model,
+            config.d_model,
+            3,
+            ConvConfig {
+                padding: 1,
+                stride: 2,
+                ..Default::default()
+            },
+        );
+        let embed_positions = nn::embedding(
+            p / "embed_positions",
+            config.max_source_positions,
+            config.d_model,
+            EmbeddingConfig::default(),
+        );
+
+        let dropout = Dropout::new(config.dropout);
+        let layer_norm = nn::layer_norm(p / "layer_norm", vec![config.d_model], Default::default());
+
+        let mut layers: Vec<WhisperEncoderLayer> = vec![];
+        let p_layers = p / "layers";
+        for layer_index in 0..config.encoder_layers {
+            layers.push(WhisperEncoderLayer::new(&p_layers / layer_index, config));
+        }
+
+        WhisperEncoder {
+            conv1,
+            conv2,
+            embed_positions,
+            dropout,
+            layer_norm,
+            layers,
+            output_attentions,
+            output_hidden_states,
+        }
+    }
+
+    /// Forward pass through the encoder
+    ///
+    /// # Arguments
+    ///
+    /// * `input_features` - Log-mel spectrogram of shape (*batch size*, *num_mel_bins*, *num_frames*), with at most
+    /// 2 * `max_source_positions` frames
+    /// * `train` - boolean flag to turn on/off the dropout layers in the model. Should be set to false for inference.
+    pub fn forward_t(
+        &self,
+        input_features: &Tensor,
+        train: bool,
+    ) -> Result<WhisperEncoderOutput, RustBertError> {
+        let x = _gelu(&_gelu(&input_features.apply(&self.conv1)).apply(&self.conv2))
+            .permute(&[0, 2, 1]);
+        let sequence_length = x.size()[1];
+        let max_positions = self.embed_positions.ws.size()[0];
+        if sequence_length > max_positions {
+            return Err(RustBertError::InputTooLongError(format!(
+                "The input features have {} frames, the Whisper encoder accepts at most {}",
+                input_features.size()[2],
+                2 * max_positions
+            )));
+        }
+        let x = x + self.embed_positions.ws.slice(0, 0, sequence_length, 1);
+
+        let mut hidden_state = x.apply_t(&self.dropout, train);
+
+        let mut all_hidden_states: Option<Vec<Tensor>> = if self.output_hidden_states {
+            Some(vec![])
+        } else {
+            None
+        };
+        let mut all_attentions: Option<Vec<Tensor>> = if self.output_attentions {
+            Some(vec![])
+        } else {
+            None
+        };
+
+        for layer in &self.layers {
+            let (output, attention_weights) = layer.forward_t(&hidden_state, train);
+            hidden_state = output;
+            if let Some(attentions) = all_attentions.borrow_mut() {
+                attentions.push(attention_weights.unwrap());
+            };
+            if let Some(hidden_states) = all_hidden_states.borrow_mut() {
+                hidden_states.push(hidden_state.copy());
+            };
+        }
+
+        Ok(WhisperEncoderOutput {
+            hidden_state: hidden_state.apply(&self.layer_norm),
+            all_hidden_states,
+            all_attentions,
+        })
+    }
+}
+
+/// Container holding a Whisper encoder output
+pub type WhisperEncoderOutput = BartEncoderOutput;
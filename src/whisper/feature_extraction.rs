@@ -0,0 +1,229 @@
+// Copyright 2022 The OpenAI Authors and The HuggingFace Inc. team. All rights reserved.
+// Copyright 2022 Guillaume Becquin
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//     http://www.apache.org/licenses/LICENSE-2.0
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use crate::RustBertError;
+use tch::{Device, Kind, Tensor};
+
+/// Sampling rate (in Hz) expected by the Whisper models
+pub const WHISPER_SAMPLING_RATE: usize = 16000;
+/// Size of the Fourier transform windows
+pub const WHISPER_N_FFT: i64 = 400;
+/// Number of samples between two successive spectrogram frames (10ms)
+pub const WHISPER_HOP_LENGTH: i64 = 160;
+/// Number of samples in a 30 seconds audio window processed by the model
+pub const WHISPER_CHUNK_SAMPLES: usize = 30 * WHISPER_SAMPLING_RATE;
+/// Number of spectrogram frames in a 30 seconds audio window
+pub const WHISPER_CHUNK_FRAMES: i64 = WHISPER_CHUNK_SAMPLES as i64 / WHISPER_HOP_LENGTH;
+
+/// # Whisper feature extractor
+/// Converts 16kHz mono PCM audio to the normalized log-mel spectrogram expected by the Whisper encoder.
+/// Audio windows shorter than 30 seconds are padded with silence.
+pub struct WhisperFeatureExtractor {
+    mel_filters: Tensor,
+    window: Tensor,
+    device: Device,
+}
+
+impl WhisperFeatureExtractor {
+    /// Creates a new `WhisperFeatureExtractor`
+    ///
+    /// # Arguments
+    ///
+    /// * `num_mel_bins` - Number of mel filters (80 for most checkpoints, 128 for `large-v3`)
+    /// * `device` - Device on which the features are computed
+    ///
+    /// # Example
+    ///
+    /// ```no_run
+    /// use rust_bert::whisper::WhisperFeatureExtractor;
+    /// use tch::Device;
+    ///
+    /// let feature_extractor = WhisperFeatureExtractor::new(80, Device::Cpu);
+    /// ```
+    pub fn new(num_mel_bins: i64, device: Device) -> WhisperFeatureExtractor {
+        let mel_filters =
+            mel_filters(WHISPER_SAMPLING_RATE as f64, WHISPER_N_FFT, num_mel_bins).to(device);
+        let window = Tensor::hann_window(WHISPER_N_FFT, (Kind::Float, device));
+        WhisperFeatureExtractor {
+            mel_filters,
+            window,
+            device,
+        }
+    }
+
+    /// Computes the log-mel spectrogram of a batch of audio windows
+    ///
+    /// # Arguments
+    ///
+    /// * `audio` - Slice of 16kHz mono audio windows with samples in [-1, 1], each at most 30 seconds long
+    ///
+    /// # Returns
+    ///
+    /// * `Tensor` of shape (*batch size*, *num_mel_bins*, 3000)
+    ///
+    /// # Example
+    ///
+    /// ```no_run
+    /// # use rust_bert::whisper::WhisperFeatureExtractor;
+    /// # use tch::Device;
+    /// # fn main() -> anyhow::Result<()> {
+    /// let feature_extractor = WhisperFeatureExtractor::new(80, Device::Cpu);
+    /// let audio = vec![0f32; 16000];
+    /// let features = feature_extractor.extract(&[audio.as_slice()])?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn extract(&self, audio: &[&[f32]]) -> Result<Tensor, RustBertError> {
+        let mut padded_audio = Vec::with_capacity(audio.len() * WHISPER_CHUNK_SAMPLES);
+        for samples in audio {
+            if samples.len() > WHISPER_CHUNK_SAMPLES {
+                return Err(RustBertError::InputTooLongError(format!(
+                    "Audio windows are limited to {} samples (30 seconds), got {}",
+                    WHISPER_CHUNK_SAMPLES,
+                    samples.len()
+                )));
+            }
+            padded_audio.extend_from_slice(samples);
+            padded_audio.resize(
+                padded_audio.len() + WHISPER_CHUNK_SAMPLES - samples.len(),
+                0f32,
+            );
+        }
+        let waveform = Tensor::of_slice(&padded_audio)
+            .view([audio.len() as i64, WHISPER_CHUNK_SAMPLES as i64])
+            .to(self.device);
+
+        let padding = WHISPER_N_FFT / 2;
+        let spectrogram = waveform
+            .unsqueeze(1)
+            .reflection_pad1d(&[padding, padding])
+            .squeeze_dim(1)
+            .stft(
+                WHISPER_N_FFT,
+                WHISPER_HOP_LENGTH,
+                WHISPER_N_FFT,
+                Some(&self.window),
+                false,
+                true,
+                true,
+            );
+        let power = spectrogram
+            .view_as_real()
+            .square()
+            .sum_dim_intlist(&[-1], false, Kind::Float)
+            .slice(2, 0, WHISPER_CHUNK_FRAMES, 1);
+
+        let log_spectrogram = self.mel_filters.matmul(&power).clamp_min(1e-10).log10();
+        let floor = log_spectrogram.amax(&[1, 2], true) - 8.0;
+        Ok((log_spectrogram.maximum(&floor) + 4.0) / 4.0)
+    }
+}
+
+fn hertz_to_mel(frequency: f64) -> f64 {
+    // Slaney scale: linear below 1kHz, logarithmic above
+    let min_log_hertz = 1000.0;
+    let min_log_mel = 15.0;
+    let log_step = 6.4f64.ln() / 27.0;
+    if frequency >= min_log_hertz {
+        min_log_mel + (frequency / min_log_hertz).ln() / log_step
+    } else {
+        3.0 * frequency / 200.0
+    }
+}
+
+fn mel_to_hertz(mel: f64) -> f64 {
+    let min_log_hertz = 1000.0;
+    let min_log_mel = 15.0;
+    let log_step = 6.4f64.ln() / 27.0;
+    if mel >= min_log_mel {
+        min_log_hertz * (log_step * (mel - min_log_mel)).exp()
+    } else {
+        200.0 * mel / 3.0
+    }
+}
+
+/// Triangular mel filter bank with Slaney normalization (matching `librosa.filters.mel`), of shape
+/// (*num_mel_bins*, *n_fft / 2 + 1*)
+pub(crate) fn mel_filters(sampling_rate: f64, n_fft: i64, num_mel_bins: i64) -> Tensor {
+    let num_frequencies = (n_fft / 2 + 1) as usize;
+    let num_mel_bins = num_mel_bins as usize;
+    let fft_frequencies: Vec<f64> = (0..num_frequencies)
+        .map(|index| index as f64 * sampling_rate / n_fft as f64)
+        .collect();
+
+    let max_mel = hertz_to_mel(sampling_rate / 2.0);
+    let mel_frequencies: Vec<f64> = (0..num_mel_bins + 2)
+        .map(|index| mel_to_hertz(max_mel * index as f64 / (num_mel_bins + 1) as f64))
+        .collect();
+
+    let mut weights = Vec::with_capacity(num_mel_bins * num_frequencies);
+    for bin in 0..num_mel_bins {
+        let (lower, center, upper) = (
+            mel_frequencies[bin],
+            mel_frequencies[bin + 1],
+            mel_frequencies[bin + 2],
+        );
+        let normalization = 2.0 / (upper - lower);
+        weights.extend(fft_frequencies.iter().map(|frequency| {
+            let rising = (frequency - lower) / (center - lower);
+            let falling = (upper - frequency) / (upper - center);
+            (rising.min(falling).max(0.0) * normalization) as f32
+        }));
+    }
+    Tensor::of_slice(&weights).view([num_mel_bins as i64, num_frequencies as i64])
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn mel_scale_round_trip() {
+        for frequency in [0.0, 440.0, 1000.0, 4000.0, 8000.0] {
+            assert!((mel_to_hertz(hertz_to_mel(frequency)) - frequency).abs() < 1e-6);
+        }
+        assert!((hertz_to_mel(1000.0) - 15.0).abs() < 1e-9);
+    }
+
+    #[test]
+    fn mel_filter_bank() {
+        let filters = mel_filters(16000.0, 400, 80);
+        assert_eq!(filters.size(), vec![80, 201]);
+        assert_eq!(f64::from(filters.min()), 0.0);
+        // every filter covers at least one frequency bin
+        let filter_sums = Vec::<f32>::from(filters.sum_dim_intlist(&[1], false, Kind::Float));
+        assert!(filter_sums.iter().all(|sum| *sum > 0.0));
+        // the DC component and the Nyquist frequency are outside of all filters
+        assert_eq!(f64::from(filters.select(1, 0).sum(Kind::Float)), 0.0);
+    }
+
+    #[test]
+    fn log_mel_spectrogram() -> anyhow::Result<()> {
+        let feature_extractor = WhisperFeatureExtractor::new(80, Device::Cpu);
+        let sine: Vec<f32> = (0..WHISPER_SAMPLING_RATE)
+            .map(|index| (2.0 * std::f32::consts::PI * 440.0 * index as f32 / 16000.0).sin() * 0.5)
+            .collect();
+        let silence = vec![0f32; 100];
+        let features = feature_extractor.extract(&[sine.as_slice(), silence.as_slice()])?;
+        assert_eq!(features.size(), vec![2, 80, WHISPER_CHUNK_FRAMES]);
+
+        // the dynamic range is limited to 8 orders of magnitude below the maximum
+        let sine_features = features.get(0);
+        let range = f64::from(sine_features.max()) - f64::from(sine_features.min());
+        assert!(range <= 2.0 + 1e-6);
+        assert!(f64::from(features.get(1).max()) < f64::from(sine_features.max()));
+
+        let too_long = vec![0f32; WHISPER_CHUNK_SAMPLES + 1];
+        assert!(feature_extractor.extract(&[too_long.as_slice()]).is_err());
+        Ok(())
+    }
+}
@@ -0,0 +1,78 @@
+//! # Whisper (Radford et al.)
+//!
+//! Implementation of the Whisper speech recognition model ([Robust Speech Recognition via Large-Scale Weak Supervision](https://arxiv.org/abs/2212.04356) Radford, Kim, Xu, Brockman, McLeavey, Sutskever, 2022).
+//! The base model is implemented in the `whisper_model::WhisperModel` struct. The model also includes a language model head: `whisper_model::WhisperForConditionalGeneration`.
+//! The encoder takes the log-mel spectrogram of 30 seconds of 16kHz audio, computed by the `WhisperFeatureExtractor`. The decoder is prompted with special tokens
+//! selecting the language of the audio and the task (transcription or translation to English), see `WhisperSpecialTokens`.
+//! A ready-to-use pipeline handling the feature extraction, the chunking of long audio and the decoding of timestamps is available in `pipelines::speech_recognition`.
+//!
+//! # Model set-up and pre-trained weights loading
+//!
+//! All models expect the following resources:
+//! - Configuration file expected to have a structure following the [Transformers library](https://github.com/huggingface/transformers)
+//! - Model weights are expected to have a structure and parameter names following the [Transformers library](https://github.com/huggingface/transformers). A conversion using the Python utility scripts is required to convert the `.bin` weights to the `.ot` format.
+//! - `Gpt2Tokenizer` using the `vocab.json` vocabulary and `merges.txt` merges files of the checkpoint
+//!
+//! No pre-trained checkpoint is hosted with the crate, the resources are provided as local files:
+//!
+//! ```no_run
+//! # fn main() -> anyhow::Result<()> {
+//! #
+//! use tch::{nn, no_grad, Device, Tensor};
+//! # use std::path::PathBuf;
+//! use rust_bert::resources::{LocalResource, ResourceProvider};
+//! use rust_bert::whisper::{
+//!     WhisperConfig, WhisperFeatureExtractor, WhisperForConditionalGeneration,
+//!     WhisperSpecialTokens,
+//! };
+//! use rust_bert::Config;
+//!
+//! let config_resource = LocalResource {
+//!     local_path: PathBuf::from("path/to/config.json"),
+//! };
+//! let weights_resource = LocalResource {
+//!     local_path: PathBuf::from("path/to/model.ot"),
+//! };
+//! let config_path = config_resource.get_local_path()?;
+//! let weights_path = weights_resource.get_local_path()?;
+//!
+//! let device = Device::cuda_if_available();
+//! let mut vs = nn::VarStore::new(device);
+//! let config = WhisperConfig::from_file(config_path);
+//! let whisper_model = WhisperForConditionalGeneration::new(&vs.root(), &config);
+//! vs.load(weights_path)?;
+//!
+//! let feature_extractor = WhisperFeatureExtractor::new(config.num_mel_bins, device);
+//! let special_tokens = WhisperSpecialTokens::from_config(&config);
+//! let audio = vec![0f32; 16000];
+//! let input_features = feature_extractor.extract(&[audio.as_slice()])?;
+//! let decoder_input_ids = Tensor::of_slice(&[
+//!     special_tokens.start_of_transcript,
+//!     special_tokens.language_token("en")?,
+//!     special_tokens.transcribe,
+//!     special_tokens.no_timestamps,
+//! ])
+//! .unsqueeze(0)
+//! .to(device);
+//! let output = no_grad(|| {
+//!     whisper_model.forward_t(Some(&input_features), &decoder_input_ids, None, None, false)
+//! })?;
+//! # Ok(())
+//! # }
+//! ```
+
+mod attention;
+mod decoder;
+mod encoder;
+mod feature_extraction;
+mod whisper_model;
+
+pub use attention::LayerState;
+pub use feature_extraction::{
+    WhisperFeatureExtractor, WHISPER_CHUNK_FRAMES, WHISPER_CHUNK_SAMPLES, WHISPER_HOP_LENGTH,
+    WHISPER_N_FFT, WHISPER_SAMPLING_RATE,
+};
+pub use whisper_model::{
+    WhisperConfig, WhisperForConditionalGeneration, WhisperModel, WhisperModelOutput,
+    WhisperSpecialTokens, WHISPER_LANGUAGES,
+};
@@ -0,0 +1,512 @@
+// Copyright 2022 The OpenAI Authors and The HuggingFace Inc. team. All rights reserved.
+// Copyright 2022 Guillaume Becquin
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//     http://www.apache.org/licenses/LICENSE-2.0
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use crate::bart::BartModelOutput;
+use crate::common::activations::Activation;
+use crate::whisper::attention::LayerState;
+use crate::whisper::decoder::WhisperDecoder;
+use crate::whisper::encoder::WhisperEncoder;
+use crate::{Config, RustBertError};
+use serde::{Deserialize, Serialize};
+use std::borrow::Borrow;
+use tch::nn::{embedding, EmbeddingConfig};
+use tch::{nn, Tensor};
+
+/// Codes of the languages supported by the multilingual Whisper checkpoints, in the order of their language tokens
+/// (`yue` is only available for the checkpoints with 100 languages, e.g. `large-v3`)
+#[rustfmt::skip]
+pub const WHISPER_LANGUAGES: [&str; 100] = [
+    "en", "zh", "de", "es", "ru", "ko", "fr", "ja", "pt", "tr", "pl", "ca", "nl", "ar", "sv", "it", "id", "hi", "fi",
+    "vi", "he", "uk", "el", "ms", "cs", "ro", "da", "hu", "ta", "no", "th", "ur", "hr", "bg", "lt", "la", "mi", "ml",
+    "cy", "sk", "te", "fa", "lv", "bn", "sr", "az", "sl", "kn", "et", "mk", "br", "eu", "is", "hy", "ne", "mn", "bs",
+    "kk", "sq", "sw", "gl", "mr", "pa", "si", "km", "sn", "yo", "so", "af", "oc", "ka", "be", "tg", "sd", "gu", "am",
+    "yi", "lo", "uz", "fo", "ht", "ps", "tk", "nn", "mt", "sa", "lb", "my", "bo", "tl", "mg", "as", "tt", "haw", "ln",
+    "ha", "ba", "jw", "su", "yue",
+];
+
+/// Number of timestamp tokens, from `<|0.00|>` to `<|30.00|>` by steps of 0.02 seconds
+const NUM_TIMESTAMP_TOKENS: i64 = 1501;
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+/// # Whisper model configuration
+/// Defines the Whisper model architecture (e.g. number of layers, hidden layer size, number of mel filters...)
+pub struct WhisperConfig {
+    pub vocab_size: i64,
+    pub num_mel_bins: i64,
+    pub encoder_layers: i64,
+    pub encoder_attention_heads: i64,
+    pub encoder_ffn_dim: i64,
+    pub decoder_layers: i64,
+    pub decoder_attention_heads: i64,
+    pub decoder_ffn_dim: i64,
+    pub d_model: i64,
+    pub max_source_positions: i64,
+    pub max_target_positions: i64,
+    pub activation_function: Option<Activation>,
+    pub dropout: f64,
+    pub attention_dropout: f64,
+    pub activation_dropout: f64,
+    pub pad_token_id: Option<i64>,
+    pub bos_token_id: Option<i64>,
+    pub eos_token_id: Option<i64>,
+    pub decoder_start_token_id: Option<i64>,
+    /// Tokens never generated (e.g. speaker tags and music notes)
+    pub suppress_tokens: Option<Vec<i64>>,
+    /// Tokens that cannot start the transcription (e.g. a blank space or the end of text token)
+    pub begin_suppress_tokens: Option<Vec<i64>>,
+    pub output_attentions: Option<bool>,
+    pub output_hidden_states: Option<bool>,
+    pub output_past: Option<bool>,
+}
+
+impl Config for WhisperConfig {}
+
+impl Default for WhisperConfig {
+    fn default() -> Self {
+        WhisperConfig {
+            vocab_size: 51865,
+            num_mel_bins: 80,
+            encoder_layers: 4,
+            encoder_attention_heads: 6,
+            encoder_ffn_dim: 1536,
+            decoder_layers: 4,
+            decoder_attention_heads: 6,
+            decoder_ffn_dim: 1536,
+            d_model: 384,
+            max_source_positions: 1500,
+            max_target_positions: 448,
+            activation_function: Some(Activation::gelu),
+            dropout: 0.0,
+            attention_dropout: 0.0,
+            activation_dropout: 0.0,
+            pad_token_id: Some(50257),
+            bos_token_id: Some(50257),
+            eos_token_id: Some(50257),
+            decoder_start_token_id: Some(50258),
+            suppress_tokens: None,
+            begin_suppress_tokens: Some(vec![220, 50257]),
+            output_attentions: None,
+            output_hidden_states: None,
+            output_past: None,
+        }
+    }
+}
+
+/// # Whisper special tokens
+/// Ids of the special tokens controlling the decoding, following the text tokens of the vocabulary. The timestamp
+/// tokens are the last tokens of the vocabulary, the other special tokens directly precede them.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct WhisperSpecialTokens {
+    /// End of text token
+    pub end_of_text: i64,
+    /// Start of transcript token, starting the decoder prompt
+    pub start_of_transcript: i64,
+    /// Translation to English task token
+    pub translate: i64,
+    /// Transcription task token
+    pub transcribe: i64,
+    /// Start of previous text token, preceding the text of the previous window used as a prompt
+    pub start_of_previous: i64,
+    /// No speech token, whose probability detects silent windows
+    pub no_speech: i64,
+    /// Token disabling the prediction of timestamps
+    pub no_timestamps: i64,
+    /// Id of the first timestamp token (`<|0.00|>`)
+    pub timestamp_begin: i64,
+    /// Number of language tokens following the start of transcript token (0 for English-only models)
+    pub num_languages: i64,
+}
+
+impl WhisperSpecialTokens {
+    /// Derives the special token ids from the vocabulary size of a configuration
+    pub fn from_config(config: &WhisperConfig) -> WhisperSpecialTokens {
+        let timestamp_begin = config.vocab_size - NUM_TIMESTAMP_TOKENS;
+        let end_of_text = config
+            .eos_token_id
+            .unwrap_or(if config.vocab_size >= 51865 {
+                50257
+            } else {
+                50256
+            });
+        let start_of_transcript = config.decoder_start_token_id.unwrap_or(end_of_text + 1);
+        let translate = timestamp_begin - 6;
+        // English-only checkpoints keep the language tokens in their vocabulary but are not trained to use them
+        let num_languages = if config.vocab_size >= 51865 {
+            translate - start_of_transcript - 1
+        } else {
+            0
+        };
+        WhisperSpecialTokens {
+            end_of_text,
+            start_of_transcript,
+            translate,
+            transcribe: timestamp_begin - 5,
+            start_of_previous: timestamp_begin - 3,
+            no_speech: timestamp_begin - 2,
+            no_timestamps: timestamp_begin - 1,
+            timestamp_begin,
+            num_languages,
+        }
+    }
+
+    /// Returns true if the model was trained on several languages
+    pub fn is_multilingual(&self) -> bool {
+        self.num_languages > 0
+    }
+
+    /// Returns the id of the token of a language
+    ///
+    /// # Arguments
+    ///
+    /// * `language` - Code of the language (e.g. `en` or `fr`), see `WHISPER_LANGUAGES`
+    pub fn language_token(&self, language: &str) -> Result<i64, RustBertError> {
+        WHISPER_LANGUAGES
+            .iter()
+            .take(self.num_languages as usize)
+            .position(|code| *code == language)
+            .map(|position| self.start_of_transcript + 1 + position as i64)
+            .ok_or_else(|| {
+                RustBertError::ValueError(format!(
+                    "Language {} is not supported by this Whisper model",
+                    language
+                ))
+            })
+    }
+
+    /// Returns the code of the language of a language token, if it is one
+    pub fn language_code(&self, token_id: i64) -> Option<&'static str> {
+        let position = token_id - self.start_of_transcript - 1;
+        if (0..self.num_languages).contains(&position) {
+            WHISPER_LANGUAGES.get(position as usize).copied()
+        } else {
+            None
+        }
+    }
+
+    /// Returns the time in seconds of a timestamp token, if it is one
+    pub fn timestamp(&self, token_id: i64) -> Option<f64> {
+        if token_id >= self.timestamp_begin {
+            Some((token_id - self.timestamp_begin) as f64 * 0.02)
+        } else {
+            None
+        }
+    }
+}
+
+/// # Whisper Base model
+/// Base architecture for Whisper model. Usually complemented with a task-specific head, such as a language model head.
+/// It is made of the following blocks:
+/// - `encoder`: `WhisperEncoder` convolution layers and transformer embedding the log-mel spectrogram of the audio
+/// - `decoder`: `WhisperDecoder` (transformer) made of a vector of decoding layers with self attention and encoder
+/// cross-attention. Caching is implemented for the decoder to avoid recalculating static states (encoder key/values
+/// and previously calculated decoder key/values)
+/// - `embeddings`: token embeddings of the decoder
+pub struct WhisperModel {
+    pub(crate) encoder: WhisperEncoder,
+    decoder: WhisperDecoder,
+    pub(crate) embeddings: nn::Embedding,
+}
+
+impl WhisperModel {
+    /// Build a new `WhisperModel`
+    ///
+    /// # Arguments
+    ///
+    /// * `p` - Variable store path for the root of the Whisper model
+    /// * `config` - `WhisperConfig` object defining the model architecture
+    ///
+    /// # Example
+    ///
+    /// ```no_run
+    /// use rust_bert::whisper::{WhisperConfig, WhisperModel};
+    /// use rust_bert::Config;
+    /// use std::path::Path;
+    /// use tch::{nn, Device};
+    ///
+    /// let config_path = Path::new("path/to/config.json");
+    /// let device = Device::Cpu;
+    /// let p = nn::VarStore::new(device);
+    /// let config = WhisperConfig::from_file(config_path);
+    /// let whisper: WhisperModel = WhisperModel::new(&p.root() / "model", &config);
+    /// ```
+    pub fn new<'p, P>(p: P, config: &WhisperConfig) -> WhisperModel
+    where
+        P: Borrow<nn::Path<'p>>,
+    {
+        let p = p.borrow();
+
+        let embedding_config = EmbeddingConfig {
+            padding_idx: config.pad_token_id.unwrap_or(50257),
+            ..Default::default()
+        };
+        let embeddings: nn::Embedding = embedding(
+            p / "decoder" / "embed_tokens",
+            config.vocab_size,
+            config.d_model,
+            embedding_config,
+        );
+
+        let encoder = WhisperEncoder::new(p / "encoder", config);
+        let decoder = WhisperDecoder::new(p / "decoder", config);
+
+        WhisperModel {
+            encoder,
+            decoder,
+            embeddings,
+        }
+    }
+
+    /// Forward pass through the model
+    ///
+    /// # Arguments
+    ///
+    /// * `input_features` - Optional log-mel spectrogram of shape (*batch size*, *num_mel_bins*, *num_frames*). Must be provided when the encoder output is not provided.
+    /// * `decoder_input_ids` - Input tensor of shape (*batch size*, *target_sequence_length*), starting with the decoder prompt (start of transcript, language and task tokens)
+    /// * `encoder_output` - Optional tensor of shape (*batch size*, *num_frames / 2*, *hidden_size*). When provided, the encoder hidden state will not be recalculated. Useful for generation tasks.
+    /// * `layer_states` - Optional vector of length *num_layers* containing tuples of optional `LayerStates` containing the last calculated key and value pairs for the decoder self attention and encoder cross attention.
+    /// * `train` - boolean flag to turn on/off the dropout layers in the model. Should be set to false for inference.
+    ///
+    /// # Returns
+    ///
+    /// * `WhisperModelOutput` containing:
+    ///   - `decoder_output` - `Tensor` of shape (*batch size*, *target_sequence_length*, *hidden_size*) representing the activations of the last decoder hidden state
+    ///   - `encoder_hidden_state` - `Option<Tensor>` of shape (*batch size*, *num_frames / 2*, *hidden_size*) representing the activations of the last encoder hidden state if it was not provided, otherwise None
+    ///   - `cache` - `Option<Vec<(Option<LayerState>, Option<LayerState>)>>` of length *n_layer* containing the past keys and values for both the self attention and the encoder cross attention of each layer of the decoder.
+    ///   - `all_encoder_hidden_states` - `Option<Vec<Tensor>>` of length *num_encoder_layers* with shape (*batch size*, *num_frames / 2*, *hidden_size*)
+    ///   - `all_encoder_attentions` - `Option<Vec<Tensor>>` of length *num_encoder_layers* with shape (*batch size*, *num_heads*, *num_frames / 2*, *num_frames / 2*)
+    ///   - `all_decoder_hidden_states` - `Option<Vec<Tensor>>` of length *num_decoder_layers* with shape (*batch size*, *target_sequence_length*, *hidden_size*)
+    ///   - `all_decoder_attentions` - `Option<Vec<Tensor>>` of length *num_decoder_layers* with shape (*batch size*, *num_heads*, *target_sequence_length*, *target_sequence_length*)
+    ///
+    /// # Example
+    ///
+    /// ```no_run
+    /// # use tch::{nn, Device, Tensor, no_grad};
+    /// # use rust_bert::Config;
+    /// # use std::path::Path;
+    /// # use tch::kind::Kind::{Float, Int64};
+    /// # use rust_bert::whisper::{WhisperConfig, WhisperModel};
+    /// # let config_path = Path::new("path/to/config.json");
+    /// # let device = Device::Cpu;
+    /// # let vs = nn::VarStore::new(device);
+    /// # let config = WhisperConfig::from_file(config_path);
+    /// # let whisper_model: WhisperModel = WhisperModel::new(&vs.root(), &config);
+    /// let input_features = Tensor::rand(&[1, 80, 3000], (Float, device));
+    /// let decoder_input_ids = Tensor::of_slice(&[50258i64, 50259, 50359, 50363]).unsqueeze(0);
+    ///
+    /// let model_output = no_grad(|| {
+    ///     whisper_model.forward_t(Some(&input_features), &decoder_input_ids, None, None, false)
+    /// });
+    /// ```
+    pub fn forward_t(
+        &self,
+        input_features: Option<&Tensor>,
+        decoder_input_ids: &Tensor,
+        encoder_output: Option<&Tensor>,
+        layer_states: Option<Vec<(Option<LayerState>, Option<LayerState>)>>,
+        train: bool,
+    ) -> Result<WhisperModelOutput, RustBertError> {
+        let calc_encoder_output = match (encoder_output, input_features) {
+            (Some(_), _) => None,
+            (None, Some(input_features)) => Some(self.encoder.forward_t(input_features, train)?),
+            (None, None) => {
+                return Err(RustBertError::ValueError(
+                    "Either the input features or the encoder output must be provided".into(),
+                ));
+            }
+        };
+
+        let (calc_hidden_states, all_encoder_hidden_states, all_encoder_attentions) =
+            if let Some(calc_encoder_output) = calc_encoder_output {
+                (
+                    Some(calc_encoder_output.hidden_state),
+                    calc_encoder_output.all_hidden_states,
+                    calc_encoder_output.all_attentions,
+                )
+            } else {
+                (None, None, None)
+            };
+
+        let encoder_output = encoder_output.unwrap_or_else(|| calc_hidden_states.as_ref().unwrap());
+
+        let decoder_output = self.decoder.forward_t(
+            decoder_input_ids,
+            encoder_output,
+            &self.embeddings,
+            layer_states,
+            train,
+        )?;
+
+        Ok(WhisperModelOutput {
+            decoder_output: decoder_output.hidden_state,
+            encoder_hidden_state: calc_hidden_states,
+            cache: decoder_output.next_decoder_cache,
+            all_decoder_hidden_states: decoder_output.all_hidden_states,
+            all_decoder_attentions: decoder_output.all_attentions,
+            all_decoder_cross_attentions: decoder_output.all_cross_attentions,
+            all_encoder_hidden_states,
+            all_encoder_attentions,
+        })
+    }
+}
+
+/// Container holding a Whisper model output
+pub type WhisperModelOutput = BartModelOutput;
+
+/// # Whisper Model for conditional generation
+/// Whisper model with a vocabulary decoding head, used for speech recognition and translation
+/// It is made of the following blocks:
+/// - `base_model`: `WhisperModel` Base Whisper model
+/// - `linear`: Linear layer without bias tied to the weights of the token id embeddings
+pub struct WhisperForConditionalGeneration {
+    base_model: WhisperModel,
+}
+
+impl WhisperForConditionalGeneration {
+    /// Build a new `WhisperForConditionalGeneration`
+    ///
+    /// # Arguments
+    ///
+    /// * `p` - Variable store path for the root of the Whisper model
+    /// * `config` - `WhisperConfig` object defining the model architecture
+    ///
+    /// # Example
+    ///
+    /// ```no_run
+    /// use rust_bert::whisper::{WhisperConfig, WhisperForConditionalGeneration};
+    /// use rust_bert::Config;
+    /// use std::path::Path;
+    /// use tch::{nn, Device};
+    ///
+    /// let config_path = Path::new("path/to/config.json");
+    /// let device = Device::Cpu;
+    /// let p = nn::VarStore::new(device);
+    /// let config = WhisperConfig::from_file(config_path);
+    /// let whisper: WhisperForConditionalGeneration =
+    ///     WhisperForConditionalGeneration::new(&p.root(), &config);
+    /// ```
+    pub fn new<'p, P>(p: P, config: &WhisperConfig) -> WhisperForConditionalGeneration
+    where
+        P: Borrow<nn::Path<'p>>,
+    {
+        let base_model = WhisperModel::new(p.borrow() / "model", config);
+        WhisperForConditionalGeneration { base_model }
+    }
+
+    /// Forward pass through the model
+    ///
+    /// # Arguments
+    ///
+    /// * `input_features` - Optional log-mel spectrogram of shape (*batch size*, *num_mel_bins*, *num_frames*). Must be provided when the encoder output is not provided.
+    /// * `decoder_input_ids` - Input tensor of shape (*batch size*, *target_sequence_length*), starting with the decoder prompt (start of transcript, language and task tokens)
+    /// * `encoder_output` - Optional tensor of shape (*batch size*, *num_frames / 2*, *hidden_size*). When provided, the encoder hidden state will not be recalculated.
+    /// * `layer_states` - Optional vector of length *num_layers* containing tuples of optional `LayerStates` containing the last calculated key and value pairs for the decoder self attention and encoder cross attention.
+    /// * `train` - boolean flag to turn on/off the dropout layers in the model. Should be set to false for inference.
+    ///
+    /// # Returns
+    ///
+    /// * `WhisperModelOutput` with `decoder_output` of shape (*batch size*, *target_sequence_length*, *vocab_size*) representing the logits for each vocabulary item and position
+    ///
+    /// # Example
+    ///
+    /// ```no_run
+    /// # use tch::{nn, Device, Tensor, no_grad};
+    /// # use rust_bert::Config;
+    /// # use std::path::Path;
+    /// # use tch::kind::Kind::Float;
+    /// # use rust_bert::whisper::{WhisperConfig, WhisperForConditionalGeneration};
+    /// # let config_path = Path::new("path/to/config.json");
+    /// # let device = Device::Cpu;
+    /// # let vs = nn::VarStore::new(device);
+    /// # let config = WhisperConfig::from_file(config_path);
+    /// # let whisper_model = WhisperForConditionalGeneration::new(&vs.root(), &config);
+    /// let input_features = Tensor::rand(&[1, 80, 3000], (Float, device));
+    /// let decoder_input_ids = Tensor::of_slice(&[50258i64, 50259, 50359, 50363]).unsqueeze(0);
+    ///
+    /// let model_output = no_grad(|| {
+    ///     whisper_model.forward_t(Some(&input_features), &decoder_input_ids, None, None, false)
+    /// });
+    /// ```
+    pub fn forward_t(
+        &self,
+        input_features: Option<&Tensor>,
+        decoder_input_ids: &Tensor,
+        encoder_output: Option<&Tensor>,
+        old_layer_states: Option<Vec<(Option<LayerState>, Option<LayerState>)>>,
+        train: bool,
+    ) -> Result<WhisperModelOutput, RustBertError> {
+        let base_model_output = self.base_model.forward_t(
+            input_features,
+            decoder_input_ids,
+            encoder_output,
+            old_layer_states,
+            train,
+        )?;
+
+        let lm_logits = base_model_output
+            .decoder_output
+            .linear::<Tensor>(&self.base_model.embeddings.ws, None);
+        Ok(WhisperModelOutput {
+            decoder_output: lm_logits,
+            ..base_model_output
+        })
+    }
+
+    /// Encodes the log-mel spectrogram of shape (*batch size*, *num_mel_bins*, *num_frames*)
+    pub fn encode(&self, input_features: &Tensor) -> Result<Tensor, RustBertError> {
+        Ok(self
+            .base_model
+            .encoder
+            .forward_t(input_features, false)?
+            .hidden_state)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn special_tokens() {
+        let multilingual = WhisperSpecialTokens::from_config(&WhisperConfig::default());
+        assert_eq!(multilingual.start_of_transcript, 50258);
+        assert_eq!(multilingual.translate, 50358);
+        assert_eq!(multilingual.transcribe, 50359);
+        assert_eq!(multilingual.no_timestamps, 50363);
+        assert_eq!(multilingual.timestamp_begin, 50364);
+        assert_eq!(multilingual.num_languages, 99);
+        assert_eq!(multilingual.language_token("en").unwrap(), 50259);
+        assert_eq!(multilingual.language_token("fr").unwrap(), 50265);
+        assert_eq!(multilingual.language_code(50265), Some("fr"));
+        assert!(multilingual.language_token("yue").is_err());
+        assert_eq!(multilingual.timestamp(50364 + 150), Some(3.0));
+
+        let english = WhisperSpecialTokens::from_config(&WhisperConfig {
+            vocab_size: 51864,
+            eos_token_id: Some(50256),
+            decoder_start_token_id: Some(50257),
+            ..Default::default()
+        });
+        assert_eq!(english.transcribe, 50358);
+        assert_eq!(english.no_timestamps, 50362);
+        assert!(!english.is_multilingual());
+        assert!(english.language_token("en").is_err());
+
+        let large_v3 = WhisperSpecialTokens::from_config(&WhisperConfig {
+            vocab_size: 51866,
+            num_mel_bins: 128,
+            ..Default::default()
+        });
+        assert_eq!(large_v3.num_languages, 100);
+        assert_eq!(large_v3.language_token("yue").unwrap(), 50358);
+        assert_eq!(large_v3.no_timestamps, 50364);
+    }
+}
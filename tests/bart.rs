@@ -2,7 +2,13 @@ use rust_bert::bart::{
     BartConfig, BartConfigResources, BartMergesResources, BartModel, BartModelResources,
     BartVocabResources,
 };
-use rust_bert::pipelines::summarization::{SummarizationConfig, SummarizationModel};
+use rust_bert::pipelines::natural_language_inference::{
+    InferenceLabel, NaturalLanguageInferenceConfig, NaturalLanguageInferenceModel,
+};
+use rust_bert::pipelines::summarization::{
+    StructuredSummary, StructuredSummaryConfig, SummarizationConfig, SummarizationModel,
+    SummaryFormat,
+};
 use rust_bert::pipelines::zero_shot_classification::{
     ZeroShotClassificationConfig, ZeroShotClassificationModel,
 };
@@ -65,7 +71,7 @@ fn bart_lm_model() -> anyhow::Result<()> {
 
     //    Forward pass
     let model_output =
-        bart_model.forward_t(Some(&input_tensor), None, None, None, None, None, false);
+        bart_model.forward_t(Some(&input_tensor), None, None, None, None, None, false)?;
     assert_eq!(model_output.decoder_output.size(), vec!(1, 6, 1024));
     assert_eq!(
         model_output.encoder_hidden_state.unwrap().size(),
@@ -197,6 +203,129 @@ about exoplanets like K2-18b."];
     Ok(())
 }
 
+#[test]
+fn bart_summarization_diverse_beam_search() -> anyhow::Result<()> {
+    let config_resource = Box::new(RemoteResource::from_pretrained(
+        BartConfigResources::DISTILBART_CNN_6_6,
+    ));
+    let vocab_resource = Box::new(RemoteResource::from_pretrained(
+        BartVocabResources::DISTILBART_CNN_6_6,
+    ));
+    let merges_resource = Box::new(RemoteResource::from_pretrained(
+        BartMergesResources::DISTILBART_CNN_6_6,
+    ));
+    let model_resource = Box::new(RemoteResource::from_pretrained(
+        BartModelResources::DISTILBART_CNN_6_6,
+    ));
+    let summarization_config = SummarizationConfig {
+        model_resource,
+        config_resource,
+        vocab_resource,
+        merges_resource,
+        num_beams: 4,
+        num_beam_groups: Some(2),
+        diversity_penalty: Some(1.0),
+        num_return_sequences: 2,
+        min_length: 10,
+        max_length: 60,
+        length_penalty: 1.0,
+        device: Device::Cpu,
+        ..Default::default()
+    };
+    let model = SummarizationModel::new(summarization_config)?;
+
+    let input = [
+        "The presence of water vapour was confirmed in the atmosphere of K2-18b, a planet circling a star in \
+the constellation Leo. This is the first such discovery in a planet in its star's habitable zone, not too hot and \
+not too cold for liquid water to exist. K2-18b was first identified in 2015 by the Kepler space telescope.",
+        "The city council approved on Monday a new budget for the public library, which will extend its opening \
+hours and renovate the reading rooms. The works will start next spring and last for six months, during which the \
+library will remain open.",
+    ];
+
+    //    The finished hypotheses of the beam groups of each input are ranked together
+    let output = model.summarize(&input);
+
+    assert_eq!(output.len(), 4);
+    for candidates in output.chunks(2) {
+        assert!(!candidates[0].trim().is_empty());
+        assert!(!candidates[1].trim().is_empty());
+        assert_ne!(candidates[0], candidates[1]);
+    }
+    assert!(output[0].contains("K2-18b"));
+    assert!(output[2].contains("library"));
+
+    Ok(())
+}
+
+#[test]
+fn bart_summarization_bullets() -> anyhow::Result<()> {
+    let config_resource = Box::new(RemoteResource::from_pretrained(
+        BartConfigResources::DISTILBART_CNN_6_6,
+    ));
+    let vocab_resource = Box::new(RemoteResource::from_pretrained(
+        BartVocabResources::DISTILBART_CNN_6_6,
+    ));
+    let merges_resource = Box::new(RemoteResource::from_pretrained(
+        BartMergesResources::DISTILBART_CNN_6_6,
+    ));
+    let model_resource = Box::new(RemoteResource::from_pretrained(
+        BartModelResources::DISTILBART_CNN_6_6,
+    ));
+    let summarization_config = SummarizationConfig {
+        model_resource,
+        config_resource,
+        vocab_resource,
+        merges_resource,
+        num_beams: 1,
+        length_penalty: 1.0,
+        min_length: 56,
+        max_length: 142,
+        device: Device::Cpu,
+        ..Default::default()
+    };
+    let model = SummarizationModel::new(summarization_config)?;
+
+    let input = ["In findings published Tuesday in Cornell University's arXiv by a team of scientists \
+from the University of Montreal and a separate report published Wednesday in Nature Astronomy by a team \
+from University College London (UCL), the presence of water vapour was confirmed in the atmosphere of K2-18b, \
+a planet circling a star in the constellation Leo. This is the first such discovery in a planet in its star's \
+habitable zone — not too hot and not too cold for liquid water to exist. The Montreal team, led by Björn Benneke, \
+used data from the NASA's Hubble telescope to assess changes in the light coming from K2-18b's star as the planet \
+passed between it and Earth. They found that certain wavelengths of light, which are usually absorbed by water, \
+weakened when the planet was in the way, indicating not only does K2-18b have an atmosphere, but the atmosphere \
+contains water in vapour form. The team from UCL then analyzed the Montreal team's data using their own software \
+and confirmed their conclusion. This was not the first time scientists have found signs of water on an exoplanet, \
+but previous discoveries were made on planets with high temperatures or other pronounced differences from Earth. \
+\"This is the first potentially habitable planet where the temperature is right and where we now know there is water,\" \
+said UCL astronomer Angelos Tsiaras. \"It's the best candidate for habitability right now.\" \"It's a good sign\", \
+said Ryan Cloutier of the Harvard–Smithsonian Center for Astrophysics, who was not one of either study's authors. \
+\"Overall,\" he continued, \"the presence of water in its atmosphere certainly improves the prospect of K2-18b being \
+a potentially habitable planet, but further observations will be required to say for sure. \" \
+K2-18b was first identified in 2015 by the Kepler space telescope. It is about 110 light-years from Earth and larger \
+but less dense. Its star, a red dwarf, is cooler than the Sun, but the planet's orbit is much closer, such that a year \
+on K2-18b lasts 33 Earth days. According to The Guardian, astronomers were optimistic that NASA's James Webb space \
+telescope — scheduled for launch in 2021 — and the European Space Agency's 2028 ARIEL program, could reveal more \
+about exoplanets like K2-18b."];
+
+    //    Credits: WikiNews, CC BY 2.5 license (https://en.wikinews.org/wiki/Astronomers_find_water_vapour_in_atmosphere_of_exoplanet_K2-18b)
+    let config = StructuredSummaryConfig::new(SummaryFormat::Bullets { max_bullets: None });
+    let output = model.summarize_structured(&input, &config);
+
+    assert_eq!(output.len(), 1);
+    assert_eq!(output[0].attempts, 1);
+    assert_eq!(
+        output[0].summary,
+        Some(StructuredSummary::Bullets(vec![
+            "K2-18b is not too hot and not too cold for liquid water to exist.".to_string(),
+            "This is the first such discovery in a planet in its star's habitable zone.".to_string(),
+            "The presence of water vapour was confirmed in the atmosphere of K2, a planet circling a star in the constellation Leo.".to_string(),
+        ]))
+    );
+
+    Ok(())
+}
+
 #[test]
 #[cfg_attr(not(feature = "all-tests"), ignore)]
 fn bart_zero_shot_classification() -> anyhow::Result<()> {
@@ -276,3 +405,41 @@ fn bart_zero_shot_classification_multilabel() -> anyhow::Result<()> {
     assert!((output[1][3].score - 0.0004).abs() < 1e-4);
     Ok(())
 }
+
+#[test]
+#[cfg_attr(not(feature = "all-tests"), ignore)]
+fn bart_natural_language_inference() -> anyhow::Result<()> {
+    // Set-up model
+    let nli_config = NaturalLanguageInferenceConfig {
+        device: Device::Cpu,
+        ..Default::default()
+    };
+    let mut nli_model = NaturalLanguageInferenceModel::new(nli_config)?;
+    nli_model.set_batch_size(2);
+
+    let pairs = [
+        (
+            "A soccer game with multiple males playing.",
+            "Some men are playing a sport.",
+        ),
+        (
+            "A man inspects the uniform of a figure in some East Asian country.",
+            "The man is sleeping.",
+        ),
+        (
+            "An older and younger man smiling.",
+            "Two men are smiling and laughing at the cats playing on the floor.",
+        ),
+    ];
+    let output = nli_model.predict(&pairs)?;
+
+    assert_eq!(output.len(), 3);
+    assert_eq!(output[0].label, InferenceLabel::Entailment);
+    assert_eq!(output[1].label, InferenceLabel::Contradiction);
+    assert_eq!(output[2].label, InferenceLabel::Neutral);
+    for prediction in output {
+        let total = prediction.entailment + prediction.neutral + prediction.contradiction;
+        assert!((total - 1.0).abs() < 1e-4);
+    }
+    Ok(())
+}
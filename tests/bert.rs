@@ -2,21 +2,25 @@ extern crate anyhow;
 extern crate dirs;
 
 use rust_bert::bert::{
-    BertConfig, BertConfigResources, BertForMaskedLM, BertForMultipleChoice,
-    BertForQuestionAnswering, BertForSequenceClassification, BertForTokenClassification,
+    BertConfig, BertConfigResources, BertEmbeddings, BertForMaskedLM, BertForMultipleChoice,
+    BertForQuestionAnswering, BertForSequenceClassification, BertForTokenClassification, BertModel,
     BertModelResources, BertVocabResources,
 };
-use rust_bert::pipelines::common::ModelType;
+use rust_bert::early_exit::EarlyExitConfig;
+use rust_bert::pipelines::common::{ModelType, TokenizerOption};
 use rust_bert::pipelines::ner::NERModel;
 use rust_bert::pipelines::question_answering::{
     QaInput, QuestionAnsweringConfig, QuestionAnsweringModel,
 };
+use rust_bert::pipelines::shared_encoder::SharedEncoder;
+use rust_bert::pruning::{gradient_head_importance, leave_one_out_head_importance, PrunableModel};
 use rust_bert::resources::{RemoteResource, ResourceProvider};
+use rust_bert::training::datasets::{Collator, DataLoader, JsonlDataset, TokenizingCollator};
 use rust_bert::Config;
 use rust_tokenizers::tokenizer::{BertTokenizer, MultiThreadedTokenizer, TruncationStrategy};
 use rust_tokenizers::vocab::Vocab;
 use std::collections::HashMap;
-use tch::{nn, no_grad, Device, Tensor};
+use tch::{nn, no_grad, Device, Kind, Tensor};
 
 #[test]
 fn bert_masked_lm() -> anyhow::Result<()> {
@@ -406,6 +410,73 @@ fn bert_pre_trained_ner_full_entities() -> anyhow::Result<()> {
     Ok(())
 }
 
+#[test]
+fn bert_pre_trained_ner_unicode_offsets() -> anyhow::Result<()> {
+    //    Set-up model
+    let ner_model = NERModel::new(Default::default())?;
+
+    //    Define input
+    let input = [
+        "👨‍👩‍👧 My name is Amélie and I live in Zürich 🇨🇭.",
+        "東京 is far from Paris ❤️ and London.",
+    ];
+
+    //    Run model
+    let output = ner_model.predict(&input);
+
+    assert_eq!(output.len(), 2);
+    assert!(!output[0].is_empty());
+    assert!(!output[1].is_empty());
+    for (text, entities) in input.iter().zip(output.iter()) {
+        let utf16 = text.encode_utf16().collect::<Vec<u16>>();
+        for entity in entities {
+            let byte_offset = entity.byte_offset;
+            let utf16_offset = entity.utf16_offset;
+            assert_eq!(
+                &text[byte_offset.begin as usize..byte_offset.end as usize],
+                entity.word
+            );
+            assert_eq!(
+                String::from_utf16(&utf16[utf16_offset.begin as usize..utf16_offset.end as usize])?,
+                entity.word
+            );
+        }
+    }
+
+    Ok(())
+}
+
+#[test]
+fn bert_pre_trained_ner_shared_encoder() -> anyhow::Result<()> {
+    //    Set-up models sharing the encoder weights
+    let shared_encoder = SharedEncoder::new(
+        ModelType::Bert,
+        RemoteResource::from_pretrained(BertModelResources::BERT_NER),
+        Device::cuda_if_available(),
+    )?;
+    let ner_model = NERModel::new_with_shared_encoder(Default::default(), &shared_encoder)?;
+    let other_ner_model = NERModel::new_with_shared_encoder(Default::default(), &shared_encoder)?;
+    drop(shared_encoder);
+
+    //    Define input
+    let input = ["My name is Amy. I live in Paris."];
+
+    //    Run models
+    for model in [ner_model, other_ner_model] {
+        let output = model.predict(&input);
+
+        assert_eq!(output[0].len(), 2);
+        assert_eq!(output[0][0].word, "Amy");
+        assert!((output[0][0].score - 0.9986).abs() < 1e-4);
+        assert_eq!(output[0][0].label, "I-PER");
+        assert_eq!(output[0][1].word, "Paris");
+        assert!((output[0][1].score - 0.9986).abs() < 1e-4);
+        assert_eq!(output[0][1].label, "I-LOC");
+    }
+
+    Ok(())
+}
+
 #[test]
 fn bert_question_answering() -> anyhow::Result<()> {
     //    Set-up question answering model
@@ -441,3 +512,312 @@ fn bert_question_answering() -> anyhow::Result<()> {
 
     Ok(())
 }
+
+#[test]
+fn bert_question_answering_unicode_offsets() -> anyhow::Result<()> {
+    //    Set-up question answering model
+    let config = QuestionAnsweringConfig {
+        model_type: ModelType::Bert,
+        model_resource: Box::new(RemoteResource::from_pretrained(BertModelResources::BERT_QA)),
+        config_resource: Box::new(RemoteResource::from_pretrained(
+            BertConfigResources::BERT_QA,
+        )),
+        vocab_resource: Box::new(RemoteResource::from_pretrained(BertVocabResources::BERT_QA)),
+        lower_case: false,
+        strip_accents: Some(false),
+        add_prefix_space: None,
+        device: Device::Cpu,
+        ..Default::default()
+    };
+
+    let qa_model = QuestionAnsweringModel::new(config)?;
+
+    //    Define input
+    let question = String::from("Where does Amy live ?");
+    let context = String::from("😀 Amy 👩‍💻 lives in Amsterdam");
+    let qa_input = QaInput {
+        question,
+        context: context.clone(),
+    };
+
+    let answers = qa_model.predict(&[qa_input], 1, 32);
+
+    assert_eq!(answers.len(), 1usize);
+    assert_eq!(answers[0].len(), 1usize);
+    let answer = &answers[0][0];
+    assert_eq!(answer.answer, "Amsterdam");
+    assert_eq!(&context[answer.byte_start..answer.byte_end], answer.answer);
+    let utf16 = context.encode_utf16().collect::<Vec<u16>>();
+    assert_eq!(
+        String::from_utf16(&utf16[answer.utf16_start..answer.utf16_end])?,
+        answer.answer
+    );
+
+    Ok(())
+}
+
+#[test]
+fn bert_packed_sequences_attention_mask() -> anyhow::Result<()> {
+    //    Resources paths
+    let config_resource = RemoteResource::from_pretrained(BertConfigResources::BERT);
+    let config_path = config_resource.get_local_path()?;
+
+    //    Set-up model
+    let device = Device::Cpu;
+    let vs = nn::VarStore::new(device);
+    let config = BertConfig::from_file(config_path);
+    let bert_model: BertModel<BertEmbeddings> = BertModel::new(&vs.root(), &config);
+
+    //    Define input: two sequences packed in a single row with a block-diagonal attention mask
+    let first_sequence = Tensor::of_slice(&[101i64, 2023, 2003, 102]).unsqueeze(0);
+    let second_sequence = Tensor::of_slice(&[101i64, 2178, 6251, 2182, 102]).unsqueeze(0);
+    let packed_input = Tensor::cat(&[&first_sequence, &second_sequence], 1);
+    let packed_position_ids = Tensor::of_slice(&[0i64, 1, 2, 3, 0, 1, 2, 3, 4]).unsqueeze(0);
+    let block_mask = Tensor::zeros(&[1, 9, 9], (Kind::Int64, device));
+    let _ = block_mask.slice(1, 0, 4, 1).slice(2, 0, 4, 1).fill_(1);
+    let _ = block_mask.slice(1, 4, 9, 1).slice(2, 4, 9, 1).fill_(1);
+
+    //    Forward pass
+    let (first_output, second_output, packed_output, packed_output_4d) = no_grad(|| {
+        let first_output = bert_model.forward_t(
+            Some(&first_sequence),
+            None,
+            None,
+            None,
+            None,
+            None,
+            None,
+            false,
+        )?;
+        let second_output = bert_model.forward_t(
+            Some(&second_sequence),
+            None,
+            None,
+            None,
+            None,
+            None,
+            None,
+            false,
+        )?;
+        let packed_output = bert_model.forward_t(
+            Some(&packed_input),
+            Some(&block_mask),
+            None,
+            Some(&packed_position_ids),
+            None,
+            None,
+            None,
+            false,
+        )?;
+        let packed_output_4d = bert_model.forward_t(
+            Some(&packed_input),
+            Some(&block_mask.unsqueeze(1)),
+            None,
+            Some(&packed_position_ids),
+            None,
+            None,
+            None,
+            false,
+        )?;
+        Ok::<_, anyhow::Error>((first_output, second_output, packed_output, packed_output_4d))
+    })?;
+
+    let first_difference = (packed_output.hidden_state.slice(1, 0, 4, 1)
+        - first_output.hidden_state)
+        .abs()
+        .max();
+    let second_difference = (packed_output.hidden_state.slice(1, 4, 9, 1)
+        - second_output.hidden_state)
+        .abs()
+        .max();
+    let mask_dim_difference = (packed_output.hidden_state - packed_output_4d.hidden_state)
+        .abs()
+        .max();
+
+    assert!(f64::from(first_difference) < 1e-4);
+    assert!(f64::from(second_difference) < 1e-4);
+    assert!(f64::from(mask_dim_difference) < 1e-6);
+
+    Ok(())
+}
+
+#[test]
+fn bert_layer_and_head_pruning() -> anyhow::Result<()> {
+    //    Set-up model with random weights and without dropout
+    let device = Device::Cpu;
+    let vs = nn::VarStore::new(device);
+    let config = BertConfig {
+        hidden_size: 32,
+        intermediate_size: 64,
+        num_attention_heads: 4,
+        num_hidden_layers: 4,
+        attention_probs_dropout_prob: 0.0,
+        hidden_dropout_prob: 0.0,
+        id2label: Some(HashMap::from([
+            (0, "NEGATIVE".into()),
+            (1, "POSITIVE".into()),
+        ])),
+        early_exit: Some(EarlyExitConfig::new(1.1)),
+        ..Default::default()
+    };
+    let mut model = BertForSequenceClassification::new(vs.root(), &config);
+    let input_ids = Tensor::of_slice(&[101i64, 2023, 3185, 2003, 2307, 102]).view([1, 6]);
+
+    //    Retaining all the heads does not change the output
+    let logits =
+        no_grad(|| model.forward_t(Some(&input_ids), None, None, None, None, false)).logits;
+    model.retain_attention_heads(1, &[0, 1, 2, 3])?;
+    let output = no_grad(|| model.forward_t(Some(&input_ids), None, None, None, None, false));
+    assert!(output.logits.allclose(&logits, 1e-5, 1e-5, false));
+
+    //    The exit head of the new last layer replaces the classifier
+    let exit_logits = no_grad(|| model.forward_t(Some(&input_ids), None, None, None, None, true))
+        .exit_logits
+        .unwrap();
+    model.drop_top_layers(1)?;
+    assert_eq!(model.num_layers(), 3);
+    let output = no_grad(|| model.forward_t(Some(&input_ids), None, None, None, None, false));
+    assert_eq!(output.exit_layers.unwrap(), vec![3]);
+    assert!(output.logits.allclose(&exit_logits[2], 1e-5, 1e-5, false));
+
+    //    Heads pruning slices the attention projections
+    model.retain_attention_heads(0, &[1, 3])?;
+    let variables = vs.variables();
+    assert_eq!(
+        variables["bert.encoder.layer.0.attention.self.query.weight"].size(),
+        [16, 32]
+    );
+    assert_eq!(
+        variables["bert.encoder.layer.0.attention.output.dense.weight"].size(),
+        [32, 16]
+    );
+    let output = no_grad(|| model.forward_t(Some(&input_ids), None, None, None, None, false));
+    assert_eq!(output.logits.size(), [1, 2]);
+
+    assert!(model.retain_attention_heads(3, &[0]).is_err());
+    assert!(model.retain_attention_heads(0, &[2]).is_err());
+    assert!(model.drop_top_layers(3).is_err());
+
+    Ok(())
+}
+
+#[test]
+fn bert_head_importance() -> anyhow::Result<()> {
+    //    Set-up model with random weights and without dropout
+    let device = Device::Cpu;
+    let mut vs = nn::VarStore::new(device);
+    let config = BertConfig {
+        hidden_size: 32,
+        intermediate_size: 64,
+        num_attention_heads: 4,
+        num_hidden_layers: 3,
+        attention_probs_dropout_prob: 0.0,
+        hidden_dropout_prob: 0.0,
+        id2label: Some(HashMap::from([
+            (0, "NEGATIVE".into()),
+            (1, "POSITIVE".into()),
+        ])),
+        ..Default::default()
+    };
+    let mut model = BertForSequenceClassification::new(vs.root(), &config);
+    vs.freeze();
+    let batches = vec![
+        (
+            Tensor::of_slice(&[101i64, 2023, 3185, 2003, 2307, 102]).view([1, 6]),
+            Tensor::of_slice(&[1i64]),
+        ),
+        (
+            Tensor::of_slice(&[101i64, 2023, 3185, 2003, 6659, 102]).view([1, 6]),
+            Tensor::of_slice(&[0i64]),
+        ),
+    ];
+
+    let importance =
+        gradient_head_importance(&mut model, &batches, |model, (input_ids, labels)| {
+            model
+                .forward_t(Some(input_ids), None, None, None, None, false)
+                .logits
+                .cross_entropy_for_logits(labels)
+        })?;
+    assert_eq!(importance.scores.len(), 3);
+    assert!(importance
+        .scores
+        .iter()
+        .all(|layer_scores| (layer_scores.len() == 4)
+            & layer_scores
+                .iter()
+                .all(|score| score.is_finite() & (*score >= 0.0))));
+
+    let importance =
+        leave_one_out_head_importance(&mut model, &batches, |model, (input_ids, labels)| {
+            -f64::from(
+                model
+                    .forward_t(Some(input_ids), None, None, None, None, false)
+                    .logits
+                    .cross_entropy_for_logits(labels),
+            )
+        })?;
+    assert_eq!(importance.scores.len(), 3);
+
+    //    Prune half of the heads
+    model.retain_heads_per_layer(&importance.retained_heads(6))?;
+    let num_heads = (0..3)
+        .map(|layer_index| model.num_attention_heads(layer_index))
+        .collect::<Result<Vec<usize>, _>>()?;
+    assert_eq!(num_heads.iter().sum::<usize>(), 6);
+    assert!(num_heads.iter().all(|layer_heads| *layer_heads >= 1));
+    let output = no_grad(|| model.forward_t(Some(&batches[0].0), None, None, None, None, false));
+    assert_eq!(output.logits.size(), [1, 2]);
+
+    Ok(())
+}
+
+#[test]
+fn bert_tokenizing_collator() -> anyhow::Result<()> {
+    let vocab_resource = RemoteResource::from_pretrained(BertVocabResources::BERT);
+    let vocab_path = vocab_resource.get_local_path()?;
+    let tokenizer = TokenizerOption::from_file(
+        ModelType::Bert,
+        vocab_path.to_str().unwrap(),
+        None,
+        false,
+        None,
+        None,
+    )?;
+
+    let data = r#"{"premise": "A man is playing a guitar.", "hypothesis": "A man plays music.", "label": "entailment"}
+{"premise": "Two dogs run.", "hypothesis": "The cat sleeps.", "label": "contradiction"}
+{"premise": "A woman reads.", "hypothesis": "A woman is reading a long book.", "label": "entailment"}"#;
+    let collator = TokenizingCollator::new(&tokenizer, "premise", 16)
+        .with_text_pair_field("hypothesis")
+        .with_label_field("label")
+        .with_label_mapping(HashMap::from([
+            ("contradiction".to_string(), 0),
+            ("entailment".to_string(), 1),
+        ]));
+    let batches = DataLoader::new(JsonlDataset::from_reader(data.as_bytes()), &collator, 2)
+        .collect::<Result<Vec<_>, _>>()?;
+    assert_eq!(batches.len(), 2);
+
+    let batch = &batches[0];
+    let sequence_length = batch.input_ids.size()[1];
+    assert_eq!(batch.attention_mask.size(), vec![2, sequence_length]);
+    assert_eq!(batch.token_type_ids.size(), vec![2, sequence_length]);
+    // The shorter pair is padded, the [SEP] token closing the second text is the last non-padding token
+    let lengths = Vec::<i64>::from(
+        batch
+            .attention_mask
+            .sum_dim_intlist(&[1], false, Kind::Int64),
+    );
+    assert_eq!(lengths[0], sequence_length);
+    assert!(lengths[1] < sequence_length);
+    assert_eq!(batch.input_ids.int64_value(&[1, lengths[1] - 1]), 102);
+    assert_eq!(batch.input_ids.int64_value(&[1, lengths[1]]), 0);
+    assert_eq!(batch.token_type_ids.int64_value(&[1, lengths[1] - 1]), 1);
+    assert_eq!(Vec::<i64>::from(batch.labels.as_ref().unwrap()), vec![1, 0]);
+
+    // The examples are truncated to the maximum length
+    assert!(batches[1].input_ids.size()[1] <= 16);
+    assert!(collator.collate(&[]).is_err());
+    Ok(())
+}
@@ -0,0 +1,161 @@
+use rust_bert::bigbird::{
+    AttentionType, BigBirdConfig, BigBirdForQuestionAnswering, BigBirdForSequenceClassification,
+    BigBirdModel,
+};
+use std::collections::HashMap;
+use tch::{nn, no_grad, Device, Kind, Tensor};
+
+fn small_bigbird_config() -> BigBirdConfig {
+    let mut id2label = HashMap::new();
+    id2label.insert(0, "negative".to_string());
+    id2label.insert(1, "neutral".to_string());
+    id2label.insert(2, "positive".to_string());
+    BigBirdConfig {
+        hidden_size: 16,
+        intermediate_size: 32,
+        num_hidden_layers: 2,
+        num_attention_heads: 2,
+        max_position_embeddings: 64,
+        vocab_size: 100,
+        block_size: 4,
+        num_random_blocks: 1,
+        sep_token_id: Some(3),
+        output_attentions: Some(true),
+        id2label: Some(id2label),
+        ..Default::default()
+    }
+}
+
+#[test]
+fn bigbird_block_sparse_attention() -> anyhow::Result<()> {
+    //    Set-up model
+    let device = Device::Cpu;
+    let vs = nn::VarStore::new(device);
+    let config = small_bigbird_config();
+    let bigbird_model = BigBirdModel::new(&vs.root(), &config, true);
+
+    //    Sequences longer than (5 + 2 * 1) blocks of 4 tokens use the block-sparse attention
+    assert!(!bigbird_model.uses_block_sparse_attention(28));
+    assert!(bigbird_model.uses_block_sparse_attention(29));
+
+    //    Define input: 50 tokens are padded to 13 blocks of 4 tokens
+    let input_ids = Tensor::randint_low(4, 100, &[2, 50], (Kind::Int64, device));
+    let model_output =
+        no_grad(|| bigbird_model.forward_t(Some(&input_ids), None, None, None, None, false))?;
+
+    assert_eq!(model_output.hidden_state.size(), vec![2, 50, 16]);
+    assert_eq!(model_output.pooled_output.unwrap().size(), vec![2, 16]);
+    let all_attentions = model_output.all_attentions.unwrap();
+    assert_eq!(all_attentions.len(), 2);
+    let attention = all_attentions[0].get(0).get(0);
+    assert_eq!(attention.size(), vec![52, 52]);
+
+    //    The attention weights of each query sum to one, the padding is not attended
+    let row_sums = Vec::<f64>::from(attention.narrow(0, 0, 50).sum_dim_intlist(
+        &[1],
+        false,
+        Kind::Float,
+    ));
+    assert!(row_sums.iter().all(|sum| (sum - 1.0).abs() < 1e-4));
+    assert!(f64::from(attention.narrow(1, 50, 2).abs().sum(Kind::Float)) < 1e-4);
+
+    //    The first block attends to all tokens, a middle block only to its window and the global blocks
+    assert!(f64::from(attention.get(0).narrow(0, 24, 4).sum(Kind::Float)) > 0.0);
+    let middle_query = attention.get(25);
+    assert!(f64::from(middle_query.narrow(0, 0, 4).sum(Kind::Float)) > 0.0);
+    assert!(f64::from(middle_query.narrow(0, 20, 12).sum(Kind::Float)) > 0.0);
+    assert!(f64::from(middle_query.narrow(0, 48, 4).sum(Kind::Float)) > 0.0);
+    assert_eq!(
+        f64::from(middle_query.narrow(0, 4, 16).abs().sum(Kind::Float)),
+        0.0
+    );
+    assert_eq!(
+        f64::from(middle_query.narrow(0, 32, 16).abs().sum(Kind::Float)),
+        0.0
+    );
+
+    Ok(())
+}
+
+#[test]
+fn bigbird_full_attention_fallback() -> anyhow::Result<()> {
+    //    Set-up model
+    let device = Device::Cpu;
+    let vs = nn::VarStore::new(device);
+    let config = small_bigbird_config();
+    let bigbird_model = BigBirdForSequenceClassification::new(&vs.root(), &config);
+
+    //    Short sequences are processed with the full attention and are not padded
+    let input_ids = Tensor::randint_low(4, 100, &[3, 10], (Kind::Int64, device));
+    let model_output =
+        no_grad(|| bigbird_model.forward_t(Some(&input_ids), None, None, None, None, false))?;
+    assert_eq!(model_output.logits.size(), vec![3, 3]);
+    assert_eq!(
+        model_output.all_attentions.unwrap()[0].size(),
+        vec![3, 2, 10, 10]
+    );
+
+    //    The block-sparse model and a full attention model with the same weights differ on long sequences
+    let full_config = BigBirdConfig {
+        attention_type: AttentionType::OriginalFull,
+        ..small_bigbird_config()
+    };
+    let mut full_vs = nn::VarStore::new(device);
+    let full_attention_model = BigBirdForSequenceClassification::new(&full_vs.root(), &full_config);
+    full_vs.copy(&vs)?;
+    let short_logits = no_grad(|| {
+        full_attention_model.forward_t(Some(&input_ids), None, None, None, None, false)
+    })?
+    .logits;
+    assert!(f64::from((short_logits - &model_output.logits).abs().max()) < 1e-5);
+
+    let long_input_ids = Tensor::randint_low(4, 100, &[1, 48], (Kind::Int64, device));
+    let sparse_logits =
+        no_grad(|| bigbird_model.forward_t(Some(&long_input_ids), None, None, None, None, false))?
+            .logits;
+    let full_logits = no_grad(|| {
+        full_attention_model.forward_t(Some(&long_input_ids), None, None, None, None, false)
+    })?
+    .logits;
+    assert!(f64::from((sparse_logits - full_logits).abs().max()) > 0.0);
+
+    //    Inputs exceeding the maximum number of positions are rejected
+    let too_long_input_ids = Tensor::ones(&[1, 65], (Kind::Int64, device));
+    assert!(no_grad(|| bigbird_model.forward_t(
+        Some(&too_long_input_ids),
+        None,
+        None,
+        None,
+        None,
+        false
+    ))
+    .is_err());
+
+    Ok(())
+}
+
+#[test]
+fn bigbird_question_answering_masks_question() -> anyhow::Result<()> {
+    //    Set-up model
+    let device = Device::Cpu;
+    let vs = nn::VarStore::new(device);
+    let config = small_bigbird_config();
+    let bigbird_model = BigBirdForQuestionAnswering::new(&vs.root(), &config);
+
+    //    [CLS] question (4 tokens) [SEP] context [SEP]
+    let mut input_ids = vec![2i64, 10, 11, 12, 13, 3];
+    input_ids.extend(20..60);
+    input_ids.push(3);
+    let input_ids = Tensor::of_slice(&input_ids).unsqueeze(0);
+    let model_output =
+        no_grad(|| bigbird_model.forward_t(Some(&input_ids), None, None, None, None, false))?;
+
+    assert_eq!(model_output.start_logits.size(), vec![1, 47]);
+    assert_eq!(model_output.end_logits.size(), vec![1, 47]);
+    let start_logits = Vec::<f64>::from(model_output.start_logits.get(0));
+    assert!(start_logits[0] > -1e5);
+    assert!(start_logits[1..6].iter().all(|logit| *logit < -1e5));
+    assert!(start_logits[6..].iter().all(|logit| *logit > -1e5));
+
+    Ok(())
+}
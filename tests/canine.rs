@@ -0,0 +1,96 @@
+use rust_bert::canine::{
+    CanineConfig, CanineForSequenceClassification, CanineForTokenClassification, CanineModel,
+    CanineTokenizer,
+};
+use std::collections::HashMap;
+use tch::{nn, no_grad, Device, Kind, Tensor};
+
+fn small_canine_config() -> CanineConfig {
+    let mut id2label = HashMap::new();
+    id2label.insert(0, "O".to_string());
+    id2label.insert(1, "B-PER".to_string());
+    id2label.insert(2, "I-PER".to_string());
+    CanineConfig {
+        hidden_size: 16,
+        intermediate_size: 32,
+        num_hidden_layers: 2,
+        num_attention_heads: 2,
+        max_position_embeddings: 64,
+        num_hash_functions: 4,
+        num_hash_buckets: 64,
+        local_transformer_stride: 8,
+        output_hidden_states: Some(true),
+        id2label: Some(id2label),
+        ..Default::default()
+    }
+}
+
+#[test]
+fn canine_downsampling_upsampling() -> anyhow::Result<()> {
+    //    Set-up model
+    let device = Device::Cpu;
+    let vs = nn::VarStore::new(device);
+    let config = small_canine_config();
+    let canine_model = CanineModel::new(&vs.root(), &config, true);
+
+    //    Define input: 22 characters are downsampled to 5 molecules (the last molecule covers the 2 last characters)
+    let input_ids = Tensor::randint_low(32, 0x1F600, &[2, 22], (Kind::Int64, device));
+    let model_output =
+        no_grad(|| canine_model.forward_t(Some(&input_ids), None, None, None, None, false))?;
+
+    assert_eq!(model_output.hidden_state.size(), vec![2, 22, 16]);
+    assert_eq!(model_output.pooled_output.unwrap().size(), vec![2, 16]);
+    let all_hidden_states = model_output.all_hidden_states.unwrap();
+    assert_eq!(all_hidden_states.len(), 2);
+    assert_eq!(all_hidden_states[0].size(), vec![2, 5, 16]);
+
+    //    Inputs shorter than the downsampling rate are padded to a molecule
+    let short_input_ids = input_ids.narrow(1, 0, 3);
+    let model_output =
+        no_grad(|| canine_model.forward_t(Some(&short_input_ids), None, None, None, None, false))?;
+    assert_eq!(model_output.hidden_state.size(), vec![2, 3, 16]);
+
+    //    Inputs exceeding the maximum number of positions are rejected
+    let too_long_input_ids = Tensor::ones(&[1, 65], (Kind::Int64, device));
+    assert!(no_grad(|| canine_model.forward_t(
+        Some(&too_long_input_ids),
+        None,
+        None,
+        None,
+        None,
+        false
+    ))
+    .is_err());
+
+    Ok(())
+}
+
+#[test]
+fn canine_classification_heads() -> anyhow::Result<()> {
+    //    Set-up models
+    let device = Device::Cpu;
+    let vs = nn::VarStore::new(device);
+    let config = small_canine_config();
+    let sequence_model = CanineForSequenceClassification::new(&vs.root() / "sequence", &config);
+    let token_model = CanineForTokenClassification::new(&vs.root() / "token", &config);
+
+    //    Noisy and multilingual inputs are encoded as code points, without unknown tokens
+    let tokenizer = CanineTokenizer::new(64);
+    let (input_ids, mask) = tokenizer.encode_list(
+        &["mEEting w/ jon smth tmrw 👍", "Treffen mit Jürgen"],
+        device,
+    )?;
+    assert_eq!(input_ids.size(), vec![2, 28]);
+
+    let sequence_output = no_grad(|| {
+        sequence_model.forward_t(Some(&input_ids), Some(&mask), None, None, None, false)
+    })?;
+    assert_eq!(sequence_output.logits.size(), vec![2, 3]);
+
+    //    One prediction per character
+    let token_output =
+        no_grad(|| token_model.forward_t(Some(&input_ids), Some(&mask), None, None, None, false))?;
+    assert_eq!(token_output.logits.size(), vec![2, 28, 3]);
+
+    Ok(())
+}
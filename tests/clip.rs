@@ -0,0 +1,94 @@
+use rust_bert::clip::{ClipConfig, ClipModel, ClipTextConfig, ClipVisionConfig};
+use tch::{nn, no_grad, Device, Kind, Tensor};
+
+fn small_clip_config() -> ClipConfig {
+    ClipConfig {
+        text_config: ClipTextConfig {
+            vocab_size: 100,
+            hidden_size: 16,
+            intermediate_size: 32,
+            num_hidden_layers: 2,
+            num_attention_heads: 2,
+            max_position_embeddings: 12,
+            eos_token_id: Some(99),
+            ..Default::default()
+        },
+        vision_config: ClipVisionConfig {
+            hidden_size: 24,
+            intermediate_size: 48,
+            num_hidden_layers: 2,
+            num_attention_heads: 2,
+            image_size: 32,
+            patch_size: 8,
+            ..Default::default()
+        },
+        projection_dim: 8,
+        logit_scale_init_value: Some(2.6592),
+    }
+}
+
+#[test]
+fn clip_output_shapes() -> anyhow::Result<()> {
+    //    Set-up model
+    let device = Device::Cpu;
+    let vs = nn::VarStore::new(device);
+    let config = small_clip_config();
+    let clip_model = ClipModel::new(&vs.root(), &config);
+
+    //    Define input
+    let input_ids = Tensor::of_slice(&[98i64, 5, 6, 7, 99, 98, 8, 9, 99, 99]).view([2, 5]);
+    let attention_mask = Tensor::of_slice(&[1i64, 1, 1, 1, 1, 1, 1, 1, 1, 0]).view([2, 5]);
+    let pixel_values = Tensor::rand(&[3, 3, 32, 32], (Kind::Float, device));
+
+    //    Forward pass
+    let model_output =
+        no_grad(|| clip_model.forward_t(&input_ids, Some(&attention_mask), &pixel_values, false))?;
+
+    assert_eq!(model_output.logits_per_image.size(), vec![3, 2]);
+    assert_eq!(model_output.logits_per_text.size(), vec![2, 3]);
+    assert_eq!(model_output.text_embeds.size(), vec![2, 8]);
+    assert_eq!(model_output.image_embeds.size(), vec![3, 8]);
+    //    Embeddings are normalized and the logits are scaled cosine similarities
+    let norms = Vec::<f64>::from(
+        model_output
+            .image_embeds
+            .norm_scalaropt_dim(2, &[-1], false),
+    );
+    assert!(norms.iter().all(|norm| (norm - 1.0).abs() < 1e-5));
+    let max_logit = model_output.logits_per_image.abs().max().double_value(&[]);
+    assert!(max_logit <= clip_model.logit_scale() + 1e-4);
+
+    //    Images of the wrong size are rejected
+    let pixel_values = Tensor::rand(&[1, 3, 48, 48], (Kind::Float, device));
+    assert!(no_grad(|| clip_model.get_image_features(&pixel_values, false)).is_err());
+
+    Ok(())
+}
+
+#[test]
+fn clip_text_features_ignore_padding() -> anyhow::Result<()> {
+    //    Set-up model
+    let device = Device::Cpu;
+    let vs = nn::VarStore::new(device);
+    let config = small_clip_config();
+    let clip_model = ClipModel::new(&vs.root(), &config);
+
+    //    The same text without padding and padded with end of text tokens
+    let input_ids = Tensor::of_slice(&[98i64, 5, 6, 99]).view([1, 4]);
+    let padded_input_ids = Tensor::of_slice(&[98i64, 5, 6, 99, 99, 99]).view([1, 6]);
+    let attention_mask = Tensor::of_slice(&[1i64, 1, 1, 1, 0, 0]).view([1, 6]);
+
+    let features = no_grad(|| clip_model.get_text_features(&input_ids, None, false))?;
+    let padded_features =
+        no_grad(|| clip_model.get_text_features(&padded_input_ids, Some(&attention_mask), false))?;
+
+    assert_eq!(features.size(), vec![1, 8]);
+    let max_difference = (features - padded_features).abs().max().double_value(&[]);
+    assert!(max_difference < 1e-5);
+
+    //    Inputs longer than the maximum number of positions are rejected
+    let long_input_ids = Tensor::ones(&[1, 13], (Kind::Int64, device));
+    assert!(no_grad(|| clip_model.get_text_features(&long_input_ids, None, false)).is_err());
+
+    Ok(())
+}
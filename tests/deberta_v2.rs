@@ -1,11 +1,18 @@
 use rust_bert::deberta_v2::{
     DebertaV2Config, DebertaV2ConfigResources, DebertaV2ForMaskedLM, DebertaV2ForQuestionAnswering,
-    DebertaV2ForSequenceClassification, DebertaV2ForTokenClassification, DebertaV2VocabResources,
+    DebertaV2ForReplacedTokenDetection, DebertaV2ForSequenceClassification,
+    DebertaV2ForTokenClassification, DebertaV2VocabResources,
 };
-use rust_bert::resources::{RemoteResource, ResourceProvider};
+use rust_bert::pipelines::translation::Language;
+use rust_bert::pipelines::zero_shot_classification::{
+    get_localized_template, ZeroShotClassificationConfig, ZeroShotClassificationModel,
+    ZeroShotClassificationModelType,
+};
+use rust_bert::resources::{LocalResource, RemoteResource, ResourceProvider};
 use rust_bert::Config;
 use rust_tokenizers::tokenizer::{DeBERTaV2Tokenizer, MultiThreadedTokenizer, TruncationStrategy};
 use std::collections::HashMap;
+use std::path::PathBuf;
 use tch::{nn, no_grad, Device, Kind, Tensor};
 
 extern crate anyhow;
@@ -65,6 +72,62 @@ fn deberta_v2_masked_lm() -> anyhow::Result<()> {
     Ok(())
 }
 
+#[test]
+fn deberta_v3_replaced_token_detection() -> anyhow::Result<()> {
+    //    Set-up the heads of the original DeBERTa-v3 checkpoints
+    let config_resource = Box::new(RemoteResource::from_pretrained(
+        DebertaV2ConfigResources::DEBERTA_V3_BASE,
+    ));
+    let config_path = config_resource.get_local_path()?;
+    let device = Device::cuda_if_available();
+    let vs = nn::VarStore::new(device);
+    let mut config = DebertaV2Config::from_file(config_path);
+    config.legacy = Some(false);
+    let masked_lm_model = DebertaV2ForMaskedLM::new(vs.root() / "generator", &config);
+    let rtd_model = DebertaV2ForReplacedTokenDetection::new(vs.root() / "discriminator", &config);
+
+    let variables = vs.variables();
+    assert!(variables.contains_key("generator.lm_predictions.lm_head.dense.weight"));
+    assert!(variables.contains_key("generator.lm_predictions.lm_head.bias"));
+    assert!(!variables.contains_key("generator.cls.predictions.decoder.weight"));
+    assert!(variables.contains_key("discriminator.mask_predictions.LayerNorm.weight"));
+    assert!(variables.contains_key("discriminator.mask_predictions.classifier.weight"));
+
+    //    Generate random input
+    let input_tensor = Tensor::randint(42, &[8, 64], (Kind::Int64, device));
+    let attention_mask = Tensor::ones(&[8, 64], (Kind::Int64, device));
+
+    //    Forward pass
+    let masked_lm_output = no_grad(|| {
+        masked_lm_model.forward_t(
+            Some(&input_tensor),
+            Some(&attention_mask),
+            None,
+            None,
+            None,
+            false,
+        )
+    })?;
+    let rtd_output = no_grad(|| {
+        rtd_model.forward_t(
+            Some(&input_tensor),
+            Some(&attention_mask),
+            None,
+            None,
+            None,
+            false,
+        )
+    })?;
+
+    assert_eq!(
+        masked_lm_output.logits.size(),
+        vec!(8, 64, config.vocab_size)
+    );
+    assert_eq!(rtd_output.logits.size(), vec!(8, 64));
+
+    Ok(())
+}
+
 #[test]
 fn deberta_v2_for_sequence_classification() -> anyhow::Result<()> {
     //    Resources paths
@@ -224,3 +287,48 @@ fn deberta_v2_for_question_answering() -> anyhow::Result<()> {
     assert_eq!(model_output.end_logits.size(), &[1, 16]);
     Ok(())
 }
+
+#[test]
+#[cfg_attr(not(feature = "all-tests"), ignore)]
+fn mdeberta_v3_zero_shot_classification_preset() -> anyhow::Result<()> {
+    //    Set-up model, the weights are converted locally from the PyTorch checkpoint
+    let weights_path = std::env::var("MDEBERTA_V3_BASE_MNLI_XNLI_WEIGHTS")?;
+    let zero_shot_config = ZeroShotClassificationConfig {
+        device: Device::Cpu,
+        ..ZeroShotClassificationConfig::from_model_type(
+            ZeroShotClassificationModelType::MDebertaV3BaseMnliXnli,
+            LocalResource::from(PathBuf::from(weights_path)),
+        )
+    };
+    let zero_shot_model = ZeroShotClassificationModel::new(zero_shot_config)?;
+
+    let input_sentence = "Who are you voting for in 2020?";
+    let input_sequence_2 = "The team won the championship after a penalty shootout.";
+    let candidate_labels = &["politique", "santé publique", "économie", "sport"];
+
+    let output = zero_shot_model.predict(
+        &[input_sentence, input_sequence_2],
+        candidate_labels,
+        Some(get_localized_template(Language::French)),
+        128,
+    );
+
+    assert_eq!(output.len(), 2);
+    assert_eq!(output[0].text, "politique");
+    assert!(output[0].score > 0.5);
+    assert_eq!(output[1].text, "sport");
+    assert!(output[1].score > 0.5);
+
+    //    The entailment probability of the matching label is high, low for the others
+    let output = zero_shot_model.predict_multilabel(
+        &[input_sentence],
+        candidate_labels,
+        Some(get_localized_template(Language::French)),
+        128,
+    );
+
+    assert!(output[0][0].score > 0.5);
+    assert!(output[0][3].score < 0.5);
+
+    Ok(())
+}
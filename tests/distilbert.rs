@@ -1,16 +1,22 @@
 use rust_bert::distilbert::{
     DistilBertConfig, DistilBertConfigResources, DistilBertForQuestionAnswering,
-    DistilBertForTokenClassification, DistilBertModelMaskedLM, DistilBertModelResources,
-    DistilBertVocabResources,
+    DistilBertForTokenClassification, DistilBertModelClassifier, DistilBertModelMaskedLM,
+    DistilBertModelResources, DistilBertVocabResources,
 };
+use rust_bert::early_exit::EarlyExitConfig;
+use rust_bert::pipelines::common::Pipeline;
+use rust_bert::pipelines::input_length::InputLengthPolicy;
 use rust_bert::pipelines::question_answering::{QaInput, QuestionAnsweringModel};
+use rust_bert::pipelines::sequence_classification::{
+    SequenceClassificationConfig, SequenceClassificationModel,
+};
 use rust_bert::pipelines::sentiment::{SentimentModel, SentimentPolarity};
 use rust_bert::resources::{RemoteResource, ResourceProvider};
-use rust_bert::Config;
+use rust_bert::{Config, RustBertError};
 use rust_tokenizers::tokenizer::{BertTokenizer, MultiThreadedTokenizer, TruncationStrategy};
 use rust_tokenizers::vocab::Vocab;
 use std::collections::HashMap;
-use tch::{nn, no_grad, Device, Tensor};
+use tch::{nn, no_grad, Device, Kind, Tensor};
 
 extern crate anyhow;
 
@@ -39,6 +45,138 @@ fn distilbert_sentiment_classifier() -> anyhow::Result<()> {
     Ok(())
 }
 
+#[test]
+fn distilbert_sequence_classification_memory_budget() -> anyhow::Result<()> {
+    //    Set-up classifier
+    let mut model = SequenceClassificationModel::new(Default::default())?;
+    let statistics = model.memory_statistics(2, 16);
+    assert!(statistics.weights > 200_000_000);
+    assert!(statistics.activations_peak > 0);
+    assert_eq!(statistics.kv_cache, 0);
+    assert!(model.memory_statistics(4, 16).activations_peak > statistics.activations_peak);
+
+    //    Define input
+    let input = ["This is a great movie!", "This movie is terrible."];
+
+    //    Run model
+    model.set_memory_budget(Some(statistics.weights));
+    assert!(matches!(
+        model.run(&input),
+        Err(RustBertError::MemoryBudgetExceededError(_))
+    ));
+    model.set_memory_budget(Some(statistics.total() * 2));
+    assert_eq!(model.run(&input)?.len(), 2);
+
+    Ok(())
+}
+
+#[test]
+fn distilbert_sequence_classification_input_length_policy() -> anyhow::Result<()> {
+    //    Set-up classifier
+    let mut model = SequenceClassificationModel::new(Default::default())?;
+
+    //    Define input
+    let long_input = "This movie is great! ".repeat(200);
+    let input = ["This movie is terrible.", long_input.as_str()];
+
+    //    Run model
+    model.set_input_length_policy(InputLengthPolicy::Error);
+    match model.run(&input) {
+        Err(RustBertError::InputTooLongError(message)) => {
+            assert!(message.starts_with("input 1 has "));
+            assert!(message.contains("exceeding the maximum length of 512 tokens"));
+        }
+        _ => panic!("Expected an InputTooLongError"),
+    }
+    assert_eq!(model.run(&input[..1])?.len(), 1);
+
+    model.set_input_length_policy(InputLengthPolicy::Chunk);
+    let output = model.run(&input)?;
+    assert_eq!(output.len(), 2);
+    assert_eq!(output[0].text, "NEGATIVE");
+    assert_eq!(output[1].text, "POSITIVE");
+    assert_eq!(output[1].sentence, 1);
+
+    Ok(())
+}
+
+#[test]
+fn distilbert_sequence_classification_intermediate_outputs() -> anyhow::Result<()> {
+    //    Set-up classifier
+    let config = SequenceClassificationConfig {
+        output_hidden_states: true,
+        output_attentions: true,
+        ..Default::default()
+    };
+    let model = SequenceClassificationModel::new(config)?;
+
+    //    Define input
+    let input = ["This is a great movie!", "This movie is terrible."];
+
+    //    Run model
+    let (labels, intermediate_outputs) = model.predict_with_intermediate_outputs(&input);
+
+    assert_eq!(labels.len(), 2);
+    assert_eq!(intermediate_outputs.len(), 2);
+    for (input_index, sequence_length) in [(0, 8), (1, 7)] {
+        let outputs = &intermediate_outputs[input_index];
+        assert_eq!(outputs.input_index, input_index);
+        let all_hidden_states = outputs.all_hidden_states.as_ref().unwrap();
+        assert_eq!(all_hidden_states.len(), 6);
+        assert_eq!(all_hidden_states[5].size(), [sequence_length, 768]);
+        let all_attentions = outputs.all_attentions.as_ref().unwrap();
+        assert_eq!(all_attentions.len(), 6);
+        assert_eq!(all_attentions[5].size(), [12, 8, 8]);
+    }
+
+    Ok(())
+}
+
+#[test]
+fn distilbert_sequence_classification_early_exit() -> anyhow::Result<()> {
+    //    Set-up model with random weights
+    let device = Device::Cpu;
+    let vs = nn::VarStore::new(device);
+    let mut config = DistilBertConfig {
+        n_layers: 4,
+        id2label: Some(HashMap::from([
+            (0, "NEGATIVE".into()),
+            (1, "POSITIVE".into()),
+        ])),
+        ..Default::default()
+    };
+    let input_ids = Tensor::of_slice(&[
+        101i64, 2023, 3185, 2003, 2307, 102, 101, 2023, 3185, 102, 0, 0,
+    ])
+    .view([2, 6]);
+    let mask = input_ids.ne(0).to_kind(Kind::Int64);
+
+    //    All the inputs exit at the first allowed layer with a zero threshold
+    config.early_exit = Some(EarlyExitConfig {
+        confidence_threshold: 0.0,
+        min_layers: Some(2),
+    });
+    let model = DistilBertModelClassifier::new(vs.root() / "early", &config);
+    let output = no_grad(|| model.forward_t(Some(&input_ids), Some(&mask), None, false))?;
+    assert_eq!(output.logits.size(), [2, 2]);
+    assert_eq!(output.exit_layers.unwrap(), vec![2, 2]);
+
+    //    No input exits before the last layer with an unreachable threshold
+    config.early_exit = Some(EarlyExitConfig::new(1.1));
+    let model = DistilBertModelClassifier::new(vs.root() / "full", &config);
+    let output = no_grad(|| model.forward_t(Some(&input_ids), Some(&mask), None, false))?;
+    assert_eq!(output.logits.size(), [2, 2]);
+    assert_eq!(output.exit_layers.unwrap(), vec![4, 4]);
+
+    //    Training returns the logits of all the exit heads
+    let output = model.forward_t(Some(&input_ids), Some(&mask), None, true)?;
+    let exit_logits = output.exit_logits.unwrap();
+    assert_eq!(exit_logits.len(), 3);
+    assert_eq!(exit_logits[0].size(), [2, 2]);
+
+    Ok(())
+}
+
 #[test]
 fn distilbert_masked_lm() -> anyhow::Result<()> {
     //    Resources paths
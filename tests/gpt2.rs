@@ -6,13 +6,23 @@ use rust_bert::pipelines::common::ModelType;
 use rust_bert::pipelines::conversation::{
     ConversationConfig, ConversationManager, ConversationModel,
 };
+use rust_bert::pipelines::generation_utils::private_generation_utils::PrivateLanguageGenerator;
 use rust_bert::pipelines::generation_utils::{
-    Cache, GenerateConfig, GenerateOptions, LMHeadModel, LanguageGenerator,
+    Cache, GenerateConfig, GenerateOptions, ItemGenerateOptions, LMHeadModel, LanguageGenerator,
+    NoRepeatNgramScope,
+};
+use rust_bert::pipelines::grammar::Grammar;
+use rust_bert::pipelines::logits_processor::LogitsProcessor;
+use rust_bert::pipelines::prompt_classification::{
+    PromptClassificationConfig, PromptClassificationModel, PromptLabel,
 };
 use rust_bert::pipelines::text_generation::{TextGenerationConfig, TextGenerationModel};
+use rust_bert::pipelines::watermark::Watermark;
 use rust_bert::resources::{RemoteResource, ResourceProvider};
 use rust_bert::Config;
 use rust_tokenizers::tokenizer::{Gpt2Tokenizer, Tokenizer, TruncationStrategy};
+use serde_json::json;
+use std::collections::HashMap;
 use tch::{nn, Device, Tensor};
 
 #[test]
@@ -728,6 +738,1127 @@ fn gpt2_beam_search_token_scores() -> anyhow::Result<()> {
     Ok(())
 }
 
+#[test]
+fn gpt2_greedy_echo_prompt_scores() -> anyhow::Result<()> {
+    //    Resources definition
+    let config_resource = Box::new(RemoteResource::from_pretrained(Gpt2ConfigResources::GPT2));
+    let vocab_resource = Box::new(RemoteResource::from_pretrained(Gpt2VocabResources::GPT2));
+    let merges_resource = Box::new(RemoteResource::from_pretrained(Gpt2MergesResources::GPT2));
+    let model_resource = Box::new(RemoteResource::from_pretrained(Gpt2ModelResources::GPT2));
+
+    let generate_config = GenerateConfig {
+        max_length: 16,
+        model_resource,
+        config_resource,
+        vocab_resource,
+        merges_resource,
+        do_sample: false,
+        num_beams: 1,
+        device: Device::Cpu,
+        ..Default::default()
+    };
+    let model = GPT2Generator::new(generate_config)?;
+
+    let input_context_1 = "Hello, my name is";
+    let input_context_2 = "It is a beautiful";
+
+    let generate_options = GenerateOptions {
+        echo: true,
+        ..Default::default()
+    };
+
+    let output = model.generate_indices(
+        Some(&[input_context_1, input_context_2]),
+        Some(generate_options),
+    );
+
+    assert_eq!(output.len(), 2);
+    assert_eq!(
+        output[0].indices,
+        vec![15496, 11, 616, 1438, 318, 1757, 13, 314, 1101, 257, 6260, 11, 290, 314, 1101, 3597,]
+    );
+    let prompt_scores_1 = output[0].prompt_token_scores.as_ref().unwrap();
+    let prompt_scores_2 = output[1].prompt_token_scores.as_ref().unwrap();
+    assert_eq!(prompt_scores_1.len(), 4);
+    assert_eq!(prompt_scores_2.len(), 3);
+    assert!(prompt_scores_1
+        .iter()
+        .chain(prompt_scores_2.iter())
+        .all(|score| *score <= 0.0));
+
+    Ok(())
+}
+
+#[test]
+fn gpt2_score_sequences() -> anyhow::Result<()> {
+    //    Resources definition
+    let config_resource = Box::new(RemoteResource::from_pretrained(Gpt2ConfigResources::GPT2));
+    let vocab_resource = Box::new(RemoteResource::from_pretrained(Gpt2VocabResources::GPT2));
+    let merges_resource = Box::new(RemoteResource::from_pretrained(Gpt2MergesResources::GPT2));
+    let model_resource = Box::new(RemoteResource::from_pretrained(Gpt2ModelResources::GPT2));
+
+    let generate_config = GenerateConfig {
+        max_length: 16,
+        model_resource,
+        config_resource,
+        vocab_resource,
+        merges_resource,
+        do_sample: false,
+        num_beams: 1,
+        device: Device::Cpu,
+        ..Default::default()
+    };
+    let model = GPT2Generator::new(generate_config)?;
+
+    let echo_output = model.generate_indices(
+        Some(&["Hello, my name is"]),
+        Some(GenerateOptions {
+            echo: true,
+            ..Default::default()
+        }),
+    );
+    let prompt_scores = echo_output[0].prompt_token_scores.as_ref().unwrap();
+
+    let scores = model.score_sequences(
+        &[
+            "Hello, my",
+            "The capital of France is",
+            "The capital of France is",
+        ],
+        &[" name is", " Paris", " Berlin"],
+    )?;
+    assert_eq!(scores.len(), 3);
+    assert_eq!(scores[0].len(), 2);
+    assert!((scores[0][0] - prompt_scores[2]).abs() < 1e-4);
+    assert!((scores[0][1] - prompt_scores[3]).abs() < 1e-4);
+    assert!(scores[1][0] > scores[2][0]);
+
+    Ok(())
+}
+
+#[test]
+fn gpt2_prompt_classification() -> anyhow::Result<()> {
+    let model_config = TextGenerationConfig {
+        model_type: ModelType::GPT2,
+        model_resource: Box::new(RemoteResource::from_pretrained(Gpt2ModelResources::GPT2)),
+        config_resource: Box::new(RemoteResource::from_pretrained(Gpt2ConfigResources::GPT2)),
+        vocab_resource: Box::new(RemoteResource::from_pretrained(Gpt2VocabResources::GPT2)),
+        merges_resource: Box::new(RemoteResource::from_pretrained(Gpt2MergesResources::GPT2)),
+        device: Device::Cpu,
+        ..Default::default()
+    };
+    let config = PromptClassificationConfig::new(
+        model_config,
+        "Question: What is the capital of {}?\nAnswer:",
+        vec![
+            PromptLabel::new("Paris", &[" Paris"]),
+            PromptLabel::new("Berlin", &[" Berlin"]),
+        ],
+    );
+    let model = PromptClassificationModel::new(config)?;
+
+    let output = model.predict_all_labels(&["France", "Germany"])?;
+    assert_eq!(output.len(), 2);
+    assert_eq!(output[0].len(), 2);
+    assert!((output[0][0].score + output[0][1].score - 1.0).abs() < 1e-6);
+    assert!(output[0][0].score > output[0][1].score);
+    assert!(output[1][1].score > output[1][0].score);
+
+    let output = model.predict(&["France"])?;
+    assert_eq!(output[0].text, "Paris");
+    assert_eq!(output[0].sentence, 0);
+
+    Ok(())
+}
+
+#[test]
+fn gpt2_greedy_token_healing() -> anyhow::Result<()> {
+    //    Resources definition
+    let config_resource = Box::new(RemoteResource::from_pretrained(Gpt2ConfigResources::GPT2));
+    let vocab_resource = Box::new(RemoteResource::from_pretrained(Gpt2VocabResources::GPT2));
+    let merges_resource = Box::new(RemoteResource::from_pretrained(Gpt2MergesResources::GPT2));
+    let model_resource = Box::new(RemoteResource::from_pretrained(Gpt2ModelResources::GPT2));
+
+    let generate_config = GenerateConfig {
+        max_length: 24,
+        model_resource,
+        config_resource,
+        vocab_resource,
+        merges_resource,
+        do_sample: false,
+        num_beams: 1,
+        device: Device::Cpu,
+        ..Default::default()
+    };
+    let model = GPT2Generator::new(generate_config)?;
+
+    let input_context_1 = "The link to the documentation is http:";
+    let input_context_2 = "My favourite programming language is Ru";
+
+    let generate_options = GenerateOptions {
+        token_healing: true,
+        ..Default::default()
+    };
+
+    let output = model.generate(
+        Some(&[input_context_1, input_context_2]),
+        Some(generate_options),
+    );
+
+    assert_eq!(output.len(), 2);
+    assert!(output[0].text.starts_with(input_context_1));
+    assert!(output[1].text.starts_with(input_context_2));
+    assert!(output[0].text.len() > input_context_1.len());
+    assert!(output[1].text.len() > input_context_2.len());
+
+    // The last prompt token is regenerated as a token extending it, while it is kept as-is without healing
+    let tokenizer = model.get_tokenizer();
+    let mut healed_tokens = vec![];
+    for input_context in [input_context_1, input_context_2] {
+        let prompt_ids = tokenizer.convert_tokens_to_ids(&tokenizer.tokenize(input_context));
+        let trimmed_position = prompt_ids.len() - 1;
+        let trimmed_token = tokenizer.decode(&prompt_ids[trimmed_position..], false, false);
+
+        let healed_output = model.generate_indices(
+            Some(&[input_context]),
+            Some(GenerateOptions {
+                token_healing: true,
+                ..Default::default()
+            }),
+        );
+        let unhealed_output = model.generate_indices(Some(&[input_context]), None);
+
+        let healed_ids = &healed_output[0].indices;
+        let unhealed_ids = &unhealed_output[0].indices;
+        assert_eq!(
+            healed_ids[..trimmed_position],
+            prompt_ids[..trimmed_position]
+        );
+        assert_eq!(unhealed_ids[..prompt_ids.len()], prompt_ids[..]);
+        let healed_token = tokenizer.decode(&[healed_ids[trimmed_position]], false, false);
+        assert!(healed_token.starts_with(&trimmed_token));
+        healed_tokens.push(healed_token);
+    }
+    // `:` is healed into the `://` token, completing the URL scheme
+    assert_eq!(healed_tokens[0], "://");
+
+    Ok(())
+}
+
+#[test]
+fn gpt2_generation_telemetry() -> anyhow::Result<()> {
+    //    Resources definition
+    let config_resource = Box::new(RemoteResource::from_pretrained(Gpt2ConfigResources::GPT2));
+    let vocab_resource = Box::new(RemoteResource::from_pretrained(Gpt2VocabResources::GPT2));
+    let merges_resource = Box::new(RemoteResource::from_pretrained(Gpt2MergesResources::GPT2));
+    let model_resource = Box::new(RemoteResource::from_pretrained(Gpt2ModelResources::GPT2));
+
+    let generate_config = GenerateConfig {
+        max_length: 16,
+        model_resource,
+        config_resource,
+        vocab_resource,
+        merges_resource,
+        do_sample: false,
+        num_beams: 1,
+        device: Device::Cpu,
+        ..Default::default()
+    };
+    let model = GPT2Generator::new(generate_config)?;
+
+    let input_context_1 = "Hello, my name is";
+    let input_context_2 = "It is a beautiful";
+
+    let generate_options = GenerateOptions {
+        output_telemetry: true,
+        ..Default::default()
+    };
+
+    let output = model.generate_indices(
+        Some(&[input_context_1, input_context_2]),
+        Some(generate_options),
+    );
+
+    assert_eq!(output.len(), 2);
+    let telemetry_1 = output[0].telemetry.as_ref().unwrap();
+    let telemetry_2 = output[1].telemetry.as_ref().unwrap();
+    assert_eq!(telemetry_1.prompt_tokens, 5);
+    assert_eq!(telemetry_2.prompt_tokens, 4);
+    assert_eq!(telemetry_1.generated_tokens, 11);
+    assert_eq!(telemetry_2.generated_tokens, 11);
+    assert_eq!(telemetry_1.decode_ms.len(), 10);
+    assert!(telemetry_1.prefill_ms > 0.0);
+    assert!(telemetry_1.max_cache_bytes > 0);
+    assert_eq!(telemetry_1.max_cache_bytes, telemetry_2.max_cache_bytes);
+
+    Ok(())
+}
+
+#[test]
+fn gpt2_prefill_decode_step() -> anyhow::Result<()> {
+    //    Resources definition
+    let config_resource = Box::new(RemoteResource::from_pretrained(Gpt2ConfigResources::GPT2));
+    let vocab_resource = Box::new(RemoteResource::from_pretrained(Gpt2VocabResources::GPT2));
+    let merges_resource = Box::new(RemoteResource::from_pretrained(Gpt2MergesResources::GPT2));
+    let model_resource = Box::new(RemoteResource::from_pretrained(Gpt2ModelResources::GPT2));
+
+    let generate_config = GenerateConfig {
+        model_resource,
+        config_resource,
+        vocab_resource,
+        merges_resource,
+        device: Device::Cpu,
+        ..Default::default()
+    };
+    let model = GPT2Generator::new(generate_config)?;
+
+    //    "Hello, my name is"
+    let input_ids = Tensor::of_slice(&[15496i64, 11, 616, 1438, 318]).view([1, -1]);
+    let attention_mask = input_ids.ones_like();
+
+    //    The prompt is processed in a single forward pass
+    let prefill_output = model.prefill(&input_ids, None, &attention_mask)?;
+    assert_eq!(prefill_output.lm_logits.size(), vec![1, 5, 50257]);
+
+    //    Prefill of the first tokens followed by a decoding step for the last token
+    let partial_output = model.prefill(
+        &input_ids.narrow(1, 0, 4),
+        None,
+        &attention_mask.narrow(1, 0, 4),
+    )?;
+    let decode_output =
+        model.decode_step(&input_ids, None, partial_output.cache, &attention_mask)?;
+    assert_eq!(decode_output.lm_logits.size(), vec![1, 1, 50257]);
+    let max_difference = (prefill_output.lm_logits.select(1, -1)
+        - decode_output.lm_logits.select(1, -1))
+    .abs()
+    .max()
+    .double_value(&[]);
+    assert!(max_difference < 1e-3);
+
+    Ok(())
+}
+
+#[test]
+fn gpt2_chunked_prefill() -> anyhow::Result<()> {
+    let create_model = |prefill_chunk_size| {
+        //    Resources definition
+        let config_resource = Box::new(RemoteResource::from_pretrained(Gpt2ConfigResources::GPT2));
+        let vocab_resource = Box::new(RemoteResource::from_pretrained(Gpt2VocabResources::GPT2));
+        let merges_resource = Box::new(RemoteResource::from_pretrained(Gpt2MergesResources::GPT2));
+        let model_resource = Box::new(RemoteResource::from_pretrained(Gpt2ModelResources::GPT2));
+
+        let generate_config = GenerateConfig {
+            max_length: 16,
+            model_resource,
+            config_resource,
+            vocab_resource,
+            merges_resource,
+            do_sample: false,
+            num_beams: 1,
+            prefill_chunk_size,
+            device: Device::Cpu,
+            ..Default::default()
+        };
+        GPT2Generator::new(generate_config)
+    };
+    let model = create_model(None)?;
+    let chunked_model = create_model(Some(2))?;
+
+    //    "Hello, my name is"
+    let input_ids = Tensor::of_slice(&[15496i64, 11, 616, 1438, 318]).view([1, -1]);
+    let attention_mask = input_ids.ones_like();
+    let output = model.prefill(&input_ids, None, &attention_mask)?;
+    let chunked_output = chunked_model.prefill(&input_ids, None, &attention_mask)?;
+    assert_eq!(chunked_output.lm_logits.size(), vec![1, 5, 50257]);
+    let max_difference = (output.lm_logits - chunked_output.lm_logits)
+        .abs()
+        .max()
+        .double_value(&[]);
+    assert!(max_difference < 1e-3);
+
+    //    Prompts of different lengths (left-padded)
+    let input_context_1 = "Hello, my name is";
+    let input_context_2 = "It is a beautiful";
+    let output = model.generate_indices(Some(&[input_context_1, input_context_2]), None);
+    let chunked_output =
+        chunked_model.generate_indices(Some(&[input_context_1, input_context_2]), None);
+    assert_eq!(output[0].indices, chunked_output[0].indices);
+    assert_eq!(output[1].indices, chunked_output[1].indices);
+
+    Ok(())
+}
+
+#[test]
+fn gpt2_prefix_cache() -> anyhow::Result<()> {
+    //    Resources definition
+    let config_resource = Box::new(RemoteResource::from_pretrained(Gpt2ConfigResources::GPT2));
+    let vocab_resource = Box::new(RemoteResource::from_pretrained(Gpt2VocabResources::GPT2));
+    let merges_resource = Box::new(RemoteResource::from_pretrained(Gpt2MergesResources::GPT2));
+    let model_resource = Box::new(RemoteResource::from_pretrained(Gpt2ModelResources::GPT2));
+
+    let generate_config = GenerateConfig {
+        max_length: 16,
+        model_resource,
+        config_resource,
+        vocab_resource,
+        merges_resource,
+        do_sample: false,
+        num_beams: 1,
+        device: Device::Cpu,
+        ..Default::default()
+    };
+    let model = GPT2Generator::new(generate_config)?;
+
+    let prefix_cache = model.encode_prefix("Hello, my")?;
+    assert_eq!(prefix_cache.token_ids, vec![15496, 11, 616]);
+    let generate_options = GenerateOptions {
+        prefix_cache: Some(&prefix_cache),
+        ..Default::default()
+    };
+
+    //    The cache is reused across calls, the generation matches the generation from the full prompt
+    let tokenizer = model.get_tokenizer();
+    for prompt in [" name is", " favourite color is"] {
+        let output = model.generate_indices(Some(&[prompt]), Some(generate_options));
+        let mut input_ids = prefix_cache.token_ids.clone();
+        input_ids.extend(tokenizer.convert_tokens_to_ids(&tokenizer.tokenize(prompt)));
+        let expected_output = model.generate_from_ids_and_past(
+            Tensor::of_slice(&input_ids).view([1, -1]),
+            None,
+            None,
+        );
+        assert_eq!(output.len(), 1);
+        assert_eq!(output[0].indices, expected_output[0].indices);
+    }
+
+    Ok(())
+}
+
+#[test]
+fn gpt2_generation_item_options() -> anyhow::Result<()> {
+    //    Resources definition
+    let config_resource = Box::new(RemoteResource::from_pretrained(Gpt2ConfigResources::GPT2));
+    let vocab_resource = Box::new(RemoteResource::from_pretrained(Gpt2VocabResources::GPT2));
+    let merges_resource = Box::new(RemoteResource::from_pretrained(Gpt2MergesResources::GPT2));
+    let model_resource = Box::new(RemoteResource::from_pretrained(Gpt2ModelResources::GPT2));
+
+    let generate_config = GenerateConfig {
+        max_length: 16,
+        model_resource,
+        config_resource,
+        vocab_resource,
+        merges_resource,
+        do_sample: false,
+        num_beams: 1,
+        device: Device::Cpu,
+        ..Default::default()
+    };
+    let model = GPT2Generator::new(generate_config)?;
+
+    let input_context_1 = "Hello, my name is";
+    let input_context_2 = "It is a beautiful";
+    let stop_sequences = ["."];
+    let item_options = [
+        ItemGenerateOptions {
+            stop_sequences: Some(&stop_sequences),
+            ..Default::default()
+        },
+        ItemGenerateOptions {
+            max_new_tokens: Some(3),
+            ..Default::default()
+        },
+    ];
+    let generate_options = GenerateOptions {
+        item_options: Some(&item_options),
+        ..Default::default()
+    };
+
+    let reference_output = model.generate_indices(Some(&[input_context_1, input_context_2]), None);
+    let output = model.generate_indices(
+        Some(&[input_context_1, input_context_2]),
+        Some(generate_options),
+    );
+    assert_eq!(output.len(), 2);
+    //    The first sequence stops after the token completing the stop sequence
+    assert_eq!(output[0].indices, vec![15496, 11, 616, 1438, 318, 1757, 13]);
+    //    The second sequence (left-padded prompt of 5 tokens) stops after 3 new tokens
+    assert_eq!(output[1].indices, reference_output[1].indices[..8].to_vec());
+
+    let output = model.generate(
+        Some(&[input_context_1, input_context_2]),
+        Some(generate_options),
+    );
+    assert_eq!(output[0].text, "Hello, my name is John");
+
+    Ok(())
+}
+
+#[test]
+fn gpt2_generation_no_repeat_ngram_scope() -> anyhow::Result<()> {
+    //    Resources definition
+    let config_resource = Box::new(RemoteResource::from_pretrained(Gpt2ConfigResources::GPT2));
+    let vocab_resource = Box::new(RemoteResource::from_pretrained(Gpt2VocabResources::GPT2));
+    let merges_resource = Box::new(RemoteResource::from_pretrained(Gpt2MergesResources::GPT2));
+    let model_resource = Box::new(RemoteResource::from_pretrained(Gpt2ModelResources::GPT2));
+
+    let generate_config = GenerateConfig {
+        max_length: 64,
+        model_resource,
+        config_resource,
+        vocab_resource,
+        merges_resource,
+        do_sample: false,
+        num_beams: 1,
+        no_repeat_ngram_size: 3,
+        device: Device::Cpu,
+        ..Default::default()
+    };
+    let model = GPT2Generator::new(generate_config)?;
+    let input_context = "The city council met on Monday. The city council";
+    let prompt_length = model.get_tokenizer().tokenize(input_context).len();
+
+    //    Returns true if the trigram ending at `position` appears earlier in the segment starting at `scope_start`
+    let is_repeated = |indices: &[i64], position: usize, scope_start: usize| {
+        let ngram = &indices[position - 2..=position];
+        (scope_start..(position - 2)).any(|start| &indices[start..start + 3] == ngram)
+    };
+
+    //    Sliding window of 16 tokens
+    let generate_options = GenerateOptions {
+        no_repeat_ngram_scope: Some(NoRepeatNgramScope::Window(16)),
+        ..Default::default()
+    };
+    let output = model.generate_indices(Some(&[input_context]), Some(generate_options));
+    let indices = &output[0].indices;
+    assert_eq!(indices.len(), 64);
+    for position in prompt_length..indices.len() {
+        assert!(!is_repeated(indices, position, position.saturating_sub(16)));
+    }
+
+    //    Sentences
+    let generate_options = GenerateOptions {
+        no_repeat_ngram_scope: Some(NoRepeatNgramScope::Sentence),
+        ..Default::default()
+    };
+    let output = model.generate_indices(Some(&[input_context]), Some(generate_options));
+    let indices = &output[0].indices;
+    let mut sentence_start = 0;
+    for position in 0..indices.len() {
+        if (position >= prompt_length) & (position >= sentence_start + 2) {
+            assert!(!is_repeated(indices, position, sentence_start));
+        }
+        let token = model
+            .get_tokenizer()
+            .decode(&[indices[position]], true, false);
+        if token.contains('\n')
+            || token
+                .trim_end_matches(|c: char| {
+                    c.is_whitespace() || matches!(c, '"' | '\'' | ')' | ']')
+                })
+                .ends_with(|c: char| matches!(c, '.' | '!' | '?'))
+        {
+            sentence_start = position + 1;
+        }
+    }
+    Ok(())
+}
+
+#[test]
+fn gpt2_generation_stream() -> anyhow::Result<()> {
+    //    Resources definition
+    let config_resource = Box::new(RemoteResource::from_pretrained(Gpt2ConfigResources::GPT2));
+    let vocab_resource = Box::new(RemoteResource::from_pretrained(Gpt2VocabResources::GPT2));
+    let merges_resource = Box::new(RemoteResource::from_pretrained(Gpt2MergesResources::GPT2));
+    let model_resource = Box::new(RemoteResource::from_pretrained(Gpt2ModelResources::GPT2));
+
+    let generate_config = GenerateConfig {
+        max_length: 16,
+        model_resource,
+        config_resource,
+        vocab_resource,
+        merges_resource,
+        do_sample: false,
+        num_beams: 1,
+        device: Device::Cpu,
+        ..Default::default()
+    };
+    let model = GPT2Generator::new(generate_config)?;
+
+    let input_context_1 = "Hello, my name is";
+    let input_context_2 = "It is a beautiful";
+
+    let mut streamed_tokens = vec![vec![], vec![]];
+    let mut streamed_texts = vec![String::new(), String::new()];
+    let output = model.generate_indices_stream(
+        Some(&[input_context_1, input_context_2]),
+        None,
+        |token| {
+            streamed_tokens[token.sequence_index].push(token.token_id);
+            streamed_texts[token.sequence_index].push_str(&token.text);
+            true
+        },
+    )?;
+
+    assert_eq!(output.len(), 2);
+    assert_eq!(
+        output[0].indices,
+        vec![15496, 11, 616, 1438, 318, 1757, 13, 314, 1101, 257, 6260, 11, 290, 314, 1101, 3597,]
+    );
+    assert_eq!(
+        streamed_tokens[0],
+        vec![1757, 13, 314, 1101, 257, 6260, 11, 290, 314, 1101, 3597]
+    );
+    assert_eq!(streamed_tokens[1], output[1].indices[5..].to_vec());
+    let tokenizer = model.get_tokenizer();
+    assert_eq!(
+        streamed_texts[0],
+        tokenizer.decode(&output[0].indices[5..], true, true)
+    );
+
+    // The generation stops when the callback returns false
+    let mut streamed_count = 0;
+    let output = model.generate_indices_stream(Some(&[input_context_1]), None, |_| {
+        streamed_count += 1;
+        streamed_count < 3
+    })?;
+    assert_eq!(output[0].indices.len(), 8);
+
+    // Beam search cannot be streamed
+    let generate_options = GenerateOptions {
+        num_beams: Some(2),
+        ..Default::default()
+    };
+    assert!(model
+        .generate_stream(Some(&[input_context_1]), Some(generate_options), |_| true)
+        .is_err());
+
+    Ok(())
+}
+
+#[test]
+fn gpt2_generation_stop_sequences() -> anyhow::Result<()> {
+    //    Resources definition
+    let config_resource = Box::new(RemoteResource::from_pretrained(Gpt2ConfigResources::GPT2));
+    let vocab_resource = Box::new(RemoteResource::from_pretrained(Gpt2VocabResources::GPT2));
+    let merges_resource = Box::new(RemoteResource::from_pretrained(Gpt2MergesResources::GPT2));
+    let model_resource = Box::new(RemoteResource::from_pretrained(Gpt2ModelResources::GPT2));
+
+    let generate_config = GenerateConfig {
+        max_length: 16,
+        model_resource,
+        config_resource,
+        vocab_resource,
+        merges_resource,
+        do_sample: false,
+        num_beams: 1,
+        stop_sequences: vec![", and".to_string()],
+        device: Device::Cpu,
+        ..Default::default()
+    };
+    let model = GPT2Generator::new(generate_config)?;
+
+    let input_context = "Hello, my name is";
+
+    // The stop sequence spans two tokens (`,` and ` and`)
+    let output = model.generate_indices(Some(&[input_context]), None);
+    assert_eq!(
+        output[0].indices,
+        vec![15496, 11, 616, 1438, 318, 1757, 13, 314, 1101, 257, 6260, 11, 290]
+    );
+    let output = model.generate(Some(&[input_context]), None);
+    assert_eq!(output[0].text, "Hello, my name is John. I'm a writer");
+
+    // The stop sequences of the options replace the configured ones
+    let generate_options = GenerateOptions {
+        stop_sequences: Some(&["."]),
+        ..Default::default()
+    };
+    let output = model.generate(Some(&[input_context]), Some(generate_options));
+    assert_eq!(output[0].text, "Hello, my name is John");
+
+    Ok(())
+}
+
+#[test]
+fn gpt2_generation_json_schema() -> anyhow::Result<()> {
+    //    Resources definition
+    let config_resource = Box::new(RemoteResource::from_pretrained(Gpt2ConfigResources::GPT2));
+    let vocab_resource = Box::new(RemoteResource::from_pretrained(Gpt2VocabResources::GPT2));
+    let merges_resource = Box::new(RemoteResource::from_pretrained(Gpt2MergesResources::GPT2));
+    let model_resource = Box::new(RemoteResource::from_pretrained(Gpt2ModelResources::GPT2));
+
+    let generate_config = GenerateConfig {
+        max_length: 40,
+        model_resource,
+        config_resource,
+        vocab_resource,
+        merges_resource,
+        do_sample: false,
+        num_beams: 1,
+        device: Device::Cpu,
+        ..Default::default()
+    };
+    let model = GPT2Generator::new(generate_config)?;
+
+    let grammar = Grammar::from_json_schema(&json!({
+        "type": "object",
+        "properties": {
+            "answer": {"enum": ["yes", "no"]}
+        },
+        "required": ["answer"]
+    }))?;
+    let generate_options = GenerateOptions {
+        grammar: Some(&grammar),
+        ..Default::default()
+    };
+
+    let input_context = "Is the sky blue? Answer as JSON:";
+    let output = model.generate(Some(&[input_context]), Some(generate_options));
+
+    let answer: serde_json::Value = serde_json::from_str(&output[0].text[input_context.len()..])?;
+    assert!(answer["answer"] == "yes" || answer["answer"] == "no");
+
+    Ok(())
+}
+
+#[test]
+fn gpt2_generation_contrastive_search() -> anyhow::Result<()> {
+    //    Resources definition
+    let config_resource = Box::new(RemoteResource::from_pretrained(Gpt2ConfigResources::GPT2));
+    let vocab_resource = Box::new(RemoteResource::from_pretrained(Gpt2VocabResources::GPT2));
+    let merges_resource = Box::new(RemoteResource::from_pretrained(Gpt2MergesResources::GPT2));
+    let model_resource = Box::new(RemoteResource::from_pretrained(Gpt2ModelResources::GPT2));
+
+    let generate_config = GenerateConfig {
+        max_length: 32,
+        model_resource,
+        config_resource,
+        vocab_resource,
+        merges_resource,
+        do_sample: false,
+        num_beams: 1,
+        top_k: 4,
+        device: Device::Cpu,
+        ..Default::default()
+    };
+    let model = GPT2Generator::new(generate_config)?;
+
+    let input_context = "The dog";
+    let greedy_output = model.generate_indices(Some(&[input_context]), None);
+
+    // Without degeneration penalty, the most likely candidate is selected as for greedy decoding
+    let generate_options = GenerateOptions {
+        penalty_alpha: Some(1e-6),
+        ..Default::default()
+    };
+    let output = model.generate_indices(Some(&[input_context]), Some(generate_options));
+    assert_eq!(output[0].indices, greedy_output[0].indices);
+
+    let generate_options = GenerateOptions {
+        penalty_alpha: Some(0.6),
+        ..Default::default()
+    };
+    let output = model.generate_indices(Some(&[input_context]), Some(generate_options));
+    assert_ne!(output[0].indices, greedy_output[0].indices);
+
+    Ok(())
+}
+
+#[test]
+fn gpt2_generation_truncation_sampling() -> anyhow::Result<()> {
+    //    Resources definition
+    let config_resource = Box::new(RemoteResource::from_pretrained(Gpt2ConfigResources::GPT2));
+    let vocab_resource = Box::new(RemoteResource::from_pretrained(Gpt2VocabResources::GPT2));
+    let merges_resource = Box::new(RemoteResource::from_pretrained(Gpt2MergesResources::GPT2));
+    let model_resource = Box::new(RemoteResource::from_pretrained(Gpt2ModelResources::GPT2));
+
+    let generate_config = GenerateConfig {
+        max_length: 16,
+        model_resource,
+        config_resource,
+        vocab_resource,
+        merges_resource,
+        do_sample: true,
+        num_beams: 1,
+        top_k: 0,
+        top_p: 1.0,
+        no_repeat_ngram_size: 0,
+        device: Device::Cpu,
+        ..Default::default()
+    };
+    let model = GPT2Generator::new(generate_config)?;
+
+    let input_context = "Hello, my name is";
+    let greedy_output = model.generate_indices(
+        Some(&[input_context]),
+        Some(GenerateOptions {
+            do_sample: Some(false),
+            ..Default::default()
+        }),
+    );
+
+    // Only the most likely token has a probability higher than the cutoff
+    let generate_options = GenerateOptions {
+        epsilon_cutoff: Some(0.99),
+        ..Default::default()
+    };
+    let output = model.generate_indices(Some(&[input_context]), Some(generate_options));
+    assert_eq!(output[0].indices, greedy_output[0].indices);
+
+    // Only the most typical token is kept
+    let generate_options = GenerateOptions {
+        typical_p: Some(1e-6),
+        ..Default::default()
+    };
+    let output_1 = model.generate_indices(Some(&[input_context]), Some(generate_options));
+    let output_2 = model.generate_indices(Some(&[input_context]), Some(generate_options));
+    assert_eq!(output_1[0].indices, output_2[0].indices);
+
+    // Eta sampling with beam search
+    let generate_options = GenerateOptions {
+        eta_cutoff: Some(9e-4),
+        num_beams: Some(3),
+        ..Default::default()
+    };
+    let output = model.generate_indices(Some(&[input_context]), Some(generate_options));
+    assert_eq!(output.len(), 1);
+    assert!(output[0].indices.len() <= 16);
+
+    Ok(())
+}
+
+#[test]
+fn gpt2_generation_logit_bias() -> anyhow::Result<()> {
+    //    Resources definition
+    let config_resource = Box::new(RemoteResource::from_pretrained(Gpt2ConfigResources::GPT2));
+    let vocab_resource = Box::new(RemoteResource::from_pretrained(Gpt2VocabResources::GPT2));
+    let merges_resource = Box::new(RemoteResource::from_pretrained(Gpt2MergesResources::GPT2));
+    let model_resource = Box::new(RemoteResource::from_pretrained(Gpt2ModelResources::GPT2));
+
+    let generate_config = GenerateConfig {
+        max_length: 12,
+        model_resource,
+        config_resource,
+        vocab_resource,
+        merges_resource,
+        do_sample: false,
+        num_beams: 1,
+        no_repeat_ngram_size: 0,
+        device: Device::Cpu,
+        ..Default::default()
+    };
+    let model = GPT2Generator::new(generate_config)?;
+
+    let input_context = "Hello, my name is";
+    let prompt_length = 5;
+    let baseline_output = model.generate_indices(Some(&[input_context]), None);
+    let first_token = baseline_output[0].indices[prompt_length];
+
+    // A large negative bias prevents the generation of the most likely token
+    let logit_bias = HashMap::from([(first_token, -100.0)]);
+    let generate_options = GenerateOptions {
+        logit_bias: Some(&logit_bias),
+        ..Default::default()
+    };
+    let output = model.generate_indices(Some(&[input_context]), Some(generate_options));
+    assert!(!output[0].indices[prompt_length..].contains(&first_token));
+
+    // A large positive bias forces the generation of a token, for beam search as well
+    let logit_bias = HashMap::from([(13, 100.0), (100_000, 100.0)]);
+    for num_beams in [1, 3] {
+        let generate_options = GenerateOptions {
+            num_beams: Some(num_beams),
+            logit_bias: Some(&logit_bias),
+            ..Default::default()
+        };
+        let output = model.generate_indices(Some(&[input_context]), Some(generate_options));
+        assert!(output[0].indices[prompt_length..]
+            .iter()
+            .all(|token_id| *token_id == 13));
+    }
+
+    Ok(())
+}
+
+#[test]
+fn gpt2_generation_config_logit_bias() -> anyhow::Result<()> {
+    //    Resources definition
+    let config_resource = Box::new(RemoteResource::from_pretrained(Gpt2ConfigResources::GPT2));
+    let vocab_resource = Box::new(RemoteResource::from_pretrained(Gpt2VocabResources::GPT2));
+    let merges_resource = Box::new(RemoteResource::from_pretrained(Gpt2MergesResources::GPT2));
+    let model_resource = Box::new(RemoteResource::from_pretrained(Gpt2ModelResources::GPT2));
+
+    //    The logit bias of the configuration applies to all generations
+    let generate_config = GenerateConfig {
+        max_length: 12,
+        model_resource,
+        config_resource,
+        vocab_resource,
+        merges_resource,
+        do_sample: false,
+        num_beams: 1,
+        no_repeat_ngram_size: 0,
+        logit_bias: HashMap::from([(13, 100.0)]),
+        device: Device::Cpu,
+        ..Default::default()
+    };
+    let model = GPT2Generator::new(generate_config)?;
+
+    let input_context = "Hello, my name is";
+    let prompt_length = 5;
+    let output = model.generate_indices(Some(&[input_context]), None);
+    assert!(output[0].indices[prompt_length..]
+        .iter()
+        .all(|token_id| *token_id == 13));
+
+    //    The logit bias of the generation options replaces it, an empty bias disabling it
+    let logit_bias = HashMap::new();
+    let generate_options = GenerateOptions {
+        logit_bias: Some(&logit_bias),
+        ..Default::default()
+    };
+    let output = model.generate_indices(Some(&[input_context]), Some(generate_options));
+    assert!(output[0].indices[prompt_length..]
+        .iter()
+        .any(|token_id| *token_id != 13));
+
+    Ok(())
+}
+
+#[test]
+fn gpt2_generation_watermark() -> anyhow::Result<()> {
+    //    Resources definition
+    let config_resource = Box::new(RemoteResource::from_pretrained(Gpt2ConfigResources::GPT2));
+    let vocab_resource = Box::new(RemoteResource::from_pretrained(Gpt2VocabResources::GPT2));
+    let merges_resource = Box::new(RemoteResource::from_pretrained(Gpt2MergesResources::GPT2));
+    let model_resource = Box::new(RemoteResource::from_pretrained(Gpt2ModelResources::GPT2));
+
+    let generate_config = GenerateConfig {
+        max_length: 64,
+        model_resource,
+        config_resource,
+        vocab_resource,
+        merges_resource,
+        do_sample: false,
+        num_beams: 1,
+        device: Device::Cpu,
+        ..Default::default()
+    };
+    let model = GPT2Generator::new(generate_config)?;
+
+    let input_context = "The history of the city of Paris";
+    let watermark = Watermark {
+        bias: 4.0,
+        ..Watermark::new(15485863)
+    };
+    for num_beams in [1, 3] {
+        let generate_options = GenerateOptions {
+            num_beams: Some(num_beams),
+            watermark: Some(&watermark),
+            ..Default::default()
+        };
+        let output = model.generate_indices(Some(&[input_context]), Some(generate_options));
+        let detection = watermark.detect(&output[0].indices);
+        assert!(detection.green_fraction > 0.5);
+        assert!(detection.z_score > 4.0);
+
+        // The watermark is not detected with another hashing key
+        let detection = Watermark::new(42).detect(&output[0].indices);
+        assert!(detection.z_score < 4.0);
+    }
+
+    let generate_options = GenerateOptions {
+        watermark: Some(&watermark),
+        ..Default::default()
+    };
+    let output = model.generate(Some(&[input_context]), Some(generate_options));
+    let detection = watermark.detect_text(model.get_tokenizer(), &output[0].text);
+    assert!(detection.z_score > 4.0);
+
+    Ok(())
+}
+
+#[test]
+fn gpt2_generation_logits_processors() -> anyhow::Result<()> {
+    //    Resources definition
+    let config_resource = Box::new(RemoteResource::from_pretrained(Gpt2ConfigResources::GPT2));
+    let vocab_resource = Box::new(RemoteResource::from_pretrained(Gpt2VocabResources::GPT2));
+    let merges_resource = Box::new(RemoteResource::from_pretrained(Gpt2MergesResources::GPT2));
+    let model_resource = Box::new(RemoteResource::from_pretrained(Gpt2ModelResources::GPT2));
+
+    let generate_config = GenerateConfig {
+        max_length: 12,
+        model_resource,
+        config_resource,
+        vocab_resource,
+        merges_resource,
+        do_sample: false,
+        num_beams: 1,
+        no_repeat_ngram_size: 0,
+        device: Device::Cpu,
+        ..Default::default()
+    };
+    let model = GPT2Generator::new(generate_config)?;
+
+    struct ForcedToken(i64);
+    impl LogitsProcessor for ForcedToken {
+        fn process(&self, _input_ids: &Tensor, next_token_logits: &mut Tensor) {
+            let _ = next_token_logits.fill_(f64::NEG_INFINITY);
+            let _ = next_token_logits.select(1, self.0).fill_(0.0);
+        }
+    }
+
+    let input_context = "Hello, my name is";
+    let prompt_length = 5;
+    let forced_token = ForcedToken(13);
+    for num_beams in [1, 3] {
+        let logits_processors: [&dyn LogitsProcessor; 1] = [&forced_token];
+        let generate_options = GenerateOptions {
+            num_beams: Some(num_beams),
+            logits_processors: Some(&logits_processors),
+            ..Default::default()
+        };
+        let output = model.generate_indices(Some(&[input_context]), Some(generate_options));
+        assert!(output[0].indices[prompt_length..]
+            .iter()
+            .all(|token_id| *token_id == 13));
+    }
+
+    // Processors are applied in order: the closure replaces the token forced by the first processor
+    let replace_forced_token = |_input_ids: &Tensor, next_token_logits: &mut Tensor| {
+        let _ = next_token_logits.select(1, 13).fill_(f64::NEG_INFINITY);
+        let _ = next_token_logits.select(1, 14).fill_(0.0);
+    };
+    let logits_processors: [&dyn LogitsProcessor; 2] = [&forced_token, &replace_forced_token];
+    let generate_options = GenerateOptions {
+        logits_processors: Some(&logits_processors),
+        ..Default::default()
+    };
+    let output = model.generate_indices(Some(&[input_context]), Some(generate_options));
+    assert!(output[0].indices[prompt_length..]
+        .iter()
+        .all(|token_id| *token_id == 14));
+
+    Ok(())
+}
+
+#[test]
+fn gpt2_generation_surface_bad_words() -> anyhow::Result<()> {
+    //    Resources definition
+    let config_resource = Box::new(RemoteResource::from_pretrained(Gpt2ConfigResources::GPT2));
+    let vocab_resource = Box::new(RemoteResource::from_pretrained(Gpt2VocabResources::GPT2));
+    let merges_resource = Box::new(RemoteResource::from_pretrained(Gpt2MergesResources::GPT2));
+    let model_resource = Box::new(RemoteResource::from_pretrained(Gpt2ModelResources::GPT2));
+
+    let generate_config = GenerateConfig {
+        max_length: 16,
+        model_resource,
+        config_resource,
+        vocab_resource,
+        merges_resource,
+        do_sample: false,
+        num_beams: 1,
+        no_repeat_ngram_size: 0,
+        device: Device::Cpu,
+        ..Default::default()
+    };
+    let model = GPT2Generator::new(generate_config)?;
+
+    let input_context = "Hello, my name is";
+    let baseline_output = model.generate(Some(&[input_context]), None);
+    let baseline_text = baseline_output[0].text.strip_prefix(input_context).unwrap();
+    let bad_word = baseline_text.split_whitespace().next().unwrap();
+
+    // The banned word is not generated, whatever its tokenization
+    let bad_words = [bad_word];
+    for num_beams in [1, 3] {
+        let generate_options = GenerateOptions {
+            num_beams: Some(num_beams),
+            bad_words: Some(&bad_words),
+            ..Default::default()
+        };
+        let output = model.generate(Some(&[input_context]), Some(generate_options));
+        let generated_text = output[0].text.strip_prefix(input_context).unwrap();
+        assert!(!generated_text.is_empty());
+        assert!(!generated_text.contains(bad_word));
+    }
+
+    Ok(())
+}
+
+#[test]
+fn gpt2_generation_token_scores() -> anyhow::Result<()> {
+    //    Resources definition
+    let config_resource = Box::new(RemoteResource::from_pretrained(Gpt2ConfigResources::GPT2));
+    let vocab_resource = Box::new(RemoteResource::from_pretrained(Gpt2VocabResources::GPT2));
+    let merges_resource = Box::new(RemoteResource::from_pretrained(Gpt2MergesResources::GPT2));
+    let model_resource = Box::new(RemoteResource::from_pretrained(Gpt2ModelResources::GPT2));
+
+    let generate_config = GenerateConfig {
+        max_length: 12,
+        model_resource,
+        config_resource,
+        vocab_resource,
+        merges_resource,
+        do_sample: false,
+        num_beams: 1,
+        no_repeat_ngram_size: 0,
+        device: Device::Cpu,
+        ..Default::default()
+    };
+    let model = GPT2Generator::new(generate_config)?;
+
+    let input_context = "Hello, my name is";
+    let prompt_length = 5;
+
+    // Greedy decoding selects the most likely token at each step
+    let generate_options = GenerateOptions {
+        output_scores: true,
+        top_logprobs: Some(3),
+        ..Default::default()
+    };
+    let output = model.generate_indices(Some(&[input_context]), Some(generate_options));
+    let token_scores = output[0].generated_token_scores.as_ref().unwrap();
+    assert_eq!(token_scores.token_logprobs.len(), 12 - prompt_length);
+    assert_eq!(token_scores.top_logprobs.len(), 12 - prompt_length);
+    assert_eq!(token_scores.sequence_score, output[0].score.unwrap());
+    for (step, (token_logprob, top_logprobs)) in token_scores
+        .token_logprobs
+        .iter()
+        .zip(token_scores.top_logprobs.iter())
+        .enumerate()
+    {
+        assert_eq!(top_logprobs.len(), 3);
+        assert_eq!(top_logprobs[0].0, output[0].indices[prompt_length + step]);
+        assert!((top_logprobs[0].1 - token_logprob).abs() < 1e-6);
+        assert!(top_logprobs[0].1 >= top_logprobs[1].1);
+    }
+
+    // The scores follow the selected hypothesis for beam search
+    let generate_options = GenerateOptions {
+        num_beams: Some(3),
+        output_scores: true,
+        top_logprobs: Some(2),
+        ..Default::default()
+    };
+    let output = model.generate(Some(&[input_context]), Some(generate_options));
+    let token_scores = output[0].generated_token_scores.as_ref().unwrap();
+    assert_eq!(token_scores.sequence_score, output[0].score.unwrap());
+    assert!(!token_scores.token_logprobs.is_empty());
+    for (token_logprob, top_logprobs) in token_scores
+        .token_logprobs
+        .iter()
+        .zip(token_scores.top_logprobs.iter())
+    {
+        assert!(*token_logprob <= 0.0);
+        assert_eq!(top_logprobs.len(), 2);
+        assert!(top_logprobs[0].1 >= *token_logprob - 1e-6);
+    }
+
+    Ok(())
+}
+
 #[test]
 #[cfg_attr(not(feature = "all-tests"), ignore)]
 fn dialogpt_single_multi_turn_conversation() -> anyhow::Result<()> {
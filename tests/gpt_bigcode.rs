@@ -0,0 +1,128 @@
+use rust_bert::gpt_bigcode::{GptBigCodeConfig, GptBigCodeForCausalLM};
+use rust_bert::pipelines::generation_utils::{Cache, LMHeadModel};
+use tch::{nn, Device, Kind, Tensor};
+
+fn small_gpt_bigcode_config() -> GptBigCodeConfig {
+    GptBigCodeConfig {
+        vocab_size: 128,
+        n_positions: 64,
+        n_embd: 32,
+        n_layer: 2,
+        n_head: 4,
+        output_attentions: Some(true),
+        output_hidden_states: Some(true),
+        ..Default::default()
+    }
+}
+
+fn max_incremental_difference(gpt_bigcode_model: &GptBigCodeForCausalLM) -> anyhow::Result<f64> {
+    let input_tensor = Tensor::of_slice(&[1i64, 5, 17, 42, 8, 3, 12]).unsqueeze(0);
+
+    //    Full forward pass
+    let full_output = LMHeadModel::forward_t(
+        gpt_bigcode_model,
+        Some(&input_tensor),
+        Cache::None,
+        None,
+        None,
+        None,
+        None,
+        None,
+        None,
+        false,
+    )?;
+
+    //    Prompt followed by token-by-token decoding using the cache
+    let mut cache = Cache::None;
+    let mut step_logits = vec![];
+    let prompt_output = LMHeadModel::forward_t(
+        gpt_bigcode_model,
+        Some(&input_tensor.slice(1, 0, 3, 1)),
+        cache,
+        None,
+        None,
+        None,
+        None,
+        None,
+        None,
+        false,
+    )?;
+    cache = prompt_output.cache;
+    for position in 3..7 {
+        let step_output = LMHeadModel::forward_t(
+            gpt_bigcode_model,
+            Some(&input_tensor.slice(1, position, position + 1, 1)),
+            cache,
+            None,
+            None,
+            None,
+            None,
+            None,
+            None,
+            false,
+        )?;
+        step_logits.push(step_output.lm_logits);
+        cache = step_output.cache;
+    }
+    let incremental_logits = Tensor::cat(&[vec![prompt_output.lm_logits], step_logits].concat(), 1);
+
+    assert_eq!(incremental_logits.size(), full_output.lm_logits.size());
+    Ok((incremental_logits - full_output.lm_logits)
+        .abs()
+        .max()
+        .to_kind(Kind::Double)
+        .double_value(&[]))
+}
+
+#[test]
+fn gpt_bigcode_multi_query_output_shapes() -> anyhow::Result<()> {
+    //    Set-up model
+    let device = Device::Cpu;
+    let vs = nn::VarStore::new(device);
+    let config = small_gpt_bigcode_config();
+    let gpt_bigcode_model = GptBigCodeForCausalLM::new(&vs.root(), &config)?;
+
+    //    Define input
+    let input_tensor = Tensor::of_slice(&[1i64, 5, 17, 42, 8, 3, 1, 5, 17, 9, 10, 11]).view([2, 6]);
+
+    //    Forward pass
+    let model_output =
+        gpt_bigcode_model.forward_t(Some(&input_tensor), None, None, None, None, false)?;
+
+    assert_eq!(model_output.lm_logits.size(), vec![2, 6, 128]);
+    assert_eq!(model_output.hidden_states.size(), vec![2, 6, 32]);
+    assert_eq!(model_output.all_hidden_states.as_ref().unwrap().len(), 3);
+    let all_attentions = model_output.all_attentions.unwrap();
+    assert_eq!(all_attentions.len(), 2);
+    assert_eq!(all_attentions[0].size(), vec![2, 4, 6, 6]);
+
+    //    A single key and value head is cached for the multi-query attention
+    let next_cache = model_output.next_cache.unwrap();
+    assert_eq!(
+        next_cache[0].as_ref().unwrap().prev_key.size(),
+        vec![2, 1, 6, 8]
+    );
+
+    Ok(())
+}
+
+#[test]
+fn gpt_bigcode_incremental_decoding() -> anyhow::Result<()> {
+    let device = Device::Cpu;
+
+    //    Multi-query attention
+    let vs = nn::VarStore::new(device);
+    let gpt_bigcode_model = GptBigCodeForCausalLM::new(&vs.root(), &small_gpt_bigcode_config())?;
+    assert!(max_incremental_difference(&gpt_bigcode_model)? < 1e-4);
+
+    //    Multi-head attention
+    let vs = nn::VarStore::new(device);
+    let config = GptBigCodeConfig {
+        multi_query: Some(false),
+        ..small_gpt_bigcode_config()
+    };
+    let gpt_bigcode_model = GptBigCodeForCausalLM::new(&vs.root(), &config)?;
+    assert!(max_incremental_difference(&gpt_bigcode_model)? < 1e-4);
+
+    Ok(())
+}
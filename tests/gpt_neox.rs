@@ -0,0 +1,126 @@
+use rust_bert::gpt_neox::{GptNeoXConfig, GptNeoXForCausalLM};
+use rust_bert::pipelines::generation_utils::{Cache, LMHeadModel};
+use tch::{nn, Device, Kind, Tensor};
+
+fn small_gpt_neox_config() -> GptNeoXConfig {
+    GptNeoXConfig {
+        vocab_size: 128,
+        hidden_size: 32,
+        intermediate_size: 128,
+        num_hidden_layers: 2,
+        num_attention_heads: 4,
+        rotary_pct: 0.5,
+        max_position_embeddings: 64,
+        output_attentions: Some(true),
+        output_hidden_states: Some(true),
+        ..Default::default()
+    }
+}
+
+#[test]
+fn gpt_neox_lm_output_shapes() -> anyhow::Result<()> {
+    //    Set-up model
+    let device = Device::Cpu;
+    let vs = nn::VarStore::new(device);
+    let config = small_gpt_neox_config();
+    let gpt_neox_model = GptNeoXForCausalLM::new(&vs.root(), &config)?;
+
+    //    Define input
+    let input_tensor = Tensor::of_slice(&[1i64, 5, 17, 42, 8, 3, 1, 5, 17, 9, 10, 11]).view([2, 6]);
+
+    //    Forward pass
+    let model_output =
+        gpt_neox_model.forward_t(Some(&input_tensor), None, None, None, None, false)?;
+
+    assert_eq!(model_output.lm_logits.size(), vec![2, 6, 128]);
+    assert_eq!(model_output.hidden_states.size(), vec![2, 6, 32]);
+    assert_eq!(model_output.all_hidden_states.as_ref().unwrap().len(), 3);
+    let all_attentions = model_output.all_attentions.unwrap();
+    assert_eq!(all_attentions.len(), 2);
+    assert_eq!(all_attentions[0].size(), vec![2, 4, 6, 6]);
+
+    let next_cache = model_output.next_cache.unwrap();
+    assert_eq!(next_cache.len(), 2);
+    assert_eq!(
+        next_cache[0].as_ref().unwrap().prev_key.size(),
+        vec![2, 4, 6, 8]
+    );
+
+    //    The attention to future positions is masked
+    let masked_weight = all_attentions[0].get(0).get(0).get(0).double_value(&[5]);
+    assert!(masked_weight.abs() < 1e-6);
+
+    Ok(())
+}
+
+#[test]
+fn gpt_neox_incremental_decoding() -> anyhow::Result<()> {
+    //    Set-up model
+    let device = Device::Cpu;
+    let vs = nn::VarStore::new(device);
+    let config = GptNeoXConfig {
+        use_parallel_residual: Some(false),
+        ..small_gpt_neox_config()
+    };
+    let gpt_neox_model = GptNeoXForCausalLM::new(&vs.root(), &config)?;
+
+    let input_tensor = Tensor::of_slice(&[1i64, 5, 17, 42, 8, 3, 12]).unsqueeze(0);
+
+    //    Full forward pass
+    let full_output = LMHeadModel::forward_t(
+        &gpt_neox_model,
+        Some(&input_tensor),
+        Cache::None,
+        None,
+        None,
+        None,
+        None,
+        None,
+        None,
+        false,
+    )?;
+
+    //    Prompt followed by token-by-token decoding using the cache
+    let mut cache = Cache::None;
+    let mut step_logits = vec![];
+    let prompt_output = LMHeadModel::forward_t(
+        &gpt_neox_model,
+        Some(&input_tensor.slice(1, 0, 3, 1)),
+        cache,
+        None,
+        None,
+        None,
+        None,
+        None,
+        None,
+        false,
+    )?;
+    cache = prompt_output.cache;
+    for position in 3..7 {
+        let step_output = LMHeadModel::forward_t(
+            &gpt_neox_model,
+            Some(&input_tensor.slice(1, position, position + 1, 1)),
+            cache,
+            None,
+            None,
+            None,
+            None,
+            None,
+            None,
+            false,
+        )?;
+        step_logits.push(step_output.lm_logits);
+        cache = step_output.cache;
+    }
+    let incremental_logits = Tensor::cat(&[vec![prompt_output.lm_logits], step_logits].concat(), 1);
+
+    assert_eq!(incremental_logits.size(), full_output.lm_logits.size());
+    let max_difference = (incremental_logits - full_output.lm_logits)
+        .abs()
+        .max()
+        .to_kind(Kind::Double)
+        .double_value(&[]);
+    assert!(max_difference < 1e-4);
+
+    Ok(())
+}
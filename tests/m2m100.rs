@@ -54,7 +54,7 @@ fn m2m100_lm_model() -> anyhow::Result<()> {
 
     //    Forward pass
     let model_output =
-        m2m100_model.forward_t(Some(&input_tensor), None, None, None, None, None, false);
+        m2m100_model.forward_t(Some(&input_tensor), None, None, None, None, None, false)?;
     assert_eq!(model_output.decoder_output.size(), vec!(1, 5, 1024));
     assert_eq!(
         model_output.encoder_hidden_state.unwrap().size(),
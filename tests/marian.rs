@@ -4,7 +4,8 @@ use rust_bert::marian::{
 };
 use rust_bert::pipelines::common::ModelType;
 use rust_bert::pipelines::translation::{
-    Language, TranslationConfig, TranslationModel, TranslationModelBuilder,
+    AlignmentMethod, Glossary, GlossaryConstraint, GlossaryEntry, Language, TranslationConfig,
+    TranslationModel, TranslationModelBuilder,
 };
 use rust_bert::resources::RemoteResource;
 use tch::Device;
@@ -72,3 +73,84 @@ fn test_translation_builder() -> anyhow::Result<()> {
 
     Ok(())
 }
+
+#[test]
+// #[cfg_attr(not(feature = "all-tests"), ignore)]
+fn test_translation_word_alignments() -> anyhow::Result<()> {
+    let model = TranslationModelBuilder::new()
+        .with_device(Device::cuda_if_available())
+        .with_model_type(ModelType::Marian)
+        .with_source_languages(vec![Language::English])
+        .with_target_languages(vec![Language::French])
+        .with_output_attentions()
+        .create_model()?;
+
+    let input_context = "The dog did not wake up";
+
+    let outputs = model.translate_with_alignments(
+        &[input_context],
+        None,
+        Language::French,
+        AlignmentMethod::Argmax,
+    )?;
+
+    assert_eq!(outputs.len(), 1);
+    assert_eq!(outputs[0].text, " Le chien ne s'est pas réveillé");
+    assert_eq!(outputs[0].source_words.len(), 6);
+    assert_eq!(outputs[0].target_words.len(), 6);
+    //    Each target word is aligned with the argmax method, "chien" to "dog"
+    assert_eq!(outputs[0].alignments.len(), 6);
+    assert!(outputs[0]
+        .alignments
+        .iter()
+        .any(|alignment| (alignment.target_word == 1) & (alignment.source_word == 1)));
+
+    let outputs = model.translate_with_alignments(
+        &[input_context],
+        None,
+        Language::French,
+        AlignmentMethod::OptimalTransport,
+    )?;
+    assert!(outputs[0].alignments.len() <= 6);
+    assert!(outputs[0]
+        .alignments
+        .iter()
+        .all(|alignment| (alignment.score > 0.0) & (alignment.score <= 1.0)));
+
+    Ok(())
+}
+
+#[test]
+// #[cfg_attr(not(feature = "all-tests"), ignore)]
+fn test_translation_glossary() -> anyhow::Result<()> {
+    let model = TranslationModelBuilder::new()
+        .with_device(Device::cuda_if_available())
+        .with_model_type(ModelType::Marian)
+        .with_source_languages(vec![Language::English])
+        .with_target_languages(vec![Language::French])
+        .create_model()?;
+
+    let input_context_1 = "The dog did not wake up";
+    let input_context_2 = "The quick brown fox jumps over the lazy dog";
+
+    //    The glossary only applies to the texts containing the source term
+    let glossary = Glossary::new(
+        vec![GlossaryEntry::new("wake up", "lever", false)],
+        GlossaryConstraint::Hard,
+    );
+    let outputs = model.translate_with_glossary(
+        &[input_context_1, input_context_2],
+        None,
+        Language::French,
+        &glossary,
+    )?;
+
+    assert_eq!(outputs.len(), 2);
+    assert!(outputs[0].contains("lever"));
+    assert_eq!(
+        outputs[1],
+        " Le rapide renard brun saute sur le chien paresseux"
+    );
+
+    Ok(())
+}
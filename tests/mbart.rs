@@ -53,7 +53,7 @@ fn mbart_lm_model() -> anyhow::Result<()> {
 
     //    Forward pass
     let model_output =
-        mbart_model.forward_t(Some(&input_tensor), None, None, None, None, None, false);
+        mbart_model.forward_t(Some(&input_tensor), None, None, None, None, None, false)?;
     assert_eq!(model_output.decoder_output.size(), vec!(1, 5, 1024));
     assert_eq!(
         model_output.encoder_hidden_state.unwrap().size(),
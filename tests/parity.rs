@@ -0,0 +1,688 @@
+//! Logits parity tests against reference outputs generated with the Python Transformers library.
+//!
+//! The reference checkpoints and outputs are generated by `utils/generate_parity_references.py` and
+//! read from the directory set in the `RUST_BERT_PARITY_DIR` environment variable:
+//! ```bash
+//! python utils/generate_parity_references.py /tmp/parity
+//! RUST_BERT_PARITY_DIR=/tmp/parity cargo test --features parity-tests --test parity
+//! ```
+//! Each architecture is checked on CPU against the reference logits. If a CUDA device is available, the
+//! logits computed on GPU are also checked against the CPU logits.
+
+extern crate anyhow;
+
+use rust_bert::albert::{AlbertConfig, AlbertForMaskedLM};
+use rust_bert::bart::{BartConfig, BartForConditionalGeneration};
+use rust_bert::bert::{BertConfig, BertForMaskedLM};
+use rust_bert::bigbird::{BigBirdConfig, BigBirdForQuestionAnswering};
+use rust_bert::bloom::{BloomConfig, BloomForCausalLM};
+use rust_bert::canine::{CanineConfig, CanineForTokenClassification};
+use rust_bert::clip::{ClipConfig, ClipModel};
+use rust_bert::deberta::{DebertaConfig, DebertaForMaskedLM};
+use rust_bert::deberta_v2::{DebertaV2Config, DebertaV2ForMaskedLM};
+use rust_bert::distilbert::{DistilBertConfig, DistilBertModelMaskedLM};
+use rust_bert::electra::{ElectraConfig, ElectraForMaskedLM};
+use rust_bert::falcon::{FalconConfig, FalconForCausalLM};
+use rust_bert::fnet::{FNetConfig, FNetForMaskedLM};
+use rust_bert::gpt2::{GPT2LMHeadModel, Gpt2Config};
+use rust_bert::gpt_bigcode::{GptBigCodeConfig, GptBigCodeForCausalLM};
+use rust_bert::gpt_neo::{GptNeoConfig, GptNeoForCausalLM};
+use rust_bert::gpt_neox::{GptNeoXConfig, GptNeoXForCausalLM};
+use rust_bert::llama::{LlamaConfig, LlamaForCausalLM};
+use rust_bert::longformer::{LongformerConfig, LongformerForMaskedLM};
+use rust_bert::m2m_100::{M2M100Config, M2M100ForConditionalGeneration};
+use rust_bert::marian::{MarianConfig, MarianForConditionalGeneration};
+use rust_bert::mbart::{MBartConfig, MBartForConditionalGeneration};
+use rust_bert::mobilebert::{MobileBertConfig, MobileBertForMaskedLM};
+use rust_bert::openai_gpt::{OpenAIGPTLMHeadModel, OpenAiGptConfig};
+use rust_bert::pegasus::{PegasusConfig, PegasusForConditionalGeneration};
+use rust_bert::pipelines::generation_utils::{Cache, LMHeadModel};
+use rust_bert::prophetnet::{ProphetNetConfig, ProphetNetForConditionalGeneration};
+use rust_bert::reformer::{ReformerConfig, ReformerModelWithLMHead};
+use rust_bert::roberta::{RobertaConfig, RobertaForMaskedLM};
+use rust_bert::t5::{T5Config, T5ForConditionalGeneration};
+use rust_bert::vision_encoder_decoder::{VisionEncoderDecoderConfig, VisionEncoderDecoderModel};
+use rust_bert::vit::{ViTConfig, ViTForImageClassification};
+use rust_bert::wav2vec2::{Wav2Vec2Config, Wav2Vec2ForCTC};
+use rust_bert::whisper::{WhisperConfig, WhisperForConditionalGeneration};
+use rust_bert::xlnet::{XLNetConfig, XLNetLMHeadModel};
+use rust_bert::Config;
+use serde::Deserialize;
+use std::fs::File;
+use std::io::BufReader;
+use std::path::PathBuf;
+use tch::{nn, no_grad, Device, Kind, Tensor};
+
+#[derive(Debug, Deserialize)]
+struct ParityReference {
+    input_ids: Option<Vec<Vec<i64>>>,
+    attention_mask: Option<Vec<Vec<i64>>>,
+    decoder_input_ids: Option<Vec<Vec<i64>>>,
+    input_features: Option<Vec<f32>>,
+    input_features_shape: Option<Vec<i64>>,
+    logits_shape: Vec<i64>,
+    logits: Vec<f32>,
+    tolerance: f64,
+}
+
+struct ParityInputs {
+    input_ids: Option<Tensor>,
+    attention_mask: Option<Tensor>,
+    decoder_input_ids: Option<Tensor>,
+    /// Pixel values, raw waveforms or log-mel spectrograms of the vision and speech models
+    input_features: Option<Tensor>,
+}
+
+fn required<'a>(input: &'a Option<Tensor>, name: &str) -> anyhow::Result<&'a Tensor> {
+    input
+        .as_ref()
+        .ok_or_else(|| anyhow::anyhow!("{} missing from the parity reference", name))
+}
+
+fn to_tensor(values: &[Vec<i64>], device: Device) -> Tensor {
+    let rows = values
+        .iter()
+        .map(|row| Tensor::of_slice(row))
+        .collect::<Vec<Tensor>>();
+    Tensor::stack(&rows, 0).to(device)
+}
+
+fn assert_close(actual: &Tensor, expected: &Tensor, tolerance: f64, description: &str) {
+    assert_eq!(
+        actual.size(),
+        expected.size(),
+        "{}: logits shape mismatch",
+        description
+    );
+    let max_difference = (actual.to_kind(Kind::Float) - expected.to_kind(Kind::Float))
+        .abs()
+        .max()
+        .double_value(&[]);
+    assert!(
+        max_difference < tolerance,
+        "{}: maximum logits difference {} above tolerance {}",
+        description,
+        max_difference,
+        tolerance
+    );
+}
+
+/// Loads the reference checkpoint of an architecture, runs the forward pass on CPU (and GPU if available)
+/// and compares the logits to the reference outputs.
+fn check_parity<C, M, B, F>(architecture: &str, build: B, forward: F) -> anyhow::Result<()>
+where
+    C: Config,
+    B: Fn(&nn::Path, &C) -> anyhow::Result<M>,
+    F: Fn(&M, &ParityInputs) -> anyhow::Result<Tensor>,
+{
+    let parity_dir = std::env::var("RUST_BERT_PARITY_DIR").expect(
+        "RUST_BERT_PARITY_DIR must point to the references generated by utils/generate_parity_references.py",
+    );
+    let reference_dir = PathBuf::from(parity_dir).join(architecture);
+    let reference: ParityReference = serde_json::from_reader(BufReader::new(File::open(
+        reference_dir.join("reference.json"),
+    )?))?;
+    let config = C::from_file(reference_dir.join("config.json"));
+    let expected_logits =
+        Tensor::of_slice(&reference.logits).view(reference.logits_shape.as_slice());
+
+    let run_on_device = |device: Device| -> anyhow::Result<Tensor> {
+        let mut vs = nn::VarStore::new(device);
+        let model = build(&vs.root(), &config)?;
+        vs.load(reference_dir.join("rust_model.ot"))?;
+        let inputs = ParityInputs {
+            input_ids: reference
+                .input_ids
+                .as_ref()
+                .map(|input_ids| to_tensor(input_ids, device)),
+            attention_mask: reference
+                .attention_mask
+                .as_ref()
+                .map(|attention_mask| to_tensor(attention_mask, device)),
+            decoder_input_ids: reference
+                .decoder_input_ids
+                .as_ref()
+                .map(|decoder_input_ids| to_tensor(decoder_input_ids, device)),
+            input_features: reference
+                .input_features
+                .as_ref()
+                .zip(reference.input_features_shape.as_ref())
+                .map(|(input_features, shape)| {
+                    Tensor::of_slice(input_features)
+                        .view(shape.as_slice())
+                        .to(device)
+                }),
+        };
+        Ok(no_grad(|| forward(&model, &inputs))?.to(Device::Cpu))
+    };
+
+    let cpu_logits = run_on_device(Device::Cpu)?;
+    assert_close(
+        &cpu_logits,
+        &expected_logits,
+        reference.tolerance,
+        &format!("{} (CPU vs. reference)", architecture),
+    );
+    if tch::Cuda::is_available() {
+        let gpu_logits = run_on_device(Device::Cuda(0))?;
+        assert_close(
+            &gpu_logits,
+            &cpu_logits,
+            reference.tolerance,
+            &format!("{} (GPU vs. CPU)", architecture),
+        );
+    }
+    Ok(())
+}
+
+/// Forward pass of the vision encoder-decoder models (Donut and TrOCR)
+fn vision_encoder_decoder_forward(
+    model: &VisionEncoderDecoderModel,
+    inputs: &ParityInputs,
+) -> anyhow::Result<Tensor> {
+    Ok(model
+        .forward_t(
+            Some(required(&inputs.input_features, "pixel_values")?),
+            required(&inputs.decoder_input_ids, "decoder_input_ids")?,
+            None,
+            None,
+            false,
+        )?
+        .decoder_output)
+}
+
+/// Forward pass for the models implementing `LMHeadModel` (decoders and encoder-decoders)
+fn lm_head_forward<M: LMHeadModel>(model: &M, inputs: &ParityInputs) -> anyhow::Result<Tensor> {
+    Ok(LMHeadModel::forward_t(
+        model,
+        inputs.input_ids.as_ref(),
+        Cache::None,
+        inputs.attention_mask.as_ref(),
+        None,
+        None,
+        None,
+        None,
+        inputs.decoder_input_ids.as_ref(),
+        false,
+    )?
+    .lm_logits)
+}
+
+#[test]
+fn albert_parity() -> anyhow::Result<()> {
+    check_parity(
+        "albert",
+        |p, config: &AlbertConfig| Ok(AlbertForMaskedLM::new(p, config)),
+        |model, inputs| {
+            Ok(model
+                .forward_t(
+                    inputs.input_ids.as_ref(),
+                    inputs.attention_mask.as_ref(),
+                    None,
+                    None,
+                    None,
+                    false,
+                )
+                .prediction_scores)
+        },
+    )
+}
+
+#[test]
+fn bart_parity() -> anyhow::Result<()> {
+    check_parity(
+        "bart",
+        |p, config: &BartConfig| Ok(BartForConditionalGeneration::new(p, config)),
+        lm_head_forward,
+    )
+}
+
+#[test]
+fn bert_parity() -> anyhow::Result<()> {
+    check_parity(
+        "bert",
+        |p, config: &BertConfig| Ok(BertForMaskedLM::new(p, config)),
+        |model, inputs| {
+            Ok(model
+                .forward_t(
+                    inputs.input_ids.as_ref(),
+                    inputs.attention_mask.as_ref(),
+                    None,
+                    None,
+                    None,
+                    None,
+                    None,
+                    false,
+                )
+                .prediction_scores)
+        },
+    )
+}
+
+#[test]
+fn bigbird_parity() -> anyhow::Result<()> {
+    // The start and end logits are stacked along the last dimension
+    check_parity(
+        "bigbird",
+        |p, config: &BigBirdConfig| Ok(BigBirdForQuestionAnswering::new(p, config)),
+        |model, inputs| {
+            let output = model.forward_t(
+                inputs.input_ids.as_ref(),
+                inputs.attention_mask.as_ref(),
+                None,
+                None,
+                None,
+                false,
+            )?;
+            Ok(Tensor::stack(&[output.start_logits, output.end_logits], -1))
+        },
+    )
+}
+
+#[test]
+fn bloom_parity() -> anyhow::Result<()> {
+    check_parity(
+        "bloom",
+        |p, config: &BloomConfig| Ok(BloomForCausalLM::new(p, config)?),
+        lm_head_forward,
+    )
+}
+
+#[test]
+fn canine_parity() -> anyhow::Result<()> {
+    check_parity(
+        "canine",
+        |p, config: &CanineConfig| Ok(CanineForTokenClassification::new(p, config)),
+        |model, inputs| {
+            Ok(model
+                .forward_t(
+                    inputs.input_ids.as_ref(),
+                    inputs.attention_mask.as_ref(),
+                    None,
+                    None,
+                    None,
+                    false,
+                )?
+                .logits)
+        },
+    )
+}
+
+#[test]
+fn clip_parity() -> anyhow::Result<()> {
+    check_parity(
+        "clip",
+        |p, config: &ClipConfig| Ok(ClipModel::new(p, config)),
+        |model, inputs| {
+            Ok(model
+                .forward_t(
+                    required(&inputs.input_ids, "input_ids")?,
+                    inputs.attention_mask.as_ref(),
+                    required(&inputs.input_features, "pixel_values")?,
+                    false,
+                )?
+                .logits_per_image)
+        },
+    )
+}
+
+#[test]
+fn deberta_parity() -> anyhow::Result<()> {
+    check_parity(
+        "deberta",
+        |p, config: &DebertaConfig| Ok(DebertaForMaskedLM::new(p, config)),
+        |model, inputs| {
+            Ok(model
+                .forward_t(
+                    inputs.input_ids.as_ref(),
+                    inputs.attention_mask.as_ref(),
+                    None,
+                    None,
+                    None,
+                    false,
+                )?
+                .logits)
+        },
+    )
+}
+
+#[test]
+fn deberta_v2_parity() -> anyhow::Result<()> {
+    check_parity(
+        "deberta_v2",
+        |p, config: &DebertaV2Config| Ok(DebertaV2ForMaskedLM::new(p, config)),
+        |model, inputs| {
+            Ok(model
+                .forward_t(
+                    inputs.input_ids.as_ref(),
+                    inputs.attention_mask.as_ref(),
+                    None,
+                    None,
+                    None,
+                    false,
+                )?
+                .logits)
+        },
+    )
+}
+
+#[test]
+fn distilbert_parity() -> anyhow::Result<()> {
+    check_parity(
+        "distilbert",
+        |p, config: &DistilBertConfig| Ok(DistilBertModelMaskedLM::new(p, config)),
+        |model, inputs| {
+            Ok(model
+                .forward_t(
+                    inputs.input_ids.as_ref(),
+                    inputs.attention_mask.as_ref(),
+                    None,
+                    false,
+                )?
+                .prediction_scores)
+        },
+    )
+}
+
+#[test]
+fn donut_parity() -> anyhow::Result<()> {
+    check_parity(
+        "donut",
+        |p, config: &VisionEncoderDecoderConfig| Ok(VisionEncoderDecoderModel::new(p, config)),
+        vision_encoder_decoder_forward,
+    )
+}
+
+#[test]
+fn electra_parity() -> anyhow::Result<()> {
+    check_parity(
+        "electra",
+        |p, config: &ElectraConfig| Ok(ElectraForMaskedLM::new(p, config)),
+        |model, inputs| {
+            Ok(model
+                .forward_t(
+                    inputs.input_ids.as_ref(),
+                    inputs.attention_mask.as_ref(),
+                    None,
+                    None,
+                    None,
+                    false,
+                )
+                .prediction_scores)
+        },
+    )
+}
+
+#[test]
+fn falcon_parity() -> anyhow::Result<()> {
+    check_parity(
+        "falcon",
+        |p, config: &FalconConfig| Ok(FalconForCausalLM::new(p, config)?),
+        lm_head_forward,
+    )
+}
+
+#[test]
+fn fnet_parity() -> anyhow::Result<()> {
+    // FNet does not use an attention mask
+    check_parity(
+        "fnet",
+        |p, config: &FNetConfig| Ok(FNetForMaskedLM::new(p, config)),
+        |model, inputs| {
+            Ok(model
+                .forward_t(inputs.input_ids.as_ref(), None, None, None, false)?
+                .prediction_scores)
+        },
+    )
+}
+
+#[test]
+fn gpt2_parity() -> anyhow::Result<()> {
+    check_parity(
+        "gpt2",
+        |p, config: &Gpt2Config| Ok(GPT2LMHeadModel::new(p, config)),
+        lm_head_forward,
+    )
+}
+
+#[test]
+fn gpt_bigcode_parity() -> anyhow::Result<()> {
+    check_parity(
+        "gpt_bigcode",
+        |p, config: &GptBigCodeConfig| Ok(GptBigCodeForCausalLM::new(p, config)?),
+        lm_head_forward,
+    )
+}
+
+#[test]
+fn gpt_neo_parity() -> anyhow::Result<()> {
+    check_parity(
+        "gpt_neo",
+        |p, config: &GptNeoConfig| Ok(GptNeoForCausalLM::new(p, config)?),
+        lm_head_forward,
+    )
+}
+
+#[test]
+fn gpt_neox_parity() -> anyhow::Result<()> {
+    check_parity(
+        "gpt_neox",
+        |p, config: &GptNeoXConfig| Ok(GptNeoXForCausalLM::new(p, config)?),
+        lm_head_forward,
+    )
+}
+
+#[test]
+fn llama_parity() -> anyhow::Result<()> {
+    check_parity(
+        "llama",
+        |p, config: &LlamaConfig| Ok(LlamaForCausalLM::new(p, config)?),
+        lm_head_forward,
+    )
+}
+
+#[test]
+fn longformer_parity() -> anyhow::Result<()> {
+    check_parity(
+        "longformer",
+        |p, config: &LongformerConfig| Ok(LongformerForMaskedLM::new(p, config)),
+        |model, inputs| {
+            Ok(model
+                .forward_t(
+                    inputs.input_ids.as_ref(),
+                    inputs.attention_mask.as_ref(),
+                    None,
+                    None,
+                    None,
+                    None,
+                    false,
+                )?
+                .prediction_scores)
+        },
+    )
+}
+
+#[test]
+fn m2m_100_parity() -> anyhow::Result<()> {
+    check_parity(
+        "m2m_100",
+        |p, config: &M2M100Config| Ok(M2M100ForConditionalGeneration::new(p, config)),
+        lm_head_forward,
+    )
+}
+
+#[test]
+fn marian_parity() -> anyhow::Result<()> {
+    check_parity(
+        "marian",
+        |p, config: &MarianConfig| Ok(MarianForConditionalGeneration::new(p, config)),
+        lm_head_forward,
+    )
+}
+
+#[test]
+fn mbart_parity() -> anyhow::Result<()> {
+    check_parity(
+        "mbart",
+        |p, config: &MBartConfig| Ok(MBartForConditionalGeneration::new(p, config)),
+        lm_head_forward,
+    )
+}
+
+#[test]
+fn mobilebert_parity() -> anyhow::Result<()> {
+    check_parity(
+        "mobilebert",
+        |p, config: &MobileBertConfig| Ok(MobileBertForMaskedLM::new(p, config)),
+        |model, inputs| {
+            Ok(model
+                .forward_t(
+                    inputs.input_ids.as_ref(),
+                    None,
+                    None,
+                    None,
+                    inputs.attention_mask.as_ref(),
+                    false,
+                )?
+                .logits)
+        },
+    )
+}
+
+#[test]
+fn openai_gpt_parity() -> anyhow::Result<()> {
+    check_parity(
+        "openai_gpt",
+        |p, config: &OpenAiGptConfig| Ok(OpenAIGPTLMHeadModel::new(p, config)),
+        lm_head_forward,
+    )
+}
+
+#[test]
+fn pegasus_parity() -> anyhow::Result<()> {
+    check_parity(
+        "pegasus",
+        |p, config: &PegasusConfig| Ok(PegasusForConditionalGeneration::new(p, config)),
+        lm_head_forward,
+    )
+}
+
+#[test]
+fn prophetnet_parity() -> anyhow::Result<()> {
+    check_parity(
+        "prophetnet",
+        |p, config: &ProphetNetConfig| Ok(ProphetNetForConditionalGeneration::new(p, config)?),
+        lm_head_forward,
+    )
+}
+
+#[test]
+fn reformer_parity() -> anyhow::Result<()> {
+    check_parity(
+        "reformer",
+        |p, config: &ReformerConfig| Ok(ReformerModelWithLMHead::new(p, config)?),
+        lm_head_forward,
+    )
+}
+
+#[test]
+fn roberta_parity() -> anyhow::Result<()> {
+    check_parity(
+        "roberta",
+        |p, config: &RobertaConfig| Ok(RobertaForMaskedLM::new(p, config)),
+        |model, inputs| {
+            Ok(model
+                .forward_t(
+                    inputs.input_ids.as_ref(),
+                    inputs.attention_mask.as_ref(),
+                    None,
+                    None,
+                    None,
+                    None,
+                    None,
+                    false,
+                )
+                .prediction_scores)
+        },
+    )
+}
+
+#[test]
+fn t5_parity() -> anyhow::Result<()> {
+    check_parity(
+        "t5",
+        |p, config: &T5Config| Ok(T5ForConditionalGeneration::new(p, config)),
+        lm_head_forward,
+    )
+}
+
+#[test]
+fn trocr_parity() -> anyhow::Result<()> {
+    check_parity(
+        "trocr",
+        |p, config: &VisionEncoderDecoderConfig| Ok(VisionEncoderDecoderModel::new(p, config)),
+        vision_encoder_decoder_forward,
+    )
+}
+
+#[test]
+fn vit_parity() -> anyhow::Result<()> {
+    check_parity(
+        "vit",
+        |p, config: &ViTConfig| Ok(ViTForImageClassification::new(p, config)),
+        |model, inputs| {
+            Ok(model
+                .forward_t(
+                    required(&inputs.input_features, "pixel_values")?,
+                    false,
+                    false,
+                )?
+                .logits)
+        },
+    )
+}
+
+#[test]
+fn wav2vec2_parity() -> anyhow::Result<()> {
+    check_parity(
+        "wav2vec2",
+        |p, config: &Wav2Vec2Config| Ok(Wav2Vec2ForCTC::new(p, config)),
+        |model, inputs| {
+            Ok(model
+                .forward_t(
+                    required(&inputs.input_features, "input_values")?,
+                    None,
+                    false,
+                )?
+                .logits)
+        },
+    )
+}
+
+#[test]
+fn whisper_parity() -> anyhow::Result<()> {
+    check_parity(
+        "whisper",
+        |p, config: &WhisperConfig| Ok(WhisperForConditionalGeneration::new(p, config)),
+        |model, inputs| {
+            Ok(model
+                .forward_t(
+                    Some(required(&inputs.input_features, "input_features")?),
+                    required(&inputs.decoder_input_ids, "decoder_input_ids")?,
+                    None,
+                    None,
+                    false,
+                )?
+                .decoder_output)
+        },
+    )
+}
+
+#[test]
+fn xlnet_parity() -> anyhow::Result<()> {
+    check_parity(
+        "xlnet",
+        |p, config: &XLNetConfig| Ok(XLNetLMHeadModel::new(p, config)),
+        lm_head_forward,
+    )
+}
@@ -4,7 +4,12 @@ use rust_bert::pipelines::question_answering::{
     QaInput, QuestionAnsweringConfig, QuestionAnsweringModel,
 };
 use rust_bert::pipelines::token_classification::TokenClassificationConfig;
-use rust_bert::resources::{RemoteResource, ResourceProvider};
+use rust_bert::pipelines::translation::Language;
+use rust_bert::pipelines::zero_shot_classification::{
+    get_localized_template, ZeroShotClassificationConfig, ZeroShotClassificationModel,
+    ZeroShotClassificationModelType,
+};
+use rust_bert::resources::{LocalResource, RemoteResource, ResourceProvider};
 use rust_bert::roberta::{
     RobertaConfig, RobertaConfigResources, RobertaForMaskedLM, RobertaForMultipleChoice,
     RobertaForSequenceClassification, RobertaForTokenClassification, RobertaMergesResources,
@@ -14,7 +19,8 @@ use rust_bert::Config;
 use rust_tokenizers::tokenizer::{RobertaTokenizer, Tokenizer, TruncationStrategy};
 use rust_tokenizers::vocab::Vocab;
 use std::collections::HashMap;
-use tch::{nn, no_grad, Device, Tensor};
+use std::path::PathBuf;
+use tch::{nn, no_grad, Device, Kind, Tensor};
 
 #[test]
 fn roberta_masked_lm() -> anyhow::Result<()> {
@@ -107,6 +113,50 @@ fn roberta_masked_lm() -> anyhow::Result<()> {
     Ok(())
 }
 
+#[test]
+fn xlm_roberta_xl_pre_layer_norm() -> anyhow::Result<()> {
+    //    Set-up a (randomly initialized) masked LM model with the XLM-RoBERTa-XL layer normalization placement
+    let config_resource =
+        RemoteResource::from_pretrained(RobertaConfigResources::DISTILROBERTA_BASE);
+    let config_path = config_resource.get_local_path()?;
+    let device = Device::Cpu;
+    let vs = nn::VarStore::new(device);
+    let mut config = RobertaConfig::from_file(config_path);
+    config.model_type = Some("xlm-roberta-xl".to_string());
+    assert!(config.is_pre_layer_norm());
+    let roberta_model = RobertaForMaskedLM::new(&vs.root(), &config);
+
+    let variables = vs.variables();
+    assert!(variables.contains_key("roberta.encoder.layer.0.attention.self_attn_layer_norm.weight"));
+    assert!(variables.contains_key("roberta.encoder.layer.0.LayerNorm.weight"));
+    assert!(variables.contains_key("roberta.encoder.LayerNorm.weight"));
+    assert!(!variables.contains_key("roberta.embeddings.LayerNorm.weight"));
+    assert!(!variables.contains_key("roberta.encoder.layer.0.attention.output.LayerNorm.weight"));
+    assert!(!variables.contains_key("roberta.encoder.layer.0.output.LayerNorm.weight"));
+
+    //    Forward pass
+    let input_tensor = Tensor::randint(config.vocab_size, &[2, 16], (Kind::Int64, device));
+    let model_output = no_grad(|| {
+        roberta_model.forward_t(
+            Some(&input_tensor),
+            None,
+            None,
+            None,
+            None,
+            None,
+            None,
+            false,
+        )
+    });
+
+    assert_eq!(
+        model_output.prediction_scores.size(),
+        vec!(2, 16, config.vocab_size)
+    );
+
+    Ok(())
+}
+
 #[test]
 fn roberta_for_sequence_classification() -> anyhow::Result<()> {
     //    Resources paths
@@ -400,3 +450,48 @@ fn xlm_roberta_german_ner() -> anyhow::Result<()> {
 
     Ok(())
 }
+
+#[test]
+#[cfg_attr(not(feature = "all-tests"), ignore)]
+fn xlm_roberta_zero_shot_classification_preset() -> anyhow::Result<()> {
+    //    Set-up model, the weights are converted locally from the PyTorch checkpoint
+    let weights_path = std::env::var("XLM_ROBERTA_LARGE_XNLI_WEIGHTS")?;
+    let zero_shot_config = ZeroShotClassificationConfig {
+        device: Device::Cpu,
+        ..ZeroShotClassificationConfig::from_model_type(
+            ZeroShotClassificationModelType::XlmRobertaLargeXnli,
+            LocalResource::from(PathBuf::from(weights_path)),
+        )
+    };
+    let zero_shot_model = ZeroShotClassificationModel::new(zero_shot_config)?;
+
+    let input_sentence = "Who are you voting for in 2020?";
+    let input_sequence_2 = "The team won the championship after a penalty shootout.";
+    let candidate_labels = &["politique", "santé publique", "économie", "sport"];
+
+    let output = zero_shot_model.predict(
+        &[input_sentence, input_sequence_2],
+        candidate_labels,
+        Some(get_localized_template(Language::French)),
+        128,
+    );
+
+    assert_eq!(output.len(), 2);
+    assert_eq!(output[0].text, "politique");
+    assert!(output[0].score > 0.5);
+    assert_eq!(output[1].text, "sport");
+    assert!(output[1].score > 0.5);
+
+    //    The entailment probability of the matching label is high, low for the others
+    let output = zero_shot_model.predict_multilabel(
+        &[input_sentence],
+        candidate_labels,
+        Some(get_localized_template(Language::French)),
+        128,
+    );
+
+    assert!(output[0][0].score > 0.5);
+    assert!(output[0][3].score < 0.5);
+
+    Ok(())
+}
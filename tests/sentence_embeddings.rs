@@ -1,5 +1,8 @@
+use rust_bert::pipelines::semantic_similarity::{
+    ScoreCalibration, SemanticSimilarityConfig, SemanticSimilarityModel, SimilarityModelConfig,
+};
 use rust_bert::pipelines::sentence_embeddings::{
-    SentenceEmbeddingsBuilder, SentenceEmbeddingsModelType,
+    SentenceEmbeddingsBuilder, SentenceEmbeddingsLayers, SentenceEmbeddingsModelType,
 };
 
 #[test]
@@ -55,6 +58,43 @@ fn sbert_bert() -> anyhow::Result<()> {
     Ok(())
 }
 
+#[test]
+fn sbert_bert_layers_selection() -> anyhow::Result<()> {
+    let sentences = ["this is an example sentence", "each sentence is converted"];
+    let embeddings =
+        SentenceEmbeddingsBuilder::remote(SentenceEmbeddingsModelType::BertBaseNliMeanTokens)
+            .create_model()?
+            .encode(&sentences)?;
+
+    let last_layer_embeddings =
+        SentenceEmbeddingsBuilder::remote(SentenceEmbeddingsModelType::BertBaseNliMeanTokens)
+            .with_layers(SentenceEmbeddingsLayers::Layer(11))
+            .create_model()?
+            .encode(&sentences)?;
+    assert_eq!(last_layer_embeddings[0].len(), 768);
+    assert!((last_layer_embeddings[0][0] - embeddings[0][0]).abs() < 1e-4);
+    assert!((last_layer_embeddings[1][767] - embeddings[1][767]).abs() < 1e-4);
+
+    // Mean pooling of the concatenated layers, the last 768 dimensions are the last layer embeddings
+    let concat_embeddings =
+        SentenceEmbeddingsBuilder::remote(SentenceEmbeddingsModelType::BertBaseNliMeanTokens)
+            .with_layers(SentenceEmbeddingsLayers::concat_last_four())
+            .create_model()?
+            .encode(&sentences)?;
+    assert_eq!(concat_embeddings[0].len(), 4 * 768);
+    assert!((concat_embeddings[0][3 * 768] - embeddings[0][0]).abs() < 1e-4);
+    assert!((concat_embeddings[1][4 * 768 - 1] - embeddings[1][767]).abs() < 1e-4);
+
+    assert!(
+        SentenceEmbeddingsBuilder::remote(SentenceEmbeddingsModelType::BertBaseNliMeanTokens)
+            .with_layers(SentenceEmbeddingsLayers::Layer(-13))
+            .create_model()
+            .is_err()
+    );
+
+    Ok(())
+}
+
 #[test]
 fn sbert_bert_small() -> anyhow::Result<()> {
     let model = SentenceEmbeddingsBuilder::remote(SentenceEmbeddingsModelType::AllMiniLmL12V2)
@@ -155,3 +195,32 @@ fn sbert_t5() -> anyhow::Result<()> {
 
     Ok(())
 }
+
+#[test]
+fn sts_bi_encoder() -> anyhow::Result<()> {
+    let config = SemanticSimilarityConfig::new(
+        SimilarityModelConfig::BiEncoder(SentenceEmbeddingsModelType::AllMiniLmL12V2.into()),
+        ScoreCalibration::Linear {
+            min: -1.0,
+            max: 1.0,
+        },
+    );
+    let mut model = SemanticSimilarityModel::new(config)?;
+    model.set_batch_size(2);
+
+    let pairs = [
+        ("A man is playing a guitar.", "A man is playing a guitar."),
+        (
+            "A man is playing a guitar.",
+            "A person plays an instrument.",
+        ),
+        ("A man is playing a guitar.", "A chef is cooking pasta."),
+    ];
+    let scores = model.predict(&pairs)?;
+
+    assert_eq!(scores.len(), 3);
+    assert!((scores[0] - 1.0).abs() < 1e-4);
+    assert!(scores[1] > scores[2]);
+    assert!(scores.iter().all(|score| (0.0..=1.0).contains(score)));
+    Ok(())
+}
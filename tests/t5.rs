@@ -1,9 +1,12 @@
-use rust_bert::pipelines::common::ModelType;
+use rust_bert::pipelines::common::{ModelType, TokenizerOption};
 use rust_bert::pipelines::summarization::{SummarizationConfig, SummarizationModel};
 use rust_bert::pipelines::translation::{Language, TranslationConfig, TranslationModel};
 use rust_bert::resources::RemoteResource;
-use rust_bert::t5::{T5ConfigResources, T5ModelResources, T5VocabResources};
-use tch::Device;
+use rust_bert::t5::{
+    T5Config, T5ConfigResources, T5ForConditionalGeneration, T5ModelResources, T5VocabResources,
+};
+use rust_tokenizers::tokenizer::TruncationStrategy;
+use tch::{nn, Device, Tensor};
 
 #[test]
 fn test_translation_t5() -> anyhow::Result<()> {
@@ -112,3 +115,117 @@ about exoplanets like K2-18b."];
 
     Ok(())
 }
+
+#[test]
+fn test_t5_v1_1_gated_gelu_architecture() -> anyhow::Result<()> {
+    //    Flan-T5 / mT5 style configuration: gated-gelu feed-forward, untied LM head and deeper decoder
+    let config: T5Config = serde_json::from_str(
+        r#"{
+            "d_ff": 48,
+            "d_kv": 8,
+            "d_model": 16,
+            "decoder_start_token_id": 0,
+            "dropout_rate": 0.1,
+            "eos_token_id": 1,
+            "feed_forward_proj": "gated-gelu",
+            "initializer_factor": 1.0,
+            "is_encoder_decoder": true,
+            "layer_norm_epsilon": 1e-06,
+            "model_type": "mt5",
+            "num_decoder_layers": 3,
+            "num_heads": 2,
+            "num_layers": 2,
+            "pad_token_id": 0,
+            "relative_attention_max_distance": 128,
+            "relative_attention_num_buckets": 32,
+            "tie_word_embeddings": false,
+            "vocab_size": 64
+        }"#,
+    )?;
+    let vs = nn::VarStore::new(Device::Cpu);
+    let model = T5ForConditionalGeneration::new(vs.root(), &config);
+
+    let variables = vs.variables();
+    assert!(variables.contains_key("encoder.block.1.layer.1.DenseReluDense.wi_0.weight"));
+    assert!(variables.contains_key("encoder.block.1.layer.1.DenseReluDense.wi_1.weight"));
+    assert!(!variables.contains_key("encoder.block.2.layer.0.layer_norm.weight"));
+    assert!(variables.contains_key("decoder.block.2.layer.2.DenseReluDense.wi_0.weight"));
+    assert!(variables.contains_key("lm_head.weight"));
+
+    let input_ids = Tensor::of_slice(&[5i64, 12, 7, 1]).unsqueeze(0);
+    let decoder_input_ids = Tensor::of_slice(&[0i64, 5]).unsqueeze(0);
+    let output = model.forward_t(
+        Some(&input_ids),
+        None,
+        None,
+        Some(&decoder_input_ids),
+        None,
+        None,
+        None,
+        None,
+        false,
+    );
+    assert_eq!(output.decoder_output.size(), vec![1, 2, 64]);
+    assert_eq!(output.next_cache.unwrap().len(), 3);
+
+    Ok(())
+}
+
+#[test]
+fn test_byt5_byte_level_tokenizer() -> anyhow::Result<()> {
+    //    The ByT5 tokenizer does not require a vocabulary file
+    let tokenizer = TokenizerOption::from_file(ModelType::ByT5, "", None, false, None, None)?;
+    assert!(TokenizerOption::from_file(ModelType::ByT5, "", None, true, None, None).is_err());
+    assert_eq!(tokenizer.get_pad_id(), Some(0));
+    assert_eq!(tokenizer.get_eos_id(), Some(1));
+
+    //    Typos, URLs and non-latin scripts are encoded byte by byte, without unknown tokens
+    let text = "chek https://exämple.com/?q=1 дом";
+    let encoded =
+        tokenizer.encode_list(&[text], 128, &TruncationStrategy::LongestFirst, 0)[0].clone();
+    assert_eq!(encoded.token_ids.len(), text.len() + 1);
+    assert!(!encoded.token_ids.contains(&tokenizer.get_unk_id()));
+    assert_eq!(tokenizer.decode(&encoded.token_ids, true, true), text);
+
+    //    The byte ids are processed by a T5 architecture with a 384 tokens vocabulary
+    let config: T5Config = serde_json::from_str(
+        r#"{
+            "d_ff": 48,
+            "d_kv": 8,
+            "d_model": 16,
+            "decoder_start_token_id": 0,
+            "dropout_rate": 0.1,
+            "eos_token_id": 1,
+            "feed_forward_proj": "gated-gelu",
+            "initializer_factor": 1.0,
+            "is_encoder_decoder": true,
+            "layer_norm_epsilon": 1e-06,
+            "model_type": "t5",
+            "num_decoder_layers": 1,
+            "num_heads": 2,
+            "num_layers": 2,
+            "pad_token_id": 0,
+            "relative_attention_num_buckets": 32,
+            "tie_word_embeddings": false,
+            "vocab_size": 384
+        }"#,
+    )?;
+    let vs = nn::VarStore::new(Device::Cpu);
+    let model = T5ForConditionalGeneration::new(vs.root(), &config);
+    let input_ids = Tensor::of_slice(&encoded.token_ids).unsqueeze(0);
+    let decoder_input_ids = Tensor::of_slice(&[0i64, 102, 107]).unsqueeze(0);
+    let output = model.forward_t(
+        Some(&input_ids),
+        None,
+        None,
+        Some(&decoder_input_ids),
+        None,
+        None,
+        None,
+        None,
+        false,
+    );
+    assert_eq!(output.decoder_output.size(), vec![1, 3, 384]);
+
+    Ok(())
+}
@@ -0,0 +1,335 @@
+use rust_bert::attention_mask::build_prefix_lm_attention_mask;
+use rust_bert::bart::{BartConfig, BartForConditionalGeneration, BartGenerator};
+use rust_bert::pipelines::common::ModelType;
+use rust_bert::pipelines::config_validation::{compatibility_check, CompatibilityResources};
+use rust_bert::pipelines::generation_utils::{GenerateConfig, GenerateOptions, LanguageGenerator};
+use rust_bert::pipelines::pretrained_registry::PretrainedTask;
+use rust_bert::pipelines::sequence_classification::{
+    SequenceClassificationConfig, SequenceClassificationModel,
+};
+use rust_bert::pipelines::summarization::{SummarizationConfig, SummarizationModel};
+use rust_bert::pipelines::text_generation::{TextGenerationConfig, TextGenerationModel};
+use rust_bert::pipelines::tiny_random::{TinyRandomConfig, TinyRandomModel};
+use rust_bert::reformer::ReformerGenerator;
+use rust_bert::resources::ResourceProvider;
+use rust_bert::Config;
+use tch::{nn, no_grad, Device, Kind, Tensor};
+
+#[test]
+fn tiny_random_models_compatibility() -> anyhow::Result<()> {
+    let models = [
+        (ModelType::Bert, PretrainedTask::SequenceClassification),
+        (
+            ModelType::DistilBert,
+            PretrainedTask::SequenceClassification,
+        ),
+        (ModelType::Deberta, PretrainedTask::SequenceClassification),
+        (ModelType::DebertaV2, PretrainedTask::SequenceClassification),
+        (ModelType::Roberta, PretrainedTask::SequenceClassification),
+        (
+            ModelType::XLMRoberta,
+            PretrainedTask::SequenceClassification,
+        ),
+        (ModelType::Electra, PretrainedTask::TokenClassification),
+        (
+            ModelType::MobileBert,
+            PretrainedTask::SequenceClassification,
+        ),
+        (ModelType::Albert, PretrainedTask::SequenceClassification),
+        (ModelType::Longformer, PretrainedTask::QuestionAnswering),
+        (ModelType::FNet, PretrainedTask::SequenceClassification),
+        (ModelType::Bart, PretrainedTask::ZeroShotClassification),
+        (ModelType::XLNet, PretrainedTask::TextGeneration),
+        (ModelType::GPT2, PretrainedTask::TextGeneration),
+        (ModelType::OpenAiGpt, PretrainedTask::TextGeneration),
+        (ModelType::Reformer, PretrainedTask::TextGeneration),
+        (ModelType::GPTNeo, PretrainedTask::TextGeneration),
+        (ModelType::GPTBigCode, PretrainedTask::TextGeneration),
+        (ModelType::GPTNeoX, PretrainedTask::TextGeneration),
+        (ModelType::Falcon, PretrainedTask::TextGeneration),
+        (ModelType::Bloom, PretrainedTask::TextGeneration),
+        (ModelType::Llama, PretrainedTask::TextGeneration),
+        (ModelType::T5, PretrainedTask::Summarization),
+        (ModelType::ByT5, PretrainedTask::Summarization),
+        (ModelType::ProphetNet, PretrainedTask::Summarization),
+        (ModelType::Pegasus, PretrainedTask::Summarization),
+        (ModelType::Marian, PretrainedTask::Translation),
+        (ModelType::MBart, PretrainedTask::Translation),
+        (ModelType::M2M100, PretrainedTask::Translation),
+    ];
+
+    for (model_type, task) in models {
+        let directory = tempfile::tempdir()?;
+        let tiny_model =
+            TinyRandomModel::new(TinyRandomConfig::new(model_type, task), directory.path())?;
+        let resources = CompatibilityResources::new(model_type, tiny_model.config_resource)
+            .with_tokenizer(tiny_model.vocab_resource, tiny_model.merges_resource)
+            .with_weights(tiny_model.model_resource)
+            .with_task(task);
+        let issues = compatibility_check(&resources)?;
+        assert!(issues.is_empty(), "{:?}: {:?}", model_type, issues);
+    }
+
+    Ok(())
+}
+
+#[test]
+fn tiny_random_unsupported_task() -> anyhow::Result<()> {
+    let directory = tempfile::tempdir()?;
+    let tiny_model = TinyRandomModel::new(
+        TinyRandomConfig::new(ModelType::GPT2, PretrainedTask::QuestionAnswering),
+        directory.path(),
+    );
+    assert!(tiny_model.is_err());
+
+    Ok(())
+}
+
+#[test]
+fn tiny_random_sequence_classification() -> anyhow::Result<()> {
+    let directory = tempfile::tempdir()?;
+    let tiny_model = TinyRandomModel::new(
+        TinyRandomConfig::new(ModelType::Roberta, PretrainedTask::SequenceClassification)
+            .with_labels(&["negative", "positive"]),
+        directory.path(),
+    )?;
+
+    let sequence_classification_config = SequenceClassificationConfig::new(
+        ModelType::Roberta,
+        tiny_model.model_resource,
+        tiny_model.config_resource,
+        tiny_model.vocab_resource,
+        tiny_model.merges_resource,
+        false,
+        None,
+        None,
+    );
+    let model = SequenceClassificationModel::new(sequence_classification_config)?;
+
+    let output = model.predict(["This is a smoke test", "Another one"]);
+
+    assert_eq!(output.len(), 2);
+    assert!(output
+        .iter()
+        .all(|label| label.text == "negative" || label.text == "positive"));
+
+    Ok(())
+}
+
+#[test]
+fn tiny_random_text_generation() -> anyhow::Result<()> {
+    let directory = tempfile::tempdir()?;
+    let tiny_model = TinyRandomModel::new(
+        TinyRandomConfig::new(ModelType::GPT2, PretrainedTask::TextGeneration),
+        directory.path(),
+    )?;
+
+    let text_generation_config = TextGenerationConfig {
+        model_type: ModelType::GPT2,
+        model_resource: Box::new(tiny_model.model_resource),
+        config_resource: Box::new(tiny_model.config_resource),
+        vocab_resource: Box::new(tiny_model.vocab_resource),
+        merges_resource: Box::new(tiny_model.merges_resource.unwrap()),
+        max_length: 12,
+        do_sample: false,
+        num_beams: 1,
+        device: Device::Cpu,
+        ..Default::default()
+    };
+    let model = TextGenerationModel::new(text_generation_config)?;
+
+    let output = model.generate(&["Hello"], None);
+
+    assert_eq!(output.len(), 1);
+
+    Ok(())
+}
+
+#[test]
+fn tiny_random_contrastive_search_unsupported() -> anyhow::Result<()> {
+    let directory = tempfile::tempdir()?;
+    let tiny_model = TinyRandomModel::new(
+        TinyRandomConfig::new(ModelType::Reformer, PretrainedTask::TextGeneration),
+        directory.path(),
+    )?;
+
+    // Reformer does not expose its hidden states: contrastive search is rejected when building the generator
+    let generator = ReformerGenerator::new(GenerateConfig {
+        model_resource: Box::new(tiny_model.model_resource),
+        config_resource: Box::new(tiny_model.config_resource),
+        vocab_resource: Box::new(tiny_model.vocab_resource),
+        do_sample: false,
+        num_beams: 1,
+        top_k: 4,
+        penalty_alpha: Some(0.6),
+        device: Device::Cpu,
+        ..Default::default()
+    });
+    assert!(generator.is_err());
+
+    Ok(())
+}
+
+#[test]
+fn tiny_random_summarization() -> anyhow::Result<()> {
+    let directory = tempfile::tempdir()?;
+    let tiny_model = TinyRandomModel::new(
+        TinyRandomConfig::new(ModelType::Bart, PretrainedTask::Summarization),
+        directory.path(),
+    )?;
+
+    let summarization_config = SummarizationConfig {
+        model_type: ModelType::Bart,
+        model_resource: Box::new(tiny_model.model_resource),
+        config_resource: Box::new(tiny_model.config_resource),
+        vocab_resource: Box::new(tiny_model.vocab_resource),
+        merges_resource: Box::new(tiny_model.merges_resource.unwrap()),
+        min_length: 2,
+        max_length: 8,
+        num_beams: 2,
+        device: Device::Cpu,
+        ..Default::default()
+    };
+    let model = SummarizationModel::new(summarization_config)?;
+
+    let output = model.summarize(&["A short document to summarize."]);
+
+    assert_eq!(output.len(), 1);
+
+    Ok(())
+}
+
+#[test]
+fn tiny_random_prefix_lm_cached_decoding() -> anyhow::Result<()> {
+    let directory = tempfile::tempdir()?;
+    let tiny_model = TinyRandomModel::new(
+        TinyRandomConfig::new(ModelType::Bart, PretrainedTask::Summarization),
+        directory.path(),
+    )?;
+    let config = BartConfig::from_file(tiny_model.config_resource.get_local_path()?);
+    let mut vs = nn::VarStore::new(Device::Cpu);
+    let model = BartForConditionalGeneration::new(&vs.root(), &config);
+    vs.load(tiny_model.model_resource.get_local_path()?)?;
+
+    let input_ids = Tensor::of_slice(&[0i64, 10, 11, 12, 13, 2]).unsqueeze(0);
+    let decoder_input_ids = Tensor::of_slice(&[2i64, 14, 15, 16, 17, 18, 19]).unsqueeze(0);
+    let decoder_attention_mask =
+        build_prefix_lm_attention_mask(&decoder_input_ids.ones_like(), &[4])?;
+
+    let (full_logits, step_logits) = no_grad(|| -> anyhow::Result<(Tensor, Vec<Tensor>)> {
+        let full_output = model.forward_t(
+            Some(&input_ids),
+            None,
+            None,
+            Some(&decoder_input_ids),
+            Some(&decoder_attention_mask),
+            None,
+            false,
+        )?;
+        // The prefix is processed in a single pass, followed by one token per step with the cache: the mask
+        // built for the whole sequence is passed at every step
+        let mut output = model.forward_t(
+            None,
+            None,
+            full_output.encoder_hidden_state.as_ref(),
+            Some(&decoder_input_ids.narrow(1, 0, 4)),
+            Some(&decoder_attention_mask),
+            None,
+            false,
+        )?;
+        let mut step_logits = vec![output.decoder_output.narrow(1, 3, 1)];
+        for position in 4..7 {
+            output = model.forward_t(
+                None,
+                None,
+                full_output.encoder_hidden_state.as_ref(),
+                Some(&decoder_input_ids.narrow(1, position, 1)),
+                Some(&decoder_attention_mask),
+                output.cache,
+                false,
+            )?;
+            step_logits.push(output.decoder_output.shallow_clone());
+        }
+        Ok((full_output.decoder_output, step_logits))
+    })?;
+
+    let cached_logits = Tensor::cat(&step_logits, 1);
+    assert!(cached_logits.allclose(&full_logits.narrow(1, 3, 4), 1e-5, 1e-5, false));
+
+    Ok(())
+}
+
+#[test]
+fn tiny_random_prefix_lm_generation() -> anyhow::Result<()> {
+    let directory = tempfile::tempdir()?;
+    let tiny_model = TinyRandomModel::new(
+        TinyRandomConfig::new(ModelType::Bart, PretrainedTask::Summarization),
+        directory.path(),
+    )?;
+    let config = BartConfig::from_file(tiny_model.config_resource.get_local_path()?);
+    let mut vs = nn::VarStore::new(Device::Cpu);
+    let model = BartForConditionalGeneration::new(&vs.root(), &config);
+    vs.load(tiny_model.model_resource.get_local_path()?)?;
+
+    let max_length = 12;
+    let generator = BartGenerator::new(GenerateConfig {
+        model_resource: Box::new(tiny_model.model_resource),
+        config_resource: Box::new(tiny_model.config_resource),
+        vocab_resource: Box::new(tiny_model.vocab_resource),
+        merges_resource: Box::new(tiny_model.merges_resource.unwrap()),
+        min_length: max_length - 1,
+        max_length,
+        do_sample: false,
+        num_beams: 1,
+        no_repeat_ngram_size: 0,
+        device: Device::Cpu,
+        ..Default::default()
+    })?;
+
+    let input_ids = Tensor::of_slice(&[0i64, 10, 11, 12, 13, 2]).unsqueeze(0);
+    let decoder_prefix_ids = Tensor::of_slice(&[14i64, 15, 16]).unsqueeze(0);
+    let generate_options = GenerateOptions {
+        decoder_prefix_ids: Some(&decoder_prefix_ids),
+        ..Default::default()
+    };
+    let output =
+        generator.generate_from_ids_and_past(input_ids.copy(), None, Some(generate_options));
+    let indices = &output[0].indices;
+
+    assert_eq!(indices.len() as i64, max_length);
+    assert_eq!(
+        indices[..4],
+        [config.decoder_start_token_id.unwrap(), 14, 15, 16]
+    );
+
+    // The tokens generated with the cache match the greedy predictions of a single pass over the generated
+    // sequence with the prefix-LM mask (the end of sequence is banned until the last token)
+    let decoder_input_ids = Tensor::of_slice(indices).unsqueeze(0);
+    let decoder_attention_mask =
+        build_prefix_lm_attention_mask(&decoder_input_ids.ones_like(), &[4])?;
+    let logits = no_grad(|| {
+        model.forward_t(
+            Some(&input_ids),
+            None,
+            None,
+            Some(&decoder_input_ids),
+            Some(&decoder_attention_mask),
+            None,
+            false,
+        )
+    })?
+    .decoder_output;
+    let eos_token_ids = Tensor::of_slice(&[config.eos_token_id.unwrap()]);
+    let predicted_ids = logits
+        .to_kind(Kind::Float)
+        .index_fill(2, &eos_token_ids, f64::NEG_INFINITY)
+        .argmax(-1, false);
+    for position in 3..indices.len() - 2 {
+        assert_eq!(
+            predicted_ids.int64_value(&[0, position as i64]),
+            indices[position + 1]
+        );
+    }
+
+    Ok(())
+}
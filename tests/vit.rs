@@ -0,0 +1,102 @@
+use rust_bert::vit::{
+    ImageBuffer, ViTConfig, ViTForImageClassification, ViTImageProcessor, ViTModel, VIT_IMAGE_MEAN,
+    VIT_IMAGE_STD,
+};
+use std::collections::HashMap;
+use tch::{nn, no_grad, Device, Kind, Tensor};
+
+fn small_vit_config() -> ViTConfig {
+    let id2label = (0..5)
+        .map(|id| (id, format!("LABEL_{}", id)))
+        .collect::<HashMap<i64, String>>();
+    ViTConfig {
+        hidden_size: 16,
+        num_hidden_layers: 2,
+        num_attention_heads: 2,
+        intermediate_size: 32,
+        image_size: 32,
+        patch_size: 8,
+        id2label: Some(id2label),
+        output_attentions: Some(true),
+        output_hidden_states: Some(true),
+        ..Default::default()
+    }
+}
+
+#[test]
+fn vit_output_shapes() -> anyhow::Result<()> {
+    //    Set-up model
+    let device = Device::Cpu;
+    let vs = nn::VarStore::new(device);
+    let config = small_vit_config();
+    let vit_model = ViTModel::new(&vs.root(), &config);
+
+    //    Define input
+    let pixel_values = Tensor::rand(&[2, 3, 32, 32], (Kind::Float, device));
+
+    //    Forward pass
+    let model_output = no_grad(|| vit_model.forward_t(&pixel_values, false, false))?;
+
+    //    4x4 patches and the class token
+    assert_eq!(model_output.hidden_state.size(), vec![2, 17, 16]);
+    let all_hidden_states = model_output.all_hidden_states.unwrap();
+    assert_eq!(all_hidden_states.len(), 3);
+    let all_attentions = model_output.all_attentions.unwrap();
+    assert_eq!(all_attentions.len(), 2);
+    assert_eq!(all_attentions[0].size(), vec![2, 2, 17, 17]);
+
+    Ok(())
+}
+
+#[test]
+fn vit_interpolate_position_embeddings() -> anyhow::Result<()> {
+    //    Set-up model
+    let device = Device::Cpu;
+    let vs = nn::VarStore::new(device);
+    let config = small_vit_config();
+    let vit_model = ViTModel::new(&vs.root(), &config);
+
+    //    Images larger than the training size require interpolated position embeddings
+    let pixel_values = Tensor::rand(&[1, 3, 48, 64], (Kind::Float, device));
+    assert!(no_grad(|| vit_model.forward_t(&pixel_values, false, false)).is_err());
+
+    let model_output = no_grad(|| vit_model.forward_t(&pixel_values, true, false))?;
+    //    6x8 patches and the class token
+    assert_eq!(model_output.hidden_state.size(), vec![1, 49, 16]);
+
+    Ok(())
+}
+
+#[test]
+fn vit_image_classification() -> anyhow::Result<()> {
+    //    Set-up model
+    let device = Device::Cpu;
+    let vs = nn::VarStore::new(device);
+    let config = small_vit_config();
+    let vit_model = ViTForImageClassification::new(&vs.root(), &config);
+    let image_processor =
+        ViTImageProcessor::new(config.image_size, VIT_IMAGE_MEAN, VIT_IMAGE_STD, device);
+
+    //    Define input
+    let rgb_pixels = (0..40 * 30 * 3)
+        .map(|index| (index % 256) as u8)
+        .collect::<Vec<u8>>();
+    let gray_pixels = vec![200u8; 20 * 20];
+    let pixel_values = image_processor.preprocess(&[
+        ImageBuffer::rgb(&rgb_pixels, 40, 30),
+        ImageBuffer {
+            pixels: &gray_pixels,
+            width: 20,
+            height: 20,
+            channels: 1,
+        },
+    ])?;
+
+    //    Forward pass
+    let model_output = no_grad(|| vit_model.forward_t(&pixel_values, false, false))?;
+
+    assert_eq!(pixel_values.size(), vec![2, 3, 32, 32]);
+    assert_eq!(model_output.logits.size(), vec![2, 5]);
+
+    Ok(())
+}
@@ -0,0 +1,146 @@
+use rust_bert::wav2vec2::{
+    FeatureExtractorNorm, Wav2Vec2Config, Wav2Vec2CtcDecoder, Wav2Vec2FeatureExtractor,
+    Wav2Vec2ForCTC,
+};
+use std::collections::HashMap;
+use tch::{nn, no_grad, Device, Kind, Tensor};
+
+fn small_wav2vec2_config() -> Wav2Vec2Config {
+    Wav2Vec2Config {
+        vocab_size: 6,
+        hidden_size: 16,
+        num_hidden_layers: 2,
+        num_attention_heads: 2,
+        intermediate_size: 32,
+        conv_dim: vec![8, 8],
+        conv_stride: vec![5, 2],
+        conv_kernel: vec![10, 3],
+        num_conv_pos_embeddings: 4,
+        num_conv_pos_embedding_groups: 2,
+        output_attentions: Some(true),
+        output_hidden_states: Some(true),
+        ..Default::default()
+    }
+}
+
+#[test]
+fn wav2vec2_output_shapes() -> anyhow::Result<()> {
+    //    Set-up model
+    let device = Device::Cpu;
+    let vs = nn::VarStore::new(device);
+    let config = small_wav2vec2_config();
+    let wav2vec2_model = Wav2Vec2ForCTC::new(&vs.root(), &config);
+
+    //    Define input
+    let feature_extractor = Wav2Vec2FeatureExtractor::new(true, device);
+    let audio = (0..400)
+        .map(|index| (index as f32 * 0.05).sin())
+        .collect::<Vec<f32>>();
+    let (input_values, _) = feature_extractor.extract(&[audio.as_slice(), &audio[..200]])?;
+
+    //    Forward pass
+    let model_output = no_grad(|| wav2vec2_model.forward_t(&input_values, None, false))?;
+
+    //    400 samples -> (400 - 10) / 5 + 1 = 79 -> (79 - 3) / 2 + 1 = 39 frames
+    assert_eq!(wav2vec2_model.get_num_frames(400), 39);
+    assert_eq!(model_output.logits.size(), vec![2, 39, 6]);
+    assert!(model_output.frame_attention_mask.is_none());
+    let all_hidden_states = model_output.all_hidden_states.unwrap();
+    assert_eq!(all_hidden_states.len(), 3);
+    assert_eq!(all_hidden_states[2].size(), vec![2, 39, 16]);
+    let all_attentions = model_output.all_attentions.unwrap();
+    assert_eq!(all_attentions.len(), 2);
+    assert_eq!(all_attentions[0].size(), vec![2, 2, 39, 39]);
+
+    //    Waveforms shorter than the receptive field of the feature encoder are rejected
+    let short_input_values = Tensor::rand(&[1, 12], (Kind::Float, device));
+    assert_eq!(wav2vec2_model.get_num_frames(12), 0);
+    assert!(no_grad(|| wav2vec2_model.forward_t(&short_input_values, None, false)).is_err());
+
+    Ok(())
+}
+
+#[test]
+fn wav2vec2_stable_layer_norm_ignores_padding() -> anyhow::Result<()> {
+    //    Set-up model
+    let device = Device::Cpu;
+    let vs = nn::VarStore::new(device);
+    let config = Wav2Vec2Config {
+        feat_extract_norm: FeatureExtractorNorm::Layer,
+        conv_bias: true,
+        do_stable_layer_norm: true,
+        ..small_wav2vec2_config()
+    };
+    let wav2vec2_model = Wav2Vec2ForCTC::new(&vs.root(), &config);
+
+    //    The same waveform alone and padded in a batch with a longer waveform
+    let feature_extractor = Wav2Vec2FeatureExtractor::new(true, device);
+    let long_audio = (0..400)
+        .map(|index| (index as f32 * 0.05).sin())
+        .collect::<Vec<f32>>();
+    let short_audio = (0..200)
+        .map(|index| (index as f32 * 0.11).cos())
+        .collect::<Vec<f32>>();
+    let (input_values, attention_mask) =
+        feature_extractor.extract(&[long_audio.as_slice(), short_audio.as_slice()])?;
+    let (short_input_values, _) = feature_extractor.extract(&[short_audio.as_slice()])?;
+
+    let model_output =
+        no_grad(|| wav2vec2_model.forward_t(&input_values, Some(&attention_mask), false))?;
+    let short_model_output =
+        no_grad(|| wav2vec2_model.forward_t(&short_input_values, None, false))?;
+
+    //    200 samples -> 39 -> 19 frames
+    let frame_attention_mask = model_output.frame_attention_mask.unwrap();
+    assert_eq!(frame_attention_mask.size(), vec![2, 39]);
+    assert_eq!(
+        Vec::<i64>::from(frame_attention_mask.sum_dim_intlist(&[1], false, Kind::Int64)),
+        vec![39, 19]
+    );
+    assert_eq!(short_model_output.logits.size(), vec![1, 19, 6]);
+    let max_difference = (model_output.logits.get(1).narrow(0, 0, 19)
+        - short_model_output.logits.get(0))
+    .abs()
+    .max()
+    .double_value(&[]);
+    assert!(max_difference < 1e-4);
+
+    Ok(())
+}
+
+#[test]
+fn wav2vec2_ctc_transcription() -> anyhow::Result<()> {
+    //    Set-up model
+    let device = Device::Cpu;
+    let vs = nn::VarStore::new(device);
+    let config = small_wav2vec2_config();
+    let wav2vec2_model = Wav2Vec2ForCTC::new(&vs.root(), &config);
+    let vocab: HashMap<String, i64> = [
+        ("<pad>", 0),
+        ("<s>", 1),
+        ("</s>", 2),
+        ("|", 3),
+        ("A", 4),
+        ("B", 5),
+    ]
+    .iter()
+    .map(|(token, id)| (token.to_string(), *id))
+    .collect();
+    let decoder = Wav2Vec2CtcDecoder::new(vocab)?;
+
+    //    Forward pass
+    let input_values = Tensor::rand(&[2, 400], (Kind::Float, device));
+    let model_output = no_grad(|| wav2vec2_model.forward_t(&input_values, None, false))?;
+
+    //    Transcriptions only contain the characters of the vocabulary and word delimiters
+    let greedy_transcriptions = decoder.decode_greedy(&model_output.logits, None);
+    let beam_transcriptions = decoder.decode_beam_search(&model_output.logits, 4, None);
+    assert_eq!(greedy_transcriptions.len(), 2);
+    assert_eq!(beam_transcriptions.len(), 2);
+    assert!(greedy_transcriptions
+        .iter()
+        .chain(beam_transcriptions.iter())
+        .all(|text| text.chars().all(|character| "AB ".contains(character))));
+
+    Ok(())
+}
@@ -0,0 +1,131 @@
+use rust_bert::whisper::{
+    WhisperConfig, WhisperFeatureExtractor, WhisperForConditionalGeneration, WHISPER_CHUNK_FRAMES,
+};
+use rust_bert::RustBertError;
+use tch::{nn, no_grad, Device, Kind, Tensor};
+
+fn small_whisper_config() -> WhisperConfig {
+    WhisperConfig {
+        vocab_size: 51865,
+        num_mel_bins: 80,
+        encoder_layers: 2,
+        encoder_attention_heads: 2,
+        encoder_ffn_dim: 32,
+        decoder_layers: 2,
+        decoder_attention_heads: 2,
+        decoder_ffn_dim: 32,
+        d_model: 16,
+        max_source_positions: 1500,
+        max_target_positions: 16,
+        output_attentions: Some(true),
+        ..Default::default()
+    }
+}
+
+#[test]
+fn whisper_output_shapes() -> anyhow::Result<()> {
+    //    Set-up model
+    let device = Device::Cpu;
+    let vs = nn::VarStore::new(device);
+    let config = small_whisper_config();
+    let whisper_model = WhisperForConditionalGeneration::new(&vs.root(), &config);
+
+    //    Define input
+    let feature_extractor = WhisperFeatureExtractor::new(config.num_mel_bins, device);
+    let audio = vec![0.1f32; 8000];
+    let input_features = feature_extractor.extract(&[audio.as_slice(), audio.as_slice()])?;
+    let decoder_input_ids =
+        Tensor::of_slice(&[50258i64, 50259, 50359, 50363, 50258, 50259, 50358, 50363]).view([2, 4]);
+
+    //    Forward pass
+    let model_output = no_grad(|| {
+        whisper_model.forward_t(Some(&input_features), &decoder_input_ids, None, None, false)
+    })?;
+
+    assert_eq!(input_features.size(), vec![2, 80, WHISPER_CHUNK_FRAMES]);
+    assert_eq!(model_output.decoder_output.size(), vec![2, 4, 51865]);
+    assert_eq!(
+        model_output.encoder_hidden_state.unwrap().size(),
+        vec![2, 1500, 16]
+    );
+    let all_attentions = model_output.all_decoder_attentions.unwrap();
+    assert_eq!(all_attentions.len(), 2);
+    assert_eq!(all_attentions[0].size(), vec![2, 2, 4, 4]);
+
+    let cache = model_output.cache.unwrap();
+    assert_eq!(cache.len(), 2);
+    assert_eq!(
+        cache[0].0.as_ref().unwrap().prev_key.size(),
+        vec![2, 2, 4, 8]
+    );
+    assert_eq!(
+        cache[0].1.as_ref().unwrap().prev_key.size(),
+        vec![2, 2, 1500, 8]
+    );
+
+    Ok(())
+}
+
+#[test]
+fn whisper_incremental_decoding() -> anyhow::Result<()> {
+    //    Set-up model
+    let device = Device::Cpu;
+    let vs = nn::VarStore::new(device);
+    let config = small_whisper_config();
+    let whisper_model = WhisperForConditionalGeneration::new(&vs.root(), &config);
+
+    let input_features = Tensor::rand(&[1, 80, WHISPER_CHUNK_FRAMES], (Kind::Float, device));
+    let encoder_output = no_grad(|| whisper_model.encode(&input_features))?;
+    let decoder_input_ids = Tensor::of_slice(&[50258i64, 50259, 50359, 50363, 1000]).view([1, 5]);
+
+    //    Full forward pass
+    let full_logits = no_grad(|| {
+        whisper_model.forward_t(None, &decoder_input_ids, Some(&encoder_output), None, false)
+    })?
+    .decoder_output;
+
+    //    Forward pass on the prompt, then on the last token with the cache
+    let prompt_output = no_grad(|| {
+        whisper_model.forward_t(
+            None,
+            &decoder_input_ids.slice(1, 0, 4, 1),
+            Some(&encoder_output),
+            None,
+            false,
+        )
+    })?;
+    let incremental_logits = no_grad(|| {
+        whisper_model.forward_t(
+            None,
+            &decoder_input_ids.slice(1, 4, 5, 1),
+            Some(&encoder_output),
+            prompt_output.cache,
+            false,
+        )
+    })?
+    .decoder_output;
+
+    let difference = (full_logits.select(1, 4) - incremental_logits.select(1, 0))
+        .abs()
+        .max()
+        .double_value(&[]);
+    assert!(difference < 1e-4);
+
+    Ok(())
+}
+
+#[test]
+fn whisper_input_too_long() {
+    let device = Device::Cpu;
+    let vs = nn::VarStore::new(device);
+    let config = small_whisper_config();
+    let whisper_model = WhisperForConditionalGeneration::new(&vs.root(), &config);
+
+    let input_features = Tensor::rand(&[1, 80, 400], (Kind::Float, device));
+    let decoder_input_ids = Tensor::ones(&[1, 17], (Kind::Int64, device));
+    let output = no_grad(|| {
+        whisper_model.forward_t(Some(&input_features), &decoder_input_ids, None, None, false)
+    });
+
+    assert!(matches!(output, Err(RustBertError::InputTooLongError(_))));
+}
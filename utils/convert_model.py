@@ -9,16 +9,19 @@ from torch import Tensor
 
 if __name__ == "__main__":
     parser = argparse.ArgumentParser()
-    parser.add_argument("source_file", help="Absolute path to the Pytorch weights file to convert")
+    parser.add_argument("source_file", nargs="+",
+                        help="Absolute path to the Pytorch weights file to convert (several files for sharded weights)")
     parser.add_argument("--skip_embeddings", action="store_true", help="Skip shared embeddings / language model head")
     parser.add_argument("--prefix", help="Add a prefix on weight names")
     parser.add_argument("--suffix", action="store_true", help="Split weight names on '.' and keep only last part")
     args = parser.parse_args()
 
-    source_file = Path(args.source_file)
-    target_folder = source_file.parent
+    source_files = [Path(source_file) for source_file in args.source_file]
+    target_folder = source_files[0].parent
 
-    weights = torch.load(str(source_file), map_location='cpu')
+    weights = {}
+    for source_file in source_files:
+        weights.update(torch.load(str(source_file), map_location='cpu'))
 
     nps = {}
     for k, v in weights.items():
@@ -31,7 +34,7 @@ if __name__ == "__main__":
         if args.suffix:
             k = k.split('.')[-1]
         if isinstance(v, Tensor):
-            nps[k] = np.ascontiguousarray(v.cpu().numpy().astype(np.float32))
+            nps[k] = np.ascontiguousarray(v.cpu().to(torch.float32).numpy())
             print(f'converted {k} - {str(sys.getsizeof(nps[k]))} bytes')
         else:
             print(f'skipped non-tensor object: {k}')
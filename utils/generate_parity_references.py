@@ -0,0 +1,295 @@
+"""Generates the reference checkpoints and outputs used by the parity tests (`tests/parity.rs`).
+
+For each architecture, a small (randomly initialized) checkpoint is downloaded from the Hugging Face hub, or
+initialized from a small configuration with a fixed seed for the architectures without a hub test checkpoint,
+converted to the `rust_model.ot` format and run on a fixed input. The configuration, converted weights and
+reference logits are stored in `<output_dir>/<architecture>/`.
+
+Usage:
+    python utils/generate_parity_references.py <output_dir> [--architectures bert gpt2 ...]
+    RUST_BERT_PARITY_DIR=<output_dir> cargo test --features parity-tests --test parity
+"""
+
+import argparse
+import json
+import subprocess
+from pathlib import Path
+
+import numpy as np
+import torch
+import transformers
+from transformers import (
+    AutoConfig,
+    AutoModel,
+    AutoModelForCausalLM,
+    AutoModelForCTC,
+    AutoModelForImageClassification,
+    AutoModelForMaskedLM,
+    AutoModelForQuestionAnswering,
+    AutoModelForSeq2SeqLM,
+    AutoModelForSpeechSeq2Seq,
+    AutoModelForTokenClassification,
+    AutoModelForVision2Seq,
+    CanineConfig,
+    DonutSwinConfig,
+    FalconConfig,
+    MBartConfig,
+    TrOCRConfig,
+    ViTConfig,
+    VisionEncoderDecoderConfig,
+)
+
+ENCODER = "encoder"
+DECODER = "decoder"
+ENCODER_DECODER = "encoder_decoder"
+TOKEN_CLASSIFICATION = "token_classification"
+QUESTION_ANSWERING = "question_answering"
+IMAGE_CLASSIFICATION = "image_classification"
+IMAGE_TEXT = "image_text"
+SPEECH_CTC = "speech_ctc"
+SPEECH_SEQ2SEQ = "speech_seq2seq"
+VISION_SEQ2SEQ = "vision_seq2seq"
+
+ARCHITECTURES = {
+    "albert": ("hf-internal-testing/tiny-random-AlbertForMaskedLM", ENCODER),
+    "bart": ("hf-internal-testing/tiny-random-BartForConditionalGeneration", ENCODER_DECODER),
+    "bert": ("hf-internal-testing/tiny-random-BertForMaskedLM", ENCODER),
+    "bigbird": ("hf-internal-testing/tiny-random-BigBirdForQuestionAnswering", QUESTION_ANSWERING),
+    "bloom": ("hf-internal-testing/tiny-random-BloomForCausalLM", DECODER),
+    "canine": ("hf-internal-testing/tiny-random-CanineForTokenClassification", TOKEN_CLASSIFICATION),
+    "clip": ("hf-internal-testing/tiny-random-CLIPModel", IMAGE_TEXT),
+    "deberta": ("hf-internal-testing/tiny-random-DebertaForMaskedLM", ENCODER),
+    "deberta_v2": ("hf-internal-testing/tiny-random-DebertaV2ForMaskedLM", ENCODER),
+    "distilbert": ("hf-internal-testing/tiny-random-DistilBertForMaskedLM", ENCODER),
+    "donut": (None, VISION_SEQ2SEQ),
+    "electra": ("hf-internal-testing/tiny-random-ElectraForMaskedLM", ENCODER),
+    "falcon": (None, DECODER),
+    "fnet": ("hf-internal-testing/tiny-random-FNetForMaskedLM", ENCODER),
+    "gpt2": ("hf-internal-testing/tiny-random-GPT2LMHeadModel", DECODER),
+    "gpt_bigcode": ("hf-internal-testing/tiny-random-GPTBigCodeForCausalLM", DECODER),
+    "gpt_neo": ("hf-internal-testing/tiny-random-GPTNeoForCausalLM", DECODER),
+    "gpt_neox": ("hf-internal-testing/tiny-random-GPTNeoXForCausalLM", DECODER),
+    "llama": ("hf-internal-testing/tiny-random-LlamaForCausalLM", DECODER),
+    "longformer": ("hf-internal-testing/tiny-random-LongformerForMaskedLM", ENCODER),
+    "m2m_100": ("hf-internal-testing/tiny-random-M2M100ForConditionalGeneration", ENCODER_DECODER),
+    "marian": ("hf-internal-testing/tiny-random-MarianMTModel", ENCODER_DECODER),
+    "mbart": ("hf-internal-testing/tiny-random-MBartForConditionalGeneration", ENCODER_DECODER),
+    "mobilebert": ("hf-internal-testing/tiny-random-MobileBertForMaskedLM", ENCODER),
+    "openai_gpt": ("hf-internal-testing/tiny-random-OpenAIGPTLMHeadModel", DECODER),
+    "pegasus": ("hf-internal-testing/tiny-random-PegasusForConditionalGeneration", ENCODER_DECODER),
+    "prophetnet": ("hf-internal-testing/tiny-random-ProphetNetForConditionalGeneration", ENCODER_DECODER),
+    "reformer": ("hf-internal-testing/tiny-random-ReformerModelWithLMHead", DECODER),
+    "roberta": ("hf-internal-testing/tiny-random-RobertaForMaskedLM", ENCODER),
+    "t5": ("hf-internal-testing/tiny-random-T5ForConditionalGeneration", ENCODER_DECODER),
+    "trocr": (None, VISION_SEQ2SEQ),
+    "vit": ("hf-internal-testing/tiny-random-ViTForImageClassification", IMAGE_CLASSIFICATION),
+    "wav2vec2": ("hf-internal-testing/tiny-random-Wav2Vec2ForCTC", SPEECH_CTC),
+    "whisper": ("hf-internal-testing/tiny-random-WhisperForConditionalGeneration", SPEECH_SEQ2SEQ),
+    "xlnet": ("hf-internal-testing/tiny-random-XLNetLMHeadModel", DECODER),
+}
+
+AUTO_CLASSES = {
+    ENCODER: AutoModelForMaskedLM,
+    DECODER: AutoModelForCausalLM,
+    ENCODER_DECODER: AutoModelForSeq2SeqLM,
+    TOKEN_CLASSIFICATION: AutoModelForTokenClassification,
+    QUESTION_ANSWERING: AutoModelForQuestionAnswering,
+    IMAGE_CLASSIFICATION: AutoModelForImageClassification,
+    IMAGE_TEXT: AutoModel,
+    SPEECH_CTC: AutoModelForCTC,
+    SPEECH_SEQ2SEQ: AutoModelForSpeechSeq2Seq,
+    VISION_SEQ2SEQ: AutoModelForVision2Seq,
+}
+
+TEXT_INPUTS = {ENCODER, DECODER, ENCODER_DECODER, TOKEN_CLASSIFICATION, QUESTION_ANSWERING, IMAGE_TEXT}
+PADDED_INPUTS = {ENCODER, TOKEN_CLASSIFICATION, QUESTION_ANSWERING}
+DECODER_INPUTS = {ENCODER_DECODER, SPEECH_SEQ2SEQ, VISION_SEQ2SEQ}
+
+BATCH_SIZE = 2
+SEQUENCE_LENGTH = 8
+NUM_AUDIO_SAMPLES = 1600
+DEFAULT_TOLERANCE = 1e-4
+
+
+def small_text_decoder_config(config_class, **kwargs):
+    return config_class(
+        vocab_size=1024,
+        d_model=32,
+        decoder_layers=2,
+        decoder_attention_heads=4,
+        decoder_ffn_dim=37,
+        max_position_embeddings=64,
+        **kwargs,
+    )
+
+
+def small_vision_encoder_decoder_config(encoder_config, decoder_config):
+    config = VisionEncoderDecoderConfig.from_encoder_decoder_configs(encoder_config, decoder_config)
+    config.decoder_start_token_id = 2
+    config.pad_token_id = 1
+    config.eos_token_id = 2
+    return config
+
+
+# Architectures without a test checkpoint on the hub are initialized from these configurations
+RANDOM_INIT_CONFIGS = {
+    "donut": lambda: small_vision_encoder_decoder_config(
+        DonutSwinConfig(
+            image_size=[32, 32], patch_size=4, embed_dim=16, depths=[1, 1], num_heads=[2, 2], window_size=2
+        ),
+        small_text_decoder_config(MBartConfig, encoder_layers=2, encoder_attention_heads=4, encoder_ffn_dim=37),
+    ),
+    "falcon": lambda: FalconConfig(vocab_size=1024, hidden_size=32, num_hidden_layers=2, num_attention_heads=4),
+    "trocr": lambda: small_vision_encoder_decoder_config(
+        ViTConfig(
+            image_size=32,
+            patch_size=8,
+            hidden_size=32,
+            num_hidden_layers=2,
+            num_attention_heads=4,
+            intermediate_size=37,
+        ),
+        small_text_decoder_config(TrOCRConfig),
+    ),
+}
+
+
+def image_shape(config):
+    image_size = config.image_size
+    height, width = image_size if isinstance(image_size, (list, tuple)) else (image_size, image_size)
+    return BATCH_SIZE, config.num_channels, height, width
+
+
+def build_inputs(config, kind):
+    """Returns the keyword arguments of the forward pass, and the name of the floating point input (if any)"""
+    generator = torch.Generator().manual_seed(0)
+    inputs = {}
+    if kind in TEXT_INPUTS:
+        text_config = getattr(config, "text_config", config)
+        # CANINE has no vocabulary: the input ids are Unicode code points
+        vocab_size = getattr(text_config, "vocab_size", 1024)
+        # Avoid special tokens (usually at the start of the vocabulary) to keep the inputs valid for all models
+        input_ids = torch.randint(5, vocab_size, (BATCH_SIZE, SEQUENCE_LENGTH), generator=generator)
+        attention_mask = torch.ones_like(input_ids)
+        if kind in PADDED_INPUTS:
+            # Padding on the second sequence checks the masking logic
+            attention_mask[1, -2:] = 0
+        inputs.update(input_ids=input_ids, attention_mask=attention_mask)
+
+    features_name = None
+    if kind == IMAGE_CLASSIFICATION:
+        features_name, shape = "pixel_values", image_shape(config)
+    elif kind == IMAGE_TEXT:
+        features_name, shape = "pixel_values", image_shape(config.vision_config)
+    elif kind == VISION_SEQ2SEQ:
+        features_name, shape = "pixel_values", image_shape(config.encoder)
+    elif kind == SPEECH_CTC:
+        features_name, shape = "input_values", (BATCH_SIZE, NUM_AUDIO_SAMPLES)
+    elif kind == SPEECH_SEQ2SEQ:
+        # The Whisper encoder expects log-mel spectrograms padded to the maximum number of frames
+        features_name, shape = "input_features", (BATCH_SIZE, config.num_mel_bins, 2 * config.max_source_positions)
+    if features_name is not None:
+        inputs[features_name] = torch.randn(shape, generator=generator)
+
+    if kind in DECODER_INPUTS:
+        decoder_config = config.decoder if kind == VISION_SEQ2SEQ else config
+        inputs["decoder_input_ids"] = torch.randint(
+            5, decoder_config.vocab_size, (BATCH_SIZE, SEQUENCE_LENGTH // 2), generator=generator
+        )
+    return inputs, features_name
+
+
+def get_logits(output, kind):
+    if kind == QUESTION_ANSWERING:
+        return torch.stack([output.start_logits, output.end_logits], dim=-1)
+    if kind == IMAGE_TEXT:
+        return output.logits_per_image
+    return output.logits
+
+
+def convert_weights(model, target_folder: Path):
+    nps = {}
+    for k, v in model.state_dict().items():
+        # Weight-normalized convolutions (Wav2Vec2 positional embeddings) are stored as parametrizations in
+        # recent PyTorch versions
+        k = k.replace("parametrizations.weight.original0", "weight_g")
+        k = k.replace("parametrizations.weight.original1", "weight_v")
+        nps[k] = np.ascontiguousarray(v.cpu().numpy().astype(np.float32))
+    np.savez(target_folder / "model.npz", **nps)
+
+    toml_location = (Path(__file__).resolve() / ".." / ".." / "Cargo.toml").resolve()
+    subprocess.run(
+        [
+            "cargo",
+            "run",
+            "--bin=convert-tensor",
+            "--manifest-path=%s" % toml_location,
+            "--",
+            str(target_folder / "model.npz"),
+            str(target_folder / "rust_model.ot"),
+        ],
+        check=True,
+    )
+    (target_folder / "model.npz").unlink()
+
+
+def generate_reference(architecture: str, model_id: str, kind: str, output_dir: Path):
+    target_folder = output_dir / architecture
+    target_folder.mkdir(parents=True, exist_ok=True)
+
+    if model_id is None:
+        config = RANDOM_INIT_CONFIGS[architecture]()
+        torch.manual_seed(0)
+        model = AUTO_CLASSES[kind].from_config(config).eval()
+    else:
+        config = AutoConfig.from_pretrained(model_id)
+        if architecture == "reformer":
+            # Seeded hashing for deterministic LSH attention
+            config.hash_seed = 0
+        model = AUTO_CLASSES[kind].from_pretrained(model_id, config=config).eval()
+    config = model.config
+    config.to_json_file(target_folder / "config.json")
+    convert_weights(model, target_folder)
+
+    inputs, features_name = build_inputs(config, kind)
+    with torch.no_grad():
+        logits = get_logits(model(**inputs), kind)
+
+    def to_list(name):
+        return inputs[name].tolist() if name in inputs else None
+
+    reference = {
+        "model_id": model_id or f"random initialization ({architecture})",
+        "transformers_version": transformers.__version__,
+        "torch_version": torch.__version__,
+        "input_ids": to_list("input_ids"),
+        "attention_mask": to_list("attention_mask"),
+        "decoder_input_ids": to_list("decoder_input_ids"),
+        # Pixel values, raw waveforms or log-mel spectrograms of the vision and speech models
+        "input_features": inputs[features_name].flatten().tolist() if features_name is not None else None,
+        "input_features_shape": list(inputs[features_name].shape) if features_name is not None else None,
+        "logits_shape": list(logits.shape),
+        "logits": logits.flatten().tolist(),
+        "tolerance": DEFAULT_TOLERANCE,
+    }
+    with open(target_folder / "reference.json", "w") as f:
+        json.dump(reference, f)
+    print(f"Generated parity reference for {architecture} ({model_id})")
+
+
+if __name__ == "__main__":
+    parser = argparse.ArgumentParser()
+    parser.add_argument("output_dir", help="Directory to store the reference checkpoints and outputs")
+    parser.add_argument(
+        "--architectures",
+        nargs="+",
+        default=list(ARCHITECTURES.keys()),
+        choices=list(ARCHITECTURES.keys()),
+        help="Architectures to generate references for (default: all)",
+    )
+    args = parser.parse_args()
+
+    torch.manual_seed(0)
+    for architecture in args.architectures:
+        model_id, kind = ARCHITECTURES[architecture]
+        generate_reference(architecture, model_id, kind, Path(args.output_dir))